@@ -1,3 +1,21 @@
+use std::process::Command;
+
 fn main() {
+    // Surfaced by `get_app_info`; `TARGET` is only visible to build scripts.
+    println!(
+        "cargo:rustc-env=MICODE_BUILD_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=MICODE_GIT_COMMIT={commit}");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+    println!("cargo:rerun-if-env-changed=MICODE_UPDATE_CHANNEL");
     tauri_build::build()
 }
@@ -0,0 +1,184 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, State};
+
+use crate::notification_inbox::unread_notification_count;
+use crate::remote_backend;
+use crate::state::AppState;
+use crate::types::WorkspaceInfo;
+
+/// Update channel of builds that do not set `MICODE_UPDATE_CHANNEL` at compile time; the
+/// updater endpoint follows the latest GitHub release.
+const DEFAULT_UPDATE_CHANNEL: &str = "stable";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AppPaths {
+    pub(crate) app_data: String,
+    pub(crate) settings_file: String,
+    pub(crate) workspaces_file: String,
+    pub(crate) logs_dir: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorkspaceCounts {
+    pub(crate) registered: usize,
+    pub(crate) connected: usize,
+}
+
+/// What support needs to know about a running install, assembled in one place for the
+/// About view and the diagnostics bundle.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AppInfo {
+    pub(crate) app_version: String,
+    /// Short commit hash of the build, `None` when it was built outside a git checkout.
+    pub(crate) git_commit: Option<String>,
+    pub(crate) target_triple: String,
+    pub(crate) os: String,
+    pub(crate) arch: String,
+    pub(crate) family: String,
+    pub(crate) tauri_version: String,
+    /// `None` when the system webview could not be queried.
+    pub(crate) webview_version: Option<String>,
+    pub(crate) update_channel: String,
+    pub(crate) paths: AppPaths,
+    /// Whether the home directory in `paths` was replaced with `~`.
+    pub(crate) paths_masked: bool,
+    pub(crate) remote_mode: bool,
+    /// `None` when the remote backend could not be reached.
+    pub(crate) workspaces: Option<WorkspaceCounts>,
+    /// Notifications in the inbox not marked read yet.
+    pub(crate) unread_count: usize,
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Replaces the home directory prefix of `path` with `~`, which hides the username.
+fn mask_home(path: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) => Path::new("~").join(rest).display().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(ToString::to_string)
+}
+
+async fn workspace_counts(
+    state: &AppState,
+    app: &AppHandle,
+    remote_mode: bool,
+) -> Option<WorkspaceCounts> {
+    if !remote_mode {
+        return Some(WorkspaceCounts {
+            registered: state.workspaces.lock().await.len(),
+            connected: state.sessions.lock().await.len(),
+        });
+    }
+    let response = remote_backend::call_remote(state, app.clone(), "list_workspaces", json!({}))
+        .await
+        .ok()?;
+    let workspaces: Vec<WorkspaceInfo> = serde_json::from_value(response).ok()?;
+    Some(WorkspaceCounts {
+        registered: workspaces.len(),
+        connected: workspaces
+            .iter()
+            .filter(|workspace| workspace.connected)
+            .count(),
+    })
+}
+
+pub(crate) async fn collect_app_info(
+    state: &AppState,
+    app: &AppHandle,
+    mask_paths: bool,
+) -> AppInfo {
+    let remote_mode = remote_backend::is_remote_mode(state).await;
+    let home = if mask_paths { home_dir() } else { None };
+    let show = |path: &Path| mask_home(path, home.as_deref());
+    let app_data = state
+        .settings_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    AppInfo {
+        app_version: app.package_info().version.to_string(),
+        git_commit: non_empty(option_env!("MICODE_GIT_COMMIT")),
+        target_triple: env!("MICODE_BUILD_TARGET").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        family: std::env::consts::FAMILY.to_string(),
+        tauri_version: tauri::VERSION.to_string(),
+        webview_version: tauri::webview_version().ok(),
+        update_channel: non_empty(option_env!("MICODE_UPDATE_CHANNEL"))
+            .unwrap_or_else(|| DEFAULT_UPDATE_CHANNEL.to_string()),
+        paths: AppPaths {
+            app_data: show(&app_data),
+            settings_file: show(&state.settings_path),
+            workspaces_file: show(&state.storage_path),
+            logs_dir: show(&state.logs_dir),
+        },
+        paths_masked: mask_paths,
+        remote_mode,
+        workspaces: workspace_counts(state, app, remote_mode).await,
+        unread_count: unread_notification_count(state).await,
+    }
+}
+
+/// Version, build and environment details of this install. Always describes the local app,
+/// also in remote mode.
+#[tauri::command]
+pub(crate) async fn get_app_info(
+    mask_paths: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<AppInfo, String> {
+    Ok(collect_app_info(&state, &app, mask_paths.unwrap_or(false)).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mask_home, non_empty};
+    use std::path::Path;
+
+    #[test]
+    fn masks_only_paths_under_home() {
+        let home = Path::new("/home/alice");
+        let settings = Path::new("/home/alice/.local/share/micode/settings.json");
+        assert_eq!(
+            mask_home(settings, Some(home)),
+            Path::new("~/.local/share/micode/settings.json")
+                .display()
+                .to_string()
+        );
+        assert_eq!(
+            mask_home(Path::new("/opt/micode/logs"), Some(home)),
+            "/opt/micode/logs"
+        );
+        assert_eq!(mask_home(settings, None), settings.display().to_string());
+        assert_eq!(
+            mask_home(Path::new("/home/alicex/a"), Some(home)),
+            "/home/alicex/a"
+        );
+    }
+
+    #[test]
+    fn blank_build_values_are_absent() {
+        assert_eq!(non_empty(Some(" ")), None);
+        assert_eq!(non_empty(None), None);
+        assert_eq!(non_empty(Some("abc123\n")).as_deref(), Some("abc123"));
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+pub(crate) const MAX_ANNOTATION_CHARS: usize = 2_000;
+pub(crate) const MAX_THREAD_ANNOTATION_CHARS: usize = 50_000;
+
+/// A user note attached to one item of a thread. Annotations live in their own sidecar
+/// file, so they are never part of the history replayed into prompts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ItemAnnotation {
+    pub(crate) id: String,
+    pub(crate) item_id: String,
+    pub(crate) text: String,
+    pub(crate) author: String,
+    pub(crate) created_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) updated_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ThreadAnnotations {
+    pub(crate) annotations: Vec<ItemAnnotation>,
+}
+
+fn normalize_text(text: &str) -> Result<String, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("annotation text is empty".to_string());
+    }
+    let chars = text.chars().count();
+    if chars > MAX_ANNOTATION_CHARS {
+        return Err(format!(
+            "annotation is {chars} characters, the limit is {MAX_ANNOTATION_CHARS}"
+        ));
+    }
+    Ok(text.to_string())
+}
+
+impl ThreadAnnotations {
+    pub(crate) fn load(path: &Path) -> Self {
+        let annotations = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<Vec<ItemAnnotation>>(&raw).ok())
+            .unwrap_or_default();
+        Self { annotations }
+    }
+
+    /// Writes the sidecar, removing it once the last annotation is gone.
+    pub(crate) fn save(&self, path: &Path) -> Result<(), String> {
+        if self.annotations.is_empty() {
+            let _ = std::fs::remove_file(path);
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let raw = serde_json::to_string(&self.annotations).map_err(|e| e.to_string())?;
+        std::fs::write(path, raw).map_err(|e| e.to_string())
+    }
+
+    fn total_chars(&self, skip_id: Option<&str>) -> usize {
+        self.annotations
+            .iter()
+            .filter(|annotation| Some(annotation.id.as_str()) != skip_id)
+            .map(|annotation| annotation.text.chars().count())
+            .sum()
+    }
+
+    fn ensure_thread_capacity(&self, text: &str, skip_id: Option<&str>) -> Result<(), String> {
+        if self.total_chars(skip_id) + text.chars().count() > MAX_THREAD_ANNOTATION_CHARS {
+            return Err(format!(
+                "thread annotations would exceed {MAX_THREAD_ANNOTATION_CHARS} characters"
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn add(
+        &mut self,
+        item_id: &str,
+        text: &str,
+        author: &str,
+        now_ts: i64,
+    ) -> Result<ItemAnnotation, String> {
+        let item_id = item_id.trim();
+        if item_id.is_empty() {
+            return Err("missing itemId".to_string());
+        }
+        let text = normalize_text(text)?;
+        self.ensure_thread_capacity(&text, None)?;
+        let annotation = ItemAnnotation {
+            id: Uuid::new_v4().to_string(),
+            item_id: item_id.to_string(),
+            text,
+            author: author.trim().to_string(),
+            created_at: now_ts,
+            updated_at: None,
+        };
+        self.annotations.push(annotation.clone());
+        Ok(annotation)
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        annotation_id: &str,
+        text: &str,
+        now_ts: i64,
+    ) -> Result<ItemAnnotation, String> {
+        let text = normalize_text(text)?;
+        self.ensure_thread_capacity(&text, Some(annotation_id))?;
+        let annotation = self
+            .annotations
+            .iter_mut()
+            .find(|annotation| annotation.id == annotation_id)
+            .ok_or_else(|| format!("annotation not found: {annotation_id}"))?;
+        annotation.text = text;
+        annotation.updated_at = Some(now_ts);
+        Ok(annotation.clone())
+    }
+
+    pub(crate) fn delete(&mut self, annotation_id: &str) -> bool {
+        let before = self.annotations.len();
+        self.annotations
+            .retain(|annotation| annotation.id != annotation_id);
+        self.annotations.len() != before
+    }
+
+    /// Annotations grouped by item id, the shape `thread/resume` returns.
+    pub(crate) fn by_item(&self) -> Value {
+        let mut grouped: BTreeMap<&str, Vec<&ItemAnnotation>> = BTreeMap::new();
+        for annotation in &self.annotations {
+            grouped
+                .entry(annotation.item_id.as_str())
+                .or_default()
+                .push(annotation);
+        }
+        serde_json::to_value(grouped).unwrap_or(Value::Null)
+    }
+
+    /// Copy for a forked thread. Item ids embed the thread id, so they are rewritten to
+    /// point at the fork's copies of the same items.
+    pub(crate) fn remapped_for_fork(&self, source_thread_id: &str, fork_thread_id: &str) -> Self {
+        let annotations = self
+            .annotations
+            .iter()
+            .map(|annotation| ItemAnnotation {
+                id: Uuid::new_v4().to_string(),
+                item_id: remap_item_id(&annotation.item_id, source_thread_id, fork_thread_id),
+                ..annotation.clone()
+            })
+            .collect();
+        Self { annotations }
+    }
+}
+
+pub(crate) fn remap_item_id(item_id: &str, source_thread_id: &str, fork_thread_id: &str) -> String {
+    if source_thread_id.is_empty() {
+        return item_id.to_string();
+    }
+    item_id.replace(source_thread_id, fork_thread_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ThreadAnnotations, MAX_ANNOTATION_CHARS, MAX_THREAD_ANNOTATION_CHARS};
+
+    #[test]
+    fn enforces_annotation_and_thread_caps() {
+        let mut annotations = ThreadAnnotations::default();
+        assert!(annotations.add("item-1", "   ", "me", 1).is_err());
+        let too_long = "x".repeat(MAX_ANNOTATION_CHARS + 1);
+        assert!(annotations.add("item-1", &too_long, "me", 1).is_err());
+
+        let note = "x".repeat(MAX_ANNOTATION_CHARS);
+        let fits = MAX_THREAD_ANNOTATION_CHARS / MAX_ANNOTATION_CHARS;
+        for _ in 0..fits {
+            annotations
+                .add("item-1", &note, "me", 1)
+                .expect("within cap");
+        }
+        assert!(annotations.add("item-2", "one more", "me", 1).is_err());
+
+        let first = annotations.annotations[0].id.clone();
+        let updated = annotations.update(&first, "shorter", 2).expect("update");
+        assert_eq!(updated.updated_at, Some(2));
+        assert!(annotations.add("item-2", "one more", "me", 3).is_ok());
+    }
+
+    #[test]
+    fn fork_copies_remap_item_ids() {
+        let mut annotations = ThreadAnnotations::default();
+        let original = annotations
+            .add("agent-t-1-turn-5", "this approach was wrong", "me", 1)
+            .expect("add");
+        let forked = annotations.remapped_for_fork("t-1", "t-2");
+        assert_eq!(forked.annotations.len(), 1);
+        assert_eq!(forked.annotations[0].item_id, "agent-t-2-turn-5");
+        assert_eq!(forked.annotations[0].text, original.text);
+        assert_ne!(forked.annotations[0].id, original.id);
+
+        let grouped = forked.by_item();
+        assert_eq!(
+            grouped["agent-t-2-turn-5"][0]["text"],
+            "this approach was wrong"
+        );
+    }
+}
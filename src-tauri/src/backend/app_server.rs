@@ -1,26 +1,141 @@
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::env;
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, ChildStdin, Command};
-use tokio::sync::{mpsc, oneshot, Mutex};
-use tokio::time::{sleep, timeout};
+use tokio::sync::{mpsc, oneshot, Mutex, Notify};
+use tokio::time::{sleep, timeout, Instant};
 use uuid::Uuid;
 
+use crate::backend::annotations::{remap_item_id, ThreadAnnotations};
+use crate::backend::approvals::{
+    approval_reminder_params, approval_resolved_params, approval_response,
+    approval_timed_out_params, auto_decision, cancelled_response, timed_out_result, ApprovalInsert,
+    ApprovalResolvedBy, AutoDecision, PendingApproval, PendingApprovals,
+};
+use crate::backend::background_reaper::{
+    BackgroundActivity, BACKGROUND_REAP_INTERVAL, BACKGROUND_THREAD_IDLE,
+};
+use crate::backend::chat_index::{
+    find_chat_file, forget_chat_file, refresh_chat_files, ChatFileIndex, ChatFileWatch,
+};
+use crate::backend::connect_queue::{queue_position_events, ConnectQueue};
+use crate::backend::connection_state::ConnectionStates;
+use crate::backend::event_methods;
 use crate::backend::events::{AppServerEvent, EventSink};
-use crate::micode::args::apply_micode_args;
+use crate::backend::handshake_cache::{HandshakeKey, HandshakeProbe};
+use crate::backend::history_prune::{HistoryPruneOptions, PrunedThread};
+use crate::backend::item_summaries::{plan_summary, tool_call_summary, ToolSummaryInput};
+use crate::backend::mcp_status::{parse_mcp_servers, probe_mcp_server, McpProbeCache};
+use crate::backend::pending_requests::{
+    background_caller, PendingRequest, PendingRequestInfo, CALLER_TURN,
+};
+use crate::backend::primer::{
+    build_repo_summary, estimate_tokens, head_changed_significantly, read_head, record_reuse,
+    PrimedSession, PrimerHead, PrimerState,
+};
+use crate::backend::prompt_images::{prompt_images_from_input, PromptImage};
+use crate::backend::prompt_text::{derive_thread_title, normalize_prompt_text};
+use crate::backend::redaction::{CompiledRedaction, Redacted};
+use crate::backend::sampling::parse_sampling_params;
+use crate::backend::session_health::{HealthTracker, SessionHealth};
+use crate::backend::settings_json::{
+    load_settings_for_update, read_settings_file, write_settings_file, SettingsParseErrors,
+};
+use crate::backend::skills::list_skills;
+use crate::backend::slash_commands::{AvailableCommands, CommandNotAvailable};
+use crate::backend::store_maintenance::{
+    maintain_items_file, FileMaintenanceOutcome, StoreMaintenanceReport, StoreMaintenanceStatus,
+};
+use crate::backend::thread_export::{render_thread_export, ExportedThread, ThreadExportFormat};
+use crate::backend::thread_references::{
+    append_reference_blocks, read_cached_summary, tail_transcript, write_cached_summary,
+    ThreadReference, ThreadReferenceSource,
+};
+use crate::backend::thread_search::{
+    item_search_text, match_snippet, normalize_query, ThreadSearchHit,
+};
+use crate::backend::thread_sync::{
+    compare_histories, imported_items, read_cli_messages, CliMessage, SyncStatus, ThreadSyncReport,
+};
+use crate::backend::tool_results::{
+    structured_tool_result, truncate_tool_result_json, truncate_tool_result_text,
+};
+use crate::backend::tool_timing::{tool_slow_params, ToolTimings};
+use crate::backend::transcript::{render_transcript, TranscriptFormat};
+use crate::backend::turn_artifacts::{
+    collect_turn_artifacts, mark_missing_artifacts, TurnArtifactBaseline,
+};
+use crate::backend::turn_audit::{
+    audit_read_from_tool, prune_turn_audits, summarize, write_turn_audit, AuditRead, AuditSummary,
+    TurnAudit,
+};
+use crate::backend::turn_live_changes::{collect_live_changes, LiveFileChange, TurnLiveChanges};
+use crate::backend::turn_phase::{turn_phase_params, TurnPhase, TurnPhaseTracker};
+use crate::backend::turn_queue::{QueuedTurn, TurnAdmission, TurnQueue};
+use crate::backend::turn_reviews::{
+    attach_review_progress, seed_turn_review, turn_changed_files, ReviewProgress,
+};
+use crate::backend::turn_stall::{stall_params, StallChange, StallThresholds, StallWatch};
+use crate::backend::workspace_paths::{merge_split_state_dirs, resolve_on_disk};
+use crate::micode::args::{apply_micode_args, model_from_micode_args};
+use crate::micode::home::{
+    is_isolated_agent_home, prepare_isolated_agent_home, resolve_default_micode_home,
+};
+use crate::rules;
+use crate::shared::auto_run_core::{self, AutoRunRegistry};
+use crate::shared::micode_core::access_mode_policies;
 use crate::shared::process_core::tokio_command;
-use crate::types::WorkspaceEntry;
+use crate::shared::resource_monitor_core::ResourceTracker;
+use crate::shared::workspaces_core::join_all;
+use crate::storage::write_file_atomically;
+use crate::types::errors::{CommandError, ErrorCode, THREAD_PINNED};
+use crate::types::{
+    AppSettings, AuditSettings, RedactionSettings, RunKickoffTemplateRef, SamplingParams,
+    WorkspaceEntry,
+};
 
 const ACP_PROTOCOL_VERSION: u32 = 1;
-const TURN_START_TIMEOUT: Duration = Duration::from_secs(6 * 60 * 60);
+const DAY_SECS: i64 = 24 * 60 * 60;
+const SESSION_RESTARTED_ERROR: &str = "session restarted";
+const AGENT_EXITED_REASON: &str = "agent exited";
+const INTERRUPTED_BY_USER_NOTE: &str = "Turn interrupted by user";
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// Tick of the session ticker: how quickly a crashed agent is noticed, and how often
+/// approvals are swept and token usage is polled. Each tick is cheap.
+const SESSION_TICK_INTERVAL: Duration = Duration::from_secs(1);
+const HEALTH_PING_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often running turns are checked for silence; bounds how late `turn/stalled` is.
+const STALL_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Request no agent implements; any answer, even method-not-found, proves it is reading stdin.
+const HEALTH_PING_METHOD: &str = "_micode/ping";
+/// Stderr lines attached to `micode/disconnected`.
+const DISCONNECT_STDERR_LINES: usize = 20;
+const STDERR_TAIL_LINES: usize = 200;
+/// ACP extension method that stops one running tool call, see `agent_supports_tool_call_cancel`.
+const CANCEL_TOOL_CALL_METHOD: &str = "_micode/cancelToolCall";
+/// How long a session's store holds changed thread items before writing them, so a
+/// burst of item updates during a turn becomes one write per thread.
+const THREAD_ITEMS_FLUSH_DELAY: Duration = Duration::from_millis(250);
+const TOKEN_USAGE_RETRY_DELAYS: &[Duration] = &[
+    Duration::from_millis(0),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(4),
+];
+/// A streaming agent message is saved as a `partial` item at least this often, or once
+/// `AGENT_CHECKPOINT_BYTES` of new text arrived, so a crash loses little of it.
+const AGENT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+const AGENT_CHECKPOINT_BYTES: usize = 4 * 1024;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct LocalThreadRecord {
@@ -34,39 +149,155 @@ struct LocalThreadRecord {
     updated_at: i64,
     #[serde(rename = "messageIndex")]
     message_index: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// Number of leading thread items the user has seen; `None` for threads recorded
+    /// before seen tracking, which count as fully seen.
+    #[serde(
+        rename = "lastSeenItemSeq",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    last_seen_item_seq: Option<u64>,
+    /// Kickoff template of the run this thread was started for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kickoff: Option<RunKickoffTemplateRef>,
+    /// Pinned threads list first and refuse `thread/archive` and `thread/delete` unless
+    /// forced.
+    #[serde(default)]
+    pinned: bool,
+    /// When the thread was archived, unix seconds. Archived records written before this
+    /// field count from `updated_at`.
+    #[serde(
+        rename = "archivedAt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    archived_at: Option<i64>,
+    /// Access mode of the thread's last turn (`read-only`, `current` or `full-access`);
+    /// sessions created for the thread, including on resume, run under it.
+    #[serde(
+        rename = "accessMode",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    access_mode: Option<String>,
+    /// Where `title` came from; `None` for the default title and for records written
+    /// before titles were tracked.
+    #[serde(
+        rename = "titleSource",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    title_source: Option<ThreadTitleSource>,
+}
+
+/// Origin of a thread title. Generated titles only replace `Prompt` ones, so a name the
+/// user picked is never overwritten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ThreadTitleSource {
+    /// Cut from the first prompt, see `derive_thread_title`.
+    Prompt,
+    /// Written by the agent after the first turn, see `autoTitleThreads`.
+    Generated,
+    /// Set by the user through `thread/name/set`.
+    User,
+}
+
+impl LocalThreadRecord {
+    fn archived_since(&self) -> i64 {
+        self.archived_at.unwrap_or(self.updated_at)
+    }
+}
+
+/// Item lists of a session's store not yet written, or kept after a write for reads.
+#[derive(Default)]
+struct ThreadItemsCache {
+    items: HashMap<String, Vec<Value>>,
+    dirty: HashSet<String>,
 }
 
 #[derive(Default)]
 struct LocalThreadStore {
     path: PathBuf,
     records: Vec<LocalThreadRecord>,
+    /// Set on a session's store: item writes stay in `items_cache` until
+    /// `flush_thread_items` writes them, and this wakes the debounced flush task.
+    /// Other stores write items through.
+    flush_notify: Option<Arc<Notify>>,
+    items_cache: std::sync::Mutex<ThreadItemsCache>,
+    /// Serializes flushes, so an older batch never lands after a newer one.
+    flush_lock: Arc<Mutex<()>>,
+    /// Items files written, counting the ones a flush has taken.
+    items_file_writes: AtomicU64,
 }
 
 impl LocalThreadStore {
     fn load(workspace_path: &str) -> Self {
-        let path = PathBuf::from(workspace_path)
-            .join(".micodemonitor")
-            .join("sessions.json");
+        // Stored paths may predate normalization, so resolve the folder as spelled on disk.
+        let root = resolve_on_disk(Path::new(workspace_path));
+        let path = root.join(".micodemonitor").join("sessions.json");
         if let Ok(raw) = std::fs::read_to_string(&path) {
             if let Ok(records) = serde_json::from_str::<Vec<LocalThreadRecord>>(&raw) {
-                let mut store = Self { path, records };
+                let mut store = Self::new(path, records);
                 if store.repair_session_collisions() {
                     store.persist();
                 }
                 return store;
             }
         }
+        Self::new(path, Vec::new())
+    }
+
+    fn new(path: PathBuf, records: Vec<LocalThreadRecord>) -> Self {
         Self {
             path,
-            records: Vec::new(),
+            records,
+            flush_notify: None,
+            items_cache: std::sync::Mutex::new(ThreadItemsCache::default()),
+            flush_lock: Arc::new(Mutex::new(())),
+            items_file_writes: AtomicU64::new(0),
+        }
+    }
+
+    /// Switches item writes to the cache; the returned notify fires on each change and
+    /// drives `spawn_thread_items_flusher`.
+    fn enable_write_behind(&mut self) -> Arc<Notify> {
+        let notify = Arc::new(Notify::new());
+        self.flush_notify = Some(notify.clone());
+        notify
+    }
+
+    /// The store a connecting session starts from. State written to a differently
+    /// normalized twin of the workspace folder is folded in first, once per connect
+    /// rather than on every read; then it is `load`ed and archived threads older than
+    /// `retention_days` are purged. Only the session's own store purges, so a read
+    /// beside it never deletes anything.
+    fn load_for_session(workspace_path: &str, retention_days: u32) -> Self {
+        let root = resolve_on_disk(Path::new(workspace_path));
+        match merge_split_state_dirs(&root, now_ms()) {
+            Ok(merged) if !merged.is_empty() => eprintln!(
+                "thread store: merged state of {} differently normalized folder(s) into {}",
+                merged.len(),
+                root.display()
+            ),
+            Ok(_) => {}
+            Err(error) => eprintln!("thread store: {error}"),
+        }
+        let mut store = Self::load(workspace_path);
+        let purged = store.purge_expired_archived(retention_days, now_ts());
+        if purged > 0 {
+            eprintln!("thread store: deleted {purged} archived thread(s) past retention");
         }
+        store
     }
 
     fn persist(&self) {
         if let Some(parent) = self.path.parent() {
             let _ = std::fs::create_dir_all(parent);
         }
-        if let Ok(raw) = serde_json::to_string_pretty(&self.records) {
+        if let Ok(raw) = serde_json::to_string(&self.records) {
             let _ = std::fs::write(&self.path, raw);
         }
     }
@@ -111,27 +342,289 @@ impl LocalThreadStore {
             .collect()
     }
 
+    /// Archived threads, most recently archived first.
+    fn list_archived(&self) -> Vec<LocalThreadRecord> {
+        let mut records: Vec<LocalThreadRecord> = self
+            .records
+            .iter()
+            .filter(|entry| entry.archived)
+            .cloned()
+            .collect();
+        records.sort_by_key(|entry| std::cmp::Reverse(entry.archived_since()));
+        records
+    }
+
+    /// Hides the thread from `thread/list`, keeping its record and items for a restore.
+    fn archive(&mut self, thread_id: &str, now: i64) -> bool {
+        let Some(entry) = self
+            .records
+            .iter_mut()
+            .find(|entry| entry.thread_id == thread_id)
+        else {
+            return false;
+        };
+        if !entry.archived {
+            entry.archived = true;
+            entry.archived_at = Some(now);
+            self.persist();
+        }
+        true
+    }
+
+    /// Brings an archived thread back; false when the thread is unknown or not archived.
+    fn restore(&mut self, thread_id: &str) -> bool {
+        let Some(entry) = self
+            .records
+            .iter_mut()
+            .find(|entry| entry.thread_id == thread_id && entry.archived)
+        else {
+            return false;
+        };
+        entry.archived = false;
+        entry.archived_at = None;
+        self.persist();
+        true
+    }
+
+    /// Archived threads whose retention ran out by `now`: archived more than
+    /// `retention_days` ago. 0 days keeps them forever.
+    fn expired_archived(&self, retention_days: u32, now: i64) -> HashSet<String> {
+        if retention_days == 0 {
+            return HashSet::new();
+        }
+        let cutoff = now.saturating_sub(i64::from(retention_days) * DAY_SECS);
+        self.records
+            .iter()
+            .filter(|entry| entry.archived && entry.archived_since() < cutoff)
+            .map(|entry| entry.thread_id.clone())
+            .collect()
+    }
+
+    /// Deletes the archived threads past their retention, items included.
+    fn purge_expired_archived(&mut self, retention_days: u32, now: i64) -> usize {
+        let expired = self.expired_archived(retention_days, now);
+        if !expired.is_empty() {
+            self.delete_many(&expired);
+        }
+        expired.len()
+    }
+
     fn delete(&mut self, thread_id: &str) -> bool {
         let before = self.records.len();
         self.records.retain(|entry| entry.thread_id != thread_id);
         let changed = self.records.len() != before;
         if changed {
+            self.forget_thread_items(thread_id);
             let _ = std::fs::remove_file(self.thread_items_path(thread_id));
+            let _ = std::fs::remove_file(self.annotations_path(thread_id));
+            let _ = std::fs::remove_file(self.summary_cache_path(thread_id));
             self.persist();
         }
         changed
     }
 
-    fn set_title(&mut self, thread_id: &str, title: String) {
-        if let Some(entry) = self
+    fn set_tags(&mut self, thread_id: &str, tags: Vec<String>) -> bool {
+        let Some(entry) = self
             .records
             .iter_mut()
             .find(|entry| entry.thread_id == thread_id)
+        else {
+            return false;
+        };
+        entry.tags = tags;
+        self.persist();
+        true
+    }
+
+    fn set_pinned(&mut self, thread_id: &str, pinned: bool) -> bool {
+        let Some(entry) = self
+            .records
+            .iter_mut()
+            .find(|entry| entry.thread_id == thread_id)
+        else {
+            return false;
+        };
+        entry.pinned = pinned;
+        self.persist();
+        true
+    }
+
+    /// Unarchived threads in `thread/list` order: pinned first, then most recently
+    /// updated.
+    fn list_for_display(&self) -> Vec<LocalThreadRecord> {
+        let mut records = self.list_unarchived();
+        records.sort_by(|a, b| {
+            b.pinned
+                .cmp(&a.pinned)
+                .then_with(|| b.updated_at.cmp(&a.updated_at))
+        });
+        records
+    }
+
+    /// Refuses to archive or delete a pinned thread unless `force` is set.
+    fn check_archivable(&self, thread_id: &str, force: bool) -> Result<(), CommandError> {
+        if force
+            || !self
+                .by_thread_id(thread_id)
+                .is_some_and(|entry| entry.pinned)
         {
-            entry.title = title;
-            entry.updated_at = now_ts();
+            return Ok(());
+        }
+        Err(CommandError::new(
+            ErrorCode::ThreadPinned,
+            format!("{THREAD_PINNED}: {thread_id}"),
+        ))
+    }
+
+    /// Unarchived threads inside the scope of `options`, with their item count and size on
+    /// disk.
+    fn prune_candidates(
+        &self,
+        workspace_id: &str,
+        options: &HistoryPruneOptions,
+        protected: &HashSet<String>,
+    ) -> Vec<PrunedThread> {
+        let now = now_ts();
+        self.records
+            .iter()
+            .filter(|entry| !entry.archived)
+            .filter(|entry| {
+                options.matches(
+                    &entry.thread_id,
+                    entry.updated_at,
+                    &entry.tags,
+                    entry.pinned,
+                    protected,
+                    now,
+                )
+            })
+            .map(|entry| {
+                let items_path = self.thread_items_path(&entry.thread_id);
+                PrunedThread {
+                    workspace_id: workspace_id.to_string(),
+                    thread_id: entry.thread_id.clone(),
+                    title: entry.title.clone(),
+                    updated_at: entry.updated_at,
+                    item_count: self.load_thread_items(&entry.thread_id).len(),
+                    disk_bytes: std::fs::metadata(items_path)
+                        .map(|meta| meta.len())
+                        .unwrap_or(0),
+                }
+            })
+            .collect()
+    }
+
+    /// Drops several threads with a single atomic rewrite of `sessions.json`, then removes
+    /// their items files. A crash in between leaves orphaned items files that store
+    /// maintenance can report, never records pointing at missing history.
+    fn delete_many(&mut self, thread_ids: &HashSet<String>) {
+        let before = self.records.len();
+        self.records
+            .retain(|entry| !thread_ids.contains(&entry.thread_id));
+        if self.records.len() == before {
+            return;
+        }
+        if let Some(parent) = self.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let Ok(raw) = serde_json::to_string(&self.records) else {
+            return;
+        };
+        let tmp_path = self.path.with_extension("json.tmp");
+        if std::fs::write(&tmp_path, raw).is_err()
+            || std::fs::rename(&tmp_path, &self.path).is_err()
+        {
+            let _ = std::fs::remove_file(&tmp_path);
             self.persist();
         }
+        for thread_id in thread_ids {
+            self.forget_thread_items(thread_id);
+            let _ = std::fs::remove_file(self.thread_items_path(thread_id));
+            let _ = std::fs::remove_file(self.annotations_path(thread_id));
+            let _ = std::fs::remove_file(self.summary_cache_path(thread_id));
+        }
+    }
+
+    /// Moves the seen marker to `seq` (clamped to the stored items), or to the last item
+    /// when `seq` is omitted. Returns the new marker.
+    fn mark_seen(&mut self, thread_id: &str, seq: Option<u64>) -> Option<u64> {
+        let last_seq = last_item_seq(&self.load_thread_items(thread_id));
+        let entry = self
+            .records
+            .iter_mut()
+            .find(|entry| entry.thread_id == thread_id)?;
+        let marker = seq.unwrap_or(last_seq).min(last_seq);
+        entry.last_seen_item_seq = Some(marker);
+        self.persist();
+        Some(marker)
+    }
+
+    /// Items after the seen marker, paired with their sequence numbers.
+    fn unseen_items(&self, record: &LocalThreadRecord) -> Vec<(u64, Value)> {
+        let items = self.load_thread_items(&record.thread_id);
+        let seen = record
+            .last_seen_item_seq
+            .unwrap_or_else(|| last_item_seq(&items));
+        items
+            .into_iter()
+            .filter_map(|item| Some((item_seq(&item)?, item)))
+            .filter(|(seq, _)| *seq > seen)
+            .collect()
+    }
+
+    /// Seconds timestamp of the last write to the thread's items file.
+    fn last_item_at(&self, thread_id: &str) -> Option<i64> {
+        let modified = std::fs::metadata(self.thread_items_path(thread_id))
+            .and_then(|meta| meta.modified())
+            .ok()?;
+        modified
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|duration| duration.as_secs() as i64)
+    }
+
+    /// Renames a thread and returns the `thread/name/updated` params to emit. A generated
+    /// title only replaces one derived from the prompt; otherwise nothing changes and this
+    /// returns `None`.
+    fn rename_thread(
+        &mut self,
+        thread_id: &str,
+        title: String,
+        source: ThreadTitleSource,
+    ) -> Option<Value> {
+        let entry = self
+            .records
+            .iter_mut()
+            .find(|entry| entry.thread_id == thread_id);
+        match entry {
+            Some(entry) => {
+                if source == ThreadTitleSource::Generated
+                    && entry.title_source != Some(ThreadTitleSource::Prompt)
+                {
+                    return None;
+                }
+                entry.title = title.clone();
+                entry.title_source = Some(source);
+                entry.updated_at = now_ts();
+                self.persist();
+            }
+            None if source == ThreadTitleSource::Generated => return None,
+            None => {}
+        }
+        Some(json!({ "threadId": thread_id, "threadName": title }))
+    }
+
+    fn set_access_mode(&mut self, thread_id: &str, access_mode: &str) {
+        if let Some(entry) = self
+            .records
+            .iter_mut()
+            .find(|entry| entry.thread_id == thread_id)
+        {
+            if entry.access_mode.as_deref() != Some(access_mode) {
+                entry.access_mode = Some(access_mode.to_string());
+                self.persist();
+            }
+        }
     }
 
     fn set_session_id(&mut self, thread_id: &str, session_id: String) {
@@ -214,65 +707,214 @@ impl LocalThreadStore {
         changed
     }
 
+    fn thread_items_dir(&self) -> PathBuf {
+        self.path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("thread-items")
+    }
+
     fn thread_items_path(&self, thread_id: &str) -> PathBuf {
+        let safe_thread_id = thread_id.replace('/', "_");
+        self.thread_items_dir()
+            .join(format!("{safe_thread_id}.json"))
+    }
+
+    fn annotations_path(&self, thread_id: &str) -> PathBuf {
         let safe_thread_id = thread_id.replace('/', "_");
         self.path
             .parent()
             .unwrap_or_else(|| Path::new("."))
-            .join("thread-items")
+            .join("annotations")
+            .join(format!("{safe_thread_id}.json"))
+    }
+
+    fn summary_cache_path(&self, thread_id: &str) -> PathBuf {
+        let safe_thread_id = thread_id.replace('/', "_");
+        self.path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("thread-summaries")
             .join(format!("{safe_thread_id}.json"))
     }
 
+    fn load_annotations(&self, thread_id: &str) -> ThreadAnnotations {
+        ThreadAnnotations::load(&self.annotations_path(thread_id))
+    }
+
+    fn save_annotations(
+        &self,
+        thread_id: &str,
+        annotations: &ThreadAnnotations,
+    ) -> Result<(), String> {
+        annotations.save(&self.annotations_path(thread_id))
+    }
+
+    fn thread_items_files(&self) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(self.thread_items_dir()) else {
+            return Vec::new();
+        };
+        let mut files: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// Checks `sessions.json`, repairs session collisions and rewrites it compactly. A
+    /// file that no longer parses is reported and never overwritten.
+    fn maintain_records(&mut self, report: &mut StoreMaintenanceReport) {
+        let Ok(raw) = std::fs::read_to_string(&self.path) else {
+            return;
+        };
+        let bytes_before = raw.len() as u64;
+        if serde_json::from_str::<Vec<LocalThreadRecord>>(&raw).is_err() {
+            report.record(
+                &self.path,
+                FileMaintenanceOutcome::Corrupted {
+                    bytes: bytes_before,
+                },
+            );
+            return;
+        }
+        report.session_collisions_repaired = self.repair_session_collisions();
+        let compact = serde_json::to_string(&self.records).unwrap_or_default();
+        let rewritten = compact != raw && write_file_atomically(&self.path, &compact).is_ok();
+        report.record(
+            &self.path,
+            FileMaintenanceOutcome::Checked {
+                bytes_before,
+                bytes_after: compact.len() as u64,
+                duplicates_removed: 0,
+                rewritten,
+            },
+        );
+    }
+
+    /// Items of the thread in `seq` order. Items saved before they carried a `seq` are
+    /// numbered on read (see `number_unsequenced_items`); the next write of the thread
+    /// saves those numbers, so reading never touches the file.
     fn load_thread_items(&self, thread_id: &str) -> Vec<Value> {
+        if let Some(items) = self.cached_items().items.get(thread_id) {
+            return items.clone();
+        }
         let path = self.thread_items_path(thread_id);
         let Ok(raw) = std::fs::read_to_string(path) else {
             return Vec::new();
         };
-        serde_json::from_str::<Vec<Value>>(&raw).unwrap_or_default()
+        parse_thread_items(&raw)
     }
 
+    /// Items of the thread in `seq` order, read from the file and bypassing the cache;
+    /// for readers that run beside a live session.
+    fn read_thread_items(&self, thread_id: &str) -> Vec<Value> {
+        read_items_file(&self.thread_items_path(thread_id))
+    }
+
+    /// Saves the thread's items: into the cache on a session's store, to disk otherwise.
     fn persist_thread_items(&self, thread_id: &str, items: &[Value]) {
-        let path = self.thread_items_path(thread_id);
-        if let Some(parent) = path.parent() {
-            let _ = std::fs::create_dir_all(parent);
-        }
-        if let Ok(raw) = serde_json::to_string_pretty(items) {
-            let _ = std::fs::write(path, raw);
-        }
+        let Some(notify) = &self.flush_notify else {
+            self.items_file_writes.fetch_add(1, Ordering::Relaxed);
+            write_items_file(&self.thread_items_path(thread_id), items);
+            return;
+        };
+        let mut cache = self.cached_items();
+        cache.items.insert(thread_id.to_string(), items.to_vec());
+        cache.dirty.insert(thread_id.to_string());
+        drop(cache);
+        notify.notify_one();
+    }
+
+    fn cached_items(&self) -> std::sync::MutexGuard<'_, ThreadItemsCache> {
+        self.items_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Takes the item lists changed since the last flush, to be written off the store
+    /// lock. They stay cached, so reads in between still see them.
+    fn take_dirty_items(&self) -> Vec<(PathBuf, Vec<Value>)> {
+        let mut cache = self.cached_items();
+        let dirty = std::mem::take(&mut cache.dirty);
+        let batch: Vec<(PathBuf, Vec<Value>)> = dirty
+            .into_iter()
+            .filter_map(|thread_id| {
+                let items = cache.items.get(&thread_id)?.clone();
+                Some((self.thread_items_path(&thread_id), items))
+            })
+            .collect();
+        self.items_file_writes
+            .fetch_add(batch.len() as u64, Ordering::Relaxed);
+        batch
+    }
+
+    fn forget_thread_items(&self, thread_id: &str) {
+        let mut cache = self.cached_items();
+        cache.items.remove(thread_id);
+        cache.dirty.remove(thread_id);
+    }
+
+    /// Whether the cache holds items for the file at `path` that no flush has written.
+    fn has_dirty_items_at(&self, path: &Path) -> bool {
+        self.cached_items()
+            .dirty
+            .iter()
+            .any(|thread_id| self.thread_items_path(thread_id) == path)
     }
 
-    fn upsert_thread_item(&self, thread_id: &str, item: Value) {
+    /// Drops the cached list of the file at `path` if it is already on disk, so the next
+    /// read sees the file again.
+    fn evict_clean_items_at(&self, path: &Path) {
+        let mut cache = self.cached_items();
+        let ThreadItemsCache { items, dirty } = &mut *cache;
+        items.retain(|thread_id, _| {
+            dirty.contains(thread_id) || self.thread_items_path(thread_id) != path
+        });
+    }
+
+    /// Whether the thread has saved items, flushed or not.
+    fn has_thread_items(&self, thread_id: &str) -> bool {
+        self.cached_items().items.contains_key(thread_id)
+            || self.thread_items_path(thread_id).is_file()
+    }
+
+    /// Saves an item. A new item gets the next `seq`; an update keeps the `seq` the item
+    /// was first saved with, so it stays where it started however late it completes.
+    fn upsert_thread_item(&self, thread_id: &str, mut item: Value) {
         let mut items = self.load_thread_items(thread_id);
-        let item_id = item
+        let existing = item
             .get("id")
             .and_then(Value::as_str)
-            .map(|value| value.to_string());
-        if let Some(item_id) = item_id {
-            if let Some(index) = items.iter().position(|entry| {
-                entry
-                    .get("id")
-                    .and_then(Value::as_str)
-                    .map(|value| value == item_id)
-                    .unwrap_or(false)
-            }) {
-                items[index] = item;
-            } else {
-                items.push(item);
-            }
-        } else {
-            items.push(item);
+            .and_then(|item_id| {
+                items
+                    .iter()
+                    .position(|entry| entry.get("id").and_then(Value::as_str) == Some(item_id))
+            });
+        let seq = existing
+            .and_then(|index| item_seq(&items[index]))
+            .unwrap_or_else(|| last_item_seq(&items) + 1);
+        if let Some(object) = item.as_object_mut() {
+            object.insert("seq".to_string(), json!(seq));
+        }
+        match existing {
+            Some(index) => items[index] = item,
+            None => items.push(item),
         }
         self.persist_thread_items(thread_id, &items);
     }
 
+    /// Attaches token usage to the last agent message segment of the turn.
     fn set_agent_item_token_usage(&self, thread_id: &str, turn_id: &str, token_usage: &Value) {
-        let target_item_id = format!("agent-{thread_id}-{turn_id}");
+        let base_item_id = agent_item_id(thread_id, turn_id, 0);
+        let segment_prefix = format!("{base_item_id}-s");
         let mut items = self.load_thread_items(thread_id);
-        let Some(index) = items.iter().position(|entry| {
+        let Some(index) = items.iter().rposition(|entry| {
             entry
                 .get("id")
                 .and_then(Value::as_str)
-                .map(|value| value == target_item_id)
+                .map(|value| value == base_item_id || value.starts_with(&segment_prefix))
                 .unwrap_or(false)
         }) else {
             return;
@@ -283,64 +925,426 @@ impl LocalThreadStore {
         item.insert("tokenUsage".to_string(), token_usage.clone());
         self.persist_thread_items(thread_id, &items);
     }
+
+    /// Adds an artifact to one item, replacing an earlier one with the same path.
+    fn add_item_artifact(&self, thread_id: &str, item_id: &str, artifact: Value) -> bool {
+        let mut items = self.load_thread_items(thread_id);
+        let Some(item) = items
+            .iter_mut()
+            .find(|entry| entry.get("id").and_then(Value::as_str) == Some(item_id))
+            .and_then(Value::as_object_mut)
+        else {
+            return false;
+        };
+        let artifacts = item
+            .entry("artifacts")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        let Some(artifacts) = artifacts.as_array_mut() else {
+            return false;
+        };
+        artifacts.retain(|existing| existing.get("path") != artifact.get("path"));
+        artifacts.push(artifact);
+        self.persist_thread_items(thread_id, &items);
+        true
+    }
+
+    /// Attaches artifacts to the last agent message segment of the turn.
+    fn set_agent_item_artifacts(&self, thread_id: &str, turn_id: &str, artifacts: &[Value]) {
+        let base_item_id = format!("agent-{thread_id}-{turn_id}");
+        let segment_prefix = format!("{base_item_id}-s");
+        let mut items = self.load_thread_items(thread_id);
+        let Some(index) = items.iter().rposition(|entry| {
+            entry
+                .get("id")
+                .and_then(Value::as_str)
+                .map(|value| value == base_item_id || value.starts_with(&segment_prefix))
+                .unwrap_or(false)
+        }) else {
+            return;
+        };
+        let Some(item) = items[index].as_object_mut() else {
+            return;
+        };
+        item.insert("artifacts".to_string(), Value::Array(artifacts.to_vec()));
+        self.persist_thread_items(thread_id, &items);
+    }
 }
 
-fn now_ts() -> i64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs() as i64
+impl Drop for LocalThreadStore {
+    /// Writes what the flush task has not yet, then wakes it so it sees the store is gone.
+    fn drop(&mut self) {
+        write_items_files(&self.take_dirty_items());
+        if let Some(notify) = &self.flush_notify {
+            notify.notify_one();
+        }
+    }
 }
 
-fn derive_thread_title(prompt: &str) -> Option<String> {
-    let first_line = prompt.lines().next().unwrap_or_default().trim();
-    if first_line.is_empty() {
-        return None;
+fn read_items_file(path: &Path) -> Vec<Value> {
+    parse_thread_items(&std::fs::read_to_string(path).unwrap_or_default())
+}
+
+fn write_items_file(path: &Path, items: &[Value]) {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
     }
-    let compact = first_line.split_whitespace().collect::<Vec<_>>().join(" ");
-    if compact.is_empty() {
-        return None;
+    if let Ok(raw) = serde_json::to_string(items) {
+        let _ = std::fs::write(path, raw);
     }
-    let title = compact.chars().take(38).collect::<String>();
-    Some(title)
 }
 
-fn build_user_thread_item(thread_id: &str, turn_id: &str, text: &str) -> Value {
-    json!({
-        "id": format!("user-{thread_id}-{turn_id}"),
-        "type": "userMessage",
-        "content": [
-            {
-                "type": "text",
-                "text": text
-            }
-        ]
-    })
+fn write_items_files(batch: &[(PathBuf, Vec<Value>)]) {
+    for (path, items) in batch {
+        write_items_file(path, items);
+    }
 }
 
-fn build_agent_thread_item(thread_id: &str, turn_id: &str, text: &str) -> Value {
-    json!({
-        "id": format!("agent-{thread_id}-{turn_id}"),
-        "type": "agentMessage",
-        "text": text
-    })
+/// Writes the item lists changed in a session's store on a blocking thread.
+async fn flush_thread_items(store: &Mutex<LocalThreadStore>) {
+    let flush_lock = store.lock().await.flush_lock.clone();
+    let _flushing = flush_lock.lock().await;
+    write_dirty_thread_items(store).await;
 }
 
-fn build_tool_thread_item(
-    thread_id: &str,
-    tool_item_id: &str,
-    presentation: &ToolCallPresentation,
-    status: &str,
-) -> Value {
-    json!({
-        "id": tool_item_id,
+/// `flush_thread_items` for a caller already holding the store's `flush_lock`.
+async fn write_dirty_thread_items(store: &Mutex<LocalThreadStore>) {
+    let batch = store.lock().await.take_dirty_items();
+    if batch.is_empty() {
+        return;
+    }
+    let _ = tokio::task::spawn_blocking(move || write_items_files(&batch)).await;
+}
+
+/// Flushes `store` `THREAD_ITEMS_FLUSH_DELAY` after the first change of a burst. Ends
+/// once the store is dropped, whose `Drop` writes the rest.
+fn spawn_thread_items_flusher(store: &Arc<Mutex<LocalThreadStore>>, notify: Arc<Notify>) {
+    let store = Arc::downgrade(store);
+    tokio::spawn(async move {
+        loop {
+            notify.notified().await;
+            sleep(THREAD_ITEMS_FLUSH_DELAY).await;
+            let Some(store) = store.upgrade() else {
+                return;
+            };
+            flush_thread_items(&store).await;
+        }
+    });
+}
+
+fn now_ts() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Scoped history pruning for a workspace without a running session. Nothing can be
+/// resumed or running there, so only pins and tags protect threads.
+pub(crate) fn prune_thread_history_at(
+    workspace_id: &str,
+    workspace_path: &str,
+    options: &HistoryPruneOptions,
+) -> Vec<PrunedThread> {
+    let mut store = LocalThreadStore::load(workspace_path);
+    prune_store(&mut store, workspace_id, options, &HashSet::new())
+}
+
+/// Thread titles of a workspace keyed by MiCode session id. When threads share a session
+/// the most recently updated one wins, as in `by_session_id`.
+pub(crate) fn thread_titles_by_session(workspace_path: &str) -> HashMap<String, String> {
+    let store = LocalThreadStore::load(workspace_path);
+    let session_ids: HashSet<&str> = store
+        .records
+        .iter()
+        .map(|record| record.session_id.as_str())
+        .filter(|session_id| !session_id.trim().is_empty())
+        .collect();
+    session_ids
+        .into_iter()
+        .filter_map(|session_id| {
+            store
+                .by_session_id(session_id)
+                .map(|record| (session_id.to_string(), record.title))
+        })
+        .collect()
+}
+
+/// Case-insensitive search over the names and saved items of the workspace's unarchived
+/// threads, most recently updated first, up to `limit` hits. Reads the store files only,
+/// so a running session is not disturbed.
+pub(crate) fn search_threads_at(
+    workspace_path: &str,
+    query: &str,
+    limit: usize,
+) -> Vec<ThreadSearchHit> {
+    let Some(query) = normalize_query(query) else {
+        return Vec::new();
+    };
+    let store = LocalThreadStore::load(workspace_path);
+    let mut records = store.list_unarchived();
+    records.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    let mut hits = Vec::new();
+    for record in records {
+        if hits.len() >= limit {
+            break;
+        }
+        let hit = |item_id: Option<String>, snippet: String| ThreadSearchHit {
+            thread_id: record.thread_id.clone(),
+            thread_name: record.title.clone(),
+            item_id,
+            snippet,
+            updated_at: record.updated_at,
+        };
+        if let Some(snippet) = match_snippet(&record.title, &query) {
+            hits.push(hit(None, snippet));
+        }
+        for item in &store.read_thread_items(&record.thread_id) {
+            if hits.len() >= limit {
+                break;
+            }
+            let Some(snippet) = match_snippet(&item_search_text(item), &query) else {
+                continue;
+            };
+            let item_id = item.get("id").and_then(Value::as_str).map(str::to_string);
+            hits.push(hit(item_id, snippet));
+        }
+    }
+    hits
+}
+
+/// Renders one saved thread, archived or not, as Markdown or JSON. Reads the store files
+/// only, like `search_threads_at`.
+pub(crate) fn export_thread_at(
+    workspace_path: &str,
+    thread_id: &str,
+    format: ThreadExportFormat,
+) -> Result<String, CommandError> {
+    let store = LocalThreadStore::load(workspace_path);
+    let record = store
+        .by_thread_id(thread_id)
+        .ok_or_else(|| CommandError::thread_not_found(thread_id))?;
+    let thread = ExportedThread {
+        id: record.thread_id.clone(),
+        name: record.title,
+        updated_at: record.updated_at,
+        tags: record.tags,
+    };
+    Ok(render_thread_export(
+        &thread,
+        &store.read_thread_items(thread_id),
+        format,
+    )?)
+}
+
+fn prune_store(
+    store: &mut LocalThreadStore,
+    workspace_id: &str,
+    options: &HistoryPruneOptions,
+    protected: &HashSet<String>,
+) -> Vec<PrunedThread> {
+    let candidates = store.prune_candidates(workspace_id, options, protected);
+    if !options.dry_run {
+        let ids: HashSet<String> = candidates
+            .iter()
+            .map(|thread| thread.thread_id.clone())
+            .collect();
+        store.delete_many(&ids);
+    }
+    candidates
+}
+
+/// The workspace override wins over the app setting; `None` when the result is 0.
+fn resolve_prompt_timeout(workspace_secs: Option<u64>, app_secs: u64) -> Option<Duration> {
+    let secs = workspace_secs.unwrap_or(app_secs);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Awaits a `session/prompt` request, giving up after `limit`. A timeout reports how long
+/// the request ran.
+async fn with_prompt_timeout<T>(
+    limit: Option<Duration>,
+    request: impl std::future::Future<Output = T>,
+) -> Result<T, Duration> {
+    let started = Instant::now();
+    match limit {
+        Some(limit) => timeout(limit, request).await.map_err(|_| started.elapsed()),
+        None => Ok(request.await),
+    }
+}
+
+/// Builds the `turn/start` timeout error, naming the request that never answered.
+fn prompt_timeout_error(stage: &str, abandoned: Option<PendingRequestInfo>) -> CommandError {
+    let base = format!("turn/start timed out waiting for MiCode response after {stage}");
+    let message = match abandoned {
+        Some(info) => format!("{base}: {}", info.describe(now_ms())),
+        None => base,
+    };
+    CommandError::new(ErrorCode::AcpTimeout, message)
+}
+
+fn build_user_thread_item(
+    thread_id: &str,
+    turn_id: &str,
+    text: &str,
+    redactions: &BTreeMap<String, u32>,
+) -> Value {
+    let mut item = json!({
+        "id": format!("user-{thread_id}-{turn_id}"),
+        "type": "userMessage",
+        "content": [
+            {
+                "type": "text",
+                "text": text
+            }
+        ]
+    });
+    if !redactions.is_empty() {
+        item["redactions"] = json!(redactions);
+    }
+    item
+}
+
+/// The text parts of a `userMessage` item, joined by newlines.
+fn user_message_text(item: &Value) -> String {
+    item.get("content")
+        .and_then(Value::as_array)
+        .map(|parts| {
+            parts
+                .iter()
+                .filter(|part| part.get("type").and_then(Value::as_str) == Some("text"))
+                .filter_map(|part| part.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default()
+}
+
+/// Store-assigned position of a persisted thread item.
+fn item_seq(item: &Value) -> Option<u64> {
+    item.get("seq").and_then(Value::as_u64)
+}
+
+fn last_item_seq(items: &[Value]) -> u64 {
+    items.iter().filter_map(item_seq).max().unwrap_or(0)
+}
+
+/// Parses a thread items file into `seq` order.
+fn parse_thread_items(raw: &str) -> Vec<Value> {
+    let mut items = serde_json::from_str::<Vec<Value>>(raw).unwrap_or_default();
+    number_unsequenced_items(&mut items);
+    items.sort_by_key(|item| item_seq(item).unwrap_or(u64::MAX));
+    items
+}
+
+/// Gives items saved without a `seq` the next numbers after the highest stored one, in
+/// their stored order. Items that already have a `seq` keep it, so seen markers stay put,
+/// and the same file always gets the same numbers.
+fn number_unsequenced_items(items: &mut [Value]) {
+    let mut next = last_item_seq(items);
+    for item in items.iter_mut() {
+        if item_seq(item).is_some() {
+            continue;
+        }
+        if let Some(object) = item.as_object_mut() {
+            next += 1;
+            object.insert("seq".to_string(), json!(next));
+        }
+    }
+}
+
+/// Id of one segment of a turn's agent message; the stream is split around tool calls.
+fn agent_item_id(thread_id: &str, turn_id: &str, segment: u32) -> String {
+    if segment == 0 {
+        return format!("agent-{thread_id}-{turn_id}");
+    }
+    format!("agent-{thread_id}-{turn_id}-s{segment}")
+}
+
+fn build_agent_thread_item(thread_id: &str, turn_id: &str, segment: u32, text: &str) -> Value {
+    json!({
+        "id": agent_item_id(thread_id, turn_id, segment),
+        "type": "agentMessage",
+        "text": text
+    })
+}
+
+/// A segment saved while it still streams. The save at the end of the prompt replaces
+/// it without the flag, so an item still `partial` on resume was cut off by a crash.
+fn build_partial_agent_thread_item(
+    thread_id: &str,
+    turn_id: &str,
+    segment: u32,
+    text: &str,
+) -> Value {
+    let mut item = build_agent_thread_item(thread_id, turn_id, segment, text);
+    item["partial"] = json!(true);
+    item
+}
+
+fn build_reasoning_thread_item(thread_id: &str, turn_id: &str, text: &str) -> Value {
+    json!({
+        "id": format!("reasoning-{thread_id}-{turn_id}"),
+        "type": "reasoning",
+        "summary": "",
+        "content": text
+    })
+}
+
+/// Left in the transcript where the user stopped a turn, so resumed threads show it.
+fn build_interruption_note_item(thread_id: &str, turn_id: &str) -> Value {
+    json!({
+        "id": format!("note-{thread_id}-{turn_id}"),
+        "type": "systemNote",
+        "reason": "interrupted",
+        "text": INTERRUPTED_BY_USER_NOTE
+    })
+}
+
+/// The saved items of a thread as the single history turn `thread/resume` returns, in
+/// the order they were saved.
+fn history_turns(thread_id: &str, items: &[Value]) -> Vec<Value> {
+    if items.is_empty() {
+        return Vec::new();
+    }
+    vec![json!({
+        "id": format!("turn-history-{thread_id}"),
+        "items": items
+    })]
+}
+
+fn cancelled_turn_response(turn: &Value, cancelled_by: Option<&str>) -> Value {
+    let mut result = json!({ "stopReason": "cancelled", "turn": turn });
+    if let Some(cancelled_by) = cancelled_by {
+        result["cancelledBy"] = json!(cancelled_by);
+    }
+    json!({ "result": result })
+}
+
+fn build_tool_thread_item(
+    thread_id: &str,
+    tool_item_id: &str,
+    presentation: &ToolCallPresentation,
+    status: &str,
+) -> Value {
+    json!({
+        "id": tool_item_id,
         "type": "mcpToolCall",
         "server": presentation.server,
         "tool": presentation.tool,
         "title": tool_call_display_title(presentation),
+        "summaryText": tool_call_summary_text(presentation, status == "completed"),
         "arguments": presentation.arguments,
         "result": presentation.result,
+        "resultJson": presentation.result_json,
         "error": presentation.error,
+        "durationMs": presentation.duration_ms,
         "status": status,
         "threadId": thread_id
     })
@@ -443,45 +1447,43 @@ fn sanitize_approval_title(raw: Option<&str>) -> Option<String> {
     }
 }
 
-fn extract_approval_command(params: &Value) -> Vec<String> {
+/// The tokens of the `command`, `argv` or `args` field a permission request carries, on
+/// its tool call or at the top level. `None` when it names no command, as for MCP tools
+/// and file writes; only these tokens are matched against remembered rules.
+fn approval_command_tokens(params: &Value) -> Option<Vec<String>> {
     let tool_call = params.get("toolCall");
-    let mut command = extract_command_tokens(tool_call.and_then(|value| value.get("command")));
-    if command.is_empty() {
-        command = extract_command_tokens(tool_call.and_then(|value| value.get("argv")));
-    }
-    if command.is_empty() {
-        command = extract_command_tokens(tool_call.and_then(|value| value.get("args")));
-    }
-    if command.is_empty() {
-        command = extract_command_tokens(params.get("command"));
-    }
-    if command.is_empty() {
-        command = extract_command_tokens(params.get("argv"));
-    }
-    if command.is_empty() {
-        command = extract_command_tokens(params.get("args"));
-    }
-    if command.is_empty() {
-        if let Some(title) = sanitize_approval_title(
-            tool_call
-                .and_then(|value| value.get("title"))
-                .and_then(Value::as_str),
-        ) {
-            command.push(title);
-        }
-    }
-    if command.is_empty() {
-        command.push("Approve action".to_string());
+    let fields = ["command", "argv", "args"];
+    fields
+        .iter()
+        .map(|key| tool_call.and_then(|value| value.get(*key)))
+        .chain(fields.iter().map(|key| params.get(*key)))
+        .map(extract_command_tokens)
+        .find(|command| !command.is_empty())
+}
+
+fn extract_approval_command(params: &Value) -> Vec<String> {
+    if let Some(command) = approval_command_tokens(params) {
+        return command;
     }
-    command
+    let title = sanitize_approval_title(
+        params
+            .get("toolCall")
+            .and_then(|value| value.get("title"))
+            .and_then(Value::as_str),
+    );
+    vec![title.unwrap_or_else(|| "Approve action".to_string())]
 }
 
-fn micode_settings_path() -> Option<PathBuf> {
-    let micode_home = resolve_micode_home_path()?;
+fn micode_settings_path(home: Option<&Path>) -> Option<PathBuf> {
+    let micode_home = resolve_micode_home_path(home)?;
     Some(micode_home.join("settings.json"))
 }
 
-fn resolve_micode_home_path() -> Option<PathBuf> {
+/// `home` is a session's isolated agent home; `None` resolves the user's home.
+fn resolve_micode_home_path(home: Option<&Path>) -> Option<PathBuf> {
+    if let Some(home) = home {
+        return Some(home.to_path_buf());
+    }
     if let Ok(raw) = env::var("MICODE_HOME") {
         let trimmed = raw.trim();
         if !trimmed.is_empty() {
@@ -541,17 +1543,12 @@ fn normalize_mcp_server_env(value: Option<&Value>) -> Vec<Value> {
     env_entries
 }
 
-fn read_configured_mcp_servers() -> Value {
-    let Some(settings_path) = micode_settings_path() else {
+fn read_configured_mcp_servers(home: Option<&Path>, errors: &SettingsParseErrors) -> Value {
+    let Some(settings_path) = micode_settings_path(home) else {
         return json!([]);
     };
-    let raw = match std::fs::read_to_string(settings_path) {
-        Ok(value) => value,
-        Err(_) => return json!([]),
-    };
-    let root: Value = match serde_json::from_str(&raw) {
-        Ok(value) => value,
-        Err(_) => return json!([]),
+    let Some(root) = read_settings_file(&settings_path, errors) else {
+        return json!([]);
     };
     let mut servers: Vec<Value> = Vec::new();
     match root.get("mcpServers") {
@@ -685,6 +1682,7 @@ fn parse_thread_token_usage_from_session(value: &Value) -> Option<Value> {
     let mut total_reasoning = 0_i64;
     let mut total_total = 0_i64;
     let mut last = None;
+    let mut model = None;
 
     for message in messages {
         let Some((input, cached, output, reasoning, total)) =
@@ -692,6 +1690,9 @@ fn parse_thread_token_usage_from_session(value: &Value) -> Option<Value> {
         else {
             continue;
         };
+        if let Some(name) = message.get("model").and_then(Value::as_str) {
+            model = Some(name.to_string());
+        }
         total_input = total_input.saturating_add(input);
         total_cached_input = total_cached_input.saturating_add(cached);
         total_output = total_output.saturating_add(output);
@@ -719,79 +1720,110 @@ fn parse_thread_token_usage_from_session(value: &Value) -> Option<Value> {
             "outputTokens": total_output,
             "reasoningOutputTokens": total_reasoning
         },
-        "modelContextWindow": null
+        "modelContextWindow": null,
+        "model": model
     }))
 }
 
-fn load_thread_token_usage_for_session_in_home(
+/// Parses the chat file at `path` if it still records `session_id`.
+fn read_chat_session(path: &Path, session_id: &str) -> Option<Value> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    let parsed: Value = serde_json::from_str(&raw).ok()?;
+    (parsed.get("sessionId").and_then(Value::as_str) == Some(session_id)).then_some(parsed)
+}
+
+fn load_thread_token_usage_for_session(
     session_id: &str,
-    micode_home: &Path,
+    chat_files: &std::sync::Mutex<ChatFileIndex>,
 ) -> Option<Value> {
     let normalized_session_id = session_id.trim();
     if normalized_session_id.is_empty() {
         return None;
     }
 
-    let tmp_root = micode_home.join("tmp");
-    let project_dirs = std::fs::read_dir(&tmp_root).ok()?;
-    let mut latest: Option<(SystemTime, Value)> = None;
+    let read_session = |path: &Path| read_chat_session(path, normalized_session_id);
+    let path = find_chat_file(chat_files, normalized_session_id)?;
+    let parsed = match read_session(&path) {
+        Some(parsed) => parsed,
+        None => {
+            // The indexed file was rewritten for another session; re-index it and retry once.
+            forget_chat_file(chat_files, &path);
+            read_session(&find_chat_file(chat_files, normalized_session_id)?)?
+        }
+    };
+    parse_thread_token_usage_from_session(&parsed)
+}
 
-    for project_dir in project_dirs.flatten() {
-        let chats_dir = project_dir.path().join("chats");
-        if !chats_dir.is_dir() {
-            continue;
+/// Token usage read while turns run: the chat files of prompted sessions are polled and
+/// each new count is reported against the turn its session is running.
+struct TokenUsageWatch {
+    files: ChatFileWatch,
+    /// Thread and turn each watched session reports to.
+    turns: HashMap<String, (String, String)>,
+    last_usage: HashMap<String, Value>,
+}
+
+impl TokenUsageWatch {
+    fn new(chat_files: Arc<std::sync::Mutex<ChatFileIndex>>) -> Self {
+        Self {
+            files: ChatFileWatch::new(chat_files),
+            turns: HashMap::new(),
+            last_usage: HashMap::new(),
         }
-        let chat_files = match std::fs::read_dir(chats_dir) {
-            Ok(entries) => entries,
-            Err(_) => continue,
-        };
-        for chat_file in chat_files.flatten() {
-            let path = chat_file.path();
-            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+    }
+
+    fn track(&mut self, session_id: &str, thread_id: &str, turn_id: &str) {
+        // A thread reports through its newest session only.
+        let replaced: Vec<String> = self
+            .turns
+            .iter()
+            .filter(|(tracked, (tracked_thread, _))| {
+                tracked_thread == thread_id && tracked.as_str() != session_id
+            })
+            .map(|(tracked, _)| tracked.clone())
+            .collect();
+        for tracked in replaced {
+            self.turns.remove(&tracked);
+            self.last_usage.remove(&tracked);
+            self.files.unwatch(&tracked);
+        }
+        self.files.watch(session_id);
+        self.turns.insert(
+            session_id.to_string(),
+            (thread_id.to_string(), turn_id.to_string()),
+        );
+    }
+
+    /// Re-parses the chat files written since the previous poll and returns
+    /// `thread/tokenUsage/updated` params for each count that changed.
+    fn poll(&mut self) -> Vec<Value> {
+        let mut updates = Vec::new();
+        for (session_id, path) in self.files.poll() {
+            let Some((thread_id, turn_id)) = self.turns.get(&session_id) else {
                 continue;
-            }
-            let raw = match std::fs::read_to_string(&path) {
-                Ok(raw) => raw,
-                Err(_) => continue,
             };
-            if !raw.contains(normalized_session_id) {
+            let Some(token_usage) = read_chat_session(&path, &session_id)
+                .as_ref()
+                .and_then(parse_thread_token_usage_from_session)
+            else {
                 continue;
-            }
-            let parsed: Value = match serde_json::from_str(&raw) {
-                Ok(value) => value,
-                Err(_) => continue,
             };
-            if parsed.get("sessionId").and_then(Value::as_str) != Some(normalized_session_id) {
-                continue;
-            }
-            let Some(token_usage) = parse_thread_token_usage_from_session(&parsed) else {
+            if self.last_usage.get(&session_id) == Some(&token_usage) {
                 continue;
-            };
-            let modified_at = std::fs::metadata(&path)
-                .and_then(|meta| meta.modified())
-                .unwrap_or(UNIX_EPOCH);
-            let replace = latest
-                .as_ref()
-                .map(|(current_modified, _)| modified_at > *current_modified)
-                .unwrap_or(true);
-            if replace {
-                latest = Some((modified_at, token_usage));
             }
+            updates.push(json!({
+                "threadId": thread_id,
+                "turnId": turn_id,
+                "tokenUsage": token_usage
+            }));
+            self.last_usage.insert(session_id, token_usage);
         }
+        updates
     }
-
-    latest.map(|(_, usage)| usage)
-}
-
-fn load_thread_token_usage_for_session(session_id: &str) -> Option<Value> {
-    let micode_home = resolve_micode_home_path()?;
-    load_thread_token_usage_for_session_in_home(session_id, &micode_home)
 }
 
-fn read_selected_auth_mode() -> Option<String> {
-    let settings_path = micode_settings_path()?;
-    let raw = std::fs::read_to_string(settings_path).ok()?;
-    let value: Value = serde_json::from_str(&raw).ok()?;
+fn read_selected_auth_mode(home: Option<&Path>, errors: &SettingsParseErrors) -> Option<String> {
+    let value = read_settings_file(&micode_settings_path(home)?, errors)?;
     let selected = value
         .get("selectedAuthType")
         .and_then(Value::as_str)
@@ -811,10 +1843,11 @@ fn read_selected_auth_mode() -> Option<String> {
     }
 }
 
-pub(crate) fn read_preferred_model() -> Option<String> {
-    let settings_path = micode_settings_path()?;
-    let raw = std::fs::read_to_string(settings_path).ok()?;
-    let value: Value = serde_json::from_str(&raw).ok()?;
+pub(crate) fn read_preferred_model(
+    home: Option<&Path>,
+    errors: &SettingsParseErrors,
+) -> Option<String> {
+    let value = read_settings_file(&micode_settings_path(home)?, errors)?;
     value
         .get("model")
         .and_then(|v| v.get("preferredModel"))
@@ -824,24 +1857,34 @@ pub(crate) fn read_preferred_model() -> Option<String> {
         .map(ToString::to_string)
 }
 
-pub(crate) fn set_preferred_model(model: &str) -> Result<bool, String> {
-    let trimmed = model.trim();
+/// Fallback for agents that take no per-prompt `reasoningEffort`: the CLI reads it from
+/// the `model` section of the settings file in the session's isolated agent home, which
+/// no other workspace reads. Returns `true` when the file changed.
+pub(crate) fn set_preferred_effort(
+    effort: &str,
+    isolated_home: &Path,
+    errors: &SettingsParseErrors,
+) -> Result<bool, String> {
+    set_model_setting("reasoningEffort", effort, isolated_home, errors)
+}
+
+/// Writes `model.<key>` to the isolated home's settings file unless it already holds
+/// `value`.
+fn set_model_setting(
+    key: &str,
+    value: &str,
+    isolated_home: &Path,
+    errors: &SettingsParseErrors,
+) -> Result<bool, String> {
+    let trimmed = value.trim();
     if trimmed.is_empty() {
         return Ok(false);
     }
-    let settings_path = micode_settings_path().ok_or_else(|| "missing HOME".to_string())?;
-    let mut root = if settings_path.is_file() {
-        let raw = std::fs::read_to_string(&settings_path).map_err(|e| e.to_string())?;
-        serde_json::from_str::<Value>(&raw).unwrap_or_else(|_| json!({}))
-    } else {
-        json!({})
-    };
-    if !root.is_object() {
-        root = json!({});
-    }
+    let settings_path = isolated_home.join("settings.json");
+    let mut root = load_settings_for_update(&settings_path, errors)?;
     let current = root
         .get("model")
-        .and_then(|v| v.get("preferredModel"))
+        .and_then(|v| v.get(key))
         .and_then(Value::as_str)
         .unwrap_or_default();
     if current.trim() == trimmed {
@@ -857,16 +1900,61 @@ pub(crate) fn set_preferred_model(model: &str) -> Result<bool, String> {
         *model_obj = json!({});
     }
     if let Some(model_map) = model_obj.as_object_mut() {
-        model_map.insert(
-            "preferredModel".to_string(),
-            Value::String(trimmed.to_string()),
-        );
+        model_map.insert(key.to_string(), Value::String(trimmed.to_string()));
     }
-    if let Some(parent) = settings_path.parent() {
-        let _ = std::fs::create_dir_all(parent);
+    write_settings_file(&settings_path, &root)?;
+    Ok(true)
+}
+
+/// Fallback for agents without per-session sampling support: mirrors the effective values
+/// into the `model` section of the settings file in the session's isolated agent home,
+/// which no other workspace reads. Keys that are unset are removed so a later turn without
+/// overrides does not inherit stale values. Returns `true` when the file changed.
+pub(crate) fn sync_sampling_params_to_settings(
+    params: &SamplingParams,
+    isolated_home: &Path,
+    errors: &SettingsParseErrors,
+) -> Result<bool, String> {
+    let settings_path = isolated_home.join("settings.json");
+    let mut root = load_settings_for_update(&settings_path, errors)?;
+    let root_obj = root
+        .as_object_mut()
+        .ok_or_else(|| "invalid settings root".to_string())?;
+    let model_obj = root_obj
+        .entry("model".to_string())
+        .or_insert_with(|| json!({}));
+    if !model_obj.is_object() {
+        *model_obj = json!({});
+    }
+    let Some(model_map) = model_obj.as_object_mut() else {
+        return Ok(false);
+    };
+    let desired = [
+        ("temperature", params.temperature.map(|value| json!(value))),
+        ("topP", params.top_p.map(|value| json!(value))),
+        (
+            "maxOutputTokens",
+            params.max_output_tokens.map(|value| json!(value)),
+        ),
+    ];
+    let mut changed = false;
+    for (key, value) in desired {
+        match value {
+            Some(value) => {
+                if model_map.get(key) != Some(&value) {
+                    model_map.insert(key.to_string(), value);
+                    changed = true;
+                }
+            }
+            None => {
+                changed |= model_map.remove(key).is_some();
+            }
+        }
     }
-    let payload = serde_json::to_string_pretty(&root).map_err(|e| e.to_string())?;
-    std::fs::write(&settings_path, payload).map_err(|e| e.to_string())?;
+    if !changed {
+        return Ok(false);
+    }
+    write_settings_file(&settings_path, &root)?;
     Ok(true)
 }
 
@@ -1062,8 +2150,10 @@ fn normalize_wrapper_cli_token(token: &str, wrapper_path: &Path) -> Option<PathB
     canonicalize_if_file(candidate)
 }
 
-fn resolve_micode_cli_bundle_path(agent_bin: Option<&str>) -> Option<PathBuf> {
-    let resolved_bin = agent_bin
+/// Resolves the configured agent binary to a path the same way the CLI is launched:
+/// bare names are looked up on `PATH`, falling back to the platform default.
+pub(crate) fn resolve_agent_binary(agent_bin: Option<&str>) -> Option<PathBuf> {
+    agent_bin
         .map(str::trim)
         .filter(|value| !value.is_empty())
         .and_then(|value| {
@@ -1079,7 +2169,19 @@ fn resolve_micode_cli_bundle_path(agent_bin: Option<&str>) -> Option<PathBuf> {
             } else {
                 find_executable_on_path("micode")
             }
-        })?;
+        })
+}
+
+/// Cache key for handshake probes of `agent_bin`; unresolvable binaries are keyed by name.
+pub(crate) fn handshake_key(agent_bin: Option<&str>, agent_args: Option<&str>) -> HandshakeKey {
+    let binary = resolve_agent_binary(agent_bin)
+        .map(|path| std::fs::canonicalize(&path).unwrap_or(path))
+        .unwrap_or_else(|| PathBuf::from(agent_bin.unwrap_or("micode").trim()));
+    HandshakeKey::new(&binary, agent_args)
+}
+
+fn resolve_micode_cli_bundle_path(agent_bin: Option<&str>) -> Option<PathBuf> {
+    let resolved_bin = resolve_agent_binary(agent_bin)?;
     let canonical = std::fs::canonicalize(&resolved_bin)
         .ok()
         .unwrap_or(resolved_bin);
@@ -1132,32 +2234,168 @@ fn parse_js_bool_field(line: &str, field: &str) -> Option<bool> {
     }
 }
 
-fn parse_models_from_cli_bundle(path: &Path) -> Vec<(String, String, String)> {
-    let raw = match std::fs::read_to_string(path) {
-        Ok(value) => value,
-        Err(_) => return Vec::new(),
-    };
-    let mut in_models = false;
-    let mut in_object = false;
-    let mut brace_depth = 0_i32;
-    let mut object_lines: Vec<String> = Vec::new();
-    let mut models: Vec<(String, String, String)> = Vec::new();
-    for line in raw.lines() {
-        let trimmed = line.trim();
-        if !in_models {
-            if trimmed.starts_with("var AVAILABLE_MODELS = [") {
-                in_models = true;
-            }
+/// Reads a one-line string array such as `reasoningEfforts: ["low", "high"],`.
+fn parse_js_string_array_field(line: &str, field: &str) -> Option<Vec<String>> {
+    let trimmed = line.trim();
+    let prefix = format!("{field}:");
+    let rest = trimmed.strip_prefix(&prefix)?.trim();
+    let start = rest.find('[')?;
+    let end = rest.rfind(']')?;
+    if end < start {
+        return None;
+    }
+    let literal = rest[start..=end].replace('\'', "\"");
+    serde_json::from_str::<Vec<String>>(&literal).ok()
+}
+
+/// A model listed in the CLI bundle's `AVAILABLE_MODELS` or defined in the MiCode
+/// settings file.
+#[derive(Debug, Clone, PartialEq)]
+struct CliModel {
+    id: String,
+    label: String,
+    description: String,
+    reasoning_efforts: Vec<String>,
+    default_reasoning_effort: Option<String>,
+    /// `bundle` or `settings`, reported as the model's `source`.
+    source: &'static str,
+}
+
+/// Settings keys holding user-defined models; the CLI loads both.
+const CUSTOM_MODEL_KEYS: [&str; 2] = ["customModels", "mifyModels"];
+
+/// A custom model entry left out of `model/list`.
+#[derive(Debug, Clone, PartialEq)]
+struct ModelParseWarning {
+    key: &'static str,
+    /// Position in the array; `None` when the key itself is not an array.
+    index: Option<usize>,
+    message: String,
+}
+
+/// User-defined models from the MiCode settings file. An entry is a model id or an
+/// object with `id` and optional `label`, `description`, `reasoningEfforts` and
+/// `defaultReasoningEffort`; invalid entries are skipped with a warning.
+fn parse_custom_models(root: &Value) -> (Vec<CliModel>, Vec<ModelParseWarning>) {
+    let mut models = Vec::new();
+    let mut warnings = Vec::new();
+    for key in CUSTOM_MODEL_KEYS {
+        let Some(entries) = root.get(key).filter(|value| !value.is_null()) else {
             continue;
-        }
-        if trimmed.starts_with("function loadCustomMifyModels") {
-            break;
-        }
-        if trimmed.starts_with("//") {
+        };
+        let Some(entries) = entries.as_array() else {
+            warnings.push(ModelParseWarning {
+                key,
+                index: None,
+                message: format!("{key} must be an array"),
+            });
             continue;
+        };
+        for (index, entry) in entries.iter().enumerate() {
+            match parse_custom_model(entry) {
+                Ok(model) => models.push(model),
+                Err(message) => warnings.push(ModelParseWarning {
+                    key,
+                    index: Some(index),
+                    message,
+                }),
+            }
         }
-        if !in_object {
-            if trimmed.starts_with('{') {
+    }
+    (models, warnings)
+}
+
+fn parse_custom_model(entry: &Value) -> Result<CliModel, String> {
+    let text = |key: &str| {
+        entry
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(ToString::to_string)
+    };
+    let id = match entry {
+        Value::String(id) => Some(id.trim().to_string()).filter(|id| !id.is_empty()),
+        Value::Object(_) => text("id").or_else(|| text("model")),
+        _ => return Err("expected a model id or an object".to_string()),
+    }
+    .ok_or_else(|| "missing model id".to_string())?;
+    let reasoning_efforts = match entry
+        .get("reasoningEfforts")
+        .or_else(|| entry.get("supportedReasoningEfforts"))
+    {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .map(ToString::to_string)
+                    .ok_or_else(|| format!("{id}: reasoningEfforts must list effort names"))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        Some(_) => return Err(format!("{id}: reasoningEfforts must be an array")),
+    };
+    let label = text("label")
+        .or_else(|| text("displayName"))
+        .unwrap_or_else(|| id.clone());
+    Ok(CliModel {
+        description: text("description").unwrap_or_else(|| label.clone()),
+        label,
+        id,
+        reasoning_efforts,
+        default_reasoning_effort: text("defaultReasoningEffort"),
+        source: "settings",
+    })
+}
+
+/// Bundle models first, then custom ones; the first model with an id wins.
+fn merge_models(bundle: Vec<CliModel>, custom: Vec<CliModel>) -> Vec<CliModel> {
+    let mut seen = HashSet::new();
+    bundle
+        .into_iter()
+        .chain(custom)
+        .filter(|model| seen.insert(model.id.clone()))
+        .collect()
+}
+
+fn reasoning_effort_description(effort: &str) -> String {
+    match effort {
+        "minimal" => "Fastest responses with as little reasoning as possible".to_string(),
+        "low" => "Fast responses with light reasoning".to_string(),
+        "medium" => "Balances speed and reasoning depth".to_string(),
+        "high" => "Deeper reasoning for complex problems".to_string(),
+        other => format!("{other} reasoning effort"),
+    }
+}
+
+fn parse_models_from_cli_bundle(path: &Path) -> Vec<CliModel> {
+    let raw = match std::fs::read_to_string(path) {
+        Ok(value) => value,
+        Err(_) => return Vec::new(),
+    };
+    let mut in_models = false;
+    let mut in_object = false;
+    let mut brace_depth = 0_i32;
+    let mut object_lines: Vec<String> = Vec::new();
+    let mut models: Vec<CliModel> = Vec::new();
+    for line in raw.lines() {
+        let trimmed = line.trim();
+        if !in_models {
+            if trimmed.starts_with("var AVAILABLE_MODELS = [") {
+                in_models = true;
+            }
+            continue;
+        }
+        if trimmed.starts_with("function loadCustomMifyModels") {
+            break;
+        }
+        if trimmed.starts_with("//") {
+            continue;
+        }
+        if !in_object {
+            if trimmed.starts_with('{') {
                 in_object = true;
                 brace_depth = 0;
                 object_lines.clear();
@@ -1173,6 +2411,8 @@ fn parse_models_from_cli_bundle(path: &Path) -> Vec<(String, String, String)> {
             let mut label: Option<String> = None;
             let mut description: Option<String> = None;
             let mut is_visible: Option<bool> = None;
+            let mut reasoning_efforts: Option<Vec<String>> = None;
+            let mut default_reasoning_effort: Option<String> = None;
             for object_line in &object_lines {
                 if id.is_none() {
                     id = parse_js_string_field(object_line, "id");
@@ -1186,31 +2426,100 @@ fn parse_models_from_cli_bundle(path: &Path) -> Vec<(String, String, String)> {
                 if is_visible.is_none() {
                     is_visible = parse_js_bool_field(object_line, "isVisible");
                 }
+                if reasoning_efforts.is_none() {
+                    reasoning_efforts = parse_js_string_array_field(object_line, "reasoningEfforts")
+                        .or_else(|| {
+                            parse_js_string_array_field(object_line, "supportedReasoningEfforts")
+                        });
+                }
+                if default_reasoning_effort.is_none() {
+                    default_reasoning_effort =
+                        parse_js_string_field(object_line, "defaultReasoningEffort");
+                }
             }
             if is_visible != Some(false) {
                 if let (Some(id), Some(label)) = (id, label) {
-                    models.push((id, label.clone(), description.unwrap_or(label)));
+                    models.push(CliModel {
+                        id,
+                        description: description.unwrap_or_else(|| label.clone()),
+                        label,
+                        reasoning_efforts: reasoning_efforts.unwrap_or_default(),
+                        default_reasoning_effort,
+                        source: "bundle",
+                    });
                 }
             }
             in_object = false;
             object_lines.clear();
         }
     }
-    let mut deduped: Vec<(String, String, String)> = Vec::new();
     let mut seen = std::collections::HashSet::new();
-    for (id, label, description) in models {
-        if seen.insert(id.clone()) {
-            deduped.push((id, label, description));
+    models.retain(|model| seen.insert(model.id.clone()));
+    models
+}
+
+/// Identifies one version of a CLI bundle without reading it.
+#[derive(Debug, Clone, PartialEq)]
+struct BundleStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl BundleStamp {
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Models parsed from each CLI bundle, keyed by canonical bundle path. Bundles run to
+/// several MB, so they are parsed again only when their mtime or size changes.
+#[derive(Default)]
+struct BundleModelCache {
+    entries: HashMap<PathBuf, (BundleStamp, Vec<CliModel>)>,
+    parses: u64,
+}
+
+impl BundleModelCache {
+    fn models(&mut self, bundle_path: &Path, refresh: bool) -> Vec<CliModel> {
+        let Some(stamp) = BundleStamp::read(bundle_path) else {
+            self.entries.remove(bundle_path);
+            return Vec::new();
+        };
+        if !refresh {
+            if let Some((cached_stamp, models)) = self.entries.get(bundle_path) {
+                if *cached_stamp == stamp {
+                    return models.clone();
+                }
+            }
         }
+        self.parses += 1;
+        let models = parse_models_from_cli_bundle(bundle_path);
+        self.entries
+            .insert(bundle_path.to_path_buf(), (stamp, models.clone()));
+        models
     }
-    deduped
 }
 
-fn discover_micode_models(agent_bin: Option<&str>) -> Vec<(String, String, String)> {
+fn bundle_model_cache() -> &'static std::sync::Mutex<BundleModelCache> {
+    static CACHE: std::sync::OnceLock<std::sync::Mutex<BundleModelCache>> =
+        std::sync::OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Models in the agent's CLI bundle; `refresh` parses the bundle even when it looks
+/// unchanged.
+fn discover_micode_models(agent_bin: Option<&str>, refresh: bool) -> Vec<CliModel> {
     let Some(bundle_path) = resolve_micode_cli_bundle_path(agent_bin) else {
         return Vec::new();
     };
-    parse_models_from_cli_bundle(&bundle_path)
+    match bundle_model_cache().lock() {
+        Ok(mut cache) => cache.models(&bundle_path, refresh),
+        Err(_) => parse_models_from_cli_bundle(&bundle_path),
+    }
 }
 
 fn build_initialize_params(_client_version: &str) -> Value {
@@ -1237,10 +2546,7 @@ impl ActivePromptContext {
     }
 
     fn agent_item_id(&self, segment: u32) -> String {
-        if segment == 0 {
-            return format!("agent-{}-{}", self.thread_id, self.turn_id);
-        }
-        format!("agent-{}-{}-s{}", self.thread_id, self.turn_id, segment)
+        agent_item_id(&self.thread_id, &self.turn_id, segment)
     }
 
     fn reasoning_item_id(&self) -> String {
@@ -1252,6 +2558,62 @@ impl ActivePromptContext {
     }
 }
 
+/// Agent text of a running prompt, by message segment, and what is not yet on disk.
+#[derive(Debug, Default)]
+struct PromptAgentMessage {
+    segments: BTreeMap<u32, String>,
+    unsaved_bytes: usize,
+    last_checkpoint_ms: u64,
+}
+
+impl PromptAgentMessage {
+    /// Adds `delta` to `segment` and returns the segments to save as `partial` items:
+    /// all of them when `segment` gets its first visible text, so it keeps its place
+    /// next to tool calls saved while it streams, and again whenever a checkpoint is due.
+    fn append(&mut self, segment: u32, delta: &str, now_ms: u64) -> Vec<(u32, String)> {
+        let text = self.segments.entry(segment).or_default();
+        let was_blank = text.trim().is_empty();
+        text.push_str(delta);
+        if text.trim().is_empty() {
+            return Vec::new();
+        }
+        self.unsaved_bytes += delta.len();
+        let due = was_blank
+            || self.unsaved_bytes >= AGENT_CHECKPOINT_BYTES
+            || now_ms.saturating_sub(self.last_checkpoint_ms)
+                >= AGENT_CHECKPOINT_INTERVAL.as_millis() as u64;
+        if !due {
+            return Vec::new();
+        }
+        self.unsaved_bytes = 0;
+        self.last_checkpoint_ms = now_ms;
+        self.segments
+            .iter()
+            .filter(|(_, text)| !text.trim().is_empty())
+            .map(|(segment, text)| (*segment, text.clone()))
+            .collect()
+    }
+}
+
+/// A tool call the agent has started and not yet reported back on.
+#[derive(Debug, Clone)]
+struct RunningToolCall {
+    thread_id: String,
+    session_id: String,
+    started_at: Instant,
+}
+
+/// A tool call stopped with `cancel_tool_call`, reported with the turn that ran it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CancelledToolCall {
+    tool_call_id: String,
+    item_id: String,
+    /// How long the tool had been running when it was cancelled.
+    elapsed_ms: u64,
+    cancelled_at_ms: u64,
+}
+
 #[derive(Debug, Clone, Default)]
 struct ToolCallPresentation {
     server: Option<String>,
@@ -1259,7 +2621,11 @@ struct ToolCallPresentation {
     title: Option<String>,
     arguments: Option<Value>,
     result: Option<String>,
+    /// Object or array output, such as edit diffs or command stdout, kept as sent.
+    result_json: Option<Value>,
     error: Option<String>,
+    /// How long the tool ran, set once the agent reported it back.
+    duration_ms: Option<u64>,
 }
 
 fn sanitize_tool_title(raw: Option<&str>) -> Option<String> {
@@ -1316,14 +2682,14 @@ fn extract_tool_content_text(value: &Value) -> Option<String> {
     match value {
         Value::String(text) => push_text(text),
         Value::Object(map) => {
-            if let Some(text) = map.get("text").and_then(Value::as_str) {
-                push_text(text);
-            }
-            if let Some(content) = map.get("content") {
-                if let Some(text) = content.get("text").and_then(Value::as_str) {
+            for key in ["text", "stdout", "output"] {
+                if let Some(text) = map.get(key).and_then(Value::as_str) {
                     push_text(text);
                 }
             }
+            if let Some(text) = map.get("content").and_then(extract_tool_content_text) {
+                push_text(&text);
+            }
             if let Some(old_text) = map.get("oldText").and_then(Value::as_str) {
                 push_text(old_text);
             }
@@ -1348,7 +2714,11 @@ fn extract_tool_content_text(value: &Value) -> Option<String> {
     }
 }
 
-fn extract_tool_presentation_from_update(update: &Value) -> ToolCallPresentation {
+/// `result_limit` caps the kept result, see `SessionSettings::tool_result_limit`.
+fn extract_tool_presentation_from_update(
+    update: &Value,
+    result_limit: Option<usize>,
+) -> ToolCallPresentation {
     let title = sanitize_tool_title(update.get("title").and_then(Value::as_str));
     let mut tool = extract_string_field(
         update,
@@ -1379,6 +2749,13 @@ fn extract_tool_presentation_from_update(update: &Value) -> ToolCallPresentation
     if server.is_none() && tool.is_some() {
         server = Some("micode".to_string());
     }
+    let result_json = structured_tool_result(update);
+    let result = update
+        .get("result")
+        .and_then(Value::as_str)
+        .map(ToString::to_string)
+        .or_else(|| result_json.as_ref().and_then(extract_tool_content_text))
+        .or_else(|| update.get("content").and_then(extract_tool_content_text));
     ToolCallPresentation {
         server,
         tool,
@@ -1391,16 +2768,20 @@ fn extract_tool_presentation_from_update(update: &Value) -> ToolCallPresentation
             .or_else(|| update.get("params").cloned())
             .or_else(|| update.get("command").cloned())
             .or_else(|| update.get("content").cloned()),
-        result: update
-            .get("result")
-            .and_then(Value::as_str)
-            .map(ToString::to_string)
-            .or_else(|| update.get("content").and_then(extract_tool_content_text)),
+        result: match result_limit {
+            Some(limit) => result.map(|text| truncate_tool_result_text(text, limit)),
+            None => result,
+        },
+        result_json: match result_limit {
+            Some(limit) => result_json.map(|value| truncate_tool_result_json(value, limit)),
+            None => result_json,
+        },
         error: update
             .get("error")
             .and_then(Value::as_str)
             .map(ToString::to_string)
             .or_else(|| update.get("failure").and_then(extract_tool_content_text)),
+        duration_ms: None,
     }
 }
 
@@ -1442,10 +2823,12 @@ fn extract_tool_presentation_from_permission(params: &Value) -> Option<(String,
                 .get("result")
                 .and_then(Value::as_str)
                 .map(ToString::to_string),
+            result_json: None,
             error: tool_call
                 .get("error")
                 .and_then(Value::as_str)
                 .map(ToString::to_string),
+            duration_ms: None,
         },
     ))
 }
@@ -1497,9 +2880,15 @@ fn merge_tool_presentation(
     if should_take_incoming_string(merged.result.as_ref(), incoming.result.as_ref()) {
         merged.result = incoming.result;
     }
+    if should_take_incoming_json(merged.result_json.as_ref(), incoming.result_json.as_ref()) {
+        merged.result_json = incoming.result_json;
+    }
     if should_take_incoming_string(merged.error.as_ref(), incoming.error.as_ref()) {
         merged.error = incoming.error;
     }
+    if merged.duration_ms.is_none() {
+        merged.duration_ms = incoming.duration_ms;
+    }
     if merged.server.is_none() && merged.tool.is_some() {
         merged.server = Some("micode".to_string());
     }
@@ -1518,198 +2907,2039 @@ fn tool_call_display_title(presentation: &ToolCallPresentation) -> String {
     }
 }
 
+fn tool_call_summary_text(presentation: &ToolCallPresentation, completed: bool) -> String {
+    tool_call_summary(&ToolSummaryInput {
+        tool: presentation.tool.as_deref(),
+        title: presentation.title.as_deref(),
+        arguments: presentation.arguments.as_ref(),
+        result: presentation.result.as_deref(),
+        error: presentation.error.as_deref(),
+        completed,
+    })
+}
+
+/// App settings a running session reads, handed to it at spawn and again after every
+/// settings update, see `WorkspaceSession::apply_session_settings`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SessionSettings {
+    /// `promptTimeoutSecs`; the workspace may override it.
+    pub(crate) prompt_timeout_secs: u64,
+    /// `turnStallWarningSecs` and `toolStallWarningSecs`.
+    pub(crate) stall: StallThresholds,
+    /// `toolSlowWarningSecs`; 0 never warns.
+    pub(crate) tool_slow_warning_secs: u64,
+    /// `approvalTimeoutSecs`; 0 lets approvals wait forever.
+    pub(crate) approval_timeout_secs: u64,
+    /// `toolResultMaxKb`; 0 keeps whole tool results.
+    pub(crate) tool_result_max_kb: u32,
+    /// `persistReasoning`: whether turn reasoning is saved into the thread history.
+    pub(crate) persist_reasoning: bool,
+    /// `archivedThreadRetentionDays`; 0 keeps archived threads forever. Read when the
+    /// session starts.
+    pub(crate) archived_thread_retention_days: u32,
+}
+
+impl SessionSettings {
+    pub(crate) fn from_app_settings(settings: &AppSettings) -> Self {
+        Self {
+            prompt_timeout_secs: settings.prompt_timeout_secs,
+            stall: StallThresholds {
+                idle_secs: settings.turn_stall_warning_secs,
+                tool_secs: settings.tool_stall_warning_secs,
+            },
+            tool_slow_warning_secs: settings.tool_slow_warning_secs,
+            approval_timeout_secs: settings.approval_timeout_secs,
+            tool_result_max_kb: settings.tool_result_max_kb,
+            persist_reasoning: settings.persist_reasoning,
+            archived_thread_retention_days: settings.archived_thread_retention_days,
+        }
+    }
+
+    /// How much of a tool result is kept in events and thread items, in bytes.
+    pub(crate) fn tool_result_limit(&self) -> Option<usize> {
+        (self.tool_result_max_kb > 0).then(|| self.tool_result_max_kb as usize * 1024)
+    }
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self::from_app_settings(&AppSettings::default())
+    }
+}
+
+/// Binary and args a session was spawned with, used to detect settings drift.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SessionLaunchConfig {
+    pub(crate) agent_bin: Option<String>,
+    pub(crate) agent_args: Option<String>,
+}
+
+impl SessionLaunchConfig {
+    pub(crate) fn resolve(
+        entry: &WorkspaceEntry,
+        default_micode_bin: Option<String>,
+        agent_args: Option<String>,
+    ) -> Self {
+        let agent_bin = entry
+            .agent_bin
+            .clone()
+            .filter(|value| !value.trim().is_empty())
+            .or(default_micode_bin)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+        let agent_args = agent_args
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+        Self {
+            agent_bin,
+            agent_args,
+        }
+    }
+}
+
 pub(crate) struct WorkspaceSession {
     pub(crate) entry: WorkspaceEntry,
+    pub(crate) launch_config: SessionLaunchConfig,
+    pub(crate) config_stale: AtomicBool,
     pub(crate) child: Mutex<Child>,
     pub(crate) stdin: Mutex<ChildStdin>,
-    pub(crate) pending: Mutex<HashMap<u64, oneshot::Sender<Value>>>,
+    pub(crate) pending: Mutex<HashMap<u64, PendingRequest>>,
     pub(crate) next_id: AtomicU64,
     pub(crate) background_thread_callbacks: Mutex<HashMap<String, mpsc::UnboundedSender<Value>>>,
     event_tx: mpsc::UnboundedSender<AppServerEvent>,
-    thread_store: Mutex<LocalThreadStore>,
-    approval_requests: Mutex<HashMap<String, Value>>,
+    thread_store: Arc<Mutex<LocalThreadStore>>,
+    approvals: Mutex<PendingApprovals>,
     pending_prompt_streaming: Mutex<HashMap<String, bool>>,
-    pending_prompt_agent_messages: Mutex<HashMap<String, String>>,
+    /// Agent text of each running prompt, by message segment.
+    pending_prompt_agent_messages: Mutex<HashMap<String, PromptAgentMessage>>,
+    /// Reasoning streamed by the running prompt of each session, see `persistReasoning`.
+    pending_prompt_reasoning: Mutex<HashMap<String, String>>,
     pending_prompt_agent_segments: Mutex<HashMap<String, u32>>,
     active_prompts: Mutex<HashMap<String, ActivePromptContext>>,
     background_threads: Mutex<HashMap<String, String>>,
+    /// Idle clocks of `background_threads`, see `reap_background_threads`.
+    background_activity: std::sync::Mutex<BackgroundActivity>,
+    /// Threads started or resumed by this session; scoped history clearing skips them.
+    resumed_threads: Mutex<HashSet<String>>,
+    /// Threads already offered for a generated title, see `claim_auto_title`.
+    auto_title_claims: std::sync::Mutex<HashSet<String>>,
+    /// Slash commands the agent advertised last, see `resolve_slash_command`.
+    available_commands: std::sync::Mutex<AvailableCommands>,
     tool_call_presentations: Mutex<HashMap<String, ToolCallPresentation>>,
+    running_tool_calls: Mutex<HashMap<String, RunningToolCall>>,
+    /// Start times of running tool calls, see `finish_tool_timing` and `check_slow_tool_calls`.
+    tool_timings: std::sync::Mutex<ToolTimings>,
+    /// Tool calls cancelled during the running turn of each thread.
+    cancelled_tool_calls: Mutex<HashMap<String, Vec<CancelledToolCall>>>,
+    /// Phase of the running foreground turn of each thread.
+    turn_phases: Mutex<HashMap<String, TurnPhaseTracker>>,
+    /// Silence of each foreground prompt in flight, keyed by thread; see `check_turn_stalls`.
+    turn_stalls: std::sync::Mutex<HashMap<String, StallWatch>>,
+    /// Threads the user interrupted, with the turn id the interrupt named.
+    user_interrupts: std::sync::Mutex<HashMap<String, String>>,
+    /// Threads running a turn and the user messages waiting behind them.
+    turn_queue: std::sync::Mutex<TurnQueue>,
+    /// Notified whenever a thread becomes free, see `claim_idle_thread`.
+    turn_released: Notify,
+    /// `turn/start` requests being handled, including the prompt they wait for.
+    turns_in_flight: AtomicUsize,
+    /// Queued messages whose thread became free, started by the dequeue task.
+    dequeued_turn_tx: mpsc::UnboundedSender<QueuedTurn>,
+    turn_artifact_baselines: Mutex<HashMap<String, TurnArtifactBaseline>>,
+    /// Files read by tools during the running turn of each thread, when auditing is on.
+    turn_audit_reads: Mutex<HashMap<String, Vec<AuditRead>>>,
+    last_activity_ms: AtomicU64,
+    /// Set once `check_unresponsive` reported the current unresponsive stretch.
+    unresponsive_reported: AtomicBool,
+    /// Heartbeat view of the agent process, see `check_health`.
+    health: std::sync::Mutex<HealthTracker>,
+    /// Set when the app kills the agent, so the exit is not reported as a disconnect.
+    stopping: AtomicBool,
+    /// Set once a liveness check has started respawning this crashed session.
+    respawn_claimed: AtomicBool,
+    supports_session_sampling: AtomicBool,
+    supports_tool_call_cancel: AtomicBool,
+    /// Whether the agent takes a per-prompt `reasoningEffort` in `_meta`.
+    supports_session_reasoning_effort: AtomicBool,
+    /// Whether the agent takes inline image content, see `agent_supports_image_prompts`.
+    supports_image_prompts: AtomicBool,
+    sampling_written_to_settings: AtomicBool,
+    turns_started: AtomicU64,
+    last_store_maintenance_ms: AtomicU64,
+    /// Result of this session's own `initialize`, reused by the doctor's handshake check.
+    handshake: std::sync::OnceLock<HandshakeProbe>,
+    /// Binary the session was launched with, keyed like the doctor's probes.
+    handshake_key: HandshakeKey,
+    /// Repository-primed sessions of background helpers that opt in with `_primer`.
+    primer: Mutex<PrimerState>,
+    /// Recent `mcpServer/list` probes, see `MCP_PROBE_TTL`.
+    mcp_probes: Mutex<McpProbeCache>,
+    /// CPU/RAM of the agent child, sampled by the resource monitor.
+    pub(crate) resource_usage: std::sync::Mutex<ResourceTracker>,
+    /// Program and arguments the agent was launched with, shell-quoted.
+    command_line: String,
+    started_at_ms: u64,
+    /// Last `STDERR_TAIL_LINES` lines the agent wrote to stderr, for diagnostics.
+    stderr_tail: std::sync::Mutex<VecDeque<String>>,
+    /// Compiled `entry.settings.redaction`, updated without restarting the agent.
+    redaction: std::sync::Mutex<CompiledRedaction>,
+    /// Live copy of `entry.settings.audit`.
+    audit: std::sync::Mutex<Option<AuditSettings>>,
+    /// Live copy of `entry.settings.prompt_timeout_secs`.
+    prompt_timeout_secs: std::sync::Mutex<Option<u64>>,
+    /// Live copy of the app settings the session reads.
+    settings: std::sync::Mutex<SessionSettings>,
+    /// Dedicated `MICODE_HOME` when the workspace runs with `isolatedAgentHome`.
+    pub(crate) isolated_home: Option<PathBuf>,
+    /// Approval rules file of the agent home, see `auto_decide_approval`.
+    rules_path: Option<PathBuf>,
+    /// MiCode settings files that failed to parse, see `emit_settings_parse_errors`.
+    settings_parse_errors: SettingsParseErrors,
+    /// Chat files of the session's MiCode home by CLI session id, see `chat_index`.
+    /// `None` when no MiCode home resolves.
+    chat_files: Option<Arc<std::sync::Mutex<ChatFileIndex>>>,
+    /// Chat file poller started by the first foreground prompt, see `watch_token_usage`.
+    /// Stays `None` when no MiCode home resolves.
+    token_usage_watch: std::sync::OnceLock<Option<Arc<std::sync::Mutex<TokenUsageWatch>>>>,
 }
 
 impl WorkspaceSession {
-    pub(crate) async fn invalidate_all_thread_sessions(&self) {
-        self.thread_store.lock().await.clear_session_ids();
-        self.background_threads.lock().await.clear();
+    /// The session's own handshake, if it was launched from the binary `key` names. A
+    /// binary replaced since the launch changes the key, so its answer is not reused.
+    pub(crate) fn recorded_handshake(&self, key: &HandshakeKey) -> Option<HandshakeProbe> {
+        if self.handshake_key != *key {
+            return None;
+        }
+        self.handshake.get().cloned()
     }
 
-    async fn begin_prompt_tracking(&self, session_id: &str) {
-        self.pending_prompt_streaming
-            .lock()
-            .await
-            .insert(session_id.to_string(), false);
-        self.pending_prompt_agent_messages
-            .lock()
-            .await
-            .remove(session_id);
-        self.pending_prompt_agent_segments
-            .lock()
-            .await
-            .insert(session_id.to_string(), 0);
+    pub(crate) fn is_config_stale(&self) -> bool {
+        self.config_stale.load(Ordering::SeqCst)
     }
 
-    async fn register_active_prompt(&self, session_id: &str, thread_id: &str, turn_id: &str) {
-        self.active_prompts.lock().await.insert(
-            session_id.to_string(),
-            ActivePromptContext::new(thread_id.to_string(), turn_id.to_string()),
-        );
+    /// Compares the recorded launch config against `expected` and updates the stale flag.
+    /// Returns `true` when the flag changed.
+    pub(crate) fn refresh_config_stale(&self, expected: &SessionLaunchConfig) -> bool {
+        let stale = self.launch_config != *expected;
+        self.config_stale.swap(stale, Ordering::SeqCst) != stale
     }
 
-    async fn active_prompt(&self, session_id: &str) -> Option<ActivePromptContext> {
-        self.active_prompts.lock().await.get(session_id).cloned()
+    /// Running user-visible turns; background helpers such as commit messages are excluded.
+    pub(crate) async fn active_turn_count(&self) -> usize {
+        let background_threads = self.background_threads.lock().await.clone();
+        self.active_prompts
+            .lock()
+            .await
+            .values()
+            .filter(|context| !background_threads.contains_key(&context.thread_id))
+            .count()
     }
 
-    async fn clear_active_prompt(&self, session_id: &str) {
-        self.active_prompts.lock().await.remove(session_id);
+    /// Whether a turn of any kind is admitted, starting or running: user and queued
+    /// messages as well as auto-run, background and one-shot turns.
+    pub(crate) async fn has_active_turns(&self) -> bool {
+        self.turns_in_flight.load(Ordering::SeqCst) > 0
+            || self.turn_queue.lock().is_ok_and(|queue| !queue.is_idle())
+            || !self.active_prompts.lock().await.is_empty()
     }
 
-    async fn merge_tool_call_presentation(
+    /// Claims the thread for a user message, or queues or rejects it while the thread is
+    /// running a turn. Emits `turn/queued` for queued messages.
+    pub(crate) fn admit_turn(
         &self,
-        tool_call_id: &str,
-        incoming: ToolCallPresentation,
-    ) -> (ToolCallPresentation, bool) {
-        let mut cache = self.tool_call_presentations.lock().await;
-        let existing = cache.get(tool_call_id).cloned();
-        let was_present = existing.is_some();
-        let merged = merge_tool_presentation(existing, incoming);
-        cache.insert(tool_call_id.to_string(), merged.clone());
-        (merged, was_present)
+        thread_id: &str,
+        params: Value,
+        queue_when_busy: bool,
+    ) -> TurnAdmission {
+        let admission = match self.turn_queue.lock() {
+            Ok(mut queue) => queue.admit(thread_id, params, queue_when_busy),
+            Err(_) => TurnAdmission::Start(params),
+        };
+        if let TurnAdmission::Queued { id, position } = &admission {
+            self.emit_event(
+                event_methods::TURN_QUEUED,
+                json!({ "threadId": thread_id, "queueId": id, "position": position }),
+            );
+        }
+        admission
     }
 
-    async fn clear_tool_call_presentation(&self, tool_call_id: &str) {
-        self.tool_call_presentations.lock().await.remove(tool_call_id);
+    /// Sends `turn/start` for `thread_id` once `admit_turn` lets it, so a thread never runs
+    /// two turns at once. A queued message answers with its place in the queue; a busy
+    /// thread without `queue_when_busy` fails with `turnInProgress`.
+    pub(crate) async fn start_thread_turn(
+        &self,
+        thread_id: &str,
+        params: Value,
+        queue_when_busy: bool,
+    ) -> Result<Value, CommandError> {
+        match self.admit_turn(thread_id, params, queue_when_busy) {
+            TurnAdmission::Start(params) => self.send_request("turn/start", params).await,
+            TurnAdmission::Busy => Err(CommandError::turn_in_progress(thread_id)),
+            admission => Ok(admission.response().unwrap_or(Value::Null)),
+        }
     }
 
-    async fn mark_prompt_streaming(&self, session_id: &str) {
-        let mut pending = self.pending_prompt_streaming.lock().await;
-        if let Some(has_streaming) = pending.get_mut(session_id) {
-            *has_streaming = true;
+    fn claim_thread_turn(&self, thread_id: &str) {
+        if let Ok(mut queue) = self.turn_queue.lock() {
+            queue.claim(thread_id);
         }
     }
 
-    async fn finish_prompt_tracking(&self, session_id: &str) -> bool {
-        let had_streaming = self
-            .pending_prompt_streaming
-            .lock()
-            .await
-            .remove(session_id)
-            .unwrap_or(false);
-        self.pending_prompt_agent_segments
-            .lock()
-            .await
-            .remove(session_id);
-        had_streaming
+    /// Tracks a `turn/start` from the moment it is handled until it ended or was dropped.
+    fn begin_turn_in_flight(&self, thread_id: Option<String>) -> TurnInFlight<'_> {
+        self.turns_in_flight.fetch_add(1, Ordering::SeqCst);
+        if let Some(thread_id) = thread_id.as_deref() {
+            self.claim_thread_turn(thread_id);
+        }
+        TurnInFlight {
+            session: self,
+            thread_id,
+        }
     }
 
-    async fn finish_prompt_lifecycle(&self, session_id: &str) -> bool {
-        let had_streaming = self.finish_prompt_tracking(session_id).await;
-        self.clear_active_prompt(session_id).await;
-        had_streaming
+    /// Waits until `thread_id` has no running or queued turn, then claims it through
+    /// `admit_turn`. Queued messages still go first, since a finishing turn hands the thread
+    /// straight to the next one. The caller sends `turn/start` with the claim held.
+    pub(crate) async fn claim_idle_thread(&self, thread_id: &str) {
+        loop {
+            // Registered before the attempt, so a release in between still wakes us.
+            let mut released = std::pin::pin!(self.turn_released.notified());
+            released.as_mut().enable();
+            if let TurnAdmission::Start(_) = self.admit_turn(thread_id, Value::Null, false) {
+                return;
+            }
+            released.await;
+        }
     }
 
-    async fn append_prompt_agent_delta(&self, session_id: &str, delta: &str) {
-        if delta.is_empty() {
-            return;
+    /// Releases the thread, or hands it to the next queued message.
+    pub(crate) fn finish_thread_turn(&self, thread_id: &str) {
+        let next = self
+            .turn_queue
+            .lock()
+            .ok()
+            .and_then(|mut queue| queue.finish(thread_id));
+        match next {
+            Some(next) => {
+                let _ = self.dequeued_turn_tx.send(next);
+            }
+            None => self.turn_released.notify_waiters(),
         }
-        let mut messages = self.pending_prompt_agent_messages.lock().await;
-        let entry = messages.entry(session_id.to_string()).or_default();
-        entry.push_str(delta);
     }
 
-    async fn current_prompt_agent_item_id(&self, session_id: &str) -> Option<String> {
-        let segment = self
-            .pending_prompt_agent_segments
+    fn clear_turn_queue(&self, thread_id: &str, reason: &str) {
+        let cleared = self
+            .turn_queue
             .lock()
-            .await
-            .get(session_id)
-            .copied()?;
-        let context = self.active_prompt(session_id).await?;
-        Some(context.agent_item_id(segment))
+            .map(|mut queue| queue.clear(thread_id))
+            .unwrap_or(0);
+        self.emit_turn_queue_cleared(thread_id, cleared, reason);
     }
 
-    async fn bump_prompt_agent_segment(&self, session_id: &str) {
-        let mut segments = self.pending_prompt_agent_segments.lock().await;
-        if let Some(segment) = segments.get_mut(session_id) {
-            *segment = segment.saturating_add(1);
+    fn emit_turn_queue_cleared(&self, thread_id: &str, cleared: usize, reason: &str) {
+        if cleared == 0 {
+            return;
         }
+        self.emit_event(
+            event_methods::TURN_QUEUE_CLEARED,
+            json!({ "threadId": thread_id, "cleared": cleared, "reason": reason }),
+        );
     }
 
-    async fn take_prompt_agent_message(&self, session_id: &str) -> Option<String> {
-        self.pending_prompt_agent_messages
+    /// Starts a message that waited for the previous turn of its thread. Nobody awaits the
+    /// result, so a turn that fails to start is reported as `turn/failed`.
+    async fn start_dequeued_turn(&self, turn: QueuedTurn) {
+        let remaining = self
+            .turn_queue
             .lock()
-            .await
-            .remove(session_id)
+            .map(|queue| queue.queued_len(&turn.thread_id))
+            .unwrap_or(0);
+        self.emit_event(
+            event_methods::TURN_DEQUEUED,
+            json!({ "threadId": turn.thread_id, "queueId": turn.id, "remaining": remaining }),
+        );
+        let response = self.send_request("turn/start", turn.params).await;
+        let error = match response {
+            Ok(response) => acp_error_message(&response),
+            Err(error) => Some(error.message),
+        };
+        if let Some(error) = error {
+            self.emit_event(
+                event_methods::TURN_FAILED,
+                json!({
+                    "threadId": turn.thread_id,
+                    "turn": { "id": turn.id, "threadId": turn.thread_id },
+                    "queueId": turn.id,
+                    "reason": error
+                }),
+            );
+        }
     }
 
-    async fn persist_thread_item(&self, thread_id: &str, item: Value) {
-        self.thread_store.lock().await.upsert_thread_item(thread_id, item);
+    fn record_activity(&self) {
+        self.last_activity_ms.store(now_ms(), Ordering::SeqCst);
     }
 
-    async fn persist_prompt_agent_item(
-        &self,
-        thread_id: &str,
-        turn_id: &str,
-        session_id: &str,
-    ) {
-        let Some(text) = self.take_prompt_agent_message(session_id).await else {
-            return;
-        };
-        if text.trim().is_empty() {
-            return;
-        }
-        self.persist_thread_item(thread_id, build_agent_thread_item(thread_id, turn_id, &text))
-            .await;
+    pub(crate) fn is_unresponsive(&self) -> bool {
+        self.health() == SessionHealth::Unresponsive
     }
 
-    async fn emit_latest_thread_token_usage(
+    /// Reports a session the heartbeat flagged unresponsive once nothing came from the agent
+    /// for `threshold`. Sessions waiting on a user approval or running a tool call, which
+    /// may print nothing for minutes, are never reported. Returns
+    /// `(idle, outstanding requests oldest first)` once per unresponsive stretch.
+    pub(crate) async fn check_unresponsive(
         &self,
-        thread_id: &str,
-        turn_id: &str,
-        session_id: &str,
-    ) {
-        let normalized_session_id = session_id.trim();
-        if normalized_session_id.is_empty() {
-            return;
+        threshold: Duration,
+    ) -> Option<(Duration, Vec<PendingRequestInfo>)> {
+        if !self.is_unresponsive()
+            || !self.approvals.lock().await.is_empty()
+            || !self.running_tool_calls.lock().await.is_empty()
+        {
+            return None;
         }
-        for _attempt in 0..3 {
-            let lookup_session_id = normalized_session_id.to_string();
-            let usage = tokio::task::spawn_blocking(move || {
-                load_thread_token_usage_for_session(&lookup_session_id)
-            })
-            .await
-            .ok()
-            .flatten();
-            if let Some(token_usage) = usage {
-                self.thread_store
-                    .lock()
-                    .await
-                    .set_agent_item_token_usage(thread_id, turn_id, &token_usage);
-                self.emit_event(
-                    "thread/tokenUsage/updated",
-                    json!({
-                        "threadId": thread_id,
-                        "tokenUsage": token_usage
-                    }),
-                );
-                return;
-            }
-            sleep(Duration::from_millis(120)).await;
+        let idle = self.idle_for();
+        if idle < threshold || self.unresponsive_reported.swap(true, Ordering::SeqCst) {
+            return None;
         }
+        let mut requests = self.list_pending_requests().await;
+        requests.retain(|info| info.method != HEALTH_PING_METHOD);
+        Some((idle, requests))
     }
 
-    async fn write_message(&self, value: Value) -> Result<(), String> {
-        let mut stdin = self.stdin.lock().await;
-        let mut line = serde_json::to_string(&value).map_err(|e| e.to_string())?;
-        line.push('\n');
+    /// Outstanding ACP requests, oldest first.
+    pub(crate) async fn list_pending_requests(&self) -> Vec<PendingRequestInfo> {
+        let mut requests: Vec<PendingRequestInfo> = self
+            .pending
+            .lock()
+            .await
+            .values()
+            .map(|request| request.info.clone())
+            .collect();
+        requests.sort_by_key(|info| (info.started_at_ms, info.id));
+        requests
+    }
+
+    /// Drops the oldest outstanding `method` request for `thread_id` after its caller gave
+    /// up waiting, and returns its metadata for the timeout error.
+    async fn take_abandoned_request(
+        &self,
+        method: &str,
+        thread_id: &str,
+    ) -> Option<PendingRequestInfo> {
+        let mut pending = self.pending.lock().await;
+        let id = pending
+            .values()
+            .filter(|request| {
+                request.info.method == method
+                    && request.info.thread_id.as_deref() == Some(thread_id)
+            })
+            .min_by_key(|request| (request.info.started_at_ms, request.info.id))
+            .map(|request| request.info.id)?;
+        pending.remove(&id).map(|request| request.info)
+    }
+
+    /// Fails every outstanding request with a "session restarted" error and converts active
+    /// turns into `turn/failed` so callers stop waiting on a child that is about to be killed.
+    pub(crate) async fn abort_in_flight(&self, reason: &str) {
+        let pending = std::mem::take(&mut *self.pending.lock().await);
+        for (_, request) in pending {
+            let _ = request
+                .tx
+                .send(json!({ "error": { "message": SESSION_RESTARTED_ERROR } }));
+        }
+        self.drop_pending_approvals().await;
+        let cleared = self
+            .turn_queue
+            .lock()
+            .map(|mut queue| queue.clear_all())
+            .unwrap_or_default();
+        for (thread_id, count) in cleared {
+            self.emit_turn_queue_cleared(&thread_id, count, reason);
+        }
+        self.turn_released.notify_waiters();
+        let active = std::mem::take(&mut *self.active_prompts.lock().await);
+        if let Ok(mut stalls) = self.turn_stalls.lock() {
+            stalls.clear();
+        }
+        let background_threads = self.background_threads.lock().await.clone();
+        for context in active.into_values() {
+            if background_threads.contains_key(&context.thread_id) {
+                continue;
+            }
+            self.turn_artifact_baselines
+                .lock()
+                .await
+                .remove(&context.thread_id);
+            self.emit_event(
+                event_methods::TURN_FAILED,
+                json!({
+                    "threadId": context.thread_id,
+                    "turn": { "id": context.turn_id, "threadId": context.thread_id },
+                    "reason": reason
+                }),
+            );
+        }
+    }
+
+    /// Forgets outstanding approvals of a session about to be killed; the agent is not
+    /// answered, but clients are told to close the dialogs.
+    pub(crate) async fn drop_pending_approvals(&self) {
+        let approvals = self.approvals.lock().await.take_all();
+        for approval in approvals {
+            self.emit_event(
+                event_methods::WORKSPACE_APPROVAL_RESOLVED,
+                approval_resolved_params(&approval, "cancel", ApprovalResolvedBy::SessionRestarted),
+            );
+        }
+    }
+
+    pub(crate) fn command_line(&self) -> &str {
+        &self.command_line
+    }
+
+    pub(crate) fn uptime(&self) -> Duration {
+        Duration::from_millis(now_ms().saturating_sub(self.started_at_ms))
+    }
+
+    fn record_stderr_line(&self, line: &str) {
+        if let Ok(mut tail) = self.stderr_tail.lock() {
+            if tail.len() >= STDERR_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line.to_string());
+        }
+    }
+
+    pub(crate) fn recent_stderr(&self) -> Vec<String> {
+        self.stderr_tail
+            .lock()
+            .map(|tail| tail.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Kills the agent on purpose; the heartbeat does not report this as a disconnect.
+    pub(crate) async fn kill(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        let _ = self.child.lock().await.kill().await;
+        flush_thread_items(&self.thread_store).await;
+    }
+
+    pub(crate) async fn pid(&self) -> Option<u32> {
+        self.child.lock().await.id()
+    }
+
+    pub(crate) fn health(&self) -> SessionHealth {
+        self.health
+            .lock()
+            .map(|tracker| tracker.health())
+            .unwrap_or(SessionHealth::Healthy)
+    }
+
+    pub(crate) fn missed_pings(&self) -> u32 {
+        self.health
+            .lock()
+            .map(|tracker| tracker.missed_pings())
+            .unwrap_or(0)
+    }
+
+    fn update_health(
+        &self,
+        step: impl FnOnce(&mut HealthTracker) -> Option<SessionHealth>,
+    ) -> Option<SessionHealth> {
+        self.health
+            .lock()
+            .ok()
+            .and_then(|mut tracker| step(&mut tracker))
+    }
+
+    /// Sends `HEALTH_PING_METHOD` and waits up to `wait` for the answer. Any line on stdout
+    /// in the meantime also counts, for agents that ignore unknown requests.
+    async fn ping(&self, wait: Duration) -> bool {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let sent_at_ms = now_ms();
+        let (tx, rx) = oneshot::channel();
+        let info = PendingRequestInfo {
+            id,
+            method: HEALTH_PING_METHOD.to_string(),
+            thread_id: None,
+            caller: "health".to_string(),
+            started_at_ms: sent_at_ms,
+        };
+        self.pending
+            .lock()
+            .await
+            .insert(id, PendingRequest { tx, info });
+        let written = self
+            .write_message(
+                json!({ "jsonrpc": "2.0", "id": id, "method": HEALTH_PING_METHOD, "params": {} }),
+            )
+            .await
+            .is_ok();
+        let answered = written && matches!(timeout(wait, rx).await, Ok(Ok(_)));
+        if !answered {
+            self.pending.lock().await.remove(&id);
+        }
+        answered || self.last_activity_ms.load(Ordering::SeqCst) > sent_at_ms
+    }
+
+    /// Reports an agent that exited on its own and fails whatever was waiting on it, so the
+    /// liveness monitor can respawn it. Returns true once the agent is gone.
+    async fn check_exited(&self) -> bool {
+        let exit_status = self.child.lock().await.try_wait();
+        let Ok(Some(status)) = exit_status else {
+            return false;
+        };
+        if !self.stopping.load(Ordering::SeqCst)
+            && self.update_health(HealthTracker::exited).is_some()
+        {
+            let stderr = self.recent_stderr();
+            let stderr_tail = &stderr[stderr.len().saturating_sub(DISCONNECT_STDERR_LINES)..];
+            self.emit_event(
+                event_methods::MICODE_DISCONNECTED,
+                json!({
+                    "workspaceId": self.entry.id,
+                    "exitCode": status.code(),
+                    "stderrTail": stderr_tail,
+                }),
+            );
+            self.abort_in_flight(AGENT_EXITED_REASON).await;
+        }
+        true
+    }
+
+    /// One heartbeat: pings the agent while a prompt or request is outstanding, or while it
+    /// is flagged unresponsive. The liveness monitor reports the flag through
+    /// `check_unresponsive`; only the recovery is emitted here.
+    async fn check_health(&self) {
+        if !self.is_unresponsive()
+            && !self.has_active_turns().await
+            && self.pending.lock().await.is_empty()
+        {
+            return;
+        }
+        if self.ping(HEALTH_PING_TIMEOUT).await {
+            if self.update_health(HealthTracker::ping_answered).is_some() {
+                self.unresponsive_reported.store(false, Ordering::SeqCst);
+                self.emit_event(
+                    event_methods::MICODE_HEALTHY,
+                    json!({ "workspaceId": self.entry.id }),
+                );
+            }
+        } else {
+            self.update_health(HealthTracker::ping_missed);
+        }
+    }
+
+    fn touch_background_thread(&self, thread_id: &str) {
+        if let Ok(mut activity) = self.background_activity.lock() {
+            activity.touch(thread_id, now_ms());
+        }
+    }
+
+    pub(crate) async fn background_thread_count(&self) -> usize {
+        self.background_threads.lock().await.len()
+    }
+
+    /// Archives background threads whose helper is gone (an early return or a crash
+    /// skipped its cleanup) once they have been idle for `BACKGROUND_THREAD_IDLE`, and
+    /// cancels their ACP sessions. Returns how many were reaped.
+    async fn reap_background_threads(&self) -> usize {
+        let attached: HashSet<String> = {
+            let mut callbacks = self.background_thread_callbacks.lock().await;
+            callbacks.retain(|_, callback| !callback.is_closed());
+            callbacks.keys().cloned().collect()
+        };
+        let leaked = {
+            let background_threads = self.background_threads.lock().await;
+            let Ok(mut activity) = self.background_activity.lock() else {
+                return 0;
+            };
+            activity
+                .reapable(
+                    background_threads.keys(),
+                    |thread_id| attached.contains(thread_id),
+                    now_ms(),
+                    BACKGROUND_THREAD_IDLE.as_millis() as u64,
+                )
+                .into_iter()
+                .filter_map(|thread_id| {
+                    let session_id = background_threads.get(&thread_id)?.clone();
+                    Some((thread_id, session_id))
+                })
+                .collect::<Vec<_>>()
+        };
+        if leaked.is_empty() {
+            return 0;
+        }
+        for (thread_id, session_id) in &leaked {
+            let _ = timeout(
+                HEALTH_PING_TIMEOUT,
+                self.send_acp_request_tagged(
+                    "session/cancel",
+                    json!({ "sessionId": session_id }),
+                    "background:reaper",
+                    Some(thread_id.as_str()),
+                ),
+            )
+            .await;
+            let _ = self
+                .send_request("thread/archive", json!({ "threadId": thread_id }))
+                .await;
+        }
+        self.emit_event(
+            event_methods::BACKGROUND_REAPED,
+            json!({
+                "workspaceId": self.entry.id,
+                "reaped": leaked.len(),
+                "remaining": self.background_thread_count().await,
+            }),
+        );
+        leaked.len()
+    }
+
+    /// True once the agent exited without the app stopping it.
+    pub(crate) fn has_crashed(&self) -> bool {
+        self.health() == SessionHealth::Disconnected
+    }
+
+    /// Claims a crashed session for respawning; false when another check already did.
+    pub(crate) fn claim_respawn(&self) -> bool {
+        !self.respawn_claimed.swap(true, Ordering::SeqCst)
+    }
+
+    pub(crate) fn set_redaction_settings(&self, settings: Option<RedactionSettings>) {
+        if let Ok(mut redaction) = self.redaction.lock() {
+            *redaction = CompiledRedaction::new(settings.as_ref());
+        }
+    }
+
+    fn redaction(&self) -> CompiledRedaction {
+        self.redaction
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Masks secrets in text sent to the agent outside a user prompt.
+    pub(crate) fn redact(&self, text: &str) -> Result<Redacted, String> {
+        self.redaction().redact(text)
+    }
+
+    pub(crate) fn set_audit_settings(&self, settings: Option<AuditSettings>) {
+        if let Ok(mut audit) = self.audit.lock() {
+            *audit = settings;
+        }
+    }
+
+    pub(crate) fn set_prompt_timeout_secs(&self, secs: Option<u64>) {
+        if let Ok(mut prompt_timeout_secs) = self.prompt_timeout_secs.lock() {
+            *prompt_timeout_secs = secs;
+        }
+    }
+
+    pub(crate) fn apply_session_settings(&self, settings: SessionSettings) {
+        if let Ok(mut current) = self.settings.lock() {
+            *current = settings;
+        }
+    }
+
+    fn session_settings(&self) -> SessionSettings {
+        self.settings
+            .lock()
+            .map(|settings| settings.clone())
+            .unwrap_or_default()
+    }
+
+    fn prompt_timeout(&self) -> Option<Duration> {
+        let workspace_secs = self.prompt_timeout_secs.lock().ok().and_then(|secs| *secs);
+        resolve_prompt_timeout(workspace_secs, self.session_settings().prompt_timeout_secs)
+    }
+
+    /// Audit settings when auditing is turned on for this workspace.
+    fn enabled_audit_settings(&self) -> Option<AuditSettings> {
+        self.audit
+            .lock()
+            .ok()
+            .and_then(|audit| audit.clone())
+            .filter(|audit| audit.enabled)
+    }
+
+    /// Time since the agent last wrote anything or was sent a request.
+    pub(crate) fn idle_for(&self) -> Duration {
+        Duration::from_millis(now_ms().saturating_sub(self.last_activity_ms.load(Ordering::SeqCst)))
+    }
+
+    pub(crate) fn since_last_store_maintenance(&self) -> Duration {
+        Duration::from_millis(
+            now_ms().saturating_sub(self.last_store_maintenance_ms.load(Ordering::SeqCst)),
+        )
+    }
+
+    /// Integrity-checks and compacts the thread store. The store lock is taken per file so
+    /// turns are never blocked for long, and the run stops as soon as a turn starts.
+    pub(crate) async fn run_store_maintenance(&self) -> StoreMaintenanceReport {
+        let mut report = StoreMaintenanceReport::new(&self.entry.id, now_ms());
+        let turns_started = self.turns_started.load(Ordering::SeqCst);
+        if self.has_active_turns().await || !self.pending.lock().await.is_empty() {
+            report.status = StoreMaintenanceStatus::Skipped;
+            report.finished_at = now_ms();
+            return report;
+        }
+        self.last_store_maintenance_ms
+            .store(now_ms(), Ordering::SeqCst);
+        // Maintenance works on the files, so they must hold everything first. It keeps the
+        // flush lock throughout, so no flush lands on a file while it is compacted.
+        let flush_lock = self.thread_store.lock().await.flush_lock.clone();
+        let _flushing = flush_lock.lock().await;
+        write_dirty_thread_items(&self.thread_store).await;
+        let files = {
+            let mut store = self.thread_store.lock().await;
+            store.maintain_records(&mut report);
+            store.thread_items_files()
+        };
+        for path in files {
+            let store = self.thread_store.lock().await;
+            if self.turns_started.load(Ordering::SeqCst) != turns_started {
+                report.status = StoreMaintenanceStatus::Aborted;
+                break;
+            }
+            // Items changed since the flush are newer than the file and replace it on the
+            // next one, so compacting it would be overwritten anyway.
+            if store.has_dirty_items_at(&path) {
+                continue;
+            }
+            if let Ok(outcome) = maintain_items_file(&path) {
+                report.record(&path, outcome);
+            }
+            // A cached list still holds what the file held before compaction.
+            store.evict_clean_items_at(&path);
+        }
+        report.finished_at = now_ms();
+        report
+    }
+
+    /// Removes (or with `dry_run` only lists) threads matching `options`. Background,
+    /// running and currently resumed threads are never touched.
+    pub(crate) async fn prune_thread_history(
+        &self,
+        options: &HistoryPruneOptions,
+    ) -> Vec<PrunedThread> {
+        let mut protected = self.resumed_threads.lock().await.clone();
+        protected.extend(self.background_threads.lock().await.keys().cloned());
+        protected.extend(
+            self.active_prompts
+                .lock()
+                .await
+                .values()
+                .map(|context| context.thread_id.clone()),
+        );
+        let mut store = self.thread_store.lock().await;
+        prune_store(&mut store, &self.entry.id, options, &protected)
+    }
+
+    /// Model the agent runs: the one it was started with, else the one in its settings file.
+    fn model(&self) -> Option<String> {
+        self.launch_config
+            .agent_args
+            .as_deref()
+            .and_then(model_from_micode_args)
+            .or_else(|| {
+                read_preferred_model(self.isolated_home.as_deref(), &self.settings_parse_errors)
+            })
+    }
+
+    pub(crate) async fn invalidate_all_thread_sessions(&self) {
+        self.thread_store.lock().await.clear_session_ids();
+        self.background_threads.lock().await.clear();
+        if let Ok(mut activity) = self.background_activity.lock() {
+            activity.clear();
+        }
+    }
+
+    async fn begin_prompt_tracking(&self, session_id: &str) {
+        self.pending_prompt_streaming
+            .lock()
+            .await
+            .insert(session_id.to_string(), false);
+        self.pending_prompt_agent_messages
+            .lock()
+            .await
+            .remove(session_id);
+        self.pending_prompt_reasoning
+            .lock()
+            .await
+            .remove(session_id);
+        self.pending_prompt_agent_segments
+            .lock()
+            .await
+            .insert(session_id.to_string(), 0);
+    }
+
+    async fn register_active_prompt(&self, session_id: &str, thread_id: &str, turn_id: &str) {
+        self.active_prompts.lock().await.insert(
+            session_id.to_string(),
+            ActivePromptContext::new(thread_id.to_string(), turn_id.to_string()),
+        );
+        // Only foreground turns have a phase tracker; helpers are never reported stalled.
+        if self.turn_phases.lock().await.contains_key(thread_id) {
+            if let Ok(mut stalls) = self.turn_stalls.lock() {
+                stalls.insert(thread_id.to_string(), StallWatch::new(turn_id, now_ms()));
+            }
+            self.watch_token_usage(session_id, thread_id, turn_id);
+        }
+    }
+
+    /// Emits `thread/tokenUsage/updated` for the counts the CLI wrote since the last poll.
+    async fn poll_token_usage(&self) {
+        let Some(Some(watch)) = self.token_usage_watch.get().cloned() else {
+            return;
+        };
+        let updates = tokio::task::spawn_blocking(move || {
+            watch
+                .lock()
+                .map(|mut watch| watch.poll())
+                .unwrap_or_default()
+        })
+        .await
+        .unwrap_or_default();
+        for params in updates {
+            self.emit_event(event_methods::THREAD_TOKEN_USAGE_UPDATED, params);
+        }
+    }
+
+    /// Pushes `thread/tokenUsage/updated` as soon as the CLI writes new counts for
+    /// `session_id`, rather than only once the turn completes.
+    fn watch_token_usage(&self, session_id: &str, thread_id: &str, turn_id: &str) {
+        let watch = self.token_usage_watch.get_or_init(|| {
+            let chat_files = Arc::clone(self.chat_files.as_ref()?);
+            Some(Arc::new(std::sync::Mutex::new(TokenUsageWatch::new(
+                chat_files,
+            ))))
+        });
+        let Some(watch) = watch.clone() else {
+            return;
+        };
+        let (session_id, thread_id, turn_id) = (
+            session_id.to_string(),
+            thread_id.to_string(),
+            turn_id.to_string(),
+        );
+        // Resolving the chat file can refresh the index, which reads the disk.
+        tokio::task::spawn_blocking(move || {
+            if let Ok(mut watch) = watch.lock() {
+                watch.track(&session_id, &thread_id, &turn_id);
+            }
+        });
+    }
+
+    async fn active_prompt(&self, session_id: &str) -> Option<ActivePromptContext> {
+        self.active_prompts.lock().await.get(session_id).cloned()
+    }
+
+    async fn clear_active_prompt(&self, session_id: &str) {
+        let removed = self.active_prompts.lock().await.remove(session_id);
+        if let (Some(context), Ok(mut stalls)) = (removed, self.turn_stalls.lock()) {
+            stalls.remove(&context.thread_id);
+        }
+    }
+
+    /// Notes a `session/update` for the thread's prompt, reporting the end of a stall.
+    fn record_turn_update(&self, thread_id: &str) {
+        let resumed = self.turn_stalls.lock().ok().and_then(|mut stalls| {
+            let watch = stalls.get_mut(thread_id)?;
+            let change = watch.update(now_ms())?;
+            Some((watch.turn_id.clone(), change))
+        });
+        if let Some((turn_id, change)) = resumed {
+            self.emit_event(
+                event_methods::TURN_RESUMED_STREAMING,
+                stall_params(thread_id, &turn_id, change),
+            );
+        }
+    }
+
+    /// Emits `turn/stalled` for prompts that have been silent past their threshold. Threads
+    /// with a tool call in progress use the tool threshold when one is set.
+    async fn check_turn_stalls(&self) {
+        let running_tools: HashSet<String> = self
+            .running_tool_calls
+            .lock()
+            .await
+            .values()
+            .map(|call| call.thread_id.clone())
+            .collect();
+        let thresholds = self.session_settings().stall;
+        let now = now_ms();
+        let stalled: Vec<(String, String, StallChange)> = match self.turn_stalls.lock() {
+            Ok(mut stalls) => stalls
+                .iter_mut()
+                .filter_map(|(thread_id, watch)| {
+                    let change = watch.check(now, running_tools.contains(thread_id), thresholds)?;
+                    Some((thread_id.clone(), watch.turn_id.clone(), change))
+                })
+                .collect(),
+            Err(_) => return,
+        };
+        for (thread_id, turn_id, change) in stalled {
+            self.emit_event(
+                event_methods::TURN_STALLED,
+                stall_params(&thread_id, &turn_id, change),
+            );
+        }
+    }
+
+    fn emit_turn_phase(&self, thread_id: &str, turn_id: &str, phase: TurnPhase) {
+        self.emit_event(
+            event_methods::TURN_PHASE,
+            turn_phase_params(thread_id, turn_id, phase, now_ms()),
+        );
+    }
+
+    fn emit_turn_timeout(&self, thread_id: &str, turn_id: &str, elapsed: Duration) {
+        self.emit_event(
+            event_methods::TURN_TIMEOUT,
+            json!({
+                "threadId": thread_id,
+                "turnId": turn_id,
+                "elapsedMs": elapsed.as_millis() as u64,
+                "timeoutMs": self.prompt_timeout().map(|limit| limit.as_millis() as u64),
+            }),
+        );
+    }
+
+    async fn start_turn_phase(&self, thread_id: &str, turn_id: &str) {
+        let tracker = TurnPhaseTracker::new(turn_id);
+        let phase = tracker.phase;
+        self.turn_phases
+            .lock()
+            .await
+            .insert(thread_id.to_string(), tracker);
+        self.emit_turn_phase(thread_id, turn_id, phase);
+    }
+
+    /// Applies `step` to the thread's tracker and emits `turn/phase` when it moved.
+    /// Threads without a foreground turn have no tracker and emit nothing.
+    async fn advance_turn_phase(
+        &self,
+        thread_id: &str,
+        step: impl FnOnce(&mut TurnPhaseTracker) -> Option<TurnPhase>,
+    ) {
+        let changed = {
+            let mut phases = self.turn_phases.lock().await;
+            phases
+                .get_mut(thread_id)
+                .and_then(|tracker| step(tracker).map(|phase| (tracker.turn_id.clone(), phase)))
+        };
+        if let Some((turn_id, phase)) = changed {
+            self.emit_turn_phase(thread_id, &turn_id, phase);
+        }
+    }
+
+    async fn merge_tool_call_presentation(
+        &self,
+        tool_call_id: &str,
+        incoming: ToolCallPresentation,
+    ) -> (ToolCallPresentation, bool) {
+        let mut cache = self.tool_call_presentations.lock().await;
+        let existing = cache.get(tool_call_id).cloned();
+        let was_present = existing.is_some();
+        let merged = merge_tool_presentation(existing, incoming);
+        cache.insert(tool_call_id.to_string(), merged.clone());
+        (merged, was_present)
+    }
+
+    async fn clear_tool_call_presentation(&self, tool_call_id: &str) {
+        self.tool_call_presentations.lock().await.remove(tool_call_id);
+    }
+
+    async fn track_running_tool_call(&self, tool_call_id: &str, thread_id: &str, session_id: &str) {
+        self.running_tool_calls
+            .lock()
+            .await
+            .entry(tool_call_id.to_string())
+            .or_insert_with(|| RunningToolCall {
+                thread_id: thread_id.to_string(),
+                session_id: session_id.to_string(),
+                started_at: Instant::now(),
+            });
+        self.tool_timings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .start(tool_call_id, thread_id, now_ms());
+    }
+
+    /// How long a tool call the agent reported back on ran, for the `durationMs` of its item.
+    fn finish_tool_timing(&self, tool_call_id: &str) -> Option<u64> {
+        self.tool_timings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .finish(tool_call_id, now_ms())
+    }
+
+    /// Emits `tool/slow` for tool calls running longer than `toolSlowWarningSecs`.
+    async fn check_slow_tool_calls(&self) {
+        let threshold_secs = self.session_settings().tool_slow_warning_secs;
+        if threshold_secs == 0 {
+            return;
+        }
+        let threshold_ms = threshold_secs * 1000;
+        let slow = self
+            .tool_timings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .check_slow(now_ms(), threshold_ms);
+        for call in slow {
+            let tool = self
+                .tool_call_presentations
+                .lock()
+                .await
+                .get(&call.tool_call_id)
+                .and_then(|presentation| presentation.tool.clone());
+            self.emit_event(
+                event_methods::TOOL_SLOW,
+                tool_slow_params(&call, tool.as_deref(), threshold_ms),
+            );
+        }
+    }
+
+    /// Forgets a tool call the agent reported back on. Returns whether it was cancelled
+    /// with `cancel_tool_call`, so its final update keeps the cancelled status.
+    async fn finish_running_tool_call(&self, thread_id: &str, tool_call_id: &str) -> bool {
+        self.running_tool_calls.lock().await.remove(tool_call_id);
+        self.cancelled_tool_calls
+            .lock()
+            .await
+            .get(thread_id)
+            .is_some_and(|cancelled| {
+                cancelled
+                    .iter()
+                    .any(|call| call.tool_call_id == tool_call_id)
+            })
+    }
+
+    /// Stops one running tool call and leaves the rest of the turn running. Agents that do
+    /// not advertise `toolCallCancel` get a capability error instead.
+    pub(crate) async fn cancel_tool_call(&self, tool_call_id: &str) -> Result<Value, String> {
+        if !self.supports_tool_call_cancel.load(Ordering::SeqCst) {
+            return Err(
+                "The agent does not support cancelling a single tool call. Interrupt the turn instead."
+                    .to_string(),
+            );
+        }
+        let running = self
+            .running_tool_calls
+            .lock()
+            .await
+            .get(tool_call_id)
+            .cloned()
+            .ok_or_else(|| format!("Tool call `{tool_call_id}` is not running."))?;
+        let item_id = format!("tool-{tool_call_id}");
+        let cancelled = CancelledToolCall {
+            tool_call_id: tool_call_id.to_string(),
+            item_id: item_id.clone(),
+            elapsed_ms: running.started_at.elapsed().as_millis() as u64,
+            cancelled_at_ms: now_ms(),
+        };
+        // Recorded before the request goes out: the agent may report the tool back before
+        // it answers, and that update must keep the cancelled status.
+        self.cancelled_tool_calls
+            .lock()
+            .await
+            .entry(running.thread_id.clone())
+            .or_default()
+            .push(cancelled.clone());
+        let response = self
+            .send_acp_request_tagged(
+                CANCEL_TOOL_CALL_METHOD,
+                json!({ "sessionId": running.session_id, "toolCallId": tool_call_id }),
+                "cancelToolCall",
+                Some(running.thread_id.as_str()),
+            )
+            .await;
+        let error = match &response {
+            Ok(response) => acp_error_message(response),
+            Err(error) => Some(error.clone()),
+        };
+        if let Some(error) = error {
+            if let Some(calls) = self
+                .cancelled_tool_calls
+                .lock()
+                .await
+                .get_mut(&running.thread_id)
+            {
+                calls.retain(|call| call.tool_call_id != tool_call_id);
+            }
+            return Err(format!("Failed to cancel tool call: {error}"));
+        }
+        let presentation = self
+            .tool_call_presentations
+            .lock()
+            .await
+            .get(tool_call_id)
+            .cloned()
+            .unwrap_or_default();
+        let item = build_tool_thread_item(&running.thread_id, &item_id, &presentation, "cancelled");
+        self.persist_thread_item(&running.thread_id, item.clone())
+            .await;
+        self.emit_event(
+            event_methods::ITEM_COMPLETED,
+            json!({ "threadId": running.thread_id, "item": item }),
+        );
+        Ok(json!(cancelled))
+    }
+
+    async fn mark_prompt_streaming(&self, session_id: &str) {
+        let mut pending = self.pending_prompt_streaming.lock().await;
+        if let Some(has_streaming) = pending.get_mut(session_id) {
+            *has_streaming = true;
+        }
+    }
+
+    async fn finish_prompt_tracking(&self, session_id: &str) -> bool {
+        let had_streaming = self
+            .pending_prompt_streaming
+            .lock()
+            .await
+            .remove(session_id)
+            .unwrap_or(false);
+        self.pending_prompt_agent_segments
+            .lock()
+            .await
+            .remove(session_id);
+        had_streaming
+    }
+
+    async fn finish_prompt_lifecycle(&self, session_id: &str) -> bool {
+        if let Some(context) = self.active_prompt(session_id).await {
+            self.advance_turn_phase(&context.thread_id, TurnPhaseTracker::finalizing)
+                .await;
+        }
+        let had_streaming = self.finish_prompt_tracking(session_id).await;
+        self.clear_active_prompt(session_id).await;
+        had_streaming
+    }
+
+    /// Collects agent text into the current segment. Returns the segments to checkpoint,
+    /// see `PromptAgentMessage::append`.
+    async fn append_prompt_agent_delta(&self, session_id: &str, delta: &str) -> Vec<(u32, String)> {
+        if delta.is_empty() {
+            return Vec::new();
+        }
+        let segment = self
+            .pending_prompt_agent_segments
+            .lock()
+            .await
+            .get(session_id)
+            .copied()
+            .unwrap_or(0);
+        self.pending_prompt_agent_messages
+            .lock()
+            .await
+            .entry(session_id.to_string())
+            .or_default()
+            .append(segment, delta, now_ms())
+    }
+
+    async fn current_prompt_agent_item_id(&self, session_id: &str) -> Option<String> {
+        let segment = self
+            .pending_prompt_agent_segments
+            .lock()
+            .await
+            .get(session_id)
+            .copied()?;
+        let context = self.active_prompt(session_id).await?;
+        Some(context.agent_item_id(segment))
+    }
+
+    async fn bump_prompt_agent_segment(&self, session_id: &str) {
+        let mut segments = self.pending_prompt_agent_segments.lock().await;
+        if let Some(segment) = segments.get_mut(session_id) {
+            *segment = segment.saturating_add(1);
+        }
+    }
+
+    async fn take_prompt_agent_message(&self, session_id: &str) -> Option<BTreeMap<u32, String>> {
+        self.pending_prompt_agent_messages
+            .lock()
+            .await
+            .remove(session_id)
+            .map(|message| message.segments)
+    }
+
+    pub(crate) async fn persist_thread_item(&self, thread_id: &str, item: Value) {
+        self.thread_store.lock().await.upsert_thread_item(thread_id, item);
+    }
+
+    /// Collects the reasoning of a foreground prompt. The first chunk already saves the
+    /// item, so it keeps its place among the tool calls that follow it in the history.
+    async fn append_prompt_reasoning_delta(
+        &self,
+        context: &ActivePromptContext,
+        session_id: &str,
+        delta: &str,
+    ) {
+        if delta.is_empty() || !self.session_settings().persist_reasoning {
+            return;
+        }
+        let first_chunk = {
+            let mut reasoning = self.pending_prompt_reasoning.lock().await;
+            let entry = reasoning.entry(session_id.to_string()).or_default();
+            let first_chunk = entry.is_empty();
+            entry.push_str(delta);
+            first_chunk.then(|| entry.clone())
+        };
+        if let Some(text) = first_chunk {
+            self.persist_thread_item(
+                &context.thread_id,
+                build_reasoning_thread_item(&context.thread_id, &context.turn_id, &text),
+            )
+            .await;
+        }
+    }
+
+    async fn persist_prompt_reasoning_item(
+        &self,
+        thread_id: &str,
+        turn_id: &str,
+        session_id: &str,
+    ) {
+        let Some(text) = self
+            .pending_prompt_reasoning
+            .lock()
+            .await
+            .remove(session_id)
+        else {
+            return;
+        };
+        if text.trim().is_empty() {
+            return;
+        }
+        self.persist_thread_item(
+            thread_id,
+            build_reasoning_thread_item(thread_id, turn_id, &text),
+        )
+        .await;
+    }
+
+    /// Saves what the finished prompt streamed: its reasoning and the segments of the
+    /// agent message.
+    async fn persist_prompt_items(&self, thread_id: &str, turn_id: &str, session_id: &str) {
+        self.persist_prompt_reasoning_item(thread_id, turn_id, session_id)
+            .await;
+        let Some(segments) = self.take_prompt_agent_message(session_id).await else {
+            return;
+        };
+        for (segment, text) in segments {
+            if text.trim().is_empty() {
+                continue;
+            }
+            self.persist_thread_item(
+                thread_id,
+                build_agent_thread_item(thread_id, turn_id, segment, &text),
+            )
+            .await;
+        }
+    }
+
+    /// Looks up the turn's token usage in the background so `turn/completed` is never held
+    /// back by it. The chat file is often written a moment after the turn ends, hence the
+    /// retries; `thread/tokenUsage/updated` is emitted whenever the lookup resolves.
+    fn emit_latest_thread_token_usage(&self, thread_id: &str, turn_id: &str, session_id: &str) {
+        let session_id = session_id.trim().to_string();
+        if session_id.is_empty() {
+            return;
+        }
+        let thread_store = Arc::clone(&self.thread_store);
+        let event_tx = self.event_tx.clone();
+        let workspace_id = self.entry.id.clone();
+        let thread_id = thread_id.to_string();
+        let turn_id = turn_id.to_string();
+        let Some(chat_files) = self.chat_files.clone() else {
+            return;
+        };
+        tokio::spawn(async move {
+            let started = Instant::now();
+            for delay in TOKEN_USAGE_RETRY_DELAYS {
+                sleep(*delay).await;
+                let lookup_session_id = session_id.clone();
+                let lookup_files = Arc::clone(&chat_files);
+                let usage = tokio::task::spawn_blocking(move || {
+                    load_thread_token_usage_for_session(&lookup_session_id, &lookup_files)
+                })
+                .await
+                .ok()
+                .flatten();
+                let Some(token_usage) = usage else {
+                    continue;
+                };
+                thread_store.lock().await.set_agent_item_token_usage(
+                    &thread_id,
+                    &turn_id,
+                    &token_usage,
+                );
+                let _ = event_tx.send(AppServerEvent {
+                    workspace_id,
+                    message: json!({
+                        "method": event_methods::THREAD_TOKEN_USAGE_UPDATED,
+                        "params": {
+                            "threadId": thread_id,
+                            "turnId": turn_id,
+                            "tokenUsage": token_usage,
+                            "lookupMs": started.elapsed().as_millis() as u64
+                        }
+                    }),
+                });
+                return;
+            }
+        });
+    }
+
+    async fn capture_turn_artifact_baseline(&self, thread_id: &str) {
+        let root = PathBuf::from(&self.entry.path);
+        let Ok(baseline) =
+            tokio::task::spawn_blocking(move || TurnArtifactBaseline::capture(&root)).await
+        else {
+            return;
+        };
+        self.turn_artifact_baselines
+            .lock()
+            .await
+            .insert(thread_id.to_string(), baseline);
+    }
+
+    async fn finalize_turn_artifacts(&self, thread_id: &str, turn_id: &str) -> Vec<Value> {
+        let Some(baseline) = self.turn_artifact_baselines.lock().await.remove(thread_id) else {
+            return Vec::new();
+        };
+        let items = self.thread_store.lock().await.load_thread_items(thread_id);
+        let root = PathBuf::from(&self.entry.path);
+        let globs = self.entry.settings.artifact_globs.clone();
+        let (scan_thread_id, scan_turn_id) = (thread_id.to_string(), turn_id.to_string());
+        let artifacts = tokio::task::spawn_blocking(move || {
+            collect_turn_artifacts(
+                &root,
+                &items,
+                &scan_thread_id,
+                &scan_turn_id,
+                &baseline,
+                globs.as_deref(),
+            )
+        })
+        .await
+        .unwrap_or_default();
+        if !artifacts.is_empty() {
+            self.thread_store
+                .lock()
+                .await
+                .set_agent_item_artifacts(thread_id, turn_id, &artifacts);
+        }
+        artifacts
+    }
+
+    async fn live_changes(&self, thread_id: &str, turn_id: &str) -> Vec<LiveFileChange> {
+        let baseline = self
+            .turn_artifact_baselines
+            .lock()
+            .await
+            .get(thread_id)
+            .cloned();
+        let items = self.thread_store.lock().await.load_thread_items(thread_id);
+        let root = PathBuf::from(&self.entry.path);
+        let (scan_thread_id, scan_turn_id) = (thread_id.to_string(), turn_id.to_string());
+        tokio::task::spawn_blocking(move || {
+            collect_live_changes(
+                &root,
+                &items,
+                &scan_thread_id,
+                &scan_turn_id,
+                baseline.as_ref(),
+            )
+        })
+        .await
+        .unwrap_or_default()
+    }
+
+    /// What the running turn of a thread changed so far. The final state of the same data
+    /// goes out as `changedFiles` on `turn/completed`.
+    pub(crate) async fn turn_live_changes(
+        &self,
+        thread_id: &str,
+    ) -> Result<TurnLiveChanges, String> {
+        let turn_id = self
+            .active_prompts
+            .lock()
+            .await
+            .values()
+            .find(|context| context.thread_id == thread_id)
+            .map(|context| context.turn_id.clone())
+            .ok_or_else(|| "No turn is running in this thread".to_string())?;
+        let files = self.live_changes(thread_id, &turn_id).await;
+        Ok(TurnLiveChanges::new(thread_id, &turn_id, files))
+    }
+
+    async fn record_audit_read(
+        &self,
+        thread_id: &str,
+        tool_item_id: &str,
+        presentation: &ToolCallPresentation,
+    ) {
+        if self.enabled_audit_settings().is_none() {
+            return;
+        }
+        let Some(read) = audit_read_from_tool(
+            Path::new(&self.entry.path),
+            tool_item_id,
+            presentation.tool.as_deref(),
+            presentation.arguments.as_ref(),
+            presentation.result.as_deref(),
+            now_ms(),
+        ) else {
+            return;
+        };
+        self.turn_audit_reads
+            .lock()
+            .await
+            .entry(thread_id.to_string())
+            .or_default()
+            .push(read);
+    }
+
+    /// Writes the turn's audit file and prunes expired ones. Returns `None` when auditing
+    /// is off for the workspace.
+    async fn finalize_turn_audit(&self, thread_id: &str, turn_id: &str) -> Option<AuditSummary> {
+        let reads = self
+            .turn_audit_reads
+            .lock()
+            .await
+            .remove(thread_id)
+            .unwrap_or_default();
+        let settings = self.enabled_audit_settings()?;
+        let audit = TurnAudit {
+            thread_id: thread_id.to_string(),
+            turn_id: turn_id.to_string(),
+            recorded_at_ms: now_ms(),
+            summary: summarize(&reads),
+            reads,
+        };
+        let summary = audit.summary.clone();
+        let root = PathBuf::from(&self.entry.path);
+        let written = tokio::task::spawn_blocking(move || {
+            let written = write_turn_audit(&root, &audit);
+            prune_turn_audits(&root, settings.retention_days);
+            written
+        })
+        .await
+        .map_err(|err| err.to_string())
+        .and_then(|written| written);
+        if let Err(error) = written {
+            self.emit_event(
+                event_methods::AUDIT_WRITE_FAILED,
+                json!({ "threadId": thread_id, "turnId": turn_id, "error": error }),
+            );
+        }
+        Some(summary)
+    }
+
+    /// Seeds the review queue with the files the turn wrote. Returns `None` when the turn
+    /// changed nothing.
+    async fn seed_turn_review(&self, thread_id: &str, turn_id: &str) -> Option<ReviewProgress> {
+        let items = self.thread_store.lock().await.load_thread_items(thread_id);
+        let root = PathBuf::from(&self.entry.path);
+        let (seed_thread_id, seed_turn_id) = (thread_id.to_string(), turn_id.to_string());
+        let seeded = tokio::task::spawn_blocking(move || {
+            let paths = turn_changed_files(&root, &items, &seed_thread_id, &seed_turn_id);
+            if paths.is_empty() {
+                return None;
+            }
+            seed_turn_review(&root, &seed_thread_id, &seed_turn_id, &paths, now_ms()).ok()
+        })
+        .await
+        .ok()
+        .flatten()?;
+        Some(seeded.progress)
+    }
+
+    fn record_user_interrupt(&self, thread_id: &str, turn_id: &str) {
+        if let Ok(mut interrupts) = self.user_interrupts.lock() {
+            interrupts.insert(thread_id.to_string(), turn_id.to_string());
+        }
+    }
+
+    /// Whether the running turn of the thread ends because the user interrupted it.
+    fn take_user_interrupt(&self, thread_id: &str) -> bool {
+        self.user_interrupts
+            .lock()
+            .ok()
+            .and_then(|mut interrupts| interrupts.remove(thread_id))
+            .is_some()
+    }
+
+    async fn emit_turn_completed(&self, thread_id: &str, turn_id: &str, turn: &Value) {
+        let params = self.finish_turn_params(thread_id, turn_id, turn).await;
+        self.emit_event(event_methods::TURN_COMPLETED, params);
+    }
+
+    /// Ends a turn the user interrupted: `turn/cancelled` replaces `turn/completed` and a
+    /// note is left in the thread.
+    async fn emit_turn_cancelled(&self, thread_id: &str, turn_id: &str, turn: &Value) {
+        self.persist_thread_item(thread_id, build_interruption_note_item(thread_id, turn_id))
+            .await;
+        let mut params = self.finish_turn_params(thread_id, turn_id, turn).await;
+        params["turnId"] = json!(turn_id);
+        params["cancelledBy"] = json!("user");
+        self.emit_event(event_methods::TURN_CANCELLED, params);
+    }
+
+    /// Finalizes the bookkeeping of an ended turn and returns the event params describing it.
+    async fn finish_turn_params(&self, thread_id: &str, turn_id: &str, turn: &Value) -> Value {
+        // Before the artifacts, which consume the turn baseline.
+        let changed_files = self.live_changes(thread_id, turn_id).await;
+        let artifacts = self.finalize_turn_artifacts(thread_id, turn_id).await;
+        let mut params = json!({
+            "threadId": thread_id,
+            "turn": turn,
+            "artifacts": artifacts,
+            "changedFiles": changed_files
+        });
+        if let Some(audit) = self.finalize_turn_audit(thread_id, turn_id).await {
+            params["audit"] = json!(audit);
+        }
+        if let Some(review) = self.seed_turn_review(thread_id, turn_id).await {
+            params["review"] = json!(review);
+        }
+        self.running_tool_calls
+            .lock()
+            .await
+            .retain(|_, call| call.thread_id != thread_id);
+        self.tool_timings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .forget_thread(thread_id);
+        self.turn_phases.lock().await.remove(thread_id);
+        let cancelled_tool_calls = self
+            .cancelled_tool_calls
+            .lock()
+            .await
+            .remove(thread_id)
+            .unwrap_or_default();
+        if !cancelled_tool_calls.is_empty() {
+            params["cancelledToolCalls"] = json!(cancelled_tool_calls);
+        }
+        // Whatever the turn saved is on disk before the frontend hears it ended.
+        flush_thread_items(&self.thread_store).await;
+        // The CLI has written the turn to its chat file, so the lookups after it hit.
+        if let Some(chat_files) = self.chat_files.clone() {
+            tokio::task::spawn_blocking(move || refresh_chat_files(&chat_files));
+        }
+        params
+    }
+
+    /// Looks up an artifact recorded on a turn. Artifacts whose file has since been
+    /// deleted are reported as missing.
+    pub(crate) async fn find_turn_artifact(
+        &self,
+        thread_id: &str,
+        turn_id: &str,
+        path: &str,
+    ) -> Result<Value, String> {
+        let base_item_id = format!("agent-{thread_id}-{turn_id}");
+        let mut items = self.thread_store.lock().await.load_thread_items(thread_id);
+        mark_missing_artifacts(Path::new(&self.entry.path), &mut items);
+        let artifact = items
+            .iter()
+            .filter(|item| {
+                item.get("id")
+                    .and_then(Value::as_str)
+                    .map(|id| id.starts_with(&base_item_id))
+                    .unwrap_or(false)
+            })
+            .filter_map(|item| item.get("artifacts").and_then(Value::as_array))
+            .flatten()
+            .find(|artifact| artifact.get("path").and_then(Value::as_str) == Some(path))
+            .cloned()
+            .ok_or_else(|| "Artifact not found for this turn".to_string())?;
+        if artifact.get("missing").and_then(Value::as_bool) == Some(true) {
+            return Err(format!("Artifact `{path}` no longer exists"));
+        }
+        Ok(artifact)
+    }
+
+    /// HEAD of the workspace and the model a primed session is seeded for.
+    async fn primer_basis(&self) -> Option<(PrimerHead, Option<String>)> {
+        let path = PathBuf::from(&self.entry.path);
+        let head = tokio::task::spawn_blocking(move || read_head(&path))
+            .await
+            .ok()?;
+        Some((head, self.model()))
+    }
+
+    /// A new session seeded with the repository summary; `None` when seeding fails.
+    async fn seed_primed_session(
+        &self,
+        head: PrimerHead,
+        model: Option<String>,
+    ) -> Option<PrimedSession> {
+        // The README excerpt may hold credentials like any other prompt.
+        let summary = self
+            .redact(&build_repo_summary(Path::new(&self.entry.path), &head))
+            .ok()?
+            .text;
+        let session_id = self
+            .create_session_for_cwd(self.entry.path.clone(), None)
+            .await
+            .ok()?;
+        let seeded = with_prompt_timeout(
+            self.prompt_timeout(),
+            self.send_acp_request_tagged(
+                "session/prompt",
+                build_prompt_params(&session_id, &summary, &[], None, None, None),
+                &background_caller(Some("primer")),
+                None,
+            ),
+        )
+        .await
+        .ok()?
+        .ok()?;
+        if acp_error_message(&seeded).is_some() {
+            return None;
+        }
+        Some(PrimedSession {
+            session_id,
+            head,
+            model,
+            summary_tokens: estimate_tokens(&summary),
+        })
+    }
+
+    /// Session id of a primed session for a background helper: the spare when the
+    /// repository and model have not moved on, otherwise one seeded now. The session is
+    /// the helper's alone. Returns `None` when seeding fails; the caller then uses a fresh
+    /// session.
+    async fn lease_primer_session(&self, thread_id: &str) -> Option<String> {
+        let (head, model) = self.primer_basis().await?;
+        let workspace_path = Path::new(&self.entry.path);
+        let spare = self.primer.lock().await.spare.take();
+        let primed = match spare {
+            Some(spare)
+                if spare.model == model
+                    && !head_changed_significantly(workspace_path, &spare.head, &head) =>
+            {
+                record_reuse(workspace_path, spare.summary_tokens);
+                spare
+            }
+            _ => self.seed_primed_session(head, model).await?,
+        };
+        self.primer
+            .lock()
+            .await
+            .leases
+            .insert(thread_id.to_string());
+        Some(primed.session_id)
+    }
+
+    /// Ends a helper's lease. Its session, which now holds the helper's prompts, is never
+    /// handed out again; a clean spare is seeded for the next helper instead.
+    async fn release_primer_session(&self, thread_id: &str) {
+        {
+            let mut primer = self.primer.lock().await;
+            if !primer.leases.remove(thread_id) || primer.spare.is_some() {
+                return;
+            }
+        }
+        let Some((head, model)) = self.primer_basis().await else {
+            return;
+        };
+        let spare = self.seed_primed_session(head, model).await;
+        let mut primer = self.primer.lock().await;
+        if primer.spare.is_none() {
+            primer.spare = spare;
+        }
+    }
+
+    async fn invalidate_primer(&self) {
+        self.primer.lock().await.spare = None;
+    }
+
+    /// The CLI's chat history for `session_id` and how the thread's stored items compare
+    /// to it. `None` when the session has no chat file. The CLI's files are only read.
+    async fn thread_sync_state(
+        &self,
+        thread_id: &str,
+        session_id: &str,
+    ) -> Option<(ThreadSyncReport, Vec<CliMessage>)> {
+        let session_id = session_id.trim().to_string();
+        if session_id.is_empty() {
+            return None;
+        }
+        let chat_files = self.chat_files.clone()?;
+        let cli = tokio::task::spawn_blocking(move || {
+            read_cli_messages(&find_chat_file(&chat_files, &session_id)?)
+        })
+        .await
+        .ok()??;
+        let store = self.thread_store.lock().await;
+        let items = store.load_thread_items(thread_id);
+        let last_item_at = store.last_item_at(thread_id).map(|secs| secs * 1000);
+        Some((compare_histories(&items, &cli, last_item_at), cli))
+    }
+
+    pub(crate) async fn has_thread(&self, thread_id: &str) -> bool {
+        self.get_thread_by_id(thread_id).await.is_ok()
+    }
+
+    /// Agent reply of a turn, joining the segments split around tool calls.
+    pub(crate) async fn turn_agent_text(&self, thread_id: &str, turn_id: &str) -> String {
+        let base_item_id = format!("agent-{thread_id}-{turn_id}");
+        let segment_prefix = format!("{base_item_id}-s");
+        self.thread_store
+            .lock()
+            .await
+            .load_thread_items(thread_id)
+            .iter()
+            .filter(|item| {
+                item.get("id")
+                    .and_then(Value::as_str)
+                    .map(|id| id == base_item_id || id.starts_with(&segment_prefix))
+                    .unwrap_or(false)
+            })
+            .filter_map(|item| item.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Text of the requested user messages in thread order. Fails when an id is unknown
+    /// or does not name a user message.
+    pub(crate) async fn user_message_texts(
+        &self,
+        thread_id: &str,
+        item_ids: &[String],
+    ) -> Result<Vec<String>, String> {
+        self.get_thread_by_id(thread_id).await?;
+        let items = self.thread_store.lock().await.load_thread_items(thread_id);
+        let mut texts = Vec::new();
+        let mut found = HashSet::new();
+        for item in &items {
+            let Some(id) = item.get("id").and_then(Value::as_str) else {
+                continue;
+            };
+            if !item_ids.iter().any(|wanted| wanted == id) {
+                continue;
+            }
+            if item.get("type").and_then(Value::as_str) != Some("userMessage") {
+                return Err(format!("Item `{id}` is not a user message"));
+            }
+            let text = user_message_text(item);
+            found.insert(id.to_string());
+            texts.push(text);
+        }
+        if let Some(missing) = item_ids.iter().find(|id| !found.contains(*id)) {
+            return Err(format!("Item `{missing}` was not found in this thread"));
+        }
+        Ok(texts)
+    }
+
+    /// How the remembered approval rules answer a permission request for `command`.
+    /// The rules file is read on every request so rules remembered meanwhile apply;
+    /// the deciding rule's hit count is bumped in the background.
+    fn auto_decide_approval(
+        &self,
+        command: &[String],
+        request_id: &Value,
+        thread_id: &str,
+    ) -> Option<AutoDecision> {
+        let rules_path = self.rules_path.clone()?;
+        let rules = rules::load_prefix_rules(&rules_path);
+        let decision = auto_decision(&rules, command, request_id, thread_id)?;
+        let rule = decision.rule.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = rules::record_rule_hit(&rules_path, &rule) {
+                eprintln!("failed to count approval rule hit: {err}");
+            }
+        });
+        Some(decision)
+    }
+
+    /// Caches the commands of an `available_commands_update`.
+    fn record_available_commands(&self, update: &Value) {
+        self.available_commands
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .update(update);
+    }
+
+    /// The advertised command named `name`, with or without its leading `/`.
+    pub(crate) fn resolve_slash_command(&self, name: &str) -> Result<String, CommandNotAvailable> {
+        self.available_commands
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .resolve(name)
+    }
+
+    /// Resolves a thread referenced from another message. Archived threads resolve as long
+    /// as their items file exists; the cached summary is only returned while it matches the
+    /// thread's latest item sequence.
+    pub(crate) async fn thread_reference_source(
+        &self,
+        thread_id: &str,
+    ) -> Result<ThreadReferenceSource, String> {
+        let not_found = || format!("Referenced thread `{thread_id}` was not found.");
+        let (record, unflushed_items, items_path, summary_path) = {
+            let store = self.thread_store.lock().await;
+            let record = store.by_thread_id(thread_id).ok_or_else(not_found)?;
+            if !store.has_thread_items(thread_id) {
+                return Err(not_found());
+            }
+            let unflushed_items = store.cached_items().items.get(thread_id).cloned();
+            (
+                record,
+                unflushed_items,
+                store.thread_items_path(thread_id),
+                store.summary_cache_path(thread_id),
+            )
+        };
+        // The files are read off the store lock; the summary is keyed on the last item's
+        // `seq`, which moves with every new item.
+        let (items, item_seq, cached_summary) = tokio::task::spawn_blocking(move || {
+            let items = unflushed_items.unwrap_or_else(|| read_items_file(&items_path));
+            let item_seq = last_item_seq(&items);
+            let cached_summary = read_cached_summary(&summary_path, item_seq);
+            (items, item_seq, cached_summary)
+        })
+        .await
+        .map_err(|err| err.to_string())?;
+        let transcript = if cached_summary.is_some() {
+            String::new()
+        } else {
+            render_transcript(&items, None, None, TranscriptFormat::Markdown)?
+        };
+        Ok(ThreadReferenceSource {
+            title: record.title,
+            item_seq,
+            transcript: tail_transcript(&transcript),
+            cached_summary,
+        })
+    }
+
+    /// The first prompt of a foreground thread whose title is still the one derived from
+    /// that prompt. Each thread is handed out once per session, so the background
+    /// auto-title runs after its first turn only.
+    pub(crate) async fn claim_auto_title(&self, thread_id: &str) -> Option<String> {
+        if self.background_threads.lock().await.contains_key(thread_id)
+            || self
+                .background_thread_callbacks
+                .lock()
+                .await
+                .contains_key(thread_id)
+        {
+            return None;
+        }
+        let store = self.thread_store.lock().await;
+        let record = store.by_thread_id(thread_id)?;
+        if record.title_source != Some(ThreadTitleSource::Prompt) {
+            return None;
+        }
+        let prompt = store
+            .load_thread_items(thread_id)
+            .iter()
+            .find(|item| item.get("type").and_then(Value::as_str) == Some("userMessage"))
+            .map(user_message_text)
+            .filter(|text| !text.trim().is_empty())?;
+        drop(store);
+        let claimed = self
+            .auto_title_claims
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(thread_id.to_string());
+        claimed.then_some(prompt)
+    }
+
+    pub(crate) async fn cache_thread_summary(&self, thread_id: &str, item_seq: u64, summary: &str) {
+        let path = self.thread_store.lock().await.summary_cache_path(thread_id);
+        let _ = write_cached_summary(&path, item_seq, summary, now_ms());
+    }
+
+    /// The review item of a thread that carries structured findings: `item_id` when
+    /// given, otherwise the most recent completed review.
+    pub(crate) async fn find_review_item(
+        &self,
+        thread_id: &str,
+        item_id: Option<&str>,
+    ) -> Result<Value, String> {
+        let items = self.thread_store.lock().await.load_thread_items(thread_id);
+        let mut reviews = items.into_iter().filter(|item| {
+            item.get("type").and_then(Value::as_str) == Some("exitedReviewMode")
+                && item.get("findings").and_then(Value::as_array).is_some()
+        });
+        match item_id {
+            Some(item_id) => reviews
+                .find(|item| item.get("id").and_then(Value::as_str) == Some(item_id))
+                .ok_or_else(|| format!("Review `{item_id}` has no structured findings")),
+            None => reviews
+                .next_back()
+                .ok_or_else(|| "No review with structured findings in this thread".to_string()),
+        }
+    }
+
+    pub(crate) async fn find_thread_item(
+        &self,
+        thread_id: &str,
+        item_id: &str,
+    ) -> Result<Value, String> {
+        self.thread_store
+            .lock()
+            .await
+            .load_thread_items(thread_id)
+            .into_iter()
+            .find(|item| item.get("id").and_then(Value::as_str) == Some(item_id))
+            .ok_or_else(|| format!("Item `{item_id}` not found in thread {thread_id}"))
+    }
+
+    pub(crate) async fn add_item_artifact(
+        &self,
+        thread_id: &str,
+        item_id: &str,
+        artifact: Value,
+    ) -> Result<(), String> {
+        let added = self
+            .thread_store
+            .lock()
+            .await
+            .add_item_artifact(thread_id, item_id, artifact);
+        if added {
+            Ok(())
+        } else {
+            Err(format!("Item `{item_id}` not found in thread {thread_id}"))
+        }
+    }
+
+    /// Returns the persisted history of a thread without touching its ACP session, so a
+    /// watching client never disturbs the client that is driving the thread.
+    pub(crate) async fn thread_snapshot(&self, thread_id: &str) -> Result<Value, String> {
+        let thread = self.get_thread_by_id(thread_id).await?;
+        let mut items = self.thread_store.lock().await.load_thread_items(thread_id);
+        mark_missing_artifacts(Path::new(&self.entry.path), &mut items);
+        attach_review_progress(Path::new(&self.entry.path), thread_id, &mut items, now_ms());
+        let active_turn_id = self
+            .active_prompts
+            .lock()
+            .await
+            .values()
+            .find(|context| context.thread_id == thread_id)
+            .map(|context| context.turn_id.clone());
+        Ok(json!({
+            "id": thread.thread_id,
+            "name": thread.title,
+            "items": items,
+            "activeTurnId": active_turn_id
+        }))
+    }
+
+    async fn write_message(&self, value: Value) -> Result<(), String> {
+        let mut stdin = self.stdin.lock().await;
+        let mut line = serde_json::to_string(&value).map_err(|e| e.to_string())?;
+        line.push('\n');
         stdin
             .write_all(line.as_bytes())
             .await
@@ -1717,17 +4947,55 @@ impl WorkspaceSession {
     }
 
     async fn send_acp_request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let thread_id = params
+            .get("threadId")
+            .and_then(Value::as_str)
+            .map(ToString::to_string);
+        self.send_acp_request_tagged(method, params, "request", thread_id.as_deref())
+            .await
+    }
+
+    /// Sends an ACP request and records who sent it, and for which thread, next to the
+    /// pending entry. The metadata never goes over the wire.
+    async fn send_acp_request_tagged(
+        &self,
+        method: &str,
+        params: Value,
+        caller: &str,
+        thread_id: Option<&str>,
+    ) -> Result<Value, String> {
         let id = self.next_id.fetch_add(1, Ordering::SeqCst);
         let (tx, rx) = oneshot::channel();
-        self.pending.lock().await.insert(id, tx);
+        let info = PendingRequestInfo {
+            id,
+            method: method.to_string(),
+            thread_id: thread_id.map(ToString::to_string),
+            caller: caller.to_string(),
+            started_at_ms: now_ms(),
+        };
+        let was_idle = {
+            let mut pending = self.pending.lock().await;
+            let was_idle = pending.is_empty();
+            pending.insert(id, PendingRequest { tx, info });
+            was_idle
+        };
+        if was_idle {
+            // Measure unresponsiveness from the first outstanding request, not from
+            // whenever the agent last happened to write something.
+            self.record_activity();
+        }
         self.write_message(
             json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }),
         )
         .await?;
+        if let (Some(thread_id), "session/prompt") = (thread_id, method) {
+            self.advance_turn_phase(thread_id, TurnPhaseTracker::prompt_sent)
+                .await;
+        }
         rx.await.map_err(|_| "request canceled".to_string())
     }
 
-    fn emit_event(&self, method: &str, params: Value) {
+    pub(crate) fn emit_event(&self, method: &str, params: Value) {
         let _ = self.event_tx.send(AppServerEvent {
             workspace_id: self.entry.id.clone(),
             message: json!({ "method": method, "params": params }),
@@ -1742,18 +5010,30 @@ impl WorkspaceSession {
             archived: false,
             updated_at: now_ts(),
             message_index: 0,
+            tags: Vec::new(),
+            last_seen_item_seq: Some(0),
+            kickoff: None,
+            pinned: false,
+            archived_at: None,
+            access_mode: None,
+            title_source: None,
         };
         let mut store = self.thread_store.lock().await;
         store.upsert(thread.clone());
         store.set_session_id(&thread.thread_id, thread.session_id.clone());
+        drop(store);
+        self.resumed_threads
+            .lock()
+            .await
+            .insert(thread.thread_id.clone());
         thread
     }
 
-    async fn get_thread_by_id(&self, thread_id: &str) -> Result<LocalThreadRecord, String> {
+    async fn get_thread_by_id(&self, thread_id: &str) -> Result<LocalThreadRecord, CommandError> {
         let store = self.thread_store.lock().await;
         store
             .by_thread_id(thread_id)
-            .ok_or_else(|| format!("thread not found: {thread_id}"))
+            .ok_or_else(|| CommandError::thread_not_found(thread_id))
     }
 
     fn parse_prompt_from_turn_start(params: &Value) -> String {
@@ -1778,35 +5058,631 @@ impl WorkspaceSession {
         if !from_input.is_empty() {
             return from_input;
         }
-        params
-            .get("text")
-            .and_then(Value::as_str)
-            .map(str::trim)
-            .filter(|value| !value.is_empty())
-            .map(ToString::to_string)
-            .unwrap_or_default()
-    }
-
-    async fn create_session_for_cwd(&self, cwd: String) -> Result<String, String> {
-        let mcp_servers = read_configured_mcp_servers();
-        let response = self
-            // ACP requires mcpServers in session/new. Pass configured servers from settings.
-            .send_acp_request(
-                "session/new",
-                json!({ "cwd": cwd, "mcpServers": mcp_servers }),
-            )
-            .await?;
-        let result = response.get("result").cloned().ok_or_else(|| {
-            acp_error_message(&response).unwrap_or_else(|| "missing ACP result".to_string())
-        })?;
-        result
-            .get("sessionId")
-            .and_then(Value::as_str)
-            .map(|v| v.to_string())
-            .ok_or_else(|| "missing sessionId from ACP session/new".to_string())
+        params
+            .get("text")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(ToString::to_string)
+            .unwrap_or_default()
+    }
+
+    async fn create_session_for_cwd(
+        &self,
+        cwd: String,
+        access_mode: Option<&str>,
+    ) -> Result<String, String> {
+        let mcp_servers =
+            read_configured_mcp_servers(self.isolated_home.as_deref(), &self.settings_parse_errors);
+        let response = self
+            .send_acp_request_tagged(
+                "session/new",
+                build_session_new_params(&cwd, mcp_servers, access_mode),
+                "session",
+                None,
+            )
+            .await?;
+        let result = response.get("result").cloned().ok_or_else(|| {
+            acp_error_message(&response).unwrap_or_else(|| "missing ACP result".to_string())
+        })?;
+        result
+            .get("sessionId")
+            .and_then(Value::as_str)
+            .map(|v| v.to_string())
+            .ok_or_else(|| "missing sessionId from ACP session/new".to_string())
+    }
+
+    pub(crate) async fn send_request(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, CommandError> {
+        let result = self.handle_request(method, params).await;
+        self.emit_settings_parse_errors();
+        result
+    }
+
+    /// Surfaces MiCode settings files that failed to parse since the last request, so a
+    /// broken file shows up instead of silently emptying model and MCP server lists.
+    fn emit_settings_parse_errors(&self) {
+        for error in self.settings_parse_errors.take_pending() {
+            self.emit_event(
+                event_methods::MICODE_SETTINGS_PARSE_ERROR,
+                serde_json::to_value(&error).unwrap_or(Value::Null),
+            );
+        }
+    }
+
+    /// Runs one foreground or background prompt to completion.
+    async fn start_turn(&self, params: Value) -> Result<Value, CommandError> {
+        self.turns_started.fetch_add(1, Ordering::SeqCst);
+        let thread_id = params
+            .get("threadId")
+            .and_then(Value::as_str)
+            .ok_or_else(|| "missing threadId".to_string())?
+            .to_string();
+        let background_session = {
+            let background_threads = self.background_threads.lock().await;
+            background_threads.get(&thread_id).cloned()
+        };
+        let is_background_thread = self
+            .background_thread_callbacks
+            .lock()
+            .await
+            .contains_key(&thread_id)
+            || background_session.is_some();
+        if background_session.is_some() {
+            self.touch_background_thread(&thread_id);
+        }
+        let request_caller = if is_background_thread {
+            background_caller(params.get("_purpose").and_then(Value::as_str))
+        } else {
+            CALLER_TURN.to_string()
+        };
+        let thread = if background_session.is_none() {
+            Some(self.get_thread_by_id(&thread_id).await?)
+        } else {
+            None
+        };
+        let raw_text = params
+            .get("rawText")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let prompt_text = Self::parse_prompt_from_turn_start(&params);
+        let prompt_text = if raw_text {
+            prompt_text
+        } else {
+            normalize_prompt_text(&prompt_text)
+        };
+        let item_kind = params
+            .get("_kind")
+            .and_then(Value::as_str)
+            .map(ToString::to_string);
+        let images = prompt_images_from_input(&params);
+        if prompt_text.is_empty() && images.is_empty() {
+            return Err("empty user message".into());
+        }
+        let image_blocks = if images.is_empty() {
+            Vec::new()
+        } else {
+            let embed = self.supports_image_prompts.load(Ordering::SeqCst);
+            let images = images.clone();
+            tokio::task::spawn_blocking(move || {
+                images
+                    .iter()
+                    .map(|image| image.to_content_block(embed))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .await
+            .map_err(|err| err.to_string())??
+        };
+        let redaction = self.redaction();
+        let skip_redaction = params
+            .get("skipRedaction")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let (prompt_text, redactions) = if skip_redaction {
+            if !redaction.allow_skip {
+                return Err("Skipping redaction is not allowed for this workspace".into());
+            }
+            (prompt_text, BTreeMap::new())
+        } else {
+            let redacted = redaction.redact(&prompt_text)?;
+            (redacted.text, redacted.redactions)
+        };
+        let mut thread_references: Vec<ThreadReference> = params
+            .get("_threadReferences")
+            .filter(|value| !value.is_null())
+            .map(|value| serde_json::from_value(value.clone()))
+            .transpose()
+            .map_err(|err| format!("invalid thread references: {err}"))?
+            .unwrap_or_default();
+        if !skip_redaction {
+            for reference in &mut thread_references {
+                reference.summary = redaction.redact(&reference.summary)?.text;
+            }
+        }
+        let agent_prompt = append_reference_blocks(&prompt_text, &thread_references);
+        if !is_background_thread {
+            if let Some(thread_entry) = thread.as_ref() {
+                if thread_entry.title.trim().eq_ignore_ascii_case("new thread") {
+                    if let Some(title) = derive_thread_title(&prompt_text) {
+                        let updated = self.thread_store.lock().await.rename_thread(
+                            &thread_id,
+                            title,
+                            ThreadTitleSource::Prompt,
+                        );
+                        if let Some(params) = updated {
+                            self.emit_event(event_methods::THREAD_NAME_UPDATED, params);
+                        }
+                    }
+                }
+            }
+        }
+        let mut session_id = background_session
+            .clone()
+            .or_else(|| thread.as_ref().map(|entry| entry.session_id.clone()))
+            .unwrap_or_default();
+        let requested_model = params
+            .get("model")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(ToString::to_string);
+        let mut needs_fresh_session = false;
+        let sampling_params = match params
+            .get("samplingParams")
+            .filter(|value| !value.is_null())
+        {
+            Some(value) => Some(parse_sampling_params(value)?),
+            None => None,
+        }
+        .filter(|sampling| !sampling.is_empty());
+        let (prompt_sampling, sampling_applied) =
+            if self.supports_session_sampling.load(Ordering::SeqCst) {
+                let prompt_sampling = sampling_params.as_ref().map(|sampling| json!(sampling));
+                let applied = sampling_params.as_ref().map(|_| "session");
+                (prompt_sampling, applied)
+            } else if let Some(isolated_home) = self.isolated_home.as_deref().filter(|_| {
+                sampling_params.is_some()
+                    || self.sampling_written_to_settings.load(Ordering::SeqCst)
+            }) {
+                // Outside an isolated home the settings file is shared by every workspace,
+                // so the parameters are left unapplied there.
+                let target = sampling_params.clone().unwrap_or_default();
+                needs_fresh_session |= sync_sampling_params_to_settings(
+                    &target,
+                    isolated_home,
+                    &self.settings_parse_errors,
+                )?;
+                self.sampling_written_to_settings
+                    .store(sampling_params.is_some(), Ordering::SeqCst);
+                (None, sampling_params.as_ref().map(|_| "settings"))
+            } else {
+                (None, None)
+            };
+        let requested_effort = params
+            .get("effort")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(ToString::to_string);
+        let (prompt_effort, effort_applied) = if self
+            .supports_session_reasoning_effort
+            .load(Ordering::SeqCst)
+        {
+            let applied = requested_effort.as_ref().map(|_| "session");
+            (requested_effort.clone(), applied)
+        } else if let (Some(effort), Some(isolated_home)) =
+            (requested_effort.as_deref(), self.isolated_home.as_deref())
+        {
+            needs_fresh_session |=
+                set_preferred_effort(effort, isolated_home, &self.settings_parse_errors)?;
+            (None, Some("settings"))
+        } else {
+            // Outside an isolated home the settings file is shared by every workspace, so
+            // the effort is left unapplied there, as sampling is; `turn/started` reports it
+            // with a null `effortApplied`.
+            (None, None)
+        };
+        let recorded_access_mode = thread.as_ref().and_then(|entry| entry.access_mode.clone());
+        let access_mode = access_mode_from_turn_params(&params)
+            .map(ToString::to_string)
+            .or_else(|| recorded_access_mode.clone());
+        let access_mode_changed = access_mode_requires_fresh_session(
+            recorded_access_mode.as_deref(),
+            access_mode.as_deref(),
+        );
+        if let (Some(access_mode), false) = (access_mode.as_deref(), is_background_thread) {
+            self.thread_store
+                .lock()
+                .await
+                .set_access_mode(&thread_id, access_mode);
+        }
+        let prompt_access = access_mode
+            .as_deref()
+            .map(|access_mode| access_mode_meta(access_mode, &self.entry.path));
+        if needs_fresh_session || access_mode_changed {
+            if needs_fresh_session {
+                // The primer was seeded under the previous model, effort or sampling settings.
+                self.invalidate_primer().await;
+            }
+            let fresh_session = self
+                .create_session_for_cwd(self.entry.path.clone(), access_mode.as_deref())
+                .await?;
+            if is_background_thread {
+                self.background_threads
+                    .lock()
+                    .await
+                    .insert(thread_id.clone(), fresh_session.clone());
+            } else {
+                self.thread_store
+                    .lock()
+                    .await
+                    .set_session_id(&thread_id, fresh_session.clone());
+            }
+            session_id = fresh_session;
+        }
+        if session_id.trim().is_empty() {
+            // Some migrated/local records may have an empty session id.
+            // Recreate proactively to avoid one failed prompt + retry roundtrip.
+            let fresh_session = self
+                .create_session_for_cwd(self.entry.path.clone(), access_mode.as_deref())
+                .await?;
+            if is_background_thread {
+                self.background_threads
+                    .lock()
+                    .await
+                    .insert(thread_id.clone(), fresh_session.clone());
+            } else {
+                self.thread_store
+                    .lock()
+                    .await
+                    .set_session_id(&thread_id, fresh_session.clone());
+            }
+            session_id = fresh_session;
+        }
+        let turn_id = Uuid::new_v4().to_string();
+        if !is_background_thread {
+            // An interrupt that arrived while no turn was running does not carry over.
+            self.take_user_interrupt(&thread_id);
+            self.capture_turn_artifact_baseline(&thread_id).await;
+            self.turn_audit_reads.lock().await.remove(&thread_id);
+            self.cancelled_tool_calls.lock().await.remove(&thread_id);
+            let mut user_item =
+                build_user_thread_item(&thread_id, &turn_id, &prompt_text, &redactions);
+            if let Some(content) = user_item["content"].as_array_mut() {
+                content.extend(images.iter().map(PromptImage::to_item_content));
+            }
+            if !thread_references.is_empty() {
+                user_item["threadReferences"] = Value::Array(
+                    thread_references
+                        .iter()
+                        .map(ThreadReference::to_item_value)
+                        .collect(),
+                );
+            }
+            if let Some(kind) = item_kind {
+                user_item["kind"] = json!(kind);
+            }
+            self.persist_thread_item(&thread_id, user_item).await;
+            self.emit_event(
+                event_methods::TURN_STARTED,
+                json!({
+                    "threadId": thread_id,
+                    "turn": { "id": turn_id, "threadId": thread_id },
+                    "samplingParams": sampling_params,
+                    "samplingApplied": sampling_applied,
+                    "effort": requested_effort,
+                    "effortApplied": effort_applied,
+                    "redactions": redactions
+                }),
+            );
+            self.start_turn_phase(&thread_id, &turn_id).await;
+        }
+        let mut tracked_session_id = session_id.clone();
+        self.begin_prompt_tracking(&tracked_session_id).await;
+        self.register_active_prompt(&tracked_session_id, &thread_id, &turn_id)
+            .await;
+        let prompt_timeout = self.prompt_timeout();
+        let response = match with_prompt_timeout(
+            prompt_timeout,
+            self.send_acp_request_tagged(
+                "session/prompt",
+                build_prompt_params(
+                    &tracked_session_id,
+                    &agent_prompt,
+                    &image_blocks,
+                    prompt_sampling.as_ref(),
+                    prompt_effort.as_deref(),
+                    prompt_access.as_ref(),
+                ),
+                &request_caller,
+                Some(&thread_id),
+            ),
+        )
+        .await
+        {
+            Ok(result) => {
+                let _ = self.finish_prompt_lifecycle(&tracked_session_id).await;
+                result?
+            }
+            Err(elapsed) => {
+                if !is_background_thread {
+                    self.emit_turn_timeout(&thread_id, &turn_id, elapsed);
+                }
+                self.take_abandoned_request("session/prompt", &thread_id)
+                    .await;
+                let had_streaming = self.finish_prompt_lifecycle(&tracked_session_id).await;
+                if had_streaming {
+                    if !is_background_thread {
+                        self.persist_prompt_items(&thread_id, &turn_id, &tracked_session_id)
+                            .await;
+                        self.thread_store.lock().await.touch_message(&thread_id);
+                        self.emit_latest_thread_token_usage(
+                            &thread_id,
+                            &turn_id,
+                            &tracked_session_id,
+                        );
+                    }
+                    let normalized_turn = json!({
+                        "id": turn_id,
+                        "threadId": thread_id
+                    });
+                    if !is_background_thread {
+                        self.emit_turn_completed(&thread_id, &turn_id, &normalized_turn)
+                            .await;
+                    }
+                    return Ok(json!({
+                        "result": {
+                            "stopReason": "end_turn",
+                            "turn": normalized_turn
+                        }
+                    }));
+                }
+                // Prompt timed out without any streamed output: recreate session once and retry.
+                let new_session = self
+                .create_session_for_cwd(self.entry.path.clone(), access_mode.as_deref())
+                .await?;
+                if is_background_thread {
+                    self.background_threads
+                        .lock()
+                        .await
+                        .insert(thread_id.clone(), new_session.clone());
+                } else {
+                    self.thread_store
+                        .lock()
+                        .await
+                        .set_session_id(&thread_id, new_session.clone());
+                }
+                tracked_session_id = new_session.clone();
+                self.begin_prompt_tracking(&tracked_session_id).await;
+                self.register_active_prompt(&tracked_session_id, &thread_id, &turn_id)
+                    .await;
+                match with_prompt_timeout(
+                    prompt_timeout,
+                    self.send_acp_request_tagged(
+                        "session/prompt",
+                        build_prompt_params(
+                            &new_session,
+                            &agent_prompt,
+                            &image_blocks,
+                            prompt_sampling.as_ref(),
+                            prompt_effort.as_deref(),
+                            prompt_access.as_ref(),
+                        ),
+                        &request_caller,
+                        Some(&thread_id),
+                    ),
+                )
+                .await
+                {
+                    Ok(result) => {
+                        let _ = self.finish_prompt_lifecycle(&tracked_session_id).await;
+                        result?
+                    }
+                    Err(elapsed) => {
+                        if !is_background_thread {
+                            self.emit_turn_timeout(&thread_id, &turn_id, elapsed);
+                        }
+                        let abandoned = self
+                            .take_abandoned_request("session/prompt", &thread_id)
+                            .await;
+                        let had_streaming = self.finish_prompt_lifecycle(&tracked_session_id).await;
+                        if had_streaming {
+                            if !is_background_thread {
+                                self.persist_prompt_items(
+                                    &thread_id,
+                                    &turn_id,
+                                    &tracked_session_id,
+                                )
+                                .await;
+                                self.thread_store.lock().await.touch_message(&thread_id);
+                                self.emit_latest_thread_token_usage(
+                                    &thread_id,
+                                    &turn_id,
+                                    &tracked_session_id,
+                                );
+                            }
+                            let normalized_turn = json!({
+                                "id": turn_id,
+                                "threadId": thread_id
+                            });
+                            if !is_background_thread {
+                                self.emit_turn_completed(&thread_id, &turn_id, &normalized_turn)
+                                    .await;
+                            }
+                            return Ok(json!({
+                                "result": {
+                                    "stopReason": "end_turn",
+                                    "turn": normalized_turn
+                                }
+                            }));
+                        }
+                        return Err(prompt_timeout_error("timeout recovery", abandoned));
+                    }
+                }
+            }
+        };
+        let response = if is_session_not_found_error(&response) {
+            // Session ids are process-local. Recreate once and retry.
+            let new_session = self
+                .create_session_for_cwd(self.entry.path.clone(), access_mode.as_deref())
+                .await?;
+            if is_background_thread {
+                self.background_threads
+                    .lock()
+                    .await
+                    .insert(thread_id.clone(), new_session.clone());
+            } else {
+                self.thread_store
+                    .lock()
+                    .await
+                    .set_session_id(&thread_id, new_session.clone());
+            }
+            tracked_session_id = new_session.clone();
+            self.begin_prompt_tracking(&tracked_session_id).await;
+            self.register_active_prompt(&tracked_session_id, &thread_id, &turn_id)
+                .await;
+            match with_prompt_timeout(
+                prompt_timeout,
+                self.send_acp_request_tagged(
+                    "session/prompt",
+                    build_prompt_params(
+                            &new_session,
+                            &agent_prompt,
+                            &image_blocks,
+                            prompt_sampling.as_ref(),
+                            prompt_effort.as_deref(),
+                            prompt_access.as_ref(),
+                        ),
+                    &request_caller,
+                    Some(&thread_id),
+                ),
+            )
+            .await
+            {
+                Ok(result) => {
+                    let _ = self.finish_prompt_lifecycle(&tracked_session_id).await;
+                    result?
+                }
+                Err(elapsed) => {
+                    if !is_background_thread {
+                        self.emit_turn_timeout(&thread_id, &turn_id, elapsed);
+                    }
+                    let abandoned = self
+                        .take_abandoned_request("session/prompt", &thread_id)
+                        .await;
+                    let had_streaming = self.finish_prompt_lifecycle(&tracked_session_id).await;
+                    if had_streaming {
+                        if !is_background_thread {
+                            self.persist_prompt_items(
+                                &thread_id,
+                                &turn_id,
+                                &tracked_session_id,
+                            )
+                            .await;
+                            self.thread_store.lock().await.touch_message(&thread_id);
+                            self.emit_latest_thread_token_usage(
+                                &thread_id,
+                                &turn_id,
+                                &tracked_session_id,
+                            );
+                        }
+                        let normalized_turn = json!({
+                            "id": turn_id,
+                            "threadId": thread_id
+                        });
+                        if !is_background_thread {
+                            self.emit_turn_completed(&thread_id, &turn_id, &normalized_turn)
+                                .await;
+                        }
+                        return Ok(json!({
+                            "result": {
+                                "stopReason": "end_turn",
+                                "turn": normalized_turn
+                            }
+                        }));
+                    }
+                    return Err(prompt_timeout_error("session recovery", abandoned));
+                }
+            }
+        } else {
+            response
+        };
+        if let Some(error) = acp_error_message(&response) {
+            if is_request_aborted_message(&error) {
+                if !is_background_thread {
+                    self.persist_prompt_items(&thread_id, &turn_id, &tracked_session_id)
+                        .await;
+                    self.thread_store.lock().await.touch_message(&thread_id);
+                    self.emit_latest_thread_token_usage(&thread_id, &turn_id, &tracked_session_id);
+                }
+                let normalized_turn = json!({
+                    "id": turn_id,
+                    "threadId": thread_id
+                });
+                let cancelled_by_user = self.take_user_interrupt(&thread_id);
+                if !is_background_thread {
+                    if cancelled_by_user {
+                        self.emit_turn_cancelled(&thread_id, &turn_id, &normalized_turn)
+                            .await;
+                    } else {
+                        self.emit_turn_completed(&thread_id, &turn_id, &normalized_turn)
+                            .await;
+                    }
+                }
+                return Ok(cancelled_turn_response(
+                    &normalized_turn,
+                    cancelled_by_user.then_some("user"),
+                ));
+            }
+            return Err(normalize_turn_start_error_message(&error, requested_model.as_deref()).into());
+        }
+        if !is_background_thread {
+            self.persist_prompt_items(&thread_id, &turn_id, &tracked_session_id)
+                .await;
+            self.thread_store.lock().await.touch_message(&thread_id);
+            self.emit_latest_thread_token_usage(&thread_id, &turn_id, &tracked_session_id);
+        }
+        let mut normalized_response = response.clone();
+        let normalized_turn = json!({
+            "id": turn_id,
+            "threadId": thread_id
+        });
+        if let Some(result) = normalized_response
+            .get_mut("result")
+            .and_then(Value::as_object_mut)
+        {
+            result
+                .entry("turn".to_string())
+                .or_insert_with(|| normalized_turn.clone());
+        } else {
+            normalized_response = json!({
+                "result": {
+                    "turn": normalized_turn
+                }
+            });
+        }
+        // Agents following ACP end a cancelled prompt normally, with this stop reason.
+        let cancelled_by_user = normalized_response["result"]["stopReason"] == "cancelled"
+            && self.take_user_interrupt(&thread_id);
+        if cancelled_by_user {
+            normalized_response["result"]["cancelledBy"] = json!("user");
+        }
+        if !is_background_thread {
+            if cancelled_by_user {
+                self.emit_turn_cancelled(&thread_id, &turn_id, &normalized_turn)
+                    .await;
+            } else {
+                self.emit_turn_completed(&thread_id, &turn_id, &normalized_turn)
+                    .await;
+            }
+        }
+        Ok(normalized_response)
     }
 
-    pub(crate) async fn send_request(&self, method: &str, params: Value) -> Result<Value, String> {
+    async fn handle_request(&self, method: &str, params: Value) -> Result<Value, CommandError> {
         match method {
             "thread/start" => {
                 let is_background = params
@@ -1818,13 +5694,31 @@ impl WorkspaceSession {
                     .and_then(Value::as_str)
                     .unwrap_or(self.entry.path.as_str())
                     .to_string();
-                let session_id = self.create_session_for_cwd(cwd).await?;
+                let use_primer = is_background
+                    && params
+                        .get("_primer")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false);
+                let kickoff: Option<RunKickoffTemplateRef> = params
+                    .get("_kickoff")
+                    .cloned()
+                    .and_then(|value| serde_json::from_value(value).ok());
+                let thread_id = Uuid::new_v4().to_string();
+                let primed_session = if use_primer {
+                    self.lease_primer_session(&thread_id).await
+                } else {
+                    None
+                };
+                let session_id = match primed_session {
+                    Some(session_id) => session_id,
+                    None => self.create_session_for_cwd(cwd, None).await?,
+                };
                 let thread = if is_background {
-                    let thread_id = Uuid::new_v4().to_string();
                     self.background_threads
                         .lock()
                         .await
                         .insert(thread_id.clone(), session_id);
+                    self.touch_background_thread(&thread_id);
                     LocalThreadRecord {
                         thread_id,
                         session_id: String::new(),
@@ -1832,13 +5726,25 @@ impl WorkspaceSession {
                         archived: true,
                         updated_at: now_ts(),
                         message_index: 0,
+                        tags: Vec::new(),
+                        last_seen_item_seq: None,
+                        kickoff: None,
+                        pinned: false,
+                        archived_at: None,
+                        access_mode: None,
+                        title_source: None,
                     }
                 } else {
-                    self.create_local_thread(session_id).await
+                    let mut thread = self.create_local_thread(session_id).await;
+                    if kickoff.is_some() {
+                        thread.kickoff = kickoff;
+                        self.thread_store.lock().await.upsert(thread.clone());
+                    }
+                    thread
                 };
                 if !is_background {
                     self.emit_event(
-                        "thread/started",
+                        event_methods::THREAD_STARTED,
                         json!({
                             "thread": {
                                 "id": thread.thread_id,
@@ -1858,20 +5764,27 @@ impl WorkspaceSession {
             }
             "thread/list" => {
                 let store = self.thread_store.lock().await;
-                let mut data = store.list_unarchived();
-                data.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
-                let threads = data
+                let threads = store
+                    .list_for_display()
                     .into_iter()
                     .map(|entry| {
+                        let unseen_item_count = store.unseen_items(&entry).len();
+                        let last_item_at = store.last_item_at(&entry.thread_id);
                         json!({
                             "id": entry.thread_id,
                             "name": entry.title,
                             "updatedAt": entry.updated_at,
                             "updated_at": entry.updated_at,
                             "preview": entry.title,
+                            "tags": entry.tags,
+                            "pinned": entry.pinned,
+                            "accessMode": entry.access_mode,
                             "cwd": self.entry.path,
                             "createdAt": entry.updated_at,
-                            "created_at": entry.updated_at
+                            "created_at": entry.updated_at,
+                            "unseenItemCount": unseen_item_count,
+                            "lastItemAt": last_item_at,
+                            "kickoff": entry.kickoff
                         })
                     })
                     .collect::<Vec<_>>();
@@ -1894,480 +5807,431 @@ impl WorkspaceSession {
                     .and_then(Value::as_str)
                     .ok_or_else(|| "missing threadId".to_string())?;
                 let mut thread = self.get_thread_by_id(thread_id).await?;
+                self.resumed_threads
+                    .lock()
+                    .await
+                    .insert(thread.thread_id.clone());
+                // Compared against the session the CLI could have continued, before it is
+                // replaced below.
+                let sync_status = self
+                    .thread_sync_state(thread_id, &thread.session_id)
+                    .await
+                    .map(|(report, _)| report);
                 // ACP has no persistent session/load. Always create a fresh session on resume.
-                let new_session = self.create_session_for_cwd(self.entry.path.clone()).await?;
+                let new_session = self
+                    .create_session_for_cwd(self.entry.path.clone(), thread.access_mode.as_deref())
+                    .await?;
                 self.thread_store
                     .lock()
                     .await
                     .set_session_id(&thread.thread_id, new_session.clone());
                 thread.session_id = new_session;
-                let history_items = self.thread_store.lock().await.load_thread_items(thread_id);
-                let turns = if history_items.is_empty() {
-                    Vec::new()
-                } else {
-                    vec![json!({
-                        "id": format!("turn-history-{}", thread.thread_id),
-                        "items": history_items
-                    })]
+                let (mut history_items, annotations) = {
+                    let store = self.thread_store.lock().await;
+                    (
+                        store.load_thread_items(thread_id),
+                        store.load_annotations(thread_id),
+                    )
                 };
+                // A turn still streaming into this thread is not cut off yet.
+                if self.turn_phases.lock().await.contains_key(thread_id) {
+                    for item in &mut history_items {
+                        if let Some(object) = item.as_object_mut() {
+                            object.remove("partial");
+                        }
+                    }
+                }
+                mark_missing_artifacts(Path::new(&self.entry.path), &mut history_items);
+                attach_review_progress(
+                    Path::new(&self.entry.path),
+                    thread_id,
+                    &mut history_items,
+                    now_ms(),
+                );
+                let turns = history_turns(&thread.thread_id, &history_items);
                 Ok(json!({
                     "result": {
                         "thread": {
                             "id": thread.thread_id,
                             "name": thread.title,
+                            "accessMode": thread.access_mode,
                             "turns": turns
                         },
-                        "items": history_items
+                        "items": history_items,
+                        "annotations": annotations.by_item(),
+                        "syncStatus": sync_status
                     }
                 }))
             }
-            "thread/archive" => {
+            "thread/fork" => {
+                let thread_id = params
+                    .get("threadId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let source = self.get_thread_by_id(thread_id).await?;
+                let session_id = self
+                    .create_session_for_cwd(self.entry.path.clone(), source.access_mode.as_deref())
+                    .await?;
+                let mut fork = self.create_local_thread(session_id).await;
+                fork.title = source.title.clone();
+                fork.tags = source.tags.clone();
+                fork.access_mode = source.access_mode.clone();
+                {
+                    let mut store = self.thread_store.lock().await;
+                    let items: Vec<Value> = store
+                        .load_thread_items(&source.thread_id)
+                        .into_iter()
+                        .map(|mut item| {
+                            if let Some(id) = item.get("id").and_then(Value::as_str) {
+                                let remapped =
+                                    remap_item_id(id, &source.thread_id, &fork.thread_id);
+                                item["id"] = Value::String(remapped);
+                            }
+                            item
+                        })
+                        .collect();
+                    // Forks copy history the user already has in front of them.
+                    fork.last_seen_item_seq = Some(last_item_seq(&items));
+                    store.upsert(fork.clone());
+                    store.persist_thread_items(&fork.thread_id, &items);
+                    let annotations = store
+                        .load_annotations(&source.thread_id)
+                        .remapped_for_fork(&source.thread_id, &fork.thread_id);
+                    store.save_annotations(&fork.thread_id, &annotations)?;
+                }
+                self.emit_event(
+                    event_methods::THREAD_STARTED,
+                    json!({
+                        "thread": {
+                            "id": fork.thread_id,
+                            "name": fork.title
+                        }
+                    }),
+                );
+                Ok(json!({
+                    "result": {
+                        "thread": {
+                            "id": fork.thread_id,
+                            "name": fork.title
+                        }
+                    }
+                }))
+            }
+            "thread/seen/mark" => {
                 let thread_id = params
                     .get("threadId")
                     .and_then(Value::as_str)
                     .ok_or_else(|| "missing threadId".to_string())?;
-                let removed_background = self
-                    .background_threads
+                let seq = params.get("itemSeq").and_then(Value::as_u64);
+                let marker = self
+                    .thread_store
                     .lock()
                     .await
-                    .remove(thread_id)
-                    .is_some();
-                if !removed_background {
-                    self.thread_store.lock().await.delete(thread_id);
-                }
-                Ok(json!({ "result": { "ok": true } }))
+                    .mark_seen(thread_id, seq)
+                    .ok_or_else(|| CommandError::thread_not_found(thread_id))?;
+                Ok(json!({ "result": { "lastSeenItemSeq": marker } }))
             }
-            "thread/name/set" => {
+            "thread/items/unseen" => {
                 let thread_id = params
                     .get("threadId")
                     .and_then(Value::as_str)
                     .ok_or_else(|| "missing threadId".to_string())?;
-                let name = params
-                    .get("name")
+                let thread = self.get_thread_by_id(thread_id).await?;
+                let unseen = self.thread_store.lock().await.unseen_items(&thread);
+                let items = unseen
+                    .into_iter()
+                    .map(|(seq, mut item)| {
+                        if let Some(object) = item.as_object_mut() {
+                            object.insert("itemSeq".to_string(), json!(seq));
+                        }
+                        item
+                    })
+                    .collect::<Vec<_>>();
+                Ok(json!({
+                    "result": {
+                        "lastSeenItemSeq": thread.last_seen_item_seq,
+                        "items": items
+                    }
+                }))
+            }
+            "thread/sync/check" => {
+                let thread_id = params
+                    .get("threadId")
                     .and_then(Value::as_str)
-                    .unwrap_or("New Thread")
-                    .trim()
-                    .to_string();
-                self.thread_store
-                    .lock()
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let thread = self.get_thread_by_id(thread_id).await?;
+                let report = self
+                    .thread_sync_state(thread_id, &thread.session_id)
                     .await
-                    .set_title(thread_id, name.clone());
-                self.emit_event(
-                    "thread/name/updated",
-                    json!({ "threadId": thread_id, "threadName": name }),
-                );
-                Ok(json!({ "result": { "ok": true } }))
+                    .map(|(report, _)| report);
+                Ok(json!({ "result": { "sync": report } }))
             }
-            "thread/compact/start" => Ok(json!({ "result": { "ok": true, "mode": "synthetic" } })),
-            "turn/start" => {
+            "thread/sync/fromCli" => {
                 let thread_id = params
                     .get("threadId")
                     .and_then(Value::as_str)
-                    .ok_or_else(|| "missing threadId".to_string())?
-                    .to_string();
-                let background_session = {
-                    let background_threads = self.background_threads.lock().await;
-                    background_threads.get(&thread_id).cloned()
-                };
-                let is_background_thread = self
-                    .background_thread_callbacks
-                    .lock()
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let thread = self.get_thread_by_id(thread_id).await?;
+                let (report, cli) = self
+                    .thread_sync_state(thread_id, &thread.session_id)
                     .await
-                    .contains_key(&thread_id)
-                    || background_session.is_some();
-                let thread = if background_session.is_none() {
-                    Some(self.get_thread_by_id(&thread_id).await?)
-                } else {
-                    None
-                };
-                let prompt_text = Self::parse_prompt_from_turn_start(&params);
-                if prompt_text.is_empty() {
-                    return Err("empty user message".to_string());
+                    .ok_or_else(|| format!("no MiCode chat file for thread {thread_id}"))?;
+                if report.status != SyncStatus::CliAhead {
+                    return Ok(json!({ "result": { "imported": 0, "sync": report } }));
                 }
-                if !is_background_thread {
-                    if let Some(thread_entry) = thread.as_ref() {
-                        if thread_entry.title.trim().eq_ignore_ascii_case("new thread") {
-                            if let Some(title) = derive_thread_title(&prompt_text) {
-                                self.thread_store
-                                    .lock()
-                                    .await
-                                    .set_title(&thread_id, title.clone());
-                                self.emit_event(
-                                    "thread/name/updated",
-                                    json!({
-                                        "threadId": thread_id,
-                                        "threadName": title
-                                    }),
-                                );
-                            }
-                        }
+                let items = imported_items(thread_id, &cli, &report);
+                {
+                    let store = self.thread_store.lock().await;
+                    for item in items.iter().cloned() {
+                        store.upsert_thread_item(thread_id, item);
                     }
                 }
-                let mut session_id = background_session
-                    .clone()
-                    .or_else(|| thread.as_ref().map(|entry| entry.session_id.clone()))
-                    .unwrap_or_default();
-                let requested_model = params
-                    .get("model")
+                let report = self
+                    .thread_sync_state(thread_id, &thread.session_id)
+                    .await
+                    .map(|(report, _)| report);
+                Ok(json!({ "result": { "imported": items.len(), "sync": report } }))
+            }
+            "thread/transcript/copy" => {
+                let thread_id = params
+                    .get("threadId")
                     .and_then(Value::as_str)
-                    .map(str::trim)
-                    .filter(|value| !value.is_empty())
-                    .map(ToString::to_string);
-                let requested_model_for_error = requested_model.clone();
-                if let Some(requested_model) = requested_model {
-                    if let Ok(changed) = set_preferred_model(&requested_model) {
-                        if changed {
-                            let fresh_session =
-                                self.create_session_for_cwd(self.entry.path.clone()).await?;
-                            if is_background_thread {
-                                self.background_threads
-                                    .lock()
-                                    .await
-                                    .insert(thread_id.clone(), fresh_session.clone());
-                            } else {
-                                self.thread_store
-                                    .lock()
-                                    .await
-                                    .set_session_id(&thread_id, fresh_session.clone());
-                            }
-                            session_id = fresh_session;
-                        }
-                    }
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let format = TranscriptFormat::parse(params.get("format").and_then(Value::as_str))?;
+                self.get_thread_by_id(thread_id).await?;
+                let items = self.thread_store.lock().await.load_thread_items(thread_id);
+                let text = render_transcript(
+                    &items,
+                    params.get("startItemId").and_then(Value::as_str),
+                    params.get("endItemId").and_then(Value::as_str),
+                    format,
+                )?;
+                Ok(json!({ "result": { "text": text } }))
+            }
+            "thread/annotations/list" => {
+                let thread_id = params
+                    .get("threadId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let annotations = self.thread_store.lock().await.load_annotations(thread_id);
+                Ok(json!({ "result": { "annotations": annotations.annotations } }))
+            }
+            "thread/annotations/add" => {
+                let thread_id = params
+                    .get("threadId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let item_id = params
+                    .get("itemId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing itemId".to_string())?;
+                let text = params.get("text").and_then(Value::as_str).unwrap_or("");
+                let author = params.get("author").and_then(Value::as_str).unwrap_or("");
+                self.get_thread_by_id(thread_id).await?;
+                let store = self.thread_store.lock().await;
+                let mut annotations = store.load_annotations(thread_id);
+                let annotation = annotations.add(item_id, text, author, now_ts())?;
+                store.save_annotations(thread_id, &annotations)?;
+                Ok(json!({ "result": { "annotation": annotation } }))
+            }
+            "thread/annotations/update" => {
+                let thread_id = params
+                    .get("threadId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let annotation_id = params
+                    .get("annotationId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing annotationId".to_string())?;
+                let text = params.get("text").and_then(Value::as_str).unwrap_or("");
+                let store = self.thread_store.lock().await;
+                let mut annotations = store.load_annotations(thread_id);
+                let annotation = annotations.update(annotation_id, text, now_ts())?;
+                store.save_annotations(thread_id, &annotations)?;
+                Ok(json!({ "result": { "annotation": annotation } }))
+            }
+            "thread/annotations/delete" => {
+                let thread_id = params
+                    .get("threadId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let annotation_id = params
+                    .get("annotationId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing annotationId".to_string())?;
+                let store = self.thread_store.lock().await;
+                let mut annotations = store.load_annotations(thread_id);
+                if !annotations.delete(annotation_id) {
+                    return Err(format!("annotation not found: {annotation_id}").into());
                 }
-                if session_id.trim().is_empty() {
-                    // Some migrated/local records may have an empty session id.
-                    // Recreate proactively to avoid one failed prompt + retry roundtrip.
-                    let fresh_session = self.create_session_for_cwd(self.entry.path.clone()).await?;
-                    if is_background_thread {
-                        self.background_threads
-                            .lock()
-                            .await
-                            .insert(thread_id.clone(), fresh_session.clone());
-                    } else {
-                        self.thread_store
-                            .lock()
-                            .await
-                            .set_session_id(&thread_id, fresh_session.clone());
+                store.save_annotations(thread_id, &annotations)?;
+                Ok(json!({ "result": { "ok": true } }))
+            }
+            "thread/archive" => {
+                let thread_id = params
+                    .get("threadId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let force = params
+                    .get("force")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false);
+                self.thread_store
+                    .lock()
+                    .await
+                    .check_archivable(thread_id, force)?;
+                let removed_background = self.background_threads.lock().await.remove(thread_id);
+                if removed_background.is_some() {
+                    if let Ok(mut activity) = self.background_activity.lock() {
+                        activity.forget(thread_id);
                     }
-                    session_id = fresh_session;
+                    self.release_primer_session(thread_id).await;
+                } else {
+                    self.thread_store.lock().await.archive(thread_id, now_ts());
+                    self.resumed_threads.lock().await.remove(thread_id);
                 }
-                let turn_id = Uuid::new_v4().to_string();
-                if !is_background_thread {
-                    self.persist_thread_item(
-                        &thread_id,
-                        build_user_thread_item(&thread_id, &turn_id, &prompt_text),
-                    )
-                    .await;
-                    self.emit_event(
-                        "turn/started",
+                Ok(json!({ "result": { "ok": true } }))
+            }
+            "thread/archived/list" => {
+                let threads = self
+                    .thread_store
+                    .lock()
+                    .await
+                    .list_archived()
+                    .into_iter()
+                    .map(|entry| {
                         json!({
-                            "threadId": thread_id,
-                            "turn": { "id": turn_id, "threadId": thread_id }
-                        }),
-                    );
+                            "id": entry.thread_id,
+                            "name": entry.title,
+                            "updatedAt": entry.updated_at,
+                            "archivedAt": entry.archived_since(),
+                            "tags": entry.tags,
+                            "pinned": entry.pinned,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                Ok(json!({ "result": { "data": threads } }))
+            }
+            "thread/restore" => {
+                let thread_id = params
+                    .get("threadId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let mut store = self.thread_store.lock().await;
+                if !store.restore(thread_id) {
+                    return Err(CommandError::thread_not_found(thread_id));
                 }
-                let mut tracked_session_id = session_id.clone();
-                self.begin_prompt_tracking(&tracked_session_id).await;
-                self.register_active_prompt(&tracked_session_id, &thread_id, &turn_id)
-                    .await;
-                let response = match timeout(
-                    TURN_START_TIMEOUT,
-                    self.send_acp_request(
-                        "session/prompt",
-                        json!({
-                            "sessionId": tracked_session_id,
-                            "prompt": [{ "type": "text", "text": prompt_text }]
-                        }),
-                    ),
-                )
-                .await
-                {
-                    Ok(result) => {
-                        let _ = self.finish_prompt_lifecycle(&tracked_session_id).await;
-                        result?
-                    }
-                    Err(_) => {
-                        let had_streaming = self.finish_prompt_lifecycle(&tracked_session_id).await;
-                        if had_streaming {
-                            if !is_background_thread {
-                                self.persist_prompt_agent_item(
-                                    &thread_id,
-                                    &turn_id,
-                                    &tracked_session_id,
-                                )
-                                .await;
-                                self.thread_store.lock().await.touch_message(&thread_id);
-                                self.emit_latest_thread_token_usage(
-                                    &thread_id,
-                                    &turn_id,
-                                    &tracked_session_id,
-                                )
-                                    .await;
-                            }
-                            let normalized_turn = json!({
-                                "id": turn_id,
-                                "threadId": thread_id
-                            });
-                            if !is_background_thread {
-                                self.emit_event(
-                                    "turn/completed",
-                                    json!({
-                                        "threadId": thread_id,
-                                        "turn": normalized_turn
-                                    }),
-                                );
-                            }
-                            return Ok(json!({
-                                "result": {
-                                    "stopReason": "end_turn",
-                                    "turn": normalized_turn
-                                }
-                            }));
-                        }
-                        // Prompt timed out without any streamed output: recreate session once and retry.
-                        let new_session = self.create_session_for_cwd(self.entry.path.clone()).await?;
-                        if is_background_thread {
-                            self.background_threads
-                                .lock()
-                                .await
-                                .insert(thread_id.clone(), new_session.clone());
-                        } else {
-                            self.thread_store
-                                .lock()
-                                .await
-                                .set_session_id(&thread_id, new_session.clone());
-                        }
-                        tracked_session_id = new_session.clone();
-                        self.begin_prompt_tracking(&tracked_session_id).await;
-                        self.register_active_prompt(&tracked_session_id, &thread_id, &turn_id)
-                            .await;
-                        match timeout(
-                            TURN_START_TIMEOUT,
-                            self.send_acp_request(
-                                "session/prompt",
-                                json!({
-                                    "sessionId": new_session,
-                                    "prompt": [{ "type": "text", "text": prompt_text }]
-                                }),
-                            ),
-                        )
-                        .await
-                        {
-                            Ok(result) => {
-                                let _ = self.finish_prompt_lifecycle(&tracked_session_id).await;
-                                result?
-                            }
-                            Err(_) => {
-                                let had_streaming =
-                                    self.finish_prompt_lifecycle(&tracked_session_id).await;
-                                if had_streaming {
-                                    if !is_background_thread {
-                                        self.persist_prompt_agent_item(
-                                            &thread_id,
-                                            &turn_id,
-                                            &tracked_session_id,
-                                        )
-                                        .await;
-                                        self.thread_store.lock().await.touch_message(&thread_id);
-                                        self.emit_latest_thread_token_usage(
-                                            &thread_id,
-                                            &turn_id,
-                                            &tracked_session_id,
-                                        )
-                                        .await;
-                                    }
-                                    let normalized_turn = json!({
-                                        "id": turn_id,
-                                        "threadId": thread_id
-                                    });
-                                    if !is_background_thread {
-                                        self.emit_event(
-                                            "turn/completed",
-                                            json!({
-                                                "threadId": thread_id,
-                                                "turn": normalized_turn
-                                            }),
-                                        );
-                                    }
-                                    return Ok(json!({
-                                        "result": {
-                                            "stopReason": "end_turn",
-                                            "turn": normalized_turn
-                                        }
-                                    }));
-                                }
-                                return Err(
-                                    "turn/start timed out waiting for MiCode response after timeout recovery"
-                                        .to_string(),
-                                );
-                            }
-                        }
-                    }
-                };
-                let response = if is_session_not_found_error(&response) {
-                    // Session ids are process-local. Recreate once and retry.
-                    let new_session = self.create_session_for_cwd(self.entry.path.clone()).await?;
-                    if is_background_thread {
-                        self.background_threads
-                            .lock()
-                            .await
-                            .insert(thread_id.clone(), new_session.clone());
-                    } else {
-                        self.thread_store
-                            .lock()
-                            .await
-                            .set_session_id(&thread_id, new_session.clone());
-                    }
-                    tracked_session_id = new_session.clone();
-                    self.begin_prompt_tracking(&tracked_session_id).await;
-                    self.register_active_prompt(&tracked_session_id, &thread_id, &turn_id)
-                        .await;
-                    match timeout(
-                        TURN_START_TIMEOUT,
-                        self.send_acp_request(
-                            "session/prompt",
-                            json!({
-                                "sessionId": new_session,
-                                "prompt": [{ "type": "text", "text": prompt_text }]
-                            }),
-                        ),
-                    )
-                    .await
+                let thread = store.by_thread_id(thread_id);
+                drop(store);
+                let name = thread.map(|thread| thread.title).unwrap_or_default();
+                Ok(json!({ "result": { "thread": { "id": thread_id, "name": name } } }))
+            }
+            "thread/delete" => {
+                let thread_id = params
+                    .get("threadId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let force = params
+                    .get("force")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false);
+                let mut store = self.thread_store.lock().await;
+                store.check_archivable(thread_id, force)?;
+                if !store.delete(thread_id) {
+                    return Err(CommandError::thread_not_found(thread_id));
+                }
+                drop(store);
+                self.resumed_threads.lock().await.remove(thread_id);
+                Ok(json!({ "result": { "ok": true } }))
+            }
+            "thread/tags/set" => {
+                let thread_id = params
+                    .get("threadId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let mut tags: Vec<String> = Vec::new();
+                for tag in params
+                    .get("tags")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                {
+                    if !tags
+                        .iter()
+                        .any(|existing| existing.eq_ignore_ascii_case(tag))
                     {
-                        Ok(result) => {
-                            let _ = self.finish_prompt_lifecycle(&tracked_session_id).await;
-                            result?
-                        }
-                        Err(_) => {
-                            let had_streaming =
-                                self.finish_prompt_lifecycle(&tracked_session_id).await;
-                            if had_streaming {
-                                if !is_background_thread {
-                                    self.persist_prompt_agent_item(
-                                        &thread_id,
-                                        &turn_id,
-                                        &tracked_session_id,
-                                    )
-                                    .await;
-                                    self.thread_store.lock().await.touch_message(&thread_id);
-                                    self.emit_latest_thread_token_usage(
-                                        &thread_id,
-                                        &turn_id,
-                                        &tracked_session_id,
-                                    )
-                                        .await;
-                                }
-                                let normalized_turn = json!({
-                                    "id": turn_id,
-                                    "threadId": thread_id
-                                });
-                                if !is_background_thread {
-                                    self.emit_event(
-                                        "turn/completed",
-                                        json!({
-                                            "threadId": thread_id,
-                                            "turn": normalized_turn
-                                        }),
-                                    );
-                                }
-                                return Ok(json!({
-                                    "result": {
-                                        "stopReason": "end_turn",
-                                        "turn": normalized_turn
-                                    }
-                                }));
-                            }
-                            return Err(
-                                "turn/start timed out waiting for MiCode response after session recovery"
-                                    .to_string(),
-                            );
-                        }
-                    }
-                } else {
-                    response
-                };
-                if let Some(error) = acp_error_message(&response) {
-                    if is_request_aborted_message(&error) {
-                        if !is_background_thread {
-                            self.persist_prompt_agent_item(
-                                &thread_id,
-                                &turn_id,
-                                &tracked_session_id,
-                            )
-                            .await;
-                            self.thread_store.lock().await.touch_message(&thread_id);
-                            self.emit_latest_thread_token_usage(
-                                &thread_id,
-                                &turn_id,
-                                &tracked_session_id,
-                            )
-                                .await;
-                        }
-                        let normalized_turn = json!({
-                            "id": turn_id,
-                            "threadId": thread_id
-                        });
-                        if !is_background_thread {
-                            self.emit_event(
-                                "turn/completed",
-                                json!({
-                                    "threadId": thread_id,
-                                    "turn": normalized_turn
-                                }),
-                            );
-                        }
-                        return Ok(json!({
-                            "result": {
-                                "stopReason": "cancelled",
-                                "turn": normalized_turn
-                            }
-                        }));
+                        tags.push(tag.to_string());
                     }
-                    return Err(normalize_turn_start_error_message(
-                        &error,
-                        requested_model_for_error.as_deref(),
-                    ));
                 }
-                if !is_background_thread {
-                    self.persist_prompt_agent_item(&thread_id, &turn_id, &tracked_session_id)
-                        .await;
-                    self.thread_store.lock().await.touch_message(&thread_id);
-                    self.emit_latest_thread_token_usage(
-                        &thread_id,
-                        &turn_id,
-                        &tracked_session_id,
-                    )
-                        .await;
+                if !self
+                    .thread_store
+                    .lock()
+                    .await
+                    .set_tags(thread_id, tags.clone())
+                {
+                    return Err(CommandError::thread_not_found(thread_id));
                 }
-                let mut normalized_response = response.clone();
-                let normalized_turn = json!({
-                    "id": turn_id,
-                    "threadId": thread_id
-                });
-                if let Some(result) = normalized_response
-                    .get_mut("result")
-                    .and_then(Value::as_object_mut)
+                Ok(json!({ "result": { "ok": true, "tags": tags } }))
+            }
+            "thread/pinned/set" => {
+                let thread_id = params
+                    .get("threadId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let pinned = params
+                    .get("pinned")
+                    .and_then(Value::as_bool)
+                    .ok_or_else(|| "missing pinned".to_string())?;
+                if !self
+                    .thread_store
+                    .lock()
+                    .await
+                    .set_pinned(thread_id, pinned)
                 {
-                    result
-                        .entry("turn".to_string())
-                        .or_insert_with(|| normalized_turn.clone());
-                } else {
-                    normalized_response = json!({
-                        "result": {
-                            "turn": normalized_turn
-                        }
-                    });
+                    return Err(CommandError::thread_not_found(thread_id));
                 }
-                if !is_background_thread {
-                    self.emit_event(
-                        "turn/completed",
-                        json!({
-                            "threadId": thread_id,
-                            "turn": normalized_turn
-                        }),
-                    );
+                Ok(json!({ "result": { "ok": true, "pinned": pinned } }))
+            }
+            "thread/name/set" => {
+                let thread_id = params
+                    .get("threadId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "missing threadId".to_string())?;
+                let name = params
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or("New Thread")
+                    .trim()
+                    .to_string();
+                // `_generated` names come from the background auto-title and must not
+                // replace a name the user picked in the meantime.
+                let source = if params.get("_generated").and_then(Value::as_bool) == Some(true) {
+                    ThreadTitleSource::Generated
+                } else {
+                    ThreadTitleSource::User
+                };
+                let updated = self
+                    .thread_store
+                    .lock()
+                    .await
+                    .rename_thread(thread_id, name, source);
+                let applied = updated.is_some();
+                if let Some(params) = updated {
+                    self.emit_event(event_methods::THREAD_NAME_UPDATED, params);
                 }
-                Ok(normalized_response)
+                Ok(json!({ "result": { "ok": true, "applied": applied } }))
+            }
+            "thread/compact/start" => Ok(json!({ "result": { "ok": true, "mode": "synthetic" } })),
+            "turn/start" => {
+                let thread_id = params
+                    .get("threadId")
+                    .and_then(Value::as_str)
+                    .map(ToString::to_string);
+                let _in_flight = self.begin_turn_in_flight(thread_id);
+                self.start_turn(params).await
             }
             "turn/interrupt" => {
                 let thread_id = params
@@ -2383,58 +6247,156 @@ impl WorkspaceSession {
                 } else {
                     self.get_thread_by_id(thread_id).await?.session_id
                 };
-                let response = self
-                    .send_acp_request("session/cancel", json!({ "sessionId": thread_session }))
-                    .await?;
+                self.clear_turn_queue(thread_id, "interrupted");
+                self.cancel_thread_approvals(thread_id).await;
+                let turn_id = params
+                    .get("turnId")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                self.record_user_interrupt(thread_id, turn_id);
+                let response = match self
+                    .send_acp_request_tagged(
+                        "session/cancel",
+                        json!({ "sessionId": thread_session }),
+                        "interrupt",
+                        Some(thread_id),
+                    )
+                    .await
+                {
+                    Ok(response) => response,
+                    Err(error) => {
+                        self.take_user_interrupt(thread_id);
+                        return Err(error.into());
+                    }
+                };
                 if let Some(error) = acp_error_message(&response) {
+                    self.take_user_interrupt(thread_id);
                     if is_not_generating_message(&error) {
                         return Ok(json!({ "result": null }));
                     }
-                    return Err(format!("turn/interrupt failed: {error}"));
+                    return Err(format!("turn/interrupt failed: {error}").into());
                 }
                 Ok(response)
             }
             "model/list" => {
-                let preferred = read_preferred_model();
-                let mut models = discover_micode_models(self.entry.agent_bin.as_deref());
+                let preferred = self.model();
+                let settings_path = micode_settings_path(self.isolated_home.as_deref());
+                let (custom_models, warnings) = settings_path
+                    .as_deref()
+                    .and_then(|path| read_settings_file(path, &self.settings_parse_errors))
+                    .map(|root| parse_custom_models(&root))
+                    .unwrap_or_default();
+                for warning in warnings {
+                    self.emit_event(
+                        event_methods::MICODE_MODEL_PARSE_WARNING,
+                        json!({
+                            "path": settings_path.as_ref().map(|path| path.display().to_string()),
+                            "key": warning.key,
+                            "index": warning.index,
+                            "message": warning.message
+                        }),
+                    );
+                }
+                let mut models = merge_models(
+                    discover_micode_models(
+                        self.entry.agent_bin.as_deref(),
+                        params
+                            .get("refresh")
+                            .and_then(Value::as_bool)
+                            .unwrap_or(false),
+                    ),
+                    custom_models,
+                );
                 if models.is_empty() {
-                    models.push((
-                        "auto".to_string(),
-                        "MiCode Auto".to_string(),
-                        "Use MiCode default model from local configuration".to_string(),
-                    ));
+                    models.push(CliModel {
+                        id: "auto".to_string(),
+                        label: "MiCode Auto".to_string(),
+                        description: "Use MiCode default model from local configuration"
+                            .to_string(),
+                        reasoning_efforts: Vec::new(),
+                        default_reasoning_effort: None,
+                        source: "bundle",
+                    });
                 }
                 let has_preferred = preferred
                     .as_ref()
-                    .map(|pref| models.iter().any(|(id, _, _)| id == pref))
+                    .map(|pref| models.iter().any(|model| model.id == *pref))
                     .unwrap_or(false);
                 let data = models
                     .into_iter()
                     .enumerate()
-                    .map(|(index, (id, label, description))| {
-                        let model_id = id.clone();
+                    .map(|(index, model)| {
                         let is_default = if let Some(pref) = preferred.as_ref() {
-                            id == *pref
+                            model.id == *pref
                         } else {
                             index == 0
                         };
+                        let efforts = model
+                            .reasoning_efforts
+                            .iter()
+                            .map(|effort| {
+                                json!({
+                                    "reasoningEffort": effort,
+                                    "description": reasoning_effort_description(effort)
+                                })
+                            })
+                            .collect::<Vec<_>>();
                         json!({
-                            "id": id,
-                            "model": model_id,
-                            "displayName": label,
-                            "description": description,
-                            "supportedReasoningEfforts": [],
-                            "defaultReasoningEffort": null,
+                            "id": model.id,
+                            "model": model.id,
+                            "displayName": model.label,
+                            "description": model.description,
+                            "supportedReasoningEfforts": efforts,
+                            "defaultReasoningEffort": model.default_reasoning_effort,
+                            "source": model.source,
                             "isDefault": if has_preferred { is_default } else { index == 0 }
                         })
                     })
                     .collect::<Vec<_>>();
                 Ok(json!({ "result": { "data": data } }))
             }
+            "mcpServer/list" | "mcpServerStatus/list" => {
+                let servers = micode_settings_path(self.isolated_home.as_deref())
+                    .as_deref()
+                    .and_then(|path| read_settings_file(path, &self.settings_parse_errors))
+                    .map(|root| parse_mcp_servers(&root))
+                    .unwrap_or_default();
+                let now = now_ms();
+                let cached = {
+                    let cache = self.mcp_probes.lock().await;
+                    servers
+                        .iter()
+                        .map(|server| cache.get(server, now))
+                        .collect::<Vec<_>>()
+                };
+                let probes = join_all(servers.iter().zip(cached).map(|(server, cached)| async move {
+                    match cached {
+                        Some(probe) => (probe, false),
+                        None => (probe_mcp_server(server, now).await, true),
+                    }
+                }))
+                .await;
+                let mut cache = self.mcp_probes.lock().await;
+                let data = servers
+                    .into_iter()
+                    .zip(probes)
+                    .map(|(server, (probe, fresh))| {
+                        let value = server.to_status_value(&probe);
+                        if fresh {
+                            cache.insert(server, probe);
+                        }
+                        value
+                    })
+                    .collect::<Vec<_>>();
+                Ok(json!({ "result": { "data": data, "nextCursor": null } }))
+            }
             "account/read" => {
-                let auth_mode = read_selected_auth_mode()
-                    .unwrap_or_else(|| "unknown".to_string())
-                    .to_ascii_lowercase();
+                let auth_mode = read_selected_auth_mode(
+                    self.isolated_home.as_deref(),
+                    &self.settings_parse_errors,
+                )
+                .unwrap_or_else(|| "unknown".to_string())
+                .to_ascii_lowercase();
                 let account_type = if auth_mode == "openai" {
                     "apikey"
                 } else if auth_mode == "qwen-oauth" {
@@ -2455,6 +6417,16 @@ impl WorkspaceSession {
             "account/rateLimits/read" => {
                 Ok(json!({ "result": { "source": "synthetic", "limits": [] } }))
             }
+            "skills/list" => {
+                let workspace = params
+                    .get("cwd")
+                    .and_then(Value::as_str)
+                    .filter(|cwd| !cwd.trim().is_empty())
+                    .unwrap_or(&self.entry.path);
+                let home = resolve_micode_home_path(self.isolated_home.as_deref());
+                let listing = list_skills(home.as_deref(), Path::new(workspace));
+                Ok(json!({ "result": listing }))
+            }
             "app/list" => {
                 Ok(json!({ "result": { "apps": [], "hasMore": false, "nextCursor": null } }))
             }
@@ -2469,7 +6441,7 @@ impl WorkspaceSession {
                     ]
                 }
             })),
-            _ => self.send_acp_request(method, params).await,
+            _ => Ok(self.send_acp_request(method, params).await?),
         }
     }
 
@@ -2488,65 +6460,80 @@ impl WorkspaceSession {
     }
 
     pub(crate) async fn send_response(&self, id: Value, result: Value) -> Result<(), String> {
-        let id_key = id
-            .as_i64()
-            .map(|v| v.to_string())
-            .unwrap_or_else(|| id.to_string());
-        let original = self.approval_requests.lock().await.remove(&id_key);
-        if let Some(original) = original {
-            let options = original
-                .get("options")
-                .and_then(Value::as_array)
-                .cloned()
-                .unwrap_or_default();
-            let decision = result
-                .get("decision")
-                .and_then(Value::as_str)
-                .unwrap_or("decline");
-            let explicit_option_id = result
-                .get("optionId")
-                .and_then(Value::as_str)
-                .map(|value| value.to_string());
-            let preferred = match decision {
-                "accept_always" => vec!["allow_always"],
-                "accept_once" => vec!["allow_once"],
-                "decline_always" => vec!["reject_always"],
-                "decline_once" => vec!["reject_once"],
-                "accept" => vec!["allow_once", "allow_always"],
-                _ => vec!["reject_once", "reject_always"],
-            };
-            let option_id = preferred
-                .into_iter()
-                .find_map(|kind| {
-                    options.iter().find_map(|opt| {
-                        if opt.get("kind").and_then(Value::as_str) == Some(kind) {
-                            opt.get("optionId")
-                                .and_then(Value::as_str)
-                                .map(|v| v.to_string())
-                        } else {
-                            None
-                        }
-                    })
-                })
-                .or(explicit_option_id)
-                .or_else(|| {
-                    options.iter().find_map(|opt| {
-                        opt.get("optionId")
-                            .and_then(Value::as_str)
-                            .map(|v| v.to_string())
-                    })
-                });
-            let mapped = if let Some(option_id) = option_id {
-                json!({ "outcome": { "outcome": "selected", "optionId": option_id } })
-            } else {
-                json!({ "outcome": { "outcome": "cancelled" } })
-            };
+        let pending = self.approvals.lock().await.take(&id);
+        let Some(pending) = pending else {
             return self
-                .write_message(json!({ "jsonrpc": "2.0", "id": id, "result": mapped }))
+                .write_message(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
                 .await;
+        };
+        self.answer_approval(pending, result).await
+    }
+
+    /// Answers every request of `pending` with the option `result` maps to and tells
+    /// clients the dialog is gone.
+    async fn answer_approval(&self, pending: PendingApproval, result: Value) -> Result<(), String> {
+        let mapped = approval_response(&pending.params, &result);
+        for request_id in pending.request_ids() {
+            self.write_message(json!({ "jsonrpc": "2.0", "id": request_id, "result": mapped }))
+                .await?;
+        }
+        let decision = result
+            .get("decision")
+            .and_then(Value::as_str)
+            .unwrap_or("decline");
+        self.emit_event(
+            event_methods::WORKSPACE_APPROVAL_RESOLVED,
+            approval_resolved_params(&pending, decision, ApprovalResolvedBy::from_result(&result)),
+        );
+        Ok(())
+    }
+
+    /// Reminds clients of approvals waiting for half of `approvalTimeoutSecs` and answers
+    /// the ones past it with the cancelled outcome, so the turn does not block forever.
+    async fn sweep_pending_approvals(&self) {
+        let timeout_secs = self.session_settings().approval_timeout_secs;
+        if timeout_secs == 0 {
+            return;
+        }
+        let timeout = Duration::from_secs(timeout_secs);
+        let now = std::time::Instant::now();
+        let sweep = self.approvals.lock().await.sweep(now, timeout);
+        for pending in &sweep.reminders {
+            self.emit_event(
+                event_methods::WORKSPACE_APPROVAL_REMINDER,
+                approval_reminder_params(pending, now, timeout),
+            );
+        }
+        for pending in sweep.expired {
+            self.emit_event(
+                event_methods::WORKSPACE_APPROVAL_TIMED_OUT,
+                approval_timed_out_params(&pending, timeout),
+            );
+            if let Err(err) = self.answer_approval(pending, timed_out_result()).await {
+                eprintln!("failed to cancel timed out approval: {err}");
+            }
+        }
+    }
+
+    /// Answers the thread's outstanding approvals with the cancelled outcome, as ACP
+    /// requires once its prompt turn is cancelled, and tells clients to drop the dialogs.
+    async fn cancel_thread_approvals(&self, thread_id: &str) {
+        let cancelled = self.approvals.lock().await.take_for_thread(thread_id);
+        for pending in cancelled {
+            for request_id in pending.request_ids() {
+                let _ = self
+                    .write_message(json!({
+                        "jsonrpc": "2.0",
+                        "id": request_id,
+                        "result": cancelled_response()
+                    }))
+                    .await;
+            }
+            self.emit_event(
+                event_methods::WORKSPACE_APPROVAL_RESOLVED,
+                approval_resolved_params(&pending, "cancel", ApprovalResolvedBy::TurnCancelled),
+            );
         }
-        self.write_message(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
-            .await
     }
 }
 
@@ -2669,7 +6656,7 @@ pub(crate) fn build_micode_command_with_bin(agent_bin: Option<String>) -> Comman
 
 pub(crate) async fn check_micode_installation(
     agent_bin: Option<String>,
-) -> Result<Option<String>, String> {
+) -> Result<Option<String>, CommandError> {
     let mut command = build_micode_command_with_bin(agent_bin);
     command.arg("--version");
     command.stdout(std::process::Stdio::piped());
@@ -2678,23 +6665,23 @@ pub(crate) async fn check_micode_installation(
     let output = match timeout(Duration::from_secs(5), command.output()).await {
         Ok(result) => result.map_err(|e| {
             if e.kind() == ErrorKind::NotFound {
-                if cfg!(windows) {
-                    "MiCode CLI not found. Install with: powershell -ExecutionPolicy Bypass -Command \"iwr -useb https://cnbj1-fds.api.xiaomi.net/mi-code-public/install.ps1 | iex\". After install, run `micode.cmd --version` (or set PowerShell policy with `Set-ExecutionPolicy RemoteSigned`).".to_string()
+                let message = if cfg!(windows) {
+                    "MiCode CLI not found. Install with: powershell -ExecutionPolicy Bypass -Command \"iwr -useb https://cnbj1-fds.api.xiaomi.net/mi-code-public/install.ps1 | iex\". After install, run `micode.cmd --version` (or set PowerShell policy with `Set-ExecutionPolicy RemoteSigned`)."
                 } else {
                     "MiCode CLI not found. Install micode and ensure `micode` is on your PATH."
-                        .to_string()
-                }
+                };
+                CommandError::new(ErrorCode::MicodeBinMissing, message)
             } else {
-                e.to_string()
+                CommandError::from(e.to_string())
             }
         })?,
         Err(_) => {
-            return Err(if cfg!(windows) {
+            return Err(CommandError::from(if cfg!(windows) {
                 "Timed out while checking MiCode CLI. Run `micode.cmd --version` in Terminal.".to_string()
             } else {
                 "Timed out while checking MiCode CLI. Make sure `micode --version` runs in Terminal."
                     .to_string()
-            });
+            }));
         }
     };
 
@@ -2706,7 +6693,7 @@ pub(crate) async fn check_micode_installation(
         } else {
             stderr.trim()
         };
-        return Err(if detail.is_empty() {
+        return Err(CommandError::from(if detail.is_empty() {
             if cfg!(windows) {
                 "MiCode CLI failed to start. Try `micode.cmd --version`. If PowerShell blocks scripts, run `Set-ExecutionPolicy RemoteSigned`.".to_string()
             } else {
@@ -2714,7 +6701,7 @@ pub(crate) async fn check_micode_installation(
             }
         } else {
             format!("MiCode CLI failed to start: {detail}")
-        });
+        }));
     }
 
     let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
@@ -2725,12 +6712,13 @@ pub(crate) async fn check_micode_installation(
     })
 }
 
+/// Spawns the CLI in ACP mode and times a single `initialize` round trip.
 pub(crate) async fn check_acp_handshake(
     agent_bin: Option<String>,
     agent_args: Option<String>,
-) -> Result<bool, String> {
+) -> Result<HandshakeProbe, String> {
     let mut command = build_micode_command_with_bin(agent_bin);
-    apply_micode_args(&mut command, agent_args.as_deref())?;
+    apply_micode_args(&mut command, agent_args.as_deref()).map_err(|err| err.to_string())?;
     command.arg("--experimental-acp");
     command.stdin(std::process::Stdio::piped());
     command.stdout(std::process::Stdio::piped());
@@ -2751,13 +6739,19 @@ pub(crate) async fn check_acp_handshake(
     });
     let mut line = serde_json::to_string(&init).map_err(|e| e.to_string())?;
     line.push('\n');
+    let started = std::time::Instant::now();
     stdin
         .write_all(line.as_bytes())
         .await
         .map_err(|e| e.to_string())?;
     let mut lines = BufReader::new(stdout).lines();
-    let deadline = std::time::Instant::now() + Duration::from_secs(60);
-    let mut ok = false;
+    let deadline = started + Duration::from_secs(60);
+    let mut probe = HandshakeProbe {
+        ok: false,
+        latency_ms: None,
+        protocol_version: None,
+        checked_at_ms: now_ms(),
+    };
     loop {
         let now = std::time::Instant::now();
         if now >= deadline {
@@ -2781,7 +6775,9 @@ pub(crate) async fn check_acp_handshake(
                         continue;
                     }
                     if value.get("result").is_some() {
-                        ok = true;
+                        probe.ok = true;
+                        probe.latency_ms = Some(started.elapsed().as_millis() as u64);
+                        probe.protocol_version = negotiated_protocol_version(&value);
                         break;
                     }
                     if value.get("error").is_some() {
@@ -2795,7 +6791,14 @@ pub(crate) async fn check_acp_handshake(
         }
     }
     let _ = child.kill().await;
-    Ok(ok)
+    Ok(probe)
+}
+
+fn negotiated_protocol_version(init_response: &Value) -> Option<u64> {
+    init_response
+        .get("result")
+        .and_then(|result| result.get("protocolVersion"))
+        .and_then(Value::as_u64)
 }
 
 fn normalize_windows_micode_bin(bin: String) -> String {
@@ -2834,6 +6837,129 @@ fn normalize_windows_micode_bin(bin: String) -> String {
     trimmed.to_string()
 }
 
+fn agent_meta_capability(init_response: &Value, key: &str) -> bool {
+    init_response
+        .get("result")
+        .and_then(|result| result.get("agentCapabilities"))
+        .and_then(|capabilities| capabilities.get("_meta"))
+        .and_then(|meta| meta.get(key))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// Agents advertise per-prompt sampling support through the ACP `_meta` extension point
+/// on their capabilities.
+fn agent_supports_session_sampling(init_response: &Value) -> bool {
+    agent_meta_capability(init_response, "samplingParams")
+}
+
+/// ACP has no standard per-tool-call cancellation; agents that implement
+/// `CANCEL_TOOL_CALL_METHOD` advertise it the same way as sampling.
+fn agent_supports_tool_call_cancel(init_response: &Value) -> bool {
+    agent_meta_capability(init_response, "toolCallCancel")
+}
+
+/// Agents that read a per-prompt reasoning effort advertise it like sampling; others get
+/// it through the settings file, see `set_preferred_effort`.
+fn agent_supports_session_reasoning_effort(init_response: &Value) -> bool {
+    agent_meta_capability(init_response, "reasoningEffort")
+}
+
+/// ACP prompt capability; agents without it are sent images as `resource_link` blocks.
+fn agent_supports_image_prompts(init_response: &Value) -> bool {
+    init_response
+        .get("result")
+        .and_then(|result| result.get("agentCapabilities"))
+        .and_then(|capabilities| capabilities.get("promptCapabilities"))
+        .and_then(|prompt| prompt.get("image"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// Rewrites the tool item carried by an `item/completed` event as cancelled.
+fn mark_tool_item_cancelled(message: &mut Value) {
+    if let Some(item) = message
+        .get_mut("params")
+        .and_then(|params| params.get_mut("item"))
+        .and_then(Value::as_object_mut)
+    {
+        item.insert("status".to_string(), json!("cancelled"));
+    }
+}
+
+fn build_session_new_params(cwd: &str, mcp_servers: Value, access_mode: Option<&str>) -> Value {
+    // ACP requires mcpServers in session/new. Pass configured servers from settings.
+    let mut params = json!({ "cwd": cwd, "mcpServers": mcp_servers });
+    if let Some(access_mode) = access_mode {
+        params["_meta"] = access_mode_meta(access_mode, cwd);
+    }
+    params
+}
+
+/// Access mode a `turn/start` asks for. `send_user_message` turns the composer's
+/// `access_mode` into a `sandboxPolicy`; this maps it back to the mode's name.
+fn access_mode_from_turn_params(params: &Value) -> Option<&'static str> {
+    match params
+        .get("sandboxPolicy")
+        .and_then(|policy| policy.get("type"))
+        .and_then(Value::as_str)?
+    {
+        "readOnly" => Some("read-only"),
+        "workspaceWrite" => Some("current"),
+        "dangerFullAccess" => Some("full-access"),
+        _ => None,
+    }
+}
+
+/// `_meta` entries telling the agent which sandbox and approval policy to run under.
+fn access_mode_meta(access_mode: &str, cwd: &str) -> Value {
+    let (sandbox_policy, approval_policy) = access_mode_policies(access_mode, cwd);
+    json!({
+        "sandboxPolicy": sandbox_policy,
+        "approvalPolicy": approval_policy
+    })
+}
+
+/// Agents fix the sandbox when a session is created, so a thread whose access mode
+/// changes needs a new session. Threads recorded before access modes were tracked keep
+/// theirs; the mode still reaches the agent with each prompt.
+fn access_mode_requires_fresh_session(recorded: Option<&str>, requested: Option<&str>) -> bool {
+    matches!((recorded, requested), (Some(recorded), Some(requested)) if recorded != requested)
+}
+
+fn build_prompt_params(
+    session_id: &str,
+    prompt_text: &str,
+    images: &[Value],
+    sampling: Option<&Value>,
+    reasoning_effort: Option<&str>,
+    access: Option<&Value>,
+) -> Value {
+    let mut prompt = Vec::new();
+    if !prompt_text.is_empty() || images.is_empty() {
+        prompt.push(json!({ "type": "text", "text": prompt_text }));
+    }
+    prompt.extend(images.iter().cloned());
+    let mut params = json!({
+        "sessionId": session_id,
+        "prompt": prompt
+    });
+    let mut meta = serde_json::Map::new();
+    if let Some(sampling) = sampling {
+        meta.insert("samplingParams".to_string(), sampling.clone());
+    }
+    if let Some(effort) = reasoning_effort {
+        meta.insert("reasoningEffort".to_string(), json!(effort));
+    }
+    if let Some(Value::Object(access)) = access {
+        meta.extend(access.clone());
+    }
+    if !meta.is_empty() {
+        params["_meta"] = Value::Object(meta);
+    }
+    params
+}
+
 fn session_id_from_notification(value: &Value) -> Option<String> {
     value
         .get("params")
@@ -2848,6 +6974,7 @@ fn translate_acp_update(
     workspace_id: &str,
     agent_item_id: Option<&str>,
     cached_tool: Option<&ToolCallPresentation>,
+    tool_result_limit: Option<usize>,
 ) -> Vec<AppServerEvent> {
     let mut events = Vec::new();
     let kind = update
@@ -2870,7 +6997,7 @@ fn translate_acp_update(
                 events.push(AppServerEvent {
                     workspace_id: workspace_id.to_string(),
                     message: json!({
-                        "method": "item/agentMessage/delta",
+                        "method": event_methods::ITEM_AGENT_MESSAGE_DELTA,
                         "params": {
                             "threadId": context.thread_id,
                             "itemId": item_id,
@@ -2891,7 +7018,7 @@ fn translate_acp_update(
                 events.push(AppServerEvent {
                     workspace_id: workspace_id.to_string(),
                     message: json!({
-                        "method": "item/reasoning/textDelta",
+                        "method": event_methods::ITEM_REASONING_TEXT_DELTA,
                         "params": {
                             "threadId": context.thread_id,
                             "itemId": context.reasoning_item_id(),
@@ -2906,11 +7033,12 @@ fn translate_acp_update(
             events.push(AppServerEvent {
                 workspace_id: workspace_id.to_string(),
                 message: json!({
-                    "method": "turn/plan/updated",
+                    "method": event_methods::TURN_PLAN_UPDATED,
                     "params": {
                         "threadId": context.thread_id,
                         "turnId": context.turn_id,
                         "explanation": null,
+                        "summaryText": plan_summary(&plan),
                         "plan": plan
                     }
                 }),
@@ -2924,7 +7052,7 @@ fn translate_acp_update(
             events.push(AppServerEvent {
                 workspace_id: workspace_id.to_string(),
                 message: json!({
-                    "method": "micode/availableCommands/updated",
+                    "method": event_methods::MICODE_AVAILABLE_COMMANDS_UPDATED,
                     "params": {
                         "threadId": context.thread_id,
                         "availableCommands": commands
@@ -2940,19 +7068,20 @@ fn translate_acp_update(
                 .unwrap_or_else(|| context.fallback_tool_item_id());
             let presentation = merge_tool_presentation(
                 cached_tool.cloned(),
-                extract_tool_presentation_from_update(update),
+                extract_tool_presentation_from_update(update, tool_result_limit),
             );
             let title = tool_call_display_title(&presentation);
             events.push(AppServerEvent {
                 workspace_id: workspace_id.to_string(),
                 message: json!({
-                    "method": "item/started",
+                    "method": event_methods::ITEM_STARTED,
                     "params": {
                         "threadId": context.thread_id,
                         "item": {
                             "id": item_id,
                             "type": "mcpToolCall",
                             "title": title,
+                            "summaryText": tool_call_summary_text(&presentation, false),
                             "server": presentation.server,
                             "tool": presentation.tool,
                             "arguments": presentation.arguments,
@@ -2970,24 +7099,27 @@ fn translate_acp_update(
                 .unwrap_or_else(|| context.fallback_tool_item_id());
             let presentation = merge_tool_presentation(
                 cached_tool.cloned(),
-                extract_tool_presentation_from_update(update),
+                extract_tool_presentation_from_update(update, tool_result_limit),
             );
             let title = tool_call_display_title(&presentation);
             events.push(AppServerEvent {
                 workspace_id: workspace_id.to_string(),
                 message: json!({
-                    "method": "item/completed",
+                    "method": event_methods::ITEM_COMPLETED,
                     "params": {
                         "threadId": context.thread_id,
                         "item": {
                             "id": item_id,
                             "type": "mcpToolCall",
                             "title": title,
+                            "summaryText": tool_call_summary_text(&presentation, true),
                             "server": presentation.server,
                             "tool": presentation.tool,
                             "arguments": presentation.arguments,
                             "result": presentation.result,
+                            "resultJson": presentation.result_json,
                             "error": presentation.error,
+                            "durationMs": presentation.duration_ms,
                             "status": "completed"
                         }
                     }
@@ -2997,35 +7129,150 @@ fn translate_acp_update(
         _ => {}
     }
 
-    events
+    events
+}
+
+/// A `turn/start` the session is handling. Dropping it, also when the caller abandons the
+/// request, releases the thread to the next queued message and ends the in-flight count
+/// `has_active_turns` reads.
+struct TurnInFlight<'a> {
+    session: &'a WorkspaceSession,
+    thread_id: Option<String>,
+}
+
+impl Drop for TurnInFlight<'_> {
+    fn drop(&mut self) {
+        if let Some(thread_id) = self.thread_id.as_deref() {
+            self.session.finish_thread_turn(thread_id);
+        }
+        self.session.turns_in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// What a workspace session is launched with: the workspace, the agent binary, arguments
+/// and home resolved for it, and the settings and client version it starts from.
+pub(crate) struct SessionLaunch {
+    pub(crate) entry: WorkspaceEntry,
+    pub(crate) default_micode_bin: Option<String>,
+    pub(crate) agent_args: Option<String>,
+    pub(crate) agent_home: Option<PathBuf>,
+    pub(crate) session_settings: SessionSettings,
+    pub(crate) client_version: String,
+}
+
+/// App or daemon state a spawning session goes through or keeps a handle to.
+pub(crate) struct SessionServices<'a> {
+    /// App data directory; isolated agent homes live under it.
+    pub(crate) data_dir: &'a Path,
+    pub(crate) connect_queue: &'a ConnectQueue,
+    pub(crate) connection_states: &'a ConnectionStates,
+    pub(crate) auto_runs: &'a AutoRunRegistry,
+    pub(crate) settings_parse_errors: &'a SettingsParseErrors,
 }
 
 pub(crate) async fn spawn_workspace_session<E: EventSink>(
-    entry: WorkspaceEntry,
-    default_micode_bin: Option<String>,
-    agent_args: Option<String>,
-    agent_home: Option<PathBuf>,
-    client_version: String,
+    launch: SessionLaunch,
+    services: SessionServices<'_>,
     event_sink: E,
-) -> Result<Arc<WorkspaceSession>, String> {
-    let agent_bin = entry
-        .agent_bin
-        .clone()
-        .filter(|value| !value.trim().is_empty())
-        .or(default_micode_bin);
+) -> Result<Arc<WorkspaceSession>, CommandError> {
+    let workspace_id = launch.entry.id.clone();
+    let queue = services.connect_queue;
+    services.connection_states.mark_connecting(&workspace_id);
+    let permit = queue
+        .acquire(&workspace_id, |queued| {
+            for event in queue_position_events(&queued) {
+                event_sink.emit_app_server_event(event);
+            }
+        })
+        .await;
+    event_sink.emit_app_server_event(AppServerEvent {
+        workspace_id: workspace_id.clone(),
+        message: json!({
+            "method": event_methods::WORKSPACE_CONNECTING,
+            "params": { "workspaceId": workspace_id },
+        }),
+    });
+    // Everyone behind this workspace moved up one place.
+    for event in queue_position_events(&queue.queued()) {
+        event_sink.emit_app_server_event(event);
+    }
+    let result = spawn_workspace_session_inner(
+        launch,
+        event_sink.clone(),
+        services.data_dir,
+        services.auto_runs,
+        services.settings_parse_errors,
+    )
+    .await;
+    drop(permit);
+    let error = result.as_ref().err().map(|error| error.message.as_str());
+    services
+        .connection_states
+        .mark_connect_finished(&workspace_id, error);
+    event_sink.emit_app_server_event(AppServerEvent {
+        workspace_id: workspace_id.clone(),
+        message: json!({
+            "method": event_methods::WORKSPACE_CONNECTED,
+            "params": { "workspaceId": workspace_id, "ok": error.is_none(), "error": error },
+        }),
+    });
+    result
+}
+
+async fn spawn_workspace_session_inner<E: EventSink>(
+    launch: SessionLaunch,
+    event_sink: E,
+    data_dir: &Path,
+    auto_runs: &AutoRunRegistry,
+    settings_parse_errors: &SettingsParseErrors,
+) -> Result<Arc<WorkspaceSession>, CommandError> {
+    let SessionLaunch {
+        entry,
+        default_micode_bin,
+        agent_args,
+        agent_home,
+        session_settings,
+        client_version,
+    } = launch;
+    let launch_config =
+        SessionLaunchConfig::resolve(&entry, default_micode_bin, agent_args.clone());
+    let agent_bin = launch_config.agent_bin.clone();
     let _ = check_micode_installation(agent_bin.clone()).await?;
+    let handshake_key = handshake_key(agent_bin.as_deref(), None);
 
     let mut command = build_micode_command_with_bin(agent_bin);
     apply_micode_args(&mut command, agent_args.as_deref())?;
     command.current_dir(&entry.path);
     command.arg("--experimental-acp");
+    // The rules file `remember_approval_rule` writes to, read to auto-answer approvals.
+    let rules_path = agent_home
+        .clone()
+        .or_else(resolve_default_micode_home)
+        .map(|home| rules::default_rules_path(&home));
     // Do not inject CODEX_HOME/MICODE_HOME by default for MiCode ACP.
     // Keeping CLI runtime environment aligned with terminal `micode` avoids
-    // accidental profile/auth mismatch and stalled prompts.
-    let _ = agent_home;
+    // accidental profile/auth mismatch and stalled prompts. Isolated homes are the
+    // exception: there the point is to keep the agent away from the user's home.
+    let isolated_home = agent_home.filter(|home| is_isolated_agent_home(home, data_dir));
+    if let Some(home) = isolated_home.as_ref() {
+        prepare_isolated_agent_home(home, entry.settings.isolated_agent_home_template.as_deref())?;
+        command.env("MICODE_HOME", home);
+        command.env_remove("CODEX_HOME");
+    }
+    // The CLI writes its chat files under the home it runs with.
+    let chat_files = resolve_micode_home_path(isolated_home.as_deref())
+        .map(|home| Arc::new(std::sync::Mutex::new(ChatFileIndex::new(home.join("tmp")))));
     command.stdin(std::process::Stdio::piped());
     command.stdout(std::process::Stdio::piped());
     command.stderr(std::process::Stdio::piped());
+    let command_line = {
+        let std_command = command.as_std();
+        shell_words::join(
+            std::iter::once(std_command.get_program())
+                .chain(std_command.get_args())
+                .map(|part| part.to_string_lossy().into_owned()),
+        )
+    };
 
     let mut child = command.spawn().map_err(|e| e.to_string())?;
     let stdin = child.stdin.take().ok_or("missing stdin")?;
@@ -3039,30 +7286,101 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
             sink_for_forward.emit_app_server_event(event);
         }
     });
+    let (dequeued_turn_tx, mut dequeued_turn_rx) = mpsc::unbounded_channel::<QueuedTurn>();
+    let mut thread_store = LocalThreadStore::load_for_session(
+        &entry.path,
+        session_settings.archived_thread_retention_days,
+    );
+    let flush_notify = thread_store.enable_write_behind();
+    let thread_store = Arc::new(Mutex::new(thread_store));
+    spawn_thread_items_flusher(&thread_store, flush_notify);
 
     let session = Arc::new(WorkspaceSession {
         entry: entry.clone(),
+        launch_config,
+        config_stale: AtomicBool::new(false),
         child: Mutex::new(child),
         stdin: Mutex::new(stdin),
         pending: Mutex::new(HashMap::new()),
         next_id: AtomicU64::new(1),
         background_thread_callbacks: Mutex::new(HashMap::new()),
         event_tx: event_tx.clone(),
-        thread_store: Mutex::new(LocalThreadStore::load(&entry.path)),
-        approval_requests: Mutex::new(HashMap::new()),
+        thread_store: thread_store.clone(),
+        approvals: Mutex::new(PendingApprovals::default()),
         pending_prompt_streaming: Mutex::new(HashMap::new()),
         pending_prompt_agent_messages: Mutex::new(HashMap::new()),
+        pending_prompt_reasoning: Mutex::new(HashMap::new()),
         pending_prompt_agent_segments: Mutex::new(HashMap::new()),
         active_prompts: Mutex::new(HashMap::new()),
         background_threads: Mutex::new(HashMap::new()),
+        background_activity: std::sync::Mutex::new(BackgroundActivity::default()),
+        resumed_threads: Mutex::new(HashSet::new()),
+        auto_title_claims: std::sync::Mutex::new(HashSet::new()),
+        available_commands: std::sync::Mutex::new(AvailableCommands::default()),
         tool_call_presentations: Mutex::new(HashMap::new()),
+        running_tool_calls: Mutex::new(HashMap::new()),
+        tool_timings: std::sync::Mutex::new(ToolTimings::default()),
+        cancelled_tool_calls: Mutex::new(HashMap::new()),
+        turn_phases: Mutex::new(HashMap::new()),
+        turn_stalls: std::sync::Mutex::new(HashMap::new()),
+        user_interrupts: std::sync::Mutex::new(HashMap::new()),
+        turn_queue: std::sync::Mutex::new(TurnQueue::default()),
+        turn_released: Notify::new(),
+        turns_in_flight: AtomicUsize::new(0),
+        dequeued_turn_tx,
+        turn_artifact_baselines: Mutex::new(HashMap::new()),
+        turn_audit_reads: Mutex::new(HashMap::new()),
+        last_activity_ms: AtomicU64::new(now_ms()),
+        unresponsive_reported: AtomicBool::new(false),
+        health: std::sync::Mutex::new(HealthTracker::default()),
+        stopping: AtomicBool::new(false),
+        respawn_claimed: AtomicBool::new(false),
+        supports_session_sampling: AtomicBool::new(false),
+        supports_tool_call_cancel: AtomicBool::new(false),
+        supports_session_reasoning_effort: AtomicBool::new(false),
+        supports_image_prompts: AtomicBool::new(false),
+        sampling_written_to_settings: AtomicBool::new(false),
+        turns_started: AtomicU64::new(0),
+        last_store_maintenance_ms: AtomicU64::new(0),
+        handshake: std::sync::OnceLock::new(),
+        handshake_key,
+        primer: Mutex::new(PrimerState::default()),
+        mcp_probes: Mutex::new(McpProbeCache::default()),
+        resource_usage: std::sync::Mutex::new(ResourceTracker::default()),
+        command_line,
+        started_at_ms: now_ms(),
+        stderr_tail: std::sync::Mutex::new(VecDeque::new()),
+        redaction: std::sync::Mutex::new(CompiledRedaction::new(
+            entry.settings.redaction.as_ref(),
+        )),
+        audit: std::sync::Mutex::new(entry.settings.audit.clone()),
+        prompt_timeout_secs: std::sync::Mutex::new(entry.settings.prompt_timeout_secs),
+        settings: std::sync::Mutex::new(session_settings),
+        settings_parse_errors: settings_parse_errors.clone(),
+        chat_files,
+        isolated_home,
+        rules_path,
+        token_usage_watch: std::sync::OnceLock::new(),
+    });
+
+    // Holds a weak reference: the session owns the sender, so the loop ends with it.
+    let queued_session = Arc::downgrade(&session);
+    tokio::spawn(async move {
+        while let Some(turn) = dequeued_turn_rx.recv().await {
+            let Some(session) = queued_session.upgrade() else {
+                break;
+            };
+            tokio::spawn(async move { session.start_dequeued_turn(turn).await });
+        }
     });
 
     let session_clone = Arc::clone(&session);
+    let auto_runs_clone = auto_runs.clone();
     let workspace_id = entry.id.clone();
     tokio::spawn(async move {
         let mut lines = BufReader::new(stdout).lines();
         while let Ok(Some(line)) = lines.next_line().await {
+            session_clone.record_activity();
             if line.trim().is_empty() {
                 continue;
             }
@@ -3072,7 +7390,7 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                     let _ = event_tx.send(AppServerEvent {
                         workspace_id: workspace_id.clone(),
                         message: json!({
-                            "method": "micode/parseError",
+                            "method": event_methods::MICODE_PARSE_ERROR,
                             "params": { "error": err.to_string(), "raw": line },
                         }),
                     });
@@ -3086,8 +7404,8 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
             });
             if let Some(id) = response_id {
                 if value.get("result").is_some() || value.get("error").is_some() {
-                    if let Some(tx) = session_clone.pending.lock().await.remove(&id) {
-                        let _ = tx.send(value.clone());
+                    if let Some(request) = session_clone.pending.lock().await.remove(&id) {
+                        let _ = request.tx.send(value.clone());
                     }
                     continue;
                 }
@@ -3101,6 +7419,9 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                             .get("sessionUpdate")
                             .and_then(Value::as_str)
                             .unwrap_or_default();
+                        if update_kind == "available_commands_update" {
+                            session_clone.record_available_commands(update);
+                        }
                         let context = if let Some(active) = session_clone.active_prompt(&session_id).await
                         {
                             Some(active)
@@ -3128,17 +7449,34 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                         ) {
                             session_clone.mark_prompt_streaming(&session_id).await;
                         }
+                        let mut agent_checkpoint = Vec::new();
                         if update_kind == "agent_message_chunk" {
                             let delta = update
                                 .get("content")
                                 .and_then(|content| content.get("text"))
                                 .and_then(Value::as_str)
                                 .unwrap_or_default();
-                            session_clone
+                            agent_checkpoint = session_clone
                                 .append_prompt_agent_delta(&session_id, delta)
                                 .await;
                         }
                         if let Some(context) = context {
+                            if context.thread_id.is_empty() {
+                                agent_checkpoint.clear();
+                            }
+                            for (segment, text) in agent_checkpoint {
+                                session_clone
+                                    .persist_thread_item(
+                                        &context.thread_id,
+                                        build_partial_agent_thread_item(
+                                            &context.thread_id,
+                                            &context.turn_id,
+                                            segment,
+                                            &text,
+                                        ),
+                                    )
+                                    .await;
+                            }
                             let agent_item_id = if update_kind == "agent_message_chunk" {
                                 session_clone.current_prompt_agent_item_id(&session_id).await
                             } else {
@@ -3148,14 +7486,19 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                                 .get("toolCallId")
                                 .and_then(Value::as_str)
                                 .map(ToString::to_string);
+                            let tool_result_limit =
+                                session_clone.session_settings().tool_result_limit();
                             let mut tool_presentation_was_existing = true;
-                            let cached_tool = if matches!(update_kind, "tool_call" | "tool_call_update")
+                            let mut cached_tool = if matches!(update_kind, "tool_call" | "tool_call_update")
                             {
                                 if let Some(tool_call_id) = tool_call_id.as_deref() {
                                     let (merged, existed) = session_clone
                                         .merge_tool_call_presentation(
                                             tool_call_id,
-                                            extract_tool_presentation_from_update(update),
+                                            extract_tool_presentation_from_update(
+                                                update,
+                                                tool_result_limit,
+                                            ),
                                         )
                                         .await;
                                     tool_presentation_was_existing = existed;
@@ -3166,18 +7509,45 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                             } else {
                                 None
                             };
-                            let translated = translate_acp_update(
+                            let mut tool_call_cancelled = false;
+                            if let Some(tool_call_id) = tool_call_id.as_deref() {
+                                if update_kind == "tool_call" && !context.thread_id.is_empty() {
+                                    session_clone
+                                        .track_running_tool_call(
+                                            tool_call_id,
+                                            &context.thread_id,
+                                            &session_id,
+                                        )
+                                        .await;
+                                } else if update_kind == "tool_call_update" {
+                                    tool_call_cancelled = session_clone
+                                        .finish_running_tool_call(&context.thread_id, tool_call_id)
+                                        .await;
+                                    if let Some(presentation) = cached_tool.as_mut() {
+                                        presentation.duration_ms =
+                                            session_clone.finish_tool_timing(tool_call_id);
+                                    }
+                                }
+                            }
+                            let mut translated = translate_acp_update(
                                 &context,
                                 update,
                                 &workspace_id,
                                 agent_item_id.as_deref(),
                                 cached_tool.as_ref(),
+                                tool_result_limit,
                             );
+                            if tool_call_cancelled {
+                                for event in &mut translated {
+                                    mark_tool_item_cancelled(&mut event.message);
+                                }
+                            }
                             let background_callback = {
                                 let callbacks = session_clone.background_thread_callbacks.lock().await;
                                 callbacks.get(&context.thread_id).cloned()
                             };
                             if let Some(callback) = background_callback {
+                                session_clone.touch_background_thread(&context.thread_id);
                                 for event in translated {
                                     let _ = callback.send(event.message);
                                 }
@@ -3185,7 +7555,23 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                                 for event in translated {
                                     let _ = event_tx.send(event);
                                 }
+                                if update_kind == "agent_thought_chunk" {
+                                    let delta = update
+                                        .get("content")
+                                        .and_then(|content| content.get("text"))
+                                        .and_then(Value::as_str)
+                                        .unwrap_or_default();
+                                    session_clone
+                                        .append_prompt_reasoning_delta(&context, &session_id, delta)
+                                        .await;
+                                }
                             }
+                            session_clone.record_turn_update(&context.thread_id);
+                            session_clone
+                                .advance_turn_phase(&context.thread_id, |tracker| {
+                                    tracker.session_update(update)
+                                })
+                                .await;
                             if update_kind == "tool_call_update" {
                                 if let Some(tool_call_id) = tool_call_id.as_deref() {
                                     session_clone.clear_tool_call_presentation(tool_call_id).await;
@@ -3195,7 +7581,9 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                             {
                                 if let Some(tool_call_id) = tool_call_id.as_deref() {
                                     if let Some(presentation) = cached_tool.as_ref() {
-                                        let status = if update_kind == "tool_call_update" {
+                                        let status = if tool_call_cancelled {
+                                            "cancelled"
+                                        } else if update_kind == "tool_call_update" {
                                             "completed"
                                         } else {
                                             "in_progress"
@@ -3212,6 +7600,15 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                                                 ),
                                             )
                                             .await;
+                                        if status == "completed" {
+                                            session_clone
+                                                .record_audit_read(
+                                                    &context.thread_id,
+                                                    &tool_item_id,
+                                                    presentation,
+                                                )
+                                                .await;
+                                        }
                                     }
                                 }
                             }
@@ -3231,16 +7628,7 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
 
                 if method == "session/request_permission" {
                     let request_id = value.get("id").cloned().unwrap_or(Value::Null);
-                    let id_key = request_id
-                        .as_i64()
-                        .map(|v| v.to_string())
-                        .unwrap_or_else(|| request_id.to_string());
                     let params = value.get("params").cloned().unwrap_or(Value::Null);
-                    session_clone
-                        .approval_requests
-                        .lock()
-                        .await
-                        .insert(id_key, params.clone());
                     let session_id = params
                         .get("sessionId")
                         .and_then(Value::as_str)
@@ -3252,6 +7640,15 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                             .map(|entry| entry.thread_id)
                             .unwrap_or_default()
                     };
+                    let inserted = session_clone.approvals.lock().await.insert(
+                        request_id.clone(),
+                        params.clone(),
+                        &thread_id,
+                    );
+                    if inserted == ApprovalInsert::Duplicate {
+                        // Answered together with the pending request for the same tool call.
+                        continue;
+                    }
                     let command = extract_approval_command(&params);
                     if let Some((tool_call_id, tool_presentation)) =
                         extract_tool_presentation_from_permission(&params)
@@ -3276,13 +7673,14 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                             let _ = event_tx.send(AppServerEvent {
                                 workspace_id: workspace_id.clone(),
                                 message: json!({
-                                    "method": "item/started",
+                                    "method": event_methods::ITEM_STARTED,
                                     "params": {
                                         "threadId": thread_id,
                                         "item": {
                                             "id": item_id,
                                             "type": "mcpToolCall",
                                             "title": tool_call_display_title(&merged),
+                                            "summaryText": tool_call_summary_text(&merged, false),
                                             "server": merged.server,
                                             "tool": merged.tool,
                                             "arguments": merged.arguments,
@@ -3293,11 +7691,29 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                             });
                         }
                     }
+                    let decision = approval_command_tokens(&params).and_then(|tokens| {
+                        session_clone.auto_decide_approval(&tokens, &request_id, &thread_id)
+                    });
+                    if let Some(decision) = decision {
+                        if let Err(err) =
+                            session_clone.send_response(request_id, decision.result).await
+                        {
+                            eprintln!("failed to answer approval from remembered rule: {err}");
+                        }
+                        let _ = event_tx.send(AppServerEvent {
+                            workspace_id: workspace_id.clone(),
+                            message: json!({
+                                "method": decision.method,
+                                "params": decision.params
+                            }),
+                        });
+                        continue;
+                    }
                     let _ = event_tx.send(AppServerEvent {
                         workspace_id: workspace_id.clone(),
                         message: json!({
                             "id": request_id,
-                            "method": "workspace/requestApproval",
+                            "method": event_methods::WORKSPACE_REQUEST_APPROVAL,
                             "params": {
                                 "threadId": thread_id,
                                 "command": command,
@@ -3305,6 +7721,11 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                             }
                         }),
                     });
+                    auto_run_core::notify_awaiting_approval(
+                        &session_clone,
+                        &auto_runs_clone,
+                        &thread_id,
+                    );
                     continue;
                 }
 
@@ -3318,16 +7739,18 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
 
     let workspace_id = entry.id.clone();
     let event_sink_clone = event_sink.clone();
+    let stderr_session = Arc::clone(&session);
     tokio::spawn(async move {
         let mut lines = BufReader::new(stderr).lines();
         while let Ok(Some(line)) = lines.next_line().await {
             if line.trim().is_empty() {
                 continue;
             }
+            stderr_session.record_stderr_line(&line);
             event_sink_clone.emit_app_server_event(AppServerEvent {
                 workspace_id: workspace_id.clone(),
                 message: json!({
-                    "method": "micode/stderr",
+                    "method": event_methods::MICODE_STDERR,
                     "params": { "message": line },
                 }),
             });
@@ -3335,51 +7758,161 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
     });
 
     let init_params = build_initialize_params(&client_version);
+    let init_started = Instant::now();
     let init_result = timeout(
         Duration::from_secs(60),
-        session.send_acp_request("initialize", init_params),
+        session.send_acp_request_tagged("initialize", init_params, "initialize", None),
     )
     .await;
     let init_response = match init_result {
         Ok(response) => response,
         Err(_) => {
-            let mut child = session.child.lock().await;
-            let _ = child.kill().await;
-            return Err(if cfg!(windows) {
-                "MiCode ACP did not respond to initialize. Check `micode.cmd --experimental-acp` in Terminal. If PowerShell blocks `micode`, use `micode.cmd` or run `Set-ExecutionPolicy RemoteSigned`.".to_string()
-            } else {
-                "MiCode ACP did not respond to initialize. Check that `micode --experimental-acp` works in Terminal."
-                    .to_string()
-            });
+            session.kill().await;
+            return Err(CommandError::new(
+                ErrorCode::AcpTimeout,
+                if cfg!(windows) {
+                    "MiCode ACP did not respond to initialize. Check `micode.cmd --experimental-acp` in Terminal. If PowerShell blocks `micode`, use `micode.cmd` or run `Set-ExecutionPolicy RemoteSigned`."
+                } else {
+                    "MiCode ACP did not respond to initialize. Check that `micode --experimental-acp` works in Terminal."
+                },
+            ));
         }
     };
     let init_response = init_response?;
     if init_response.get("error").is_some() {
-        return Err(format!("ACP initialize failed: {init_response}"));
+        return Err(format!("ACP initialize failed: {init_response}").into());
     }
+    let _ = session.handshake.set(HandshakeProbe {
+        ok: true,
+        latency_ms: Some(init_started.elapsed().as_millis() as u64),
+        protocol_version: negotiated_protocol_version(&init_response),
+        checked_at_ms: now_ms(),
+    });
+    session.supports_session_sampling.store(
+        agent_supports_session_sampling(&init_response),
+        Ordering::SeqCst,
+    );
+    session.supports_tool_call_cancel.store(
+        agent_supports_tool_call_cancel(&init_response),
+        Ordering::SeqCst,
+    );
+    session.supports_session_reasoning_effort.store(
+        agent_supports_session_reasoning_effort(&init_response),
+        Ordering::SeqCst,
+    );
+    session.supports_image_prompts.store(
+        agent_supports_image_prompts(&init_response),
+        Ordering::SeqCst,
+    );
 
     event_sink.emit_app_server_event(AppServerEvent {
         workspace_id: entry.id.clone(),
         message: json!({
-            "method": "micode/connected",
-            "params": { "workspaceId": entry.id.clone() }
+            "method": event_methods::MICODE_CONNECTED,
+            "params": {
+                "workspaceId": entry.id.clone(),
+                "eventsSchemaVersion": event_methods::EVENTS_SCHEMA_VERSION
+            }
         }),
     });
+    auto_run_core::resume_auto_runs(&session, auto_runs).await;
+    spawn_session_ticker(&session);
 
     Ok(session)
 }
 
+/// A check the session ticker runs every `interval`.
+struct Periodic {
+    interval: Duration,
+    last_run: Instant,
+}
+
+impl Periodic {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_run: Instant::now(),
+        }
+    }
+
+    fn due(&mut self, now: Instant) -> bool {
+        if now.duration_since(self.last_run) < self.interval {
+            return false;
+        }
+        self.last_run = now;
+        true
+    }
+}
+
+/// The one maintenance task of a session. Every `SESSION_TICK_INTERVAL` it checks whether the
+/// agent exited, sweeps pending approvals and polls token usage, then runs the health ping,
+/// the background reaper and the stall checks whenever their own interval has passed. It stops once the agent exits or the session is
+/// dropped; the task only holds a weak reference.
+fn spawn_session_ticker(session: &Arc<WorkspaceSession>) {
+    let session = Arc::downgrade(session);
+    tokio::spawn(async move {
+        let mut health = Periodic::new(HEALTH_CHECK_INTERVAL);
+        let mut reap = Periodic::new(BACKGROUND_REAP_INTERVAL);
+        let mut stalls = Periodic::new(STALL_CHECK_INTERVAL);
+        loop {
+            sleep(SESSION_TICK_INTERVAL).await;
+            let Some(session) = session.upgrade() else {
+                break;
+            };
+            if session.check_exited().await {
+                break;
+            }
+            session.sweep_pending_approvals().await;
+            session.poll_token_usage().await;
+            let now = Instant::now();
+            if health.due(now) {
+                // The ping can wait for `HEALTH_PING_TIMEOUT`, shorter than the interval;
+                // the other checks must not queue behind it.
+                let session = Arc::clone(&session);
+                tokio::spawn(async move { session.check_health().await });
+            }
+            if reap.due(now) {
+                session.reap_background_threads().await;
+            }
+            if stalls.due(now) {
+                session.check_turn_stalls().await;
+                session.check_slow_tool_calls().await;
+            }
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        build_initialize_params, extract_approval_command, extract_tool_presentation_from_update,
-        load_thread_token_usage_for_session_in_home, normalize_turn_start_error_message,
-        normalize_wrapper_cli_token,
-        resolve_cli_bundle_near_bin, translate_acp_update, merge_tool_presentation, ActivePromptContext,
+        access_mode_from_turn_params, access_mode_meta, access_mode_requires_fresh_session,
+        agent_supports_image_prompts, agent_supports_session_reasoning_effort,
+        agent_supports_session_sampling, agent_supports_tool_call_cancel, approval_command_tokens,
+        build_initialize_params, build_prompt_params, build_session_new_params,
+        build_tool_thread_item, extract_approval_command, extract_tool_presentation_from_update,
+        load_thread_token_usage_for_session, mark_tool_item_cancelled, merge_models,
+        merge_tool_presentation, micode_settings_path, normalize_turn_start_error_message,
+        normalize_wrapper_cli_token, parse_custom_models, parse_models_from_cli_bundle,
+        read_settings_file, resolve_cli_bundle_near_bin, resolve_prompt_timeout,
+        set_preferred_effort, spawn_workspace_session_inner, sync_sampling_params_to_settings,
+        translate_acp_update, ActivePromptContext, AutoRunRegistry, BundleModelCache, CliModel,
+        SessionLaunch, SessionSettings, SettingsParseErrors, ThreadTitleSource, TokenUsageWatch,
         ToolCallPresentation, WorkspaceSession,
     };
+    use crate::backend::chat_index::ChatFileIndex;
+    use crate::backend::event_methods;
+    use crate::backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
+    use crate::backend::history_prune::{HistoryPruneOptions, HistoryPruneParams};
+    use crate::backend::session_health::HealthTracker;
+    use crate::backend::tool_timing::ToolTimings;
+    use crate::backend::turn_queue::TurnAdmission;
+    use crate::types::errors::ErrorCode;
+    use crate::types::{SamplingParams, WorkspaceEntry, WorkspaceKind, WorkspaceSettings};
     use serde_json::{json, Value};
     use std::path::PathBuf;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::Duration;
     use uuid::Uuid;
 
     #[test]
@@ -3393,6 +7926,375 @@ mod tests {
         );
     }
 
+    #[test]
+    fn prompt_params_carry_sampling_only_when_supported() {
+        let init = json!({
+            "result": { "agentCapabilities": { "_meta": { "samplingParams": true } } }
+        });
+        assert!(agent_supports_session_sampling(&init));
+        assert!(!agent_supports_session_sampling(&json!({ "result": {} })));
+
+        let sampling = json!({ "temperature": 0.2 });
+        let params = build_prompt_params("s1", "hello", &[], Some(&sampling), None, None);
+        assert_eq!(params["_meta"]["samplingParams"]["temperature"], 0.2);
+        let plain = build_prompt_params("s1", "hello", &[], None, None, None);
+        assert!(plain.get("_meta").is_none());
+        assert_eq!(plain["prompt"][0]["text"], "hello");
+    }
+
+    #[test]
+    fn prompt_params_carry_reasoning_effort_next_to_sampling() {
+        let init = json!({
+            "result": { "agentCapabilities": { "_meta": { "reasoningEffort": true } } }
+        });
+        assert!(agent_supports_session_reasoning_effort(&init));
+        assert!(!agent_supports_session_reasoning_effort(
+            &json!({ "result": {} })
+        ));
+
+        let sampling = json!({ "temperature": 0.2 });
+        let params = build_prompt_params("s1", "hello", &[], Some(&sampling), Some("high"), None);
+        assert_eq!(params["_meta"]["reasoningEffort"], "high");
+        assert_eq!(params["_meta"]["samplingParams"]["temperature"], 0.2);
+        let effort_only = build_prompt_params("s1", "hello", &[], None, Some("low"), None);
+        assert_eq!(effort_only["_meta"], json!({ "reasoningEffort": "low" }));
+    }
+
+    #[test]
+    fn access_modes_map_to_sandbox_policies_on_session_new_and_prompt() {
+        let cases = [
+            ("readOnly", "read-only", json!({ "type": "readOnly" }), "on-request"),
+            (
+                "workspaceWrite",
+                "current",
+                json!({
+                    "type": "workspaceWrite",
+                    "writableRoots": ["/repo"],
+                    "networkAccess": true
+                }),
+                "on-request",
+            ),
+            (
+                "dangerFullAccess",
+                "full-access",
+                json!({ "type": "dangerFullAccess" }),
+                "never",
+            ),
+        ];
+        for (policy_type, mode, sandbox_policy, approval_policy) in cases {
+            let turn = json!({ "threadId": "t1", "sandboxPolicy": { "type": policy_type } });
+            assert_eq!(access_mode_from_turn_params(&turn), Some(mode));
+
+            let new_params = build_session_new_params("/repo", json!([]), Some(mode));
+            assert_eq!(new_params["cwd"], "/repo");
+            assert_eq!(new_params["_meta"]["sandboxPolicy"], sandbox_policy);
+            assert_eq!(new_params["_meta"]["approvalPolicy"], approval_policy);
+
+            let access = access_mode_meta(mode, "/repo");
+            let prompt = build_prompt_params("s1", "hi", &[], None, Some("low"), Some(&access));
+            assert_eq!(prompt["_meta"]["sandboxPolicy"], sandbox_policy);
+            assert_eq!(prompt["_meta"]["approvalPolicy"], approval_policy);
+            assert_eq!(prompt["_meta"]["reasoningEffort"], "low");
+        }
+
+        assert_eq!(access_mode_from_turn_params(&json!({ "threadId": "t1" })), None);
+        let plain = build_session_new_params("/repo", json!([]), None);
+        assert!(plain.get("_meta").is_none());
+    }
+
+    #[test]
+    fn only_a_changed_access_mode_recreates_the_session() {
+        assert!(access_mode_requires_fresh_session(
+            Some("read-only"),
+            Some("full-access")
+        ));
+        assert!(!access_mode_requires_fresh_session(
+            Some("current"),
+            Some("current")
+        ));
+        // Threads recorded before access modes keep their session.
+        assert!(!access_mode_requires_fresh_session(None, Some("read-only")));
+        assert!(!access_mode_requires_fresh_session(Some("read-only"), None));
+
+        // The recorded mode survives a reload, so resumed threads keep their sandbox.
+        let root = std::env::temp_dir().join(format!("micode-access-mode-{}", Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("create workspace dir");
+        let workspace_path = workspace.to_string_lossy().to_string();
+        let mut store = super::LocalThreadStore::load(&workspace_path);
+        store.upsert(super::LocalThreadRecord {
+            thread_id: "t1".to_string(),
+            session_id: "s1".to_string(),
+            title: "New Thread".to_string(),
+            archived: false,
+            updated_at: 1,
+            message_index: 0,
+            tags: Vec::new(),
+            last_seen_item_seq: None,
+            kickoff: None,
+            pinned: false,
+            archived_at: None,
+            access_mode: None,
+            title_source: None,
+        });
+        store.set_access_mode("t1", "read-only");
+        let reloaded = super::LocalThreadStore::load(&workspace_path);
+        let recorded = reloaded
+            .records
+            .iter()
+            .find(|entry| entry.thread_id == "t1")
+            .and_then(|entry| entry.access_mode.clone());
+        assert_eq!(recorded.as_deref(), Some("read-only"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn preferred_effort_is_written_once_per_change() {
+        let errors = SettingsParseErrors::default();
+        let home = std::env::temp_dir().join(format!("micode-effort-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&home).expect("create home");
+        let settings_path = home.join("settings.json");
+        std::fs::write(
+            &settings_path,
+            json!({ "model": { "preferredModel": "micode-pro" } }).to_string(),
+        )
+        .expect("write settings");
+
+        assert!(set_preferred_effort("high", &home, &errors).expect("set effort"));
+        let written: Value =
+            serde_json::from_str(&std::fs::read_to_string(&settings_path).expect("read"))
+                .expect("parse settings");
+        assert_eq!(written["model"]["reasoningEffort"], "high");
+        assert_eq!(written["model"]["preferredModel"], "micode-pro");
+
+        // An unchanged effort leaves the file alone, so no session restart follows.
+        let untouched = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1);
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&settings_path)
+            .and_then(|file| file.set_modified(untouched))
+            .expect("age settings");
+        assert!(!set_preferred_effort(" high ", &home, &errors).expect("same effort"));
+        assert!(!set_preferred_effort("", &home, &errors).expect("empty effort"));
+        let modified = std::fs::metadata(&settings_path)
+            .and_then(|meta| meta.modified())
+            .expect("settings mtime");
+        assert_eq!(modified, untouched);
+
+        assert!(set_preferred_effort("low", &home, &errors).expect("change effort"));
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn sampling_fallback_writes_only_the_isolated_home() {
+        let errors = SettingsParseErrors::default();
+        let home = std::env::temp_dir().join(format!("micode-sampling-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&home).expect("create home");
+        let params = SamplingParams {
+            temperature: Some(0.2),
+            ..SamplingParams::default()
+        };
+        assert!(sync_sampling_params_to_settings(&params, &home, &errors).expect("write sampling"));
+        assert!(!sync_sampling_params_to_settings(&params, &home, &errors).expect("same sampling"));
+        let read = || read_settings_file(&home.join("settings.json"), &errors).expect("settings");
+        assert_eq!(read()["model"]["temperature"], 0.2);
+
+        assert!(
+            sync_sampling_params_to_settings(&SamplingParams::default(), &home, &errors)
+                .expect("clear sampling")
+        );
+        assert!(read()["model"].get("temperature").is_none());
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn cli_bundle_models_list_their_reasoning_efforts() {
+        let dir = std::env::temp_dir().join(format!("micode-bundle-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create bundle dir");
+        let bundle = dir.join("cli.js");
+        std::fs::write(
+            &bundle,
+            [
+                "var AVAILABLE_MODELS = [",
+                "  {",
+                "    id: \"micode-pro\",",
+                "    label: \"MiCode Pro\",",
+                "    reasoningEfforts: [\"low\", \"medium\", \"high\"],",
+                "    defaultReasoningEffort: \"medium\",",
+                "  },",
+                "  {",
+                "    id: \"micode-flash\",",
+                "    label: \"MiCode Flash\",",
+                "  },",
+                "];",
+                "function loadCustomMifyModels() {}",
+            ]
+            .join("\n"),
+        )
+        .expect("write bundle");
+
+        let models = parse_models_from_cli_bundle(&bundle);
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].reasoning_efforts, vec!["low", "medium", "high"]);
+        assert_eq!(models[0].default_reasoning_effort.as_deref(), Some("medium"));
+        assert!(models[1].reasoning_efforts.is_empty());
+        assert_eq!(models[1].description, "MiCode Flash");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn bundle_models_are_parsed_again_only_after_the_bundle_changes() {
+        let dir = std::env::temp_dir().join(format!("micode-bundle-cache-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create bundle dir");
+        let bundle = dir.join("cli.js");
+        let write_bundle = |ids: &[&str]| {
+            let mut lines = vec!["var AVAILABLE_MODELS = [".to_string()];
+            for id in ids {
+                lines.push("  {".to_string());
+                lines.push(format!("    id: \"{id}\","));
+                lines.push(format!("    label: \"{id}\","));
+                lines.push("  },".to_string());
+            }
+            lines.push("];".to_string());
+            std::fs::write(&bundle, lines.join("\n")).expect("write bundle");
+        };
+        write_bundle(&["micode-pro"]);
+
+        let mut cache = BundleModelCache::default();
+        assert_eq!(cache.models(&bundle, false).len(), 1);
+        assert_eq!(cache.models(&bundle, false).len(), 1);
+        assert_eq!(cache.parses, 1);
+
+        write_bundle(&["micode-pro", "micode-flash"]);
+        let models = cache.models(&bundle, false);
+        assert_eq!(models.len(), 2);
+        assert_eq!(cache.parses, 2);
+        assert_eq!(cache.models(&bundle, false), models);
+        assert_eq!(cache.parses, 2);
+
+        assert_eq!(cache.models(&bundle, true), models);
+        assert_eq!(cache.parses, 3);
+
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(cache.models(&bundle, false).is_empty());
+        assert!(cache.entries.is_empty());
+    }
+
+    #[test]
+    fn custom_models_from_settings_merge_after_bundle_models() {
+        let home = std::env::temp_dir().join(format!("micode-custom-models-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&home).expect("create home");
+        std::fs::write(
+            home.join("settings.json"),
+            r#"{
+                // Models served through a Mify gateway.
+                "customModels": [
+                    { "id": "team-coder", "label": "Team Coder", "reasoningEfforts": ["low", "high"] },
+                    "bare-model",
+                    { "label": "No id" },
+                    { "id": "micode-pro", "label": "Shadowed" },
+                    { "id": "bad-efforts", "reasoningEfforts": "high" },
+                    42
+                ],
+                "mifyModels": { "id": "not-a-list" }
+            }"#,
+        )
+        .expect("write settings");
+        let root = micode_settings_path(Some(&home))
+            .as_deref()
+            .and_then(|path| read_settings_file(path, &SettingsParseErrors::default()))
+            .expect("read settings");
+
+        let (custom, warnings) = parse_custom_models(&root);
+        let ids: Vec<&str> = custom.iter().map(|model| model.id.as_str()).collect();
+        assert_eq!(ids, vec!["team-coder", "bare-model", "micode-pro"]);
+        assert_eq!(custom[0].reasoning_efforts, vec!["low", "high"]);
+        assert_eq!(custom[1].label, "bare-model");
+        assert!(custom.iter().all(|model| model.source == "settings"));
+        let skipped: Vec<(&str, Option<usize>)> = warnings
+            .iter()
+            .map(|warning| (warning.key, warning.index))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                ("customModels", Some(2)),
+                ("customModels", Some(4)),
+                ("customModels", Some(5)),
+                ("mifyModels", None),
+            ]
+        );
+
+        let bundle = CliModel {
+            id: "micode-pro".to_string(),
+            label: "MiCode Pro".to_string(),
+            description: "MiCode Pro".to_string(),
+            reasoning_efforts: Vec::new(),
+            default_reasoning_effort: None,
+            source: "bundle",
+        };
+        let merged = merge_models(vec![bundle], custom);
+        let merged: Vec<(&str, &str)> = merged
+            .iter()
+            .map(|model| (model.id.as_str(), model.source))
+            .collect();
+        assert_eq!(
+            merged,
+            vec![
+                ("micode-pro", "bundle"),
+                ("team-coder", "settings"),
+                ("bare-model", "settings"),
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn prompt_params_append_image_blocks_after_the_text() {
+        let init = json!({
+            "result": { "agentCapabilities": { "promptCapabilities": { "image": true } } }
+        });
+        assert!(agent_supports_image_prompts(&init));
+        assert!(!agent_supports_image_prompts(&json!({ "result": {} })));
+
+        let image = json!({ "type": "image", "mimeType": "image/png", "data": "iVBORw0KGgo=" });
+        let params = build_prompt_params(
+            "s1",
+            "what is this?",
+            std::slice::from_ref(&image),
+            None,
+            None,
+            None,
+        );
+        assert_eq!(
+            params["prompt"],
+            json!([{ "type": "text", "text": "what is this?" }, image.clone()])
+        );
+        // An image sent without text is the whole prompt.
+        let params = build_prompt_params("s1", "", std::slice::from_ref(&image), None, None, None);
+        assert_eq!(params["prompt"], json!([image]));
+    }
+
+    #[test]
+    fn tool_call_cancel_is_opt_in_and_marks_items_cancelled() {
+        let init = json!({
+            "result": { "agentCapabilities": { "_meta": { "toolCallCancel": true } } }
+        });
+        assert!(agent_supports_tool_call_cancel(&init));
+        assert!(!agent_supports_session_sampling(&init));
+        assert!(!agent_supports_tool_call_cancel(&json!({ "result": {} })));
+
+        let mut event = json!({
+            "method": "item/completed",
+            "params": { "threadId": "t1", "item": { "id": "tool-call_1", "status": "completed" } }
+        });
+        mark_tool_item_cancelled(&mut event);
+        assert_eq!(event["params"]["item"]["status"], "cancelled");
+    }
+
     #[test]
     fn translate_agent_message_chunk_to_delta_event() {
         let update = json!({
@@ -3400,7 +8302,7 @@ mod tests {
             "content": { "type": "text", "text": "hello" }
         });
         let context = ActivePromptContext::new("thread-1".to_string(), "turn-1".to_string());
-        let events = translate_acp_update(&context, &update, "ws-1", None, None);
+        let events = translate_acp_update(&context, &update, "ws-1", None, None, None);
         assert_eq!(events.len(), 1);
         let method = events[0]
             .message
@@ -3418,13 +8320,17 @@ mod tests {
             ]
         });
         let context = ActivePromptContext::new("thread-2".to_string(), "turn-2".to_string());
-        let events = translate_acp_update(&context, &update, "ws-2", None, None);
+        let events = translate_acp_update(&context, &update, "ws-2", None, None, None);
         assert_eq!(events.len(), 1);
         let method = events[0]
             .message
             .get("method")
             .and_then(|value| value.as_str());
         assert_eq!(method, Some("turn/plan/updated"));
+        assert_eq!(
+            events[0].message["params"]["summaryText"],
+            "Plan updated: 0 of 1 step complete"
+        );
     }
 
     #[test]
@@ -3436,7 +8342,7 @@ mod tests {
             ]
         });
         let context = ActivePromptContext::new("thread-3".to_string(), "turn-3".to_string());
-        let events = translate_acp_update(&context, &update, "ws-3", None, None);
+        let events = translate_acp_update(&context, &update, "ws-3", None, None, None);
         assert_eq!(events.len(), 1);
         let method = events[0]
             .message
@@ -3453,7 +8359,7 @@ mod tests {
             "toolName": "glob"
         });
         let context = ActivePromptContext::new("thread-4".to_string(), "turn-4".to_string());
-        let events = translate_acp_update(&context, &update, "ws-4", None, None);
+        let events = translate_acp_update(&context, &update, "ws-4", None, None, None);
         assert_eq!(events.len(), 1);
         let item = events[0]
             .message
@@ -3465,6 +8371,34 @@ mod tests {
         assert_eq!(item.get("tool").and_then(Value::as_str), Some("glob"));
     }
 
+    #[test]
+    fn tool_call_updates_report_how_long_the_tool_ran() {
+        let mut timings = ToolTimings::default();
+        timings.start("call_3", "thread-6", 10_000);
+        let update = json!({
+            "sessionUpdate": "tool_call_update",
+            "toolCallId": "call_3",
+            "status": "completed"
+        });
+        let cached = ToolCallPresentation {
+            tool: Some("execute".to_string()),
+            duration_ms: timings.finish("call_3", 12_345),
+            ..ToolCallPresentation::default()
+        };
+        let context = ActivePromptContext::new("thread-6".to_string(), "turn-6".to_string());
+        let events = translate_acp_update(&context, &update, "ws-6", None, Some(&cached), None);
+        assert_eq!(events[0].message["params"]["item"]["durationMs"], 2_345);
+        let item = build_tool_thread_item("thread-6", "tool-call_3", &cached, "completed");
+        assert_eq!(item["durationMs"], 2_345);
+        let started = build_tool_thread_item(
+            "thread-6",
+            "tool-call_3",
+            &ToolCallPresentation::default(),
+            "in_progress",
+        );
+        assert_eq!(started["durationMs"], Value::Null);
+    }
+
     #[test]
     fn translate_tool_call_update_uses_cached_tool_identity() {
         let update = json!({
@@ -3478,9 +8412,11 @@ mod tests {
             title: Some("abc.md: foo => bar".to_string()),
             arguments: None,
             result: None,
+            result_json: None,
             error: None,
+            duration_ms: None,
         };
-        let events = translate_acp_update(&context, &update, "ws-5", None, Some(&cached));
+        let events = translate_acp_update(&context, &update, "ws-5", None, Some(&cached), None);
         assert_eq!(events.len(), 1);
         let item = events[0]
             .message
@@ -3490,6 +8426,10 @@ mod tests {
             .unwrap_or(Value::Null);
         assert_eq!(item.get("server").and_then(Value::as_str), Some("micode"));
         assert_eq!(item.get("tool").and_then(Value::as_str), Some("edit"));
+        assert_eq!(
+            item.get("summaryText").and_then(Value::as_str),
+            Some("Edited abc.md")
+        );
     }
 
     #[test]
@@ -3503,7 +8443,7 @@ mod tests {
             }
         });
         let context = ActivePromptContext::new("thread-6".to_string(), "turn-6".to_string());
-        let events = translate_acp_update(&context, &update, "ws-6", None, None);
+        let events = translate_acp_update(&context, &update, "ws-6", None, None, None);
         assert_eq!(events.len(), 1);
         let item = events[0]
             .message
@@ -3544,9 +8484,11 @@ mod tests {
             title: Some("execute".to_string()),
             arguments: None,
             result: None,
+            result_json: None,
             error: None,
+            duration_ms: None,
         };
-        let events = translate_acp_update(&context, &update, "ws-7", None, Some(&cached));
+        let events = translate_acp_update(&context, &update, "ws-7", None, Some(&cached), None);
         assert_eq!(events.len(), 1);
         let item = events[0]
             .message
@@ -3585,7 +8527,9 @@ mod tests {
             title: Some("execute".to_string()),
             arguments: Some(json!([])),
             result: None,
+            result_json: None,
             error: None,
+            duration_ms: None,
         });
         let incoming = ToolCallPresentation {
             server: Some("micode".to_string()),
@@ -3593,7 +8537,9 @@ mod tests {
             title: Some("execute".to_string()),
             arguments: Some(json!({ "command": ["python", "run.py"] })),
             result: None,
+            result_json: None,
             error: None,
+            duration_ms: None,
         };
 
         let merged = merge_tool_presentation(existing, incoming);
@@ -3617,7 +8563,7 @@ mod tests {
                 "command": ["python", "scripts/run_pipeline.py"]
             }
         });
-        let presentation = extract_tool_presentation_from_update(&update);
+        let presentation = extract_tool_presentation_from_update(&update, None);
         let command = presentation
             .arguments
             .as_ref()
@@ -3628,6 +8574,72 @@ mod tests {
         assert_eq!(command, Some("scripts/run_pipeline.py"));
     }
 
+    #[test]
+    fn extract_tool_presentation_keeps_string_results() {
+        let update = json!({
+            "sessionUpdate": "tool_call_update",
+            "toolCallId": "call_read",
+            "status": "completed",
+            "result": "fn main() {}"
+        });
+        let presentation = extract_tool_presentation_from_update(&update, None);
+        assert_eq!(presentation.result.as_deref(), Some("fn main() {}"));
+        assert_eq!(presentation.result_json, None);
+    }
+
+    #[test]
+    fn extract_tool_presentation_keeps_structured_results() {
+        let update = json!({
+            "sessionUpdate": "tool_call_update",
+            "toolCallId": "call_exec",
+            "status": "completed",
+            "rawOutput": {
+                "stdout": "3 passed",
+                "exitCode": 0,
+                "content": [
+                    { "type": "text", "text": "first block" },
+                    { "type": "text", "text": "second block" }
+                ]
+            }
+        });
+        let presentation = extract_tool_presentation_from_update(&update, None);
+        assert_eq!(
+            presentation.result.as_deref(),
+            Some("3 passed\nfirst block\nsecond block")
+        );
+        assert_eq!(presentation.result_json.as_ref(), update.get("rawOutput"));
+
+        let item = build_tool_thread_item("thread-1", "tool-call_exec", &presentation, "completed");
+        assert_eq!(item["resultJson"]["exitCode"], 0);
+
+        let context = ActivePromptContext::new("thread-1".to_string(), "turn-1".to_string());
+        let events = translate_acp_update(&context, &update, "ws-1", None, None, None);
+        assert_eq!(
+            events[0].message["params"]["item"]["resultJson"]["stdout"],
+            "3 passed"
+        );
+    }
+
+    #[test]
+    fn extract_tool_presentation_truncates_oversized_results() {
+        let update = json!({
+            "sessionUpdate": "tool_call_update",
+            "toolCallId": "call_glob",
+            "result": "x".repeat(70 * 1024),
+            "rawOutput": { "files": vec!["src/backend/app_server.rs"; 4096] }
+        });
+        let presentation = extract_tool_presentation_from_update(&update, Some(64 * 1024));
+        let result = presentation.result.expect("text result");
+        assert!(result.starts_with(&"x".repeat(64 * 1024)));
+        assert!(result.ends_with("… [truncated 6144 bytes]"));
+        let result_json = presentation.result_json.expect("structured result");
+        assert_eq!(result_json["truncated"], true);
+        assert_eq!(
+            result_json["preview"].as_str().map(str::len),
+            Some(64 * 1024)
+        );
+    }
+
     #[test]
     fn parse_prompt_from_turn_start_falls_back_to_text() {
         let params = json!({
@@ -3649,6 +8661,82 @@ mod tests {
         assert_eq!(command, vec!["Approve action"]);
     }
 
+    #[test]
+    fn approval_rules_only_see_tokens_from_command_fields() {
+        let titled = json!({ "toolCall": { "title": "Write src/lib.rs" } });
+        assert_eq!(approval_command_tokens(&titled), None);
+        assert_eq!(extract_approval_command(&titled), vec!["Write src/lib.rs"]);
+        assert_eq!(approval_command_tokens(&json!({ "toolCall": {} })), None);
+
+        let shell = json!({ "toolCall": { "title": "Run", "command": "git status" } });
+        assert_eq!(
+            approval_command_tokens(&shell),
+            Some(vec!["git".to_string(), "status".to_string()])
+        );
+        let argv = json!({ "argv": ["cargo", "test"] });
+        assert_eq!(
+            approval_command_tokens(&argv),
+            Some(vec!["cargo".to_string(), "test".to_string()])
+        );
+    }
+
+    #[test]
+    fn token_usage_watch_reports_counts_as_the_chat_file_is_written() {
+        let root = std::env::temp_dir().join(format!("micode-usage-watch-{}", Uuid::new_v4()));
+        let chat_files = Arc::new(std::sync::Mutex::new(ChatFileIndex::new(root.join("tmp"))));
+        let mut watch = TokenUsageWatch::new(chat_files);
+        watch.track("session-watch-1", "thread-1", "turn-1");
+        // Neither the home nor the chat file exists yet.
+        assert!(watch.poll().is_empty());
+
+        let chats = root.join("tmp").join("project-a").join("chats");
+        std::fs::create_dir_all(&chats).expect("create chats dir");
+        let file = chats.join("session-watch.json");
+        let write_chat = |total: i64| {
+            let payload = json!({
+                "sessionId": "session-watch-1",
+                "messages": [
+                    { "type": "user", "content": "hello" },
+                    {
+                        "type": "assistant",
+                        "content": "working",
+                        "tokens": { "input": total - 4, "output": 4, "total": total }
+                    }
+                ]
+            });
+            std::fs::write(&file, payload.to_string()).expect("write chat");
+        };
+        write_chat(20);
+        let updates = watch.poll();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0]["threadId"], json!("thread-1"));
+        assert_eq!(updates[0]["turnId"], json!("turn-1"));
+        assert_eq!(
+            updates[0]["tokenUsage"]["last"]["inputTokens"].as_i64(),
+            Some(16)
+        );
+        assert!(watch.poll().is_empty());
+
+        watch.track("session-watch-1", "thread-1", "turn-2");
+        write_chat(50);
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&file)
+            .and_then(|chat| {
+                chat.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+            })
+            .expect("touch chat");
+        let updates = watch.poll();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0]["turnId"], json!("turn-2"));
+        assert_eq!(
+            updates[0]["tokenUsage"]["last"]["inputTokens"].as_i64(),
+            Some(46)
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
     #[test]
     fn load_thread_token_usage_reads_last_and_total_from_micode_session_file() {
         let root = std::env::temp_dir().join(format!("micode-usage-{}", Uuid::new_v4()));
@@ -3693,152 +8781,1242 @@ mod tests {
         )
         .expect("write payload");
 
-        let usage = load_thread_token_usage_for_session_in_home(session_id, &root)
+        let chat_files = std::sync::Mutex::new(ChatFileIndex::new(root.join("tmp")));
+        let usage = load_thread_token_usage_for_session(session_id, &chat_files)
             .expect("expected token usage");
         assert_eq!(
-            usage
-                .get("last")
-                .and_then(|v| v.get("inputTokens"))
-                .and_then(|v| v.as_i64()),
-            Some(20)
+            usage
+                .get("last")
+                .and_then(|v| v.get("inputTokens"))
+                .and_then(|v| v.as_i64()),
+            Some(20)
+        );
+        assert_eq!(
+            usage
+                .get("last")
+                .and_then(|v| v.get("outputTokens"))
+                .and_then(|v| v.as_i64()),
+            Some(9)
+        );
+        assert_eq!(
+            usage
+                .get("total")
+                .and_then(|v| v.get("totalTokens"))
+                .and_then(|v| v.as_i64()),
+            Some(48)
+        );
+
+        let _ = std::fs::remove_dir_all(PathBuf::from(&root));
+    }
+
+    #[test]
+    fn resolve_cli_bundle_near_bin_finds_npm_layout() {
+        let root = std::env::temp_dir().join(format!("micode-cli-near-bin-{}", Uuid::new_v4()));
+        let bin = root.join("bin");
+        let bundle = bin
+            .join("node_modules")
+            .join("@mi")
+            .join("mi-code-cli")
+            .join("dist")
+            .join("cli.js");
+        std::fs::create_dir_all(bundle.parent().expect("bundle parent"))
+            .expect("create bundle dir");
+        std::fs::write(&bundle, "var AVAILABLE_MODELS = [];").expect("write cli bundle");
+        let wrapper = bin.join("micode.cmd");
+        std::fs::write(&wrapper, "@echo off").expect("write wrapper");
+
+        let resolved = resolve_cli_bundle_near_bin(&wrapper);
+        assert!(resolved.is_some());
+        assert!(
+            resolved
+                .and_then(|path| path.file_name().map(|name| name == "cli.js"))
+                .unwrap_or(false)
+        );
+
+        let _ = std::fs::remove_dir_all(PathBuf::from(&root));
+    }
+
+    #[test]
+    fn normalize_wrapper_cli_token_expands_windows_dp0_path() {
+        let root = std::env::temp_dir().join(format!("micode-cli-token-{}", Uuid::new_v4()));
+        let bundle = root
+            .join("node_modules")
+            .join("@mi")
+            .join("mi-code-cli")
+            .join("dist")
+            .join("cli.js");
+        std::fs::create_dir_all(bundle.parent().expect("bundle parent"))
+            .expect("create bundle dir");
+        std::fs::write(&bundle, "var AVAILABLE_MODELS = [];").expect("write cli bundle");
+        let wrapper = root.join("micode.cmd");
+        std::fs::write(&wrapper, "@echo off").expect("write wrapper");
+
+        let resolved = normalize_wrapper_cli_token(
+            "%~dp0\\node_modules\\@mi\\mi-code-cli\\dist\\cli.js",
+            &wrapper,
+        );
+        assert!(resolved.is_some());
+        assert!(
+            resolved
+                .and_then(|path| path.file_name().map(|name| name == "cli.js"))
+                .unwrap_or(false)
+        );
+
+        let _ = std::fs::remove_dir_all(PathBuf::from(&root));
+    }
+
+    #[test]
+    fn streamed_agent_text_survives_a_session_dropped_mid_turn() {
+        let root = std::env::temp_dir().join(format!("micode-agent-checkpoint-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create workspace dir");
+        let workspace_path = root.to_string_lossy().to_string();
+        let mut message = super::PromptAgentMessage::default();
+        let store = super::LocalThreadStore::load(&workspace_path);
+        let mut checkpoint = |store: &super::LocalThreadStore, delta: &str, now_ms: u64| {
+            for (segment, text) in message.append(0, delta, now_ms) {
+                store.upsert_thread_item(
+                    "thread-1",
+                    super::build_partial_agent_thread_item("thread-1", "turn-1", segment, &text),
+                );
+            }
+        };
+        let interval_ms = super::AGENT_CHECKPOINT_INTERVAL.as_millis() as u64;
+
+        // The first visible text is saved at once, then only when a checkpoint is due.
+        checkpoint(&store, "Hello", 1_000);
+        checkpoint(&store, ", world", 1_001);
+        let saved = store.load_thread_items("thread-1");
+        assert_eq!(saved[0]["text"], "Hello");
+        checkpoint(&store, ".", 1_000 + interval_ms);
+        checkpoint(&store, &"x".repeat(super::AGENT_CHECKPOINT_BYTES), 1_001 + interval_ms);
+        checkpoint(&store, " lost", 1_002 + interval_ms);
+        drop(store);
+
+        let reloaded = super::LocalThreadStore::load(&workspace_path);
+        let items = reloaded.load_thread_items("thread-1");
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["partial"], true);
+        assert_eq!(
+            items[0]["text"],
+            format!("Hello, world.{}", "x".repeat(super::AGENT_CHECKPOINT_BYTES))
+        );
+
+        // A prompt that completes replaces the checkpoint and drops the flag.
+        reloaded.upsert_thread_item(
+            "thread-1",
+            super::build_agent_thread_item("thread-1", "turn-1", 0, "Hello, world."),
+        );
+        let items = reloaded.load_thread_items("thread-1");
+        assert_eq!(items.len(), 1);
+        assert!(items[0].get("partial").is_none());
+        assert_eq!(items[0]["seq"], 1);
+
+        drop(reloaded);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn generated_titles_only_replace_titles_cut_from_the_prompt() {
+        let root = std::env::temp_dir().join(format!("micode-auto-title-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create workspace dir");
+        let workspace_path = root.to_string_lossy().to_string();
+        let mut store = super::LocalThreadStore::load(&workspace_path);
+        for thread_id in ["derived", "renamed"] {
+            store.upsert(super::LocalThreadRecord {
+                thread_id: thread_id.to_string(),
+                session_id: "s1".to_string(),
+                title: "New Thread".to_string(),
+                archived: false,
+                updated_at: 1,
+                message_index: 0,
+                tags: Vec::new(),
+                last_seen_item_seq: None,
+                kickoff: None,
+                pinned: false,
+                archived_at: None,
+                access_mode: None,
+                title_source: None,
+            });
+        }
+        let prompt_title = "can you please look at the file src/ba…".to_string();
+        for thread_id in ["derived", "renamed"] {
+            store.rename_thread(thread_id, prompt_title.clone(), ThreadTitleSource::Prompt);
+        }
+        store.rename_thread("renamed", "Login bug".to_string(), ThreadTitleSource::User);
+
+        // A generated title replaces the prompt title and yields the event params.
+        let updated =
+            store.rename_thread("derived", "Review backend module".to_string(), ThreadTitleSource::Generated);
+        assert_eq!(
+            updated,
+            Some(json!({ "threadId": "derived", "threadName": "Review backend module" }))
+        );
+        // It leaves names the user picked, unknown threads, and its own titles alone.
+        for thread_id in ["renamed", "missing", "derived"] {
+            let updated =
+                store.rename_thread(thread_id, "Other title".to_string(), ThreadTitleSource::Generated);
+            assert_eq!(updated, None, "{thread_id}");
+        }
+
+        let reloaded = super::LocalThreadStore::load(&workspace_path);
+        let title_of = |thread_id: &str| {
+            reloaded
+                .by_thread_id(thread_id)
+                .map(|record| (record.title, record.title_source))
+        };
+        assert_eq!(
+            title_of("derived"),
+            Some((
+                "Review backend module".to_string(),
+                Some(ThreadTitleSource::Generated)
+            ))
+        );
+        assert_eq!(
+            title_of("renamed"),
+            Some(("Login bug".to_string(), Some(ThreadTitleSource::User)))
+        );
+
+        drop(reloaded);
+        drop(store);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn scoped_history_pruning_lists_on_dry_run_and_removes_matching_threads() {
+        let root = std::env::temp_dir().join(format!("micode-history-prune-{}", Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("create workspace dir");
+        let workspace_path = workspace.to_string_lossy().to_string();
+        let mut store = super::LocalThreadStore::load(&workspace_path);
+        for (thread_id, updated_at, tags) in [
+            ("old", 1, Vec::new()),
+            ("old-keep", 1, vec!["keep".to_string()]),
+            ("fresh", super::now_ts(), Vec::new()),
+        ] {
+            store.upsert(super::LocalThreadRecord {
+                thread_id: thread_id.to_string(),
+                session_id: String::new(),
+                title: thread_id.to_string(),
+                archived: false,
+                updated_at,
+                message_index: 0,
+                tags,
+                last_seen_item_seq: None,
+                kickoff: None,
+                pinned: false,
+                archived_at: None,
+                access_mode: None,
+                title_source: None,
+            });
+            store.upsert_thread_item(thread_id, json!({ "id": format!("item-{thread_id}") }));
+        }
+
+        let mut options = HistoryPruneOptions::from_params(HistoryPruneParams {
+            older_than_days: Some(30),
+            dry_run: Some(true),
+            ..HistoryPruneParams::default()
+        });
+        let listed = super::prune_thread_history_at("ws-1", &workspace_path, &options);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].thread_id, "old");
+        assert_eq!(listed[0].item_count, 1);
+        assert!(listed[0].disk_bytes > 0);
+        assert!(store.thread_items_path("old").exists());
+
+        options.dry_run = false;
+        let removed = super::prune_thread_history_at("ws-1", &workspace_path, &options);
+        assert_eq!(removed.len(), 1);
+        let reloaded = super::LocalThreadStore::load(&workspace_path);
+        let mut remaining: Vec<String> = reloaded
+            .list_unarchived()
+            .into_iter()
+            .map(|entry| entry.thread_id)
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["fresh".to_string(), "old-keep".to_string()]);
+        assert!(!reloaded.thread_items_path("old").exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn local_thread_store_persists_and_updates_thread_items() {
+        let root = std::env::temp_dir().join(format!("micode-thread-store-{}", Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("create workspace dir");
+        let workspace_path = workspace.to_string_lossy().to_string();
+        let mut store = super::LocalThreadStore::load(&workspace_path);
+
+        let thread_id = "thread-1";
+        store.upsert(super::LocalThreadRecord {
+            thread_id: thread_id.to_string(),
+            session_id: "session-1".to_string(),
+            title: "New Thread".to_string(),
+            archived: false,
+            updated_at: 1,
+            message_index: 0,
+            tags: Vec::new(),
+            last_seen_item_seq: Some(0),
+            kickoff: None,
+            pinned: false,
+            archived_at: None,
+            access_mode: None,
+            title_source: None,
+        });
+
+        store.upsert_thread_item(
+            thread_id,
+            json!({
+                "id": "agent-thread-1-turn-1",
+                "type": "agentMessage",
+                "text": "hello"
+            }),
+        );
+        store.upsert_thread_item(
+            thread_id,
+            json!({
+                "id": "agent-thread-1-turn-1",
+                "type": "agentMessage",
+                "text": "hello world"
+            }),
+        );
+
+        let loaded = store.load_thread_items(thread_id);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(
+            loaded[0].get("text").and_then(Value::as_str),
+            Some("hello world")
+        );
+
+        store.set_agent_item_token_usage(
+            thread_id,
+            "turn-1",
+            &json!({
+                "last": { "totalTokens": 12 },
+                "total": { "totalTokens": 24 }
+            }),
+        );
+        let loaded_with_usage = store.load_thread_items(thread_id);
+        assert_eq!(
+            loaded_with_usage[0]
+                .get("tokenUsage")
+                .and_then(|value| value.get("last"))
+                .and_then(|value| value.get("totalTokens"))
+                .and_then(Value::as_i64),
+            Some(12)
+        );
+
+        assert!(store.delete(thread_id));
+        assert!(store.load_thread_items(thread_id).is_empty());
+
+        let _ = std::fs::remove_dir_all(PathBuf::from(&root));
+    }
+
+    #[test]
+    fn user_interrupt_reports_cancellation_and_leaves_a_note() {
+        let turn = json!({ "id": "turn-1", "threadId": "thread-1" });
+        let response = super::cancelled_turn_response(&turn, Some("user"));
+        assert_eq!(response["result"]["stopReason"], "cancelled");
+        assert_eq!(response["result"]["cancelledBy"], "user");
+        assert_eq!(response["result"]["turn"], turn);
+        let aborted = super::cancelled_turn_response(&turn, None);
+        assert_eq!(aborted["result"]["stopReason"], "cancelled");
+        assert!(aborted["result"].get("cancelledBy").is_none());
+
+        let root = std::env::temp_dir().join(format!("micode-interrupt-{}", Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("create workspace dir");
+        let store = super::LocalThreadStore::load(&workspace.to_string_lossy());
+        store.upsert_thread_item(
+            "thread-1",
+            super::build_agent_thread_item("thread-1", "turn-1", 0, "partial answer"),
+        );
+        let note = super::build_interruption_note_item("thread-1", "turn-1");
+        store.upsert_thread_item("thread-1", note.clone());
+        store.upsert_thread_item("thread-1", note);
+
+        let items = store.load_thread_items("thread-1");
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["type"], "agentMessage");
+        assert_eq!(items[1]["type"], "systemNote");
+        assert_eq!(items[1]["text"], super::INTERRUPTED_BY_USER_NOTE);
+        assert_eq!(items[1]["id"], "note-thread-1-turn-1");
+
+        let _ = std::fs::remove_dir_all(PathBuf::from(&root));
+    }
+
+    #[test]
+    fn resume_returns_reasoning_where_it_was_streamed() {
+        let root = std::env::temp_dir().join(format!("micode-reasoning-{}", Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("create workspace dir");
+        let store = super::LocalThreadStore::load(&workspace.to_string_lossy());
+        store.upsert_thread_item(
+            "thread-1",
+            super::build_user_thread_item("thread-1", "turn-1", "fix it", &Default::default()),
+        );
+        // The first thought chunk saves the item, the end of the prompt completes it.
+        store.upsert_thread_item(
+            "thread-1",
+            super::build_reasoning_thread_item("thread-1", "turn-1", "Looking"),
+        );
+        store.upsert_thread_item(
+            "thread-1",
+            json!({ "id": "tool-call-1", "type": "mcpToolCall", "status": "completed" }),
+        );
+        store.upsert_thread_item(
+            "thread-1",
+            super::build_reasoning_thread_item("thread-1", "turn-1", "Looking at the file"),
+        );
+        store.upsert_thread_item(
+            "thread-1",
+            super::build_agent_thread_item("thread-1", "turn-1", 0, "Fixed."),
+        );
+
+        let turns = super::history_turns("thread-1", &store.load_thread_items("thread-1"));
+        assert_eq!(turns.len(), 1);
+        assert_eq!(turns[0]["id"], "turn-history-thread-1");
+        let items = turns[0]["items"].as_array().expect("history items");
+        let ids: Vec<&str> = items
+            .iter()
+            .filter_map(|item| item["id"].as_str())
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                "user-thread-1-turn-1",
+                "reasoning-thread-1-turn-1",
+                "tool-call-1",
+                "agent-thread-1-turn-1",
+            ]
+        );
+        assert_eq!(
+            items[1],
+            json!({
+                "id": "reasoning-thread-1-turn-1",
+                "type": "reasoning",
+                "summary": "",
+                "content": "Looking at the file",
+                "seq": 2,
+            })
+        );
+        assert!(super::history_turns("thread-2", &[]).is_empty());
+
+        let _ = std::fs::remove_dir_all(PathBuf::from(&root));
+    }
+
+    #[test]
+    fn resume_keeps_items_in_the_order_they_started() {
+        let root = std::env::temp_dir().join(format!("micode-item-seq-{}", Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("create workspace dir");
+        let store = super::LocalThreadStore::load(&workspace.to_string_lossy());
+        let tool = |status: &str| {
+            json!({ "id": "tool-call-1", "type": "mcpToolCall", "status": status })
+        };
+        store.upsert_thread_item(
+            "thread-1",
+            super::build_user_thread_item("thread-1", "turn-1", "fix it", &Default::default()),
+        );
+        store.upsert_thread_item("thread-1", tool("in_progress"));
+        // Each segment is saved on its first text and completed when the prompt ends.
+        store.upsert_thread_item(
+            "thread-1",
+            super::build_agent_thread_item("thread-1", "turn-1", 1, "Reading"),
+        );
+        store.upsert_thread_item("thread-1", tool("completed"));
+        store.upsert_thread_item(
+            "thread-1",
+            super::build_agent_thread_item("thread-1", "turn-1", 2, "Done"),
+        );
+        store.upsert_thread_item(
+            "thread-1",
+            super::build_agent_thread_item("thread-1", "turn-1", 1, "Reading the file."),
+        );
+        store.upsert_thread_item(
+            "thread-1",
+            super::build_agent_thread_item("thread-1", "turn-1", 2, "Done, it is fixed."),
+        );
+
+        let turns = super::history_turns("thread-1", &store.load_thread_items("thread-1"));
+        let items = turns[0]["items"].as_array().expect("history items");
+        let order: Vec<(&str, u64)> = items
+            .iter()
+            .map(|item| {
+                (
+                    item["id"].as_str().unwrap_or_default(),
+                    item["seq"].as_u64().unwrap_or_default(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("user-thread-1-turn-1", 1),
+                ("tool-call-1", 2),
+                ("agent-thread-1-turn-1-s1", 3),
+                ("agent-thread-1-turn-1-s2", 4),
+            ]
+        );
+        assert_eq!(items[1]["status"], "completed");
+        assert_eq!(items[2]["text"], "Reading the file.");
+
+        // Files from before `seq` are numbered in their stored order on read, without
+        // rewriting the file; the next write saves the numbers.
+        let legacy = store.thread_items_path("legacy");
+        let legacy_raw = json!([{ "id": "b" }, { "id": "a" }, { "id": "c" }]).to_string();
+        std::fs::write(&legacy, &legacy_raw).expect("write legacy items");
+        let loaded = store.load_thread_items("legacy");
+        assert_eq!(
+            loaded,
+            vec![
+                json!({ "id": "b", "seq": 1 }),
+                json!({ "id": "a", "seq": 2 }),
+                json!({ "id": "c", "seq": 3 }),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(&legacy).expect("read legacy items"),
+            legacy_raw
         );
+        assert_eq!(store.load_thread_items("legacy"), loaded);
+        store.upsert_thread_item("legacy", json!({ "id": "d" }));
+        let migrated: Vec<Value> =
+            serde_json::from_str(&std::fs::read_to_string(&legacy).expect("read legacy items"))
+                .expect("parse legacy items");
+        assert_eq!(migrated[..3], loaded[..]);
+        assert_eq!(store.load_thread_items("legacy")[3]["seq"], 4);
+
+        // Items that already carry a `seq` keep it; only the rest are numbered after them.
+        let mixed = store.thread_items_path("mixed");
+        std::fs::write(
+            &mixed,
+            json!([{ "id": "x" }, { "id": "y", "seq": 7 }, { "id": "z", "seq": 3 }]).to_string(),
+        )
+        .expect("write mixed items");
         assert_eq!(
-            usage
-                .get("last")
-                .and_then(|v| v.get("outputTokens"))
-                .and_then(|v| v.as_i64()),
-            Some(9)
+            store.load_thread_items("mixed"),
+            vec![
+                json!({ "id": "z", "seq": 3 }),
+                json!({ "id": "y", "seq": 7 }),
+                json!({ "id": "x", "seq": 8 }),
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(PathBuf::from(&root));
+    }
+
+    #[test]
+    fn search_finds_thread_names_and_items_newest_thread_first() {
+        let root = std::env::temp_dir().join(format!("micode-thread-search-{}", Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("create workspace dir");
+        let workspace_path = workspace.to_string_lossy().to_string();
+        let mut store = super::LocalThreadStore::load(&workspace_path);
+        let record = |thread_id: &str, title: &str, updated_at, archived| super::LocalThreadRecord {
+            thread_id: thread_id.to_string(),
+            session_id: format!("session-{thread_id}"),
+            title: title.to_string(),
+            archived,
+            updated_at,
+            message_index: 0,
+            tags: Vec::new(),
+            last_seen_item_seq: None,
+            kickoff: None,
+            pinned: false,
+            archived_at: None,
+            access_mode: None,
+            title_source: None,
+        };
+        store.upsert(record("old", "Flaky login test", 10, false));
+        store.upsert(record("new", "Release notes", 20, false));
+        store.upsert(record("hidden", "Flaky archived", 30, true));
+        store.upsert_thread_item(
+            "old",
+            super::build_user_thread_item("old", "turn-1", "Why is the LOGIN test flaky?", &Default::default()),
+        );
+        store.upsert_thread_item(
+            "new",
+            super::build_agent_thread_item(
+                "new",
+                "turn-1",
+                0,
+                &format!("{} the login flow changed {}", "x".repeat(300), "y".repeat(300)),
+            ),
+        );
+        store.upsert_thread_item(
+            "hidden",
+            super::build_agent_thread_item("hidden", "turn-1", 0, "login"),
         );
+
+        let hits = super::search_threads_at(&workspace_path, "Login", 50);
+        let found: Vec<(&str, Option<&str>)> = hits
+            .iter()
+            .map(|hit| (hit.thread_id.as_str(), hit.item_id.as_deref()))
+            .collect();
         assert_eq!(
-            usage
-                .get("total")
-                .and_then(|v| v.get("totalTokens"))
-                .and_then(|v| v.as_i64()),
-            Some(48)
+            found,
+            vec![
+                ("new", Some("agent-new-turn-1")),
+                ("old", None),
+                ("old", Some("user-old-turn-1")),
+            ]
         );
+        assert_eq!(hits[0].thread_name, "Release notes");
+        assert_eq!(hits[0].updated_at, 20);
+        let snippet = hits[0].snippet.trim_matches('…');
+        assert_eq!(snippet.chars().count(), 200);
+        assert!(snippet.contains(" the login flow changed "));
+        assert_eq!(hits[2].snippet, "Why is the LOGIN test flaky?");
+
+        assert_eq!(super::search_threads_at(&workspace_path, "login", 2).len(), 2);
+        assert!(super::search_threads_at(&workspace_path, "deploy", 50).is_empty());
+        assert!(super::search_threads_at(&workspace_path, "   ", 50).is_empty());
 
         let _ = std::fs::remove_dir_all(PathBuf::from(&root));
     }
 
     #[test]
-    fn resolve_cli_bundle_near_bin_finds_npm_layout() {
-        let root = std::env::temp_dir().join(format!("micode-cli-near-bin-{}", Uuid::new_v4()));
-        let bin = root.join("bin");
-        let bundle = bin
-            .join("node_modules")
-            .join("@mi")
-            .join("mi-code-cli")
-            .join("dist")
-            .join("cli.js");
-        std::fs::create_dir_all(bundle.parent().expect("bundle parent"))
-            .expect("create bundle dir");
-        std::fs::write(&bundle, "var AVAILABLE_MODELS = [];").expect("write cli bundle");
-        let wrapper = bin.join("micode.cmd");
-        std::fs::write(&wrapper, "@echo off").expect("write wrapper");
+    fn export_renders_a_saved_thread_in_item_order() {
+        let root = std::env::temp_dir().join(format!("micode-thread-export-{}", Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("create workspace dir");
+        let workspace_path = workspace.to_string_lossy().to_string();
+        let mut store = super::LocalThreadStore::load(&workspace_path);
+        store.upsert(super::LocalThreadRecord {
+            thread_id: "thread-1".to_string(),
+            session_id: "session-1".to_string(),
+            title: "Release notes".to_string(),
+            archived: true,
+            updated_at: 0,
+            message_index: 0,
+            tags: vec!["docs".to_string()],
+            last_seen_item_seq: None,
+            kickoff: None,
+            pinned: false,
+            archived_at: None,
+            access_mode: None,
+            title_source: None,
+        });
+        store.upsert_thread_item(
+            "thread-1",
+            super::build_user_thread_item("thread-1", "turn-1", "Draft them", &Default::default()),
+        );
+        store.upsert_thread_item(
+            "thread-1",
+            super::build_agent_thread_item("thread-1", "turn-1", 0, "Here they are."),
+        );
 
-        let resolved = resolve_cli_bundle_near_bin(&wrapper);
-        assert!(resolved.is_some());
+        let markdown =
+            super::export_thread_at(&workspace_path, "thread-1", super::ThreadExportFormat::Markdown)
+                .expect("markdown export");
+        assert!(markdown.starts_with("# Release notes\n"));
+        assert!(markdown.contains("## User\n\nDraft them\n\n## Assistant\n\nHere they are.\n"));
+        let json = super::export_thread_at(&workspace_path, "thread-1", super::ThreadExportFormat::Json)
+            .expect("json export");
+        let json: Value = serde_json::from_str(&json).expect("json");
+        assert_eq!(json["thread"]["tags"], json!(["docs"]));
+        assert_eq!(json["items"].as_array().map(Vec::len), Some(2));
         assert!(
-            resolved
-                .and_then(|path| path.file_name().map(|name| name == "cli.js"))
-                .unwrap_or(false)
+            super::export_thread_at(&workspace_path, "missing", super::ThreadExportFormat::Json).is_err()
         );
 
         let _ = std::fs::remove_dir_all(PathBuf::from(&root));
     }
 
     #[test]
-    fn normalize_wrapper_cli_token_expands_windows_dp0_path() {
-        let root = std::env::temp_dir().join(format!("micode-cli-token-{}", Uuid::new_v4()));
-        let bundle = root
-            .join("node_modules")
-            .join("@mi")
-            .join("mi-code-cli")
-            .join("dist")
-            .join("cli.js");
-        std::fs::create_dir_all(bundle.parent().expect("bundle parent"))
-            .expect("create bundle dir");
-        std::fs::write(&bundle, "var AVAILABLE_MODELS = [];").expect("write cli bundle");
-        let wrapper = root.join("micode.cmd");
-        std::fs::write(&wrapper, "@echo off").expect("write wrapper");
+    fn pinned_threads_persist_list_first_and_refuse_plain_archive() {
+        let root = std::env::temp_dir().join(format!("micode-thread-pins-{}", Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        let state_dir = workspace.join(".micodemonitor");
+        std::fs::create_dir_all(&state_dir).expect("create state dir");
+        // Written before pinning existed: no `pinned` field.
+        let legacy = json!([
+            { "threadId": "old", "sessionId": "s-old", "title": "Old", "archived": false, "updatedAt": 10, "messageIndex": 0 },
+            { "threadId": "new", "sessionId": "s-new", "title": "New", "archived": false, "updatedAt": 20, "messageIndex": 0 },
+            { "threadId": "gone", "sessionId": "s-gone", "title": "Gone", "archived": true, "updatedAt": 30, "messageIndex": 0 },
+        ]);
+        std::fs::write(state_dir.join("sessions.json"), legacy.to_string())
+            .expect("write sessions.json");
+        let workspace_path = workspace.to_string_lossy().to_string();
 
-        let resolved = normalize_wrapper_cli_token(
-            "%~dp0\\node_modules\\@mi\\mi-code-cli\\dist\\cli.js",
-            &wrapper,
+        let mut store = super::LocalThreadStore::load(&workspace_path);
+        assert!(store.records.iter().all(|entry| !entry.pinned));
+        let order = |store: &super::LocalThreadStore| -> Vec<String> {
+            store
+                .list_for_display()
+                .into_iter()
+                .map(|entry| entry.thread_id)
+                .collect()
+        };
+        assert_eq!(order(&store), vec!["new", "old"]);
+
+        assert!(store.set_pinned("old", true));
+        assert!(!store.set_pinned("missing", true));
+        let store = super::LocalThreadStore::load(&workspace_path);
+        assert!(store.by_thread_id("old").expect("old").pinned);
+        assert_eq!(order(&store), vec!["old", "new"]);
+
+        let error = store.check_archivable("old", false).unwrap_err();
+        assert_eq!(error.code, ErrorCode::ThreadPinned);
+        assert!(store.check_archivable("old", true).is_ok());
+        assert!(store.check_archivable("new", false).is_ok());
+        assert!(store.check_archivable("missing", false).is_ok());
+
+        let _ = std::fs::remove_dir_all(PathBuf::from(&root));
+    }
+
+    #[test]
+    fn archive_keeps_the_thread_until_retention_runs_out() {
+        let root =
+            std::env::temp_dir().join(format!("micode-thread-archive-{}", Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("create workspace dir");
+        let workspace_path = workspace.to_string_lossy().to_string();
+        let mut store = super::LocalThreadStore::load(&workspace_path);
+        let record = |thread_id: &str, updated_at, archived, archived_at| super::LocalThreadRecord {
+            thread_id: thread_id.to_string(),
+            session_id: format!("session-{thread_id}"),
+            title: thread_id.to_string(),
+            archived,
+            updated_at,
+            message_index: 0,
+            tags: Vec::new(),
+            last_seen_item_seq: None,
+            kickoff: None,
+            pinned: false,
+            archived_at,
+            access_mode: None,
+            title_source: None,
+        };
+        let now = 100 * super::DAY_SECS;
+        let cutoff = now - 30 * super::DAY_SECS;
+        store.upsert(record("expired", 0, true, Some(cutoff - 1)));
+        store.upsert(record("at-cutoff", 0, true, Some(cutoff)));
+        store.upsert(record("recent", 0, true, Some(now - super::DAY_SECS)));
+        // Archived before `archivedAt` existed: counts from `updatedAt`.
+        store.upsert(record("legacy", cutoff - 1, true, None));
+        store.upsert(record("active", 0, false, None));
+
+        let mut expired: Vec<String> = store.expired_archived(30, now).into_iter().collect();
+        expired.sort();
+        assert_eq!(expired, vec!["expired", "legacy"]);
+        assert!(store.expired_archived(0, now).is_empty());
+        // Exactly one retention period ago is not yet past it.
+        assert_eq!(store.expired_archived(1, now).len(), 3);
+
+        // Archiving keeps the items; restoring brings the thread back as it was.
+        store.upsert_thread_item(
+            "active",
+            super::build_agent_thread_item("active", "turn-1", 0, "kept"),
         );
-        assert!(resolved.is_some());
-        assert!(
-            resolved
-                .and_then(|path| path.file_name().map(|name| name == "cli.js"))
-                .unwrap_or(false)
+        assert!(store.archive("active", now));
+        assert!(!store.list_unarchived().iter().any(|entry| entry.thread_id == "active"));
+        assert_eq!(store.list_archived()[0].thread_id, "active");
+        assert!(store.thread_items_path("active").exists());
+        assert!(store.restore("active"));
+        assert!(!store.restore("active"));
+        assert!(!store.restore("missing"));
+        let restored = store.by_thread_id("active").expect("restored");
+        assert!(!restored.archived && restored.archived_at.is_none());
+        assert_eq!(store.read_thread_items("active").len(), 1);
+
+        store.upsert_thread_item(
+            "expired",
+            super::build_agent_thread_item("expired", "turn-1", 0, "old"),
         );
+        assert_eq!(store.purge_expired_archived(30, now), 2);
+        assert!(store.by_thread_id("expired").is_none());
+        assert!(!store.thread_items_path("expired").exists());
+        let kept: Vec<String> = super::LocalThreadStore::load(&workspace_path)
+            .list_archived()
+            .into_iter()
+            .map(|entry| entry.thread_id)
+            .collect();
+        assert_eq!(kept, vec!["recent", "at-cutoff"]);
 
         let _ = std::fs::remove_dir_all(PathBuf::from(&root));
     }
 
     #[test]
-    fn local_thread_store_persists_and_updates_thread_items() {
-        let root = std::env::temp_dir().join(format!("micode-thread-store-{}", Uuid::new_v4()));
+    fn a_burst_of_item_upserts_is_written_once_per_flush() {
+        let root = std::env::temp_dir().join(format!("micode-item-flush-{}", Uuid::new_v4()));
         let workspace = root.join("workspace");
         std::fs::create_dir_all(&workspace).expect("create workspace dir");
         let workspace_path = workspace.to_string_lossy().to_string();
-        let mut store = super::LocalThreadStore::load(&workspace_path);
+        let writes = |store: &super::LocalThreadStore| {
+            store
+                .items_file_writes
+                .load(std::sync::atomic::Ordering::Relaxed)
+        };
+        let saved = |count: usize| {
+            super::LocalThreadStore::load(&workspace_path)
+                .read_thread_items("thread-1")
+                .len()
+                == count
+        };
+        let item = |turn: usize| {
+            super::build_agent_thread_item("thread-1", &format!("turn-{turn}"), 0, "chunk")
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            let mut store = super::LocalThreadStore::load(&workspace_path);
+            let notify = store.enable_write_behind();
+            let store = std::sync::Arc::new(tokio::sync::Mutex::new(store));
+            super::spawn_thread_items_flusher(&store, notify);
+            for turn in 0..50 {
+                store.lock().await.upsert_thread_item("thread-1", item(turn));
+            }
+            {
+                // Nothing written yet, but reads already see every item.
+                let store = store.lock().await;
+                assert_eq!(writes(&store), 0);
+                assert_eq!(store.load_thread_items("thread-1").len(), 50);
+                assert!(!store.thread_items_path("thread-1").exists());
+            }
+            tokio::time::sleep(super::THREAD_ITEMS_FLUSH_DELAY * 3).await;
+            assert_eq!(writes(&*store.lock().await), 1);
+            assert!(saved(50));
+
+            // A forced flush, as at the end of a turn, does not wait for the delay.
+            store.lock().await.upsert_thread_item("thread-1", item(50));
+            super::flush_thread_items(&store).await;
+            assert_eq!(writes(&*store.lock().await), 2);
+            assert!(saved(51));
+
+            // Dropping the store writes what is left.
+            store.lock().await.upsert_thread_item("thread-1", item(51));
+            drop(store);
+            assert!(saved(52));
+        });
 
-        let thread_id = "thread-1";
-        store.upsert(super::LocalThreadRecord {
+        let _ = std::fs::remove_dir_all(PathBuf::from(&root));
+    }
+
+    #[test]
+    fn maintenance_leaves_unflushed_items_files_to_the_flush() {
+        let root = std::env::temp_dir().join(format!("micode-item-evict-{}", Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("create workspace dir");
+        let mut store = super::LocalThreadStore::load(&workspace.to_string_lossy());
+        let _notify = store.enable_write_behind();
+        let item = super::build_agent_thread_item("thread-1", "turn-1", 0, "chunk");
+        store.upsert_thread_item("thread-1", item);
+        let path = store.thread_items_path("thread-1");
+        assert!(store.has_dirty_items_at(&path));
+        assert!(!store.has_dirty_items_at(&store.thread_items_path("thread-2")));
+
+        // A dirty list is never evicted, a written one is.
+        store.evict_clean_items_at(&path);
+        assert!(store.cached_items().items.contains_key("thread-1"));
+        super::write_items_files(&store.take_dirty_items());
+        assert!(!store.has_dirty_items_at(&path));
+        store.evict_clean_items_at(&path);
+        assert!(store.cached_items().items.is_empty());
+        assert_eq!(store.load_thread_items("thread-1").len(), 1);
+
+        let _ = std::fs::remove_dir_all(PathBuf::from(&root));
+    }
+
+    #[test]
+    fn seen_marker_tracks_unseen_thread_items() {
+        let root = std::env::temp_dir().join(format!("micode-thread-seen-{}", Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("create workspace dir");
+        let workspace_path = workspace.to_string_lossy().to_string();
+        let mut store = super::LocalThreadStore::load(&workspace_path);
+        let record = |thread_id: &str, last_seen_item_seq| super::LocalThreadRecord {
             thread_id: thread_id.to_string(),
-            session_id: "session-1".to_string(),
-            title: "New Thread".to_string(),
+            session_id: String::new(),
+            title: thread_id.to_string(),
             archived: false,
             updated_at: 1,
             message_index: 0,
-        });
+            tags: Vec::new(),
+            last_seen_item_seq,
+            kickoff: None,
+            pinned: false,
+            archived_at: None,
+            access_mode: None,
+            title_source: None,
+        };
+        store.upsert(record("fresh", Some(0)));
+        store.upsert(record("legacy", None));
+        for thread_id in ["fresh", "legacy"] {
+            for index in 0..3 {
+                store.upsert_thread_item(thread_id, json!({ "id": format!("item-{index}") }));
+            }
+        }
 
-        store.upsert_thread_item(
-            thread_id,
-            json!({
-                "id": "agent-thread-1-turn-1",
-                "type": "agentMessage",
-                "text": "hello"
-            }),
+        let fresh = store.by_thread_id("fresh").expect("fresh");
+        assert_eq!(store.unseen_items(&fresh).len(), 3);
+        let legacy = store.by_thread_id("legacy").expect("legacy");
+        assert!(store.unseen_items(&legacy).is_empty());
+        assert!(store.last_item_at("fresh").is_some());
+
+        assert_eq!(store.mark_seen("fresh", Some(2)), Some(2));
+        let fresh = store.by_thread_id("fresh").expect("fresh");
+        let unseen = store.unseen_items(&fresh);
+        assert_eq!(unseen.len(), 1);
+        assert_eq!(unseen[0].0, 3);
+        assert_eq!(unseen[0].1["id"], "item-2");
+
+        // Re-streaming an existing item keeps its position and does not unsee earlier ones.
+        store.upsert_thread_item("fresh", json!({ "id": "item-0", "text": "edited" }));
+        let fresh = store.by_thread_id("fresh").expect("fresh");
+        assert_eq!(store.unseen_items(&fresh).len(), 1);
+
+        assert_eq!(store.mark_seen("fresh", Some(99)), Some(3));
+        assert_eq!(store.mark_seen("missing", None), None);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn prompt_timeout_prefers_workspace_override_and_zero_disables_it() {
+        assert_eq!(
+            resolve_prompt_timeout(None, 120),
+            Some(std::time::Duration::from_secs(120))
         );
-        store.upsert_thread_item(
-            thread_id,
-            json!({
-                "id": "agent-thread-1-turn-1",
-                "type": "agentMessage",
-                "text": "hello world"
-            }),
+        assert_eq!(
+            resolve_prompt_timeout(Some(5), 120),
+            Some(std::time::Duration::from_secs(5))
         );
+        assert_eq!(resolve_prompt_timeout(Some(0), 120), None);
+        assert_eq!(resolve_prompt_timeout(None, 0), None);
+    }
 
-        let loaded = store.load_thread_items(thread_id);
-        assert_eq!(loaded.len(), 1);
-        assert_eq!(
-            loaded[0].get("text").and_then(Value::as_str),
-            Some("hello world")
+    /// Event sink that hands every app-server event to the test.
+    #[derive(Clone)]
+    struct ChannelSink(tokio::sync::mpsc::UnboundedSender<AppServerEvent>);
+
+    impl EventSink for ChannelSink {
+        fn emit_app_server_event(&self, event: AppServerEvent) {
+            let _ = self.0.send(event);
+        }
+
+        fn emit_terminal_output(&self, _event: TerminalOutput) {}
+
+        fn emit_terminal_exit(&self, _event: TerminalExit) {}
+    }
+
+    fn session_runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .expect("runtime")
+    }
+
+    /// Writes a stand-in agent into `root` that speaks just enough ACP for a session. It
+    /// answers `initialize`, `session/new` and anything else with an empty result, except
+    /// `session/prompt`, which runs the shell snippet `on_prompt` with the request id in
+    /// `$id` and the session in `$session`: the snippet prints the reply itself, or stores
+    /// the id in `prompt_id` to leave the prompt open until a `session/cancel` ends it.
+    #[cfg(unix)]
+    fn write_mock_agent(root: &std::path::Path, on_prompt: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = format!(
+            r#"#!/bin/sh
+if [ "$1" = "--version" ]; then echo "micode 0.0.0-mock"; exit 0; fi
+prompt_id=""
+sessions=0
+while IFS= read -r line; do
+  id=$(printf '%s\n' "$line" | sed -n 's/^{{[^{{]*"id":\([0-9]*\).*/\1/p')
+  session=$(printf '%s\n' "$line" | sed -n 's/.*"sessionId":"\([^"]*\)".*/\1/p')
+  case "$line" in
+    *'"method":"session/new"'*)
+      sessions=$((sessions + 1))
+      printf '{{"jsonrpc":"2.0","id":%s,"result":{{"sessionId":"mock-%s"}}}}\n' "$id" "$sessions" ;;
+    *'"method":"session/prompt"'*)
+      {on_prompt} ;;
+    *'"method":"session/cancel"'*)
+      if [ -n "$prompt_id" ]; then
+        printf '{{"jsonrpc":"2.0","id":%s,"result":{{"stopReason":"cancelled"}}}}\n' "$prompt_id"
+        prompt_id=""
+      fi
+      printf '{{"jsonrpc":"2.0","id":%s,"result":{{}}}}\n' "$id" ;;
+    *)
+      if [ -n "$id" ]; then printf '{{"jsonrpc":"2.0","id":%s,"result":{{}}}}\n' "$id"; fi ;;
+  esac
+done
+"#
         );
+        std::fs::create_dir_all(root).expect("create root");
+        let path = root.join("mock-agent.sh");
+        std::fs::write(&path, script).expect("write mock agent");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .expect("make mock agent executable");
+        path
+    }
+
+    /// Connects a session of a workspace at `root` to `agent`, with its events.
+    async fn spawn_mock_session(
+        root: &std::path::Path,
+        agent: &std::path::Path,
+        settings: SessionSettings,
+    ) -> (
+        std::sync::Arc<WorkspaceSession>,
+        tokio::sync::mpsc::UnboundedReceiver<AppServerEvent>,
+    ) {
+        let entry = WorkspaceEntry {
+            id: "ws-mock".to_string(),
+            name: "mock".to_string(),
+            path: root.to_string_lossy().to_string(),
+            agent_bin: Some(agent.to_string_lossy().to_string()),
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings::default(),
+            stack: None,
+        };
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let launch = SessionLaunch {
+            entry,
+            default_micode_bin: None,
+            agent_args: None,
+            agent_home: None,
+            session_settings: settings,
+            client_version: "0.0.0".to_string(),
+        };
+        let session = spawn_workspace_session_inner(
+            launch,
+            ChannelSink(tx),
+            &std::env::temp_dir(),
+            &AutoRunRegistry::default(),
+            &SettingsParseErrors::default(),
+        )
+        .await
+        .expect("spawn mock session");
+        (session, rx)
+    }
 
-        store.set_agent_item_token_usage(
-            thread_id,
-            "turn-1",
-            &json!({
-                "last": { "totalTokens": 12 },
-                "total": { "totalTokens": 24 }
-            }),
+    async fn start_mock_thread(session: &WorkspaceSession) -> String {
+        let response = session
+            .send_request("thread/start", json!({}))
+            .await
+            .expect("thread/start");
+        response["result"]["thread"]["id"]
+            .as_str()
+            .expect("thread id")
+            .to_string()
+    }
+
+    /// Params of the next event with `method`, skipping others; events reach the sink
+    /// through a forwarding task, so they may trail the request that caused them.
+    async fn next_event(
+        events: &mut tokio::sync::mpsc::UnboundedReceiver<AppServerEvent>,
+        method: &str,
+    ) -> Value {
+        let wait = async {
+            while let Some(event) = events.recv().await {
+                if event.message["method"] == method {
+                    return event.message["params"].clone();
+                }
+            }
+            panic!("event channel closed before {method}");
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(5), wait)
+            .await
+            .unwrap_or_else(|_| panic!("no {method} event"))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unanswered_prompt_times_out_against_a_mock_agent() {
+        let root = std::env::temp_dir().join(format!("micode-prompt-timeout-{}", Uuid::new_v4()));
+        let agent = write_mock_agent(&root, r#"prompt_id="$id""#);
+        session_runtime().block_on(async {
+            let settings = SessionSettings {
+                prompt_timeout_secs: 1,
+                ..SessionSettings::default()
+            };
+            let (session, mut events) = spawn_mock_session(&root, &agent, settings).await;
+            let thread_id = start_mock_thread(&session).await;
+            let error = session
+                .send_request(
+                    "turn/start",
+                    json!({ "threadId": thread_id, "input": [{ "type": "text", "text": "hi" }] }),
+                )
+                .await
+                .expect_err("the prompt should time out");
+            assert_eq!(error.code, ErrorCode::AcpTimeout);
+            assert!(error.message.contains("timed out"), "{error}");
+            // The first prompt and the retry on a fresh session both time out.
+            for _ in 0..2 {
+                let timed_out = next_event(&mut events, event_methods::TURN_TIMEOUT).await;
+                assert_eq!(timed_out["threadId"], thread_id.as_str());
+                assert_eq!(timed_out["timeoutMs"], 1000);
+            }
+            assert!(session.list_pending_requests().await.is_empty());
+            session.kill().await;
+        });
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn busy_thread_rejects_or_queues_new_turns() {
+        let root = std::env::temp_dir().join(format!("micode-turn-busy-{}", Uuid::new_v4()));
+        let agent = write_mock_agent(&root, r#"prompt_id="$id""#);
+        session_runtime().block_on(async {
+            let (session, _events) =
+                spawn_mock_session(&root, &agent, SessionSettings::default()).await;
+            let thread_id = start_mock_thread(&session).await;
+            let params =
+                json!({ "threadId": thread_id, "input": [{ "type": "text", "text": "hi" }] });
+            assert!(matches!(
+                session.admit_turn(&thread_id, params.clone(), false),
+                TurnAdmission::Start(_)
+            ));
+            let error = session
+                .start_thread_turn(&thread_id, params.clone(), false)
+                .await
+                .expect_err("a busy thread should refuse the turn");
+            assert_eq!(error.code, ErrorCode::TurnInProgress);
+            let queued = session
+                .start_thread_turn(&thread_id, params, true)
+                .await
+                .expect("queued turn");
+            assert_eq!(queued["result"]["queued"], true);
+            session.kill().await;
+        });
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn missing_agent_binary_fails_with_bin_missing_code() {
+        let root = std::env::temp_dir().join(format!("micode-bin-missing-{}", Uuid::new_v4()));
+        session_runtime().block_on(async {
+            let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+            let launch = SessionLaunch {
+                entry: WorkspaceEntry {
+                    id: "ws-missing".to_string(),
+                    name: "missing".to_string(),
+                    path: std::env::temp_dir().to_string_lossy().to_string(),
+                    agent_bin: Some(root.join("micode").to_string_lossy().to_string()),
+                    kind: WorkspaceKind::Main,
+                    parent_id: None,
+                    worktree: None,
+                    settings: WorkspaceSettings::default(),
+                    stack: None,
+                },
+                default_micode_bin: None,
+                agent_args: None,
+                agent_home: None,
+                session_settings: SessionSettings::default(),
+                client_version: "0.0.0".to_string(),
+            };
+            let error = spawn_workspace_session_inner(
+                launch,
+                ChannelSink(tx),
+                &std::env::temp_dir(),
+                &AutoRunRegistry::default(),
+                &SettingsParseErrors::default(),
+            )
+            .await
+            .err()
+            .expect("a missing agent binary should not spawn");
+            assert_eq!(error.code, ErrorCode::MicodeBinMissing, "{error}");
+        });
+    }
+
+    #[test]
+    fn primed_helpers_never_share_a_session() {
+        let root = std::env::temp_dir().join(format!("micode-primer-lease-{}", Uuid::new_v4()));
+        let agent = write_mock_agent(
+            &root,
+            r#"printf '{"jsonrpc":"2.0","id":%s,"result":{"stopReason":"end_turn"}}\n' "$id""#,
         );
-        let loaded_with_usage = store.load_thread_items(thread_id);
-        assert_eq!(
-            loaded_with_usage[0]
-                .get("tokenUsage")
-                .and_then(|value| value.get("last"))
-                .and_then(|value| value.get("totalTokens"))
-                .and_then(Value::as_i64),
-            Some(12)
+        session_runtime().block_on(async {
+            let (session, _events) =
+                spawn_mock_session(&root, &agent, SessionSettings::default()).await;
+            let start_helper = || async {
+                let response = session
+                    .send_request(
+                        "thread/start",
+                        json!({ "_background": true, "_primer": true }),
+                    )
+                    .await
+                    .expect("thread/start");
+                let thread_id = response["result"]["thread"]["id"]
+                    .as_str()
+                    .expect("thread id")
+                    .to_string();
+                let session_id = session.background_threads.lock().await[&thread_id].clone();
+                (thread_id, session_id)
+            };
+            let (first, first_session) = start_helper().await;
+            session
+                .send_request("thread/archive", json!({ "threadId": first }))
+                .await
+                .expect("archive first helper");
+            let spare = session
+                .primer
+                .lock()
+                .await
+                .spare
+                .as_ref()
+                .map(|spare| spare.session_id.clone())
+                .expect("a spare is seeded when the lease ends");
+            assert_ne!(spare, first_session);
+
+            let (_second, second_session) = start_helper().await;
+            assert_eq!(second_session, spare);
+            assert!(session.primer.lock().await.spare.is_none());
+            session.kill().await;
+        });
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn heartbeat_flag_is_reported_once_per_unresponsive_stretch() {
+        let root = std::env::temp_dir().join(format!("micode-unresponsive-{}", Uuid::new_v4()));
+        let agent = write_mock_agent(
+            &root,
+            r#"printf '{"jsonrpc":"2.0","id":%s,"result":{"stopReason":"end_turn"}}\n' "$id""#,
         );
+        session_runtime().block_on(async {
+            let (session, mut events) =
+                spawn_mock_session(&root, &agent, SessionSettings::default()).await;
+            let threshold = Duration::from_secs(1);
+            let miss_pings = || {
+                session.update_health(HealthTracker::ping_missed);
+                session.update_health(HealthTracker::ping_missed);
+                session.last_activity_ms.store(0, Ordering::SeqCst);
+            };
+            assert!(session.check_unresponsive(threshold).await.is_none());
 
-        assert!(store.delete(thread_id));
-        assert!(store.load_thread_items(thread_id).is_empty());
+            miss_pings();
+            let (_, pending) = session
+                .check_unresponsive(threshold)
+                .await
+                .expect("flagged");
+            assert!(pending.is_empty());
+            assert!(session.check_unresponsive(threshold).await.is_none());
+
+            session.check_health().await;
+            assert_eq!(
+                next_event(&mut events, event_methods::MICODE_HEALTHY).await["workspaceId"],
+                session.entry.id
+            );
+            assert!(!session.is_unresponsive());
 
-        let _ = std::fs::remove_dir_all(PathBuf::from(&root));
+            // A silent tool call holds the report off until it finishes.
+            miss_pings();
+            session
+                .track_running_tool_call("tool-1", "thread-1", "session-1")
+                .await;
+            assert!(session.check_unresponsive(threshold).await.is_none());
+            session.finish_running_tool_call("thread-1", "tool-1").await;
+            session.last_activity_ms.store(0, Ordering::SeqCst);
+            assert!(session.check_unresponsive(threshold).await.is_some());
+            session.kill().await;
+        });
+        let _ = std::fs::remove_dir_all(&root);
     }
 }
@@ -0,0 +1,539 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::backend::event_methods;
+use crate::rules::{match_prefix_rules, PrefixRule, RuleDecision};
+
+/// Why an approval stopped being pending, reported with `workspace/approvalResolved`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ApprovalResolvedBy {
+    User,
+    /// Answered by a remembered approval rule without showing a dialog.
+    AutoRule,
+    TurnCancelled,
+    SessionRestarted,
+    /// Nobody answered within `approvalTimeoutSecs`; the agent got the cancelled outcome.
+    TimedOut,
+}
+
+impl ApprovalResolvedBy {
+    /// Reads the optional `resolvedBy` hint clients send with their decision.
+    pub(crate) fn from_result(result: &Value) -> Self {
+        match result.get("resolvedBy").and_then(Value::as_str) {
+            Some("auto-rule") => Self::AutoRule,
+            Some("timed-out") => Self::TimedOut,
+            _ => Self::User,
+        }
+    }
+}
+
+/// A `session/request_permission` the agent is waiting on.
+#[derive(Debug, Clone)]
+pub(crate) struct PendingApproval {
+    pub(crate) request_id: Value,
+    pub(crate) params: Value,
+    pub(crate) thread_id: String,
+    /// Later requests for the same tool call, answered together with this one.
+    pub(crate) duplicates: Vec<Value>,
+    /// When the first request arrived; the approval timeout counts from here.
+    pub(crate) requested_at: Instant,
+    /// Set once `workspace/approvalReminder` went out for it.
+    pub(crate) reminded: bool,
+}
+
+impl PendingApproval {
+    pub(crate) fn request_ids(&self) -> impl Iterator<Item = &Value> {
+        std::iter::once(&self.request_id).chain(&self.duplicates)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ApprovalInsert {
+    New,
+    /// Another request for the same tool call is already pending; no dialog is needed.
+    Duplicate,
+}
+
+pub(crate) fn approval_key(request_id: &Value) -> String {
+    request_id
+        .as_i64()
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| request_id.to_string())
+}
+
+fn tool_call_id(params: &Value) -> Option<&str> {
+    params
+        .get("toolCall")
+        .and_then(|tool_call| tool_call.get("toolCallId"))
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct PendingApprovals {
+    by_key: HashMap<String, PendingApproval>,
+}
+
+impl PendingApprovals {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.by_key.is_empty()
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        request_id: Value,
+        params: Value,
+        thread_id: &str,
+    ) -> ApprovalInsert {
+        if let Some(tool_call_id) = tool_call_id(&params) {
+            let existing = self.by_key.values_mut().find(|pending| {
+                pending.thread_id == thread_id
+                    && self::tool_call_id(&pending.params) == Some(tool_call_id)
+            });
+            if let Some(existing) = existing {
+                existing.duplicates.push(request_id);
+                return ApprovalInsert::Duplicate;
+            }
+        }
+        self.by_key.insert(
+            approval_key(&request_id),
+            PendingApproval {
+                request_id,
+                params,
+                thread_id: thread_id.to_string(),
+                duplicates: Vec::new(),
+                requested_at: Instant::now(),
+                reminded: false,
+            },
+        );
+        ApprovalInsert::New
+    }
+
+    pub(crate) fn take(&mut self, request_id: &Value) -> Option<PendingApproval> {
+        self.by_key.remove(&approval_key(request_id))
+    }
+
+    pub(crate) fn take_for_thread(&mut self, thread_id: &str) -> Vec<PendingApproval> {
+        let keys = self
+            .by_key
+            .iter()
+            .filter(|(_, pending)| pending.thread_id == thread_id)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        keys.iter()
+            .filter_map(|key| self.by_key.remove(key))
+            .collect()
+    }
+
+    pub(crate) fn take_all(&mut self) -> Vec<PendingApproval> {
+        self.by_key.drain().map(|(_, pending)| pending).collect()
+    }
+
+    /// Takes the approvals pending for `timeout` or longer and marks the ones past half of
+    /// it as reminded, so each reminder is reported once.
+    pub(crate) fn sweep(&mut self, now: Instant, timeout: Duration) -> ApprovalSweep {
+        let mut sweep = ApprovalSweep::default();
+        let expired = self
+            .by_key
+            .iter()
+            .filter(|(_, pending)| now.saturating_duration_since(pending.requested_at) >= timeout)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        sweep.expired = expired
+            .iter()
+            .filter_map(|key| self.by_key.remove(key))
+            .collect();
+        for pending in self.by_key.values_mut() {
+            if !pending.reminded
+                && now.saturating_duration_since(pending.requested_at) >= timeout / 2
+            {
+                pending.reminded = true;
+                sweep.reminders.push(pending.clone());
+            }
+        }
+        sweep
+    }
+}
+
+/// What `PendingApprovals::sweep` found.
+#[derive(Debug, Default)]
+pub(crate) struct ApprovalSweep {
+    /// Still pending and past half of the timeout.
+    pub(crate) reminders: Vec<PendingApproval>,
+    /// No longer pending; the agent still has to be answered.
+    pub(crate) expired: Vec<PendingApproval>,
+}
+
+/// Maps a client decision onto one of the permission options the agent offered.
+pub(crate) fn approval_response(params: &Value, result: &Value) -> Value {
+    let options = params
+        .get("options")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let decision = result
+        .get("decision")
+        .and_then(Value::as_str)
+        .unwrap_or("decline");
+    if decision == "cancel" {
+        return cancelled_response();
+    }
+    let explicit_option_id = result
+        .get("optionId")
+        .and_then(Value::as_str)
+        .map(|value| value.to_string());
+    let preferred = match decision {
+        "accept_always" => vec!["allow_always"],
+        "accept_once" => vec!["allow_once"],
+        "decline_always" => vec!["reject_always"],
+        "decline_once" => vec!["reject_once"],
+        "accept" => vec!["allow_once", "allow_always"],
+        _ => vec!["reject_once", "reject_always"],
+    };
+    let option_id = preferred
+        .into_iter()
+        .find_map(|kind| {
+            options.iter().find_map(|opt| {
+                if opt.get("kind").and_then(Value::as_str) == Some(kind) {
+                    opt.get("optionId")
+                        .and_then(Value::as_str)
+                        .map(|v| v.to_string())
+                } else {
+                    None
+                }
+            })
+        })
+        .or(explicit_option_id)
+        .or_else(|| {
+            options.iter().find_map(|opt| {
+                opt.get("optionId")
+                    .and_then(Value::as_str)
+                    .map(|v| v.to_string())
+            })
+        });
+    if let Some(option_id) = option_id {
+        json!({ "outcome": { "outcome": "selected", "optionId": option_id } })
+    } else {
+        cancelled_response()
+    }
+}
+
+/// A permission request answered by a remembered rule instead of a dialog.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AutoDecision {
+    /// Client-style decision for `send_response`.
+    pub(crate) result: Value,
+    /// `workspace/autoApproved` or `workspace/autoDenied`, sent instead of the dialog.
+    pub(crate) method: &'static str,
+    pub(crate) params: Value,
+    /// The rule that decided, whose hit count goes up.
+    pub(crate) rule: PrefixRule,
+}
+
+/// Whether a token chains, pipes, substitutes or redirects commands. A rule vouches for
+/// the command its prefix names, not for whatever else the shell line runs.
+fn has_shell_control_operator(command: &[String]) -> bool {
+    command
+        .iter()
+        .any(|token| token.contains([';', '&', '|', '`', '<', '>', '\n']) || token.contains("$("))
+}
+
+/// Checks the request's command tokens against the remembered rules; `None` leaves the
+/// decision to the user. Allow rules never answer a command with shell control operators.
+pub(crate) fn auto_decision(
+    rules: &[PrefixRule],
+    command: &[String],
+    request_id: &Value,
+    thread_id: &str,
+) -> Option<AutoDecision> {
+    let rule = match_prefix_rules(rules, command)?;
+    let (decision, method) = match rule.decision {
+        RuleDecision::Allow if has_shell_control_operator(command) => return None,
+        RuleDecision::Allow => ("accept", event_methods::WORKSPACE_AUTO_APPROVED),
+        RuleDecision::Deny => ("decline", event_methods::WORKSPACE_AUTO_DENIED),
+    };
+    Some(AutoDecision {
+        result: json!({ "decision": decision, "resolvedBy": ApprovalResolvedBy::AutoRule }),
+        method,
+        params: json!({
+            "requestId": request_id,
+            "threadId": thread_id,
+            "command": command,
+            "rule": rule.pattern,
+            "decision": decision,
+        }),
+        rule: rule.clone(),
+    })
+}
+
+/// The outcome ACP expects for permission requests of a cancelled prompt turn.
+pub(crate) fn cancelled_response() -> Value {
+    json!({ "outcome": { "outcome": "cancelled" } })
+}
+
+/// The decision `send_response` is given for an approval nobody answered in time.
+pub(crate) fn timed_out_result() -> Value {
+    json!({ "decision": "cancel", "resolvedBy": ApprovalResolvedBy::TimedOut })
+}
+
+fn tool_call_title(params: &Value) -> Option<&str> {
+    params
+        .get("toolCall")
+        .and_then(|tool_call| tool_call.get("title"))
+        .and_then(Value::as_str)
+}
+
+/// `workspace/approvalReminder` once half of `timeout` passed without an answer.
+pub(crate) fn approval_reminder_params(
+    pending: &PendingApproval,
+    now: Instant,
+    timeout: Duration,
+) -> Value {
+    let remaining = timeout.saturating_sub(now.saturating_duration_since(pending.requested_at));
+    json!({
+        "requestId": pending.request_id,
+        "threadId": pending.thread_id,
+        "title": tool_call_title(&pending.params),
+        "remainingSecs": remaining.as_secs(),
+        "timeoutSecs": timeout.as_secs(),
+    })
+}
+
+/// `workspace/approvalTimedOut` when the approval is answered with the cancelled outcome.
+pub(crate) fn approval_timed_out_params(pending: &PendingApproval, timeout: Duration) -> Value {
+    json!({
+        "requestId": pending.request_id,
+        "duplicateRequestIds": pending.duplicates,
+        "threadId": pending.thread_id,
+        "title": tool_call_title(&pending.params),
+        "timeoutSecs": timeout.as_secs(),
+    })
+}
+
+pub(crate) fn approval_resolved_params(
+    pending: &PendingApproval,
+    decision: &str,
+    resolved_by: ApprovalResolvedBy,
+) -> Value {
+    json!({
+        "requestId": pending.request_id,
+        "duplicateRequestIds": pending.duplicates,
+        "threadId": pending.thread_id,
+        "decision": decision,
+        "resolvedBy": resolved_by,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{PrefixRule, RuleDecision};
+
+    fn permission(tool_call_id: &str) -> Value {
+        json!({
+            "sessionId": "s-1",
+            "toolCall": { "toolCallId": tool_call_id, "title": "rm -rf build" },
+            "options": [
+                { "optionId": "yes", "kind": "allow_once" },
+                { "optionId": "no", "kind": "reject_once" }
+            ]
+        })
+    }
+
+    #[test]
+    fn interrupt_cancels_pending_approvals_including_duplicates() {
+        let mut approvals = PendingApprovals::default();
+        assert_eq!(
+            approvals.insert(json!(7), permission("call-1"), "thread-1"),
+            ApprovalInsert::New
+        );
+        assert_eq!(
+            approvals.insert(json!(8), permission("call-1"), "thread-1"),
+            ApprovalInsert::Duplicate
+        );
+        assert_eq!(
+            approvals.insert(json!("other"), permission("call-2"), "thread-2"),
+            ApprovalInsert::New
+        );
+
+        let cancelled = approvals.take_for_thread("thread-1");
+        assert_eq!(cancelled.len(), 1);
+        let ids = cancelled[0].request_ids().cloned().collect::<Vec<_>>();
+        assert_eq!(ids, vec![json!(7), json!(8)]);
+        assert_eq!(cancelled_response()["outcome"]["outcome"], "cancelled");
+        let event =
+            approval_resolved_params(&cancelled[0], "cancel", ApprovalResolvedBy::TurnCancelled);
+        assert_eq!(event["resolvedBy"], "turn-cancelled");
+        assert_eq!(event["duplicateRequestIds"], json!([8]));
+
+        // A late answer for the cancelled request finds nothing to resolve.
+        assert!(approvals.take(&json!(7)).is_none());
+        let remaining = approvals
+            .take(&json!("other"))
+            .expect("other thread untouched");
+        assert_eq!(remaining.thread_id, "thread-2");
+        assert!(approvals.is_empty());
+    }
+
+    #[test]
+    fn maps_decisions_to_offered_options() {
+        let params = permission("call-1");
+        let accept = approval_response(&params, &json!({ "decision": "accept" }));
+        assert_eq!(accept["outcome"]["optionId"], "yes");
+        let decline = approval_response(&params, &json!({ "decision": "decline" }));
+        assert_eq!(decline["outcome"]["optionId"], "no");
+        let none = approval_response(&json!({}), &json!({ "decision": "accept" }));
+        assert_eq!(none, cancelled_response());
+        assert_eq!(
+            ApprovalResolvedBy::from_result(
+                &json!({ "decision": "accept", "resolvedBy": "auto-rule" })
+            ),
+            ApprovalResolvedBy::AutoRule
+        );
+        assert_eq!(
+            ApprovalResolvedBy::from_result(&json!({ "decision": "accept" })),
+            ApprovalResolvedBy::User
+        );
+    }
+
+    #[test]
+    fn unanswered_approvals_are_reminded_once_then_cancelled() {
+        let timeout = Duration::from_millis(40);
+        let mut approvals = PendingApprovals::default();
+        approvals.insert(json!(7), permission("call-1"), "thread-1");
+        approvals.insert(json!(8), permission("call-1"), "thread-1");
+        let requested_at = approvals.by_key["7"].requested_at;
+
+        let early = approvals.sweep(requested_at + Duration::from_millis(10), timeout);
+        assert!(early.reminders.is_empty() && early.expired.is_empty());
+
+        let halfway = requested_at + Duration::from_millis(20);
+        let sweep = approvals.sweep(halfway, timeout);
+        assert!(sweep.expired.is_empty());
+        assert_eq!(sweep.reminders.len(), 1);
+        let reminder = approval_reminder_params(&sweep.reminders[0], halfway, timeout);
+        assert_eq!(reminder["requestId"], 7);
+        assert_eq!(reminder["title"], "rm -rf build");
+        let again = approvals.sweep(requested_at + Duration::from_millis(30), timeout);
+        assert!(again.reminders.is_empty(), "reminded only once");
+
+        let sweep = approvals.sweep(requested_at + timeout, timeout);
+        assert!(approvals.is_empty());
+        assert_eq!(sweep.expired.len(), 1);
+        let expired = &sweep.expired[0];
+        let ids = expired.request_ids().cloned().collect::<Vec<_>>();
+        assert_eq!(ids, vec![json!(7), json!(8)]);
+        let result = timed_out_result();
+        assert_eq!(
+            approval_response(&expired.params, &result),
+            json!({ "outcome": { "outcome": "cancelled" } })
+        );
+        assert_eq!(
+            ApprovalResolvedBy::from_result(&result),
+            ApprovalResolvedBy::TimedOut
+        );
+        let event = approval_timed_out_params(expired, timeout);
+        assert_eq!(event["duplicateRequestIds"], json!([8]));
+        assert_eq!(event["timeoutSecs"], 0);
+    }
+
+    #[test]
+    fn remembered_rules_answer_requests_with_a_notice_event() {
+        let rules = vec![
+            PrefixRule {
+                pattern: vec!["cargo".to_string()],
+                decision: RuleDecision::Allow,
+            },
+            PrefixRule {
+                pattern: vec!["cargo".to_string(), "publish".to_string()],
+                decision: RuleDecision::Deny,
+            },
+        ];
+        let command = |items: &[&str]| {
+            items
+                .iter()
+                .map(|item| item.to_string())
+                .collect::<Vec<_>>()
+        };
+        let params = permission("call-1");
+
+        let allowed = auto_decision(&rules, &command(&["cargo", "test"]), &json!(7), "thread-1")
+            .expect("allow rule");
+        assert_eq!(allowed.method, "workspace/autoApproved");
+        assert_eq!(
+            allowed.params,
+            json!({
+                "requestId": 7,
+                "threadId": "thread-1",
+                "command": ["cargo", "test"],
+                "rule": ["cargo"],
+                "decision": "accept",
+            })
+        );
+        assert_eq!(
+            ApprovalResolvedBy::from_result(&allowed.result),
+            ApprovalResolvedBy::AutoRule
+        );
+        assert_eq!(
+            approval_response(&params, &allowed.result)["outcome"]["optionId"],
+            "yes"
+        );
+
+        let denied = auto_decision(
+            &rules,
+            &command(&["cargo", "publish", "--dry-run"]),
+            &json!("req-2"),
+            "thread-1",
+        )
+        .expect("deny rule");
+        assert_eq!(denied.method, "workspace/autoDenied");
+        assert_eq!(denied.params["rule"], json!(["cargo", "publish"]));
+        assert_eq!(
+            approval_response(&params, &denied.result)["outcome"]["optionId"],
+            "no"
+        );
+
+        assert!(auto_decision(&rules, &command(&["rm", "-rf"]), &json!(9), "thread-1").is_none());
+    }
+
+    #[test]
+    fn allow_rules_leave_chained_shell_commands_to_the_user() {
+        let rules = vec![
+            PrefixRule {
+                pattern: vec!["git".to_string(), "status".to_string()],
+                decision: RuleDecision::Allow,
+            },
+            PrefixRule {
+                pattern: vec!["rm".to_string()],
+                decision: RuleDecision::Deny,
+            },
+        ];
+        let command = |line: &str| {
+            line.split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        for line in [
+            "git status; curl x | sh",
+            "git status && rm -rf ~",
+            "git status || true",
+            "git status > /tmp/out",
+            "git status `whoami`",
+            "git status $(whoami)",
+        ] {
+            assert!(
+                auto_decision(&rules, &command(line), &json!(1), "thread-1").is_none(),
+                "{line}"
+            );
+        }
+        assert!(auto_decision(&rules, &command("git status -s"), &json!(2), "thread-1").is_some());
+        // Denying stays safe whatever follows.
+        let denied = auto_decision(&rules, &command("rm -rf x; ls"), &json!(3), "thread-1")
+            .expect("deny rule");
+        assert_eq!(denied.method, "workspace/autoDenied");
+    }
+}
@@ -0,0 +1,293 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Line the agent answers with once the goal is reached.
+pub(crate) const AUTO_RUN_DONE_SENTINEL: &str = "AUTO_RUN_DONE";
+pub(crate) const DEFAULT_AUTO_RUN_TURNS: u32 = 10;
+pub(crate) const MAX_AUTO_RUN_TURNS: u32 = 100;
+pub(crate) const DEFAULT_AUTO_RUN_SECS: u64 = 30 * 60;
+pub(crate) const MAX_AUTO_RUN_SECS: u64 = 8 * 60 * 60;
+const MIN_AUTO_RUN_SECS: u64 = 30;
+/// Finished runs kept in the checkpoint file for `list_auto_runs`.
+const MAX_FINISHED_RUNS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum AutoRunStatus {
+    Running,
+    Completed,
+    TurnLimit,
+    TimeLimit,
+    Cancelled,
+    Failed,
+}
+
+impl AutoRunStatus {
+    pub(crate) fn is_finished(self) -> bool {
+        self != AutoRunStatus::Running
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            AutoRunStatus::Running => "is still running",
+            AutoRunStatus::Completed => "finished: the agent reported the goal as done",
+            AutoRunStatus::TurnLimit => "stopped: the turn limit was reached",
+            AutoRunStatus::TimeLimit => "stopped: the time limit was reached",
+            AutoRunStatus::Cancelled => "was cancelled",
+            AutoRunStatus::Failed => "failed",
+        }
+    }
+}
+
+/// What `start_auto_run` is called with. Without a thread a new one is started; the
+/// limits are clamped when the run is created.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AutoRunRequest {
+    #[serde(default)]
+    pub(crate) thread_id: Option<String>,
+    pub(crate) goal: String,
+    #[serde(default)]
+    pub(crate) max_turns: Option<u32>,
+    #[serde(default)]
+    pub(crate) max_duration_secs: Option<u64>,
+    #[serde(default)]
+    pub(crate) access_mode: Option<String>,
+}
+
+/// Checkpoint of an auto run. Written before every turn so a restarted app can pick the
+/// loop up at the same iteration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AutoRunState {
+    pub(crate) id: String,
+    pub(crate) thread_id: String,
+    pub(crate) goal: String,
+    pub(crate) max_turns: u32,
+    pub(crate) max_duration_secs: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) access_mode: Option<String>,
+    pub(crate) iteration: u32,
+    pub(crate) started_at_ms: u64,
+    pub(crate) updated_at_ms: u64,
+    pub(crate) status: AutoRunStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<String>,
+}
+
+impl AutoRunState {
+    pub(crate) fn deadline_ms(&self) -> u64 {
+        self.started_at_ms
+            .saturating_add(self.max_duration_secs.saturating_mul(1000))
+    }
+
+    /// The limit that stops the run before its next turn, if any.
+    pub(crate) fn limit_reached(&self, now_ms: u64) -> Option<AutoRunStatus> {
+        if self.iteration >= self.max_turns {
+            Some(AutoRunStatus::TurnLimit)
+        } else if now_ms >= self.deadline_ms() {
+            Some(AutoRunStatus::TimeLimit)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn summary_text(&self) -> String {
+        let mut text = format!(
+            "Auto run {} after {} of {} turns.",
+            self.status.describe(),
+            self.iteration,
+            self.max_turns
+        );
+        if let Some(error) = self.error.as_deref().filter(|error| !error.is_empty()) {
+            text.push_str(&format!(" Error: {error}"));
+        }
+        text
+    }
+
+    /// Thread item recording why the run stopped.
+    pub(crate) fn summary_item(&self) -> Value {
+        json!({
+            "id": format!("autorun-{}-{}", self.thread_id, self.id),
+            "type": "autoRunSummary",
+            "runId": self.id,
+            "status": self.status,
+            "iterations": self.iteration,
+            "maxTurns": self.max_turns,
+            "goal": self.goal,
+            "text": self.summary_text(),
+        })
+    }
+}
+
+pub(crate) fn clamp_max_turns(value: Option<u32>) -> u32 {
+    value
+        .unwrap_or(DEFAULT_AUTO_RUN_TURNS)
+        .clamp(1, MAX_AUTO_RUN_TURNS)
+}
+
+pub(crate) fn clamp_max_duration_secs(value: Option<u64>) -> u64 {
+    value
+        .unwrap_or(DEFAULT_AUTO_RUN_SECS)
+        .clamp(MIN_AUTO_RUN_SECS, MAX_AUTO_RUN_SECS)
+}
+
+pub(crate) fn goal_prompt(goal: &str) -> String {
+    format!(
+        "{}\n\nYou are running in auto mode: keep working toward this goal across turns without \
+         waiting for confirmation. When the goal is fully achieved, end your reply with a line \
+         containing only `{AUTO_RUN_DONE_SENTINEL}` after a short summary of what changed.",
+        goal.trim()
+    )
+}
+
+pub(crate) fn continuation_prompt(iteration: u32, max_turns: u32) -> String {
+    format!(
+        "Continue working toward the goal (turn {iteration} of {max_turns}). If the goal is \
+         fully achieved, summarize what changed and end your reply with a line containing \
+         only `{AUTO_RUN_DONE_SENTINEL}`."
+    )
+}
+
+/// Whether the agent's reply signals completion: the sentinel on a line of its own, or a
+/// JSON object line with `"done": true`.
+pub(crate) fn agent_signals_done(text: &str) -> bool {
+    text.lines().map(str::trim).any(|line| {
+        let line = line.trim_matches('`').trim();
+        if line == AUTO_RUN_DONE_SENTINEL {
+            return true;
+        }
+        line.starts_with('{')
+            && serde_json::from_str::<Value>(line)
+                .ok()
+                .and_then(|value| value.get("done").and_then(Value::as_bool))
+                == Some(true)
+    })
+}
+
+fn runs_path(workspace_path: &str) -> PathBuf {
+    PathBuf::from(workspace_path)
+        .join(".micodemonitor")
+        .join("auto-runs.json")
+}
+
+/// Serializes read-modify-write cycles of the checkpoint files across concurrent runs.
+fn store_lock() -> &'static Mutex<()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    &LOCK
+}
+
+fn read_runs(path: &Path) -> Vec<AutoRunState> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|raw| serde_json::from_str::<Vec<AutoRunState>>(&raw).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn load_runs(workspace_path: &str) -> Vec<AutoRunState> {
+    let _guard = store_lock().lock();
+    read_runs(&runs_path(workspace_path))
+}
+
+/// Upserts a checkpoint, trimming the oldest finished runs.
+pub(crate) fn save_run(workspace_path: &str, state: &AutoRunState) -> Result<(), String> {
+    let _guard = store_lock().lock();
+    let path = runs_path(workspace_path);
+    let mut runs = read_runs(&path);
+    match runs.iter_mut().find(|run| run.id == state.id) {
+        Some(existing) => *existing = state.clone(),
+        None => runs.push(state.clone()),
+    }
+    let finished = runs.iter().filter(|run| run.status.is_finished()).count();
+    if finished > MAX_FINISHED_RUNS {
+        let mut excess = finished - MAX_FINISHED_RUNS;
+        runs.retain(|run| {
+            if excess > 0 && run.status.is_finished() {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let raw = serde_json::to_string_pretty(&runs).map_err(|e| e.to_string())?;
+    std::fs::write(&path, raw).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        agent_signals_done, load_runs, save_run, AutoRunState, AutoRunStatus,
+        AUTO_RUN_DONE_SENTINEL,
+    };
+    use uuid::Uuid;
+
+    fn state(id: &str, status: AutoRunStatus) -> AutoRunState {
+        AutoRunState {
+            id: id.to_string(),
+            thread_id: "thread-1".to_string(),
+            goal: "migrate".to_string(),
+            max_turns: 3,
+            max_duration_secs: 60,
+            access_mode: None,
+            iteration: 0,
+            started_at_ms: 1_000,
+            updated_at_ms: 1_000,
+            status,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn detects_completion_signals() {
+        assert!(agent_signals_done(&format!(
+            "Migrated all call sites.\n{AUTO_RUN_DONE_SENTINEL}"
+        )));
+        assert!(agent_signals_done("All done.\n`AUTO_RUN_DONE`"));
+        assert!(agent_signals_done("Summary\n{\"done\": true}"));
+        assert!(!agent_signals_done("{\"done\": false}"));
+        assert!(!agent_signals_done(
+            "I will print AUTO_RUN_DONE when the goal is reached."
+        ));
+    }
+
+    #[test]
+    fn stops_on_turn_and_time_limits() {
+        let mut run = state("r", AutoRunStatus::Running);
+        assert_eq!(run.limit_reached(1_000), None);
+        assert_eq!(run.limit_reached(61_000), Some(AutoRunStatus::TimeLimit));
+        run.iteration = 3;
+        assert_eq!(run.limit_reached(1_000), Some(AutoRunStatus::TurnLimit));
+        run.status = AutoRunStatus::TurnLimit;
+        assert_eq!(run.summary_item()["type"], "autoRunSummary");
+        assert!(run.summary_text().contains("3 of 3 turns"));
+    }
+
+    #[test]
+    fn checkpoints_upsert_and_survive_reload() {
+        let root = std::env::temp_dir().join(format!("micode-auto-run-{}", Uuid::new_v4()));
+        let workspace = root.to_string_lossy().to_string();
+        let mut run = state("run-1", AutoRunStatus::Running);
+        save_run(&workspace, &run).expect("save");
+        run.iteration = 2;
+        save_run(&workspace, &run).expect("update");
+        for index in 0..25 {
+            save_run(
+                &workspace,
+                &state(&format!("done-{index}"), AutoRunStatus::Completed),
+            )
+            .expect("save finished");
+        }
+        let runs = load_runs(&workspace);
+        assert_eq!(runs.len(), 21);
+        assert_eq!(runs[0].id, "run-1");
+        assert_eq!(runs[0].iteration, 2);
+        assert_eq!(runs[1].id, "done-5");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How often each session looks for leaked background threads.
+pub(crate) const BACKGROUND_REAP_INTERVAL: Duration = Duration::from_secs(60);
+/// A background thread nobody listens to is reaped once it has been quiet this long.
+pub(crate) const BACKGROUND_THREAD_IDLE: Duration = Duration::from_secs(10 * 60);
+
+/// Last activity of each hidden background thread of a session: creation, a prompt, or
+/// an update forwarded to its callback.
+#[derive(Debug, Default)]
+pub(crate) struct BackgroundActivity {
+    last_activity_ms: HashMap<String, u64>,
+}
+
+impl BackgroundActivity {
+    pub(crate) fn touch(&mut self, thread_id: &str, now_ms: u64) {
+        self.last_activity_ms.insert(thread_id.to_string(), now_ms);
+    }
+
+    pub(crate) fn forget(&mut self, thread_id: &str) {
+        self.last_activity_ms.remove(thread_id);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.last_activity_ms.clear();
+    }
+
+    /// Background threads left behind by their helper: nothing is attached to collect
+    /// their output and they have been idle for at least `idle_ms`. Threads seen for
+    /// the first time start their idle clock now. Entries for threads that no longer
+    /// exist are dropped.
+    pub(crate) fn reapable<'a>(
+        &mut self,
+        threads: impl IntoIterator<Item = &'a String>,
+        is_attached: impl Fn(&str) -> bool,
+        now_ms: u64,
+        idle_ms: u64,
+    ) -> Vec<String> {
+        let mut seen = HashMap::new();
+        let mut reapable = Vec::new();
+        for thread_id in threads {
+            let last_activity = self
+                .last_activity_ms
+                .get(thread_id)
+                .copied()
+                .unwrap_or(now_ms);
+            seen.insert(thread_id.clone(), last_activity);
+            if !is_attached(thread_id) && now_ms.saturating_sub(last_activity) >= idle_ms {
+                reapable.push(thread_id.clone());
+            }
+        }
+        self.last_activity_ms = seen;
+        reapable.sort();
+        reapable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reaps_leaked_background_threads_once_idle() {
+        let idle_ms = BACKGROUND_THREAD_IDLE.as_millis() as u64;
+        let threads = vec![
+            "leaked".to_string(),
+            "attached".to_string(),
+            "fresh".to_string(),
+        ];
+        let mut activity = BackgroundActivity::default();
+        activity.touch("leaked", 1_000);
+        activity.touch("attached", 1_000);
+        activity.touch("archived", 1_000);
+        activity.touch("fresh", 1_000 + idle_ms);
+
+        let now = 1_000 + idle_ms;
+        let reapable =
+            activity.reapable(&threads, |thread_id| thread_id == "attached", now, idle_ms);
+        assert_eq!(reapable, vec!["leaked".to_string()]);
+
+        // An untracked thread starts its idle clock on the first sweep.
+        let threads = vec!["untracked".to_string()];
+        assert!(activity
+            .reapable(&threads, |_| false, now, idle_ms)
+            .is_empty());
+        assert!(activity
+            .reapable(&threads, |_| false, now + idle_ms - 1, idle_ms)
+            .is_empty());
+        assert_eq!(
+            activity.reapable(&threads, |_| false, now + idle_ms, idle_ms),
+            threads
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+/// Only the most recently modified project directories under `tmp` are indexed, so users
+/// with a long CLI history do not pay for scanning every project they ever opened.
+pub(crate) const MAX_SCANNED_PROJECT_DIRS: usize = 200;
+/// Directory mtimes can have one-second resolution, so recently touched directories are
+/// always re-listed instead of trusting an unchanged mtime.
+const RECENT_CHANGE_WINDOW: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct ChatFileHeader {
+    #[serde(rename = "sessionId")]
+    session_id: Option<String>,
+}
+
+/// Maps MiCode session ids to the chat file that records them. The index is built once
+/// and then refreshed incrementally: a `chats` directory is only re-listed when its
+/// mtime changes, and only files that were not indexed yet are opened.
+#[derive(Debug, Default)]
+pub(crate) struct ChatFileIndex {
+    tmp_root: PathBuf,
+    sessions: HashMap<String, (PathBuf, SystemTime)>,
+    chat_dirs: HashMap<PathBuf, SystemTime>,
+    files: HashMap<PathBuf, String>,
+    files_opened: usize,
+}
+
+fn modified_at(path: &Path) -> SystemTime {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .unwrap_or(UNIX_EPOCH)
+}
+
+fn read_chat_session_id(path: &Path) -> Option<String> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    let header: ChatFileHeader = serde_json::from_str(&raw).ok()?;
+    header
+        .session_id
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+impl ChatFileIndex {
+    pub(crate) fn new(tmp_root: PathBuf) -> Self {
+        Self {
+            tmp_root,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn lookup(&self, session_id: &str) -> Option<PathBuf> {
+        self.sessions
+            .get(session_id.trim())
+            .map(|(path, _)| path.clone())
+    }
+
+    /// Returns the indexed chat file for `session_id`, refreshing once when it is unknown
+    /// or its file has disappeared.
+    pub(crate) fn resolve(&mut self, session_id: &str) -> Option<PathBuf> {
+        if let Some(path) = self.lookup(session_id).filter(|path| path.is_file()) {
+            return Some(path);
+        }
+        self.refresh();
+        // A removed project directory is not re-listed, so its files can outlive it here.
+        self.lookup(session_id).filter(|path| path.is_file())
+    }
+
+    /// Drops `path` from the index and marks its directory for re-listing, so the next
+    /// refresh re-reads that file alone. Used when a file no longer records the session
+    /// it was indexed under.
+    pub(crate) fn forget(&mut self, path: &Path) {
+        if let Some(session_id) = self.files.remove(path) {
+            if self.sessions.get(&session_id).map(|(indexed, _)| indexed.as_path()) == Some(path)
+            {
+                self.sessions.remove(&session_id);
+            }
+        }
+        if let Some(dir) = path.parent() {
+            self.chat_dirs.remove(dir);
+        }
+    }
+
+    fn project_chat_dirs(&self) -> Vec<(PathBuf, SystemTime)> {
+        let Ok(entries) = std::fs::read_dir(&self.tmp_root) else {
+            return Vec::new();
+        };
+        let mut dirs: Vec<(PathBuf, SystemTime)> = entries
+            .flatten()
+            .map(|entry| entry.path().join("chats"))
+            .filter(|path| path.is_dir())
+            .map(|path| {
+                let modified = modified_at(&path);
+                (path, modified)
+            })
+            .collect();
+        dirs.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+        dirs.truncate(MAX_SCANNED_PROJECT_DIRS);
+        dirs
+    }
+
+    /// Picks up chat files created or removed since the previous refresh. Returns the
+    /// number of files that had to be opened.
+    pub(crate) fn refresh(&mut self) -> usize {
+        let mut opened = 0;
+        let now = SystemTime::now();
+        for (dir, modified) in self.project_chat_dirs() {
+            let recently_changed = now
+                .duration_since(modified)
+                .map(|age| age < RECENT_CHANGE_WINDOW)
+                .unwrap_or(true);
+            if self.chat_dirs.get(&dir) == Some(&modified) && !recently_changed {
+                continue;
+            }
+            self.chat_dirs.insert(dir.clone(), modified);
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            let present: Vec<PathBuf> = entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+                .collect();
+            self.forget_missing_files(&dir, &present);
+            for path in present {
+                if self.files.contains_key(&path) {
+                    continue;
+                }
+                opened += 1;
+                self.files_opened += 1;
+                let Some(session_id) = read_chat_session_id(&path) else {
+                    continue;
+                };
+                self.insert(session_id, path);
+            }
+        }
+        opened
+    }
+
+    fn insert(&mut self, session_id: String, path: PathBuf) {
+        let modified = modified_at(&path);
+        self.files.insert(path.clone(), session_id.clone());
+        let newer = self
+            .sessions
+            .get(&session_id)
+            .map(|(_, current)| modified >= *current)
+            .unwrap_or(true);
+        if newer {
+            self.sessions.insert(session_id, (path, modified));
+        }
+    }
+
+    fn forget_missing_files(&mut self, dir: &Path, present: &[PathBuf]) {
+        let missing: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter(|path| path.parent() == Some(dir) && !present.contains(path))
+            .cloned()
+            .collect();
+        for path in missing {
+            if let Some(session_id) = self.files.remove(&path) {
+                if self.sessions.get(&session_id).map(|(indexed, _)| indexed) == Some(&path) {
+                    self.sessions.remove(&session_id);
+                }
+            }
+        }
+    }
+}
+
+/// Resolves the chat file for `session_id` through `index`, refreshing it once on a miss
+/// so files written moments ago are still found.
+pub(crate) fn find_chat_file(index: &Mutex<ChatFileIndex>, session_id: &str) -> Option<PathBuf> {
+    index.lock().ok()?.resolve(session_id)
+}
+
+/// Evicts a chat file whose contents no longer match the session it was resolved for.
+pub(crate) fn forget_chat_file(index: &Mutex<ChatFileIndex>, path: &Path) {
+    if let Ok(mut index) = index.lock() {
+        index.forget(path);
+    }
+}
+
+/// Picks up the chat files a finished turn wrote, so the lookups that follow it hit
+/// without touching the file system.
+pub(crate) fn refresh_chat_files(index: &Mutex<ChatFileIndex>) {
+    if let Ok(mut index) = index.lock() {
+        index.refresh();
+    }
+}
+
+/// Chat files of a set of sessions, polled for writes. Each poll stats only the files
+/// of the watched sessions; discovery of new files is left to the index of the home.
+#[derive(Debug)]
+pub(crate) struct ChatFileWatch {
+    index: Arc<Mutex<ChatFileIndex>>,
+    /// Last seen modification time of each watched session's chat file, if it had one.
+    sessions: HashMap<String, Option<(PathBuf, SystemTime)>>,
+}
+
+impl ChatFileWatch {
+    pub(crate) fn new(index: Arc<Mutex<ChatFileIndex>>) -> Self {
+        Self {
+            index,
+            sessions: HashMap::new(),
+        }
+    }
+
+    /// Starts reporting writes to the chat file of `session_id`. A file that already
+    /// exists is taken as the baseline, so only later writes are reported.
+    pub(crate) fn watch(&mut self, session_id: &str) {
+        let session_id = session_id.trim();
+        if session_id.is_empty() || self.sessions.contains_key(session_id) {
+            return;
+        }
+        let baseline = find_chat_file(&self.index, session_id).map(|path| {
+            let modified = modified_at(&path);
+            (path, modified)
+        });
+        self.sessions.insert(session_id.to_string(), baseline);
+    }
+
+    pub(crate) fn unwatch(&mut self, session_id: &str) {
+        self.sessions.remove(session_id.trim());
+    }
+
+    /// Watched sessions whose chat file appeared or was written since the previous poll.
+    pub(crate) fn poll(&mut self) -> Vec<(String, PathBuf)> {
+        let mut changed = Vec::new();
+        for (session_id, seen) in self.sessions.iter_mut() {
+            let Some(path) = find_chat_file(&self.index, session_id) else {
+                continue;
+            };
+            let modified = modified_at(&path);
+            if seen.as_ref() == Some(&(path.clone(), modified)) {
+                continue;
+            }
+            *seen = Some((path.clone(), modified));
+            changed.push((session_id.clone(), path));
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{find_chat_file, ChatFileIndex, ChatFileWatch};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[test]
+    fn indexes_new_files_incrementally_and_forgets_removed_ones() {
+        let root = std::env::temp_dir().join(format!("micode-chat-index-{}", Uuid::new_v4()));
+        let chats = root.join("project-a").join("chats");
+        std::fs::create_dir_all(&chats).expect("create chats dir");
+        std::fs::write(
+            chats.join("one.json"),
+            json!({ "sessionId": "s-1", "messages": [] }).to_string(),
+        )
+        .expect("write chat");
+
+        let mut index = ChatFileIndex::new(root.clone());
+        assert_eq!(index.refresh(), 1);
+        assert_eq!(index.lookup("s-1"), Some(chats.join("one.json")));
+        assert_eq!(index.refresh(), 0);
+
+        std::fs::write(
+            chats.join("two.json"),
+            json!({ "sessionId": "s-2", "messages": [] }).to_string(),
+        )
+        .expect("write chat");
+        std::fs::remove_file(chats.join("one.json")).expect("remove chat");
+        assert_eq!(index.refresh(), 1);
+        assert_eq!(index.lookup("s-2"), Some(chats.join("two.json")));
+        assert_eq!(index.lookup("s-1"), None);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn repeat_lookups_do_not_reopen_unrelated_chat_files() {
+        let root = std::env::temp_dir().join(format!("micode-chat-index-{}", Uuid::new_v4()));
+        let chats = root.join("project-a").join("chats");
+        std::fs::create_dir_all(&chats).expect("create chats dir");
+        for n in 0..50 {
+            std::fs::write(
+                chats.join(format!("other-{n}.json")),
+                json!({ "sessionId": format!("other-{n}"), "messages": [] }).to_string(),
+            )
+            .expect("write chat");
+        }
+        let target = chats.join("target.json");
+        std::fs::write(
+            &target,
+            json!({ "sessionId": "s-target", "messages": [] }).to_string(),
+        )
+        .expect("write chat");
+
+        let mut index = ChatFileIndex::new(root.clone());
+        assert_eq!(index.resolve("s-target"), Some(target.clone()));
+        assert_eq!(index.files_opened, 51);
+        for _ in 0..3 {
+            assert_eq!(index.resolve("s-target"), Some(target.clone()));
+        }
+        assert_eq!(index.files_opened, 51);
+
+        // The cached file now records another session: forgetting it re-reads only that
+        // file and finds the session where it moved.
+        std::fs::write(
+            &target,
+            json!({ "sessionId": "s-other", "messages": [] }).to_string(),
+        )
+        .expect("rewrite chat");
+        let moved = chats.join("moved.json");
+        std::fs::write(
+            &moved,
+            json!({ "sessionId": "s-target", "messages": [] }).to_string(),
+        )
+        .expect("write chat");
+        index.forget(&target);
+        assert_eq!(index.resolve("s-target"), Some(moved));
+        assert_eq!(index.files_opened, 53);
+        assert_eq!(index.lookup("s-other"), Some(target));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn each_home_keeps_its_own_index() {
+        let homes: Vec<_> = (0..2)
+            .map(|n| {
+                let root =
+                    std::env::temp_dir().join(format!("micode-chat-home-{}", Uuid::new_v4()));
+                let chats = root.join("project-a").join("chats");
+                std::fs::create_dir_all(&chats).expect("create chats dir");
+                let chat = chats.join("chat.json");
+                std::fs::write(
+                    &chat,
+                    json!({ "sessionId": format!("s-{n}"), "messages": [] }).to_string(),
+                )
+                .expect("write chat");
+                let index = Mutex::new(ChatFileIndex::new(root.clone()));
+                (root, chat, index)
+            })
+            .collect();
+        for _ in 0..3 {
+            for (n, (_, chat, index)) in homes.iter().enumerate() {
+                assert_eq!(find_chat_file(index, &format!("s-{n}")), Some(chat.clone()));
+                assert_eq!(find_chat_file(index, &format!("s-{}", 1 - n)), None);
+            }
+        }
+        for (root, _, index) in &homes {
+            assert_eq!(index.lock().expect("lock").files_opened, 1);
+            let _ = std::fs::remove_dir_all(root);
+        }
+    }
+
+    #[test]
+    fn watch_reports_new_and_rewritten_chat_files_once() {
+        let root = std::env::temp_dir().join(format!("micode-chat-watch-{}", Uuid::new_v4()));
+        let chats = root.join("project-a").join("chats");
+        std::fs::create_dir_all(&chats).expect("create chats dir");
+        let existing = chats.join("existing.json");
+        std::fs::write(
+            &existing,
+            json!({ "sessionId": "w-1", "messages": [] }).to_string(),
+        )
+        .expect("write chat");
+
+        let mut watch = ChatFileWatch::new(Arc::new(Mutex::new(ChatFileIndex::new(root.clone()))));
+        watch.watch("w-1");
+        watch.watch("w-2");
+        assert!(watch.poll().is_empty());
+
+        let created = chats.join("created.json");
+        std::fs::write(
+            &created,
+            json!({ "sessionId": "w-2", "messages": [] }).to_string(),
+        )
+        .expect("write chat");
+        assert_eq!(watch.poll(), vec![("w-2".to_string(), created)]);
+        assert!(watch.poll().is_empty());
+
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&existing)
+            .expect("open chat");
+        file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+            .expect("touch chat");
+        assert_eq!(watch.poll(), vec![("w-1".to_string(), existing)]);
+
+        watch.unwatch("w-1");
+        let _ = std::fs::remove_dir_all(&root);
+        assert!(watch.poll().is_empty());
+    }
+
+    #[test]
+    fn watch_tolerates_a_missing_tmp_root() {
+        let root = std::env::temp_dir().join(format!("micode-chat-watch-{}", Uuid::new_v4()));
+        let mut watch = ChatFileWatch::new(Arc::new(Mutex::new(ChatFileIndex::new(root))));
+        watch.watch("missing");
+        assert!(watch.poll().is_empty());
+    }
+}
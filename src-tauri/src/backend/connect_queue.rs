@@ -0,0 +1,202 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use serde_json::json;
+use tokio::sync::oneshot;
+
+use crate::backend::event_methods;
+use crate::backend::events::AppServerEvent;
+
+struct Waiter {
+    workspace_id: String,
+    priority: bool,
+    tx: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    running: usize,
+    waiting: Vec<Waiter>,
+    priority_workspace: Option<String>,
+}
+
+/// Caps how many agent processes are being spawned and initialized at once, so a
+/// launch that reconnects every workspace does not start them all together. App and
+/// daemon state each own one, built with `maxConcurrentConnects`.
+pub(crate) struct ConnectQueue {
+    limit: AtomicUsize,
+    state: Mutex<QueueState>,
+}
+
+/// Holds one connect slot; dropping it lets the next queued workspace start.
+pub(crate) struct ConnectPermit<'a> {
+    queue: &'a ConnectQueue,
+}
+
+impl Drop for ConnectPermit<'_> {
+    fn drop(&mut self) {
+        self.queue.release();
+    }
+}
+
+impl ConnectQueue {
+    pub(crate) fn new(limit: u32) -> Self {
+        Self {
+            limit: AtomicUsize::new(limit.max(1) as usize),
+            state: Mutex::new(QueueState::default()),
+        }
+    }
+
+    pub(crate) fn set_limit(&self, limit: u32) {
+        self.limit.store(limit.max(1) as usize, Ordering::Relaxed);
+        if let Ok(mut state) = self.state.lock() {
+            self.admit(&mut state);
+        }
+    }
+
+    /// Workspace that jumps ahead of everything queued after it is set.
+    pub(crate) fn set_priority_workspace(&self, workspace_id: Option<&str>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.priority_workspace = workspace_id.map(ToString::to_string);
+        }
+    }
+
+    /// Workspaces waiting for a slot, next to start first.
+    pub(crate) fn queued(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .map(|state| {
+                state
+                    .waiting
+                    .iter()
+                    .map(|waiter| waiter.workspace_id.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Waits for a free slot. When the connect has to wait, `on_queued` receives the
+    /// queue including this workspace.
+    pub(crate) async fn acquire(
+        &self,
+        workspace_id: &str,
+        on_queued: impl FnOnce(Vec<String>),
+    ) -> ConnectPermit<'_> {
+        let rx = {
+            let Ok(mut state) = self.state.lock() else {
+                return ConnectPermit { queue: self };
+            };
+            if state.waiting.is_empty() && state.running < self.limit.load(Ordering::Relaxed) {
+                state.running += 1;
+                return ConnectPermit { queue: self };
+            }
+            let priority = state.priority_workspace.as_deref() == Some(workspace_id);
+            let at = if priority {
+                state
+                    .waiting
+                    .iter()
+                    .position(|waiter| !waiter.priority)
+                    .unwrap_or(state.waiting.len())
+            } else {
+                state.waiting.len()
+            };
+            let (tx, rx) = oneshot::channel();
+            state.waiting.insert(
+                at,
+                Waiter {
+                    workspace_id: workspace_id.to_string(),
+                    priority,
+                    tx,
+                },
+            );
+            rx
+        };
+        on_queued(self.queued());
+        let _ = rx.await;
+        ConnectPermit { queue: self }
+    }
+
+    fn release(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.running = state.running.saturating_sub(1);
+            self.admit(&mut state);
+        }
+    }
+
+    /// Hands free slots to waiters in order, skipping ones whose connect was dropped.
+    fn admit(&self, state: &mut QueueState) {
+        let limit = self.limit.load(Ordering::Relaxed);
+        while state.running < limit && !state.waiting.is_empty() {
+            let waiter = state.waiting.remove(0);
+            if waiter.tx.send(()).is_ok() {
+                state.running += 1;
+            }
+        }
+    }
+}
+
+/// One `workspace/connectQueued` per waiting workspace with its 1-based position.
+pub(crate) fn queue_position_events(queued: &[String]) -> Vec<AppServerEvent> {
+    queued
+        .iter()
+        .enumerate()
+        .map(|(index, workspace_id)| AppServerEvent {
+            workspace_id: workspace_id.clone(),
+            message: json!({
+                "method": event_methods::WORKSPACE_CONNECT_QUEUED,
+                "params": {
+                    "workspaceId": workspace_id,
+                    "position": index + 1,
+                    "queueLength": queued.len(),
+                },
+            }),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::runtime::Builder;
+
+    #[test]
+    fn limits_concurrent_connects_and_prefers_priority_workspace() {
+        let runtime = Builder::new_current_thread().build().expect("runtime");
+        runtime.block_on(async {
+            let queue = Arc::new(ConnectQueue::new(2));
+            let first = queue.acquire("ws-1", |_| panic!("not queued")).await;
+            let second = queue.acquire("ws-2", |_| panic!("not queued")).await;
+
+            let mut waiting = Vec::new();
+            for id in ["ws-3", "ws-4", "ws-5"] {
+                if id == "ws-5" {
+                    queue.set_priority_workspace(Some("ws-5"));
+                }
+                let task_queue = queue.clone();
+                waiting.push(tokio::spawn(async move {
+                    let _permit = task_queue.acquire(id, |_| {}).await;
+                }));
+                while !queue.queued().iter().any(|queued| queued == id) {
+                    tokio::task::yield_now().await;
+                }
+            }
+            assert_eq!(queue.queued(), vec!["ws-5", "ws-3", "ws-4"]);
+            let events = queue_position_events(&queue.queued());
+            assert_eq!(events[0].workspace_id, "ws-5");
+            assert_eq!(events[2].message["params"]["position"], 3);
+
+            // Slots are handed over synchronously, before any waiter gets to run.
+            drop(first);
+            assert_eq!(queue.queued(), vec!["ws-3", "ws-4"]);
+            drop(second);
+            assert_eq!(queue.queued(), vec!["ws-4"]);
+            for task in waiting {
+                task.await.expect("connect task");
+            }
+            assert!(queue.queued().is_empty());
+            let _third = queue.acquire("ws-6", |_| panic!("not queued")).await;
+            let _fourth = queue.acquire("ws-7", |_| panic!("not queued")).await;
+        });
+    }
+}
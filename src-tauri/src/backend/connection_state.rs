@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::backend::app_server::now_ms;
+use crate::types::WorkspaceConnectionStatus;
+
+#[derive(Debug, Clone, Default)]
+struct ConnectionRecord {
+    attempts_in_flight: usize,
+    last_error: Option<(String, u64)>,
+}
+
+/// Connect attempts and failures per workspace. App and daemon state each keep one beside
+/// `sessions`, so the last error is still known after a failed spawn left no session.
+#[derive(Default)]
+pub(crate) struct ConnectionStates {
+    records: Mutex<HashMap<String, ConnectionRecord>>,
+}
+
+impl ConnectionStates {
+    pub(crate) fn mark_connecting(&self, workspace_id: &str) {
+        if let Ok(mut records) = self.records.lock() {
+            records
+                .entry(workspace_id.to_string())
+                .or_default()
+                .attempts_in_flight += 1;
+        }
+    }
+
+    pub(crate) fn mark_connect_finished(&self, workspace_id: &str, error: Option<&str>) {
+        if let Ok(mut records) = self.records.lock() {
+            let record = records.entry(workspace_id.to_string()).or_default();
+            record.attempts_in_flight = record.attempts_in_flight.saturating_sub(1);
+            if let Some(error) = error {
+                record.last_error = Some((error.to_string(), now_ms()));
+            }
+        }
+    }
+
+    /// Drops everything known about a workspace, e.g. after it was removed or moved to a
+    /// new path where the old error no longer applies.
+    pub(crate) fn forget_workspace(&self, workspace_id: &str) {
+        if let Ok(mut records) = self.records.lock() {
+            records.remove(workspace_id);
+        }
+    }
+
+    /// Current status plus the last connect error and when it happened.
+    pub(crate) fn connection_status(
+        &self,
+        workspace_id: &str,
+        connected: bool,
+    ) -> (WorkspaceConnectionStatus, Option<(String, u64)>) {
+        let record = self
+            .records
+            .lock()
+            .ok()
+            .and_then(|records| records.get(workspace_id).cloned())
+            .unwrap_or_default();
+        let status = if record.attempts_in_flight > 0 {
+            WorkspaceConnectionStatus::Connecting
+        } else if connected {
+            WorkspaceConnectionStatus::Connected
+        } else if record.last_error.is_some() {
+            WorkspaceConnectionStatus::Error
+        } else {
+            WorkspaceConnectionStatus::Disconnected
+        };
+        (status, record.last_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConnectionStates;
+    use crate::types::WorkspaceConnectionStatus;
+
+    #[test]
+    fn tracks_attempts_and_keeps_the_last_error() {
+        let states = ConnectionStates::default();
+        let id = "connection-state-test";
+        assert_eq!(
+            states.connection_status(id, false).0,
+            WorkspaceConnectionStatus::Disconnected
+        );
+        states.mark_connecting(id);
+        assert_eq!(
+            states.connection_status(id, false).0,
+            WorkspaceConnectionStatus::Connecting
+        );
+        states.mark_connect_finished(id, Some("micode not found"));
+        let (status, error) = states.connection_status(id, false);
+        assert_eq!(status, WorkspaceConnectionStatus::Error);
+        assert_eq!(
+            error.map(|(message, _)| message).as_deref(),
+            Some("micode not found")
+        );
+
+        states.mark_connecting(id);
+        states.mark_connect_finished(id, None);
+        let (status, error) = states.connection_status(id, true);
+        assert_eq!(status, WorkspaceConnectionStatus::Connected);
+        assert!(error.is_some());
+
+        states.forget_workspace(id);
+        assert_eq!(
+            states.connection_status(id, false),
+            (WorkspaceConnectionStatus::Disconnected, None)
+        );
+    }
+}
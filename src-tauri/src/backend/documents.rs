@@ -0,0 +1,1299 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use flate2::read::ZlibDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Notebooks and PDFs are parsed whole, so they may be larger than plain-text reads.
+const MAX_DOCUMENT_BYTES: u64 = 25 * 1024 * 1024;
+const MAX_NOTEBOOK_CELLS: usize = 500;
+const MAX_OUTPUT_CHARS: usize = 4_000;
+const MAX_PDF_PAGES: usize = 50;
+const MAX_DECODED_STREAM_BYTES: u64 = 16 * 1024 * 1024;
+
+/// How `read_workspace_file` should treat notebooks and PDFs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum ReadFormat {
+    /// Structured when the file is a notebook or PDF; notebooks that fail to parse fall
+    /// back to their raw JSON.
+    #[default]
+    Auto,
+    Raw,
+    /// Like `Auto`, but parse failures are errors.
+    Structured,
+}
+
+impl ReadFormat {
+    pub(crate) fn parse(raw: Option<&str>) -> Result<Self, String> {
+        match raw.map(str::trim).unwrap_or("auto") {
+            "" | "auto" => Ok(Self::Auto),
+            "raw" => Ok(Self::Raw),
+            "structured" => Ok(Self::Structured),
+            other => Err(format!("unsupported read format: {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NotebookOutput {
+    pub(crate) output_type: String,
+    pub(crate) text: String,
+    pub(crate) truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NotebookCell {
+    pub(crate) cell_type: String,
+    pub(crate) source: String,
+    pub(crate) execution_count: Option<u64>,
+    pub(crate) outputs: Vec<NotebookOutput>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NotebookDocument {
+    pub(crate) language: Option<String>,
+    pub(crate) cells: Vec<NotebookCell>,
+    /// Cells past `MAX_NOTEBOOK_CELLS` that were left out.
+    pub(crate) omitted_cells: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PdfPage {
+    pub(crate) number: usize,
+    pub(crate) text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PdfDocument {
+    pub(crate) page_count: usize,
+    pub(crate) pages: Vec<PdfPage>,
+    /// Pages past `MAX_PDF_PAGES` that were not extracted.
+    pub(crate) omitted_pages: usize,
+    /// No page had extractable text, which usually means a scanned document.
+    pub(crate) no_text: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum StructuredDocument {
+    Notebook(NotebookDocument),
+    Pdf(PdfDocument),
+}
+
+/// A structured read: the document plus its text rendering, which is what gets shown or
+/// inlined into prompts.
+pub(crate) struct StructuredRead {
+    pub(crate) content: String,
+    pub(crate) truncated: bool,
+    pub(crate) document: StructuredDocument,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DocumentKind {
+    Notebook,
+    Pdf,
+}
+
+fn document_kind(path: &Path) -> Option<DocumentKind> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "ipynb" => Some(DocumentKind::Notebook),
+        "pdf" => Some(DocumentKind::Pdf),
+        _ => None,
+    }
+}
+
+/// Reads notebooks and PDFs as structured documents. `None` means the file should be read
+/// as plain text, either because of `format` or because it is not a document type.
+pub(crate) fn read_structured(
+    path: &Path,
+    format: ReadFormat,
+    max_content_bytes: usize,
+) -> Result<Option<StructuredRead>, String> {
+    if format == ReadFormat::Raw {
+        return Ok(None);
+    }
+    let Some(kind) = document_kind(path) else {
+        return Ok(None);
+    };
+    let file = File::open(path).map_err(|err| format!("Failed to open file: {err}"))?;
+    let mut bytes = Vec::new();
+    file.take(MAX_DOCUMENT_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|err| format!("Failed to read file: {err}"))?;
+    if bytes.len() as u64 > MAX_DOCUMENT_BYTES {
+        return Err(format!(
+            "File is larger than {} MB and cannot be converted",
+            MAX_DOCUMENT_BYTES / (1024 * 1024)
+        ));
+    }
+    let document = match kind {
+        DocumentKind::Notebook => match parse_notebook(&String::from_utf8_lossy(&bytes)) {
+            Ok(notebook) => StructuredDocument::Notebook(notebook),
+            Err(_) if format == ReadFormat::Auto => return Ok(None),
+            Err(err) => return Err(err),
+        },
+        DocumentKind::Pdf => StructuredDocument::Pdf(extract_pdf_text(&bytes)?),
+    };
+    let (content, truncated) = render_document(&document, max_content_bytes);
+    Ok(Some(StructuredRead {
+        content,
+        truncated,
+        document,
+    }))
+}
+
+fn multiline_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Drops ANSI color sequences, which tracebacks are full of.
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\u{1b}' && chars.peek() == Some(&'[') {
+            chars.next();
+            for next in chars.by_ref() {
+                if next.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        out.push(ch);
+    }
+    out
+}
+
+fn notebook_output(output: &Value) -> Option<NotebookOutput> {
+    let output_type = output.get("output_type")?.as_str()?.to_string();
+    let text = match output_type.as_str() {
+        "stream" => multiline_text(output.get("text")),
+        "execute_result" | "display_data" => {
+            let data = output.get("data")?;
+            match data.get("text/plain") {
+                Some(text) => multiline_text(Some(text)),
+                None => {
+                    let mime = data
+                        .as_object()
+                        .and_then(|data| data.keys().next().cloned())
+                        .unwrap_or_default();
+                    format!("[{mime} output]")
+                }
+            }
+        }
+        "error" => {
+            let traceback: Vec<&str> = output
+                .get("traceback")
+                .and_then(Value::as_array)
+                .map(|lines| lines.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            if traceback.is_empty() {
+                format!(
+                    "{}: {}",
+                    output
+                        .get("ename")
+                        .and_then(Value::as_str)
+                        .unwrap_or("Error"),
+                    output.get("evalue").and_then(Value::as_str).unwrap_or("")
+                )
+            } else {
+                strip_ansi(&traceback.join("\n"))
+            }
+        }
+        _ => return None,
+    };
+    let truncated = text.chars().count() > MAX_OUTPUT_CHARS;
+    let text = if truncated {
+        let kept: String = text.chars().take(MAX_OUTPUT_CHARS).collect();
+        format!("{kept}\n[… output truncated]")
+    } else {
+        text
+    };
+    Some(NotebookOutput {
+        output_type,
+        text,
+        truncated,
+    })
+}
+
+/// Cells with their source and text outputs. Rich outputs (images, HTML without a
+/// plain-text fallback) are replaced by a `[mime output]` placeholder.
+pub(crate) fn parse_notebook(raw: &str) -> Result<NotebookDocument, String> {
+    let parsed: Value =
+        serde_json::from_str(raw).map_err(|err| format!("Invalid notebook: {err}"))?;
+    let cells = parsed
+        .get("cells")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "Invalid notebook: missing cells".to_string())?;
+    let metadata = parsed.get("metadata");
+    let language = metadata
+        .and_then(|metadata| metadata.get("language_info"))
+        .and_then(|info| info.get("name"))
+        .or_else(|| {
+            metadata
+                .and_then(|metadata| metadata.get("kernelspec"))
+                .and_then(|spec| spec.get("language"))
+        })
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    Ok(NotebookDocument {
+        language,
+        cells: cells
+            .iter()
+            .take(MAX_NOTEBOOK_CELLS)
+            .map(|cell| NotebookCell {
+                cell_type: cell
+                    .get("cell_type")
+                    .and_then(Value::as_str)
+                    .unwrap_or("raw")
+                    .to_string(),
+                source: multiline_text(cell.get("source")),
+                execution_count: cell.get("execution_count").and_then(Value::as_u64),
+                outputs: cell
+                    .get("outputs")
+                    .and_then(Value::as_array)
+                    .map(|outputs| outputs.iter().filter_map(notebook_output).collect())
+                    .unwrap_or_default(),
+            })
+            .collect(),
+        omitted_cells: cells.len().saturating_sub(MAX_NOTEBOOK_CELLS),
+    })
+}
+
+fn push_block(out: &mut String, block: &str) {
+    if !out.is_empty() {
+        out.push_str("\n\n");
+    }
+    out.push_str(block.trim_end());
+}
+
+/// Text rendering of a document, capped at `max_bytes` with an explicit marker.
+pub(crate) fn render_document(document: &StructuredDocument, max_bytes: usize) -> (String, bool) {
+    let mut out = String::new();
+    match document {
+        StructuredDocument::Notebook(notebook) => {
+            let language = notebook.language.as_deref().unwrap_or("");
+            for cell in &notebook.cells {
+                match cell.cell_type.as_str() {
+                    "code" => {
+                        let label = match cell.execution_count {
+                            Some(count) => format!("In [{count}]:"),
+                            None => "In [ ]:".to_string(),
+                        };
+                        push_block(
+                            &mut out,
+                            &format!("{label}\n```{language}\n{}\n```", cell.source.trim_end()),
+                        );
+                        for output in &cell.outputs {
+                            push_block(
+                                &mut out,
+                                &format!("Out:\n```\n{}\n```", output.text.trim_end()),
+                            );
+                        }
+                    }
+                    _ => push_block(&mut out, &cell.source),
+                }
+            }
+            if notebook.omitted_cells > 0 {
+                push_block(
+                    &mut out,
+                    &format!(
+                        "[… {} more cells not shown (limit {MAX_NOTEBOOK_CELLS})]",
+                        notebook.omitted_cells
+                    ),
+                );
+            }
+        }
+        StructuredDocument::Pdf(pdf) => {
+            if pdf.no_text {
+                push_block(
+                    &mut out,
+                    "[No extractable text found; the PDF may consist of scanned images]",
+                );
+            } else {
+                for page in &pdf.pages {
+                    push_block(
+                        &mut out,
+                        &format!("--- Page {} ---\n{}", page.number, page.text),
+                    );
+                }
+            }
+            if pdf.omitted_pages > 0 {
+                push_block(
+                    &mut out,
+                    &format!(
+                        "[… pages {}–{} not extracted (limit {MAX_PDF_PAGES} pages)]",
+                        pdf.pages.len() + 1,
+                        pdf.page_count
+                    ),
+                );
+            }
+        }
+    }
+    if out.len() <= max_bytes {
+        return (out, false);
+    }
+    let mut cut = max_bytes;
+    while !out.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    out.truncate(cut);
+    out.push_str(&format!("\n[… truncated at {max_bytes} bytes]"));
+    (out, true)
+}
+
+struct PdfObject {
+    dict: String,
+    stream: Option<Vec<u8>>,
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if from >= haystack.len() {
+        return None;
+    }
+    haystack[from..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|offset| from + offset)
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| byte as char).collect()
+}
+
+fn is_pdf_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0')
+}
+
+fn is_pdf_delimiter(byte: u8) -> bool {
+    matches!(
+        byte,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+/// Object number of an `N G obj` header whose `obj` keyword starts at `at`.
+fn object_number_before(data: &[u8], at: usize) -> Option<u32> {
+    let mut pos = at;
+    let read_int = |pos: &mut usize| -> Option<u32> {
+        let end = *pos;
+        while *pos > 0 && is_pdf_whitespace(data[*pos - 1]) {
+            *pos -= 1;
+        }
+        if *pos == end {
+            return None;
+        }
+        let digits_end = *pos;
+        while *pos > 0 && data[*pos - 1].is_ascii_digit() {
+            *pos -= 1;
+        }
+        std::str::from_utf8(&data[*pos..digits_end])
+            .ok()?
+            .parse()
+            .ok()
+    };
+    read_int(&mut pos)?;
+    let number = read_int(&mut pos)?;
+    let boundary_ok =
+        pos == 0 || is_pdf_whitespace(data[pos - 1]) || is_pdf_delimiter(data[pos - 1]);
+    boundary_ok.then_some(number)
+}
+
+/// Value of `key` in a dictionary, i.e. the text right after the key.
+fn dict_entry<'a>(dict: &'a str, key: &str) -> Option<&'a str> {
+    let mut from = 0;
+    while let Some(offset) = dict[from..].find(key) {
+        let start = from + offset + key.len();
+        let next = dict[start..].chars().next();
+        if !next.is_some_and(|ch| ch.is_ascii_alphanumeric()) {
+            return Some(dict[start..].trim_start());
+        }
+        from = start;
+    }
+    None
+}
+
+fn name_after(dict: &str, key: &str) -> Option<String> {
+    let rest = dict_entry(dict, key)?.strip_prefix('/')?;
+    let end = rest
+        .find(|ch: char| {
+            !ch.is_ascii() || is_pdf_whitespace(ch as u8) || is_pdf_delimiter(ch as u8)
+        })
+        .unwrap_or(rest.len());
+    Some(rest[..end].to_string())
+}
+
+/// Parses `N G R` references from the start of `text`.
+fn parse_refs(text: &str) -> Vec<u32> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let mut refs = Vec::new();
+    let mut index = 0;
+    while index + 2 < tokens.len() {
+        match (
+            tokens[index].parse::<u32>(),
+            tokens[index + 1].parse::<u32>(),
+        ) {
+            (Ok(number), Ok(_)) if tokens[index + 2].starts_with('R') => {
+                refs.push(number);
+                index += 3;
+            }
+            _ => break,
+        }
+    }
+    refs
+}
+
+fn refs_after(dict: &str, key: &str) -> Vec<u32> {
+    let Some(rest) = dict_entry(dict, key) else {
+        return Vec::new();
+    };
+    match rest.strip_prefix('[') {
+        Some(array) => parse_refs(&array[..array.find(']').unwrap_or(array.len())]),
+        None => parse_refs(rest).into_iter().take(1).collect(),
+    }
+}
+
+/// A direct integer value; indirect references yield `None`.
+fn int_after(dict: &str, key: &str) -> Option<usize> {
+    let rest = dict_entry(dict, key)?;
+    if !parse_refs(rest).is_empty() {
+        return None;
+    }
+    let end = rest
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+/// The `<< … >>` at the start of `text`, brackets included.
+fn balanced_dict(text: &str) -> Option<&str> {
+    if !text.starts_with("<<") {
+        return None;
+    }
+    let bytes = text.as_bytes();
+    let mut depth = 0usize;
+    let mut index = 0;
+    while index + 1 < bytes.len() {
+        if bytes[index] == b'<' && bytes[index + 1] == b'<' {
+            depth += 1;
+            index += 2;
+        } else if bytes[index] == b'>' && bytes[index + 1] == b'>' {
+            depth -= 1;
+            index += 2;
+            if depth == 0 {
+                return Some(&text[..index]);
+            }
+        } else {
+            index += 1;
+        }
+    }
+    None
+}
+
+fn decode_stream(dict: &str, raw: &[u8]) -> Option<Vec<u8>> {
+    if dict.contains("/FlateDecode") || dict.contains("/Fl ") || dict.contains("/Fl]") {
+        let mut decoded = Vec::new();
+        ZlibDecoder::new(raw)
+            .take(MAX_DECODED_STREAM_BYTES)
+            .read_to_end(&mut decoded)
+            .ok()?;
+        return Some(decoded);
+    }
+    if dict.contains("/Filter") {
+        // Image and other filters carry no text.
+        return None;
+    }
+    Some(raw.to_vec())
+}
+
+fn parse_objects(data: &[u8]) -> HashMap<u32, PdfObject> {
+    let mut objects = HashMap::new();
+    let mut pos = 0;
+    while let Some(found) = find(data, b"obj", pos) {
+        pos = found + 3;
+        let follows_ok = data
+            .get(found + 3)
+            .is_none_or(|&byte| is_pdf_whitespace(byte) || is_pdf_delimiter(byte));
+        let Some(number) = object_number_before(data, found).filter(|_| follows_ok) else {
+            continue;
+        };
+        let body_start = found + 3;
+        let end_obj = find(data, b"endobj", body_start).unwrap_or(data.len());
+        let stream_kw = find(data, b"stream", body_start)
+            .filter(|&at| at < end_obj && !data[..at].ends_with(b"end"));
+        let object = match stream_kw {
+            Some(at) => {
+                let dict = latin1(&data[body_start..at]);
+                let mut start = at + b"stream".len();
+                if data.get(start) == Some(&b'\r') {
+                    start += 1;
+                }
+                if data.get(start) == Some(&b'\n') {
+                    start += 1;
+                }
+                // A `/Length` past the end of the file, overflowing included, is ignored.
+                let end = int_after(&dict, "/Length")
+                    .and_then(|length| start.checked_add(length))
+                    .filter(|&end| {
+                        end <= data.len()
+                            && data[end..]
+                                .iter()
+                                .position(|&byte| !is_pdf_whitespace(byte))
+                                .is_some_and(|skip| data[end + skip..].starts_with(b"endstream"))
+                    })
+                    .or_else(|| find(data, b"endstream", start))
+                    .unwrap_or(data.len());
+                pos = end;
+                PdfObject {
+                    dict,
+                    stream: Some(data[start..end].to_vec()),
+                }
+            }
+            None => {
+                pos = end_obj;
+                PdfObject {
+                    dict: latin1(&data[body_start..end_obj]),
+                    stream: None,
+                }
+            }
+        };
+        // Later definitions win, as with incremental updates.
+        objects.insert(number, object);
+    }
+
+    let object_streams: Vec<(String, Vec<u8>)> = objects
+        .values()
+        .filter(|object| name_after(&object.dict, "/Type").as_deref() == Some("ObjStm"))
+        .filter_map(|object| Some((object.dict.clone(), object.stream.clone()?)))
+        .collect();
+    for (dict, raw) in object_streams {
+        let (Some(count), Some(first), Some(decoded)) = (
+            int_after(&dict, "/N"),
+            int_after(&dict, "/First"),
+            decode_stream(&dict, &raw),
+        ) else {
+            continue;
+        };
+        if first > decoded.len() {
+            continue;
+        }
+        let header: Vec<usize> = latin1(&decoded[..first])
+            .split_whitespace()
+            .filter_map(|token| token.parse().ok())
+            .collect();
+        // An object number or offset out of range makes the whole stream malformed.
+        let Some(entries) = header
+            .chunks(2)
+            .take(count)
+            .filter(|pair| pair.len() == 2)
+            .map(|pair| Some((u32::try_from(pair[0]).ok()?, first.checked_add(pair[1])?)))
+            .collect::<Option<Vec<(u32, usize)>>>()
+        else {
+            continue;
+        };
+        for (index, (number, start)) in entries.iter().enumerate() {
+            let end = entries
+                .get(index + 1)
+                .map(|(_, next)| *next)
+                .unwrap_or(decoded.len());
+            if *start > end || end > decoded.len() {
+                continue;
+            }
+            objects.entry(*number).or_insert_with(|| PdfObject {
+                dict: latin1(&decoded[*start..end]),
+                stream: None,
+            });
+        }
+    }
+    objects
+}
+
+/// Page objects in document order, following the page tree from the catalog. Falls back
+/// to object order when there is no usable tree.
+fn page_numbers(objects: &HashMap<u32, PdfObject>) -> Vec<u32> {
+    fn walk(objects: &HashMap<u32, PdfObject>, node: u32, depth: usize, pages: &mut Vec<u32>) {
+        let Some(object) = objects.get(&node) else {
+            return;
+        };
+        match name_after(&object.dict, "/Type").as_deref() {
+            Some("Page") => pages.push(node),
+            Some("Pages") if depth < 64 => {
+                for kid in refs_after(&object.dict, "/Kids") {
+                    walk(objects, kid, depth + 1, pages);
+                }
+            }
+            _ => {}
+        }
+    }
+    let root = objects
+        .values()
+        .find(|object| name_after(&object.dict, "/Type").as_deref() == Some("Catalog"))
+        .and_then(|catalog| refs_after(&catalog.dict, "/Pages").first().copied());
+    let mut pages = Vec::new();
+    if let Some(root) = root {
+        walk(objects, root, 0, &mut pages);
+    }
+    if pages.is_empty() {
+        pages = objects
+            .iter()
+            .filter(|(_, object)| name_after(&object.dict, "/Type").as_deref() == Some("Page"))
+            .map(|(number, _)| *number)
+            .collect();
+        pages.sort_unstable();
+    }
+    pages
+}
+
+/// Glyph code to Unicode mapping from a font's `/ToUnicode` CMap.
+#[derive(Default)]
+struct ToUnicode {
+    two_byte: bool,
+    map: HashMap<u32, String>,
+}
+
+/// Hex strings in CMap text; a `[ … ]` group becomes one `Array` item.
+#[derive(Debug)]
+enum CMapItem {
+    Hex(Vec<u8>),
+    Array(Vec<Vec<u8>>),
+}
+
+fn cmap_items(text: &str) -> Vec<CMapItem> {
+    let mut items = Vec::new();
+    let mut array: Option<Vec<Vec<u8>>> = None;
+    let mut rest = text;
+    while let Some(start) = rest.find(['<', '[', ']']) {
+        match rest.as_bytes()[start] {
+            b'[' => {
+                array = Some(Vec::new());
+                rest = &rest[start + 1..];
+            }
+            b']' => {
+                if let Some(values) = array.take() {
+                    items.push(CMapItem::Array(values));
+                }
+                rest = &rest[start + 1..];
+            }
+            _ => {
+                let Some(end) = rest[start..].find('>') else {
+                    break;
+                };
+                let value = decode_hex(&rest.as_bytes()[start + 1..start + end]);
+                match array.as_mut() {
+                    Some(values) => values.push(value),
+                    None => items.push(CMapItem::Hex(value)),
+                }
+                rest = &rest[start + end + 1..];
+            }
+        }
+    }
+    items
+}
+
+fn decode_hex(raw: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = raw
+        .iter()
+        .filter_map(|&byte| (byte as char).to_digit(16).map(|digit| digit as u8))
+        .collect();
+    digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+fn utf16_units(bytes: &[u8]) -> Vec<u16> {
+    bytes
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]))
+        .collect()
+}
+
+fn utf16be(bytes: &[u8]) -> String {
+    String::from_utf16_lossy(&utf16_units(bytes))
+}
+
+fn code_of(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |code, &byte| (code << 8) | byte as u32)
+}
+
+fn cmap_sections<'a>(cmap: &'a str, begin: &str, end: &str) -> Vec<&'a str> {
+    cmap.split(begin)
+        .skip(1)
+        .filter_map(|section| section.split(end).next())
+        .collect()
+}
+
+fn parse_to_unicode(cmap: &str) -> ToUnicode {
+    let mut result = ToUnicode::default();
+    for section in cmap_sections(cmap, "begincodespacerange", "endcodespacerange") {
+        let two_byte = cmap_items(section)
+            .iter()
+            .any(|item| matches!(item, CMapItem::Hex(code) if code.len() >= 2));
+        result.two_byte |= two_byte;
+    }
+    for section in cmap_sections(cmap, "beginbfchar", "endbfchar") {
+        let items = cmap_items(section);
+        for pair in items.chunks(2) {
+            if let [CMapItem::Hex(code), CMapItem::Hex(text)] = pair {
+                result.two_byte |= code.len() >= 2;
+                result.map.insert(code_of(code), utf16be(text));
+            }
+        }
+    }
+    for section in cmap_sections(cmap, "beginbfrange", "endbfrange") {
+        // Entries are `<lo> <hi> <dst>` or `<lo> <hi> [<dst> …]`.
+        let items = cmap_items(section);
+        for entry in items.chunks(3) {
+            let [CMapItem::Hex(lo), CMapItem::Hex(hi), dst] = entry else {
+                continue;
+            };
+            result.two_byte |= lo.len() >= 2;
+            let (lo, hi) = (code_of(lo), code_of(hi));
+            if hi < lo || hi - lo > 0xffff {
+                continue;
+            }
+            match dst {
+                CMapItem::Array(values) => {
+                    for (offset, text) in values.iter().enumerate() {
+                        result.map.insert(lo + offset as u32, utf16be(text));
+                    }
+                }
+                CMapItem::Hex(start) => {
+                    let mut units = utf16_units(start);
+                    for code in lo..=hi {
+                        result.map.insert(code, String::from_utf16_lossy(&units));
+                        if let Some(last) = units.last_mut() {
+                            *last = last.wrapping_add(1);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+/// The page's `/Resources` dictionary text, inherited from ancestors when absent.
+fn page_resources(objects: &HashMap<u32, PdfObject>, page: u32) -> Option<String> {
+    let mut node = page;
+    for _ in 0..64 {
+        let object = objects.get(&node)?;
+        if let Some(rest) = dict_entry(&object.dict, "/Resources") {
+            if let Some(dict) = balanced_dict(rest) {
+                return Some(dict.to_string());
+            }
+            let reference = parse_refs(rest).first().copied()?;
+            return objects.get(&reference).map(|object| object.dict.clone());
+        }
+        node = refs_after(&object.dict, "/Parent").first().copied()?;
+    }
+    None
+}
+
+/// `/Name N G R` pairs inside a dictionary.
+fn named_refs(dict: &str) -> Vec<(String, u32)> {
+    let spaced = dict
+        .replace('/', " /")
+        .replace("<<", " ")
+        .replace(">>", " ");
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+    let mut pairs = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if let Some(name) = token.strip_prefix('/') {
+            if let Some(&reference) = parse_refs(&tokens[index + 1..].join(" ")).first() {
+                pairs.push((name.to_string(), reference));
+            }
+        }
+    }
+    pairs
+}
+
+fn page_fonts(objects: &HashMap<u32, PdfObject>, page: u32) -> HashMap<String, ToUnicode> {
+    let mut fonts = HashMap::new();
+    let Some(resources) = page_resources(objects, page) else {
+        return fonts;
+    };
+    let Some(rest) = dict_entry(&resources, "/Font") else {
+        return fonts;
+    };
+    let font_dict = match balanced_dict(rest) {
+        Some(dict) => dict.to_string(),
+        None => match parse_refs(rest)
+            .first()
+            .and_then(|number| objects.get(number))
+        {
+            Some(object) => object.dict.clone(),
+            None => return fonts,
+        },
+    };
+    for (name, reference) in named_refs(&font_dict) {
+        let cmap = objects
+            .get(&reference)
+            .and_then(|font| refs_after(&font.dict, "/ToUnicode").first().copied())
+            .and_then(|number| objects.get(&number))
+            .and_then(|cmap| decode_stream(&cmap.dict, cmap.stream.as_deref()?))
+            .map(|decoded| parse_to_unicode(&latin1(&decoded)));
+        if let Some(cmap) = cmap {
+            fonts.insert(name, cmap);
+        }
+    }
+    fonts
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Str(Vec<u8>),
+    Num(f64),
+    Name(String),
+    Op(String),
+    Array(Vec<Token>),
+}
+
+fn read_literal_string(data: &[u8], pos: &mut usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut depth = 1;
+    while *pos < data.len() {
+        let byte = data[*pos];
+        *pos += 1;
+        match byte {
+            b'\\' => {
+                let Some(&next) = data.get(*pos) else {
+                    break;
+                };
+                *pos += 1;
+                match next {
+                    b'n' => out.push(b'\n'),
+                    b'r' => out.push(b'\r'),
+                    b't' => out.push(b'\t'),
+                    b'b' => out.push(8),
+                    b'f' => out.push(12),
+                    b'\r' | b'\n' => {
+                        if next == b'\r' && data.get(*pos) == Some(&b'\n') {
+                            *pos += 1;
+                        }
+                    }
+                    b'0'..=b'7' => {
+                        let mut value = (next - b'0') as u32;
+                        for _ in 0..2 {
+                            match data.get(*pos) {
+                                Some(&digit @ b'0'..=b'7') => {
+                                    value = value * 8 + (digit - b'0') as u32;
+                                    *pos += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        out.push(value as u8);
+                    }
+                    other => out.push(other),
+                }
+            }
+            b'(' => {
+                depth += 1;
+                out.push(byte);
+            }
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+                out.push(byte);
+            }
+            _ => out.push(byte),
+        }
+    }
+    out
+}
+
+fn tokenize(data: &[u8]) -> Vec<Token> {
+    let mut stack: Vec<Vec<Token>> = vec![Vec::new()];
+    let mut pos = 0;
+    while pos < data.len() {
+        let byte = data[pos];
+        let token = match byte {
+            _ if is_pdf_whitespace(byte) => {
+                pos += 1;
+                continue;
+            }
+            b'%' => {
+                while pos < data.len() && data[pos] != b'\n' && data[pos] != b'\r' {
+                    pos += 1;
+                }
+                continue;
+            }
+            b'(' => {
+                pos += 1;
+                Token::Str(read_literal_string(data, &mut pos))
+            }
+            b'<' if data.get(pos + 1) == Some(&b'<') => {
+                pos += 2;
+                continue;
+            }
+            b'>' => {
+                pos += 1;
+                continue;
+            }
+            b'<' => {
+                let end = find(data, b">", pos).unwrap_or(data.len());
+                let token = Token::Str(decode_hex(&data[pos + 1..end]));
+                pos = end + 1;
+                token
+            }
+            b'[' => {
+                pos += 1;
+                stack.push(Vec::new());
+                continue;
+            }
+            b']' => {
+                pos += 1;
+                if stack.len() > 1 {
+                    let array = stack.pop().unwrap_or_default();
+                    Token::Array(array)
+                } else {
+                    continue;
+                }
+            }
+            b'{' | b'}' | b')' => {
+                pos += 1;
+                continue;
+            }
+            _ => {
+                let start = pos;
+                pos += 1;
+                while pos < data.len()
+                    && !is_pdf_whitespace(data[pos])
+                    && !is_pdf_delimiter(data[pos])
+                {
+                    pos += 1;
+                }
+                let word = latin1(&data[start..pos]);
+                if let Some(name) = word.strip_prefix('/') {
+                    Token::Name(name.to_string())
+                } else if let Ok(number) = word.parse::<f64>() {
+                    Token::Num(number)
+                } else if word == "ID" {
+                    // Inline image data runs until `EI`.
+                    pos = find(data, b"EI", pos)
+                        .map(|at| at + 2)
+                        .unwrap_or(data.len());
+                    continue;
+                } else {
+                    Token::Op(word)
+                }
+            }
+        };
+        if let Some(top) = stack.last_mut() {
+            top.push(token);
+        }
+    }
+    while stack.len() > 1 {
+        let array = stack.pop().unwrap_or_default();
+        if let Some(top) = stack.last_mut() {
+            top.push(Token::Array(array));
+        }
+    }
+    stack.pop().unwrap_or_default()
+}
+
+fn decode_text(bytes: &[u8], font: Option<&ToUnicode>) -> String {
+    if let Some(font) = font {
+        let width = if font.two_byte { 2 } else { 1 };
+        return bytes
+            .chunks(width)
+            .map(|code| match font.map.get(&code_of(code)) {
+                Some(text) => text.clone(),
+                None if width == 1 => (code[0] as char).to_string(),
+                None => String::new(),
+            })
+            .collect();
+    }
+    if bytes.starts_with(&[0xfe, 0xff]) {
+        return utf16be(&bytes[2..]);
+    }
+    latin1(bytes)
+}
+
+fn push_newline(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn push_space(out: &mut String) {
+    if !out.is_empty() && !out.ends_with([' ', '\n']) {
+        out.push(' ');
+    }
+}
+
+/// Text shown by a page's content stream. Positioning operators become line breaks or
+/// spaces; this is a reading aid, not a layout-faithful rendering.
+fn content_text(content: &[u8], fonts: &HashMap<String, ToUnicode>) -> String {
+    let mut out = String::new();
+    let mut operands: Vec<Token> = Vec::new();
+    let mut font: Option<&ToUnicode> = None;
+    let mut last_y: Option<f64> = None;
+    for token in tokenize(content) {
+        let Token::Op(op) = token else {
+            operands.push(token);
+            continue;
+        };
+        let number = |index: usize| match operands.get(index) {
+            Some(Token::Num(value)) => Some(*value),
+            _ => None,
+        };
+        match op.as_str() {
+            "Tf" => {
+                font = operands.iter().find_map(|operand| match operand {
+                    Token::Name(name) => fonts.get(name),
+                    _ => None,
+                });
+            }
+            "Tj" | "'" | "\"" => {
+                if op != "Tj" {
+                    push_newline(&mut out);
+                }
+                if let Some(Token::Str(bytes)) = operands.last() {
+                    out.push_str(&decode_text(bytes, font));
+                }
+            }
+            "TJ" => {
+                if let Some(Token::Array(items)) = operands.last() {
+                    for item in items {
+                        match item {
+                            Token::Str(bytes) => out.push_str(&decode_text(bytes, font)),
+                            Token::Num(adjust) if *adjust < -250.0 => push_space(&mut out),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            "Td" | "TD" => match number(1) {
+                Some(dy) if dy.abs() > 0.01 => push_newline(&mut out),
+                _ => push_space(&mut out),
+            },
+            "T*" => push_newline(&mut out),
+            "Tm" => {
+                let y = number(5);
+                if last_y.is_some() && y != last_y {
+                    push_newline(&mut out);
+                } else {
+                    push_space(&mut out);
+                }
+                last_y = y;
+            }
+            "ET" => push_space(&mut out),
+            _ => {}
+        }
+        operands.clear();
+    }
+    let lines: Vec<&str> = out.lines().map(str::trim_end).collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// Best-effort text extraction: pages in document order, up to `MAX_PDF_PAGES`. Handles
+/// uncompressed and Flate streams, object streams and `/ToUnicode` font maps. Encrypted
+/// documents are rejected.
+pub(crate) fn extract_pdf_text(data: &[u8]) -> Result<PdfDocument, String> {
+    if find(data, b"%PDF-", 0).is_none_or(|at| at > 1024) {
+        return Err("Not a PDF file".to_string());
+    }
+    if find(data, b"/Encrypt", 0).is_some() {
+        return Err("Encrypted PDFs are not supported".to_string());
+    }
+    let objects = parse_objects(data);
+    let page_numbers = page_numbers(&objects);
+    let mut pages = Vec::new();
+    for (index, page) in page_numbers.iter().take(MAX_PDF_PAGES).enumerate() {
+        let Some(object) = objects.get(page) else {
+            continue;
+        };
+        let fonts = page_fonts(&objects, *page);
+        let mut content = Vec::new();
+        for number in refs_after(&object.dict, "/Contents") {
+            let decoded = objects
+                .get(&number)
+                .and_then(|stream| decode_stream(&stream.dict, stream.stream.as_deref()?));
+            if let Some(decoded) = decoded {
+                content.extend(decoded);
+                content.push(b'\n');
+            }
+        }
+        pages.push(PdfPage {
+            number: index + 1,
+            text: content_text(&content, &fonts),
+        });
+    }
+    Ok(PdfDocument {
+        page_count: page_numbers.len(),
+        no_text: pages.iter().all(|page| page.text.trim().is_empty()),
+        omitted_pages: page_numbers.len().saturating_sub(MAX_PDF_PAGES),
+        pages,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        extract_pdf_text, parse_notebook, render_document, ReadFormat, StructuredDocument,
+    };
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn pdf(objects: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut out = b"%PDF-1.5\n".to_vec();
+        for (index, body) in objects.into_iter().enumerate() {
+            out.extend(format!("{} 0 obj\n", index + 1).bytes());
+            out.extend(body);
+            out.extend(b"\nendobj\n");
+        }
+        out.extend(b"trailer\n<< /Root 1 0 R >>\n%%EOF\n");
+        out
+    }
+
+    fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut out = format!("<< {dict} /Length {} >>\nstream\n", data.len()).into_bytes();
+        out.extend(data);
+        out.extend(b"\nendstream");
+        out
+    }
+
+    #[test]
+    fn extracts_text_per_page_in_tree_order() {
+        let bytes = pdf(vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            b"<< /Type /Pages /Kids [5 0 R 3 0 R] /Count 2 >>".to_vec(),
+            b"<< /Type /Page /Parent 2 0 R /Contents 4 0 R >>".to_vec(),
+            stream(
+                "",
+                b"BT /F1 12 Tf 72 700 Td (Hello) Tj 0 -14 Td [(Wor) -20 (ld)] TJ ET",
+            ),
+            b"<< /Type /Page /Parent 2 0 R /Contents [6 0 R] >>".to_vec(),
+            stream("", b"BT (Page \\(one\\)) Tj ET"),
+        ]);
+        let document = extract_pdf_text(&bytes).expect("extract");
+        assert_eq!(document.page_count, 2);
+        assert!(!document.no_text);
+        assert_eq!(document.pages[0].text, "Page (one)");
+        assert_eq!(document.pages[1].text, "Hello\nWorld");
+    }
+
+    #[test]
+    fn decodes_flate_streams_with_to_unicode_maps() {
+        let compress = |data: &[u8]| {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).expect("compress");
+            encoder.finish().expect("finish")
+        };
+        let cmap = b"1 begincodespacerange <0000> <FFFF> endcodespacerange\n\
+            1 beginbfchar <0001> <0048> endbfchar\n\
+            1 beginbfrange <0002> <0003> <0069> endbfrange\nendcmap";
+        let bytes = pdf(vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            b"<< /Type /Pages /Kids [3 0 R] /Resources << /Font << /F1 5 0 R >> >> >>".to_vec(),
+            b"<< /Type /Page /Parent 2 0 R /Contents 4 0 R >>".to_vec(),
+            stream(
+                "/Filter /FlateDecode",
+                &compress(b"BT /F1 10 Tf <000100020003> Tj ET"),
+            ),
+            b"<< /Type /Font /Subtype /Type0 /ToUnicode 6 0 R >>".to_vec(),
+            stream("/Filter /FlateDecode", &compress(cmap)),
+        ]);
+        let document = extract_pdf_text(&bytes).expect("extract");
+        assert_eq!(document.pages[0].text, "Hij");
+    }
+
+    #[test]
+    fn flags_pdfs_without_text() {
+        let bytes = pdf(vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            b"<< /Type /Pages /Kids [3 0 R] >>".to_vec(),
+            b"<< /Type /Page /Parent 2 0 R /Contents 4 0 R >>".to_vec(),
+            stream("", b"q 612 0 0 792 0 0 cm /Im1 Do Q"),
+        ]);
+        let document = extract_pdf_text(&bytes).expect("extract");
+        assert!(document.no_text);
+        let (text, truncated) = render_document(&StructuredDocument::Pdf(document), 1_000);
+        assert!(text.contains("scanned"));
+        assert!(!truncated);
+        assert!(extract_pdf_text(b"not a pdf").is_err());
+    }
+
+    #[test]
+    fn ignores_offsets_that_overflow() {
+        let bytes = pdf(vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            b"<< /Type /Pages /Kids [3 0 R] >>".to_vec(),
+            b"<< /Type /Page /Parent 2 0 R /Contents 4 0 R >>".to_vec(),
+            b"<< /Length 18446744073709551615 >>\nstream\nBT (Hi) Tj ET\nendstream".to_vec(),
+            stream("/Type /ObjStm /N 1 /First 23", b"6 18446744073709551615 "),
+        ]);
+        let document = extract_pdf_text(&bytes).expect("extract");
+        assert_eq!(document.pages[0].text, "Hi");
+    }
+
+    #[test]
+    fn parses_notebook_cells_and_outputs() {
+        let raw = serde_json::json!({
+            "metadata": { "language_info": { "name": "python" } },
+            "cells": [
+                { "cell_type": "markdown", "source": ["# Title\n", "Intro"] },
+                {
+                    "cell_type": "code",
+                    "execution_count": 3,
+                    "source": "print('hi')",
+                    "outputs": [
+                        { "output_type": "stream", "name": "stdout", "text": ["hi\n"] },
+                        { "output_type": "display_data", "data": { "image/png": "AAAA" } },
+                        {
+                            "output_type": "error",
+                            "ename": "ValueError",
+                            "evalue": "bad",
+                            "traceback": ["\u{1b}[0;31mValueError\u{1b}[0m: bad"]
+                        }
+                    ]
+                }
+            ]
+        })
+        .to_string();
+        let notebook = parse_notebook(&raw).expect("parse");
+        assert_eq!(notebook.language.as_deref(), Some("python"));
+        assert_eq!(notebook.cells[0].source, "# Title\nIntro");
+        let outputs = &notebook.cells[1].outputs;
+        assert_eq!(outputs[0].text, "hi\n");
+        assert_eq!(outputs[1].text, "[image/png output]");
+        assert_eq!(outputs[2].text, "ValueError: bad");
+
+        let (text, truncated) = render_document(&StructuredDocument::Notebook(notebook), 60);
+        assert!(truncated);
+        assert!(text.starts_with("# Title\nIntro\n\nIn [3]:\n```python\nprint('hi')"));
+        assert!(text.ends_with("[… truncated at 60 bytes]"));
+        assert!(parse_notebook("{}").is_err());
+    }
+
+    #[test]
+    fn parses_read_formats() {
+        assert_eq!(ReadFormat::parse(None).expect("default"), ReadFormat::Auto);
+        assert_eq!(
+            ReadFormat::parse(Some("structured")).expect("structured"),
+            ReadFormat::Structured
+        );
+        assert!(ReadFormat::parse(Some("html")).is_err());
+    }
+}
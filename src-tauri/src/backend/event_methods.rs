@@ -0,0 +1,462 @@
+use serde::Serialize;
+
+/// Bumped whenever an event method is added, removed or changes its payload shape.
+pub(crate) const EVENTS_SCHEMA_VERSION: u32 = 22;
+
+pub(crate) const MICODE_CONNECTED: &str = "micode/connected";
+pub(crate) const MICODE_STDERR: &str = "micode/stderr";
+pub(crate) const MICODE_PARSE_ERROR: &str = "micode/parseError";
+pub(crate) const MICODE_SETTINGS_PARSE_ERROR: &str = "micode/settingsParseError";
+pub(crate) const MICODE_MODEL_PARSE_WARNING: &str = "micode/modelParseWarning";
+pub(crate) const MICODE_MCP_SERVERS_CHANGED: &str = "micode/mcpServersChanged";
+pub(crate) const MICODE_AVAILABLE_COMMANDS_UPDATED: &str = "micode/availableCommands/updated";
+pub(crate) const MICODE_BACKGROUND_THREAD: &str = "micode/backgroundThread";
+pub(crate) const MICODE_RESTART_FAILED: &str = "micode/restartFailed";
+pub(crate) const MICODE_UNRESPONSIVE: &str = "micode/unresponsive";
+pub(crate) const MICODE_HEALTHY: &str = "micode/healthy";
+pub(crate) const MICODE_DISCONNECTED: &str = "micode/disconnected";
+pub(crate) const MICODE_RECONNECTING: &str = "micode/reconnecting";
+pub(crate) const MICODE_RECONNECT_FAILED: &str = "micode/reconnectFailed";
+pub(crate) const MICODE_STORE_MAINTENANCE: &str = "micode/storeMaintenance";
+pub(crate) const BACKGROUND_REAPED: &str = "background/reaped";
+pub(crate) const THREAD_STARTED: &str = "thread/started";
+pub(crate) const THREAD_NAME_UPDATED: &str = "thread/name/updated";
+pub(crate) const THREAD_TOKEN_USAGE_UPDATED: &str = "thread/tokenUsage/updated";
+pub(crate) const THREAD_OWNERSHIP_CHANGED: &str = "thread/ownershipChanged";
+pub(crate) const THREADS_CHANGED: &str = "threads/changed";
+pub(crate) const TURN_STARTED: &str = "turn/started";
+pub(crate) const TURN_COMPLETED: &str = "turn/completed";
+pub(crate) const TURN_FAILED: &str = "turn/failed";
+pub(crate) const TURN_CANCELLED: &str = "turn/cancelled";
+pub(crate) const TURN_PLAN_UPDATED: &str = "turn/plan/updated";
+pub(crate) const TURN_PHASE: &str = "turn/phase";
+pub(crate) const TURN_TIMEOUT: &str = "turn/timeout";
+pub(crate) const TURN_STALLED: &str = "turn/stalled";
+pub(crate) const TURN_RESUMED_STREAMING: &str = "turn/resumedStreaming";
+pub(crate) const TURN_QUEUED: &str = "turn/queued";
+pub(crate) const TURN_DEQUEUED: &str = "turn/dequeued";
+pub(crate) const TURN_QUEUE_CLEARED: &str = "turn/queueCleared";
+pub(crate) const TOOL_SLOW: &str = "tool/slow";
+pub(crate) const ITEM_STARTED: &str = "item/started";
+pub(crate) const ITEM_COMPLETED: &str = "item/completed";
+pub(crate) const ITEM_AGENT_MESSAGE_DELTA: &str = "item/agentMessage/delta";
+pub(crate) const ITEM_REASONING_TEXT_DELTA: &str = "item/reasoning/textDelta";
+pub(crate) const WORKSPACE_REQUEST_APPROVAL: &str = "workspace/requestApproval";
+pub(crate) const WORKSPACE_APPROVAL_RESOLVED: &str = "workspace/approvalResolved";
+pub(crate) const WORKSPACE_AUTO_APPROVED: &str = "workspace/autoApproved";
+pub(crate) const WORKSPACE_AUTO_DENIED: &str = "workspace/autoDenied";
+pub(crate) const WORKSPACE_APPROVAL_REMINDER: &str = "workspace/approvalReminder";
+pub(crate) const WORKSPACE_APPROVAL_TIMED_OUT: &str = "workspace/approvalTimedOut";
+pub(crate) const WORKSPACE_CONFIG_STALE: &str = "workspace/configStale";
+pub(crate) const WORKSPACE_RESTART_PENDING: &str = "workspace/restartPending";
+pub(crate) const WORKSPACE_RESTARTED: &str = "workspace/restarted";
+pub(crate) const WORKSPACE_CONNECT_QUEUED: &str = "workspace/connectQueued";
+pub(crate) const WORKSPACE_CONNECTING: &str = "workspace/connecting";
+pub(crate) const WORKSPACE_CONNECTED: &str = "workspace/connected";
+pub(crate) const WORKSPACE_BOOTSTRAP_WARNINGS: &str = "workspace/bootstrapWarnings";
+pub(crate) const WORKSPACE_RESOURCE_WARNING: &str = "workspace/resourceWarning";
+pub(crate) const AUTO_RUN_PROGRESS: &str = "autoRun/progress";
+pub(crate) const AUDIT_WRITE_FAILED: &str = "audit/writeFailed";
+pub(crate) const DICTATION_PAUSED: &str = "dictation/paused";
+pub(crate) const DICTATION_RESUMED: &str = "dictation/resumed";
+pub(crate) const NOTIFICATIONS_UNREAD_CHANGED: &str = "notifications/unreadChanged";
+pub(crate) const SETTINGS_UPDATED: &str = "settings/updated";
+pub(crate) const OPERATION_PROGRESS: &str = "operation/progress";
+pub(crate) const OPERATION_FINISHED: &str = "operation/finished";
+
+/// Notifications the daemon sends to remote clients; each wraps one payload.
+pub(crate) const DAEMON_APP_SERVER_EVENT: &str = "app-server-event";
+pub(crate) const DAEMON_TERMINAL_OUTPUT: &str = "terminal-output";
+pub(crate) const DAEMON_TERMINAL_EXIT: &str = "terminal-exit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub(crate) struct EventMethod {
+    pub(crate) method: &'static str,
+    pub(crate) description: &'static str,
+}
+
+const fn event(method: &'static str, description: &'static str) -> EventMethod {
+    EventMethod {
+        method,
+        description,
+    }
+}
+
+/// Top-level payload keys the registry documents for `method`: the `{ … }` its
+/// description starts with.
+#[cfg(test)]
+pub(crate) fn documented_payload_keys(method: &str) -> Vec<&'static str> {
+    let description = EVENT_METHODS
+        .iter()
+        .chain(DAEMON_NOTIFICATIONS)
+        .find(|event| event.method == method)
+        .map(|event| event.description)
+        .unwrap_or_default();
+    description
+        .strip_prefix('{')
+        .and_then(|rest| rest.split_once('}'))
+        .map(|(keys, _)| keys.split(',').map(str::trim).collect())
+        .unwrap_or_default()
+}
+
+/// Every method emitted as an app-server event, with its payload in a line.
+pub(crate) const EVENT_METHODS: &[EventMethod] = &[
+    event(
+        MICODE_CONNECTED,
+        "{ workspaceId, eventsSchemaVersion } once the agent finished its handshake",
+    ),
+    event(
+        MICODE_STDERR,
+        "{ message } for each line the agent wrote to stderr",
+    ),
+    event(
+        MICODE_PARSE_ERROR,
+        "{ error, raw } for agent output that is not JSON",
+    ),
+    event(
+        MICODE_SETTINGS_PARSE_ERROR,
+        "{ path, message, line?, column? } for an unreadable settings file",
+    ),
+    event(
+        MICODE_MODEL_PARSE_WARNING,
+        "{ path, key, index, message } for a custom model entry left out of model/list",
+    ),
+    event(
+        MICODE_MCP_SERVERS_CHANGED,
+        "{ workspaceId, name, action, servers } after an MCP server was added, updated or removed",
+    ),
+    event(
+        MICODE_AVAILABLE_COMMANDS_UPDATED,
+        "{ threadId, availableCommands } slash commands offered by the agent",
+    ),
+    event(
+        MICODE_BACKGROUND_THREAD,
+        "{ threadId, action } for helper threads the UI should hide",
+    ),
+    event(
+        MICODE_RESTART_FAILED,
+        "{ workspaceId, error } when a restart could not respawn",
+    ),
+    event(
+        MICODE_UNRESPONSIVE,
+        "{ workspaceId, idleSeconds, pendingRequests, stuckOn, missedPings, autoRestart } when the agent stopped answering",
+    ),
+    event(
+        MICODE_HEALTHY,
+        "{ workspaceId } when an unresponsive agent answers a ping again",
+    ),
+    event(
+        MICODE_DISCONNECTED,
+        "{ workspaceId, exitCode, stderrTail } when the agent exited on its own",
+    ),
+    event(
+        MICODE_RECONNECTING,
+        "{ workspaceId, attempt, maxAttempts, delayMs } before each respawn of a crashed agent",
+    ),
+    event(
+        MICODE_RECONNECT_FAILED,
+        "{ workspaceId, attempts, error } once respawning a crashed agent gave up",
+    ),
+    event(
+        MICODE_STORE_MAINTENANCE,
+        "{ workspaceId, report } after thread stores were compacted",
+    ),
+    event(
+        BACKGROUND_REAPED,
+        "{ workspaceId, reaped, remaining } after leaked background threads were archived",
+    ),
+    event(
+        THREAD_STARTED,
+        "{ thread: { id, name } } for a new or forked thread",
+    ),
+    event(THREAD_NAME_UPDATED, "{ threadId, threadName }"),
+    event(
+        THREAD_TOKEN_USAGE_UPDATED,
+        "{ threadId, turnId, tokenUsage, lookupMs? } while a turn runs; lookupMs once it ended",
+    ),
+    event(
+        THREAD_OWNERSHIP_CHANGED,
+        "{ workspaceId, threadId, ownerClientId, since } in remote mode",
+    ),
+    event(
+        THREADS_CHANGED,
+        "{ workspaceId } when the thread list changed on disk",
+    ),
+    event(
+        TURN_STARTED,
+        "{ threadId, turn, samplingParams, samplingApplied, effort, effortApplied, redactions }",
+    ),
+    event(TURN_COMPLETED, "{ threadId, turn, artifacts, audit? }"),
+    event(TURN_FAILED, "{ threadId, turn, reason }"),
+    event(
+        TURN_CANCELLED,
+        "{ threadId, turnId, turn, cancelledBy, artifacts } when the user interrupted the turn",
+    ),
+    event(
+        TURN_PLAN_UPDATED,
+        "{ threadId, turnId, explanation, summaryText, plan }",
+    ),
+    event(
+        TURN_PHASE,
+        "{ threadId, turnId, phase, at } when a foreground turn changes phase",
+    ),
+    event(
+        TURN_TIMEOUT,
+        "{ threadId, turnId, elapsedMs, timeoutMs } when a prompt exceeds promptTimeoutSecs",
+    ),
+    event(
+        TURN_STALLED,
+        "{ threadId, turnId, idleSecs } when a running turn has been silent for turnStallWarningSecs",
+    ),
+    event(
+        TURN_RESUMED_STREAMING,
+        "{ threadId, turnId, stalledSecs } when a stalled turn produces output again",
+    ),
+    event(
+        TOOL_SLOW,
+        "{ threadId, toolCallId, itemId, tool, elapsedMs, thresholdMs } once per tool call still running after toolSlowWarningSecs",
+    ),
+    event(
+        TURN_QUEUED,
+        "{ threadId, queueId, position } when a message waits for the running turn",
+    ),
+    event(
+        TURN_DEQUEUED,
+        "{ threadId, queueId, remaining } when a queued message starts its turn",
+    ),
+    event(
+        TURN_QUEUE_CLEARED,
+        "{ threadId, cleared, reason } when queued messages are dropped",
+    ),
+    event(ITEM_STARTED, "{ threadId, item } when a tool call starts"),
+    event(
+        ITEM_COMPLETED,
+        "{ threadId, item } when a tool call or auto-run item finishes",
+    ),
+    event(
+        ITEM_AGENT_MESSAGE_DELTA,
+        "{ threadId, itemId, delta } streamed reply text",
+    ),
+    event(
+        ITEM_REASONING_TEXT_DELTA,
+        "{ threadId, itemId, delta } streamed reasoning",
+    ),
+    event(
+        WORKSPACE_REQUEST_APPROVAL,
+        "JSON-RPC request { threadId, command, raw } awaiting an approval decision",
+    ),
+    event(
+        WORKSPACE_APPROVAL_RESOLVED,
+        "{ requestId, duplicateRequestIds, threadId, decision, resolvedBy } once an approval is no longer pending",
+    ),
+    event(
+        WORKSPACE_AUTO_APPROVED,
+        "{ requestId, threadId, command, rule, decision } when an allow rule answered an approval",
+    ),
+    event(
+        WORKSPACE_AUTO_DENIED,
+        "{ requestId, threadId, command, rule, decision } when a deny rule answered an approval",
+    ),
+    event(
+        WORKSPACE_APPROVAL_REMINDER,
+        "{ requestId, threadId, title, remainingSecs, timeoutSecs } once half of approvalTimeoutSecs passed unanswered",
+    ),
+    event(
+        WORKSPACE_APPROVAL_TIMED_OUT,
+        "{ requestId, duplicateRequestIds, threadId, title, timeoutSecs } when an unanswered approval was cancelled",
+    ),
+    event(
+        WORKSPACE_CONFIG_STALE,
+        "{ workspaceId, reasons } when the running agent uses outdated settings",
+    ),
+    event(
+        WORKSPACE_RESTART_PENDING,
+        "{ workspaceId, timeoutMs } when a restart waits for running turns to finish",
+    ),
+    event(
+        WORKSPACE_RESTARTED,
+        "{ workspaceId } once a restart that waited for running turns respawned the agent",
+    ),
+    event(
+        WORKSPACE_CONNECT_QUEUED,
+        "{ workspaceId, position, queueLength } while waiting for a connect slot",
+    ),
+    event(
+        WORKSPACE_CONNECTING,
+        "{ workspaceId } when the agent process starts spawning",
+    ),
+    event(
+        WORKSPACE_CONNECTED,
+        "{ workspaceId, ok, error } once the spawn finished or failed",
+    ),
+    event(
+        WORKSPACE_BOOTSTRAP_WARNINGS,
+        "{ workspaceId, warnings } found when adding a workspace",
+    ),
+    event(
+        WORKSPACE_RESOURCE_WARNING,
+        "{ workspaceId, terminalId, kind, usage, thresholds, action } for a runaway process tree",
+    ),
+    event(
+        AUTO_RUN_PROGRESS,
+        "{ threadId, run } as an auto-run advances",
+    ),
+    event(
+        AUDIT_WRITE_FAILED,
+        "{ threadId, turnId, error } when a turn audit could not be saved",
+    ),
+    event(
+        DICTATION_PAUSED,
+        "{ reason } when an approval interrupts an active dictation session",
+    ),
+    event(
+        DICTATION_RESUMED,
+        "{ heldTranscripts } once the approval resolved; held transcripts follow",
+    ),
+    event(
+        NOTIFICATIONS_UNREAD_CHANGED,
+        "{ unreadCount } when the notification inbox gains or reads entries",
+    ),
+    event(
+        SETTINGS_UPDATED,
+        "{ scope, workspaceId?, revision, keys, values } after every settings mutation; secrets are left out",
+    ),
+    event(
+        OPERATION_PROGRESS,
+        "{ operationId, kind, stage, completed?, total? } while a cancellable command runs",
+    ),
+    event(
+        OPERATION_FINISHED,
+        "{ operationId, kind, status, error? } once it completed, was cancelled or failed",
+    ),
+];
+
+/// Notifications sent by the daemon over its JSON-RPC connection.
+pub(crate) const DAEMON_NOTIFICATIONS: &[EventMethod] = &[
+    event(
+        DAEMON_APP_SERVER_EVENT,
+        "{ workspace_id, message } wrapping one app-server event",
+    ),
+    event(DAEMON_TERMINAL_OUTPUT, "{ workspaceId, terminalId, data }"),
+    event(DAEMON_TERMINAL_EXIT, "{ workspaceId, terminalId }"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BackendCapabilities {
+    pub(crate) backend_version: &'static str,
+    pub(crate) events_schema_version: u32,
+    pub(crate) events: &'static [EventMethod],
+    pub(crate) daemon_notifications: &'static [EventMethod],
+}
+
+pub(crate) fn backend_capabilities() -> BackendCapabilities {
+    BackendCapabilities {
+        backend_version: env!("CARGO_PKG_VERSION"),
+        events_schema_version: EVENTS_SCHEMA_VERSION,
+        events: EVENT_METHODS,
+        daemon_notifications: DAEMON_NOTIFICATIONS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    /// Requests the backend sends to the agent rather than events it emits.
+    const AGENT_REQUEST_METHODS: &[&str] = &[
+        "initialize",
+        "session/new",
+        "session/prompt",
+        "session/cancel",
+    ];
+
+    fn is_registered(method: &str) -> bool {
+        EVENT_METHODS
+            .iter()
+            .chain(DAEMON_NOTIFICATIONS)
+            .any(|event| event.method == method)
+    }
+
+    fn rust_sources(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+        for entry in std::fs::read_dir(dir).expect("read source dir").flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                rust_sources(&path, files);
+            } else if path.extension().and_then(|ext| ext.to_str()) == Some("rs") {
+                files.push(path);
+            }
+        }
+    }
+
+    /// The parts of a source file outside its test modules, whose payloads are fixtures
+    /// rather than emitted events.
+    fn production_source(path: &Path, source: &str) -> String {
+        if path.file_name().and_then(|name| name.to_str()) == Some("tests.rs") {
+            return String::new();
+        }
+        const TEST_MODULE: &str = "#[cfg(test)]\nmod ";
+        let mut production = String::new();
+        let mut rest = source;
+        while let Some(start) = rest.find(TEST_MODULE) {
+            production.push_str(&rest[..start]);
+            let module = &rest[start..];
+            let declaration = module.find('\n').and_then(|attr_end| {
+                let line_end = module[attr_end + 1..].find('\n')? + attr_end + 1;
+                module[..line_end].ends_with(';').then_some(line_end)
+            });
+            // `mod tests;` only declares a file of its own; an inline module ends at the
+            // first closing brace in the first column.
+            let end = declaration.or_else(|| module.find("\n}\n").map(|end| end + 3));
+            rest = end.map_or("", |end| &module[end..]);
+        }
+        production.push_str(rest);
+        production
+    }
+
+    #[test]
+    fn registry_has_no_duplicates() {
+        let mut seen = HashSet::new();
+        for event in EVENT_METHODS.iter().chain(DAEMON_NOTIFICATIONS) {
+            assert!(
+                seen.insert(event.method),
+                "{} registered twice",
+                event.method
+            );
+            assert!(!event.description.is_empty());
+        }
+    }
+
+    #[test]
+    fn every_emitted_method_is_registered() {
+        let literal_method =
+            Regex::new(r#""method":\s*"([^"]+)"|emit_event\(\s*"([^"]+)""#).expect("method regex");
+        let mut files = Vec::new();
+        rust_sources(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+            &mut files,
+        );
+        let mut unregistered = Vec::new();
+        for file in files {
+            let source = std::fs::read_to_string(&file).expect("read source");
+            let source = production_source(&file, &source);
+            for captures in literal_method.captures_iter(&source) {
+                let method = captures
+                    .get(1)
+                    .or_else(|| captures.get(2))
+                    .map(|found| found.as_str())
+                    .unwrap_or_default();
+                if !is_registered(method) && !AGENT_REQUEST_METHODS.contains(&method) {
+                    unregistered.push(format!("{}: {method}", file.display()));
+                }
+            }
+        }
+        assert!(
+            unregistered.is_empty(),
+            "unregistered event methods: {unregistered:?}"
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// Doctor runs in quick succession (settings edits, retries) reuse a probe this young.
+pub(crate) const HANDSHAKE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Identifies a probed CLI. The binary mtime is part of the key so reinstalling or
+/// upgrading MiCode invalidates the cached answer immediately.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct HandshakeKey {
+    pub(crate) binary: String,
+    pub(crate) args: Option<String>,
+    pub(crate) binary_mtime_ms: Option<u64>,
+}
+
+impl HandshakeKey {
+    pub(crate) fn new(binary: &Path, args: Option<&str>) -> Self {
+        let binary_mtime_ms = std::fs::metadata(binary)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|age| age.as_millis() as u64);
+        Self {
+            binary: binary.to_string_lossy().to_string(),
+            args: args
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(ToString::to_string),
+            binary_mtime_ms,
+        }
+    }
+}
+
+/// Outcome of an ACP `initialize` round trip.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HandshakeProbe {
+    pub(crate) ok: bool,
+    pub(crate) latency_ms: Option<u64>,
+    /// Protocol version the agent answered with, which may differ from the one requested.
+    pub(crate) protocol_version: Option<u64>,
+    pub(crate) checked_at_ms: u64,
+}
+
+/// Where the doctor's handshake answer came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum HandshakeSource {
+    Probe,
+    Cache,
+    Session,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct HandshakeCache {
+    entries: HashMap<HandshakeKey, HandshakeProbe>,
+}
+
+impl HandshakeCache {
+    pub(crate) fn get(&self, key: &HandshakeKey, now_ms: u64) -> Option<HandshakeProbe> {
+        self.entries
+            .get(key)
+            .filter(|probe| is_fresh(probe, now_ms))
+            .cloned()
+    }
+
+    pub(crate) fn insert(&mut self, key: HandshakeKey, probe: HandshakeProbe) {
+        let now_ms = probe.checked_at_ms;
+        self.entries.retain(|_, cached| is_fresh(cached, now_ms));
+        self.entries.insert(key, probe);
+    }
+}
+
+fn is_fresh(probe: &HandshakeProbe, now_ms: u64) -> bool {
+    now_ms.saturating_sub(probe.checked_at_ms) < HANDSHAKE_CACHE_TTL.as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HandshakeCache, HandshakeKey, HandshakeProbe, HANDSHAKE_CACHE_TTL};
+
+    fn key(binary: &str, args: Option<&str>, mtime: u64) -> HandshakeKey {
+        HandshakeKey {
+            binary: binary.to_string(),
+            args: args.map(ToString::to_string),
+            binary_mtime_ms: Some(mtime),
+        }
+    }
+
+    fn probe(checked_at_ms: u64) -> HandshakeProbe {
+        HandshakeProbe {
+            ok: true,
+            latency_ms: Some(120),
+            protocol_version: Some(1),
+            checked_at_ms,
+        }
+    }
+
+    #[test]
+    fn keys_on_binary_args_and_mtime() {
+        let mut cache = HandshakeCache::default();
+        cache.insert(key("/usr/bin/micode", None, 10), probe(1_000));
+        assert_eq!(
+            cache.get(&key("/usr/bin/micode", None, 10), 1_000),
+            Some(probe(1_000))
+        );
+        assert_eq!(cache.get(&key("/usr/bin/micode", None, 11), 1_000), None);
+        assert_eq!(
+            cache.get(&key("/usr/bin/micode", Some("--debug"), 10), 1_000),
+            None
+        );
+        assert_eq!(cache.get(&key("/opt/micode", None, 10), 1_000), None);
+    }
+
+    #[test]
+    fn entries_expire_after_the_ttl() {
+        let ttl_ms = HANDSHAKE_CACHE_TTL.as_millis() as u64;
+        let mut cache = HandshakeCache::default();
+        cache.insert(key("micode", None, 10), probe(1_000));
+        assert!(cache
+            .get(&key("micode", None, 10), 1_000 + ttl_ms - 1)
+            .is_some());
+        assert!(cache
+            .get(&key("micode", None, 10), 1_000 + ttl_ms)
+            .is_none());
+
+        cache.insert(key("other", None, 10), probe(1_000 + ttl_ms));
+        assert_eq!(cache.entries.len(), 1);
+    }
+
+    #[test]
+    fn missing_binaries_have_no_mtime() {
+        let key = HandshakeKey::new(
+            std::path::Path::new("/definitely/missing/micode"),
+            Some("  "),
+        );
+        assert_eq!(key.binary_mtime_ms, None);
+        assert_eq!(key.args, None);
+    }
+}
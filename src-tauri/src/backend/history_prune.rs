@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+/// Threads carrying this tag are never pruned unless the tag is requested explicitly.
+pub(crate) const KEEP_TAG: &str = "keep";
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Scope of a `clear_workspace_history` call. Without `older_than_days` or `tags` the whole
+/// history is cleared, as before.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HistoryPruneOptions {
+    pub(crate) older_than_days: Option<u64>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) exclude_pinned: bool,
+    /// Pins the caller holds beside the store's own; both are kept unless `exclude_pinned`
+    /// is turned off.
+    pub(crate) pinned_thread_ids: HashSet<String>,
+    pub(crate) dry_run: bool,
+}
+
+/// `clear_workspace_history` parameters as the frontend sends them.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HistoryPruneParams {
+    pub(crate) older_than_days: Option<u64>,
+    pub(crate) tags: Option<Vec<String>>,
+    pub(crate) exclude_pinned: Option<bool>,
+    pub(crate) pinned_thread_ids: Option<Vec<String>>,
+    pub(crate) dry_run: Option<bool>,
+}
+
+impl HistoryPruneOptions {
+    /// Builds options from command parameters. `exclude_pinned` defaults to `true`.
+    pub(crate) fn from_params(params: HistoryPruneParams) -> Self {
+        let HistoryPruneParams {
+            older_than_days,
+            tags,
+            exclude_pinned,
+            pinned_thread_ids,
+            dry_run,
+        } = params;
+        Self {
+            older_than_days,
+            tags: tags
+                .unwrap_or_default()
+                .into_iter()
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+            exclude_pinned: exclude_pinned.unwrap_or(true),
+            pinned_thread_ids: pinned_thread_ids.unwrap_or_default().into_iter().collect(),
+            dry_run: dry_run.unwrap_or(false),
+        }
+    }
+
+    pub(crate) fn is_scoped(&self) -> bool {
+        self.older_than_days.is_some() || !self.tags.is_empty()
+    }
+
+    /// Whether a thread falls inside the requested scope. `pinned` is the store's pin;
+    /// `protected` holds threads that must survive regardless, such as background or
+    /// currently resumed threads.
+    pub(crate) fn matches(
+        &self,
+        thread_id: &str,
+        updated_at: i64,
+        thread_tags: &[String],
+        pinned: bool,
+        protected: &HashSet<String>,
+        now_ts: i64,
+    ) -> bool {
+        if protected.contains(thread_id) {
+            return false;
+        }
+        if self.exclude_pinned && (pinned || self.pinned_thread_ids.contains(thread_id)) {
+            return false;
+        }
+        let has_tag = |tag: &str| thread_tags.iter().any(|own| own.eq_ignore_ascii_case(tag));
+        let keep_requested = self
+            .tags
+            .iter()
+            .any(|tag| tag.eq_ignore_ascii_case(KEEP_TAG));
+        if has_tag(KEEP_TAG) && !keep_requested {
+            return false;
+        }
+        if let Some(days) = self.older_than_days {
+            let cutoff = now_ts.saturating_sub((days as i64).saturating_mul(SECONDS_PER_DAY));
+            if updated_at > cutoff {
+                return false;
+            }
+        }
+        self.tags.is_empty() || self.tags.iter().any(|tag| has_tag(tag))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PrunedThread {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) title: String,
+    pub(crate) updated_at: i64,
+    pub(crate) item_count: usize,
+    pub(crate) disk_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HistoryPruneResult {
+    pub(crate) dry_run: bool,
+    /// `false` when the whole history was cleared instead of individual threads.
+    pub(crate) scoped: bool,
+    pub(crate) threads: Vec<PrunedThread>,
+    pub(crate) removed_count: usize,
+    pub(crate) freed_bytes: u64,
+}
+
+impl HistoryPruneResult {
+    pub(crate) fn new(options: &HistoryPruneOptions) -> Self {
+        Self {
+            dry_run: options.dry_run,
+            scoped: options.is_scoped() || options.dry_run,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn extend(&mut self, threads: Vec<PrunedThread>) {
+        if !self.dry_run {
+            self.removed_count += threads.len();
+            self.freed_bytes += threads.iter().map(|thread| thread.disk_bytes).sum::<u64>();
+        }
+        self.threads.extend(threads);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HistoryPruneOptions, SECONDS_PER_DAY};
+    use std::collections::HashSet;
+
+    const NOW: i64 = 1_800_000_000;
+
+    fn tags(values: &[&str]) -> Vec<String> {
+        values.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn age_cutoff_keeps_recent_pinned_and_protected_threads() {
+        let options = HistoryPruneOptions {
+            older_than_days: Some(30),
+            exclude_pinned: true,
+            pinned_thread_ids: HashSet::from(["pinned".to_string()]),
+            ..HistoryPruneOptions::default()
+        };
+        let protected = HashSet::from(["resumed".to_string()]);
+        let old = NOW - 31 * SECONDS_PER_DAY;
+        let recent = NOW - 29 * SECONDS_PER_DAY;
+        assert!(options.matches("a", old, &[], false, &protected, NOW));
+        assert!(!options.matches("b", recent, &[], false, &protected, NOW));
+        assert!(!options.matches("pinned", old, &[], false, &protected, NOW));
+        assert!(!options.matches("store-pinned", old, &[], true, &protected, NOW));
+        assert!(!options.matches("resumed", old, &[], false, &protected, NOW));
+        assert!(!options.matches("c", old, &tags(&["Keep"]), false, &protected, NOW));
+
+        let include_pinned = HistoryPruneOptions {
+            exclude_pinned: false,
+            ..options
+        };
+        assert!(include_pinned.matches("pinned", old, &[], false, &protected, NOW));
+        assert!(include_pinned.matches("store-pinned", old, &[], true, &protected, NOW));
+    }
+
+    #[test]
+    fn tag_filter_requires_a_matching_tag() {
+        let options = HistoryPruneOptions {
+            tags: tags(&["explore"]),
+            exclude_pinned: true,
+            ..HistoryPruneOptions::default()
+        };
+        let none = HashSet::new();
+        assert!(options.is_scoped());
+        assert!(options.matches("a", NOW, &tags(&["Explore", "x"]), false, &none, NOW));
+        assert!(!options.matches("b", NOW, &tags(&["other"]), false, &none, NOW));
+        assert!(!options.matches("c", NOW, &[], false, &none, NOW));
+    }
+}
@@ -0,0 +1,347 @@
+use serde_json::Value;
+
+const MAX_COMMAND_CHARS: usize = 60;
+const MAX_ERROR_CHARS: usize = 80;
+
+/// Fields of a tool call that the plain-text summary is built from.
+pub(crate) struct ToolSummaryInput<'a> {
+    pub(crate) tool: Option<&'a str>,
+    pub(crate) title: Option<&'a str>,
+    pub(crate) arguments: Option<&'a Value>,
+    pub(crate) result: Option<&'a str>,
+    pub(crate) error: Option<&'a str>,
+    pub(crate) completed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ToolKind {
+    Edit,
+    Write,
+    Shell,
+    Read,
+    Search,
+    List,
+    Fetch,
+    Other,
+}
+
+fn classify_tool(tool: &str) -> ToolKind {
+    match tool.to_ascii_lowercase().as_str() {
+        "edit" | "replace" | "apply_patch" | "patch" | "multi_edit" => ToolKind::Edit,
+        "write" | "write_file" | "create_file" => ToolKind::Write,
+        "execute" | "shell" | "bash" | "run_shell_command" | "exec" | "command" | "terminal" => {
+            ToolKind::Shell
+        }
+        "read" | "read_file" | "read_many_files" | "view" => ToolKind::Read,
+        "search" | "grep" | "search_file_content" | "glob" | "find" => ToolKind::Search,
+        "ls" | "list_directory" => ToolKind::List,
+        "fetch" | "web_fetch" | "google_web_search" | "web_search" => ToolKind::Fetch,
+        _ => ToolKind::Other,
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let head: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    format!("{}…", head.trim_end())
+}
+
+fn string_arg<'a>(arguments: Option<&'a Value>, keys: &[&str]) -> Option<&'a str> {
+    let arguments = arguments?;
+    keys.iter()
+        .find_map(|key| arguments.get(*key).and_then(Value::as_str))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// ACP edits arrive as content blocks like `{ "type": "diff", "path", "oldText", "newText" }`.
+fn diff_block(arguments: Option<&Value>) -> Option<&Value> {
+    match arguments? {
+        Value::Array(items) => items.iter().find(|item| item.get("path").is_some()),
+        value if value.get("path").is_some() && value.get("newText").is_some() => Some(value),
+        _ => None,
+    }
+}
+
+fn edited_path<'a>(input: &'a ToolSummaryInput<'a>) -> Option<&'a str> {
+    string_arg(
+        input.arguments,
+        &["file_path", "filePath", "path", "absolute_path"],
+    )
+    .or_else(|| diff_block(input.arguments).and_then(|diff| diff.get("path")?.as_str()))
+    .or_else(|| {
+        input
+            .title
+            .and_then(|title| title.split([':', ' ']).next())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    })
+}
+
+fn replaced_line_count(arguments: Option<&Value>) -> Option<usize> {
+    let old_text = string_arg(arguments, &["old_string", "oldString", "oldText"])
+        .or_else(|| diff_block(arguments).and_then(|diff| diff.get("oldText")?.as_str()))?;
+    Some(old_text.lines().count())
+}
+
+fn shell_command(arguments: Option<&Value>) -> Option<String> {
+    let command = arguments?.get("command")?;
+    match command {
+        Value::String(text) => Some(text.trim().to_string()),
+        Value::Array(parts) => {
+            let parts: Vec<&str> = parts.iter().filter_map(Value::as_str).collect();
+            // `["zsh", "-lc", "cargo test"]` reads better as just the script.
+            if parts.len() >= 3 && matches!(parts[1], "-c" | "-lc") {
+                return parts.last().map(|script| script.trim().to_string());
+            }
+            Some(parts.join(" "))
+        }
+        _ => None,
+    }
+    .filter(|command| !command.is_empty())
+}
+
+fn exit_code(result: Option<&str>) -> Option<i64> {
+    let result = result?;
+    result.lines().find_map(|line| {
+        let lower = line.trim().to_ascii_lowercase();
+        let rest = lower.strip_prefix("exit code:")?;
+        rest.trim()
+            .split(|ch: char| !ch.is_ascii_digit() && ch != '-')
+            .next()?
+            .parse()
+            .ok()
+    })
+}
+
+fn with_failure(summary: String, error: Option<&str>) -> String {
+    match error.map(str::trim).filter(|error| !error.is_empty()) {
+        Some(error) => {
+            let first_line = error.lines().next().unwrap_or(error);
+            format!(
+                "{summary}, failed: {}",
+                truncate(first_line, MAX_ERROR_CHARS)
+            )
+        }
+        None => summary,
+    }
+}
+
+fn quoted(text: &str) -> String {
+    format!("`{}`", truncate(text, MAX_COMMAND_CHARS))
+}
+
+/// One-sentence, screen-reader friendly description of a tool call, e.g.
+/// "Edited src/lib.rs, replacing 3 lines" or "Ran `cargo test`, exited 0". All wording
+/// lives here so it can move to the message catalog in one place.
+pub(crate) fn tool_call_summary(input: &ToolSummaryInput<'_>) -> String {
+    let tool = input.tool.unwrap_or_default();
+    let completed = input.completed;
+    let summary = match classify_tool(tool) {
+        ToolKind::Edit => {
+            let path = edited_path(input).unwrap_or("a file");
+            if !completed {
+                format!("Editing {path}")
+            } else {
+                match replaced_line_count(input.arguments) {
+                    Some(1) => format!("Edited {path}, replacing 1 line"),
+                    Some(count) if count > 1 => format!("Edited {path}, replacing {count} lines"),
+                    _ => format!("Edited {path}"),
+                }
+            }
+        }
+        ToolKind::Write => {
+            let path = edited_path(input).unwrap_or("a file");
+            if completed {
+                format!("Wrote {path}")
+            } else {
+                format!("Writing {path}")
+            }
+        }
+        ToolKind::Shell => {
+            let command = shell_command(input.arguments)
+                .or_else(|| input.title.map(ToString::to_string))
+                .map(|command| quoted(&command))
+                .unwrap_or_else(|| "a command".to_string());
+            if !completed {
+                format!("Running {command}")
+            } else if let Some(code) = exit_code(input.result) {
+                format!("Ran {command}, exited {code}")
+            } else {
+                format!("Ran {command}")
+            }
+        }
+        ToolKind::Read => {
+            let path = edited_path(input).unwrap_or("a file");
+            if completed {
+                format!("Read {path}")
+            } else {
+                format!("Reading {path}")
+            }
+        }
+        ToolKind::Search => {
+            let pattern = string_arg(input.arguments, &["pattern", "query", "regex"])
+                .or(input.title)
+                .map(quoted);
+            match (pattern, completed) {
+                (Some(pattern), true) => format!("Searched for {pattern}"),
+                (Some(pattern), false) => format!("Searching for {pattern}"),
+                (None, true) => "Searched the workspace".to_string(),
+                (None, false) => "Searching the workspace".to_string(),
+            }
+        }
+        ToolKind::List => {
+            let path = edited_path(input).unwrap_or("a directory");
+            if completed {
+                format!("Listed {path}")
+            } else {
+                format!("Listing {path}")
+            }
+        }
+        ToolKind::Fetch => {
+            let target = string_arg(input.arguments, &["url", "query", "prompt"])
+                .map(quoted)
+                .unwrap_or_else(|| "the web".to_string());
+            if completed {
+                format!("Fetched {target}")
+            } else {
+                format!("Fetching {target}")
+            }
+        }
+        ToolKind::Other => {
+            let name = input
+                .tool
+                .or(input.title)
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .unwrap_or("a tool");
+            if completed {
+                format!("Ran {name}")
+            } else {
+                format!("Running {name}")
+            }
+        }
+    };
+    if completed {
+        with_failure(summary, input.error)
+    } else {
+        summary
+    }
+}
+
+/// Plain-text counterpart of a `turn/plan/updated` payload, e.g.
+/// "Plan updated: 2 of 5 steps complete".
+pub(crate) fn plan_summary(plan: &Value) -> String {
+    let entries = plan.as_array().map(Vec::as_slice).unwrap_or_default();
+    if entries.is_empty() {
+        return "Plan cleared".to_string();
+    }
+    let complete = entries
+        .iter()
+        .filter(|entry| entry.get("status").and_then(Value::as_str) == Some("completed"))
+        .count();
+    let noun = if entries.len() == 1 { "step" } else { "steps" };
+    format!(
+        "Plan updated: {complete} of {} {noun} complete",
+        entries.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plan_summary, tool_call_summary, ToolSummaryInput};
+    use serde_json::json;
+
+    fn input<'a>(
+        tool: &'a str,
+        arguments: Option<&'a serde_json::Value>,
+        result: Option<&'a str>,
+        error: Option<&'a str>,
+    ) -> ToolSummaryInput<'a> {
+        ToolSummaryInput {
+            tool: Some(tool),
+            title: None,
+            arguments,
+            result,
+            error,
+            completed: true,
+        }
+    }
+
+    #[test]
+    fn summarizes_parsed_edits() {
+        let arguments = json!({
+            "file_path": "src/lib.rs",
+            "old_string": "a\nb\nc",
+            "new_string": "d"
+        });
+        assert_eq!(
+            tool_call_summary(&input("edit", Some(&arguments), None, None)),
+            "Edited src/lib.rs, replacing 3 lines"
+        );
+        let diff = json!([
+            { "type": "diff", "path": "README.md", "oldText": "old", "newText": "new" }
+        ]);
+        let mut pending = input("edit", Some(&diff), None, None);
+        pending.completed = false;
+        assert_eq!(tool_call_summary(&pending), "Editing README.md");
+    }
+
+    #[test]
+    fn summarizes_shell_commands_with_exit_codes() {
+        let arguments = json!({ "command": ["zsh", "-lc", "cargo test"] });
+        assert_eq!(
+            tool_call_summary(&input(
+                "execute",
+                Some(&arguments),
+                Some("Output: ok\nExit Code: 0"),
+                None
+            )),
+            "Ran `cargo test`, exited 0"
+        );
+        let arguments = json!({ "command": "npm run lint" });
+        assert_eq!(
+            tool_call_summary(&input(
+                "run_shell_command",
+                Some(&arguments),
+                None,
+                Some("Command failed\nmore detail")
+            )),
+            "Ran `npm run lint`, failed: Command failed"
+        );
+    }
+
+    #[test]
+    fn summarizes_searches_and_unknown_tools() {
+        let arguments = json!({ "pattern": "**/*.rs" });
+        assert_eq!(
+            tool_call_summary(&input("glob", Some(&arguments), None, None)),
+            "Searched for `**/*.rs`"
+        );
+        let unknown = ToolSummaryInput {
+            tool: None,
+            title: Some("lint-fixer"),
+            arguments: None,
+            result: None,
+            error: None,
+            completed: false,
+        };
+        assert_eq!(tool_call_summary(&unknown), "Running lint-fixer");
+    }
+
+    #[test]
+    fn summarizes_plan_progress() {
+        let plan = json!([
+            { "content": "a", "status": "completed" },
+            { "content": "b", "status": "completed" },
+            { "content": "c", "status": "in_progress" },
+            { "content": "d", "status": "pending" },
+            { "content": "e", "status": "pending" }
+        ]);
+        assert_eq!(plan_summary(&plan), "Plan updated: 2 of 5 steps complete");
+        assert_eq!(plan_summary(&json!([])), "Plan cleared");
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::time::{timeout, Instant};
+
+use crate::shared::process_core::tokio_command;
+
+/// `mcpServer/list` reuses a probe this young instead of starting the server again.
+pub(crate) const MCP_PROBE_TTL: Duration = Duration::from_secs(30);
+/// How long a stdio server gets to answer `initialize`, and a URL server to answer `HEAD`.
+const MCP_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// How the agent reaches an MCP server configured in the MiCode settings file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum McpTransport {
+    Stdio {
+        command: String,
+        args: Vec<String>,
+        env: Vec<(String, String)>,
+    },
+    Http {
+        url: String,
+    },
+    /// The entry cannot be started; `reason` says why.
+    Misconfigured {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct McpServerConfig {
+    pub(crate) name: String,
+    pub(crate) transport: McpTransport,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum McpServerStatus {
+    Ok,
+    Unreachable,
+    Misconfigured,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct McpProbe {
+    pub(crate) status: McpServerStatus,
+    pub(crate) detail: String,
+    pub(crate) checked_at_ms: u64,
+}
+
+/// MCP servers from the `mcpServers` of a MiCode settings file, either an object keyed
+/// by name or an array of entries with a `name`.
+pub(crate) fn parse_mcp_servers(root: &Value) -> Vec<McpServerConfig> {
+    match root.get("mcpServers") {
+        Some(Value::Object(map)) => map
+            .iter()
+            .filter(|(name, _)| !name.trim().is_empty())
+            .map(|(name, config)| parse_mcp_server(name.trim(), config))
+            .collect(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| {
+                let name = item
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())?;
+                Some(parse_mcp_server(name, item))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_mcp_server(name: &str, config: &Value) -> McpServerConfig {
+    let text = |key: &str| {
+        config
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(ToString::to_string)
+    };
+    let declared = text("transport").or_else(|| text("type"));
+    let wants_url = declared
+        .as_deref()
+        .is_some_and(|transport| matches!(transport, "http" | "sse" | "streamable-http"));
+    let url = text("httpUrl").or_else(|| text("url"));
+    let transport = match (text("command"), url) {
+        (Some(command), _) if !wants_url => McpTransport::Stdio {
+            command,
+            args: string_list(config.get("args")),
+            env: env_pairs(config.get("env")),
+        },
+        (_, Some(url)) if url.starts_with("http://") || url.starts_with("https://") => {
+            McpTransport::Http { url }
+        }
+        (_, Some(url)) => McpTransport::Misconfigured {
+            reason: format!("{url} is not an http(s) URL"),
+        },
+        (_, None) if wants_url => McpTransport::Misconfigured {
+            reason: "missing url".to_string(),
+        },
+        (_, None) => McpTransport::Misconfigured {
+            reason: "missing command or url".to_string(),
+        },
+    };
+    McpServerConfig {
+        name: name.to_string(),
+        transport,
+    }
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn env_pairs(value: Option<&Value>) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = match value {
+        Some(Value::Object(map)) => map
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((name.trim().to_string(), value.as_str()?.to_string()))
+            })
+            .collect(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| {
+                let name = item.get("name").and_then(Value::as_str)?.trim();
+                let value = item
+                    .get("value")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    pairs.retain(|(name, _)| !name.is_empty());
+    pairs
+}
+
+impl McpServerConfig {
+    /// The server's `mcpServer/list` entry. `authStatus` and `tools` keep the shape the
+    /// agent's own status list used.
+    pub(crate) fn to_status_value(&self, probe: &McpProbe) -> Value {
+        let (transport, command, args, url) = match &self.transport {
+            McpTransport::Stdio { command, args, .. } => {
+                (Some("stdio"), Some(command.as_str()), args.clone(), None)
+            }
+            McpTransport::Http { url } => (Some("http"), None, Vec::new(), Some(url.as_str())),
+            McpTransport::Misconfigured { .. } => (None, None, Vec::new(), None),
+        };
+        json!({
+            "name": self.name,
+            "transport": transport,
+            "command": command,
+            "args": args,
+            "httpUrl": url,
+            "status": probe.status,
+            "detail": probe.detail,
+            "checkedAtMs": probe.checked_at_ms,
+            "authStatus": "configured",
+            "tools": {}
+        })
+    }
+}
+
+/// Probes keyed by the full server config, so editing an entry probes it again.
+#[derive(Debug, Default)]
+pub(crate) struct McpProbeCache {
+    entries: HashMap<McpServerConfig, McpProbe>,
+}
+
+impl McpProbeCache {
+    pub(crate) fn get(&self, config: &McpServerConfig, now_ms: u64) -> Option<McpProbe> {
+        self.entries
+            .get(config)
+            .filter(|probe| is_fresh(probe, now_ms))
+            .cloned()
+    }
+
+    pub(crate) fn insert(&mut self, config: McpServerConfig, probe: McpProbe) {
+        let now_ms = probe.checked_at_ms;
+        self.entries.retain(|_, cached| is_fresh(cached, now_ms));
+        self.entries.insert(config, probe);
+    }
+}
+
+fn is_fresh(probe: &McpProbe, now_ms: u64) -> bool {
+    now_ms.saturating_sub(probe.checked_at_ms) < MCP_PROBE_TTL.as_millis() as u64
+}
+
+/// Starts a stdio server and waits for its `initialize` answer, or sends `HEAD` to a
+/// URL server. Any HTTP answer counts as reachable; authentication is the agent's job.
+pub(crate) async fn probe_mcp_server(config: &McpServerConfig, now_ms: u64) -> McpProbe {
+    let (status, detail) = match &config.transport {
+        McpTransport::Misconfigured { reason } => (McpServerStatus::Misconfigured, reason.clone()),
+        McpTransport::Stdio { command, args, env } => {
+            probe_stdio(command, args, env, MCP_PROBE_TIMEOUT).await
+        }
+        McpTransport::Http { url } => probe_http(url, MCP_PROBE_TIMEOUT).await,
+    };
+    McpProbe {
+        status,
+        detail,
+        checked_at_ms: now_ms,
+    }
+}
+
+async fn probe_stdio(
+    command: &str,
+    args: &[String],
+    env: &[(String, String)],
+    limit: Duration,
+) -> (McpServerStatus, String) {
+    let mut child = match tokio_command(command)
+        .args(args)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) => {
+            return (
+                McpServerStatus::Unreachable,
+                format!("Failed to start {command}: {err}"),
+            )
+        }
+    };
+    let started = Instant::now();
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": MCP_PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "micode-monitor", "version": env!("CARGO_PKG_VERSION") }
+        }
+    });
+    let outcome = timeout(limit, async {
+        let mut stdin = child.stdin.take().ok_or("stdin unavailable")?;
+        let stdout = child.stdout.take().ok_or("stdout unavailable")?;
+        stdin
+            .write_all(format!("{request}\n").as_bytes())
+            .await
+            .map_err(|_| "closed its input before initialize")?;
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            if message.get("id") != Some(&json!(1)) {
+                continue;
+            }
+            return Ok(message);
+        }
+        Err("exited before answering initialize")
+    })
+    .await;
+    let _ = child.kill().await;
+    match outcome {
+        Ok(Ok(message)) => match message.get("error") {
+            Some(error) => (
+                McpServerStatus::Unreachable,
+                format!(
+                    "initialize failed: {}",
+                    error
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .unwrap_or("unknown error")
+                ),
+            ),
+            None => {
+                let server = message
+                    .pointer("/result/serverInfo/name")
+                    .and_then(Value::as_str)
+                    .unwrap_or(command);
+                (
+                    McpServerStatus::Ok,
+                    format!(
+                        "{server} answered initialize in {} ms",
+                        started.elapsed().as_millis()
+                    ),
+                )
+            }
+        },
+        Ok(Err(reason)) => (McpServerStatus::Unreachable, reason.to_string()),
+        Err(_) => (
+            McpServerStatus::Unreachable,
+            format!("no answer to initialize within {}s", limit.as_secs()),
+        ),
+    }
+}
+
+async fn probe_http(url: &str, limit: Duration) -> (McpServerStatus, String) {
+    let client = match reqwest::Client::builder().timeout(limit).build() {
+        Ok(client) => client,
+        Err(err) => return (McpServerStatus::Unreachable, err.to_string()),
+    };
+    match client.head(url).send().await {
+        Ok(response) => (
+            McpServerStatus::Ok,
+            format!("HTTP {}", response.status().as_u16()),
+        ),
+        Err(err) if err.is_timeout() => (
+            McpServerStatus::Unreachable,
+            format!("no answer within {}s", limit.as_secs()),
+        ),
+        Err(err) => (McpServerStatus::Unreachable, err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_mcp_servers, probe_http, probe_mcp_server, McpProbe, McpProbeCache, McpServerConfig,
+        McpServerStatus, McpTransport, MCP_PROBE_TTL,
+    };
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime")
+            .block_on(future)
+    }
+
+    #[test]
+    fn parses_stdio_and_url_servers_in_both_layouts() {
+        let root = json!({
+            "mcpServers": {
+                "files": {
+                    "command": "npx",
+                    "args": ["-y", "@modelcontextprotocol/server-filesystem", "."],
+                    "env": { "DEBUG": "1" }
+                },
+                "docs": { "httpUrl": "https://mcp.example.com/mcp" },
+                "events": { "type": "sse", "url": "http://localhost:9000/sse", "command": "ignored" },
+                "broken": { "args": ["--port", "1"] },
+                "ftp": { "url": "ftp://example.com" }
+            }
+        });
+        let servers = parse_mcp_servers(&root);
+        let by_name = |name: &str| {
+            servers
+                .iter()
+                .find(|server| server.name == name)
+                .map(|server| server.transport.clone())
+                .expect("server listed")
+        };
+        assert_eq!(
+            by_name("files"),
+            McpTransport::Stdio {
+                command: "npx".to_string(),
+                args: vec![
+                    "-y".to_string(),
+                    "@modelcontextprotocol/server-filesystem".to_string(),
+                    ".".to_string()
+                ],
+                env: vec![("DEBUG".to_string(), "1".to_string())],
+            }
+        );
+        assert_eq!(
+            by_name("docs"),
+            McpTransport::Http {
+                url: "https://mcp.example.com/mcp".to_string()
+            }
+        );
+        assert_eq!(
+            by_name("events"),
+            McpTransport::Http {
+                url: "http://localhost:9000/sse".to_string()
+            }
+        );
+        assert!(matches!(
+            by_name("broken"),
+            McpTransport::Misconfigured { .. }
+        ));
+        assert!(matches!(by_name("ftp"), McpTransport::Misconfigured { .. }));
+
+        let listed = parse_mcp_servers(&json!({
+            "mcpServers": [
+                { "name": "git", "command": "mcp-git", "env": [{ "name": "TOKEN", "value": "x" }] },
+                { "command": "nameless" }
+            ]
+        }));
+        assert_eq!(listed.len(), 1);
+        assert_eq!(
+            listed[0].transport,
+            McpTransport::Stdio {
+                command: "mcp-git".to_string(),
+                args: Vec::new(),
+                env: vec![("TOKEN".to_string(), "x".to_string())],
+            }
+        );
+    }
+
+    #[test]
+    fn missing_commands_and_closed_ports_are_unreachable() {
+        let missing = McpServerConfig {
+            name: "gone".to_string(),
+            transport: McpTransport::Stdio {
+                command: "/definitely/missing/mcp-server".to_string(),
+                args: Vec::new(),
+                env: Vec::new(),
+            },
+        };
+        let probe = block_on(probe_mcp_server(&missing, 1_000));
+        assert_eq!(probe.status, McpServerStatus::Unreachable);
+        assert!(probe.detail.contains("Failed to start"), "{}", probe.detail);
+        let value = missing.to_status_value(&probe);
+        assert_eq!(value["status"], "unreachable");
+        assert_eq!(value["transport"], "stdio");
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("free port")
+            .port();
+        let (status, _) = block_on(probe_http(
+            &format!("http://127.0.0.1:{port}/mcp"),
+            Duration::from_secs(2),
+        ));
+        assert_eq!(status, McpServerStatus::Unreachable);
+
+        let misconfigured = parse_mcp_servers(&json!({ "mcpServers": { "x": {} } }));
+        let probe = block_on(probe_mcp_server(&misconfigured[0], 1_000));
+        assert_eq!(probe.status, McpServerStatus::Misconfigured);
+    }
+
+    #[test]
+    fn probes_are_reused_until_the_ttl_or_a_config_change() {
+        let config = |url: &str| McpServerConfig {
+            name: "docs".to_string(),
+            transport: McpTransport::Http {
+                url: url.to_string(),
+            },
+        };
+        let probe = McpProbe {
+            status: McpServerStatus::Ok,
+            detail: "HTTP 200".to_string(),
+            checked_at_ms: 1_000,
+        };
+        let ttl_ms = MCP_PROBE_TTL.as_millis() as u64;
+        let mut cache = McpProbeCache::default();
+        cache.insert(config("https://a.example"), probe.clone());
+        assert_eq!(
+            cache.get(&config("https://a.example"), 1_000 + ttl_ms - 1),
+            Some(probe)
+        );
+        assert_eq!(
+            cache.get(&config("https://a.example"), 1_000 + ttl_ms),
+            None
+        );
+        assert_eq!(cache.get(&config("https://b.example"), 1_000), None);
+    }
+}
@@ -1,2 +1,44 @@
+pub(crate) mod annotations;
 pub(crate) mod app_server;
+pub(crate) mod approvals;
+pub(crate) mod auto_run;
+pub(crate) mod background_reaper;
+pub(crate) mod chat_index;
+pub(crate) mod connect_queue;
+pub(crate) mod connection_state;
+pub(crate) mod documents;
+pub(crate) mod event_methods;
 pub(crate) mod events;
+pub(crate) mod handshake_cache;
+pub(crate) mod history_prune;
+pub(crate) mod item_summaries;
+pub(crate) mod mcp_status;
+pub(crate) mod pending_requests;
+pub(crate) mod primer;
+pub(crate) mod prompt_images;
+pub(crate) mod prompt_text;
+pub(crate) mod redaction;
+pub(crate) mod review_context;
+pub(crate) mod review_sarif;
+pub(crate) mod sampling;
+pub(crate) mod session_health;
+pub(crate) mod settings_events;
+pub(crate) mod settings_json;
+pub(crate) mod skills;
+pub(crate) mod slash_commands;
+pub(crate) mod store_maintenance;
+pub(crate) mod thread_export;
+pub(crate) mod thread_references;
+pub(crate) mod thread_search;
+pub(crate) mod thread_sync;
+pub(crate) mod tool_results;
+pub(crate) mod tool_timing;
+pub(crate) mod transcript;
+pub(crate) mod turn_artifacts;
+pub(crate) mod turn_audit;
+pub(crate) mod turn_live_changes;
+pub(crate) mod turn_phase;
+pub(crate) mod turn_queue;
+pub(crate) mod turn_reviews;
+pub(crate) mod turn_stall;
+pub(crate) mod workspace_paths;
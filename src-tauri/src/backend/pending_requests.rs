@@ -0,0 +1,84 @@
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+/// Caller tag for `turn/start` prompts on user-visible threads.
+pub(crate) const CALLER_TURN: &str = "turn";
+
+/// What an outstanding ACP request is for. Kept next to the response channel so timeouts,
+/// `get_session_info` and the unresponsiveness detector can say what the agent is stuck on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PendingRequestInfo {
+    pub(crate) id: u64,
+    pub(crate) method: String,
+    pub(crate) thread_id: Option<String>,
+    /// Who issued the request, e.g. `turn`, `background:commitMessage` or `initialize`.
+    pub(crate) caller: String,
+    pub(crate) started_at_ms: u64,
+}
+
+impl PendingRequestInfo {
+    pub(crate) fn age_secs(&self, now_ms: u64) -> u64 {
+        now_ms.saturating_sub(self.started_at_ms) / 1000
+    }
+
+    /// Human readable summary such as
+    /// `session/prompt for thread t-1 (turn) for 240s`.
+    pub(crate) fn describe(&self, now_ms: u64) -> String {
+        let target = match self.thread_id.as_deref() {
+            Some(thread_id) => format!("{} for thread {thread_id}", self.method),
+            None => self.method.clone(),
+        };
+        format!("{target} ({}) for {}s", self.caller, self.age_secs(now_ms))
+    }
+}
+
+pub(crate) struct PendingRequest {
+    pub(crate) tx: oneshot::Sender<Value>,
+    pub(crate) info: PendingRequestInfo,
+}
+
+/// Caller tag for prompts sent on background threads. `purpose` comes from the internal
+/// `_purpose` field that background helpers put on `turn/start`.
+pub(crate) fn background_caller(purpose: Option<&str>) -> String {
+    let purpose = purpose
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .unwrap_or("task");
+    format!("background:{purpose}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{background_caller, PendingRequestInfo};
+
+    #[test]
+    fn describes_requests_with_and_without_threads() {
+        let mut info = PendingRequestInfo {
+            id: 7,
+            method: "session/prompt".to_string(),
+            thread_id: Some("t-1".to_string()),
+            caller: "turn".to_string(),
+            started_at_ms: 1_000,
+        };
+        assert_eq!(
+            info.describe(241_500),
+            "session/prompt for thread t-1 (turn) for 240s"
+        );
+        info.thread_id = None;
+        info.method = "initialize".to_string();
+        info.caller = "initialize".to_string();
+        assert_eq!(info.describe(500), "initialize (initialize) for 0s");
+    }
+
+    #[test]
+    fn background_callers_default_to_task() {
+        assert_eq!(
+            background_caller(Some("commitMessage")),
+            "background:commitMessage"
+        );
+        assert_eq!(background_caller(Some("  ")), "background:task");
+        assert_eq!(background_caller(None), "background:task");
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Local;
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Commits HEAD may move ahead of the primed commit before the summary is considered stale.
+pub(crate) const PRIMER_MAX_HEAD_DRIFT: usize = 20;
+const MAX_LAYOUT_ENTRIES: usize = 40;
+const MAX_README_CHARS: usize = 1_500;
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "dist", "build", "vendor", "__pycache__"];
+
+/// HEAD of the workspace when the primer was seeded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct PrimerHead {
+    pub(crate) branch: Option<String>,
+    pub(crate) commit: Option<String>,
+}
+
+/// ACP session seeded with a repository summary that no helper has prompted yet.
+#[derive(Debug, Clone)]
+pub(crate) struct PrimedSession {
+    pub(crate) session_id: String,
+    pub(crate) head: PrimerHead,
+    pub(crate) model: Option<String>,
+    pub(crate) summary_tokens: u64,
+}
+
+/// Primed sessions of background helpers. Every helper gets a session of its own, so one
+/// helper's prompts never reach the next; a seeded spare waits for the next helper.
+#[derive(Debug, Default)]
+pub(crate) struct PrimerState {
+    pub(crate) spare: Option<PrimedSession>,
+    /// Background threads running on a primed session.
+    pub(crate) leases: HashSet<String>,
+}
+
+pub(crate) fn read_head(workspace_path: &Path) -> PrimerHead {
+    let Ok(repo) = Repository::discover(workspace_path) else {
+        return PrimerHead::default();
+    };
+    let Ok(head) = repo.head() else {
+        return PrimerHead::default();
+    };
+    PrimerHead {
+        branch: head.shorthand().map(ToString::to_string),
+        commit: head.target().map(|oid| oid.to_string()),
+    }
+}
+
+/// Commits `current` is ahead of `primed`, or `None` when `current` does not descend
+/// from `primed` (rebase, reset, unrelated history).
+fn head_drift(workspace_path: &Path, primed: &str, current: &str) -> Option<usize> {
+    let repo = Repository::discover(workspace_path).ok()?;
+    let primed = Oid::from_str(primed).ok()?;
+    let current = Oid::from_str(current).ok()?;
+    let (ahead, behind) = repo.graph_ahead_behind(current, primed).ok()?;
+    (behind == 0).then_some(ahead)
+}
+
+/// Whether the repository moved far enough from the primed HEAD that the summary can no
+/// longer be trusted: another branch, rewritten history or many new commits.
+pub(crate) fn head_changed_significantly(
+    workspace_path: &Path,
+    primed: &PrimerHead,
+    current: &PrimerHead,
+) -> bool {
+    if primed.branch != current.branch {
+        return true;
+    }
+    match (&primed.commit, &current.commit) {
+        (Some(primed), Some(current)) if primed != current => {
+            head_drift(workspace_path, primed, current)
+                .map(|ahead| ahead > PRIMER_MAX_HEAD_DRIFT)
+                .unwrap_or(true)
+        }
+        (primed, current) => primed != current,
+    }
+}
+
+/// Rough token count used for savings estimates; about four characters per token.
+pub(crate) fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+fn top_level_layout(workspace_path: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(workspace_path) else {
+        return Vec::new();
+    };
+    let mut layout: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_str()) {
+                return None;
+            }
+            let is_dir = entry.file_type().map(|kind| kind.is_dir()).unwrap_or(false);
+            Some(if is_dir { format!("{name}/") } else { name })
+        })
+        .collect();
+    layout.sort();
+    layout.truncate(MAX_LAYOUT_ENTRIES);
+    layout
+}
+
+fn readme_excerpt(workspace_path: &Path) -> Option<String> {
+    ["README.md", "README", "readme.md", "README.txt"]
+        .iter()
+        .map(|name| workspace_path.join(name))
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .map(|raw| raw.trim().chars().take(MAX_README_CHARS).collect::<String>())
+        .filter(|excerpt| !excerpt.is_empty())
+}
+
+fn build_commands(workspace_path: &Path) -> Vec<String> {
+    let mut commands = Vec::new();
+    if workspace_path.join("Cargo.toml").is_file() {
+        commands.push("cargo build".to_string());
+        commands.push("cargo test".to_string());
+    }
+    if let Some(scripts) = std::fs::read_to_string(workspace_path.join("package.json"))
+        .ok()
+        .and_then(|raw| serde_json::from_str::<Value>(&raw).ok())
+        .and_then(|package| package.get("scripts").cloned())
+        .and_then(|scripts| scripts.as_object().cloned())
+    {
+        for name in ["build", "test", "lint", "dev"] {
+            if scripts.contains_key(name) {
+                commands.push(format!("npm run {name}"));
+            }
+        }
+    }
+    if workspace_path.join("go.mod").is_file() {
+        commands.push("go build ./...".to_string());
+        commands.push("go test ./...".to_string());
+    }
+    if workspace_path.join("pyproject.toml").is_file() {
+        commands.push("pytest".to_string());
+    }
+    if workspace_path.join("Makefile").is_file() {
+        commands.push("make".to_string());
+    }
+    commands
+}
+
+/// Repository context the primer session is seeded with.
+pub(crate) fn build_repo_summary(workspace_path: &Path, head: &PrimerHead) -> String {
+    let mut summary = String::from(
+        "Repository context for upcoming helper requests (commit messages, titles, run \
+         metadata). Keep it in mind and reply only with \"OK\".\n",
+    );
+    if let Some(branch) = &head.branch {
+        summary.push_str(&format!("\nBranch: {branch}\n"));
+    }
+    let layout = top_level_layout(workspace_path);
+    if !layout.is_empty() {
+        summary.push_str("\nTop-level layout:\n");
+        for entry in layout {
+            summary.push_str(&format!("- {entry}\n"));
+        }
+    }
+    let commands = build_commands(workspace_path);
+    if !commands.is_empty() {
+        summary.push_str(&format!("\nBuild commands: {}\n", commands.join(", ")));
+    }
+    if let Some(readme) = readme_excerpt(workspace_path) {
+        summary.push_str(&format!("\nREADME excerpt:\n{readme}\n"));
+    }
+    summary
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PrimerDayStats {
+    pub(crate) reuses: i64,
+    pub(crate) tokens_saved: i64,
+}
+
+fn stats_path(workspace_path: &Path) -> PathBuf {
+    workspace_path.join(".micodemonitor").join("primer-stats.json")
+}
+
+fn stats_lock() -> &'static Mutex<()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    &LOCK
+}
+
+/// Estimated savings per day (`YYYY-MM-DD`) for one workspace.
+pub(crate) fn load_stats(workspace_path: &Path) -> BTreeMap<String, PrimerDayStats> {
+    std::fs::read_to_string(stats_path(workspace_path))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Records one helper served by a spare seeded ahead of time: it did not have to wait for
+/// the summary to be sent.
+pub(crate) fn record_reuse(workspace_path: &Path, tokens_saved: u64) {
+    let _guard = stats_lock().lock();
+    let mut stats = load_stats(workspace_path);
+    let day = Local::now().format("%Y-%m-%d").to_string();
+    let entry = stats.entry(day).or_default();
+    entry.reuses += 1;
+    entry.tokens_saved += tokens_saved as i64;
+    let path = stats_path(workspace_path);
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(raw) = serde_json::to_string(&stats) {
+        let _ = std::fs::write(path, raw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_repo_summary, estimate_tokens, head_changed_significantly, load_stats,
+        record_reuse, PrimerHead,
+    };
+    use uuid::Uuid;
+
+    #[test]
+    fn summary_lists_layout_commands_and_readme() {
+        let root = std::env::temp_dir().join(format!("micode-primer-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).expect("create src");
+        std::fs::create_dir_all(root.join("node_modules")).expect("create node_modules");
+        std::fs::write(root.join("Cargo.toml"), "[package]").expect("write manifest");
+        std::fs::write(root.join("README.md"), "# Demo\nDoes things.").expect("write readme");
+        let head = PrimerHead {
+            branch: Some("main".to_string()),
+            commit: None,
+        };
+        let summary = build_repo_summary(&root, &head);
+        assert!(summary.contains("Branch: main"));
+        assert!(summary.contains("- src/"));
+        assert!(!summary.contains("node_modules"));
+        assert!(summary.contains("cargo test"));
+        assert!(summary.contains("Does things."));
+        assert!(estimate_tokens(&summary) > 0);
+
+        record_reuse(&root, 300);
+        record_reuse(&root, 300);
+        let stats = load_stats(&root);
+        let today = stats.values().next().expect("today");
+        assert_eq!((today.reuses, today.tokens_saved), (2, 600));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn branch_switches_and_unknown_commits_invalidate() {
+        let root = std::env::temp_dir();
+        let head = |branch: &str, commit: Option<&str>| PrimerHead {
+            branch: Some(branch.to_string()),
+            commit: commit.map(ToString::to_string),
+        };
+        assert!(!head_changed_significantly(
+            &root,
+            &head("main", Some("abc")),
+            &head("main", Some("abc"))
+        ));
+        assert!(head_changed_significantly(
+            &root,
+            &head("main", Some("abc")),
+            &head("feature", Some("abc"))
+        ));
+        // Commits that cannot be related (no repository here) are treated as a rewrite.
+        assert!(head_changed_significantly(
+            &root,
+            &head("main", Some("abc")),
+            &head("main", Some("def"))
+        ));
+    }
+}
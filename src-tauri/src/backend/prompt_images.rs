@@ -0,0 +1,275 @@
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde_json::{json, Value};
+
+/// Largest image embedded into a prompt. Bigger files would bloat every request the
+/// agent makes with the conversation.
+pub(crate) const MAX_PROMPT_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// An image attached to a `turn/start`, as sent by `send_user_message`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PromptImage {
+    /// A file on the machine running the agent.
+    Local(PathBuf),
+    /// A `data:` URL or a remote `http(s)` URL.
+    Url(String),
+}
+
+impl PromptImage {
+    /// What the persisted user item records about the image; the bytes stay in the file.
+    pub(crate) fn to_item_content(&self) -> Value {
+        match self {
+            Self::Local(path) => json!({
+                "type": "localImage",
+                "path": path.display().to_string(),
+                "mimeType": mime_from_extension(path)
+            }),
+            Self::Url(url) => json!({ "type": "image", "url": url }),
+        }
+    }
+
+    /// The ACP content block for the image. Agents that accept images get the bytes
+    /// inline; others get a `resource_link` they can open themselves.
+    pub(crate) fn to_content_block(&self, embed: bool) -> Result<Value, String> {
+        self.content_block(embed, MAX_PROMPT_IMAGE_BYTES)
+    }
+
+    fn content_block(&self, embed: bool, max_bytes: u64) -> Result<Value, String> {
+        match self {
+            Self::Local(path) if embed => local_image_block(path, max_bytes),
+            Self::Local(path) => {
+                if !path.is_file() {
+                    return Err(format!("Image not found: {}", path.display()));
+                }
+                let mut block = json!({
+                    "type": "resource_link",
+                    "uri": file_uri(path),
+                    "name": file_name(path)
+                });
+                if let Some(mime) = mime_from_extension(path) {
+                    block["mimeType"] = json!(mime);
+                }
+                Ok(block)
+            }
+            Self::Url(url) if url.starts_with("data:") => {
+                if !embed {
+                    return Err("The agent does not accept inline images.".to_string());
+                }
+                data_url_block(url, max_bytes)
+            }
+            Self::Url(url) => Ok(json!({
+                "type": "resource_link",
+                "uri": url,
+                "name": url.rsplit('/').find(|part| !part.is_empty()).unwrap_or(url)
+            })),
+        }
+    }
+}
+
+/// Images listed in the `input` of a `turn/start`, in order.
+pub(crate) fn prompt_images_from_input(params: &Value) -> Vec<PromptImage> {
+    params
+        .get("input")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let text = |key: &str| {
+                item.get(key)
+                    .and_then(Value::as_str)
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+            };
+            match item.get("type").and_then(Value::as_str) {
+                Some("localImage") => text("path").map(|path| PromptImage::Local(path.into())),
+                Some("image") => text("url").map(|url| PromptImage::Url(url.to_string())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Sniffs the image format from its first bytes, falling back to the file extension.
+pub(crate) fn detect_image_mime(bytes: &[u8], path: &Path) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else {
+        mime_from_extension(path)
+    }
+}
+
+fn mime_from_extension(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        "gif" => Some("image/gif"),
+        _ => None,
+    }
+}
+
+fn too_large_error(name: &str, bytes: u64, max_bytes: u64) -> String {
+    let mb = |value: u64| value as f64 / (1024.0 * 1024.0);
+    format!(
+        "{name} is {:.1} MB; images over {:.0} MB cannot be attached.",
+        mb(bytes),
+        mb(max_bytes)
+    )
+}
+
+fn local_image_block(path: &Path, max_bytes: u64) -> Result<Value, String> {
+    let size = std::fs::metadata(path)
+        .map_err(|err| format!("Failed to read image {}: {err}", path.display()))?
+        .len();
+    if size > max_bytes {
+        return Err(too_large_error(&file_name(path), size, max_bytes));
+    }
+    let bytes = std::fs::read(path)
+        .map_err(|err| format!("Failed to read image {}: {err}", path.display()))?;
+    let mime = detect_image_mime(&bytes, path)
+        .ok_or_else(|| format!("{} is not a PNG, JPEG, WebP or GIF image.", path.display()))?;
+    Ok(json!({
+        "type": "image",
+        "mimeType": mime,
+        "data": STANDARD.encode(&bytes),
+        "uri": file_uri(path)
+    }))
+}
+
+fn data_url_block(url: &str, max_bytes: u64) -> Result<Value, String> {
+    let invalid = || "Unsupported image data URL; expected base64 image data.".to_string();
+    let (header, data) = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(','))
+        .ok_or_else(invalid)?;
+    let mime = header.strip_suffix(";base64").ok_or_else(invalid)?;
+    if !mime.starts_with("image/") {
+        return Err(invalid());
+    }
+    let size = (data.len() as u64) / 4 * 3;
+    if size > max_bytes {
+        return Err(too_large_error("The pasted image", size, max_bytes));
+    }
+    Ok(json!({ "type": "image", "mimeType": mime, "data": data }))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+fn file_uri(path: &Path) -> String {
+    let text = path.display().to_string().replace('\\', "/");
+    if text.starts_with('/') {
+        format!("file://{text}")
+    } else {
+        format!("file:///{text}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_image_mime, prompt_images_from_input, PromptImage};
+    use serde_json::json;
+    use std::path::{Path, PathBuf};
+    use uuid::Uuid;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    fn temp_image(name: &str, bytes: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("micode-prompt-image-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let path = dir.join(name);
+        std::fs::write(&path, bytes).expect("write image");
+        path
+    }
+
+    #[test]
+    fn detects_png_jpeg_and_webp_from_their_bytes() {
+        let unnamed = Path::new("upload");
+        assert_eq!(detect_image_mime(PNG, unnamed), Some("image/png"));
+        assert_eq!(
+            detect_image_mime(&[0xff, 0xd8, 0xff, 0xe0, 0, 0x10], unnamed),
+            Some("image/jpeg")
+        );
+        assert_eq!(
+            detect_image_mime(b"RIFF\x24\0\0\0WEBPVP8 ", unnamed),
+            Some("image/webp")
+        );
+        // Unknown bytes fall back to the extension, and then give up.
+        assert_eq!(
+            detect_image_mime(b"????", Path::new("shot.JPG")),
+            Some("image/jpeg")
+        );
+        assert_eq!(detect_image_mime(b"????", Path::new("notes.txt")), None);
+    }
+
+    #[test]
+    fn refuses_images_over_the_size_limit() {
+        let path = temp_image("big.png", &[PNG, &[0u8; 64]].concat());
+        let image = PromptImage::Local(path.clone());
+        let error = image
+            .content_block(true, 32)
+            .expect_err("oversized image must be refused");
+        assert!(error.contains("big.png"), "{error}");
+        assert!(error.contains("cannot be attached"), "{error}");
+        assert!(image.content_block(true, 1024).is_ok());
+        // A link does not carry the bytes, so the limit does not apply.
+        assert!(image.content_block(false, 32).is_ok());
+
+        let pasted = PromptImage::Url(format!("data:image/png;base64,{}", "A".repeat(64)));
+        assert!(pasted.content_block(true, 16).is_err());
+
+        let _ = std::fs::remove_dir_all(path.parent().expect("temp dir"));
+    }
+
+    #[test]
+    fn builds_inline_images_or_resource_links_from_turn_input() {
+        let path = temp_image("shot.png", PNG);
+        let params = json!({
+            "input": [
+                { "type": "text", "text": "what is this?" },
+                { "type": "localImage", "path": path.display().to_string() },
+                { "type": "image", "url": "data:image/webp;base64,UklGRg==" },
+                { "type": "image", "url": "https://example.com/a/chart.png" }
+            ]
+        });
+        let images = prompt_images_from_input(&params);
+        assert_eq!(images.len(), 3);
+
+        let inline = images[0].to_content_block(true).expect("inline image");
+        assert_eq!(inline["type"], "image");
+        assert_eq!(inline["mimeType"], "image/png");
+        assert_eq!(inline["data"], "iVBORw0KGgoAAAANSUhEUg==");
+        let linked = images[0].to_content_block(false).expect("linked image");
+        assert_eq!(linked["type"], "resource_link");
+        assert_eq!(linked["name"], "shot.png");
+        assert!(linked["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .starts_with("file:///"));
+
+        let pasted = images[1].to_content_block(true).expect("pasted image");
+        assert_eq!(pasted["mimeType"], "image/webp");
+        assert_eq!(pasted["data"], "UklGRg==");
+        assert!(images[1].to_content_block(false).is_err());
+
+        let remote = images[2].to_content_block(true).expect("remote image");
+        assert_eq!(remote["type"], "resource_link");
+        assert_eq!(remote["name"], "chart.png");
+
+        assert_eq!(images[0].to_item_content()["type"], "localImage");
+        assert_eq!(images[0].to_item_content()["mimeType"], "image/png");
+
+        let _ = std::fs::remove_dir_all(path.parent().expect("temp dir"));
+    }
+}
@@ -0,0 +1,209 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+const MAX_CONSECUTIVE_BLANK_LINES: usize = 2;
+const THREAD_TITLE_MAX_GRAPHEMES: usize = 38;
+const THREAD_TITLE_ELLIPSIS: &str = "…";
+/// Prompt characters shown to the agent when it names a thread.
+const GENERATED_TITLE_PROMPT_CHARS: usize = 2_000;
+/// Longest generated title accepted; longer replies are not a title.
+const GENERATED_TITLE_MAX_GRAPHEMES: usize = 80;
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+fn is_zero_width(ch: char) -> bool {
+    matches!(ch, '\u{200B}' | '\u{200C}' | '\u{2060}' | '\u{FEFF}')
+}
+
+fn normalize_line_endings(raw: &str) -> String {
+    raw.replace("\r\n", "\n")
+        .replace(['\r', '\u{2028}', '\u{2029}'], "\n")
+}
+
+/// Drops zero-width and control characters (tab/newline excepted). A zero-width joiner
+/// is kept only when it glues an emoji sequence together inside one grapheme cluster.
+fn strip_invisible_chars(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for grapheme in text.graphemes(true) {
+        let last = grapheme.chars().last();
+        for ch in grapheme.chars() {
+            if ch == '\t' || ch == '\n' {
+                output.push(ch);
+                continue;
+            }
+            if ch.is_control() || is_zero_width(ch) {
+                continue;
+            }
+            if ch == ZERO_WIDTH_JOINER && last == Some(ZERO_WIDTH_JOINER) {
+                continue;
+            }
+            output.push(ch);
+        }
+    }
+    output
+}
+
+fn collapse_blank_lines(text: &str) -> String {
+    let mut lines = Vec::new();
+    let mut blank_run = 0;
+    for line in text.split('\n') {
+        if line.trim().is_empty() {
+            blank_run += 1;
+            if blank_run > MAX_CONSECUTIVE_BLANK_LINES {
+                continue;
+            }
+            lines.push("");
+        } else {
+            blank_run = 0;
+            lines.push(line);
+        }
+    }
+    lines.join("\n")
+}
+
+/// Normalizes pasted prompt text before it is persisted or sent to MiCode:
+/// unifies line endings, strips invisible characters and collapses long blank runs.
+pub(crate) fn normalize_prompt_text(raw: &str) -> String {
+    let text = normalize_line_endings(raw);
+    let text = strip_invisible_chars(&text);
+    collapse_blank_lines(&text).trim().to_string()
+}
+
+pub(crate) fn derive_thread_title(prompt: &str) -> Option<String> {
+    let normalized = normalize_prompt_text(prompt);
+    let first_line = normalized.lines().next().unwrap_or_default().trim();
+    let compact = first_line.split_whitespace().collect::<Vec<_>>().join(" ");
+    if compact.is_empty() {
+        return None;
+    }
+    let graphemes = compact.graphemes(true).collect::<Vec<_>>();
+    if graphemes.len() <= THREAD_TITLE_MAX_GRAPHEMES {
+        return Some(compact);
+    }
+    let mut title = graphemes[..THREAD_TITLE_MAX_GRAPHEMES - 1]
+        .concat()
+        .trim_end()
+        .to_string();
+    title.push_str(THREAD_TITLE_ELLIPSIS);
+    Some(title)
+}
+
+/// Asks the agent for a short title for a thread that started with `prompt`.
+pub(crate) fn thread_title_prompt(prompt: &str) -> String {
+    let prompt = normalize_prompt_text(prompt)
+        .chars()
+        .take(GENERATED_TITLE_PROMPT_CHARS)
+        .collect::<String>();
+    format!(
+        "Write a title of 3 to 7 words for a conversation that starts with the message below. \
+Use the language of the message, no quotes and no trailing punctuation. \
+Only output the title, nothing else.\n\nMessage:\n{prompt}"
+    )
+}
+
+/// The title in an agent reply to `thread_title_prompt`: its first line without quotes,
+/// a `Title:` label or a trailing period. `None` when the reply is not a usable title.
+pub(crate) fn clean_generated_title(reply: &str) -> Option<String> {
+    let line = normalize_prompt_text(reply)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())?
+        .to_string();
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(&line)
+        .trim()
+        .trim_matches(|ch: char| matches!(ch, '"' | '\'' | '`' | '*' | '“' | '”'))
+        .trim_end_matches(['.', '。'])
+        .trim();
+    let title = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    let graphemes = title.graphemes(true).count();
+    (graphemes > 0 && graphemes <= GENERATED_TITLE_MAX_GRAPHEMES).then_some(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        clean_generated_title, derive_thread_title, normalize_prompt_text, thread_title_prompt,
+    };
+    use unicode_segmentation::UnicodeSegmentation;
+
+    #[test]
+    fn normalizes_line_endings_and_strips_invisible_chars() {
+        let raw = "hello\u{200B} world\r\nsecond\u{0007} line\rthird\u{FEFF}\tcol";
+        assert_eq!(
+            normalize_prompt_text(raw),
+            "hello world\nsecond line\nthird\tcol"
+        );
+    }
+
+    #[test]
+    fn collapses_excessive_blank_lines() {
+        let raw = "first\n\n\n\n\n  \nsecond\n\nthird\n\n\n";
+        assert_eq!(normalize_prompt_text(raw), "first\n\n\nsecond\n\nthird");
+    }
+
+    #[test]
+    fn strips_dangling_zero_width_joiner_but_keeps_emoji_sequences() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let raw = format!("fix\u{200D} the {family} bug");
+        assert_eq!(normalize_prompt_text(&raw), format!("fix the {family} bug"));
+    }
+
+    #[test]
+    fn title_keeps_short_prompts_intact() {
+        assert_eq!(
+            derive_thread_title("  Fix   the login\r\nflow please"),
+            Some("Fix the login".to_string())
+        );
+        assert_eq!(derive_thread_title("\u{200B}\n\r\n"), None);
+    }
+
+    #[test]
+    fn title_truncates_emoji_on_grapheme_boundaries() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let prompt = family.repeat(45);
+        let title = derive_thread_title(&prompt).expect("title");
+        assert!(title.ends_with('…'));
+        let graphemes = title.graphemes(true).collect::<Vec<_>>();
+        assert_eq!(graphemes.len(), 38);
+        assert!(graphemes[..37].iter().all(|grapheme| *grapheme == family));
+    }
+
+    #[test]
+    fn title_truncates_cjk_text() {
+        let prompt =
+            "修复登录页面在移动端显示错位的问题并补充对应的单元测试以及更新文档说明和发布记录内容";
+        let title = derive_thread_title(prompt).expect("title");
+        assert_eq!(title.chars().count(), 38);
+        assert!(title.starts_with("修复登录页面"));
+        assert!(title.ends_with('…'));
+    }
+
+    #[test]
+    fn title_does_not_split_combining_marks() {
+        let word = "e\u{0301}";
+        let prompt = word.repeat(50);
+        let title = derive_thread_title(&prompt).expect("title");
+        let body = title.trim_end_matches('…');
+        assert!(body.ends_with('\u{0301}'));
+        assert_eq!(body.graphemes(true).count(), 37);
+    }
+
+    #[test]
+    fn generated_titles_are_cleaned_or_rejected() {
+        assert_eq!(
+            clean_generated_title("\n\"Fix login layout on mobile.\"\nExplanation follows").as_deref(),
+            Some("Fix login layout on mobile")
+        );
+        assert_eq!(
+            clean_generated_title("Title: `Refactor   settings loader`").as_deref(),
+            Some("Refactor settings loader")
+        );
+        assert_eq!(clean_generated_title("  \n "), None);
+        assert_eq!(clean_generated_title(&"word ".repeat(40)), None);
+
+        let prompt = thread_title_prompt("can you please look at the file src/backend.rs");
+        assert!(prompt.contains("3 to 7 words"));
+        assert!(prompt.ends_with("Message:\ncan you please look at the file src/backend.rs"));
+    }
+}
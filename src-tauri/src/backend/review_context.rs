@@ -0,0 +1,641 @@
+use std::collections::{HashSet, VecDeque};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+use git2::{Delta, DiffOptions, Repository};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const DEFAULT_BYTE_BUDGET: usize = 48 * 1024;
+const DEFAULT_DEPTH: usize = 1;
+/// Fence the read-only context between in the review prompt, see `review_target_with_context`.
+const CONTEXT_START: &str = "=== BEGIN READ-ONLY CONTEXT (not under review) ===";
+const CONTEXT_END: &str = "=== END READ-ONLY CONTEXT ===";
+/// Caps whatever the caller asks for, so the context never crowds out the diff.
+const MAX_BYTE_BUDGET: usize = 512 * 1024;
+const MAX_DEPTH: usize = 3;
+const SCRIPT_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mts", "cts", "mjs", "cjs"];
+/// TypeScript sources import the emitted `.js` name; `(written, source extensions)`.
+const EMITTED_SCRIPT_EXTENSIONS: &[(&str, &[&str])] = &[
+    ("js", &["ts", "tsx"]),
+    ("jsx", &["tsx"]),
+    ("mjs", &["mts"]),
+    ("cjs", &["cts"]),
+];
+
+/// How much read-only context a review pulls in next to the diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ReviewContextOptions {
+    /// Total size of the included files.
+    pub(crate) byte_budget: usize,
+    /// Import hops followed from each changed file; 1 includes only direct imports.
+    pub(crate) depth: usize,
+}
+
+impl Default for ReviewContextOptions {
+    fn default() -> Self {
+        Self {
+            byte_budget: DEFAULT_BYTE_BUDGET,
+            depth: DEFAULT_DEPTH,
+        }
+    }
+}
+
+impl ReviewContextOptions {
+    fn clamped(self) -> Self {
+        Self {
+            byte_budget: self.byte_budget.min(MAX_BYTE_BUDGET),
+            depth: self.depth.min(MAX_DEPTH),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportLanguage {
+    Rust,
+    Script,
+}
+
+impl ImportLanguage {
+    fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        if extension == "rs" {
+            Some(Self::Rust)
+        } else if SCRIPT_EXTENSIONS.contains(&extension) {
+            Some(Self::Script)
+        } else {
+            None
+        }
+    }
+
+    /// Detected stack languages that turn this heuristic on.
+    fn stack_languages(self) -> &'static [&'static str] {
+        match self {
+            Self::Rust => &["Rust"],
+            Self::Script => &["TypeScript", "JavaScript"],
+        }
+    }
+
+    /// An undetected stack enables every heuristic; the file extension still decides.
+    fn enabled_for(self, stack_languages: &[String]) -> bool {
+        stack_languages.is_empty()
+            || stack_languages
+                .iter()
+                .any(|language| self.stack_languages().contains(&language.as_str()))
+    }
+}
+
+/// A file the review sees as read-only context.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReviewContextFile {
+    /// Workspace-relative path.
+    pub(crate) path: String,
+    pub(crate) bytes: usize,
+    /// The changed or context file that imports it.
+    pub(crate) imported_by: String,
+    #[serde(skip)]
+    content: String,
+}
+
+/// Context files picked for a review, reported back with the review result.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReviewContext {
+    pub(crate) files: Vec<ReviewContextFile>,
+    /// Changed files in languages without an import heuristic.
+    pub(crate) unsupported_files: Vec<String>,
+    /// Imported files left out because they did not fit the byte budget.
+    pub(crate) over_budget: Vec<String>,
+}
+
+impl ReviewContext {
+    /// The context files as one prompt section, kept apart from the diff.
+    pub(crate) fn prompt_block(&self) -> Option<String> {
+        if self.files.is_empty() {
+            return None;
+        }
+        let mut block = String::from(
+            "Read-only context: files imported by the changed code. They are not part of the \
+             diff; use them to understand the change and do not review them.\n",
+        );
+        for file in &self.files {
+            block.push_str(&format!("\n--- {} ---\n", file.path));
+            block.push_str(&file.content);
+            if !file.content.ends_with('\n') {
+                block.push('\n');
+            }
+        }
+        Some(block)
+    }
+}
+
+fn slash_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Resolves `.` and `..` without touching the disk; `None` when the path leaves the root.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(normalized)
+}
+
+fn existing_file(root: &Path, candidates: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+    candidates
+        .into_iter()
+        .find(|candidate| root.join(candidate).is_file())
+}
+
+/// Files `file` imports, relative to `root`; only paths that exist are returned.
+fn resolve_imports(
+    root: &Path,
+    file: &Path,
+    source: &str,
+    language: ImportLanguage,
+) -> Vec<PathBuf> {
+    let mut imports = match language {
+        ImportLanguage::Rust => rust_imports(root, file, source),
+        ImportLanguage::Script => script_imports(root, file, source),
+    };
+    let mut seen = HashSet::new();
+    imports.retain(|import| import != file && seen.insert(import.clone()));
+    imports
+}
+
+fn rust_mod_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+([A-Za-z_][A-Za-z0-9_]*)\s*;")
+            .expect("mod regex")
+    })
+}
+
+fn rust_path_attr_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r#"(?m)^\s*#\[path\s*=\s*"([^"]+)"\]\s*(?:pub(?:\([^)]*\))?\s+)?mod\s"#)
+            .expect("path attribute regex")
+    })
+}
+
+fn rust_use_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?use\s+(crate|super|self)((?:::[A-Za-z_][A-Za-z0-9_]*)+)",
+        )
+        .expect("use regex")
+    })
+}
+
+/// Directory holding the child modules of `file`.
+fn rust_module_dir(file: &Path) -> PathBuf {
+    let parent = file.parent().unwrap_or(Path::new(""));
+    match file.file_stem().and_then(|stem| stem.to_str()) {
+        Some("mod" | "lib" | "main") | None => parent.to_path_buf(),
+        Some(stem) => parent.join(stem),
+    }
+}
+
+/// The `src` directory of the crate `file` belongs to: the nearest ancestor with a
+/// `lib.rs` or `main.rs`.
+fn rust_crate_dir(root: &Path, file: &Path) -> Option<PathBuf> {
+    file.ancestors()
+        .skip(1)
+        .find(|dir| {
+            root.join(dir).join("lib.rs").is_file() || root.join(dir).join("main.rs").is_file()
+        })
+        .map(Path::to_path_buf)
+}
+
+/// The file of the longest module prefix of `segments` under `dir`, since a `use` path
+/// usually ends in an item rather than a module.
+fn rust_module_file(root: &Path, dir: &Path, segments: &[&str]) -> Option<PathBuf> {
+    (1..=segments.len()).rev().find_map(|len| {
+        let base = segments[..len]
+            .iter()
+            .fold(dir.to_path_buf(), |path, segment| path.join(segment));
+        existing_file(root, [base.with_extension("rs"), base.join("mod.rs")])
+    })
+}
+
+fn rust_imports(root: &Path, file: &Path, source: &str) -> Vec<PathBuf> {
+    let module_dir = rust_module_dir(file);
+    let mut imports = Vec::new();
+    for captures in rust_mod_regex().captures_iter(source) {
+        if let Some(found) = rust_module_file(root, &module_dir, &[&captures[1]]) {
+            imports.push(found);
+        }
+    }
+    let file_dir = file.parent().unwrap_or(Path::new(""));
+    for captures in rust_path_attr_regex().captures_iter(source) {
+        if let Some(path) = normalize(&file_dir.join(&captures[1])) {
+            imports.extend(existing_file(root, [path]));
+        }
+    }
+    for captures in rust_use_regex().captures_iter(source) {
+        let mut segments: Vec<&str> = captures[2]
+            .split("::")
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let mut base = match &captures[1] {
+            "crate" => match rust_crate_dir(root, file) {
+                Some(dir) => dir,
+                None => continue,
+            },
+            "self" => module_dir.clone(),
+            _ => match module_dir.parent() {
+                Some(dir) => dir.to_path_buf(),
+                None => continue,
+            },
+        };
+        while segments.first() == Some(&"super") {
+            segments.remove(0);
+            match base.parent() {
+                Some(dir) => base = dir.to_path_buf(),
+                None => break,
+            }
+        }
+        if let Some(found) = rust_module_file(root, &base, &segments) {
+            imports.push(found);
+        }
+    }
+    imports
+}
+
+fn script_import_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r#"(?m)(?:^|[^\w$.])(?:import|export)\s+(?:type\s+)?(?:[^'";]*?\s+from\s+)?['"]([^'"]+)['"]|(?:^|[^\w$.])(?:require|import)\s*\(\s*['"]([^'"]+)['"]\s*\)"#,
+        )
+        .expect("import regex")
+    })
+}
+
+/// Resolves a relative specifier the way bundlers do: exact file, added extension, then
+/// an `index` file. Package imports are not followed.
+fn script_module_file(root: &Path, file: &Path, specifier: &str) -> Option<PathBuf> {
+    if !specifier.starts_with("./") && !specifier.starts_with("../") {
+        return None;
+    }
+    let base = normalize(&file.parent()?.join(specifier))?;
+    let base_text = slash_path(&base);
+    let mut candidates = vec![base.clone()];
+    if let Some(extension) = base.extension().and_then(|extension| extension.to_str()) {
+        if let Some((_, sources)) = EMITTED_SCRIPT_EXTENSIONS
+            .iter()
+            .find(|(written, _)| *written == extension)
+        {
+            candidates.extend(sources.iter().map(|source| base.with_extension(source)));
+        }
+    }
+    candidates.extend(
+        SCRIPT_EXTENSIONS
+            .iter()
+            .map(|extension| PathBuf::from(format!("{base_text}.{extension}"))),
+    );
+    candidates.extend(
+        SCRIPT_EXTENSIONS
+            .iter()
+            .map(|extension| base.join(format!("index.{extension}"))),
+    );
+    existing_file(root, candidates)
+}
+
+fn script_imports(root: &Path, file: &Path, source: &str) -> Vec<PathBuf> {
+    script_import_regex()
+        .captures_iter(source)
+        .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)))
+        .filter_map(|specifier| script_module_file(root, file, specifier.as_str()))
+        .collect()
+}
+
+/// Follows imports from the changed files breadth first, so direct imports win the budget
+/// over transitive ones. Changed files themselves are never added as context.
+pub(crate) fn collect_review_context(
+    root: &Path,
+    changed_files: &[String],
+    stack_languages: &[String],
+    options: ReviewContextOptions,
+) -> ReviewContext {
+    let options = options.clamped();
+    let mut context = ReviewContext::default();
+    let mut seen: HashSet<PathBuf> = changed_files.iter().map(PathBuf::from).collect();
+    let mut queue: VecDeque<(PathBuf, usize)> = changed_files
+        .iter()
+        .map(|file| (PathBuf::from(file), 0))
+        .collect();
+    let mut remaining = options.byte_budget;
+    while let Some((file, depth)) = queue.pop_front() {
+        let language = ImportLanguage::for_path(&file)
+            .filter(|language| language.enabled_for(stack_languages));
+        let Some(language) = language else {
+            if depth == 0 {
+                context.unsupported_files.push(slash_path(&file));
+            }
+            continue;
+        };
+        if depth >= options.depth {
+            continue;
+        }
+        let Ok(source) = std::fs::read_to_string(root.join(&file)) else {
+            continue;
+        };
+        for import in resolve_imports(root, &file, &source, language) {
+            if !seen.insert(import.clone()) {
+                continue;
+            }
+            let Ok(content) = std::fs::read_to_string(root.join(&import)) else {
+                continue;
+            };
+            if content.len() > remaining {
+                context.over_budget.push(slash_path(&import));
+                continue;
+            }
+            remaining -= content.len();
+            context.files.push(ReviewContextFile {
+                path: slash_path(&import),
+                bytes: content.len(),
+                imported_by: slash_path(&file),
+                content,
+            });
+            queue.push_back((import, depth + 1));
+        }
+    }
+    context
+}
+
+/// The `review/start` target to send when the review has read-only context: a `custom`
+/// target whose instructions ask for the original review and then carry `context_block`
+/// between markers, so the model sees the context apart from the changes under review.
+pub(crate) fn review_target_with_context(target: &Value, context_block: &str) -> Value {
+    let field = |name: &str| target.get(name).and_then(Value::as_str).unwrap_or_default();
+    let request = match target.get("type").and_then(Value::as_str) {
+        Some("uncommittedChanges") => {
+            "Review the uncommitted changes in the working tree: staged, unstaged and untracked."
+                .to_string()
+        }
+        Some("baseBranch") => format!(
+            "Review the changes on the current branch since it diverged from `{}`.",
+            field("branch")
+        ),
+        Some("commit") => {
+            let title = field("title");
+            if title.is_empty() {
+                format!("Review the changes introduced by commit {}.", field("sha"))
+            } else {
+                format!(
+                    "Review the changes introduced by commit {} ({title}).",
+                    field("sha")
+                )
+            }
+        }
+        _ => field("instructions").to_string(),
+    };
+    json!({
+        "type": "custom",
+        "instructions": format!(
+            "{}\n\n{CONTEXT_START}\n{context_block}{CONTEXT_END}\n",
+            request.trim_end()
+        ),
+    })
+}
+
+/// Workspace-relative files a `review/start` target covers; custom reviews have none.
+pub(crate) fn changed_files_for_target(root: &Path, target: &Value) -> Result<Vec<String>, String> {
+    let repo = Repository::open(root).map_err(|err| err.to_string())?;
+    let mut options = DiffOptions::new();
+    let diff = match target.get("type").and_then(Value::as_str) {
+        Some("uncommittedChanges") => {
+            options.include_untracked(true).recurse_untracked_dirs(true);
+            let head = repo.head().and_then(|head| head.peel_to_tree()).ok();
+            repo.diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut options))
+        }
+        Some("baseBranch") => {
+            let branch = target
+                .get("branch")
+                .and_then(Value::as_str)
+                .ok_or("missing `branch`")?;
+            let base = repo
+                .revparse_single(branch)
+                .and_then(|object| object.peel_to_commit())
+                .map_err(|err| err.to_string())?;
+            let head = repo
+                .head()
+                .and_then(|head| head.peel_to_commit())
+                .map_err(|err| err.to_string())?;
+            let merge_base = repo
+                .merge_base(base.id(), head.id())
+                .and_then(|oid| repo.find_commit(oid))
+                .and_then(|commit| commit.tree())
+                .map_err(|err| err.to_string())?;
+            repo.diff_tree_to_workdir_with_index(Some(&merge_base), Some(&mut options))
+        }
+        Some("commit") => {
+            let sha = target
+                .get("sha")
+                .and_then(Value::as_str)
+                .ok_or("missing `sha`")?;
+            let commit = repo
+                .revparse_single(sha)
+                .and_then(|object| object.peel_to_commit())
+                .map_err(|err| err.to_string())?;
+            let tree = commit.tree().map_err(|err| err.to_string())?;
+            let parent_tree = commit.parent(0).and_then(|parent| parent.tree()).ok();
+            repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut options))
+        }
+        _ => return Ok(Vec::new()),
+    }
+    .map_err(|err| err.to_string())?;
+    Ok(diff
+        .deltas()
+        .filter(|delta| delta.status() != Delta::Deleted)
+        .filter_map(|delta| delta.new_file().path().map(slash_path))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn fixture(files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("review-context-{}", Uuid::new_v4()));
+        for (path, content) in files {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().expect("parent")).expect("create dir");
+            std::fs::write(path, content).expect("write fixture");
+        }
+        root
+    }
+
+    fn imports(root: &Path, file: &str) -> Vec<String> {
+        let source = std::fs::read_to_string(root.join(file)).expect("read fixture");
+        let language = ImportLanguage::for_path(Path::new(file)).expect("language");
+        resolve_imports(root, Path::new(file), &source, language)
+            .iter()
+            .map(|path| slash_path(path))
+            .collect()
+    }
+
+    #[test]
+    fn resolves_rust_mods_and_use_paths() {
+        let root = fixture(&[
+            (
+                "src/lib.rs",
+                "mod backend;\npub(crate) mod util;\nmod missing;\n",
+            ),
+            (
+                "src/backend/mod.rs",
+                "pub(crate) mod app_server;\npub(crate) mod events;\n",
+            ),
+            (
+                "src/backend/app_server.rs",
+                "use std::sync::Arc;\n\
+                 use crate::util::{clamp, Limit};\n\
+                 use super::events::EventSink;\n\
+                 use self::helpers::split;\n\
+                 // use crate::backend::nowhere;\n\
+                 #[path = \"../generated/schema.rs\"]\n\
+                 mod schema;\n\
+                 mod helpers;\n",
+            ),
+            ("src/backend/app_server/helpers.rs", "pub fn split() {}\n"),
+            ("src/backend/events.rs", "pub trait EventSink {}\n"),
+            ("src/generated/schema.rs", "pub struct Schema;\n"),
+            ("src/util/mod.rs", "pub fn clamp() {}\n"),
+        ]);
+
+        assert_eq!(
+            imports(&root, "src/lib.rs"),
+            vec!["src/backend/mod.rs", "src/util/mod.rs"]
+        );
+        assert_eq!(
+            imports(&root, "src/backend/app_server.rs"),
+            vec![
+                "src/backend/app_server/helpers.rs",
+                "src/generated/schema.rs",
+                "src/util/mod.rs",
+                "src/backend/events.rs",
+            ]
+        );
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn resolves_relative_typescript_imports() {
+        let root = fixture(&[
+            (
+                "src/app/App.tsx",
+                "import React from \"react\";\n\
+                 import {\n  useThreads,\n} from \"../hooks/useThreads\";\n\
+                 import type { Thread } from '../types';\n\
+                 export * from \"./panel.js\";\n\
+                 import \"./styles.css\";\n\
+                 const lazy = import(\"../utils/lazy\");\n\
+                 const legacy = require(\"../../outside\");\n",
+            ),
+            (
+                "src/hooks/useThreads.ts",
+                "export function useThreads() {}\n",
+            ),
+            ("src/types/index.ts", "export type Thread = {};\n"),
+            ("src/app/panel.tsx", "export const Panel = 1;\n"),
+            ("src/app/styles.css", "body {}\n"),
+            ("src/utils/lazy.js", "module.exports = {};\n"),
+        ]);
+
+        assert_eq!(
+            imports(&root, "src/app/App.tsx"),
+            vec![
+                "src/hooks/useThreads.ts",
+                "src/types/index.ts",
+                "src/app/panel.tsx",
+                "src/app/styles.css",
+                "src/utils/lazy.js",
+            ]
+        );
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn collects_context_within_budget_and_depth() {
+        let root = fixture(&[
+            ("src/lib.rs", "mod a;\nmod b;\n"),
+            ("src/a.rs", "mod deep;\n"),
+            ("src/a/deep.rs", "// transitive\n"),
+            ("src/b.rs", "// this file is far too large for the budget\n"),
+            ("README.md", "# docs\n"),
+        ]);
+        let changed = vec!["src/lib.rs".to_string(), "README.md".to_string()];
+        let options = ReviewContextOptions {
+            byte_budget: 30,
+            depth: 2,
+        };
+
+        let context = collect_review_context(&root, &changed, &["Rust".to_string()], options);
+        let paths: Vec<&str> = context
+            .files
+            .iter()
+            .map(|file| file.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["src/a.rs", "src/a/deep.rs"]);
+        assert_eq!(context.files[1].imported_by, "src/a.rs");
+        assert_eq!(context.over_budget, vec!["src/b.rs"]);
+        assert_eq!(context.unsupported_files, vec!["README.md"]);
+        let block = context.prompt_block().expect("prompt block");
+        assert!(block.starts_with("Read-only context"));
+        assert!(block.contains("--- src/a/deep.rs ---\n// transitive\n"));
+
+        let target =
+            review_target_with_context(&json!({ "type": "baseBranch", "branch": "main" }), &block);
+        assert_eq!(target["type"], "custom");
+        let prompt = target["instructions"].as_str().expect("review prompt");
+        let request_end = prompt
+            .find("since it diverged from `main`.")
+            .expect("review request");
+        let context_start = prompt.find(CONTEXT_START).expect("context start");
+        assert!(request_end < context_start);
+        assert!(prompt[context_start..].contains(&block));
+        assert!(prompt.trim_end().ends_with(CONTEXT_END));
+        let custom = review_target_with_context(
+            &json!({ "type": "custom", "instructions": "Check the error handling." }),
+            &block,
+        );
+        assert!(custom["instructions"]
+            .as_str()
+            .is_some_and(|prompt| prompt.starts_with("Check the error handling.\n\n=== BEGIN")));
+
+        let shallow = collect_review_context(
+            &root,
+            &changed,
+            &[],
+            ReviewContextOptions {
+                byte_budget: 1024,
+                depth: 1,
+            },
+        );
+        assert_eq!(shallow.files.len(), 2);
+
+        let typescript_only =
+            collect_review_context(&root, &changed, &["TypeScript".to_string()], options);
+        assert!(typescript_only.files.is_empty());
+        assert_eq!(typescript_only.unsupported_files, changed);
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::backend::turn_artifacts::relative_to_root;
+
+pub(crate) const SARIF_VERSION: &str = "2.1.0";
+pub(crate) const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const TOOL_NAME: &str = "MiCode Monitor Review";
+const DEFAULT_RULE_ID: &str = "review-finding";
+const SRCROOT: &str = "%SRCROOT%";
+
+/// One structured finding of a review, as stored on the `exitedReviewMode` item.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReviewFinding {
+    #[serde(alias = "path")]
+    pub(crate) file: String,
+    #[serde(default, alias = "startLine")]
+    pub(crate) line: Option<u64>,
+    #[serde(default)]
+    pub(crate) end_line: Option<u64>,
+    #[serde(default, alias = "priority")]
+    pub(crate) severity: Option<String>,
+    #[serde(default, alias = "rule")]
+    pub(crate) category: Option<String>,
+    #[serde(default)]
+    pub(crate) title: Option<String>,
+    #[serde(alias = "body")]
+    pub(crate) message: String,
+}
+
+/// Findings of a review item. Entries that lack a file or message are skipped instead of
+/// failing the whole export.
+pub(crate) fn findings_from_item(item: &Value) -> Vec<ReviewFinding> {
+    item.get("findings")
+        .and_then(Value::as_array)
+        .map(|findings| {
+            findings
+                .iter()
+                .filter_map(|finding| serde_json::from_value::<ReviewFinding>(finding.clone()).ok())
+                .filter(|finding| {
+                    !finding.file.trim().is_empty() && !finding.message.trim().is_empty()
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Maps free-form review severities onto SARIF levels. Unknown values become warnings so
+/// nothing is silently hidden by code scanning filters.
+pub(crate) fn sarif_level(severity: Option<&str>) -> &'static str {
+    let Some(severity) = severity else {
+        return "warning";
+    };
+    match severity.trim().to_lowercase().as_str() {
+        "critical" | "blocker" | "high" | "error" | "p0" | "p1" | "0" | "1" => "error",
+        "medium" | "moderate" | "warning" | "p2" | "2" => "warning",
+        "low" | "minor" | "info" | "note" | "nit" | "suggestion" | "p3" | "3" => "note",
+        "none" => "none",
+        _ => "warning",
+    }
+}
+
+fn rule_id(category: Option<&str>) -> String {
+    let slug = category
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        DEFAULT_RULE_ID.to_string()
+    } else {
+        slug
+    }
+}
+
+fn encode_uri_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for ch in path.chars() {
+        match ch {
+            ' ' => encoded.push_str("%20"),
+            '%' => encoded.push_str("%25"),
+            '#' => encoded.push_str("%23"),
+            '?' => encoded.push_str("%3F"),
+            _ => encoded.push(ch),
+        }
+    }
+    encoded
+}
+
+fn file_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    // Windows drive paths need the extra slash: `file:///C:/repo`.
+    let separator = if path.starts_with('/') { "" } else { "/" };
+    format!("file://{separator}{}", encode_uri_path(&path))
+}
+
+fn artifact_location(workspace_root: &Path, file: &str) -> Value {
+    match relative_to_root(workspace_root, &file.replace('\\', "/")) {
+        Some(relative) => json!({ "uri": encode_uri_path(&relative), "uriBaseId": SRCROOT }),
+        // Files outside the workspace keep an absolute URI rather than being dropped.
+        None => json!({ "uri": file_uri(file) }),
+    }
+}
+
+fn result_for(finding: &ReviewFinding, rule_index: usize, rule_id: &str, root: &Path) -> Value {
+    let mut physical = Map::new();
+    physical.insert(
+        "artifactLocation".to_string(),
+        artifact_location(root, &finding.file),
+    );
+    // Findings without a line stay file-level results: SARIF has no "line 0".
+    if let Some(start) = finding.line.filter(|line| *line > 0) {
+        let mut region = json!({ "startLine": start });
+        if let Some(end) = finding.end_line.filter(|end| *end >= start) {
+            region["endLine"] = json!(end);
+        }
+        physical.insert("region".to_string(), region);
+    }
+    let mut properties = Map::new();
+    if let Some(severity) = &finding.severity {
+        properties.insert("severity".to_string(), json!(severity));
+    }
+    if let Some(title) = &finding.title {
+        properties.insert("title".to_string(), json!(title));
+    }
+    let mut result = json!({
+        "ruleId": rule_id,
+        "ruleIndex": rule_index,
+        "level": sarif_level(finding.severity.as_deref()),
+        "message": { "text": finding.message.trim() },
+        "locations": [{ "physicalLocation": physical }],
+    });
+    if !properties.is_empty() {
+        result["properties"] = Value::Object(properties);
+    }
+    result
+}
+
+/// Builds a SARIF 2.1.0 log with one run. Rules are synthesized from the finding
+/// categories; paths inside `workspace_root` are made relative to `%SRCROOT%`.
+pub(crate) fn build_sarif(
+    findings: &[ReviewFinding],
+    workspace_root: &Path,
+    tool_version: &str,
+) -> Value {
+    let mut rules: BTreeMap<String, Option<String>> = BTreeMap::new();
+    for finding in findings {
+        rules
+            .entry(rule_id(finding.category.as_deref()))
+            .or_insert_with(|| finding.category.clone());
+    }
+    let rule_ids: Vec<&String> = rules.keys().collect();
+    let results: Vec<Value> = findings
+        .iter()
+        .map(|finding| {
+            let id = rule_id(finding.category.as_deref());
+            let index = rule_ids
+                .iter()
+                .position(|rule| **rule == id)
+                .unwrap_or_default();
+            result_for(finding, index, &id, workspace_root)
+        })
+        .collect();
+    let rules: Vec<Value> = rules
+        .iter()
+        .map(|(id, category)| {
+            let label = category
+                .clone()
+                .unwrap_or_else(|| "Review finding".to_string());
+            json!({
+                "id": id,
+                "name": label,
+                "shortDescription": { "text": label },
+            })
+        })
+        .collect();
+    let root = workspace_root.to_string_lossy();
+    let root_uri = format!("{}/", file_uri(root.trim_end_matches(['/', '\\'])));
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": TOOL_NAME,
+                    "version": tool_version,
+                    "rules": rules,
+                },
+            },
+            "originalUriBaseIds": {
+                SRCROOT: { "uri": root_uri },
+            },
+            "results": results,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_sarif, findings_from_item, sarif_level, SARIF_VERSION};
+    use serde_json::{json, Value};
+    use std::path::Path;
+
+    const LEVELS: &[&str] = &["none", "note", "warning", "error"];
+
+    /// Checks the constraints of the SARIF 2.1.0 schema that the export relies on:
+    /// required properties, enum values, minimums and rule references.
+    fn assert_valid_sarif(log: &Value) {
+        assert_eq!(log["version"], SARIF_VERSION);
+        let runs = log["runs"].as_array().expect("runs array");
+        for run in runs {
+            let driver = &run["tool"]["driver"];
+            assert!(driver["name"].as_str().is_some_and(|name| !name.is_empty()));
+            let rules = driver["rules"].as_array().expect("rules array");
+            for rule in rules {
+                assert!(rule["id"].is_string());
+            }
+            for result in run["results"].as_array().expect("results array") {
+                assert!(result["message"]["text"].is_string());
+                assert!(LEVELS.contains(&result["level"].as_str().expect("level")));
+                let index = result["ruleIndex"].as_u64().expect("ruleIndex") as usize;
+                assert_eq!(rules[index]["id"], result["ruleId"]);
+                for location in result["locations"].as_array().expect("locations") {
+                    let physical = &location["physicalLocation"];
+                    assert!(physical["artifactLocation"]["uri"].is_string());
+                    if let Some(region) = physical.get("region") {
+                        let start = region["startLine"].as_u64().expect("startLine");
+                        assert!(start >= 1);
+                        if let Some(end) = region.get("endLine") {
+                            assert!(end.as_u64().expect("endLine") >= start);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exports_findings_with_and_without_lines() {
+        let item = json!({
+            "type": "exitedReviewMode",
+            "findings": [
+                {
+                    "file": "/repo/src/auth.rs",
+                    "line": 42,
+                    "endLine": 44,
+                    "severity": "high",
+                    "category": "SQL Injection",
+                    "message": "Query built from user input."
+                },
+                {
+                    "path": "docs/setup guide.md",
+                    "severity": "low",
+                    "message": "Outdated install steps."
+                },
+                { "file": "src/lib.rs", "line": 0, "body": "Missing docs.", "priority": "P2" },
+                { "file": "", "message": "dropped" }
+            ]
+        });
+        let findings = findings_from_item(&item);
+        assert_eq!(findings.len(), 3);
+
+        let log = build_sarif(&findings, Path::new("/repo"), "1.2.3");
+        assert_valid_sarif(&log);
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["version"], "1.2.3");
+        let results = run["results"].as_array().expect("results");
+
+        let first = &results[0];
+        assert_eq!(first["level"], "error");
+        assert_eq!(first["ruleId"], "sql-injection");
+        assert_eq!(first["message"]["text"], "Query built from user input.");
+        assert_eq!(first["properties"]["severity"], "high");
+        let location = &first["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/auth.rs");
+        assert_eq!(location["artifactLocation"]["uriBaseId"], "%SRCROOT%");
+        assert_eq!(
+            location["region"],
+            json!({ "startLine": 42, "endLine": 44 })
+        );
+
+        let second = &results[1];
+        assert_eq!(second["level"], "note");
+        assert_eq!(second["ruleId"], "review-finding");
+        let location = &second["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "docs/setup%20guide.md");
+        assert!(location.get("region").is_none());
+
+        assert_eq!(results[2]["level"], "warning");
+        assert!(results[2]["locations"][0]["physicalLocation"]
+            .get("region")
+            .is_none());
+    }
+
+    #[test]
+    fn maps_severities_to_levels() {
+        assert_eq!(sarif_level(Some("Critical")), "error");
+        assert_eq!(sarif_level(Some("medium")), "warning");
+        assert_eq!(sarif_level(Some("nit")), "note");
+        assert_eq!(sarif_level(Some("whatever")), "warning");
+        assert_eq!(sarif_level(None), "warning");
+    }
+}
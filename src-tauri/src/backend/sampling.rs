@@ -0,0 +1,129 @@
+use serde_json::Value;
+
+use crate::types::SamplingParams;
+
+const SUPPORTED_KEYS: &[&str] = &[
+    "temperature",
+    "topP",
+    "top_p",
+    "maxOutputTokens",
+    "max_output_tokens",
+];
+const TEMPERATURE_RANGE: (f64, f64) = (0.0, 2.0);
+const MAX_OUTPUT_TOKENS_LIMIT: u32 = 1_048_576;
+
+/// Parses a `samplingParams` object coming from the UI or an RPC call. Unknown keys are
+/// rejected instead of being silently dropped.
+pub(crate) fn parse_sampling_params(value: &Value) -> Result<SamplingParams, String> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| "samplingParams must be an object".to_string())?;
+    if let Some(key) = object
+        .keys()
+        .find(|key| !SUPPORTED_KEYS.contains(&key.as_str()))
+    {
+        return Err(format!(
+            "Unsupported sampling parameter `{key}`. Supported: temperature, topP, maxOutputTokens."
+        ));
+    }
+    let params: SamplingParams = serde_json::from_value(value.clone())
+        .map_err(|err| format!("Invalid samplingParams: {err}"))?;
+    validate_sampling_params(&params)?;
+    Ok(params)
+}
+
+pub(crate) fn validate_sampling_params(params: &SamplingParams) -> Result<(), String> {
+    if let Some(temperature) = params.temperature {
+        let (min, max) = TEMPERATURE_RANGE;
+        if !temperature.is_finite() || temperature < min || temperature > max {
+            return Err(format!(
+                "temperature must be between {min} and {max} (got {temperature})"
+            ));
+        }
+    }
+    if let Some(top_p) = params.top_p {
+        if !top_p.is_finite() || top_p <= 0.0 || top_p > 1.0 {
+            return Err(format!(
+                "topP must be greater than 0 and at most 1 (got {top_p})"
+            ));
+        }
+    }
+    if let Some(max_output_tokens) = params.max_output_tokens {
+        if max_output_tokens == 0 || max_output_tokens > MAX_OUTPUT_TOKENS_LIMIT {
+            return Err(format!(
+                "maxOutputTokens must be between 1 and {MAX_OUTPUT_TOKENS_LIMIT} (got {max_output_tokens})"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Layers sampling overrides: later layers win per key.
+pub(crate) fn merge_sampling_params<'a>(
+    layers: impl IntoIterator<Item = Option<&'a SamplingParams>>,
+) -> SamplingParams {
+    let mut merged = SamplingParams::default();
+    for layer in layers.into_iter().flatten() {
+        if layer.temperature.is_some() {
+            merged.temperature = layer.temperature;
+        }
+        if layer.top_p.is_some() {
+            merged.top_p = layer.top_p;
+        }
+        if layer.max_output_tokens.is_some() {
+            merged.max_output_tokens = layer.max_output_tokens;
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_sampling_params, parse_sampling_params};
+    use crate::types::SamplingParams;
+    use serde_json::json;
+
+    #[test]
+    fn parses_camel_and_snake_case_keys() {
+        let params = parse_sampling_params(&json!({
+            "temperature": 0.2,
+            "top_p": 0.9,
+            "maxOutputTokens": 4096
+        }))
+        .expect("valid params");
+        assert_eq!(params.temperature, Some(0.2));
+        assert_eq!(params.top_p, Some(0.9));
+        assert_eq!(params.max_output_tokens, Some(4096));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_out_of_range_values() {
+        let error = parse_sampling_params(&json!({ "topK": 40 })).expect_err("unknown key");
+        assert!(error.contains("`topK`"));
+        assert!(parse_sampling_params(&json!({ "temperature": 3.5 })).is_err());
+        assert!(parse_sampling_params(&json!({ "topP": 0 })).is_err());
+        assert!(parse_sampling_params(&json!({ "maxOutputTokens": 0 })).is_err());
+        assert!(parse_sampling_params(&json!(["temperature"])).is_err());
+    }
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let model = SamplingParams {
+            temperature: Some(0.7),
+            top_p: Some(0.95),
+            max_output_tokens: None,
+        };
+        let workspace = SamplingParams {
+            temperature: Some(0.1),
+            ..SamplingParams::default()
+        };
+        let message = SamplingParams {
+            max_output_tokens: Some(2048),
+            ..SamplingParams::default()
+        };
+        let merged = merge_sampling_params([Some(&model), Some(&workspace), None, Some(&message)]);
+        assert_eq!(merged.temperature, Some(0.1));
+        assert_eq!(merged.top_p, Some(0.95));
+        assert_eq!(merged.max_output_tokens, Some(2048));
+    }
+}
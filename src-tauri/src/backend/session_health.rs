@@ -0,0 +1,92 @@
+use serde::Serialize;
+
+/// Pings missed in a row before a session counts as unresponsive.
+pub(crate) const MISSED_PINGS_BEFORE_UNRESPONSIVE: u32 = 2;
+
+/// Liveness of a workspace's agent process as seen by the heartbeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum SessionHealth {
+    Healthy,
+    /// Pings sent while a prompt was running went unanswered.
+    Unresponsive,
+    /// The agent process exited without being stopped by the app.
+    Disconnected,
+}
+
+#[derive(Debug)]
+pub(crate) struct HealthTracker {
+    health: SessionHealth,
+    missed_pings: u32,
+}
+
+impl Default for HealthTracker {
+    fn default() -> Self {
+        Self {
+            health: SessionHealth::Healthy,
+            missed_pings: 0,
+        }
+    }
+}
+
+impl HealthTracker {
+    pub(crate) fn health(&self) -> SessionHealth {
+        self.health
+    }
+
+    pub(crate) fn missed_pings(&self) -> u32 {
+        self.missed_pings
+    }
+
+    fn transition(&mut self, next: SessionHealth) -> Option<SessionHealth> {
+        if self.health == next || self.health == SessionHealth::Disconnected {
+            return None;
+        }
+        self.health = next;
+        Some(next)
+    }
+
+    /// Returns the new health when the answer ends an unresponsive stretch.
+    pub(crate) fn ping_answered(&mut self) -> Option<SessionHealth> {
+        self.missed_pings = 0;
+        self.transition(SessionHealth::Healthy)
+    }
+
+    /// Returns the new health once enough pings in a row went unanswered.
+    pub(crate) fn ping_missed(&mut self) -> Option<SessionHealth> {
+        self.missed_pings += 1;
+        if self.missed_pings < MISSED_PINGS_BEFORE_UNRESPONSIVE {
+            return None;
+        }
+        self.transition(SessionHealth::Unresponsive)
+    }
+
+    pub(crate) fn exited(&mut self) -> Option<SessionHealth> {
+        self.transition(SessionHealth::Disconnected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missed_pings_flag_unresponsive_until_an_answer_arrives() {
+        let mut tracker = HealthTracker::default();
+        assert_eq!(tracker.ping_answered(), None);
+        assert_eq!(tracker.ping_missed(), None);
+        assert_eq!(tracker.ping_missed(), Some(SessionHealth::Unresponsive));
+        assert_eq!(tracker.ping_missed(), None);
+        assert_eq!(tracker.missed_pings(), 3);
+
+        assert_eq!(tracker.ping_answered(), Some(SessionHealth::Healthy));
+        assert_eq!(tracker.missed_pings(), 0);
+        // A single miss after recovering is not enough to flag the session again.
+        assert_eq!(tracker.ping_missed(), None);
+
+        assert_eq!(tracker.exited(), Some(SessionHealth::Disconnected));
+        assert_eq!(tracker.ping_answered(), None);
+        assert_eq!(tracker.exited(), None);
+        assert_eq!(tracker.health(), SessionHealth::Disconnected);
+    }
+}
@@ -0,0 +1,211 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde_json::{json, Map, Value};
+
+use crate::backend::event_methods;
+use crate::backend::events::AppServerEvent;
+
+
+/// Keys ending in one of these never leave the backend in a `settings/updated` event.
+const SECRET_KEY_MARKERS: &[&str] = &["password", "secret", "token", "apikey", "api_key"];
+/// Maps that hold credentials under arbitrary names, such as MCP server environments.
+const SECRET_MAPS: &[&str] = &["env", "headers", "http_headers"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SettingsScope<'a> {
+    App,
+    Workspace(&'a str),
+    Accelerators,
+    MicodeConfig,
+}
+
+impl SettingsScope<'_> {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::App => "app",
+            Self::Workspace(_) => "workspace",
+            Self::Accelerators => "accelerators",
+            Self::MicodeConfig => "micodeConfig",
+        }
+    }
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SECRET_MAPS.contains(&key.as_str())
+        || SECRET_KEY_MARKERS
+            .iter()
+            .any(|marker| key.ends_with(marker))
+}
+
+fn without_secrets(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(key, _)| !is_secret_key(key))
+                .map(|(key, value)| (key.clone(), without_secrets(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(without_secrets).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Top-level keys whose value differs between `before` and `after`, with their new
+/// values (`null` once removed). Secrets are stripped first, so a changed secret alone
+/// changes no key.
+fn changed_keys(before: &Value, after: &Value) -> (Vec<String>, Map<String, Value>) {
+    let (Value::Object(before), Value::Object(after)) =
+        (without_secrets(before), without_secrets(after))
+    else {
+        return (Vec::new(), Map::new());
+    };
+    let mut keys: Vec<String> = before
+        .keys()
+        .chain(after.keys())
+        .filter(|key| before.get(*key) != after.get(*key))
+        .cloned()
+        .collect();
+    keys.sort();
+    keys.dedup();
+    let values = keys
+        .iter()
+        .map(|key| (key.clone(), after.get(key).cloned().unwrap_or(Value::Null)))
+        .collect();
+    (keys, values)
+}
+
+/// Top-level tables and values of a `config.toml`, as JSON; unparsable text reads as empty.
+pub(crate) fn micode_config_values(content: &str) -> Value {
+    toml::from_str::<toml::Value>(content)
+        .ok()
+        .and_then(|value| serde_json::to_value(value).ok())
+        .unwrap_or_else(|| json!({}))
+}
+
+/// Bumped once per successful settings mutation, so a window can tell whether the
+/// settings it holds are older than the ones an event announces. Kept in app state.
+#[derive(Debug, Default)]
+pub(crate) struct SettingsRevision(AtomicU64);
+
+impl SettingsRevision {
+    pub(crate) fn current(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Records a successful settings mutation: bumps the revision and builds the
+    /// `settings/updated` event, sent even when nothing changed so every window sees the
+    /// new revision. Returns the new revision with the event.
+    pub(crate) fn record_update(
+        &self,
+        scope: SettingsScope<'_>,
+        before: &Value,
+        after: &Value,
+    ) -> (u64, AppServerEvent) {
+        let revision = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+        (
+            revision,
+            settings_updated_event(scope, revision, before, after),
+        )
+    }
+}
+
+fn settings_updated_event(
+    scope: SettingsScope<'_>,
+    revision: u64,
+    before: &Value,
+    after: &Value,
+) -> AppServerEvent {
+    let (keys, values) = changed_keys(before, after);
+    let mut params = json!({
+        "scope": scope.name(),
+        "revision": revision,
+        "keys": keys,
+        "values": values,
+    });
+    let workspace_id = match scope {
+        SettingsScope::Workspace(workspace_id) => {
+            params["workspaceId"] = json!(workspace_id);
+            workspace_id.to_string()
+        }
+        _ => String::new(),
+    };
+    AppServerEvent {
+        workspace_id,
+        message: json!({ "method": event_methods::SETTINGS_UPDATED, "params": params }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_update_bumps_the_revision_exactly_once() {
+        let settings_revision = SettingsRevision::default();
+        let before = json!({ "theme": "dark", "uiScale": 1.0 });
+        let after = json!({ "theme": "light", "uiScale": 1.0 });
+        let (revision, event) =
+            settings_revision.record_update(SettingsScope::App, &before, &after);
+        assert_eq!(revision, 1);
+        assert_eq!(settings_revision.current(), 1);
+        assert_eq!(
+            event.message,
+            json!({
+                "method": "settings/updated",
+                "params": {
+                    "scope": "app",
+                    "revision": revision,
+                    "keys": ["theme"],
+                    "values": { "theme": "light" },
+                },
+            })
+        );
+
+        // A no-op save still moves the revision, by one.
+        let (next, event) =
+            settings_revision.record_update(SettingsScope::Workspace("ws-1"), &after, &after);
+        assert_eq!(next, 2);
+        assert_eq!(settings_revision.current(), 2);
+        assert_eq!(event.workspace_id, "ws-1");
+        assert_eq!(event.message["params"]["workspaceId"], "ws-1");
+        assert_eq!(event.message["params"]["keys"], json!([]));
+    }
+
+    #[test]
+    fn secrets_never_reach_the_event() {
+        let before = micode_config_values("model = \"a\"\n");
+        let after = micode_config_values(
+            r#"
+model = "b"
+api_key = "sk-1"
+
+[mcp_servers.docs]
+command = "docs-mcp"
+env = { DOCS_TOKEN = "t-1" }
+http_headers = { Authorization = "Bearer t-2" }
+"#,
+        );
+        let (keys, values) = changed_keys(&before, &after);
+        assert_eq!(keys, vec!["mcp_servers".to_string(), "model".to_string()]);
+        assert_eq!(
+            Value::Object(values),
+            json!({
+                "mcp_servers": { "docs": { "command": "docs-mcp" } },
+                "model": "b",
+            })
+        );
+
+        let (keys, _) = changed_keys(
+            &json!({ "proxy": { "url": "http://a", "password": "old" }, "remoteBackendToken": "x" }),
+            &json!({ "proxy": { "url": "http://a", "password": "new" }, "remoteBackendToken": "y" }),
+        );
+        assert!(keys.is_empty());
+        let (keys, _) = changed_keys(
+            &json!({ "model_max_output_tokens": 1 }),
+            &json!({ "model_max_output_tokens": 2 }),
+        );
+        assert_eq!(keys, vec!["model_max_output_tokens".to_string()]);
+        assert_eq!(micode_config_values("not = [toml"), json!({}));
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// A MiCode settings file that could not be parsed even after tolerating comments and
+/// trailing commas. Line and column point into the original file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SettingsParseError {
+    pub(crate) path: String,
+    pub(crate) message: String,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+#[derive(Default)]
+struct ParseErrorLog {
+    reported: BTreeSet<(String, String)>,
+    pending: Vec<SettingsParseError>,
+}
+
+/// Parse errors waiting for a `micode/settingsParseError` event. App and daemon state
+/// each own one; sessions get a handle when they spawn and emit what it collected.
+#[derive(Clone, Default)]
+pub(crate) struct SettingsParseErrors {
+    log: Arc<Mutex<ParseErrorLog>>,
+}
+
+impl SettingsParseErrors {
+    /// Queues `error` once per distinct file and error for the lifetime of the owner.
+    fn report(&self, error: &SettingsParseError) {
+        let Ok(mut log) = self.log.lock() else {
+            return;
+        };
+        if log
+            .reported
+            .insert((error.path.clone(), error.message.clone()))
+        {
+            log.pending.push(error.clone());
+        }
+    }
+
+    /// Parse errors not yet surfaced to the frontend.
+    pub(crate) fn take_pending(&self) -> Vec<SettingsParseError> {
+        self.log
+            .lock()
+            .map(|mut log| std::mem::take(&mut log.pending))
+            .unwrap_or_default()
+    }
+}
+
+/// Blanks out `//` and `/* */` comments outside of strings and drops commas that directly
+/// precede `}` or `]`. Newlines and character columns are kept, so serde's error positions
+/// still match the original text.
+fn strip_jsonc(raw: &str) -> String {
+    let chars: Vec<char> = raw.chars().collect();
+    let mut out: Vec<char> = Vec::with_capacity(chars.len());
+    let mut index = 0;
+    let mut in_string = false;
+    while index < chars.len() {
+        let ch = chars[index];
+        if in_string {
+            out.push(ch);
+            if ch == '\\' && index + 1 < chars.len() {
+                out.push(chars[index + 1]);
+                index += 2;
+                continue;
+            }
+            if ch == '"' {
+                in_string = false;
+            }
+            index += 1;
+            continue;
+        }
+        match (ch, chars.get(index + 1)) {
+            ('"', _) => {
+                in_string = true;
+                out.push(ch);
+                index += 1;
+            }
+            ('/', Some('/')) => {
+                while index < chars.len() && chars[index] != '\n' {
+                    out.push(' ');
+                    index += 1;
+                }
+            }
+            ('/', Some('*')) => {
+                out.extend([' ', ' ']);
+                index += 2;
+                while index < chars.len() {
+                    if chars[index] == '*' && chars.get(index + 1) == Some(&'/') {
+                        out.extend([' ', ' ']);
+                        index += 2;
+                        break;
+                    }
+                    out.push(if chars[index] == '\n' { '\n' } else { ' ' });
+                    index += 1;
+                }
+            }
+            ('}' | ']', _) => {
+                if let Some(comma) = out.iter().rposition(|c| !c.is_whitespace()) {
+                    if out[comma] == ',' {
+                        out[comma] = ' ';
+                    }
+                }
+                out.push(ch);
+                index += 1;
+            }
+            _ => {
+                out.push(ch);
+                index += 1;
+            }
+        }
+    }
+    out.into_iter().collect()
+}
+
+/// Whether the raw text relies on JSONC extensions, i.e. a strict rewrite would drop them.
+fn has_jsonc_extensions(raw: &str) -> bool {
+    serde_json::from_str::<Value>(raw).is_err() && strip_jsonc(raw) != raw
+}
+
+/// Parses settings text, tolerating comments and trailing commas.
+pub(crate) fn parse_settings_text(path: &Path, raw: &str) -> Result<Value, SettingsParseError> {
+    if raw.trim().is_empty() {
+        return Ok(Value::Object(Default::default()));
+    }
+    serde_json::from_str::<Value>(&strip_jsonc(raw)).map_err(|err| SettingsParseError {
+        path: path.display().to_string(),
+        message: err.to_string(),
+        line: err.line(),
+        column: err.column(),
+    })
+}
+
+/// Reads and parses a settings file. Missing files yield `None`; files that still fail to
+/// parse are reported to `errors` once and also yield `None`.
+pub(crate) fn read_settings_file(path: &Path, errors: &SettingsParseErrors) -> Option<Value> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    match parse_settings_text(path, &raw) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            errors.report(&error);
+            None
+        }
+    }
+}
+
+/// Loads a settings file for rewriting. A file that cannot be parsed is copied to a
+/// `.bak` sibling and the rewrite is refused, so hand edits are never clobbered.
+pub(crate) fn load_settings_for_update(
+    path: &Path,
+    errors: &SettingsParseErrors,
+) -> Result<Value, String> {
+    if !path.is_file() {
+        return Ok(Value::Object(Default::default()));
+    }
+    let raw = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    match parse_settings_text(path, &raw) {
+        Ok(value) if value.is_object() => Ok(value),
+        Ok(_) => Err(format!("{} is not a JSON object", path.display())),
+        Err(error) => {
+            errors.report(&error);
+            let backup = backup_path(path);
+            let _ = std::fs::copy(path, &backup);
+            Err(format!(
+                "Refusing to rewrite {} ({} at line {} column {}); a copy was saved to {}",
+                error.path,
+                error.message,
+                error.line,
+                error.column,
+                backup.display()
+            ))
+        }
+    }
+}
+
+/// Writes settings back. Comments cannot survive a structured rewrite, so a file that
+/// had them is backed up first.
+pub(crate) fn write_settings_file(path: &Path, root: &Value) -> Result<(), String> {
+    if let Ok(raw) = std::fs::read_to_string(path) {
+        if has_jsonc_extensions(&raw) {
+            let _ = std::fs::copy(path, backup_path(path));
+        }
+    }
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let payload = serde_json::to_string_pretty(root).map_err(|e| e.to_string())?;
+    std::fs::write(path, payload).map_err(|e| e.to_string())
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        load_settings_for_update, parse_settings_text, read_settings_file, write_settings_file,
+        SettingsParseErrors,
+    };
+    use serde_json::json;
+    use std::path::Path;
+    use uuid::Uuid;
+
+    const COMMENTED: &str = r#"{
+  // Picked in the MiCode TUI
+  "model": {
+    "preferredModel": "mify-large", /* trailing note */
+  },
+  "url": "http://example.com/a//b",
+  "mcpServers": {
+    "files": { "command": "npx", "args": ["-y", "server",], },
+  },
+}
+"#;
+
+    #[test]
+    fn parses_comments_and_trailing_commas() {
+        let value = parse_settings_text(Path::new("settings.json"), COMMENTED).expect("parse");
+        assert_eq!(value["model"]["preferredModel"], "mify-large");
+        assert_eq!(value["url"], "http://example.com/a//b");
+        assert_eq!(
+            value["mcpServers"]["files"]["args"],
+            json!(["-y", "server"])
+        );
+        let value = parse_settings_text(Path::new("settings.json"), r#"{"a": "x,}"}"#)
+            .expect("parse string with brace");
+        assert_eq!(value["a"], "x,}");
+    }
+
+    #[test]
+    fn errors_keep_original_positions_and_are_reported_once() {
+        let root = std::env::temp_dir().join(format!("micode-settings-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create dir");
+        let path = root.join("settings.json");
+        std::fs::write(&path, "{\n  // note\n  \"model\": oops\n}").expect("write");
+
+        let errors = SettingsParseErrors::default();
+        assert!(read_settings_file(&path, &errors).is_none());
+        assert!(read_settings_file(&path, &errors).is_none());
+        let pending = errors.take_pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].path, path.display().to_string());
+        assert_eq!(pending[0].line, 3);
+        assert!(errors.take_pending().is_empty());
+
+        assert!(load_settings_for_update(&path, &errors).is_err());
+        assert!(root.join("settings.json.bak").is_file());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn rewriting_commented_file_keeps_a_backup() {
+        let root = std::env::temp_dir().join(format!("micode-settings-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create dir");
+        let path = root.join("settings.json");
+        std::fs::write(&path, COMMENTED).expect("write");
+
+        let errors = SettingsParseErrors::default();
+        let mut value = load_settings_for_update(&path, &errors).expect("load");
+        value["model"]["preferredModel"] = json!("mify-small");
+        write_settings_file(&path, &value).expect("write settings");
+
+        let backup = std::fs::read_to_string(root.join("settings.json.bak")).expect("backup");
+        assert!(backup.contains("// Picked in the MiCode TUI"));
+        let rewritten = read_settings_file(&path, &errors).expect("reparse");
+        assert_eq!(rewritten["model"]["preferredModel"], "mify-small");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+/// Folder of skills, in the MiCode home and in a workspace's `.micode` folder.
+const SKILLS_DIR: &str = "skills";
+/// Definition file of a skill that lives in its own folder.
+const SKILL_FILE: &str = "SKILL.md";
+
+/// Where a skill is defined. Workspace skills shadow global skills with the same id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SkillSource {
+    Global,
+    Workspace,
+}
+
+/// A skill as listed by `skills/list`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct Skill {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+    pub(crate) source: SkillSource,
+    pub(crate) path: String,
+}
+
+/// Skills found for a workspace, and one message per skill file that could not be read.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct SkillListing {
+    pub(crate) skills: Vec<Skill>,
+    pub(crate) warnings: Vec<String>,
+}
+
+/// Lists the skills under `<micode_home>/skills` and `<workspace>/.micode/skills`. A skill
+/// is either `<name>.md` or `<name>/SKILL.md`, optionally starting with a `---` block
+/// that sets `name` and `description`.
+pub(crate) fn list_skills(micode_home: Option<&Path>, workspace: &Path) -> SkillListing {
+    let roots = micode_home
+        .map(|home| (home.join(SKILLS_DIR), SkillSource::Global))
+        .into_iter()
+        .chain([(
+            workspace.join(".micode").join(SKILLS_DIR),
+            SkillSource::Workspace,
+        )]);
+    let mut skills = BTreeMap::new();
+    let mut warnings = Vec::new();
+    for (dir, source) in roots {
+        let mut seen = Vec::new();
+        for path in skill_files(&dir) {
+            let skill = match read_skill(&path, source) {
+                Ok(skill) => skill,
+                Err(error) => {
+                    warnings.push(format!("{}: {error}", path.display()));
+                    continue;
+                }
+            };
+            if seen.contains(&skill.id) {
+                warnings.push(format!(
+                    "{}: skill `{}` is already defined in {}",
+                    path.display(),
+                    skill.name,
+                    dir.display()
+                ));
+                continue;
+            }
+            seen.push(skill.id.clone());
+            skills.insert(skill.id.clone(), skill);
+        }
+    }
+    SkillListing {
+        skills: skills.into_values().collect(),
+        warnings,
+    }
+}
+
+fn skill_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            if path.is_dir() {
+                let file = path.join(SKILL_FILE);
+                file.is_file().then_some(file)
+            } else {
+                let is_markdown = path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("md"));
+                is_markdown.then_some(path)
+            }
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+fn read_skill(path: &Path, source: SkillSource) -> Result<Skill, String> {
+    let content = fs::read_to_string(path).map_err(|err| format!("cannot be read: {err}"))?;
+    let (fields, body) = split_frontmatter(&content)?;
+    let default_name = if path.file_name().is_some_and(|name| name == SKILL_FILE) {
+        path.parent().and_then(Path::file_name)
+    } else {
+        path.file_stem()
+    }
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_default();
+    let name = fields
+        .get("name")
+        .cloned()
+        .unwrap_or(default_name)
+        .trim()
+        .to_string();
+    if name.is_empty() || name.chars().any(|ch| ch.is_whitespace() || ch == '/') {
+        return Err(format!("invalid skill name `{name}`"));
+    }
+    let description = fields
+        .get("description")
+        .cloned()
+        .or_else(|| {
+            body.lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string)
+        })
+        .filter(|description| !description.is_empty());
+    Ok(Skill {
+        id: name.to_lowercase(),
+        name,
+        description,
+        source,
+        path: path.display().to_string(),
+    })
+}
+
+/// The `key: value` fields of a leading `---` block, and the text after it. Indented
+/// and comment lines in the block are skipped.
+fn split_frontmatter(content: &str) -> Result<(BTreeMap<String, String>, &str), String> {
+    let mut fields = BTreeMap::new();
+    let Some(rest) = content
+        .trim_start_matches('\u{feff}')
+        .strip_prefix("---")
+        .and_then(|rest| {
+            rest.strip_prefix('\n')
+                .or_else(|| rest.strip_prefix("\r\n"))
+        })
+    else {
+        return Ok((fields, content));
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end();
+        if line == "---" {
+            return Ok((fields, &rest[offset..]));
+        }
+        if line.is_empty() || line.starts_with([' ', '\t', '#']) {
+            continue;
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("invalid frontmatter line `{line}`"))?;
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .or_else(|| {
+                value
+                    .strip_prefix('\'')
+                    .and_then(|value| value.strip_suffix('\''))
+            })
+            .unwrap_or(value);
+        fields.insert(key.trim().to_string(), value.to_string());
+    }
+    Err("frontmatter is not closed with `---`".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{list_skills, SkillSource};
+    use std::path::Path;
+    use uuid::Uuid;
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().expect("parent")).expect("create dir");
+        std::fs::write(path, content).expect("write skill");
+    }
+
+    #[test]
+    fn workspace_skills_shadow_global_ones_and_bad_files_become_warnings() {
+        let root = std::env::temp_dir().join(format!("micode-skills-{}", Uuid::new_v4()));
+        let home = root.join("home");
+        let workspace = root.join("workspace");
+        write(
+            &home.join("skills/deploy/SKILL.md"),
+            "---\nname: deploy\ndescription: \"Ship to staging\"\n---\nSteps...\n",
+        );
+        write(
+            &home.join("skills/review.md"),
+            "# Review\n\nChecks a diff for bugs.\n",
+        );
+        write(&home.join("skills/notes.txt"), "not a skill");
+        write(&home.join("skills/broken.md"), "---\nname: broken\n");
+        write(
+            &workspace.join(".micode/skills/Deploy.md"),
+            "---\nname: Deploy\ndescription: Ship with the repo script\n---\n",
+        );
+        write(
+            &workspace.join(".micode/skills/lint/SKILL.md"),
+            "---\n# tooling\ndescription: Run the linters\n  across the repo\n---\n",
+        );
+
+        let listing = list_skills(Some(&home), &workspace);
+        let summary = listing
+            .skills
+            .iter()
+            .map(|skill| {
+                (
+                    skill.id.as_str(),
+                    skill.name.as_str(),
+                    skill.description.as_deref(),
+                    skill.source,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "deploy",
+                    "Deploy",
+                    Some("Ship with the repo script"),
+                    SkillSource::Workspace
+                ),
+                (
+                    "lint",
+                    "lint",
+                    Some("Run the linters"),
+                    SkillSource::Workspace
+                ),
+                (
+                    "review",
+                    "review",
+                    Some("Checks a diff for bugs."),
+                    SkillSource::Global
+                ),
+            ]
+        );
+        assert_eq!(listing.warnings.len(), 1);
+        assert!(listing.warnings[0].contains("broken.md"));
+        assert!(listing.warnings[0].contains("not closed"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn missing_folders_list_nothing() {
+        let root = std::env::temp_dir().join(format!("micode-skills-{}", Uuid::new_v4()));
+        let listing = list_skills(None, &root);
+        assert!(listing.skills.is_empty());
+        assert!(listing.warnings.is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::types::errors::{CommandError, ErrorCode, COMMAND_NOT_AVAILABLE};
+
+/// Last `availableCommands` list the agent sent in an `available_commands_update`.
+#[derive(Debug, Default)]
+pub(crate) struct AvailableCommands {
+    names: Vec<String>,
+}
+
+impl AvailableCommands {
+    /// Replaces the cached names with the ones in `update`; entries without a name are
+    /// skipped.
+    pub(crate) fn update(&mut self, update: &Value) {
+        self.names = update
+            .get("availableCommands")
+            .and_then(Value::as_array)
+            .map(|commands| {
+                commands
+                    .iter()
+                    .filter_map(|command| command.get("name").and_then(Value::as_str))
+                    .map(|name| name.trim().trim_start_matches('/').to_string())
+                    .filter(|name| !name.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+
+    /// The cached name matching `name`, which may start with `/`.
+    pub(crate) fn resolve(&self, name: &str) -> Result<String, CommandNotAvailable> {
+        let name = name.trim().trim_start_matches('/');
+        self.names
+            .iter()
+            .find(|known| known.as_str() == name)
+            .cloned()
+            .ok_or_else(|| CommandNotAvailable {
+                name: name.to_string(),
+                known: self.names.clone(),
+            })
+    }
+}
+
+/// A slash command the agent did not advertise; the message lists the ones it did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CommandNotAvailable {
+    pub(crate) name: String,
+    pub(crate) known: Vec<String>,
+}
+
+impl fmt::Display for CommandNotAvailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{COMMAND_NOT_AVAILABLE}: /{}", self.name)?;
+        if self.known.is_empty() {
+            write!(f, " (the agent has not listed any commands yet)")
+        } else {
+            let known = self
+                .known
+                .iter()
+                .map(|name| format!("/{name}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            write!(f, " (available: {known})")
+        }
+    }
+}
+
+impl From<CommandNotAvailable> for CommandError {
+    fn from(error: CommandNotAvailable) -> Self {
+        Self::new(ErrorCode::CommandNotAvailable, error.to_string())
+    }
+}
+
+/// The prompt text that runs `name`: `/name` followed by the trimmed arguments.
+pub(crate) fn slash_command_prompt(name: &str, args: Option<&str>) -> String {
+    match args.map(str::trim).filter(|args| !args.is_empty()) {
+        Some(args) => format!("/{name} {args}"),
+        None => format!("/{name}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slash_command_prompt, AvailableCommands, CommandNotAvailable};
+    use crate::types::errors::{CommandError, ErrorCode};
+    use serde_json::json;
+
+    #[test]
+    fn updates_replace_the_cached_commands() {
+        let mut commands = AvailableCommands::default();
+        commands.update(&json!({
+            "sessionUpdate": "available_commands_update",
+            "availableCommands": [
+                { "name": "review", "description": "Review the diff" },
+                { "name": "/init" },
+                { "description": "no name" }
+            ]
+        }));
+        assert_eq!(commands.resolve("/review").as_deref(), Ok("review"));
+        assert_eq!(commands.resolve(" init ").as_deref(), Ok("init"));
+
+        commands.update(&json!({ "availableCommands": [{ "name": "compact" }] }));
+        assert_eq!(
+            commands.resolve("review"),
+            Err(CommandNotAvailable {
+                name: "review".to_string(),
+                known: vec!["compact".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_commands_list_the_known_names() {
+        let mut commands = AvailableCommands::default();
+        let error = commands.resolve("/deploy").expect_err("nothing cached");
+        assert_eq!(
+            error.to_string(),
+            "slash command not available: /deploy (the agent has not listed any commands yet)"
+        );
+
+        commands.update(&json!({
+            "availableCommands": [{ "name": "review" }, { "name": "init" }]
+        }));
+        let error = CommandError::from(commands.resolve("deploy").expect_err("unknown"));
+        assert_eq!(error.code, ErrorCode::CommandNotAvailable);
+        assert_eq!(
+            error.message,
+            "slash command not available: /deploy (available: /review, /init)"
+        );
+    }
+
+    #[test]
+    fn prompt_appends_trimmed_arguments() {
+        assert_eq!(
+            slash_command_prompt("review", Some("  src/lib.rs ")),
+            "/review src/lib.rs"
+        );
+        assert_eq!(slash_command_prompt("init", Some("  ")), "/init");
+        assert_eq!(slash_command_prompt("init", None), "/init");
+    }
+}
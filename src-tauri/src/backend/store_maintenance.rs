@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::storage::write_file_atomically;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum StoreMaintenanceStatus {
+    Completed,
+    /// A turn started while maintenance was running; remaining files were left untouched.
+    Aborted,
+    /// The workspace was busy when maintenance was requested.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StoreMaintenanceReport {
+    pub(crate) workspace_id: String,
+    pub(crate) status: StoreMaintenanceStatus,
+    pub(crate) started_at: u64,
+    pub(crate) finished_at: u64,
+    pub(crate) files_checked: usize,
+    pub(crate) files_rewritten: usize,
+    pub(crate) corrupted_files: Vec<String>,
+    pub(crate) duplicate_items_removed: usize,
+    pub(crate) session_collisions_repaired: bool,
+    pub(crate) bytes_before: u64,
+    pub(crate) bytes_after: u64,
+}
+
+impl StoreMaintenanceReport {
+    pub(crate) fn new(workspace_id: &str, started_at: u64) -> Self {
+        Self {
+            workspace_id: workspace_id.to_string(),
+            status: StoreMaintenanceStatus::Completed,
+            started_at,
+            finished_at: started_at,
+            files_checked: 0,
+            files_rewritten: 0,
+            corrupted_files: Vec::new(),
+            duplicate_items_removed: 0,
+            session_collisions_repaired: false,
+            bytes_before: 0,
+            bytes_after: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, path: &Path, outcome: FileMaintenanceOutcome) {
+        self.files_checked += 1;
+        match outcome {
+            FileMaintenanceOutcome::Corrupted { bytes } => {
+                self.corrupted_files
+                    .push(path.to_string_lossy().to_string());
+                self.bytes_before += bytes;
+                self.bytes_after += bytes;
+            }
+            FileMaintenanceOutcome::Checked {
+                bytes_before,
+                bytes_after,
+                duplicates_removed,
+                rewritten,
+            } => {
+                self.bytes_before += bytes_before;
+                self.bytes_after += bytes_after;
+                self.duplicate_items_removed += duplicates_removed;
+                if rewritten {
+                    self.files_rewritten += 1;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FileMaintenanceOutcome {
+    /// The file is not valid JSON. It is reported and left as-is so nothing is lost.
+    Corrupted { bytes: u64 },
+    Checked {
+        bytes_before: u64,
+        bytes_after: u64,
+        duplicates_removed: usize,
+        rewritten: bool,
+    },
+}
+
+/// Drops items that share an id with a later item. The last write wins, matching
+/// `upsert_thread_item`, and keeps its position in the list.
+pub(crate) fn dedupe_items_by_id(items: Vec<Value>) -> (Vec<Value>, usize) {
+    let mut seen = HashSet::new();
+    let mut kept = Vec::with_capacity(items.len());
+    let mut removed = 0;
+    for item in items.into_iter().rev() {
+        if let Some(id) = item.get("id").and_then(Value::as_str) {
+            if !seen.insert(id.to_string()) {
+                removed += 1;
+                continue;
+            }
+        }
+        kept.push(item);
+    }
+    kept.reverse();
+    (kept, removed)
+}
+
+/// Verifies a thread-items file, removes duplicate ids and rewrites it in compact form.
+pub(crate) fn maintain_items_file(path: &Path) -> Result<FileMaintenanceOutcome, String> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+    let bytes_before = raw.len() as u64;
+    let Ok(items) = serde_json::from_str::<Vec<Value>>(&raw) else {
+        return Ok(FileMaintenanceOutcome::Corrupted {
+            bytes: bytes_before,
+        });
+    };
+    let (items, duplicates_removed) = dedupe_items_by_id(items);
+    let compact = serde_json::to_string(&items).map_err(|err| err.to_string())?;
+    let rewritten = compact != raw;
+    if rewritten {
+        write_file_atomically(path, &compact)
+            .map_err(|err| format!("Failed to write {}: {err}", path.display()))?;
+    }
+    Ok(FileMaintenanceOutcome::Checked {
+        bytes_before,
+        bytes_after: compact.len() as u64,
+        duplicates_removed,
+        rewritten,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dedupe_items_by_id, maintain_items_file, FileMaintenanceOutcome};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[test]
+    fn dedupe_keeps_the_last_write_for_each_id() {
+        let items = vec![
+            json!({ "id": "user-t1-a", "text": "hi" }),
+            json!({ "id": "agent-t1-a", "text": "partial" }),
+            json!({ "id": "agent-t1-a", "text": "final" }),
+            json!({ "type": "noId" }),
+        ];
+        let (kept, removed) = dedupe_items_by_id(items);
+        assert_eq!(removed, 1);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[1]["text"], "final");
+        assert_eq!(kept[2]["type"], "noId");
+    }
+
+    #[test]
+    fn compacts_valid_files_and_leaves_corrupted_ones_alone() {
+        let dir = std::env::temp_dir().join(format!("micode-maintenance-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let valid = dir.join("t1.json");
+        let items = json!([{ "id": "a" }, { "id": "a", "done": true }]);
+        std::fs::write(&valid, serde_json::to_string_pretty(&items).unwrap()).expect("write");
+        let outcome = maintain_items_file(&valid).expect("maintain");
+        match outcome {
+            FileMaintenanceOutcome::Checked {
+                bytes_before,
+                bytes_after,
+                duplicates_removed,
+                rewritten,
+            } => {
+                assert!(rewritten);
+                assert_eq!(duplicates_removed, 1);
+                assert!(bytes_after < bytes_before);
+            }
+            other => panic!("unexpected outcome: {other:?}"),
+        }
+        assert_eq!(
+            std::fs::read_to_string(&valid).unwrap(),
+            r#"[{"done":true,"id":"a"}]"#
+        );
+
+        let corrupted = dir.join("t2.json");
+        std::fs::write(&corrupted, "[{\"id\":").expect("write");
+        assert_eq!(
+            maintain_items_file(&corrupted).expect("maintain"),
+            FileMaintenanceOutcome::Corrupted { bytes: 7 }
+        );
+        assert_eq!(std::fs::read_to_string(&corrupted).unwrap(), "[{\"id\":");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
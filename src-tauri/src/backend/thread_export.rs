@@ -0,0 +1,262 @@
+use std::path::Path;
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::backend::transcript::{
+    render_transcript, transcript_entry, TranscriptEntry, TranscriptFormat,
+};
+
+/// Bumped whenever the JSON export changes shape in a way readers must know about.
+pub(crate) const THREAD_EXPORT_VERSION: u32 = 1;
+
+/// Output shapes for `export_thread`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ThreadExportFormat {
+    Markdown,
+    Json,
+}
+
+impl ThreadExportFormat {
+    pub(crate) fn parse(raw: &str) -> Result<Self, String> {
+        match raw.trim() {
+            "" | "markdown" => Ok(Self::Markdown),
+            "json" => Ok(Self::Json),
+            other => Err(format!("unsupported export format: {other}")),
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// The thread record fields an export carries.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExportedThread {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    /// Unix seconds.
+    pub(crate) updated_at: i64,
+    pub(crate) tags: Vec<String>,
+}
+
+/// Where an export went: the file it was written to, or its content when no path was
+/// given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ThreadExport {
+    pub(crate) format: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) content: Option<String>,
+    pub(crate) bytes: u64,
+}
+
+fn render_markdown(thread: &ExportedThread, items: &[Value]) -> Result<String, String> {
+    let title = match thread.name.trim() {
+        "" => "Untitled thread",
+        name => name,
+    };
+    let mut meta = vec![format!("- Thread: `{}`", thread.id)];
+    if let Some(updated) = DateTime::from_timestamp(thread.updated_at, 0) {
+        meta.push(format!(
+            "- Updated: {}",
+            updated.format("%Y-%m-%d %H:%M UTC")
+        ));
+    }
+    if !thread.tags.is_empty() {
+        meta.push(format!("- Tags: {}", thread.tags.join(", ")));
+    }
+    let mut out = format!("# {title}\n\n{}", meta.join("\n"));
+    let transcript = render_transcript(items, None, None, TranscriptFormat::Markdown)?;
+    if !transcript.is_empty() {
+        out.push_str("\n\n");
+        out.push_str(&transcript);
+    }
+    out.push('\n');
+    Ok(out)
+}
+
+/// Renders a thread and its items, in store order, as Markdown or versioned JSON, through
+/// the shared transcript entries. Items with nothing to show, and item types transcripts
+/// do not know, are left out.
+pub(crate) fn render_thread_export(
+    thread: &ExportedThread,
+    items: &[Value],
+    format: ThreadExportFormat,
+) -> Result<String, String> {
+    match format {
+        ThreadExportFormat::Markdown => render_markdown(thread, items),
+        ThreadExportFormat::Json => {
+            let entries: Vec<TranscriptEntry> = items.iter().filter_map(transcript_entry).collect();
+            let document = serde_json::json!({
+                "version": THREAD_EXPORT_VERSION,
+                "thread": thread,
+                "items": entries,
+            });
+            serde_json::to_string_pretty(&document).map_err(|err| err.to_string())
+        }
+    }
+}
+
+/// Writes the export to `path` when one is given, or hands the content back.
+pub(crate) fn deliver_thread_export(
+    content: String,
+    format: ThreadExportFormat,
+    path: Option<&str>,
+) -> Result<ThreadExport, String> {
+    let bytes = content.len() as u64;
+    let path = path.map(str::trim).filter(|path| !path.is_empty());
+    let Some(path) = path else {
+        return Ok(ThreadExport {
+            format: format.name().to_string(),
+            path: None,
+            content: Some(content),
+            bytes,
+        });
+    };
+    let destination = Path::new(path);
+    if let Some(parent) = destination
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    std::fs::write(destination, content).map_err(|err| err.to_string())?;
+    Ok(ThreadExport {
+        format: format.name().to_string(),
+        path: Some(destination.display().to_string()),
+        content: None,
+        bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn thread() -> ExportedThread {
+        ExportedThread {
+            id: "thread-1".to_string(),
+            name: "Fix the flaky test".to_string(),
+            updated_at: 1_700_000_000,
+            tags: vec!["ci".to_string()],
+        }
+    }
+
+    fn items() -> Vec<Value> {
+        vec![
+            json!({
+                "id": "u1", "type": "userMessage", "seq": 1,
+                "content": [{ "type": "text", "text": "Why does `ci` fail?" }],
+            }),
+            json!({
+                "id": "r1", "type": "reasoning", "seq": 2,
+                "summary": "Check the logs", "content": "Look at the runner.\n\nThen retry.",
+            }),
+            json!({
+                "id": "t1", "type": "mcpToolCall", "seq": 3,
+                "server": "shell", "tool": "run", "title": "Run tests", "status": "completed",
+                "arguments": { "command": "cargo test" },
+                "result": "test result: FAILED\n```\nnested fence\n```",
+            }),
+            json!({
+                "id": "t2", "type": "mcpToolCall", "seq": 4,
+                "server": "fs", "tool": "read", "status": "failed",
+                "arguments": { "path": "a.rs" }, "error": "not found",
+            }),
+            json!({ "id": "p1", "type": "plan", "seq": 5, "steps": [] }),
+            json!({ "id": "a1", "type": "agentMessage", "seq": 6, "text": "It was a race." }),
+            json!({ "id": "n1", "type": "systemNote", "seq": 7, "reason": "x", "text": "Turn stopped." }),
+        ]
+    }
+
+    #[test]
+    fn markdown_renders_each_item_in_order_with_fenced_tool_io() {
+        let markdown =
+            render_thread_export(&thread(), &items(), ThreadExportFormat::Markdown).unwrap();
+        assert!(markdown.starts_with(
+            "# Fix the flaky test\n\n- Thread: `thread-1`\n- Updated: 2023-11-14 22:13 UTC\n- Tags: ci\n\n"
+        ));
+        let headings: Vec<&str> = markdown
+            .lines()
+            .filter(|line| line.starts_with('#'))
+            .collect();
+        assert_eq!(
+            headings,
+            vec![
+                "# Fix the flaky test",
+                "## User",
+                "## Reasoning",
+                "## Tool call: Run tests",
+                "## Tool call: fs/read",
+                "## Assistant",
+                "## Note",
+            ]
+        );
+        assert!(markdown.contains("## User\n\nWhy does `ci` fail?\n\n"));
+        assert!(markdown.contains("> Check the logs\n>\n> Look at the runner.\n>\n> Then retry."));
+        assert!(markdown.contains(
+            "- Tool: `shell/run`\n- Status: completed\n\nArguments:\n\n```json\n{\n  \"command\": \"cargo test\"\n}\n```"
+        ));
+        // A result holding its own fence gets a longer one.
+        assert!(markdown
+            .contains("Result:\n\n````text\ntest result: FAILED\n```\nnested fence\n```\n````"));
+        assert!(markdown.contains("- Status: failed\n\nArguments:"));
+        assert!(markdown.contains("Error:\n\n```text\nnot found\n```"));
+        assert!(markdown.ends_with("## Note\n\n> Turn stopped.\n"));
+    }
+
+    #[test]
+    fn json_export_has_a_versioned_stable_schema() {
+        let raw = render_thread_export(&thread(), &items(), ThreadExportFormat::Json).unwrap();
+        let document: Value = serde_json::from_str(&raw).unwrap();
+        assert_eq!(document["version"], THREAD_EXPORT_VERSION);
+        assert_eq!(
+            document["thread"],
+            json!({ "id": "thread-1", "name": "Fix the flaky test", "updatedAt": 1_700_000_000, "tags": ["ci"] })
+        );
+        let types: Vec<&str> = document["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                "user",
+                "reasoning",
+                "toolCall",
+                "toolCall",
+                "assistant",
+                "note"
+            ]
+        );
+        assert_eq!(
+            document["items"][3],
+            json!({
+                "type": "toolCall", "id": "t2", "title": null, "server": "fs", "tool": "read",
+                "status": "failed", "arguments": { "path": "a.rs" }, "result": null,
+                "error": "not found",
+            })
+        );
+
+        assert_eq!(
+            ThreadExportFormat::parse(" json ").unwrap(),
+            ThreadExportFormat::Json
+        );
+        assert!(ThreadExportFormat::parse("html").is_err());
+        let export = deliver_thread_export(raw.clone(), ThreadExportFormat::Json, None).unwrap();
+        assert_eq!(export.content.as_deref(), Some(raw.as_str()));
+        assert_eq!(export.bytes, raw.len() as u64);
+    }
+}
@@ -0,0 +1,224 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::storage::write_file_atomically;
+
+/// Threads one message may reference; each one costs a background summary on a cache miss.
+pub(crate) const MAX_THREAD_REFERENCES: usize = 3;
+/// Summaries are cut to this many characters before they are inlined.
+pub(crate) const MAX_SUMMARY_CHARS: usize = 4_000;
+/// Transcript characters handed to the summarizer; the oldest content is dropped first.
+const MAX_TRANSCRIPT_CHARS: usize = 60_000;
+const REFERENCE_PREFIX: &str = "#thread:";
+
+/// A referenced thread resolved for one message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ThreadReference {
+    pub(crate) thread_id: String,
+    pub(crate) title: String,
+    /// Item count of the referenced thread when the summary was made.
+    pub(crate) item_seq: u64,
+    pub(crate) summary: String,
+}
+
+impl ThreadReference {
+    /// What the persisted user item records about the reference; the summary itself only
+    /// goes to the agent.
+    pub(crate) fn to_item_value(&self) -> Value {
+        json!({
+            "threadId": self.thread_id,
+            "title": self.title,
+            "itemSeq": self.item_seq
+        })
+    }
+}
+
+/// What a message needs from a referenced thread to reuse or generate its summary.
+#[derive(Debug, Clone)]
+pub(crate) struct ThreadReferenceSource {
+    pub(crate) title: String,
+    pub(crate) item_seq: u64,
+    pub(crate) transcript: String,
+    /// Cached summary, only when it was made at the current `item_seq`.
+    pub(crate) cached_summary: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedSummary {
+    item_seq: u64,
+    summary: String,
+    generated_at_ms: u64,
+}
+
+fn is_thread_id_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '-' || ch == '_'
+}
+
+/// Distinct `#thread:<id>` references in `text`, in order of appearance.
+pub(crate) fn parse_thread_references(text: &str) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    let mut rest = text;
+    while let Some(index) = rest.find(REFERENCE_PREFIX) {
+        let preceded_by_word = rest[..index]
+            .chars()
+            .next_back()
+            .is_some_and(|ch| ch.is_alphanumeric());
+        rest = &rest[index + REFERENCE_PREFIX.len()..];
+        let id: String = rest
+            .chars()
+            .take_while(|ch| is_thread_id_char(*ch))
+            .collect();
+        rest = &rest[id.len()..];
+        if preceded_by_word || id.is_empty() || ids.contains(&id) {
+            continue;
+        }
+        ids.push(id);
+    }
+    ids
+}
+
+/// Checks the references of a message against the cap and the thread it is sent to.
+pub(crate) fn validate_thread_references(ids: &[String], thread_id: &str) -> Result<(), String> {
+    if ids.len() > MAX_THREAD_REFERENCES {
+        return Err(format!(
+            "A message can reference at most {MAX_THREAD_REFERENCES} threads."
+        ));
+    }
+    if ids.iter().any(|id| id == thread_id) {
+        return Err("A message cannot reference its own thread.".to_string());
+    }
+    Ok(())
+}
+
+/// Keeps the end of a transcript, which holds the latest decisions.
+pub(crate) fn tail_transcript(transcript: &str) -> String {
+    let count = transcript.chars().count();
+    if count <= MAX_TRANSCRIPT_CHARS {
+        return transcript.to_string();
+    }
+    let tail: String = transcript
+        .chars()
+        .skip(count - MAX_TRANSCRIPT_CHARS)
+        .collect();
+    format!("[earlier messages omitted]\n{tail}")
+}
+
+pub(crate) fn truncate_summary(summary: &str) -> String {
+    let summary = summary.trim();
+    if summary.chars().count() <= MAX_SUMMARY_CHARS {
+        return summary.to_string();
+    }
+    let head: String = summary.chars().take(MAX_SUMMARY_CHARS - 1).collect();
+    format!("{}…", head.trim_end())
+}
+
+pub(crate) fn summary_prompt(title: &str, transcript: &str) -> String {
+    format!(
+        "Summarize the conversation below so another conversation can continue from it. \
+Cover the goal, the decisions made and why, the current state of the work (files and \
+commands that matter) and any open questions. Use short bullet points, stay under {} \
+characters and output only the summary.\n\n\
+Conversation \"{title}\":\n{transcript}",
+        MAX_SUMMARY_CHARS
+    )
+}
+
+/// Appends one delimited block per referenced thread to the prompt sent to the agent.
+pub(crate) fn append_reference_blocks(prompt: &str, references: &[ThreadReference]) -> String {
+    if references.is_empty() {
+        return prompt.to_string();
+    }
+    let mut out = prompt.to_string();
+    for reference in references {
+        out.push_str(&format!(
+            "\n\n<referenced-thread id=\"{}\" title=\"{}\">\n{}\n</referenced-thread>",
+            reference.thread_id,
+            reference.title.replace('"', "'"),
+            reference.summary
+        ));
+    }
+    out
+}
+
+/// The cached summary at `path` when it was generated at `item_seq`.
+pub(crate) fn read_cached_summary(path: &Path, item_seq: u64) -> Option<String> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    let cached: CachedSummary = serde_json::from_str(&raw).ok()?;
+    (cached.item_seq == item_seq).then_some(cached.summary)
+}
+
+pub(crate) fn write_cached_summary(
+    path: &Path,
+    item_seq: u64,
+    summary: &str,
+    now_ms: u64,
+) -> Result<(), String> {
+    let cached = CachedSummary {
+        item_seq,
+        summary: summary.to_string(),
+        generated_at_ms: now_ms,
+    };
+    let raw = serde_json::to_string_pretty(&cached).map_err(|err| err.to_string())?;
+    write_file_atomically(path, &raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn parses_distinct_references_and_enforces_limits() {
+        let text = "Continue from #thread:abc-1 and #thread:def_2, see #thread:abc-1.\n\
+Not a ref: issue#thread:zzz or #thread: alone.";
+        let ids = parse_thread_references(text);
+        assert_eq!(ids, vec!["abc-1".to_string(), "def_2".to_string()]);
+        assert!(validate_thread_references(&ids, "current").is_ok());
+        assert!(validate_thread_references(&ids, "abc-1").is_err());
+
+        let many: Vec<String> = (0..=MAX_THREAD_REFERENCES)
+            .map(|index| format!("t{index}"))
+            .collect();
+        let error = validate_thread_references(&many, "current").unwrap_err();
+        assert!(error.contains("at most 3"));
+    }
+
+    #[test]
+    fn inlines_delimited_blocks_and_caps_summaries() {
+        let reference = ThreadReference {
+            thread_id: "abc".to_string(),
+            title: "Pick a \"queue\"".to_string(),
+            item_seq: 4,
+            summary: truncate_summary(&"x".repeat(MAX_SUMMARY_CHARS + 10)),
+        };
+        assert_eq!(reference.summary.chars().count(), MAX_SUMMARY_CHARS);
+        let prompt = append_reference_blocks("Go on", std::slice::from_ref(&reference));
+        assert!(prompt
+            .starts_with("Go on\n\n<referenced-thread id=\"abc\" title=\"Pick a 'queue'\">\n"));
+        assert!(prompt.ends_with("…\n</referenced-thread>"));
+        assert_eq!(append_reference_blocks("Go on", &[]), "Go on");
+        assert_eq!(
+            reference.to_item_value(),
+            json!({ "threadId": "abc", "title": "Pick a \"queue\"", "itemSeq": 4 })
+        );
+    }
+
+    #[test]
+    fn cached_summaries_are_keyed_by_item_sequence() {
+        let path = std::env::temp_dir()
+            .join(format!("micode-summary-{}", Uuid::new_v4()))
+            .join("abc.json");
+        assert_eq!(read_cached_summary(&path, 3), None);
+        write_cached_summary(&path, 3, "- decided on sqlite", 1).expect("write cache");
+        assert_eq!(
+            read_cached_summary(&path, 3).as_deref(),
+            Some("- decided on sqlite")
+        );
+        assert_eq!(read_cached_summary(&path, 4), None);
+        let _ = std::fs::remove_dir_all(path.parent().expect("cache dir"));
+    }
+}
@@ -0,0 +1,181 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// Characters of context a snippet keeps around a hit, the hit included.
+const SNIPPET_CHARS: usize = 200;
+const ELLIPSIS: &str = "…";
+pub(crate) const DEFAULT_SEARCH_LIMIT: usize = 50;
+pub(crate) const MAX_SEARCH_LIMIT: usize = 500;
+
+/// One matching item of a thread. A hit on the thread name alone has no `item_id`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ThreadSearchHit {
+    pub(crate) thread_id: String,
+    pub(crate) thread_name: String,
+    pub(crate) item_id: Option<String>,
+    pub(crate) snippet: String,
+    pub(crate) updated_at: i64,
+}
+
+/// `limit` clamped to `1..=MAX_SEARCH_LIMIT`, `DEFAULT_SEARCH_LIMIT` when missing.
+pub(crate) fn search_limit(limit: Option<usize>) -> usize {
+    limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT)
+}
+
+/// Lowercased query with its whitespace collapsed; `None` when nothing is left to find.
+pub(crate) fn normalize_query(query: &str) -> Option<String> {
+    let query = collapse_whitespace(query).to_lowercase();
+    (!query.is_empty()).then_some(query)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The searchable text of a persisted item: message text, user content parts, reasoning
+/// and tool output.
+pub(crate) fn item_search_text(item: &Value) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for key in ["text", "summary", "command", "aggregatedOutput"] {
+        if let Some(text) = item.get(key).and_then(Value::as_str) {
+            parts.push(text);
+        }
+    }
+    match item.get("content") {
+        Some(Value::String(text)) => parts.push(text),
+        Some(Value::Array(content)) => parts.extend(
+            content
+                .iter()
+                .filter_map(|part| part.get("text").and_then(Value::as_str)),
+        ),
+        _ => {}
+    }
+    parts.join("\n")
+}
+
+/// Byte range in `text` of the first case-insensitive occurrence of `query`, which is
+/// already lowercased. Lowercasing may change byte lengths, so every lowered byte maps
+/// back to the char it came from.
+fn find_case_insensitive(text: &str, query: &str) -> Option<(usize, usize)> {
+    let mut lowered = String::with_capacity(text.len());
+    let mut origins: Vec<usize> = Vec::with_capacity(text.len());
+    for (index, ch) in text.char_indices() {
+        for lower in ch.to_lowercase() {
+            lowered.push(lower);
+            origins.extend(std::iter::repeat_n(index, lower.len_utf8()));
+        }
+    }
+    let start = lowered.find(query)?;
+    let last = origins[start + query.len() - 1];
+    let end = last + text[last..].chars().next().map_or(0, char::len_utf8);
+    Some((origins[start], end))
+}
+
+/// Up to `SNIPPET_CHARS` characters of `text` centred on `start..end`, with an ellipsis on
+/// each cut side. A hit longer than the snippet is cut at its end.
+fn snippet_around(text: &str, start: usize, end: usize) -> String {
+    let hit_chars = text[start..end].chars().count();
+    let room = SNIPPET_CHARS.saturating_sub(hit_chars);
+    let before_available = text[..start].chars().count();
+    let after_available = text[end..].chars().count();
+    let mut before = (room / 2).min(before_available);
+    let after = (room - before).min(after_available);
+    // Context the end of the text cannot use goes before the hit.
+    before = (room - after).min(before_available);
+
+    let from = text[..start]
+        .char_indices()
+        .nth(before_available - before)
+        .map_or(start, |(index, _)| index);
+    let to = text[end..]
+        .char_indices()
+        .nth(after)
+        .map_or(text.len(), |(index, _)| end + index);
+    let mut snippet: String = text[from..to].chars().take(SNIPPET_CHARS).collect();
+    if from > 0 {
+        snippet.insert_str(0, ELLIPSIS);
+    }
+    if to < text.len() || hit_chars > SNIPPET_CHARS {
+        snippet.push_str(ELLIPSIS);
+    }
+    snippet
+}
+
+/// Snippet of the first occurrence of `query` in `text`, whitespace collapsed.
+pub(crate) fn match_snippet(text: &str, query: &str) -> Option<String> {
+    let text = collapse_whitespace(text);
+    let (start, end) = find_case_insensitive(&text, query)?;
+    Some(snippet_around(&text, start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn matches_case_insensitively_and_reads_every_text_field() {
+        let query = normalize_query("  Flaky   TEST ").expect("query");
+        assert_eq!(query, "flaky test");
+        assert_eq!(
+            match_snippet("Fix the flaky\n  test in ci", &query).as_deref(),
+            Some("Fix the flaky test in ci")
+        );
+        assert_eq!(match_snippet("nothing here", &query), None);
+        assert_eq!(normalize_query(" \n "), None);
+
+        // Lowercasing `İ` yields two chars; the hit still maps onto the original text.
+        assert_eq!(
+            match_snippet("İstanbul build", "build").as_deref(),
+            Some("İstanbul build")
+        );
+        assert_eq!(find_case_insensitive("xİy", "i\u{307}"), Some((1, 3)));
+
+        let user = json!({
+            "type": "userMessage",
+            "content": [{ "type": "text", "text": "first" }, { "type": "image" }],
+        });
+        assert_eq!(item_search_text(&user), "first");
+        let reasoning = json!({ "type": "reasoning", "summary": "plan", "content": "think" });
+        assert_eq!(item_search_text(&reasoning), "plan\nthink");
+        assert_eq!(search_limit(None), DEFAULT_SEARCH_LIMIT);
+        assert_eq!(search_limit(Some(0)), 1);
+        assert_eq!(search_limit(Some(10_000)), MAX_SEARCH_LIMIT);
+    }
+
+    #[test]
+    fn snippets_keep_about_two_hundred_chars_around_the_hit() {
+        let text = format!("{}needle{}", "a".repeat(500), "b".repeat(500));
+        let snippet = match_snippet(&text, "needle").expect("hit");
+        assert!(snippet.starts_with(ELLIPSIS) && snippet.ends_with(ELLIPSIS));
+        let inner = snippet.trim_matches('…');
+        assert_eq!(inner.chars().count(), SNIPPET_CHARS);
+        assert_eq!(inner, format!("{}needle{}", "a".repeat(97), "b".repeat(97)));
+
+        // Near the start, the unused context moves after the hit.
+        let text = format!("ab needle {}", "c".repeat(400));
+        let snippet = match_snippet(&text, "needle").expect("hit");
+        assert!(snippet.starts_with("ab needle"));
+        assert_eq!(snippet.trim_end_matches('…').chars().count(), SNIPPET_CHARS);
+
+        // Near the end, it moves before it; short texts come back whole.
+        let text = format!("{} needle", "d".repeat(400));
+        let snippet = match_snippet(&text, "needle").expect("hit");
+        assert!(snippet.starts_with(ELLIPSIS) && snippet.ends_with("needle"));
+        assert_eq!(
+            snippet.trim_start_matches('…').chars().count(),
+            SNIPPET_CHARS
+        );
+        assert_eq!(
+            match_snippet("a needle", "needle").as_deref(),
+            Some("a needle")
+        );
+
+        let long_hit = "x".repeat(300);
+        let snippet = match_snippet(&long_hit, &long_hit).expect("hit");
+        assert_eq!(snippet.chars().count(), SNIPPET_CHARS + 1);
+    }
+}
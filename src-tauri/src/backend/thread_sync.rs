@@ -0,0 +1,298 @@
+use std::path::Path;
+
+use chrono::DateTime;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CliRole {
+    User,
+    Assistant,
+}
+
+/// A user or assistant message recorded in a MiCode CLI chat file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CliMessage {
+    /// Position in the chat file's `messages` array, used for stable imported item ids.
+    pub(crate) index: usize,
+    pub(crate) role: CliRole,
+    pub(crate) text: String,
+    pub(crate) timestamp_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum SyncStatus {
+    InSync,
+    CliAhead,
+    MonitorAhead,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ThreadSyncReport {
+    pub(crate) status: SyncStatus,
+    pub(crate) monitor_message_count: usize,
+    pub(crate) cli_message_count: usize,
+    /// CLI messages the monitor does not have yet (`cliAhead`) or user turns the CLI
+    /// does not have (`monitorAhead`).
+    pub(crate) ahead_by: usize,
+    pub(crate) monitor_last_message_at: Option<i64>,
+    pub(crate) cli_last_message_at: Option<i64>,
+    /// Index into the CLI messages of the first message to import.
+    #[serde(skip)]
+    pub(crate) first_missing: Option<usize>,
+}
+
+fn message_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| {
+                part.as_str()
+                    .or_else(|| part.get("text").and_then(Value::as_str))
+            })
+            .collect::<Vec<_>>()
+            .join(""),
+        Some(value) => value
+            .get("text")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        None => String::new(),
+    }
+}
+
+fn read_timestamp_ms(message: &Value) -> Option<i64> {
+    let raw = message.get("timestamp")?;
+    if let Some(text) = raw.as_str() {
+        return DateTime::parse_from_rfc3339(text)
+            .map(|value| value.timestamp_millis())
+            .ok();
+    }
+    let numeric = raw.as_i64()?;
+    // Chat files written by older CLI versions store seconds.
+    if numeric > 0 && numeric < 1_000_000_000_000 {
+        return Some(numeric * 1000);
+    }
+    Some(numeric)
+}
+
+/// User and assistant messages with text from a chat file. Tool-only assistant turns
+/// carry no text and are skipped, like the monitor skips them in its own history.
+pub(crate) fn read_cli_messages(path: &Path) -> Option<Vec<CliMessage>> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    let parsed: Value = serde_json::from_str(&raw).ok()?;
+    let messages = parsed.get("messages")?.as_array()?;
+    Some(
+        messages
+            .iter()
+            .enumerate()
+            .filter_map(|(index, message)| {
+                let role = match message.get("type").and_then(Value::as_str)? {
+                    "user" => CliRole::User,
+                    "assistant" | "gemini" | "model" => CliRole::Assistant,
+                    _ => return None,
+                };
+                let text = message_text(message.get("content")).trim().to_string();
+                if text.is_empty() {
+                    return None;
+                }
+                Some(CliMessage {
+                    index,
+                    role,
+                    text,
+                    timestamp_ms: read_timestamp_ms(message),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn monitor_user_text(item: &Value) -> Option<String> {
+    if item.get("type").and_then(Value::as_str) != Some("userMessage") {
+        return None;
+    }
+    let text = message_text(item.get("content"));
+    Some(text.trim().to_string())
+}
+
+fn is_monitor_message(item: &Value) -> bool {
+    matches!(
+        item.get("type").and_then(Value::as_str),
+        Some("userMessage" | "agentMessage")
+    )
+}
+
+/// Compares the monitor's items with the CLI's messages. Both sides are aligned on user
+/// prompts, since agent replies may be split into several segments on either side: the
+/// monitor's last prompt is located in the CLI history, and whatever the CLI recorded
+/// after that prompt's reply is missing from the monitor (and vice versa).
+pub(crate) fn compare_histories(
+    items: &[Value],
+    cli: &[CliMessage],
+    monitor_last_message_at: Option<i64>,
+) -> ThreadSyncReport {
+    let monitor_users: Vec<String> = items.iter().filter_map(monitor_user_text).collect();
+    let cli_user_positions: Vec<usize> = cli
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| message.role == CliRole::User)
+        .map(|(position, _)| position)
+        .collect();
+    let mut report = ThreadSyncReport {
+        status: SyncStatus::InSync,
+        monitor_message_count: items.iter().filter(|item| is_monitor_message(item)).count(),
+        cli_message_count: cli.len(),
+        ahead_by: 0,
+        monitor_last_message_at,
+        cli_last_message_at: cli.iter().rev().find_map(|message| message.timestamp_ms),
+        first_missing: None,
+    };
+
+    let anchor = monitor_users.last().and_then(|last| {
+        cli_user_positions
+            .iter()
+            .rposition(|position| &cli[*position].text == last)
+    });
+    let cli_turns_after = match anchor {
+        Some(anchor) => cli_user_positions.len() - anchor - 1,
+        None if monitor_users.is_empty() => cli_user_positions.len(),
+        None => {
+            // The monitor's last prompt never reached the CLI: find the CLI's last
+            // prompt in the monitor instead.
+            let cli_last = cli_user_positions
+                .last()
+                .map(|position| cli[*position].text.as_str());
+            let monitor_turns_after = match cli_last {
+                Some(text) => monitor_users
+                    .iter()
+                    .rposition(|user| user == text)
+                    .map(|found| monitor_users.len() - found - 1),
+                None => Some(monitor_users.len()),
+            };
+            match monitor_turns_after {
+                Some(ahead) if ahead > 0 => {
+                    report.status = SyncStatus::MonitorAhead;
+                    report.ahead_by = ahead;
+                    return report;
+                }
+                // Unrelated histories: fall back to comparing prompt counts.
+                _ => cli_user_positions.len().saturating_sub(monitor_users.len()),
+            }
+        }
+    };
+    if cli_turns_after == 0 {
+        if cli_user_positions.len() < monitor_users.len() && anchor.is_none() {
+            report.status = SyncStatus::MonitorAhead;
+            report.ahead_by = monitor_users.len() - cli_user_positions.len();
+        }
+        return report;
+    }
+    let first_missing = cli_user_positions[cli_user_positions.len() - cli_turns_after];
+    report.status = SyncStatus::CliAhead;
+    report.ahead_by = cli.len() - first_missing;
+    report.first_missing = Some(first_missing);
+    report
+}
+
+/// Thread items for the CLI messages the monitor is missing, flagged `importedFromCli`.
+/// Ids derive from the chat file position, so importing twice updates in place.
+pub(crate) fn imported_items(
+    thread_id: &str,
+    cli: &[CliMessage],
+    report: &ThreadSyncReport,
+) -> Vec<Value> {
+    let Some(first_missing) = report.first_missing else {
+        return Vec::new();
+    };
+    cli[first_missing..]
+        .iter()
+        .map(|message| match message.role {
+            CliRole::User => json!({
+                "id": format!("cli-user-{thread_id}-{}", message.index),
+                "type": "userMessage",
+                "content": [{ "type": "text", "text": message.text }],
+                "importedFromCli": true
+            }),
+            CliRole::Assistant => json!({
+                "id": format!("cli-agent-{thread_id}-{}", message.index),
+                "type": "agentMessage",
+                "text": message.text,
+                "importedFromCli": true
+            }),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_histories, imported_items, read_cli_messages, SyncStatus};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn user(text: &str) -> serde_json::Value {
+        json!({ "type": "userMessage", "content": [{ "type": "text", "text": text }] })
+    }
+
+    fn agent(text: &str) -> serde_json::Value {
+        json!({ "type": "agentMessage", "text": text })
+    }
+
+    fn write_chat(messages: serde_json::Value) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("micode-sync-{}.json", Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            json!({ "sessionId": "s-1", "messages": messages }).to_string(),
+        )
+        .expect("write chat");
+        path
+    }
+
+    #[test]
+    fn cli_ahead_after_external_turns_and_imports_them() {
+        let path = write_chat(json!([
+            { "type": "user", "content": "fix it" },
+            { "type": "gemini", "content": "done", "timestamp": "2026-01-01T00:00:00Z" },
+            { "type": "user", "content": [{ "text": "now test it" }] },
+            { "type": "gemini", "content": "" },
+            { "type": "gemini", "content": "tests pass" }
+        ]));
+        let cli = read_cli_messages(&path).expect("read chat");
+        let items = vec![user("fix it"), agent("done")];
+
+        let report = compare_histories(&items, &cli, None);
+        assert_eq!(report.status, SyncStatus::CliAhead);
+        assert_eq!(report.ahead_by, 2);
+        assert_eq!(report.cli_message_count, 4);
+        assert_eq!(report.monitor_message_count, 2);
+        let imported = imported_items("t-1", &cli, &report);
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0]["id"], "cli-user-t-1-2");
+        assert_eq!(imported[1]["text"], "tests pass");
+        assert_eq!(imported[1]["importedFromCli"], true);
+
+        let mut synced = items.clone();
+        synced.extend(imported);
+        let report = compare_histories(&synced, &cli, None);
+        assert_eq!(report.status, SyncStatus::InSync);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn monitor_ahead_when_cli_missed_latest_prompt() {
+        let path = write_chat(json!([
+            { "type": "user", "content": "fix it" },
+            { "type": "gemini", "content": "done" }
+        ]));
+        let cli = read_cli_messages(&path).expect("read chat");
+        let items = vec![user("fix it"), agent("done"), user("and docs")];
+
+        let report = compare_histories(&items, &cli, None);
+        assert_eq!(report.status, SyncStatus::MonitorAhead);
+        assert_eq!(report.ahead_by, 1);
+        assert!(imported_items("t-1", &cli, &report).is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+}
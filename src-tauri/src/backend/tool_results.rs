@@ -0,0 +1,110 @@
+use serde_json::{json, Value};
+
+/// Content block types that carry more than display text, such as edit diffs.
+fn is_structured_content(item: &Value) -> bool {
+    matches!(
+        item.get("type").and_then(Value::as_str),
+        Some("diff" | "terminal" | "resource" | "resource_link")
+    )
+}
+
+/// The structured output of a tool call: `result` or `rawOutput` when they are objects or
+/// arrays, else a `content` array holding diffs or other non-text blocks.
+pub(crate) fn structured_tool_result(update: &Value) -> Option<Value> {
+    let structured = |value: &&Value| value.is_object() || value.is_array();
+    update
+        .get("result")
+        .filter(structured)
+        .or_else(|| update.get("rawOutput").filter(structured))
+        .or_else(|| {
+            update.get("content").filter(|content| {
+                content
+                    .as_array()
+                    .is_some_and(|items| items.iter().any(is_structured_content))
+            })
+        })
+        .cloned()
+}
+
+/// Cuts `text` to at most `limit` bytes on a char boundary and says how much was dropped.
+pub(crate) fn truncate_tool_result_text(text: String, limit: usize) -> String {
+    if text.len() <= limit {
+        return text;
+    }
+    let mut end = limit;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n… [truncated {} bytes]", &text[..end], text.len() - end)
+}
+
+/// Replaces a result whose JSON is larger than `limit` bytes with a marker object holding
+/// the start of that JSON.
+pub(crate) fn truncate_tool_result_json(value: Value, limit: usize) -> Value {
+    let serialized = value.to_string();
+    if serialized.len() <= limit {
+        return value;
+    }
+    let original_bytes = serialized.len();
+    let mut end = limit;
+    while !serialized.is_char_boundary(end) {
+        end -= 1;
+    }
+    json!({
+        "truncated": true,
+        "originalBytes": original_bytes,
+        "preview": &serialized[..end],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{structured_tool_result, truncate_tool_result_json, truncate_tool_result_text};
+    use serde_json::json;
+
+    #[test]
+    fn picks_object_results_and_diff_content() {
+        let update = json!({ "result": "done", "rawOutput": { "stdout": "ok", "exitCode": 0 } });
+        assert_eq!(
+            structured_tool_result(&update),
+            Some(json!({ "stdout": "ok", "exitCode": 0 }))
+        );
+
+        let diff = json!({
+            "content": [
+                { "type": "content", "content": { "type": "text", "text": "edited" } },
+                { "type": "diff", "path": "src/lib.rs", "oldText": "a", "newText": "b" }
+            ]
+        });
+        assert_eq!(structured_tool_result(&diff), diff.get("content").cloned());
+
+        let text_only = json!({
+            "result": "done",
+            "content": [{ "type": "content", "content": { "type": "text", "text": "done" } }]
+        });
+        assert_eq!(structured_tool_result(&text_only), None);
+    }
+
+    #[test]
+    fn oversized_results_keep_a_marked_prefix() {
+        assert_eq!(truncate_tool_result_text("short".to_string(), 16), "short");
+        assert_eq!(
+            truncate_tool_result_text("ééé".to_string(), 3),
+            "é\n… [truncated 4 bytes]"
+        );
+
+        let small = json!({ "files": ["a.rs"] });
+        assert_eq!(truncate_tool_result_json(small.clone(), 64), small);
+        let large = json!({ "files": vec!["src/backend/app_server.rs"; 100] });
+        let truncated = truncate_tool_result_json(large.clone(), 32);
+        assert_eq!(truncated["truncated"], true);
+        assert_eq!(
+            truncated["originalBytes"].as_u64(),
+            Some(large.to_string().len() as u64)
+        );
+        assert_eq!(
+            truncated["preview"].as_str(),
+            Some(&large.to_string()[..32])
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+/// When each running tool call started, for `durationMs` on its completed item and the
+/// `tool/slow` warning. Times are unix milliseconds passed in by the caller.
+#[derive(Debug, Default)]
+pub(crate) struct ToolTimings {
+    running: HashMap<String, ToolTiming>,
+}
+
+#[derive(Debug)]
+struct ToolTiming {
+    thread_id: String,
+    started_at_ms: u64,
+    slow_reported: bool,
+}
+
+/// A tool call still running past the slow threshold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SlowToolCall {
+    pub(crate) tool_call_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) elapsed_ms: u64,
+}
+
+impl ToolTimings {
+    /// Notes the start of a tool call; repeated `tool_call` updates keep the first time.
+    pub(crate) fn start(&mut self, tool_call_id: &str, thread_id: &str, now_ms: u64) {
+        self.running
+            .entry(tool_call_id.to_string())
+            .or_insert_with(|| ToolTiming {
+                thread_id: thread_id.to_string(),
+                started_at_ms: now_ms,
+                slow_reported: false,
+            });
+    }
+
+    /// How long the tool call ran; `None` when its start was not seen.
+    pub(crate) fn finish(&mut self, tool_call_id: &str, now_ms: u64) -> Option<u64> {
+        let timing = self.running.remove(tool_call_id)?;
+        Some(now_ms.saturating_sub(timing.started_at_ms))
+    }
+
+    /// Drops the thread's tool calls once its turn ended without reporting them back.
+    pub(crate) fn forget_thread(&mut self, thread_id: &str) {
+        self.running
+            .retain(|_, timing| timing.thread_id != thread_id);
+    }
+
+    /// Tool calls that have been running for `threshold_ms` or longer; each is reported
+    /// once.
+    pub(crate) fn check_slow(&mut self, now_ms: u64, threshold_ms: u64) -> Vec<SlowToolCall> {
+        let mut slow = self
+            .running
+            .iter_mut()
+            .filter(|(_, timing)| !timing.slow_reported)
+            .filter_map(|(tool_call_id, timing)| {
+                let elapsed_ms = now_ms.saturating_sub(timing.started_at_ms);
+                if elapsed_ms < threshold_ms {
+                    return None;
+                }
+                timing.slow_reported = true;
+                Some(SlowToolCall {
+                    tool_call_id: tool_call_id.clone(),
+                    thread_id: timing.thread_id.clone(),
+                    elapsed_ms,
+                })
+            })
+            .collect::<Vec<_>>();
+        slow.sort_by(|a, b| a.tool_call_id.cmp(&b.tool_call_id));
+        slow
+    }
+}
+
+pub(crate) fn tool_slow_params(
+    slow: &SlowToolCall,
+    tool: Option<&str>,
+    threshold_ms: u64,
+) -> Value {
+    json!({
+        "threadId": slow.thread_id,
+        "toolCallId": slow.tool_call_id,
+        "itemId": format!("tool-{}", slow.tool_call_id),
+        "tool": tool,
+        "elapsedMs": slow.elapsed_ms,
+        "thresholdMs": threshold_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tool_slow_params, SlowToolCall, ToolTimings};
+    use serde_json::json;
+
+    #[test]
+    fn slow_tools_are_reported_once_and_finish_with_their_duration() {
+        let mut timings = ToolTimings::default();
+        timings.start("call-1", "thread-1", 1_000);
+        timings.start("call-1", "thread-1", 5_000);
+        timings.start("call-2", "thread-1", 30_000);
+
+        assert!(timings.check_slow(60_000, 60_000).is_empty());
+        let slow = timings.check_slow(61_000, 60_000);
+        assert_eq!(
+            slow,
+            vec![SlowToolCall {
+                tool_call_id: "call-1".to_string(),
+                thread_id: "thread-1".to_string(),
+                elapsed_ms: 60_000,
+            }]
+        );
+        assert_eq!(
+            tool_slow_params(&slow[0], Some("execute"), 60_000),
+            json!({
+                "threadId": "thread-1",
+                "toolCallId": "call-1",
+                "itemId": "tool-call-1",
+                "tool": "execute",
+                "elapsedMs": 60_000,
+                "thresholdMs": 60_000,
+            })
+        );
+        assert!(timings.check_slow(80_000, 60_000).is_empty());
+
+        assert_eq!(timings.finish("call-1", 71_500), Some(70_500));
+        assert_eq!(timings.finish("call-1", 72_000), None);
+        timings.forget_thread("thread-1");
+        assert_eq!(timings.finish("call-2", 90_000), None);
+    }
+}
@@ -0,0 +1,495 @@
+use std::fmt::Write as _;
+
+use git2::Patch;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Output shapes for `copy_thread_range`; the thread export's Markdown is `Markdown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TranscriptFormat {
+    Markdown,
+    Plain,
+    CodeOnly,
+}
+
+impl TranscriptFormat {
+    pub(crate) fn parse(raw: Option<&str>) -> Result<Self, String> {
+        match raw.map(str::trim).unwrap_or("markdown") {
+            "" | "markdown" => Ok(Self::Markdown),
+            "plain" => Ok(Self::Plain),
+            "code-only" => Ok(Self::CodeOnly),
+            other => Err(format!("unsupported transcript format: {other}")),
+        }
+    }
+}
+
+fn item_id(item: &Value) -> Option<&str> {
+    item.get("id").and_then(Value::as_str)
+}
+
+/// One stored item as every transcript shape and the thread export see it. Only these
+/// fields are read, whatever else the stored item carries, so the JSON export's schema
+/// stays put when the store's item shapes grow.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum TranscriptEntry {
+    User {
+        id: Option<String>,
+        text: String,
+    },
+    Assistant {
+        id: Option<String>,
+        text: String,
+    },
+    Reasoning {
+        id: Option<String>,
+        summary: Option<String>,
+        text: Option<String>,
+    },
+    ToolCall {
+        id: Option<String>,
+        title: Option<String>,
+        server: Option<String>,
+        tool: Option<String>,
+        status: Option<String>,
+        arguments: Value,
+        result: Value,
+        error: Option<String>,
+    },
+    Note {
+        id: Option<String>,
+        text: String,
+    },
+}
+
+fn string_field(item: &Value, key: &str) -> Option<String> {
+    item.get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// Text of a field that is either a string or an array of `{ "text" }` parts.
+fn text_parts(value: Option<&Value>) -> Option<String> {
+    let text = match value? {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => return None,
+    };
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// The entry of a stored item; `None` for items with nothing to show and item types
+/// transcripts do not know.
+pub(crate) fn transcript_entry(item: &Value) -> Option<TranscriptEntry> {
+    let id = string_field(item, "id");
+    let entry = match item.get("type").and_then(Value::as_str)? {
+        "userMessage" => TranscriptEntry::User {
+            id,
+            text: text_parts(item.get("content")).unwrap_or_default(),
+        },
+        "agentMessage" => TranscriptEntry::Assistant {
+            id,
+            text: string_field(item, "text")?,
+        },
+        "reasoning" => {
+            let summary = text_parts(item.get("summary"));
+            let text = text_parts(item.get("content"));
+            if summary.is_none() && text.is_none() {
+                return None;
+            }
+            TranscriptEntry::Reasoning { id, summary, text }
+        }
+        "mcpToolCall" => TranscriptEntry::ToolCall {
+            id,
+            title: string_field(item, "summaryText").or_else(|| string_field(item, "title")),
+            server: string_field(item, "server"),
+            tool: string_field(item, "tool"),
+            status: string_field(item, "status"),
+            arguments: item.get("arguments").cloned().unwrap_or(Value::Null),
+            result: item.get("result").cloned().unwrap_or(Value::Null),
+            error: string_field(item, "error"),
+        },
+        "systemNote" => TranscriptEntry::Note {
+            id,
+            text: string_field(item, "text")?,
+        },
+        _ => return None,
+    };
+    Some(entry)
+}
+
+/// Unified diffs for the `{ "type": "diff", "path", "oldText", "newText" }` blocks a tool
+/// call carries in its arguments.
+fn tool_diffs(arguments: &Value) -> Vec<String> {
+    let blocks: Vec<&Value> = match arguments {
+        Value::Array(values) => values.iter().collect(),
+        Value::Null => Vec::new(),
+        value => vec![value],
+    };
+    blocks
+        .into_iter()
+        .filter_map(|block| {
+            let path = block.get("path")?.as_str()?;
+            let new_text = block.get("newText")?.as_str()?;
+            let old_text = block.get("oldText").and_then(Value::as_str).unwrap_or("");
+            let mut patch = Patch::from_buffers(
+                old_text.as_bytes(),
+                Some(std::path::Path::new(path)),
+                new_text.as_bytes(),
+                Some(std::path::Path::new(path)),
+                None,
+            )
+            .ok()?;
+            let buf = patch.to_buf().ok()?;
+            let diff = buf.as_str()?.trim_end().to_string();
+            (!diff.is_empty()).then_some(diff)
+        })
+        .collect()
+}
+
+/// Contents of the fenced code blocks in a markdown message, fences included.
+fn fenced_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        let is_fence = line.trim_start().starts_with("```");
+        match current.as_mut() {
+            None if is_fence => current = Some(format!("{line}\n")),
+            None => {}
+            Some(block) => {
+                block.push_str(line);
+                block.push('\n');
+                if is_fence {
+                    blocks.push(current.take().unwrap_or_default().trim_end().to_string());
+                }
+            }
+        }
+    }
+    if let Some(block) = current {
+        // Unterminated fence: keep what streamed in and close it.
+        blocks.push(format!("{}\n```", block.trim_end()));
+    }
+    blocks
+}
+
+fn strip_inline_markdown(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('[') {
+        let Some(close) = rest[start..].find("](").map(|offset| start + offset) else {
+            break;
+        };
+        let Some(end) = rest[close..].find(')').map(|offset| close + offset) else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let label = &rest[start + 1..close];
+        let url = &rest[close + 2..end];
+        let _ = write!(out, "{label} ({url})");
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out.replace("**", "").replace("__", "").replace('`', "")
+}
+
+/// Markdown flattened to readable text: fences and heading markers dropped, links spelled
+/// out, emphasis and inline code markers removed. Code block contents are kept verbatim.
+fn strip_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_code = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            out.push_str(line);
+        } else {
+            let trimmed = line.trim_start();
+            let unheaded = trimmed.trim_start_matches('#');
+            let line = if unheaded.len() != trimmed.len() && unheaded.starts_with(' ') {
+                unheaded.trim_start()
+            } else {
+                line
+            };
+            out.push_str(&strip_inline_markdown(line));
+        }
+        out.push('\n');
+    }
+    out.trim_end().to_string()
+}
+
+/// A fence longer than any backtick run in `content`, so the block cannot be closed early.
+fn fence_for(content: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for ch in content.chars() {
+        if ch == '`' {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+    }
+    "`".repeat((longest + 1).max(3))
+}
+
+fn fenced(info: &str, content: &str) -> String {
+    let fence = fence_for(content);
+    format!("{fence}{info}\n{}\n{fence}", content.trim_end_matches('\n'))
+}
+
+/// Tool arguments and results as a fenced block: strings as text, anything else as
+/// pretty-printed JSON. `None` for an empty value.
+fn fenced_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(text) if text.trim().is_empty() => None,
+        Value::String(text) => Some(fenced("text", text)),
+        other => Some(fenced(
+            "json",
+            &serde_json::to_string_pretty(other).unwrap_or_default(),
+        )),
+    }
+}
+
+fn blockquote(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A tool call in Markdown: its name and status, then the diffs it made, or its
+/// arguments when it made none, its result and its error.
+fn markdown_tool_call(entry: &TranscriptEntry) -> String {
+    let TranscriptEntry::ToolCall {
+        title,
+        server,
+        tool,
+        status,
+        arguments,
+        result,
+        error,
+        ..
+    } = entry
+    else {
+        return String::new();
+    };
+    let name = match (server, tool) {
+        (Some(server), Some(tool)) => Some(format!("{server}/{tool}")),
+        (None, Some(tool)) => Some(tool.clone()),
+        _ => None,
+    };
+    let heading = title.as_ref().or(name.as_ref()).map_or("", String::as_str);
+    let mut sections = vec![format!("## Tool call: {heading}").trim_end().to_string()];
+    let details: Vec<String> = [
+        name.filter(|name| title.as_ref().is_some_and(|title| title != name))
+            .map(|name| format!("- Tool: `{name}`")),
+        status.as_ref().map(|status| format!("- Status: {status}")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !details.is_empty() {
+        sections.push(details.join("\n"));
+    }
+    let diffs = tool_diffs(arguments);
+    if diffs.is_empty() {
+        if let Some(block) = fenced_value(arguments) {
+            sections.push(format!("Arguments:\n\n{block}"));
+        }
+    }
+    sections.extend(diffs.iter().map(|diff| fenced("diff", diff)));
+    if let Some(block) = fenced_value(result) {
+        sections.push(format!("Result:\n\n{block}"));
+    }
+    if let Some(error) = error {
+        sections.push(format!("Error:\n\n{}", fenced("text", error)));
+    }
+    sections.join("\n\n")
+}
+
+fn push_section(out: &mut String, section: &str) {
+    if section.trim().is_empty() {
+        return;
+    }
+    if !out.is_empty() {
+        out.push_str("\n\n");
+    }
+    out.push_str(section.trim_end());
+}
+
+fn render_entry(out: &mut String, entry: &TranscriptEntry, format: TranscriptFormat) {
+    match (entry, format) {
+        (TranscriptEntry::User { text, .. }, TranscriptFormat::Markdown) => {
+            push_section(out, &format!("## User\n\n{text}"));
+        }
+        (TranscriptEntry::User { text, .. }, TranscriptFormat::Plain) => {
+            push_section(out, &format!("User:\n{}", strip_markdown(text)));
+        }
+        (TranscriptEntry::Assistant { text, .. }, TranscriptFormat::Markdown) => {
+            push_section(out, &format!("## Assistant\n\n{text}"));
+        }
+        (TranscriptEntry::Assistant { text, .. }, TranscriptFormat::Plain) => {
+            push_section(out, &format!("Assistant:\n{}", strip_markdown(text)));
+        }
+        (TranscriptEntry::Assistant { text, .. }, TranscriptFormat::CodeOnly) => {
+            for block in fenced_blocks(text) {
+                push_section(out, &block);
+            }
+        }
+        (TranscriptEntry::Reasoning { summary, text, .. }, TranscriptFormat::Markdown) => {
+            let body: Vec<&str> = [summary.as_deref(), text.as_deref()]
+                .into_iter()
+                .flatten()
+                .collect();
+            push_section(
+                out,
+                &format!("## Reasoning\n\n{}", blockquote(&body.join("\n\n"))),
+            );
+        }
+        (TranscriptEntry::ToolCall { .. }, TranscriptFormat::Markdown) => {
+            push_section(out, &markdown_tool_call(entry));
+        }
+        (
+            TranscriptEntry::ToolCall {
+                title, arguments, ..
+            },
+            TranscriptFormat::Plain,
+        ) => {
+            if let Some(title) = title {
+                push_section(out, &format!("Tool: {title}"));
+            }
+            for diff in tool_diffs(arguments) {
+                push_section(out, &diff);
+            }
+        }
+        (TranscriptEntry::ToolCall { arguments, .. }, TranscriptFormat::CodeOnly) => {
+            for diff in tool_diffs(arguments) {
+                push_section(out, &fenced("diff", &diff));
+            }
+        }
+        (TranscriptEntry::Note { text, .. }, TranscriptFormat::Markdown) => {
+            push_section(out, &format!("## Note\n\n{}", blockquote(text)));
+        }
+        _ => {}
+    }
+}
+
+/// Renders the items from `start_item_id` through `end_item_id` (both inclusive, both
+/// optional) into one buffer, item by item.
+pub(crate) fn render_transcript(
+    items: &[Value],
+    start_item_id: Option<&str>,
+    end_item_id: Option<&str>,
+    format: TranscriptFormat,
+) -> Result<String, String> {
+    let start = match start_item_id {
+        Some(id) => items
+            .iter()
+            .position(|item| item_id(item) == Some(id))
+            .ok_or_else(|| format!("item not found: {id}"))?,
+        None => 0,
+    };
+    let end = match end_item_id {
+        Some(id) => items
+            .iter()
+            .position(|item| item_id(item) == Some(id))
+            .ok_or_else(|| format!("item not found: {id}"))?,
+        None => items.len().saturating_sub(1),
+    };
+    if items.is_empty() || start > end {
+        return Ok(String::new());
+    }
+    let mut out = String::new();
+    for entry in items[start..=end].iter().filter_map(transcript_entry) {
+        render_entry(&mut out, &entry, format);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_transcript, TranscriptFormat};
+    use serde_json::json;
+
+    fn items() -> Vec<serde_json::Value> {
+        vec![
+            json!({
+                "id": "user-1",
+                "type": "userMessage",
+                "content": [{ "type": "text", "text": "Fix **the** bug" }]
+            }),
+            json!({
+                "id": "agent-1",
+                "type": "agentMessage",
+                "text": "## Plan\nSee [docs](https://example.com).\n```rust\nfn main() {}\n```"
+            }),
+            json!({
+                "id": "tool-1",
+                "type": "mcpToolCall",
+                "summaryText": "Edited src/lib.rs",
+                "arguments": [{
+                    "type": "diff",
+                    "path": "src/lib.rs",
+                    "oldText": "a\nb\n",
+                    "newText": "a\nc\n"
+                }]
+            }),
+        ]
+    }
+
+    #[test]
+    fn markdown_keeps_code_blocks_and_renders_tool_diffs() {
+        let text =
+            render_transcript(&items(), None, None, TranscriptFormat::Markdown).expect("render");
+        assert!(text.starts_with("## User\n\nFix **the** bug"));
+        assert!(text.contains("```rust\nfn main() {}\n```"));
+        assert!(text.contains("## Tool call: Edited src/lib.rs"));
+        assert!(text.contains("```diff\n"));
+        assert!(text.contains("-b\n+c"));
+        // The diff stands in for the raw arguments it was made from.
+        assert!(!text.contains("Arguments:"));
+    }
+
+    #[test]
+    fn plain_strips_markdown_and_code_only_keeps_just_code() {
+        let plain = render_transcript(&items(), None, Some("agent-1"), TranscriptFormat::Plain)
+            .expect("render");
+        assert!(plain.contains("User:\nFix the bug"));
+        assert!(plain.contains("Plan\nSee docs (https://example.com).\nfn main() {}"));
+        assert!(!plain.contains("Tool:"));
+
+        let code = render_transcript(&items(), Some("agent-1"), None, TranscriptFormat::CodeOnly)
+            .expect("render");
+        assert!(code.starts_with("```rust\nfn main() {}\n```"));
+        assert!(code.contains("```diff\n"));
+        assert!(!code.contains("Plan"));
+    }
+
+    #[test]
+    fn unknown_range_ids_and_formats_are_rejected() {
+        assert!(
+            render_transcript(&items(), Some("missing"), None, TranscriptFormat::Plain).is_err()
+        );
+        assert!(TranscriptFormat::parse(Some("html")).is_err());
+        assert_eq!(
+            TranscriptFormat::parse(None).expect("default"),
+            TranscriptFormat::Markdown
+        );
+    }
+}
@@ -0,0 +1,406 @@
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use git2::{Repository, Status, StatusOptions};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde_json::{json, Value};
+
+/// Files larger than this are never surfaced as artifacts.
+pub(crate) const MAX_ARTIFACT_BYTES: u64 = 5 * 1024 * 1024;
+
+pub(crate) const DEFAULT_ARTIFACT_GLOBS: &[&str] = &[
+    "**/*.md",
+    "**/*.markdown",
+    "**/*.txt",
+    "**/*.patch",
+    "**/*.diff",
+    "**/*.html",
+    "**/*.csv",
+    "**/*.pdf",
+    "**/*.svg",
+    "**/*.png",
+];
+
+const WRITE_TOOL_NAMES: &[&str] = &[
+    "write",
+    "write_file",
+    "writefile",
+    "create",
+    "create_file",
+    "edit",
+    "multi_edit",
+    "multiedit",
+    "replace",
+    "str_replace",
+    "apply_patch",
+];
+
+pub(crate) const TOOL_PATH_KEYS: &[&str] = &[
+    "file_path",
+    "filePath",
+    "path",
+    "target_file",
+    "targetFile",
+    "filename",
+];
+
+/// State captured when a turn starts so completion can tell new files from old ones.
+#[derive(Debug, Clone)]
+pub(crate) struct TurnArtifactBaseline {
+    pub(crate) started_at: SystemTime,
+    pub(crate) untracked: HashSet<String>,
+    /// Every path git reported as changed (untracked ones included) before the turn.
+    pub(crate) changed: HashSet<String>,
+}
+
+impl TurnArtifactBaseline {
+    pub(crate) fn capture(root: &Path) -> Self {
+        let statuses = changed_path_statuses(root);
+        Self {
+            started_at: SystemTime::now(),
+            untracked: statuses
+                .iter()
+                .filter(|(_, status)| status.contains(Status::WT_NEW))
+                .map(|(path, _)| path.clone())
+                .collect(),
+            changed: statuses.into_iter().map(|(path, _)| path).collect(),
+        }
+    }
+}
+
+/// Paths git reports as changed in the working tree or index, ignored files excluded.
+pub(crate) fn changed_path_statuses(root: &Path) -> Vec<(String, Status)> {
+    let Ok(repo) = Repository::open(root) else {
+        return Vec::new();
+    };
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false);
+    let Ok(statuses) = repo.statuses(Some(&mut options)) else {
+        return Vec::new();
+    };
+    statuses
+        .iter()
+        .filter_map(|entry| Some((entry.path()?.to_string(), entry.status())))
+        .collect()
+}
+
+fn untracked_paths(root: &Path) -> HashSet<String> {
+    changed_path_statuses(root)
+        .into_iter()
+        .filter(|(_, status)| status.contains(Status::WT_NEW))
+        .map(|(path, _)| path)
+        .collect()
+}
+
+fn is_tracked(repo: Option<&Repository>, relative_path: &str) -> bool {
+    let Some(repo) = repo else {
+        return false;
+    };
+    repo.index()
+        .ok()
+        .and_then(|index| index.get_path(Path::new(relative_path), 0))
+        .is_some()
+}
+
+pub(crate) fn build_artifact_matcher(globs: Option<&[String]>) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    let patterns: Vec<String> = match globs {
+        Some(globs) if !globs.is_empty() => globs.to_vec(),
+        _ => DEFAULT_ARTIFACT_GLOBS
+            .iter()
+            .map(|glob| glob.to_string())
+            .collect(),
+    };
+    for pattern in patterns {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            continue;
+        }
+        if let Ok(glob) = Glob::new(pattern) {
+            builder.add(glob);
+        }
+    }
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
+/// Normalizes a tool-supplied path to a `/`-separated path relative to the workspace root.
+/// Returns `None` for paths that escape the root.
+pub(crate) fn relative_to_root(root: &Path, raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return None;
+    }
+    let candidate = Path::new(raw);
+    let relative = if candidate.is_absolute() {
+        candidate.strip_prefix(root).ok()?.to_path_buf()
+    } else {
+        candidate.to_path_buf()
+    };
+    let mut parts: Vec<String> = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+pub(crate) fn is_write_tool(item: &Value) -> bool {
+    let tool = item
+        .get("tool")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    WRITE_TOOL_NAMES.contains(&tool.as_str())
+}
+
+/// Tool call items that belong to the turn, i.e. persisted after the turn's user message
+/// and before the next one.
+pub(crate) fn turn_tool_items<'a>(
+    items: &'a [Value],
+    thread_id: &str,
+    turn_id: &str,
+) -> Vec<&'a Value> {
+    let user_item_id = format!("user-{thread_id}-{turn_id}");
+    let Some(start) = items
+        .iter()
+        .position(|item| item.get("id").and_then(Value::as_str) == Some(&user_item_id))
+    else {
+        return Vec::new();
+    };
+    items[start + 1..]
+        .iter()
+        .take_while(|item| item.get("type").and_then(Value::as_str) != Some("userMessage"))
+        .filter(|item| item.get("type").and_then(Value::as_str) == Some("mcpToolCall"))
+        .collect()
+}
+
+/// Returns `(path, tool item id)` for every write/create tool call that belongs to the
+/// turn.
+pub(crate) fn extract_written_paths(
+    items: &[Value],
+    thread_id: &str,
+    turn_id: &str,
+) -> Vec<(String, String)> {
+    turn_tool_items(items, thread_id, turn_id)
+        .into_iter()
+        .filter(|item| is_write_tool(item))
+        .filter_map(|item| {
+            let tool_item_id = item.get("id").and_then(Value::as_str)?.to_string();
+            let arguments = item.get("arguments")?;
+            let path = TOOL_PATH_KEYS
+                .iter()
+                .find_map(|key| arguments.get(*key).and_then(Value::as_str))?;
+            Some((path.to_string(), tool_item_id))
+        })
+        .collect()
+}
+
+pub(crate) fn guess_mime(path: &str) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|value| value.to_str())
+        .map(|value| value.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "md" | "markdown" => "text/markdown",
+        "txt" | "log" => "text/plain",
+        "patch" | "diff" => "text/x-diff",
+        "html" | "htm" => "text/html",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "pdf" => "application/pdf",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Classifies files created during a turn as artifacts. Candidates come from write/create
+/// tool calls and from files that became untracked in git since the baseline; a candidate
+/// counts only if it is not tracked, was not untracked before the turn, was written after
+/// the turn started, fits under the size limit and matches the workspace artifact globs.
+pub(crate) fn collect_turn_artifacts(
+    root: &Path,
+    items: &[Value],
+    thread_id: &str,
+    turn_id: &str,
+    baseline: &TurnArtifactBaseline,
+    globs: Option<&[String]>,
+) -> Vec<Value> {
+    let matcher = build_artifact_matcher(globs);
+    let repo = Repository::open(root).ok();
+    let mut candidates: Vec<(String, Option<String>)> = Vec::new();
+    for (path, tool_item_id) in extract_written_paths(items, thread_id, turn_id) {
+        if let Some(relative) = relative_to_root(root, &path) {
+            candidates.push((relative, Some(tool_item_id)));
+        }
+    }
+    for path in untracked_paths(root) {
+        if !baseline.untracked.contains(&path) {
+            candidates.push((path, None));
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut artifacts = Vec::new();
+    for (relative, tool_item_id) in candidates {
+        if !seen.insert(relative.clone()) {
+            continue;
+        }
+        if baseline.untracked.contains(&relative)
+            || is_tracked(repo.as_ref(), &relative)
+            || !matcher.is_match(&relative)
+        {
+            continue;
+        }
+        let full_path: PathBuf = root.join(&relative);
+        let Ok(metadata) = std::fs::metadata(&full_path) else {
+            continue;
+        };
+        if !metadata.is_file() || metadata.len() > MAX_ARTIFACT_BYTES {
+            continue;
+        }
+        let written_during_turn = metadata
+            .modified()
+            .map(|modified| modified >= baseline.started_at)
+            .unwrap_or(true);
+        if !written_during_turn {
+            continue;
+        }
+        artifacts.push(json!({
+            "path": relative,
+            "size": metadata.len(),
+            "mime": guess_mime(&relative),
+            "createdByToolId": tool_item_id,
+            "missing": false,
+        }));
+    }
+    artifacts
+}
+
+/// Re-checks every persisted artifact against the file system so deleted files stay
+/// listed but are flagged as missing.
+pub(crate) fn mark_missing_artifacts(root: &Path, items: &mut [Value]) {
+    for item in items.iter_mut() {
+        let Some(artifacts) = item.get_mut("artifacts").and_then(Value::as_array_mut) else {
+            continue;
+        };
+        for artifact in artifacts.iter_mut() {
+            let Some(path) = artifact.get("path").and_then(Value::as_str) else {
+                continue;
+            };
+            let missing = !root.join(path).is_file();
+            if let Some(object) = artifact.as_object_mut() {
+                object.insert("missing".to_string(), Value::Bool(missing));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_artifact_matcher, collect_turn_artifacts, extract_written_paths, guess_mime,
+        mark_missing_artifacts, relative_to_root, TurnArtifactBaseline,
+    };
+    use serde_json::{json, Value};
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+    use uuid::Uuid;
+
+    fn turn_items() -> Vec<Value> {
+        vec![
+            json!({ "id": "user-t1-old", "type": "userMessage" }),
+            json!({
+                "id": "tool-old",
+                "type": "mcpToolCall",
+                "tool": "write",
+                "arguments": { "file_path": "OLD.md" }
+            }),
+            json!({ "id": "user-t1-turn", "type": "userMessage" }),
+            json!({
+                "id": "tool-read",
+                "type": "mcpToolCall",
+                "tool": "read",
+                "arguments": { "path": "src/lib.rs" }
+            }),
+            json!({
+                "id": "tool-write",
+                "type": "mcpToolCall",
+                "tool": "write",
+                "arguments": { "file_path": "REPORT.md" }
+            }),
+        ]
+    }
+
+    #[test]
+    fn extracts_write_paths_for_the_requested_turn_only() {
+        let paths = extract_written_paths(&turn_items(), "t1", "turn");
+        assert_eq!(
+            paths,
+            vec![("REPORT.md".to_string(), "tool-write".to_string())]
+        );
+    }
+
+    #[test]
+    fn matcher_uses_defaults_and_custom_globs() {
+        let defaults = build_artifact_matcher(None);
+        assert!(defaults.is_match("REPORT.md"));
+        assert!(defaults.is_match("out/fix.patch"));
+        assert!(!defaults.is_match("src/main.rs"));
+
+        let custom = build_artifact_matcher(Some(&["reports/**".to_string()]));
+        assert!(custom.is_match("reports/summary.json"));
+        assert!(!custom.is_match("REPORT.md"));
+    }
+
+    #[test]
+    fn relative_paths_reject_escapes() {
+        let root = Path::new("/repo");
+        assert_eq!(
+            relative_to_root(root, "/repo/docs/REPORT.md").as_deref(),
+            Some("docs/REPORT.md")
+        );
+        assert_eq!(relative_to_root(root, "../etc/passwd"), None);
+        assert_eq!(relative_to_root(root, "/elsewhere/x.md"), None);
+        assert_eq!(guess_mime("fix.PATCH"), "text/x-diff");
+    }
+
+    #[test]
+    fn collects_new_files_and_marks_deleted_ones_missing() {
+        let root = std::env::temp_dir().join(format!("micode-artifacts-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create root");
+        let baseline = TurnArtifactBaseline {
+            started_at: SystemTime::now() - Duration::from_secs(5),
+            untracked: Default::default(),
+            changed: Default::default(),
+        };
+        std::fs::write(root.join("REPORT.md"), "# Report").expect("write report");
+        std::fs::write(root.join("main.rs"), "fn main() {}").expect("write source");
+
+        let artifacts = collect_turn_artifacts(&root, &turn_items(), "t1", "turn", &baseline, None);
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0]["path"], "REPORT.md");
+        assert_eq!(artifacts[0]["createdByToolId"], "tool-write");
+        assert_eq!(artifacts[0]["mime"], "text/markdown");
+
+        let mut items = vec![json!({ "id": "agent-t1-turn", "artifacts": artifacts })];
+        std::fs::remove_file(root.join("REPORT.md")).expect("remove report");
+        mark_missing_artifacts(&root, &mut items);
+        assert_eq!(items[0]["artifacts"][0]["missing"], true);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
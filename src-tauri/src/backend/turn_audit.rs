@@ -0,0 +1,340 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::backend::turn_artifacts::{relative_to_root, TOOL_PATH_KEYS};
+
+const READ_TOOL_NAMES: &[&str] = &["read", "read_file", "readfile", "view", "cat", "open_file"];
+
+const SEARCH_TOOL_NAMES: &[&str] = &[
+    "glob",
+    "grep",
+    "search",
+    "search_files",
+    "find",
+    "ls",
+    "list",
+    "list_dir",
+    "list_directory",
+];
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Extra argument keys naming the searched directory of glob/grep-style tools.
+const SEARCH_PATH_KEYS: &[&str] = &["dir_path", "dirPath", "directory", "cwd"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuditRead {
+    /// Workspace-relative path, or the absolute path when it lies outside the workspace.
+    pub(crate) path: String,
+    pub(crate) tool: String,
+    pub(crate) tool_item_id: String,
+    /// `read` for file contents, `search` for glob/grep/list results.
+    pub(crate) kind: String,
+    /// Bytes returned to the agent, or the file size when the tool reported no content.
+    pub(crate) bytes: u64,
+    pub(crate) read_at_ms: u64,
+    pub(crate) outside_workspace: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuditSummary {
+    pub(crate) file_count: usize,
+    pub(crate) total_bytes: u64,
+    /// Paths read outside the workspace root; surfaced so the UI can flag them.
+    pub(crate) outside_workspace: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TurnAudit {
+    pub(crate) thread_id: String,
+    pub(crate) turn_id: String,
+    pub(crate) recorded_at_ms: u64,
+    pub(crate) summary: AuditSummary,
+    pub(crate) reads: Vec<AuditRead>,
+}
+
+fn read_kind(tool: &str) -> Option<&'static str> {
+    let tool = tool.trim().to_ascii_lowercase();
+    if READ_TOOL_NAMES.contains(&tool.as_str()) {
+        Some("read")
+    } else if SEARCH_TOOL_NAMES.contains(&tool.as_str()) {
+        Some("search")
+    } else {
+        None
+    }
+}
+
+fn argument_path<'a>(arguments: &'a Value, kind: &str) -> Option<&'a str> {
+    let extra_keys: &[&str] = if kind == "search" {
+        SEARCH_PATH_KEYS
+    } else {
+        &[]
+    };
+    for key in TOOL_PATH_KEYS.iter().chain(extra_keys) {
+        if let Some(path) = arguments.get(*key).and_then(Value::as_str) {
+            if !path.trim().is_empty() {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+/// Builds the audit record for a completed tool call, or `None` when the tool does not
+/// read files. Searches without an explicit directory are recorded against the root.
+pub(crate) fn audit_read_from_tool(
+    root: &Path,
+    tool_item_id: &str,
+    tool: Option<&str>,
+    arguments: Option<&Value>,
+    result: Option<&str>,
+    read_at_ms: u64,
+) -> Option<AuditRead> {
+    let tool = tool?;
+    let kind = read_kind(tool)?;
+    let raw_path = arguments.and_then(|arguments| argument_path(arguments, kind));
+    let (path, outside_workspace) = match raw_path.map(str::trim) {
+        None | Some(".") | Some("./") => (".".to_string(), false),
+        Some(raw) => match relative_to_root(root, raw) {
+            Some(relative) => (relative, false),
+            None => {
+                let candidate = Path::new(raw);
+                let absolute = if candidate.is_absolute() {
+                    candidate.to_path_buf()
+                } else {
+                    root.join(candidate)
+                };
+                let inside = absolute
+                    .canonicalize()
+                    .ok()
+                    .zip(root.canonicalize().ok())
+                    .and_then(|(absolute, root)| {
+                        absolute
+                            .strip_prefix(&root)
+                            .ok()
+                            .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+                    });
+                match inside {
+                    Some(relative) if !relative.is_empty() => (relative, false),
+                    Some(_) => (".".to_string(), false),
+                    None => (absolute.display().to_string(), true),
+                }
+            }
+        },
+    };
+    let bytes = match result {
+        Some(result) if !result.is_empty() => result.len() as u64,
+        _ if kind == "read" => {
+            let full_path = if outside_workspace {
+                PathBuf::from(&path)
+            } else {
+                root.join(&path)
+            };
+            std::fs::metadata(full_path)
+                .map(|metadata| metadata.len())
+                .unwrap_or(0)
+        }
+        _ => 0,
+    };
+    Some(AuditRead {
+        path,
+        tool: tool.to_string(),
+        tool_item_id: tool_item_id.to_string(),
+        kind: kind.to_string(),
+        bytes,
+        read_at_ms,
+        outside_workspace,
+    })
+}
+
+pub(crate) fn summarize(reads: &[AuditRead]) -> AuditSummary {
+    let mut paths = HashSet::new();
+    let mut outside_workspace = Vec::new();
+    for read in reads {
+        if paths.insert(read.path.as_str()) && read.outside_workspace {
+            outside_workspace.push(read.path.clone());
+        }
+    }
+    AuditSummary {
+        file_count: paths.len(),
+        total_bytes: reads.iter().map(|read| read.bytes).sum(),
+        outside_workspace,
+    }
+}
+
+pub(crate) fn file_name_component(id: &str) -> String {
+    id.chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn audit_dir(workspace_root: &Path) -> PathBuf {
+    workspace_root.join(".micodemonitor").join("audit")
+}
+
+fn audit_file_path(workspace_root: &Path, thread_id: &str, turn_id: &str) -> PathBuf {
+    audit_dir(workspace_root)
+        .join(file_name_component(thread_id))
+        .join(format!("{}.json", file_name_component(turn_id)))
+}
+
+pub(crate) fn write_turn_audit(workspace_root: &Path, audit: &TurnAudit) -> Result<(), String> {
+    let path = audit_file_path(workspace_root, &audit.thread_id, &audit.turn_id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let raw = serde_json::to_string_pretty(audit).map_err(|err| err.to_string())?;
+    std::fs::write(&path, raw).map_err(|err| err.to_string())
+}
+
+pub(crate) fn read_turn_audit(
+    workspace_root: &Path,
+    thread_id: &str,
+    turn_id: &str,
+) -> Result<TurnAudit, String> {
+    let path = audit_file_path(workspace_root, thread_id, turn_id);
+    let raw = std::fs::read_to_string(&path)
+        .map_err(|_| "No audit was recorded for this turn".to_string())?;
+    serde_json::from_str(&raw).map_err(|err| format!("Failed to parse turn audit: {err}"))
+}
+
+/// Deletes audit files older than `retention_days` (0 keeps everything) and drops
+/// thread folders left empty. Returns the number of removed files.
+pub(crate) fn prune_turn_audits(workspace_root: &Path, retention_days: u32) -> usize {
+    if retention_days == 0 {
+        return 0;
+    }
+    let cutoff = SystemTime::now()
+        .checked_sub(Duration::from_secs(
+            u64::from(retention_days) * SECONDS_PER_DAY,
+        ))
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let Ok(thread_dirs) = std::fs::read_dir(audit_dir(workspace_root)) else {
+        return 0;
+    };
+    let mut removed = 0;
+    for thread_dir in thread_dirs.flatten() {
+        let thread_path = thread_dir.path();
+        let Ok(files) = std::fs::read_dir(&thread_path) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let expired = file
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .map(|modified| modified < cutoff)
+                .unwrap_or(false);
+            if expired && std::fs::remove_file(file.path()).is_ok() {
+                removed += 1;
+            }
+        }
+        // Only succeeds once the folder is empty.
+        let _ = std::fs::remove_dir(&thread_path);
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[test]
+    fn records_reads_and_flags_paths_outside_the_workspace() {
+        let root = std::env::temp_dir().join(format!("micode-audit-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).expect("create root");
+        std::fs::write(root.join("src/lib.rs"), "fn main() {}").expect("write source");
+
+        let inside = audit_read_from_tool(
+            &root,
+            "tool-1",
+            Some("read"),
+            Some(&json!({ "file_path": root.join("src/lib.rs").display().to_string() })),
+            None,
+            10,
+        )
+        .expect("read tool");
+        assert_eq!(inside.path, "src/lib.rs");
+        assert_eq!(inside.bytes, 12);
+        assert!(!inside.outside_workspace);
+
+        let outside = audit_read_from_tool(
+            &root,
+            "tool-2",
+            Some("Read"),
+            Some(&json!({ "path": "../secrets.env" })),
+            Some("TOKEN=x"),
+            11,
+        )
+        .expect("read tool");
+        assert!(outside.outside_workspace);
+        assert_eq!(outside.bytes, 7);
+
+        let search = audit_read_from_tool(
+            &root,
+            "tool-3",
+            Some("grep"),
+            Some(&json!({ "pattern": "fn main" })),
+            Some("src/lib.rs:1"),
+            12,
+        )
+        .expect("search tool");
+        assert_eq!(search.path, ".");
+        assert_eq!(search.kind, "search");
+
+        assert!(audit_read_from_tool(&root, "tool-4", Some("write"), None, None, 13).is_none());
+
+        let summary = summarize(&[inside.clone(), inside, outside.clone(), search]);
+        assert_eq!(summary.file_count, 3);
+        assert_eq!(summary.total_bytes, 12 + 12 + 7 + 12);
+        assert_eq!(summary.outside_workspace, vec![outside.path]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn round_trips_audit_files_and_prunes_expired_ones() {
+        let root = std::env::temp_dir().join(format!("micode-audit-{}", Uuid::new_v4()));
+        let audit = TurnAudit {
+            thread_id: "thread/1".to_string(),
+            turn_id: "turn-1".to_string(),
+            recorded_at_ms: 5,
+            summary: AuditSummary::default(),
+            reads: Vec::new(),
+        };
+        write_turn_audit(&root, &audit).expect("write audit");
+        assert_eq!(
+            read_turn_audit(&root, "thread/1", "turn-1").expect("read audit"),
+            audit
+        );
+        assert!(read_turn_audit(&root, "thread/1", "turn-2").is_err());
+
+        assert_eq!(prune_turn_audits(&root, 0), 0);
+        assert_eq!(prune_turn_audits(&root, 30), 0);
+        let file = audit_file_path(&root, "thread/1", "turn-1");
+        let old = SystemTime::now() - Duration::from_secs(40 * SECONDS_PER_DAY);
+        std::fs::File::options()
+            .write(true)
+            .open(&file)
+            .and_then(|handle| handle.set_modified(old))
+            .expect("age audit file");
+        assert_eq!(prune_turn_audits(&root, 30), 1);
+        assert!(!file.parent().expect("thread dir").exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
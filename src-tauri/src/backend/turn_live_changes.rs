@@ -0,0 +1,371 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use git2::{DiffOptions, Patch, Repository, Status};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::backend::turn_artifacts::{
+    changed_path_statuses, is_write_tool, relative_to_root, turn_tool_items, TurnArtifactBaseline,
+    TOOL_PATH_KEYS,
+};
+
+/// The app's own state directory changes during every turn and is never reported.
+const STATE_DIR_PREFIX: &str = ".micodemonitor/";
+
+/// One file the running turn changed so far.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LiveFileChange {
+    /// Workspace-relative path.
+    pub(crate) path: String,
+    pub(crate) additions: u64,
+    pub(crate) deletions: u64,
+    /// Completed tool calls of the turn that wrote the file.
+    pub(crate) tool_item_ids: Vec<String>,
+    /// Changed on disk during the turn without a completed tool call that wrote it, e.g. by
+    /// a shell command or a call that is still running.
+    pub(crate) unobserved: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TurnLiveChanges {
+    pub(crate) thread_id: String,
+    pub(crate) turn_id: String,
+    pub(crate) files: Vec<LiveFileChange>,
+    pub(crate) additions: u64,
+    pub(crate) deletions: u64,
+}
+
+impl TurnLiveChanges {
+    pub(crate) fn new(thread_id: &str, turn_id: &str, files: Vec<LiveFileChange>) -> Self {
+        Self {
+            thread_id: thread_id.to_string(),
+            turn_id: turn_id.to_string(),
+            additions: files.iter().map(|file| file.additions).sum(),
+            deletions: files.iter().map(|file| file.deletions).sum(),
+            files,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ToolChange {
+    additions: u64,
+    deletions: u64,
+    tool_item_ids: Vec<String>,
+}
+
+/// Added and removed lines between two texts, counting lines regardless of order. Exact
+/// enough for a preview and linear in the size of the edit.
+fn line_counts(old_text: &str, new_text: &str) -> (u64, u64) {
+    let mut remaining: HashMap<&str, i64> = HashMap::new();
+    for line in old_text.lines() {
+        *remaining.entry(line).or_default() += 1;
+    }
+    let mut additions = 0;
+    for line in new_text.lines() {
+        match remaining.get_mut(line) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => additions += 1,
+        }
+    }
+    let deletions = remaining.values().map(|count| *count as u64).sum();
+    (additions, deletions)
+}
+
+fn text_arg<'a>(value: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|key| value.get(*key).and_then(Value::as_str))
+}
+
+/// `(path, additions, deletions)` for each file a write tool call describes. ACP edits
+/// arrive as `{ "type": "diff", "path", "oldText", "newText" }` blocks; native tools pass
+/// a path with old/new strings, a list of edits or the whole new content.
+fn tool_edits(arguments: &Value) -> Vec<(String, u64, u64)> {
+    if let Value::Array(blocks) = arguments {
+        return blocks
+            .iter()
+            .filter_map(|block| {
+                let path = block.get("path").and_then(Value::as_str)?;
+                let (additions, deletions) = line_counts(
+                    text_arg(block, &["oldText"]).unwrap_or_default(),
+                    text_arg(block, &["newText"]).unwrap_or_default(),
+                );
+                Some((path.to_string(), additions, deletions))
+            })
+            .collect();
+    }
+    let Some(path) = text_arg(arguments, TOOL_PATH_KEYS) else {
+        return Vec::new();
+    };
+    let edit_counts = |edit: &Value| {
+        line_counts(
+            text_arg(edit, &["old_string", "oldString", "oldText"]).unwrap_or_default(),
+            text_arg(edit, &["new_string", "newString", "newText"]).unwrap_or_default(),
+        )
+    };
+    let (additions, deletions) = match arguments.get("edits").and_then(Value::as_array) {
+        Some(edits) => edits.iter().map(edit_counts).fold((0, 0), |total, counts| {
+            (total.0 + counts.0, total.1 + counts.1)
+        }),
+        None => match text_arg(arguments, &["content", "contents"]) {
+            Some(content) => (content.lines().count() as u64, 0),
+            None => edit_counts(arguments),
+        },
+    };
+    vec![(path.to_string(), additions, deletions)]
+}
+
+fn tool_changes(
+    root: &Path,
+    items: &[Value],
+    thread_id: &str,
+    turn_id: &str,
+) -> BTreeMap<String, ToolChange> {
+    let mut changes: BTreeMap<String, ToolChange> = BTreeMap::new();
+    for item in turn_tool_items(items, thread_id, turn_id) {
+        if !is_write_tool(item) || item.get("status").and_then(Value::as_str) != Some("completed") {
+            continue;
+        }
+        let (Some(item_id), Some(arguments)) = (
+            item.get("id").and_then(Value::as_str),
+            item.get("arguments"),
+        ) else {
+            continue;
+        };
+        for (path, additions, deletions) in tool_edits(arguments) {
+            let Some(relative) = relative_to_root(root, &path) else {
+                continue;
+            };
+            let change = changes.entry(relative).or_default();
+            change.additions += additions;
+            change.deletions += deletions;
+            if !change.tool_item_ids.iter().any(|id| id == item_id) {
+                change.tool_item_ids.push(item_id.to_string());
+            }
+        }
+    }
+    changes
+}
+
+fn changed_during_turn(root: &Path, path: &str, baseline: &TurnArtifactBaseline) -> bool {
+    if !baseline.changed.contains(path) {
+        return true;
+    }
+    // Already dirty before the turn: only counts when written again since it started.
+    std::fs::metadata(root.join(path))
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified >= baseline.started_at)
+}
+
+/// Line counts against `HEAD` for every path git reports as changed during the turn, or
+/// `None` outside a git repository.
+fn git_changes(
+    root: &Path,
+    baseline: &TurnArtifactBaseline,
+) -> Option<BTreeMap<String, (u64, u64)>> {
+    let repo = Repository::open(root).ok()?;
+    let paths: Vec<String> = changed_path_statuses(root)
+        .into_iter()
+        .filter(|(path, status)| {
+            !path.starts_with(STATE_DIR_PREFIX)
+                && !status.contains(Status::IGNORED)
+                && changed_during_turn(root, path, baseline)
+        })
+        .map(|(path, _)| path)
+        .collect();
+    let mut changes: BTreeMap<String, (u64, u64)> =
+        paths.iter().map(|path| (path.clone(), (0, 0))).collect();
+    if paths.is_empty() {
+        return Some(changes);
+    }
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let mut options = DiffOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true)
+        .disable_pathspec_match(true);
+    for path in &paths {
+        options.pathspec(path);
+    }
+    let diff = repo
+        .diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut options))
+        .ok()?;
+    for index in 0..diff.deltas().len() {
+        let Ok(Some(patch)) = Patch::from_diff(&diff, index) else {
+            continue;
+        };
+        let delta = patch.delta();
+        let Some(path) = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .and_then(Path::to_str)
+        else {
+            continue;
+        };
+        if let (Some(counts), Ok((_, additions, deletions))) =
+            (changes.get_mut(path), patch.line_stats())
+        {
+            *counts = (additions as u64, deletions as u64);
+        }
+    }
+    Some(changes)
+}
+
+/// Files the turn changed so far. Tool calls supply which call wrote a file; with a
+/// baseline in a git repository the disk decides what changed and the line counts
+/// (against `HEAD`), otherwise the counts come from the tool call arguments.
+pub(crate) fn collect_live_changes(
+    root: &Path,
+    items: &[Value],
+    thread_id: &str,
+    turn_id: &str,
+    baseline: Option<&TurnArtifactBaseline>,
+) -> Vec<LiveFileChange> {
+    let mut tool = tool_changes(root, items, thread_id, turn_id);
+    let git = baseline.and_then(|baseline| git_changes(root, baseline));
+    let mut files: Vec<LiveFileChange> = Vec::new();
+    if let Some(git) = git {
+        for (path, (additions, deletions)) in git {
+            let tool_change = tool.remove(&path);
+            files.push(LiveFileChange {
+                unobserved: tool_change.is_none(),
+                tool_item_ids: tool_change
+                    .map(|change| change.tool_item_ids)
+                    .unwrap_or_default(),
+                path,
+                additions,
+                deletions,
+            });
+        }
+    }
+    // Written by a tool call but not reported by git: outside a repository, ignored, or
+    // changed back since.
+    files.extend(tool.into_iter().map(|(path, change)| LiveFileChange {
+        path,
+        additions: change.additions,
+        deletions: change.deletions,
+        tool_item_ids: change.tool_item_ids,
+        unobserved: false,
+    }));
+    files.sort_by(|left, right| left.path.cmp(&right.path));
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use std::process::Command;
+    use std::time::{Duration, SystemTime};
+    use uuid::Uuid;
+
+    fn items() -> Vec<Value> {
+        vec![
+            json!({ "id": "user-t1-turn", "type": "userMessage" }),
+            json!({
+                "id": "tool-edit",
+                "type": "mcpToolCall",
+                "tool": "edit",
+                "status": "completed",
+                "arguments": [{
+                    "type": "diff",
+                    "path": "src/lib.rs",
+                    "oldText": "fn a() {}\nfn b() {}",
+                    "newText": "fn a() {}\nfn b() { todo!() }\nfn c() {}"
+                }]
+            }),
+            json!({
+                "id": "tool-write",
+                "type": "mcpToolCall",
+                "tool": "write_file",
+                "status": "in_progress",
+                "arguments": { "file_path": "NOTES.md", "content": "one\ntwo" }
+            }),
+        ]
+    }
+
+    fn git(root: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(root)
+            .status()
+            .expect("run git");
+        assert!(status.success(), "git {args:?}");
+    }
+
+    #[test]
+    fn counts_lines_from_tool_arguments() {
+        assert_eq!(line_counts("a\nb\nc", "a\nc\nd\ne"), (2, 1));
+        assert_eq!(
+            tool_edits(&json!({ "path": "a.rs", "edits": [
+                { "old_string": "x", "new_string": "y\nz" },
+                { "old_string": "q", "new_string": "" }
+            ] })),
+            vec![("a.rs".to_string(), 2, 2)]
+        );
+
+        let files = collect_live_changes(Path::new("/repo"), &items(), "t1", "turn", None);
+        assert_eq!(
+            files,
+            vec![LiveFileChange {
+                path: "src/lib.rs".to_string(),
+                additions: 2,
+                deletions: 1,
+                tool_item_ids: vec!["tool-edit".to_string()],
+                unobserved: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn flags_files_changed_outside_completed_tool_calls() {
+        let root = std::env::temp_dir().join(format!("micode-live-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).expect("create repo");
+        git(&root, &["init", "-q"]);
+        git(&root, &["config", "user.email", "test@example.com"]);
+        git(&root, &["config", "user.name", "Test"]);
+        std::fs::write(root.join("src/lib.rs"), "fn a() {}\nfn b() {}\n").expect("write lib");
+        std::fs::write(root.join("DIRTY.md"), "draft\n").expect("write dirty");
+        git(&root, &["add", "src/lib.rs"]);
+        git(&root, &["commit", "-q", "-m", "init"]);
+
+        let mut baseline = TurnArtifactBaseline {
+            started_at: SystemTime::now() + Duration::from_secs(60),
+            untracked: HashSet::new(),
+            changed: HashSet::new(),
+        };
+        baseline.changed.insert("DIRTY.md".to_string());
+        std::fs::write(
+            root.join("src/lib.rs"),
+            "fn a() {}\nfn b() { todo!() }\nfn c() {}\n",
+        )
+        .expect("edit lib");
+        std::fs::write(root.join("NOTES.md"), "one\ntwo\n").expect("write notes");
+        std::fs::create_dir_all(root.join(".micodemonitor")).expect("create state dir");
+        std::fs::write(root.join(".micodemonitor/sessions.json"), "[]").expect("write state");
+
+        let files = collect_live_changes(&root, &items(), "t1", "turn", Some(&baseline));
+        let summary: Vec<(&str, u64, u64, bool)> = files
+            .iter()
+            .map(|file| {
+                (
+                    file.path.as_str(),
+                    file.additions,
+                    file.deletions,
+                    file.unobserved,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("NOTES.md", 2, 0, true), ("src/lib.rs", 2, 1, false)]
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
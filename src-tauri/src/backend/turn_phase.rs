@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Coarse progress of a running turn, reported with `turn/phase`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TurnPhase {
+    /// The prompt is being prepared and written to the agent.
+    Sending,
+    /// The prompt was written; nothing has streamed back yet.
+    WaitingForModel,
+    Streaming,
+    ToolRunning,
+    /// The agent answered the prompt; the turn is being persisted.
+    Finalizing,
+}
+
+/// Phase of one thread's running turn, advanced from prompt lifecycle points and
+/// `session/update` notifications.
+#[derive(Debug, Clone)]
+pub(crate) struct TurnPhaseTracker {
+    pub(crate) turn_id: String,
+    pub(crate) phase: TurnPhase,
+    running_tools: HashSet<String>,
+}
+
+impl TurnPhaseTracker {
+    pub(crate) fn new(turn_id: &str) -> Self {
+        Self {
+            turn_id: turn_id.to_string(),
+            phase: TurnPhase::Sending,
+            running_tools: HashSet::new(),
+        }
+    }
+
+    fn transition(&mut self, next: TurnPhase) -> Option<TurnPhase> {
+        if self.phase == next || self.phase == TurnPhase::Finalizing {
+            return None;
+        }
+        self.phase = next;
+        Some(next)
+    }
+
+    pub(crate) fn prompt_sent(&mut self) -> Option<TurnPhase> {
+        if self.phase != TurnPhase::Sending {
+            return None;
+        }
+        self.transition(TurnPhase::WaitingForModel)
+    }
+
+    /// Returns the new phase when the update moves the turn to a different one.
+    pub(crate) fn session_update(&mut self, update: &Value) -> Option<TurnPhase> {
+        let tool_call_id = update
+            .get("toolCallId")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        match update.get("sessionUpdate").and_then(Value::as_str) {
+            Some("agent_message_chunk" | "agent_thought_chunk" | "plan") => {
+                if self.running_tools.is_empty() {
+                    self.transition(TurnPhase::Streaming)
+                } else {
+                    None
+                }
+            }
+            Some("tool_call") => {
+                self.running_tools.insert(tool_call_id);
+                self.transition(TurnPhase::ToolRunning)
+            }
+            // Every `tool_call_update` ends its call, matching how tool items are persisted.
+            Some("tool_call_update") => {
+                self.running_tools.remove(&tool_call_id);
+                if self.running_tools.is_empty() {
+                    self.transition(TurnPhase::Streaming)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn finalizing(&mut self) -> Option<TurnPhase> {
+        self.transition(TurnPhase::Finalizing)
+    }
+}
+
+pub(crate) fn turn_phase_params(
+    thread_id: &str,
+    turn_id: &str,
+    phase: TurnPhase,
+    at_ms: u64,
+) -> Value {
+    json!({
+        "threadId": thread_id,
+        "turnId": turn_id,
+        "phase": phase,
+        "at": at_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(kind: &str, tool_call_id: Option<&str>) -> Value {
+        let mut update = json!({ "sessionUpdate": kind });
+        if let Some(tool_call_id) = tool_call_id {
+            update["toolCallId"] = json!(tool_call_id);
+        }
+        update
+    }
+
+    #[test]
+    fn session_updates_drive_phase_transitions() {
+        let mut tracker = TurnPhaseTracker::new("turn-1");
+        let mut phases = vec![tracker.phase];
+        phases.extend(tracker.prompt_sent());
+        for update in [
+            update("agent_thought_chunk", None),
+            update("agent_message_chunk", None),
+            update("tool_call", Some("call-1")),
+            update("tool_call", Some("call-2")),
+            update("agent_message_chunk", None),
+            update("tool_call_update", Some("call-1")),
+            update("tool_call_update", Some("call-2")),
+            update("agent_message_chunk", None),
+            update("available_commands_update", None),
+        ] {
+            phases.extend(tracker.session_update(&update));
+        }
+        phases.extend(tracker.finalizing());
+        phases.extend(tracker.session_update(&update("agent_message_chunk", None)));
+
+        assert_eq!(
+            phases,
+            vec![
+                TurnPhase::Sending,
+                TurnPhase::WaitingForModel,
+                TurnPhase::Streaming,
+                TurnPhase::ToolRunning,
+                TurnPhase::Streaming,
+                TurnPhase::Finalizing,
+            ]
+        );
+        let params = turn_phase_params("thread-1", &tracker.turn_id, tracker.phase, 42);
+        assert_eq!(params["phase"], "finalizing");
+        assert_eq!(params["turnId"], "turn-1");
+    }
+
+    #[test]
+    fn tool_call_before_any_text_leaves_waiting_for_model() {
+        let mut tracker = TurnPhaseTracker::new("turn-1");
+        tracker.prompt_sent();
+        assert_eq!(
+            tracker.session_update(&update("tool_call", Some("call-1"))),
+            Some(TurnPhase::ToolRunning)
+        );
+        assert_eq!(tracker.prompt_sent(), None);
+        assert_eq!(
+            tracker.session_update(&update("tool_call_update", Some("call-1"))),
+            Some(TurnPhase::Streaming)
+        );
+    }
+}
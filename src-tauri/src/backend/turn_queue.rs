@@ -0,0 +1,199 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// A user message that arrived while its thread was running a turn.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct QueuedTurn {
+    pub(crate) id: String,
+    pub(crate) thread_id: String,
+    /// `turn/start` params, sent unchanged once the thread is free.
+    pub(crate) params: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TurnAdmission {
+    /// The thread was idle and is now claimed by the caller.
+    Start(Value),
+    /// Waiting behind the running turn; `position` is 1-based.
+    Queued {
+        id: String,
+        position: usize,
+    },
+    Busy,
+}
+
+impl TurnAdmission {
+    /// Response returned to the sender of a queued message in place of the `turn/start`
+    /// result.
+    pub(crate) fn response(&self) -> Option<Value> {
+        match self {
+            Self::Queued { id, position } => Some(json!({
+                "result": { "queued": true, "queueId": id, "position": position }
+            })),
+            Self::Start(_) | Self::Busy => None,
+        }
+    }
+}
+
+/// Threads running a turn and the messages waiting for each of them.
+#[derive(Debug, Default)]
+pub(crate) struct TurnQueue {
+    busy: HashSet<String>,
+    queued: HashMap<String, VecDeque<QueuedTurn>>,
+}
+
+impl TurnQueue {
+    /// Marks the thread as running a turn. Claiming an already busy thread is a no-op, so
+    /// a dequeued turn can reuse the claim it was handed.
+    pub(crate) fn claim(&mut self, thread_id: &str) {
+        self.busy.insert(thread_id.to_string());
+    }
+
+    /// Claims an idle thread for `params`, or queues them behind the running turn.
+    pub(crate) fn admit(
+        &mut self,
+        thread_id: &str,
+        params: Value,
+        queue_when_busy: bool,
+    ) -> TurnAdmission {
+        if self.busy.insert(thread_id.to_string()) {
+            return TurnAdmission::Start(params);
+        }
+        if !queue_when_busy {
+            return TurnAdmission::Busy;
+        }
+        let queue = self.queued.entry(thread_id.to_string()).or_default();
+        let id = Uuid::new_v4().to_string();
+        queue.push_back(QueuedTurn {
+            id: id.clone(),
+            thread_id: thread_id.to_string(),
+            params,
+        });
+        TurnAdmission::Queued {
+            id,
+            position: queue.len(),
+        }
+    }
+
+    /// Ends the running turn of the thread. Returns the next queued message, which keeps
+    /// the thread claimed so nothing can slip in before it starts.
+    pub(crate) fn finish(&mut self, thread_id: &str) -> Option<QueuedTurn> {
+        let next = self.queued.get_mut(thread_id).and_then(VecDeque::pop_front);
+        if self.queued.get(thread_id).is_some_and(VecDeque::is_empty) {
+            self.queued.remove(thread_id);
+        }
+        if next.is_none() {
+            self.busy.remove(thread_id);
+        }
+        next
+    }
+
+    /// Whether no thread is claimed and no message is waiting.
+    pub(crate) fn is_idle(&self) -> bool {
+        self.busy.is_empty() && self.queued.is_empty()
+    }
+
+    pub(crate) fn queued_len(&self, thread_id: &str) -> usize {
+        self.queued.get(thread_id).map_or(0, VecDeque::len)
+    }
+
+    /// Drops the messages waiting on the thread and returns how many there were.
+    pub(crate) fn clear(&mut self, thread_id: &str) -> usize {
+        self.queued.remove(thread_id).map_or(0, |queue| queue.len())
+    }
+
+    /// Drops every queue and claim, returning `(thread id, dropped messages)` for the
+    /// threads that had any.
+    pub(crate) fn clear_all(&mut self) -> Vec<(String, usize)> {
+        self.busy.clear();
+        let mut cleared: Vec<(String, usize)> = self
+            .queued
+            .drain()
+            .map(|(thread_id, queue)| (thread_id, queue.len()))
+            .collect();
+        cleared.sort();
+        cleared
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(text: &str) -> Value {
+        json!({ "threadId": "thread-1", "input": [{ "type": "text", "text": text }] })
+    }
+
+    #[test]
+    fn queues_behind_the_running_turn_and_hands_over_in_order() {
+        let mut queue = TurnQueue::default();
+        assert_eq!(
+            queue.admit("thread-1", message("first"), true),
+            TurnAdmission::Start(message("first"))
+        );
+        let TurnAdmission::Queued { position, .. } =
+            queue.admit("thread-1", message("second"), true)
+        else {
+            panic!("expected the second message to be queued");
+        };
+        assert_eq!(position, 1);
+        let TurnAdmission::Queued { id, position } =
+            queue.admit("thread-1", message("third"), true)
+        else {
+            panic!("expected the third message to be queued");
+        };
+        assert_eq!(position, 2);
+        // Other threads are independent.
+        assert!(matches!(
+            queue.admit("thread-2", message("other"), true),
+            TurnAdmission::Start(_)
+        ));
+
+        assert_eq!(
+            queue.finish("thread-1").map(|turn| turn.params),
+            Some(message("second"))
+        );
+        assert_eq!(queue.queued_len("thread-1"), 1);
+        // The handed-over claim still blocks new messages.
+        assert!(matches!(
+            queue.admit("thread-1", message("fourth"), true),
+            TurnAdmission::Queued { position: 2, .. }
+        ));
+        assert_eq!(queue.finish("thread-1").map(|turn| turn.id), Some(id));
+        assert!(queue.finish("thread-1").is_some());
+        assert_eq!(queue.finish("thread-1"), None);
+        assert!(!queue.is_idle());
+        assert_eq!(queue.finish("thread-2"), None);
+        assert!(queue.is_idle());
+        assert!(matches!(
+            queue.admit("thread-1", message("fifth"), true),
+            TurnAdmission::Start(_)
+        ));
+    }
+
+    #[test]
+    fn rejects_or_clears_messages_for_busy_threads() {
+        let mut queue = TurnQueue::default();
+        queue.claim("thread-1");
+        let busy = queue.admit("thread-1", message("again"), false);
+        assert_eq!(busy, TurnAdmission::Busy);
+        assert_eq!(busy.response(), None);
+
+        queue.admit("thread-1", message("a"), true);
+        queue.admit("thread-1", message("b"), true);
+        assert_eq!(queue.clear("thread-1"), 2);
+        assert_eq!(queue.clear("thread-1"), 0);
+        assert_eq!(queue.finish("thread-1"), None);
+
+        queue.claim("thread-2");
+        queue.admit("thread-2", message("c"), true);
+        queue.claim("thread-3");
+        assert_eq!(queue.clear_all(), vec![("thread-2".to_string(), 1)]);
+        assert!(matches!(
+            queue.admit("thread-3", message("d"), true),
+            TurnAdmission::Start(_)
+        ));
+    }
+}
@@ -0,0 +1,410 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::backend::turn_artifacts::{extract_written_paths, relative_to_root};
+use crate::backend::turn_audit::file_name_component;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum FileReviewState {
+    #[default]
+    Pending,
+    Reviewed,
+    NeedsChanges,
+}
+
+impl FileReviewState {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Reviewed => "reviewed",
+            Self::NeedsChanges => "needs changes",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FileReview {
+    /// Workspace-relative path.
+    pub(crate) path: String,
+    pub(crate) state: FileReviewState,
+    pub(crate) note: Option<String>,
+    /// SHA-256 of the file contents when the entry was last updated; `None` once the
+    /// file is gone.
+    pub(crate) content_hash: Option<String>,
+    pub(crate) updated_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReviewProgress {
+    pub(crate) reviewed: usize,
+    pub(crate) total: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TurnReview {
+    pub(crate) thread_id: String,
+    pub(crate) turn_id: String,
+    pub(crate) files: Vec<FileReview>,
+    #[serde(default)]
+    pub(crate) progress: ReviewProgress,
+}
+
+impl TurnReview {
+    fn update_progress(&mut self) {
+        self.progress = ReviewProgress {
+            reviewed: self
+                .files
+                .iter()
+                .filter(|file| file.state == FileReviewState::Reviewed)
+                .count(),
+            total: self.files.len(),
+        };
+    }
+
+    /// Reverts entries whose file changed since they were last touched back to pending.
+    /// Returns whether anything changed.
+    fn refresh(&mut self, root: &Path, now_ms: u64) -> bool {
+        let mut changed = false;
+        for file in &mut self.files {
+            let current = content_hash(root, &file.path);
+            if current == file.content_hash {
+                continue;
+            }
+            if file.state != FileReviewState::Pending {
+                file.note = Some(format!(
+                    "Reset to pending: the file changed after it was marked {}.",
+                    file.state.label()
+                ));
+                file.state = FileReviewState::Pending;
+            }
+            file.content_hash = current;
+            file.updated_at_ms = now_ms;
+            changed = true;
+        }
+        self.update_progress();
+        changed
+    }
+}
+
+fn content_hash(root: &Path, path: &str) -> Option<String> {
+    let data = std::fs::read(root.join(path)).ok()?;
+    Some(format!("{:x}", Sha256::digest(&data)))
+}
+
+pub(crate) fn review_dir(workspace_root: &Path) -> PathBuf {
+    workspace_root.join(".micodemonitor").join("reviews")
+}
+
+fn review_file_path(workspace_root: &Path, turn_id: &str) -> PathBuf {
+    review_dir(workspace_root).join(format!("turn-{}.json", file_name_component(turn_id)))
+}
+
+fn write_turn_review(workspace_root: &Path, review: &TurnReview) -> Result<(), String> {
+    let path = review_file_path(workspace_root, &review.turn_id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let raw = serde_json::to_string_pretty(review).map_err(|err| err.to_string())?;
+    std::fs::write(&path, raw).map_err(|err| err.to_string())
+}
+
+fn load_turn_review(path: &Path) -> Result<TurnReview, String> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|_| "No review was recorded for this turn".to_string())?;
+    serde_json::from_str(&raw).map_err(|err| format!("Failed to parse turn review: {err}"))
+}
+
+/// Reads a turn's review, first resetting entries whose file changed on disk since they
+/// were last updated.
+pub(crate) fn read_turn_review(
+    workspace_root: &Path,
+    turn_id: &str,
+    now_ms: u64,
+) -> Result<TurnReview, String> {
+    let mut review = load_turn_review(&review_file_path(workspace_root, turn_id))?;
+    if review.refresh(workspace_root, now_ms) {
+        write_turn_review(workspace_root, &review)?;
+    }
+    Ok(review)
+}
+
+/// Returns the workspace-relative paths written by the turn's tool calls, in order.
+pub(crate) fn turn_changed_files(
+    workspace_root: &Path,
+    items: &[Value],
+    thread_id: &str,
+    turn_id: &str,
+) -> Vec<String> {
+    let mut seen = HashSet::new();
+    extract_written_paths(items, thread_id, turn_id)
+        .into_iter()
+        .filter_map(|(path, _)| relative_to_root(workspace_root, &path))
+        .filter(|path| seen.insert(path.clone()))
+        .collect()
+}
+
+/// Creates the review for a finished turn with every changed file pending, and resets
+/// entries for the same files in earlier turns whose content no longer matches.
+pub(crate) fn seed_turn_review(
+    workspace_root: &Path,
+    thread_id: &str,
+    turn_id: &str,
+    paths: &[String],
+    now_ms: u64,
+) -> Result<TurnReview, String> {
+    let files: Vec<FileReview> = paths
+        .iter()
+        .map(|path| FileReview {
+            path: path.clone(),
+            state: FileReviewState::Pending,
+            note: None,
+            content_hash: content_hash(workspace_root, path),
+            updated_at_ms: now_ms,
+        })
+        .collect();
+    invalidate_earlier_reviews(workspace_root, turn_id, &files, now_ms);
+    let mut review = TurnReview {
+        thread_id: thread_id.to_string(),
+        turn_id: turn_id.to_string(),
+        files,
+        progress: ReviewProgress::default(),
+    };
+    review.update_progress();
+    write_turn_review(workspace_root, &review)?;
+    Ok(review)
+}
+
+fn invalidate_earlier_reviews(
+    workspace_root: &Path,
+    turn_id: &str,
+    changed: &[FileReview],
+    now_ms: u64,
+) {
+    let Ok(entries) = std::fs::read_dir(review_dir(workspace_root)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(mut review) = load_turn_review(&entry.path()) else {
+            continue;
+        };
+        if review.turn_id == turn_id {
+            continue;
+        }
+        let mut updated = false;
+        for file in &mut review.files {
+            let Some(latest) = changed.iter().find(|latest| latest.path == file.path) else {
+                continue;
+            };
+            if latest.content_hash == file.content_hash {
+                continue;
+            }
+            if file.state != FileReviewState::Pending {
+                file.note = Some(format!(
+                    "Reset to pending: turn {turn_id} changed this file again after it was marked {}.",
+                    file.state.label()
+                ));
+                file.state = FileReviewState::Pending;
+            }
+            file.content_hash = latest.content_hash.clone();
+            file.updated_at_ms = now_ms;
+            updated = true;
+        }
+        if updated {
+            review.update_progress();
+            let _ = write_turn_review(workspace_root, &review);
+        }
+    }
+}
+
+/// Records the review state of one file in a turn's review.
+pub(crate) fn set_file_review_state(
+    workspace_root: &Path,
+    turn_id: &str,
+    path: &str,
+    state: FileReviewState,
+    note: Option<String>,
+    now_ms: u64,
+) -> Result<TurnReview, String> {
+    let mut review = load_turn_review(&review_file_path(workspace_root, turn_id))?;
+    let file = review
+        .files
+        .iter_mut()
+        .find(|file| file.path == path)
+        .ok_or_else(|| format!("`{path}` is not part of this turn's review"))?;
+    file.state = state;
+    file.note = note.filter(|note| !note.trim().is_empty());
+    file.content_hash = content_hash(workspace_root, path);
+    file.updated_at_ms = now_ms;
+    review.update_progress();
+    write_turn_review(workspace_root, &review)?;
+    Ok(review)
+}
+
+/// Adds `reviewProgress` to the last agent message of every turn in `items` that has a
+/// review, so restored threads can show how far the review got.
+pub(crate) fn attach_review_progress(
+    workspace_root: &Path,
+    thread_id: &str,
+    items: &mut [Value],
+    now_ms: u64,
+) {
+    let user_prefix = format!("user-{thread_id}-");
+    let turn_ids: Vec<String> = items
+        .iter()
+        .filter_map(|item| item.get("id").and_then(Value::as_str))
+        .filter_map(|id| id.strip_prefix(&user_prefix))
+        .map(str::to_string)
+        .collect();
+    for turn_id in turn_ids {
+        let Ok(review) = read_turn_review(workspace_root, &turn_id, now_ms) else {
+            continue;
+        };
+        let base_item_id = format!("agent-{thread_id}-{turn_id}");
+        let segment_prefix = format!("{base_item_id}-s");
+        let Some(item) = items.iter_mut().rev().find(|item| {
+            item.get("id")
+                .and_then(Value::as_str)
+                .map(|id| id == base_item_id || id.starts_with(&segment_prefix))
+                .unwrap_or(false)
+        }) else {
+            continue;
+        };
+        if let Some(object) = item.as_object_mut() {
+            object.insert("reviewProgress".to_string(), json!(review.progress));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn seeds_reviews_and_resets_entries_changed_by_later_turns() {
+        let root = std::env::temp_dir().join(format!("micode-reviews-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).expect("create root");
+        std::fs::write(root.join("src/lib.rs"), "fn a() {}").expect("write lib");
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").expect("write main");
+        let paths = vec!["src/lib.rs".to_string(), "src/main.rs".to_string()];
+
+        let review = seed_turn_review(&root, "thread-1", "turn-1", &paths, 1).expect("seed");
+        assert_eq!(
+            review.progress,
+            ReviewProgress {
+                reviewed: 0,
+                total: 2
+            }
+        );
+        assert!(root
+            .join(".micodemonitor/reviews/turn-turn-1.json")
+            .is_file());
+
+        set_file_review_state(
+            &root,
+            "turn-1",
+            "src/lib.rs",
+            FileReviewState::Reviewed,
+            None,
+            2,
+        )
+        .expect("mark lib");
+        let review = set_file_review_state(
+            &root,
+            "turn-1",
+            "src/main.rs",
+            FileReviewState::NeedsChanges,
+            Some("rename main".to_string()),
+            3,
+        )
+        .expect("mark main");
+        assert_eq!(
+            review.progress,
+            ReviewProgress {
+                reviewed: 1,
+                total: 2
+            }
+        );
+        assert!(set_file_review_state(
+            &root,
+            "turn-1",
+            "README.md",
+            FileReviewState::Reviewed,
+            None,
+            4
+        )
+        .is_err());
+
+        std::fs::write(root.join("src/lib.rs"), "fn b() {}").expect("rewrite lib");
+        seed_turn_review(&root, "thread-1", "turn-2", &paths[..1], 5).expect("seed turn 2");
+        let review = read_turn_review(&root, "turn-1", 6).expect("read turn 1");
+        let lib = &review.files[0];
+        assert_eq!(lib.state, FileReviewState::Pending);
+        assert!(lib.note.as_deref().unwrap_or_default().contains("turn-2"));
+        assert_eq!(review.files[1].state, FileReviewState::NeedsChanges);
+        assert_eq!(
+            review.progress,
+            ReviewProgress {
+                reviewed: 0,
+                total: 2
+            }
+        );
+
+        set_file_review_state(
+            &root,
+            "turn-1",
+            "src/main.rs",
+            FileReviewState::Reviewed,
+            None,
+            7,
+        )
+        .expect("mark main");
+        std::fs::write(root.join("src/main.rs"), "fn main() { run() }").expect("edit main");
+        let review = read_turn_review(&root, "turn-1", 8).expect("read turn 1");
+        assert_eq!(review.files[1].state, FileReviewState::Pending);
+        assert!(review.files[1].note.is_some());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn attaches_progress_to_the_last_agent_item_of_reviewed_turns() {
+        let root = std::env::temp_dir().join(format!("micode-reviews-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create root");
+        std::fs::write(root.join("notes.md"), "hello").expect("write notes");
+        let mut items = vec![
+            json!({ "id": "user-t-turn-1", "type": "userMessage" }),
+            json!({
+                "id": "tool-1",
+                "type": "mcpToolCall",
+                "tool": "write_file",
+                "arguments": { "path": root.join("notes.md").to_string_lossy() }
+            }),
+            json!({ "id": "agent-t-turn-1-s0", "type": "agentMessage" }),
+            json!({ "id": "agent-t-turn-1-s1", "type": "agentMessage" }),
+            json!({ "id": "user-t-turn-2", "type": "userMessage" }),
+            json!({ "id": "agent-t-turn-2", "type": "agentMessage" }),
+        ];
+        let paths = turn_changed_files(&root, &items, "t", "turn-1");
+        assert_eq!(paths, vec!["notes.md".to_string()]);
+        seed_turn_review(&root, "t", "turn-1", &paths, 1).expect("seed");
+
+        attach_review_progress(&root, "t", &mut items, 2);
+        assert!(items[2].get("reviewProgress").is_none());
+        assert_eq!(
+            items[3]["reviewProgress"],
+            json!({ "reviewed": 0, "total": 1 })
+        );
+        assert!(items[5].get("reviewProgress").is_none());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
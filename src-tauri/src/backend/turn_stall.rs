@@ -0,0 +1,164 @@
+use serde_json::{json, Value};
+
+/// When a running turn counts as stalled, from `turnStallWarningSecs` and
+/// `toolStallWarningSecs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct StallThresholds {
+    /// Silence before `turn/stalled`; 0 never warns.
+    pub(crate) idle_secs: u64,
+    /// Silence allowed while a tool call runs; `None` uses `idle_secs`, 0 never warns.
+    pub(crate) tool_secs: Option<u64>,
+}
+
+impl StallThresholds {
+    fn limit_ms(&self, tool_running: bool) -> Option<u64> {
+        let secs = if tool_running {
+            self.tool_secs.unwrap_or(self.idle_secs)
+        } else {
+            self.idle_secs
+        };
+        (secs > 0).then(|| secs * 1000)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StallChange {
+    Stalled { idle_secs: u64 },
+    ResumedStreaming { stalled_secs: u64 },
+}
+
+/// Time since the last `session/update` of one running foreground turn.
+#[derive(Debug, Clone)]
+pub(crate) struct StallWatch {
+    pub(crate) turn_id: String,
+    last_update_ms: u64,
+    stalled: bool,
+}
+
+impl StallWatch {
+    pub(crate) fn new(turn_id: &str, now_ms: u64) -> Self {
+        Self {
+            turn_id: turn_id.to_string(),
+            last_update_ms: now_ms,
+            stalled: false,
+        }
+    }
+
+    /// Records an update; reports the end of a stall the turn was flagged for.
+    pub(crate) fn update(&mut self, now_ms: u64) -> Option<StallChange> {
+        let silent_ms = now_ms.saturating_sub(self.last_update_ms);
+        self.last_update_ms = now_ms;
+        if !std::mem::take(&mut self.stalled) {
+            return None;
+        }
+        Some(StallChange::ResumedStreaming {
+            stalled_secs: silent_ms / 1000,
+        })
+    }
+
+    /// Reports a stall once per silence, when it outlasted the threshold that applies.
+    pub(crate) fn check(
+        &mut self,
+        now_ms: u64,
+        tool_running: bool,
+        thresholds: StallThresholds,
+    ) -> Option<StallChange> {
+        if self.stalled {
+            return None;
+        }
+        let silent_ms = now_ms.saturating_sub(self.last_update_ms);
+        if silent_ms < thresholds.limit_ms(tool_running)? {
+            return None;
+        }
+        self.stalled = true;
+        Some(StallChange::Stalled {
+            idle_secs: silent_ms / 1000,
+        })
+    }
+}
+
+pub(crate) fn stall_params(thread_id: &str, turn_id: &str, change: StallChange) -> Value {
+    match change {
+        StallChange::Stalled { idle_secs } => json!({
+            "threadId": thread_id,
+            "turnId": turn_id,
+            "idleSecs": idle_secs,
+        }),
+        StallChange::ResumedStreaming { stalled_secs } => json!({
+            "threadId": thread_id,
+            "turnId": turn_id,
+            "stalledSecs": stalled_secs,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHORT: StallThresholds = StallThresholds {
+        idle_secs: 2,
+        tool_secs: None,
+    };
+
+    #[test]
+    fn flags_a_silent_turn_once_and_reports_when_it_streams_again() {
+        let mut watch = StallWatch::new("turn-1", 0);
+        assert_eq!(watch.check(1_999, false, SHORT), None);
+        assert_eq!(
+            watch.check(2_500, false, SHORT),
+            Some(StallChange::Stalled { idle_secs: 2 })
+        );
+        assert_eq!(watch.check(9_000, false, SHORT), None);
+        assert_eq!(
+            watch.update(9_000),
+            Some(StallChange::ResumedStreaming { stalled_secs: 9 })
+        );
+        assert_eq!(watch.update(9_500), None);
+        assert_eq!(watch.check(11_000, false, SHORT), None);
+        assert_eq!(
+            watch.check(11_500, false, SHORT),
+            Some(StallChange::Stalled { idle_secs: 2 })
+        );
+
+        let disabled = StallThresholds {
+            idle_secs: 0,
+            tool_secs: None,
+        };
+        assert_eq!(
+            StallWatch::new("turn-2", 0).check(u64::MAX, false, disabled),
+            None
+        );
+    }
+
+    #[test]
+    fn running_tools_use_the_longer_tool_threshold_when_set() {
+        let with_tool = StallThresholds {
+            idle_secs: 2,
+            tool_secs: Some(10),
+        };
+        let mut watch = StallWatch::new("turn-1", 0);
+        assert_eq!(watch.check(5_000, true, with_tool), None);
+        assert_eq!(
+            watch.check(10_000, true, with_tool),
+            Some(StallChange::Stalled { idle_secs: 10 })
+        );
+
+        // Without a tool threshold a running tool gets the regular one.
+        let mut watch = StallWatch::new("turn-2", 0);
+        assert!(watch.check(2_000, true, SHORT).is_some());
+
+        let never_during_tools = StallThresholds {
+            idle_secs: 2,
+            tool_secs: Some(0),
+        };
+        let mut watch = StallWatch::new("turn-3", 0);
+        assert_eq!(watch.check(60_000, true, never_during_tools), None);
+        assert!(watch.check(60_000, false, never_during_tools).is_some());
+
+        assert_eq!(
+            stall_params("thread-1", "turn-1", StallChange::Stalled { idle_secs: 61 }),
+            json!({ "threadId": "thread-1", "turnId": "turn-1", "idleSecs": 61 })
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! Workspace paths and unicode normalization.
+//!
+//! A folder named in Finder is stored in NFD on HFS+ while the frontend hands paths over in
+//! NFC, and on normalization-sensitive file systems (network shares, Linux) the two
+//! spellings name different directories. Stored workspace paths therefore use the spelling
+//! found on disk, and comparisons between paths use NFC.
+
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+
+use unicode_normalization::UnicodeNormalization;
+
+const STATE_DIR_NAME: &str = ".micodemonitor";
+/// Where files replaced while merging split state directories are kept, inside the
+/// workspace state directory.
+const NORMALIZATION_BACKUP_DIR: &str = "normalization-backups";
+/// The thread index, whose records are merged by thread id instead of one copy replacing
+/// the other.
+const THREAD_INDEX_FILE: &str = "sessions.json";
+
+fn nfc(value: &str) -> String {
+    value.nfc().collect()
+}
+
+/// Whether two file names are the same name, possibly in different normalization forms.
+/// Names that are not valid UTF-8 only match byte for byte.
+fn names_equivalent(left: &OsStr, right: &OsStr) -> bool {
+    if left == right {
+        return true;
+    }
+    match (left.to_str(), right.to_str()) {
+        (Some(left), Some(right)) => nfc(left) == nfc(right),
+        _ => false,
+    }
+}
+
+fn components_equivalent(left: Component<'_>, right: Component<'_>) -> bool {
+    match (left, right) {
+        (Component::Normal(left), Component::Normal(right)) => names_equivalent(left, right),
+        (left, right) => left == right,
+    }
+}
+
+/// `Path::eq` that ignores unicode normalization differences.
+pub(crate) fn paths_equivalent(left: &Path, right: &Path) -> bool {
+    left.components().count() == right.components().count() && path_starts_with(left, right)
+}
+
+/// `Path::starts_with` that ignores unicode normalization differences.
+pub(crate) fn path_starts_with(path: &Path, base: &Path) -> bool {
+    let mut path = path.components();
+    base.components().all(|base| {
+        path.next()
+            .is_some_and(|component| components_equivalent(component, base))
+    })
+}
+
+/// Rewrites each component of `path` to the spelling of the existing entry it names, so an
+/// NFC path finds an NFD folder on normalization-sensitive file systems. Components that
+/// do not exist are kept as given.
+pub(crate) fn resolve_on_disk(path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        let Component::Normal(name) = component else {
+            resolved.push(component.as_os_str());
+            continue;
+        };
+        let candidate = resolved.join(name);
+        if std::fs::symlink_metadata(&candidate).is_ok() {
+            resolved = candidate;
+            continue;
+        }
+        let on_disk = std::fs::read_dir(if resolved.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            resolved.as_path()
+        })
+        .ok()
+        .and_then(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.file_name())
+                .find(|entry| names_equivalent(entry, name))
+        });
+        resolved.push(on_disk.as_deref().unwrap_or(name));
+    }
+    resolved
+}
+
+/// Stored paths stay in the user's notation on Windows, where canonical paths carry the
+/// `\\?\` prefix.
+#[cfg(not(windows))]
+fn canonicalize(path: PathBuf) -> PathBuf {
+    std::fs::canonicalize(&path).unwrap_or(path)
+}
+
+#[cfg(windows)]
+fn canonicalize(path: PathBuf) -> PathBuf {
+    path
+}
+
+/// The path a workspace entry stores: symlinks resolved and every component spelled the
+/// way it is on disk. Paths that are not valid UTF-8 are rejected instead of being stored
+/// lossily.
+pub(crate) fn normalize_workspace_path(path: &Path) -> Result<String, String> {
+    canonicalize(resolve_on_disk(path))
+        .into_os_string()
+        .into_string()
+        .map_err(|path| {
+            format!(
+                "Workspace path is not valid UTF-8: {}",
+                Path::new(&path).display()
+            )
+        })
+}
+
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from)
+}
+
+fn modified(path: &Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+fn read_thread_index(path: &Path) -> Option<Vec<serde_json::Value>> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+/// Both thread indexes' records, one per thread id. The more recently updated record of a
+/// thread wins, and on a tie the one from `newer`. `None` when either file is unreadable.
+fn merged_thread_index(newer: &Path, older: &Path) -> Option<Vec<serde_json::Value>> {
+    let newer = read_thread_index(newer)?;
+    let older = read_thread_index(older)?;
+    let updated_at = |record: &serde_json::Value| record["updatedAt"].as_i64().unwrap_or(0);
+    let mut merged: Vec<serde_json::Value> = Vec::with_capacity(newer.len() + older.len());
+    for record in newer.into_iter().chain(older) {
+        let thread_id = record["threadId"].as_str().map(str::to_string);
+        let existing = merged.iter_mut().find(|entry| {
+            thread_id.is_some() && entry["threadId"].as_str() == thread_id.as_deref()
+        });
+        match existing {
+            Some(entry) if updated_at(&record) > updated_at(entry) => *entry = record,
+            Some(_) => {}
+            None => merged.push(record),
+        }
+    }
+    Some(merged)
+}
+
+/// Moves `from` over `to` when it is newer; the file that loses goes to `kept`.
+fn keep_newer(from: &Path, to: &Path, kept: &Path) -> std::io::Result<()> {
+    if modified(from) > modified(to) {
+        move_file(to, kept)?;
+        move_file(from, to)
+    } else {
+        move_file(from, kept)
+    }
+}
+
+/// Writes the records of both thread indexes into `to` and moves its previous copy to
+/// `kept`. Falls back to `keep_newer` when either index cannot be read.
+fn merge_thread_indexes(from: &Path, to: &Path, kept: &Path) -> std::io::Result<()> {
+    let merged = if modified(from) > modified(to) {
+        merged_thread_index(from, to)
+    } else {
+        merged_thread_index(to, from)
+    };
+    let Some(merged) = merged else {
+        return keep_newer(from, to, kept);
+    };
+    let raw = serde_json::to_string(&merged).map_err(std::io::Error::other)?;
+    move_file(to, kept)?;
+    std::fs::write(to, raw)?;
+    std::fs::remove_file(from)
+}
+
+/// Moves every file of `stray` into `target`. When both have a file the newer one wins and
+/// the other goes to the same relative path under `backup`, except that the records of
+/// both thread indexes are kept.
+fn merge_state_dir(stray: &Path, target: &Path, backup: &Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(stray)?.flatten() {
+        let from = entry.path();
+        let to = target.join(entry.file_name());
+        let kept = backup.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            merge_state_dir(&from, &to, &kept)?;
+        } else if !to.exists() {
+            move_file(&from, &to)?;
+        } else if entry.file_name() == THREAD_INDEX_FILE {
+            merge_thread_indexes(&from, &to, &kept)?;
+        } else {
+            keep_newer(&from, &to, &kept)?;
+        }
+    }
+    Ok(())
+}
+
+/// Folds `.micodemonitor` directories of sibling folders whose names differ from the
+/// workspace folder only by unicode normalization into the workspace's own one, and
+/// removes such a sibling when nothing else is left in it. Returns the merged siblings.
+pub(crate) fn merge_split_state_dirs(
+    workspace_path: &Path,
+    now_ms: u64,
+) -> Result<Vec<PathBuf>, String> {
+    let (Some(parent), Some(name)) = (workspace_path.parent(), workspace_path.file_name()) else {
+        return Ok(Vec::new());
+    };
+    let Ok(entries) = std::fs::read_dir(parent) else {
+        return Ok(Vec::new());
+    };
+    let strays: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.file_name() != name && names_equivalent(&entry.file_name(), name))
+        .map(|entry| entry.path())
+        .filter(|path| path.join(STATE_DIR_NAME).is_dir())
+        .collect();
+    let target = workspace_path.join(STATE_DIR_NAME);
+    for (index, stray) in strays.iter().enumerate() {
+        let backup = target
+            .join(NORMALIZATION_BACKUP_DIR)
+            .join(format!("{now_ms}-{index}"));
+        let stray_state = stray.join(STATE_DIR_NAME);
+        merge_state_dir(&stray_state, &target, &backup).map_err(|err| {
+            format!(
+                "Failed to merge {} into {}: {err}",
+                stray_state.display(),
+                target.display()
+            )
+        })?;
+        std::fs::remove_dir_all(&stray_state).map_err(|err| err.to_string())?;
+        // Only succeeds when the sibling was created just to hold the state directory.
+        let _ = std::fs::remove_dir(stray);
+    }
+    Ok(strays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use uuid::Uuid;
+
+    const NFC_NAME: &str = "Caf\u{e9} app";
+    const NFD_NAME: &str = "Cafe\u{301} app";
+
+    fn temp_root() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("micode-paths-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create temp root");
+        root
+    }
+
+    /// Whether `root` tells NFC and NFD names apart; APFS and HFS+ do not.
+    fn normalization_sensitive(root: &Path) -> bool {
+        std::fs::create_dir(root.join(NFD_NAME)).expect("create probe");
+        let sensitive = !root.join(NFC_NAME).exists();
+        std::fs::remove_dir(root.join(NFD_NAME)).expect("remove probe");
+        sensitive
+    }
+
+    fn write_aged(path: &Path, contents: &str, age_secs: u64) {
+        std::fs::create_dir_all(path.parent().expect("parent")).expect("create parent");
+        std::fs::write(path, contents).expect("write file");
+        let file = std::fs::File::options()
+            .write(true)
+            .open(path)
+            .expect("open file");
+        file.set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .expect("set mtime");
+    }
+
+    #[test]
+    fn compares_paths_across_normalization_forms() {
+        let nfc = Path::new("/Users/me").join(NFC_NAME);
+        let nfd = Path::new("/Users/me").join(NFD_NAME);
+        assert_ne!(nfc, nfd);
+        assert!(paths_equivalent(&nfc, &nfd));
+        assert!(path_starts_with(&nfd.join("src/main.rs"), &nfc));
+        assert!(!paths_equivalent(&nfc, &nfd.join("src")));
+        assert!(!path_starts_with(
+            Path::new("/Users/me/Cafe"),
+            Path::new("/Users/me/Caf\u{e9}")
+        ));
+    }
+
+    #[test]
+    fn resolves_the_spelling_found_on_disk() {
+        let root = temp_root();
+        let sensitive = normalization_sensitive(&root);
+        std::fs::create_dir_all(root.join(NFD_NAME).join("src")).expect("create workspace");
+        let resolved = resolve_on_disk(&root.join(NFC_NAME).join("src"));
+        assert!(resolved.is_dir());
+        if sensitive {
+            assert_eq!(resolved, root.join(NFD_NAME).join("src"));
+        }
+        let normalized = normalize_workspace_path(&root.join(NFC_NAME)).expect("normalize");
+        assert!(paths_equivalent(
+            Path::new(&normalized),
+            &root.join(NFD_NAME)
+        ));
+        assert!(Path::new(&normalized).is_dir());
+        let missing = root.join("missing").join(NFC_NAME);
+        assert_eq!(resolve_on_disk(&missing), missing);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn merges_split_state_dirs_keeping_newer_files_and_all_threads() {
+        let root = temp_root();
+        if !normalization_sensitive(&root) {
+            let _ = std::fs::remove_dir_all(&root);
+            return;
+        }
+        let workspace = root.join(NFD_NAME);
+        let stray = root.join(NFC_NAME);
+        let state = workspace.join(STATE_DIR_NAME);
+        let stray_state = stray.join(STATE_DIR_NAME);
+        let old_sessions = r#"[{"threadId":"a","updatedAt":5},{"threadId":"b","updatedAt":9}]"#;
+        write_aged(&state.join("sessions.json"), old_sessions, 600);
+        write_aged(
+            &stray_state.join("sessions.json"),
+            r#"[{"threadId":"b","updatedAt":7},{"threadId":"c","updatedAt":8}]"#,
+            10,
+        );
+        write_aged(&state.join("primer-stats.json"), "new stats", 10);
+        write_aged(&stray_state.join("primer-stats.json"), "old stats", 600);
+        write_aged(&stray_state.join("audit/turn-1.json"), "audit", 10);
+
+        let merged = merge_split_state_dirs(&workspace, 42).expect("merge");
+        assert_eq!(merged, vec![stray.clone()]);
+        let read = |path: PathBuf| std::fs::read_to_string(path).expect("read");
+        let sessions: serde_json::Value =
+            serde_json::from_str(&read(state.join("sessions.json"))).expect("parse sessions");
+        assert_eq!(
+            sessions,
+            serde_json::json!([
+                { "threadId": "b", "updatedAt": 9 },
+                { "threadId": "c", "updatedAt": 8 },
+                { "threadId": "a", "updatedAt": 5 },
+            ])
+        );
+        assert_eq!(read(state.join("primer-stats.json")), "new stats");
+        assert_eq!(read(state.join("audit/turn-1.json")), "audit");
+        let backup = state.join(NORMALIZATION_BACKUP_DIR).join("42-0");
+        assert_eq!(read(backup.join("sessions.json")), old_sessions);
+        assert_eq!(read(backup.join("primer-stats.json")), "old stats");
+        assert!(!stray.exists());
+        assert!(merge_split_state_dirs(&workspace, 43)
+            .expect("second merge")
+            .is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
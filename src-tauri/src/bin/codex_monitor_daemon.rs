@@ -11,6 +11,9 @@ mod file_io;
 mod file_ops;
 #[path = "../files/policy.rs"]
 mod file_policy;
+#[allow(dead_code)]
+#[path = "../git_utils.rs"]
+mod git_utils;
 #[path = "../micode/config.rs"]
 mod micode_config;
 #[path = "../rules.rs"]
@@ -20,7 +23,7 @@ mod shared;
 #[path = "../storage.rs"]
 mod storage;
 #[allow(dead_code)]
-#[path = "../types.rs"]
+#[path = "../types/mod.rs"]
 mod types;
 #[path = "../utils.rs"]
 mod utils;
@@ -60,6 +63,7 @@ use std::fs::File;
 use std::io::Read;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use ignore::WalkBuilder;
@@ -67,37 +71,80 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{broadcast, mpsc, Mutex};
 
-use backend::app_server::{spawn_workspace_session, WorkspaceSession};
+use backend::app_server::{
+    spawn_workspace_session, SessionLaunch, SessionServices, SessionSettings, WorkspaceSession,
+};
+use backend::auto_run::AutoRunRequest;
+use backend::connect_queue::ConnectQueue;
+use backend::connection_state::ConnectionStates;
+use backend::documents::{read_structured, ReadFormat, StructuredDocument};
+use backend::event_methods;
 use backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
+use backend::history_prune::{HistoryPruneOptions, HistoryPruneParams};
+use backend::review_context::ReviewContextOptions;
+use backend::settings_events::{SettingsRevision, SettingsScope};
+use backend::settings_json::SettingsParseErrors;
+use backend::store_maintenance::StoreMaintenanceReport;
+use rules::RuleDecision;
+use shared::auto_run_core::AutoRunRegistry;
+use shared::bootstrap_core::bootstrap_warnings_event;
+use shared::command_timings_core::CommandTimingsRegistry;
 use shared::micode_core::MiCodeLoginCancelState;
-use shared::{files_core, git_core, micode_core, settings_core, workspaces_core, worktree_core};
-use storage::{read_settings, read_workspaces};
-use types::{AppSettings, WorkspaceEntry, WorkspaceInfo, WorkspaceSettings, WorktreeSetupStatus};
+use shared::operations_core::{CancellationToken, OperationOutcome, OperationRegistry};
+use shared::thread_ownership_core::{
+    ownership_changed_params, ownership_now_ms, ThreadOwnership, ThreadOwnershipRegistry,
+};
+use shared::usage_counters_core::FeatureUsageCounters;
+use shared::usage_ledger_core::UsageLedger;
+use shared::workspaces_core::{ConnectAllSelection, NewWorkspace, WorkspaceStores};
+use shared::{
+    auto_run_core, files_core, git_core, micode_core, resource_monitor_core, run_kickoff_core,
+    settings_core, usage_ledger_core, workspace_stack_core, workspaces_core, worktree_core,
+};
+use storage::{read_settings, read_workspaces, write_workspaces};
+use types::errors::CommandError;
+use types::{
+    AppSettings, WorkspaceBootstrapWarning, WorkspaceEntry, WorkspaceInfo, WorkspaceSettings,
+    WorktreeSetupStatus,
+};
 use workspace_settings::apply_workspace_settings_update;
 
 const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:4732";
 
-fn spawn_with_client(
-    event_sink: DaemonEventSink,
+async fn spawn_with_client(
+    state: &DaemonState,
     client_version: String,
     entry: WorkspaceEntry,
     default_bin: Option<String>,
     agent_args: Option<String>,
     agent_home: Option<PathBuf>,
-) -> impl std::future::Future<Output = Result<Arc<WorkspaceSession>, String>> {
-    spawn_workspace_session(
+) -> Result<Arc<WorkspaceSession>, CommandError> {
+    let session_settings = SessionSettings::from_app_settings(&*state.app_settings.lock().await);
+    let launch = SessionLaunch {
         entry,
-        default_bin,
+        default_micode_bin: default_bin,
         agent_args,
         agent_home,
+        session_settings,
         client_version,
-        event_sink,
-    )
+    };
+    let services = SessionServices {
+        data_dir: &state.data_dir,
+        connect_queue: &state.connect_queue,
+        connection_states: &state.connection_states,
+        auto_runs: &state.auto_runs,
+        settings_parse_errors: &state.settings_parse_errors,
+    };
+    spawn_workspace_session(launch, services, state.event_sink.clone()).await
 }
 
 #[derive(Clone)]
 struct DaemonEventSink {
     tx: broadcast::Sender<DaemonEvent>,
+    /// Turn-end token usage, see `usage_ledger_core`.
+    usage_ledger: UsageLedger,
+    /// Opt-in feature-usage counters, see `usage_counters_core`.
+    usage_counters: FeatureUsageCounters,
 }
 
 #[derive(Clone)]
@@ -111,6 +158,10 @@ enum DaemonEvent {
 
 impl EventSink for DaemonEventSink {
     fn emit_app_server_event(&self, event: AppServerEvent) {
+        self.usage_counters
+            .record_event(&event.workspace_id, &event.message);
+        self.usage_ledger
+            .record_token_usage_event(&event.workspace_id, &event.message);
         let _ = self.tx.send(DaemonEvent::AppServer(event));
     }
 
@@ -133,17 +184,27 @@ struct DaemonState {
     data_dir: PathBuf,
     workspaces: Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    connection_states: ConnectionStates,
     storage_path: PathBuf,
     settings_path: PathBuf,
     app_settings: Mutex<AppSettings>,
+    settings_revision: SettingsRevision,
     event_sink: DaemonEventSink,
     micode_login_cancels: Mutex<HashMap<String, MiCodeLoginCancelState>>,
+    thread_owners: Mutex<ThreadOwnershipRegistry>,
+    operations: OperationRegistry,
+    auto_runs: AutoRunRegistry,
+    settings_parse_errors: SettingsParseErrors,
+    connect_queue: ConnectQueue,
+    command_timings: CommandTimingsRegistry,
 }
 
 #[derive(Serialize, Deserialize)]
 struct WorkspaceFileResponse {
     content: String,
     truncated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    document: Option<StructuredDocument>,
 }
 
 impl DaemonState {
@@ -159,22 +220,59 @@ impl DaemonState {
     fn load(config: &DaemonConfig, event_sink: DaemonEventSink) -> Self {
         let storage_path = config.data_dir.join("workspaces.json");
         let settings_path = config.data_dir.join("settings.json");
-        let workspaces = read_workspaces(&storage_path).unwrap_or_default();
+        let settings_parse_errors = SettingsParseErrors::default();
+        let mut workspaces = read_workspaces(&storage_path).unwrap_or_default();
+        if micode_core::migrate_workspace_preferred_models(
+            &mut workspaces,
+            &config.data_dir,
+            &settings_parse_errors,
+        ) {
+            let list: Vec<WorkspaceEntry> = workspaces.values().cloned().collect();
+            let _ = write_workspaces(&storage_path, &list);
+        }
         let app_settings = read_settings(&settings_path).unwrap_or_default();
+        event_sink
+            .usage_counters
+            .configure(Some(&config.data_dir), &app_settings, &workspaces);
+        let connect_queue = ConnectQueue::new(app_settings.max_concurrent_connects);
+        let command_timings = CommandTimingsRegistry::new(
+            Some(&config.data_dir.join("logs")),
+            app_settings.slow_command_threshold_ms,
+        );
         Self {
             data_dir: config.data_dir.clone(),
             workspaces: Mutex::new(workspaces),
             sessions: Mutex::new(HashMap::new()),
+            connection_states: ConnectionStates::default(),
             storage_path,
             settings_path,
             app_settings: Mutex::new(app_settings),
+            settings_revision: SettingsRevision::default(),
             event_sink,
             micode_login_cancels: Mutex::new(HashMap::new()),
+            thread_owners: Mutex::new(ThreadOwnershipRegistry::default()),
+            operations: OperationRegistry::default(),
+            auto_runs: AutoRunRegistry::default(),
+            settings_parse_errors,
+            connect_queue,
+            command_timings,
         }
     }
 
-    async fn list_workspaces(&self) -> Vec<WorkspaceInfo> {
-        workspaces_core::list_workspaces_core(&self.workspaces, &self.sessions).await
+    async fn list_workspaces(&self, include_runtime: bool) -> Vec<WorkspaceInfo> {
+        let mut workspaces =
+            workspaces_core::list_workspaces_core(&self.workspaces, &self.sessions).await;
+        if include_runtime {
+            // Usage files live with the desktop app, so the daemon reports no daily count.
+            workspaces_core::attach_workspace_runtime_core(
+                &mut workspaces,
+                &self.sessions,
+                &self.connection_states,
+                None,
+            )
+            .await;
+        }
+        workspaces
     }
 
     async fn is_workspace_path_dir(&self, path: String) -> bool {
@@ -188,16 +286,16 @@ impl DaemonState {
         client_version: String,
     ) -> Result<WorkspaceInfo, String> {
         let client_version = client_version.clone();
-        workspaces_core::add_workspace_core(
-            path,
-            agent_bin,
+        let workspace = workspaces_core::add_workspace_core(
+            NewWorkspace { path, agent_bin },
             &self.workspaces,
             &self.sessions,
             &self.app_settings,
+            &self.data_dir,
             &self.storage_path,
             move |entry, default_bin, agent_args, agent_home| {
                 spawn_with_client(
-                    self.event_sink.clone(),
+                    self,
                     client_version.clone(),
                     entry,
                     default_bin,
@@ -206,7 +304,12 @@ impl DaemonState {
                 )
             },
         )
-        .await
+        .await?;
+        if let Some(event) = bootstrap_warnings_event(&workspace.id, &workspace.bootstrap_warnings)
+        {
+            self.event_sink.emit_app_server_event(event);
+        }
+        Ok(workspace)
     }
 
     async fn add_worktree(
@@ -245,7 +348,7 @@ impl DaemonState {
             },
             move |entry, default_bin, agent_args, agent_home| {
                 spawn_with_client(
-                    self.event_sink.clone(),
+                    self,
                     client_version.clone(),
                     entry,
                     default_bin,
@@ -263,6 +366,7 @@ impl DaemonState {
     ) -> Result<WorktreeSetupStatus, String> {
         workspaces_core::worktree_setup_status_core(&self.workspaces, &workspace_id, &self.data_dir)
             .await
+            .map_err(String::from)
     }
 
     async fn worktree_setup_mark_ran(&self, workspace_id: String) -> Result<(), String> {
@@ -271,15 +375,17 @@ impl DaemonState {
             &workspace_id,
             &self.data_dir,
         )
-        .await
+        .await.map_err(String::from)
     }
 
-    async fn remove_workspace(&self, id: String) -> Result<(), String> {
+    async fn remove_workspace(&self, id: String, delete_agent_home: bool) -> Result<(), String> {
         workspaces_core::remove_workspace_core(
             id,
             &self.workspaces,
             &self.sessions,
+            &self.connection_states,
             &self.storage_path,
+            &self.data_dir,
             |root, args| {
                 workspaces_core::run_git_command_unit(root, args, git_core::run_git_command_owned)
             },
@@ -290,16 +396,20 @@ impl DaemonState {
             },
             true,
             true,
+            delete_agent_home,
         )
-        .await
+        .await.map_err(String::from)
     }
 
     async fn remove_worktree(&self, id: String) -> Result<(), String> {
         workspaces_core::remove_worktree_core(
             id,
-            &self.workspaces,
-            &self.sessions,
-            &self.storage_path,
+            WorkspaceStores {
+                workspaces: &self.workspaces,
+                sessions: &self.sessions,
+                connection_states: &self.connection_states,
+                storage_path: &self.storage_path,
+            },
             |root, args| {
                 workspaces_core::run_git_command_unit(root, args, git_core::run_git_command_owned)
             },
@@ -309,15 +419,25 @@ impl DaemonState {
                     .map_err(|err| format!("Failed to remove worktree folder: {err}"))
             },
         )
-        .await
+        .await.map_err(String::from)
     }
 
-    async fn clear_workspace_history(&self, id: String) -> Result<(), String> {
-        let (workspace_ids, _paths) =
-            workspaces_core::resolve_workspace_history_targets_core(&id, &self.workspaces).await?;
-        workspaces_core::clear_workspace_history_core(&id, &self.workspaces).await?;
-        workspaces_core::kill_sessions_core(&self.sessions, &workspace_ids).await;
-        Ok(())
+    async fn clear_workspace_history(
+        &self,
+        id: String,
+        options: HistoryPruneOptions,
+    ) -> Result<Value, String> {
+        let (result, events) = workspaces_core::clear_workspace_history_scoped_core(
+            &id,
+            &self.workspaces,
+            &self.sessions,
+            &options,
+        )
+        .await?;
+        for event in events {
+            self.event_sink.emit_app_server_event(event);
+        }
+        serde_json::to_value(result).map_err(|err| err.to_string())
     }
 
     async fn rename_worktree(
@@ -333,6 +453,7 @@ impl DaemonState {
             &self.data_dir,
             &self.workspaces,
             &self.sessions,
+            &self.connection_states,
             &self.app_settings,
             &self.storage_path,
             |entry| Ok(PathBuf::from(entry.path.clone())),
@@ -354,7 +475,7 @@ impl DaemonState {
             },
             move |entry, default_bin, agent_args, agent_home| {
                 spawn_with_client(
-                    self.event_sink.clone(),
+                    self,
                     client_version.clone(),
                     entry,
                     default_bin,
@@ -363,7 +484,7 @@ impl DaemonState {
                 )
             },
         )
-        .await
+        .await.map_err(String::from)
     }
 
     async fn rename_worktree_upstream(
@@ -405,7 +526,7 @@ impl DaemonState {
                 workspaces_core::run_git_command_unit(root, args, git_core::run_git_command_owned)
             },
         )
-        .await
+        .await.map_err(String::from)
     }
 
     async fn update_workspace_settings(
@@ -415,19 +536,26 @@ impl DaemonState {
         client_version: String,
     ) -> Result<WorkspaceInfo, String> {
         let client_version = client_version.clone();
-        workspaces_core::update_workspace_settings_core(
+        let previous = self
+            .workspaces
+            .lock()
+            .await
+            .get(&id)
+            .map(|entry| entry.settings.clone());
+        let workspace = workspaces_core::update_workspace_settings_core(
             id,
             settings,
             &self.workspaces,
             &self.sessions,
             &self.app_settings,
+            &self.data_dir,
             &self.storage_path,
             |workspaces, workspace_id, next_settings| {
                 apply_workspace_settings_update(workspaces, workspace_id, next_settings)
             },
             move |entry, default_bin, agent_args, agent_home| {
                 spawn_with_client(
-                    self.event_sink.clone(),
+                    self,
                     client_version.clone(),
                     entry,
                     default_bin,
@@ -436,7 +564,17 @@ impl DaemonState {
                 )
             },
         )
-        .await
+        .await?;
+        self.event_sink
+            .usage_counters
+            .set_workspace(&workspace.id, workspace.settings.usage_counters);
+        let (_, event) = self.settings_revision.record_update(
+            SettingsScope::Workspace(&workspace.id),
+            &json!(previous),
+            &json!(workspace.settings),
+        );
+        self.event_sink.emit_app_server_event(event);
+        Ok(workspace)
     }
 
     async fn update_workspace_micode_bin(
@@ -444,33 +582,209 @@ impl DaemonState {
         id: String,
         agent_bin: Option<String>,
     ) -> Result<WorkspaceInfo, String> {
-        workspaces_core::update_workspace_micode_bin_core(
+        let mut workspace = workspaces_core::update_workspace_micode_bin_core(
             id,
             agent_bin,
             &self.workspaces,
             &self.sessions,
             &self.storage_path,
         )
+        .await?;
+        self.refresh_stale_sessions().await;
+        workspace.config_stale = self
+            .sessions
+            .lock()
+            .await
+            .get(&workspace.id)
+            .map(|session| session.is_config_stale())
+            .unwrap_or(false);
+        Ok(workspace)
+    }
+
+    async fn refresh_stale_sessions(&self) {
+        let events = workspaces_core::refresh_stale_sessions_core(
+            &self.workspaces,
+            &self.sessions,
+            &self.app_settings,
+        )
+        .await;
+        for event in events {
+            self.event_sink.emit_app_server_event(event);
+        }
+    }
+
+    async fn restart_workspace_session(
+        self: &Arc<Self>,
+        workspace_id: String,
+        force: bool,
+        client_version: String,
+    ) -> Result<Option<WorkspaceInfo>, String> {
+        let restarted = workspaces_core::restart_workspace_session_core(
+            workspace_id.clone(),
+            force,
+            &self.workspaces,
+            &self.sessions,
+            &self.app_settings,
+            &self.data_dir,
+            |entry, default_bin, agent_args, agent_home| {
+                spawn_with_client(
+                    self,
+                    client_version.clone(),
+                    entry,
+                    default_bin,
+                    agent_args,
+                    agent_home,
+                )
+            },
+        )
+        .await?;
+        if restarted.is_none() {
+            let state = Arc::clone(self);
+            tokio::spawn(async move {
+                workspaces_core::drain_and_restart_session_core(
+                    workspace_id,
+                    &state.workspaces,
+                    &state.sessions,
+                    &state.app_settings,
+                    &state.data_dir,
+                    |event| state.event_sink.emit_app_server_event(event),
+                    |entry, default_bin, agent_args, agent_home| {
+                        spawn_with_client(
+                            &state,
+                            client_version.clone(),
+                            entry,
+                            default_bin,
+                            agent_args,
+                            agent_home,
+                        )
+                    },
+                )
+                .await;
+            });
+        }
+        Ok(restarted)
+    }
+
+    async fn force_restart_session(
+        &self,
+        workspace_id: String,
+        client_version: String,
+    ) -> Result<WorkspaceInfo, String> {
+        workspaces_core::force_restart_session_core(
+            workspace_id,
+            &self.workspaces,
+            &self.sessions,
+            &self.app_settings,
+            &self.data_dir,
+            move |entry, default_bin, agent_args, agent_home| {
+                spawn_with_client(
+                    self,
+                    client_version.clone(),
+                    entry,
+                    default_bin,
+                    agent_args,
+                    agent_home,
+                )
+            },
+        )
         .await
     }
 
-    async fn connect_workspace(&self, id: String, client_version: String) -> Result<(), String> {
+    fn record_store_maintenance(&self, reports: &[StoreMaintenanceReport]) {
+        let logs_dir = self.data_dir.join("logs");
+        for event in workspaces_core::record_store_maintenance_reports(&logs_dir, reports) {
+            self.event_sink.emit_app_server_event(event);
+        }
+    }
+
+    async fn run_store_maintenance_now(
+        &self,
+        workspace_id: Option<String>,
+    ) -> Result<Value, String> {
+        let reports =
+            workspaces_core::run_store_maintenance_core(&self.sessions, workspace_id.as_deref())
+                .await?;
+        self.record_store_maintenance(&reports);
+        serde_json::to_value(reports).map_err(|err| err.to_string())
+    }
+
+    async fn run_idle_store_maintenance(&self) {
+        let reports =
+            workspaces_core::run_idle_store_maintenance_core(&self.sessions, &self.app_settings)
+                .await;
+        self.record_store_maintenance(&reports);
+    }
+
+    async fn check_liveness(self: &Arc<Self>, client_version: &str) {
+        let (events, restart_ids) =
+            workspaces_core::detect_unresponsive_sessions_core(&self.sessions, &self.app_settings)
+                .await;
+        for event in events {
+            self.event_sink.emit_app_server_event(event);
+        }
+        for workspace_id in restart_ids {
+            if let Err(error) = self
+                .force_restart_session(workspace_id.clone(), client_version.to_string())
+                .await
+            {
+                self.event_sink.emit_app_server_event(AppServerEvent {
+                    workspace_id: workspace_id.clone(),
+                    message: json!({
+                        "method": event_methods::MICODE_RESTART_FAILED,
+                        "params": { "workspaceId": workspace_id, "error": error }
+                    }),
+                });
+            }
+        }
+        for workspace_id in workspaces_core::crashed_session_ids_core(&self.sessions).await {
+            let state = Arc::clone(self);
+            let client_version = client_version.to_string();
+            tokio::spawn(async move {
+                workspaces_core::respawn_crashed_session_core(
+                    workspace_id,
+                    &state.workspaces,
+                    &state.sessions,
+                    &state.app_settings,
+                    &state.data_dir,
+                    |event| state.event_sink.emit_app_server_event(event),
+                    |entry, default_bin, agent_args, agent_home| {
+                        spawn_with_client(
+                            &state,
+                            client_version.clone(),
+                            entry,
+                            default_bin,
+                            agent_args,
+                            agent_home,
+                        )
+                    },
+                )
+                .await;
+            });
+        }
+    }
+
+    async fn connect_workspace(
+        &self,
+        id: String,
+        client_version: String,
+    ) -> Result<Vec<WorkspaceBootstrapWarning>, String> {
         {
             let sessions = self.sessions.lock().await;
             if sessions.contains_key(&id) {
-                return Ok(());
+                return Ok(Vec::new());
             }
         }
 
         let client_version = client_version.clone();
-        workspaces_core::connect_workspace_core(
-            id,
+        let bootstrap_warnings = workspaces_core::connect_workspace_core(
+            id.clone(),
             &self.workspaces,
             &self.sessions,
             &self.app_settings,
+            &self.data_dir,
             move |entry, default_bin, agent_args, agent_home| {
                 spawn_with_client(
-                    self.event_sink.clone(),
+                    self,
                     client_version.clone(),
                     entry,
                     default_bin,
@@ -479,37 +793,119 @@ impl DaemonState {
                 )
             },
         )
-        .await
+        .await?;
+        if let Some(event) = bootstrap_warnings_event(&id, &bootstrap_warnings) {
+            self.event_sink.emit_app_server_event(event);
+        }
+        Ok(bootstrap_warnings)
+    }
+
+    async fn connect_all_workspaces(
+        &self,
+        selection: ConnectAllSelection,
+        client_version: String,
+    ) -> Value {
+        let (summary, events) = workspaces_core::connect_all_workspaces_core(
+            selection,
+            &self.workspaces,
+            &self.sessions,
+            &self.app_settings,
+            &self.data_dir,
+            &self.connect_queue,
+            move |entry, default_bin, agent_args, agent_home| {
+                spawn_with_client(
+                    self,
+                    client_version.clone(),
+                    entry,
+                    default_bin,
+                    agent_args,
+                    agent_home,
+                )
+            },
+        )
+        .await;
+        for event in events {
+            self.event_sink.emit_app_server_event(event);
+        }
+        summary
     }
 
     async fn get_app_settings(&self) -> AppSettings {
-        settings_core::get_app_settings_core(&self.app_settings).await
+        settings_core::get_app_settings_core(&self.app_settings, &self.settings_revision).await
     }
 
     async fn update_app_settings(&self, settings: AppSettings) -> Result<AppSettings, String> {
-        settings_core::update_app_settings_core(settings, &self.app_settings, &self.settings_path)
-            .await
+        let (updated, event) = settings_core::update_app_settings_core(
+            settings,
+            &self.app_settings,
+            &self.settings_path,
+            &self.settings_revision,
+        )
+        .await?;
+        self.event_sink.usage_counters.configure(
+            Some(&self.data_dir),
+            &updated,
+            &*self.workspaces.lock().await,
+        );
+        settings_core::apply_backend_settings(&updated, &self.connect_queue, &self.command_timings);
+        settings_core::apply_session_settings(&updated, &self.sessions).await;
+        self.event_sink.emit_app_server_event(event);
+        self.refresh_stale_sessions().await;
+        Ok(updated)
     }
 
-    async fn list_workspace_files(&self, workspace_id: String) -> Result<Vec<String>, String> {
-        workspaces_core::list_workspace_files_core(&self.workspaces, &workspace_id, |root| {
-            list_workspace_files_inner(root, 20000)
+    async fn list_workspace_files(
+        &self,
+        workspace_id: String,
+        operation_id: Option<String>,
+    ) -> Result<OperationOutcome<Vec<String>>, String> {
+        let operation =
+            self.operations
+                .start(operation_id, "workspaceSearch", self.event_sink.clone());
+        let scanned = AtomicU64::new(0);
+        let result = workspaces_core::list_workspace_files_core(&self.workspaces, &workspace_id, |root| {
+            let files = list_workspace_files_inner(root, 20000, operation.token());
+            let total = scanned.fetch_add(files.len() as u64, Ordering::Relaxed) + files.len() as u64;
+            operation.progress("scanning", Some(total), None);
+            files
         })
         .await
+        .map(|files| (!operation.is_cancelled()).then_some(files));
+        operation.finish(result).map_err(String::from)
     }
 
     async fn read_workspace_file(
         &self,
         workspace_id: String,
         path: String,
+        format: ReadFormat,
     ) -> Result<WorkspaceFileResponse, String> {
         workspaces_core::read_workspace_file_core(
             &self.workspaces,
             &workspace_id,
             &path,
-            |root, rel_path| read_workspace_file_inner(root, rel_path),
+            |root, rel_path| read_workspace_file_inner(root, rel_path, format),
         )
-        .await
+        .await.map_err(String::from)
+    }
+
+    async fn read_turn_artifact(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        turn_id: String,
+        path: String,
+    ) -> Result<WorkspaceFileResponse, String> {
+        workspaces_core::read_turn_artifact_core(
+            &self.workspaces,
+            &self.sessions,
+            &workspace_id,
+            &thread_id,
+            &turn_id,
+            &path,
+            |root, rel_path| read_workspace_file_inner(root, rel_path, ReadFormat::Auto),
+        )
+        .await.map_err(String::from)
     }
 
     async fn file_read(
@@ -528,23 +924,151 @@ impl DaemonState {
         workspace_id: Option<String>,
         content: String,
     ) -> Result<(), String> {
-        files_core::file_write_core(&self.workspaces, scope, kind, workspace_id, content).await
+        if let Some(event) = files_core::file_write_core(
+            &self.workspaces,
+            &self.settings_revision,
+            scope,
+            kind,
+            workspace_id,
+            content,
+        )
+        .await?
+        {
+            self.event_sink.emit_app_server_event(event);
+        }
+        Ok(())
     }
 
     async fn start_thread(&self, workspace_id: String) -> Result<Value, String> {
-        micode_core::start_thread_core(&self.sessions, workspace_id).await
+        micode_core::start_thread_core(&self.sessions, workspace_id)
+            .await
+            .map_err(String::from)
     }
 
     async fn resume_thread(
         &self,
         workspace_id: String,
         thread_id: String,
+        client_id: &str,
+    ) -> Result<Value, String> {
+        let owned_elsewhere = self
+            .thread_owners
+            .lock()
+            .await
+            .get(&workspace_id, &thread_id)
+            .map(|ownership| ownership.owner_client_id != client_id)
+            .unwrap_or(false);
+        if owned_elsewhere {
+            // Resuming replaces the ACP session, which would cut off the owning client.
+            let thread =
+                micode_core::thread_snapshot_core(&self.sessions, workspace_id, thread_id).await?;
+            return Ok(json!({ "result": { "thread": thread, "items": thread["items"] } }));
+        }
+        micode_core::resume_thread_core(&self.sessions, workspace_id, thread_id)
+            .await
+            .map_err(String::from)
+    }
+
+    fn emit_ownership_changed(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        ownership: Option<&ThreadOwnership>,
+        previous_owner_client_id: Option<&str>,
+    ) {
+        self.event_sink.emit_app_server_event(AppServerEvent {
+            workspace_id: workspace_id.to_string(),
+            message: json!({
+                "method": event_methods::THREAD_OWNERSHIP_CHANGED,
+                "params": ownership_changed_params(
+                    workspace_id,
+                    thread_id,
+                    ownership,
+                    previous_owner_client_id,
+                ),
+            }),
+        });
+    }
+
+    async fn watch_thread(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        client_id: &str,
+    ) -> Result<Value, String> {
+        let ownership = self
+            .thread_owners
+            .lock()
+            .await
+            .get(&workspace_id, &thread_id);
+        let thread =
+            micode_core::thread_snapshot_core(&self.sessions, workspace_id, thread_id).await?;
+        Ok(json!({
+            "clientId": client_id,
+            "ownership": ownership,
+            "thread": thread,
+        }))
+    }
+
+    async fn take_over_thread(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        client_id: &str,
     ) -> Result<Value, String> {
-        micode_core::resume_thread_core(&self.sessions, workspace_id, thread_id).await
+        micode_core::thread_snapshot_core(&self.sessions, workspace_id.clone(), thread_id.clone())
+            .await?;
+        let (ownership, previous) = self.thread_owners.lock().await.take_over(
+            &workspace_id,
+            &thread_id,
+            client_id,
+            ownership_now_ms(),
+        );
+        if previous.as_deref() != Some(client_id) {
+            self.emit_ownership_changed(
+                &workspace_id,
+                &thread_id,
+                Some(&ownership),
+                previous.as_deref(),
+            );
+        }
+        Ok(json!({
+            "clientId": client_id,
+            "ownership": ownership,
+            "previousOwnerClientId": previous,
+        }))
+    }
+
+    async fn get_thread_ownership(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        client_id: &str,
+    ) -> Value {
+        let ownership = self
+            .thread_owners
+            .lock()
+            .await
+            .get(&workspace_id, &thread_id);
+        json!({ "clientId": client_id, "ownership": ownership })
+    }
+
+    async fn release_client_threads(&self, client_id: &str) {
+        let released = self.thread_owners.lock().await.release_client(client_id);
+        for ownership in released {
+            self.emit_ownership_changed(
+                &ownership.workspace_id,
+                &ownership.thread_id,
+                None,
+                Some(client_id),
+            );
+        }
     }
 
     async fn fork_thread(&self, workspace_id: String, thread_id: String) -> Result<Value, String> {
-        micode_core::fork_thread_core(&self.sessions, workspace_id, thread_id).await
+        micode_core::fork_thread_core(&self.sessions, workspace_id, thread_id)
+            .await
+            .map_err(String::from)
     }
 
     async fn list_threads(
@@ -553,7 +1077,30 @@ impl DaemonState {
         cursor: Option<String>,
         limit: Option<u32>,
     ) -> Result<Value, String> {
-        micode_core::list_threads_core(&self.sessions, workspace_id, cursor, limit).await
+        micode_core::list_threads_core(&self.sessions, workspace_id, cursor, limit)
+            .await
+            .map_err(String::from)
+    }
+
+    async fn edit_mcp_server(
+        &self,
+        workspace_id: String,
+        edit: micode_core::McpServerEdit,
+        name: String,
+        config: Option<Value>,
+    ) -> Result<(), String> {
+        let event = micode_core::edit_mcp_server_core(
+            &self.workspaces,
+            &self.data_dir,
+            &self.settings_parse_errors,
+            workspace_id,
+            edit,
+            name,
+            config,
+        )
+        .await?;
+        self.event_sink.emit_app_server_event(event);
+        Ok(())
     }
 
     async fn list_mcp_server_status(
@@ -576,16 +1123,20 @@ impl DaemonState {
                 } else {
                     micode_core::list_mcp_server_status_from_settings_core(
                         &self.workspaces,
+                        &self.data_dir,
                         workspace_id,
                     )
                     .await
                     .or(Ok(value))
                 }
             }
-            Err(_) => {
-                micode_core::list_mcp_server_status_from_settings_core(&self.workspaces, workspace_id)
-                    .await
-            }
+            Err(_) => micode_core::list_mcp_server_status_from_settings_core(
+                &self.workspaces,
+                &self.data_dir,
+                workspace_id,
+            )
+            .await
+            .map_err(String::from),
         }
     }
 
@@ -593,8 +1144,11 @@ impl DaemonState {
         &self,
         workspace_id: String,
         thread_id: String,
+        force: bool,
     ) -> Result<Value, String> {
-        micode_core::archive_thread_core(&self.sessions, workspace_id, thread_id).await
+        micode_core::archive_thread_core(&self.sessions, workspace_id, thread_id, force)
+            .await
+            .map_err(String::from)
     }
 
     async fn compact_thread(
@@ -602,7 +1156,9 @@ impl DaemonState {
         workspace_id: String,
         thread_id: String,
     ) -> Result<Value, String> {
-        micode_core::compact_thread_core(&self.sessions, workspace_id, thread_id).await
+        micode_core::compact_thread_core(&self.sessions, workspace_id, thread_id)
+            .await
+            .map_err(String::from)
     }
 
     async fn set_thread_name(
@@ -611,7 +1167,9 @@ impl DaemonState {
         thread_id: String,
         name: String,
     ) -> Result<Value, String> {
-        micode_core::set_thread_name_core(&self.sessions, workspace_id, thread_id, name).await
+        micode_core::set_thread_name_core(&self.sessions, workspace_id, thread_id, name)
+            .await
+            .map_err(String::from)
     }
 
     async fn send_user_message(
@@ -624,7 +1182,44 @@ impl DaemonState {
         access_mode: Option<String>,
         images: Option<Vec<String>>,
         collaboration_mode: Option<Value>,
+        raw_text: Option<bool>,
+        sampling_params: Option<Value>,
+        skip_redaction: Option<bool>,
+        client_id: &str,
+        client_version: String,
     ) -> Result<Value, String> {
+        let claimed = self.thread_owners.lock().await.claim_for_send(
+            &workspace_id,
+            &thread_id,
+            client_id,
+            ownership_now_ms(),
+        )?;
+        if let Some(ownership) = claimed {
+            self.emit_ownership_changed(&workspace_id, &thread_id, Some(&ownership), None);
+        }
+        if let Some(settings) = micode_core::preferred_model_update_core(
+            &self.workspaces,
+            &self.data_dir,
+            &self.settings_parse_errors,
+            &workspace_id,
+            model.as_deref(),
+        )
+        .await
+        {
+            self.update_workspace_settings(workspace_id.clone(), settings, client_version)
+                .await?;
+        }
+        let sampling_params = micode_core::resolve_sampling_params_core(
+            &self.workspaces,
+            &self.data_dir,
+            &self.settings_parse_errors,
+            &self.app_settings,
+            &workspace_id,
+            model.as_deref(),
+            sampling_params.as_ref(),
+        )
+        .await?;
+        let queue_when_busy = self.app_settings.lock().await.queue_when_busy;
         micode_core::send_user_message_core(
             &self.sessions,
             workspace_id,
@@ -635,8 +1230,42 @@ impl DaemonState {
             access_mode,
             images,
             collaboration_mode,
+            raw_text,
+            sampling_params,
+            skip_redaction,
+            Vec::new(),
+            queue_when_busy,
         )
-        .await
+        .await.map_err(String::from)
+    }
+
+    async fn run_slash_command(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        command_name: String,
+        args: Option<String>,
+        client_id: &str,
+    ) -> Result<Value, String> {
+        let claimed = self.thread_owners.lock().await.claim_for_send(
+            &workspace_id,
+            &thread_id,
+            client_id,
+            ownership_now_ms(),
+        )?;
+        if let Some(ownership) = claimed {
+            self.emit_ownership_changed(&workspace_id, &thread_id, Some(&ownership), None);
+        }
+        let queue_when_busy = self.app_settings.lock().await.queue_when_busy;
+        micode_core::run_slash_command_core(
+            &self.sessions,
+            workspace_id,
+            thread_id,
+            command_name,
+            args,
+            queue_when_busy,
+        )
+        .await.map_err(String::from)
     }
 
     async fn turn_interrupt(
@@ -645,7 +1274,29 @@ impl DaemonState {
         thread_id: String,
         turn_id: String,
     ) -> Result<Value, String> {
-        micode_core::turn_interrupt_core(&self.sessions, workspace_id, thread_id, turn_id).await
+        micode_core::turn_interrupt_core(&self.sessions, workspace_id, thread_id, turn_id)
+            .await
+            .map_err(String::from)
+    }
+
+    async fn cancel_tool_call(
+        &self,
+        workspace_id: String,
+        tool_call_id: String,
+    ) -> Result<Value, String> {
+        micode_core::cancel_tool_call_core(&self.sessions, workspace_id, tool_call_id)
+            .await
+            .map_err(String::from)
+    }
+
+    async fn get_turn_live_changes(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+    ) -> Result<Value, String> {
+        micode_core::get_turn_live_changes_core(&self.sessions, workspace_id, thread_id)
+            .await
+            .map_err(String::from)
     }
 
     async fn start_review(
@@ -654,30 +1305,52 @@ impl DaemonState {
         thread_id: String,
         target: Value,
         delivery: Option<String>,
+        context: Option<ReviewContextOptions>,
     ) -> Result<Value, String> {
-        micode_core::start_review_core(&self.sessions, workspace_id, thread_id, target, delivery)
-            .await
+        micode_core::start_review_core(
+            &self.sessions,
+            workspace_id,
+            thread_id,
+            target,
+            delivery,
+            context,
+        )
+        .await.map_err(String::from)
     }
 
-    async fn model_list(&self, workspace_id: String) -> Result<Value, String> {
-        micode_core::model_list_core(&self.sessions, workspace_id).await
+    async fn model_list(&self, workspace_id: String, refresh: bool) -> Result<Value, String> {
+        micode_core::model_list_core(&self.sessions, workspace_id, refresh)
+            .await
+            .map_err(String::from)
     }
 
     async fn collaboration_mode_list(&self, workspace_id: String) -> Result<Value, String> {
-        micode_core::collaboration_mode_list_core(&self.sessions, workspace_id).await
+        micode_core::collaboration_mode_list_core(&self.sessions, workspace_id)
+            .await
+            .map_err(String::from)
     }
 
     async fn account_rate_limits(&self, workspace_id: String) -> Result<Value, String> {
-        micode_core::account_rate_limits_core(&self.sessions, workspace_id).await
+        micode_core::account_rate_limits_core(&self.sessions, workspace_id)
+            .await
+            .map_err(String::from)
     }
 
     async fn account_read(&self, workspace_id: String) -> Result<Value, String> {
-        micode_core::account_read_core(&self.sessions, &self.workspaces, workspace_id).await
+        micode_core::account_read_core(
+            &self.sessions,
+            &self.workspaces,
+            &self.data_dir,
+            workspace_id,
+        )
+        .await
+        .map_err(String::from)
     }
 
     async fn micode_login(&self, workspace_id: String) -> Result<Value, String> {
         micode_core::micode_login_core(&self.sessions, &self.micode_login_cancels, workspace_id)
             .await
+            .map_err(String::from)
     }
 
     async fn micode_login_cancel(&self, workspace_id: String) -> Result<Value, String> {
@@ -686,11 +1359,13 @@ impl DaemonState {
             &self.micode_login_cancels,
             workspace_id,
         )
-        .await
+        .await.map_err(String::from)
     }
 
     async fn skills_list(&self, workspace_id: String) -> Result<Value, String> {
-        micode_core::skills_list_core(&self.sessions, workspace_id).await
+        micode_core::skills_list_core(&self.sessions, workspace_id)
+            .await
+            .map_err(String::from)
     }
 
     async fn apps_list(
@@ -699,7 +1374,9 @@ impl DaemonState {
         cursor: Option<String>,
         limit: Option<u32>,
     ) -> Result<Value, String> {
-        micode_core::apps_list_core(&self.sessions, workspace_id, cursor, limit).await
+        micode_core::apps_list_core(&self.sessions, workspace_id, cursor, limit)
+            .await
+            .map_err(String::from)
     }
 
     async fn respond_to_server_request(
@@ -722,24 +1399,115 @@ impl DaemonState {
         &self,
         workspace_id: String,
         command: Vec<String>,
+        decision: RuleDecision,
     ) -> Result<Value, String> {
-        micode_core::remember_approval_rule_core(&self.workspaces, workspace_id, command).await
+        micode_core::remember_approval_rule_core(
+            &self.workspaces,
+            &self.data_dir,
+            workspace_id,
+            command,
+            decision,
+        )
+        .await
+        .map_err(String::from)
     }
 
     async fn list_approval_rules(&self, workspace_id: String) -> Result<Value, String> {
-        micode_core::list_approval_rules_core(&self.workspaces, workspace_id).await
+        micode_core::list_approval_rules_core(&self.workspaces, &self.data_dir, workspace_id)
+            .await
+            .map_err(String::from)
     }
 
     async fn remove_approval_rule(
         &self,
         workspace_id: String,
         command: Vec<String>,
+        decision: RuleDecision,
+    ) -> Result<Value, String> {
+        micode_core::remove_approval_rule_core(
+            &self.workspaces,
+            &self.data_dir,
+            workspace_id,
+            command,
+            decision,
+        )
+        .await
+        .map_err(String::from)
+    }
+
+    async fn approval_rules_list(&self, workspace_id: String) -> Result<Value, String> {
+        micode_core::approval_rules_list_core(&self.workspaces, &self.data_dir, workspace_id)
+            .await
+            .map_err(String::from)
+    }
+
+    async fn approval_rules_delete(
+        &self,
+        workspace_id: String,
+        rule_id: String,
+    ) -> Result<Value, String> {
+        micode_core::approval_rules_delete_core(
+            &self.workspaces,
+            &self.data_dir,
+            workspace_id,
+            rule_id,
+        )
+        .await
+        .map_err(String::from)
+    }
+
+    async fn approval_rules_update(
+        &self,
+        workspace_id: String,
+        rule_id: String,
+        pattern: Option<Vec<String>>,
+        decision: Option<RuleDecision>,
+    ) -> Result<Value, String> {
+        micode_core::approval_rules_update_core(
+            &self.workspaces,
+            &self.data_dir,
+            workspace_id,
+            rule_id,
+            pattern,
+            decision,
+        )
+        .await.map_err(String::from)
+    }
+
+    async fn approval_rules_export(
+        &self,
+        workspace_id: String,
+        path: String,
+    ) -> Result<Value, String> {
+        micode_core::approval_rules_export_core(
+            &self.workspaces,
+            &self.data_dir,
+            workspace_id,
+            path,
+        )
+        .await
+        .map_err(String::from)
+    }
+
+    async fn approval_rules_import(
+        &self,
+        workspace_id: String,
+        path: String,
     ) -> Result<Value, String> {
-        micode_core::remove_approval_rule_core(&self.workspaces, workspace_id, command).await
+        micode_core::approval_rules_import_core(
+            &self.workspaces,
+            &self.data_dir,
+            workspace_id,
+            path,
+        )
+        .await
+        .map_err(String::from)
     }
 
     async fn get_config_model(&self, workspace_id: String) -> Result<Value, String> {
-        micode_core::get_config_model_core(&self.workspaces, workspace_id).await
+        micode_core::get_config_model_core(&self.workspaces, &self.data_dir, workspace_id)
+            .await
+            .map_err(String::from)
     }
 }
 
@@ -754,7 +1522,11 @@ fn normalize_git_path(path: &str) -> String {
     path.replace('\\', "/")
 }
 
-fn list_workspace_files_inner(root: &PathBuf, max_files: usize) -> Vec<String> {
+fn list_workspace_files_inner(
+    root: &PathBuf,
+    max_files: usize,
+    token: &CancellationToken,
+) -> Vec<String> {
     let mut results = Vec::new();
     let walker = WalkBuilder::new(root)
         .hidden(false)
@@ -773,6 +1545,9 @@ fn list_workspace_files_inner(root: &PathBuf, max_files: usize) -> Vec<String> {
         .build();
 
     for entry in walker {
+        if token.is_cancelled() {
+            break;
+        }
         let entry = match entry {
             Ok(entry) => entry,
             Err(_) => continue,
@@ -800,6 +1575,7 @@ const MAX_WORKSPACE_FILE_BYTES: u64 = 400_000;
 fn read_workspace_file_inner(
     root: &PathBuf,
     relative_path: &str,
+    format: ReadFormat,
 ) -> Result<WorkspaceFileResponse, String> {
     let canonical_root = root
         .canonicalize()
@@ -816,6 +1592,14 @@ fn read_workspace_file_inner(
     if !metadata.is_file() {
         return Err("Path is not a file".to_string());
     }
+    if let Some(read) = read_structured(&canonical_path, format, MAX_WORKSPACE_FILE_BYTES as usize)?
+    {
+        return Ok(WorkspaceFileResponse {
+            content: read.content,
+            truncated: read.truncated,
+            document: Some(read.document),
+        });
+    }
 
     let file = File::open(&canonical_path).map_err(|err| format!("Failed to open file: {err}"))?;
     let mut buffer = Vec::new();
@@ -829,7 +1613,11 @@ fn read_workspace_file_inner(
     }
 
     let content = String::from_utf8(buffer).map_err(|_| "File is not valid UTF-8".to_string())?;
-    Ok(WorkspaceFileResponse { content, truncated })
+    Ok(WorkspaceFileResponse {
+        content,
+        truncated,
+        document: None,
+    })
 }
 
 fn default_data_dir() -> PathBuf {
@@ -939,15 +1727,15 @@ fn build_result_response(id: Option<u64>, result: Value) -> Option<String> {
 fn build_event_notification(event: DaemonEvent) -> Option<String> {
     let payload = match event {
         DaemonEvent::AppServer(payload) => json!({
-            "method": "app-server-event",
+            "method": event_methods::DAEMON_APP_SERVER_EVENT,
             "params": payload,
         }),
         DaemonEvent::TerminalOutput(payload) => json!({
-            "method": "terminal-output",
+            "method": event_methods::DAEMON_TERMINAL_OUTPUT,
             "params": payload,
         }),
         DaemonEvent::TerminalExit(payload) => json!({
-            "method": "terminal-exit",
+            "method": event_methods::DAEMON_TERMINAL_EXIT,
             "params": payload,
         }),
     };
@@ -986,6 +1774,13 @@ fn parse_optional_string(value: &Value, key: &str) -> Option<String> {
     }
 }
 
+fn parse_optional_u64(value: &Value, key: &str) -> Option<u64> {
+    match value {
+        Value::Object(map) => map.get(key).and_then(|value| value.as_u64()),
+        _ => None,
+    }
+}
+
 fn parse_optional_u32(value: &Value, key: &str) -> Option<u32> {
     match value {
         Value::Object(map) => map.get(key).and_then(|value| value.as_u64()).and_then(|v| {
@@ -1025,6 +1820,20 @@ fn parse_string_array(value: &Value, key: &str) -> Result<Vec<String>, String> {
     parse_optional_string_array(value, key).ok_or_else(|| format!("missing `{key}`"))
 }
 
+/// The optional `decision` of an approval rule call.
+fn parse_optional_rule_decision(value: &Value) -> Result<Option<RuleDecision>, String> {
+    parse_optional_value(value, "decision")
+        .filter(|value| !value.is_null())
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|err| format!("invalid rule decision: {err}"))
+}
+
+/// Like `parse_optional_rule_decision`; rules allow by default.
+fn parse_rule_decision(value: &Value) -> Result<RuleDecision, String> {
+    parse_optional_rule_decision(value).map(Option::unwrap_or_default)
+}
+
 fn parse_optional_value(value: &Value, key: &str) -> Option<Value> {
     match value {
         Value::Object(map) => map.get(key).cloned(),
@@ -1058,15 +1867,17 @@ fn parse_file_write_request(params: &Value) -> Result<FileWriteRequest, String>
 }
 
 async fn handle_rpc_request(
-    state: &DaemonState,
+    state: &Arc<DaemonState>,
     method: &str,
     params: Value,
     client_version: String,
+    client_id: &str,
 ) -> Result<Value, String> {
     match method {
         "ping" => Ok(json!({ "ok": true })),
         "list_workspaces" => {
-            let workspaces = state.list_workspaces().await;
+            let include_runtime = parse_optional_bool(&params, "includeRuntime").unwrap_or(false);
+            let workspaces = state.list_workspaces(include_runtime).await;
             serde_json::to_value(workspaces).map_err(|err| err.to_string())
         }
         "is_workspace_path_dir" => {
@@ -1103,12 +1914,23 @@ async fn handle_rpc_request(
         }
         "connect_workspace" => {
             let id = parse_string(&params, "id")?;
-            state.connect_workspace(id, client_version).await?;
-            Ok(json!({ "ok": true }))
+            let bootstrap_warnings = state.connect_workspace(id, client_version).await?;
+            Ok(json!({ "ok": true, "bootstrapWarnings": bootstrap_warnings }))
+        }
+        "connect_all_workspaces" => {
+            let selection = ConnectAllSelection {
+                workspace_ids: parse_optional_string_array(&params, "workspaceIds"),
+                priority_workspace_id: parse_optional_string(&params, "priorityWorkspaceId"),
+            };
+            Ok(state
+                .connect_all_workspaces(selection, client_version)
+                .await)
         }
         "remove_workspace" => {
             let id = parse_string(&params, "id")?;
-            state.remove_workspace(id).await?;
+            let delete_agent_home =
+                parse_optional_bool(&params, "deleteAgentHome").unwrap_or(false);
+            state.remove_workspace(id, delete_agent_home).await?;
             Ok(json!({ "ok": true }))
         }
         "remove_worktree" => {
@@ -1118,8 +1940,14 @@ async fn handle_rpc_request(
         }
         "clear_workspace_history" => {
             let id = parse_string(&params, "id")?;
-            state.clear_workspace_history(id).await?;
-            Ok(json!({ "ok": true }))
+            let options = HistoryPruneOptions::from_params(HistoryPruneParams {
+                older_than_days: parse_optional_u64(&params, "olderThanDays"),
+                tags: parse_optional_string_array(&params, "tags"),
+                exclude_pinned: parse_optional_bool(&params, "excludePinned"),
+                pinned_thread_ids: parse_optional_string_array(&params, "pinnedThreadIds"),
+                dry_run: parse_optional_bool(&params, "dryRun"),
+            });
+            state.clear_workspace_history(id, options).await
         }
         "rename_worktree" => {
             let id = parse_string(&params, "id")?;
@@ -1156,17 +1984,172 @@ async fn handle_rpc_request(
             let workspace = state.update_workspace_micode_bin(id, agent_bin).await?;
             serde_json::to_value(workspace).map_err(|err| err.to_string())
         }
+        "restart_workspace_session" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let force = parse_optional_bool(&params, "force").unwrap_or(false);
+            let workspace = state
+                .restart_workspace_session(workspace_id, force, client_version)
+                .await?;
+            serde_json::to_value(workspace).map_err(|err| err.to_string())
+        }
+        "run_store_maintenance_now" => {
+            let workspace_id = parse_optional_string(&params, "workspaceId");
+            state.run_store_maintenance_now(workspace_id).await
+        }
+        "get_session_info" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            workspaces_core::get_session_info_core(&workspace_id, &state.sessions).await.map_err(String::from)
+        }
+        "micode_session_status" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            workspaces_core::micode_session_status_core(&workspace_id, &state.sessions).await.map_err(String::from)
+        }
+        "get_command_timings" => {
+            serde_json::to_value(state.command_timings.snapshot()).map_err(|err| err.to_string())
+        }
+        "reset_command_timings" => {
+            state.command_timings.reset();
+            Ok(json!({ "ok": true }))
+        }
+        "get_feature_usage" => {
+            serde_json::to_value(state.event_sink.usage_counters.feature_usage())
+                .map_err(|err| err.to_string())
+        }
+        "usage_summary" => {
+            let workspace_id = parse_optional_string(&params, "workspaceId");
+            let since = parse_optional_string(&params, "since");
+            let prices = state.app_settings.lock().await.model_prices.clone();
+            let summary = usage_ledger_core::usage_summary_core(
+                state.event_sink.usage_ledger.path(),
+                workspace_id.as_deref(),
+                since.as_deref(),
+                &prices,
+            )?;
+            serde_json::to_value(summary).map_err(|err| err.to_string())
+        }
+        "usage_ledger_csv" => {
+            let (content, row_count) =
+                usage_ledger_core::usage_ledger_csv_core(state.event_sink.usage_ledger.path());
+            Ok(json!({ "content": content, "rowCount": row_count }))
+        }
+        "get_backend_capabilities" => serde_json::to_value(event_methods::backend_capabilities())
+            .map_err(|err| err.to_string()),
+        "test_redaction" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let text = parse_string(&params, "text")?;
+            let settings = parse_optional_value(&params, "settings")
+                .filter(|value| !value.is_null())
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(|err| format!("invalid redaction settings: {err}"))?;
+            let redacted = workspaces_core::test_redaction_core(
+                &state.workspaces,
+                &workspace_id,
+                &text,
+                settings,
+            )
+            .await?;
+            serde_json::to_value(redacted).map_err(|err| err.to_string())
+        }
+        "get_resource_usage" => Ok(json!({
+            "sessions": resource_monitor_core::session_resource_usage_core(&state.sessions).await,
+            // Terminals run in the app, which fills in their usage.
+            "terminals": []
+        })),
+        "force_restart_session" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let workspace = state
+                .force_restart_session(workspace_id, client_version)
+                .await?;
+            serde_json::to_value(workspace).map_err(|err| err.to_string())
+        }
         "list_workspace_files" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
-            let files = state.list_workspace_files(workspace_id).await?;
-            serde_json::to_value(files).map_err(|err| err.to_string())
+            let operation_id = parse_optional_string(&params, "operationId");
+            let outcome = state
+                .list_workspace_files(workspace_id, operation_id)
+                .await?;
+            serde_json::to_value(outcome).map_err(|err| err.to_string())
+        }
+        "cancel_operation" => {
+            let operation_id = parse_string(&params, "operationId")?;
+            Ok(json!(state.operations.cancel(&operation_id)))
         }
         "read_workspace_file" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let path = parse_string(&params, "path")?;
-            let response = state.read_workspace_file(workspace_id, path).await?;
+            let format = ReadFormat::parse(parse_optional_string(&params, "format").as_deref())?;
+            let response = state
+                .read_workspace_file(workspace_id, path, format)
+                .await?;
+            serde_json::to_value(response).map_err(|err| err.to_string())
+        }
+        "read_turn_artifact" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let turn_id = parse_string(&params, "turnId")?;
+            let path = parse_string(&params, "path")?;
+            let response = state
+                .read_turn_artifact(workspace_id, thread_id, turn_id, path)
+                .await?;
             serde_json::to_value(response).map_err(|err| err.to_string())
         }
+        "get_turn_audit" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let turn_id = parse_string(&params, "turnId")?;
+            let audit = workspaces_core::get_turn_audit_core(
+                &state.workspaces,
+                &workspace_id,
+                &thread_id,
+                &turn_id,
+            )
+            .await?;
+            serde_json::to_value(audit).map_err(|err| err.to_string())
+        }
+        "get_workspace_stack" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let refresh = parse_optional_bool(&params, "refresh").unwrap_or(false);
+            let stack = workspace_stack_core::get_workspace_stack_core(
+                &state.workspaces,
+                &workspace_id,
+                refresh,
+            )
+            .await?;
+            serde_json::to_value(stack).map_err(|err| err.to_string())
+        }
+        "get_turn_review_state" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let turn_id = parse_string(&params, "turnId")?;
+            let review = workspaces_core::get_turn_review_state_core(
+                &state.workspaces,
+                &workspace_id,
+                &turn_id,
+            )
+            .await?;
+            serde_json::to_value(review).map_err(|err| err.to_string())
+        }
+        "set_file_review_state" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let turn_id = parse_string(&params, "turnId")?;
+            let path = parse_string(&params, "path")?;
+            let review_state = parse_optional_value(&params, "reviewState")
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(|err| format!("invalid reviewState: {err}"))?
+                .ok_or("missing `reviewState`")?;
+            let note = parse_optional_string(&params, "note");
+            let review = workspaces_core::set_file_review_state_core(
+                &state.workspaces,
+                &workspace_id,
+                &turn_id,
+                &path,
+                review_state,
+                note,
+            )
+            .await?;
+            serde_json::to_value(review).map_err(|err| err.to_string())
+        }
         "file_read" => {
             let request = parse_file_read_request(&params)?;
             let response = state
@@ -1200,6 +2183,7 @@ async fn handle_rpc_request(
             let updated = state.update_app_settings(settings).await?;
             serde_json::to_value(updated).map_err(|err| err.to_string())
         }
+        "get_settings_revision" => Ok(json!(state.settings_revision.current())),
         "get_micode_config_path" => {
             let path = settings_core::get_micode_config_path_core()?;
             Ok(Value::String(path))
@@ -1212,10 +2196,61 @@ async fn handle_rpc_request(
             let workspace_id = parse_string(&params, "workspaceId")?;
             state.start_thread(workspace_id).await
         }
+        "build_run_kickoff_message" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let task = parse_string(&params, "task")?;
+            let branch = parse_optional_string(&params, "branch");
+            let kickoff = run_kickoff_core::build_run_kickoff_message_core(
+                &state.workspaces,
+                &state.app_settings,
+                &workspace_id,
+                &task,
+                branch,
+            )
+            .await?;
+            serde_json::to_value(kickoff).map_err(|err| err.to_string())
+        }
+        "start_run" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let task = parse_string(&params, "task")?;
+            let branch = parse_optional_string(&params, "branch");
+            let send_kickoff = parse_optional_bool(&params, "sendKickoff").unwrap_or(true);
+            micode_core::start_run_core(
+                &state.sessions,
+                &state.workspaces,
+                &state.app_settings,
+                workspace_id,
+                task,
+                branch,
+                send_kickoff,
+            )
+            .await.map_err(String::from)
+        }
         "resume_thread" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let thread_id = parse_string(&params, "threadId")?;
-            state.resume_thread(workspace_id, thread_id).await
+            state
+                .resume_thread(workspace_id, thread_id, client_id)
+                .await
+        }
+        "watch_thread" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            state.watch_thread(workspace_id, thread_id, client_id).await
+        }
+        "take_over_thread" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            state
+                .take_over_thread(workspace_id, thread_id, client_id)
+                .await
+        }
+        "get_thread_ownership" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            Ok(state
+                .get_thread_ownership(workspace_id, thread_id, client_id)
+                .await)
         }
         "fork_thread" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
@@ -1236,10 +2271,46 @@ async fn handle_rpc_request(
                 .list_mcp_server_status(workspace_id, cursor, limit)
                 .await
         }
+        "mcp_server_add" | "mcp_server_update" | "mcp_server_remove" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let name = parse_string(&params, "name")?;
+            let config = parse_optional_value(&params, "config");
+            let edit = match method {
+                "mcp_server_add" => micode_core::McpServerEdit::Add,
+                "mcp_server_update" => micode_core::McpServerEdit::Update,
+                _ => micode_core::McpServerEdit::Remove,
+            };
+            state
+                .edit_mcp_server(workspace_id, edit, name, config)
+                .await?;
+            Ok(json!({ "ok": true }))
+        }
         "archive_thread" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let thread_id = parse_string(&params, "threadId")?;
-            state.archive_thread(workspace_id, thread_id).await
+            let force = parse_optional_bool(&params, "force").unwrap_or(false);
+            state.archive_thread(workspace_id, thread_id, force).await
+        }
+        "list_archived_threads" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            micode_core::list_archived_threads_core(&state.sessions, workspace_id).await.map_err(String::from)
+        }
+        "restore_thread" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            micode_core::restore_thread_core(&state.sessions, workspace_id, thread_id).await.map_err(String::from)
+        }
+        "delete_thread_permanently" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let force = parse_optional_bool(&params, "force").unwrap_or(false);
+            micode_core::delete_thread_permanently_core(
+                &state.sessions,
+                workspace_id,
+                thread_id,
+                force,
+            )
+            .await.map_err(String::from)
         }
         "compact_thread" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
@@ -1252,6 +2323,169 @@ async fn handle_rpc_request(
             let name = parse_string(&params, "name")?;
             state.set_thread_name(workspace_id, thread_id, name).await
         }
+        "set_thread_tags" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let tags = parse_string_array(&params, "tags")?;
+            micode_core::set_thread_tags_core(&state.sessions, workspace_id, thread_id, tags).await.map_err(String::from)
+        }
+        "set_thread_pinned" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let pinned = parse_optional_bool(&params, "pinned")
+                .ok_or_else(|| "missing or invalid `pinned`".to_string())?;
+            micode_core::set_thread_pinned_core(&state.sessions, workspace_id, thread_id, pinned)
+                .await.map_err(String::from)
+        }
+        "search_threads" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let query = parse_string(&params, "query")?;
+            let limit = parse_optional_u64(&params, "limit").map(|limit| limit as usize);
+            micode_core::search_threads_core(&state.workspaces, workspace_id, query, limit).await.map_err(String::from)
+        }
+        "export_thread" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let format = parse_optional_string(&params, "format").unwrap_or_default();
+            let path = parse_optional_string(&params, "path");
+            let export = micode_core::export_thread_core(
+                &state.workspaces,
+                workspace_id,
+                thread_id,
+                format,
+                path,
+            )
+            .await?;
+            serde_json::to_value(export).map_err(|err| err.to_string())
+        }
+        "start_auto_run" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let request: AutoRunRequest =
+                serde_json::from_value(params.clone()).map_err(|err| err.to_string())?;
+            auto_run_core::start_auto_run_core(
+                &state.sessions,
+                &state.auto_runs,
+                workspace_id,
+                request,
+            )
+            .await.map_err(String::from)
+        }
+        "cancel_auto_run" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let run_id = parse_string(&params, "runId")?;
+            let interrupt = parse_optional_bool(&params, "interrupt").unwrap_or(false);
+            auto_run_core::cancel_auto_run_core(
+                &state.sessions,
+                &state.auto_runs,
+                workspace_id,
+                run_id,
+                interrupt,
+            )
+                .await.map_err(String::from)
+        }
+        "list_auto_runs" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            auto_run_core::list_auto_runs_core(&state.sessions, workspace_id).await.map_err(String::from)
+        }
+        "export_review_sarif" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let item_id = parse_optional_string(&params, "itemId");
+            let output_path = parse_optional_string(&params, "outputPath");
+            micode_core::export_review_sarif_core(
+                &state.sessions,
+                workspace_id,
+                thread_id,
+                item_id,
+                output_path,
+            )
+            .await.map_err(String::from)
+        }
+        "mark_thread_seen" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let item_seq = parse_optional_u64(&params, "itemSeq");
+            micode_core::mark_thread_seen_core(&state.sessions, workspace_id, thread_id, item_seq)
+                .await.map_err(String::from)
+        }
+        "get_unseen_items" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            micode_core::get_unseen_items_core(&state.sessions, workspace_id, thread_id).await.map_err(String::from)
+        }
+        "check_thread_sync" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            micode_core::check_thread_sync_core(&state.sessions, workspace_id, thread_id).await.map_err(String::from)
+        }
+        "sync_thread_from_cli" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            micode_core::sync_thread_from_cli_core(&state.sessions, workspace_id, thread_id).await.map_err(String::from)
+        }
+        "copy_thread_range" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let start_item_id = parse_optional_string(&params, "startItemId");
+            let end_item_id = parse_optional_string(&params, "endItemId");
+            let format = parse_optional_string(&params, "format");
+            micode_core::copy_thread_range_core(
+                &state.sessions,
+                workspace_id,
+                thread_id,
+                start_item_id,
+                end_item_id,
+                format,
+            )
+            .await.map_err(String::from)
+        }
+        "list_item_annotations" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            micode_core::list_item_annotations_core(&state.sessions, workspace_id, thread_id).await.map_err(String::from)
+        }
+        "add_item_annotation" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let item_id = parse_string(&params, "itemId")?;
+            let text = parse_string(&params, "text")?;
+            let author = parse_optional_string(&params, "author").unwrap_or_default();
+            micode_core::add_item_annotation_core(
+                &state.sessions,
+                workspace_id,
+                thread_id,
+                item_id,
+                text,
+                author,
+            )
+            .await.map_err(String::from)
+        }
+        "update_item_annotation" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let annotation_id = parse_string(&params, "annotationId")?;
+            let text = parse_string(&params, "text")?;
+            micode_core::update_item_annotation_core(
+                &state.sessions,
+                workspace_id,
+                thread_id,
+                annotation_id,
+                text,
+            )
+            .await.map_err(String::from)
+        }
+        "delete_item_annotation" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let annotation_id = parse_string(&params, "annotationId")?;
+            micode_core::delete_item_annotation_core(
+                &state.sessions,
+                workspace_id,
+                thread_id,
+                annotation_id,
+            )
+            .await.map_err(String::from)
+        }
         "send_user_message" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let thread_id = parse_string(&params, "threadId")?;
@@ -1261,6 +2495,9 @@ async fn handle_rpc_request(
             let access_mode = parse_optional_string(&params, "accessMode");
             let images = parse_optional_string_array(&params, "images");
             let collaboration_mode = parse_optional_value(&params, "collaborationMode");
+            let raw_text = parse_optional_bool(&params, "rawText");
+            let sampling_params = parse_optional_value(&params, "samplingParams");
+            let skip_redaction = parse_optional_bool(&params, "skipRedaction");
             state
                 .send_user_message(
                     workspace_id,
@@ -1271,15 +2508,39 @@ async fn handle_rpc_request(
                     access_mode,
                     images,
                     collaboration_mode,
+                    raw_text,
+                    sampling_params,
+                    skip_redaction,
+                    client_id,
+                    client_version,
                 )
                 .await
         }
+        "run_slash_command" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let command_name = parse_string(&params, "commandName")?;
+            let args = parse_optional_string(&params, "args");
+            state
+                .run_slash_command(workspace_id, thread_id, command_name, args, client_id)
+                .await
+        }
         "turn_interrupt" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let thread_id = parse_string(&params, "threadId")?;
             let turn_id = parse_string(&params, "turnId")?;
             state.turn_interrupt(workspace_id, thread_id, turn_id).await
         }
+        "cancel_tool_call" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let tool_call_id = parse_string(&params, "toolCallId")?;
+            state.cancel_tool_call(workspace_id, tool_call_id).await
+        }
+        "get_turn_live_changes" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            state.get_turn_live_changes(workspace_id, thread_id).await
+        }
         "start_review" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let thread_id = parse_string(&params, "threadId")?;
@@ -1289,13 +2550,19 @@ async fn handle_rpc_request(
                 .cloned()
                 .ok_or("missing `target`")?;
             let delivery = parse_optional_string(&params, "delivery");
+            let context = parse_optional_value(&params, "context")
+                .filter(|value| !value.is_null())
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(|err| format!("invalid review context options: {err}"))?;
             state
-                .start_review(workspace_id, thread_id, target, delivery)
+                .start_review(workspace_id, thread_id, target, delivery, context)
                 .await
         }
         "model_list" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
-            state.model_list(workspace_id).await
+            let refresh = parse_optional_bool(&params, "refresh").unwrap_or(false);
+            state.model_list(workspace_id, refresh).await
         }
         "collaboration_mode_list" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
@@ -1343,7 +2610,10 @@ async fn handle_rpc_request(
         "remember_approval_rule" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let command = parse_string_array(&params, "command")?;
-            state.remember_approval_rule(workspace_id, command).await
+            let decision = parse_rule_decision(&params)?;
+            state
+                .remember_approval_rule(workspace_id, command, decision)
+                .await
         }
         "list_approval_rules" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
@@ -1352,7 +2622,38 @@ async fn handle_rpc_request(
         "remove_approval_rule" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let command = parse_string_array(&params, "command")?;
-            state.remove_approval_rule(workspace_id, command).await
+            let decision = parse_rule_decision(&params)?;
+            state
+                .remove_approval_rule(workspace_id, command, decision)
+                .await
+        }
+        "approval_rules_list" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            state.approval_rules_list(workspace_id).await
+        }
+        "approval_rules_delete" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let rule_id = parse_string(&params, "ruleId")?;
+            state.approval_rules_delete(workspace_id, rule_id).await
+        }
+        "approval_rules_update" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let rule_id = parse_string(&params, "ruleId")?;
+            let pattern = parse_optional_string_array(&params, "pattern");
+            let decision = parse_optional_rule_decision(&params)?;
+            state
+                .approval_rules_update(workspace_id, rule_id, pattern, decision)
+                .await
+        }
+        "approval_rules_export" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let path = parse_string(&params, "path")?;
+            state.approval_rules_export(workspace_id, path).await
+        }
+        "approval_rules_import" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let path = parse_string(&params, "path")?;
+            state.approval_rules_import(workspace_id, path).await
         }
         _ => Err(format!("unknown method: {method}")),
     }
@@ -1387,6 +2688,8 @@ async fn handle_client(
 ) {
     let (reader, mut writer) = socket.into_split();
     let mut lines = BufReader::new(reader).lines();
+    // Identifies this connection for thread ownership; it lives as long as the socket.
+    let client_id = uuid::Uuid::new_v4().to_string();
 
     let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
     let write_task = tokio::spawn(async move {
@@ -1458,7 +2761,22 @@ async fn handle_client(
         }
 
         let client_version = format!("daemon-{}", env!("CARGO_PKG_VERSION"));
-        let result = handle_rpc_request(&state, &method, params, client_version).await;
+        let workspace_id = params
+            .get("workspaceId")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        state
+            .event_sink
+            .usage_counters
+            .record_command(&method, workspace_id.as_deref());
+        let result = state
+            .command_timings
+            .timed(
+                &method,
+                workspace_id.as_deref(),
+                handle_rpc_request(&state, &method, params, client_version, &client_id),
+            )
+            .await;
         let response = match result {
             Ok(result) => build_result_response(id, result),
             Err(message) => build_error_response(id, &message),
@@ -1468,6 +2786,7 @@ async fn handle_client(
         }
     }
 
+    state.release_client_threads(&client_id).await;
     drop(out_tx);
     if let Some(task) = events_task {
         task.abort();
@@ -1493,10 +2812,44 @@ fn main() {
         let (events_tx, _events_rx) = broadcast::channel::<DaemonEvent>(2048);
         let event_sink = DaemonEventSink {
             tx: events_tx.clone(),
+            usage_ledger: UsageLedger::new(Some(&config.data_dir)),
+            usage_counters: FeatureUsageCounters::new(),
         };
         let state = Arc::new(DaemonState::load(&config, event_sink));
         let config = Arc::new(config);
 
+        let liveness_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            let client_version = format!("daemon-{}", env!("CARGO_PKG_VERSION"));
+            loop {
+                tokio::time::sleep(workspaces_core::LIVENESS_CHECK_INTERVAL).await;
+                liveness_state.check_liveness(&client_version).await;
+            }
+        });
+
+        let resource_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(resource_monitor_core::RESOURCE_SAMPLE_INTERVAL).await;
+                let events = resource_monitor_core::sample_session_resources_core(
+                    &resource_state.sessions,
+                    &resource_state.app_settings,
+                )
+                .await;
+                for event in events {
+                    resource_state.event_sink.emit_app_server_event(event);
+                }
+            }
+        });
+
+        let maintenance_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(workspaces_core::STORE_MAINTENANCE_CHECK_INTERVAL).await;
+                maintenance_state.run_idle_store_maintenance().await;
+            }
+        });
+
         let listener = TcpListener::bind(config.listen)
             .await
             .unwrap_or_else(|err| panic!("failed to bind {}: {err}", config.listen));
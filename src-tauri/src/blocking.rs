@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::backend::event_methods;
+use crate::backend::events::{AppServerEvent, EventSink};
+use crate::dictation::{DictationEvent, DictationSessionState};
+use crate::event_sink::TauriEventSink;
+use crate::state::AppState;
+
+pub(crate) const DICTATION_BLOCKED_MESSAGE: &str =
+    "Dictation is paused while an approval is pending.";
+/// Notification kind that still goes out while an approval blocks the workspace.
+const APPROVAL_NOTIFICATION_KIND: &str = "approval";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BlockingChange {
+    Blocked,
+    Unblocked,
+}
+
+/// Tracks outstanding approval requests so dictation and notifications can stand back
+/// while a modal approval is shown for the focused workspace.
+#[derive(Debug, Default)]
+pub(crate) struct BlockingState {
+    focused_workspace_id: Option<String>,
+    /// Outstanding approval request ids per workspace, with the thread they belong to.
+    approvals: HashMap<String, HashMap<String, Option<String>>>,
+    dictation_paused: bool,
+    /// Transcripts finished while paused; released once the approval resolves.
+    held_transcripts: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BlockingSnapshot {
+    pub(crate) focused_workspace_id: Option<String>,
+    pub(crate) blocked: bool,
+    pub(crate) dictation_paused: bool,
+    /// Outstanding approval count per workspace.
+    pub(crate) pending_approvals: HashMap<String, usize>,
+}
+
+fn request_key(request_id: &Value) -> String {
+    match request_id {
+        Value::String(value) => value.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl BlockingState {
+    pub(crate) fn is_blocked(&self) -> bool {
+        self.focused_workspace_id
+            .as_ref()
+            .and_then(|workspace_id| self.approvals.get(workspace_id))
+            .is_some_and(|requests| !requests.is_empty())
+    }
+
+    fn track(&mut self, update: impl FnOnce(&mut Self)) -> Option<BlockingChange> {
+        let was_blocked = self.is_blocked();
+        update(self);
+        self.approvals.retain(|_, requests| !requests.is_empty());
+        match (was_blocked, self.is_blocked()) {
+            (false, true) => Some(BlockingChange::Blocked),
+            (true, false) => Some(BlockingChange::Unblocked),
+            _ => None,
+        }
+    }
+
+    /// Follows approval requests and the turn ends that make them moot.
+    pub(crate) fn observe_event(
+        &mut self,
+        workspace_id: &str,
+        message: &Value,
+    ) -> Option<BlockingChange> {
+        let method = message.get("method").and_then(Value::as_str)?;
+        let thread_id = message
+            .get("params")
+            .and_then(|params| params.get("threadId"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        match method {
+            event_methods::WORKSPACE_REQUEST_APPROVAL => {
+                let request_id = message.get("id")?;
+                self.track(|state| {
+                    state
+                        .approvals
+                        .entry(workspace_id.to_string())
+                        .or_default()
+                        .insert(request_key(request_id), thread_id);
+                })
+            }
+            event_methods::WORKSPACE_APPROVAL_RESOLVED => {
+                let request_id = message.get("params")?.get("requestId")?;
+                self.resolve_request(workspace_id, request_id)
+            }
+            event_methods::TURN_COMPLETED | event_methods::TURN_FAILED => {
+                let thread_id = thread_id?;
+                self.track(|state| {
+                    if let Some(requests) = state.approvals.get_mut(workspace_id) {
+                        requests.retain(|_, owner| owner.as_deref() != Some(thread_id.as_str()));
+                    }
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn resolve_request(
+        &mut self,
+        workspace_id: &str,
+        request_id: &Value,
+    ) -> Option<BlockingChange> {
+        self.track(|state| {
+            if let Some(requests) = state.approvals.get_mut(workspace_id) {
+                requests.remove(&request_key(request_id));
+            }
+        })
+    }
+
+    pub(crate) fn set_focused_workspace(
+        &mut self,
+        workspace_id: Option<String>,
+    ) -> Option<BlockingChange> {
+        self.track(|state| state.focused_workspace_id = workspace_id)
+    }
+
+    /// Returns `true` when dictation was not paused yet.
+    pub(crate) fn pause_dictation(&mut self) -> bool {
+        !std::mem::replace(&mut self.dictation_paused, true)
+    }
+
+    /// Lifts the pause and hands back the transcripts held meanwhile, or `None` when
+    /// dictation was not paused.
+    pub(crate) fn resume_dictation(&mut self) -> Option<Vec<String>> {
+        if !std::mem::replace(&mut self.dictation_paused, false) {
+            return None;
+        }
+        Some(std::mem::take(&mut self.held_transcripts))
+    }
+
+    /// Keeps `text` back while dictation is paused; otherwise returns it for delivery.
+    pub(crate) fn hold_transcript(&mut self, text: String) -> Option<String> {
+        if self.dictation_paused {
+            self.held_transcripts.push(text);
+            None
+        } else {
+            Some(text)
+        }
+    }
+
+    pub(crate) fn notification_suppressed(&self, workspace_id: &str, kind: Option<&str>) -> bool {
+        kind != Some(APPROVAL_NOTIFICATION_KIND)
+            && self.is_blocked()
+            && self.focused_workspace_id.as_deref() == Some(workspace_id)
+    }
+
+    pub(crate) fn snapshot(&self) -> BlockingSnapshot {
+        BlockingSnapshot {
+            focused_workspace_id: self.focused_workspace_id.clone(),
+            blocked: self.is_blocked(),
+            dictation_paused: self.dictation_paused,
+            pending_approvals: self
+                .approvals
+                .iter()
+                .map(|(workspace_id, requests)| (workspace_id.clone(), requests.len()))
+                .collect(),
+        }
+    }
+}
+
+fn with_blocking<T>(app: &AppHandle, run: impl FnOnce(&mut BlockingState) -> T) -> T {
+    let state = app.state::<AppState>();
+    let mut blocking = state
+        .blocking
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    run(&mut blocking)
+}
+
+fn emit_dictation_event(app: &AppHandle, method: &str, params: Value) {
+    let Some(workspace_id) = with_blocking(app, |state| state.focused_workspace_id.clone()) else {
+        return;
+    };
+    TauriEventSink::new(app.clone()).emit_app_server_event(AppServerEvent {
+        workspace_id,
+        message: json!({ "method": method, "params": params }),
+    });
+}
+
+/// Pauses an active dictation session when the focused workspace becomes blocked and
+/// resumes it, delivering held transcripts, once the approval resolves.
+fn apply_change(app: &AppHandle, change: Option<BlockingChange>) {
+    let Some(change) = change else {
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match change {
+            BlockingChange::Blocked => {
+                let session_state = {
+                    let state = app.state::<AppState>();
+                    let dictation = state.dictation.lock().await;
+                    dictation.session_state
+                };
+                if session_state == DictationSessionState::Idle
+                    || !with_blocking(&app, BlockingState::pause_dictation)
+                {
+                    return;
+                }
+                emit_dictation_event(
+                    &app,
+                    event_methods::DICTATION_PAUSED,
+                    json!({ "reason": "approvalPending" }),
+                );
+            }
+            BlockingChange::Unblocked => {
+                let Some(held) = with_blocking(&app, BlockingState::resume_dictation) else {
+                    return;
+                };
+                emit_dictation_event(
+                    &app,
+                    event_methods::DICTATION_RESUMED,
+                    json!({ "heldTranscripts": held.len() }),
+                );
+                for text in held {
+                    let _ = app.emit("dictation-event", DictationEvent::Transcript { text });
+                }
+            }
+        }
+    });
+}
+
+pub(crate) fn observe_app_server_event(app: &AppHandle, workspace_id: &str, message: &Value) {
+    let change = with_blocking(app, |state| state.observe_event(workspace_id, message));
+    apply_change(app, change);
+}
+
+pub(crate) fn resolve_approval(app: &AppHandle, workspace_id: &str, request_id: &Value) {
+    let change = with_blocking(app, |state| state.resolve_request(workspace_id, request_id));
+    apply_change(app, change);
+}
+
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+pub(crate) fn dictation_blocked(app: &AppHandle) -> bool {
+    with_blocking(app, |state| state.is_blocked())
+}
+
+/// Returns the transcript to emit now, or `None` when it was held for later.
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+pub(crate) fn hold_transcript(app: &AppHandle, text: String) -> Option<String> {
+    with_blocking(app, |state| state.hold_transcript(text))
+}
+
+#[tauri::command]
+pub(crate) async fn get_blocking_state(
+    state: State<'_, AppState>,
+) -> Result<BlockingSnapshot, String> {
+    let blocking = state.blocking.lock().map_err(|err| err.to_string())?;
+    Ok(blocking.snapshot())
+}
+
+#[tauri::command]
+pub(crate) async fn set_focused_workspace(
+    workspace_id: Option<String>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let change = with_blocking(&app, |state| state.set_focused_workspace(workspace_id));
+    apply_change(&app, change);
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) async fn should_dispatch_notification(
+    workspace_id: String,
+    kind: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let blocking = state.blocking.lock().map_err(|err| err.to_string())?;
+    Ok(!blocking.notification_suppressed(&workspace_id, kind.as_deref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approval_request(id: u64, thread_id: &str) -> Value {
+        json!({
+            "id": id,
+            "method": event_methods::WORKSPACE_REQUEST_APPROVAL,
+            "params": { "threadId": thread_id, "command": ["rm", "-rf", "build"] }
+        })
+    }
+
+    #[test]
+    fn pauses_dictation_and_notifications_until_the_approval_resolves() {
+        let mut state = BlockingState::default();
+        assert_eq!(state.set_focused_workspace(Some("ws-1".to_string())), None);
+
+        assert_eq!(
+            state.observe_event("ws-1", &approval_request(7, "t-1")),
+            Some(BlockingChange::Blocked)
+        );
+        assert!(state.pause_dictation());
+        assert!(!state.pause_dictation());
+        assert_eq!(state.hold_transcript("run the tests".to_string()), None);
+
+        assert!(state.notification_suppressed("ws-1", Some("thread")));
+        assert!(state.notification_suppressed("ws-1", None));
+        assert!(!state.notification_suppressed("ws-1", Some("approval")));
+        assert!(!state.notification_suppressed("ws-2", Some("thread")));
+        assert_eq!(state.snapshot().pending_approvals["ws-1"], 1);
+
+        assert_eq!(state.resolve_request("ws-1", &json!(8)), None);
+        assert_eq!(
+            state.resolve_request("ws-1", &json!(7)),
+            Some(BlockingChange::Unblocked)
+        );
+        assert_eq!(
+            state.resume_dictation(),
+            Some(vec!["run the tests".to_string()])
+        );
+        assert_eq!(state.resume_dictation(), None);
+        assert_eq!(
+            state.hold_transcript("next".to_string()),
+            Some("next".to_string())
+        );
+        assert!(!state.notification_suppressed("ws-1", Some("thread")));
+        assert!(state.snapshot().pending_approvals.is_empty());
+    }
+
+    #[test]
+    fn only_the_focused_workspace_blocks_and_turn_ends_clear_approvals() {
+        let mut state = BlockingState::default();
+        state.set_focused_workspace(Some("ws-1".to_string()));
+
+        assert_eq!(
+            state.observe_event("ws-2", &approval_request(1, "t-2")),
+            None
+        );
+        assert!(!state.is_blocked());
+        assert_eq!(
+            state.set_focused_workspace(Some("ws-2".to_string())),
+            Some(BlockingChange::Blocked)
+        );
+
+        state.observe_event("ws-2", &approval_request(2, "t-3"));
+        let completed = json!({
+            "method": event_methods::TURN_COMPLETED,
+            "params": { "threadId": "t-2", "turn": { "id": "turn-1" } }
+        });
+        assert_eq!(state.observe_event("ws-2", &completed), None);
+        assert_eq!(state.snapshot().pending_approvals["ws-2"], 1);
+
+        let failed = json!({
+            "method": event_methods::TURN_FAILED,
+            "params": { "threadId": "t-3", "reason": "exited" }
+        });
+        assert_eq!(
+            state.observe_event("ws-2", &failed),
+            Some(BlockingChange::Unblocked)
+        );
+
+        state.observe_event("ws-2", &approval_request(4, "t-4"));
+        let resolved = json!({
+            "method": event_methods::WORKSPACE_APPROVAL_RESOLVED,
+            "params": { "requestId": 4, "threadId": "t-4", "resolvedBy": "turn-cancelled" }
+        });
+        assert_eq!(
+            state.observe_event("ws-2", &resolved),
+            Some(BlockingChange::Unblocked)
+        );
+        assert_eq!(state.set_focused_workspace(None), None);
+    }
+}
@@ -0,0 +1,624 @@
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, State};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::app_info::collect_app_info;
+use crate::backend::app_server::now_ms;
+use crate::backend::event_methods;
+use crate::event_sink::TauriEventSink;
+use crate::remote_backend;
+use crate::shared::operations_core::{Operation, OperationOutcome};
+use crate::shared::usage_counters_core::FeatureUsage;
+use crate::shared::{command_timings_core, resource_monitor_core, workspaces_core};
+use crate::state::AppState;
+
+/// Lines kept from the end of each log file.
+const LOG_TAIL_LINES: usize = 2000;
+const REDACTED: &str = "[redacted]";
+const OMITTED: &str = "[omitted]";
+const USAGE_EXPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Keys whose string values are credentials, matched case-insensitively as substrings.
+const SECRET_KEYS: &[&str] = &[
+    "token",
+    "secret",
+    "password",
+    "apikey",
+    "api_key",
+    "authorization",
+    "cookie",
+    "credential",
+];
+
+/// Keys that carry prompt, reply or tool content; dropped unless conversations are included.
+const CONVERSATION_KEYS: &[&str] = &[
+    "text",
+    "content",
+    "prompt",
+    "delta",
+    "arguments",
+    "output",
+    "summaryText",
+    "title",
+    "preview",
+];
+
+/// Well-known credential prefixes scrubbed from free text.
+const SECRET_PREFIXES: &[&str] = &[
+    "sk-",
+    "ghp_",
+    "gho_",
+    "ghs_",
+    "github_pat_",
+    "xoxb-",
+    "xoxp-",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DiagnosticsEntry {
+    name: String,
+    size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DiagnosticsExport {
+    /// Where the bundle was (or would be) written.
+    path: String,
+    size_bytes: u64,
+    entries: Vec<DiagnosticsEntry>,
+    written: bool,
+}
+
+/// The redaction pass applied to every file in the bundle.
+struct Redactor {
+    include_conversations: bool,
+    /// `(original, replacement)` pairs, longest original first.
+    paths: Vec<(String, String)>,
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SECRET_KEYS.iter().any(|secret| key.contains(secret))
+}
+
+fn hash_path(path: &str) -> String {
+    let digest = Sha256::digest(path.as_bytes());
+    let hex: String = digest[..4]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("<path:{hex}>")
+}
+
+/// Scrubs bearer tokens, `secret=value` pairs and well-known credential prefixes.
+fn scrub_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut redact_next = false;
+    for piece in text.split_inclusive(char::is_whitespace) {
+        let word = piece.trim_end();
+        let trailing = &piece[word.len()..];
+        if word.is_empty() {
+            out.push_str(piece);
+            continue;
+        }
+        if redact_next {
+            redact_next = false;
+            out.push_str(REDACTED);
+            out.push_str(trailing);
+            continue;
+        }
+        if word.eq_ignore_ascii_case("bearer") || word.eq_ignore_ascii_case("basic") {
+            redact_next = true;
+            out.push_str(piece);
+            continue;
+        }
+        let bare = word.trim_matches(|c: char| matches!(c, '"' | '\'' | ',' | ';'));
+        if SECRET_PREFIXES
+            .iter()
+            .any(|prefix| bare.starts_with(prefix))
+            && bare.len() >= 20
+        {
+            out.push_str(&word.replace(bare, REDACTED));
+        } else if let Some((key, _)) = word
+            .split_once('=')
+            .or_else(|| word.split_once(':'))
+            .filter(|(key, value)| is_secret_key(key) && !value.is_empty())
+        {
+            out.push_str(key);
+            out.push_str(&word[key.len()..key.len() + 1]);
+            out.push_str(REDACTED);
+        } else {
+            out.push_str(word);
+        }
+        out.push_str(trailing);
+    }
+    out
+}
+
+impl Redactor {
+    fn new(include_conversations: bool, hash_paths: bool, workspace_paths: &[String]) -> Self {
+        let mut paths: Vec<(String, String)> = Vec::new();
+        if hash_paths {
+            for path in workspace_paths {
+                if !path.trim().is_empty() {
+                    paths.push((path.clone(), hash_path(path)));
+                }
+            }
+            if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))
+            {
+                let home = home.to_string_lossy().to_string();
+                if !home.is_empty() {
+                    paths.push((home, "<home>".to_string()));
+                }
+            }
+            paths.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+        }
+        Self {
+            include_conversations,
+            paths,
+        }
+    }
+
+    fn text(&self, text: &str) -> String {
+        let mut out = scrub_text(text);
+        for (original, replacement) in &self.paths {
+            out = out.replace(original, replacement);
+        }
+        out
+    }
+
+    fn value(&self, value: &Value) -> Value {
+        match value {
+            Value::String(text) => Value::String(self.text(text)),
+            Value::Array(values) => Value::Array(values.iter().map(|v| self.value(v)).collect()),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| {
+                        let redacted = if is_secret_key(key) && value.is_string() {
+                            if value.as_str().is_some_and(str::is_empty) {
+                                value.clone()
+                            } else {
+                                Value::String(REDACTED.to_string())
+                            }
+                        } else if !self.include_conversations
+                            && CONVERSATION_KEYS.contains(&key.as_str())
+                            && !value.is_null()
+                        {
+                            Value::String(OMITTED.to_string())
+                        } else {
+                            self.value(value)
+                        };
+                        (key.clone(), redacted)
+                    })
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    fn json(&self, value: &Value) -> String {
+        serde_json::to_string_pretty(&self.value(value)).unwrap_or_default()
+    }
+
+    /// Redacts NDJSON line by line; lines that are not JSON are scrubbed as text.
+    fn ndjson(&self, lines: &[String]) -> String {
+        let mut out = String::new();
+        for line in lines {
+            match serde_json::from_str::<Value>(line) {
+                Ok(value) => out.push_str(&self.value(&value).to_string()),
+                Err(_) => out.push_str(&self.text(line)),
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn tail_lines(path: &Path, limit: usize) -> Vec<String> {
+    let Ok(raw) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let lines: Vec<&str> = raw.lines().collect();
+    lines[lines.len().saturating_sub(limit)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn build_zip(files: &[(String, String)]) -> Result<Vec<u8>, String> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    for (name, contents) in files {
+        zip.start_file(name.as_str(), options)
+            .map_err(|err| err.to_string())?;
+        zip.write_all(contents.as_bytes())
+            .map_err(|err| err.to_string())?;
+    }
+    let cursor = zip.finish().map_err(|err| err.to_string())?;
+    Ok(cursor.into_inner())
+}
+
+/// Assembles a zip for bug reports: app/OS info, sanitized settings, the doctor report,
+/// per-workspace session info and stderr tails, resource usage, and recent activity and
+/// store maintenance logs. Everything goes through a redaction pass; message contents are
+/// dropped unless `include_conversations` is set. With `dry_run` nothing is written and the
+/// would-be size is returned, so the user can confirm first. Cancellable through
+/// `cancel_operation`; a cancelled export leaves no file behind.
+#[tauri::command]
+pub(crate) async fn export_diagnostics(
+    include_conversations: Option<bool>,
+    hash_paths: Option<bool>,
+    dry_run: Option<bool>,
+    destination: Option<String>,
+    operation_id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<OperationOutcome<DiagnosticsExport>, String> {
+    let operation = state.operations.start(
+        operation_id,
+        "diagnosticsExport",
+        TauriEventSink::new(app.clone()),
+    );
+    let result = build_diagnostics_export(
+        include_conversations,
+        hash_paths,
+        dry_run,
+        destination,
+        &operation,
+        state.clone(),
+        app,
+    )
+    .await;
+    operation.finish(result)
+}
+
+async fn build_diagnostics_export(
+    include_conversations: Option<bool>,
+    hash_paths: Option<bool>,
+    dry_run: Option<bool>,
+    destination: Option<String>,
+    operation: &Operation<'_, TauriEventSink>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Option<DiagnosticsExport>, String> {
+    operation.progress("collecting", None, None);
+    let include_conversations = include_conversations.unwrap_or(false);
+    let remote = remote_backend::is_remote_mode(&*state).await;
+    let workspaces: Vec<_> = state.workspaces.lock().await.values().cloned().collect();
+    let workspace_paths: Vec<String> = workspaces.iter().map(|entry| entry.path.clone()).collect();
+    let redactor = Redactor::new(
+        include_conversations,
+        hash_paths.unwrap_or(false),
+        &workspace_paths,
+    );
+    let mut files: Vec<(String, String)> = Vec::new();
+
+    let settings = state.app_settings.lock().await.clone();
+    let app_info = collect_app_info(&state, &app, hash_paths.unwrap_or(false)).await;
+    let mut system = serde_json::to_value(&app_info).unwrap_or_else(|_| json!({}));
+    system["backendMode"] = json!(if remote { "remote" } else { "local" });
+    system["generatedAtMs"] = json!(now_ms());
+    system["includeConversations"] = json!(include_conversations);
+    system["pathsHashed"] = json!(hash_paths.unwrap_or(false));
+    files.push(("system.json".to_string(), redactor.json(&system)));
+    files.push((
+        "settings.json".to_string(),
+        redactor.json(&serde_json::to_value(&settings).unwrap_or(Value::Null)),
+    ));
+
+    let doctor = crate::micode::micode_doctor(None, None, Some(false), state.clone());
+    let Some(doctor) = operation.token().run_until_cancelled(doctor).await else {
+        return Ok(None);
+    };
+    let doctor = doctor.unwrap_or_else(|error| json!({ "error": error }));
+    files.push(("doctor.json".to_string(), redactor.json(&doctor)));
+
+    let mut sessions = Vec::with_capacity(workspaces.len());
+    for entry in &workspaces {
+        if operation.is_cancelled() {
+            return Ok(None);
+        }
+        let info = if remote {
+            remote_backend::call_remote(
+                &*state,
+                app.clone(),
+                "get_session_info",
+                json!({ "workspaceId": entry.id }),
+            )
+            .await
+        } else {
+            workspaces_core::get_session_info_core(&entry.id, &state.sessions)
+                .await
+                .map_err(String::from)
+        };
+        sessions.push(json!({
+            "workspaceId": entry.id,
+            "name": entry.name,
+            "path": entry.path,
+            "kind": entry.kind,
+            "session": info.unwrap_or_else(|error| json!({ "error": error })),
+        }));
+        let session = state.sessions.lock().await.get(&entry.id).cloned();
+        if let Some(session) = session {
+            let stderr = session.recent_stderr();
+            if !stderr.is_empty() {
+                files.push((format!("stderr/{}.log", entry.id), redactor.ndjson(&stderr)));
+            }
+        }
+    }
+    files.push((
+        "sessions.json".to_string(),
+        redactor.json(&Value::Array(sessions)),
+    ));
+    files.push((
+        "resources.json".to_string(),
+        redactor.json(&json!({
+            "sessions": resource_monitor_core::session_resource_usage_core(&state.sessions).await,
+            "terminals": crate::terminal::terminal_resource_usage(&state).await
+        })),
+    ));
+
+    let activity = tail_lines(
+        &state
+            .logs_dir
+            .join("silver")
+            .join("global")
+            .join(&state.log_session_file),
+        LOG_TAIL_LINES,
+    );
+    files.push((
+        "logs/activity.ndjson".to_string(),
+        redactor.ndjson(&activity),
+    ));
+    let maintenance = tail_lines(
+        &state
+            .logs_dir
+            .join(workspaces_core::STORE_MAINTENANCE_LOG_FILE),
+        LOG_TAIL_LINES,
+    );
+    files.push((
+        "logs/store-maintenance.jsonl".to_string(),
+        redactor.ndjson(&maintenance),
+    ));
+
+    files.push((
+        "timings.json".to_string(),
+        redactor
+            .json(&serde_json::to_value(state.command_timings.snapshot()).unwrap_or(Value::Null)),
+    ));
+    let slow_commands = tail_lines(
+        &state
+            .logs_dir
+            .join(command_timings_core::SLOW_COMMAND_LOG_FILE),
+        LOG_TAIL_LINES,
+    );
+    files.push((
+        "logs/slow-commands.jsonl".to_string(),
+        redactor.ndjson(&slow_commands),
+    ));
+
+    if operation.is_cancelled() {
+        return Ok(None);
+    }
+    operation.progress("compressing", Some(files.len() as u64), Some(files.len() as u64));
+    let bytes = build_zip(&files)?;
+    let path = match destination.filter(|value| !value.trim().is_empty()) {
+        Some(destination) => PathBuf::from(destination),
+        None => state
+            .logs_dir
+            .join("diagnostics")
+            .join(format!("micode-diagnostics-{}.zip", now_ms())),
+    };
+    let dry_run = dry_run.unwrap_or(false);
+    if !dry_run {
+        operation.progress("writing", None, None);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        // Written beside the destination first, so a cancel or failure never leaves a
+        // truncated bundle under the final name.
+        let partial_path = partial_path(&path);
+        let written = std::fs::write(&partial_path, &bytes).map_err(|err| err.to_string());
+        if written.is_err() || operation.is_cancelled() {
+            let _ = std::fs::remove_file(&partial_path);
+            return written.map(|_| None);
+        }
+        if let Err(error) = std::fs::rename(&partial_path, &path) {
+            let _ = std::fs::remove_file(&partial_path);
+            return Err(error.to_string());
+        }
+    }
+    Ok(Some(DiagnosticsExport {
+        path: path.display().to_string(),
+        size_bytes: bytes.len() as u64,
+        entries: files
+            .iter()
+            .map(|(name, contents)| DiagnosticsEntry {
+                name: name.clone(),
+                size_bytes: contents.len() as u64,
+            })
+            .collect(),
+        written: !dry_run,
+    }))
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    path.with_file_name(name)
+}
+
+/// Per-command call counts and latency percentiles since launch or the last reset.
+#[tauri::command]
+pub(crate) async fn get_command_timings(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "get_command_timings", json!({})).await;
+    }
+    serde_json::to_value(state.command_timings.snapshot()).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub(crate) async fn reset_command_timings(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        remote_backend::call_remote(&*state, app, "reset_command_timings", json!({})).await?;
+        return Ok(());
+    }
+    state.command_timings.reset();
+    Ok(())
+}
+
+/// Opt-in feature-usage counters by day, read from the local counters file.
+#[tauri::command]
+pub(crate) async fn get_feature_usage(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<FeatureUsage, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response =
+            remote_backend::call_remote(&*state, app, "get_feature_usage", json!({})).await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+    Ok(state.usage_counters.feature_usage())
+}
+
+/// Writes the feature-usage counters to a JSON file the user can share; in remote mode the
+/// daemon's counters are written on this machine.
+#[tauri::command]
+pub(crate) async fn export_feature_usage(
+    destination: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<FeatureUsageExport, String> {
+    let usage = get_feature_usage(state.clone(), app).await?;
+    let data_dir = state
+        .settings_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    write_feature_usage_export(&usage, &data_dir, destination)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FeatureUsageExport {
+    pub(crate) path: String,
+    pub(crate) size_bytes: u64,
+}
+
+/// The shareable document written by `export_feature_usage`.
+fn feature_usage_export_json(usage: &FeatureUsage, generated_at_ms: u64) -> Value {
+    json!({
+        "schemaVersion": USAGE_EXPORT_SCHEMA_VERSION,
+        "generatedAtMs": generated_at_ms,
+        "appVersion": env!("CARGO_PKG_VERSION"),
+        "days": usage.counters.days,
+        "totals": usage.totals,
+    })
+}
+
+/// Writes `usage` to `destination`, or to `usage/` under `data_dir`. Nothing is sent
+/// anywhere; the user decides whether to share the file.
+fn write_feature_usage_export(
+    usage: &FeatureUsage,
+    data_dir: &Path,
+    destination: Option<String>,
+) -> Result<FeatureUsageExport, String> {
+    let now = now_ms();
+    let path = match destination.filter(|value| !value.trim().is_empty()) {
+        Some(destination) => PathBuf::from(destination),
+        None => data_dir
+            .join("usage")
+            .join(format!("feature-usage-{now}.json")),
+    };
+    let raw = serde_json::to_string_pretty(&feature_usage_export_json(usage, now))
+        .map_err(|err| err.to_string())?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    std::fs::write(&path, &raw).map_err(|err| err.to_string())?;
+    Ok(FeatureUsageExport {
+        path: path.display().to_string(),
+        size_bytes: raw.len() as u64,
+    })
+}
+
+/// Event schema version and every event method the backend can emit.
+#[tauri::command]
+pub(crate) async fn get_backend_capabilities(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "get_backend_capabilities", json!({}))
+            .await;
+    }
+    serde_json::to_value(event_methods::backend_capabilities()).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_zip, scrub_text, Redactor};
+    use serde_json::json;
+
+    #[test]
+    fn scrubs_secrets_from_free_text() {
+        assert_eq!(
+            scrub_text("Authorization: Bearer abc.def\nnext"),
+            "Authorization: Bearer [redacted]\nnext"
+        );
+        assert_eq!(
+            scrub_text("failed with api_key=xyz123 and token:"),
+            "failed with api_key=[redacted] and token:"
+        );
+        assert_eq!(
+            scrub_text("key \"sk-abcdefghijklmnopqrstuv\" rejected"),
+            "key \"[redacted]\" rejected"
+        );
+    }
+
+    #[test]
+    fn drops_conversations_and_hashes_paths() {
+        let redactor = Redactor::new(false, true, &["/work/secret-project".to_string()]);
+        let value = redactor.value(&json!({
+            "remoteBackendToken": "abc",
+            "maxTokens": 42,
+            "payload": {
+                "method": "item/completed",
+                "cwd": "/work/secret-project/src",
+                "item": { "type": "agentMessage", "text": "the reply" }
+            }
+        }));
+        assert_eq!(value["remoteBackendToken"], "[redacted]");
+        assert_eq!(value["maxTokens"], 42);
+        assert_eq!(value["payload"]["item"]["text"], "[omitted]");
+        assert_eq!(value["payload"]["item"]["type"], "agentMessage");
+        let cwd = value["payload"]["cwd"].as_str().expect("cwd");
+        assert!(cwd.starts_with("<path:") && cwd.ends_with("/src"));
+
+        let redactor = Redactor::new(true, false, &[]);
+        let value = redactor.value(&json!({ "text": "the reply", "cwd": "/work/a" }));
+        assert_eq!(value["text"], "the reply");
+        assert_eq!(value["cwd"], "/work/a");
+    }
+
+    #[test]
+    fn bundle_is_a_readable_zip() {
+        let bytes = build_zip(&[("system.json".to_string(), "{}".to_string())]).expect("zip");
+        let archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).expect("read zip");
+        assert_eq!(archive.len(), 1);
+    }
+}
@@ -8,6 +8,7 @@ use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::io::AsyncWriteExt;
 use tokio::sync::oneshot;
 
+use crate::http_client;
 use crate::state::AppState;
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
@@ -482,11 +483,14 @@ pub(crate) async fn dictation_download_model(
                 return;
             }
         };
-        let client = match reqwest::Client::builder()
-            .connect_timeout(Duration::from_secs(10))
-            .timeout(Duration::from_secs(30 * 60))
-            .build()
-        {
+        let proxy = http_client::proxy_for(&state, None).await;
+        let client = match http_client::http_client_builder(proxy.as_ref()).and_then(|builder| {
+            builder
+                .connect_timeout(Duration::from_secs(10))
+                .timeout(Duration::from_secs(30 * 60))
+                .build()
+                .map_err(|error| error.to_string())
+        }) {
             Ok(client) => client,
             Err(error) => {
                 let status = DictationModelStatus {
@@ -742,6 +746,16 @@ pub(crate) async fn dictation_start(
     app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<DictationSessionState, String> {
+    if crate::blocking::dictation_blocked(&app) {
+        let message = crate::blocking::DICTATION_BLOCKED_MESSAGE.to_string();
+        emit_event(
+            &app,
+            DictationEvent::Error {
+                message: message.clone(),
+            },
+        );
+        return Err(message);
+    }
     let model_id = resolve_model_id(&state, None).await;
     let model_status = refresh_status(&app, &state, &model_id).await;
     if model_status.state != DictationModelState::Ready {
@@ -1020,7 +1034,9 @@ pub(crate) async fn dictation_stop(
         match outcome {
             Ok(text) => {
                 if !text.trim().is_empty() {
-                    emit_event(&app_handle, DictationEvent::Transcript { text });
+                    if let Some(text) = crate::blocking::hold_transcript(&app_handle, text) {
+                        emit_event(&app_handle, DictationEvent::Transcript { text });
+                    }
                 }
             }
             Err(message) => {
@@ -1,6 +1,8 @@
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager};
 
 use crate::backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
+use crate::event_subscriptions::emit_routed_event;
+use crate::state::AppState;
 
 #[derive(Clone)]
 pub(crate) struct TauriEventSink {
@@ -15,7 +17,15 @@ impl TauriEventSink {
 
 impl EventSink for TauriEventSink {
     fn emit_app_server_event(&self, event: AppServerEvent) {
-        let _ = self.app.emit("app-server-event", event);
+        crate::blocking::observe_app_server_event(&self.app, &event.workspace_id, &event.message);
+        let state = self.app.state::<AppState>();
+        state
+            .usage_counters
+            .record_event(&event.workspace_id, &event.message);
+        state
+            .usage_ledger
+            .record_token_usage_event(&event.workspace_id, &event.message);
+        emit_routed_event(&self.app, event);
     }
 
     fn emit_terminal_output(&self, event: TerminalOutput) {
@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use crate::backend::event_methods;
+use crate::backend::events::AppServerEvent;
+use crate::state::AppState;
+
+/// Subscribes to every workspace when passed to `set_event_subscriptions`.
+const WILDCARD: &str = "*";
+/// Held events per workspace before they are flushed anyway, so an idle view never
+/// pins an unbounded backlog.
+const MAX_HELD_EVENTS: usize = 1_000;
+/// Delivered even for unsubscribed workspaces: they need the user's attention or change
+/// what the sidebar shows. Anything held for the workspace is flushed before them.
+const ALWAYS_DELIVERED: &[&str] = &[
+    event_methods::MICODE_CONNECTED,
+    event_methods::MICODE_DISCONNECTED,
+    event_methods::MICODE_RECONNECT_FAILED,
+    event_methods::MICODE_RESTART_FAILED,
+    event_methods::THREAD_STARTED,
+    event_methods::TURN_STARTED,
+    event_methods::TURN_COMPLETED,
+    event_methods::TURN_FAILED,
+    event_methods::TURN_CANCELLED,
+    event_methods::TURN_TIMEOUT,
+    event_methods::TURN_STALLED,
+    event_methods::TURN_RESUMED_STREAMING,
+    event_methods::WORKSPACE_APPROVAL_RESOLVED,
+];
+
+/// Which workspaces the webview renders. Events for the others are held, with streamed
+/// deltas merged, and replayed as a compact catch-up once the workspace is shown again.
+#[derive(Default)]
+pub(crate) struct EventSubscriptions {
+    /// `None` subscribes to every workspace.
+    workspaces: Option<HashSet<String>>,
+    held: HashMap<String, Vec<AppServerEvent>>,
+}
+
+fn is_always_delivered(event: &AppServerEvent) -> bool {
+    // Requests carry an id and wait for an answer (approvals, user input).
+    if event.message.get("id").is_some() {
+        return true;
+    }
+    event
+        .message
+        .get("method")
+        .and_then(Value::as_str)
+        .is_some_and(|method| ALWAYS_DELIVERED.contains(&method))
+}
+
+fn delta_method(message: &Value) -> Option<&str> {
+    message
+        .get("method")
+        .and_then(Value::as_str)
+        .filter(|method| method.ends_with("/delta") || method.ends_with("Delta"))
+}
+
+/// Appends the delta of `next` to `last` when both stream into the same item.
+fn merge_delta(last: &mut AppServerEvent, next: &AppServerEvent) -> bool {
+    let Some(method) = delta_method(&next.message) else {
+        return false;
+    };
+    if delta_method(&last.message) != Some(method) {
+        return false;
+    }
+    let (Some(last_params), Some(next_params)) = (
+        last.message
+            .get_mut("params")
+            .and_then(Value::as_object_mut),
+        next.message.get("params").and_then(Value::as_object),
+    ) else {
+        return false;
+    };
+    let same_target = last_params.len() == next_params.len()
+        && next_params
+            .iter()
+            .all(|(key, value)| key == "delta" || last_params.get(key) == Some(value));
+    if !same_target {
+        return false;
+    }
+    let (Some(Value::String(held)), Some(Value::String(delta))) =
+        (last_params.get_mut("delta"), next_params.get("delta"))
+    else {
+        return false;
+    };
+    held.push_str(delta);
+    true
+}
+
+impl EventSubscriptions {
+    fn is_subscribed(&self, workspace_id: &str) -> bool {
+        workspace_id.is_empty()
+            || self
+                .workspaces
+                .as_ref()
+                .is_none_or(|workspaces| workspaces.contains(workspace_id))
+    }
+
+    /// Returns the events to emit now for `event`, which may be none while it is held.
+    pub(crate) fn route(&mut self, event: AppServerEvent) -> Vec<AppServerEvent> {
+        if self.is_subscribed(&event.workspace_id) {
+            return vec![event];
+        }
+        if is_always_delivered(&event) {
+            let mut events = self.held.remove(&event.workspace_id).unwrap_or_default();
+            events.push(event);
+            return events;
+        }
+        let held = self.held.entry(event.workspace_id.clone()).or_default();
+        if held
+            .last_mut()
+            .is_some_and(|last| merge_delta(last, &event))
+        {
+            return Vec::new();
+        }
+        held.push(event);
+        if held.len() >= MAX_HELD_EVENTS {
+            return std::mem::take(held);
+        }
+        Vec::new()
+    }
+
+    /// Replaces the subscriptions; `"*"` subscribes to everything. Returns the catch-up
+    /// held for the workspaces that are subscribed now.
+    pub(crate) fn subscribe(&mut self, workspace_ids: Vec<String>) -> Vec<AppServerEvent> {
+        self.workspaces = if workspace_ids.iter().any(|id| id == WILDCARD) {
+            None
+        } else {
+            Some(workspace_ids.into_iter().collect())
+        };
+        let mut workspace_ids: Vec<String> = self
+            .held
+            .keys()
+            .filter(|workspace_id| self.is_subscribed(workspace_id))
+            .cloned()
+            .collect();
+        workspace_ids.sort();
+        workspace_ids
+            .into_iter()
+            .flat_map(|workspace_id| self.held.remove(&workspace_id).unwrap_or_default())
+            .collect()
+    }
+
+    /// Back to the wildcard after a webview reload. The held events are dropped: the new
+    /// page loads its threads from scratch.
+    pub(crate) fn reset(&mut self) {
+        self.workspaces = None;
+        self.held.clear();
+    }
+}
+
+fn with_subscriptions<R: Runtime, T>(
+    app: &AppHandle<R>,
+    run: impl FnOnce(&mut EventSubscriptions) -> T,
+) -> Option<T> {
+    let state = app.try_state::<AppState>()?;
+    let mut subscriptions = state
+        .event_subscriptions
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    Some(run(&mut subscriptions))
+}
+
+fn emit_all<R: Runtime>(app: &AppHandle<R>, events: Vec<AppServerEvent>) {
+    for event in events {
+        let _ = app.emit("app-server-event", event);
+    }
+}
+
+/// Emits an outgoing event through the subscriptions. Emitting happens under the lock, so
+/// a catch-up and the events routed after it reach the webview in order. Before the app
+/// state exists everything is delivered.
+pub(crate) fn emit_routed_event<R: Runtime>(app: &AppHandle<R>, event: AppServerEvent) {
+    let mut event = Some(event);
+    with_subscriptions(app, |subscriptions| {
+        if let Some(event) = event.take() {
+            emit_all(app, subscriptions.route(event));
+        }
+    });
+    if let Some(event) = event {
+        let _ = app.emit("app-server-event", event);
+    }
+}
+
+pub(crate) fn reset_event_subscriptions(app: &AppHandle) {
+    with_subscriptions(app, EventSubscriptions::reset);
+}
+
+/// Sets the workspaces the webview renders, or `["*"]` for all of them. Local only: the
+/// events are filtered where this app emits them, in remote mode too.
+#[tauri::command]
+pub(crate) async fn set_event_subscriptions(
+    workspace_ids: Vec<String>,
+    app: AppHandle,
+) -> Result<(), String> {
+    with_subscriptions(&app, |subscriptions| {
+        emit_all(&app, subscriptions.subscribe(workspace_ids));
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(workspace_id: &str, method: &str, params: Value) -> AppServerEvent {
+        AppServerEvent {
+            workspace_id: workspace_id.to_string(),
+            message: json!({ "method": method, "params": params }),
+        }
+    }
+
+    fn delta(workspace_id: &str, item_id: &str, text: &str) -> AppServerEvent {
+        event(
+            workspace_id,
+            event_methods::ITEM_AGENT_MESSAGE_DELTA,
+            json!({ "threadId": "thread-1", "itemId": item_id, "delta": text }),
+        )
+    }
+
+    fn messages(events: Vec<AppServerEvent>) -> Vec<Value> {
+        events.into_iter().map(|event| event.message).collect()
+    }
+
+    #[test]
+    fn holds_and_coalesces_events_of_unsubscribed_workspaces() {
+        let mut subscriptions = EventSubscriptions::default();
+        assert_eq!(subscriptions.route(delta("ws-2", "item-1", "a")).len(), 1);
+
+        assert!(subscriptions.subscribe(vec!["ws-1".to_string()]).is_empty());
+        assert_eq!(subscriptions.route(delta("ws-1", "item-1", "a")).len(), 1);
+        assert!(subscriptions
+            .route(delta("ws-2", "item-1", "He"))
+            .is_empty());
+        assert!(subscriptions
+            .route(delta("ws-2", "item-1", "llo"))
+            .is_empty());
+        assert!(subscriptions.route(delta("ws-2", "item-2", "!")).is_empty());
+
+        let approval = AppServerEvent {
+            workspace_id: "ws-3".to_string(),
+            message: json!({ "id": 7, "method": "workspace/requestApproval", "params": {} }),
+        };
+        assert_eq!(subscriptions.route(approval).len(), 1);
+
+        let catch_up = subscriptions.subscribe(vec![WILDCARD.to_string()]);
+        assert_eq!(
+            messages(catch_up),
+            vec![
+                delta("ws-2", "item-1", "Hello").message,
+                delta("ws-2", "item-2", "!").message,
+            ]
+        );
+        assert_eq!(subscriptions.route(delta("ws-2", "item-1", "b")).len(), 1);
+    }
+
+    #[test]
+    fn flushes_held_events_before_priority_ones() {
+        let mut subscriptions = EventSubscriptions::default();
+        subscriptions.subscribe(vec![]);
+        let started = event(
+            "ws-1",
+            event_methods::ITEM_STARTED,
+            json!({ "threadId": "thread-1", "item": { "id": "item-1" } }),
+        );
+        assert!(subscriptions.route(started.clone()).is_empty());
+        assert!(subscriptions
+            .route(delta("ws-1", "item-1", "Hi"))
+            .is_empty());
+        let completed = event(
+            "ws-1",
+            event_methods::TURN_COMPLETED,
+            json!({ "threadId": "thread-1" }),
+        );
+        assert_eq!(
+            messages(subscriptions.route(completed.clone())),
+            vec![
+                started.message,
+                delta("ws-1", "item-1", "Hi").message,
+                completed.message
+            ]
+        );
+
+        assert!(subscriptions.route(delta("ws-1", "item-1", "x")).is_empty());
+        subscriptions.reset();
+        assert!(subscriptions.subscribe(vec!["ws-1".to_string()]).is_empty());
+        assert_eq!(
+            subscriptions
+                .route(event(
+                    "",
+                    event_methods::NOTIFICATIONS_UNREAD_CHANGED,
+                    json!({})
+                ))
+                .len(),
+            1
+        );
+    }
+}
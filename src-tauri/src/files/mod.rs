@@ -3,6 +3,7 @@ use tauri::{AppHandle, State};
 
 use self::io::TextFileResponse;
 use self::policy::{FileKind, FileScope};
+use crate::event_subscriptions::emit_routed_event;
 use crate::remote_backend;
 use crate::shared::files_core::{file_read_core, file_write_core};
 use crate::state::AppState;
@@ -56,7 +57,19 @@ async fn file_write_impl(
         return Ok(());
     }
 
-    file_write_core(&state.workspaces, scope, kind, workspace_id, content).await
+    if let Some(event) = file_write_core(
+        &state.workspaces,
+        &state.settings_revision,
+        scope,
+        kind,
+        workspace_id,
+        content,
+    )
+    .await?
+    {
+        emit_routed_event(app, event);
+    }
+    Ok(())
 }
 
 #[tauri::command]
@@ -0,0 +1,404 @@
+use std::path::Path;
+
+use git2::{Repository, RepositoryState};
+use serde::Serialize;
+
+use crate::backend::turn_artifacts::relative_to_root;
+use crate::shared::git_core::{run_git_command, run_git_command_bytes};
+
+const OURS_MARKER: &str = "<<<<<<<";
+const BASE_MARKER: &str = "|||||||";
+const SEPARATOR_MARKER: &str = "=======";
+const THEIRS_MARKER: &str = ">>>>>>>";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConflictHunk {
+    /// 1-based lines of the `<<<<<<<` and `>>>>>>>` markers in the working copy.
+    pub(crate) start_line: usize,
+    pub(crate) end_line: usize,
+    /// Marker labels, e.g. `HEAD` and the merged branch name.
+    pub(crate) ours_label: String,
+    pub(crate) theirs_label: String,
+    pub(crate) base_label: Option<String>,
+    pub(crate) ours: String,
+    /// Only present when the file was written with `merge.conflictStyle=diff3`/`zdiff3`.
+    pub(crate) base: Option<String>,
+    pub(crate) theirs: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConflictDetail {
+    pub(crate) path: String,
+    /// `merge`, `rebase`, `cherryPick`, `revert` or `am`; `None` for conflicts left by
+    /// operations without repository state, such as `stash pop` or worktree apply.
+    pub(crate) operation: Option<String>,
+    pub(crate) binary: bool,
+    /// Index stages present for the path: 1 base, 2 ours, 3 theirs. A missing 2 or 3
+    /// means that side deleted the file.
+    pub(crate) stages: Vec<u8>,
+    pub(crate) base: Option<String>,
+    pub(crate) ours: Option<String>,
+    pub(crate) theirs: Option<String>,
+    pub(crate) working: Option<String>,
+    pub(crate) hunks: Vec<ConflictHunk>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ConflictResolution {
+    Ours,
+    Theirs,
+    Content(String),
+}
+
+impl ConflictResolution {
+    pub(crate) fn parse(resolution: &str, content: Option<String>) -> Result<Self, String> {
+        match resolution {
+            "ours" => Ok(Self::Ours),
+            "theirs" => Ok(Self::Theirs),
+            "content" => content
+                .map(Self::Content)
+                .ok_or_else(|| "A `content` resolution needs the resolved text".to_string()),
+            other => Err(format!("Unknown conflict resolution `{other}`")),
+        }
+    }
+}
+
+fn marker_label<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(marker)?;
+    let rest = rest.trim_end_matches(['\r', '\n']);
+    if rest.is_empty() {
+        return Some("");
+    }
+    rest.strip_prefix(' ')
+}
+
+/// Parses conflict markers from a working copy, accepting both the default and the
+/// diff3 styles. An unterminated conflict is ignored.
+pub(crate) fn parse_conflict_hunks(text: &str) -> Vec<ConflictHunk> {
+    enum Section {
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let mut hunks = Vec::new();
+    let mut current: Option<(ConflictHunk, Section)> = None;
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        let line_number = index + 1;
+        let Some((hunk, section)) = current.as_mut() else {
+            if let Some(label) = marker_label(line, OURS_MARKER) {
+                current = Some((
+                    ConflictHunk {
+                        start_line: line_number,
+                        end_line: line_number,
+                        ours_label: label.to_string(),
+                        theirs_label: String::new(),
+                        base_label: None,
+                        ours: String::new(),
+                        base: None,
+                        theirs: String::new(),
+                    },
+                    Section::Ours,
+                ));
+            }
+            continue;
+        };
+        match section {
+            Section::Ours | Section::Base if marker_label(line, SEPARATOR_MARKER) == Some("") => {
+                *section = Section::Theirs;
+            }
+            Section::Ours => {
+                if let Some(label) = marker_label(line, BASE_MARKER) {
+                    hunk.base_label = Some(label.to_string());
+                    hunk.base = Some(String::new());
+                    *section = Section::Base;
+                } else {
+                    hunk.ours.push_str(line);
+                }
+            }
+            Section::Base => {
+                if let Some(base) = hunk.base.as_mut() {
+                    base.push_str(line);
+                }
+            }
+            Section::Theirs => {
+                if let Some(label) = marker_label(line, THEIRS_MARKER) {
+                    hunk.theirs_label = label.to_string();
+                    hunk.end_line = line_number;
+                    hunks.extend(current.take().map(|(hunk, _)| hunk));
+                } else {
+                    hunk.theirs.push_str(line);
+                }
+            }
+        }
+    }
+    hunks
+}
+
+fn bytes_look_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8192).any(|byte| *byte == 0)
+}
+
+fn operation_name(repo_root: &Path) -> Option<String> {
+    let repo = Repository::open(repo_root).ok()?;
+    let name = match repo.state() {
+        RepositoryState::Merge => "merge",
+        RepositoryState::Rebase
+        | RepositoryState::RebaseInteractive
+        | RepositoryState::RebaseMerge => "rebase",
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => "cherryPick",
+        RepositoryState::Revert | RepositoryState::RevertSequence => "revert",
+        RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => "am",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+/// Rejects paths that escape the repository before anything is read or written.
+fn checked_relative_path(repo_root: &Path, path: &str) -> Result<String, String> {
+    relative_to_root(repo_root, path).ok_or_else(|| format!("Invalid path `{path}`"))
+}
+
+/// Index stages recorded for an unmerged path; empty when the path has no conflict.
+async fn conflict_stages(repo_root: &Path, path: &str) -> Result<Vec<u8>, String> {
+    let output = run_git_command(
+        &repo_root.to_path_buf(),
+        &["ls-files", "--unmerged", "--", path],
+    )
+    .await?;
+    let mut stages: Vec<u8> = output
+        .lines()
+        .filter_map(|line| line.split('\t').next())
+        .filter_map(|meta| meta.split_whitespace().nth(2))
+        .filter_map(|stage| stage.parse().ok())
+        .collect();
+    stages.sort_unstable();
+    stages.dedup();
+    Ok(stages)
+}
+
+async fn require_conflict(repo_root: &Path, path: &str) -> Result<Vec<u8>, String> {
+    let stages = conflict_stages(repo_root, path).await?;
+    if stages.is_empty() {
+        return Err(format!("`{path}` has no unresolved conflict"));
+    }
+    Ok(stages)
+}
+
+async fn stage_content(repo_root: &Path, stage: u8, path: &str) -> Result<Vec<u8>, String> {
+    let spec = format!(":{stage}:{path}");
+    run_git_command_bytes(&repo_root.to_path_buf(), &["show", &spec]).await
+}
+
+pub(crate) async fn conflict_detail(
+    repo_root: &Path,
+    path: &str,
+) -> Result<ConflictDetail, String> {
+    let path = checked_relative_path(repo_root, path)?;
+    let stages = require_conflict(repo_root, &path).await?;
+    let mut versions: [Option<Vec<u8>>; 3] = [None, None, None];
+    for stage in &stages {
+        if let 1..=3 = stage {
+            versions[usize::from(*stage) - 1] =
+                Some(stage_content(repo_root, *stage, &path).await?);
+        }
+    }
+    let working = std::fs::read(repo_root.join(&path)).ok();
+    let binary = versions
+        .iter()
+        .chain(std::iter::once(&working))
+        .flatten()
+        .any(|bytes| bytes_look_binary(bytes));
+    let [base, ours, theirs] = versions;
+    let text = |bytes: Option<Vec<u8>>| {
+        bytes
+            .filter(|_| !binary)
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    };
+    let working = text(working);
+    let hunks = working
+        .as_deref()
+        .map(parse_conflict_hunks)
+        .unwrap_or_default();
+    Ok(ConflictDetail {
+        operation: operation_name(repo_root),
+        binary,
+        stages,
+        base: text(base),
+        ours: text(ours),
+        theirs: text(theirs),
+        working,
+        hunks,
+        path,
+    })
+}
+
+/// Writes the chosen resolution and stages it. Picking a side that deleted the file
+/// removes it; binary conflicts can only be resolved by picking a side.
+pub(crate) async fn resolve_conflict(
+    repo_root: &Path,
+    path: &str,
+    resolution: &ConflictResolution,
+) -> Result<(), String> {
+    let path = checked_relative_path(repo_root, path)?;
+    let stages = require_conflict(repo_root, &path).await?;
+    let repo_path = repo_root.to_path_buf();
+    let (stage, flag) = match resolution {
+        ConflictResolution::Ours => (2, "--ours"),
+        ConflictResolution::Theirs => (3, "--theirs"),
+        ConflictResolution::Content(content) => {
+            let mut binary = false;
+            for stage in &stages {
+                binary |= bytes_look_binary(&stage_content(repo_root, *stage, &path).await?);
+            }
+            if binary {
+                return Err(
+                    "Binary conflicts can only be resolved by picking ours or theirs".to_string(),
+                );
+            }
+            std::fs::write(repo_root.join(&path), content).map_err(|err| err.to_string())?;
+            run_git_command(&repo_path, &["add", "--", &path]).await?;
+            return Ok(());
+        }
+    };
+    if stages.contains(&stage) {
+        run_git_command(&repo_path, &["checkout", flag, "--", &path]).await?;
+        run_git_command(&repo_path, &["add", "--", &path]).await?;
+    } else {
+        run_git_command(&repo_path, &["rm", "--quiet", "--", &path]).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    fn git(root: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(root)
+            .output()
+            .expect("run git");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    /// Repository left mid-merge with `notes.txt` edited on both sides.
+    fn conflicted_repo(base: &[u8], ours: &[u8], theirs: &[u8]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("micode-conflicts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create repo root");
+        git(&root, &["init", "--quiet", "--initial-branch=main"]);
+        std::fs::write(root.join("notes.txt"), base).expect("write base");
+        git(&root, &["add", "."]);
+        git(&root, &["commit", "--quiet", "-m", "base"]);
+        git(&root, &["checkout", "--quiet", "-b", "feature"]);
+        std::fs::write(root.join("notes.txt"), theirs).expect("write theirs");
+        git(&root, &["commit", "--quiet", "-am", "theirs"]);
+        git(&root, &["checkout", "--quiet", "main"]);
+        std::fs::write(root.join("notes.txt"), ours).expect("write ours");
+        git(&root, &["commit", "--quiet", "-am", "ours"]);
+        git(&root, &["merge", "--quiet", "feature"]);
+        root
+    }
+
+    #[test]
+    fn parses_default_and_diff3_hunks() {
+        let text = "a\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> feature\nb\n\
+            <<<<<<< HEAD\nx\n||||||| base\nw\n=======\ny\n>>>>>>> feature\n";
+        let hunks = parse_conflict_hunks(text);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!((hunks[0].start_line, hunks[0].end_line), (2, 6));
+        assert_eq!(hunks[0].ours, "ours\n");
+        assert_eq!(hunks[0].theirs, "theirs\n");
+        assert_eq!(hunks[0].ours_label, "HEAD");
+        assert_eq!(hunks[0].theirs_label, "feature");
+        assert_eq!(hunks[1].base.as_deref(), Some("w\n"));
+        assert_eq!(hunks[1].base_label.as_deref(), Some("base"));
+        assert!(parse_conflict_hunks("<<<<<<< HEAD\nunterminated\n").is_empty());
+    }
+
+    #[test]
+    fn reports_and_resolves_text_conflicts_both_ways() {
+        let runtime = tokio::runtime::Runtime::new().expect("create runtime");
+        for resolution in [ConflictResolution::Ours, ConflictResolution::Theirs] {
+            let root = conflicted_repo(b"one\ntwo\n", b"one\nmain\n", b"one\nfeature\n");
+            runtime.block_on(async {
+                let detail = conflict_detail(&root, "notes.txt").await.expect("detail");
+                assert_eq!(detail.operation.as_deref(), Some("merge"));
+                assert_eq!(detail.stages, vec![1, 2, 3]);
+                assert!(!detail.binary);
+                assert_eq!(detail.base.as_deref(), Some("one\ntwo\n"));
+                assert_eq!(detail.ours.as_deref(), Some("one\nmain\n"));
+                assert_eq!(detail.theirs.as_deref(), Some("one\nfeature\n"));
+                assert_eq!(detail.hunks.len(), 1);
+                assert_eq!(detail.hunks[0].ours, "main\n");
+                assert_eq!(detail.hunks[0].theirs, "feature\n");
+
+                resolve_conflict(&root, "notes.txt", &resolution)
+                    .await
+                    .expect("resolve");
+                assert!(conflict_detail(&root, "notes.txt").await.is_err());
+            });
+            let expected = match resolution {
+                ConflictResolution::Ours => "one\nmain\n",
+                _ => "one\nfeature\n",
+            };
+            assert_eq!(
+                std::fs::read_to_string(root.join("notes.txt")).expect("read"),
+                expected
+            );
+            assert!(git(
+                &root,
+                &["diff", "--cached", "--name-only", "--diff-filter=U"]
+            )
+            .is_empty());
+            let _ = std::fs::remove_dir_all(&root);
+        }
+    }
+
+    #[test]
+    fn resolves_with_edited_content_and_limits_binary_conflicts_to_a_side() {
+        let runtime = tokio::runtime::Runtime::new().expect("create runtime");
+        let root = conflicted_repo(b"one\n", b"main\n", b"feature\n");
+        runtime.block_on(async {
+            let merged = ConflictResolution::parse("content", Some("merged\n".to_string()))
+                .expect("parse resolution");
+            resolve_conflict(&root, "notes.txt", &merged)
+                .await
+                .expect("resolve");
+        });
+        assert_eq!(
+            std::fs::read_to_string(root.join("notes.txt")).expect("read"),
+            "merged\n"
+        );
+        assert!(git(&root, &["ls-files", "--unmerged"]).is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+
+        let root = conflicted_repo(b"\0base", b"\0main", b"\0feature");
+        runtime.block_on(async {
+            let detail = conflict_detail(&root, "notes.txt").await.expect("detail");
+            assert!(detail.binary);
+            assert!(detail.ours.is_none() && detail.hunks.is_empty());
+
+            let content = ConflictResolution::Content("text".to_string());
+            assert!(resolve_conflict(&root, "notes.txt", &content)
+                .await
+                .unwrap_err()
+                .contains("Binary"));
+            resolve_conflict(&root, "notes.txt", &ConflictResolution::Theirs)
+                .await
+                .expect("resolve binary");
+        });
+        assert_eq!(
+            std::fs::read(root.join("notes.txt")).expect("read"),
+            b"\0feature"
+        );
+        assert!(ConflictResolution::parse("both", None).is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
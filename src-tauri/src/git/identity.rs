@@ -0,0 +1,135 @@
+use std::path::Path;
+
+use git2::{ConfigLevel, ErrorCode, Repository};
+use serde::Serialize;
+
+use crate::types::GitIdentity;
+
+/// Author identity for a repository, as reported by `get_git_identity`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(crate) struct GitIdentityInfo {
+    /// What `git commit` would record: environment overrides, then local, global and
+    /// system config.
+    pub(crate) effective: GitIdentity,
+    /// Values set in the repository's own config.
+    pub(crate) local: GitIdentity,
+    /// The workspace's expected identity, if one is configured.
+    pub(crate) expected: Option<GitIdentity>,
+    pub(crate) matches: bool,
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn read_config_identity(config: &git2::Config) -> GitIdentity {
+    GitIdentity {
+        name: non_empty(config.get_string("user.name").ok()),
+        email: non_empty(config.get_string("user.email").ok()),
+    }
+}
+
+pub(crate) fn read_identity(
+    repo_root: &Path,
+    expected: Option<&GitIdentity>,
+) -> Result<GitIdentityInfo, String> {
+    let repo = Repository::open(repo_root).map_err(|e| e.to_string())?;
+    let mut config = repo.config().map_err(|e| e.to_string())?;
+    let configured = read_config_identity(&config.snapshot().map_err(|e| e.to_string())?);
+    let effective = GitIdentity {
+        name: non_empty(std::env::var("GIT_AUTHOR_NAME").ok()).or(configured.name),
+        email: non_empty(std::env::var("GIT_AUTHOR_EMAIL").ok()).or(configured.email),
+    };
+    let local = match config.open_level(ConfigLevel::Local) {
+        Ok(mut local) => read_config_identity(&local.snapshot().map_err(|e| e.to_string())?),
+        Err(_) => GitIdentity::default(),
+    };
+    let matches = expected.is_none_or(|expected| identity_matches(expected, &effective));
+    Ok(GitIdentityInfo {
+        effective,
+        local,
+        expected: expected.cloned(),
+        matches,
+    })
+}
+
+/// Writes the identity to the repository's own config; empty fields are unset there.
+/// Global and system config are never touched.
+pub(crate) fn write_local_identity(repo_root: &Path, identity: &GitIdentity) -> Result<(), String> {
+    let repo = Repository::open(repo_root).map_err(|e| e.to_string())?;
+    let mut local = repo
+        .config()
+        .and_then(|config| config.open_level(ConfigLevel::Local))
+        .map_err(|e| e.to_string())?;
+    for (key, value) in [
+        ("user.name", non_empty(identity.name.clone())),
+        ("user.email", non_empty(identity.email.clone())),
+    ] {
+        let result = match value {
+            Some(value) => local.set_str(key, &value),
+            None => match local.remove(key) {
+                Err(error) if error.code() == ErrorCode::NotFound => Ok(()),
+                other => other,
+            },
+        };
+        result.map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Only fields set in `expected` are compared; emails ignore case.
+fn identity_matches(expected: &GitIdentity, effective: &GitIdentity) -> bool {
+    let name_matches = match non_empty(expected.name.clone()) {
+        Some(name) => effective.name.as_deref() == Some(name.as_str()),
+        None => true,
+    };
+    let email_matches = match non_empty(expected.email.clone()) {
+        Some(email) => effective
+            .email
+            .as_deref()
+            .is_some_and(|actual| actual.eq_ignore_ascii_case(&email)),
+        None => true,
+    };
+    name_matches && email_matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_identity_is_written_to_repo_config_only() {
+        let root = std::env::temp_dir().join(format!("micode-identity-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create repo root");
+        Repository::init(&root).expect("init repo");
+        let work = GitIdentity {
+            name: Some("Dev Work".to_string()),
+            email: Some("dev@client.example".to_string()),
+        };
+
+        write_local_identity(&root, &work).expect("write identity");
+        let config = std::fs::read_to_string(root.join(".git").join("config")).expect("config");
+        assert!(config.contains("dev@client.example"));
+        let info = read_identity(&root, Some(&work)).expect("read identity");
+        assert_eq!(info.local, work);
+
+        let expected = GitIdentity {
+            name: None,
+            email: Some("DEV@client.example".to_string()),
+        };
+        assert!(identity_matches(&expected, &work));
+        let personal = GitIdentity {
+            name: Some("Dev Work".to_string()),
+            email: Some("dev@home.example".to_string()),
+        };
+        assert!(!identity_matches(&expected, &personal));
+
+        write_local_identity(&root, &GitIdentity::default()).expect("clear identity");
+        let info = read_identity(&root, None).expect("read identity");
+        assert_eq!(info.local, GitIdentity::default());
+        assert!(info.matches);
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
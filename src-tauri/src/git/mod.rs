@@ -1,27 +1,73 @@
+mod conflicts;
+mod identity;
+mod patches;
+mod repo_lock;
+mod trash;
+
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 use git2::{BranchType, DiffOptions, Repository, Sort, Status, StatusOptions};
-use serde_json::json;
+use serde::Serialize;
+use serde_json::{json, Value};
 use tauri::State;
+use tokio::process::Command;
 
+use crate::backend::app_server::WorkspaceSession;
 use crate::git_utils::{
     checkout_branch, commit_to_entry, diff_patch_to_string, diff_stats_for_path, image_mime_type,
     list_git_roots as scan_git_roots, parse_github_repo, resolve_git_root,
 };
+use crate::http_client::{self, ProxyConfig};
 use crate::shared::process_core::tokio_command;
+use crate::shared::workspace_roots_core::{select_root, workspace_roots, WorkspaceRoot};
 use crate::state::AppState;
+use crate::types::errors::CommandError;
 use crate::types::{
     BranchInfo, GitCommitDiff, GitFileDiff, GitFileStatus, GitHubIssue, GitHubIssuesResponse,
     GitHubPullRequest, GitHubPullRequestComment, GitHubPullRequestDiff, GitHubPullRequestsResponse,
-    GitLogResponse,
+    GitIdentity, GitLogResponse, WorkspaceEntry,
 };
 use crate::utils::{git_env_path, normalize_git_path, resolve_git_binary};
+use conflicts::{conflict_detail, ConflictDetail, ConflictResolution};
+use identity::{read_identity, write_local_identity, GitIdentityInfo};
+use patches::{AppliedPatch, ExtractedPatch};
+use repo_lock::with_repository_lock;
+use trash::{GitRevertResult, TrashEntry, TrashRestoreResult};
 
 const INDEX_SKIP_WORKTREE_FLAG: u16 = 0x4000;
 const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
 const MAX_TEXT_DIFF_BYTES: usize = 2 * 1024 * 1024;
+/// Patch bytes kept per file when a branch diff goes into a prompt.
+const MAX_BRANCH_DIFF_FILE_BYTES: usize = 12 * 1024;
+/// Patch bytes of a branch diff after which further files are only listed by name.
+const MAX_BRANCH_DIFF_BYTES: usize = 160 * 1024;
+/// Commit subjects listed alongside a branch diff.
+const MAX_BRANCH_DIFF_COMMITS: usize = 50;
+
+/// Commits read for one changelog; older ones in the range are left out.
+const MAX_CHANGELOG_COMMITS: usize = 500;
+
+/// A commit in a changelog range, with its full message body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ChangelogCommit {
+    pub(crate) sha: String,
+    pub(crate) summary: String,
+    pub(crate) body: String,
+}
+
+/// The changes the current branch makes on top of its base, for PR descriptions.
+#[derive(Debug, Clone)]
+pub(crate) struct BranchDiff {
+    pub(crate) branch: String,
+    pub(crate) base: String,
+    /// Subjects of the branch's own commits, newest first.
+    pub(crate) commits: Vec<String>,
+    pub(crate) diff: String,
+}
 
 fn encode_image_base64(data: &[u8]) -> Option<String> {
     if data.len() > MAX_IMAGE_BYTES {
@@ -314,8 +360,8 @@ fn status_for_delta(status: git2::Delta) -> &'static str {
     }
 }
 
-fn build_combined_diff(diff: &git2::Diff) -> String {
-    let mut combined_diff = String::new();
+fn diff_file_patches(diff: &git2::Diff) -> Vec<(PathBuf, String)> {
+    let mut patches = Vec::new();
     for (index, delta) in diff.deltas().enumerate() {
         let path = delta.new_file().path().or_else(|| delta.old_file().path());
         let Some(path) = path else {
@@ -335,6 +381,14 @@ fn build_combined_diff(diff: &git2::Diff) -> String {
         if content.trim().is_empty() {
             continue;
         }
+        patches.push((path.to_path_buf(), content));
+    }
+    patches
+}
+
+fn build_combined_diff(diff: &git2::Diff) -> String {
+    let mut combined_diff = String::new();
+    for (path, content) in diff_file_patches(diff) {
         if !combined_diff.is_empty() {
             combined_diff.push_str("\n\n");
         }
@@ -344,6 +398,143 @@ fn build_combined_diff(diff: &git2::Diff) -> String {
     combined_diff
 }
 
+/// Cuts `content` to at most `max_bytes` on a line boundary, noting how much was left out.
+fn truncate_file_patch(content: &str, max_bytes: usize) -> String {
+    if content.len() <= max_bytes {
+        return content.to_string();
+    }
+    let mut end = content[..max_bytes].rfind('\n').map_or(0, |index| index + 1);
+    if end == 0 {
+        end = max_bytes;
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+    }
+    let omitted = content[end..].lines().count();
+    format!(
+        "{}[... {omitted} more line(s) of this file omitted ...]\n",
+        &content[..end]
+    )
+}
+
+/// Like `build_combined_diff`, but each file's patch is cut to `file_limit` bytes and
+/// files past `total_limit` are only listed, so huge branches still fit in a prompt.
+fn build_truncated_diff(diff: &git2::Diff, file_limit: usize, total_limit: usize) -> String {
+    let mut combined_diff = String::new();
+    let mut omitted = Vec::new();
+    for (path, content) in diff_file_patches(diff) {
+        if combined_diff.len() >= total_limit {
+            omitted.push(path.display().to_string());
+            continue;
+        }
+        if !combined_diff.is_empty() {
+            combined_diff.push_str("\n\n");
+        }
+        combined_diff.push_str(&format!("=== {} ===\n", path.display()));
+        combined_diff.push_str(&truncate_file_patch(&content, file_limit));
+    }
+    if !omitted.is_empty() {
+        combined_diff.push_str(&format!(
+            "\n\n[... diff too large; {} more changed file(s) omitted: {} ...]\n",
+            omitted.len(),
+            omitted.join(", ")
+        ));
+    }
+    combined_diff
+}
+
+/// Finds what the current branch is compared against: `base` when given, otherwise the
+/// remote's default branch, `main`/`master`, and finally the branch's upstream.
+fn resolve_branch_base(
+    repo: &Repository,
+    base: Option<&str>,
+) -> Result<(String, git2::Oid), String> {
+    let commit_of = |name: &str| {
+        repo.revparse_single(name)
+            .and_then(|object| object.peel_to_commit())
+            .map(|commit| commit.id())
+            .ok()
+    };
+    if let Some(base) = base.map(str::trim).filter(|base| !base.is_empty()) {
+        let oid = commit_of(base).ok_or_else(|| format!("Base branch `{base}` not found"))?;
+        return Ok((base.to_string(), oid));
+    }
+
+    let head_branch = repo
+        .head()
+        .ok()
+        .filter(|head| head.is_branch())
+        .and_then(|head| head.shorthand().map(str::to_string));
+    let mut candidates = Vec::new();
+    if let Some(default_branch) = repo
+        .find_reference("refs/remotes/origin/HEAD")
+        .and_then(|reference| reference.resolve())
+        .ok()
+        .and_then(|reference| reference.shorthand().map(str::to_string))
+    {
+        candidates.push(default_branch);
+    }
+    candidates.extend(
+        ["origin/main", "origin/master", "main", "master"]
+            .iter()
+            .map(|name| name.to_string()),
+    );
+    for candidate in candidates {
+        if head_branch.as_deref() == Some(candidate.as_str()) {
+            continue;
+        }
+        if let Some(oid) = commit_of(&candidate) {
+            return Ok((candidate, oid));
+        }
+    }
+
+    let upstream = head_branch
+        .as_deref()
+        .and_then(|name| repo.find_branch(name, BranchType::Local).ok())
+        .and_then(|branch| branch.upstream().ok())
+        .and_then(|upstream| {
+            let name = upstream.get().shorthand()?.to_string();
+            Some((name, upstream.get().target()?))
+        });
+    upstream.ok_or_else(|| "No base branch found to compare the current branch against".to_string())
+}
+
+fn collect_branch_diff(repo_root: &Path, base: Option<&str>) -> Result<BranchDiff, String> {
+    let repo = Repository::open(repo_root).map_err(|e| e.to_string())?;
+    let head = repo.head().map_err(|e| e.to_string())?;
+    let branch = head.shorthand().unwrap_or("HEAD").to_string();
+    let head_commit = head.peel_to_commit().map_err(|e| e.to_string())?;
+    let (base, base_oid) = resolve_branch_base(&repo, base)?;
+    let merge_base = repo
+        .merge_base(head_commit.id(), base_oid)
+        .map_err(|e| e.to_string())?;
+
+    let mut revwalk = repo.revwalk().map_err(|e| e.to_string())?;
+    revwalk.push(head_commit.id()).map_err(|e| e.to_string())?;
+    revwalk.hide(merge_base).map_err(|e| e.to_string())?;
+    let commits = revwalk
+        .filter_map(Result::ok)
+        .take(MAX_BRANCH_DIFF_COMMITS)
+        .filter_map(|oid| repo.find_commit(oid).ok())
+        .filter_map(|commit| commit.summary().map(str::to_string))
+        .collect();
+
+    let base_tree = repo
+        .find_commit(merge_base)
+        .and_then(|commit| commit.tree())
+        .map_err(|e| e.to_string())?;
+    let head_tree = head_commit.tree().map_err(|e| e.to_string())?;
+    let diff = repo
+        .diff_tree_to_tree(Some(&base_tree), Some(&head_tree), None)
+        .map_err(|e| e.to_string())?;
+    Ok(BranchDiff {
+        branch,
+        base,
+        commits,
+        diff: build_truncated_diff(&diff, MAX_BRANCH_DIFF_FILE_BYTES, MAX_BRANCH_DIFF_BYTES),
+    })
+}
+
 fn collect_workspace_diff(repo_root: &Path) -> Result<String, String> {
     let repo = Repository::open(repo_root).map_err(|e| e.to_string())?;
     let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
@@ -489,20 +680,48 @@ fn parse_pr_diff(diff: &str) -> Vec<GitHubPullRequestDiff> {
 
     entries
 }
-#[tauri::command]
-pub(crate) async fn get_git_status(
-    workspace_id: String,
-    state: State<'_, AppState>,
-) -> Result<serde_json::Value, String> {
-    let workspaces = state.workspaces.lock().await;
-    let entry = workspaces
-        .get(&workspace_id)
-        .ok_or("workspace not found")?
-        .clone();
-    drop(workspaces);
+/// Git root of the workspace root named `root`, or of the primary root.
+fn resolve_root(entry: &WorkspaceEntry, root: Option<&str>) -> Result<PathBuf, String> {
+    let (root, _) = select_root(entry, root, None)?;
+    root.git_root(entry)
+}
 
-    let repo_root = resolve_git_root(&entry)?;
-    let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
+/// Git root for a file and the file's path relative to it. Without an explicit `root`
+/// the root is detected from the path prefix.
+fn resolve_root_and_path(
+    entry: &WorkspaceEntry,
+    root: Option<&str>,
+    path: &str,
+) -> Result<(PathBuf, String), String> {
+    let (root, relative) = select_root(entry, root, Some(path))?;
+    let repo_root = root.git_root(entry)?;
+    Ok((repo_root, relative.unwrap_or_else(|| path.to_string())))
+}
+
+struct GitStatusSnapshot {
+    branch_name: String,
+    files: Vec<GitFileStatus>,
+    staged_files: Vec<GitFileStatus>,
+    unstaged_files: Vec<GitFileStatus>,
+    total_additions: i64,
+    total_deletions: i64,
+}
+
+impl GitStatusSnapshot {
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "branchName": self.branch_name,
+            "files": self.files,
+            "stagedFiles": self.staged_files,
+            "unstagedFiles": self.unstaged_files,
+            "totalAdditions": self.total_additions,
+            "totalDeletions": self.total_deletions,
+        })
+    }
+}
+
+fn collect_git_status(repo_root: &Path) -> Result<GitStatusSnapshot, String> {
+    let repo = Repository::open(repo_root).map_err(|e| e.to_string())?;
 
     let branch_name = repo
         .head()
@@ -608,199 +827,527 @@ pub(crate) async fn get_git_status(
         }
     }
 
-    Ok(json!({
-        "branchName": branch_name,
-        "files": files,
-        "stagedFiles": staged_files,
-        "unstagedFiles": unstaged_files,
-        "totalAdditions": total_additions,
-        "totalDeletions": total_deletions,
-    }))
+    Ok(GitStatusSnapshot {
+        branch_name,
+        files,
+        staged_files,
+        unstaged_files,
+        total_additions,
+        total_deletions,
+    })
+}
+
+fn prefix_status_paths(root: &WorkspaceRoot, files: &[GitFileStatus]) -> Vec<GitFileStatus> {
+    files
+        .iter()
+        .map(|file| GitFileStatus {
+            path: root.prefixed_path(&file.path),
+            ..file.clone()
+        })
+        .collect()
+}
+
+/// Status of every root of the workspace. Single-root workspaces get the plain status;
+/// multi-root workspaces get merged lists with root-prefixed paths plus a `roots` entry
+/// per root, where a root that fails (e.g. is not a repository) reports its error.
+#[tauri::command]
+pub(crate) async fn get_git_status(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, CommandError> {
+    state
+        .command_timings
+        .timed(
+            "get_git_status",
+            Some(&workspace_id),
+            git_status_for_workspace(workspace_id.clone(), &state),
+        )
+        .await
+        .map_err(CommandError::git)
+}
+
+async fn git_status_for_workspace(
+    workspace_id: String,
+    state: &AppState,
+) -> Result<serde_json::Value, String> {
+    let workspaces = state.workspaces.lock().await;
+    let entry = workspaces
+        .get(&workspace_id)
+        .ok_or_else(CommandError::workspace_not_found)?
+        .clone();
+    drop(workspaces);
+
+    let roots = workspace_roots(&entry);
+    if roots.len() == 1 {
+        let repo_root = resolve_git_root(&entry)?;
+        return Ok(collect_git_status(&repo_root)?.to_json());
+    }
+
+    let mut merged: Option<GitStatusSnapshot> = None;
+    let mut groups = Vec::new();
+    let mut first_error = None;
+    for root in &roots {
+        let snapshot = root
+            .git_root(&entry)
+            .and_then(|repo_root| collect_git_status(&repo_root));
+        let snapshot = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                groups.push(json!({
+                    "name": root.name,
+                    "path": root.path,
+                    "primary": root.primary,
+                    "error": error,
+                }));
+                first_error.get_or_insert(error);
+                continue;
+            }
+        };
+        let mut group = snapshot.to_json();
+        group["name"] = json!(root.name);
+        group["path"] = json!(root.path);
+        group["primary"] = json!(root.primary);
+        groups.push(group);
+        let merged = merged.get_or_insert_with(|| GitStatusSnapshot {
+            branch_name: snapshot.branch_name.clone(),
+            files: Vec::new(),
+            staged_files: Vec::new(),
+            unstaged_files: Vec::new(),
+            total_additions: 0,
+            total_deletions: 0,
+        });
+        merged
+            .files
+            .extend(prefix_status_paths(root, &snapshot.files));
+        merged
+            .staged_files
+            .extend(prefix_status_paths(root, &snapshot.staged_files));
+        merged
+            .unstaged_files
+            .extend(prefix_status_paths(root, &snapshot.unstaged_files));
+        merged.total_additions += snapshot.total_additions;
+        merged.total_deletions += snapshot.total_deletions;
+    }
+    let Some(merged) = merged else {
+        return Err(first_error.unwrap_or_else(|| "workspace has no git roots".to_string()));
+    };
+    let mut response = merged.to_json();
+    response["roots"] = json!(groups);
+    Ok(response)
 }
 
 #[tauri::command]
 pub(crate) async fn stage_git_file(
     workspace_id: String,
     path: String,
+    root: Option<String>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let entry = {
         let workspaces = state.workspaces.lock().await;
         workspaces
             .get(&workspace_id)
             .cloned()
-            .ok_or("workspace not found")?
+            .ok_or_else(CommandError::workspace_not_found)?
     };
 
-    let repo_root = resolve_git_root(&entry)?;
-    // If libgit2 reports a rename, we want a single UI action to stage both the
-    // old + new paths so the change actually moves to the staged section.
-    for path in action_paths_for_file(&repo_root, &path) {
-        run_git_command(&repo_root, &["add", "-A", "--", &path]).await?;
-    }
-    Ok(())
+    let (repo_root, path) =
+        resolve_root_and_path(&entry, root.as_deref(), &path).map_err(CommandError::git)?;
+    stage_paths(&repo_root, &action_paths_for_file(&repo_root, &path)).await
+}
+
+/// If libgit2 reports a rename, `paths` holds both the old and new path so a single UI
+/// action moves the whole change to the staged section.
+async fn stage_paths(repo_root: &Path, paths: &[String]) -> Result<(), CommandError> {
+    with_repository_lock(repo_root, "stage", move || async move {
+        for path in paths {
+            run_git_command(repo_root, &["add", "-A", "--", path]).await?;
+        }
+        Ok(())
+    })
+    .await
 }
 
 #[tauri::command]
 pub(crate) async fn stage_git_all(
     workspace_id: String,
+    root: Option<String>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let entry = {
         let workspaces = state.workspaces.lock().await;
         workspaces
             .get(&workspace_id)
             .cloned()
-            .ok_or("workspace not found")?
+            .ok_or_else(CommandError::workspace_not_found)?
     };
 
-    let repo_root = resolve_git_root(&entry)?;
-    run_git_command(&repo_root, &["add", "-A"]).await
+    let repo_root = resolve_root(&entry, root.as_deref()).map_err(CommandError::git)?;
+    let repo_root = repo_root.as_path();
+    with_repository_lock(repo_root, "stage", move || {
+        run_git_command(repo_root, &["add", "-A"])
+    })
+    .await
 }
 
 #[tauri::command]
 pub(crate) async fn unstage_git_file(
     workspace_id: String,
     path: String,
+    root: Option<String>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let entry = {
         let workspaces = state.workspaces.lock().await;
         workspaces
             .get(&workspace_id)
             .cloned()
-            .ok_or("workspace not found")?
+            .ok_or_else(CommandError::workspace_not_found)?
     };
 
-    let repo_root = resolve_git_root(&entry)?;
-    for path in action_paths_for_file(&repo_root, &path) {
-        run_git_command(&repo_root, &["restore", "--staged", "--", &path]).await?;
-    }
-    Ok(())
+    let (repo_root, path) =
+        resolve_root_and_path(&entry, root.as_deref(), &path).map_err(CommandError::git)?;
+    let paths = action_paths_for_file(&repo_root, &path);
+    let (repo_root, paths) = (repo_root.as_path(), paths.as_slice());
+    with_repository_lock(repo_root, "unstage", move || async move {
+        for path in paths {
+            run_git_command(repo_root, &["restore", "--staged", "--", path]).await?;
+        }
+        Ok(())
+    })
+    .await
 }
 
+/// Discards changes to one file after backing them up to the workspace trash.
 #[tauri::command]
 pub(crate) async fn revert_git_file(
     workspace_id: String,
     path: String,
+    root: Option<String>,
+    skip_backup: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<GitRevertResult, CommandError> {
     let entry = {
         let workspaces = state.workspaces.lock().await;
         workspaces
             .get(&workspace_id)
             .cloned()
-            .ok_or("workspace not found")?
+            .ok_or_else(CommandError::workspace_not_found)?
     };
 
-    let repo_root = resolve_git_root(&entry)?;
-    for path in action_paths_for_file(&repo_root, &path) {
-        if run_git_command(
-            &repo_root,
-            &["restore", "--staged", "--worktree", "--", &path],
-        )
-        .await
-        .is_ok()
-        {
-            continue;
+    let workspace_root = PathBuf::from(&entry.path);
+    let (repo_root, path) =
+        resolve_root_and_path(&entry, root.as_deref(), &path).map_err(CommandError::git)?;
+    let paths = action_paths_for_file(&repo_root, &path);
+    let backup = trash::backup_before_revert(
+        &workspace_root,
+        &repo_root,
+        "revertFile",
+        &paths,
+        skip_backup.unwrap_or(false),
+    )
+    .map_err(CommandError::git)?;
+    let (repo_root, paths) = (repo_root.as_path(), paths.as_slice());
+    let reverted = with_repository_lock(repo_root, "revert", move || async move {
+        for path in paths {
+            match run_git_command(
+                repo_root,
+                &["restore", "--staged", "--worktree", "--", path],
+            )
+            .await
+            {
+                Ok(()) => continue,
+                Err(error) if repo_lock::is_index_lock_error(&error) => return Err(error),
+                Err(_) => {}
+            }
+            run_git_command(repo_root, &["clean", "-f", "--", path]).await?;
         }
-        run_git_command(&repo_root, &["clean", "-f", "--", &path]).await?;
-    }
-    Ok(())
+        Ok(())
+    })
+    .await;
+    let result = trash::finish_revert(&workspace_root, backup, reverted.is_ok());
+    reverted.map(|()| result)
 }
 
+/// Discards all changes after backing them up to the workspace trash. App state under
+/// `.micodemonitor` survives the clean.
 #[tauri::command]
 pub(crate) async fn revert_git_all(
     workspace_id: String,
+    root: Option<String>,
+    skip_backup: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let workspaces = state.workspaces.lock().await;
-    let entry = workspaces.get(&workspace_id).ok_or("workspace not found")?;
-    let repo_root = resolve_git_root(entry)?;
-    run_git_command(
+) -> Result<GitRevertResult, CommandError> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
+    let workspace_root = PathBuf::from(&entry.path);
+    let repo_root = resolve_root(&entry, root.as_deref()).map_err(CommandError::git)?;
+    let skip_backup = skip_backup.unwrap_or(false);
+    let paths = if skip_backup {
+        Vec::new()
+    } else {
+        trash::changed_paths(&repo_root).map_err(CommandError::git)?
+    };
+    let backup = trash::backup_before_revert(
+        &workspace_root,
         &repo_root,
-        &["restore", "--staged", "--worktree", "--", "."],
+        "revertAll",
+        &paths,
+        skip_backup,
     )
-    .await?;
-    run_git_command(&repo_root, &["clean", "-f", "-d"]).await
+    .map_err(CommandError::git)?;
+    let repo_root = repo_root.as_path();
+    let reverted = with_repository_lock(repo_root, "revert", move || async move {
+        run_git_command(repo_root, &["restore", "--staged", "--worktree", "--", "."]).await?;
+        run_git_command(repo_root, &["clean", "-f", "-d", "-e", ".micodemonitor"]).await
+    })
+    .await;
+    let result = trash::finish_revert(&workspace_root, backup, reverted.is_ok());
+    reverted.map(|()| result)
 }
 
 #[tauri::command]
-pub(crate) async fn commit_git(
+pub(crate) async fn list_git_trash(
     workspace_id: String,
-    message: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<Vec<TrashEntry>, CommandError> {
+    let workspace_root = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .map(|entry| PathBuf::from(&entry.path))
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
+    Ok(trash::list_trash(&workspace_root))
+}
+
+/// Puts a trash entry back as unstaged changes, skipping files edited since the revert.
+#[tauri::command]
+pub(crate) async fn restore_git_trash(
+    workspace_id: String,
+    trash_id: String,
+    state: State<'_, AppState>,
+) -> Result<TrashRestoreResult, CommandError> {
+    let workspace_root = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .map(|entry| PathBuf::from(&entry.path))
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
+    let entry = trash::list_trash(&workspace_root)
+        .into_iter()
+        .find(|entry| entry.id == trash_id)
+        .ok_or_else(|| CommandError::git("Trash entry not found".to_string()))?;
+    let repo_root = PathBuf::from(&entry.repo_root);
+    let (workspace_root, trash_id) = (workspace_root.as_path(), trash_id.as_str());
+    with_repository_lock(&repo_root, "restore trash", move || async move {
+        trash::restore_trash(workspace_root, trash_id)
+    })
+    .await
+}
+
+/// Worktrees share their parent's repository config, so they inherit its expectation.
+fn expected_git_identity(
+    workspaces: &HashMap<String, WorkspaceEntry>,
+    entry: &WorkspaceEntry,
+) -> Option<GitIdentity> {
+    entry
+        .settings
+        .expected_git_identity
+        .clone()
+        .or_else(|| {
+            let parent = workspaces.get(entry.parent_id.as_deref()?)?;
+            parent.settings.expected_git_identity.clone()
+        })
+        .filter(|identity| identity.name.is_some() || identity.email.is_some())
+}
+
+async fn entry_with_expected_identity(
+    workspace_id: &str,
+    state: &AppState,
+) -> Result<(WorkspaceEntry, Option<GitIdentity>), CommandError> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
-        .get(&workspace_id)
-        .ok_or("workspace not found")?
-        .clone();
+        .get(workspace_id)
+        .cloned()
+        .ok_or_else(CommandError::workspace_not_found)?;
+    let expected = expected_git_identity(&workspaces, &entry);
+    Ok((entry, expected))
+}
 
-    let repo_root = resolve_git_root(&entry)?;
-    run_git_command(&repo_root, &["commit", "-m", &message]).await
+#[tauri::command]
+pub(crate) async fn get_git_identity(
+    workspace_id: String,
+    root: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<GitIdentityInfo, CommandError> {
+    let (entry, expected) = entry_with_expected_identity(&workspace_id, &state).await?;
+    let repo_root = resolve_root(&entry, root.as_deref()).map_err(CommandError::git)?;
+    read_identity(&repo_root, expected.as_ref()).map_err(CommandError::git)
+}
+
+/// Sets `user.name`/`user.email` in the repository's own config; global config is
+/// never modified.
+#[tauri::command]
+pub(crate) async fn set_git_identity(
+    workspace_id: String,
+    name: Option<String>,
+    email: Option<String>,
+    root: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<GitIdentityInfo, CommandError> {
+    let (entry, expected) = entry_with_expected_identity(&workspace_id, &state).await?;
+    let repo_root = resolve_root(&entry, root.as_deref()).map_err(CommandError::git)?;
+    write_local_identity(&repo_root, &GitIdentity { name, email }).map_err(CommandError::git)?;
+    read_identity(&repo_root, expected.as_ref()).map_err(CommandError::git)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorkspaceGitInfo {
+    repo_root: String,
+    branch_name: Option<String>,
+    remote_url: Option<String>,
+    identity: GitIdentityInfo,
+}
+
+/// Repository facts for the workspace header: root, branch, remote and author identity.
+#[tauri::command]
+pub(crate) async fn get_workspace_git_info(
+    workspace_id: String,
+    root: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<WorkspaceGitInfo, CommandError> {
+    let (entry, expected) = entry_with_expected_identity(&workspace_id, &state).await?;
+    let repo_root = resolve_root(&entry, root.as_deref()).map_err(CommandError::git)?;
+    let identity = read_identity(&repo_root, expected.as_ref()).map_err(CommandError::git)?;
+    let repo = Repository::open(&repo_root).map_err(|e| CommandError::git(e.to_string()))?;
+    let branch_name = repo
+        .head()
+        .ok()
+        .and_then(|head| head.shorthand().map(str::to_string));
+    let remote_url = repo
+        .find_remote("origin")
+        .ok()
+        .and_then(|remote| remote.url().map(str::to_string));
+    Ok(WorkspaceGitInfo {
+        repo_root: repo_root.to_string_lossy().to_string(),
+        branch_name,
+        remote_url,
+        identity,
+    })
+}
+
+/// Refuses to commit with an identity other than the workspace's expected one unless
+/// `allow_mismatch` is set.
+#[tauri::command]
+pub(crate) async fn commit_git(
+    workspace_id: String,
+    message: String,
+    root: Option<String>,
+    allow_mismatch: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let (entry, expected) = entry_with_expected_identity(&workspace_id, &state).await?;
+
+    let repo_root = resolve_root(&entry, root.as_deref()).map_err(CommandError::git)?;
+    if let Some(expected) = expected.filter(|_| !allow_mismatch.unwrap_or(false)) {
+        let info = read_identity(&repo_root, Some(&expected)).map_err(CommandError::git)?;
+        if !info.matches {
+            return Err(repo_lock::identity_mismatch(expected, info.effective));
+        }
+    }
+    let (repo_root, message) = (repo_root.as_path(), message.as_str());
+    with_repository_lock(repo_root, "commit", move || async move {
+        run_git_command(repo_root, &["commit", "-m", message]).await
+    })
+    .await
 }
 
 #[tauri::command]
 pub(crate) async fn push_git(
     workspace_id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
 
-    let repo_root = resolve_git_root(&entry)?;
-    push_with_upstream(&repo_root).await
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    push_with_upstream(&repo_root)
+        .await
+        .map_err(CommandError::git)
 }
 
 #[tauri::command]
 pub(crate) async fn pull_git(
     workspace_id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let workspaces = state.workspaces.lock().await;
-    let entry = workspaces
-        .get(&workspace_id)
-        .ok_or("workspace not found")?
-        .clone();
+) -> Result<(), CommandError> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
 
-    let repo_root = resolve_git_root(&entry)?;
-    pull_with_default_strategy(&repo_root).await
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    let repo_root = repo_root.as_path();
+    with_repository_lock(repo_root, "pull", move || {
+        pull_with_default_strategy(repo_root)
+    })
+    .await
 }
 
 #[tauri::command]
 pub(crate) async fn fetch_git(
     workspace_id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
 
-    let repo_root = resolve_git_root(&entry)?;
-    fetch_with_default_remote(&repo_root).await
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    fetch_with_default_remote(&repo_root)
+        .await
+        .map_err(CommandError::git)
 }
 
 #[tauri::command]
 pub(crate) async fn sync_git(
     workspace_id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let workspaces = state.workspaces.lock().await;
-    let entry = workspaces
-        .get(&workspace_id)
-        .ok_or("workspace not found")?
-        .clone();
+) -> Result<(), CommandError> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
 
-    let repo_root = resolve_git_root(&entry)?;
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    let repo_root = repo_root.as_path();
     // Pull first, then push (like VSCode sync)
-    pull_with_default_strategy(&repo_root).await?;
-    push_with_upstream(&repo_root).await
+    with_repository_lock(repo_root, "sync", move || {
+        pull_with_default_strategy(repo_root)
+    })
+    .await?;
+    push_with_upstream(repo_root)
+        .await
+        .map_err(CommandError::git)
 }
 
 #[tauri::command]
@@ -808,11 +1355,11 @@ pub(crate) async fn list_git_roots(
     workspace_id: String,
     depth: Option<usize>,
     state: State<'_, AppState>,
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<String>, CommandError> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
 
     let root = PathBuf::from(&entry.path);
@@ -820,6 +1367,35 @@ pub(crate) async fn list_git_roots(
     Ok(scan_git_roots(&root, depth, 200))
 }
 
+/// Repositories below the workspace path that are not configured as roots yet, to seed
+/// `settings.roots` for multi-root workspaces.
+#[tauri::command]
+pub(crate) async fn detect_workspace_roots(
+    workspace_id: String,
+    depth: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, CommandError> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
+    let depth = depth.unwrap_or(2).clamp(1, 6);
+    let current = workspace_roots(&entry);
+    let base = PathBuf::from(&entry.path);
+    let detected: Vec<String> =
+        tokio::task::spawn_blocking(move || scan_git_roots(&base, depth, 200))
+            .await
+            .map_err(|e| CommandError::git(e.to_string()))?;
+    let candidates: Vec<String> = detected
+        .into_iter()
+        .filter(|candidate| !current.iter().any(|root| &root.name == candidate))
+        .collect();
+    Ok(json!({ "roots": current, "candidates": candidates }))
+}
+
 /// Helper function to get the combined diff for a workspace (used by commit message generation)
 pub(crate) async fn get_workspace_diff(
     workspace_id: &str,
@@ -828,7 +1404,7 @@ pub(crate) async fn get_workspace_diff(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
     drop(workspaces);
 
@@ -836,76 +1412,103 @@ pub(crate) async fn get_workspace_diff(
     collect_workspace_diff(&repo_root)
 }
 
-#[tauri::command]
-pub(crate) async fn get_git_diffs(
-    workspace_id: String,
-    state: State<'_, AppState>,
-) -> Result<Vec<GitFileDiff>, String> {
+/// The diff of the workspace's current branch against `base` (or the detected base
+/// branch), cut per file so it fits a generation prompt. Used for PR descriptions.
+pub(crate) async fn get_branch_diff(
+    workspace_id: &str,
+    base: Option<&str>,
+    state: &State<'_, AppState>,
+) -> Result<BranchDiff, String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
-        .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .get(workspace_id)
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
     drop(workspaces);
 
     let repo_root = resolve_git_root(&entry)?;
-    let ignore_whitespace_changes = {
-        let settings = state.app_settings.lock().await;
-        settings.git_diff_ignore_whitespace_changes
+    collect_branch_diff(&repo_root, base)
+}
+
+fn collect_git_diffs(
+    repo_root: &Path,
+    ignore_whitespace_changes: bool,
+) -> Result<Vec<GitFileDiff>, String> {
+    let repo = Repository::open(repo_root).map_err(|e| e.to_string())?;
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+
+    let mut options = DiffOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    options.ignore_whitespace_change(ignore_whitespace_changes);
+
+    let diff = match head_tree.as_ref() {
+        Some(tree) => repo
+            .diff_tree_to_workdir_with_index(Some(tree), Some(&mut options))
+            .map_err(|e| e.to_string())?,
+        None => repo
+            .diff_tree_to_workdir_with_index(None, Some(&mut options))
+            .map_err(|e| e.to_string())?,
     };
-    tokio::task::spawn_blocking(move || {
-        let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
-        let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
 
-        let mut options = DiffOptions::new();
-        options
-            .include_untracked(true)
-            .recurse_untracked_dirs(true)
-            .show_untracked_content(true);
-        options.ignore_whitespace_change(ignore_whitespace_changes);
-
-        let diff = match head_tree.as_ref() {
-            Some(tree) => repo
-                .diff_tree_to_workdir_with_index(Some(tree), Some(&mut options))
-                .map_err(|e| e.to_string())?,
-            None => repo
-                .diff_tree_to_workdir_with_index(None, Some(&mut options))
-                .map_err(|e| e.to_string())?,
+    let mut results = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let old_path = delta.old_file().path();
+        let new_path = delta.new_file().path();
+        let display_path = new_path.or(old_path);
+        let Some(display_path) = display_path else {
+            continue;
         };
+        let old_path_str = old_path.map(|path| path.to_string_lossy());
+        let new_path_str = new_path.map(|path| path.to_string_lossy());
+        let display_path_str = display_path.to_string_lossy();
+        let normalized_path = normalize_git_path(&display_path_str);
+        let old_image_mime = old_path_str.as_deref().and_then(image_mime_type);
+        let new_image_mime = new_path_str.as_deref().and_then(image_mime_type);
+        let is_image = old_image_mime.is_some() || new_image_mime.is_some();
+        let is_deleted = delta.status() == git2::Delta::Deleted;
+        let is_added = delta.status() == git2::Delta::Added;
 
-        let mut results = Vec::new();
-        for (index, delta) in diff.deltas().enumerate() {
-            let old_path = delta.old_file().path();
-            let new_path = delta.new_file().path();
-            let display_path = new_path.or(old_path);
-            let Some(display_path) = display_path else {
-                continue;
-            };
-            let old_path_str = old_path.map(|path| path.to_string_lossy());
-            let new_path_str = new_path.map(|path| path.to_string_lossy());
-            let display_path_str = display_path.to_string_lossy();
-            let normalized_path = normalize_git_path(&display_path_str);
-            let old_image_mime = old_path_str.as_deref().and_then(image_mime_type);
-            let new_image_mime = new_path_str.as_deref().and_then(image_mime_type);
-            let is_image = old_image_mime.is_some() || new_image_mime.is_some();
-            let is_deleted = delta.status() == git2::Delta::Deleted;
-            let is_added = delta.status() == git2::Delta::Added;
-
-            let old_lines = if !is_added {
+        let old_lines = if !is_added {
+            head_tree
+                .as_ref()
+                .and_then(|tree| old_path.and_then(|path| tree.get_path(path).ok()))
+                .and_then(|entry| repo.find_blob(entry.id()).ok())
+                .and_then(blob_to_lines)
+        } else {
+            None
+        };
+
+        let new_lines = if !is_deleted {
+            match new_path {
+                Some(path) => {
+                    let full_path = repo_root.join(path);
+                    read_text_lines(&full_path)
+                }
+                None => None,
+            }
+        } else {
+            None
+        };
+
+        if is_image {
+            let old_image_data = if !is_added && old_image_mime.is_some() {
                 head_tree
                     .as_ref()
                     .and_then(|tree| old_path.and_then(|path| tree.get_path(path).ok()))
                     .and_then(|entry| repo.find_blob(entry.id()).ok())
-                    .and_then(blob_to_lines)
+                    .and_then(blob_to_base64)
             } else {
                 None
             };
 
-            let new_lines = if !is_deleted {
+            let new_image_data = if !is_deleted && new_image_mime.is_some() {
                 match new_path {
                     Some(path) => {
                         let full_path = repo_root.join(path);
-                        read_text_lines(&full_path)
+                        read_image_base64(&full_path)
                     }
                     None => None,
                 }
@@ -913,73 +1516,120 @@ pub(crate) async fn get_git_diffs(
                 None
             };
 
-            if is_image {
-                let old_image_data = if !is_added && old_image_mime.is_some() {
-                    head_tree
-                        .as_ref()
-                        .and_then(|tree| old_path.and_then(|path| tree.get_path(path).ok()))
-                        .and_then(|entry| repo.find_blob(entry.id()).ok())
-                        .and_then(blob_to_base64)
-                } else {
-                    None
-                };
-
-                let new_image_data = if !is_deleted && new_image_mime.is_some() {
-                    match new_path {
-                        Some(path) => {
-                            let full_path = repo_root.join(path);
-                            read_image_base64(&full_path)
-                        }
-                        None => None,
-                    }
-                } else {
-                    None
-                };
-
-                results.push(GitFileDiff {
-                    path: normalized_path,
-                    diff: String::new(),
-                    old_lines: None,
-                    new_lines: None,
-                    is_binary: true,
-                    is_image: true,
-                    old_image_data,
-                    new_image_data,
-                    old_image_mime: old_image_mime.map(str::to_string),
-                    new_image_mime: new_image_mime.map(str::to_string),
-                });
-                continue;
-            }
-
-            let patch = match git2::Patch::from_diff(&diff, index) {
-                Ok(patch) => patch,
-                Err(_) => continue,
-            };
-            let Some(mut patch) = patch else {
-                continue;
-            };
-            let content = match diff_patch_to_string(&mut patch) {
-                Ok(content) => content,
-                Err(_) => continue,
-            };
-            if content.trim().is_empty() {
-                continue;
-            }
             results.push(GitFileDiff {
                 path: normalized_path,
-                diff: content,
-                old_lines,
-                new_lines,
-                is_binary: false,
-                is_image: false,
-                old_image_data: None,
-                new_image_data: None,
-                old_image_mime: None,
-                new_image_mime: None,
+                diff: String::new(),
+                old_lines: None,
+                new_lines: None,
+                is_binary: true,
+                is_image: true,
+                old_image_data,
+                new_image_data,
+                old_image_mime: old_image_mime.map(str::to_string),
+                new_image_mime: new_image_mime.map(str::to_string),
+                root: None,
             });
+            continue;
+        }
+
+        let patch = match git2::Patch::from_diff(&diff, index) {
+            Ok(patch) => patch,
+            Err(_) => continue,
+        };
+        let Some(mut patch) = patch else {
+            continue;
+        };
+        let content = match diff_patch_to_string(&mut patch) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        if content.trim().is_empty() {
+            continue;
         }
+        results.push(GitFileDiff {
+            path: normalized_path,
+            diff: content,
+            old_lines,
+            new_lines,
+            is_binary: false,
+            is_image: false,
+            old_image_data: None,
+            new_image_data: None,
+            old_image_mime: None,
+            new_image_mime: None,
+            root: None,
+        });
+    }
+
+    Ok(results)
+}
+
+#[tauri::command]
+pub(crate) async fn get_git_diffs(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<GitFileDiff>, CommandError> {
+    state
+        .command_timings
+        .timed(
+            "get_git_diffs",
+            Some(&workspace_id),
+            git_diffs_for_workspace(workspace_id.clone(), &state),
+        )
+        .await
+        .map_err(CommandError::git)
+}
 
-        Ok(results)
+async fn git_diffs_for_workspace(
+    workspace_id: String,
+    state: &AppState,
+) -> Result<Vec<GitFileDiff>, String> {
+    let workspaces = state.workspaces.lock().await;
+    let entry = workspaces
+        .get(&workspace_id)
+        .ok_or_else(CommandError::workspace_not_found)?
+        .clone();
+    drop(workspaces);
+
+    let ignore_whitespace_changes = {
+        let settings = state.app_settings.lock().await;
+        settings.git_diff_ignore_whitespace_changes
+    };
+    let roots = workspace_roots(&entry);
+    if roots.len() == 1 {
+        let repo_root = resolve_git_root(&entry)?;
+        return tokio::task::spawn_blocking(move || {
+            collect_git_diffs(&repo_root, ignore_whitespace_changes)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    tokio::task::spawn_blocking(move || {
+        let mut results = Vec::new();
+        let mut first_error = None;
+        let mut any_ok = false;
+        for root in &roots {
+            let diffs = root
+                .git_root(&entry)
+                .and_then(|repo_root| collect_git_diffs(&repo_root, ignore_whitespace_changes));
+            match diffs {
+                Ok(diffs) => {
+                    any_ok = true;
+                    results.extend(diffs.into_iter().map(|diff| GitFileDiff {
+                        path: root.prefixed_path(&diff.path),
+                        root: Some(root.name.clone()),
+                        ..diff
+                    }));
+                }
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+        match (any_ok, first_error) {
+            (false, Some(error)) => Err(error),
+            _ => Ok(results),
+        }
     })
     .await
     .map_err(|e| e.to_string())?
@@ -990,11 +1640,106 @@ pub(crate) async fn get_git_log(
     workspace_id: String,
     limit: Option<usize>,
     state: State<'_, AppState>,
+) -> Result<GitLogResponse, CommandError> {
+    state
+        .command_timings
+        .timed(
+            "get_git_log",
+            Some(&workspace_id),
+            git_log_for_workspace(workspace_id.clone(), limit, &state),
+        )
+        .await
+        .map_err(CommandError::git)
+}
+
+/// The revwalk range for a changelog: commits reachable from `to_ref` (default `HEAD`)
+/// but not from `from_ref`, or all of `to_ref`'s history when `from_ref` is empty.
+pub(crate) fn changelog_range_spec(
+    from_ref: Option<&str>,
+    to_ref: Option<&str>,
+) -> Result<String, String> {
+    let clean = |value: Option<&str>| {
+        value
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    let from_ref = clean(from_ref);
+    let to_ref = clean(to_ref).unwrap_or_else(|| "HEAD".to_string());
+    for reference in from_ref.iter().chain(std::iter::once(&to_ref)) {
+        if reference.starts_with('-') || reference.contains("..") {
+            return Err(format!("Invalid ref `{reference}`"));
+        }
+    }
+    Ok(match from_ref {
+        Some(from_ref) => format!("{from_ref}..{to_ref}"),
+        None => to_ref,
+    })
+}
+
+fn collect_changelog_commits(
+    repo_root: &Path,
+    range: &str,
+) -> Result<Vec<ChangelogCommit>, String> {
+    let repo = Repository::open(repo_root).map_err(|e| e.to_string())?;
+    let mut revwalk = repo.revwalk().map_err(|e| e.to_string())?;
+    if range.contains("..") {
+        revwalk
+            .push_range(range)
+            .map_err(|e| format!("Invalid range `{range}`: {}", e.message()))?;
+    } else {
+        let oid = repo
+            .revparse_single(range)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| format!("Invalid ref `{range}`: {}", e.message()))?
+            .id();
+        revwalk.push(oid).map_err(|e| e.to_string())?;
+    }
+    revwalk.set_sorting(Sort::TIME).map_err(|e| e.to_string())?;
+
+    let mut commits = Vec::new();
+    for oid_result in revwalk.take(MAX_CHANGELOG_COMMITS) {
+        let oid = oid_result.map_err(|e| e.to_string())?;
+        let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+        let body = commit.body().unwrap_or("").trim().to_string();
+        let entry = commit_to_entry(commit);
+        commits.push(ChangelogCommit {
+            sha: entry.sha,
+            summary: entry.summary,
+            body,
+        });
+    }
+    Ok(commits)
+}
+
+/// Commits between `from_ref` and `to_ref` in the workspace repo, newest first.
+pub(crate) async fn get_changelog_commits(
+    workspace_id: &str,
+    from_ref: Option<&str>,
+    to_ref: Option<&str>,
+    state: &State<'_, AppState>,
+) -> Result<Vec<ChangelogCommit>, String> {
+    let range = changelog_range_spec(from_ref, to_ref)?;
+    let workspaces = state.workspaces.lock().await;
+    let entry = workspaces
+        .get(workspace_id)
+        .ok_or_else(CommandError::workspace_not_found)?
+        .clone();
+    drop(workspaces);
+
+    let repo_root = resolve_git_root(&entry)?;
+    collect_changelog_commits(&repo_root, &range)
+}
+
+async fn git_log_for_workspace(
+    workspace_id: String,
+    limit: Option<usize>,
+    state: &AppState,
 ) -> Result<GitLogResponse, String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
     drop(workspaces);
 
@@ -1089,11 +1834,11 @@ pub(crate) async fn get_git_commit_diff(
     workspace_id: String,
     sha: String,
     state: State<'_, AppState>,
-) -> Result<Vec<GitCommitDiff>, String> {
+) -> Result<Vec<GitCommitDiff>, CommandError> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
     drop(workspaces);
 
@@ -1102,18 +1847,22 @@ pub(crate) async fn get_git_commit_diff(
         settings.git_diff_ignore_whitespace_changes
     };
 
-    let repo_root = resolve_git_root(&entry)?;
-    let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
-    let oid = git2::Oid::from_str(&sha).map_err(|e| e.to_string())?;
-    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
-    let commit_tree = commit.tree().map_err(|e| e.to_string())?;
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    let repo = Repository::open(&repo_root).map_err(|e| CommandError::git(e.to_string()))?;
+    let oid = git2::Oid::from_str(&sha).map_err(|e| CommandError::git(e.to_string()))?;
+    let commit = repo
+        .find_commit(oid)
+        .map_err(|e| CommandError::git(e.to_string()))?;
+    let commit_tree = commit
+        .tree()
+        .map_err(|e| CommandError::git(e.to_string()))?;
     let parent_tree = commit.parent(0).ok().and_then(|parent| parent.tree().ok());
 
     let mut options = DiffOptions::new();
     options.ignore_whitespace_change(ignore_whitespace_changes);
     let diff = repo
         .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), Some(&mut options))
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| CommandError::git(e.to_string()))?;
 
     let mut results = Vec::new();
     for (index, delta) in diff.deltas().enumerate() {
@@ -1224,16 +1973,18 @@ pub(crate) async fn get_git_commit_diff(
 pub(crate) async fn get_git_remote(
     workspace_id: String,
     state: State<'_, AppState>,
-) -> Result<Option<String>, String> {
+) -> Result<Option<String>, CommandError> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
 
-    let repo_root = resolve_git_root(&entry)?;
-    let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
-    let remotes = repo.remotes().map_err(|e| e.to_string())?;
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    let repo = Repository::open(&repo_root).map_err(|e| CommandError::git(e.to_string()))?;
+    let remotes = repo
+        .remotes()
+        .map_err(|e| CommandError::git(e.to_string()))?;
     let name = if remotes.iter().any(|remote| remote == Some("origin")) {
         "origin".to_string()
     } else {
@@ -1242,25 +1993,52 @@ pub(crate) async fn get_git_remote(
     if name.is_empty() {
         return Ok(None);
     }
-    let remote = repo.find_remote(&name).map_err(|e| e.to_string())?;
+    let remote = repo
+        .find_remote(&name)
+        .map_err(|e| CommandError::git(e.to_string()))?;
     Ok(remote.url().map(|url| url.to_string()))
 }
 
+/// `gh` routed through the workspace's proxy.
+fn github_command(proxy: Option<&ProxyConfig>) -> Command {
+    let mut command = tokio_command("gh");
+    http_client::apply_proxy_env(&mut command, proxy);
+    command
+}
+
+/// Error for a failed `gh` invocation, with its exit code in the details.
+fn github_cli_error(output: &std::process::Output) -> CommandError {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let detail = if stderr.trim().is_empty() {
+        stdout.trim()
+    } else {
+        stderr.trim()
+    };
+    let message = if detail.is_empty() {
+        "GitHub CLI command failed."
+    } else {
+        detail
+    };
+    CommandError::git(message.to_string()).with_details(json!({ "exitCode": output.status.code() }))
+}
+
 #[tauri::command]
 pub(crate) async fn get_github_issues(
     workspace_id: String,
     state: State<'_, AppState>,
-) -> Result<GitHubIssuesResponse, String> {
+) -> Result<GitHubIssuesResponse, CommandError> {
+    let proxy = http_client::proxy_for(&state, Some(&workspace_id)).await;
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
 
-    let repo_root = resolve_git_root(&entry)?;
-    let repo_name = github_repo_from_path(&repo_root)?;
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    let repo_name = github_repo_from_path(&repo_root).map_err(CommandError::git)?;
 
-    let output = tokio_command("gh")
+    let output = github_command(proxy.as_ref())
         .args([
             "issue",
             "list",
@@ -1274,28 +2052,18 @@ pub(crate) async fn get_github_issues(
         .current_dir(&repo_root)
         .output()
         .await
-        .map_err(|e| format!("Failed to run gh: {e}"))?;
+        .map_err(|e| CommandError::git(format!("Failed to run gh: {e}")))?;
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let detail = if stderr.trim().is_empty() {
-            stdout.trim()
-        } else {
-            stderr.trim()
-        };
-        if detail.is_empty() {
-            return Err("GitHub CLI command failed.".to_string());
-        }
-        return Err(detail.to_string());
+        return Err(github_cli_error(&output));
     }
 
     let issues: Vec<GitHubIssue> =
-        serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+        serde_json::from_slice(&output.stdout).map_err(|e| CommandError::git(e.to_string()))?;
 
     let search_query = format!("repo:{repo_name} is:issue is:open");
     let search_query = search_query.replace(' ', "+");
-    let total = match tokio_command("gh")
+    let total = match github_command(proxy.as_ref())
         .args([
             "api",
             &format!("/search/issues?q={search_query}"),
@@ -1320,17 +2088,18 @@ pub(crate) async fn get_github_issues(
 pub(crate) async fn get_github_pull_requests(
     workspace_id: String,
     state: State<'_, AppState>,
-) -> Result<GitHubPullRequestsResponse, String> {
+) -> Result<GitHubPullRequestsResponse, CommandError> {
+    let proxy = http_client::proxy_for(&state, Some(&workspace_id)).await;
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
 
-    let repo_root = resolve_git_root(&entry)?;
-    let repo_name = github_repo_from_path(&repo_root)?;
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    let repo_name = github_repo_from_path(&repo_root).map_err(CommandError::git)?;
 
-    let output = tokio_command("gh")
+    let output = github_command(proxy.as_ref())
         .args([
             "pr",
             "list",
@@ -1346,28 +2115,18 @@ pub(crate) async fn get_github_pull_requests(
         .current_dir(&repo_root)
         .output()
         .await
-        .map_err(|e| format!("Failed to run gh: {e}"))?;
+        .map_err(|e| CommandError::git(format!("Failed to run gh: {e}")))?;
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let detail = if stderr.trim().is_empty() {
-            stdout.trim()
-        } else {
-            stderr.trim()
-        };
-        if detail.is_empty() {
-            return Err("GitHub CLI command failed.".to_string());
-        }
-        return Err(detail.to_string());
+        return Err(github_cli_error(&output));
     }
 
     let pull_requests: Vec<GitHubPullRequest> =
-        serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+        serde_json::from_slice(&output.stdout).map_err(|e| CommandError::git(e.to_string()))?;
 
     let search_query = format!("repo:{repo_name} is:pr is:open");
     let search_query = search_query.replace(' ', "+");
-    let total = match tokio_command("gh")
+    let total = match github_command(proxy.as_ref())
         .args([
             "api",
             &format!("/search/issues?q={search_query}"),
@@ -1396,17 +2155,18 @@ pub(crate) async fn get_github_pull_request_diff(
     workspace_id: String,
     pr_number: u64,
     state: State<'_, AppState>,
-) -> Result<Vec<GitHubPullRequestDiff>, String> {
+) -> Result<Vec<GitHubPullRequestDiff>, CommandError> {
+    let proxy = http_client::proxy_for(&state, Some(&workspace_id)).await;
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
 
-    let repo_root = resolve_git_root(&entry)?;
-    let repo_name = github_repo_from_path(&repo_root)?;
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    let repo_name = github_repo_from_path(&repo_root).map_err(CommandError::git)?;
 
-    let output = tokio_command("gh")
+    let output = github_command(proxy.as_ref())
         .args([
             "pr",
             "diff",
@@ -1419,20 +2179,10 @@ pub(crate) async fn get_github_pull_request_diff(
         .current_dir(&repo_root)
         .output()
         .await
-        .map_err(|e| format!("Failed to run gh: {e}"))?;
+        .map_err(|e| CommandError::git(format!("Failed to run gh: {e}")))?;
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let detail = if stderr.trim().is_empty() {
-            stdout.trim()
-        } else {
-            stderr.trim()
-        };
-        if detail.is_empty() {
-            return Err("GitHub CLI command failed.".to_string());
-        }
-        return Err(detail.to_string());
+        return Err(github_cli_error(&output));
     }
 
     let diff_text = String::from_utf8_lossy(&output.stdout);
@@ -1444,42 +2194,33 @@ pub(crate) async fn get_github_pull_request_comments(
     workspace_id: String,
     pr_number: u64,
     state: State<'_, AppState>,
-) -> Result<Vec<GitHubPullRequestComment>, String> {
+) -> Result<Vec<GitHubPullRequestComment>, CommandError> {
+    let proxy = http_client::proxy_for(&state, Some(&workspace_id)).await;
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
 
-    let repo_root = resolve_git_root(&entry)?;
-    let repo_name = github_repo_from_path(&repo_root)?;
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    let repo_name = github_repo_from_path(&repo_root).map_err(CommandError::git)?;
 
     let comments_endpoint = format!("/repos/{repo_name}/issues/{pr_number}/comments?per_page=30");
     let jq_filter = r#"[.[] | {id, body, createdAt: .created_at, url: .html_url, author: (if .user then {login: .user.login} else null end)}]"#;
 
-    let output = tokio_command("gh")
+    let output = github_command(proxy.as_ref())
         .args(["api", &comments_endpoint, "--jq", jq_filter])
         .current_dir(&repo_root)
         .output()
         .await
-        .map_err(|e| format!("Failed to run gh: {e}"))?;
+        .map_err(|e| CommandError::git(format!("Failed to run gh: {e}")))?;
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let detail = if stderr.trim().is_empty() {
-            stdout.trim()
-        } else {
-            stderr.trim()
-        };
-        if detail.is_empty() {
-            return Err("GitHub CLI command failed.".to_string());
-        }
-        return Err(detail.to_string());
+        return Err(github_cli_error(&output));
     }
 
     let comments: Vec<GitHubPullRequestComment> =
-        serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+        serde_json::from_slice(&output.stdout).map_err(|e| CommandError::git(e.to_string()))?;
 
     Ok(comments)
 }
@@ -1488,20 +2229,20 @@ pub(crate) async fn get_github_pull_request_comments(
 pub(crate) async fn list_git_branches(
     workspace_id: String,
     state: State<'_, AppState>,
-) -> Result<serde_json::Value, String> {
+) -> Result<serde_json::Value, CommandError> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(CommandError::workspace_not_found)?
         .clone();
-    let repo_root = resolve_git_root(&entry)?;
-    let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    let repo = Repository::open(&repo_root).map_err(|e| CommandError::git(e.to_string()))?;
     let mut branches = Vec::new();
     let refs = repo
         .branches(Some(BranchType::Local))
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| CommandError::git(e.to_string()))?;
     for branch_result in refs {
-        let (branch, _) = branch_result.map_err(|e| e.to_string())?;
+        let (branch, _) = branch_result.map_err(|e| CommandError::git(e.to_string()))?;
         let name = branch.name().ok().flatten().unwrap_or("").to_string();
         if name.is_empty() {
             continue;
@@ -1523,15 +2264,21 @@ pub(crate) async fn checkout_git_branch(
     workspace_id: String,
     name: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let workspaces = state.workspaces.lock().await;
-    let entry = workspaces
-        .get(&workspace_id)
-        .ok_or("workspace not found")?
-        .clone();
-    let repo_root = resolve_git_root(&entry)?;
-    let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
-    checkout_branch(&repo, &name).map_err(|e| e.to_string())
+) -> Result<(), CommandError> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    let (repo_root, name) = (repo_root.as_path(), name.as_str());
+    with_repository_lock(repo_root, "checkout", move || async move {
+        let repo = Repository::open(repo_root).map_err(|e| e.to_string())?;
+        checkout_branch(&repo, name).map_err(|e| e.to_string())
+    })
+    .await
 }
 
 #[tauri::command]
@@ -1539,19 +2286,171 @@ pub(crate) async fn create_git_branch(
     workspace_id: String,
     name: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let workspaces = state.workspaces.lock().await;
-    let entry = workspaces
-        .get(&workspace_id)
-        .ok_or("workspace not found")?
-        .clone();
-    let repo_root = resolve_git_root(&entry)?;
-    let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
-    let head = repo.head().map_err(|e| e.to_string())?;
-    let target = head.peel_to_commit().map_err(|e| e.to_string())?;
-    repo.branch(&name, &target, false)
-        .map_err(|e| e.to_string())?;
-    checkout_branch(&repo, &name).map_err(|e| e.to_string())
+) -> Result<(), CommandError> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
+    let repo_root = resolve_git_root(&entry).map_err(CommandError::git)?;
+    let (repo_root, name) = (repo_root.as_path(), name.as_str());
+    with_repository_lock(repo_root, "checkout", move || async move {
+        let repo = Repository::open(repo_root).map_err(|e| e.to_string())?;
+        let head = repo.head().map_err(|e| e.to_string())?;
+        let target = head.peel_to_commit().map_err(|e| e.to_string())?;
+        repo.branch(name, &target, false)
+            .map_err(|e| e.to_string())?;
+        checkout_branch(&repo, name).map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// Base/ours/theirs versions and parsed hunks of a conflicted file, for any operation
+/// that can leave conflicts (merge, rebase, stash pop, worktree apply).
+#[tauri::command]
+pub(crate) async fn get_conflict_detail(
+    workspace_id: String,
+    path: String,
+    root: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ConflictDetail, CommandError> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
+    let (repo_root, path) =
+        resolve_root_and_path(&entry, root.as_deref(), &path).map_err(CommandError::git)?;
+    conflict_detail(&repo_root, &path)
+        .await
+        .map_err(CommandError::git)
+}
+
+/// Resolves a conflicted file with `ours`, `theirs` or the given `content`, then stages it.
+#[tauri::command]
+pub(crate) async fn resolve_conflict(
+    workspace_id: String,
+    path: String,
+    resolution: String,
+    content: Option<String>,
+    root: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
+    let resolution = ConflictResolution::parse(&resolution, content).map_err(CommandError::git)?;
+    let (repo_root, path) =
+        resolve_root_and_path(&entry, root.as_deref(), &path).map_err(CommandError::git)?;
+    let (repo_root, path, resolution) = (repo_root.as_path(), path.as_str(), &resolution);
+    with_repository_lock(repo_root, "resolve conflict", move || {
+        conflicts::resolve_conflict(repo_root, path, resolution)
+    })
+    .await
+}
+
+/// Text of an agent message item, with the session that owns its thread.
+async fn agent_message_text(
+    state: &AppState,
+    workspace_id: &str,
+    thread_id: &str,
+    item_id: &str,
+) -> Result<(Arc<WorkspaceSession>, String), String> {
+    let session = state
+        .sessions
+        .lock()
+        .await
+        .get(workspace_id)
+        .cloned()
+        .ok_or_else(CommandError::workspace_not_connected)?;
+    let item = session.find_thread_item(thread_id, item_id).await?;
+    if item.get("type").and_then(Value::as_str) != Some("agentMessage") {
+        return Err(format!("Item `{item_id}` is not an agent message"));
+    }
+    let text = item
+        .get("text")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    Ok((session, text))
+}
+
+/// Finds the fenced diff blocks of an agent message and checks each one with
+/// `git apply --check`, explaining why a patch does not apply.
+#[tauri::command]
+pub(crate) async fn extract_patches_from_item(
+    workspace_id: String,
+    thread_id: String,
+    item_id: String,
+    root: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ExtractedPatch>, CommandError> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
+    let repo_root = resolve_root(&entry, root.as_deref()).map_err(CommandError::git)?;
+    let (_, text) = agent_message_text(&state, &workspace_id, &thread_id, &item_id).await?;
+    Ok(patches::extract_patches(&repo_root, &text).await)
+}
+
+/// Applies one patch returned by `extract_patches_from_item`, optionally staging it, and
+/// records it as an artifact of the item.
+#[tauri::command]
+pub(crate) async fn apply_extracted_patch(
+    workspace_id: String,
+    thread_id: String,
+    item_id: String,
+    index: usize,
+    stage: Option<bool>,
+    root: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<AppliedPatch, CommandError> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
+    let repo_root = resolve_root(&entry, root.as_deref()).map_err(CommandError::git)?;
+    let (session, text) = agent_message_text(&state, &workspace_id, &thread_id, &item_id).await?;
+    let block = patches::fenced_patch_blocks(&text)
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| CommandError::git(format!("Item `{item_id}` has no patch #{index}")))?;
+    let patch = patches::parse_patch(&block.text).map_err(CommandError::git)?;
+    let stage = stage.unwrap_or(false);
+    let (repo_root, patch_ref) = (repo_root.as_path(), &patch);
+    with_repository_lock(repo_root, "apply patch", move || async move {
+        patches::git_apply(repo_root, patch_ref, true, stage).await?;
+        patches::git_apply(repo_root, patch_ref, false, stage).await
+    })
+    .await?;
+    let artifact =
+        patches::record_patch_artifact(Path::new(&entry.path), &item_id, index, &patch, stage)
+            .map_err(CommandError::git)?;
+    session
+        .add_item_artifact(&thread_id, &item_id, artifact.clone())
+        .await
+        .map_err(CommandError::git)?;
+    Ok(AppliedPatch {
+        index,
+        files: patch.files,
+        staged: stage,
+        artifact,
+    })
 }
 
 #[cfg(test)]
@@ -1593,6 +2492,39 @@ mod tests {
         assert!(diff.contains("unstaged"));
     }
 
+    #[test]
+    fn concurrent_staging_runs_one_after_the_other() {
+        let (root, repo) = create_temp_repo();
+        fs::write(root.join("one.txt"), "one\n").expect("write file");
+        fs::write(root.join("two.txt"), "two\n").expect("write file");
+
+        let runtime = tokio::runtime::Runtime::new().expect("create runtime");
+        runtime.block_on(async {
+            let stage_one = tokio::spawn({
+                let root = root.clone();
+                async move { stage_paths(&root, &["one.txt".to_string()]).await }
+            });
+            let stage_all = tokio::spawn({
+                let root = root.clone();
+                async move {
+                    with_repository_lock(&root, "stage", || run_git_command(&root, &["add", "-A"]))
+                        .await
+                }
+            });
+            stage_one.await.expect("join").expect("stage one.txt");
+            stage_all.await.expect("join").expect("stage all");
+        });
+
+        assert!(!root.join(".git").join("index.lock").exists());
+        let statuses = repo.statuses(None).expect("statuses");
+        let staged = statuses
+            .iter()
+            .filter(|entry| entry.status().contains(Status::INDEX_NEW))
+            .filter_map(|entry| entry.path().map(ToString::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(staged, vec!["one.txt".to_string(), "two.txt".to_string()]);
+    }
+
     #[test]
     fn action_paths_for_file_expands_renames() {
         let (root, repo) = create_temp_repo();
@@ -1619,4 +2551,114 @@ mod tests {
         let paths = action_paths_for_file(&root, "b.txt");
         assert_eq!(paths, vec!["a.txt".to_string(), "b.txt".to_string()]);
     }
+
+    fn commit_file(repo: &Repository, root: &Path, name: &str, content: &str, message: &str) {
+        fs::write(root.join(name), content).expect("write file");
+        let mut index = repo.index().expect("repo index");
+        index.add_path(Path::new(name)).expect("add path");
+        index.write().expect("write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let sig = git2::Signature::now("Test", "test@example.com").expect("signature");
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents = parent.iter().collect::<Vec<_>>();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .expect("commit");
+    }
+
+    #[test]
+    fn branch_diff_compares_against_the_default_branch() {
+        let (root, repo) = create_temp_repo();
+        commit_file(&repo, &root, "base.txt", "base\n", "init");
+        let base_commit = repo.head().expect("head").peel_to_commit().expect("commit");
+        repo.branch("main", &base_commit, true).expect("main branch");
+        repo.branch("feature", &base_commit, false)
+            .expect("feature branch");
+        repo.set_head("refs/heads/feature").expect("checkout feature");
+        commit_file(&repo, &root, "feature.txt", "feature\n", "Add feature");
+
+        let branch_diff = collect_branch_diff(&root, None).expect("branch diff");
+        assert_eq!(branch_diff.branch, "feature");
+        assert_eq!(branch_diff.base, "main");
+        assert_eq!(branch_diff.commits, vec!["Add feature".to_string()]);
+        assert!(branch_diff.diff.contains("=== feature.txt ==="));
+        assert!(!branch_diff.diff.contains("base.txt"));
+
+        let against_feature = collect_branch_diff(&root, Some("feature")).expect("diff");
+        assert!(against_feature.diff.trim().is_empty());
+        assert!(collect_branch_diff(&root, Some("missing")).is_err());
+    }
+
+    #[test]
+    fn truncated_diffs_cut_large_files_and_list_the_rest() {
+        let patch = (0..100)
+            .map(|line| format!("+line {line}\n"))
+            .collect::<String>();
+        let cut = truncate_file_patch(&patch, 64);
+        assert!(cut.len() < patch.len());
+        assert!(cut.starts_with("+line 0\n"));
+        assert!(cut.contains("more line(s) of this file omitted"), "{cut}");
+        assert_eq!(truncate_file_patch("+short\n", 64), "+short\n");
+
+        let (root, repo) = create_temp_repo();
+        commit_file(&repo, &root, "seed.txt", "seed\n", "init");
+        fs::write(root.join("big.txt"), &patch).expect("write file");
+        fs::write(root.join("later.txt"), "later\n").expect("write file");
+        let mut options = DiffOptions::new();
+        options.include_untracked(true).show_untracked_content(true);
+        let head_tree = repo.head().expect("head").peel_to_tree().expect("tree");
+        let diff = repo
+            .diff_tree_to_workdir(Some(&head_tree), Some(&mut options))
+            .expect("diff");
+        let combined = build_truncated_diff(&diff, 128, 64);
+        assert!(combined.contains("=== big.txt ==="));
+        assert!(combined.contains("more line(s) of this file omitted"));
+        assert!(combined.contains("1 more changed file(s) omitted: later.txt"));
+    }
+
+    #[test]
+    fn changelog_range_spec_builds_the_revwalk_range() {
+        assert_eq!(
+            changelog_range_spec(Some("v1.2.0"), None).as_deref(),
+            Ok("v1.2.0..HEAD")
+        );
+        assert_eq!(
+            changelog_range_spec(Some(" v1.2.0 "), Some("release/1.3")).as_deref(),
+            Ok("v1.2.0..release/1.3")
+        );
+        assert_eq!(
+            changelog_range_spec(Some(""), Some("main")).as_deref(),
+            Ok("main")
+        );
+        assert_eq!(changelog_range_spec(None, None).as_deref(), Ok("HEAD"));
+        assert!(changelog_range_spec(Some("--all"), None).is_err());
+        assert!(changelog_range_spec(Some("a..b"), None).is_err());
+    }
+
+    #[test]
+    fn changelog_commits_cover_only_the_range() {
+        let (root, repo) = create_temp_repo();
+        commit_file(&repo, &root, "a.txt", "a\n", "chore: init");
+        let first = repo.head().expect("head").peel_to_commit().expect("commit");
+        repo.tag_lightweight("v1.0.0", first.as_object(), false)
+            .expect("tag");
+        commit_file(
+            &repo,
+            &root,
+            "b.txt",
+            "b\n",
+            "feat: add b\n\nExplains why b exists.",
+        );
+
+        let range = changelog_range_spec(Some("v1.0.0"), None).expect("range");
+        let commits = collect_changelog_commits(&root, &range).expect("commits");
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].summary, "feat: add b");
+        assert_eq!(commits[0].body, "Explains why b exists.");
+
+        let all = collect_changelog_commits(&root, "HEAD").expect("commits");
+        assert_eq!(all.len(), 2);
+        assert!(collect_changelog_commits(&root, "missing..HEAD").is_err());
+    }
 }
@@ -0,0 +1,563 @@
+use std::path::{Component, Path};
+use std::process::Stdio;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+
+use crate::backend::app_server::now_ms;
+use crate::backend::turn_artifacts::guess_mime;
+use crate::shared::process_core::tokio_command;
+use crate::utils::{git_env_path, resolve_git_binary};
+
+/// Info strings of fenced blocks that are read as patches.
+const PATCH_LANGUAGES: &[&str] = &["diff", "patch", "udiff"];
+/// Applied patches are kept here, relative to the workspace root, as item artifacts.
+const PATCHES_DIR: &str = ".micodemonitor/patches";
+
+/// A fenced diff block of an agent message and whether it applies to the repository.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExtractedPatch {
+    /// Position among the patch blocks of the item, as passed to `apply_extracted_patch`.
+    pub(crate) index: usize,
+    /// 1-based line of the opening fence in the message.
+    pub(crate) line: usize,
+    /// Repository-relative paths the patch touches.
+    pub(crate) files: Vec<String>,
+    pub(crate) patch: String,
+    pub(crate) applicable: bool,
+    /// Why the patch cannot be applied; `None` when it can.
+    pub(crate) reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AppliedPatch {
+    pub(crate) index: usize,
+    pub(crate) files: Vec<String>,
+    pub(crate) staged: bool,
+    /// Artifact recorded on the originating item.
+    pub(crate) artifact: Value,
+}
+
+/// A fenced block that looks like a patch, before it is parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PatchBlock {
+    pub(crate) line: usize,
+    pub(crate) text: String,
+}
+
+/// A patch that passed the structural checks, normalized for `git apply`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ParsedPatch {
+    pub(crate) files: Vec<String>,
+    /// Leading path components `git apply -p` strips: 1 for `a/`/`b/` headers, else 0.
+    pub(crate) strip: u8,
+    pub(crate) text: String,
+}
+
+fn fence_of(line: &str) -> Option<(char, usize, &str)> {
+    let trimmed = line.trim_start();
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.chars().take_while(|c| *c == marker).count();
+    (len >= 3).then(|| (marker, len, trimmed[len..].trim()))
+}
+
+fn looks_like_patch(body: &[&str]) -> bool {
+    body.iter()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| line.starts_with("diff --git ") || line.starts_with("--- "))
+}
+
+/// Fenced blocks tagged `diff`/`patch`, plus untagged ones whose content starts like a
+/// diff. An unclosed fence runs to the end of the message.
+pub(crate) fn fenced_patch_blocks(text: &str) -> Vec<PatchBlock> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut blocks = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let Some((marker, len, info)) = fence_of(lines[index]) else {
+            index += 1;
+            continue;
+        };
+        let start = index + 1;
+        let end = lines[start..]
+            .iter()
+            .position(|line| {
+                fence_of(line).is_some_and(|(closing, closing_len, rest)| {
+                    closing == marker && closing_len >= len && rest.is_empty()
+                })
+            })
+            .map(|offset| start + offset)
+            .unwrap_or(lines.len());
+        let body = &lines[start..end];
+        let language = info
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let is_patch = if language.is_empty() {
+            looks_like_patch(body)
+        } else {
+            PATCH_LANGUAGES.contains(&language.as_str())
+        };
+        if is_patch {
+            blocks.push(PatchBlock {
+                line: index + 1,
+                text: body.join("\n"),
+            });
+        }
+        index = end + 1;
+    }
+    blocks
+}
+
+fn header_path(line: &str, prefix: &str) -> String {
+    let raw = line[prefix.len()..].trim_end();
+    // `git diff` separates a timestamp with a tab.
+    raw.split('\t').next().unwrap_or(raw).trim().to_string()
+}
+
+fn hunk_counts(header: &str) -> Option<(usize, usize)> {
+    let ranges = header.strip_prefix("@@ ")?.split(" @@").next()?;
+    let mut parts = ranges.split_whitespace();
+    let count = |range: Option<&str>, sign: char| -> Option<usize> {
+        let range = range?.strip_prefix(sign)?;
+        let mut numbers = range.splitn(2, ',');
+        numbers.next()?.parse::<usize>().ok()?;
+        match numbers.next() {
+            Some(count) => count.parse().ok(),
+            None => Some(1),
+        }
+    };
+    Some((count(parts.next(), '-')?, count(parts.next(), '+')?))
+}
+
+fn strip_path(path: &str, strip: u8) -> String {
+    if strip == 0 {
+        return path.to_string();
+    }
+    path.split_once('/')
+        .map(|(_, rest)| rest.to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn check_inside_repository(path: &str) -> Result<(), String> {
+    let escapes = Path::new(path).components().any(|component| {
+        matches!(
+            component,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_)
+        )
+    });
+    if escapes {
+        return Err(format!("`{path}` is outside the repository"));
+    }
+    Ok(())
+}
+
+/// Checks the structure of a patch block: file headers before hunks and hunk bodies
+/// matching their line counts. Blank lines inside a hunk, which models often emit for
+/// empty context lines, are restored to a single space.
+pub(crate) fn parse_patch(text: &str) -> Result<ParsedPatch, String> {
+    // Not `lines()`: a trailing blank line can still be the last context line of a hunk.
+    let lines: Vec<&str> = text
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect();
+    let mut out: Vec<String> = Vec::new();
+    // (old path, new path) of each `---`/`+++` pair.
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut git_files: Vec<String> = Vec::new();
+    let mut current_file: Option<String> = None;
+    let mut hunks = 0;
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            if let Some((_, new_path)) = rest.split_once(" b/") {
+                git_files.push(format!("b/{new_path}"));
+            }
+            current_file = None;
+            out.push(line.to_string());
+            index += 1;
+            continue;
+        }
+        if line.starts_with("--- ")
+            && lines
+                .get(index + 1)
+                .is_some_and(|next| next.starts_with("+++ "))
+        {
+            let old_path = header_path(line, "--- ");
+            let new_path = header_path(lines[index + 1], "+++ ");
+            let path = if new_path == "/dev/null" {
+                old_path.clone()
+            } else {
+                new_path.clone()
+            };
+            current_file = Some(path);
+            headers.push((old_path, new_path));
+            out.push(line.to_string());
+            out.push(lines[index + 1].to_string());
+            index += 2;
+            continue;
+        }
+        if line.starts_with("@@") {
+            let Some(file) = current_file.clone() else {
+                return Err(format!(
+                    "hunk at patch line {} has no file header (`--- a/<path>` and `+++ b/<path>`)",
+                    index + 1
+                ));
+            };
+            let Some((old_count, new_count)) = hunk_counts(line) else {
+                return Err(format!(
+                    "hunk header at patch line {} of `{file}` has no line numbers",
+                    index + 1
+                ));
+            };
+            hunks += 1;
+            let header_line = index + 1;
+            out.push(line.to_string());
+            index += 1;
+            let (mut old_seen, mut new_seen) = (0, 0);
+            while old_seen < old_count || new_seen < new_count {
+                let Some(body) = lines.get(index) else {
+                    break;
+                };
+                match body.chars().next() {
+                    None => {
+                        old_seen += 1;
+                        new_seen += 1;
+                        out.push(" ".to_string());
+                    }
+                    Some(' ') => {
+                        old_seen += 1;
+                        new_seen += 1;
+                        out.push(body.to_string());
+                    }
+                    Some('-') => {
+                        old_seen += 1;
+                        out.push(body.to_string());
+                    }
+                    Some('+') => {
+                        new_seen += 1;
+                        out.push(body.to_string());
+                    }
+                    Some('\\') => out.push(body.to_string()),
+                    Some(_) => break,
+                }
+                index += 1;
+            }
+            if old_seen != old_count || new_seen != new_count {
+                return Err(format!(
+                    "hunk at patch line {header_line} of `{file}` declares {old_count} old and \
+                     {new_count} new lines but has {old_seen} and {new_seen}"
+                ));
+            }
+            while lines.get(index).is_some_and(|next| next.starts_with('\\')) {
+                out.push(lines[index].to_string());
+                index += 1;
+            }
+            if let Some(next) = lines.get(index) {
+                let starts_header = next.starts_with("--- ")
+                    && lines
+                        .get(index + 1)
+                        .is_some_and(|after| after.starts_with("+++ "));
+                if (next.starts_with('+') || next.starts_with('-') || next.starts_with(' '))
+                    && !starts_header
+                {
+                    return Err(format!(
+                        "hunk at patch line {header_line} of `{file}` has more lines than its \
+                         header declares"
+                    ));
+                }
+            }
+            continue;
+        }
+        out.push(line.to_string());
+        index += 1;
+    }
+    if hunks == 0 && git_files.is_empty() {
+        return Err(if headers.is_empty() {
+            "no file header (`--- a/<path>` and `+++ b/<path>`)".to_string()
+        } else {
+            "no hunks".to_string()
+        });
+    }
+    let prefixed = |path: &str, prefix: &str| path == "/dev/null" || path.starts_with(prefix);
+    let strip = if headers.is_empty()
+        || headers
+            .iter()
+            .all(|(old, new)| prefixed(old, "a/") && prefixed(new, "b/"))
+    {
+        1
+    } else {
+        0
+    };
+    let mut files: Vec<String> = headers
+        .iter()
+        .map(|(old, new)| if new == "/dev/null" { old } else { new })
+        .chain(git_files.iter())
+        .map(|path| strip_path(path, strip))
+        .collect();
+    files.sort();
+    files.dedup();
+    for file in &files {
+        check_inside_repository(file)?;
+    }
+    while out.last().is_some_and(|line| line.is_empty()) {
+        out.pop();
+    }
+    let mut text = out.join("\n");
+    text.push('\n');
+    Ok(ParsedPatch { files, strip, text })
+}
+
+/// Turns `git apply` output into one reason, naming the file and line of a context
+/// mismatch. Unknown errors are passed through.
+pub(crate) fn describe_apply_error(output: &str) -> String {
+    let mut reasons = Vec::new();
+    for line in output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        let message = line
+            .strip_prefix("error: ")
+            .or_else(|| line.strip_prefix("fatal: "))
+            .unwrap_or(line);
+        if let Some(location) = message.strip_prefix("patch failed: ") {
+            let (path, line) = location.rsplit_once(':').unwrap_or((location, "?"));
+            reasons.push(format!("context does not match `{path}` at line {line}"));
+        } else if message.ends_with(": patch does not apply") && !reasons.is_empty() {
+            // Already explained by the `patch failed` line before it.
+        } else if let Some(path) = message.strip_suffix(": does not exist in index") {
+            reasons.push(format!("`{path}` is not tracked by git"));
+        } else if let Some(path) = message.strip_suffix(": No such file or directory") {
+            reasons.push(format!("`{path}` does not exist"));
+        } else if let Some(path) = message.strip_suffix(": already exists in working directory") {
+            reasons.push(format!("`{path}` already exists"));
+        } else {
+            reasons.push(message.to_string());
+        }
+    }
+    if reasons.is_empty() {
+        return "git apply failed".to_string();
+    }
+    reasons.join("; ")
+}
+
+/// Runs `git apply`, with `--check` only validating. `stage` also updates the index.
+pub(crate) async fn git_apply(
+    repo_root: &Path,
+    patch: &ParsedPatch,
+    check: bool,
+    stage: bool,
+) -> Result<(), String> {
+    let git_bin = resolve_git_binary().map_err(|e| format!("Failed to run git: {e}"))?;
+    let mut args = vec!["apply".to_string(), format!("-p{}", patch.strip)];
+    if check {
+        args.push("--check".to_string());
+    }
+    if stage {
+        args.push("--index".to_string());
+    }
+    args.push("-".to_string());
+    let mut child = tokio_command(git_bin)
+        .args(&args)
+        .current_dir(repo_root)
+        .env("PATH", git_env_path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(patch.text.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to run git: {e}"))?;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // Keep the raw text of a locked index so the repository lock retries it.
+    if super::repo_lock::is_index_lock_error(&stderr) {
+        return Err(stderr.trim().to_string());
+    }
+    Err(describe_apply_error(&stderr))
+}
+
+/// Parses every patch block of `text` and checks it against the repository.
+pub(crate) async fn extract_patches(repo_root: &Path, text: &str) -> Vec<ExtractedPatch> {
+    let mut patches = Vec::new();
+    for (index, block) in fenced_patch_blocks(text).into_iter().enumerate() {
+        let extracted = match parse_patch(&block.text) {
+            Ok(parsed) => {
+                let checked = git_apply(repo_root, &parsed, true, false).await;
+                ExtractedPatch {
+                    index,
+                    line: block.line,
+                    files: parsed.files,
+                    patch: parsed.text,
+                    applicable: checked.is_ok(),
+                    reason: checked.err(),
+                }
+            }
+            Err(reason) => ExtractedPatch {
+                index,
+                line: block.line,
+                files: Vec::new(),
+                patch: block.text,
+                applicable: false,
+                reason: Some(reason),
+            },
+        };
+        patches.push(extracted);
+    }
+    patches
+}
+
+fn patch_file_name(item_id: &str, index: usize) -> String {
+    let safe: String = item_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{safe}-{index}.patch")
+}
+
+/// Keeps a copy of an applied patch under the workspace state directory and describes
+/// it as an artifact of the item it came from.
+pub(crate) fn record_patch_artifact(
+    workspace_root: &Path,
+    item_id: &str,
+    index: usize,
+    patch: &ParsedPatch,
+    staged: bool,
+) -> Result<Value, String> {
+    let relative = format!("{PATCHES_DIR}/{}", patch_file_name(item_id, index));
+    let target = workspace_root.join(&relative);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(&target, &patch.text).map_err(|e| e.to_string())?;
+    Ok(json!({
+        "path": relative,
+        "size": patch.text.len(),
+        "mime": guess_mime(&relative),
+        "createdByToolId": null,
+        "missing": false,
+        "appliedPatch": {
+            "index": index,
+            "files": patch.files,
+            "staged": staged,
+            "appliedAtMs": now_ms(),
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "Apply this:\n\n```diff\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,3 +1,3 @@\n fn one() {}\n-fn two() {}\n+fn deux() {}\n\n```\n\nAnd run:\n\n```sh\ngit diff\n```\n\n```\n--- notes.txt\n+++ notes.txt\n@@ -1 +1 @@\n-old\n+new\n```\n";
+
+    #[test]
+    fn extracts_diff_blocks_and_restores_blank_context_lines() {
+        let blocks = fenced_patch_blocks(MESSAGE);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].line, 3);
+        assert_eq!(blocks[1].line, 19);
+
+        let parsed = parse_patch(&blocks[0].text).expect("first patch");
+        assert_eq!(parsed.strip, 1);
+        assert_eq!(parsed.files, vec!["src/lib.rs".to_string()]);
+        assert!(parsed.text.ends_with("+fn deux() {}\n \n"));
+
+        let parsed = parse_patch(&blocks[1].text).expect("second patch");
+        assert_eq!(parsed.strip, 0);
+        assert_eq!(parsed.files, vec!["notes.txt".to_string()]);
+    }
+
+    #[test]
+    fn explains_ambiguous_patches() {
+        assert_eq!(
+            parse_patch("@@ -1 +1 @@\n-old\n+new").unwrap_err(),
+            "hunk at patch line 1 has no file header (`--- a/<path>` and `+++ b/<path>`)"
+        );
+        assert_eq!(
+            parse_patch("--- a/x.txt\n+++ b/x.txt\n@@ -1,2 +1,2 @@\n-old\n+new").unwrap_err(),
+            "hunk at patch line 3 of `b/x.txt` declares 2 old and 2 new lines but has 1 and 1"
+        );
+        assert_eq!(
+            parse_patch("--- a/x.txt\n+++ b/x.txt\n@@ -1 +1 @@\n-old\n+new\n+extra").unwrap_err(),
+            "hunk at patch line 3 of `b/x.txt` has more lines than its header declares"
+        );
+        assert_eq!(
+            parse_patch("--- a/x.txt\n+++ b/x.txt\n@@ @@\n-old").unwrap_err(),
+            "hunk header at patch line 3 of `b/x.txt` has no line numbers"
+        );
+        assert_eq!(
+            parse_patch("--- ../x.txt\n+++ ../x.txt\n@@ -1 +1 @@\n-old\n+new").unwrap_err(),
+            "`../x.txt` is outside the repository"
+        );
+        assert_eq!(
+            describe_apply_error(
+                "error: patch failed: src/lib.rs:12\nerror: src/lib.rs: patch does not apply\n"
+            ),
+            "context does not match `src/lib.rs` at line 12"
+        );
+    }
+
+    #[test]
+    fn checks_patches_against_the_repository_before_applying_them() {
+        let root = std::env::temp_dir().join(format!("micode-patches-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).expect("create repo dir");
+        git2::Repository::init(&root).expect("init repo");
+        std::fs::write(root.join("src/lib.rs"), "fn one() {}\nfn two() {}\n\n")
+            .expect("write file");
+        std::fs::write(root.join("notes.txt"), "current\n").expect("write file");
+
+        let runtime = tokio::runtime::Runtime::new().expect("create runtime");
+        let patches = runtime.block_on(extract_patches(&root, MESSAGE));
+        assert_eq!(patches.len(), 2);
+        assert!(patches[0].applicable, "{:?}", patches[0].reason);
+        assert_eq!(
+            patches[1].reason.as_deref(),
+            Some("context does not match `notes.txt` at line 1")
+        );
+
+        let parsed = parse_patch(&fenced_patch_blocks(MESSAGE)[0].text).expect("parse");
+        runtime
+            .block_on(git_apply(&root, &parsed, false, false))
+            .expect("apply patch");
+        assert_eq!(
+            std::fs::read_to_string(root.join("src/lib.rs")).expect("read file"),
+            "fn one() {}\nfn deux() {}\n\n"
+        );
+        let artifact =
+            record_patch_artifact(&root, "agent-t1-turn", 0, &parsed, false).expect("artifact");
+        assert_eq!(
+            artifact["path"],
+            ".micodemonitor/patches/agent-t1-turn-0.patch"
+        );
+        assert_eq!(artifact["mime"], "text/x-diff");
+        assert_eq!(artifact["appliedPatch"]["files"], json!(["src/lib.rs"]));
+        assert!(root
+            .join(".micodemonitor/patches/agent-t1-turn-0.patch")
+            .exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
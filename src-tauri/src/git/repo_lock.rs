@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use crate::types::errors::{CommandError, ErrorCode};
+use crate::types::GitIdentity;
+use tokio::time::{sleep, timeout, Instant};
+
+/// How long an index-mutating command waits for the repository before giving up.
+pub(crate) const REPOSITORY_BUSY_TIMEOUT: Duration = Duration::from_secs(20);
+const INDEX_LOCK_INITIAL_BACKOFF: Duration = Duration::from_millis(50);
+const INDEX_LOCK_MAX_BACKOFF: Duration = Duration::from_secs(1);
+const EXTERNAL_HOLDER: &str = "an external git process";
+
+fn repository_busy(operation: &str, held_by: &str) -> CommandError {
+    CommandError::new(
+        ErrorCode::RepositoryBusy,
+        format!("Repository is busy with {held_by}; {operation} was not started."),
+    )
+    .with_details(json!({ "heldBy": held_by }))
+}
+
+pub(crate) fn identity_mismatch(expected: GitIdentity, actual: GitIdentity) -> CommandError {
+    let describe = |identity: &GitIdentity| {
+        format!(
+            "{} <{}>",
+            identity.name.as_deref().unwrap_or("(no name)"),
+            identity.email.as_deref().unwrap_or("(no email)")
+        )
+    };
+    CommandError::new(
+        ErrorCode::IdentityMismatch,
+        format!(
+            "Git would commit as {} but this workspace expects {}.",
+            describe(&actual),
+            describe(&expected)
+        ),
+    )
+    .with_details(json!({ "expected": expected, "actual": actual }))
+}
+
+#[derive(Default)]
+struct RepositoryLock {
+    mutex: Mutex<()>,
+    holder: std::sync::Mutex<Option<&'static str>>,
+}
+
+impl RepositoryLock {
+    fn holder(&self) -> Option<&'static str> {
+        self.holder.lock().ok().and_then(|holder| *holder)
+    }
+
+    fn set_holder(&self, operation: Option<&'static str>) {
+        if let Ok(mut holder) = self.holder.lock() {
+            *holder = operation;
+        }
+    }
+}
+
+/// Clears the recorded holder even when the operation is cancelled mid-way.
+struct HolderGuard<'a>(&'a RepositoryLock);
+
+impl Drop for HolderGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set_holder(None);
+    }
+}
+
+fn registry() -> &'static std::sync::Mutex<HashMap<PathBuf, Arc<RepositoryLock>>> {
+    static REGISTRY: OnceLock<std::sync::Mutex<HashMap<PathBuf, Arc<RepositoryLock>>>> =
+        OnceLock::new();
+    REGISTRY.get_or_init(|| std::sync::Mutex::new(HashMap::new()))
+}
+
+fn repository_lock(repo_root: &Path) -> Arc<RepositoryLock> {
+    let key = repo_root
+        .canonicalize()
+        .unwrap_or_else(|_| repo_root.to_path_buf());
+    let mut registry = registry()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    registry.entry(key).or_default().clone()
+}
+
+pub(crate) fn is_index_lock_error(error: &str) -> bool {
+    error.contains("index.lock")
+}
+
+/// Runs an index-mutating git operation once no other one is running on the same
+/// repository. Read-only commands don't take the lock.
+pub(crate) async fn with_repository_lock<T, F, Fut>(
+    repo_root: &Path,
+    operation: &'static str,
+    run: F,
+) -> Result<T, CommandError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    with_repository_lock_timeout(repo_root, operation, REPOSITORY_BUSY_TIMEOUT, run).await
+}
+
+pub(crate) async fn with_repository_lock_timeout<T, F, Fut>(
+    repo_root: &Path,
+    operation: &'static str,
+    wait: Duration,
+    mut run: F,
+) -> Result<T, CommandError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let deadline = Instant::now() + wait;
+    let lock = repository_lock(repo_root);
+    let _guard = match timeout(wait, lock.mutex.lock()).await {
+        Ok(guard) => guard,
+        Err(_) => {
+            let held_by = lock.holder().unwrap_or("another git operation");
+            return Err(repository_busy(operation, held_by));
+        }
+    };
+    lock.set_holder(Some(operation));
+    let _holder = HolderGuard(&lock);
+
+    // With our own operations serialized, an `index.lock` now belongs to another
+    // process (an editor, a terminal); wait for it to go away.
+    let mut backoff = INDEX_LOCK_INITIAL_BACKOFF;
+    loop {
+        match run().await {
+            Ok(value) => return Ok(value),
+            Err(error) if is_index_lock_error(&error) => {
+                if Instant::now() + backoff > deadline {
+                    return Err(repository_busy(operation, EXTERNAL_HOLDER));
+                }
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(INDEX_LOCK_MAX_BACKOFF);
+            }
+            Err(error) => return Err(CommandError::git(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::runtime::Runtime;
+
+    fn temp_root() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("micode-repo-lock-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create temp root");
+        root
+    }
+
+    #[test]
+    fn reports_the_holding_operation_when_the_wait_times_out() {
+        let root = temp_root();
+        Runtime::new().expect("runtime").block_on(async {
+            let lock = repository_lock(&root);
+            let guard = lock.mutex.lock().await;
+            lock.set_holder(Some("commit"));
+
+            let result =
+                with_repository_lock_timeout(&root, "stage", Duration::from_millis(50), || async {
+                    Ok::<_, String>(())
+                })
+                .await;
+
+            let error = result.expect_err("busy");
+            assert_eq!(error.code, ErrorCode::RepositoryBusy);
+            assert!(error.retryable);
+            assert_eq!(error.details, Some(json!({ "heldBy": "commit" })));
+            drop(guard);
+        });
+    }
+
+    #[test]
+    fn retries_while_an_external_index_lock_exists() {
+        let root = temp_root();
+        Runtime::new().expect("runtime").block_on(async {
+            let attempts = &AtomicUsize::new(0);
+            let result = with_repository_lock_timeout(
+                &root,
+                "stage",
+                Duration::from_secs(5),
+                move || async move {
+                    if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                        Err("fatal: Unable to create '.git/index.lock': File exists.".to_string())
+                    } else {
+                        Ok(())
+                    }
+                },
+            )
+            .await;
+            assert!(result.is_ok());
+            assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+            let result = with_repository_lock_timeout(
+                &root,
+                "stage",
+                Duration::from_millis(120),
+                || async { Err::<(), _>("Unable to create 'index.lock'".to_string()) },
+            )
+            .await;
+            let error = result.expect_err("busy");
+            assert_eq!(error.details, Some(json!({ "heldBy": EXTERNAL_HOLDER })));
+            assert!(repository_lock(&root).holder().is_none());
+        });
+    }
+}
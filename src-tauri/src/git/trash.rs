@@ -0,0 +1,498 @@
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+use git2::{Repository, StatusOptions};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::backend::app_server::now_ms;
+
+const STATE_DIR_NAME: &str = ".micodemonitor";
+const MANIFEST_FILE: &str = "manifest.json";
+const FILES_DIR: &str = "files";
+const RETENTION_MS: u64 = 14 * 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TrashLimits {
+    /// Files larger than this are not backed up; the revert still discards them.
+    pub(crate) max_file_bytes: u64,
+    pub(crate) max_snapshot_bytes: u64,
+    /// Oldest snapshots are pruned until the whole trash fits.
+    pub(crate) max_total_bytes: u64,
+}
+
+pub(crate) const DEFAULT_TRASH_LIMITS: TrashLimits = TrashLimits {
+    max_file_bytes: 10 * 1024 * 1024,
+    max_snapshot_bytes: 100 * 1024 * 1024,
+    max_total_bytes: 500 * 1024 * 1024,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TrashFile {
+    /// Path relative to the repository root.
+    pub(crate) path: String,
+    pub(crate) bytes: u64,
+    /// The file did not exist in the working tree, so restoring removes it again.
+    pub(crate) deleted: bool,
+    /// Hash of what the revert left behind (`None` when it left no file); a different
+    /// file on restore means it was edited since.
+    pub(crate) reverted_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TrashSkippedFile {
+    pub(crate) path: String,
+    pub(crate) bytes: u64,
+    pub(crate) reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TrashEntry {
+    pub(crate) id: String,
+    pub(crate) created_at_ms: u64,
+    /// `revertFile` or `revertAll`.
+    pub(crate) operation: String,
+    pub(crate) repo_root: String,
+    pub(crate) total_bytes: u64,
+    pub(crate) files: Vec<TrashFile>,
+    pub(crate) skipped: Vec<TrashSkippedFile>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GitRevertResult {
+    /// Snapshot of the discarded files, absent when nothing was backed up.
+    pub(crate) trash_id: Option<String>,
+    pub(crate) warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TrashRestoreResult {
+    pub(crate) restored: Vec<String>,
+    /// Files edited since the revert; left untouched.
+    pub(crate) conflicts: Vec<String>,
+}
+
+pub(crate) fn trash_dir(workspace_root: &Path) -> PathBuf {
+    workspace_root.join(STATE_DIR_NAME).join("trash")
+}
+
+fn is_state_path(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .next()
+        .is_some_and(|component| component.as_os_str() == STATE_DIR_NAME)
+}
+
+/// Keeps stored paths inside the repository when a manifest has been tampered with.
+fn is_safe_relative(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn content_hash(path: &Path) -> Option<String> {
+    let data = std::fs::read(path).ok()?;
+    Some(
+        Sha256::digest(&data)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+    )
+}
+
+/// Working-tree paths a full revert touches, including the old side of renames.
+pub(crate) fn changed_paths(repo_root: &Path) -> Result<Vec<String>, String> {
+    let repo = Repository::open(repo_root).map_err(|err| err.to_string())?;
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .renames_head_to_index(true)
+        .renames_index_to_workdir(true)
+        .include_ignored(false);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|err| err.to_string())?;
+    let mut paths = BTreeSet::new();
+    for entry in statuses.iter() {
+        for delta in [entry.head_to_index(), entry.index_to_workdir()]
+            .into_iter()
+            .flatten()
+        {
+            for file in [delta.old_file(), delta.new_file()] {
+                if let Some(path) = file.path().and_then(Path::to_str) {
+                    paths.insert(path.replace('\\', "/"));
+                }
+            }
+        }
+        if let Some(path) = entry.path() {
+            paths.insert(path.to_string());
+        }
+    }
+    Ok(paths.into_iter().collect())
+}
+
+fn unique_entry_dir(trash_root: &Path, created_at_ms: u64) -> (String, PathBuf) {
+    let mut id = created_at_ms.to_string();
+    let mut suffix = 1;
+    while trash_root.join(&id).exists() {
+        id = format!("{created_at_ms}-{suffix}");
+        suffix += 1;
+    }
+    let dir = trash_root.join(&id);
+    (id, dir)
+}
+
+fn write_manifest(entry_dir: &Path, entry: &TrashEntry) -> Result<(), String> {
+    let raw = serde_json::to_string_pretty(entry).map_err(|err| err.to_string())?;
+    std::fs::write(entry_dir.join(MANIFEST_FILE), raw).map_err(|err| err.to_string())
+}
+
+fn read_manifest(entry_dir: &Path) -> Result<TrashEntry, String> {
+    let raw = std::fs::read_to_string(entry_dir.join(MANIFEST_FILE))
+        .map_err(|_| "Trash entry not found".to_string())?;
+    serde_json::from_str(&raw).map_err(|err| format!("Failed to parse trash entry: {err}"))
+}
+
+/// Copies the current contents of `paths` into a new trash entry before a revert
+/// discards them. Returns `None` when there was nothing to back up.
+pub(crate) fn snapshot_files(
+    workspace_root: &Path,
+    repo_root: &Path,
+    operation: &str,
+    paths: &[String],
+    limits: TrashLimits,
+) -> Result<Option<(TrashEntry, Vec<String>)>, String> {
+    let created_at_ms = now_ms();
+    let trash_root = trash_dir(workspace_root);
+    let (id, entry_dir) = unique_entry_dir(&trash_root, created_at_ms);
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    let mut warnings = Vec::new();
+    let mut total_bytes = 0;
+    for path in paths {
+        if is_state_path(path) || !is_safe_relative(path) {
+            continue;
+        }
+        let source = repo_root.join(path);
+        let Ok(metadata) = std::fs::metadata(&source) else {
+            files.push(TrashFile {
+                path: path.clone(),
+                bytes: 0,
+                deleted: true,
+                reverted_hash: None,
+            });
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        let bytes = metadata.len();
+        let reason = if bytes > limits.max_file_bytes {
+            Some(format!(
+                "larger than the {} MB backup limit",
+                limits.max_file_bytes / (1024 * 1024)
+            ))
+        } else if total_bytes + bytes > limits.max_snapshot_bytes {
+            Some("backup size limit reached".to_string())
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            warnings.push(format!("{path} was not backed up: {reason}."));
+            skipped.push(TrashSkippedFile {
+                path: path.clone(),
+                bytes,
+                reason,
+            });
+            continue;
+        }
+        let target = entry_dir.join(FILES_DIR).join(path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        std::fs::copy(&source, &target).map_err(|err| err.to_string())?;
+        total_bytes += bytes;
+        files.push(TrashFile {
+            path: path.clone(),
+            bytes,
+            deleted: false,
+            reverted_hash: None,
+        });
+    }
+    if files.is_empty() && skipped.is_empty() {
+        return Ok(None);
+    }
+    std::fs::create_dir_all(&entry_dir).map_err(|err| err.to_string())?;
+    let entry = TrashEntry {
+        id,
+        created_at_ms,
+        operation: operation.to_string(),
+        repo_root: repo_root.display().to_string(),
+        total_bytes,
+        files,
+        skipped,
+    };
+    write_manifest(&entry_dir, &entry)?;
+    Ok(Some((entry, warnings)))
+}
+
+/// Records what the revert left behind so a later restore can spot newer edits.
+pub(crate) fn record_reverted_state(workspace_root: &Path, trash_id: &str) -> Result<(), String> {
+    let entry_dir = trash_dir(workspace_root).join(trash_id);
+    let mut entry = read_manifest(&entry_dir)?;
+    let repo_root = PathBuf::from(&entry.repo_root);
+    for file in &mut entry.files {
+        file.reverted_hash = content_hash(&repo_root.join(&file.path));
+    }
+    write_manifest(&entry_dir, &entry)
+}
+
+pub(crate) fn discard_trash(workspace_root: &Path, trash_id: &str) {
+    if is_safe_relative(trash_id) {
+        let _ = std::fs::remove_dir_all(trash_dir(workspace_root).join(trash_id));
+    }
+}
+
+/// Backs up `paths` ahead of a revert unless the caller opted out. A failed backup
+/// stops the revert rather than losing work silently.
+pub(crate) fn backup_before_revert(
+    workspace_root: &Path,
+    repo_root: &Path,
+    operation: &str,
+    paths: &[String],
+    skip_backup: bool,
+) -> Result<Option<(TrashEntry, Vec<String>)>, String> {
+    if skip_backup {
+        return Ok(None);
+    }
+    snapshot_files(
+        workspace_root,
+        repo_root,
+        operation,
+        paths,
+        DEFAULT_TRASH_LIMITS,
+    )
+    .map_err(|err| format!("Failed to back up files before reverting: {err}"))
+}
+
+/// Completes the backup once the revert ran: drops it when the revert failed, else
+/// records the reverted state and prunes old entries.
+pub(crate) fn finish_revert(
+    workspace_root: &Path,
+    backup: Option<(TrashEntry, Vec<String>)>,
+    reverted: bool,
+) -> GitRevertResult {
+    let Some((entry, mut warnings)) = backup else {
+        return GitRevertResult::default();
+    };
+    if !reverted {
+        discard_trash(workspace_root, &entry.id);
+        return GitRevertResult::default();
+    }
+    if let Err(err) = record_reverted_state(workspace_root, &entry.id) {
+        warnings.push(format!("Restoring may overwrite newer edits: {err}"));
+    }
+    prune_trash(
+        workspace_root,
+        now_ms(),
+        DEFAULT_TRASH_LIMITS.max_total_bytes,
+    );
+    GitRevertResult {
+        trash_id: Some(entry.id),
+        warnings,
+    }
+}
+
+/// Trash entries of a workspace, newest first.
+pub(crate) fn list_trash(workspace_root: &Path) -> Vec<TrashEntry> {
+    let Ok(dirs) = std::fs::read_dir(trash_dir(workspace_root)) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<TrashEntry> = dirs
+        .flatten()
+        .filter_map(|dir| read_manifest(&dir.path()).ok())
+        .collect();
+    entries.sort_by(|a, b| {
+        b.created_at_ms
+            .cmp(&a.created_at_ms)
+            .then_with(|| b.id.cmp(&a.id))
+    });
+    entries
+}
+
+/// Writes backed-up contents back as unstaged changes. Files edited since the revert
+/// are reported as conflicts and left alone; the entry is removed once fully restored.
+pub(crate) fn restore_trash(
+    workspace_root: &Path,
+    trash_id: &str,
+) -> Result<TrashRestoreResult, String> {
+    if !is_safe_relative(trash_id) {
+        return Err("Trash entry not found".to_string());
+    }
+    let entry_dir = trash_dir(workspace_root).join(trash_id);
+    let entry = read_manifest(&entry_dir)?;
+    let repo_root = PathBuf::from(&entry.repo_root);
+    let mut result = TrashRestoreResult::default();
+    for file in &entry.files {
+        if !is_safe_relative(&file.path) {
+            continue;
+        }
+        let target = repo_root.join(&file.path);
+        if content_hash(&target) != file.reverted_hash {
+            result.conflicts.push(file.path.clone());
+            continue;
+        }
+        if file.deleted {
+            if target.exists() {
+                std::fs::remove_file(&target).map_err(|err| err.to_string())?;
+            }
+        } else {
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+            }
+            std::fs::copy(entry_dir.join(FILES_DIR).join(&file.path), &target)
+                .map_err(|err| err.to_string())?;
+        }
+        result.restored.push(file.path.clone());
+    }
+    if result.conflicts.is_empty() {
+        let _ = std::fs::remove_dir_all(&entry_dir);
+    }
+    Ok(result)
+}
+
+/// Drops entries older than the retention window, then the oldest ones until the trash
+/// fits in `max_total_bytes`. Returns the number of removed entries.
+pub(crate) fn prune_trash(workspace_root: &Path, now_ms: u64, max_total_bytes: u64) -> usize {
+    let mut entries = list_trash(workspace_root);
+    let mut removed = 0;
+    let mut total: u64 = entries.iter().map(|entry| entry.total_bytes).sum();
+    while let Some(oldest) = entries.last() {
+        let expired = now_ms.saturating_sub(oldest.created_at_ms) > RETENTION_MS;
+        if !expired && total <= max_total_bytes {
+            break;
+        }
+        total = total.saturating_sub(oldest.total_bytes);
+        discard_trash(workspace_root, &oldest.id);
+        entries.pop();
+        removed += 1;
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn git(root: &Path, args: &[&str]) {
+        Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(root)
+            .output()
+            .expect("run git");
+    }
+
+    fn temp_root() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("micode-trash-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create temp root");
+        root
+    }
+
+    #[test]
+    fn restores_reverted_files_and_reports_newer_edits() {
+        let root = temp_root();
+        git(&root, &["init", "--quiet"]);
+        for name in ["edited.txt", "removed.txt", "touched.txt"] {
+            std::fs::write(root.join(name), "base\n").expect("write base");
+        }
+        git(&root, &["add", "."]);
+        git(&root, &["commit", "--quiet", "-m", "base"]);
+        std::fs::write(root.join("edited.txt"), "my work\n").expect("edit");
+        std::fs::write(root.join("touched.txt"), "my other work\n").expect("edit");
+        std::fs::remove_file(root.join("removed.txt")).expect("remove");
+        std::fs::write(root.join("new.txt"), "draft\n").expect("add untracked");
+        std::fs::write(root.join("big.bin"), vec![0u8; 64]).expect("add large");
+
+        let paths = changed_paths(&root).expect("changed paths");
+        assert_eq!(
+            paths,
+            vec![
+                "big.bin",
+                "edited.txt",
+                "new.txt",
+                "removed.txt",
+                "touched.txt"
+            ]
+        );
+        let limits = TrashLimits {
+            max_file_bytes: 32,
+            ..DEFAULT_TRASH_LIMITS
+        };
+        let (entry, warnings) = snapshot_files(&root, &root, "revertAll", &paths, limits)
+            .expect("snapshot")
+            .expect("entry");
+        assert_eq!(entry.skipped.len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert!(entry.files.iter().any(|file| file.deleted));
+
+        git(&root, &["restore", "--staged", "--worktree", "--", "."]);
+        git(&root, &["clean", "-f", "-d", "-e", STATE_DIR_NAME]);
+        record_reverted_state(&root, &entry.id).expect("record");
+        assert!(!root.join("new.txt").exists());
+        std::fs::write(root.join("touched.txt"), "newer edit\n").expect("newer edit");
+
+        let listed = list_trash(&root);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, entry.id);
+
+        let result = restore_trash(&root, &entry.id).expect("restore");
+        assert_eq!(result.conflicts, vec!["touched.txt"]);
+        assert_eq!(
+            result.restored,
+            vec!["edited.txt", "new.txt", "removed.txt"]
+        );
+        let read = |name: &str| std::fs::read_to_string(root.join(name)).expect("read");
+        assert_eq!(read("edited.txt"), "my work\n");
+        assert_eq!(read("new.txt"), "draft\n");
+        assert_eq!(read("touched.txt"), "newer edit\n");
+        assert!(!root.join("removed.txt").exists());
+        assert_eq!(list_trash(&root).len(), 1);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn prunes_expired_and_oversized_entries_oldest_first() {
+        let root = temp_root();
+        std::fs::write(root.join("a.txt"), "0123456789").expect("write file");
+        let paths = vec!["a.txt".to_string()];
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let (entry, _) =
+                snapshot_files(&root, &root, "revertFile", &paths, DEFAULT_TRASH_LIMITS)
+                    .expect("snapshot")
+                    .expect("entry");
+            ids.push(entry.id);
+        }
+        let now = now_ms();
+        assert_eq!(prune_trash(&root, now, 30), 0);
+        assert_eq!(prune_trash(&root, now, 20), 1);
+        let remaining: Vec<String> = list_trash(&root)
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect();
+        assert_eq!(remaining, vec![ids[2].clone(), ids[1].clone()]);
+        assert_eq!(prune_trash(&root, now + RETENTION_MS + 1_000, u64::MAX), 2);
+        assert!(list_trash(&root).is_empty());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -2,20 +2,29 @@ use tauri::Manager;
 #[cfg(target_os = "macos")]
 use tauri::{RunEvent, WindowEvent};
 
+mod app_info;
 mod backend;
+mod blocking;
 mod dictation;
 mod debug_logs;
+mod diagnostics;
 mod event_sink;
+mod event_subscriptions;
 mod files;
 mod git;
 mod git_utils;
+mod http_client;
 mod local_usage;
 mod menu;
+mod menu_accelerators;
 mod micode;
+mod notification_inbox;
 mod notifications;
+mod operations;
 mod prompts;
 mod remote_backend;
 mod rules;
+mod secrets;
 mod settings;
 mod shared;
 mod state;
@@ -51,6 +60,11 @@ pub fn run() {
                 let _ = window.hide();
             }
         })
+        .on_page_load(|webview, payload| {
+            if payload.event() == tauri::webview::PageLoadEvent::Started {
+                event_subscriptions::reset_event_subscriptions(webview.app_handle());
+            }
+        })
         .setup(|app| {
             let state = state::AppState::load(&app.handle());
             let menu_is_zh = state
@@ -61,6 +75,9 @@ pub fn run() {
                 .eq_ignore_ascii_case("zh");
             menu::set_menu_language_zh(menu_is_zh);
             app.manage(state);
+            workspaces::spawn_liveness_monitor(app.handle().clone());
+            workspaces::spawn_resource_monitor(app.handle().clone());
+            workspaces::spawn_store_maintenance_task(app.handle().clone());
             let _ = menu::rebuild_menu(&app.handle());
             Ok(())
         });
@@ -75,14 +92,16 @@ pub fn run() {
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
-        .invoke_handler(tauri::generate_handler![
+        .invoke_handler(counting_commands(tauri::generate_handler![
             settings::get_app_settings,
             settings::update_app_settings,
+            settings::get_settings_revision,
             settings::get_micode_config_path,
             files::file_read,
             files::file_write,
             micode::get_config_model,
             menu::menu_set_accelerators,
+            menu::get_default_accelerators,
             micode::micode_doctor,
             micode::micode_install_windows,
             workspaces::list_workspaces,
@@ -103,25 +122,72 @@ pub fn run() {
             micode::start_thread,
             micode::send_user_message,
             micode::turn_interrupt,
+            micode::cancel_tool_call,
+            micode::get_turn_live_changes,
             micode::start_review,
             micode::respond_to_server_request,
             micode::remember_approval_rule,
             micode::list_approval_rules,
             micode::remove_approval_rule,
+            micode::approval_rules_list,
+            micode::approval_rules_delete,
+            micode::approval_rules_update,
+            micode::approval_rules_export,
+            micode::approval_rules_import,
             micode::get_commit_message_prompt,
             micode::generate_commit_message,
+            micode::run_slash_command,
+            micode::generate_pr_description,
+            micode::generate_changelog,
             micode::generate_run_metadata,
+            micode::build_run_kickoff_message,
+            micode::start_run,
             micode::resume_thread,
+            micode::watch_thread,
+            micode::take_over_thread,
+            micode::get_thread_ownership,
             micode::fork_thread,
             micode::list_threads,
             micode::list_mcp_server_status,
+            micode::mcp_server_add,
+            micode::mcp_server_update,
+            micode::mcp_server_remove,
             micode::archive_thread,
+            micode::list_archived_threads,
+            micode::restore_thread,
+            micode::delete_thread_permanently,
             micode::compact_thread,
             micode::set_thread_name,
+            micode::set_thread_tags,
+            micode::set_thread_pinned,
+            micode::search_threads,
+            micode::export_thread,
+            micode::export_review_sarif,
+            micode::start_auto_run,
+            micode::cancel_auto_run,
+            micode::list_auto_runs,
+            micode::mark_thread_seen,
+            micode::get_unseen_items,
+            micode::check_thread_sync,
+            micode::sync_thread_from_cli,
+            micode::copy_thread_range,
+            micode::list_item_annotations,
+            micode::add_item_annotation,
+            micode::update_item_annotation,
+            micode::delete_item_annotation,
             micode::collaboration_mode_list,
             workspaces::connect_workspace,
+            workspaces::connect_all_workspaces,
+            workspaces::restart_workspace_session,
+            workspaces::force_restart_session,
+            workspaces::run_store_maintenance_now,
+            workspaces::get_session_info,
+            workspaces::micode_session_status,
+            workspaces::get_resource_usage,
+            workspaces::test_redaction,
             git::get_git_status,
             git::list_git_roots,
+            git::detect_workspace_roots,
             git::get_git_diffs,
             git::get_git_log,
             git::get_git_commit_diff,
@@ -131,7 +197,12 @@ pub fn run() {
             git::unstage_git_file,
             git::revert_git_file,
             git::revert_git_all,
+            git::list_git_trash,
+            git::restore_git_trash,
             git::commit_git,
+            git::get_git_identity,
+            git::set_git_identity,
+            git::get_workspace_git_info,
             git::push_git,
             git::pull_git,
             git::fetch_git,
@@ -142,11 +213,21 @@ pub fn run() {
             git::get_github_pull_request_comments,
             workspaces::list_workspace_files,
             workspaces::read_workspace_file,
+            workspaces::read_turn_artifact,
+            workspaces::get_turn_audit,
+            workspaces::get_workspace_stack,
+            workspaces::get_turn_review_state,
+            workspaces::set_file_review_state,
             workspaces::open_workspace_in,
             workspaces::get_open_app_icon,
+            workspaces::list_openable_apps,
             git::list_git_branches,
             git::checkout_git_branch,
             git::create_git_branch,
+            git::get_conflict_detail,
+            git::resolve_conflict,
+            git::extract_patches_from_item,
+            git::apply_extracted_patch,
             micode::model_list,
             micode::account_rate_limits,
             micode::account_read,
@@ -158,9 +239,14 @@ pub fn run() {
             prompts::prompts_create,
             prompts::prompts_update,
             prompts::prompts_delete,
+            prompts::prompts_list_trash,
+            prompts::prompts_restore,
             prompts::prompts_move,
             prompts::prompts_workspace_dir,
             prompts::prompts_global_dir,
+            prompts::extract_prompt_from_thread,
+            prompts::cancel_prompt_extraction,
+            operations::cancel_operation,
             terminal::terminal_open,
             terminal::terminal_write,
             terminal::terminal_resize,
@@ -174,10 +260,31 @@ pub fn run() {
             dictation::dictation_stop,
             dictation::dictation_cancel,
             local_usage::local_usage_snapshot,
+            local_usage::export_usage_csv,
+            local_usage::usage_summary,
+            local_usage::usage_export_csv,
             debug_logs::append_debug_logs,
+            app_info::get_app_info,
+            diagnostics::export_diagnostics,
+            diagnostics::get_command_timings,
+            diagnostics::get_feature_usage,
+            diagnostics::export_feature_usage,
+            diagnostics::reset_command_timings,
+            diagnostics::get_backend_capabilities,
             notifications::is_macos_debug_build,
-            notifications::send_notification_fallback
-        ])
+            notifications::send_notification_fallback,
+            notification_inbox::record_notification,
+            notification_inbox::list_notifications,
+            notification_inbox::mark_notification_read,
+            notification_inbox::clear_notifications,
+            blocking::get_blocking_state,
+            blocking::set_focused_workspace,
+            blocking::should_dispatch_notification,
+            event_subscriptions::set_event_subscriptions,
+            http_client::set_proxy_password,
+            http_client::get_updater_proxy,
+            http_client::test_proxy_connectivity
+        ]))
         .build(tauri::generate_context!())
         .expect("error while running tauri application");
 
@@ -191,3 +298,24 @@ pub fn run() {
         }
     });
 }
+
+/// Counts every invoked command in the local feature-usage counters, under the
+/// `workspaceId` argument when it has one, before `handler` runs it.
+fn counting_commands<R: tauri::Runtime>(
+    handler: impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let workspace_id = match invoke.message.payload() {
+            tauri::ipc::InvokeBody::Json(args) => {
+                args.get("workspaceId").and_then(serde_json::Value::as_str)
+            }
+            _ => None,
+        };
+        if let Some(state) = invoke.message.webview_ref().try_state::<state::AppState>() {
+            state
+                .usage_counters
+                .record_command(invoke.message.command(), workspace_id);
+        }
+        handler(invoke)
+    }
+}
@@ -1,17 +1,24 @@
-use chrono::{DateTime, Duration, Local, TimeZone, Utc};
-use serde_json::Value;
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use serde_json::{json, Value};
 use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
-use tauri::State;
+use tauri::{AppHandle, State};
 
+use crate::backend::app_server::thread_titles_by_session;
+use crate::backend::primer::load_stats as load_primer_stats;
+use crate::backend::workspace_paths::path_starts_with;
 use crate::micode::home::{resolve_default_micode_home, resolve_workspace_micode_home};
+use crate::remote_backend;
+use crate::shared::usage_ledger_core::{self, csv_text, UsageSummary};
 use crate::state::AppState;
+use crate::storage::write_file_atomically;
 use crate::types::{
-    LocalUsageDay, LocalUsageModel, LocalUsageSnapshot, LocalUsageTotals, WorkspaceEntry,
+    LocalUsageDay, LocalUsageModel, LocalUsageSnapshot, LocalUsageTotals, ModelPrice,
+    UsageCsvExport, WorkspaceEntry,
 };
 
 #[derive(Default, Clone, Copy)]
@@ -31,6 +38,38 @@ struct UsageTotals {
 }
 
 const MAX_ACTIVITY_GAP_MS: i64 = 2 * 60 * 1000;
+const USAGE_CSV_COLUMNS: [&str; 10] = [
+    "date",
+    "workspace",
+    "thread",
+    "model",
+    "input_tokens",
+    "cached_input_tokens",
+    "output_tokens",
+    "estimated_cost_usd",
+    "duration_seconds",
+    "status",
+];
+const UNKNOWN_WORKSPACE_LABEL: &str = "(unknown workspace)";
+const DELETED_THREAD_LABEL: &str = "(deleted thread)";
+
+/// One agent turn of a chat file: a user message and everything the agent wrote after it.
+struct TurnUsage {
+    started_at_ms: i64,
+    ended_at_ms: i64,
+    project_hash: Option<String>,
+    session_id: Option<String>,
+    model: Option<String>,
+    totals: UsageTotals,
+    status: &'static str,
+}
+
+/// Names used for the rows of one workspace's chat files.
+struct WorkspaceLabels {
+    name: String,
+    /// Thread titles keyed by MiCode session id.
+    thread_titles: HashMap<String, String>,
+}
 
 #[tauri::command]
 pub(crate) async fn local_usage_snapshot(
@@ -47,18 +86,198 @@ pub(crate) async fn local_usage_snapshot(
             Some(PathBuf::from(trimmed))
         }
     });
-    let sessions_roots = {
+    let (sessions_roots, primer_paths) = {
         let workspaces = state.workspaces.lock().await;
-        resolve_sessions_roots(&workspaces, workspace_path.as_deref())
+        let primer_paths = match workspace_path.as_ref() {
+            Some(path) => vec![path.clone()],
+            None => workspaces
+                .values()
+                .map(|entry| PathBuf::from(&entry.path))
+                .collect(),
+        };
+        (
+            resolve_sessions_roots(&workspaces, workspace_path.as_deref(), &state.data_dir),
+            primer_paths,
+        )
     };
     let snapshot = tokio::task::spawn_blocking(move || {
-        scan_local_usage(days, workspace_path.as_deref(), &sessions_roots)
+        let mut snapshot = scan_local_usage(days, workspace_path.as_deref(), &sessions_roots)?;
+        apply_primer_savings(&mut snapshot, &primer_paths);
+        Ok::<_, String>(snapshot)
     })
     .await
     .map_err(|err| err.to_string())??;
     Ok(snapshot)
 }
 
+/// Writes one CSV row per agent turn started between `start_day` and `end_day` (local
+/// `YYYY-MM-DD`, inclusive) to `destination`. Only chat files carry per-turn data, so the
+/// legacy `sessions/` logs are not exported.
+#[tauri::command]
+pub(crate) async fn export_usage_csv(
+    start_day: String,
+    end_day: String,
+    destination: String,
+    state: State<'_, AppState>,
+) -> Result<UsageCsvExport, String> {
+    let start = parse_day(&start_day)?;
+    let end = parse_day(&end_day)?;
+    if end < start {
+        return Err("The end day is before the start day.".to_string());
+    }
+    let destination = PathBuf::from(destination.trim());
+    if destination.as_os_str().is_empty() {
+        return Err("Choose a file to export to.".to_string());
+    }
+    let (sessions_roots, workspace_paths) = {
+        let workspaces = state.workspaces.lock().await;
+        let paths: Vec<(String, String)> = workspaces
+            .values()
+            .map(|entry| (entry.name.clone(), entry.path.clone()))
+            .collect();
+        (
+            resolve_sessions_roots(&workspaces, None, &state.data_dir),
+            paths,
+        )
+    };
+    let prices = state.app_settings.lock().await.model_prices.clone();
+    tokio::task::spawn_blocking(move || {
+        let labels: HashMap<String, WorkspaceLabels> = workspace_paths
+            .into_iter()
+            .map(|(name, path)| {
+                let labels = WorkspaceLabels {
+                    name,
+                    thread_titles: thread_titles_by_session(&path),
+                };
+                (project_hash_for_workspace(Path::new(&path)), labels)
+            })
+            .collect();
+        let turns = scan_chat_turns(&sessions_roots, start, end);
+        let csv = build_usage_csv(&turns, &labels, &prices);
+        write_file_atomically(&destination, &csv)?;
+        Ok::<_, String>(UsageCsvExport {
+            path: destination.display().to_string(),
+            row_count: turns.len() as u64,
+        })
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+/// Daily token totals and estimated cost from the usage ledger, for one workspace or all
+/// of them, from the UTC day `since` (`YYYY-MM-DD`) on.
+#[tauri::command]
+pub(crate) async fn usage_summary(
+    workspace_id: Option<String>,
+    since: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<UsageSummary, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "usage_summary",
+            json!({ "workspaceId": workspace_id, "since": since }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+    let prices = state.app_settings.lock().await.model_prices.clone();
+    let ledger_path = state.usage_ledger.path().map(Path::to_path_buf);
+    tokio::task::spawn_blocking(move || {
+        usage_ledger_core::usage_summary_core(
+            ledger_path.as_deref(),
+            workspace_id.as_deref(),
+            since.as_deref(),
+            &prices,
+        )
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+/// Writes every usage ledger record to `path` as CSV; in remote mode the daemon's ledger
+/// is written on this machine.
+#[tauri::command]
+pub(crate) async fn usage_export_csv(
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<UsageCsvExport, String> {
+    let destination = PathBuf::from(path.trim());
+    if destination.as_os_str().is_empty() {
+        return Err("Choose a file to export to.".to_string());
+    }
+    let (csv, row_count) = if remote_backend::is_remote_mode(&*state).await {
+        let response =
+            remote_backend::call_remote(&*state, app, "usage_ledger_csv", json!({})).await?;
+        let csv = response
+            .get("content")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let row_count = response
+            .get("rowCount")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        (csv, row_count)
+    } else {
+        let ledger_path = state.usage_ledger.path().map(Path::to_path_buf);
+        tokio::task::spawn_blocking(move || {
+            usage_ledger_core::usage_ledger_csv_core(ledger_path.as_deref())
+        })
+        .await
+        .map_err(|err| err.to_string())?
+    };
+    tokio::task::spawn_blocking(move || {
+        write_file_atomically(&destination, &csv)?;
+        Ok::<_, String>(UsageCsvExport {
+            path: destination.display().to_string(),
+            row_count,
+        })
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+/// Agent runs recorded today, keyed by workspace id. Scans usage files, so run it off the
+/// async runtime.
+pub(crate) fn today_agent_runs_by_workspace(
+    workspaces: &HashMap<String, WorkspaceEntry>,
+    data_dir: &Path,
+) -> HashMap<String, u64> {
+    workspaces
+        .values()
+        .map(|entry| {
+            let path = PathBuf::from(&entry.path);
+            let roots = resolve_sessions_roots(workspaces, Some(&path), data_dir);
+            let runs = scan_local_usage(1, Some(&path), &roots)
+                .ok()
+                .and_then(|snapshot| snapshot.days.last().map(|day| day.agent_runs))
+                .unwrap_or(0);
+            (entry.id.clone(), runs.max(0) as u64)
+        })
+        .collect()
+}
+
+/// Folds the primer's estimated savings for the given workspaces into the snapshot days.
+fn apply_primer_savings(snapshot: &mut LocalUsageSnapshot, workspace_paths: &[PathBuf]) {
+    let mut reuses = 0;
+    for path in workspace_paths {
+        let stats = load_primer_stats(path);
+        for day in snapshot.days.iter_mut() {
+            if let Some(day_stats) = stats.get(&day.day) {
+                day.primer_tokens_saved += day_stats.tokens_saved;
+                reuses += day_stats.reuses;
+            }
+        }
+    }
+    snapshot.totals.primer_tokens_saved =
+        snapshot.days.iter().map(|day| day.primer_tokens_saved).sum();
+    snapshot.totals.primer_reuses = reuses;
+}
+
 fn scan_local_usage(
     days: u32,
     workspace_path: Option<&Path>,
@@ -157,16 +376,8 @@ fn scan_chat_file(
     model_totals: &mut HashMap<String, i64>,
     workspace_project_hash: Option<&str>,
 ) -> Result<bool, String> {
-    let text = match std::fs::read_to_string(path) {
-        Ok(text) => text,
-        Err(_) => return Ok(false),
-    };
-    if text.len() > 8_000_000 {
+    let Some(value) = read_chat_file(path) else {
         return Ok(false);
-    }
-    let value = match serde_json::from_str::<Value>(&text) {
-        Ok(value) => value,
-        Err(_) => return Ok(false),
     };
     if let Some(target_hash) = workspace_project_hash {
         let file_hash = value.get("projectHash").and_then(|hash| hash.as_str());
@@ -204,31 +415,7 @@ fn scan_chat_file(
         let Some(tokens) = message.get("tokens").and_then(|tokens| tokens.as_object()) else {
             continue;
         };
-        let input = read_i64(tokens, &["input", "input_tokens", "inputTokens"]);
-        let cached = read_i64(
-            tokens,
-            &[
-                "cached",
-                "cached_input_tokens",
-                "cache_read_input_tokens",
-                "cachedInputTokens",
-                "cacheReadInputTokens",
-            ],
-        );
-        let output = read_i64(tokens, &["output", "output_tokens", "outputTokens"]);
-
-        let prev = previous_totals.unwrap_or_default();
-        let delta = UsageTotals {
-            input: (input - prev.input).max(0),
-            cached: (cached - prev.cached).max(0),
-            output: (output - prev.output).max(0),
-        };
-        previous_totals = Some(UsageTotals {
-            input,
-            cached,
-            output,
-        });
-
+        let delta = token_delta(tokens, &mut previous_totals);
         if delta.input == 0 && delta.cached == 0 && delta.output == 0 {
             continue;
         }
@@ -253,6 +440,190 @@ fn scan_chat_file(
     Ok(true)
 }
 
+fn read_chat_file(path: &Path) -> Option<Value> {
+    let text = std::fs::read_to_string(path).ok()?;
+    if text.len() > 8_000_000 {
+        return None;
+    }
+    serde_json::from_str::<Value>(&text).ok()
+}
+
+/// Chat messages carry running token totals; returns what this message added.
+fn token_delta(
+    tokens: &serde_json::Map<String, Value>,
+    previous_totals: &mut Option<UsageTotals>,
+) -> UsageTotals {
+    let input = read_i64(tokens, &["input", "input_tokens", "inputTokens"]);
+    let cached = read_i64(
+        tokens,
+        &[
+            "cached",
+            "cached_input_tokens",
+            "cache_read_input_tokens",
+            "cachedInputTokens",
+            "cacheReadInputTokens",
+        ],
+    );
+    let output = read_i64(tokens, &["output", "output_tokens", "outputTokens"]);
+
+    let prev = previous_totals.unwrap_or_default();
+    *previous_totals = Some(UsageTotals {
+        input,
+        cached,
+        output,
+    });
+    UsageTotals {
+        input: (input - prev.input).max(0),
+        cached: (cached - prev.cached).max(0),
+        output: (output - prev.output).max(0),
+    }
+}
+
+/// Splits a chat file into turns, each starting at a user message. Messages before the
+/// first user message belong to no turn.
+fn chat_turns(chat: &Value) -> Vec<TurnUsage> {
+    let text_field = |key: &str| {
+        chat.get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(ToString::to_string)
+    };
+    let project_hash = text_field("projectHash");
+    let session_id = text_field("sessionId");
+    let mut turns = Vec::new();
+    let mut current: Option<TurnUsage> = None;
+    let mut previous_totals: Option<UsageTotals> = None;
+    let messages = chat.get("messages").and_then(Value::as_array);
+    for message in messages.into_iter().flatten() {
+        let timestamp_ms = read_timestamp_ms(message);
+        let message_type = message.get("type").and_then(Value::as_str).unwrap_or("");
+        let delta = message
+            .get("tokens")
+            .and_then(Value::as_object)
+            .map(|tokens| token_delta(tokens, &mut previous_totals))
+            .unwrap_or_default();
+        if message_type.eq_ignore_ascii_case("user") {
+            turns.extend(current.take());
+            current = timestamp_ms.map(|started_at_ms| TurnUsage {
+                started_at_ms,
+                ended_at_ms: started_at_ms,
+                project_hash: project_hash.clone(),
+                session_id: session_id.clone(),
+                model: None,
+                totals: UsageTotals::default(),
+                status: "no_response",
+            });
+            continue;
+        }
+        let Some(turn) = current.as_mut() else {
+            continue;
+        };
+        if let Some(timestamp_ms) = timestamp_ms {
+            turn.ended_at_ms = turn.ended_at_ms.max(timestamp_ms);
+        }
+        if let Some(model) = message.get("model").and_then(Value::as_str) {
+            turn.model = Some(model.to_string());
+        }
+        if message_type.eq_ignore_ascii_case("error") {
+            turn.status = "error";
+        } else if turn.status != "error" {
+            turn.status = "completed";
+        }
+        turn.totals.input += delta.input;
+        turn.totals.cached += delta.cached.min(delta.input);
+        turn.totals.output += delta.output;
+    }
+    turns.extend(current);
+    turns
+}
+
+/// Turns from every chat file under `sessions_roots` that started within the local days
+/// `start..=end`, oldest first.
+fn scan_chat_turns(sessions_roots: &[PathBuf], start: NaiveDate, end: NaiveDate) -> Vec<TurnUsage> {
+    let mut turns = Vec::new();
+    for root in sessions_roots {
+        let Ok(entries) = std::fs::read_dir(root.join("chats")) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_session_file = path.extension().and_then(|ext| ext.to_str()) == Some("json")
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("session-"));
+            if !is_session_file {
+                continue;
+            }
+            let Some(chat) = read_chat_file(&path) else {
+                continue;
+            };
+            turns.extend(chat_turns(&chat).into_iter().filter(|turn| {
+                local_date_for_timestamp_ms(turn.started_at_ms)
+                    .is_some_and(|day| start <= day && day <= end)
+            }));
+        }
+    }
+    turns.sort_by_key(|turn| turn.started_at_ms);
+    turns
+}
+
+fn estimate_cost(prices: &[ModelPrice], model: Option<&str>, totals: UsageTotals) -> Option<f64> {
+    let price = ModelPrice::find(prices, model?)?;
+    Some(price.estimate_cost(totals.input, totals.cached, totals.output))
+}
+
+/// Builds the export with `.`-decimal numbers and no digit grouping, whatever the system
+/// locale. The byte order mark lets spreadsheet apps detect UTF-8 titles.
+fn build_usage_csv(
+    turns: &[TurnUsage],
+    labels: &HashMap<String, WorkspaceLabels>,
+    prices: &[ModelPrice],
+) -> String {
+    let mut csv = format!("\u{feff}{}\r\n", USAGE_CSV_COLUMNS.join(","));
+    for turn in turns {
+        let workspace = turn
+            .project_hash
+            .as_deref()
+            .and_then(|hash| labels.get(hash));
+        let thread = workspace
+            .zip(turn.session_id.as_deref())
+            .and_then(|(workspace, session_id)| workspace.thread_titles.get(session_id))
+            .map(String::as_str)
+            .unwrap_or(DELETED_THREAD_LABEL);
+        let date = Utc
+            .timestamp_millis_opt(turn.started_at_ms)
+            .single()
+            .map(|utc| {
+                utc.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let cost = estimate_cost(prices, turn.model.as_deref(), turn.totals)
+            .map(|cost| format!("{cost:.4}"))
+            .unwrap_or_default();
+        let duration_secs = (turn.ended_at_ms - turn.started_at_ms).max(0) as f64 / 1000.0;
+        csv.push_str(&format!(
+            "{date},{},{},{},{},{},{},{cost},{duration_secs:.1},{}\r\n",
+            csv_text(workspace.map_or(UNKNOWN_WORKSPACE_LABEL, |workspace| &workspace.name)),
+            csv_text(thread),
+            csv_text(turn.model.as_deref().unwrap_or_default()),
+            turn.totals.input,
+            turn.totals.cached,
+            turn.totals.output,
+            turn.status,
+        ));
+    }
+    csv
+}
+
+fn parse_day(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid day \"{value}\", expected YYYY-MM-DD."))
+}
+
 fn project_hash_for_workspace(workspace_path: &Path) -> String {
     let path_text = workspace_path.to_string_lossy();
     format!("{:x}", Sha256::digest(path_text.as_bytes()))
@@ -279,6 +650,7 @@ fn build_snapshot(
             total_tokens: total,
             agent_time_ms: totals.agent_ms,
             agent_runs: totals.agent_runs,
+            primer_tokens_saved: 0,
         });
     }
 
@@ -332,6 +704,8 @@ fn build_snapshot(
             cache_hit_rate_percent,
             peak_day,
             peak_day_tokens,
+            primer_tokens_saved: 0,
+            primer_reuses: 0,
         },
         top_models,
     }
@@ -649,8 +1023,12 @@ fn track_activity(
 }
 
 fn day_key_for_timestamp_ms(timestamp_ms: i64) -> Option<String> {
+    local_date_for_timestamp_ms(timestamp_ms).map(|day| day.format("%Y-%m-%d").to_string())
+}
+
+fn local_date_for_timestamp_ms(timestamp_ms: i64) -> Option<NaiveDate> {
     let utc = Utc.timestamp_millis_opt(timestamp_ms).single()?;
-    Some(utc.with_timezone(&Local).format("%Y-%m-%d").to_string())
+    Some(utc.with_timezone(&Local).date_naive())
 }
 
 fn extract_cwd(value: &Value) -> Option<String> {
@@ -662,8 +1040,7 @@ fn extract_cwd(value: &Value) -> Option<String> {
 }
 
 fn path_matches_workspace(cwd: &str, workspace_path: &Path) -> bool {
-    let cwd_path = Path::new(cwd);
-    cwd_path == workspace_path || cwd_path.starts_with(workspace_path)
+    path_starts_with(Path::new(cwd), workspace_path)
 }
 
 fn make_day_keys(days: u32) -> Vec<String> {
@@ -736,6 +1113,7 @@ fn resolve_micode_scan_roots(micode_home_override: Option<PathBuf>) -> Vec<PathB
 fn resolve_sessions_roots(
     workspaces: &HashMap<String, WorkspaceEntry>,
     workspace_path: Option<&Path>,
+    data_dir: &Path,
 ) -> Vec<PathBuf> {
     let mut roots = Vec::new();
     let mut seen = HashSet::new();
@@ -748,7 +1126,7 @@ fn resolve_sessions_roots(
 
     if let Some(workspace_path) = workspace_path {
         let micode_home_override =
-            resolve_workspace_micode_home_for_path(workspaces, Some(workspace_path));
+            resolve_workspace_micode_home_for_path(workspaces, Some(workspace_path), data_dir);
         for root in resolve_micode_scan_roots(micode_home_override) {
             push_root(root);
         }
@@ -768,7 +1146,7 @@ fn resolve_sessions_roots(
             .parent_id
             .as_ref()
             .and_then(|parent_id| workspaces.get(parent_id));
-        let Some(agent_home) = resolve_workspace_micode_home(entry, parent_entry) else {
+        let Some(agent_home) = resolve_workspace_micode_home(entry, parent_entry, data_dir) else {
             continue;
         };
         for root in resolve_micode_scan_roots(Some(agent_home)) {
@@ -782,14 +1160,12 @@ fn resolve_sessions_roots(
 fn resolve_workspace_micode_home_for_path(
     workspaces: &HashMap<String, crate::types::WorkspaceEntry>,
     workspace_path: Option<&Path>,
+    data_dir: &Path,
 ) -> Option<PathBuf> {
     let workspace_path = workspace_path?;
     let entry = workspaces
         .values()
-        .filter(|entry| {
-            let entry_path = Path::new(&entry.path);
-            workspace_path == entry_path || workspace_path.starts_with(entry_path)
-        })
+        .filter(|entry| path_starts_with(workspace_path, Path::new(&entry.path)))
         .max_by_key(|entry| entry.path.len())?;
 
     let parent_entry = entry
@@ -797,7 +1173,7 @@ fn resolve_workspace_micode_home_for_path(
         .as_ref()
         .and_then(|parent_id| workspaces.get(parent_id));
 
-    resolve_workspace_micode_home(entry, parent_entry)
+    resolve_workspace_micode_home(entry, parent_entry, data_dir)
 }
 
 fn day_dir_for_key(root: &Path, day_key: &str) -> PathBuf {
@@ -1065,6 +1441,7 @@ mod tests {
             parent_id: None,
             worktree: None,
             settings: settings_a,
+            stack: None,
         };
         let mut settings_b = WorkspaceSettings::default();
         settings_b.agent_home = Some(
@@ -1086,11 +1463,12 @@ mod tests {
             parent_id: None,
             worktree: None,
             settings: settings_b,
+            stack: None,
         };
         workspaces.insert(entry_a.id.clone(), entry_a.clone());
         workspaces.insert(entry_b.id.clone(), entry_b.clone());
 
-        let roots = resolve_sessions_roots(&workspaces, None);
+        let roots = resolve_sessions_roots(&workspaces, None, &std::env::temp_dir());
         let expected_a = PathBuf::from(entry_a.settings.agent_home.unwrap()).join("sessions");
         let expected_b = PathBuf::from(entry_b.settings.agent_home.unwrap()).join("sessions");
 
@@ -1129,4 +1507,76 @@ mod tests {
         assert_eq!(snapshot.top_models[0].model, "mimo-v2-flash");
         assert_eq!(snapshot.top_models[0].tokens, 15);
     }
+
+    #[test]
+    fn usage_csv_has_one_row_per_turn_in_range() {
+        let root = make_temp_sessions_root();
+        let hash = project_hash_for_workspace(Path::new("/tmp/project-usage-export"));
+        write_chat_session_file(
+            &root,
+            "session-kept.json",
+            &format!(
+                r#"{{"sessionId":"s-kept","projectHash":"{hash}","messages":[
+                    {{"timestamp":"2026-03-02T10:00:00Z","type":"user"}},
+                    {{"timestamp":"2026-03-02T10:00:05Z","type":"gemini","model":"mimo-v2-flash","tokens":{{"input":1000,"cached":200,"output":100}}}},
+                    {{"timestamp":"2026-03-02T10:00:12Z","type":"gemini","model":"mimo-v2-flash","tokens":{{"input":1500,"cached":400,"output":300}}}},
+                    {{"timestamp":"2026-03-02T10:05:00Z","type":"user"}},
+                    {{"timestamp":"2026-03-02T10:05:01Z","type":"error"}}
+                ]}}"#
+            ),
+        );
+        write_chat_session_file(
+            &root,
+            "session-deleted.json",
+            &format!(
+                r#"{{"sessionId":"s-deleted","projectHash":"{hash}","messages":[
+                    {{"timestamp":"2026-03-02T11:00:00Z","type":"user"}},
+                    {{"timestamp":"2026-03-02T11:00:30Z","type":"gemini","model":"mimo-v2","tokens":{{"input":10,"cached":0,"output":5}}}}
+                ]}}"#
+            ),
+        );
+        write_chat_session_file(
+            &root,
+            "session-later.json",
+            &format!(
+                r#"{{"sessionId":"s-later","projectHash":"{hash}","messages":[
+                    {{"timestamp":"2026-03-10T11:00:00Z","type":"user"}}
+                ]}}"#
+            ),
+        );
+
+        let start = NaiveDate::from_ymd_opt(2026, 3, 1).expect("start");
+        let end = NaiveDate::from_ymd_opt(2026, 3, 3).expect("end");
+        let turns = scan_chat_turns(std::slice::from_ref(&root), start, end);
+        assert_eq!(turns.len(), 3);
+
+        let labels = HashMap::from([(
+            hash,
+            WorkspaceLabels {
+                name: "Client, Inc.".to_string(),
+                thread_titles: HashMap::from([("s-kept".to_string(), "=SUM(A1)".to_string())]),
+            },
+        )]);
+        let prices = vec![ModelPrice {
+            model: "mimo-v2-flash".to_string(),
+            input_per_million: 1.0,
+            cached_input_per_million: Some(0.1),
+            output_per_million: 4.0,
+        }];
+        let csv = build_usage_csv(&turns, &labels, &prices);
+        let rows: Vec<&str> = csv.trim_end().split("\r\n").collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[0].ends_with("duration_seconds,status"));
+        assert!(rows[1].contains(
+            ",\"Client, Inc.\",'=SUM(A1),mimo-v2-flash,1500,400,300,0.0023,12.0,completed"
+        ));
+        assert!(rows[2].ends_with(",0,0,0,,1.0,error"));
+        assert!(rows[3].contains(",(deleted thread),mimo-v2,10,0,5,,30.0,completed"));
+
+        let destination = root.join("exports").join("usage.csv");
+        write_file_atomically(&destination, &csv).expect("write export");
+        assert_eq!(fs::read_to_string(&destination).expect("read export"), csv);
+        assert!(!root.join("exports").join("usage.csv.tmp").exists());
+        let _ = fs::remove_dir_all(&root);
+    }
 }
@@ -1,30 +1,85 @@
-﻿use std::collections::HashMap;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 
 use serde::Deserialize;
+use serde_json::json;
 use tauri::menu::{Menu, MenuItem, MenuItemBuilder, PredefinedMenuItem, Submenu};
 use tauri::{Emitter, Manager, Runtime, WebviewUrl, WebviewWindowBuilder};
 
+use crate::backend::settings_events::SettingsScope;
+use crate::event_subscriptions::emit_routed_event;
+use crate::menu_accelerators::{
+    default_accelerators, plan_accelerator_updates, AcceleratorPlatform, AcceleratorUpdateResult,
+    AcceleratorUpdateStatus, DefaultAccelerator, DEFAULT_ACCELERATORS,
+};
+use crate::state::AppState;
+
 static MENU_LANGUAGE_ZH: AtomicBool = AtomicBool::new(true);
 
 pub struct MenuItemRegistry<R: Runtime> {
     items: Mutex<HashMap<String, MenuItem<R>>>,
+    /// Accelerators set through `menu_set_accelerators`; re-applied whenever the menu is
+    /// rebuilt so a language switch does not reset custom shortcuts.
+    overrides: Mutex<HashMap<String, Option<String>>>,
 }
 
 impl<R: Runtime> Default for MenuItemRegistry<R> {
     fn default() -> Self {
         Self {
             items: Mutex::new(HashMap::new()),
+            overrides: Mutex::new(HashMap::new()),
         }
     }
 }
 
 impl<R: Runtime> MenuItemRegistry<R> {
+    /// Registers a freshly built item. When it replaces an item from a previous build, the
+    /// old item's enabled state and any custom accelerator carry over.
     fn register(&self, id: &str, item: &MenuItem<R>) {
-        if let Ok(mut items) = self.items.lock() {
-            items.insert(id.to_string(), item.clone());
+        let Ok(mut items) = self.items.lock() else {
+            return;
+        };
+        if let Some(previous) = items.get(id) {
+            if let Ok(enabled) = previous.is_enabled() {
+                let _ = item.set_enabled(enabled);
+            }
+        }
+        let override_accelerator = self
+            .overrides
+            .lock()
+            .ok()
+            .and_then(|overrides| overrides.get(id).cloned());
+        if let Some(accelerator) = override_accelerator {
+            let _ = item.set_accelerator(accelerator.as_deref());
         }
+        items.insert(id.to_string(), item.clone());
+    }
+
+    /// Accelerator currently assigned to each registered item.
+    fn current_accelerators(&self) -> HashMap<String, Option<String>> {
+        let overrides = self
+            .overrides
+            .lock()
+            .map(|overrides| overrides.clone())
+            .unwrap_or_default();
+        let registered: Vec<String> = self
+            .items
+            .lock()
+            .map(|items| items.keys().cloned().collect())
+            .unwrap_or_default();
+        registered
+            .into_iter()
+            .map(|id| {
+                let accelerator = overrides.get(&id).cloned().unwrap_or_else(|| {
+                    DEFAULT_ACCELERATORS
+                        .iter()
+                        .find(|(default_id, _)| *default_id == id)
+                        .and_then(|(_, accelerator)| accelerator.map(str::to_string))
+                });
+                (id, accelerator)
+            })
+            .collect()
     }
 
     fn set_accelerator(&self, id: &str, accelerator: Option<&str>) -> tauri::Result<bool> {
@@ -34,6 +89,9 @@ impl<R: Runtime> MenuItemRegistry<R> {
         };
         if let Some(item) = item {
             item.set_accelerator(accelerator)?;
+            if let Ok(mut overrides) = self.overrides.lock() {
+                overrides.insert(id.to_string(), accelerator.map(str::to_string));
+            }
             Ok(true)
         } else {
             Ok(false)
@@ -47,18 +105,45 @@ pub struct MenuAcceleratorUpdate {
     pub accelerator: Option<String>,
 }
 
+/// Validates and applies accelerator updates, reporting a result per item instead of
+/// failing the whole batch. Invalid or conflicting entries leave the item unchanged.
 #[tauri::command]
 pub fn menu_set_accelerators<R: Runtime>(
     app: tauri::AppHandle<R>,
     updates: Vec<MenuAcceleratorUpdate>,
-) -> Result<(), String> {
+) -> Result<Vec<AcceleratorUpdateResult>, String> {
     let registry = app.state::<MenuItemRegistry<R>>();
-    for update in updates {
-        registry
-            .set_accelerator(&update.id, update.accelerator.as_deref())
-            .map_err(|error| error.to_string())?;
+    let previous = registry.current_accelerators();
+    let updates: Vec<(String, Option<String>)> = updates
+        .into_iter()
+        .map(|update| (update.id, update.accelerator))
+        .collect();
+    let mut results = plan_accelerator_updates(
+        &registry.current_accelerators(),
+        &updates,
+        AcceleratorPlatform::current(),
+    );
+    for result in results.iter_mut() {
+        if result.status != AcceleratorUpdateStatus::Applied {
+            continue;
+        }
+        if let Err(error) = registry.set_accelerator(&result.id, result.accelerator.as_deref()) {
+            result.status = AcceleratorUpdateStatus::InvalidSyntax;
+            result.error = Some(error.to_string());
+        }
     }
-    Ok(())
+    let (_, event) = app.state::<AppState>().settings_revision.record_update(
+        SettingsScope::Accelerators,
+        &json!(previous),
+        &json!(registry.current_accelerators()),
+    );
+    emit_routed_event(&app, event);
+    Ok(results)
+}
+
+#[tauri::command]
+pub fn get_default_accelerators() -> Vec<DefaultAccelerator> {
+    default_accelerators(AcceleratorPlatform::current())
 }
 
 pub(crate) fn build_menu<R: Runtime>(handle: &tauri::AppHandle<R>) -> tauri::Result<Menu<R>> {
@@ -564,5 +649,3 @@ fn emit_menu_event<R: Runtime>(app: &tauri::AppHandle<R>, event: &str) {
         let _ = app.emit(event, ());
     }
 }
-
-
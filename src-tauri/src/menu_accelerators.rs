@@ -0,0 +1,472 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AcceleratorPlatform {
+    MacOs,
+    Windows,
+    Linux,
+}
+
+impl AcceleratorPlatform {
+    pub(crate) fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            Self::MacOs
+        } else if cfg!(target_os = "windows") {
+            Self::Windows
+        } else {
+            Self::Linux
+        }
+    }
+}
+
+/// Default accelerators of the items registered in `MenuItemRegistry`.
+pub(crate) const DEFAULT_ACCELERATORS: &[(&str, Option<&str>)] = &[
+    ("file_new_agent", None),
+    ("file_new_worktree_agent", None),
+    ("composer_cycle_model", Some("CmdOrCtrl+Shift+M")),
+    ("composer_cycle_access", Some("CmdOrCtrl+Shift+A")),
+    ("composer_cycle_reasoning", Some("CmdOrCtrl+Shift+R")),
+    ("composer_cycle_collaboration", Some("Shift+Tab")),
+    ("view_toggle_projects_sidebar", None),
+    ("view_toggle_git_sidebar", None),
+    ("view_toggle_debug_panel", Some("CmdOrCtrl+Shift+D")),
+    ("view_toggle_terminal", Some("CmdOrCtrl+Shift+T")),
+    ("view_next_agent", None),
+    ("view_prev_agent", None),
+    ("view_next_workspace", None),
+    ("view_prev_workspace", None),
+];
+
+/// Shortcuts owned by fixed and predefined menu items that cannot be rebound.
+fn reserved_accelerators(platform: AcceleratorPlatform) -> Vec<(&'static str, &'static str)> {
+    let mut reserved = vec![
+        ("file_open_settings", "CmdOrCtrl+,"),
+        ("edit_undo", "CmdOrCtrl+Z"),
+        ("edit_cut", "CmdOrCtrl+X"),
+        ("edit_copy", "CmdOrCtrl+C"),
+        ("edit_paste", "CmdOrCtrl+V"),
+        ("edit_select_all", "CmdOrCtrl+A"),
+    ];
+    match platform {
+        AcceleratorPlatform::MacOs => reserved.extend([
+            ("edit_redo", "Cmd+Shift+Z"),
+            ("app_hide", "Cmd+H"),
+            ("app_hide_others", "Cmd+Alt+H"),
+            ("app_quit", "Cmd+Q"),
+            ("window_close", "Cmd+W"),
+            ("window_minimize", "Cmd+M"),
+            ("view_fullscreen", "Cmd+Ctrl+F"),
+        ]),
+        AcceleratorPlatform::Windows => reserved.push(("edit_redo", "Ctrl+Y")),
+        AcceleratorPlatform::Linux => reserved.push(("edit_redo", "Ctrl+Shift+Z")),
+    }
+    reserved
+}
+
+const MODIFIER_ORDER: &[&str] = &["CmdOrCtrl", "Cmd", "Super", "Ctrl", "Alt", "Shift"];
+const NAMED_KEYS: &[(&str, &str)] = &[
+    ("tab", "Tab"),
+    ("space", "Space"),
+    ("enter", "Enter"),
+    ("return", "Enter"),
+    ("escape", "Escape"),
+    ("esc", "Escape"),
+    ("backspace", "Backspace"),
+    ("delete", "Delete"),
+    ("insert", "Insert"),
+    ("up", "Up"),
+    ("arrowup", "Up"),
+    ("down", "Down"),
+    ("arrowdown", "Down"),
+    ("left", "Left"),
+    ("arrowleft", "Left"),
+    ("right", "Right"),
+    ("arrowright", "Right"),
+    ("home", "Home"),
+    ("end", "End"),
+    ("pageup", "PageUp"),
+    ("pagedown", "PageDown"),
+];
+const PUNCTUATION_KEYS: &str = ",.;'/\\[]-=`";
+
+/// A syntactically valid accelerator. `normalized` is what gets handed to the menu;
+/// `canonical` resolves `CmdOrCtrl` for the platform and is used for collision checks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ParsedAccelerator {
+    pub(crate) normalized: String,
+    pub(crate) canonical: String,
+}
+
+fn normalize_modifier(raw: &str, platform: AcceleratorPlatform) -> Result<&'static str, String> {
+    match raw.to_ascii_lowercase().as_str() {
+        "cmdorctrl" | "commandorcontrol" | "cmdorcontrol" | "commandorctrl" => Ok("CmdOrCtrl"),
+        "cmd" | "command" => {
+            if platform == AcceleratorPlatform::MacOs {
+                Ok("Cmd")
+            } else {
+                Err(format!("`{raw}` is only available on macOS; use CmdOrCtrl"))
+            }
+        }
+        "super" | "meta" => Ok("Super"),
+        "ctrl" | "control" => Ok("Ctrl"),
+        "alt" => Ok("Alt"),
+        "option" => {
+            if platform == AcceleratorPlatform::MacOs {
+                Ok("Alt")
+            } else {
+                Err(format!("`{raw}` is only available on macOS; use Alt"))
+            }
+        }
+        "shift" => Ok("Shift"),
+        _ => Err(format!("Unknown modifier `{raw}`")),
+    }
+}
+
+fn normalize_key(raw: &str) -> Option<String> {
+    let lower = raw.to_ascii_lowercase();
+    if let Some((_, name)) = NAMED_KEYS.iter().find(|(alias, _)| *alias == lower) {
+        return Some(name.to_string());
+    }
+    let mut chars = raw.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        if ch.is_ascii_alphanumeric() {
+            return Some(ch.to_ascii_uppercase().to_string());
+        }
+        if PUNCTUATION_KEYS.contains(ch) {
+            return Some(ch.to_string());
+        }
+        return None;
+    }
+    let number = lower.strip_prefix('f')?.parse::<u8>().ok()?;
+    (1..=24).contains(&number).then(|| format!("F{number}"))
+}
+
+fn is_function_key(key: &str) -> bool {
+    key.len() > 1 && key.starts_with('F') && key[1..].chars().all(|ch| ch.is_ascii_digit())
+}
+
+fn modifier_rank(modifier: &str) -> usize {
+    MODIFIER_ORDER
+        .iter()
+        .position(|candidate| *candidate == modifier)
+        .unwrap_or(MODIFIER_ORDER.len())
+}
+
+pub(crate) fn parse_accelerator(
+    raw: &str,
+    platform: AcceleratorPlatform,
+) -> Result<ParsedAccelerator, String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Err("Accelerator is empty".to_string());
+    }
+    let parts: Vec<&str> = trimmed.split('+').map(str::trim).collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(format!("`{trimmed}` has an empty segment"));
+    }
+    let (key_part, modifier_parts) = parts.split_last().expect("split yields one part");
+    let key = normalize_key(key_part).ok_or_else(|| {
+        if normalize_modifier(key_part, platform).is_ok() {
+            format!("`{trimmed}` has no key after its modifiers")
+        } else {
+            format!("Unknown key `{key_part}`")
+        }
+    })?;
+    let mut modifiers: Vec<&'static str> = Vec::new();
+    for part in modifier_parts {
+        let modifier = normalize_modifier(part, platform)?;
+        if modifiers.contains(&modifier) {
+            return Err(format!("Modifier `{modifier}` is repeated in `{trimmed}`"));
+        }
+        modifiers.push(modifier);
+    }
+    modifiers.sort_by_key(|modifier| modifier_rank(modifier));
+
+    let printable = key.chars().count() == 1;
+    let only_shift = modifiers.iter().all(|modifier| *modifier == "Shift");
+    if printable && only_shift {
+        return Err(format!(
+            "`{trimmed}` would capture normal typing; add CmdOrCtrl, Ctrl or Alt"
+        ));
+    }
+    if modifiers.is_empty() && !is_function_key(&key) {
+        return Err(format!("`{trimmed}` needs at least one modifier"));
+    }
+
+    let mut canonical_modifiers: Vec<&str> = modifiers
+        .iter()
+        .map(|modifier| match (*modifier, platform) {
+            ("CmdOrCtrl", AcceleratorPlatform::MacOs) | ("Super", AcceleratorPlatform::MacOs) => {
+                "Cmd"
+            }
+            ("CmdOrCtrl", _) => "Ctrl",
+            (modifier, _) => modifier,
+        })
+        .collect();
+    let unique: HashSet<&str> = canonical_modifiers.iter().copied().collect();
+    if unique.len() != canonical_modifiers.len() {
+        return Err(format!(
+            "`{trimmed}` repeats the same modifier on this platform"
+        ));
+    }
+    canonical_modifiers.sort_by_key(|modifier| modifier_rank(modifier));
+
+    let join = |modifiers: &[&str]| {
+        let mut segments: Vec<&str> = modifiers.to_vec();
+        segments.push(&key);
+        segments.join("+")
+    };
+    Ok(ParsedAccelerator {
+        normalized: join(&modifiers),
+        canonical: join(&canonical_modifiers),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum AcceleratorUpdateStatus {
+    Applied,
+    Conflicted,
+    InvalidSyntax,
+    UnknownItem,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AcceleratorUpdateResult {
+    pub(crate) id: String,
+    pub(crate) status: AcceleratorUpdateStatus,
+    /// Normalized accelerator that was applied, or `None` when the shortcut was cleared.
+    pub(crate) accelerator: Option<String>,
+    pub(crate) conflicted_with: Option<String>,
+    pub(crate) error: Option<String>,
+}
+
+impl AcceleratorUpdateResult {
+    fn rejected(id: &str, status: AcceleratorUpdateStatus) -> Self {
+        Self {
+            id: id.to_string(),
+            status,
+            accelerator: None,
+            conflicted_with: None,
+            error: None,
+        }
+    }
+}
+
+/// Validates a batch of updates against the accelerators currently assigned to registered
+/// items (`current`) and the reserved built-in shortcuts. Items outside the batch keep
+/// their shortcut; within the batch the earlier entry wins a collision.
+pub(crate) fn plan_accelerator_updates(
+    current: &HashMap<String, Option<String>>,
+    updates: &[(String, Option<String>)],
+    platform: AcceleratorPlatform,
+) -> Vec<AcceleratorUpdateResult> {
+    let updated_ids: HashSet<&str> = updates
+        .iter()
+        .filter(|(id, accelerator)| {
+            current.contains_key(id)
+                && accelerator
+                    .as_deref()
+                    .map(|raw| parse_accelerator(raw, platform).is_ok())
+                    .unwrap_or(true)
+        })
+        .map(|(id, _)| id.as_str())
+        .collect();
+
+    let mut taken: HashMap<String, String> = HashMap::new();
+    for (id, accelerator) in reserved_accelerators(platform) {
+        if let Ok(parsed) = parse_accelerator(accelerator, platform) {
+            taken.insert(parsed.canonical, id.to_string());
+        }
+    }
+    let mut kept: Vec<(&String, &String)> = current
+        .iter()
+        .filter(|(id, _)| !updated_ids.contains(id.as_str()))
+        .filter_map(|(id, accelerator)| accelerator.as_ref().map(|accelerator| (id, accelerator)))
+        .collect();
+    kept.sort();
+    for (id, accelerator) in kept {
+        if let Ok(parsed) = parse_accelerator(accelerator, platform) {
+            taken.entry(parsed.canonical).or_insert_with(|| id.clone());
+        }
+    }
+
+    updates
+        .iter()
+        .map(|(id, accelerator)| {
+            if !current.contains_key(id) {
+                return AcceleratorUpdateResult::rejected(id, AcceleratorUpdateStatus::UnknownItem);
+            }
+            let Some(raw) = accelerator.as_deref() else {
+                return AcceleratorUpdateResult::rejected(id, AcceleratorUpdateStatus::Applied);
+            };
+            let parsed = match parse_accelerator(raw, platform) {
+                Ok(parsed) => parsed,
+                Err(error) => {
+                    return AcceleratorUpdateResult {
+                        error: Some(error),
+                        ..AcceleratorUpdateResult::rejected(
+                            id,
+                            AcceleratorUpdateStatus::InvalidSyntax,
+                        )
+                    }
+                }
+            };
+            if let Some(owner) = taken.get(&parsed.canonical).filter(|owner| *owner != id) {
+                return AcceleratorUpdateResult {
+                    accelerator: Some(parsed.normalized),
+                    conflicted_with: Some(owner.clone()),
+                    error: Some(format!("Already used by `{owner}`")),
+                    ..AcceleratorUpdateResult::rejected(id, AcceleratorUpdateStatus::Conflicted)
+                };
+            }
+            taken.insert(parsed.canonical, id.clone());
+            AcceleratorUpdateResult {
+                accelerator: Some(parsed.normalized),
+                ..AcceleratorUpdateResult::rejected(id, AcceleratorUpdateStatus::Applied)
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DefaultAccelerator {
+    pub(crate) id: String,
+    pub(crate) accelerator: Option<String>,
+    /// The accelerator with `CmdOrCtrl` resolved for the running platform.
+    pub(crate) resolved: Option<String>,
+}
+
+pub(crate) fn default_accelerators(platform: AcceleratorPlatform) -> Vec<DefaultAccelerator> {
+    DEFAULT_ACCELERATORS
+        .iter()
+        .map(|(id, accelerator)| DefaultAccelerator {
+            id: id.to_string(),
+            accelerator: accelerator.map(str::to_string),
+            resolved: accelerator
+                .and_then(|raw| parse_accelerator(raw, platform).ok())
+                .map(|parsed| parsed.canonical),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_accelerator, plan_accelerator_updates, AcceleratorPlatform, AcceleratorUpdateStatus,
+        DEFAULT_ACCELERATORS,
+    };
+    use std::collections::HashMap;
+
+    fn current_defaults() -> HashMap<String, Option<String>> {
+        DEFAULT_ACCELERATORS
+            .iter()
+            .map(|(id, accelerator)| (id.to_string(), accelerator.map(str::to_string)))
+            .collect()
+    }
+
+    #[test]
+    fn parses_and_normalizes_modifiers_per_platform() {
+        let mac = parse_accelerator("shift+cmdorctrl+m", AcceleratorPlatform::MacOs).unwrap();
+        assert_eq!(mac.normalized, "CmdOrCtrl+Shift+M");
+        assert_eq!(mac.canonical, "Cmd+Shift+M");
+        let linux =
+            parse_accelerator("CommandOrControl+Shift+m", AcceleratorPlatform::Linux).unwrap();
+        assert_eq!(linux.canonical, "Ctrl+Shift+M");
+        assert_eq!(
+            parse_accelerator("Ctrl+F5", AcceleratorPlatform::Windows)
+                .unwrap()
+                .canonical,
+            "Ctrl+F5"
+        );
+        assert!(parse_accelerator("F2", AcceleratorPlatform::Linux).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_syntax() {
+        let linux = AcceleratorPlatform::Linux;
+        assert!(parse_accelerator("Cmd+K", linux)
+            .unwrap_err()
+            .contains("only available on macOS"));
+        assert!(parse_accelerator("Cmd+K", AcceleratorPlatform::MacOs).is_ok());
+        assert!(parse_accelerator("Ctrl+Shift", linux).is_err());
+        assert!(parse_accelerator("Ctrl++K", linux).is_err());
+        assert!(parse_accelerator("Ctrl+Ctrl+K", linux).is_err());
+        assert!(parse_accelerator("CmdOrCtrl+Ctrl+K", linux).is_err());
+        assert!(parse_accelerator("Hyper+K", linux).is_err());
+        assert!(parse_accelerator("K", linux).is_err());
+        assert!(parse_accelerator("Shift+K", linux).is_err());
+        assert!(parse_accelerator("Shift+Tab", linux).is_ok());
+    }
+
+    #[test]
+    fn detects_conflicts_with_builtins_and_other_items() {
+        let current = current_defaults();
+        let results = plan_accelerator_updates(
+            &current,
+            &[
+                (
+                    "view_next_agent".to_string(),
+                    Some("Ctrl+Shift+M".to_string()),
+                ),
+                (
+                    "view_prev_agent".to_string(),
+                    Some("CmdOrCtrl+C".to_string()),
+                ),
+                (
+                    "view_next_workspace".to_string(),
+                    Some("Alt+Down".to_string()),
+                ),
+                (
+                    "view_prev_workspace".to_string(),
+                    Some("Alt+Down".to_string()),
+                ),
+                ("missing".to_string(), Some("Alt+X".to_string())),
+                ("file_new_agent".to_string(), Some("Cmd+N".to_string())),
+            ],
+            AcceleratorPlatform::Linux,
+        );
+        assert_eq!(results[0].status, AcceleratorUpdateStatus::Conflicted);
+        assert_eq!(
+            results[0].conflicted_with.as_deref(),
+            Some("composer_cycle_model")
+        );
+        assert_eq!(results[1].conflicted_with.as_deref(), Some("edit_copy"));
+        assert_eq!(results[2].status, AcceleratorUpdateStatus::Applied);
+        assert_eq!(results[2].accelerator.as_deref(), Some("Alt+Down"));
+        assert_eq!(
+            results[3].conflicted_with.as_deref(),
+            Some("view_next_workspace")
+        );
+        assert_eq!(results[4].status, AcceleratorUpdateStatus::UnknownItem);
+        assert_eq!(results[5].status, AcceleratorUpdateStatus::InvalidSyntax);
+    }
+
+    #[test]
+    fn swapping_shortcuts_within_a_batch_is_allowed() {
+        let current = current_defaults();
+        let results = plan_accelerator_updates(
+            &current,
+            &[
+                (
+                    "composer_cycle_model".to_string(),
+                    Some("CmdOrCtrl+Shift+A".to_string()),
+                ),
+                (
+                    "composer_cycle_access".to_string(),
+                    Some("CmdOrCtrl+Shift+M".to_string()),
+                ),
+                ("view_toggle_terminal".to_string(), None),
+            ],
+            AcceleratorPlatform::MacOs,
+        );
+        assert!(results
+            .iter()
+            .all(|result| result.status == AcceleratorUpdateStatus::Applied));
+        assert_eq!(results[2].accelerator, None);
+    }
+}
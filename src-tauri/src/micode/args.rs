@@ -1,38 +1,288 @@
+use std::fmt;
+use std::path::Path;
+
+use serde_json::json;
 use tokio::process::Command;
 
+use crate::types::errors::{CommandError, ErrorCode};
 use crate::types::{AppSettings, WorkspaceEntry};
 
-pub(crate) fn parse_micode_args(value: Option<&str>) -> Result<Vec<String>, String> {
-    let raw = match value {
-        Some(raw) if !raw.trim().is_empty() => raw.trim(),
-        _ => return Ok(Vec::new()),
-    };
-    shell_words::split(raw)
-        .map_err(|err| format!("Invalid MiCode args: {err}"))
-        .map(|args| {
-            args.into_iter()
-                .filter(|arg| {
-                    let normalized = arg.trim();
-                    !normalized.is_empty()
-                        && normalized != "--experimental-acp"
-                        && normalized != "-experimental-acp"
-                })
-                .collect()
-        })
-}
-
-pub(crate) fn apply_micode_args(command: &mut Command, value: Option<&str>) -> Result<(), String> {
-    let args = parse_micode_args(value)?;
-    if !args.is_empty() {
-        command.args(args);
+/// Added by the monitor itself, so a copy in the user's args is dropped.
+const ACP_FLAGS: &[&str] = &["--experimental-acp", "-experimental-acp"];
+/// Picks the model the agent runs, see `resolve_workspace_micode_args`.
+const MODEL_FLAGS: &[&str] = &["--model", "-m"];
+/// Flags that make the CLI print and exit, or run a one-shot prompt, instead of
+/// serving the ACP session the monitor starts.
+const CONFLICTING_FLAGS: &[&str] = &[
+    "--help",
+    "-h",
+    "--version",
+    "-v",
+    "--prompt",
+    "-p",
+    "--prompt-interactive",
+    "-i",
+];
+
+/// Why `agent_args` cannot be used; `position` is the 0-based character offset in the
+/// string the user typed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MicodeArgsError {
+    pub(crate) position: usize,
+    pub(crate) reason: String,
+}
+
+impl MicodeArgsError {
+    fn new(position: usize, reason: impl Into<String>) -> Self {
+        Self {
+            position,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for MicodeArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid MiCode args at position {}: {}",
+            self.position, self.reason
+        )
+    }
+}
+
+impl From<MicodeArgsError> for CommandError {
+    fn from(error: MicodeArgsError) -> Self {
+        CommandError::new(ErrorCode::InvalidAgentArgs, error.to_string())
+            .with_details(json!({ "position": error.position }))
     }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct MicodeArgs {
+    /// Leading `VAR=value` words, set on the spawned process instead of passed as args.
+    pub(crate) env: Vec<(String, String)>,
+    pub(crate) args: Vec<String>,
+}
+
+struct Word {
+    text: String,
+    start: usize,
+    /// Characters at the start of `text` that were neither quoted nor escaped.
+    plain_len: usize,
+    quoted: bool,
+}
+
+impl Word {
+    fn new(start: usize) -> Self {
+        Self {
+            text: String::new(),
+            start,
+            plain_len: 0,
+            quoted: false,
+        }
+    }
+
+    /// The `(name, value)` of a `VAR=value` word whose name was typed unquoted.
+    fn assignment(&self) -> Option<(String, String)> {
+        let (name, value) = self.text.split_once('=')?;
+        let mut chars = name.chars();
+        let valid_name = chars
+            .next()
+            .is_some_and(|first| first == '_' || first.is_ascii_alphabetic())
+            && chars.all(|ch| ch == '_' || ch.is_ascii_alphanumeric());
+        (valid_name && name.len() < self.plain_len).then(|| (name.to_string(), value.to_string()))
+    }
+}
+
+/// Splits like a POSIX shell: whitespace separates words, single quotes are literal,
+/// double quotes allow `\"`, `\\`, `\$` and `` \` `` escapes, and a bare backslash
+/// escapes the next character.
+fn split_words(raw: &str) -> Result<Vec<Word>, MicodeArgsError> {
+    let mut words = Vec::new();
+    let mut current: Option<Word> = None;
+    let mut chars = raw.chars().enumerate();
+    while let Some((index, ch)) = chars.next() {
+        if ch.is_whitespace() {
+            words.extend(current.take());
+            continue;
+        }
+        let word = current.get_or_insert_with(|| Word::new(index));
+        match ch {
+            '\'' => {
+                word.quoted = true;
+                loop {
+                    match chars.next() {
+                        Some((_, '\'')) => break,
+                        Some((_, ch)) => word.text.push(ch),
+                        None => {
+                            return Err(MicodeArgsError::new(index, "unterminated single quote"))
+                        }
+                    }
+                }
+            }
+            '"' => {
+                word.quoted = true;
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped @ ('"' | '\\' | '$' | '`'))) => {
+                                word.text.push(escaped)
+                            }
+                            Some((_, '\n')) => {}
+                            Some((_, other)) => {
+                                word.text.push('\\');
+                                word.text.push(other);
+                            }
+                            None => {
+                                return Err(MicodeArgsError::new(
+                                    index,
+                                    "unterminated double quote",
+                                ))
+                            }
+                        },
+                        Some((_, ch)) => word.text.push(ch),
+                        None => {
+                            return Err(MicodeArgsError::new(index, "unterminated double quote"))
+                        }
+                    }
+                }
+            }
+            '\\' => {
+                word.quoted = true;
+                match chars.next() {
+                    Some((_, '\n')) => {}
+                    Some((_, escaped)) => word.text.push(escaped),
+                    None => {
+                        return Err(MicodeArgsError::new(
+                            index,
+                            "trailing backslash escapes nothing",
+                        ))
+                    }
+                }
+            }
+            ch => {
+                word.text.push(ch);
+                if !word.quoted {
+                    word.plain_len += 1;
+                }
+            }
+        }
+    }
+    words.extend(current);
+    Ok(words)
+}
+
+fn is_micode_program(word: &str) -> bool {
+    Path::new(word)
+        .file_stem()
+        .is_some_and(|stem| stem.eq_ignore_ascii_case("micode"))
+}
+
+fn conflicting_flag(arg: &str) -> Option<&'static str> {
+    CONFLICTING_FLAGS.iter().copied().find(|flag| {
+        arg == *flag
+            || (flag.starts_with("--")
+                && arg
+                    .strip_prefix(flag)
+                    .is_some_and(|rest| rest.starts_with('=')))
+    })
+}
+
+/// Parses the user's `agent_args`. Leading `VAR=value` words become environment
+/// variables, and a pasted `micode` program name after them is dropped.
+pub(crate) fn parse_micode_args(value: Option<&str>) -> Result<MicodeArgs, MicodeArgsError> {
+    let raw = value.unwrap_or_default();
+    let mut words = split_words(raw)?.into_iter().peekable();
+    let mut parsed = MicodeArgs::default();
+    while let Some(assignment) = words.peek().and_then(Word::assignment) {
+        parsed.env.push(assignment);
+        words.next();
+    }
+    words.next_if(|word| !word.quoted && is_micode_program(&word.text));
+    for word in words {
+        let arg = word.text.trim();
+        if arg.is_empty() || ACP_FLAGS.contains(&arg) {
+            continue;
+        }
+        if let Some(flag) = conflicting_flag(arg) {
+            return Err(MicodeArgsError::new(
+                word.start,
+                format!("`{flag}` conflicts with the ACP session the monitor starts"),
+            ));
+        }
+        parsed.args.push(word.text);
+    }
+    Ok(parsed)
+}
+
+pub(crate) fn apply_micode_args(
+    command: &mut Command,
+    value: Option<&str>,
+) -> Result<(), MicodeArgsError> {
+    let parsed = parse_micode_args(value)?;
+    command.envs(parsed.env);
+    command.args(parsed.args);
     Ok(())
 }
 
+/// Args the agent of `entry` starts with: its `agentArgs`, then its parent's for worktrees,
+/// then the app's, plus `--model` for the workspace's `preferredModel` unless those args
+/// already pick a model. Each agent gets its model on the command line, so switching models
+/// in one workspace never changes the model of another.
 pub(crate) fn resolve_workspace_micode_args(
     entry: &WorkspaceEntry,
     parent_entry: Option<&WorkspaceEntry>,
     app_settings: Option<&AppSettings>,
+) -> Option<String> {
+    let args = resolve_configured_micode_args(entry, parent_entry, app_settings);
+    let Some(model) = workspace_model(entry, parent_entry) else {
+        return args;
+    };
+    if args
+        .as_deref()
+        .is_some_and(|args| model_from_micode_args(args).is_some())
+    {
+        return args;
+    }
+    let model_arg = format!("--model {}", shell_words::quote(model));
+    Some(match args {
+        Some(args) => format!("{args} {model_arg}"),
+        None => model_arg,
+    })
+}
+
+/// The model `args` start the agent with, if they pick one.
+pub(crate) fn model_from_micode_args(args: &str) -> Option<String> {
+    let parts = shell_words::split(args).ok()?;
+    parts.iter().enumerate().find_map(|(index, part)| {
+        if MODEL_FLAGS.contains(&part.as_str()) {
+            return parts.get(index + 1).cloned();
+        }
+        part.strip_prefix("--model=").map(ToString::to_string)
+    })
+}
+
+/// The workspace's own `preferredModel`, then its parent's for worktrees.
+fn workspace_model<'a>(
+    entry: &'a WorkspaceEntry,
+    parent_entry: Option<&'a WorkspaceEntry>,
+) -> Option<&'a str> {
+    let parent_model = parent_entry
+        .filter(|_| entry.kind.is_worktree())
+        .and_then(|parent| parent.settings.preferred_model.as_deref());
+    [entry.settings.preferred_model.as_deref(), parent_model]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .find(|model| !model.is_empty())
+}
+
+fn resolve_configured_micode_args(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+    app_settings: Option<&AppSettings>,
 ) -> Option<String> {
     if let Some(value) = entry.settings.agent_args.as_deref() {
         if let Some(normalized) = normalize_micode_args(value) {
@@ -67,34 +317,114 @@ fn normalize_micode_args(value: &str) -> Option<String> {
 
 #[cfg(test)]
 mod tests {
-    use super::{parse_micode_args, resolve_workspace_micode_args};
+    use super::{
+        model_from_micode_args, parse_micode_args, resolve_workspace_micode_args, MicodeArgsError,
+    };
+    use crate::types::errors::{CommandError, ErrorCode};
     use crate::types::{AppSettings, WorkspaceEntry, WorkspaceKind, WorkspaceSettings};
 
+    fn args(value: &str) -> Vec<String> {
+        parse_micode_args(Some(value)).expect("parse args").args
+    }
+
     #[test]
     fn parses_empty_args() {
-        assert!(parse_micode_args(None).expect("parse none").is_empty());
-        assert!(parse_micode_args(Some("   "))
-            .expect("parse blanks")
-            .is_empty());
+        assert!(parse_micode_args(None).expect("parse none").args.is_empty());
+        assert!(args("   ").is_empty());
     }
 
     #[test]
     fn parses_simple_args() {
-        let args = parse_micode_args(Some("--profile personal --flag")).expect("parse args");
-        assert_eq!(args, vec!["--profile", "personal", "--flag"]);
+        assert_eq!(
+            args("--profile personal --flag"),
+            vec!["--profile", "personal", "--flag"]
+        );
     }
 
     #[test]
     fn filters_reserved_acp_flag() {
-        let args = parse_micode_args(Some("--experimental-acp --flag x"))
-            .expect("parse args");
-        assert_eq!(args, vec!["--flag", "x"]);
+        assert_eq!(args("--experimental-acp --flag x"), vec!["--flag", "x"]);
     }
 
     #[test]
     fn parses_quoted_args() {
-        let args = parse_micode_args(Some("--path \"a b\" --name='c d'")).expect("parse args");
-        assert_eq!(args, vec!["--path", "a b", "--name=c d"]);
+        assert_eq!(
+            args("--path \"a b\" --name='c d'"),
+            vec!["--path", "a b", "--name=c d"]
+        );
+        assert_eq!(
+            args("--config \"my profile.json\" ''"),
+            vec!["--config", "my profile.json"]
+        );
+    }
+
+    #[test]
+    fn parses_escapes() {
+        assert_eq!(
+            args(r#"--title "say \"hi\" \$HOME" --dir my\ dir 'it\s' \'"#),
+            vec![
+                "--title",
+                "say \"hi\" $HOME",
+                "--dir",
+                "my dir",
+                "it\\s",
+                "'"
+            ]
+        );
+        assert_eq!(args("--a \\\n--b"), vec!["--a", "--b"]);
+    }
+
+    #[test]
+    fn parses_unicode_args() {
+        assert_eq!(
+            args("--name \"日本 語\" --emoji=🚀"),
+            vec!["--name", "日本 語", "--emoji=🚀"]
+        );
+        let error = parse_micode_args(Some("--name 日本 \"語")).unwrap_err();
+        assert_eq!(
+            error,
+            MicodeArgsError {
+                position: 10,
+                reason: "unterminated double quote".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn moves_env_prefixes_to_the_environment() {
+        let parsed = parse_micode_args(Some("FOO=bar _DEBUG='a b' micode --flag X=1 \"QUOTED=1\""))
+            .expect("parse args");
+        assert_eq!(
+            parsed.env,
+            vec![
+                ("FOO".to_string(), "bar".to_string()),
+                ("_DEBUG".to_string(), "a b".to_string()),
+            ]
+        );
+        assert_eq!(parsed.args, vec!["--flag", "X=1", "QUOTED=1"]);
+
+        let parsed = parse_micode_args(Some("'FOO=bar' 1X=2 --flag")).expect("parse args");
+        assert!(parsed.env.is_empty());
+        assert_eq!(parsed.args, vec!["FOO=bar", "1X=2", "--flag"]);
+    }
+
+    #[test]
+    fn reports_the_position_of_unusable_args() {
+        let error = |value: &str| parse_micode_args(Some(value)).unwrap_err();
+        assert_eq!(error("--flag 'open").position, 7);
+        assert_eq!(error("--flag \\").position, 7);
+        let conflict = error("--model x --prompt=hi");
+        assert_eq!(conflict.position, 10);
+        assert!(conflict.reason.contains("`--prompt`"));
+        assert_eq!(error("-h").position, 0);
+        assert!(parse_micode_args(Some("--prompts-dir x")).is_ok());
+
+        let command_error = CommandError::from(error("\"open"));
+        assert_eq!(command_error.code, ErrorCode::InvalidAgentArgs);
+        assert_eq!(
+            command_error.details,
+            Some(serde_json::json!({ "position": 0 }))
+        );
     }
 
     #[test]
@@ -114,6 +444,7 @@ mod tests {
                 agent_args: Some("--profile parent".to_string()),
                 ..WorkspaceSettings::default()
             },
+            stack: None,
         };
 
         let child = WorkspaceEntry {
@@ -125,6 +456,7 @@ mod tests {
             parent_id: Some(parent.id.clone()),
             worktree: None,
             settings: WorkspaceSettings::default(),
+            stack: None,
         };
 
         let resolved = resolve_workspace_micode_args(&child, Some(&parent), Some(&app_settings));
@@ -145,6 +477,7 @@ mod tests {
             parent_id: None,
             worktree: None,
             settings: WorkspaceSettings::default(),
+            stack: None,
         };
         let resolved_main = resolve_workspace_micode_args(&main, None, Some(&app_settings));
         assert_eq!(resolved_main.as_deref(), Some("--profile app"));
@@ -163,8 +496,72 @@ mod tests {
             parent_id: None,
             worktree: None,
             settings: WorkspaceSettings::default(),
+            stack: None,
         };
         let resolved = resolve_workspace_micode_args(&entry, None, Some(&app_settings));
         assert!(resolved.is_none());
     }
+
+    #[test]
+    fn each_workspace_starts_its_agent_with_its_own_model() {
+        let app_settings = AppSettings {
+            agent_args: Some("--profile app".to_string()),
+            ..AppSettings::default()
+        };
+        let workspace = |id: &str, kind: WorkspaceKind, model: Option<&str>| WorkspaceEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: format!("/tmp/{id}"),
+            agent_bin: None,
+            kind,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings {
+                preferred_model: model.map(ToString::to_string),
+                ..WorkspaceSettings::default()
+            },
+            stack: None,
+        };
+        let a = workspace("a", WorkspaceKind::Main, Some("mimo-pro"));
+        let b = workspace("b", WorkspaceKind::Main, Some("mimo flash"));
+        let resolve =
+            |entry, parent| resolve_workspace_micode_args(entry, parent, Some(&app_settings));
+        assert_eq!(
+            resolve(&a, None).as_deref(),
+            Some("--profile app --model mimo-pro")
+        );
+        assert_eq!(
+            resolve(&b, None).as_deref(),
+            Some("--profile app --model 'mimo flash'")
+        );
+        assert_eq!(
+            resolve(&b, None)
+                .as_deref()
+                .and_then(model_from_micode_args)
+                .as_deref(),
+            Some("mimo flash")
+        );
+
+        // Worktrees follow their parent unless they picked a model of their own.
+        let worktree = workspace("wt", WorkspaceKind::Worktree, None);
+        assert_eq!(
+            resolve(&worktree, Some(&a)).as_deref(),
+            Some("--profile app --model mimo-pro")
+        );
+        let unpinned = workspace("c", WorkspaceKind::Main, None);
+        assert_eq!(
+            resolve(&unpinned, Some(&a)).as_deref(),
+            Some("--profile app")
+        );
+
+        // A model in the user's own args wins.
+        let mut pinned = a.clone();
+        pinned.settings.agent_args = Some("-m custom".to_string());
+        assert_eq!(resolve(&pinned, None).as_deref(), Some("-m custom"));
+        assert_eq!(
+            model_from_micode_args("--model=x --flag").as_deref(),
+            Some("x")
+        );
+        assert_eq!(model_from_micode_args("--flag"), None);
+    }
 }
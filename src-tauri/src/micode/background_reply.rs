@@ -0,0 +1,187 @@
+use std::future::Future;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tokio::time::{timeout, Instant};
+
+use crate::types::AppSettings;
+
+/// Pushed into a helper's own event channel once its `turn/start` answered: background
+/// threads get no `turn/completed`, and every update of the turn is queued before it.
+const BACKGROUND_TURN_DONE: &str = "turn/completed";
+/// Turns started per reply: the first, and one retry when it came back empty.
+const BACKGROUND_REPLY_ATTEMPTS: usize = 2;
+
+/// How long a background helper waits for the agent's reply, from `AppSettings`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BackgroundReplyTimeouts {
+    /// Silence after which the reply counts as finished.
+    pub(crate) idle: Duration,
+    /// Longest wait for one turn, however steadily it streams.
+    pub(crate) max_wait: Duration,
+}
+
+impl BackgroundReplyTimeouts {
+    pub(crate) fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            idle: Duration::from_millis(settings.background_idle_timeout_ms.max(1)),
+            max_wait: Duration::from_secs(settings.background_max_wait_secs.max(1)),
+        }
+    }
+}
+
+/// Tells the collector reading the other end of `done_tx` that the turn finished.
+pub(crate) fn mark_background_turn_done(done_tx: &mpsc::UnboundedSender<Value>) {
+    let _ = done_tx.send(json!({ "method": BACKGROUND_TURN_DONE }));
+}
+
+/// Collects the agent text streamed into `rx` until the turn is done, `rx` stays silent
+/// for `idle`, or `max_wait` passed.
+pub(crate) async fn collect_background_agent_text(
+    rx: &mut mpsc::UnboundedReceiver<Value>,
+    timeouts: BackgroundReplyTimeouts,
+) -> String {
+    let started_at = Instant::now();
+    let mut output = String::new();
+    loop {
+        let remaining = timeouts.max_wait.saturating_sub(started_at.elapsed());
+        if remaining.is_zero() {
+            break;
+        }
+        let event = match timeout(timeouts.idle.min(remaining), rx.recv()).await {
+            Ok(Some(event)) => event,
+            Ok(None) | Err(_) => break,
+        };
+        match event.get("method").and_then(Value::as_str).unwrap_or("") {
+            "item/agentMessage/delta" => {
+                if let Some(delta) = event
+                    .get("params")
+                    .and_then(|params| params.get("delta"))
+                    .and_then(Value::as_str)
+                {
+                    output.push_str(delta);
+                }
+            }
+            BACKGROUND_TURN_DONE => break,
+            _ => {}
+        }
+    }
+    output
+}
+
+/// Runs a background turn with `start_turn` and collects its reply from `rx`, whose
+/// sender `done_tx` is also registered for the thread. `start_turn` resolves to whether
+/// the turn finished, which ends the collection without waiting out `idle`. A reply
+/// that is still empty, without a start error, gets the turn started once more.
+pub(crate) async fn collect_background_reply<F, Fut>(
+    rx: &mut mpsc::UnboundedReceiver<Value>,
+    done_tx: &mpsc::UnboundedSender<Value>,
+    timeouts: BackgroundReplyTimeouts,
+    mut start_turn: F,
+) -> Result<String, String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<bool, String>>,
+{
+    let mut output = String::new();
+    for _ in 0..BACKGROUND_REPLY_ATTEMPTS {
+        if start_turn().await? {
+            mark_background_turn_done(done_tx);
+        }
+        output = collect_background_agent_text(rx, timeouts).await;
+        if !output.trim().is_empty() {
+            break;
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collect_background_reply, BackgroundReplyTimeouts};
+    use serde_json::{json, Value};
+    use std::cell::Cell;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tokio::time::Instant;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("runtime")
+            .block_on(future)
+    }
+
+    fn delta(text: &str) -> Value {
+        json!({ "method": "item/agentMessage/delta", "params": { "delta": text } })
+    }
+
+    fn timeouts(idle_ms: u64) -> BackgroundReplyTimeouts {
+        BackgroundReplyTimeouts {
+            idle: Duration::from_millis(idle_ms),
+            max_wait: Duration::from_secs(30),
+        }
+    }
+
+    #[test]
+    fn a_finished_turn_ends_the_collection_without_waiting_for_silence() {
+        block_on(async {
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            let started = Instant::now();
+            let reply = collect_background_reply(&mut rx, &tx.clone(), timeouts(10_000), || {
+                let _ = tx.send(delta("feat: add "));
+                let _ = tx.send(delta("retries"));
+                async { Ok(true) }
+            })
+            .await;
+            assert_eq!(reply.as_deref(), Ok("feat: add retries"));
+            assert!(started.elapsed() < Duration::from_secs(1));
+        });
+    }
+
+    #[test]
+    fn an_empty_reply_is_retried_once() {
+        block_on(async {
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            let attempts = Cell::new(0);
+            let reply = collect_background_reply(&mut rx, &tx.clone(), timeouts(20), || {
+                attempts.set(attempts.get() + 1);
+                // The first turn never reports back; the retry answers.
+                let finished = attempts.get() > 1;
+                if finished {
+                    let _ = tx.send(delta("fix: retry"));
+                }
+                async move { Ok(finished) }
+            })
+            .await;
+            assert_eq!(reply.as_deref(), Ok("fix: retry"));
+            assert_eq!(attempts.get(), 2);
+
+            attempts.set(0);
+            let reply = collect_background_reply(&mut rx, &tx.clone(), timeouts(20), || {
+                attempts.set(attempts.get() + 1);
+                async { Ok(true) }
+            })
+            .await;
+            assert_eq!(reply.as_deref(), Ok(""));
+            assert_eq!(attempts.get(), 2);
+        });
+    }
+
+    #[test]
+    fn errors_fail_without_a_retry() {
+        block_on(async {
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            let attempts = Cell::new(0);
+            let reply = collect_background_reply(&mut rx, &tx.clone(), timeouts(20), || {
+                attempts.set(attempts.get() + 1);
+                async { Err::<bool, _>("turn/start failed".to_string()) }
+            })
+            .await;
+            assert_eq!(reply, Err("turn/start failed".to_string()));
+            assert_eq!(attempts.get(), 1);
+        });
+    }
+}
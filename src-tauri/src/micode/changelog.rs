@@ -0,0 +1,242 @@
+use serde::Deserialize;
+
+use crate::git::ChangelogCommit;
+
+/// Characters of a commit body passed to the model; longer bodies are cut.
+const MAX_PROMPT_BODY_CHARS: usize = 600;
+
+/// Markdown layout of a generated changelog.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ChangelogStyle {
+    /// A `## [version]` release with `###` sections, as in keepachangelog.com.
+    #[default]
+    KeepAChangelog,
+    /// Only the `##` sections.
+    Plain,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChangelogSection {
+    Features,
+    Fixes,
+    Other,
+}
+
+impl ChangelogSection {
+    const ALL: [Self; 3] = [Self::Features, Self::Fixes, Self::Other];
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Features => "Features",
+            Self::Fixes => "Fixes",
+            Self::Other => "Other",
+        }
+    }
+}
+
+/// Sorts a commit subject by its conventional-commit type and renders its entry text.
+/// Subjects without a type land in `Other` unchanged.
+fn classify_commit(summary: &str) -> (ChangelogSection, String) {
+    let summary = summary.trim();
+    let Some((prefix, text)) = summary.split_once(':') else {
+        return (ChangelogSection::Other, summary.to_string());
+    };
+    let (prefix, breaking) = match prefix.strip_suffix('!') {
+        Some(prefix) => (prefix, true),
+        None => (prefix, false),
+    };
+    let (kind, scope) = match prefix.split_once('(') {
+        Some((kind, scope)) => match scope.strip_suffix(')') {
+            Some(scope) => (kind, Some(scope.trim())),
+            None => return (ChangelogSection::Other, summary.to_string()),
+        },
+        None => (prefix, None),
+    };
+    let text = text.trim();
+    if text.is_empty() || kind.is_empty() || !kind.chars().all(|ch| ch.is_ascii_alphabetic()) {
+        return (ChangelogSection::Other, summary.to_string());
+    }
+    let section = match kind.to_ascii_lowercase().as_str() {
+        "feat" | "feature" => ChangelogSection::Features,
+        "fix" | "bugfix" | "hotfix" => ChangelogSection::Fixes,
+        _ => ChangelogSection::Other,
+    };
+    let mut entry = match scope.filter(|scope| !scope.is_empty()) {
+        Some(scope) => format!("**{scope}:** {text}"),
+        None => text.to_string(),
+    };
+    if breaking {
+        entry.push_str(" (breaking)");
+    }
+    (section, entry)
+}
+
+/// Release notes grouped purely from the commit subjects; merge commits are left out.
+/// Used as-is when the model is unavailable, and as the draft the model polishes.
+pub(crate) fn build_fallback_changelog(
+    commits: &[ChangelogCommit],
+    style: ChangelogStyle,
+    to_ref: Option<&str>,
+) -> String {
+    let mut sections: Vec<(ChangelogSection, Vec<String>)> = ChangelogSection::ALL
+        .iter()
+        .map(|section| (*section, Vec::new()))
+        .collect();
+    for commit in commits {
+        if commit.summary.starts_with("Merge ") {
+            continue;
+        }
+        let (section, entry) = classify_commit(&commit.summary);
+        if let Some((_, entries)) = sections.iter_mut().find(|(s, _)| *s == section) {
+            entries.push(entry);
+        }
+    }
+
+    let (mut output, section_prefix) = match style {
+        ChangelogStyle::KeepAChangelog => {
+            let version = to_ref
+                .map(str::trim)
+                .filter(|to_ref| !to_ref.is_empty() && *to_ref != "HEAD")
+                .unwrap_or("Unreleased");
+            (format!("## [{version}]\n"), "###")
+        }
+        ChangelogStyle::Plain => (String::new(), "##"),
+    };
+    for (section, entries) in sections {
+        if entries.is_empty() {
+            continue;
+        }
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&format!("{section_prefix} {}\n", section.title()));
+        for entry in entries {
+            output.push_str(&format!("- {entry}\n"));
+        }
+    }
+    output
+}
+
+/// The prompt asking the model to polish `draft` using the full commit messages.
+pub(crate) fn build_changelog_prompt(commits: &[ChangelogCommit], draft: &str) -> String {
+    let commits = commits
+        .iter()
+        .map(|commit| {
+            let sha = commit.sha.get(..7).unwrap_or(&commit.sha);
+            let mut line = format!("- {sha} {}", commit.summary);
+            if !commit.body.is_empty() {
+                let body = commit
+                    .body
+                    .chars()
+                    .take(MAX_PROMPT_BODY_CHARS)
+                    .collect::<String>();
+                line.push_str(&format!("\n  {}", body.replace('\n', "\n  ")));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Rewrite the following draft release notes into clear, user-facing Markdown. \
+Keep its headings and the Features, Fixes and Other sections in that order; \
+you may move an entry to a better section, merge duplicates and drop purely internal noise. \
+Use the commit messages below for detail. Only output the Markdown, nothing else.\n\n\
+Draft:\n{draft}\nCommits:\n{commits}"
+    )
+}
+
+/// The model's Markdown without a surrounding code fence; `None` when it is empty.
+pub(crate) fn clean_changelog_reply(reply: &str) -> Option<String> {
+    let mut text = reply.trim();
+    if let Some(rest) = text.strip_prefix("```") {
+        let rest = rest.split_once('\n').map_or("", |(_, rest)| rest);
+        text = rest.trim_end().strip_suffix("```").unwrap_or(rest).trim();
+    }
+    (!text.is_empty()).then(|| format!("{text}\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_changelog_prompt, build_fallback_changelog, clean_changelog_reply, ChangelogStyle,
+    };
+    use crate::git::ChangelogCommit;
+
+    fn commit(summary: &str, body: &str) -> ChangelogCommit {
+        ChangelogCommit {
+            sha: "0123456789abcdef".to_string(),
+            summary: summary.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    fn commits() -> Vec<ChangelogCommit> {
+        vec![
+            commit("feat(git): add changelog generation", "Groups commits."),
+            commit("fix: keep the sidebar scroll position", ""),
+            commit("Merge branch 'main' into feature", ""),
+            commit("docs: describe the settings file", ""),
+            commit("feat!: drop the legacy config format", ""),
+            commit("Update dependencies", ""),
+            commit("fix(scope: not a prefix", ""),
+        ]
+    }
+
+    #[test]
+    fn fallback_groups_commits_by_conventional_prefix() {
+        let changelog =
+            build_fallback_changelog(&commits(), ChangelogStyle::KeepAChangelog, Some("v1.4.0"));
+        assert_eq!(
+            changelog,
+            "## [v1.4.0]\n\n\
+### Features\n\
+- **git:** add changelog generation\n\
+- drop the legacy config format (breaking)\n\n\
+### Fixes\n\
+- keep the sidebar scroll position\n\n\
+### Other\n\
+- describe the settings file\n\
+- Update dependencies\n\
+- fix(scope: not a prefix\n"
+        );
+    }
+
+    #[test]
+    fn plain_style_skips_the_release_heading_and_empty_sections() {
+        let changelog = build_fallback_changelog(
+            &[commit("fix: handle empty ranges", "")],
+            ChangelogStyle::Plain,
+            None,
+        );
+        assert_eq!(changelog, "## Fixes\n- handle empty ranges\n");
+        let unreleased = build_fallback_changelog(
+            &[commit("feat: x", "")],
+            ChangelogStyle::KeepAChangelog,
+            Some("HEAD"),
+        );
+        assert!(unreleased.starts_with("## [Unreleased]\n"));
+    }
+
+    #[test]
+    fn prompt_carries_the_draft_and_commit_bodies() {
+        let commits = commits();
+        let draft = build_fallback_changelog(&commits, ChangelogStyle::Plain, None);
+        let prompt = build_changelog_prompt(&commits, &draft);
+        assert!(prompt.contains(&format!("Draft:\n{draft}")));
+        assert!(prompt.contains("- 0123456 feat(git): add changelog generation\n  Groups commits."));
+    }
+
+    #[test]
+    fn replies_are_unfenced_and_empty_ones_rejected() {
+        assert_eq!(
+            clean_changelog_reply("```markdown\n## Fixes\n- a\n```").as_deref(),
+            Some("## Fixes\n- a\n")
+        );
+        assert_eq!(
+            clean_changelog_reply("## Fixes\n- a").as_deref(),
+            Some("## Fixes\n- a\n")
+        );
+        assert_eq!(clean_changelog_reply("  \n"), None);
+    }
+}
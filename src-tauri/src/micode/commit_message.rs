@@ -0,0 +1,216 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+
+/// Hunks kept per file when an oversized diff is summarized.
+const SUMMARY_HUNKS_PER_FILE: usize = 3;
+
+/// One file of a combined workspace diff, as built by `git::build_combined_diff`.
+struct FileDiff<'a> {
+    path: &'a str,
+    /// The file's patch, without the `=== path ===` header.
+    patch: &'a str,
+}
+
+impl<'a> FileDiff<'a> {
+    /// The `@@` hunks of the patch, each with its lines.
+    fn hunks(&self) -> Vec<&'a str> {
+        let mut starts = Vec::new();
+        let mut offset = 0;
+        for line in self.patch.split_inclusive('\n') {
+            if line.starts_with("@@") {
+                starts.push(offset);
+            }
+            offset += line.len();
+        }
+        starts
+            .iter()
+            .enumerate()
+            .map(|(index, &start)| {
+                let end = starts.get(index + 1).copied().unwrap_or(self.patch.len());
+                &self.patch[start..end]
+            })
+            .collect()
+    }
+
+    /// Added and removed lines across the file's hunks.
+    fn line_counts(&self) -> (usize, usize) {
+        let mut added = 0;
+        let mut removed = 0;
+        for hunk in self.hunks() {
+            for line in hunk.lines().skip(1) {
+                if line.starts_with('+') {
+                    added += 1;
+                } else if line.starts_with('-') {
+                    removed += 1;
+                }
+            }
+        }
+        (added, removed)
+    }
+}
+
+fn split_file_diffs(diff: &str) -> Vec<FileDiff<'_>> {
+    let mut files = Vec::new();
+    let mut current: Option<(&str, usize)> = None;
+    let mut offset = 0;
+    for line in diff.split_inclusive('\n') {
+        let header = line
+            .trim_end()
+            .strip_prefix("=== ")
+            .and_then(|rest| rest.strip_suffix(" ==="));
+        if let Some(path) = header {
+            if let Some((path, start)) = current.take() {
+                files.push(FileDiff {
+                    path,
+                    patch: &diff[start..offset],
+                });
+            }
+            current = Some((path, offset + line.len()));
+        }
+        offset += line.len();
+    }
+    if let Some((path, start)) = current {
+        files.push(FileDiff {
+            path,
+            patch: &diff[start..],
+        });
+    }
+    files
+}
+
+/// Returns `diff` unchanged when it fits in `max_bytes`. Larger diffs are reduced to
+/// one stat line per changed file plus the first hunks of the largest files, headed
+/// by a note on the truncation, staying within `max_bytes` where the file list allows.
+pub(crate) fn summarize_commit_diff(diff: &str, max_bytes: usize) -> Cow<'_, str> {
+    if diff.len() <= max_bytes {
+        return Cow::Borrowed(diff);
+    }
+    let files = split_file_diffs(diff);
+    let mut summary = format!(
+        "[Diff truncated: {} bytes across {} file(s) exceeds the {max_bytes}-byte limit. \
+Every changed file is listed; only the first hunks of the largest files are shown.]\n\n",
+        diff.len(),
+        files.len()
+    );
+    for file in &files {
+        let (added, removed) = file.line_counts();
+        summary.push_str(&format!("{} | +{added} -{removed}\n", file.path));
+    }
+
+    let mut largest = files.iter().collect::<Vec<_>>();
+    largest.sort_by_key(|file| Reverse(file.patch.len()));
+    for file in largest {
+        let header = format!("\n=== {} ===\n", file.path);
+        let mut excerpt = String::new();
+        for hunk in file.hunks().into_iter().take(SUMMARY_HUNKS_PER_FILE) {
+            if summary.len() + header.len() + excerpt.len() + hunk.len() > max_bytes {
+                break;
+            }
+            excerpt.push_str(hunk);
+        }
+        if !excerpt.is_empty() {
+            summary.push_str(&header);
+            summary.push_str(&excerpt);
+        }
+    }
+    Cow::Owned(summary)
+}
+
+/// The commit message prompt for `diff`, summarized when it is over `max_diff_bytes`.
+pub(crate) fn build_commit_message_prompt(
+    diff: &str,
+    stack: Option<&str>,
+    max_diff_bytes: usize,
+) -> String {
+    let stack = stack
+        .map(|stack| {
+            format!(
+                "Project stack: {stack}. Use it to pick the commit type and a fitting scope \
+(e.g., build: for dependency or toolchain changes).\n\n"
+            )
+        })
+        .unwrap_or_default();
+    let diff = summarize_commit_diff(diff, max_diff_bytes);
+    let truncated = if matches!(diff, Cow::Owned(_)) {
+        "The changes are too large to show in full: describe the overall change from the \
+file list and the excerpts.\n\n"
+    } else {
+        ""
+    };
+    format!(
+        "Generate a concise git commit message for the following changes. \
+Follow conventional commit format (e.g., feat:, fix:, refactor:, docs:, etc.). \
+Keep the summary line under 72 characters. \
+Only output the commit message, nothing else.\n\n\
+{stack}{truncated}Changes:\n{diff}"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_commit_message_prompt, summarize_commit_diff};
+
+    fn file_diff(path: &str, hunks: usize, lines_per_hunk: usize) -> String {
+        let mut patch =
+            format!("=== {path} ===\ndiff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n");
+        for hunk in 0..hunks {
+            let start = hunk * 100 + 1;
+            patch.push_str(&format!("@@ -{start},1 +{start},{lines_per_hunk} @@\n"));
+            patch.push_str(&format!("-old line {hunk}\n"));
+            for line in 0..lines_per_hunk {
+                patch.push_str(&format!("+new line {hunk}.{line} in {path}\n"));
+            }
+        }
+        patch
+    }
+
+    fn synthetic_diff() -> (Vec<String>, String) {
+        let paths = (0..40)
+            .map(|index| format!("src/module_{index}/mod.rs"))
+            .collect::<Vec<_>>();
+        let diff = paths
+            .iter()
+            .enumerate()
+            .map(|(index, path)| file_diff(path, 2 + index % 5, 20 + index * 3))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        (paths, diff)
+    }
+
+    #[test]
+    fn small_diffs_are_left_alone() {
+        let diff = file_diff("src/lib.rs", 1, 2);
+        let prompt = build_commit_message_prompt(&diff, None, 10_000);
+        assert!(prompt.ends_with(&format!("Changes:\n{diff}")));
+        assert!(!prompt.contains("too large"));
+    }
+
+    #[test]
+    fn large_diffs_stay_under_the_limit_and_keep_every_filename() {
+        let (paths, diff) = synthetic_diff();
+        let limit = 8_000;
+        assert!(diff.len() > limit * 10);
+
+        let prompt = build_commit_message_prompt(&diff, Some("Rust (cargo)"), limit);
+        let (instructions, changes) = prompt.split_once("Changes:\n").expect("changes");
+        assert!(instructions.contains("too large to show in full"));
+        assert!(changes.len() <= limit, "{} bytes", changes.len());
+        assert!(changes.starts_with("[Diff truncated:"));
+        for path in &paths {
+            assert!(changes.contains(&format!("{path} | +")), "missing {path}");
+        }
+        // The largest file gets an excerpt, starting from its first hunk.
+        let largest = &paths[paths.len() - 1];
+        assert!(changes.contains(&format!("=== {largest} ===\n@@ -1,1 ")));
+        assert!(changes.contains(&format!("+new line 0.0 in {largest}\n")));
+    }
+
+    #[test]
+    fn stat_lines_count_the_changed_lines() {
+        let diff = [file_diff("a.rs", 2, 3), file_diff("b.rs", 1, 1)].join("\n\n");
+        let summary = summarize_commit_diff(&diff, 10);
+        assert!(summary.contains("a.rs | +6 -2\n"));
+        assert!(summary.contains("b.rs | +1 -1\n"));
+        assert!(!summary.contains("@@"));
+    }
+}
@@ -1,12 +1,98 @@
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use crate::types::WorkspaceEntry;
 
+const ISOLATED_HOMES_DIR: &str = "agent-homes";
+
+/// Dedicated home for a workspace running with `isolatedAgentHome`, under the app data
+/// directory `data_dir`.
+pub(crate) fn isolated_agent_home_path(workspace_id: &str, data_dir: &Path) -> Option<PathBuf> {
+    let id = workspace_id.trim();
+    if id.is_empty() || id.contains(['/', '\\']) || id == "." || id == ".." {
+        return None;
+    }
+    Some(data_dir.join(ISOLATED_HOMES_DIR).join(id))
+}
+
+/// Whether `path` is one workspace's home directly inside the isolated homes root of
+/// `data_dir`; the root itself is not.
+pub(crate) fn is_isolated_agent_home(path: &Path, data_dir: &Path) -> bool {
+    let root = data_dir.join(ISOLATED_HOMES_DIR);
+    path.file_name().is_some() && path.parent() == Some(root.as_path())
+}
+
+/// Isolated home for `entry`, if it or (for worktrees) its parent opted in. Worktrees
+/// share the parent's home so auth and model choices carry over.
+pub(crate) fn isolated_workspace_home(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+    data_dir: &Path,
+) -> Option<PathBuf> {
+    if entry.settings.isolated_agent_home {
+        return isolated_agent_home_path(&entry.id, data_dir);
+    }
+    if entry.kind.is_worktree() {
+        let parent = parent_entry?;
+        if parent.settings.isolated_agent_home {
+            return isolated_agent_home_path(&parent.id, data_dir);
+        }
+    }
+    None
+}
+
+/// Creates `home` with a minimal `settings.json`, seeded from `template` when given.
+/// An existing settings file is kept so model choices survive restarts.
+pub(crate) fn prepare_isolated_agent_home(
+    home: &Path,
+    template: Option<&str>,
+) -> Result<(), String> {
+    fs::create_dir_all(home).map_err(|err| format!("Failed to create agent home: {err}"))?;
+    let settings_path = home.join("settings.json");
+    if settings_path.exists() {
+        return Ok(());
+    }
+    let contents = match template.map(str::trim).filter(|value| !value.is_empty()) {
+        Some(template) => {
+            let template_path =
+                normalize_micode_home(template).unwrap_or_else(|| PathBuf::from(template));
+            let raw = fs::read_to_string(&template_path).map_err(|err| {
+                format!(
+                    "Failed to read agent home template {}: {err}",
+                    template_path.display()
+                )
+            })?;
+            serde_json::from_str::<serde_json::Value>(&raw)
+                .map_err(|err| format!("Agent home template is not valid JSON: {err}"))?;
+            raw
+        }
+        None => "{}\n".to_string(),
+    };
+    fs::write(&settings_path, contents)
+        .map_err(|err| format!("Failed to write agent home settings: {err}"))
+}
+
+/// Deletes an isolated home. The isolated homes root and paths outside it are refused.
+pub(crate) fn remove_isolated_agent_home(home: &Path, data_dir: &Path) -> Result<(), String> {
+    if !is_isolated_agent_home(home, data_dir) {
+        return Err("Refusing to delete a non-isolated agent home.".to_string());
+    }
+    match fs::remove_dir_all(home) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(format!("Failed to delete agent home: {err}")),
+    }
+}
+
 pub(crate) fn resolve_workspace_micode_home(
     entry: &WorkspaceEntry,
     parent_entry: Option<&WorkspaceEntry>,
+    data_dir: &Path,
 ) -> Option<PathBuf> {
+    if let Some(path) = isolated_workspace_home(entry, parent_entry, data_dir) {
+        return Some(path);
+    }
     if let Some(value) = entry.settings.agent_home.as_ref() {
         let base = PathBuf::from(&entry.path);
         if let Some(path) = normalize_micode_home_with_base(value, &base) {
@@ -242,6 +328,7 @@ mod tests {
                 agent_home: agent_home.map(|value| value.to_string()),
                 ..WorkspaceSettings::default()
             },
+            stack: None,
         }
     }
 
@@ -250,7 +337,7 @@ mod tests {
         let parent = workspace_entry(WorkspaceKind::Main, "/repo", Some("/tmp/micode-parent"));
         let child = workspace_entry(WorkspaceKind::Worktree, "/repo/worktree", None);
 
-        let resolved = resolve_workspace_micode_home(&child, Some(&parent));
+        let resolved = resolve_workspace_micode_home(&child, Some(&parent), Path::new("/data"));
 
         assert_eq!(resolved, Some(PathBuf::from("/tmp/micode-parent")));
     }
@@ -259,7 +346,7 @@ mod tests {
     fn workspace_micode_home_relative_resolves_against_workspace_path() {
         let entry = workspace_entry(WorkspaceKind::Main, "/repo", Some(".micode"));
 
-        let resolved = resolve_workspace_micode_home(&entry, None);
+        let resolved = resolve_workspace_micode_home(&entry, None, Path::new("/data"));
 
         assert_eq!(resolved, Some(PathBuf::from("/repo/.micode")));
     }
@@ -269,11 +356,53 @@ mod tests {
         let parent = workspace_entry(WorkspaceKind::Main, "/repo", Some(".micode"));
         let child = workspace_entry(WorkspaceKind::Worktree, "/repo/worktree", None);
 
-        let resolved = resolve_workspace_micode_home(&child, Some(&parent));
+        let resolved = resolve_workspace_micode_home(&child, Some(&parent), Path::new("/data"));
 
         assert_eq!(resolved, Some(PathBuf::from("/repo/.micode")));
     }
 
+    #[test]
+    fn isolated_home_overrides_agent_home_and_is_shared_with_worktrees() {
+        let root = std::env::temp_dir().join("micode-isolated-home-test");
+        let _ = std::fs::remove_dir_all(&root);
+        let homes_root = root.join(ISOLATED_HOMES_DIR);
+
+        let mut parent = workspace_entry(WorkspaceKind::Main, "/repo", Some("/tmp/micode-parent"));
+        parent.settings.isolated_agent_home = true;
+        let mut child = workspace_entry(WorkspaceKind::Worktree, "/repo/worktree", None);
+        child.id = "child-id".to_string();
+
+        let expected = homes_root.join("workspace-id");
+        assert_eq!(
+            resolve_workspace_micode_home(&parent, None, &root),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            resolve_workspace_micode_home(&child, Some(&parent), &root),
+            Some(expected.clone())
+        );
+        assert!(is_isolated_agent_home(&expected, &root));
+        assert!(!is_isolated_agent_home(
+            Path::new("/tmp/micode-parent"),
+            &root
+        ));
+        assert!(!is_isolated_agent_home(&homes_root, &root));
+        assert!(!is_isolated_agent_home(&expected.join(".."), &root));
+
+        prepare_isolated_agent_home(&expected, None).expect("prepare home");
+        let settings = expected.join("settings.json");
+        assert_eq!(std::fs::read_to_string(&settings).unwrap().trim(), "{}");
+        std::fs::write(&settings, r#"{"model":{"preferredModel":"demo"}}"#).unwrap();
+        prepare_isolated_agent_home(&expected, None).expect("prepare again");
+        assert!(std::fs::read_to_string(&settings).unwrap().contains("demo"));
+
+        assert!(remove_isolated_agent_home(Path::new("/tmp/micode-parent"), &root).is_err());
+        assert!(remove_isolated_agent_home(&homes_root, &root).is_err());
+        remove_isolated_agent_home(&expected, &root).expect("remove home");
+        assert!(!expected.exists());
+        let _ = std::fs::remove_dir_all(root);
+    }
+
     #[test]
     fn micode_home_expands_tilde_and_env_vars() {
         let _guard = ENV_LOCK.lock().expect("lock env");
@@ -331,7 +460,7 @@ mod tests {
         std::env::set_var("HOME", home_root.to_string_lossy().to_string());
 
         let entry = workspace_entry(WorkspaceKind::Main, &repo.to_string_lossy(), None);
-        let resolved = resolve_workspace_micode_home(&entry, None);
+        let resolved = resolve_workspace_micode_home(&entry, None, Path::new("/data"));
         assert_eq!(resolved, Some(global));
 
         match prev_home {
@@ -359,7 +488,7 @@ mod tests {
         std::env::set_var("HOME", home_root.to_string_lossy().to_string());
 
         let entry = workspace_entry(WorkspaceKind::Main, &repo.to_string_lossy(), None);
-        let resolved = resolve_workspace_micode_home(&entry, None);
+        let resolved = resolve_workspace_micode_home(&entry, None, Path::new("/data"));
         assert_eq!(resolved, Some(global));
 
         match prev_home {
@@ -1,28 +1,59 @@
 use serde_json::{json, Map, Value};
 use std::io::ErrorKind;
 use std::path::PathBuf;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 
-use tauri::{AppHandle, Emitter, State};
-use tokio::sync::mpsc;
-use tokio::time::{timeout, Instant};
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::timeout;
 
 pub(crate) mod args;
+pub(crate) mod background_reply;
+pub(crate) mod changelog;
+pub(crate) mod commit_message;
 pub(crate) mod config;
 pub(crate) mod home;
+pub(crate) mod pr_description;
 
+use self::background_reply::{
+    collect_background_agent_text, collect_background_reply, mark_background_turn_done,
+    BackgroundReplyTimeouts,
+};
+use self::changelog::{
+    build_changelog_prompt, build_fallback_changelog, clean_changelog_reply, ChangelogStyle,
+};
+use self::commit_message::build_commit_message_prompt;
+use self::pr_description::{build_pr_description_prompt, parse_pr_description, PrDescription};
 pub(crate) use crate::backend::app_server::WorkspaceSession;
 use crate::backend::app_server::{
-    build_micode_path_env, check_acp_handshake, check_micode_installation,
-    spawn_workspace_session as spawn_workspace_session_inner,
+    build_micode_path_env, check_acp_handshake, check_micode_installation, handshake_key, now_ms,
+    spawn_workspace_session as spawn_workspace_session_inner, SessionLaunch, SessionServices,
+    SessionSettings,
 };
+use crate::backend::auto_run::AutoRunRequest;
+use crate::backend::event_methods;
 use crate::backend::events::AppServerEvent;
+use crate::backend::handshake_cache::{HandshakeProbe, HandshakeSource};
+use crate::backend::prompt_text::{clean_generated_title, thread_title_prompt};
+use crate::backend::review_context::ReviewContextOptions;
+use crate::backend::thread_export::{deliver_thread_export, ThreadExport, ThreadExportFormat};
+use crate::backend::thread_references::{
+    parse_thread_references, summary_prompt, truncate_summary, validate_thread_references,
+    ThreadReference,
+};
 use crate::event_sink::TauriEventSink;
+use crate::event_subscriptions::emit_routed_event;
 use crate::remote_backend;
-use crate::shared::{micode_core, workspaces_core};
+use crate::rules::RuleDecision;
 use crate::shared::process_core::tokio_command;
+use crate::shared::run_kickoff_core::{build_run_kickoff_message_core, RunKickoffMessage};
+use crate::shared::thread_ownership_core::THREAD_WATCHING_ERROR;
+use crate::shared::workspace_stack_core::{get_workspace_stack_core, stack_prompt_context};
+use crate::shared::{auto_run_core, micode_core, workspaces_core};
 use crate::state::AppState;
+use crate::types::errors::{CommandError, ErrorCode};
 use crate::types::WorkspaceEntry;
 
 pub(crate) async fn spawn_workspace_session(
@@ -31,70 +62,34 @@ pub(crate) async fn spawn_workspace_session(
     agent_args: Option<String>,
     app_handle: AppHandle,
     agent_home: Option<PathBuf>,
-) -> Result<Arc<WorkspaceSession>, String> {
+) -> Result<Arc<WorkspaceSession>, CommandError> {
     let client_version = app_handle.package_info().version.to_string();
-    let event_sink = TauriEventSink::new(app_handle);
-    spawn_workspace_session_inner(
+    let state = app_handle.state::<AppState>();
+    let session_settings = SessionSettings::from_app_settings(&*state.app_settings.lock().await);
+    let event_sink = TauriEventSink::new(app_handle.clone());
+    let launch = SessionLaunch {
         entry,
         default_micode_bin,
         agent_args,
         agent_home,
+        session_settings,
         client_version,
-        event_sink,
-    )
-    .await
-}
-
-fn is_workspace_not_connected_error(error: &str) -> bool {
-    error
-        .to_ascii_lowercase()
-        .contains("workspace not connected")
-}
-
-async fn collect_background_agent_text(
-    rx: &mut mpsc::UnboundedReceiver<Value>,
-    idle_timeout: Duration,
-    max_wait: Duration,
-) -> Result<String, String> {
-    let started_at = Instant::now();
-    let mut output = String::new();
-    while started_at.elapsed() < max_wait {
-        match timeout(idle_timeout, rx.recv()).await {
-            Ok(Some(event)) => {
-                let method = event.get("method").and_then(|m| m.as_str()).unwrap_or("");
-                match method {
-                    "item/agentMessage/delta" => {
-                        if let Some(delta) = event
-                            .get("params")
-                            .and_then(|params| params.get("delta"))
-                            .and_then(|d| d.as_str())
-                        {
-                            output.push_str(delta);
-                        }
-                    }
-                    "turn/error" => {
-                        let error_msg = event
-                            .get("params")
-                            .and_then(|p| p.get("error"))
-                            .and_then(|e| e.as_str())
-                            .unwrap_or("Unknown background generation error");
-                        return Err(error_msg.to_string());
-                    }
-                    _ => {}
-                }
-            }
-            Ok(None) => break,
-            Err(_) => break,
-        }
-    }
-    Ok(output)
+    };
+    let services = SessionServices {
+        data_dir: &state.data_dir,
+        connect_queue: &state.connect_queue,
+        connection_states: &state.connection_states,
+        auto_runs: &state.auto_runs,
+        settings_parse_errors: &state.settings_parse_errors,
+    };
+    spawn_workspace_session_inner(launch, services, event_sink).await
 }
 
 async fn ensure_workspace_session_connected(
     state: &AppState,
     workspace_id: &str,
     app: &AppHandle,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let has_session = { state.sessions.lock().await.contains_key(workspace_id) };
     if has_session {
         return Ok(());
@@ -105,6 +100,7 @@ async fn ensure_workspace_session_connected(
         &state.workspaces,
         &state.sessions,
         &state.app_settings,
+        &state.data_dir,
         move |entry, default_bin, agent_args, agent_home| {
             spawn_workspace_session(
                 entry,
@@ -116,6 +112,7 @@ async fn ensure_workspace_session_connected(
         },
     )
     .await
+    .map(|_| ())
 }
 
 fn mcp_status_has_entries(value: &Value) -> bool {
@@ -127,25 +124,84 @@ fn mcp_status_has_entries(value: &Value) -> bool {
         .unwrap_or(false)
 }
 
+/// Answers the doctor's handshake check without spawning when possible: first from a live
+/// session launched from the same, unchanged binary, then from a recent probe of it.
+async fn cached_acp_handshake(
+    state: &AppState,
+    agent_bin: Option<String>,
+    force_recheck: bool,
+) -> Result<(HandshakeProbe, HandshakeSource), String> {
+    let key = handshake_key(agent_bin.as_deref(), None);
+    if !force_recheck {
+        let sessions: Vec<Arc<WorkspaceSession>> =
+            state.sessions.lock().await.values().cloned().collect();
+        let from_session = sessions
+            .iter()
+            .find_map(|session| session.recorded_handshake(&key));
+        if let Some(probe) = from_session {
+            return Ok((probe, HandshakeSource::Session));
+        }
+        if let Some(probe) = state.handshake_cache.lock().await.get(&key, now_ms()) {
+            return Ok((probe, HandshakeSource::Cache));
+        }
+    }
+    let probe = check_acp_handshake(agent_bin, None).await?;
+    state
+        .handshake_cache
+        .lock()
+        .await
+        .insert(key, probe.clone());
+    Ok((probe, HandshakeSource::Probe))
+}
+
 #[tauri::command]
 pub(crate) async fn micode_doctor(
     micode_bin: Option<String>,
-    _micode_args: Option<String>,
+    micode_args: Option<String>,
+    force_recheck: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<Value, String> {
-    let default_bin = {
-        let settings = state.app_settings.lock().await;
-        settings.agent_bin.clone()
-    };
+) -> Result<Value, CommandError> {
+    let mut probe_settings = state.app_settings.lock().await.clone();
     let resolved = micode_bin
         .clone()
         .filter(|value| !value.trim().is_empty())
-        .or(default_bin);
+        .or(probe_settings.agent_bin.clone());
+    probe_settings.agent_bin = resolved.clone();
+    if micode_args.is_some() {
+        probe_settings.agent_args = micode_args;
+    }
+    args::parse_micode_args(probe_settings.agent_args.as_deref())?;
+    // Running sessions launched with a different binary/args keep using the old config
+    // until they are restarted.
+    let stale_workspace_ids = workspaces_core::find_stale_sessions_core(
+        &state.workspaces,
+        &state.sessions,
+        &probe_settings,
+    )
+    .await;
+    let isolated_agent_homes = {
+        let workspaces = state.workspaces.lock().await;
+        let mut homes = workspaces
+            .values()
+            .filter_map(|entry| {
+                let parent = entry
+                    .parent_id
+                    .as_ref()
+                    .and_then(|parent_id| workspaces.get(parent_id));
+                let home = home::isolated_workspace_home(entry, parent, &state.data_dir)?;
+                Some(json!({ "workspaceId": entry.id, "agentHome": home }))
+            })
+            .collect::<Vec<_>>();
+        homes.sort_by(|a, b| a["workspaceId"].as_str().cmp(&b["workspaceId"].as_str()));
+        homes
+    };
     let path_env = build_micode_path_env(resolved.as_deref());
     let version = check_micode_installation(resolved.clone()).await?;
     // Doctor should validate baseline ACP availability first.
     // Additional runtime args can be valid for real sessions but still break handshake probes.
-    let app_server_ok = check_acp_handshake(resolved.clone(), None).await?;
+    let (handshake, handshake_source) =
+        cached_acp_handshake(&state, resolved.clone(), force_recheck.unwrap_or(false)).await?;
+    let app_server_ok = handshake.ok;
     let (node_ok, node_version, node_details) = {
         let mut node_command = tokio_command("node");
         if let Some(ref path_env) = path_env {
@@ -217,19 +273,26 @@ pub(crate) async fn micode_doctor(
         "micodeBin": resolved,
         "version": version,
         "appServerOk": app_server_ok,
+        "handshakeLatencyMs": handshake.latency_ms,
+        "protocolVersion": handshake.protocol_version,
+        "handshakeSource": handshake_source,
+        "handshakeCheckedAt": handshake.checked_at_ms,
         "details": details,
         "path": path_env,
         "nodeOk": node_ok,
         "nodeVersion": node_version,
         "nodeDetails": node_details,
+        "configMismatch": !stale_workspace_ids.is_empty(),
+        "staleWorkspaceIds": stale_workspace_ids,
+        "isolatedAgentHomes": isolated_agent_homes,
     }))
 }
 
 #[tauri::command]
-pub(crate) async fn micode_install_windows() -> Result<Value, String> {
+pub(crate) async fn micode_install_windows() -> Result<Value, CommandError> {
     #[cfg(not(target_os = "windows"))]
     {
-        return Err("MiCode auto-install is only supported on Windows.".to_string());
+        return Err("MiCode auto-install is only supported on Windows.".into());
     }
 
     #[cfg(target_os = "windows")]
@@ -277,7 +340,7 @@ pub(crate) async fn start_thread(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -285,13 +348,14 @@ pub(crate) async fn start_thread(
             "start_thread",
             json!({ "workspaceId": workspace_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
     let result = micode_core::start_thread_core(&state.sessions, workspace_id.clone()).await;
     match result {
         Ok(value) => Ok(value),
-        Err(error) if is_workspace_not_connected_error(&error) => {
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
             ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
             micode_core::start_thread_core(&state.sessions, workspace_id).await
         }
@@ -305,7 +369,7 @@ pub(crate) async fn resume_thread(
     thread_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -313,15 +377,25 @@ pub(crate) async fn resume_thread(
             "resume_thread",
             json!({ "workspaceId": workspace_id, "threadId": thread_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
-    let result =
-        micode_core::resume_thread_core(&state.sessions, workspace_id.clone(), thread_id.clone())
-            .await;
+    let result = state
+        .command_timings
+        .timed(
+            "resume_thread",
+            Some(&workspace_id),
+            micode_core::resume_thread_core(
+                &state.sessions,
+                workspace_id.clone(),
+                thread_id.clone(),
+            ),
+        )
+        .await;
     match result {
         Ok(value) => Ok(value),
-        Err(error) if is_workspace_not_connected_error(&error) => {
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
             ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
             micode_core::resume_thread_core(&state.sessions, workspace_id, thread_id).await
         }
@@ -329,13 +403,83 @@ pub(crate) async fn resume_thread(
     }
 }
 
+#[tauri::command]
+pub(crate) async fn watch_thread(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if !remote_backend::is_remote_mode(&*state).await {
+        return Err("Thread watch mode is only available in remote mode".into());
+    }
+    let response = remote_backend::call_remote(
+        &*state,
+        app,
+        "watch_thread",
+        json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+    )
+    .await?;
+    state
+        .watched_threads
+        .lock()
+        .await
+        .insert((workspace_id, thread_id));
+    Ok(response)
+}
+
+#[tauri::command]
+pub(crate) async fn take_over_thread(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if !remote_backend::is_remote_mode(&*state).await {
+        return Err("Thread watch mode is only available in remote mode".into());
+    }
+    let response = remote_backend::call_remote(
+        &*state,
+        app,
+        "take_over_thread",
+        json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+    )
+    .await?;
+    state
+        .watched_threads
+        .lock()
+        .await
+        .remove(&(workspace_id, thread_id));
+    Ok(response)
+}
+
+#[tauri::command]
+pub(crate) async fn get_thread_ownership(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if !remote_backend::is_remote_mode(&*state).await {
+        return Ok(json!({ "clientId": null, "ownership": null }));
+    }
+    remote_backend::call_remote(
+        &*state,
+        app,
+        "get_thread_ownership",
+        json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+    )
+    .await
+    .map_err(CommandError::from)
+}
+
 #[tauri::command]
 pub(crate) async fn fork_thread(
     workspace_id: String,
     thread_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -343,7 +487,8 @@ pub(crate) async fn fork_thread(
             "fork_thread",
             json!({ "workspaceId": workspace_id, "threadId": thread_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
     let result =
@@ -351,7 +496,7 @@ pub(crate) async fn fork_thread(
             .await;
     match result {
         Ok(value) => Ok(value),
-        Err(error) if is_workspace_not_connected_error(&error) => {
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
             ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
             micode_core::fork_thread_core(&state.sessions, workspace_id, thread_id).await
         }
@@ -366,7 +511,7 @@ pub(crate) async fn list_threads(
     limit: Option<u32>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -374,19 +519,26 @@ pub(crate) async fn list_threads(
             "list_threads",
             json!({ "workspaceId": workspace_id, "cursor": cursor, "limit": limit }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
-    let result = micode_core::list_threads_core(
-        &state.sessions,
-        workspace_id.clone(),
-        cursor.clone(),
-        limit,
-    )
-    .await;
+    let result = state
+        .command_timings
+        .timed(
+            "list_threads",
+            Some(&workspace_id),
+            micode_core::list_threads_core(
+                &state.sessions,
+                workspace_id.clone(),
+                cursor.clone(),
+                limit,
+            ),
+        )
+        .await;
     match result {
         Ok(value) => Ok(value),
-        Err(error) if is_workspace_not_connected_error(&error) => {
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
             ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
             micode_core::list_threads_core(&state.sessions, workspace_id, cursor, limit).await
         }
@@ -401,7 +553,7 @@ pub(crate) async fn list_mcp_server_status(
     limit: Option<u32>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -409,7 +561,8 @@ pub(crate) async fn list_mcp_server_status(
             "list_mcp_server_status",
             json!({ "workspaceId": workspace_id, "cursor": cursor, "limit": limit }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
     let result = micode_core::list_mcp_server_status_core(
@@ -426,13 +579,14 @@ pub(crate) async fn list_mcp_server_status(
             } else {
                 micode_core::list_mcp_server_status_from_settings_core(
                     &state.workspaces,
+                    &state.data_dir,
                     workspace_id,
                 )
                 .await
                 .or(Ok(value))
             }
         }
-        Err(error) if is_workspace_not_connected_error(&error) => {
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
             ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
             let retried = micode_core::list_mcp_server_status_core(
                 &state.sessions,
@@ -448,230 +602,263 @@ pub(crate) async fn list_mcp_server_status(
                     } else {
                         micode_core::list_mcp_server_status_from_settings_core(
                             &state.workspaces,
+                            &state.data_dir,
                             workspace_id,
                         )
                         .await
                         .or(Ok(value))
                     }
                 }
-                Err(_) => micode_core::list_mcp_server_status_from_settings_core(
-                    &state.workspaces,
-                    workspace_id,
-                )
-                .await,
+                Err(_) => {
+                    micode_core::list_mcp_server_status_from_settings_core(
+                        &state.workspaces,
+                        &state.data_dir,
+                        workspace_id,
+                    )
+                    .await
+                }
             }
         }
         Err(_) => {
-            micode_core::list_mcp_server_status_from_settings_core(&state.workspaces, workspace_id)
-                .await
+            micode_core::list_mcp_server_status_from_settings_core(
+                &state.workspaces,
+                &state.data_dir,
+                workspace_id,
+            )
+            .await
         }
     }
 }
 
+async fn edit_mcp_server(
+    method: &str,
+    edit: micode_core::McpServerEdit,
+    workspace_id: String,
+    name: String,
+    config: Option<Value>,
+    state: &AppState,
+    app: AppHandle,
+) -> Result<(), String> {
+    if remote_backend::is_remote_mode(state).await {
+        remote_backend::call_remote(
+            state,
+            app,
+            method,
+            json!({ "workspaceId": workspace_id, "name": name, "config": config }),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let event = micode_core::edit_mcp_server_core(
+        &state.workspaces,
+        &state.data_dir,
+        &state.settings_parse_errors,
+        workspace_id,
+        edit,
+        name,
+        config,
+    )
+    .await?;
+    emit_routed_event(&app, event);
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) async fn mcp_server_add(
+    workspace_id: String,
+    name: String,
+    config: Value,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    edit_mcp_server(
+        "mcp_server_add",
+        micode_core::McpServerEdit::Add,
+        workspace_id,
+        name,
+        Some(config),
+        &state,
+        app,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn mcp_server_update(
+    workspace_id: String,
+    name: String,
+    config: Value,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    edit_mcp_server(
+        "mcp_server_update",
+        micode_core::McpServerEdit::Update,
+        workspace_id,
+        name,
+        Some(config),
+        &state,
+        app,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn mcp_server_remove(
+    workspace_id: String,
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    edit_mcp_server(
+        "mcp_server_remove",
+        micode_core::McpServerEdit::Remove,
+        workspace_id,
+        name,
+        None,
+        &state,
+        app,
+    )
+    .await
+}
+
 #[tauri::command]
 pub(crate) async fn archive_thread(
     workspace_id: String,
     thread_id: String,
+    force: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
+    let force = force.unwrap_or(false);
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
             app,
             "archive_thread",
-            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "force": force }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
     let result = micode_core::archive_thread_core(
         &state.sessions,
         workspace_id.clone(),
         thread_id.clone(),
+        force,
     )
     .await;
     match result {
         Ok(value) => Ok(value),
-        Err(error) if is_workspace_not_connected_error(&error) => {
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
             ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
-            micode_core::archive_thread_core(&state.sessions, workspace_id, thread_id).await
+            micode_core::archive_thread_core(&state.sessions, workspace_id, thread_id, force).await
         }
         Err(error) => Err(error),
     }
 }
 
 #[tauri::command]
-pub(crate) async fn compact_thread(
+pub(crate) async fn list_archived_threads(
     workspace_id: String,
-    thread_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
             app,
-            "compact_thread",
-            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+            "list_archived_threads",
+            json!({ "workspaceId": workspace_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
-    let result = micode_core::compact_thread_core(
-        &state.sessions,
-        workspace_id.clone(),
-        thread_id.clone(),
-    )
-    .await;
+    let result =
+        micode_core::list_archived_threads_core(&state.sessions, workspace_id.clone()).await;
     match result {
         Ok(value) => Ok(value),
-        Err(error) if is_workspace_not_connected_error(&error) => {
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
             ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
-            micode_core::compact_thread_core(&state.sessions, workspace_id, thread_id).await
+            micode_core::list_archived_threads_core(&state.sessions, workspace_id).await
         }
         Err(error) => Err(error),
     }
 }
 
 #[tauri::command]
-pub(crate) async fn set_thread_name(
+pub(crate) async fn restore_thread(
     workspace_id: String,
     thread_id: String,
-    name: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
             app,
-            "set_thread_name",
-            json!({ "workspaceId": workspace_id, "threadId": thread_id, "name": name }),
+            "restore_thread",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
-    let result = micode_core::set_thread_name_core(
+    let result = micode_core::restore_thread_core(
         &state.sessions,
         workspace_id.clone(),
         thread_id.clone(),
-        name.clone(),
     )
     .await;
     match result {
         Ok(value) => Ok(value),
-        Err(error) if is_workspace_not_connected_error(&error) => {
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
             ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
-            micode_core::set_thread_name_core(&state.sessions, workspace_id, thread_id, name)
-                .await
+            micode_core::restore_thread_core(&state.sessions, workspace_id, thread_id).await
         }
         Err(error) => Err(error),
     }
 }
 
 #[tauri::command]
-pub(crate) async fn send_user_message(
+pub(crate) async fn delete_thread_permanently(
     workspace_id: String,
     thread_id: String,
-    text: String,
-    model: Option<String>,
-    effort: Option<String>,
-    access_mode: Option<String>,
-    images: Option<Vec<String>>,
-    collaboration_mode: Option<Value>,
+    force: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
+    let force = force.unwrap_or(false);
     if remote_backend::is_remote_mode(&*state).await {
-        let images = images.map(|paths| {
-            paths
-                .into_iter()
-                .map(remote_backend::normalize_path_for_remote)
-                .collect::<Vec<_>>()
-        });
-        let mut payload = Map::new();
-        payload.insert("workspaceId".to_string(), json!(workspace_id));
-        payload.insert("threadId".to_string(), json!(thread_id));
-        payload.insert("text".to_string(), json!(text));
-        payload.insert("model".to_string(), json!(model));
-        payload.insert("effort".to_string(), json!(effort));
-        payload.insert("accessMode".to_string(), json!(access_mode));
-        payload.insert("images".to_string(), json!(images));
-        if let Some(mode) = collaboration_mode {
-            if !mode.is_null() {
-                payload.insert("collaborationMode".to_string(), mode);
-            }
-        }
         return remote_backend::call_remote(
             &*state,
             app,
-            "send_user_message",
-            Value::Object(payload),
+            "delete_thread_permanently",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "force": force }),
         )
-        .await;
-    }
-
-    let requested_model = model
-        .as_ref()
-        .map(String::as_str)
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .map(ToString::to_string);
-    if let Some(requested_model) = requested_model {
-        let model_changed = crate::backend::app_server::set_preferred_model(&requested_model)?;
-        if model_changed {
-            if let Some(previous_session) = state.sessions.lock().await.remove(&workspace_id) {
-                previous_session.invalidate_all_thread_sessions().await;
-                let mut child = previous_session.child.lock().await;
-                let _ = child.kill().await;
-            }
-            let app_for_spawn = app.clone();
-            workspaces_core::connect_workspace_core(
-                workspace_id.clone(),
-                &state.workspaces,
-                &state.sessions,
-                &state.app_settings,
-                move |entry, default_bin, agent_args, agent_home| {
-                    spawn_workspace_session(
-                        entry,
-                        default_bin,
-                        agent_args,
-                        app_for_spawn.clone(),
-                        agent_home,
-                    )
-                },
-            )
-            .await?;
-        }
+        .await
+        .map_err(CommandError::from);
     }
 
-    let result = micode_core::send_user_message_core(
+    let result = micode_core::delete_thread_permanently_core(
         &state.sessions,
         workspace_id.clone(),
         thread_id.clone(),
-        text.clone(),
-        model.clone(),
-        effort.clone(),
-        access_mode.clone(),
-        images.clone(),
-        collaboration_mode.clone(),
+        force,
     )
     .await;
     match result {
         Ok(value) => Ok(value),
-        Err(error) if is_workspace_not_connected_error(&error) => {
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
             ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
-            micode_core::send_user_message_core(
+            micode_core::delete_thread_permanently_core(
                 &state.sessions,
                 workspace_id,
                 thread_id,
-                text,
-                model,
-                effort,
-                access_mode,
-                images,
-                collaboration_mode,
+                force,
             )
             .await
         }
@@ -680,22 +867,1014 @@ pub(crate) async fn send_user_message(
 }
 
 #[tauri::command]
-pub(crate) async fn collaboration_mode_list(
+pub(crate) async fn compact_thread(
     workspace_id: String,
+    thread_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
             app,
-            "collaboration_mode_list",
-            json!({ "workspaceId": workspace_id }),
+            "compact_thread",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
-    micode_core::collaboration_mode_list_core(&state.sessions, workspace_id).await
+    let result = micode_core::compact_thread_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::compact_thread_core(&state.sessions, workspace_id, thread_id).await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn set_thread_name(
+    workspace_id: String,
+    thread_id: String,
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "set_thread_name",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "name": name }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::set_thread_name_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+        name.clone(),
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::set_thread_name_core(&state.sessions, workspace_id, thread_id, name).await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn set_thread_tags(
+    workspace_id: String,
+    thread_id: String,
+    tags: Vec<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "set_thread_tags",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "tags": tags }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::set_thread_tags_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+        tags.clone(),
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::set_thread_tags_core(&state.sessions, workspace_id, thread_id, tags).await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn set_thread_pinned(
+    workspace_id: String,
+    thread_id: String,
+    pinned: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "set_thread_pinned",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "pinned": pinned }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::set_thread_pinned_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+        pinned,
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::set_thread_pinned_core(&state.sessions, workspace_id, thread_id, pinned)
+                .await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Case-insensitive search over the workspace's saved threads: names, messages, reasoning
+/// and tool output. Hits carry a ~200 character snippet around the match.
+#[tauri::command]
+pub(crate) async fn search_threads(
+    workspace_id: String,
+    query: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "search_threads",
+            json!({ "workspaceId": workspace_id, "query": query, "limit": limit }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    micode_core::search_threads_core(&state.workspaces, workspace_id, query, limit).await
+}
+
+/// Exports a saved thread. In remote mode the daemon renders it and the file, if any, is
+/// written on this machine.
+#[tauri::command]
+pub(crate) async fn export_thread(
+    workspace_id: String,
+    thread_id: String,
+    format: String,
+    path: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ThreadExport, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "export_thread",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "format": format }),
+        )
+        .await
+        .map_err(CommandError::from)?;
+        let export: ThreadExport =
+            serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()))?;
+        let format = ThreadExportFormat::parse(&export.format).map_err(CommandError::from)?;
+        return deliver_thread_export(export.content.unwrap_or_default(), format, path.as_deref())
+            .map_err(CommandError::from);
+    }
+
+    micode_core::export_thread_core(&state.workspaces, workspace_id, thread_id, format, path).await
+}
+
+#[tauri::command]
+pub(crate) async fn start_auto_run(
+    workspace_id: String,
+    request: AutoRunRequest,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let mut params = serde_json::to_value(&request).map_err(|err| err.to_string())?;
+        params["workspaceId"] = json!(workspace_id);
+        return remote_backend::call_remote(&state, app, "start_auto_run", params)
+            .await
+            .map_err(CommandError::from);
+    }
+
+    ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+    auto_run_core::start_auto_run_core(&state.sessions, &state.auto_runs, workspace_id, request)
+        .await
+}
+
+#[tauri::command]
+pub(crate) async fn cancel_auto_run(
+    workspace_id: String,
+    run_id: String,
+    interrupt: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "cancel_auto_run",
+            json!({ "workspaceId": workspace_id, "runId": run_id, "interrupt": interrupt }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    auto_run_core::cancel_auto_run_core(
+        &state.sessions,
+        &state.auto_runs,
+        workspace_id,
+        run_id,
+        interrupt.unwrap_or(false),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn list_auto_runs(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "list_auto_runs",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+    auto_run_core::list_auto_runs_core(&state.sessions, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn export_review_sarif(
+    workspace_id: String,
+    thread_id: String,
+    item_id: Option<String>,
+    output_path: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "export_review_sarif",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "itemId": item_id,
+                "outputPath": output_path,
+            }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::export_review_sarif_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+        item_id.clone(),
+        output_path.clone(),
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::export_review_sarif_core(
+                &state.sessions,
+                workspace_id,
+                thread_id,
+                item_id,
+                output_path,
+            )
+            .await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn mark_thread_seen(
+    workspace_id: String,
+    thread_id: String,
+    item_seq: Option<u64>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "mark_thread_seen",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "itemSeq": item_seq }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::mark_thread_seen_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+        item_seq,
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::mark_thread_seen_core(&state.sessions, workspace_id, thread_id, item_seq)
+                .await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn get_unseen_items(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "get_unseen_items",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::get_unseen_items_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::get_unseen_items_core(&state.sessions, workspace_id, thread_id).await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn check_thread_sync(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "check_thread_sync",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::check_thread_sync_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::check_thread_sync_core(&state.sessions, workspace_id, thread_id).await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn sync_thread_from_cli(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "sync_thread_from_cli",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::sync_thread_from_cli_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::sync_thread_from_cli_core(&state.sessions, workspace_id, thread_id).await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn copy_thread_range(
+    workspace_id: String,
+    thread_id: String,
+    start_item_id: Option<String>,
+    end_item_id: Option<String>,
+    format: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "copy_thread_range",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "startItemId": start_item_id,
+                "endItemId": end_item_id,
+                "format": format,
+            }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::copy_thread_range_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+        start_item_id.clone(),
+        end_item_id.clone(),
+        format.clone(),
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::copy_thread_range_core(
+                &state.sessions,
+                workspace_id,
+                thread_id,
+                start_item_id,
+                end_item_id,
+                format,
+            )
+            .await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn list_item_annotations(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "list_item_annotations",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::list_item_annotations_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::list_item_annotations_core(&state.sessions, workspace_id, thread_id).await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn add_item_annotation(
+    workspace_id: String,
+    thread_id: String,
+    item_id: String,
+    text: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    let author = state.actor_id.clone();
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "add_item_annotation",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "itemId": item_id,
+                "text": text,
+                "author": author,
+            }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::add_item_annotation_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+        item_id.clone(),
+        text.clone(),
+        author.clone(),
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::add_item_annotation_core(
+                &state.sessions,
+                workspace_id,
+                thread_id,
+                item_id,
+                text,
+                author,
+            )
+            .await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn update_item_annotation(
+    workspace_id: String,
+    thread_id: String,
+    annotation_id: String,
+    text: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "update_item_annotation",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "annotationId": annotation_id,
+                "text": text,
+            }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::update_item_annotation_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+        annotation_id.clone(),
+        text.clone(),
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::update_item_annotation_core(
+                &state.sessions,
+                workspace_id,
+                thread_id,
+                annotation_id,
+                text,
+            )
+            .await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn delete_item_annotation(
+    workspace_id: String,
+    thread_id: String,
+    annotation_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "delete_item_annotation",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "annotationId": annotation_id,
+            }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let result = micode_core::delete_item_annotation_core(
+        &state.sessions,
+        workspace_id.clone(),
+        thread_id.clone(),
+        annotation_id.clone(),
+    )
+    .await;
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::delete_item_annotation_core(
+                &state.sessions,
+                workspace_id,
+                thread_id,
+                annotation_id,
+            )
+            .await
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn send_user_message(
+    workspace_id: String,
+    thread_id: String,
+    text: String,
+    model: Option<String>,
+    effort: Option<String>,
+    access_mode: Option<String>,
+    images: Option<Vec<String>>,
+    collaboration_mode: Option<Value>,
+    raw_text: Option<bool>,
+    sampling_params: Option<Value>,
+    skip_redaction: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        if state
+            .watched_threads
+            .lock()
+            .await
+            .contains(&(workspace_id.clone(), thread_id.clone()))
+        {
+            return Err(THREAD_WATCHING_ERROR.into());
+        }
+        let images = images.map(|paths| {
+            paths
+                .into_iter()
+                .map(remote_backend::normalize_path_for_remote)
+                .collect::<Vec<_>>()
+        });
+        let mut payload = Map::new();
+        payload.insert("workspaceId".to_string(), json!(workspace_id));
+        payload.insert("threadId".to_string(), json!(thread_id));
+        payload.insert("text".to_string(), json!(text));
+        payload.insert("model".to_string(), json!(model));
+        payload.insert("effort".to_string(), json!(effort));
+        payload.insert("accessMode".to_string(), json!(access_mode));
+        payload.insert("images".to_string(), json!(images));
+        if let Some(mode) = collaboration_mode {
+            if !mode.is_null() {
+                payload.insert("collaborationMode".to_string(), mode);
+            }
+        }
+        if raw_text.unwrap_or(false) {
+            payload.insert("rawText".to_string(), json!(true));
+        }
+        if let Some(sampling_params) = sampling_params.filter(|value| !value.is_null()) {
+            payload.insert("samplingParams".to_string(), sampling_params);
+        }
+        if skip_redaction.unwrap_or(false) {
+            payload.insert("skipRedaction".to_string(), json!(true));
+        }
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "send_user_message",
+            Value::Object(payload),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let sampling_params = micode_core::resolve_sampling_params_core(
+        &state.workspaces,
+        &state.data_dir,
+        &state.settings_parse_errors,
+        &state.app_settings,
+        &workspace_id,
+        model.as_deref(),
+        sampling_params.as_ref(),
+    )
+    .await?;
+    // Each workspace keeps its own model; switching this workspace's model restarts its
+    // agent with the new `--model`.
+    if let Some(settings) = micode_core::preferred_model_update_core(
+        &state.workspaces,
+        &state.data_dir,
+        &state.settings_parse_errors,
+        &workspace_id,
+        model.as_deref(),
+    )
+    .await
+    {
+        crate::workspaces::update_workspace_settings(
+            workspace_id.clone(),
+            settings,
+            state.clone(),
+            app.clone(),
+        )
+        .await?;
+    }
+
+    let thread_references =
+        resolve_thread_references(&state, &app, &workspace_id, &thread_id, &text).await?;
+    let queue_when_busy = state.app_settings.lock().await.queue_when_busy;
+    let result = state
+        .command_timings
+        .timed(
+            "send_user_message",
+            Some(&workspace_id),
+            micode_core::send_user_message_core(
+                &state.sessions,
+                workspace_id.clone(),
+                thread_id.clone(),
+                text.clone(),
+                model.clone(),
+                effort.clone(),
+                access_mode.clone(),
+                images.clone(),
+                collaboration_mode.clone(),
+                raw_text,
+                sampling_params.clone(),
+                skip_redaction,
+                thread_references.clone(),
+                queue_when_busy,
+            ),
+        )
+        .await;
+    let result = match result {
+        Ok(value) => Ok(value),
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
+            ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+            micode_core::send_user_message_core(
+                &state.sessions,
+                workspace_id.clone(),
+                thread_id.clone(),
+                text,
+                model,
+                effort,
+                access_mode,
+                images,
+                collaboration_mode,
+                raw_text,
+                sampling_params,
+                skip_redaction,
+                thread_references,
+                queue_when_busy,
+            )
+            .await
+        }
+        Err(error) => Err(error),
+    };
+    if result.is_ok() {
+        spawn_thread_auto_title(&state, &app, &workspace_id, &thread_id).await;
+    }
+    result
+}
+
+/// Runs one of the slash commands the agent last advertised on a thread, as a normal turn.
+#[tauri::command]
+pub(crate) async fn run_slash_command(
+    workspace_id: String,
+    thread_id: String,
+    command_name: String,
+    args: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&state).await {
+        return remote_backend::call_remote(
+            &state,
+            app,
+            "run_slash_command",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "commandName": command_name,
+                "args": args,
+            }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+    let queue_when_busy = state.app_settings.lock().await.queue_when_busy;
+    state
+        .command_timings
+        .timed(
+            "run_slash_command",
+            Some(&workspace_id),
+            micode_core::run_slash_command_core(
+                &state.sessions,
+                workspace_id.clone(),
+                thread_id,
+                command_name,
+                args,
+                queue_when_busy,
+            ),
+        )
+        .await
+}
+
+/// With `autoTitleThreads` on, names a thread whose first turn just finished on a hidden
+/// background thread. Any failure keeps the title cut from the first prompt.
+async fn spawn_thread_auto_title(
+    state: &AppState,
+    app: &AppHandle,
+    workspace_id: &str,
+    thread_id: &str,
+) {
+    let timeouts = {
+        let settings = state.app_settings.lock().await;
+        if !settings.auto_title_threads {
+            return;
+        }
+        BackgroundReplyTimeouts::from_settings(&settings)
+    };
+    let Some(session) = state.sessions.lock().await.get(workspace_id).cloned() else {
+        return;
+    };
+    let Some(prompt) = session.claim_auto_title(thread_id).await else {
+        return;
+    };
+    let app = app.clone();
+    let workspace_id = workspace_id.to_string();
+    let thread_id = thread_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let (_cancel_tx, cancel_rx) = oneshot::channel();
+        let reply = run_background_prompt(
+            &session,
+            &app,
+            &workspace_id,
+            thread_title_prompt(&prompt),
+            "threadTitle",
+            timeouts,
+            cancel_rx,
+        )
+        .await;
+        let Some(title) = reply.ok().and_then(|reply| clean_generated_title(&reply)) else {
+            return;
+        };
+        let params = json!({ "threadId": thread_id, "name": title, "_generated": true });
+        let _ = session.send_request("thread/name/set", params).await;
+    });
+}
+
+/// Resolves the `#thread:<id>` references of a message, reusing cached summaries and
+/// generating missing ones on hidden background threads.
+async fn resolve_thread_references(
+    state: &AppState,
+    app: &AppHandle,
+    workspace_id: &str,
+    thread_id: &str,
+    text: &str,
+) -> Result<Vec<ThreadReference>, CommandError> {
+    let ids = parse_thread_references(text);
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    validate_thread_references(&ids, thread_id)?;
+    ensure_workspace_session_connected(state, workspace_id, app).await?;
+    let session = state
+        .sessions
+        .lock()
+        .await
+        .get(workspace_id)
+        .cloned()
+        .ok_or_else(CommandError::workspace_not_connected)?;
+    let timeouts = BackgroundReplyTimeouts::from_settings(&*state.app_settings.lock().await);
+    let mut references = Vec::new();
+    for id in ids {
+        let source = session.thread_reference_source(&id).await?;
+        let summary = match source.cached_summary {
+            Some(summary) => summary,
+            None => {
+                let (_cancel_tx, cancel_rx) = oneshot::channel();
+                let generated = run_background_prompt(
+                    &session,
+                    app,
+                    workspace_id,
+                    summary_prompt(&source.title, &source.transcript),
+                    "threadSummary",
+                    timeouts,
+                    cancel_rx,
+                )
+                .await
+                .map_err(|error| format!("Failed to summarize thread `{id}`: {error}"))?;
+                let summary = truncate_summary(&generated);
+                if summary.is_empty() {
+                    return Err(format!("Failed to summarize thread `{id}`: empty summary.").into());
+                }
+                session
+                    .cache_thread_summary(&id, source.item_seq, &summary)
+                    .await;
+                summary
+            }
+        };
+        references.push(ThreadReference {
+            thread_id: id,
+            title: source.title,
+            item_seq: source.item_seq,
+            summary,
+        });
+    }
+    Ok(references)
+}
+
+#[tauri::command]
+pub(crate) async fn collaboration_mode_list(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "collaboration_mode_list",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    micode_core::collaboration_mode_list_core(&state.sessions, workspace_id).await
 }
 
 #[tauri::command]
@@ -705,7 +1884,7 @@ pub(crate) async fn turn_interrupt(
     turn_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -713,7 +1892,8 @@ pub(crate) async fn turn_interrupt(
             "turn_interrupt",
             json!({ "workspaceId": workspace_id, "threadId": thread_id, "turnId": turn_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
     let result = micode_core::turn_interrupt_core(
@@ -725,7 +1905,7 @@ pub(crate) async fn turn_interrupt(
     .await;
     match result {
         Ok(value) => Ok(value),
-        Err(error) if is_workspace_not_connected_error(&error) => {
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
             ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
             micode_core::turn_interrupt_core(&state.sessions, workspace_id, thread_id, turn_id)
                 .await
@@ -734,15 +1914,58 @@ pub(crate) async fn turn_interrupt(
     }
 }
 
+#[tauri::command]
+pub(crate) async fn cancel_tool_call(
+    workspace_id: String,
+    tool_call_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "cancel_tool_call",
+            json!({ "workspaceId": workspace_id, "toolCallId": tool_call_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    micode_core::cancel_tool_call_core(&state.sessions, workspace_id, tool_call_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn get_turn_live_changes(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "get_turn_live_changes",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    micode_core::get_turn_live_changes_core(&state.sessions, workspace_id, thread_id).await
+}
+
 #[tauri::command]
 pub(crate) async fn start_review(
     workspace_id: String,
     thread_id: String,
     target: Value,
     delivery: Option<String>,
+    context: Option<ReviewContextOptions>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -753,31 +1976,44 @@ pub(crate) async fn start_review(
                 "threadId": thread_id,
                 "target": target,
                 "delivery": delivery,
+                "context": context,
             }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
-    micode_core::start_review_core(&state.sessions, workspace_id, thread_id, target, delivery).await
+    micode_core::start_review_core(
+        &state.sessions,
+        workspace_id,
+        thread_id,
+        target,
+        delivery,
+        context,
+    )
+    .await
 }
 
 #[tauri::command]
 pub(crate) async fn model_list(
     workspace_id: String,
+    refresh: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
+    let refresh = refresh.unwrap_or(false);
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
             app,
             "model_list",
-            json!({ "workspaceId": workspace_id }),
+            json!({ "workspaceId": workspace_id, "refresh": refresh }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
-    micode_core::model_list_core(&state.sessions, workspace_id).await
+    micode_core::model_list_core(&state.sessions, workspace_id, refresh).await
 }
 
 #[tauri::command]
@@ -785,7 +2021,7 @@ pub(crate) async fn account_rate_limits(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -793,7 +2029,8 @@ pub(crate) async fn account_rate_limits(
             "account_rate_limits",
             json!({ "workspaceId": workspace_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
     micode_core::account_rate_limits_core(&state.sessions, workspace_id).await
@@ -804,7 +2041,7 @@ pub(crate) async fn account_read(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -812,10 +2049,17 @@ pub(crate) async fn account_read(
             "account_read",
             json!({ "workspaceId": workspace_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
-    micode_core::account_read_core(&state.sessions, &state.workspaces, workspace_id).await
+    micode_core::account_read_core(
+        &state.sessions,
+        &state.workspaces,
+        &state.data_dir,
+        workspace_id,
+    )
+    .await
 }
 
 #[tauri::command]
@@ -823,7 +2067,7 @@ pub(crate) async fn micode_login(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -831,7 +2075,8 @@ pub(crate) async fn micode_login(
             "micode_login",
             json!({ "workspaceId": workspace_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
     micode_core::micode_login_core(&state.sessions, &state.micode_login_cancels, workspace_id).await
@@ -842,7 +2087,7 @@ pub(crate) async fn micode_login_cancel(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -850,7 +2095,8 @@ pub(crate) async fn micode_login_cancel(
             "micode_login_cancel",
             json!({ "workspaceId": workspace_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
     micode_core::micode_login_cancel_core(
@@ -866,7 +2112,7 @@ pub(crate) async fn skills_list(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -874,13 +2120,14 @@ pub(crate) async fn skills_list(
             "skills_list",
             json!({ "workspaceId": workspace_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
     let result = micode_core::skills_list_core(&state.sessions, workspace_id.clone()).await;
     match result {
         Ok(value) => Ok(value),
-        Err(error) if is_workspace_not_connected_error(&error) => {
+        Err(error) if error.code == ErrorCode::WorkspaceNotConnected => {
             ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
             micode_core::skills_list_core(&state.sessions, workspace_id).await
         }
@@ -895,7 +2142,7 @@ pub(crate) async fn apps_list(
     limit: Option<u32>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -903,7 +2150,8 @@ pub(crate) async fn apps_list(
             "apps_list",
             json!({ "workspaceId": workspace_id, "cursor": cursor, "limit": limit }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
     micode_core::apps_list_core(&state.sessions, workspace_id, cursor, limit).await
@@ -916,30 +2164,36 @@ pub(crate) async fn respond_to_server_request(
     result: Value,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         remote_backend::call_remote(
             &*state,
-            app,
+            app.clone(),
             "respond_to_server_request",
             json!({ "workspaceId": workspace_id, "requestId": request_id, "result": result }),
         )
         .await?;
+        crate::blocking::resolve_approval(&app, &workspace_id, &request_id);
         return Ok(());
     }
 
-    micode_core::respond_to_server_request_core(&state.sessions, workspace_id, request_id, result)
-        .await
+    micode_core::respond_to_server_request_core(
+        &state.sessions,
+        workspace_id.clone(),
+        request_id.clone(),
+        result,
+    )
+    .await?;
+    crate::blocking::resolve_approval(&app, &workspace_id, &request_id);
+    Ok(())
 }
 
-fn build_commit_message_prompt(diff: &str) -> String {
-    format!(
-        "Generate a concise git commit message for the following changes. \
-Follow conventional commit format (e.g., feat:, fix:, refactor:, docs:, etc.). \
-Keep the summary line under 72 characters. \
-Only output the commit message, nothing else.\n\n\
-Changes:\n{diff}"
-    )
+/// Stack summary of a workspace for generation prompts; `None` for unknown stacks.
+async fn workspace_stack_context(state: &AppState, workspace_id: &str) -> Option<String> {
+    let stack = get_workspace_stack_core(&state.workspaces, workspace_id, false)
+        .await
+        .ok()?;
+    stack_prompt_context(&stack)
 }
 
 /// Gets the diff content for commit message generation
@@ -947,15 +2201,17 @@ Changes:\n{diff}"
 pub(crate) async fn get_commit_message_prompt(
     workspace_id: String,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<String, CommandError> {
     // Get the diff from git
     let diff = crate::git::get_workspace_diff(&workspace_id, &state).await?;
 
     if diff.trim().is_empty() {
-        return Err("No changes to generate commit message for".to_string());
+        return Err("No changes to generate commit message for".into());
     }
 
-    let prompt = build_commit_message_prompt(&diff);
+    let stack = workspace_stack_context(&state, &workspace_id).await;
+    let max_diff_bytes = state.app_settings.lock().await.commit_message_diff_max_bytes;
+    let prompt = build_commit_message_prompt(&diff, stack.as_deref(), max_diff_bytes);
 
     Ok(prompt)
 }
@@ -964,20 +2220,29 @@ pub(crate) async fn get_commit_message_prompt(
 pub(crate) async fn remember_approval_rule(
     workspace_id: String,
     command: Vec<String>,
+    decision: Option<RuleDecision>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
             app,
             "remember_approval_rule",
-            json!({ "workspaceId": workspace_id, "command": command }),
+            json!({ "workspaceId": workspace_id, "command": command, "decision": decision }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
-    micode_core::remember_approval_rule_core(&state.workspaces, workspace_id, command).await
+    micode_core::remember_approval_rule_core(
+        &state.workspaces,
+        &state.data_dir,
+        workspace_id,
+        command,
+        decision.unwrap_or_default(),
+    )
+    .await
 }
 
 #[tauri::command]
@@ -985,38 +2250,181 @@ pub(crate) async fn list_approval_rules(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "list_approval_rules",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    micode_core::list_approval_rules_core(&state.workspaces, &state.data_dir, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn remove_approval_rule(
+    workspace_id: String,
+    command: Vec<String>,
+    decision: Option<RuleDecision>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
-            &*state,
+            &*state,
+            app,
+            "remove_approval_rule",
+            json!({ "workspaceId": workspace_id, "command": command, "decision": decision }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    micode_core::remove_approval_rule_core(
+        &state.workspaces,
+        &state.data_dir,
+        workspace_id,
+        command,
+        decision.unwrap_or_default(),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn approval_rules_list(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&state).await {
+        return remote_backend::call_remote(
+            &state,
+            app,
+            "approval_rules_list",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    micode_core::approval_rules_list_core(&state.workspaces, &state.data_dir, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn approval_rules_delete(
+    workspace_id: String,
+    rule_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&state).await {
+        return remote_backend::call_remote(
+            &state,
+            app,
+            "approval_rules_delete",
+            json!({ "workspaceId": workspace_id, "ruleId": rule_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    micode_core::approval_rules_delete_core(
+        &state.workspaces,
+        &state.data_dir,
+        workspace_id,
+        rule_id,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn approval_rules_update(
+    workspace_id: String,
+    rule_id: String,
+    pattern: Option<Vec<String>>,
+    decision: Option<RuleDecision>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&state).await {
+        return remote_backend::call_remote(
+            &state,
+            app,
+            "approval_rules_update",
+            json!({
+                "workspaceId": workspace_id,
+                "ruleId": rule_id,
+                "pattern": pattern,
+                "decision": decision,
+            }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    micode_core::approval_rules_update_core(
+        &state.workspaces,
+        &state.data_dir,
+        workspace_id,
+        rule_id,
+        pattern,
+        decision,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn approval_rules_export(
+    workspace_id: String,
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&state).await {
+        return remote_backend::call_remote(
+            &state,
             app,
-            "list_approval_rules",
-            json!({ "workspaceId": workspace_id }),
+            "approval_rules_export",
+            json!({
+                "workspaceId": workspace_id,
+                "path": remote_backend::normalize_path_for_remote(path),
+            }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
-    micode_core::list_approval_rules_core(&state.workspaces, workspace_id).await
+    micode_core::approval_rules_export_core(&state.workspaces, &state.data_dir, workspace_id, path)
+        .await
 }
 
 #[tauri::command]
-pub(crate) async fn remove_approval_rule(
+pub(crate) async fn approval_rules_import(
     workspace_id: String,
-    command: Vec<String>,
+    path: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
-    if remote_backend::is_remote_mode(&*state).await {
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&state).await {
         return remote_backend::call_remote(
-            &*state,
+            &state,
             app,
-            "remove_approval_rule",
-            json!({ "workspaceId": workspace_id, "command": command }),
+            "approval_rules_import",
+            json!({
+                "workspaceId": workspace_id,
+                "path": remote_backend::normalize_path_for_remote(path),
+            }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
-    micode_core::remove_approval_rule_core(&state.workspaces, workspace_id, command).await
+    micode_core::approval_rules_import_core(&state.workspaces, &state.data_dir, workspace_id, path)
+        .await
 }
 
 #[tauri::command]
@@ -1024,7 +2432,7 @@ pub(crate) async fn get_config_model(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -1032,10 +2440,11 @@ pub(crate) async fn get_config_model(
             "get_config_model",
             json!({ "workspaceId": workspace_id }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
-    micode_core::get_config_model_core(&state.workspaces, workspace_id).await
+    micode_core::get_config_model_core(&state.workspaces, &state.data_dir, workspace_id).await
 }
 
 /// Generates a commit message in the background without showing in the main chat
@@ -1044,22 +2453,24 @@ pub(crate) async fn generate_commit_message(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<String, String> {
+) -> Result<String, CommandError> {
     // Get the diff from git
     let diff = crate::git::get_workspace_diff(&workspace_id, &state).await?;
 
     if diff.trim().is_empty() {
-        return Err("No changes to generate commit message for".to_string());
+        return Err("No changes to generate commit message for".into());
     }
 
-    let prompt = build_commit_message_prompt(&diff);
+    let stack = workspace_stack_context(&state, &workspace_id).await;
+    let max_diff_bytes = state.app_settings.lock().await.commit_message_diff_max_bytes;
+    let prompt = build_commit_message_prompt(&diff, stack.as_deref(), max_diff_bytes);
 
     // Get the session
     let session = {
         let sessions = state.sessions.lock().await;
         sessions
             .get(&workspace_id)
-            .ok_or("workspace not connected")?
+            .ok_or_else(CommandError::workspace_not_connected)?
             .clone()
     };
 
@@ -1067,7 +2478,8 @@ pub(crate) async fn generate_commit_message(
     let thread_params = json!({
         "cwd": session.entry.path,
         "approvalPolicy": "never",  // Never ask for approval in background
-        "_background": true
+        "_background": true,
+        "_primer": session.entry.settings.context_priming
     });
     let thread_result = session.send_request("thread/start", thread_params).await?;
 
@@ -1077,7 +2489,7 @@ pub(crate) async fn generate_commit_message(
             .get("message")
             .and_then(|m| m.as_str())
             .unwrap_or("Unknown error starting thread");
-        return Err(error_msg.to_string());
+        return Err(error_msg.into());
     }
 
     // Extract threadId - try multiple paths since response format may vary
@@ -1102,12 +2514,12 @@ pub(crate) async fn generate_commit_message(
         .to_string();
 
     // Hide background helper threads from the sidebar, even if a thread/started event leaked.
-    let _ = app.emit(
-        "app-server-event",
+    emit_routed_event(
+        &app,
         AppServerEvent {
             workspace_id: workspace_id.clone(),
             message: json!({
-                "method": "micode/backgroundThread",
+                "method": event_methods::MICODE_BACKGROUND_THREAD,
                 "params": {
                     "threadId": thread_id,
                     "action": "hide"
@@ -1118,6 +2530,7 @@ pub(crate) async fn generate_commit_message(
 
     // Create channel for receiving events
     let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let done_tx = tx.clone();
 
     // Register callback for this thread
     {
@@ -1132,60 +2545,249 @@ pub(crate) async fn generate_commit_message(
         "cwd": session.entry.path,
         "approvalPolicy": "never",
         "sandboxPolicy": { "type": "readOnly" },
-        "_background": true
+        "_background": true,
+        "_purpose": "commitMessage"
     });
-    let turn_result = session.send_request("turn/start", turn_params).await;
-    let turn_result = match turn_result {
-        Ok(result) => result,
-        Err(error) => {
-            // Clean up if turn fails to start
-            {
-                let mut callbacks = session.background_thread_callbacks.lock().await;
-                callbacks.remove(&thread_id);
-            }
-            let archive_params = json!({ "threadId": thread_id.as_str() });
-            let _ = session.send_request("thread/archive", archive_params).await;
-            return Err(error);
-        }
+    let timeouts = BackgroundReplyTimeouts::from_settings(&*state.app_settings.lock().await);
+    let commit_message = collect_background_reply(&mut rx, &done_tx, timeouts, || {
+        start_background_turn(&session, &thread_id, turn_params.clone())
+    })
+    .await;
+
+    // Unregister callback
+    {
+        let mut callbacks = session.background_thread_callbacks.lock().await;
+        callbacks.remove(&thread_id);
+    }
+
+    // Archive the thread to clean up
+    let archive_params = json!({ "threadId": thread_id });
+    let _ = session.send_request("thread/archive", archive_params).await;
+
+    let trimmed = commit_message?.trim().to_string();
+    if trimmed.is_empty() {
+        return Err("No commit message was generated".into());
+    }
+
+    Ok(trimmed)
+}
+
+/// Generates a pull request title and body for the current branch in the background
+#[tauri::command]
+pub(crate) async fn generate_pr_description(
+    workspace_id: String,
+    base: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<PrDescription, CommandError> {
+    let branch_diff = crate::git::get_branch_diff(&workspace_id, base.as_deref(), &state).await?;
+    let stack = workspace_stack_context(&state, &workspace_id).await;
+    let prompt = build_pr_description_prompt(&branch_diff, stack.as_deref())?;
+
+    let session = {
+        let sessions = state.sessions.lock().await;
+        sessions
+            .get(&workspace_id)
+            .ok_or_else(CommandError::workspace_not_connected)?
+            .clone()
     };
 
-    if let Some(error) = turn_result.get("error") {
+    let thread_params = json!({
+        "cwd": session.entry.path,
+        "approvalPolicy": "never",
+        "_background": true,
+        "_primer": session.entry.settings.context_priming
+    });
+    let thread_result = session.send_request("thread/start", thread_params).await?;
+    if let Some(error) = thread_result.get("error") {
         let error_msg = error
             .get("message")
             .and_then(|m| m.as_str())
-            .unwrap_or("Unknown error starting turn");
-        {
-            let mut callbacks = session.background_thread_callbacks.lock().await;
-            callbacks.remove(&thread_id);
-        }
-        let archive_params = json!({ "threadId": thread_id.as_str() });
-        let _ = session.send_request("thread/archive", archive_params).await;
-        return Err(error_msg.to_string());
+            .unwrap_or("Unknown error starting thread");
+        return Err(error_msg.into());
     }
+    let thread_id = thread_result
+        .get("result")
+        .and_then(|r| r.get("threadId"))
+        .or_else(|| {
+            thread_result
+                .get("result")
+                .and_then(|r| r.get("thread"))
+                .and_then(|t| t.get("id"))
+        })
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| {
+            format!(
+                "Failed to get threadId from thread/start response: {:?}",
+                thread_result
+            )
+        })?
+        .to_string();
 
-    let commit_message = collect_background_agent_text(
-        &mut rx,
-        Duration::from_millis(200),
-        Duration::from_secs(3),
-    )
-    .await?;
+    emit_routed_event(
+        &app,
+        AppServerEvent {
+            workspace_id: workspace_id.clone(),
+            message: json!({
+                "method": event_methods::MICODE_BACKGROUND_THREAD,
+                "params": {
+                    "threadId": thread_id,
+                    "action": "hide"
+                }
+            }),
+        },
+    );
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let done_tx = tx.clone();
+    {
+        let mut callbacks = session.background_thread_callbacks.lock().await;
+        callbacks.insert(thread_id.clone(), tx);
+    }
+
+    let turn_params = json!({
+        "threadId": thread_id,
+        "input": [{ "type": "text", "text": prompt }],
+        "cwd": session.entry.path,
+        "approvalPolicy": "never",
+        "sandboxPolicy": { "type": "readOnly" },
+        "_background": true,
+        "_purpose": "prDescription"
+    });
+    let timeouts = BackgroundReplyTimeouts::from_settings(&*state.app_settings.lock().await);
+    let reply = collect_background_reply(&mut rx, &done_tx, timeouts, || {
+        start_background_turn(&session, &thread_id, turn_params.clone())
+    })
+    .await;
 
-    // Unregister callback
     {
         let mut callbacks = session.background_thread_callbacks.lock().await;
         callbacks.remove(&thread_id);
     }
 
-    // Archive the thread to clean up
     let archive_params = json!({ "threadId": thread_id });
     let _ = session.send_request("thread/archive", archive_params).await;
 
-    let trimmed = commit_message.trim().to_string();
-    if trimmed.is_empty() {
-        return Err("No commit message was generated".to_string());
+    let reply = reply?;
+    if reply.trim().is_empty() {
+        return Err("No PR description was generated".into());
+    }
+    parse_pr_description(&reply).map_err(CommandError::from)
+}
+
+/// Generates release notes for the commits between `from_ref` and `to_ref`. The notes
+/// are grouped by conventional-commit prefix first; the agent only polishes that draft,
+/// which is returned unchanged when the workspace is offline or the agent fails.
+#[tauri::command]
+pub(crate) async fn generate_changelog(
+    workspace_id: String,
+    from_ref: Option<String>,
+    to_ref: Option<String>,
+    style: Option<ChangelogStyle>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<String, CommandError> {
+    let commits = crate::git::get_changelog_commits(
+        &workspace_id,
+        from_ref.as_deref(),
+        to_ref.as_deref(),
+        &state,
+    )
+    .await?;
+    if commits.is_empty() {
+        return Err("No commits in the selected range".into());
     }
+    let draft = build_fallback_changelog(&commits, style.unwrap_or_default(), to_ref.as_deref());
 
-    Ok(trimmed)
+    let session = state.sessions.lock().await.get(&workspace_id).cloned();
+    let Some(session) = session else {
+        return Ok(draft);
+    };
+    let timeouts = BackgroundReplyTimeouts::from_settings(&*state.app_settings.lock().await);
+    let (_cancel_tx, cancel_rx) = oneshot::channel();
+    let reply = run_background_prompt(
+        &session,
+        &app,
+        &workspace_id,
+        build_changelog_prompt(&commits, &draft),
+        "changelog",
+        timeouts,
+        cancel_rx,
+    )
+    .await;
+    Ok(reply
+        .ok()
+        .and_then(|reply| clean_changelog_reply(&reply))
+        .unwrap_or(draft))
+}
+
+/// Renders the run kickoff template for `task` so the composer can be prefilled.
+#[tauri::command]
+pub(crate) async fn build_run_kickoff_message(
+    workspace_id: String,
+    task: String,
+    branch: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<RunKickoffMessage, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "build_run_kickoff_message",
+            json!({ "workspaceId": workspace_id, "task": task, "branch": branch }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
+    }
+
+    build_run_kickoff_message_core(
+        &state.workspaces,
+        &state.app_settings,
+        &workspace_id,
+        &task,
+        branch,
+    )
+    .await
+    .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub(crate) async fn start_run(
+    workspace_id: String,
+    task: String,
+    branch: Option<String>,
+    send_kickoff: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "start_run",
+            json!({
+                "workspaceId": workspace_id,
+                "task": task,
+                "branch": branch,
+                "sendKickoff": send_kickoff
+            }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    ensure_workspace_session_connected(&state, &workspace_id, &app).await?;
+    micode_core::start_run_core(
+        &state.sessions,
+        &state.workspaces,
+        &state.app_settings,
+        workspace_id,
+        task,
+        branch,
+        send_kickoff.unwrap_or(true),
+    )
+    .await
 }
 
 #[tauri::command]
@@ -1194,7 +2796,7 @@ pub(crate) async fn generate_run_metadata(
     prompt: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -1202,22 +2804,32 @@ pub(crate) async fn generate_run_metadata(
             "generate_run_metadata",
             json!({ "workspaceId": workspace_id, "prompt": prompt }),
         )
-        .await;
+        .await
+        .map_err(CommandError::from);
     }
 
     let cleaned_prompt = prompt.trim();
     if cleaned_prompt.is_empty() {
-        return Err("Prompt is required.".to_string());
+        return Err("Prompt is required.".into());
     }
 
     let session = {
         let sessions = state.sessions.lock().await;
         sessions
             .get(&workspace_id)
-            .ok_or("workspace not connected")?
+            .ok_or_else(CommandError::workspace_not_connected)?
             .clone()
     };
 
+    let stack = workspace_stack_context(&state, &workspace_id)
+        .await
+        .map(|stack| {
+            format!(
+                "Workspace stack: {stack}\n\
+Use it for precise titles and prefixes (e.g. build/ for toolchain or dependency work).\n\n"
+            )
+        })
+        .unwrap_or_default();
     let title_prompt = format!(
         "You create concise run metadata for a coding task.\n\
 Return ONLY a JSON object with keys:\n\
@@ -1235,13 +2847,14 @@ Examples:\n\
 {{\"title\":\"Update Lint Config\",\"worktreeName\":\"chore/update-lint-config\"}}\n\
 {{\"title\":\"Add Coverage Tests\",\"worktreeName\":\"test/add-coverage-tests\"}}\n\
 \n\
-Task:\n{cleaned_prompt}"
+{stack}Task:\n{cleaned_prompt}"
     );
 
     let thread_params = json!({
         "cwd": session.entry.path,
         "approvalPolicy": "never",
-        "_background": true
+        "_background": true,
+        "_primer": session.entry.settings.context_priming
     });
     let thread_result = session.send_request("thread/start", thread_params).await?;
 
@@ -1250,7 +2863,7 @@ Task:\n{cleaned_prompt}"
             .get("message")
             .and_then(|m| m.as_str())
             .unwrap_or("Unknown error starting thread");
-        return Err(error_msg.to_string());
+        return Err(error_msg.into());
     }
 
     let thread_id = thread_result
@@ -1274,12 +2887,12 @@ Task:\n{cleaned_prompt}"
         .to_string();
 
     // Hide background helper threads from the sidebar, even if a thread/started event leaked.
-    let _ = app.emit(
-        "app-server-event",
+    emit_routed_event(
+        &app,
         AppServerEvent {
             workspace_id: workspace_id.clone(),
             message: json!({
-                "method": "micode/backgroundThread",
+                "method": event_methods::MICODE_BACKGROUND_THREAD,
                 "params": {
                     "threadId": thread_id,
                     "action": "hide"
@@ -1289,6 +2902,7 @@ Task:\n{cleaned_prompt}"
     );
 
     let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let done_tx = tx.clone();
     {
         let mut callbacks = session.background_thread_callbacks.lock().await;
         callbacks.insert(thread_id.clone(), tx);
@@ -1300,42 +2914,14 @@ Task:\n{cleaned_prompt}"
         "cwd": session.entry.path,
         "approvalPolicy": "never",
         "sandboxPolicy": { "type": "readOnly" },
-        "_background": true
+        "_background": true,
+        "_purpose": "runMetadata"
     });
-    let turn_result = session.send_request("turn/start", turn_params).await;
-    let turn_result = match turn_result {
-        Ok(result) => result,
-        Err(error) => {
-            {
-                let mut callbacks = session.background_thread_callbacks.lock().await;
-                callbacks.remove(&thread_id);
-            }
-            let archive_params = json!({ "threadId": thread_id.as_str() });
-            let _ = session.send_request("thread/archive", archive_params).await;
-            return Err(error);
-        }
-    };
-
-    if let Some(error) = turn_result.get("error") {
-        let error_msg = error
-            .get("message")
-            .and_then(|m| m.as_str())
-            .unwrap_or("Unknown error starting turn");
-        {
-            let mut callbacks = session.background_thread_callbacks.lock().await;
-            callbacks.remove(&thread_id);
-        }
-        let archive_params = json!({ "threadId": thread_id.as_str() });
-        let _ = session.send_request("thread/archive", archive_params).await;
-        return Err(error_msg.to_string());
-    }
-
-    let response_text = collect_background_agent_text(
-        &mut rx,
-        Duration::from_millis(200),
-        Duration::from_secs(3),
-    )
-    .await?;
+    let timeouts = BackgroundReplyTimeouts::from_settings(&*state.app_settings.lock().await);
+    let response_text = collect_background_reply(&mut rx, &done_tx, timeouts, || {
+        start_background_turn(&session, &thread_id, turn_params.clone())
+    })
+    .await;
 
     {
         let mut callbacks = session.background_thread_callbacks.lock().await;
@@ -1345,9 +2931,10 @@ Task:\n{cleaned_prompt}"
     let archive_params = json!({ "threadId": thread_id });
     let _ = session.send_request("thread/archive", archive_params).await;
 
+    let response_text = response_text?;
     let trimmed = response_text.trim();
     if trimmed.is_empty() {
-        return Err("No metadata was generated".to_string());
+        return Err("No metadata was generated".into());
     }
 
     let json_value =
@@ -1372,6 +2959,140 @@ Task:\n{cleaned_prompt}"
     }))
 }
 
+/// Starts a turn on a background thread; the request answers once the prompt finished.
+async fn start_background_turn(
+    session: &WorkspaceSession,
+    thread_id: &str,
+    params: Value,
+) -> Result<bool, String> {
+    let response = session.start_thread_turn(thread_id, params, false).await?;
+    match response.get("error") {
+        Some(error) => Err(error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("Unknown error starting turn")
+            .to_string()),
+        None => Ok(true),
+    }
+}
+
+/// Runs `prompt` on a hidden background thread and returns the reply. Firing `cancel`
+/// interrupts the turn, archives the thread and fails with a cancellation error.
+pub(crate) async fn run_background_prompt(
+    session: &WorkspaceSession,
+    app: &AppHandle,
+    workspace_id: &str,
+    prompt: String,
+    purpose: &str,
+    timeouts: BackgroundReplyTimeouts,
+    mut cancel: oneshot::Receiver<()>,
+) -> Result<String, String> {
+    let thread_params = json!({
+        "cwd": session.entry.path,
+        "approvalPolicy": "never",
+        "_background": true,
+        "_primer": session.entry.settings.context_priming
+    });
+    let thread_result = session.send_request("thread/start", thread_params).await?;
+    if let Some(error) = thread_result.get("error") {
+        let error_msg = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("Unknown error starting thread");
+        return Err(error_msg.to_string());
+    }
+    let thread_id = thread_result
+        .get("result")
+        .and_then(|r| r.get("threadId"))
+        .or_else(|| {
+            thread_result
+                .get("result")
+                .and_then(|r| r.get("thread"))
+                .and_then(|t| t.get("id"))
+        })
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| {
+            format!(
+                "Failed to get threadId from thread/start response: {:?}",
+                thread_result
+            )
+        })?
+        .to_string();
+
+    emit_routed_event(
+        app,
+        AppServerEvent {
+            workspace_id: workspace_id.to_string(),
+            message: json!({
+                "method": event_methods::MICODE_BACKGROUND_THREAD,
+                "params": {
+                    "threadId": thread_id,
+                    "action": "hide"
+                }
+            }),
+        },
+    );
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let done_tx = tx.clone();
+    session
+        .background_thread_callbacks
+        .lock()
+        .await
+        .insert(thread_id.clone(), tx);
+
+    let turn_params = json!({
+        "threadId": thread_id,
+        "input": [{ "type": "text", "text": prompt }],
+        "cwd": session.entry.path,
+        "approvalPolicy": "never",
+        "sandboxPolicy": { "type": "readOnly" },
+        "_background": true,
+        "_purpose": purpose
+    });
+    let mut turn_request: Pin<Box<_>> =
+        Box::pin(session.start_thread_turn(&thread_id, turn_params, false));
+    let turn_result = loop {
+        if cancel.try_recv().is_ok() {
+            let interrupt_params = json!({ "threadId": thread_id.as_str() });
+            let _ = session
+                .send_request("turn/interrupt", interrupt_params)
+                .await;
+            // Let the prompt settle before the thread is archived underneath it.
+            let _ = timeout(Duration::from_secs(5), &mut turn_request).await;
+            break None;
+        }
+        match timeout(Duration::from_millis(150), &mut turn_request).await {
+            Ok(result) => break Some(result),
+            Err(_elapsed) => continue,
+        }
+    };
+    let outcome = match turn_result {
+        None => Err("Background generation canceled.".to_string()),
+        Some(Err(error)) => Err(error.message),
+        Some(Ok(response)) => match response.get("error") {
+            Some(error) => Err(error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("Unknown error starting turn")
+                .to_string()),
+            None => {
+                mark_background_turn_done(&done_tx);
+                Ok(collect_background_agent_text(&mut rx, timeouts).await)
+            }
+        },
+    };
+
+    session
+        .background_thread_callbacks
+        .lock()
+        .await
+        .remove(&thread_id);
+    let archive_params = json!({ "threadId": thread_id });
+    let _ = session.send_request("thread/archive", archive_params).await;
+    outcome
+}
+
 fn extract_json_value(raw: &str) -> Option<Value> {
     let start = raw.find('{')?;
     let end = raw.rfind('}')?;
@@ -0,0 +1,127 @@
+use serde::Serialize;
+
+use super::extract_json_value;
+use crate::git::BranchDiff;
+
+/// A generated pull request title and Markdown body.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct PrDescription {
+    pub(crate) title: String,
+    pub(crate) body: String,
+}
+
+/// The prompt asking for a PR description of `branch_diff`; fails when the branch
+/// changes nothing relative to its base.
+pub(crate) fn build_pr_description_prompt(
+    branch_diff: &BranchDiff,
+    stack: Option<&str>,
+) -> Result<String, String> {
+    if branch_diff.diff.trim().is_empty() {
+        return Err(format!(
+            "No changes between {} and {} to describe",
+            branch_diff.branch, branch_diff.base
+        ));
+    }
+    let stack = stack
+        .map(|stack| format!("Project stack: {stack}.\n\n"))
+        .unwrap_or_default();
+    let commits = if branch_diff.commits.is_empty() {
+        String::new()
+    } else {
+        let list = branch_diff
+            .commits
+            .iter()
+            .map(|subject| format!("- {subject}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!("Commits:\n{list}\n\n")
+    };
+    Ok(format!(
+        "Write a pull request description for merging branch `{branch}` into `{base}`. \
+Respond with only a JSON object of the form {{\"title\": \"...\", \"body\": \"...\"}}. \
+The title is a single line under 72 characters. \
+The body is Markdown with a \"## Summary\" section of short bullets describing what changed and why, \
+and a \"## Test plan\" section describing how the change can be verified. \
+Parts of the diff may be truncated; do not guess at omitted content.\n\n\
+{stack}{commits}Changes:\n{diff}",
+        branch = branch_diff.branch,
+        base = branch_diff.base,
+        diff = branch_diff.diff,
+    ))
+}
+
+/// Reads the `{title, body}` object out of the agent's reply.
+pub(crate) fn parse_pr_description(reply: &str) -> Result<PrDescription, String> {
+    let value = extract_json_value(reply)
+        .ok_or_else(|| "Failed to parse PR description JSON".to_string())?;
+    let field = |key: &str| {
+        value
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .ok_or_else(|| format!("Missing {key} in PR description"))
+    };
+    Ok(PrDescription {
+        title: field("title")?,
+        body: field("body")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_pr_description_prompt, parse_pr_description, PrDescription};
+    use crate::git::BranchDiff;
+
+    fn branch_diff(diff: &str) -> BranchDiff {
+        BranchDiff {
+            branch: "feat/retries".to_string(),
+            base: "origin/main".to_string(),
+            commits: vec!["Retry empty replies".to_string()],
+            diff: diff.to_string(),
+        }
+    }
+
+    #[test]
+    fn prompt_names_the_branches_commits_and_diff() {
+        let prompt = build_pr_description_prompt(
+            &branch_diff("=== src/lib.rs ===\n+retry();\n"),
+            Some("Rust (cargo)"),
+        )
+        .expect("prompt");
+        assert!(prompt.contains("`feat/retries` into `origin/main`"));
+        assert!(prompt.contains("## Summary"));
+        assert!(prompt.contains("## Test plan"));
+        assert!(prompt.contains("Project stack: Rust (cargo)."));
+        assert!(prompt.contains("Commits:\n- Retry empty replies\n"));
+        assert!(prompt.ends_with("Changes:\n=== src/lib.rs ===\n+retry();\n"));
+    }
+
+    #[test]
+    fn an_empty_branch_diff_is_an_error() {
+        let error = build_pr_description_prompt(&branch_diff("  \n"), None)
+            .expect_err("nothing to describe");
+        assert_eq!(
+            error,
+            "No changes between feat/retries and origin/main to describe"
+        );
+    }
+
+    #[test]
+    fn parses_title_and_body_from_the_reply() {
+        let reply = "Here you go:\n```json\n{\"title\": \" Retry empty replies \", \
+\"body\": \"## Summary\\n- Retries once\\n\\n## Test plan\\n- cargo test\"}\n```";
+        assert_eq!(
+            parse_pr_description(reply),
+            Ok(PrDescription {
+                title: "Retry empty replies".to_string(),
+                body: "## Summary\n- Retries once\n\n## Test plan\n- cargo test".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_pr_description("{\"title\": \"Only a title\"}"),
+            Err("Missing body in PR description".to_string())
+        );
+        assert!(parse_pr_description("no json here").is_err());
+    }
+}
@@ -0,0 +1,367 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+use crate::backend::app_server::now_ms;
+use crate::backend::event_methods;
+use crate::backend::events::{AppServerEvent, EventSink};
+use crate::event_sink::TauriEventSink;
+use crate::state::AppState;
+use crate::storage::write_file_atomically;
+
+pub(crate) const NOTIFICATIONS_FILE: &str = "notifications.json";
+/// The inbox never grows past this many entries, whatever the retention.
+const MAX_NOTIFICATIONS: usize = 500;
+const DEFAULT_PAGE_SIZE: usize = 50;
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// A dispatched notification as the frontend describes it, with the ids needed to open
+/// the thread or approval it is about.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct NewNotification {
+    /// `approval`, `question`, `plan`, `thread` or `app`.
+    #[serde(rename = "type")]
+    pub(crate) kind: String,
+    pub(crate) title: String,
+    pub(crate) message: String,
+    pub(crate) workspace_id: Option<String>,
+    pub(crate) thread_id: Option<String>,
+    pub(crate) turn_id: Option<String>,
+    pub(crate) item_id: Option<String>,
+    pub(crate) request_id: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NotificationRecord {
+    pub(crate) id: String,
+    #[serde(rename = "type")]
+    pub(crate) kind: String,
+    pub(crate) title: String,
+    pub(crate) message: String,
+    pub(crate) workspace_id: Option<String>,
+    pub(crate) thread_id: Option<String>,
+    pub(crate) turn_id: Option<String>,
+    pub(crate) item_id: Option<String>,
+    pub(crate) request_id: Option<Value>,
+    pub(crate) created_at: u64,
+    pub(crate) read_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NotificationPage {
+    /// Newest first.
+    pub(crate) items: Vec<NotificationRecord>,
+    /// Entries matching the filter, across all pages.
+    pub(crate) total: usize,
+    pub(crate) unread_count: usize,
+}
+
+/// Every notification the app dispatched, natively or through the fallback, kept so
+/// nothing is lost when the system notification disappears.
+#[derive(Debug, Default)]
+pub(crate) struct NotificationInbox {
+    /// Oldest first.
+    records: Vec<NotificationRecord>,
+    /// Bumped by every change, so unchanged inboxes are not saved again.
+    revision: u64,
+}
+
+impl NotificationInbox {
+    pub(crate) fn load(path: &Path) -> Self {
+        let records = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            records,
+            revision: 0,
+        }
+    }
+
+    fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(&self.records).map_err(|err| err.to_string())
+    }
+
+    pub(crate) fn unread_count(&self) -> usize {
+        self.records
+            .iter()
+            .filter(|record| record.read_at.is_none())
+            .count()
+    }
+
+    /// Drops entries older than `retention_days` (0 keeps them) and the oldest ones past
+    /// `MAX_NOTIFICATIONS`. Returns how many were dropped.
+    pub(crate) fn prune(&mut self, retention_days: u32, now: u64) -> usize {
+        let before = self.records.len();
+        if retention_days > 0 {
+            let cutoff = now.saturating_sub(u64::from(retention_days) * DAY_MS);
+            self.records.retain(|record| record.created_at >= cutoff);
+        }
+        let overflow = self.records.len().saturating_sub(MAX_NOTIFICATIONS);
+        self.records.drain(..overflow);
+        self.changed(before - self.records.len())
+    }
+
+    fn changed(&mut self, count: usize) -> usize {
+        if count > 0 {
+            self.revision += 1;
+        }
+        count
+    }
+
+    pub(crate) fn record(&mut self, notification: NewNotification, now: u64) -> NotificationRecord {
+        let kind = Some(notification.kind.trim())
+            .filter(|kind| !kind.is_empty())
+            .unwrap_or("app")
+            .to_string();
+        let record = NotificationRecord {
+            id: Uuid::new_v4().to_string(),
+            kind,
+            title: notification.title,
+            message: notification.message,
+            workspace_id: notification.workspace_id,
+            thread_id: notification.thread_id,
+            turn_id: notification.turn_id,
+            item_id: notification.item_id,
+            request_id: notification.request_id,
+            created_at: now,
+            read_at: None,
+        };
+        self.records.push(record.clone());
+        self.changed(1);
+        record
+    }
+
+    pub(crate) fn list(&self, unread_only: bool, offset: usize, limit: usize) -> NotificationPage {
+        let matching: Vec<&NotificationRecord> = self
+            .records
+            .iter()
+            .rev()
+            .filter(|record| !unread_only || record.read_at.is_none())
+            .collect();
+        NotificationPage {
+            total: matching.len(),
+            items: matching
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+            unread_count: self.unread_count(),
+        }
+    }
+
+    /// Marks one entry, or every entry when `id` is `None`, as read. Returns how many
+    /// changed; an unknown id is an error.
+    pub(crate) fn mark_read(&mut self, id: Option<&str>, now: u64) -> Result<usize, String> {
+        if let Some(id) = id {
+            let record = self
+                .records
+                .iter_mut()
+                .find(|record| record.id == id)
+                .ok_or_else(|| format!("notification not found: {id}"))?;
+            let changed = record.read_at.is_none();
+            record.read_at.get_or_insert(now);
+            return Ok(self.changed(usize::from(changed)));
+        }
+        let mut changed = 0;
+        for record in self
+            .records
+            .iter_mut()
+            .filter(|record| record.read_at.is_none())
+        {
+            record.read_at = Some(now);
+            changed += 1;
+        }
+        Ok(self.changed(changed))
+    }
+
+    /// Removes read entries only, or everything. Returns how many were removed.
+    pub(crate) fn clear(&mut self, read_only: bool) -> usize {
+        let before = self.records.len();
+        if read_only {
+            self.records.retain(|record| record.read_at.is_none());
+        } else {
+            self.records.clear();
+        }
+        self.changed(before - self.records.len())
+    }
+}
+
+pub(crate) async fn unread_notification_count(state: &AppState) -> usize {
+    state.notification_inbox.lock().await.unread_count()
+}
+
+/// Applies `update` to the inbox after pruning it, saves it when that changed anything
+/// and tells the UI when the unread count moved. The file is written on a blocking thread
+/// while the inbox stays locked, so saves land in order.
+async fn update_inbox<T>(
+    state: &AppState,
+    app: &AppHandle,
+    update: impl FnOnce(&mut NotificationInbox, u64) -> Result<T, String>,
+) -> Result<T, String> {
+    let retention_days = state.app_settings.lock().await.notification_retention_days;
+    let now = now_ms();
+    let (result, unread_before, unread_after) = {
+        let mut inbox = state.notification_inbox.lock().await;
+        let unread_before = inbox.unread_count();
+        let revision = inbox.revision;
+        inbox.prune(retention_days, now);
+        let result = update(&mut inbox, now);
+        if inbox.revision != revision {
+            let data = inbox.to_json()?;
+            let path = state.notifications_path.clone();
+            tokio::task::spawn_blocking(move || write_file_atomically(&path, &data))
+                .await
+                .map_err(|err| err.to_string())??;
+        }
+        (result, unread_before, inbox.unread_count())
+    };
+    if unread_before != unread_after {
+        TauriEventSink::new(app.clone()).emit_app_server_event(AppServerEvent {
+            workspace_id: String::new(),
+            message: json!({
+                "method": event_methods::NOTIFICATIONS_UNREAD_CHANGED,
+                "params": { "unreadCount": unread_after },
+            }),
+        });
+    }
+    result
+}
+
+/// Adds a dispatched notification to the inbox. Local only: notifications are shown by
+/// this app even in remote mode.
+#[tauri::command]
+pub(crate) async fn record_notification(
+    notification: NewNotification,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<NotificationRecord, String> {
+    update_inbox(&state, &app, |inbox, now| {
+        Ok(inbox.record(notification, now))
+    })
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn list_notifications(
+    unread_only: Option<bool>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<NotificationPage, String> {
+    update_inbox(&state, &app, |inbox, _| {
+        Ok(inbox.list(
+            unread_only.unwrap_or(false),
+            offset.unwrap_or(0),
+            limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        ))
+    })
+    .await
+}
+
+/// Marks the notification `id` as read, or all of them when `id` is omitted.
+#[tauri::command]
+pub(crate) async fn mark_notification_read(
+    id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<usize, String> {
+    update_inbox(&state, &app, |inbox, now| {
+        inbox.mark_read(id.as_deref(), now)
+    })
+    .await
+}
+
+/// Empties the inbox, or only drops what was already read with `read_only`.
+#[tauri::command]
+pub(crate) async fn clear_notifications(
+    read_only: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<usize, String> {
+    update_inbox(&state, &app, |inbox, _| {
+        Ok(inbox.clear(read_only.unwrap_or(false)))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approval(workspace_id: &str, request_id: u64) -> NewNotification {
+        NewNotification {
+            kind: "approval".to_string(),
+            title: "Approval needed".to_string(),
+            message: "rm -rf build".to_string(),
+            workspace_id: Some(workspace_id.to_string()),
+            request_id: Some(json!(request_id)),
+            ..NewNotification::default()
+        }
+    }
+
+    #[test]
+    fn records_lists_and_marks_notifications_read() {
+        let mut inbox = NotificationInbox::default();
+        let first = inbox.record(approval("ws-1", 7), 1_000);
+        let second = inbox.record(
+            NewNotification {
+                title: "Update ready".to_string(),
+                ..NewNotification::default()
+            },
+            2_000,
+        );
+        assert_eq!(second.kind, "app");
+        inbox.record(approval("ws-2", 8), 3_000);
+
+        let page = inbox.list(false, 0, 2);
+        assert_eq!(page.total, 3);
+        assert_eq!(page.unread_count, 3);
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].created_at, 3_000);
+        assert_eq!(inbox.list(false, 2, 2).items[0].id, first.id);
+
+        let revision = inbox.revision;
+        inbox.list(false, 0, 10);
+        assert_eq!(inbox.prune(0, 3_000), 0);
+        assert_eq!(inbox.revision, revision);
+        assert_eq!(inbox.mark_read(Some(&first.id), 4_000), Ok(1));
+        let revision = inbox.revision;
+        assert_eq!(inbox.mark_read(Some(&first.id), 5_000), Ok(0));
+        assert_eq!(inbox.revision, revision);
+        assert!(inbox.mark_read(Some("missing"), 5_000).is_err());
+        let unread = inbox.list(true, 0, 10);
+        assert_eq!(unread.total, 2);
+        assert!(unread.items.iter().all(|item| item.id != first.id));
+        let read = inbox.list(false, 2, 1).items.remove(0);
+        assert_eq!(read.read_at, Some(4_000));
+        assert_eq!(read.request_id, Some(json!(7)));
+
+        assert_eq!(inbox.mark_read(None, 6_000), Ok(2));
+        assert_eq!(inbox.unread_count(), 0);
+        assert_eq!(inbox.clear(true), 3);
+        assert_eq!(inbox.list(false, 0, 10).total, 0);
+    }
+
+    #[test]
+    fn prunes_by_age_and_count() {
+        let mut inbox = NotificationInbox::default();
+        inbox.record(approval("ws-1", 1), 0);
+        for index in 0..MAX_NOTIFICATIONS as u64 {
+            inbox.record(approval("ws-1", index), 3 * DAY_MS + index);
+        }
+        assert_eq!(inbox.prune(0, 3 * DAY_MS + 1_000), 1);
+        assert_eq!(inbox.list(false, 0, 1).total, MAX_NOTIFICATIONS);
+
+        assert_eq!(inbox.prune(2, 5 * DAY_MS + 100), 100);
+        assert_eq!(inbox.list(false, 0, 1).total, MAX_NOTIFICATIONS - 100);
+    }
+}
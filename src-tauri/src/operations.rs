@@ -0,0 +1,31 @@
+use serde_json::json;
+use tauri::{AppHandle, State};
+
+use crate::remote_backend;
+use crate::state::AppState;
+
+/// Cancels a running operation started with `operationId`, such as a clone, a workspace
+/// file listing or a diagnostics export. The command then resolves with a `cancelled`
+/// outcome. Returns false when nothing by that id is running here or on the daemon.
+#[tauri::command]
+pub(crate) async fn cancel_operation(
+    operation_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<bool, String> {
+    if state.operations.cancel(&operation_id) {
+        return Ok(true);
+    }
+    // Remote mode runs some of them on the daemon, under the same id.
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "cancel_operation",
+            json!({ "operationId": operation_id }),
+        )
+        .await?;
+        return Ok(response.as_bool().unwrap_or(false));
+    }
+    Ok(false)
+}
@@ -1,11 +1,18 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use tauri::State;
+use tauri::{AppHandle, State};
+use tokio::sync::oneshot;
 use tokio::task;
+use uuid::Uuid;
 
+use crate::backend::app_server::now_ms;
+use crate::backend::prompt_text::normalize_prompt_text;
+use crate::micode::background_reply::BackgroundReplyTimeouts;
 use crate::micode::home::{resolve_default_micode_home, resolve_workspace_micode_home};
+use crate::remote_backend;
 use crate::state::AppState;
 use crate::types::WorkspaceEntry;
 
@@ -21,22 +28,47 @@ pub(crate) struct CustomPromptEntry {
     pub(crate) scope: Option<String>,
 }
 
+/// Subfolder of a scope directory holding deleted prompts as `<deletedAtMs>-<name>.md`.
+const TRASH_DIR: &str = ".trash";
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TrashedPromptEntry {
+    /// `path` points into the trash folder and is what `prompts_restore` takes.
+    #[serde(flatten)]
+    pub(crate) prompt: CustomPromptEntry,
+    pub(crate) deleted_at: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PromptRestoreResult {
+    pub(crate) prompt: CustomPromptEntry,
+    pub(crate) original_name: String,
+    /// `true` when a prompt with the original name existed and a numeric suffix was added.
+    pub(crate) renamed: bool,
+}
+
 fn resolve_micode_home_for_workspace(
     workspaces: &HashMap<String, WorkspaceEntry>,
     entry: &WorkspaceEntry,
+    data_dir: &Path,
 ) -> Option<PathBuf> {
     let parent_entry = entry
         .parent_id
         .as_ref()
         .and_then(|parent_id| workspaces.get(parent_id));
-    resolve_workspace_micode_home(entry, parent_entry).or_else(resolve_default_micode_home)
+    resolve_workspace_micode_home(entry, parent_entry, data_dir)
+        .or_else(resolve_default_micode_home)
 }
 
 fn default_prompts_dir_for_workspace(
     workspaces: &HashMap<String, WorkspaceEntry>,
     entry: &WorkspaceEntry,
+    data_dir: &Path,
 ) -> Option<PathBuf> {
-    resolve_micode_home_for_workspace(workspaces, entry).map(|home| home.join("prompts"))
+    resolve_micode_home_for_workspace(workspaces, entry, data_dir).map(|home| home.join("prompts"))
 }
 
 fn require_workspace_entry(
@@ -72,7 +104,8 @@ fn prompt_roots_for_workspace(
 ) -> Result<Vec<PathBuf>, String> {
     let mut roots = Vec::new();
     roots.push(workspace_prompts_dir(state, entry)?);
-    if let Some(global_dir) = default_prompts_dir_for_workspace(workspaces, entry) {
+    if let Some(global_dir) = default_prompts_dir_for_workspace(workspaces, entry, &state.data_dir)
+    {
         roots.push(global_dir);
     }
     Ok(roots)
@@ -113,6 +146,115 @@ fn move_file(src: &Path, dest: &Path) -> Result<(), String> {
     }
 }
 
+fn parse_trash_file_name(file_name: &str) -> Option<(u64, String)> {
+    let stem = file_name.strip_suffix(".md")?;
+    let (deleted_at, name) = stem.split_once('-')?;
+    if name.is_empty() {
+        return None;
+    }
+    Some((deleted_at.parse().ok()?, name.to_string()))
+}
+
+/// Moves `path` into the trash folder next to it. A rename keeps the file's metadata;
+/// the deletion time is carried in the file name.
+fn trash_prompt_file(path: &Path, now: u64) -> Result<PathBuf, String> {
+    let dir = path
+        .parent()
+        .ok_or("Unable to resolve prompt directory.".to_string())?;
+    let name = path
+        .file_stem()
+        .and_then(|value| value.to_str())
+        .ok_or("Invalid prompt path.".to_string())?;
+    let trash_dir = dir.join(TRASH_DIR);
+    fs::create_dir_all(&trash_dir).map_err(|err| err.to_string())?;
+    let mut deleted_at = now;
+    let mut dest = trash_dir.join(format!("{deleted_at}-{name}.md"));
+    while dest.exists() {
+        deleted_at += 1;
+        dest = trash_dir.join(format!("{deleted_at}-{name}.md"));
+    }
+    move_file(path, &dest)?;
+    Ok(dest)
+}
+
+fn discover_trash_in(dir: &Path, scope: &str) -> Vec<TrashedPromptEntry> {
+    let Ok(entries) = fs::read_dir(dir.join(TRASH_DIR)) else {
+        return Vec::new();
+    };
+    let mut out = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let (deleted_at, name) = parse_trash_file_name(path.file_name()?.to_str()?)?;
+            let content = fs::read_to_string(&path).ok()?;
+            let (description, argument_hint, body) = parse_frontmatter(&content);
+            Some(TrashedPromptEntry {
+                prompt: CustomPromptEntry {
+                    name,
+                    path: path.to_string_lossy().to_string(),
+                    description,
+                    argument_hint,
+                    content: body,
+                    scope: Some(scope.to_string()),
+                },
+                deleted_at,
+            })
+        })
+        .collect::<Vec<_>>();
+    out.sort_by_key(|entry| Reverse(entry.deleted_at));
+    out
+}
+
+/// Removes trash entries deleted more than `retention_days` ago; 0 keeps everything.
+fn purge_trash_in(dir: &Path, retention_days: u32, now: u64) -> usize {
+    if retention_days == 0 {
+        return 0;
+    }
+    let cutoff = now.saturating_sub(u64::from(retention_days) * DAY_MS);
+    let Ok(entries) = fs::read_dir(dir.join(TRASH_DIR)) else {
+        return 0;
+    };
+    let mut purged = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let expired = path
+            .file_name()
+            .and_then(|value| value.to_str())
+            .and_then(parse_trash_file_name)
+            .is_some_and(|(deleted_at, _)| deleted_at < cutoff);
+        if expired && fs::remove_file(&path).is_ok() {
+            purged += 1;
+        }
+    }
+    purged
+}
+
+/// Moves a trashed prompt back into its scope directory under its original name, or
+/// `<name>-2`, `<name>-3`, ... when that name is taken. Returns the new path and name.
+fn restore_trashed_file(trash_path: &Path) -> Result<(PathBuf, String), String> {
+    let trash_dir = trash_path
+        .parent()
+        .filter(|dir| dir.file_name().and_then(|value| value.to_str()) == Some(TRASH_DIR))
+        .ok_or("Prompt is not in the trash.".to_string())?;
+    let scope_dir = trash_dir
+        .parent()
+        .ok_or("Unable to resolve prompt directory.".to_string())?;
+    let (_, original_name) = trash_path
+        .file_name()
+        .and_then(|value| value.to_str())
+        .and_then(parse_trash_file_name)
+        .ok_or("Invalid trashed prompt name.".to_string())?;
+    let mut name = original_name.clone();
+    let mut suffix = 2;
+    while scope_dir.join(format!("{name}.md")).exists() {
+        name = format!("{original_name}-{suffix}");
+        suffix += 1;
+    }
+    let dest = scope_dir.join(format!("{name}.md"));
+    move_file(trash_path, &dest)?;
+    Ok((dest, name))
+}
+
 fn parse_frontmatter(content: &str) -> (Option<String>, Option<String>, String) {
     let mut segments = content.split_inclusive('\n');
     let Some(first_segment) = segments.next() else {
@@ -285,20 +427,24 @@ pub(crate) async fn prompts_list(
         let workspace_dir = entry
             .as_ref()
             .and_then(|entry| workspace_prompts_dir(&state, entry).ok());
-        let global_dir = entry
-            .as_ref()
-            .and_then(|entry| default_prompts_dir_for_workspace(&workspaces, entry));
+        let global_dir = entry.as_ref().and_then(|entry| {
+            default_prompts_dir_for_workspace(&workspaces, entry, &state.data_dir)
+        });
         (workspace_dir, global_dir)
     };
+    let retention_days = state.app_settings.lock().await.prompt_trash_retention_days;
 
     task::spawn_blocking(move || {
+        let now = now_ms();
         let mut out = Vec::new();
         if let Some(dir) = workspace_dir {
             let _ = fs::create_dir_all(&dir);
+            purge_trash_in(&dir, retention_days, now);
             out.extend(discover_prompts_in(&dir, Some("workspace")));
         }
         if let Some(dir) = global_dir {
             let _ = fs::create_dir_all(&dir);
+            purge_trash_in(&dir, retention_days, now);
             out.extend(discover_prompts_in(&dir, Some("global")));
         }
         out
@@ -328,33 +474,32 @@ pub(crate) async fn prompts_global_dir(
 ) -> Result<String, String> {
     let workspaces = state.workspaces.lock().await;
     let entry = require_workspace_entry(&workspaces, &workspace_id)?;
-    let dir = default_prompts_dir_for_workspace(&workspaces, &entry)
+    let dir = default_prompts_dir_for_workspace(&workspaces, &entry, &state.data_dir)
         .ok_or("Unable to resolve CODEX_HOME".to_string())?;
     fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
     Ok(dir.to_string_lossy().to_string())
 }
 
-#[tauri::command]
-pub(crate) async fn prompts_create(
-    state: State<'_, AppState>,
-    workspace_id: String,
-    scope: String,
-    name: String,
+async fn create_prompt(
+    state: &State<'_, AppState>,
+    workspace_id: &str,
+    scope: &str,
+    name: &str,
     description: Option<String>,
     argument_hint: Option<String>,
     content: String,
 ) -> Result<CustomPromptEntry, String> {
-    let name = sanitize_prompt_name(&name)?;
+    let name = sanitize_prompt_name(name)?;
     let (target_dir, resolved_scope) = {
         let workspaces = state.workspaces.lock().await;
-        let entry = require_workspace_entry(&workspaces, &workspace_id)?;
-        match scope.as_str() {
+        let entry = require_workspace_entry(&workspaces, workspace_id)?;
+        match scope {
             "workspace" => {
-                let dir = workspace_prompts_dir(&state, &entry)?;
+                let dir = workspace_prompts_dir(state, &entry)?;
                 (dir, "workspace")
             }
             "global" => {
-                let dir = default_prompts_dir_for_workspace(&workspaces, &entry)
+                let dir = default_prompts_dir_for_workspace(&workspaces, &entry, &state.data_dir)
                     .ok_or("Unable to resolve CODEX_HOME".to_string())?;
                 (dir, "global")
             }
@@ -380,6 +525,28 @@ pub(crate) async fn prompts_create(
     })
 }
 
+#[tauri::command]
+pub(crate) async fn prompts_create(
+    state: State<'_, AppState>,
+    workspace_id: String,
+    scope: String,
+    name: String,
+    description: Option<String>,
+    argument_hint: Option<String>,
+    content: String,
+) -> Result<CustomPromptEntry, String> {
+    create_prompt(
+        &state,
+        &workspace_id,
+        &scope,
+        &name,
+        description,
+        argument_hint,
+        content,
+    )
+    .await
+}
+
 #[tauri::command]
 pub(crate) async fn prompts_update(
     state: State<'_, AppState>,
@@ -433,11 +600,13 @@ pub(crate) async fn prompts_update(
     })
 }
 
+/// Moves the prompt to the trash; `purge_immediately` deletes it for good instead.
 #[tauri::command]
 pub(crate) async fn prompts_delete(
     state: State<'_, AppState>,
     workspace_id: String,
     path: String,
+    purge_immediately: Option<bool>,
 ) -> Result<(), String> {
     let target = PathBuf::from(path);
     if !target.exists() {
@@ -449,7 +618,80 @@ pub(crate) async fn prompts_delete(
         let roots = prompt_roots_for_workspace(&state, &workspaces, &entry)?;
         ensure_path_within_roots(&target, &roots)?;
     }
-    fs::remove_file(&target).map_err(|err| err.to_string())
+    if purge_immediately.unwrap_or(false) {
+        return fs::remove_file(&target).map_err(|err| err.to_string());
+    }
+    trash_prompt_file(&target, now_ms()).map(|_| ())
+}
+
+#[tauri::command]
+pub(crate) async fn prompts_list_trash(
+    state: State<'_, AppState>,
+    workspace_id: String,
+) -> Result<Vec<TrashedPromptEntry>, String> {
+    let (workspace_dir, global_dir) = {
+        let workspaces = state.workspaces.lock().await;
+        let entry = require_workspace_entry(&workspaces, &workspace_id)?;
+        (
+            workspace_prompts_dir(&state, &entry)?,
+            default_prompts_dir_for_workspace(&workspaces, &entry, &state.data_dir),
+        )
+    };
+    task::spawn_blocking(move || {
+        let mut out = discover_trash_in(&workspace_dir, "workspace");
+        if let Some(dir) = global_dir {
+            out.extend(discover_trash_in(&dir, "global"));
+        }
+        out.sort_by_key(|entry| Reverse(entry.deleted_at));
+        out
+    })
+    .await
+    .map_err(|_| "prompt trash listing failed".to_string())
+}
+
+#[tauri::command]
+pub(crate) async fn prompts_restore(
+    state: State<'_, AppState>,
+    workspace_id: String,
+    path: String,
+) -> Result<PromptRestoreResult, String> {
+    let trash_path = PathBuf::from(&path);
+    if !trash_path.exists() {
+        return Err("Prompt not found in the trash.".to_string());
+    }
+    let workspace_dir = {
+        let workspaces = state.workspaces.lock().await;
+        let entry = require_workspace_entry(&workspaces, &workspace_id)?;
+        let roots = prompt_roots_for_workspace(&state, &workspaces, &entry)?;
+        ensure_path_within_roots(&trash_path, &roots)?;
+        workspace_prompts_dir(&state, &entry)?
+    };
+    let original_name = trash_path
+        .file_name()
+        .and_then(|value| value.to_str())
+        .and_then(parse_trash_file_name)
+        .map(|(_, name)| name)
+        .ok_or("Invalid trashed prompt name.".to_string())?;
+    let (restored_path, name) = restore_trashed_file(&trash_path)?;
+    let content = fs::read_to_string(&restored_path).unwrap_or_default();
+    let (description, argument_hint, body) = parse_frontmatter(&content);
+    let scope = if restored_path.starts_with(&workspace_dir) {
+        "workspace"
+    } else {
+        "global"
+    };
+    Ok(PromptRestoreResult {
+        renamed: name != original_name,
+        original_name,
+        prompt: CustomPromptEntry {
+            name,
+            path: restored_path.to_string_lossy().to_string(),
+            description,
+            argument_hint,
+            content: body,
+            scope: Some(scope.to_string()),
+        },
+    })
 }
 
 #[tauri::command]
@@ -478,7 +720,7 @@ pub(crate) async fn prompts_move(
         let entry = require_workspace_entry(&workspaces, &workspace_id)?;
         match scope.as_str() {
             "workspace" => workspace_prompts_dir(&state, &entry)?,
-            "global" => default_prompts_dir_for_workspace(&workspaces, &entry)
+            "global" => default_prompts_dir_for_workspace(&workspaces, &entry, &state.data_dir)
                 .ok_or("Unable to resolve CODEX_HOME".to_string())?,
             _ => return Err("Invalid scope.".to_string()),
         }
@@ -510,3 +752,270 @@ pub(crate) async fn prompts_move(
         scope: Some(scope),
     })
 }
+
+/// Joins the picked user messages into one prompt body, normalizing each message and
+/// dropping the ones left empty.
+fn join_extracted_messages(texts: &[String]) -> String {
+    texts
+        .iter()
+        .map(|text| normalize_prompt_text(text))
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Unwraps a template the model answered inside a single code fence.
+fn clean_generated_template(output: &str) -> String {
+    let trimmed = output.trim();
+    let unfenced = trimmed
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(|inner| inner.split_once('\n').map_or("", |(_, body)| body))
+        .unwrap_or(trimmed);
+    normalize_prompt_text(unfenced)
+}
+
+/// `{{variable}}` placeholder names in order of first appearance.
+fn template_variables(template: &str) -> Vec<String> {
+    let mut variables: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        let name = after[..end].trim();
+        let is_identifier = !name.is_empty()
+            && name
+                .chars()
+                .all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-');
+        if is_identifier && !variables.iter().any(|known| known == name) {
+            variables.push(name.to_string());
+        }
+        rest = &after[end + 2..];
+    }
+    variables
+}
+
+fn template_generation_prompt(text: &str) -> String {
+    format!(
+        "You turn a request someone sent to a coding agent into a reusable prompt template.\n\
+Keep the instructions, structure and tone. Replace details that only apply to this one \
+task (file paths, identifiers, ticket numbers, error messages, concrete values) with \
+{{{{snake_case}}}} placeholders, reusing a placeholder when the same detail repeats.\n\
+Return ONLY the template text, without commentary or code fences.\n\
+\n\
+Request:\n{text}"
+    )
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PromptExtraction {
+    /// Path of the created prompt, which identifies it to the other prompt commands;
+    /// `None` on a dry run.
+    pub(crate) prompt_id: Option<String>,
+    pub(crate) prompt: Option<CustomPromptEntry>,
+    pub(crate) text: String,
+    pub(crate) generalized: bool,
+    pub(crate) variables: Vec<String>,
+}
+
+/// Which messages `extract_prompt_from_thread` turns into a prompt, and where it is saved.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PromptExtractionRequest {
+    thread_id: String,
+    item_ids: Vec<String>,
+    scope: String,
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    generalize: bool,
+    #[serde(default)]
+    dry_run: bool,
+    #[serde(default)]
+    request_id: Option<String>,
+}
+
+#[tauri::command]
+pub(crate) async fn extract_prompt_from_thread(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    workspace_id: String,
+    request: PromptExtractionRequest,
+) -> Result<PromptExtraction, String> {
+    let PromptExtractionRequest {
+        thread_id,
+        item_ids,
+        scope,
+        name,
+        description,
+        generalize: generalized,
+        dry_run,
+        request_id,
+    } = request;
+    if remote_backend::is_remote_mode(&state).await {
+        return Err("Prompt extraction is only available for local workspaces".to_string());
+    }
+    if item_ids.is_empty() {
+        return Err("Select at least one user message.".to_string());
+    }
+    if !dry_run {
+        sanitize_prompt_name(&name)?;
+    }
+    let session = {
+        let sessions = state.sessions.lock().await;
+        sessions
+            .get(&workspace_id)
+            .ok_or("workspace not connected")?
+            .clone()
+    };
+    let texts = session.user_message_texts(&thread_id, &item_ids).await?;
+    let mut text = join_extracted_messages(&texts);
+    if text.is_empty() {
+        return Err("The selected messages contain no text.".to_string());
+    }
+
+    if generalized {
+        let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+        let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+        state
+            .prompt_extraction_cancels
+            .lock()
+            .await
+            .insert(request_id.clone(), cancel_tx);
+        let output = crate::micode::run_background_prompt(
+            &session,
+            &app,
+            &workspace_id,
+            template_generation_prompt(&text),
+            "promptTemplate",
+            BackgroundReplyTimeouts::from_settings(&*state.app_settings.lock().await),
+            cancel_rx,
+        )
+        .await;
+        state
+            .prompt_extraction_cancels
+            .lock()
+            .await
+            .remove(&request_id);
+        text = clean_generated_template(&output?);
+        if text.is_empty() {
+            return Err("No template was generated".to_string());
+        }
+    }
+
+    let variables = template_variables(&text);
+    if dry_run {
+        return Ok(PromptExtraction {
+            prompt_id: None,
+            prompt: None,
+            text,
+            generalized,
+            variables,
+        });
+    }
+    let prompt = create_prompt(
+        &state,
+        &workspace_id,
+        &scope,
+        &name,
+        description,
+        None,
+        text.clone(),
+    )
+    .await?;
+    Ok(PromptExtraction {
+        prompt_id: Some(prompt.path.clone()),
+        prompt: Some(prompt),
+        text,
+        generalized,
+        variables,
+    })
+}
+
+/// Stops the background generalization of `extract_prompt_from_thread`. Returns
+/// `false` when no extraction with that request id is running.
+#[tauri::command]
+pub(crate) async fn cancel_prompt_extraction(
+    state: State<'_, AppState>,
+    request_id: String,
+) -> Result<bool, String> {
+    let cancel = state
+        .prompt_extraction_cancels
+        .lock()
+        .await
+        .remove(&request_id);
+    Ok(cancel.is_some_and(|tx| tx.send(()).is_ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_messages_and_collects_template_variables() {
+        let texts = vec![
+            "  Fix the flaky test in\r\nsrc/app.ts  ".to_string(),
+            "\u{200b}\n".to_string(),
+            "Then run\n\n\n\n\nthe suite".to_string(),
+        ];
+        assert_eq!(
+            join_extracted_messages(&texts),
+            "Fix the flaky test in\nsrc/app.ts\n\nThen run\n\n\nthe suite"
+        );
+
+        let template = clean_generated_template(
+            "```markdown\nFix {{ test_name }} in {{file_path}}, then rerun {{test_name}}.\n```",
+        );
+        assert_eq!(
+            template,
+            "Fix {{ test_name }} in {{file_path}}, then rerun {{test_name}}."
+        );
+        assert_eq!(
+            template_variables(&template),
+            vec!["test_name", "file_path"]
+        );
+        assert_eq!(
+            template_variables("no {{ }} or {{bad name}} {{open"),
+            Vec::<String>::new()
+        );
+        assert_eq!(clean_generated_template("  plain  "), "plain");
+    }
+
+    #[test]
+    fn trashes_restores_and_purges_prompts() {
+        let dir = std::env::temp_dir().join(format!("prompts-trash-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let prompt = dir.join("review.md");
+        fs::write(&prompt, "---\ndescription: \"Review\"\n---\nCheck it").unwrap();
+
+        let trashed = trash_prompt_file(&prompt, 1_000).unwrap();
+        assert!(!prompt.exists());
+        assert_eq!(trashed, dir.join(TRASH_DIR).join("1000-review.md"));
+        assert!(discover_prompts_in(&dir, None).is_empty());
+        let listed = discover_trash_in(&dir, "workspace");
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].prompt.name, "review");
+        assert_eq!(listed[0].prompt.description.as_deref(), Some("Review"));
+        assert_eq!(listed[0].deleted_at, 1_000);
+
+        fs::write(&prompt, "newer").unwrap();
+        let (restored, name) = restore_trashed_file(&trashed).unwrap();
+        assert_eq!(name, "review-2");
+        assert_eq!(restored, dir.join("review-2.md"));
+        assert_eq!(fs::read_to_string(&prompt).unwrap(), "newer");
+        assert!(restore_trashed_file(&prompt).is_err());
+
+        let old = trash_prompt_file(&restored, 1_000).unwrap();
+        let recent = trash_prompt_file(&prompt, 40 * DAY_MS).unwrap();
+        assert_eq!(purge_trash_in(&dir, 0, 40 * DAY_MS), 0);
+        assert_eq!(purge_trash_in(&dir, 30, 40 * DAY_MS), 1);
+        assert!(!old.exists());
+        assert!(recent.exists());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -5,13 +5,15 @@ use std::sync::Arc;
 
 use tauri::{AppHandle, Emitter};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::TcpStream;
 use tokio::sync::{mpsc, oneshot, Mutex};
 
+use crate::backend::events::AppServerEvent;
+use crate::event_subscriptions::emit_routed_event;
+use crate::http_client;
 use crate::state::AppState;
 use crate::types::BackendMode;
 
-const DEFAULT_REMOTE_HOST: &str = "127.0.0.1:4732";
+pub(crate) const DEFAULT_REMOTE_HOST: &str = "127.0.0.1:4732";
 const DISCONNECTED_MESSAGE: &str = "remote backend disconnected";
 
 type PendingMap = HashMap<u64, oneshot::Sender<Result<Value, String>>>;
@@ -130,7 +132,8 @@ async fn ensure_remote_backend(state: &AppState, app: AppHandle) -> Result<Remot
         host
     };
 
-    let stream = TcpStream::connect(resolved_host.clone())
+    let proxy = http_client::proxy_for(state, None).await;
+    let stream = http_client::connect_tcp(&resolved_host, proxy.as_ref())
         .await
         .map_err(|err| format!("Failed to connect to remote backend at {resolved_host}: {err}"))?;
     let (reader, mut writer) = stream.into_split();
@@ -245,7 +248,19 @@ async fn read_loop(
         let params = message.get("params").cloned().unwrap_or(Value::Null);
         match method {
             "app-server-event" => {
-                let _ = app.emit("app-server-event", params);
+                if let (Some(workspace_id), Some(message)) = (
+                    params.get("workspace_id").and_then(Value::as_str),
+                    params.get("message"),
+                ) {
+                    crate::blocking::observe_app_server_event(&app, workspace_id, message);
+                    let event = AppServerEvent {
+                        workspace_id: workspace_id.to_string(),
+                        message: message.clone(),
+                    };
+                    emit_routed_event(&app, event);
+                } else {
+                    let _ = app.emit("app-server-event", params);
+                }
             }
             "terminal-output" => {
                 let _ = app.emit("terminal-output", params);
@@ -4,14 +4,104 @@ use std::path::{Path, PathBuf};
 use std::thread;
 use std::time::{Duration, Instant, SystemTime};
 
+use serde::{Deserialize, Serialize};
+
+use crate::backend::app_server::now_ms;
+
 const RULES_DIR: &str = "rules";
 const DEFAULT_RULES_FILE: &str = "default.rules";
+/// Version written to exported rule files.
+const RULES_EXPORT_VERSION: u32 = 1;
+
+/// What a remembered prefix rule does with a matching approval request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RuleDecision {
+    #[default]
+    Allow,
+    Deny,
+}
+
+impl RuleDecision {
+    /// Value of the rule's `decision` field; deny rules use MiCode's `forbidden`.
+    fn rule_value(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "forbidden",
+        }
+    }
+
+    fn parse(raw_value: &str) -> Option<Self> {
+        let value = raw_value
+            .trim()
+            .trim_end_matches(',')
+            .trim()
+            .trim_matches(|ch| ch == '"' || ch == '\'');
+        match value {
+            "allow" => Some(Self::Allow),
+            "forbidden" | "deny" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct PrefixRule {
+    pub(crate) pattern: Vec<String>,
+    #[serde(default)]
+    pub(crate) decision: RuleDecision,
+}
+
+/// A rule of the rules file with the bookkeeping the app keeps beside it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StoredRule {
+    /// Assigned when the app first sees the rule; kept across edits.
+    pub(crate) id: String,
+    pub(crate) pattern: Vec<String>,
+    pub(crate) decision: RuleDecision,
+    pub(crate) created_at_ms: u64,
+    /// Approval requests the rule answered automatically.
+    pub(crate) hits: u64,
+}
+
+impl StoredRule {
+    fn matches(&self, rule: &PrefixRule) -> bool {
+        self.pattern == rule.pattern && self.decision == rule.decision
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RulesMeta {
+    #[serde(default)]
+    rules: Vec<StoredRule>,
+}
+
+/// Contents of an exported rules file.
+#[derive(Debug, Serialize, Deserialize)]
+struct RulesExport {
+    #[serde(default)]
+    version: u32,
+    rules: Vec<PrefixRule>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RulesImport {
+    pub(crate) added: usize,
+    /// Rules the file already had.
+    pub(crate) skipped: usize,
+}
 
 pub(crate) fn default_rules_path(agent_home: &Path) -> PathBuf {
     agent_home.join(RULES_DIR).join(DEFAULT_RULES_FILE)
 }
 
-pub(crate) fn append_prefix_rule(path: &Path, pattern: &[String]) -> Result<(), String> {
+pub(crate) fn append_prefix_rule(
+    path: &Path,
+    pattern: &[String],
+    decision: RuleDecision,
+) -> Result<(), String> {
     if pattern.is_empty() {
         return Err("empty command pattern".to_string());
     }
@@ -22,7 +112,7 @@ pub(crate) fn append_prefix_rule(path: &Path, pattern: &[String]) -> Result<(),
 
     let _lock = acquire_rules_lock(path)?;
     let existing = fs::read_to_string(path).unwrap_or_default();
-    if rule_already_present(&existing, pattern) {
+    if rule_already_present(&existing, pattern, decision) {
         return Ok(());
     }
     let mut updated = existing;
@@ -34,7 +124,7 @@ pub(crate) fn append_prefix_rule(path: &Path, pattern: &[String]) -> Result<(),
         updated.push('\n');
     }
 
-    let rule = format_prefix_rule(pattern);
+    let rule = format_prefix_rule(pattern, decision);
     updated.push_str(&rule);
 
     if !updated.ends_with('\n') {
@@ -44,21 +134,61 @@ pub(crate) fn append_prefix_rule(path: &Path, pattern: &[String]) -> Result<(),
     fs::write(path, updated).map_err(|err| err.to_string())
 }
 
-pub(crate) fn list_prefix_rules(path: &Path) -> Result<Vec<Vec<String>>, String> {
+pub(crate) fn list_prefix_rules(
+    path: &Path,
+    decision: RuleDecision,
+) -> Result<Vec<Vec<String>>, String> {
+    let rules = load_prefix_rules(path)
+        .into_iter()
+        .filter(|rule| rule.decision == decision)
+        .map(|rule| rule.pattern)
+        .collect::<Vec<_>>();
+    Ok(rules)
+}
+
+/// Every allow and deny rule in the file; a missing file has none.
+pub(crate) fn load_prefix_rules(path: &Path) -> Vec<PrefixRule> {
     let contents = fs::read_to_string(path).unwrap_or_default();
     let lines = contents
         .lines()
         .map(|line| line.to_string())
         .collect::<Vec<_>>();
-    let rules = parse_rule_blocks(&lines)
+    parse_rule_blocks(&lines)
         .into_iter()
-        .filter(|block| block.decision_allows)
-        .filter_map(|block| block.pattern)
-        .collect::<Vec<_>>();
-    Ok(rules)
+        .filter_map(|block| {
+            Some(PrefixRule {
+                pattern: block.pattern.filter(|pattern| !pattern.is_empty())?,
+                decision: block.decision?,
+            })
+        })
+        .collect()
+}
+
+/// The rule deciding `command`: a deny rule whose pattern prefixes the command tokens
+/// wins over any matching allow rule.
+pub(crate) fn match_prefix_rules<'a>(
+    rules: &'a [PrefixRule],
+    command: &[String],
+) -> Option<&'a PrefixRule> {
+    let command = normalize_pattern(command);
+    let matches = |rule: &&PrefixRule| {
+        !rule.pattern.is_empty()
+            && rule.pattern.len() <= command.len()
+            && rule.pattern == command[..rule.pattern.len()]
+    };
+    let matching = rules.iter().filter(matches).collect::<Vec<_>>();
+    matching
+        .iter()
+        .find(|rule| rule.decision == RuleDecision::Deny)
+        .or(matching.first())
+        .copied()
 }
 
-pub(crate) fn remove_prefix_rule(path: &Path, pattern: &[String]) -> Result<bool, String> {
+pub(crate) fn remove_prefix_rule(
+    path: &Path,
+    pattern: &[String],
+    decision: RuleDecision,
+) -> Result<bool, String> {
     let normalized_target = normalize_pattern(pattern);
     if normalized_target.is_empty() {
         return Err("empty command pattern".to_string());
@@ -82,7 +212,7 @@ pub(crate) fn remove_prefix_rule(path: &Path, pattern: &[String]) -> Result<bool
     let mut removed = false;
 
     for block in blocks {
-        if !block.decision_allows {
+        if block.decision != Some(decision) {
             continue;
         }
         if block.pattern.as_deref() != Some(normalized_target.as_slice()) {
@@ -103,7 +233,13 @@ pub(crate) fn remove_prefix_rule(path: &Path, pattern: &[String]) -> Result<bool
     let mut updated = lines
         .iter()
         .enumerate()
-        .filter_map(|(index, line)| if keep[index] { Some(line.as_str()) } else { None })
+        .filter_map(|(index, line)| {
+            if keep[index] {
+                Some(line.as_str())
+            } else {
+                None
+            }
+        })
         .collect::<Vec<_>>()
         .join("\n");
     if !updated.is_empty() {
@@ -113,6 +249,173 @@ pub(crate) fn remove_prefix_rule(path: &Path, pattern: &[String]) -> Result<bool
     Ok(true)
 }
 
+/// Ids, creation times and hit counts, kept beside the rules file since MiCode owns its
+/// format.
+fn rules_meta_path(path: &Path) -> PathBuf {
+    path.with_extension("rules.meta.json")
+}
+
+fn read_rules_meta(meta_path: &Path) -> RulesMeta {
+    fs::read_to_string(meta_path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn write_rules_meta(meta_path: &Path, meta: &RulesMeta) -> Result<(), String> {
+    if let Some(parent) = meta_path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let contents = serde_json::to_string_pretty(meta).map_err(|err| err.to_string())?;
+    fs::write(meta_path, contents).map_err(|err| err.to_string())
+}
+
+/// Pairs every rule of the file with its bookkeeping, giving new rules an id and
+/// dropping entries of rules removed from the file. Callers hold the meta lock.
+fn reconcile_rules_meta(path: &Path, meta_path: &Path) -> Result<Vec<StoredRule>, String> {
+    let mut meta = read_rules_meta(meta_path);
+    let mut changed = false;
+    let mut stored = Vec::new();
+    for rule in load_prefix_rules(path) {
+        if stored.iter().any(|entry: &StoredRule| entry.matches(&rule)) {
+            continue;
+        }
+        let entry = match meta.rules.iter().find(|entry| entry.matches(&rule)) {
+            Some(entry) => entry.clone(),
+            None => {
+                changed = true;
+                StoredRule {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    pattern: rule.pattern,
+                    decision: rule.decision,
+                    created_at_ms: now_ms(),
+                    hits: 0,
+                }
+            }
+        };
+        stored.push(entry);
+    }
+    if changed || stored.len() != meta.rules.len() {
+        meta.rules = stored.clone();
+        write_rules_meta(meta_path, &meta)?;
+    }
+    Ok(stored)
+}
+
+/// Every rule of the file with its id, creation time and hit count.
+pub(crate) fn list_stored_rules(path: &Path) -> Result<Vec<StoredRule>, String> {
+    let meta_path = rules_meta_path(path);
+    let _lock = acquire_rules_lock(&meta_path)?;
+    reconcile_rules_meta(path, &meta_path)
+}
+
+/// Removes the rule with `id`; `false` when no rule has it.
+pub(crate) fn delete_stored_rule(path: &Path, id: &str) -> Result<bool, String> {
+    let meta_path = rules_meta_path(path);
+    let _lock = acquire_rules_lock(&meta_path)?;
+    let stored = reconcile_rules_meta(path, &meta_path)?;
+    let Some(rule) = stored.iter().find(|rule| rule.id == id) else {
+        return Ok(false);
+    };
+    remove_prefix_rule(path, &rule.pattern, rule.decision)?;
+    reconcile_rules_meta(path, &meta_path)?;
+    Ok(true)
+}
+
+/// Replaces the pattern and/or decision of the rule with `id`, keeping its id, creation
+/// time and hits.
+pub(crate) fn update_stored_rule(
+    path: &Path,
+    id: &str,
+    pattern: Option<Vec<String>>,
+    decision: Option<RuleDecision>,
+) -> Result<StoredRule, String> {
+    let meta_path = rules_meta_path(path);
+    let _lock = acquire_rules_lock(&meta_path)?;
+    let mut stored = reconcile_rules_meta(path, &meta_path)?;
+    let current = stored
+        .iter()
+        .find(|rule| rule.id == id)
+        .cloned()
+        .ok_or_else(|| format!("approval rule not found: {id}"))?;
+    let pattern = pattern
+        .map(|pattern| normalize_pattern(&pattern))
+        .unwrap_or_else(|| current.pattern.clone());
+    if pattern.is_empty() {
+        return Err("empty command pattern".to_string());
+    }
+    let decision = decision.unwrap_or(current.decision);
+    let updated = StoredRule {
+        pattern,
+        decision,
+        ..current.clone()
+    };
+    if stored
+        .iter()
+        .any(|rule| rule.id != id && rule.pattern == updated.pattern && rule.decision == decision)
+    {
+        return Err("an identical approval rule already exists".to_string());
+    }
+    remove_prefix_rule(path, &current.pattern, current.decision)?;
+    append_prefix_rule(path, &updated.pattern, updated.decision)?;
+    stored.retain(|rule| rule.id != id);
+    stored.push(updated.clone());
+    write_rules_meta(&meta_path, &RulesMeta { rules: stored })?;
+    Ok(updated)
+}
+
+/// Counts one automatic answer by `rule`.
+pub(crate) fn record_rule_hit(path: &Path, rule: &PrefixRule) -> Result<(), String> {
+    let meta_path = rules_meta_path(path);
+    let _lock = acquire_rules_lock(&meta_path)?;
+    let mut rules = reconcile_rules_meta(path, &meta_path)?;
+    if let Some(entry) = rules.iter_mut().find(|entry| entry.matches(rule)) {
+        entry.hits += 1;
+        write_rules_meta(&meta_path, &RulesMeta { rules })?;
+    }
+    Ok(())
+}
+
+/// Writes every rule to `destination` as JSON; returns how many were written.
+pub(crate) fn export_rules(path: &Path, destination: &Path) -> Result<usize, String> {
+    let export = RulesExport {
+        version: RULES_EXPORT_VERSION,
+        rules: load_prefix_rules(path),
+    };
+    let contents = serde_json::to_string_pretty(&export).map_err(|err| err.to_string())?;
+    fs::write(destination, contents).map_err(|err| err.to_string())?;
+    Ok(export.rules.len())
+}
+
+/// Adds the rules of a file written by `export_rules`; rules already present are skipped.
+pub(crate) fn import_rules(path: &Path, source: &Path) -> Result<RulesImport, String> {
+    let contents = fs::read_to_string(source).map_err(|err| err.to_string())?;
+    let export: RulesExport =
+        serde_json::from_str(&contents).map_err(|err| format!("invalid rules file: {err}"))?;
+    if export.version > RULES_EXPORT_VERSION {
+        return Err(format!(
+            "rules file version {} is newer than supported version {RULES_EXPORT_VERSION}",
+            export.version
+        ));
+    }
+    let mut existing = load_prefix_rules(path);
+    let mut summary = RulesImport::default();
+    for rule in export.rules {
+        let rule = PrefixRule {
+            pattern: normalize_pattern(&rule.pattern),
+            decision: rule.decision,
+        };
+        if rule.pattern.is_empty() || existing.contains(&rule) {
+            summary.skipped += 1;
+            continue;
+        }
+        append_prefix_rule(path, &rule.pattern, rule.decision)?;
+        existing.push(rule);
+        summary.added += 1;
+    }
+    Ok(summary)
+}
+
 struct RulesFileLock {
     path: PathBuf,
 }
@@ -163,9 +466,10 @@ fn is_lock_stale(path: &Path, stale_after: Duration) -> bool {
     age > stale_after
 }
 
-fn format_prefix_rule(pattern: &[String]) -> String {
+fn format_prefix_rule(pattern: &[String], decision: RuleDecision) -> String {
     let items = format_pattern_list(pattern);
-    format!("prefix_rule(\n    pattern = [{items}],\n    decision = \"allow\",\n)\n")
+    let decision = decision.rule_value();
+    format!("prefix_rule(\n    pattern = [{items}],\n    decision = \"{decision}\",\n)\n")
 }
 
 fn format_pattern_list(pattern: &[String]) -> String {
@@ -176,14 +480,14 @@ fn format_pattern_list(pattern: &[String]) -> String {
         .join(", ")
 }
 
-fn rule_already_present(contents: &str, pattern: &[String]) -> bool {
+fn rule_already_present(contents: &str, pattern: &[String], decision: RuleDecision) -> bool {
     let target = normalize_pattern(pattern);
     let lines = contents
         .lines()
         .map(|line| line.to_string())
         .collect::<Vec<_>>();
     parse_rule_blocks(&lines).into_iter().any(|block| {
-        block.decision_allows && block.pattern.as_deref() == Some(target.as_slice())
+        block.decision == Some(decision) && block.pattern.as_deref() == Some(target.as_slice())
     })
 }
 
@@ -200,7 +504,7 @@ struct ParsedRuleBlock {
     start: usize,
     end: usize,
     pattern: Option<Vec<String>>,
-    decision_allows: bool,
+    decision: Option<RuleDecision>,
 }
 
 fn parse_rule_blocks(lines: &[String]) -> Vec<ParsedRuleBlock> {
@@ -208,7 +512,7 @@ fn parse_rule_blocks(lines: &[String]) -> Vec<ParsedRuleBlock> {
     let mut in_rule = false;
     let mut start = 0usize;
     let mut pattern: Option<Vec<String>> = None;
-    let mut decision_allows = false;
+    let mut decision = None;
 
     for (index, line) in lines.iter().enumerate() {
         let trimmed = line.trim();
@@ -216,7 +520,7 @@ fn parse_rule_blocks(lines: &[String]) -> Vec<ParsedRuleBlock> {
             in_rule = true;
             start = index;
             pattern = None;
-            decision_allows = false;
+            decision = None;
             continue;
         }
         if !in_rule {
@@ -228,17 +532,14 @@ fn parse_rule_blocks(lines: &[String]) -> Vec<ParsedRuleBlock> {
             }
         } else if trimmed.starts_with("decision") {
             if let Some((_, value)) = trimmed.split_once('=') {
-                let candidate = value.trim().trim_end_matches(',');
-                if candidate.contains("\"allow\"") || candidate.contains("'allow'") {
-                    decision_allows = true;
-                }
+                decision = RuleDecision::parse(value);
             }
         } else if trimmed.starts_with(')') {
             blocks.push(ParsedRuleBlock {
                 start,
                 end: index,
                 pattern: pattern.clone(),
-                decision_allows,
+                decision,
             });
             in_rule = false;
         }
@@ -272,3 +573,179 @@ fn escape_string(value: &str) -> String {
         .replace('\r', "\\r")
         .replace('\t', "\\t")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        append_prefix_rule, delete_stored_rule, export_rules, import_rules, list_prefix_rules,
+        list_stored_rules, load_prefix_rules, match_prefix_rules, record_rule_hit,
+        remove_prefix_rule, update_stored_rule, PrefixRule, RuleDecision, RulesImport,
+    };
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    fn tokens(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    fn rules_file() -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("micode-rules-{}", Uuid::new_v4()));
+        let path = root.join("rules/default.rules");
+        (root, path)
+    }
+
+    fn rule(pattern: &[&str], decision: RuleDecision) -> PrefixRule {
+        PrefixRule {
+            pattern: tokens(pattern),
+            decision,
+        }
+    }
+
+    #[test]
+    fn rules_match_whole_leading_tokens() {
+        let rules = vec![rule(&["git", "status"], RuleDecision::Allow)];
+        let matched = match_prefix_rules(&rules, &tokens(&["git", "status", "--short"]));
+        assert_eq!(matched, Some(&rules[0]));
+        assert!(match_prefix_rules(&rules, &tokens(&[" git ", "status"])).is_some());
+        assert!(match_prefix_rules(&rules, &tokens(&["git"])).is_none());
+        assert!(match_prefix_rules(&rules, &tokens(&["git", "stat"])).is_none());
+        assert!(match_prefix_rules(&rules, &tokens(&["sudo", "git", "status"])).is_none());
+    }
+
+    #[test]
+    fn deny_rules_win_over_allow_rules() {
+        let rules = vec![
+            rule(&["git"], RuleDecision::Allow),
+            rule(&["git", "push", "--force"], RuleDecision::Deny),
+        ];
+        let push = match_prefix_rules(&rules, &tokens(&["git", "push", "--force", "origin"]));
+        assert_eq!(push.map(|rule| rule.decision), Some(RuleDecision::Deny));
+        let log = match_prefix_rules(&rules, &tokens(&["git", "log"]));
+        assert_eq!(log.map(|rule| rule.decision), Some(RuleDecision::Allow));
+    }
+
+    #[test]
+    fn allow_and_deny_rules_share_the_rules_file() {
+        let (root, path) = rules_file();
+        let pattern = tokens(&["rm", "-rf"]);
+        append_prefix_rule(&path, &tokens(&["npm", "test"]), RuleDecision::Allow).unwrap();
+        append_prefix_rule(&path, &pattern, RuleDecision::Deny).unwrap();
+        append_prefix_rule(&path, &pattern, RuleDecision::Deny).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.matches("decision = \"forbidden\"").count(), 1);
+        assert_eq!(
+            list_prefix_rules(&path, RuleDecision::Allow).unwrap(),
+            vec![tokens(&["npm", "test"])]
+        );
+        assert_eq!(load_prefix_rules(&path).len(), 2);
+
+        assert!(!remove_prefix_rule(&path, &pattern, RuleDecision::Allow).unwrap());
+        assert!(remove_prefix_rule(&path, &pattern, RuleDecision::Deny).unwrap());
+        assert!(list_prefix_rules(&path, RuleDecision::Deny)
+            .unwrap()
+            .is_empty());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn stored_rule_ids_survive_reloads_and_edits() {
+        let (root, path) = rules_file();
+        append_prefix_rule(&path, &tokens(&["npm", "test"]), RuleDecision::Allow).unwrap();
+        append_prefix_rule(&path, &tokens(&["rm"]), RuleDecision::Deny).unwrap();
+
+        let first = list_stored_rules(&path).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_ne!(first[0].id, first[1].id);
+        assert_eq!(list_stored_rules(&path).unwrap(), first);
+
+        // A rule remembered later gets its own id; the others keep theirs.
+        append_prefix_rule(&path, &tokens(&["cargo"]), RuleDecision::Allow).unwrap();
+        let second = list_stored_rules(&path).unwrap();
+        assert_eq!(second[..2], first[..]);
+
+        let edited = update_stored_rule(
+            &path,
+            &first[0].id,
+            Some(tokens(&["npm", "run", "test"])),
+            None,
+        )
+        .unwrap();
+        assert_eq!(edited.id, first[0].id);
+        assert_eq!(edited.created_at_ms, first[0].created_at_ms);
+        assert_eq!(
+            list_prefix_rules(&path, RuleDecision::Allow).unwrap(),
+            vec![tokens(&["cargo"]), tokens(&["npm", "run", "test"])]
+        );
+        assert!(list_stored_rules(&path).unwrap().contains(&edited));
+        assert!(update_stored_rule(
+            &path,
+            &first[1].id,
+            Some(tokens(&["cargo"])),
+            Some(RuleDecision::Allow)
+        )
+        .is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn deleted_rules_stay_deleted() {
+        let (root, path) = rules_file();
+        append_prefix_rule(&path, &tokens(&["npm", "test"]), RuleDecision::Allow).unwrap();
+        append_prefix_rule(&path, &tokens(&["rm"]), RuleDecision::Deny).unwrap();
+        let stored = list_stored_rules(&path).unwrap();
+
+        assert!(delete_stored_rule(&path, &stored[1].id).unwrap());
+        assert!(!delete_stored_rule(&path, &stored[1].id).unwrap());
+        assert_eq!(
+            load_prefix_rules(&path),
+            vec![rule(&["npm", "test"], RuleDecision::Allow)]
+        );
+        assert_eq!(list_stored_rules(&path).unwrap(), stored[..1]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn hits_count_automatic_answers() {
+        let (root, path) = rules_file();
+        append_prefix_rule(&path, &tokens(&["git", "status"]), RuleDecision::Allow).unwrap();
+        let matched = rule(&["git", "status"], RuleDecision::Allow);
+        record_rule_hit(&path, &matched).unwrap();
+        record_rule_hit(&path, &matched).unwrap();
+        record_rule_hit(&path, &rule(&["git", "status"], RuleDecision::Deny)).unwrap();
+
+        let stored = list_stored_rules(&path).unwrap();
+        assert_eq!(stored[0].hits, 2);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn exported_rules_import_into_another_home() {
+        let (root, path) = rules_file();
+        let (other_root, other_path) = rules_file();
+        append_prefix_rule(&path, &tokens(&["npm", "test"]), RuleDecision::Allow).unwrap();
+        append_prefix_rule(&path, &tokens(&["rm"]), RuleDecision::Deny).unwrap();
+        append_prefix_rule(&other_path, &tokens(&["rm"]), RuleDecision::Deny).unwrap();
+
+        let export = root.join("rules.json");
+        assert_eq!(export_rules(&path, &export).unwrap(), 2);
+        assert_eq!(
+            import_rules(&other_path, &export).unwrap(),
+            RulesImport {
+                added: 1,
+                skipped: 1,
+            }
+        );
+        assert_eq!(load_prefix_rules(&other_path).len(), 2);
+
+        std::fs::write(&export, "{\"version\": 9, \"rules\": []}").unwrap();
+        assert!(import_rules(&other_path, &export).is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+        let _ = std::fs::remove_dir_all(&other_root);
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Serializes writers so concurrent updates to different keys don't drop each other
+/// or race on the shared temp file.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Reads `key` from the secrets file next to `settings.json`. Secrets never go through
+/// `AppSettings`, so they stay out of settings exports and the settings UI.
+pub(crate) fn read_secret(path: &Path, key: &str) -> Option<String> {
+    read_all(path).remove(key)
+}
+
+/// Stores `value` under `key`, or removes the key when `value` is `None` or empty.
+pub(crate) fn write_secret(path: &Path, key: &str, value: Option<&str>) -> Result<(), String> {
+    let _guard = WRITE_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut secrets = read_all(path);
+    match value.filter(|value| !value.is_empty()) {
+        Some(value) => {
+            secrets.insert(key.to_string(), value.to_string());
+        }
+        None => {
+            if secrets.remove(key).is_none() {
+                return Ok(());
+            }
+        }
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let raw = serde_json::to_string_pretty(&secrets).map_err(|err| err.to_string())?;
+    write_private_file(path, &raw)
+}
+
+fn read_all(path: &Path) -> BTreeMap<String, String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Writes a temp file that is only readable by the user from the moment it exists and
+/// renames it over `path`, so the secrets are never readable by others nor half written.
+fn write_private_file(path: &Path, contents: &str) -> Result<(), String> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    // A temp file left by an interrupted write may carry other permissions.
+    let _ = std::fs::remove_file(&tmp_path);
+    let written = create_private(&tmp_path).and_then(|mut file| {
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    });
+    written
+        .and_then(|()| std::fs::rename(&tmp_path, path))
+        .map_err(|err| {
+            let _ = std::fs::remove_file(&tmp_path);
+            err.to_string()
+        })
+}
+
+#[cfg(unix)]
+fn create_private(path: &Path) -> std::io::Result<std::fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn create_private(path: &Path) -> std::io::Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn writes_reads_and_removes_secrets() {
+        let dir = std::env::temp_dir().join(format!("micode-secrets-{}", Uuid::new_v4()));
+        let path = dir.join("secrets.json");
+        assert_eq!(read_secret(&path, "proxy.password"), None);
+
+        write_secret(&path, "proxy.password", Some("hunter2")).expect("write secret");
+        write_secret(&path, "proxy.password.ws-1", Some("s3cret")).expect("write secret");
+        assert_eq!(
+            read_secret(&path, "proxy.password").as_deref(),
+            Some("hunter2")
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path)
+                .expect("metadata")
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert!(!dir.join("secrets.json.tmp").exists());
+
+        write_secret(&path, "proxy.password", None).expect("remove secret");
+        assert_eq!(read_secret(&path, "proxy.password"), None);
+        assert_eq!(
+            read_secret(&path, "proxy.password.ws-1").as_deref(),
+            Some("s3cret")
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn concurrent_writes_keep_every_key() {
+        let dir = std::env::temp_dir().join(format!("micode-secrets-{}", Uuid::new_v4()));
+        let path = dir.join("secrets.json");
+        let writers: Vec<_> = (0..8)
+            .map(|index| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    write_secret(&path, &format!("key-{index}"), Some("value"))
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().expect("writer thread").expect("write secret");
+        }
+
+        assert_eq!(read_all(&path).len(), 8);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,37 +1,65 @@
 use tauri::{Manager, State, Window};
 
+use crate::backend::events::EventSink;
+use crate::event_sink::TauriEventSink;
+use crate::http_client;
+use crate::menu;
+use crate::micode::args::parse_micode_args;
 use crate::shared::settings_core::{
-    get_app_settings_core, get_micode_config_path_core, update_app_settings_core,
+    apply_backend_settings, apply_session_settings, get_app_settings_core,
+    get_micode_config_path_core, update_app_settings_core,
 };
-use crate::menu;
 use crate::state::AppState;
+use crate::types::errors::CommandError;
 use crate::types::AppSettings;
 use crate::window;
+use crate::workspaces::refresh_stale_sessions;
 
 #[tauri::command]
 pub(crate) async fn get_app_settings(
     state: State<'_, AppState>,
     window: Window,
 ) -> Result<AppSettings, String> {
-    let settings = get_app_settings_core(&state.app_settings).await;
+    let settings = get_app_settings_core(&state.app_settings, &state.settings_revision).await;
     let _ = window::apply_window_appearance(&window, settings.theme.as_str());
     Ok(settings)
 }
 
 #[tauri::command]
 pub(crate) async fn update_app_settings(
-    settings: AppSettings,
+    mut settings: AppSettings,
     state: State<'_, AppState>,
     window: Window,
-) -> Result<AppSettings, String> {
-    let updated =
-        update_app_settings_core(settings, &state.app_settings, &state.settings_path).await?;
+) -> Result<AppSettings, CommandError> {
+    parse_micode_args(settings.agent_args.as_deref())?;
+    http_client::store_url_credentials(&mut settings.proxy, &state.secrets_path, None)?;
+    let (updated, event) = update_app_settings_core(
+        settings,
+        &state.app_settings,
+        &state.settings_path,
+        &state.settings_revision,
+    )
+    .await?;
     let _ = window::apply_window_appearance(&window, updated.theme.as_str());
+    state.usage_counters.configure(
+        Some(&state.data_dir),
+        &updated,
+        &*state.workspaces.lock().await,
+    );
+    apply_backend_settings(&updated, &state.connect_queue, &state.command_timings);
+    apply_session_settings(&updated, &state.sessions).await;
     menu::set_menu_language_zh(updated.language.trim().eq_ignore_ascii_case("zh"));
     let _ = menu::rebuild_menu(&window.app_handle());
+    TauriEventSink::new(window.app_handle().clone()).emit_app_server_event(event);
+    refresh_stale_sessions(&state, window.app_handle()).await;
     Ok(updated)
 }
 
+#[tauri::command]
+pub(crate) async fn get_settings_revision(state: State<'_, AppState>) -> Result<u64, String> {
+    Ok(state.settings_revision.current())
+}
+
 #[tauri::command]
 pub(crate) async fn get_micode_config_path() -> Result<String, String> {
     get_micode_config_path_core()
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tokio::sync::{Mutex, Notify};
+use uuid::Uuid;
+
+use crate::backend::app_server::{now_ms, WorkspaceSession};
+use crate::backend::auto_run::{
+    agent_signals_done, clamp_max_duration_secs, clamp_max_turns, continuation_prompt, goal_prompt,
+    load_runs, save_run, AutoRunRequest, AutoRunState, AutoRunStatus,
+};
+use crate::backend::event_methods;
+use crate::shared::micode_core::access_mode_policies;
+use crate::types::errors::CommandError;
+
+/// Controls of a run that is looping in this process.
+struct RunControl {
+    workspace_id: String,
+    thread_id: String,
+    cancelled: AtomicBool,
+    interrupt: Notify,
+}
+
+/// Runs looping in this process, by run id. App and daemon state each own one; sessions
+/// get a handle when they spawn so approvals and resumed runs reach it.
+#[derive(Clone, Default)]
+pub(crate) struct AutoRunRegistry {
+    runs: Arc<std::sync::Mutex<HashMap<String, Arc<RunControl>>>>,
+}
+
+impl AutoRunRegistry {
+    fn register(&self, run: &AutoRunState, workspace_id: &str) -> Option<Arc<RunControl>> {
+        let mut runs = self.runs.lock().ok()?;
+        if runs.contains_key(&run.id) {
+            return None;
+        }
+        let control = Arc::new(RunControl {
+            workspace_id: workspace_id.to_string(),
+            thread_id: run.thread_id.clone(),
+            cancelled: AtomicBool::new(false),
+            interrupt: Notify::new(),
+        });
+        runs.insert(run.id.clone(), Arc::clone(&control));
+        Some(control)
+    }
+
+    fn unregister(&self, run_id: &str) {
+        if let Ok(mut runs) = self.runs.lock() {
+            runs.remove(run_id);
+        }
+    }
+
+    fn control(&self, run_id: &str) -> Option<Arc<RunControl>> {
+        self.runs
+            .lock()
+            .ok()
+            .and_then(|runs| runs.get(run_id).cloned())
+    }
+
+    fn active_run_for_thread(&self, workspace_id: &str, thread_id: &str) -> Option<String> {
+        self.runs.lock().ok().and_then(|runs| {
+            runs.iter()
+                .find(|(_, control)| {
+                    control.workspace_id == workspace_id && control.thread_id == thread_id
+                })
+                .map(|(run_id, _)| run_id.clone())
+        })
+    }
+}
+
+fn emit_progress(session: &WorkspaceSession, run: &AutoRunState, phase: &str) {
+    session.emit_event(
+        event_methods::AUTO_RUN_PROGRESS,
+        json!({
+            "workspaceId": session.entry.id,
+            "threadId": run.thread_id,
+            "runId": run.id,
+            "phase": phase,
+            "run": run,
+        }),
+    );
+}
+
+/// Lets the UI know a looping run is blocked on an approval; the turn itself simply
+/// waits for the answer, so the loop needs no extra pausing.
+pub(crate) fn notify_awaiting_approval(
+    session: &WorkspaceSession,
+    registry: &AutoRunRegistry,
+    thread_id: &str,
+) {
+    if thread_id.is_empty() {
+        return;
+    }
+    let Some(run_id) = registry.active_run_for_thread(&session.entry.id, thread_id) else {
+        return;
+    };
+    session.emit_event(
+        event_methods::AUTO_RUN_PROGRESS,
+        json!({
+            "workspaceId": session.entry.id,
+            "threadId": thread_id,
+            "runId": run_id,
+            "phase": "awaitingApproval",
+        }),
+    );
+}
+
+fn turn_params(session: &WorkspaceSession, run: &AutoRunState, text: String) -> Value {
+    let access_mode = run.access_mode.as_deref().unwrap_or("current");
+    let (sandbox_policy, approval_policy) = access_mode_policies(access_mode, &session.entry.path);
+    json!({
+        "threadId": run.thread_id,
+        "input": [{ "type": "text", "text": text }],
+        "cwd": session.entry.path,
+        "approvalPolicy": approval_policy,
+        "sandboxPolicy": sandbox_policy,
+    })
+}
+
+async fn finish(
+    session: &WorkspaceSession,
+    registry: &AutoRunRegistry,
+    run: &mut AutoRunState,
+    status: AutoRunStatus,
+) {
+    run.status = status;
+    run.updated_at_ms = now_ms();
+    let _ = save_run(&session.entry.path, run);
+    let item = run.summary_item();
+    session
+        .persist_thread_item(&run.thread_id, item.clone())
+        .await;
+    session.emit_event(
+        event_methods::ITEM_COMPLETED,
+        json!({ "threadId": run.thread_id, "item": item }),
+    );
+    emit_progress(session, run, "finished");
+    registry.unregister(&run.id);
+}
+
+/// Waits until the run's thread has no running or queued turn and claims it, so the run
+/// never overlaps a user turn. Returns `false` when the run was interrupted or ran out of
+/// time first, or was cancelled while it waited.
+async fn claim_thread(
+    session: &WorkspaceSession,
+    control: &RunControl,
+    run: &AutoRunState,
+) -> bool {
+    let remaining = Duration::from_millis(run.deadline_ms().saturating_sub(now_ms()));
+    let mut claim = std::pin::pin!(session.claim_idle_thread(&run.thread_id));
+    let mut interrupted = std::pin::pin!(control.interrupt.notified());
+    let claim = std::future::poll_fn(|cx| {
+        if interrupted.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(false);
+        }
+        claim.as_mut().poll(cx).map(|()| true)
+    });
+    let claimed = tokio::time::timeout(remaining, claim)
+        .await
+        .unwrap_or(false);
+    if claimed && control.cancelled.load(Ordering::SeqCst) {
+        session.finish_thread_turn(&run.thread_id);
+        return false;
+    }
+    claimed
+}
+
+/// Sends one turn on the claimed thread and waits for it. Cancelling with `interrupt` or
+/// hitting the deadline interrupts the turn instead of abandoning it, so the thread stays
+/// consistent.
+async fn run_turn(
+    session: &Arc<WorkspaceSession>,
+    control: &Arc<RunControl>,
+    run: &AutoRunState,
+    text: String,
+) -> Result<Value, String> {
+    let remaining = Duration::from_millis(run.deadline_ms().saturating_sub(now_ms()));
+    let watcher = {
+        let session = Arc::clone(session);
+        let control = Arc::clone(control);
+        let thread_id = run.thread_id.clone();
+        tokio::spawn(async move {
+            // Either an interrupt request or the deadline ends the wait.
+            let _ = tokio::time::timeout(remaining, control.interrupt.notified()).await;
+            let _ = session
+                .send_request("turn/interrupt", json!({ "threadId": thread_id }))
+                .await;
+        })
+    };
+    let result = session
+        .send_request("turn/start", turn_params(session, run, text))
+        .await;
+    watcher.abort();
+    Ok(result?)
+}
+
+async fn drive(
+    session: Arc<WorkspaceSession>,
+    registry: AutoRunRegistry,
+    control: Arc<RunControl>,
+    mut run: AutoRunState,
+) {
+    loop {
+        if control.cancelled.load(Ordering::SeqCst) {
+            finish(&session, &registry, &mut run, AutoRunStatus::Cancelled).await;
+            return;
+        }
+        if let Some(status) = run.limit_reached(now_ms()) {
+            finish(&session, &registry, &mut run, status).await;
+            return;
+        }
+        if !claim_thread(&session, &control, &run).await {
+            continue;
+        }
+        let prompt = if run.iteration == 0 {
+            goal_prompt(&run.goal)
+        } else {
+            continuation_prompt(run.iteration + 1, run.max_turns)
+        };
+        run.iteration += 1;
+        run.updated_at_ms = now_ms();
+        let _ = save_run(&session.entry.path, &run);
+        emit_progress(&session, &run, "turnStarted");
+
+        let response = match run_turn(&session, &control, &run, prompt).await {
+            Ok(response) => response,
+            Err(error) => {
+                run.error = Some(error);
+                finish(&session, &registry, &mut run, AutoRunStatus::Failed).await;
+                return;
+            }
+        };
+        let result = response.get("result").unwrap_or(&response);
+        let turn_id = result
+            .get("turn")
+            .and_then(|turn| turn.get("id"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let reply = session.turn_agent_text(&run.thread_id, turn_id).await;
+        run.updated_at_ms = now_ms();
+        let _ = save_run(&session.entry.path, &run);
+        emit_progress(&session, &run, "turnCompleted");
+        if agent_signals_done(&reply) {
+            finish(&session, &registry, &mut run, AutoRunStatus::Completed).await;
+            return;
+        }
+        if result.get("stopReason").and_then(Value::as_str) == Some("cancelled")
+            && !control.cancelled.load(Ordering::SeqCst)
+            && run.limit_reached(now_ms()).is_none()
+        {
+            // Interrupted from the composer: treat it like a cancel of the whole run.
+            control.cancelled.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+fn spawn_run(
+    session: &Arc<WorkspaceSession>,
+    registry: &AutoRunRegistry,
+    run: AutoRunState,
+) -> Result<(), String> {
+    let control = registry
+        .register(&run, &session.entry.id)
+        .ok_or_else(|| format!("auto run {} is already running", run.id))?;
+    tokio::spawn(drive(Arc::clone(session), registry.clone(), control, run));
+    Ok(())
+}
+
+/// Picks up runs that were still looping when the app or daemon stopped.
+pub(crate) async fn resume_auto_runs(session: &Arc<WorkspaceSession>, registry: &AutoRunRegistry) {
+    for mut run in load_runs(&session.entry.path) {
+        if run.status.is_finished() {
+            continue;
+        }
+        if !session.has_thread(&run.thread_id).await {
+            run.error = Some("thread no longer exists".to_string());
+            run.status = AutoRunStatus::Failed;
+            run.updated_at_ms = now_ms();
+            let _ = save_run(&session.entry.path, &run);
+            continue;
+        }
+        let _ = spawn_run(session, registry, run);
+    }
+}
+
+async fn get_session_clone(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: &str,
+) -> Result<Arc<WorkspaceSession>, CommandError> {
+    sessions
+        .lock()
+        .await
+        .get(workspace_id)
+        .cloned()
+        .ok_or_else(CommandError::workspace_not_connected)
+}
+
+pub(crate) async fn start_auto_run_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    registry: &AutoRunRegistry,
+    workspace_id: String,
+    request: AutoRunRequest,
+) -> Result<Value, CommandError> {
+    let goal = request.goal.trim().to_string();
+    if goal.is_empty() {
+        return Err("auto run goal is empty".into());
+    }
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let thread_id = match request.thread_id.filter(|id| !id.trim().is_empty()) {
+        Some(thread_id) => {
+            if !session.has_thread(&thread_id).await {
+                return Err(CommandError::thread_not_found(&thread_id));
+            }
+            if registry
+                .active_run_for_thread(&workspace_id, &thread_id)
+                .is_some()
+            {
+                return Err("an auto run is already active in this thread".into());
+            }
+            thread_id
+        }
+        None => {
+            let response = session
+                .send_request("thread/start", json!({ "cwd": session.entry.path }))
+                .await?;
+            response
+                .get("result")
+                .and_then(|result| result.get("thread"))
+                .and_then(|thread| thread.get("id"))
+                .and_then(Value::as_str)
+                .map(ToString::to_string)
+                .ok_or_else(|| "thread/start returned no thread id".to_string())?
+        }
+    };
+    let now = now_ms();
+    let run = AutoRunState {
+        id: Uuid::new_v4().to_string(),
+        thread_id,
+        goal,
+        max_turns: clamp_max_turns(request.max_turns),
+        max_duration_secs: clamp_max_duration_secs(request.max_duration_secs),
+        access_mode: request.access_mode.filter(|mode| !mode.trim().is_empty()),
+        iteration: 0,
+        started_at_ms: now,
+        updated_at_ms: now,
+        status: AutoRunStatus::Running,
+        error: None,
+    };
+    save_run(&session.entry.path, &run)?;
+    spawn_run(&session, registry, run.clone())?;
+    Ok(json!({ "run": run }))
+}
+
+/// Stops a run before its next turn; with `interrupt` the current turn is interrupted
+/// as well.
+pub(crate) async fn cancel_auto_run_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    registry: &AutoRunRegistry,
+    workspace_id: String,
+    run_id: String,
+    interrupt: bool,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    if let Some(control) = registry.control(&run_id) {
+        control.cancelled.store(true, Ordering::SeqCst);
+        if interrupt {
+            control.interrupt.notify_one();
+        }
+        return Ok(json!({ "runId": run_id, "cancelled": true }));
+    }
+    // Not looping in this process (e.g. never resumed): settle the checkpoint directly.
+    let mut run = load_runs(&session.entry.path)
+        .into_iter()
+        .find(|run| run.id == run_id)
+        .ok_or_else(|| format!("auto run not found: {run_id}"))?;
+    if run.status.is_finished() {
+        return Ok(json!({ "runId": run_id, "cancelled": false }));
+    }
+    finish(&session, registry, &mut run, AutoRunStatus::Cancelled).await;
+    Ok(json!({ "runId": run_id, "cancelled": true }))
+}
+
+pub(crate) async fn list_auto_runs_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let mut runs = load_runs(&session.entry.path);
+    runs.sort_by_key(|run| std::cmp::Reverse(run.started_at_ms));
+    Ok(json!({ "runs": runs }))
+}
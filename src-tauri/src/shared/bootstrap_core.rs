@@ -0,0 +1,287 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::backend::event_methods;
+use crate::backend::events::AppServerEvent;
+use crate::git_utils::{list_git_roots, resolve_git_root};
+use crate::types::{WorkspaceBootstrapWarning, WorkspaceBootstrapWarningKind, WorkspaceEntry};
+
+const NESTED_REPO_SCAN_DEPTH: usize = 3;
+const NESTED_REPO_SCAN_LIMIT: usize = 20;
+const STATE_DIR_NAME: &str = ".micodemonitor";
+
+/// Path fragments of folders managed by sync clients that lock or rewrite files while
+/// the agent and the git index are busy with them.
+const CLOUD_SYNC_MARKERS: &[(&str, &str)] = &[
+    ("/library/mobile documents/", "iCloud Drive"),
+    ("/library/cloudstorage/", "a cloud storage provider"),
+    ("/icloud drive/", "iCloud Drive"),
+    ("/icloudrive/", "iCloud Drive"),
+    ("/dropbox/", "Dropbox"),
+    ("/onedrive/", "OneDrive"),
+    ("/google drive/", "Google Drive"),
+];
+
+fn has_git_marker(path: &Path) -> bool {
+    let marker = path.join(".git");
+    marker.is_dir() || marker.is_file()
+}
+
+fn enclosing_git_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|ancestor| has_git_marker(ancestor))
+        .map(Path::to_path_buf)
+}
+
+fn display(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// Name of the sync client that manages `path`, if any.
+pub(crate) fn cloud_sync_provider(path: &Path) -> Option<&'static str> {
+    let normalized = format!(
+        "{}/",
+        path.to_string_lossy().replace('\\', "/").to_lowercase()
+    );
+    CLOUD_SYNC_MARKERS
+        .iter()
+        .find(|(marker, _)| normalized.contains(marker))
+        .map(|(_, provider)| *provider)
+        .or_else(|| {
+            // Dropbox and OneDrive for Business add suffixes such as `Dropbox (Personal)`.
+            path.components()
+                .filter_map(|component| component.as_os_str().to_str())
+                .find_map(|name| {
+                    let lower = name.to_lowercase();
+                    if lower.starts_with("dropbox (") {
+                        Some("Dropbox")
+                    } else if lower.starts_with("onedrive - ") {
+                        Some("OneDrive")
+                    } else {
+                        None
+                    }
+                })
+        })
+}
+
+fn check_git(entry: &WorkspaceEntry, warnings: &mut Vec<WorkspaceBootstrapWarning>) {
+    let workspace_path = PathBuf::from(&entry.path);
+    let git_root = match resolve_git_root(entry) {
+        Ok(root) => root,
+        Err(error) => {
+            warnings.push(WorkspaceBootstrapWarning {
+                kind: WorkspaceBootstrapWarningKind::NotGitRepo,
+                message: error,
+                suggestion: Some("Fix or clear the git root in workspace settings.".to_string()),
+                paths: Vec::new(),
+            });
+            return;
+        }
+    };
+    if has_git_marker(&git_root) {
+        let nested = list_git_roots(&git_root, NESTED_REPO_SCAN_DEPTH, NESTED_REPO_SCAN_LIMIT);
+        if !nested.is_empty() {
+            warnings.push(WorkspaceBootstrapWarning {
+                kind: WorkspaceBootstrapWarningKind::NestedRepositories,
+                message: format!(
+                    "Found {} nested git repositories; their changes do not show up in this workspace's git status.",
+                    nested.len()
+                ),
+                suggestion: Some(
+                    "Add nested repositories as their own workspaces, or use them as submodules."
+                        .to_string(),
+                ),
+                paths: nested,
+            });
+        }
+        return;
+    }
+    if let Some(root) = enclosing_git_root(&git_root) {
+        warnings.push(WorkspaceBootstrapWarning {
+            kind: WorkspaceBootstrapWarningKind::InsideGitRepo,
+            message: format!(
+                "This folder is inside the git repository at {}.",
+                display(&root)
+            ),
+            suggestion: Some(
+                "Git status covers the whole repository. Add the repository root instead if you work outside this folder."
+                    .to_string(),
+            ),
+            paths: vec![display(&root)],
+        });
+        return;
+    }
+    let candidates = list_git_roots(
+        &workspace_path,
+        NESTED_REPO_SCAN_DEPTH,
+        NESTED_REPO_SCAN_LIMIT,
+    );
+    warnings.push(WorkspaceBootstrapWarning {
+        kind: WorkspaceBootstrapWarningKind::NotGitRepo,
+        message:
+            "This folder is not a git repository, so diffs, commits and worktrees are unavailable."
+                .to_string(),
+        suggestion: Some(if candidates.is_empty() {
+            "Run `git init` in the folder to enable git features.".to_string()
+        } else {
+            "Set the git root in workspace settings to one of the repositories found inside it."
+                .to_string()
+        }),
+        paths: candidates,
+    });
+}
+
+fn check_state_dir(workspace_path: &Path, warnings: &mut Vec<WorkspaceBootstrapWarning>) {
+    let state_dir = workspace_path.join(STATE_DIR_NAME);
+    let probe = state_dir.join(format!(".write-probe-{}", Uuid::new_v4()));
+    let result = std::fs::create_dir_all(&state_dir).and_then(|_| std::fs::write(&probe, b""));
+    let _ = std::fs::remove_file(&probe);
+    if let Err(error) = result {
+        warnings.push(WorkspaceBootstrapWarning {
+            kind: WorkspaceBootstrapWarningKind::StateDirNotWritable,
+            message: format!(
+                "Cannot write to {}: {error}. Thread history will not be saved.",
+                display(&state_dir)
+            ),
+            suggestion: Some(
+                "Check the folder permissions or move the project to a writable location."
+                    .to_string(),
+            ),
+            paths: vec![display(&state_dir)],
+        });
+    }
+}
+
+/// Looks for setups that cause confusing failures later: missing or misplaced git repos,
+/// nested repositories, an unwritable `.micodemonitor` and cloud-synced folders. The
+/// result is advisory; nothing here blocks connecting.
+pub(crate) fn check_workspace_bootstrap(entry: &WorkspaceEntry) -> Vec<WorkspaceBootstrapWarning> {
+    let workspace_path = PathBuf::from(&entry.path);
+    let mut warnings = Vec::new();
+    check_git(entry, &mut warnings);
+    check_state_dir(&workspace_path, &mut warnings);
+    if let Some(provider) = cloud_sync_provider(&workspace_path) {
+        warnings.push(WorkspaceBootstrapWarning {
+            kind: WorkspaceBootstrapWarningKind::CloudSynced,
+            message: format!(
+                "This folder is synced by {provider}, which can lock files while the agent or git writes them."
+            ),
+            suggestion: Some(
+                "Move the project outside the synced folder or pause syncing while working."
+                    .to_string(),
+            ),
+            paths: Vec::new(),
+        });
+    }
+    warnings
+}
+
+pub(crate) async fn check_workspace_bootstrap_core(
+    entry: &WorkspaceEntry,
+) -> Vec<WorkspaceBootstrapWarning> {
+    let entry = entry.clone();
+    tokio::task::spawn_blocking(move || check_workspace_bootstrap(&entry))
+        .await
+        .unwrap_or_default()
+}
+
+pub(crate) fn bootstrap_warnings_event(
+    workspace_id: &str,
+    warnings: &[WorkspaceBootstrapWarning],
+) -> Option<AppServerEvent> {
+    if warnings.is_empty() {
+        return None;
+    }
+    Some(AppServerEvent {
+        workspace_id: workspace_id.to_string(),
+        message: json!({
+            "method": event_methods::WORKSPACE_BOOTSTRAP_WARNINGS,
+            "params": { "workspaceId": workspace_id, "warnings": warnings },
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_workspace_bootstrap, cloud_sync_provider};
+    use crate::types::{
+        WorkspaceBootstrapWarningKind, WorkspaceEntry, WorkspaceKind, WorkspaceSettings,
+    };
+    use std::path::Path;
+    use uuid::Uuid;
+
+    fn entry(path: &Path) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: "w".to_string(),
+            name: "w".to_string(),
+            path: path.to_string_lossy().to_string(),
+            agent_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings::default(),
+            stack: None,
+        }
+    }
+
+    fn kinds(path: &Path) -> Vec<WorkspaceBootstrapWarningKind> {
+        check_workspace_bootstrap(&entry(path))
+            .into_iter()
+            .map(|warning| warning.kind)
+            .collect()
+    }
+
+    #[test]
+    fn detects_cloud_synced_folders() {
+        assert_eq!(
+            cloud_sync_provider(Path::new(
+                "/Users/me/Library/Mobile Documents/com~apple~CloudDocs/app"
+            )),
+            Some("iCloud Drive")
+        );
+        assert_eq!(
+            cloud_sync_provider(Path::new("/Users/me/Dropbox (Personal)/app")),
+            Some("Dropbox")
+        );
+        assert_eq!(
+            cloud_sync_provider(Path::new("C:\\Users\\me\\OneDrive\\app")),
+            Some("OneDrive")
+        );
+        assert_eq!(cloud_sync_provider(Path::new("/Users/me/code/app")), None);
+    }
+
+    #[test]
+    fn reports_missing_nested_and_enclosing_repositories() {
+        let root = std::env::temp_dir().join(format!("micode-bootstrap-{}", Uuid::new_v4()));
+        let plain = root.join("plain");
+        std::fs::create_dir_all(&plain).expect("create plain dir");
+        assert_eq!(
+            kinds(&plain),
+            vec![WorkspaceBootstrapWarningKind::NotGitRepo]
+        );
+        assert!(plain.join(".micodemonitor").is_dir());
+
+        let repo = root.join("repo");
+        std::fs::create_dir_all(repo.join(".git")).expect("create repo");
+        std::fs::create_dir_all(repo.join("vendor/lib/.git")).expect("create nested repo");
+        let warnings = check_workspace_bootstrap(&entry(&repo));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].kind,
+            WorkspaceBootstrapWarningKind::NestedRepositories
+        );
+        assert_eq!(warnings[0].paths, vec!["vendor/lib".to_string()]);
+
+        let subdir = repo.join("src");
+        std::fs::create_dir_all(&subdir).expect("create subdir");
+        assert_eq!(
+            kinds(&subdir),
+            vec![WorkspaceBootstrapWarningKind::InsideGitRepo]
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
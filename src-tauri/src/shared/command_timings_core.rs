@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::backend::app_server::now_ms;
+
+pub(crate) const SLOW_COMMAND_LOG_FILE: &str = "slow-commands.jsonl";
+
+/// Upper bounds (ms) of the duration histogram buckets; one more bucket catches the rest.
+const BUCKET_BOUNDS_MS: [u64; 14] = [
+    1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 30_000,
+];
+
+#[derive(Default)]
+struct CommandStats {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    total_ms: AtomicU64,
+    max_ms: AtomicU64,
+    last_error_at_ms: AtomicU64,
+    buckets: [AtomicU64; BUCKET_BOUNDS_MS.len() + 1],
+}
+
+impl CommandStats {
+    fn record(&self, elapsed_ms: u64, failed: bool, now: u64) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.total_ms.fetch_add(elapsed_ms, Ordering::Relaxed);
+        self.max_ms.fetch_max(elapsed_ms, Ordering::Relaxed);
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|bound| elapsed_ms <= *bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            self.last_error_at_ms.store(now, Ordering::Relaxed);
+        }
+    }
+
+    /// Upper bound of the bucket holding the `quantile` call, capped at the slowest call.
+    fn percentile_ms(&self, quantile: f64) -> u64 {
+        let calls = self.calls.load(Ordering::Relaxed);
+        if calls == 0 {
+            return 0;
+        }
+        let max_ms = self.max_ms.load(Ordering::Relaxed);
+        let rank = ((calls as f64) * quantile).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket.load(Ordering::Relaxed);
+            if seen >= rank {
+                return BUCKET_BOUNDS_MS
+                    .get(index)
+                    .map_or(max_ms, |bound| (*bound).min(max_ms));
+            }
+        }
+        max_ms
+    }
+
+    fn summary(&self) -> TimingSummary {
+        let calls = self.calls.load(Ordering::Relaxed);
+        let total_ms = self.total_ms.load(Ordering::Relaxed);
+        let last_error_at_ms = self.last_error_at_ms.load(Ordering::Relaxed);
+        TimingSummary {
+            calls,
+            errors: self.errors.load(Ordering::Relaxed),
+            mean_ms: total_ms.checked_div(calls).unwrap_or(0),
+            p50_ms: self.percentile_ms(0.5),
+            p95_ms: self.percentile_ms(0.95),
+            max_ms: self.max_ms.load(Ordering::Relaxed),
+            last_error_at_ms: (last_error_at_ms > 0).then_some(last_error_at_ms),
+        }
+    }
+}
+
+#[derive(Default)]
+struct CommandEntry {
+    overall: CommandStats,
+    workspaces: RwLock<HashMap<String, Arc<CommandStats>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TimingSummary {
+    pub(crate) calls: u64,
+    pub(crate) errors: u64,
+    pub(crate) mean_ms: u64,
+    pub(crate) p50_ms: u64,
+    pub(crate) p95_ms: u64,
+    pub(crate) max_ms: u64,
+    pub(crate) last_error_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CommandTimings {
+    pub(crate) command: String,
+    #[serde(flatten)]
+    pub(crate) summary: TimingSummary,
+    /// Breakdown for commands called with a `workspaceId`.
+    pub(crate) workspaces: HashMap<String, TimingSummary>,
+}
+
+/// Command durations since launch or the last reset, plus the slow-call log. App and
+/// daemon state each own one, built from `slowCommandThresholdMs`.
+pub(crate) struct CommandTimingsRegistry {
+    commands: RwLock<HashMap<String, Arc<CommandEntry>>>,
+    /// Calls at least this slow are logged; 0 turns slow logging off.
+    slow_threshold_ms: AtomicU64,
+    slow_log_path: Option<PathBuf>,
+}
+
+fn workspace_stats(entry: &CommandEntry, workspace_id: &str) -> Arc<CommandStats> {
+    if let Some(stats) = entry
+        .workspaces
+        .read()
+        .ok()
+        .and_then(|workspaces| workspaces.get(workspace_id).cloned())
+    {
+        return stats;
+    }
+    let mut workspaces = entry
+        .workspaces
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    workspaces
+        .entry(workspace_id.to_string())
+        .or_default()
+        .clone()
+}
+
+impl CommandTimingsRegistry {
+    /// Slow calls are logged to `SLOW_COMMAND_LOG_FILE` in `logs_dir`, if there is one.
+    pub(crate) fn new(logs_dir: Option<&Path>, slow_threshold_ms: u64) -> Self {
+        Self {
+            commands: RwLock::new(HashMap::new()),
+            slow_threshold_ms: AtomicU64::new(slow_threshold_ms),
+            slow_log_path: logs_dir.map(|dir| dir.join(SLOW_COMMAND_LOG_FILE)),
+        }
+    }
+
+    pub(crate) fn set_slow_threshold(&self, threshold_ms: u64) {
+        self.slow_threshold_ms
+            .store(threshold_ms, Ordering::Relaxed);
+    }
+
+    fn command_entry(&self, command: &str) -> Arc<CommandEntry> {
+        if let Some(entry) = self
+            .commands
+            .read()
+            .ok()
+            .and_then(|commands| commands.get(command).cloned())
+        {
+            return entry;
+        }
+        let mut commands = self
+            .commands
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        commands.entry(command.to_string()).or_default().clone()
+    }
+
+    fn log_slow_call(
+        &self,
+        command: &str,
+        workspace_id: Option<&str>,
+        elapsed_ms: u64,
+        failed: bool,
+    ) {
+        let threshold_ms = self.slow_threshold_ms.load(Ordering::Relaxed);
+        if threshold_ms == 0 || elapsed_ms < threshold_ms {
+            return;
+        }
+        let Some(path) = &self.slow_log_path else {
+            return;
+        };
+        let line = json!({
+            "timestampMs": now_ms(),
+            "command": command,
+            "workspaceId": workspace_id,
+            "durationMs": elapsed_ms,
+            "thresholdMs": threshold_ms,
+            "failed": failed,
+        });
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(mut file) = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+        {
+            let _ = writeln!(file, "{line}");
+        }
+    }
+
+    pub(crate) fn record(
+        &self,
+        command: &str,
+        workspace_id: Option<&str>,
+        elapsed_ms: u64,
+        failed: bool,
+    ) {
+        let now = now_ms();
+        let entry = self.command_entry(command);
+        entry.overall.record(elapsed_ms, failed, now);
+        if let Some(workspace_id) = workspace_id.filter(|id| !id.is_empty()) {
+            workspace_stats(&entry, workspace_id).record(elapsed_ms, failed, now);
+        }
+        self.log_slow_call(command, workspace_id, elapsed_ms, failed);
+    }
+
+    /// Awaits `future` and records its duration and outcome under `command`.
+    pub(crate) async fn timed<T, E, F>(
+        &self,
+        command: &str,
+        workspace_id: Option<&str>,
+        future: F,
+    ) -> Result<T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        let started = Instant::now();
+        let result = future.await;
+        let elapsed_ms = started.elapsed().as_millis().min(u64::MAX as u128) as u64;
+        self.record(command, workspace_id, elapsed_ms, result.is_err());
+        result
+    }
+
+    /// Timings of every recorded command, slowest p95 first.
+    pub(crate) fn snapshot(&self) -> Vec<CommandTimings> {
+        let entries: Vec<(String, Arc<CommandEntry>)> = self
+            .commands
+            .read()
+            .map(|commands| {
+                commands
+                    .iter()
+                    .map(|(command, entry)| (command.clone(), entry.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut timings: Vec<CommandTimings> = entries
+            .into_iter()
+            .map(|(command, entry)| CommandTimings {
+                command,
+                summary: entry.overall.summary(),
+                workspaces: entry
+                    .workspaces
+                    .read()
+                    .map(|workspaces| {
+                        workspaces
+                            .iter()
+                            .map(|(id, stats)| (id.clone(), stats.summary()))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+            .collect();
+        timings.sort_by(|a, b| {
+            b.summary
+                .p95_ms
+                .cmp(&a.summary.p95_ms)
+                .then_with(|| a.command.cmp(&b.command))
+        });
+        timings
+    }
+
+    pub(crate) fn reset(&self) {
+        if let Ok(mut commands) = self.commands.write() {
+            commands.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_percentiles_from_the_histogram() {
+        let stats = CommandStats::default();
+        for _ in 0..90 {
+            stats.record(3, false, 1);
+        }
+        for _ in 0..9 {
+            stats.record(400, false, 1);
+        }
+        stats.record(1_200, true, 42);
+
+        let summary = stats.summary();
+        assert_eq!(summary.calls, 100);
+        assert_eq!(summary.errors, 1);
+        assert_eq!(summary.p50_ms, 5);
+        assert_eq!(summary.p95_ms, 500);
+        assert_eq!(summary.max_ms, 1_200);
+        assert_eq!(summary.last_error_at_ms, Some(42));
+        assert_eq!(CommandStats::default().summary().p95_ms, 0);
+    }
+
+    #[test]
+    fn records_per_workspace_breakdowns() {
+        let registry = CommandTimingsRegistry::new(None, 0);
+        let command = "test_command";
+        registry.record(command, Some("ws-1"), 10, false);
+        registry.record(command, Some("ws-1"), 30, true);
+        registry.record(command, Some("ws-2"), 5, false);
+        registry.record(command, None, 5, false);
+
+        let timings = registry
+            .snapshot()
+            .into_iter()
+            .find(|timings| timings.command == command)
+            .expect("timings recorded");
+        assert_eq!(timings.summary.calls, 4);
+        assert_eq!(timings.summary.errors, 1);
+        assert_eq!(timings.workspaces["ws-1"].calls, 2);
+        assert_eq!(timings.workspaces["ws-1"].max_ms, 30);
+        assert_eq!(timings.workspaces["ws-2"].calls, 1);
+        assert_eq!(timings.workspaces.len(), 2);
+
+        registry.reset();
+        assert!(registry.snapshot().is_empty());
+    }
+}
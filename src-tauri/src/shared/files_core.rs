@@ -3,6 +3,8 @@ use std::path::PathBuf;
 
 use tokio::sync::Mutex;
 
+use crate::backend::events::AppServerEvent;
+use crate::backend::settings_events::{micode_config_values, SettingsRevision, SettingsScope};
 use crate::files::io::TextFileResponse;
 use crate::files::ops::{read_with_policy, write_with_policy};
 use crate::files::policy::{policy_for, FileKind, FileScope};
@@ -52,12 +54,26 @@ pub(crate) async fn file_read_core(
 
 pub(crate) async fn file_write_core(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    settings_revision: &SettingsRevision,
     scope: FileScope,
     kind: FileKind,
     workspace_id: Option<String>,
     content: String,
-) -> Result<(), String> {
+) -> Result<Option<AppServerEvent>, String> {
     let policy = policy_for(scope, kind)?;
     let root = resolve_root_core(workspaces, scope, workspace_id.as_deref()).await?;
-    write_with_policy(&root, policy, &content)
+    if kind != FileKind::Config {
+        return write_with_policy(&root, policy, &content).map(|()| None);
+    }
+    // config.toml carries settings (MCP servers among them): report what changed.
+    let previous = read_with_policy(&root, policy)
+        .map(|response| response.content)
+        .unwrap_or_default();
+    write_with_policy(&root, policy, &content)?;
+    let (_, event) = settings_revision.record_update(
+        SettingsScope::MicodeConfig,
+        &micode_config_values(&previous),
+        &micode_config_values(&content),
+    );
+    Ok(Some(event))
 }
@@ -2,6 +2,7 @@
 
 use std::path::PathBuf;
 
+use crate::shared::operations_core::CancellationToken;
 use crate::shared::process_core::tokio_command;
 use crate::utils::{git_env_path, resolve_git_binary};
 
@@ -35,6 +36,29 @@ pub(crate) async fn run_git_command(repo_path: &PathBuf, args: &[&str]) -> Resul
     Err(format_git_error(&output.stdout, &output.stderr))
 }
 
+/// Like `run_git_command`, but kills git once `token` is cancelled and returns `Ok(None)`.
+pub(crate) async fn run_git_command_cancellable(
+    repo_path: &PathBuf,
+    args: &[&str],
+    token: &CancellationToken,
+) -> Result<Option<String>, String> {
+    let git_bin = resolve_git_binary().map_err(|err| format!("Failed to run git: {err}"))?;
+    let output = tokio_command(git_bin)
+        .args(args)
+        .current_dir(repo_path)
+        .env("PATH", git_env_path())
+        .kill_on_drop(true)
+        .output();
+    let Some(output) = token.run_until_cancelled(output).await else {
+        return Ok(None);
+    };
+    let output = output.map_err(|err| format!("Failed to run git: {err}"))?;
+    if output.status.success() {
+        return Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string()));
+    }
+    Err(format_git_error(&output.stdout, &output.stderr))
+}
+
 pub(crate) async fn run_git_command_owned(
     repo_path: PathBuf,
     args_owned: Vec<String>,
@@ -1,6 +1,6 @@
 use serde_json::{json, Map, Value};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
@@ -10,12 +10,37 @@ use tokio::sync::{oneshot, Mutex};
 use tokio::time::timeout;
 use tokio::time::Instant;
 
-use crate::backend::app_server::WorkspaceSession;
+use crate::backend::app_server::{
+    export_thread_at, read_preferred_model, search_threads_at, WorkspaceSession,
+};
+use crate::backend::event_methods;
+use crate::backend::events::AppServerEvent;
+use crate::backend::review_context::{
+    changed_files_for_target, collect_review_context, review_target_with_context, ReviewContext,
+    ReviewContextOptions,
+};
+use crate::backend::review_sarif;
+use crate::backend::sampling::{
+    merge_sampling_params, parse_sampling_params, validate_sampling_params,
+};
+use crate::backend::settings_json::{
+    load_settings_for_update, parse_settings_text, write_settings_file, SettingsParseErrors,
+};
+use crate::backend::slash_commands::slash_command_prompt;
+use crate::backend::thread_export::{deliver_thread_export, ThreadExport, ThreadExportFormat};
+use crate::backend::thread_references::ThreadReference;
+use crate::backend::thread_search::search_limit;
+use crate::backend::turn_artifacts::relative_to_root;
 use crate::micode::config as micode_config;
-use crate::micode::home::{resolve_default_micode_home, resolve_workspace_micode_home};
-use crate::rules;
+use crate::micode::home::{
+    isolated_workspace_home, resolve_default_micode_home, resolve_workspace_micode_home,
+};
+use crate::rules::{self, RuleDecision};
 use crate::shared::account::{build_account_response, read_auth_account};
-use crate::types::WorkspaceEntry;
+use crate::shared::run_kickoff_core::build_run_kickoff_message_core;
+use crate::shared::workspace_stack_core::{detect_workspace_stack_core, effective_workspace_stack};
+use crate::types::errors::CommandError;
+use crate::types::{AppSettings, SamplingParams, WorkspaceEntry, WorkspaceSettings};
 
 const LOGIN_START_TIMEOUT: Duration = Duration::from_secs(30);
 
@@ -27,23 +52,23 @@ pub(crate) enum MiCodeLoginCancelState {
 async fn get_session_clone(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: &str,
-) -> Result<Arc<WorkspaceSession>, String> {
+) -> Result<Arc<WorkspaceSession>, CommandError> {
     let sessions = sessions.lock().await;
     sessions
         .get(workspace_id)
         .cloned()
-        .ok_or_else(|| "workspace not connected".to_string())
+        .ok_or_else(CommandError::workspace_not_connected)
 }
 
 async fn resolve_workspace_and_parent(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     workspace_id: &str,
-) -> Result<(WorkspaceEntry, Option<WorkspaceEntry>), String> {
+) -> Result<(WorkspaceEntry, Option<WorkspaceEntry>), CommandError> {
     let workspaces = workspaces.lock().await;
     let entry = workspaces
         .get(workspace_id)
         .cloned()
-        .ok_or_else(|| "workspace not found".to_string())?;
+        .ok_or_else(CommandError::workspace_not_found)?;
     let parent_entry = entry
         .parent_id
         .as_ref()
@@ -54,18 +79,130 @@ async fn resolve_workspace_and_parent(
 
 async fn resolve_micode_home_for_workspace_core(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
     workspace_id: &str,
-) -> Result<PathBuf, String> {
+) -> Result<PathBuf, CommandError> {
     let (entry, parent_entry) = resolve_workspace_and_parent(workspaces, workspace_id).await?;
-    resolve_workspace_micode_home(&entry, parent_entry.as_ref())
+    resolve_workspace_micode_home(&entry, parent_entry.as_ref(), data_dir)
         .or_else(resolve_default_micode_home)
-        .ok_or_else(|| "Unable to resolve CODEX_HOME".to_string())
+        .ok_or_else(|| "Unable to resolve CODEX_HOME".into())
+}
+
+/// Model a workspace runs: its own `preferredModel`, then its parent's for worktrees,
+/// then the `model.preferredModel` of the MiCode settings file in `home`.
+pub(crate) fn resolve_preferred_model(
+    workspace_model: Option<&str>,
+    parent_model: Option<&str>,
+    home: Option<&Path>,
+    settings_parse_errors: &SettingsParseErrors,
+) -> Option<String> {
+    [workspace_model, parent_model]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .find(|model| !model.is_empty())
+        .map(ToString::to_string)
+        .or_else(|| read_preferred_model(home, settings_parse_errors))
+}
+
+fn workspace_preferred_model(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+    data_dir: &Path,
+    settings_parse_errors: &SettingsParseErrors,
+) -> Option<String> {
+    resolve_preferred_model(
+        entry.settings.preferred_model.as_deref(),
+        parent_entry.and_then(|parent| parent.settings.preferred_model.as_deref()),
+        isolated_workspace_home(entry, parent_entry, data_dir).as_deref(),
+        settings_parse_errors,
+    )
+}
+
+pub(crate) async fn preferred_model_for_workspace_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
+    settings_parse_errors: &SettingsParseErrors,
+    workspace_id: &str,
+) -> Option<String> {
+    let (entry, parent_entry) = resolve_workspace_and_parent(workspaces, workspace_id)
+        .await
+        .ok()?;
+    workspace_preferred_model(
+        &entry,
+        parent_entry.as_ref(),
+        data_dir,
+        settings_parse_errors,
+    )
+}
+
+/// Workspace settings recording `model` as the workspace's model, or `None` when the
+/// workspace already runs it.
+pub(crate) async fn preferred_model_update_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
+    settings_parse_errors: &SettingsParseErrors,
+    workspace_id: &str,
+    model: Option<&str>,
+) -> Option<WorkspaceSettings> {
+    let model = model.map(str::trim).filter(|model| !model.is_empty())?;
+    let (entry, parent_entry) = resolve_workspace_and_parent(workspaces, workspace_id)
+        .await
+        .ok()?;
+    if workspace_preferred_model(
+        &entry,
+        parent_entry.as_ref(),
+        data_dir,
+        settings_parse_errors,
+    )
+    .as_deref()
+        == Some(model)
+    {
+        return None;
+    }
+    let mut settings = entry.settings;
+    settings.preferred_model = Some(model.to_string());
+    Some(settings)
+}
+
+/// Workspaces saved before models were tracked per workspace all followed the shared
+/// settings file. Pins each main workspace without a model to the one it was using, so
+/// switching models elsewhere leaves it alone; worktrees keep following their parent.
+/// Returns `true` when an entry changed.
+pub(crate) fn migrate_workspace_preferred_models(
+    workspaces: &mut HashMap<String, WorkspaceEntry>,
+    data_dir: &Path,
+    settings_parse_errors: &SettingsParseErrors,
+) -> bool {
+    pin_workspace_models(workspaces, |entry| {
+        read_preferred_model(
+            isolated_workspace_home(entry, None, data_dir).as_deref(),
+            settings_parse_errors,
+        )
+    })
+}
+
+fn pin_workspace_models(
+    workspaces: &mut HashMap<String, WorkspaceEntry>,
+    settings_model: impl Fn(&WorkspaceEntry) -> Option<String>,
+) -> bool {
+    let mut changed = false;
+    for entry in workspaces.values_mut() {
+        if entry.kind.is_worktree() || entry.settings.preferred_model.is_some() {
+            continue;
+        }
+        if let Some(model) = settings_model(entry) {
+            entry.settings.preferred_model = Some(model);
+            changed = true;
+        }
+    }
+    changed
 }
 
 pub(crate) async fn start_thread_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     let params = json!({
         "cwd": session.entry.path,
@@ -74,21 +211,90 @@ pub(crate) async fn start_thread_core(
     session.send_request("thread/start", params).await
 }
 
+/// Starts a thread for a run. With `send_kickoff`, the rendered kickoff message is sent as
+/// the first turn and the thread records which template produced it; like
+/// `send_user_message_core`, the call then resolves when that turn finishes. The thread is
+/// announced through `thread/started` as soon as it exists.
+pub(crate) async fn start_run_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    workspace_id: String,
+    task: String,
+    branch: Option<String>,
+    send_kickoff: bool,
+) -> Result<Value, CommandError> {
+    let kickoff =
+        build_run_kickoff_message_core(workspaces, app_settings, &workspace_id, &task, branch)
+            .await?;
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let mut params = json!({
+        "cwd": session.entry.path,
+        "approvalPolicy": "on-request"
+    });
+    if send_kickoff {
+        params["_kickoff"] = json!(kickoff.template);
+    }
+    let response = session.send_request("thread/start", params).await?;
+    let thread_id = response
+        .get("result")
+        .and_then(|result| result.get("thread"))
+        .and_then(|thread| thread.get("id"))
+        .and_then(Value::as_str)
+        .ok_or_else(|| "Failed to start a thread for the run.".to_string())?
+        .to_string();
+    let queue_when_busy = app_settings.lock().await.queue_when_busy;
+    let turn = if send_kickoff {
+        send_user_message_core(
+            sessions,
+            workspace_id,
+            thread_id.clone(),
+            kickoff.message.clone(),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Vec::new(),
+            queue_when_busy,
+        )
+        .await?
+    } else {
+        Value::Null
+    };
+    Ok(json!({ "threadId": thread_id, "kickoff": kickoff, "turn": turn }))
+}
+
 pub(crate) async fn resume_thread_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: String,
     thread_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     let params = json!({ "threadId": thread_id });
     session.send_request("thread/resume", params).await
 }
 
+/// Read-only view of a thread used by watch mode; unlike `resume_thread_core` it keeps
+/// the thread's current ACP session intact.
+#[allow(dead_code)]
+pub(crate) async fn thread_snapshot_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    Ok(session.thread_snapshot(&thread_id).await?)
+}
+
 pub(crate) async fn fork_thread_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: String,
     thread_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     let params = json!({ "threadId": thread_id });
     session.send_request("thread/fork", params).await
@@ -99,7 +305,7 @@ pub(crate) async fn list_threads_core(
     workspace_id: String,
     cursor: Option<String>,
     limit: Option<u32>,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     let params = json!({ "cursor": cursor, "limit": limit });
     session.send_request("thread/list", params).await
@@ -110,25 +316,30 @@ pub(crate) async fn list_mcp_server_status_core(
     workspace_id: String,
     cursor: Option<String>,
     limit: Option<u32>,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     let params = json!({ "cursor": cursor, "limit": limit });
-    session.send_request("mcpServerStatus/list", params).await
+    session.send_request("mcpServer/list", params).await
 }
 
 pub(crate) async fn list_mcp_server_status_from_settings_core(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
     workspace_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let (entry, parent_entry) = resolve_workspace_and_parent(workspaces, &workspace_id).await?;
-    let micode_home = resolve_workspace_micode_home(&entry, parent_entry.as_ref())
+    let micode_home = resolve_workspace_micode_home(&entry, parent_entry.as_ref(), data_dir)
         .or_else(resolve_default_micode_home)
         .ok_or_else(|| "Unable to resolve CODEX_HOME".to_string())?;
     let settings_path = micode_home.join("settings.json");
     let raw = std::fs::read_to_string(&settings_path)
         .map_err(|err| format!("Failed to read {}: {err}", settings_path.display()))?;
-    let root: Value =
-        serde_json::from_str(&raw).map_err(|err| format!("Invalid settings.json: {err}"))?;
+    let root = parse_settings_text(&settings_path, &raw).map_err(|err| {
+        format!(
+            "Invalid settings.json: {} at line {} column {}",
+            err.message, err.line, err.column
+        )
+    })?;
     let mcp_servers = root
         .get("mcpServers")
         .cloned()
@@ -189,21 +400,201 @@ pub(crate) async fn list_mcp_server_status_from_settings_core(
     Ok(json!({ "result": { "data": data } }))
 }
 
+/// Archives a thread, keeping it for `restore_thread_core`; a pinned one is refused
+/// unless `force` is set.
+/// Change made to the `mcpServers` of a MiCode settings file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum McpServerEdit {
+    Add,
+    Update,
+    Remove,
+}
+
+impl McpServerEdit {
+    fn action(self) -> &'static str {
+        match self {
+            Self::Add => "added",
+            Self::Update => "updated",
+            Self::Remove => "removed",
+        }
+    }
+}
+
+/// Checks an `mcpServers` entry before it is written: it needs a `command` or a
+/// `url`/`httpUrl`, `args` must be strings and `env` a map of strings.
+pub(crate) fn validate_mcp_server_config(config: &Value) -> Result<(), String> {
+    let map = config
+        .as_object()
+        .ok_or_else(|| "MCP server config must be an object".to_string())?;
+    let non_empty = |key: &str| -> Result<bool, String> {
+        match map.get(key) {
+            None | Some(Value::Null) => Ok(false),
+            Some(Value::String(value)) => Ok(!value.trim().is_empty()),
+            Some(_) => Err(format!("MCP server `{key}` must be a string")),
+        }
+    };
+    let has_command = non_empty("command")?;
+    let has_url = non_empty("url")? | non_empty("httpUrl")?;
+    if !has_command && !has_url {
+        return Err("MCP server needs a command or a url".to_string());
+    }
+    match map.get("args") {
+        None | Some(Value::Null) => {}
+        Some(Value::Array(items)) if items.iter().all(Value::is_string) => {}
+        Some(_) => return Err("MCP server `args` must be a list of strings".to_string()),
+    }
+    match map.get("env") {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(env)) if env.values().all(Value::is_string) => {}
+        Some(_) => return Err("MCP server `env` must map names to strings".to_string()),
+    }
+    Ok(())
+}
+
+/// Applies `edit` to the `mcpServers` object of `root`. `config` is ignored for
+/// removals.
+pub(crate) fn apply_mcp_server_edit(
+    root: &mut Value,
+    edit: McpServerEdit,
+    name: &str,
+    config: Option<Value>,
+) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("MCP server name is required".to_string());
+    }
+    let root_obj = root
+        .as_object_mut()
+        .ok_or_else(|| "invalid settings root".to_string())?;
+    let servers = root_obj
+        .entry("mcpServers".to_string())
+        .or_insert_with(|| json!({}));
+    if servers.is_null() {
+        *servers = json!({});
+    }
+    let servers = servers.as_object_mut().ok_or_else(|| {
+        "mcpServers in settings.json is not an object; edit it by hand".to_string()
+    })?;
+    let exists = servers.contains_key(name);
+    match edit {
+        McpServerEdit::Add if exists => Err(format!("MCP server {name} already exists")),
+        McpServerEdit::Update | McpServerEdit::Remove if !exists => {
+            Err(format!("MCP server {name} not found"))
+        }
+        McpServerEdit::Remove => {
+            servers.remove(name);
+            Ok(())
+        }
+        McpServerEdit::Add | McpServerEdit::Update => {
+            let config = config.ok_or_else(|| "MCP server config is required".to_string())?;
+            validate_mcp_server_config(&config)?;
+            servers.insert(name.to_string(), config);
+            Ok(())
+        }
+    }
+}
+
+/// Rewrites the settings file at `path` with `edit` applied, keeping every other key.
+/// Returns the configured server names afterwards.
+pub(crate) fn edit_mcp_server_at(
+    path: &Path,
+    settings_parse_errors: &SettingsParseErrors,
+    edit: McpServerEdit,
+    name: &str,
+    config: Option<Value>,
+) -> Result<Vec<String>, String> {
+    let mut root = load_settings_for_update(path, settings_parse_errors)?;
+    apply_mcp_server_edit(&mut root, edit, name, config)?;
+    write_settings_file(path, &root)?;
+    Ok(root
+        .get("mcpServers")
+        .and_then(Value::as_object)
+        .map(|servers| servers.keys().cloned().collect())
+        .unwrap_or_default())
+}
+
+/// Edits the MCP servers of the workspace's MiCode home. New sessions pick the change
+/// up through `session/new`; the returned `micode/mcpServersChanged` event lets the UI
+/// offer to restart running ones.
+pub(crate) async fn edit_mcp_server_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
+    settings_parse_errors: &SettingsParseErrors,
+    workspace_id: String,
+    edit: McpServerEdit,
+    name: String,
+    config: Option<Value>,
+) -> Result<AppServerEvent, CommandError> {
+    let home = resolve_micode_home_for_workspace_core(workspaces, data_dir, &workspace_id).await?;
+    let servers = edit_mcp_server_at(
+        &home.join("settings.json"),
+        settings_parse_errors,
+        edit,
+        &name,
+        config,
+    )?;
+    Ok(AppServerEvent {
+        workspace_id: workspace_id.clone(),
+        message: json!({
+            "method": event_methods::MICODE_MCP_SERVERS_CHANGED,
+            "params": {
+                "workspaceId": workspace_id,
+                "name": name.trim(),
+                "action": edit.action(),
+                "servers": servers
+            }
+        }),
+    })
+}
+
 pub(crate) async fn archive_thread_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: String,
     thread_id: String,
-) -> Result<Value, String> {
+    force: bool,
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({ "threadId": thread_id });
+    let params = json!({ "threadId": thread_id, "force": force });
     session.send_request("thread/archive", params).await
 }
 
+pub(crate) async fn list_archived_threads_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    session
+        .send_request("thread/archived/list", json!({}))
+        .await
+}
+
+pub(crate) async fn restore_thread_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id });
+    session.send_request("thread/restore", params).await
+}
+
+/// Deletes a thread with its items for good; a pinned one is refused unless `force`.
+pub(crate) async fn delete_thread_permanently_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    force: bool,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id, "force": force });
+    session.send_request("thread/delete", params).await
+}
+
 pub(crate) async fn compact_thread_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: String,
     thread_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     let params = json!({ "threadId": thread_id });
     session.send_request("thread/compact/start", params).await
@@ -214,40 +605,287 @@ pub(crate) async fn set_thread_name_core(
     workspace_id: String,
     thread_id: String,
     name: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     let params = json!({ "threadId": thread_id, "name": name });
     session.send_request("thread/name/set", params).await
 }
 
-pub(crate) async fn send_user_message_core(
+pub(crate) async fn set_thread_tags_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    tags: Vec<String>,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id, "tags": tags });
+    session.send_request("thread/tags/set", params).await
+}
+
+pub(crate) async fn set_thread_pinned_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    pinned: bool,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id, "pinned": pinned });
+    session.send_request("thread/pinned/set", params).await
+}
+
+pub(crate) async fn mark_thread_seen_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    item_seq: Option<u64>,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id, "itemSeq": item_seq });
+    session.send_request("thread/seen/mark", params).await
+}
+
+pub(crate) async fn get_unseen_items_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id });
+    session.send_request("thread/items/unseen", params).await
+}
+
+pub(crate) async fn check_thread_sync_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id });
+    session.send_request("thread/sync/check", params).await
+}
+
+pub(crate) async fn sync_thread_from_cli_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id });
+    session.send_request("thread/sync/fromCli", params).await
+}
+
+pub(crate) async fn copy_thread_range_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    start_item_id: Option<String>,
+    end_item_id: Option<String>,
+    format: Option<String>,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({
+        "threadId": thread_id,
+        "startItemId": start_item_id,
+        "endItemId": end_item_id,
+        "format": format,
+    });
+    session.send_request("thread/transcript/copy", params).await
+}
+
+pub(crate) async fn list_item_annotations_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id });
+    session
+        .send_request("thread/annotations/list", params)
+        .await
+}
+
+pub(crate) async fn add_item_annotation_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: String,
     thread_id: String,
+    item_id: String,
     text: String,
-    model: Option<String>,
-    effort: Option<String>,
-    access_mode: Option<String>,
-    images: Option<Vec<String>>,
-    collaboration_mode: Option<Value>,
-) -> Result<Value, String> {
+    author: String,
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
-    let access_mode = access_mode.unwrap_or_else(|| "current".to_string());
-    let sandbox_policy = match access_mode.as_str() {
+    let params = json!({
+        "threadId": thread_id,
+        "itemId": item_id,
+        "text": text,
+        "author": author,
+    });
+    session.send_request("thread/annotations/add", params).await
+}
+
+pub(crate) async fn update_item_annotation_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    annotation_id: String,
+    text: String,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id, "annotationId": annotation_id, "text": text });
+    session
+        .send_request("thread/annotations/update", params)
+        .await
+}
+
+pub(crate) async fn delete_item_annotation_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    annotation_id: String,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id, "annotationId": annotation_id });
+    session
+        .send_request("thread/annotations/delete", params)
+        .await
+}
+
+/// Converts the structured findings of a stored review into a SARIF log. With
+/// `output_path` the log is also written to that workspace-relative path.
+pub(crate) async fn export_review_sarif_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    item_id: Option<String>,
+    output_path: Option<String>,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let item = session
+        .find_review_item(&thread_id, item_id.as_deref())
+        .await?;
+    let workspace_root = PathBuf::from(&session.entry.path);
+    let findings = review_sarif::findings_from_item(&item);
+    let sarif = review_sarif::build_sarif(&findings, &workspace_root, env!("CARGO_PKG_VERSION"));
+    let written = match output_path
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+    {
+        Some(output_path) => {
+            let relative = relative_to_root(&workspace_root, output_path)
+                .ok_or_else(|| "SARIF output path must be inside the workspace".to_string())?;
+            let target = workspace_root.join(&relative);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let raw = serde_json::to_string_pretty(&sarif).map_err(|e| e.to_string())?;
+            std::fs::write(&target, raw).map_err(|e| e.to_string())?;
+            Some(relative)
+        }
+        None => None,
+    };
+    Ok(json!({
+        "sarif": sarif,
+        "itemId": item.get("id").cloned().unwrap_or(Value::Null),
+        "resultCount": findings.len(),
+        "path": written,
+    }))
+}
+
+/// Resolves the effective sampling parameters for a message: model defaults from app
+/// settings, then the workspace override, then the per-message request.
+pub(crate) async fn resolve_sampling_params_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
+    settings_parse_errors: &SettingsParseErrors,
+    app_settings: &Mutex<AppSettings>,
+    workspace_id: &str,
+    model: Option<&str>,
+    requested: Option<&Value>,
+) -> Result<Option<SamplingParams>, CommandError> {
+    let requested = match requested.filter(|value| !value.is_null()) {
+        Some(value) => Some(parse_sampling_params(value)?),
+        None => None,
+    };
+    let workspace = workspaces
+        .lock()
+        .await
+        .get(workspace_id)
+        .and_then(|entry| entry.settings.sampling_params.clone());
+    let model = match model
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(ToString::to_string)
+    {
+        Some(model) => Some(model),
+        None => {
+            preferred_model_for_workspace_core(
+                workspaces,
+                data_dir,
+                settings_parse_errors,
+                workspace_id,
+            )
+            .await
+        }
+    };
+    let model_defaults = match model {
+        Some(model) => app_settings
+            .lock()
+            .await
+            .model_sampling_params
+            .get(&model)
+            .cloned(),
+        None => None,
+    };
+    let merged = merge_sampling_params([
+        model_defaults.as_ref(),
+        workspace.as_ref(),
+        requested.as_ref(),
+    ]);
+    validate_sampling_params(&merged)?;
+    Ok(Some(merged).filter(|params| !params.is_empty()))
+}
+
+/// Sandbox and approval policy sent with `turn/start` for a composer access mode.
+pub(crate) fn access_mode_policies(
+    access_mode: &str,
+    workspace_path: &str,
+) -> (Value, &'static str) {
+    let sandbox_policy = match access_mode {
         "full-access" => json!({ "type": "dangerFullAccess" }),
         "read-only" => json!({ "type": "readOnly" }),
         _ => json!({
             "type": "workspaceWrite",
-            "writableRoots": [session.entry.path],
+            "writableRoots": [workspace_path],
             "networkAccess": true
         }),
     };
-
     let approval_policy = if access_mode == "full-access" {
         "never"
     } else {
         "on-request"
     };
+    (sandbox_policy, approval_policy)
+}
+
+pub(crate) async fn send_user_message_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    text: String,
+    model: Option<String>,
+    effort: Option<String>,
+    access_mode: Option<String>,
+    images: Option<Vec<String>>,
+    collaboration_mode: Option<Value>,
+    raw_text: Option<bool>,
+    sampling_params: Option<SamplingParams>,
+    skip_redaction: Option<bool>,
+    thread_references: Vec<ThreadReference>,
+    queue_when_busy: bool,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let access_mode = access_mode.unwrap_or_else(|| "current".to_string());
+    let (sandbox_policy, approval_policy) = access_mode_policies(&access_mode, &session.entry.path);
 
     let trimmed_text = text.trim();
     let mut input: Vec<Value> = Vec::new();
@@ -271,7 +909,7 @@ pub(crate) async fn send_user_message_core(
         }
     }
     if input.is_empty() {
-        return Err("empty user message".to_string());
+        return Err("empty user message".into());
     }
 
     let mut params = Map::new();
@@ -287,15 +925,58 @@ pub(crate) async fn send_user_message_core(
             params.insert("collaborationMode".to_string(), mode);
         }
     }
+    if raw_text.unwrap_or(false) {
+        params.insert("rawText".to_string(), json!(true));
+    }
+    if let Some(sampling_params) = sampling_params.filter(|params| !params.is_empty()) {
+        params.insert("samplingParams".to_string(), json!(sampling_params));
+    }
+    if skip_redaction.unwrap_or(false) {
+        params.insert("skipRedaction".to_string(), json!(true));
+    }
+    if !thread_references.is_empty() {
+        params.insert("_threadReferences".to_string(), json!(thread_references));
+    }
+    // A thread runs one turn at a time; later messages wait for it or are turned away.
+    session
+        .start_thread_turn(&thread_id, Value::Object(params), queue_when_busy)
+        .await
+}
+
+/// Sends `/command_name args` on a thread through the normal `turn/start` path, tagging
+/// the persisted user item `kind: "command"`. Fails fast when the agent has not
+/// advertised the command.
+pub(crate) async fn run_slash_command_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    command_name: String,
+    args: Option<String>,
+    queue_when_busy: bool,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let name = session
+        .resolve_slash_command(&command_name)
+        .map_err(|err| err.to_string())?;
+    let (sandbox_policy, approval_policy) = access_mode_policies("current", &session.entry.path);
+    let params = json!({
+        "threadId": thread_id,
+        "input": [{ "type": "text", "text": slash_command_prompt(&name, args.as_deref()) }],
+        "cwd": session.entry.path,
+        "approvalPolicy": approval_policy,
+        "sandboxPolicy": sandbox_policy,
+        "rawText": true,
+        "_kind": "command"
+    });
     session
-        .send_request("turn/start", Value::Object(params))
+        .start_thread_turn(&thread_id, params, queue_when_busy)
         .await
 }
 
 pub(crate) async fn collaboration_mode_list_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     session
         .send_request("collaborationMode/list", json!({}))
@@ -307,43 +988,108 @@ pub(crate) async fn turn_interrupt_core(
     workspace_id: String,
     thread_id: String,
     turn_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     let params = json!({ "threadId": thread_id, "turnId": turn_id });
     session.send_request("turn/interrupt", params).await
 }
 
+pub(crate) async fn cancel_tool_call_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    tool_call_id: String,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    Ok(session.cancel_tool_call(&tool_call_id).await?)
+}
+
+/// Per-file summary of what the running turn of a thread changed so far.
+pub(crate) async fn get_turn_live_changes_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, CommandError> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let changes = session.turn_live_changes(&thread_id).await?;
+    serde_json::to_value(changes).map_err(|err| err.to_string().into())
+}
+
+/// Files the changed code of `target` imports, for languages the workspace stack has an
+/// import heuristic for. Outside a git repository the review simply gets no context.
+async fn review_context_for_target(
+    entry: &WorkspaceEntry,
+    target: &Value,
+    options: ReviewContextOptions,
+) -> ReviewContext {
+    let detected = match entry.stack.clone() {
+        Some(stack) => stack,
+        None => detect_workspace_stack_core(&entry.path).await,
+    };
+    let stack = effective_workspace_stack(entry, detected);
+    let root = PathBuf::from(&entry.path);
+    let target = target.clone();
+    tokio::task::spawn_blocking(move || {
+        let changed_files = changed_files_for_target(&root, &target).unwrap_or_default();
+        collect_review_context(&root, &changed_files, &stack.languages, options)
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Starts a review. With `context`, files the diff imports but does not touch are sent as a
+/// separate read-only section and listed under `reviewContext` in the result.
 pub(crate) async fn start_review_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: String,
     thread_id: String,
     target: Value,
     delivery: Option<String>,
-) -> Result<Value, String> {
+    context: Option<ReviewContextOptions>,
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
+    let review_context = match context {
+        Some(options) => Some(review_context_for_target(&session.entry, &target, options).await),
+        None => None,
+    };
+    // The agent only sees the review prompt, so the context goes into its text.
+    let target = match review_context
+        .as_ref()
+        .and_then(ReviewContext::prompt_block)
+    {
+        Some(block) => review_target_with_context(&target, &session.redact(&block)?.text),
+        None => target,
+    };
     let mut params = Map::new();
     params.insert("threadId".to_string(), json!(thread_id));
     params.insert("target".to_string(), target);
     if let Some(delivery) = delivery {
         params.insert("delivery".to_string(), json!(delivery));
     }
-    session
+    let mut response = session
         .send_request("review/start", Value::Object(params))
-        .await
+        .await?;
+    if let (Some(review_context), Some(object)) = (review_context, response.as_object_mut()) {
+        object.insert("reviewContext".to_string(), json!(review_context));
+    }
+    Ok(response)
 }
 
+/// With `refresh`, the agent's CLI bundle is parsed again even when it looks unchanged.
 pub(crate) async fn model_list_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: String,
-) -> Result<Value, String> {
+    refresh: bool,
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
-    session.send_request("model/list", json!({})).await
+    session
+        .send_request("model/list", json!({ "refresh": refresh }))
+        .await
 }
 
 pub(crate) async fn account_rate_limits_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     session
         .send_request("account/rateLimits/read", Value::Null)
@@ -353,8 +1099,9 @@ pub(crate) async fn account_rate_limits_core(
 pub(crate) async fn account_read_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
     workspace_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = {
         let sessions = sessions.lock().await;
         sessions.get(&workspace_id).cloned()
@@ -366,7 +1113,7 @@ pub(crate) async fn account_read_core(
     };
 
     let (entry, parent_entry) = resolve_workspace_and_parent(workspaces, &workspace_id).await?;
-    let agent_home = resolve_workspace_micode_home(&entry, parent_entry.as_ref())
+    let agent_home = resolve_workspace_micode_home(&entry, parent_entry.as_ref(), data_dir)
         .or_else(resolve_default_micode_home);
     let fallback = read_auth_account(agent_home);
 
@@ -377,7 +1124,7 @@ pub(crate) async fn micode_login_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     micode_login_cancels: &Mutex<HashMap<String, MiCodeLoginCancelState>>,
     workspace_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
     {
@@ -406,12 +1153,12 @@ pub(crate) async fn micode_login_core(
             Ok(_) => {
                 let mut cancels = micode_login_cancels.lock().await;
                 cancels.remove(&workspace_id);
-                return Err("MiCode login canceled.".to_string());
+                return Err("MiCode login canceled.".into());
             }
             Err(TryRecvError::Closed) => {
                 let mut cancels = micode_login_cancels.lock().await;
                 cancels.remove(&workspace_id);
-                return Err("MiCode login canceled.".to_string());
+                return Err("MiCode login canceled.".into());
             }
             Err(TryRecvError::Empty) => {}
         }
@@ -420,7 +1167,7 @@ pub(crate) async fn micode_login_core(
         if elapsed >= LOGIN_START_TIMEOUT {
             let mut cancels = micode_login_cancels.lock().await;
             cancels.remove(&workspace_id);
-            return Err("MiCode login start timed out.".to_string());
+            return Err("MiCode login start timed out.".into());
         }
 
         let tick = Duration::from_millis(150);
@@ -466,7 +1213,7 @@ pub(crate) async fn micode_login_cancel_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     micode_login_cancels: &Mutex<HashMap<String, MiCodeLoginCancelState>>,
     workspace_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let cancel_state = {
         let mut cancels = micode_login_cancels.lock().await;
         cancels.remove(&workspace_id)
@@ -514,7 +1261,7 @@ pub(crate) async fn micode_login_cancel_core(
 pub(crate) async fn skills_list_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     let params = json!({ "cwd": session.entry.path });
     session.send_request("skills/list", params).await
@@ -525,7 +1272,7 @@ pub(crate) async fn apps_list_core(
     workspace_id: String,
     cursor: Option<String>,
     limit: Option<u32>,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
     let params = json!({ "cursor": cursor, "limit": limit });
     session.send_request("app/list", params).await
@@ -536,28 +1283,85 @@ pub(crate) async fn respond_to_server_request_core(
     workspace_id: String,
     request_id: Value,
     result: Value,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let session = get_session_clone(sessions, &workspace_id).await?;
-    session.send_response(request_id, result).await
+    Ok(session.send_response(request_id, result).await?)
+}
+
+/// Searches the saved threads of a workspace; works without a running session. The
+/// store files are read on a blocking thread.
+pub(crate) async fn search_threads_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Value, CommandError> {
+    let workspace_path = workspaces
+        .lock()
+        .await
+        .get(&workspace_id)
+        .map(|entry| entry.path.clone())
+        .ok_or_else(CommandError::workspace_not_found)?;
+    let limit = search_limit(limit);
+    let hits =
+        tokio::task::spawn_blocking(move || search_threads_at(&workspace_path, &query, limit))
+            .await
+            .map_err(|err| err.to_string())?;
+    serde_json::to_value(hits).map_err(|err| err.to_string().into())
+}
+
+/// Exports one saved thread as Markdown or JSON, written to `path` when given and
+/// returned as content otherwise. Works without a running session.
+pub(crate) async fn export_thread_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: String,
+    thread_id: String,
+    format: String,
+    path: Option<String>,
+) -> Result<ThreadExport, CommandError> {
+    let format = ThreadExportFormat::parse(&format)?;
+    let workspace_path = workspaces
+        .lock()
+        .await
+        .get(&workspace_id)
+        .map(|entry| entry.path.clone())
+        .ok_or_else(CommandError::workspace_not_found)?;
+    tokio::task::spawn_blocking(move || {
+        let content = export_thread_at(&workspace_path, &thread_id, format)?;
+        Ok(deliver_thread_export(content, format, path.as_deref())?)
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+async fn approval_rules_path_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
+    workspace_id: &str,
+) -> Result<PathBuf, CommandError> {
+    let agent_home =
+        resolve_micode_home_for_workspace_core(workspaces, data_dir, workspace_id).await?;
+    Ok(rules::default_rules_path(&agent_home))
 }
 
 pub(crate) async fn remember_approval_rule_core(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
     workspace_id: String,
     command: Vec<String>,
-) -> Result<Value, String> {
+    decision: RuleDecision,
+) -> Result<Value, CommandError> {
     let command = command
         .into_iter()
         .map(|item| item.trim().to_string())
         .filter(|item| !item.is_empty())
         .collect::<Vec<_>>();
     if command.is_empty() {
-        return Err("empty command".to_string());
+        return Err("empty command".into());
     }
 
-    let agent_home = resolve_micode_home_for_workspace_core(workspaces, &workspace_id).await?;
-    let rules_path = rules::default_rules_path(&agent_home);
-    rules::append_prefix_rule(&rules_path, &command)?;
+    let rules_path = approval_rules_path_core(workspaces, data_dir, &workspace_id).await?;
+    rules::append_prefix_rule(&rules_path, &command, decision)?;
 
     Ok(json!({
         "ok": true,
@@ -567,34 +1371,37 @@ pub(crate) async fn remember_approval_rule_core(
 
 pub(crate) async fn list_approval_rules_core(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
     workspace_id: String,
-) -> Result<Value, String> {
-    let agent_home = resolve_micode_home_for_workspace_core(workspaces, &workspace_id).await?;
-    let rules_path = rules::default_rules_path(&agent_home);
-    let rules = rules::list_prefix_rules(&rules_path)?;
+) -> Result<Value, CommandError> {
+    let rules_path = approval_rules_path_core(workspaces, data_dir, &workspace_id).await?;
+    let rules = rules::list_prefix_rules(&rules_path, RuleDecision::Allow)?;
+    let deny_rules = rules::list_prefix_rules(&rules_path, RuleDecision::Deny)?;
     Ok(json!({
         "rules": rules,
+        "denyRules": deny_rules,
         "rulesPath": rules_path,
     }))
 }
 
 pub(crate) async fn remove_approval_rule_core(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
     workspace_id: String,
     command: Vec<String>,
-) -> Result<Value, String> {
+    decision: RuleDecision,
+) -> Result<Value, CommandError> {
     let command = command
         .into_iter()
         .map(|item| item.trim().to_string())
         .filter(|item| !item.is_empty())
         .collect::<Vec<_>>();
     if command.is_empty() {
-        return Err("empty command".to_string());
+        return Err("empty command".into());
     }
 
-    let agent_home = resolve_micode_home_for_workspace_core(workspaces, &workspace_id).await?;
-    let rules_path = rules::default_rules_path(&agent_home);
-    let removed = rules::remove_prefix_rule(&rules_path, &command)?;
+    let rules_path = approval_rules_path_core(workspaces, data_dir, &workspace_id).await?;
+    let removed = rules::remove_prefix_rule(&rules_path, &command, decision)?;
 
     Ok(json!({
         "ok": true,
@@ -603,11 +1410,272 @@ pub(crate) async fn remove_approval_rule_core(
     }))
 }
 
+/// The workspace's approval rules with their ids, creation times and hit counts.
+pub(crate) async fn approval_rules_list_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
+    workspace_id: String,
+) -> Result<Value, CommandError> {
+    let rules_path = approval_rules_path_core(workspaces, data_dir, &workspace_id).await?;
+    let rules = rules::list_stored_rules(&rules_path)?;
+    Ok(json!({
+        "rules": rules,
+        "rulesPath": rules_path,
+    }))
+}
+
+pub(crate) async fn approval_rules_delete_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
+    workspace_id: String,
+    rule_id: String,
+) -> Result<Value, CommandError> {
+    let rules_path = approval_rules_path_core(workspaces, data_dir, &workspace_id).await?;
+    let removed = rules::delete_stored_rule(&rules_path, &rule_id)?;
+    Ok(json!({
+        "ok": true,
+        "removed": removed,
+    }))
+}
+
+pub(crate) async fn approval_rules_update_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
+    workspace_id: String,
+    rule_id: String,
+    pattern: Option<Vec<String>>,
+    decision: Option<RuleDecision>,
+) -> Result<Value, CommandError> {
+    let rules_path = approval_rules_path_core(workspaces, data_dir, &workspace_id).await?;
+    let rule = rules::update_stored_rule(&rules_path, &rule_id, pattern, decision)?;
+    serde_json::to_value(rule).map_err(|err| err.to_string().into())
+}
+
+/// Writes the workspace's approval rules to a JSON file at `path`.
+pub(crate) async fn approval_rules_export_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
+    workspace_id: String,
+    path: String,
+) -> Result<Value, CommandError> {
+    let rules_path = approval_rules_path_core(workspaces, data_dir, &workspace_id).await?;
+    let exported = rules::export_rules(&rules_path, Path::new(&path))?;
+    Ok(json!({
+        "exported": exported,
+        "path": path,
+    }))
+}
+
+/// Adds the approval rules of a JSON file written by `approval_rules_export`.
+pub(crate) async fn approval_rules_import_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
+    workspace_id: String,
+    path: String,
+) -> Result<Value, CommandError> {
+    let rules_path = approval_rules_path_core(workspaces, data_dir, &workspace_id).await?;
+    let summary = rules::import_rules(&rules_path, Path::new(&path))?;
+    serde_json::to_value(summary).map_err(|err| err.to_string().into())
+}
+
 pub(crate) async fn get_config_model_core(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
     workspace_id: String,
-) -> Result<Value, String> {
-    let agent_home = resolve_micode_home_for_workspace_core(workspaces, &workspace_id).await?;
+) -> Result<Value, CommandError> {
+    let agent_home =
+        resolve_micode_home_for_workspace_core(workspaces, data_dir, &workspace_id).await?;
     let model = micode_config::read_config_model(Some(agent_home))?;
     Ok(json!({ "model": model }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        edit_mcp_server_at, pin_workspace_models, resolve_preferred_model,
+        validate_mcp_server_config, McpServerEdit, SettingsParseErrors,
+    };
+    use crate::types::{WorkspaceEntry, WorkspaceKind, WorkspaceSettings, WorktreeInfo};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn workspace(id: &str, kind: WorkspaceKind, preferred_model: Option<&str>) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: format!("/tmp/{id}"),
+            agent_bin: None,
+            worktree: kind.is_worktree().then(|| WorktreeInfo {
+                branch: "feature".to_string(),
+            }),
+            parent_id: kind.is_worktree().then(|| "main".to_string()),
+            kind,
+            settings: WorkspaceSettings {
+                preferred_model: preferred_model.map(ToString::to_string),
+                ..WorkspaceSettings::default()
+            },
+            stack: None,
+        }
+    }
+
+    #[test]
+    fn workspace_model_overrides_the_settings_file() {
+        let errors = SettingsParseErrors::default();
+        let home = std::env::temp_dir().join(format!("micode-preferred-model-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&home).expect("create home");
+        std::fs::write(
+            home.join("settings.json"),
+            r#"{ "model": { "preferredModel": "global-model" } }"#,
+        )
+        .expect("write settings");
+
+        let resolve =
+            |workspace, parent| resolve_preferred_model(workspace, parent, Some(&home), &errors);
+        assert_eq!(
+            resolve(Some("ws-model"), Some("parent-model")).as_deref(),
+            Some("ws-model")
+        );
+        // Worktrees without their own model follow the parent workspace.
+        assert_eq!(
+            resolve(None, Some("parent-model")).as_deref(),
+            Some("parent-model")
+        );
+        assert_eq!(resolve(Some("  "), None).as_deref(), Some("global-model"));
+        assert_eq!(resolve(None, None).as_deref(), Some("global-model"));
+
+        let _ = std::fs::remove_dir_all(&home);
+        assert_eq!(resolve(None, None), None);
+    }
+
+    #[test]
+    fn migration_pins_main_workspaces_to_the_model_they_were_using() {
+        let saved: WorkspaceEntry = serde_json::from_str(
+            r#"{ "id": "main", "name": "main", "path": "/tmp/main", "settings": {} }"#,
+        )
+        .expect("parse workspace saved before per-workspace models");
+        assert_eq!(saved.settings.preferred_model, None);
+
+        let mut workspaces = HashMap::from([
+            ("main".to_string(), saved),
+            (
+                "chosen".to_string(),
+                workspace("chosen", WorkspaceKind::Main, Some("chosen-model")),
+            ),
+            (
+                "tree".to_string(),
+                workspace("tree", WorkspaceKind::Worktree, None),
+            ),
+        ]);
+        let model = |id: &str, workspaces: &HashMap<String, WorkspaceEntry>| {
+            workspaces[id].settings.preferred_model.clone()
+        };
+
+        assert!(pin_workspace_models(&mut workspaces, |_| Some(
+            "global-model".to_string()
+        )));
+        assert_eq!(model("main", &workspaces).as_deref(), Some("global-model"));
+        assert_eq!(model("chosen", &workspaces).as_deref(), Some("chosen-model"));
+        assert_eq!(model("tree", &workspaces), None);
+        // Already migrated entries are left alone.
+        assert!(!pin_workspace_models(&mut workspaces, |_| Some(
+            "other-model".to_string()
+        )));
+    }
+
+    #[test]
+    fn mcp_server_edits_keep_the_other_settings() {
+        let errors = SettingsParseErrors::default();
+        let home = std::env::temp_dir().join(format!("micode-mcp-edit-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&home).expect("create home");
+        let path = home.join("settings.json");
+        std::fs::write(
+            &path,
+            r#"{ "model": { "preferredModel": "m" }, "mcpServers": { "git": { "command": "mcp-git" } } }"#,
+        )
+        .expect("write settings");
+        let read = || -> Value {
+            serde_json::from_str(&std::fs::read_to_string(&path).expect("read settings"))
+                .expect("parse settings")
+        };
+
+        let docs = json!({ "url": "https://mcp.example.com", "env": { "TOKEN": "t" } });
+        let servers = edit_mcp_server_at(
+            &path,
+            &errors,
+            McpServerEdit::Add,
+            " docs ",
+            Some(docs.clone()),
+        )
+        .expect("add docs");
+        assert_eq!(servers, vec!["docs".to_string(), "git".to_string()]);
+        assert_eq!(read()["mcpServers"]["docs"], docs);
+        assert_eq!(read()["model"]["preferredModel"], "m");
+        assert!(
+            edit_mcp_server_at(&path, &errors, McpServerEdit::Add, "docs", Some(docs)).is_err()
+        );
+
+        let git = json!({ "command": "mcp-git", "args": ["--repo", "."] });
+        edit_mcp_server_at(
+            &path,
+            &errors,
+            McpServerEdit::Update,
+            "git",
+            Some(git.clone()),
+        )
+        .expect("update git");
+        assert_eq!(read()["mcpServers"]["git"], git);
+        assert!(
+            edit_mcp_server_at(&path, &errors, McpServerEdit::Update, "nope", Some(git)).is_err()
+        );
+
+        let servers = edit_mcp_server_at(&path, &errors, McpServerEdit::Remove, "docs", None)
+            .expect("remove docs");
+        assert_eq!(servers, vec!["git".to_string()]);
+        assert_eq!(
+            read(),
+            json!({
+                "model": { "preferredModel": "m" },
+                "mcpServers": { "git": { "command": "mcp-git", "args": ["--repo", "."] } }
+            })
+        );
+
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn invalid_servers_and_malformed_settings_are_never_written() {
+        let errors = SettingsParseErrors::default();
+        assert!(validate_mcp_server_config(&json!({ "command": "mcp" })).is_ok());
+        assert!(validate_mcp_server_config(&json!({ "httpUrl": "http://x" })).is_ok());
+        assert!(validate_mcp_server_config(&json!({ "command": "  " })).is_err());
+        assert!(validate_mcp_server_config(&json!({ "command": 1 })).is_err());
+        assert!(validate_mcp_server_config(&json!({ "url": "http://x", "args": [1] })).is_err());
+        assert!(
+            validate_mcp_server_config(&json!({ "command": "mcp", "env": { "A": 1 } })).is_err()
+        );
+        assert!(validate_mcp_server_config(&json!(["mcp"])).is_err());
+
+        let home = std::env::temp_dir().join(format!("micode-mcp-edit-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&home).expect("create home");
+        let path = home.join("settings.json");
+        let server = json!({ "command": "mcp" });
+        assert!(
+            edit_mcp_server_at(&path, &errors, McpServerEdit::Add, "", Some(server.clone()))
+                .is_err()
+        );
+        assert!(!path.exists());
+
+        let malformed = r#"{ "model": { "preferredModel": "m" "#;
+        std::fs::write(&path, malformed).expect("write settings");
+        let error = edit_mcp_server_at(&path, &errors, McpServerEdit::Add, "docs", Some(server))
+            .expect_err("malformed settings must not be rewritten");
+        assert!(error.contains("Refusing to rewrite"), "{error}");
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("read settings"),
+            malformed
+        );
+
+        let _ = std::fs::remove_dir_all(&home);
+    }
+}
@@ -1,8 +1,19 @@
 pub(crate) mod account;
+pub(crate) mod auto_run_core;
+pub(crate) mod bootstrap_core;
+pub(crate) mod command_timings_core;
 pub(crate) mod files_core;
 pub(crate) mod git_core;
 pub(crate) mod micode_core;
+pub(crate) mod operations_core;
 pub(crate) mod process_core;
+pub(crate) mod resource_monitor_core;
+pub(crate) mod run_kickoff_core;
 pub(crate) mod settings_core;
+pub(crate) mod thread_ownership_core;
+pub(crate) mod usage_counters_core;
+pub(crate) mod usage_ledger_core;
+pub(crate) mod workspace_roots_core;
+pub(crate) mod workspace_stack_core;
 pub(crate) mod workspaces_core;
 pub(crate) mod worktree_core;
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::backend::event_methods;
+use crate::backend::events::{AppServerEvent, EventSink};
+
+/// Set once by `cancel_operation`; long-running commands check it between steps or race
+/// their child processes against it.
+#[derive(Clone, Default)]
+pub(crate) struct CancellationToken(Arc<TokenState>);
+
+#[derive(Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    pub(crate) fn cancel(&self) {
+        if !self.0.cancelled.swap(true, Ordering::SeqCst) {
+            self.0.notify.notify_waiters();
+        }
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub(crate) async fn cancelled(&self) {
+        loop {
+            // Registered before the check, so a cancel in between still wakes us.
+            let notified = self.0.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Drives `future` until it finishes, or drops it and returns `None` once cancelled.
+    pub(crate) async fn run_until_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        let mut future = std::pin::pin!(future);
+        let mut cancelled = std::pin::pin!(self.cancelled());
+        std::future::poll_fn(|cx| {
+            if cancelled.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+            future.as_mut().poll(cx).map(Some)
+        })
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum OperationStatus {
+    Completed,
+    Cancelled,
+}
+
+/// What a cancellable command returns: its result, or `cancelled` with no result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OperationOutcome<T> {
+    pub(crate) operation_id: String,
+    pub(crate) status: OperationStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) result: Option<T>,
+}
+
+/// Tokens of the running operations, keyed by operation id.
+#[derive(Default)]
+pub(crate) struct OperationRegistry {
+    tokens: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl OperationRegistry {
+    fn tokens(&self) -> std::sync::MutexGuard<'_, HashMap<String, CancellationToken>> {
+        self.tokens
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Registers an operation under `operation_id`, or a fresh id when the caller has none.
+    pub(crate) fn start<E: EventSink>(
+        &self,
+        operation_id: Option<String>,
+        kind: &'static str,
+        event_sink: E,
+    ) -> Operation<'_, E> {
+        let id = operation_id
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let token = CancellationToken::default();
+        if let Some(previous) = self.tokens().insert(id.clone(), token.clone()) {
+            // An id reused while its operation still runs stops the older one.
+            previous.cancel();
+        }
+        Operation {
+            registry: self,
+            id,
+            kind,
+            token,
+            event_sink,
+        }
+    }
+
+    /// Cancels a running operation; false when the id is unknown or already finished.
+    pub(crate) fn cancel(&self, operation_id: &str) -> bool {
+        match self.tokens().get(operation_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// One registered operation; unregistered when dropped.
+pub(crate) struct Operation<'a, E: EventSink> {
+    registry: &'a OperationRegistry,
+    id: String,
+    kind: &'static str,
+    token: CancellationToken,
+    event_sink: E,
+}
+
+impl<E: EventSink> Operation<'_, E> {
+    pub(crate) fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Emits `operation/progress`; `completed` and `total` count whatever the stage counts.
+    pub(crate) fn progress(&self, stage: &str, completed: Option<u64>, total: Option<u64>) {
+        let mut params = json!({
+            "operationId": self.id,
+            "kind": self.kind,
+            "stage": stage,
+        });
+        if let Some(completed) = completed {
+            params["completed"] = json!(completed);
+        }
+        if let Some(total) = total {
+            params["total"] = json!(total);
+        }
+        self.emit(event_methods::OPERATION_PROGRESS, params);
+    }
+
+    /// Emits `operation/finished` and turns the body's result into the command's outcome.
+    /// `Ok(None)` means the body stopped for a cancel; an error after a cancel is taken
+    /// as caused by it.
+    pub(crate) fn finish<T, Error: std::fmt::Display>(
+        self,
+        result: Result<Option<T>, Error>,
+    ) -> Result<OperationOutcome<T>, Error> {
+        let (status, result) = match result {
+            Ok(Some(value)) => (OperationStatus::Completed, Some(value)),
+            Ok(None) => (OperationStatus::Cancelled, None),
+            Err(_) if self.is_cancelled() => (OperationStatus::Cancelled, None),
+            Err(error) => {
+                self.emit(
+                    event_methods::OPERATION_FINISHED,
+                    finished_params(&self.id, self.kind, "failed", Some(&error.to_string())),
+                );
+                return Err(error);
+            }
+        };
+        let status_name = match status {
+            OperationStatus::Completed => "completed",
+            OperationStatus::Cancelled => "cancelled",
+        };
+        self.emit(
+            event_methods::OPERATION_FINISHED,
+            finished_params(&self.id, self.kind, status_name, None),
+        );
+        Ok(OperationOutcome {
+            operation_id: self.id.clone(),
+            status,
+            result,
+        })
+    }
+
+    fn emit(&self, method: &str, params: Value) {
+        self.event_sink.emit_app_server_event(AppServerEvent {
+            workspace_id: String::new(),
+            message: json!({ "method": method, "params": params }),
+        });
+    }
+}
+
+impl<E: EventSink> Drop for Operation<'_, E> {
+    fn drop(&mut self) {
+        let mut tokens = self.registry.tokens();
+        // Only our own token: a newer operation may have taken over the id.
+        if tokens
+            .get(&self.id)
+            .is_some_and(|token| Arc::ptr_eq(&token.0, &self.token.0))
+        {
+            tokens.remove(&self.id);
+        }
+    }
+}
+
+fn finished_params(operation_id: &str, kind: &str, status: &str, error: Option<&str>) -> Value {
+    let mut params = json!({
+        "operationId": operation_id,
+        "kind": kind,
+        "status": status,
+    });
+    if let Some(error) = error {
+        params["error"] = json!(error);
+    }
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::events::{TerminalExit, TerminalOutput};
+
+    #[derive(Clone, Default)]
+    struct RecordingSink(Arc<Mutex<Vec<Value>>>);
+
+    impl EventSink for RecordingSink {
+        fn emit_app_server_event(&self, event: AppServerEvent) {
+            self.0.lock().unwrap().push(event.message);
+        }
+
+        fn emit_terminal_output(&self, _event: TerminalOutput) {}
+
+        fn emit_terminal_exit(&self, _event: TerminalExit) {}
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn cancel_stops_the_operation_with_a_cancelled_outcome() {
+        let registry = OperationRegistry::default();
+        let sink = RecordingSink::default();
+        let operation = registry.start(Some("op-1".to_string()), "clone", sink.clone());
+        operation.progress("cloning", None, None);
+        assert!(registry.cancel("op-1"));
+
+        let token = operation.token().clone();
+        let output = block_on(token.run_until_cancelled(std::future::pending::<()>()));
+        assert_eq!(output, None);
+        let outcome = operation.finish::<(), String>(Err("git was killed".to_string()));
+        let outcome = serde_json::to_value(outcome.unwrap()).unwrap();
+        assert_eq!(
+            outcome,
+            json!({ "operationId": "op-1", "status": "cancelled" })
+        );
+
+        assert!(!registry.cancel("op-1"));
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            vec![
+                json!({
+                    "method": "operation/progress",
+                    "params": { "operationId": "op-1", "kind": "clone", "stage": "cloning" },
+                }),
+                json!({
+                    "method": "operation/finished",
+                    "params": { "operationId": "op-1", "kind": "clone", "status": "cancelled" },
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn uncancelled_operations_complete_or_fail_as_before() {
+        let registry = OperationRegistry::default();
+        let sink = RecordingSink::default();
+        let operation = registry.start(None, "workspaceSearch", sink.clone());
+        let token = operation.token().clone();
+        assert_eq!(block_on(token.run_until_cancelled(async { 3 })), Some(3));
+        // A cancel from another thread wakes a pending wait.
+        let waiting = CancellationToken::default();
+        let canceller = waiting.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            canceller.cancel();
+        });
+        assert_eq!(
+            block_on(waiting.run_until_cancelled(std::future::pending::<()>())),
+            None
+        );
+        let outcome = operation
+            .finish(Ok::<_, String>(Some(vec!["a.rs".to_string()])))
+            .unwrap();
+        assert_eq!(outcome.status, OperationStatus::Completed);
+        assert_eq!(outcome.result, Some(vec!["a.rs".to_string()]));
+        assert!(!outcome.operation_id.is_empty());
+
+        let operation = registry.start(Some("op-2".to_string()), "export", sink.clone());
+        assert_eq!(
+            operation
+                .finish::<(), String>(Err("disk full".to_string()))
+                .unwrap_err(),
+            "disk full"
+        );
+        assert_eq!(
+            sink.0.lock().unwrap().last().unwrap()["params"],
+            json!({ "operationId": "op-2", "kind": "export", "status": "failed", "error": "disk full" })
+        );
+
+        // Reusing a running id cancels the older operation; its drop keeps the newer one.
+        let older = registry.start(Some("op-3".to_string()), "export", sink.clone());
+        let newer = registry.start(Some("op-3".to_string()), "export", sink.clone());
+        assert!(older.is_cancelled());
+        drop(older);
+        assert!(registry.cancel("op-3"));
+        assert!(newer.is_cancelled());
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::backend::app_server::{now_ms, WorkspaceSession};
+use crate::backend::event_methods;
+use crate::backend::events::AppServerEvent;
+use crate::types::AppSettings;
+
+/// How often child processes are sampled. One snapshot of the process table covers every
+/// child.
+pub(crate) const RESOURCE_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Limits a child may exceed only briefly before `workspace/resourceWarning` fires.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ResourceThresholds {
+    pub(crate) rss_bytes: u64,
+    pub(crate) cpu_percent: f64,
+    pub(crate) sustained: Duration,
+}
+
+impl ResourceThresholds {
+    pub(crate) fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            rss_bytes: settings.resource_warning_rss_mb.saturating_mul(1024 * 1024),
+            cpu_percent: settings.resource_warning_cpu_percent,
+            sustained: Duration::from_secs(settings.resource_warning_sustained_secs),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ProcessSample {
+    pub(crate) rss_bytes: u64,
+    /// Cumulative CPU time; CPU% comes from the delta between two samples.
+    pub(crate) cpu_time_ms: u64,
+}
+
+/// Current and peak usage of one child process.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ResourceUsage {
+    pub(crate) pid: Option<u32>,
+    pub(crate) cpu_percent: f64,
+    pub(crate) rss_bytes: u64,
+    pub(crate) peak_cpu_percent: f64,
+    pub(crate) peak_rss_bytes: u64,
+    pub(crate) sampled_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ResourceWarningKind {
+    Memory,
+    Cpu,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ResourceTracker {
+    usage: ResourceUsage,
+    last_cpu: Option<(u64, Instant)>,
+    over_since: Option<Instant>,
+    warned: bool,
+}
+
+impl ResourceTracker {
+    pub(crate) fn usage(&self) -> ResourceUsage {
+        self.usage.clone()
+    }
+
+    /// Records a sample and returns the exceeded limit once it has held for the sustained
+    /// period. Warns once per episode; dropping back under the limits re-arms it.
+    pub(crate) fn record(
+        &mut self,
+        pid: u32,
+        sample: ProcessSample,
+        now: Instant,
+        thresholds: &ResourceThresholds,
+    ) -> Option<ResourceWarningKind> {
+        if self.usage.pid != Some(pid) {
+            *self = Self::default();
+            self.usage.pid = Some(pid);
+        }
+        let cpu_percent = match self.last_cpu {
+            Some((cpu_time_ms, at)) if now > at => {
+                let elapsed_ms = now.duration_since(at).as_millis().max(1) as f64;
+                let used_ms = sample.cpu_time_ms.saturating_sub(cpu_time_ms) as f64;
+                (used_ms / elapsed_ms * 1000.0).round() / 10.0
+            }
+            _ => 0.0,
+        };
+        self.last_cpu = Some((sample.cpu_time_ms, now));
+        self.usage.cpu_percent = cpu_percent;
+        self.usage.rss_bytes = sample.rss_bytes;
+        self.usage.peak_cpu_percent = self.usage.peak_cpu_percent.max(cpu_percent);
+        self.usage.peak_rss_bytes = self.usage.peak_rss_bytes.max(sample.rss_bytes);
+        self.usage.sampled_at_ms = Some(now_ms());
+
+        let exceeded = if thresholds.rss_bytes > 0 && sample.rss_bytes > thresholds.rss_bytes {
+            Some(ResourceWarningKind::Memory)
+        } else if thresholds.cpu_percent > 0.0 && cpu_percent > thresholds.cpu_percent {
+            Some(ResourceWarningKind::Cpu)
+        } else {
+            None
+        };
+        let Some(kind) = exceeded else {
+            self.over_since = None;
+            self.warned = false;
+            return None;
+        };
+        let since = *self.over_since.get_or_insert(now);
+        if self.warned || now.duration_since(since) < thresholds.sustained {
+            return None;
+        }
+        self.warned = true;
+        Some(kind)
+    }
+}
+
+/// One row of the process table: pid, parent pid and usage.
+type ProcessRow = (u32, Option<u32>, ProcessSample);
+
+/// Sums the usage of each root with all its descendants, so MCP servers and the commands
+/// they run count toward the child that spawned them. Roots missing from `rows` exited and
+/// are absent from the result.
+fn aggregate_process_trees(rows: &[ProcessRow], roots: &[u32]) -> HashMap<u32, ProcessSample> {
+    let mut samples: HashMap<u32, ProcessSample> = HashMap::with_capacity(rows.len());
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (pid, parent, sample) in rows {
+        samples.insert(*pid, *sample);
+        if let Some(parent) = parent {
+            children.entry(*parent).or_default().push(*pid);
+        }
+    }
+    roots
+        .iter()
+        .filter_map(|root| {
+            let mut total = *samples.get(root)?;
+            let mut visited = HashSet::from([*root]);
+            let mut pending = children.get(root).cloned().unwrap_or_default();
+            while let Some(pid) = pending.pop() {
+                if !visited.insert(pid) {
+                    continue;
+                }
+                if let Some(sample) = samples.get(&pid) {
+                    total.rss_bytes += sample.rss_bytes;
+                    total.cpu_time_ms += sample.cpu_time_ms;
+                }
+                pending.extend(children.get(&pid).into_iter().flatten());
+            }
+            Some((*root, total))
+        })
+        .collect()
+}
+
+fn read_process_table() -> Vec<ProcessRow> {
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing().with_cpu().with_memory(),
+    );
+    system
+        .processes()
+        .values()
+        // Linux lists threads as tasks of their process; they share its memory.
+        .filter(|process| process.thread_kind().is_none())
+        .map(|process| {
+            (
+                process.pid().as_u32(),
+                process.parent().map(Pid::as_u32),
+                ProcessSample {
+                    rss_bytes: process.memory(),
+                    cpu_time_ms: process.accumulated_cpu_time(),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Samples RSS and cumulative CPU time of the process tree under each of `pids` from one
+/// snapshot of the process table. Processes that exited are simply absent.
+pub(crate) async fn sample_processes(pids: &[u32]) -> HashMap<u32, ProcessSample> {
+    if pids.is_empty() {
+        return HashMap::new();
+    }
+    let roots = pids.to_vec();
+    tokio::task::spawn_blocking(move || aggregate_process_trees(&read_process_table(), &roots))
+        .await
+        .unwrap_or_default()
+}
+
+pub(crate) fn resource_warning_event(
+    workspace_id: &str,
+    terminal_id: Option<&str>,
+    kind: ResourceWarningKind,
+    usage: &ResourceUsage,
+    thresholds: &ResourceThresholds,
+) -> AppServerEvent {
+    AppServerEvent {
+        workspace_id: workspace_id.to_string(),
+        message: json!({
+            "method": event_methods::WORKSPACE_RESOURCE_WARNING,
+            "params": {
+                "workspaceId": workspace_id,
+                "terminalId": terminal_id,
+                "kind": kind,
+                "usage": usage,
+                "thresholds": {
+                    "rssBytes": thresholds.rss_bytes,
+                    "cpuPercent": thresholds.cpu_percent,
+                    "sustainedSeconds": thresholds.sustained.as_secs()
+                },
+                "action": if terminal_id.is_some() { "closeTerminal" } else { "restartSession" }
+            }
+        }),
+    }
+}
+
+/// Samples every connected agent child and returns warning events for runaway ones.
+/// Does nothing when resource monitoring is disabled in settings.
+pub(crate) async fn sample_session_resources_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    app_settings: &Mutex<AppSettings>,
+) -> Vec<AppServerEvent> {
+    let thresholds = {
+        let settings = app_settings.lock().await;
+        if !settings.resource_monitoring_enabled {
+            return Vec::new();
+        }
+        ResourceThresholds::from_settings(&settings)
+    };
+    let sessions: Vec<(String, Arc<WorkspaceSession>)> = sessions
+        .lock()
+        .await
+        .iter()
+        .map(|(id, session)| (id.clone(), Arc::clone(session)))
+        .collect();
+    let mut targets = Vec::with_capacity(sessions.len());
+    for (workspace_id, session) in sessions {
+        let pid = session.child.lock().await.id();
+        if let Some(pid) = pid {
+            targets.push((workspace_id, session, pid));
+        }
+    }
+    let pids: Vec<u32> = targets.iter().map(|(_, _, pid)| *pid).collect();
+    let samples = sample_processes(&pids).await;
+    let now = Instant::now();
+    let mut events = Vec::new();
+    for (workspace_id, session, pid) in targets {
+        let Some(sample) = samples.get(&pid) else {
+            continue;
+        };
+        let Ok(mut tracker) = session.resource_usage.lock() else {
+            continue;
+        };
+        if let Some(kind) = tracker.record(pid, *sample, now, &thresholds) {
+            events.push(resource_warning_event(
+                &workspace_id,
+                None,
+                kind,
+                &tracker.usage(),
+                &thresholds,
+            ));
+        }
+    }
+    events
+}
+
+/// Current and peak usage of each connected agent child, keyed by workspace id.
+pub(crate) async fn session_resource_usage_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+) -> Value {
+    let sessions = sessions.lock().await;
+    let usage: serde_json::Map<String, Value> = sessions
+        .iter()
+        .map(|(workspace_id, session)| {
+            let usage = session
+                .resource_usage
+                .lock()
+                .map(|tracker| tracker.usage())
+                .unwrap_or_default();
+            (
+                workspace_id.clone(),
+                serde_json::to_value(usage).unwrap_or(Value::Null),
+            )
+        })
+        .collect();
+    Value::Object(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        aggregate_process_trees, resource_warning_event, ProcessSample, ResourceThresholds,
+        ResourceTracker, ResourceUsage, ResourceWarningKind,
+    };
+    use crate::backend::event_methods::{self, documented_payload_keys};
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn warning_payload_matches_the_registered_description() {
+        let thresholds = ResourceThresholds {
+            rss_bytes: 1_000,
+            cpu_percent: 90.0,
+            sustained: Duration::from_secs(10),
+        };
+        for terminal_id in [None, Some("terminal-1")] {
+            let event = resource_warning_event(
+                "ws-1",
+                terminal_id,
+                ResourceWarningKind::Memory,
+                &ResourceUsage::default(),
+                &thresholds,
+            );
+            let mut keys: Vec<&str> = event.message["params"]
+                .as_object()
+                .expect("params object")
+                .keys()
+                .map(String::as_str)
+                .collect();
+            let mut documented = documented_payload_keys(event_methods::WORKSPACE_RESOURCE_WARNING);
+            keys.sort_unstable();
+            documented.sort_unstable();
+            assert_eq!(keys, documented);
+        }
+    }
+
+    #[test]
+    fn sums_each_process_tree() {
+        let sample = |rss_bytes, cpu_time_ms| ProcessSample {
+            rss_bytes,
+            cpu_time_ms,
+        };
+        let rows = [
+            (1, None, sample(1, 1)),
+            (10, Some(1), sample(100, 1_000)),
+            // An MCP server and the command it runs.
+            (11, Some(10), sample(20, 200)),
+            (12, Some(11), sample(3, 30)),
+            (20, Some(1), sample(50, 500)),
+        ];
+        let samples = aggregate_process_trees(&rows, &[10, 20, 99]);
+        assert_eq!(samples.get(&10), Some(&sample(123, 1_230)));
+        assert_eq!(samples.get(&20), Some(&sample(50, 500)));
+        assert!(!samples.contains_key(&99));
+    }
+
+    #[test]
+    fn warns_once_after_sustained_overuse() {
+        let thresholds = ResourceThresholds {
+            rss_bytes: 1_000,
+            cpu_percent: 90.0,
+            sustained: Duration::from_secs(10),
+        };
+        let start = Instant::now();
+        let sample = |rss_bytes, cpu_time_ms| ProcessSample {
+            rss_bytes,
+            cpu_time_ms,
+        };
+        let mut tracker = ResourceTracker::default();
+        assert_eq!(tracker.record(7, sample(500, 0), start, &thresholds), None);
+        // Two seconds of CPU in two seconds of wall time: 100%.
+        let at = start + Duration::from_secs(2);
+        assert_eq!(tracker.record(7, sample(500, 2_000), at, &thresholds), None);
+        assert_eq!(tracker.usage().cpu_percent, 100.0);
+
+        let at = start + Duration::from_secs(3);
+        assert_eq!(
+            tracker.record(7, sample(5_000, 2_000), at, &thresholds),
+            None
+        );
+        let at = start + Duration::from_secs(14);
+        assert_eq!(
+            tracker.record(7, sample(5_000, 2_000), at, &thresholds),
+            Some(ResourceWarningKind::Memory)
+        );
+        let at = start + Duration::from_secs(20);
+        assert_eq!(
+            tracker.record(7, sample(5_000, 2_000), at, &thresholds),
+            None
+        );
+        assert_eq!(tracker.usage().peak_rss_bytes, 5_000);
+        assert_eq!(tracker.usage().peak_cpu_percent, 100.0);
+
+        // A respawned child starts from scratch.
+        let at = start + Duration::from_secs(21);
+        assert_eq!(tracker.record(8, sample(100, 0), at, &thresholds), None);
+        assert_eq!(tracker.usage().peak_rss_bytes, 100);
+    }
+}
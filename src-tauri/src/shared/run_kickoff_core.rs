@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+use crate::shared::workspace_stack_core::{get_workspace_stack_core, stack_prompt_context};
+use crate::shared::workspaces_core::AGENTS_MD_FILE_NAME;
+use crate::types::{AppSettings, RunKickoffTemplateRef, WorkspaceEntry};
+
+/// Placeholders a run kickoff template may use, written as `{{name}}`.
+pub(crate) const KICKOFF_PLACEHOLDERS: &[&str] = &["task", "stack", "branch", "instructions_file"];
+
+/// Used when neither the workspace nor the app settings define a template. Lines whose
+/// placeholders are all empty are dropped when rendering.
+pub(crate) const DEFAULT_RUN_KICKOFF_TEMPLATE: &str = "{{task}}
+
+Branch: {{branch}}
+Workspace stack: {{stack}}
+Follow the project instructions in {{instructions_file}}.
+
+Keep the change focused on this task. When you are done, run the tests and summarize \
+what changed.";
+
+/// A rendered kickoff message and the template it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RunKickoffMessage {
+    pub(crate) message: String,
+    pub(crate) template: RunKickoffTemplateRef,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct KickoffValues {
+    pub(crate) task: String,
+    pub(crate) stack: String,
+    pub(crate) branch: String,
+    pub(crate) instructions_file: String,
+}
+
+impl KickoffValues {
+    fn get(&self, name: &str) -> &str {
+        match name {
+            "task" => &self.task,
+            "stack" => &self.stack,
+            "branch" => &self.branch,
+            "instructions_file" => &self.instructions_file,
+            _ => "",
+        }
+    }
+}
+
+/// Checks a template when it is saved, so an unknown placeholder never surfaces in the
+/// middle of starting a run.
+pub(crate) fn validate_kickoff_template(template: &str) -> Result<(), String> {
+    let mut has_task = false;
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| "Run kickoff template has an unclosed `{{`.".to_string())?;
+        let name = after[..end].trim();
+        if !KICKOFF_PLACEHOLDERS.contains(&name) {
+            let known = KICKOFF_PLACEHOLDERS
+                .iter()
+                .map(|name| format!("{{{{{name}}}}}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(format!(
+                "Unknown placeholder `{{{{{name}}}}}` in run kickoff template. Use one of: {known}."
+            ));
+        }
+        has_task |= name == "task";
+        rest = &after[end + 2..];
+    }
+    if !has_task {
+        return Err("Run kickoff template must include `{{task}}`.".to_string());
+    }
+    Ok(())
+}
+
+/// Fills in a validated template. A line is dropped when it has placeholders and all of
+/// them are empty, so an unknown branch or stack leaves no dangling label.
+pub(crate) fn render_kickoff_template(template: &str, values: &KickoffValues) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in template.lines() {
+        let mut out = String::new();
+        let mut rest = line;
+        let mut placeholders = 0;
+        let mut filled = 0;
+        while let Some(start) = rest.find("{{") {
+            let after = &rest[start + 2..];
+            let Some(end) = after.find("}}") else {
+                break;
+            };
+            out.push_str(&rest[..start]);
+            let value = values.get(after[..end].trim());
+            placeholders += 1;
+            if !value.is_empty() {
+                filled += 1;
+            }
+            out.push_str(value);
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+        if placeholders > 0 && filled == 0 {
+            continue;
+        }
+        lines.push(out);
+    }
+    lines.join("\n").trim().to_string()
+}
+
+pub(crate) fn kickoff_template_hash(template: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(template.as_bytes()));
+    digest[..12].to_string()
+}
+
+fn non_blank(template: Option<&String>) -> Option<&str> {
+    template
+        .map(String::as_str)
+        .filter(|template| !template.trim().is_empty())
+}
+
+/// The workspace template, then the parent's for worktrees, then the app-level one.
+pub(crate) fn resolve_kickoff_template<'a>(
+    entry: &'a WorkspaceEntry,
+    parent: Option<&'a WorkspaceEntry>,
+    global: Option<&'a String>,
+) -> (String, &'a str) {
+    let workspace = std::iter::once(entry).chain(parent).find_map(|entry| {
+        non_blank(entry.settings.run_kickoff_template.as_ref())
+            .map(|template| (format!("workspace:{}", entry.id), template))
+    });
+    workspace
+        .or_else(|| non_blank(global).map(|template| ("global".to_string(), template)))
+        .unwrap_or_else(|| ("default".to_string(), DEFAULT_RUN_KICKOFF_TEMPLATE))
+}
+
+pub(crate) async fn build_run_kickoff_message_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    workspace_id: &str,
+    task: &str,
+    branch: Option<String>,
+) -> Result<RunKickoffMessage, String> {
+    let task = task.trim();
+    if task.is_empty() {
+        return Err("Task is required.".to_string());
+    }
+    let (entry, parent) = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(workspace_id)
+            .cloned()
+            .ok_or_else(|| "workspace not found".to_string())?;
+        let parent = entry
+            .parent_id
+            .as_ref()
+            .and_then(|parent_id| workspaces.get(parent_id))
+            .cloned();
+        (entry, parent)
+    };
+    let global = app_settings.lock().await.run_kickoff_template.clone();
+    let (template_id, template) =
+        resolve_kickoff_template(&entry, parent.as_ref(), global.as_ref());
+    validate_kickoff_template(template)
+        .map_err(|err| format!("Run kickoff template `{template_id}` is invalid: {err}"))?;
+
+    let stack = get_workspace_stack_core(workspaces, workspace_id, false)
+        .await
+        .ok()
+        .and_then(|stack| stack_prompt_context(&stack))
+        .unwrap_or_default();
+    let branch = branch
+        .map(|branch| branch.trim().to_string())
+        .filter(|branch| !branch.is_empty())
+        .or_else(|| {
+            entry
+                .worktree
+                .as_ref()
+                .map(|worktree| worktree.branch.clone())
+        })
+        .unwrap_or_default();
+    let instructions_file = if Path::new(&entry.path).join(AGENTS_MD_FILE_NAME).is_file() {
+        AGENTS_MD_FILE_NAME.to_string()
+    } else {
+        String::new()
+    };
+    let values = KickoffValues {
+        task: task.to_string(),
+        stack,
+        branch,
+        instructions_file,
+    };
+    Ok(RunKickoffMessage {
+        message: render_kickoff_template(template, &values),
+        template: RunKickoffTemplateRef {
+            template_id,
+            template_hash: kickoff_template_hash(template),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{WorkspaceKind, WorkspaceSettings};
+
+    fn workspace(id: &str, template: Option<&str>) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: format!("/tmp/{id}"),
+            agent_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings {
+                run_kickoff_template: template.map(ToString::to_string),
+                ..WorkspaceSettings::default()
+            },
+            stack: None,
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_placeholders_and_missing_task() {
+        assert!(validate_kickoff_template(DEFAULT_RUN_KICKOFF_TEMPLATE).is_ok());
+        let error = validate_kickoff_template("{{task}} on {{ticket}}").unwrap_err();
+        assert!(error.contains("`{{ticket}}`"), "{error}");
+        assert!(error.contains("{{instructions_file}}"), "{error}");
+        assert!(validate_kickoff_template("Do {{task").is_err());
+        assert!(validate_kickoff_template("Run the tests.").is_err());
+    }
+
+    #[test]
+    fn drops_lines_whose_placeholders_are_empty() {
+        let values = KickoffValues {
+            task: "Fix the login loop".to_string(),
+            branch: "fix/login-loop".to_string(),
+            ..KickoffValues::default()
+        };
+        let message = render_kickoff_template(DEFAULT_RUN_KICKOFF_TEMPLATE, &values);
+        assert!(message.starts_with("Fix the login loop\n\nBranch: fix/login-loop\n\nKeep"));
+        assert!(!message.contains("Workspace stack"));
+        assert!(!message.contains("{{"));
+    }
+
+    #[test]
+    fn workspace_templates_win_over_parent_and_global() {
+        let global = "Global: {{task}}".to_string();
+        let parent = workspace("main", Some("Parent: {{task}}"));
+        let mut child = workspace("wt", None);
+        child.kind = WorkspaceKind::Worktree;
+
+        let (id, template) = resolve_kickoff_template(&child, Some(&parent), Some(&global));
+        assert_eq!(
+            (id.as_str(), template),
+            ("workspace:main", "Parent: {{task}}")
+        );
+        child.settings.run_kickoff_template = Some("Own: {{task}}".to_string());
+        let (id, _) = resolve_kickoff_template(&child, Some(&parent), Some(&global));
+        assert_eq!(id, "workspace:wt");
+        let blank = workspace("blank", Some(" "));
+        let (id, template) = resolve_kickoff_template(&blank, None, Some(&global));
+        assert_eq!((id.as_str(), template), ("global", "Global: {{task}}"));
+        let solo = workspace("solo", None);
+        let (id, template) = resolve_kickoff_template(&solo, None, None);
+        assert_eq!(id, "default");
+        assert_eq!(kickoff_template_hash(template).len(), 12);
+        assert_ne!(
+            kickoff_template_hash(template),
+            kickoff_template_hash(&global)
+        );
+    }
+}
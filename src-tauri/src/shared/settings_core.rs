@@ -1,8 +1,18 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use tokio::sync::Mutex;
 
+use crate::backend::app_server::{SessionSettings, WorkspaceSession};
+use crate::backend::connect_queue::ConnectQueue;
+use crate::backend::events::AppServerEvent;
+use crate::backend::sampling::validate_sampling_params;
+use crate::backend::settings_events::{SettingsRevision, SettingsScope};
+use crate::micode::args::parse_micode_args;
 use crate::micode::config as micode_config;
+use crate::shared::command_timings_core::CommandTimingsRegistry;
+use crate::shared::run_kickoff_core::validate_kickoff_template;
 use crate::storage::write_settings;
 use crate::types::AppSettings;
 
@@ -14,7 +24,10 @@ fn normalize_personality(value: &str) -> Option<&'static str> {
     }
 }
 
-pub(crate) async fn get_app_settings_core(app_settings: &Mutex<AppSettings>) -> AppSettings {
+pub(crate) async fn get_app_settings_core(
+    app_settings: &Mutex<AppSettings>,
+    settings_revision: &SettingsRevision,
+) -> AppSettings {
     let mut settings = app_settings.lock().await.clone();
     if let Ok(Some(collab_enabled)) = micode_config::read_collab_enabled() {
         settings.experimental_collab_enabled = collab_enabled;
@@ -39,14 +52,48 @@ pub(crate) async fn get_app_settings_core(app_settings: &Mutex<AppSettings>) ->
             .unwrap_or("friendly")
             .to_string();
     }
+    settings.settings_revision = settings_revision.current();
     settings
 }
 
+/// Hands updated settings to the state's backend consumers that keep them outside
+/// `AppSettings`, so none of them keeps the value it was built with.
+pub(crate) fn apply_backend_settings(
+    settings: &AppSettings,
+    connect_queue: &ConnectQueue,
+    command_timings: &CommandTimingsRegistry,
+) {
+    connect_queue.set_limit(settings.max_concurrent_connects);
+    command_timings.set_slow_threshold(settings.slow_command_threshold_ms);
+}
+
+/// Hands updated settings to the running sessions; new sessions read them at spawn.
+pub(crate) async fn apply_session_settings(
+    settings: &AppSettings,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+) {
+    let session_settings = SessionSettings::from_app_settings(settings);
+    for session in sessions.lock().await.values() {
+        session.apply_session_settings(session_settings.clone());
+    }
+}
+
+/// Saves the settings and returns them, at the new revision, with the
+/// `settings/updated` event for the caller to emit.
 pub(crate) async fn update_app_settings_core(
     settings: AppSettings,
     app_settings: &Mutex<AppSettings>,
     settings_path: &PathBuf,
-) -> Result<AppSettings, String> {
+    settings_revision: &SettingsRevision,
+) -> Result<(AppSettings, AppServerEvent), String> {
+    for (model, params) in &settings.model_sampling_params {
+        validate_sampling_params(params)
+            .map_err(|err| format!("Invalid sampling parameters for model `{model}`: {err}"))?;
+    }
+    if let Some(template) = settings.run_kickoff_template.as_deref() {
+        validate_kickoff_template(template)?;
+    }
+    parse_micode_args(settings.agent_args.as_deref()).map_err(|err| err.to_string())?;
     let _ = micode_config::write_collab_enabled(settings.experimental_collab_enabled);
     let _ = micode_config::write_collaboration_modes_enabled(settings.collaboration_modes_enabled);
     let _ = micode_config::write_steer_enabled(settings.steer_enabled);
@@ -55,8 +102,15 @@ pub(crate) async fn update_app_settings_core(
     let _ = micode_config::write_personality(settings.personality.as_str());
     write_settings(settings_path, &settings)?;
     let mut current = app_settings.lock().await;
+    let (revision, event) = settings_revision.record_update(
+        SettingsScope::App,
+        &serde_json::to_value(&*current).unwrap_or_default(),
+        &serde_json::to_value(&settings).unwrap_or_default(),
+    );
     *current = settings.clone();
-    Ok(settings)
+    let mut updated = settings;
+    updated.settings_revision = revision;
+    Ok((updated, event))
 }
 
 pub(crate) fn get_micode_config_path_core() -> Result<String, String> {
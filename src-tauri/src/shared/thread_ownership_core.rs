@@ -0,0 +1,178 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+pub(crate) const THREAD_WATCHING_ERROR: &str =
+    "Thread is watching another client's session. Take over the thread to send messages.";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ThreadOwnership {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) owner_client_id: String,
+    pub(crate) since: u64,
+}
+
+/// Host-side record of which connected client currently drives each thread. All
+/// mutations go through one lock on the host, so concurrent claims resolve in arrival
+/// order: the first sender owns the thread until someone explicitly takes it over.
+#[derive(Debug, Default)]
+pub(crate) struct ThreadOwnershipRegistry {
+    owners: HashMap<(String, String), ThreadOwnership>,
+}
+
+impl ThreadOwnershipRegistry {
+    pub(crate) fn get(&self, workspace_id: &str, thread_id: &str) -> Option<ThreadOwnership> {
+        self.owners
+            .get(&(workspace_id.to_string(), thread_id.to_string()))
+            .cloned()
+    }
+
+    /// Checks that `client_id` may send to the thread. An unowned thread is claimed by
+    /// the caller; the new ownership is returned so the host can announce it.
+    pub(crate) fn claim_for_send(
+        &mut self,
+        workspace_id: &str,
+        thread_id: &str,
+        client_id: &str,
+        now: u64,
+    ) -> Result<Option<ThreadOwnership>, String> {
+        let key = (workspace_id.to_string(), thread_id.to_string());
+        if let Some(existing) = self.owners.get(&key) {
+            if existing.owner_client_id == client_id {
+                return Ok(None);
+            }
+            return Err(THREAD_WATCHING_ERROR.to_string());
+        }
+        let ownership = ThreadOwnership {
+            workspace_id: workspace_id.to_string(),
+            thread_id: thread_id.to_string(),
+            owner_client_id: client_id.to_string(),
+            since: now,
+        };
+        self.owners.insert(key, ownership.clone());
+        Ok(Some(ownership))
+    }
+
+    /// Transfers write ownership to `client_id`, returning the new record and the
+    /// previous owner, if any.
+    pub(crate) fn take_over(
+        &mut self,
+        workspace_id: &str,
+        thread_id: &str,
+        client_id: &str,
+        now: u64,
+    ) -> (ThreadOwnership, Option<String>) {
+        let ownership = ThreadOwnership {
+            workspace_id: workspace_id.to_string(),
+            thread_id: thread_id.to_string(),
+            owner_client_id: client_id.to_string(),
+            since: now,
+        };
+        let previous = self
+            .owners
+            .insert(
+                (workspace_id.to_string(), thread_id.to_string()),
+                ownership.clone(),
+            )
+            .map(|previous| previous.owner_client_id);
+        (ownership, previous)
+    }
+
+    /// Drops every thread owned by a client that disconnected.
+    pub(crate) fn release_client(&mut self, client_id: &str) -> Vec<ThreadOwnership> {
+        let released: Vec<(String, String)> = self
+            .owners
+            .iter()
+            .filter(|(_, ownership)| ownership.owner_client_id == client_id)
+            .map(|(key, _)| key.clone())
+            .collect();
+        released
+            .into_iter()
+            .filter_map(|key| self.owners.remove(&key))
+            .collect()
+    }
+}
+
+pub(crate) fn ownership_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Params for `thread/ownershipChanged`. `ownerClientId` is null once the owner has
+/// disconnected and the thread is free to claim again.
+pub(crate) fn ownership_changed_params(
+    workspace_id: &str,
+    thread_id: &str,
+    ownership: Option<&ThreadOwnership>,
+    previous_owner_client_id: Option<&str>,
+) -> Value {
+    json!({
+        "workspaceId": workspace_id,
+        "threadId": thread_id,
+        "ownerClientId": ownership.map(|ownership| ownership.owner_client_id.clone()),
+        "previousOwnerClientId": previous_owner_client_id,
+        "since": ownership.map(|ownership| ownership.since),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ownership_changed_params, ThreadOwnershipRegistry, THREAD_WATCHING_ERROR};
+
+    #[test]
+    fn first_sender_claims_and_others_are_rejected() {
+        let mut registry = ThreadOwnershipRegistry::default();
+        let claimed = registry
+            .claim_for_send("ws", "t1", "laptop", 10)
+            .expect("claim")
+            .expect("new ownership");
+        assert_eq!(claimed.owner_client_id, "laptop");
+        assert_eq!(claimed.since, 10);
+        assert_eq!(registry.claim_for_send("ws", "t1", "laptop", 20), Ok(None));
+        assert_eq!(
+            registry.claim_for_send("ws", "t1", "desktop", 30),
+            Err(THREAD_WATCHING_ERROR.to_string())
+        );
+        assert!(registry
+            .claim_for_send("ws", "t2", "desktop", 30)
+            .expect("other thread")
+            .is_some());
+    }
+
+    #[test]
+    fn take_over_transfers_ownership() {
+        let mut registry = ThreadOwnershipRegistry::default();
+        registry
+            .claim_for_send("ws", "t1", "laptop", 10)
+            .expect("claim");
+        let (ownership, previous) = registry.take_over("ws", "t1", "desktop", 50);
+        assert_eq!(previous.as_deref(), Some("laptop"));
+        assert_eq!(ownership.since, 50);
+        assert!(registry.claim_for_send("ws", "t1", "laptop", 60).is_err());
+        assert_eq!(registry.claim_for_send("ws", "t1", "desktop", 60), Ok(None));
+
+        let params = ownership_changed_params("ws", "t1", Some(&ownership), previous.as_deref());
+        assert_eq!(params["ownerClientId"], "desktop");
+        assert_eq!(params["previousOwnerClientId"], "laptop");
+    }
+
+    #[test]
+    fn releasing_a_client_frees_its_threads() {
+        let mut registry = ThreadOwnershipRegistry::default();
+        registry.take_over("ws", "t1", "laptop", 10);
+        registry.take_over("ws", "t2", "desktop", 10);
+        let released = registry.release_client("laptop");
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].thread_id, "t1");
+        assert!(registry.get("ws", "t1").is_none());
+        assert!(registry.get("ws", "t2").is_some());
+    }
+}
@@ -0,0 +1,500 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::backend::app_server::now_ms;
+use crate::backend::event_methods::EVENT_METHODS;
+use crate::storage::write_file_atomically;
+use crate::types::{AppSettings, WorkspaceEntry};
+
+pub(crate) const USAGE_COUNTERS_FILE: &str = "usage-counters.json";
+/// Daily buckets kept in the counters file; older days are dropped on flush.
+const RETENTION_DAYS: usize = 90;
+/// Counts are kept in memory and merged into the file at most this often.
+const FLUSH_INTERVAL_MS: u64 = 60_000;
+const MAX_COMMAND_NAME_LEN: usize = 64;
+
+/// Counter values by UTC day (`YYYY-MM-DD`) and counter name. Names are
+/// `command:<name>`, `event:<method>` or `feature:<flag>`; they never carry message
+/// contents, paths or identifiers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UsageCounters {
+    #[serde(default)]
+    pub(crate) days: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl UsageCounters {
+    fn is_empty(&self) -> bool {
+        self.days.is_empty()
+    }
+
+    fn add(&mut self, day: &str, name: &str, amount: u64) {
+        *self
+            .days
+            .entry(day.to_string())
+            .or_default()
+            .entry(name.to_string())
+            .or_default() += amount;
+    }
+
+    fn merge(&mut self, other: UsageCounters) {
+        for (day, counters) in other.days {
+            for (name, amount) in counters {
+                self.add(&day, &name, amount);
+            }
+        }
+    }
+
+    fn prune(&mut self) {
+        while self.days.len() > RETENTION_DAYS {
+            self.days.pop_first();
+        }
+    }
+
+    fn totals(&self) -> BTreeMap<String, u64> {
+        let mut totals = BTreeMap::new();
+        for counters in self.days.values() {
+            for (name, amount) in counters {
+                *totals.entry(name.clone()).or_default() += amount;
+            }
+        }
+        totals
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FeatureUsage {
+    pub(crate) enabled: bool,
+    pub(crate) counters: UsageCounters,
+    /// Sum of each counter over the kept days.
+    pub(crate) totals: BTreeMap<String, u64>,
+}
+
+struct UsageRecorder {
+    enabled: AtomicBool,
+    /// Per-workspace opt-in or opt-out; workspaces not listed follow `enabled`.
+    workspaces: RwLock<HashMap<String, bool>>,
+    path: RwLock<Option<PathBuf>>,
+    pending: Mutex<UsageCounters>,
+    /// Held for a whole flush, so the writer thread and `snapshot` never interleave their
+    /// read-merge-write of the file.
+    flushing: Mutex<()>,
+    last_flush_ms: AtomicU64,
+    /// Background thread that runs due flushes, so recording never touches the disk.
+    /// Unset recorders flush inline.
+    writer: OnceLock<Sender<()>>,
+}
+
+impl UsageRecorder {
+    fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            workspaces: RwLock::new(HashMap::new()),
+            path: RwLock::new(None),
+            pending: Mutex::new(UsageCounters::default()),
+            flushing: Mutex::new(()),
+            last_flush_ms: AtomicU64::new(0),
+            writer: OnceLock::new(),
+        }
+    }
+
+    fn is_enabled_for(&self, workspace_id: Option<&str>) -> bool {
+        let workspace_choice = workspace_id.and_then(|id| {
+            self.workspaces
+                .read()
+                .ok()
+                .and_then(|workspaces| workspaces.get(id).copied())
+        });
+        workspace_choice.unwrap_or_else(|| self.enabled.load(Ordering::Relaxed))
+    }
+
+    fn any_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+            || self
+                .workspaces
+                .read()
+                .map(|workspaces| workspaces.values().any(|enabled| *enabled))
+                .unwrap_or(false)
+    }
+
+    fn configure(
+        &self,
+        path: Option<PathBuf>,
+        settings: &AppSettings,
+        workspaces: &HashMap<String, WorkspaceEntry>,
+        now: u64,
+    ) {
+        self.flush();
+        if let Ok(mut current) = self.path.write() {
+            *current = path;
+        }
+        self.enabled
+            .store(settings.usage_counters_enabled, Ordering::Relaxed);
+        if let Ok(mut overrides) = self.workspaces.write() {
+            *overrides = workspaces
+                .values()
+                .filter_map(|entry| Some((entry.id.clone(), entry.settings.usage_counters?)))
+                .collect();
+        }
+        if self.any_enabled() {
+            for flag in enabled_feature_flags(settings) {
+                self.record_once_today(&format!("feature:{flag}"), now);
+            }
+        }
+    }
+
+    fn set_workspace(&self, workspace_id: &str, enabled: Option<bool>) {
+        if let Ok(mut overrides) = self.workspaces.write() {
+            match enabled {
+                Some(enabled) => overrides.insert(workspace_id.to_string(), enabled),
+                None => overrides.remove(workspace_id),
+            };
+        }
+    }
+
+    fn increment(&self, name: &str, now: u64) {
+        let Some(day) = day_key(now) else {
+            return;
+        };
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.add(&day, name, 1);
+        }
+        let last_flush = self.last_flush_ms.load(Ordering::Relaxed);
+        if now.saturating_sub(last_flush) >= FLUSH_INTERVAL_MS {
+            self.last_flush_ms.store(now, Ordering::Relaxed);
+            match self.writer.get() {
+                Some(writer) => {
+                    let _ = writer.send(());
+                }
+                None => self.flush(),
+            }
+        }
+    }
+
+    /// Marks `name` as used today without counting repeats, for settings-derived flags.
+    fn record_once_today(&self, name: &str, now: u64) {
+        let Some(day) = day_key(now) else {
+            return;
+        };
+        let seen = self.pending.lock().is_ok_and(|pending| {
+            pending
+                .days
+                .get(&day)
+                .is_some_and(|counters| counters.contains_key(name))
+        }) || self.path().is_some_and(|path| {
+            read_counters(&path)
+                .days
+                .get(&day)
+                .is_some_and(|counters| counters.contains_key(name))
+        });
+        if !seen {
+            self.increment(name, now);
+        }
+    }
+
+    fn record_command(&self, command: &str, workspace_id: Option<&str>, now: u64) {
+        if self.is_enabled_for(workspace_id) && is_command_name(command) {
+            self.increment(&format!("command:{command}"), now);
+        }
+    }
+
+    fn record_event(&self, workspace_id: &str, message: &Value, now: u64) {
+        if !self.is_enabled_for(Some(workspace_id)) {
+            return;
+        }
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return;
+        };
+        if EVENT_METHODS.iter().any(|event| event.method == method) {
+            self.increment(&format!("event:{method}"), now);
+        }
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        self.path.read().ok().and_then(|path| path.clone())
+    }
+
+    /// Merges the in-memory counts into the counters file. Nothing is written while no
+    /// count was recorded.
+    fn flush(&self) {
+        let _flushing = self
+            .flushing
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.last_flush_ms.store(now_ms(), Ordering::Relaxed);
+        let pending = match self.pending.lock() {
+            Ok(mut pending) => std::mem::take(&mut *pending),
+            Err(_) => return,
+        };
+        if pending.is_empty() {
+            return;
+        }
+        let Some(path) = self.path() else {
+            return;
+        };
+        let mut counters = read_counters(&path);
+        counters.merge(pending);
+        counters.prune();
+        let _ = write_counters(&path, &counters);
+    }
+
+    fn snapshot(&self) -> FeatureUsage {
+        self.flush();
+        let counters = self
+            .path()
+            .map(|path| read_counters(&path))
+            .unwrap_or_default();
+        FeatureUsage {
+            enabled: self.any_enabled(),
+            totals: counters.totals(),
+            counters,
+        }
+    }
+}
+
+/// The feature-usage counters of the app or the daemon; each state owns one. Clones share
+/// the recorder and its background thread that runs due flushes.
+#[derive(Clone)]
+pub(crate) struct FeatureUsageCounters {
+    recorder: Arc<UsageRecorder>,
+}
+
+impl FeatureUsageCounters {
+    /// Counts in memory until `configure` points the counters at a data dir.
+    pub(crate) fn new() -> Self {
+        let recorder = Arc::new(UsageRecorder::new());
+        let (tx, rx) = mpsc::channel::<()>();
+        // Holds a weak reference: the recorder owns the sender, so the thread ends with it.
+        let flushed = Arc::downgrade(&recorder);
+        std::thread::spawn(move || {
+            while rx.recv().is_ok() {
+                let Some(recorder) = flushed.upgrade() else {
+                    break;
+                };
+                recorder.flush();
+            }
+        });
+        let _ = recorder.writer.set(tx);
+        Self { recorder }
+    }
+
+    /// Applies the opt-in settings and points the counters at `data_dir`; `None` keeps
+    /// counts in memory only.
+    pub(crate) fn configure(
+        &self,
+        data_dir: Option<&Path>,
+        settings: &AppSettings,
+        workspaces: &HashMap<String, WorkspaceEntry>,
+    ) {
+        self.recorder.configure(
+            data_dir.map(|dir| dir.join(USAGE_COUNTERS_FILE)),
+            settings,
+            workspaces,
+            now_ms(),
+        );
+    }
+
+    pub(crate) fn set_workspace(&self, workspace_id: &str, enabled: Option<bool>) {
+        self.recorder.set_workspace(workspace_id, enabled);
+    }
+
+    pub(crate) fn record_command(&self, command: &str, workspace_id: Option<&str>) {
+        self.recorder
+            .record_command(command, workspace_id, now_ms());
+    }
+
+    pub(crate) fn record_event(&self, workspace_id: &str, message: &Value) {
+        self.recorder.record_event(workspace_id, message, now_ms());
+    }
+
+    pub(crate) fn feature_usage(&self) -> FeatureUsage {
+        self.recorder.snapshot()
+    }
+}
+
+fn day_key(now: u64) -> Option<String> {
+    chrono::DateTime::from_timestamp_millis(now as i64)
+        .map(|time| time.format("%Y-%m-%d").to_string())
+}
+
+/// Command names come from code, but the daemon also sees names sent by clients; only
+/// plain snake_case names are counted so nothing else can end up in the file.
+fn is_command_name(command: &str) -> bool {
+    !command.is_empty()
+        && command.len() <= MAX_COMMAND_NAME_LEN
+        && command
+            .chars()
+            .all(|ch| ch.is_ascii_lowercase() || ch == '_')
+}
+
+fn enabled_feature_flags(settings: &AppSettings) -> Vec<&'static str> {
+    [
+        ("experimentalCollab", settings.experimental_collab_enabled),
+        ("collaborationModes", settings.collaboration_modes_enabled),
+        ("steer", settings.steer_enabled),
+        ("unifiedExec", settings.unified_exec_enabled),
+        ("experimentalApps", settings.experimental_apps_enabled),
+        (
+            "autoRestartUnresponsiveAgent",
+            settings.auto_restart_unresponsive_agent,
+        ),
+        ("autoStoreMaintenance", settings.auto_store_maintenance),
+        ("resourceMonitoring", settings.resource_monitoring_enabled),
+    ]
+    .into_iter()
+    .filter_map(|(flag, enabled)| enabled.then_some(flag))
+    .collect()
+}
+
+fn read_counters(path: &Path) -> UsageCounters {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn write_counters(path: &Path, counters: &UsageCounters) -> Result<(), String> {
+    let raw = serde_json::to_string_pretty(counters).map_err(|err| err.to_string())?;
+    write_file_atomically(path, &raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    /// 2026-03-01T12:00:00Z.
+    const NOON: u64 = 1_772_366_400_000;
+    const DAY_MS: u64 = 86_400_000;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("micode-usage-{}", Uuid::new_v4()))
+    }
+
+    fn settings(enabled: bool) -> AppSettings {
+        AppSettings {
+            usage_counters_enabled: enabled,
+            steer_enabled: true,
+            ..AppSettings::default()
+        }
+    }
+
+    fn exercise(recorder: &UsageRecorder, workspace_id: &str) {
+        recorder.record_command("send_user_message", Some(workspace_id), NOON);
+        recorder.record_command("list_workspaces", None, NOON);
+        recorder.record_event(
+            workspace_id,
+            &json!({ "method": "turn/completed", "params": { "text": "secret" } }),
+            NOON,
+        );
+        recorder.flush();
+    }
+
+    #[test]
+    fn opted_out_records_nothing() {
+        let dir = temp_dir();
+        let path = dir.join(USAGE_COUNTERS_FILE);
+        let recorder = UsageRecorder::new();
+        recorder.configure(Some(path.clone()), &settings(false), &HashMap::new(), NOON);
+        exercise(&recorder, "ws-1");
+
+        assert!(!path.exists());
+        assert!(!dir.exists());
+        assert!(recorder.pending.lock().unwrap().is_empty());
+        let usage = recorder.snapshot();
+        assert!(!usage.enabled);
+        assert!(usage.counters.is_empty());
+        assert!(usage.totals.is_empty());
+
+        // A workspace opting out stays silent while the rest of the app counts.
+        recorder.configure(Some(path.clone()), &settings(true), &HashMap::new(), NOON);
+        recorder.set_workspace("ws-2", Some(false));
+        recorder.flush();
+        std::fs::remove_file(&path).expect("remove feature counters");
+        recorder.record_command("send_user_message", Some("ws-2"), NOON);
+        recorder.record_event("ws-2", &json!({ "method": "turn/completed" }), NOON);
+        recorder.flush();
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn counts_only_known_names_in_daily_buckets() {
+        let dir = temp_dir();
+        let path = dir.join(USAGE_COUNTERS_FILE);
+        let recorder = UsageRecorder::new();
+        recorder.configure(Some(path.clone()), &settings(true), &HashMap::new(), NOON);
+        exercise(&recorder, "ws-1");
+        recorder.record_command("send_user_message", Some("ws-1"), NOON + DAY_MS);
+        recorder.record_command("thread-3f2a9c0e", None, NOON);
+        recorder.record_command("/home/alice/project", None, NOON);
+        recorder.record_event("ws-1", &json!({ "method": "Fix the login loop" }), NOON);
+        recorder.flush();
+
+        let usage = recorder.snapshot();
+        let today = &usage.counters.days["2026-03-01"];
+        assert_eq!(today["command:send_user_message"], 1);
+        assert_eq!(today["command:list_workspaces"], 1);
+        assert_eq!(today["event:turn/completed"], 1);
+        assert_eq!(today["feature:steer"], 1);
+        assert_eq!(
+            today.len(),
+            3 + enabled_feature_flags(&settings(true)).len()
+        );
+        assert_eq!(usage.counters.days["2026-03-02"].len(), 1);
+        assert_eq!(usage.totals["command:send_user_message"], 2);
+
+        let raw = std::fs::read_to_string(&path).expect("counters file");
+        assert!(!raw.contains("ws-1"));
+        assert!(!raw.contains("secret"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn concurrent_flushes_keep_every_count() {
+        let dir = temp_dir();
+        let recorder = Arc::new(UsageRecorder::new());
+        recorder.configure(
+            Some(dir.join(USAGE_COUNTERS_FILE)),
+            &settings(true),
+            &HashMap::new(),
+            NOON,
+        );
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let recorder = Arc::clone(&recorder);
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        recorder.record_command("list_workspaces", None, NOON);
+                        recorder.flush();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().expect("flush thread");
+        }
+        assert_eq!(recorder.snapshot().totals["command:list_workspaces"], 100);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keeps_a_bounded_number_of_days() {
+        let mut counters = UsageCounters::default();
+        for offset in 0..(RETENTION_DAYS as u64 + 5) {
+            let day = day_key(NOON + offset * DAY_MS).expect("day");
+            counters.add(&day, "command:list_workspaces", 1);
+        }
+        counters.prune();
+        assert_eq!(counters.days.len(), RETENTION_DAYS);
+        assert!(!counters.days.contains_key("2026-03-01"));
+    }
+}
@@ -0,0 +1,421 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::backend::app_server::now_ms;
+use crate::backend::event_methods::THREAD_TOKEN_USAGE_UPDATED;
+use crate::types::ModelPrice;
+
+pub(crate) const USAGE_LEDGER_FILE: &str = "usage.jsonl";
+const LEDGER_CSV_COLUMNS: [&str; 8] = [
+    "timestamp",
+    "workspace_id",
+    "thread_id",
+    "turn_id",
+    "model",
+    "input_tokens",
+    "cached_input_tokens",
+    "output_tokens",
+];
+
+/// The counts of one finished turn, from the `thread/tokenUsage/updated` looked up after
+/// it ended. A turn can still be reported again, so readers keep its latest record.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UsageLedgerRecord {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) turn_id: String,
+    pub(crate) input_tokens: i64,
+    #[serde(default)]
+    pub(crate) cached_input_tokens: i64,
+    pub(crate) output_tokens: i64,
+    #[serde(default)]
+    pub(crate) model: Option<String>,
+    /// Unix milliseconds.
+    pub(crate) timestamp: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UsageSummaryTotals {
+    pub(crate) turns: u64,
+    pub(crate) input_tokens: i64,
+    pub(crate) cached_input_tokens: i64,
+    pub(crate) output_tokens: i64,
+    /// Sum over the turns whose model has a price in `modelPrices`.
+    pub(crate) estimated_cost_usd: f64,
+    /// Turns left out of the cost because their model has no price.
+    pub(crate) unpriced_turns: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UsageSummaryDay {
+    /// UTC day, `YYYY-MM-DD`.
+    pub(crate) day: String,
+    #[serde(flatten)]
+    pub(crate) totals: UsageSummaryTotals,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UsageSummary {
+    /// Days with at least one turn, oldest first.
+    pub(crate) days: Vec<UsageSummaryDay>,
+    pub(crate) totals: UsageSummaryTotals,
+}
+
+impl UsageSummaryTotals {
+    fn add(&mut self, record: &UsageLedgerRecord, prices: &[ModelPrice]) {
+        self.turns += 1;
+        self.input_tokens += record.input_tokens;
+        self.cached_input_tokens += record.cached_input_tokens;
+        self.output_tokens += record.output_tokens;
+        match record
+            .model
+            .as_deref()
+            .and_then(|model| ModelPrice::find(prices, model))
+        {
+            Some(price) => {
+                self.estimated_cost_usd += price.estimate_cost(
+                    record.input_tokens,
+                    record.cached_input_tokens,
+                    record.output_tokens,
+                )
+            }
+            None => self.unpriced_turns += 1,
+        }
+    }
+}
+
+/// The usage ledger of one data dir. App and daemon state each own one; clones share the
+/// background thread that appends records, so emitting an event never touches the disk.
+#[derive(Clone)]
+pub(crate) struct UsageLedger {
+    path: Option<PathBuf>,
+    writer: Option<Sender<UsageLedgerRecord>>,
+}
+
+impl UsageLedger {
+    /// Records to `USAGE_LEDGER_FILE` in `data_dir`; without one nothing is recorded.
+    pub(crate) fn new(data_dir: Option<&Path>) -> Self {
+        let path = data_dir.map(|dir| dir.join(USAGE_LEDGER_FILE));
+        let writer = path.clone().map(|path| {
+            let (tx, rx) = mpsc::channel::<UsageLedgerRecord>();
+            std::thread::spawn(move || {
+                while let Ok(record) = rx.recv() {
+                    let _ = append_record(&path, &record);
+                }
+            });
+            tx
+        });
+        Self { path, writer }
+    }
+
+    pub(crate) fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Queues the turn-end `thread/tokenUsage/updated` of each turn; other events are
+    /// ignored.
+    pub(crate) fn record_token_usage_event(&self, workspace_id: &str, message: &Value) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        if let Some(record) = ledger_record(workspace_id, message, now_ms()) {
+            let _ = writer.send(record);
+        }
+    }
+}
+
+/// The record of a turn-end usage update. Only the lookup made once a turn ended carries
+/// `lookupMs`; the live updates sent while it runs are not recorded.
+fn ledger_record(workspace_id: &str, message: &Value, now: u64) -> Option<UsageLedgerRecord> {
+    if message.get("method").and_then(Value::as_str) != Some(THREAD_TOKEN_USAGE_UPDATED) {
+        return None;
+    }
+    let params = message.get("params")?;
+    params.get("lookupMs")?;
+    let text = |key: &str| params.get(key).and_then(Value::as_str).map(str::to_string);
+    let usage = params.get("tokenUsage")?;
+    let last = usage.get("last")?;
+    let count = |key: &str| last.get(key).and_then(Value::as_i64).unwrap_or(0).max(0);
+    Some(UsageLedgerRecord {
+        workspace_id: workspace_id.to_string(),
+        thread_id: text("threadId")?,
+        turn_id: text("turnId")?,
+        input_tokens: count("inputTokens"),
+        cached_input_tokens: count("cachedInputTokens"),
+        output_tokens: count("outputTokens"),
+        model: usage
+            .get("model")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        timestamp: now,
+    })
+}
+
+fn append_record(path: &Path, record: &UsageLedgerRecord) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let mut line = serde_json::to_string(record).map_err(|err| err.to_string())?;
+    line.push('\n');
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|err| err.to_string())
+}
+
+/// Ledger records in file order; lines that do not parse are skipped.
+fn read_ledger(path: &Path) -> Vec<UsageLedgerRecord> {
+    std::fs::read_to_string(path)
+        .map(|raw| {
+            raw.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn day_key(timestamp_ms: u64) -> Option<String> {
+    DateTime::from_timestamp_millis(timestamp_ms as i64)
+        .map(|time| time.format("%Y-%m-%d").to_string())
+}
+
+/// Daily aggregates of `records`, counting each turn once with its latest counts on the
+/// day they were reported.
+fn summarize_usage(
+    records: &[UsageLedgerRecord],
+    workspace_id: Option<&str>,
+    since: Option<NaiveDate>,
+    prices: &[ModelPrice],
+) -> UsageSummary {
+    let mut turns: HashMap<(&str, &str, &str), &UsageLedgerRecord> = HashMap::new();
+    for record in records {
+        if workspace_id.is_some_and(|id| id != record.workspace_id) {
+            continue;
+        }
+        let key = (
+            record.workspace_id.as_str(),
+            record.thread_id.as_str(),
+            record.turn_id.as_str(),
+        );
+        let latest = turns.entry(key).or_insert(record);
+        if record.timestamp >= latest.timestamp {
+            *latest = record;
+        }
+    }
+    let since = since.map(|day| day.format("%Y-%m-%d").to_string());
+    let mut days: BTreeMap<String, UsageSummaryTotals> = BTreeMap::new();
+    let mut totals = UsageSummaryTotals::default();
+    for record in turns.into_values() {
+        let Some(day) = day_key(record.timestamp) else {
+            continue;
+        };
+        if since.as_ref().is_some_and(|since| day < *since) {
+            continue;
+        }
+        days.entry(day).or_default().add(record, prices);
+        totals.add(record, prices);
+    }
+    UsageSummary {
+        days: days
+            .into_iter()
+            .map(|(day, totals)| UsageSummaryDay { day, totals })
+            .collect(),
+        totals,
+    }
+}
+
+/// Summarizes the ledger at `ledger_path` for one workspace, or all of them, from the UTC
+/// day `since` (`YYYY-MM-DD`) on.
+pub(crate) fn usage_summary_core(
+    ledger_path: Option<&Path>,
+    workspace_id: Option<&str>,
+    since: Option<&str>,
+    prices: &[ModelPrice],
+) -> Result<UsageSummary, String> {
+    let since = since
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map_err(|_| format!("Invalid day \"{value}\", expected YYYY-MM-DD."))
+        })
+        .transpose()?;
+    let records = ledger_path.map(read_ledger).unwrap_or_default();
+    Ok(summarize_usage(&records, workspace_id, since, prices))
+}
+
+/// Quotes a text cell when needed and keeps spreadsheets from reading values that start
+/// with a formula character as formulas.
+pub(crate) fn csv_text(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{value}")
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn build_ledger_csv(records: &[UsageLedgerRecord]) -> String {
+    let mut csv = format!("\u{feff}{}\r\n", LEDGER_CSV_COLUMNS.join(","));
+    for record in records {
+        let timestamp = DateTime::from_timestamp_millis(record.timestamp as i64)
+            .map(|time| time.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+            .unwrap_or_default();
+        csv.push_str(&format!(
+            "{timestamp},{},{},{},{},{},{},{}\r\n",
+            csv_text(&record.workspace_id),
+            csv_text(&record.thread_id),
+            csv_text(&record.turn_id),
+            csv_text(record.model.as_deref().unwrap_or_default()),
+            record.input_tokens,
+            record.cached_input_tokens,
+            record.output_tokens,
+        ));
+    }
+    csv
+}
+
+/// The whole ledger at `ledger_path` as CSV, one row per recorded emission, with its row
+/// count.
+pub(crate) fn usage_ledger_csv_core(ledger_path: Option<&Path>) -> (String, u64) {
+    let records = ledger_path.map(read_ledger).unwrap_or_default();
+    (build_ledger_csv(&records), records.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    /// 2026-03-01T23:50:00Z.
+    const LATE: u64 = 1_772_409_000_000;
+    const MINUTE_MS: u64 = 60_000;
+
+    fn record(thread_id: &str, turn_id: &str, input: i64, timestamp: u64) -> UsageLedgerRecord {
+        UsageLedgerRecord {
+            workspace_id: "ws-1".to_string(),
+            thread_id: thread_id.to_string(),
+            turn_id: turn_id.to_string(),
+            input_tokens: input,
+            cached_input_tokens: 0,
+            output_tokens: 10,
+            model: Some("micode-pro".to_string()),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn records_token_usage_events_as_jsonl_lines() {
+        let path = std::env::temp_dir()
+            .join(format!("micode-ledger-{}", Uuid::new_v4()))
+            .join(USAGE_LEDGER_FILE);
+        let message = json!({
+            "method": THREAD_TOKEN_USAGE_UPDATED,
+            "params": {
+                "threadId": "thread-1",
+                "turnId": "turn-1",
+                "tokenUsage": {
+                    "last": { "inputTokens": 120, "cachedInputTokens": 20, "outputTokens": 30 },
+                    "total": { "inputTokens": 500, "outputTokens": 90 },
+                    "model": "micode-pro"
+                },
+                "lookupMs": 40
+            }
+        });
+        let record = ledger_record("ws-1", &message, LATE).expect("ledger record");
+        assert_eq!(record.input_tokens, 120);
+        assert_eq!(record.cached_input_tokens, 20);
+        assert_eq!(record.model.as_deref(), Some("micode-pro"));
+        assert!(ledger_record("ws-1", &json!({ "method": "turn/completed" }), LATE).is_none());
+        let mut live_update = message.clone();
+        live_update["params"]
+            .as_object_mut()
+            .expect("params")
+            .remove("lookupMs");
+        assert!(ledger_record("ws-1", &live_update, LATE).is_none());
+
+        append_record(&path, &record).expect("append");
+        append_record(&path, &record).expect("append");
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(b"not json\n"))
+            .expect("append garbage");
+        assert_eq!(read_ledger(&path), vec![record.clone(), record]);
+
+        let _ = std::fs::remove_dir_all(path.parent().expect("ledger dir"));
+    }
+
+    #[test]
+    fn summary_counts_each_turn_once_on_the_day_it_was_last_reported() {
+        let prices = vec![ModelPrice {
+            model: "micode-pro".to_string(),
+            input_per_million: 1.0,
+            cached_input_per_million: None,
+            output_per_million: 2.0,
+        }];
+        let mut unpriced = record("thread-2", "turn-1", 1_000, LATE + 30 * MINUTE_MS);
+        unpriced.model = None;
+        let mut other_workspace = record("thread-1", "turn-9", 5_000, LATE);
+        other_workspace.workspace_id = "ws-2".to_string();
+        let records = vec![
+            record("thread-1", "turn-1", 100, LATE),
+            // The same turn reported again after midnight moves to 2026-03-02.
+            record("thread-1", "turn-2", 200, LATE + MINUTE_MS),
+            record("thread-1", "turn-2", 300, LATE + 15 * MINUTE_MS),
+            unpriced,
+            other_workspace,
+        ];
+
+        let summary = summarize_usage(&records, Some("ws-1"), None, &prices);
+        let days: Vec<(&str, u64, i64)> = summary
+            .days
+            .iter()
+            .map(|day| (day.day.as_str(), day.totals.turns, day.totals.input_tokens))
+            .collect();
+        assert_eq!(days, vec![("2026-03-01", 1, 100), ("2026-03-02", 2, 1_300)]);
+        assert_eq!(summary.totals.turns, 3);
+        assert_eq!(summary.totals.input_tokens, 1_400);
+        assert_eq!(summary.totals.unpriced_turns, 1);
+        let expected_cost = (100.0 + 300.0 + 2.0 * 20.0) / 1_000_000.0;
+        assert!((summary.totals.estimated_cost_usd - expected_cost).abs() < 1e-12);
+
+        let since = NaiveDate::from_ymd_opt(2026, 3, 2);
+        let summary = summarize_usage(&records, None, since, &prices);
+        assert_eq!(summary.days.len(), 1);
+        assert_eq!(summary.totals.turns, 2);
+    }
+
+    #[test]
+    fn ledger_csv_escapes_cells() {
+        let mut tricky = record("thread, \"quoted\"", "=SUM(A1)", 7, LATE);
+        tricky.model = Some("line\nbreak".to_string());
+        let csv = build_ledger_csv(&[tricky]);
+        let mut lines = csv.split("\r\n");
+        assert_eq!(
+            lines.next(),
+            Some("\u{feff}timestamp,workspace_id,thread_id,turn_id,model,input_tokens,cached_input_tokens,output_tokens")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("2026-03-01T23:50:00Z,ws-1,\"thread, \"\"quoted\"\"\",'=SUM(A1),\"line\nbreak\",7,0,10")
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::backend::workspace_paths::paths_equivalent;
+use crate::git_utils::resolve_git_root;
+use crate::types::WorkspaceEntry;
+use crate::utils::normalize_git_path;
+
+/// Name of the primary root. Paths in the primary root are never prefixed.
+pub(crate) const PRIMARY_ROOT_NAME: &str = ".";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorkspaceRoot {
+    /// Prefix that file paths of this root carry in workspace-wide listings.
+    pub(crate) name: String,
+    pub(crate) path: PathBuf,
+    pub(crate) primary: bool,
+}
+
+impl WorkspaceRoot {
+    /// Directory git commands run in: the configured git root for the primary root,
+    /// the root itself otherwise.
+    #[allow(dead_code)]
+    pub(crate) fn git_root(&self, entry: &WorkspaceEntry) -> Result<PathBuf, String> {
+        if self.primary {
+            resolve_git_root(entry)
+        } else {
+            Ok(self.path.clone())
+        }
+    }
+
+    pub(crate) fn prefixed_path(&self, path: &str) -> String {
+        if self.primary {
+            path.to_string()
+        } else {
+            format!("{}/{}", self.name, path)
+        }
+    }
+}
+
+fn extra_root_name(primary: &Path, path: &Path) -> String {
+    match path.strip_prefix(primary) {
+        Ok(relative) => normalize_git_path(&relative.to_string_lossy()),
+        Err(_) => path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| normalize_git_path(&path.to_string_lossy())),
+    }
+}
+
+/// The workspace path followed by the extra roots from `settings.roots`. Relative roots
+/// are resolved against the workspace path; missing directories are skipped.
+pub(crate) fn workspace_roots(entry: &WorkspaceEntry) -> Vec<WorkspaceRoot> {
+    let primary_path = PathBuf::from(&entry.path);
+    let mut roots = vec![WorkspaceRoot {
+        name: PRIMARY_ROOT_NAME.to_string(),
+        path: primary_path.clone(),
+        primary: true,
+    }];
+    for configured in entry.settings.roots.iter().flatten() {
+        let configured = configured.trim();
+        if configured.is_empty() {
+            continue;
+        }
+        let path = if Path::new(configured).is_absolute() {
+            PathBuf::from(configured)
+        } else {
+            primary_path.join(configured)
+        };
+        if !path.is_dir() || roots.iter().any(|root| paths_equivalent(&root.path, &path)) {
+            continue;
+        }
+        let name = extra_root_name(&primary_path, &path);
+        if name.is_empty() || roots.iter().any(|root| root.name == name) {
+            continue;
+        }
+        roots.push(WorkspaceRoot {
+            name,
+            path,
+            primary: false,
+        });
+    }
+    roots
+}
+
+/// Picks the root a command applies to and returns `path` relative to it. An explicit
+/// `root` (by name or path) wins; otherwise the root is detected from the path prefix,
+/// falling back to the primary root.
+pub(crate) fn select_root(
+    entry: &WorkspaceEntry,
+    root: Option<&str>,
+    path: Option<&str>,
+) -> Result<(WorkspaceRoot, Option<String>), String> {
+    let roots = workspace_roots(entry);
+    let requested = root.map(str::trim).filter(|value| !value.is_empty());
+    let selected = match requested {
+        Some(requested) => roots
+            .iter()
+            .find(|root| {
+                root.name == requested || paths_equivalent(&root.path, Path::new(requested))
+            })
+            .cloned()
+            .ok_or_else(|| format!("Unknown workspace root: {requested}"))?,
+        None => path
+            .and_then(|path| {
+                roots
+                    .iter()
+                    .filter(|root| !root.primary && strip_root_prefix(root, path).is_some())
+                    .max_by_key(|root| root.name.len())
+                    .cloned()
+            })
+            .unwrap_or_else(|| roots[0].clone()),
+    };
+    let relative = path.map(|path| {
+        strip_root_prefix(&selected, path)
+            .unwrap_or(path)
+            .to_string()
+    });
+    Ok((selected, relative))
+}
+
+fn strip_root_prefix<'a>(root: &WorkspaceRoot, path: &'a str) -> Option<&'a str> {
+    if root.primary {
+        return None;
+    }
+    let rest = path.strip_prefix(root.name.as_str())?;
+    if rest.is_empty() {
+        return Some(".");
+    }
+    rest.strip_prefix('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{select_root, workspace_roots, PRIMARY_ROOT_NAME};
+    use crate::types::{WorkspaceEntry, WorkspaceKind, WorkspaceSettings};
+    use std::path::Path;
+    use uuid::Uuid;
+
+    fn entry(path: &Path, roots: Vec<String>) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: "w".to_string(),
+            name: "w".to_string(),
+            path: path.to_string_lossy().to_string(),
+            agent_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings {
+                roots: Some(roots),
+                ..WorkspaceSettings::default()
+            },
+            stack: None,
+        }
+    }
+
+    #[test]
+    fn resolves_roots_and_routes_paths_by_prefix() {
+        let base = std::env::temp_dir().join(format!("micode-roots-{}", Uuid::new_v4()));
+        let meta = base.join("meta");
+        let sibling = base.join("shared-lib");
+        std::fs::create_dir_all(meta.join("api")).expect("create api");
+        std::fs::create_dir_all(&sibling).expect("create sibling");
+
+        let entry = entry(
+            &meta,
+            vec![
+                "api".to_string(),
+                sibling.to_string_lossy().to_string(),
+                "missing".to_string(),
+            ],
+        );
+        let names: Vec<String> = workspace_roots(&entry)
+            .into_iter()
+            .map(|root| root.name)
+            .collect();
+        assert_eq!(names, vec![PRIMARY_ROOT_NAME, "api", "shared-lib"]);
+
+        let (root, path) = select_root(&entry, None, Some("api/src/main.rs")).expect("select");
+        assert_eq!(root.name, "api");
+        assert_eq!(path.as_deref(), Some("src/main.rs"));
+
+        let (root, path) = select_root(&entry, None, Some("apis/readme.md")).expect("select");
+        assert!(root.primary);
+        assert_eq!(path.as_deref(), Some("apis/readme.md"));
+
+        let (root, path) = select_root(&entry, Some("shared-lib"), Some("lib.rs")).expect("select");
+        assert_eq!(root.path, sibling);
+        assert_eq!(path.as_deref(), Some("lib.rs"));
+
+        assert!(select_root(&entry, Some("nope"), None).is_err());
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,663 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+use serde_json::Value;
+use tokio::sync::Mutex;
+use toml::Value as TomlValue;
+
+use crate::types::{WorkspaceEntry, WorkspaceStack};
+
+/// Manifests and configs are read up to this size; anything larger is truncated.
+const MAX_FILE_BYTES: u64 = 256 * 1024;
+/// Immediate subfolders searched for manifests, for monorepos and Tauri-style layouts.
+const MAX_SUBDIRS: usize = 32;
+const MAX_WORKFLOW_FILES: usize = 20;
+const SKIPPED_SUBDIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    "vendor",
+    "dist",
+    "build",
+    "out",
+    "venv",
+];
+
+const CARGO_FRAMEWORKS: &[(&str, &str)] = &[
+    ("tauri", "Tauri"),
+    ("axum", "Axum"),
+    ("actix-web", "Actix Web"),
+    ("rocket", "Rocket"),
+    ("leptos", "Leptos"),
+    ("bevy", "Bevy"),
+];
+
+const NODE_FRAMEWORKS: &[(&str, &str)] = &[
+    ("next", "Next.js"),
+    ("nuxt", "Nuxt"),
+    ("@sveltejs/kit", "SvelteKit"),
+    ("@remix-run/react", "Remix"),
+    ("astro", "Astro"),
+    ("react-native", "React Native"),
+    ("react", "React"),
+    ("vue", "Vue"),
+    ("svelte", "Svelte"),
+    ("@angular/core", "Angular"),
+    ("@nestjs/core", "NestJS"),
+    ("express", "Express"),
+    ("electron", "Electron"),
+    ("@tauri-apps/api", "Tauri"),
+    ("vite", "Vite"),
+    ("vitest", "Vitest"),
+    ("jest", "Jest"),
+];
+
+const PYTHON_FRAMEWORKS: &[(&str, &str)] = &[
+    ("django", "Django"),
+    ("fastapi", "FastAPI"),
+    ("flask", "Flask"),
+    ("pytest", "pytest"),
+];
+
+const GO_FRAMEWORKS: &[(&str, &str)] = &[
+    ("github.com/gin-gonic/gin", "Gin"),
+    ("github.com/labstack/echo", "Echo"),
+    ("github.com/gofiber/fiber", "Fiber"),
+    ("github.com/spf13/cobra", "Cobra"),
+];
+
+/// `(lockfile, package manager)` for JavaScript projects, most specific first.
+const NODE_LOCKFILES: &[(&str, &str)] = &[
+    ("pnpm-lock.yaml", "pnpm"),
+    ("yarn.lock", "yarn"),
+    ("bun.lockb", "bun"),
+    ("bun.lock", "bun"),
+    ("package-lock.json", "npm"),
+];
+
+const PYTHON_LOCKFILES: &[(&str, &str)] = &[
+    ("poetry.lock", "poetry"),
+    ("uv.lock", "uv"),
+    ("pdm.lock", "pdm"),
+    ("Pipfile.lock", "pipenv"),
+];
+
+const CI_CONFIGS: &[(&str, &str)] = &[
+    (".gitlab-ci.yml", "GitLab CI"),
+    (".circleci/config.yml", "CircleCI"),
+    ("azure-pipelines.yml", "Azure Pipelines"),
+    (".travis.yml", "Travis CI"),
+    ("Jenkinsfile", "Jenkins"),
+];
+
+/// npm writes this placeholder into `scripts.test` for new packages.
+const NPM_PLACEHOLDER_TEST: &str = "no test specified";
+
+fn read_bounded(path: &Path) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut raw = Vec::new();
+    file.take(MAX_FILE_BYTES).read_to_end(&mut raw).ok()?;
+    Some(String::from_utf8_lossy(&raw).into_owned())
+}
+
+fn push_unique(values: &mut Vec<String>, value: &str) {
+    if !values.iter().any(|existing| existing == value) {
+        values.push(value.to_string());
+    }
+}
+
+fn relative(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{prefix}/{name}")
+    }
+}
+
+#[derive(Default)]
+struct Detection {
+    stack: WorkspaceStack,
+    /// Test command guessed from a manifest, preferred over one found in CI.
+    manifest_test_command: Option<String>,
+    ci_test_command: Option<String>,
+}
+
+impl Detection {
+    fn add_frameworks<'a>(
+        &mut self,
+        dependencies: impl IntoIterator<Item = &'a str>,
+        known: &[(&str, &str)],
+    ) {
+        let dependencies: Vec<&str> = dependencies.into_iter().collect();
+        for (dependency, framework) in known {
+            if dependencies.contains(dependency) {
+                push_unique(&mut self.stack.frameworks, framework);
+            }
+        }
+    }
+
+    fn set_package_manager(&mut self, manager: &str) {
+        if self.stack.package_manager.is_none() {
+            self.stack.package_manager = Some(manager.to_string());
+        }
+    }
+
+    fn set_test_command(&mut self, command: String) {
+        if self.manifest_test_command.is_none() {
+            self.manifest_test_command = Some(command);
+        }
+    }
+
+    fn inspect_dir(&mut self, dir: &Path, prefix: &str) {
+        let manifest = |name: &str| {
+            let path = dir.join(name);
+            read_bounded(&path).map(|raw| (raw, relative(prefix, name)))
+        };
+        if let Some((raw, source)) = manifest("Cargo.toml") {
+            self.stack.sources.push(source);
+            self.inspect_cargo(&raw);
+        }
+        if let Some((raw, source)) = manifest("package.json") {
+            self.stack.sources.push(source);
+            self.inspect_package_json(dir, prefix, &raw);
+        }
+        if let Some((raw, source)) = manifest("pyproject.toml") {
+            self.stack.sources.push(source);
+            self.inspect_pyproject(dir, prefix, &raw);
+        } else if let Some((raw, source)) = manifest("requirements.txt") {
+            self.stack.sources.push(source);
+            self.inspect_requirements(&raw);
+        }
+        if let Some((raw, source)) = manifest("go.mod") {
+            self.stack.sources.push(source);
+            self.inspect_go_mod(&raw);
+        }
+    }
+
+    fn inspect_cargo(&mut self, raw: &str) {
+        push_unique(&mut self.stack.languages, "Rust");
+        self.set_package_manager("cargo");
+        let Ok(parsed) = toml::from_str::<TomlValue>(raw) else {
+            self.set_test_command("cargo test".to_string());
+            return;
+        };
+        let mut dependencies = Vec::new();
+        let mut tables = vec![&parsed];
+        if let Some(workspace) = parsed.get("workspace") {
+            tables.push(workspace);
+        }
+        if let Some(targets) = parsed.get("target").and_then(TomlValue::as_table) {
+            tables.extend(targets.values());
+        }
+        for table in tables {
+            for key in ["dependencies", "dev-dependencies", "build-dependencies"] {
+                if let Some(deps) = table.get(key).and_then(TomlValue::as_table) {
+                    dependencies.extend(deps.keys().map(String::as_str));
+                }
+            }
+        }
+        self.add_frameworks(dependencies, CARGO_FRAMEWORKS);
+        let command = if parsed.get("workspace").is_some() {
+            "cargo test --workspace"
+        } else {
+            "cargo test"
+        };
+        self.set_test_command(command.to_string());
+    }
+
+    fn inspect_package_json(&mut self, dir: &Path, prefix: &str, raw: &str) {
+        push_unique(&mut self.stack.languages, "JavaScript");
+        let parsed: Value = serde_json::from_str(raw).unwrap_or(Value::Null);
+        let mut dependencies = Vec::new();
+        for key in ["dependencies", "devDependencies", "peerDependencies"] {
+            if let Some(deps) = parsed.get(key).and_then(Value::as_object) {
+                dependencies.extend(deps.keys().map(String::as_str));
+            }
+        }
+        if dependencies.contains(&"typescript") || dir.join("tsconfig.json").is_file() {
+            push_unique(&mut self.stack.languages, "TypeScript");
+        }
+        self.add_frameworks(dependencies, NODE_FRAMEWORKS);
+
+        let declared = parsed
+            .get("packageManager")
+            .and_then(Value::as_str)
+            .and_then(|value| value.split('@').next())
+            .filter(|value| !value.is_empty())
+            .map(str::to_string);
+        let locked = NODE_LOCKFILES.iter().find_map(|(lockfile, manager)| {
+            dir.join(lockfile).is_file().then(|| {
+                self.stack.sources.push(relative(prefix, lockfile));
+                manager.to_string()
+            })
+        });
+        let manager = declared.or(locked).unwrap_or_else(|| "npm".to_string());
+        self.set_package_manager(&manager);
+
+        let has_test_script = parsed
+            .get("scripts")
+            .and_then(|scripts| scripts.get("test"))
+            .and_then(Value::as_str)
+            .map(|script| !script.contains(NPM_PLACEHOLDER_TEST))
+            .unwrap_or(false);
+        if has_test_script {
+            let command = match manager.as_str() {
+                // `bun test` runs bun's own runner rather than the script.
+                "bun" => "bun run test".to_string(),
+                other => format!("{other} test"),
+            };
+            self.set_test_command(command);
+        }
+    }
+
+    fn inspect_pyproject(&mut self, dir: &Path, prefix: &str, raw: &str) {
+        push_unique(&mut self.stack.languages, "Python");
+        let parsed = toml::from_str::<TomlValue>(raw)
+            .unwrap_or_else(|_| TomlValue::Table(Default::default()));
+        let mut dependencies: Vec<String> = Vec::new();
+        let requirement_lists = [
+            parsed.get("project").and_then(|p| p.get("dependencies")),
+            parsed
+                .get("project")
+                .and_then(|p| p.get("optional-dependencies")),
+            parsed.get("dependency-groups"),
+        ];
+        for list in requirement_lists.into_iter().flatten() {
+            collect_requirements(list, &mut dependencies);
+        }
+        let poetry = parsed.get("tool").and_then(|tool| tool.get("poetry"));
+        if let Some(poetry) = poetry {
+            let mut tables: Vec<&TomlValue> = poetry.get("dependencies").into_iter().collect();
+            if let Some(groups) = poetry.get("group").and_then(TomlValue::as_table) {
+                tables.extend(
+                    groups
+                        .values()
+                        .filter_map(|group| group.get("dependencies")),
+                );
+            }
+            for table in tables.iter().filter_map(|table| table.as_table()) {
+                dependencies.extend(table.keys().map(|key| key.to_ascii_lowercase()));
+            }
+        }
+        self.add_frameworks(dependencies.iter().map(String::as_str), PYTHON_FRAMEWORKS);
+
+        let locked = PYTHON_LOCKFILES.iter().find_map(|(lockfile, manager)| {
+            dir.join(lockfile).is_file().then(|| {
+                self.stack.sources.push(relative(prefix, lockfile));
+                *manager
+            })
+        });
+        let manager = locked.or(poetry.map(|_| "poetry")).unwrap_or("pip");
+        self.set_package_manager(manager);
+
+        let uses_pytest = dependencies.iter().any(|name| name == "pytest")
+            || parsed
+                .get("tool")
+                .and_then(|tool| tool.get("pytest"))
+                .is_some();
+        if uses_pytest {
+            let command = match manager {
+                "pip" => "pytest".to_string(),
+                other => format!("{other} run pytest"),
+            };
+            self.set_test_command(command);
+        }
+    }
+
+    fn inspect_requirements(&mut self, raw: &str) {
+        push_unique(&mut self.stack.languages, "Python");
+        self.set_package_manager("pip");
+        let dependencies: Vec<String> = raw.lines().filter_map(requirement_name).collect();
+        self.add_frameworks(dependencies.iter().map(String::as_str), PYTHON_FRAMEWORKS);
+        if dependencies.iter().any(|name| name == "pytest") {
+            self.set_test_command("pytest".to_string());
+        }
+    }
+
+    fn inspect_go_mod(&mut self, raw: &str) {
+        push_unique(&mut self.stack.languages, "Go");
+        self.set_package_manager("go");
+        let modules: Vec<&str> = raw
+            .lines()
+            .filter_map(|line| line.split_whitespace().find(|part| part.contains('/')))
+            .map(go_module_base)
+            .collect();
+        self.add_frameworks(modules, GO_FRAMEWORKS);
+        self.set_test_command("go test ./...".to_string());
+    }
+
+    fn inspect_ci(&mut self, root: &Path) {
+        let workflows = root.join(".github").join("workflows");
+        if let Ok(entries) = std::fs::read_dir(&workflows) {
+            let mut files: Vec<_> = entries
+                .flatten()
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|name| name.ends_with(".yml") || name.ends_with(".yaml"))
+                .collect();
+            files.sort();
+            files.truncate(MAX_WORKFLOW_FILES);
+            if !files.is_empty() {
+                push_unique(&mut self.stack.ci, "GitHub Actions");
+            }
+            for name in files {
+                self.stack.sources.push(format!(".github/workflows/{name}"));
+                if let Some(raw) = read_bounded(&workflows.join(&name)) {
+                    self.scan_ci_commands(&raw);
+                }
+            }
+        }
+        for (config, provider) in CI_CONFIGS {
+            let path = root.join(config);
+            if let Some(raw) = read_bounded(&path) {
+                push_unique(&mut self.stack.ci, provider);
+                self.stack.sources.push(config.to_string());
+                self.scan_ci_commands(&raw);
+            }
+        }
+    }
+
+    /// Remembers the first single-line CI step that runs tests.
+    fn scan_ci_commands(&mut self, raw: &str) {
+        if self.ci_test_command.is_some() {
+            return;
+        }
+        self.ci_test_command = raw
+            .lines()
+            .map(|line| line.trim().trim_start_matches("- ").trim())
+            .filter_map(|line| {
+                line.strip_prefix("run:")
+                    .or_else(|| line.strip_prefix("script:"))
+            })
+            .map(|command| command.trim().trim_matches('"').trim_matches('\'').trim())
+            .find(|command| {
+                !command.is_empty()
+                    && !command.starts_with('|')
+                    && !command.contains("&&")
+                    && is_test_command(command)
+            })
+            .map(str::to_string);
+    }
+
+    fn finish(mut self) -> WorkspaceStack {
+        self.stack.test_command = self.manifest_test_command.or(self.ci_test_command);
+        self.stack
+    }
+}
+
+fn is_test_command(command: &str) -> bool {
+    command
+        .split_whitespace()
+        .take(3)
+        .any(|word| word == "test" || word == "pytest" || word == "test:unit")
+}
+
+/// Strips a major-version suffix such as `/v4` from a Go module path.
+fn go_module_base(module: &str) -> &str {
+    match module.rsplit_once('/') {
+        Some((base, version))
+            if version.len() > 1
+                && version.starts_with('v')
+                && version[1..].chars().all(|ch| ch.is_ascii_digit()) =>
+        {
+            base
+        }
+        _ => module,
+    }
+}
+
+/// Package name of a PEP 508 requirement such as `fastapi[all]>=0.110`.
+fn requirement_name(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') || line.starts_with('-') {
+        return None;
+    }
+    let name: String = line
+        .chars()
+        .take_while(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.'))
+        .collect();
+    (!name.is_empty()).then(|| name.to_ascii_lowercase())
+}
+
+fn collect_requirements(value: &TomlValue, names: &mut Vec<String>) {
+    match value {
+        TomlValue::String(requirement) => names.extend(requirement_name(requirement)),
+        TomlValue::Array(items) => items
+            .iter()
+            .for_each(|item| collect_requirements(item, names)),
+        TomlValue::Table(groups) => groups
+            .values()
+            .for_each(|group| collect_requirements(group, names)),
+        _ => {}
+    }
+}
+
+/// Detects the workspace stack from manifests in the root and its immediate subfolders,
+/// lockfiles next to them and CI configs in the root. Only known file names are read, each
+/// up to `MAX_FILE_BYTES`, so folders without code simply produce an empty stack.
+pub(crate) fn detect_workspace_stack(root: &Path) -> WorkspaceStack {
+    let mut detection = Detection::default();
+    if !root.is_dir() {
+        return detection.finish();
+    }
+    detection.inspect_dir(root, "");
+    if let Ok(entries) = std::fs::read_dir(root) {
+        let mut subdirs: Vec<String> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().map(|kind| kind.is_dir()).unwrap_or(false))
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| !name.starts_with('.') && !SKIPPED_SUBDIRS.contains(&name.as_str()))
+            .collect();
+        subdirs.sort();
+        subdirs.truncate(MAX_SUBDIRS);
+        for name in subdirs {
+            detection.inspect_dir(&root.join(&name), &name);
+        }
+    }
+    detection.inspect_ci(root);
+    detection.finish()
+}
+
+/// Applies the workspace's manual overrides to a detected stack.
+pub(crate) fn effective_workspace_stack(
+    entry: &WorkspaceEntry,
+    detected: WorkspaceStack,
+) -> WorkspaceStack {
+    let mut stack = detected;
+    let Some(overrides) = entry.settings.stack.as_ref() else {
+        return stack;
+    };
+    if let Some(languages) = overrides.languages.clone() {
+        stack.languages = languages;
+    }
+    if let Some(frameworks) = overrides.frameworks.clone() {
+        stack.frameworks = frameworks;
+    }
+    if let Some(package_manager) = overrides.package_manager.clone() {
+        stack.package_manager = Some(package_manager).filter(|value| !value.trim().is_empty());
+    }
+    if let Some(test_command) = overrides.test_command.clone() {
+        stack.test_command = Some(test_command).filter(|value| !value.trim().is_empty());
+    }
+    stack
+}
+
+/// One-line summary of the stack for generation prompts, `None` when nothing is known.
+pub(crate) fn stack_prompt_context(stack: &WorkspaceStack) -> Option<String> {
+    if stack.is_empty() {
+        return None;
+    }
+    let mut parts = Vec::new();
+    if !stack.languages.is_empty() {
+        parts.push(format!("languages: {}", stack.languages.join(", ")));
+    }
+    if !stack.frameworks.is_empty() {
+        parts.push(format!("frameworks: {}", stack.frameworks.join(", ")));
+    }
+    if let Some(package_manager) = &stack.package_manager {
+        parts.push(format!("package manager: {package_manager}"));
+    }
+    if let Some(test_command) = &stack.test_command {
+        parts.push(format!("tests: `{test_command}`"));
+    }
+    if !stack.ci.is_empty() {
+        parts.push(format!("CI: {}", stack.ci.join(", ")));
+    }
+    Some(parts.join("; "))
+}
+
+pub(crate) async fn detect_workspace_stack_core(path: &str) -> WorkspaceStack {
+    let root = std::path::PathBuf::from(path);
+    tokio::task::spawn_blocking(move || detect_workspace_stack(&root))
+        .await
+        .unwrap_or_default()
+}
+
+/// Returns the workspace stack with overrides applied, detecting it first when the cache on
+/// the entry is empty or `refresh` is set.
+pub(crate) async fn get_workspace_stack_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    refresh: bool,
+) -> Result<WorkspaceStack, String> {
+    let entry = workspaces
+        .lock()
+        .await
+        .get(workspace_id)
+        .cloned()
+        .ok_or_else(|| "workspace not found".to_string())?;
+    let detected = match entry.stack.clone() {
+        Some(stack) if !refresh => stack,
+        _ => {
+            let stack = detect_workspace_stack_core(&entry.path).await;
+            if let Some(stored) = workspaces.lock().await.get_mut(workspace_id) {
+                stored.stack = Some(stack.clone());
+            }
+            stack
+        }
+    };
+    Ok(effective_workspace_stack(&entry, detected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{WorkspaceKind, WorkspaceSettings, WorkspaceStackOverride};
+    use uuid::Uuid;
+
+    fn temp_root() -> std::path::PathBuf {
+        let root = std::env::temp_dir().join(format!("micode-stack-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create root");
+        root
+    }
+
+    #[test]
+    fn detects_tauri_app_with_frontend_and_ci() {
+        let root = temp_root();
+        std::fs::write(
+            root.join("package.json"),
+            r#"{
+                "scripts": { "test": "vitest run" },
+                "dependencies": { "react": "^19.0.0", "@tauri-apps/api": "^2" },
+                "devDependencies": { "typescript": "^5", "vite": "^6", "vitest": "^3" }
+            }"#,
+        )
+        .expect("write package.json");
+        std::fs::write(root.join("package-lock.json"), "{}").expect("write lockfile");
+        std::fs::create_dir_all(root.join("src-tauri")).expect("create src-tauri");
+        std::fs::write(
+            root.join("src-tauri/Cargo.toml"),
+            "[package]\nname = \"app\"\n\n[dependencies]\ntauri = \"2\"\nserde = \"1\"\n",
+        )
+        .expect("write Cargo.toml");
+        std::fs::create_dir_all(root.join(".github/workflows")).expect("create workflows");
+        std::fs::write(
+            root.join(".github/workflows/ci.yml"),
+            "jobs:\n  test:\n    steps:\n      - run: npm ci\n      - run: npm test\n",
+        )
+        .expect("write workflow");
+
+        let stack = detect_workspace_stack(&root);
+        assert_eq!(stack.languages, vec!["JavaScript", "TypeScript", "Rust"]);
+        assert_eq!(stack.frameworks, vec!["React", "Tauri", "Vite", "Vitest"]);
+        assert_eq!(stack.package_manager.as_deref(), Some("npm"));
+        assert_eq!(stack.test_command.as_deref(), Some("npm test"));
+        assert_eq!(stack.ci, vec!["GitHub Actions"]);
+        assert!(stack.sources.contains(&"src-tauri/Cargo.toml".to_string()));
+        assert!(stack.sources.contains(&"package-lock.json".to_string()));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn detects_python_and_go_and_ignores_plain_folders() {
+        let root = temp_root();
+        std::fs::write(
+            root.join("pyproject.toml"),
+            "[project]\nname = \"api\"\ndependencies = [\"fastapi[all]>=0.110\"]\n\n\
+[dependency-groups]\ndev = [\"pytest>=8\"]\n",
+        )
+        .expect("write pyproject");
+        std::fs::write(root.join("uv.lock"), "").expect("write uv.lock");
+        std::fs::create_dir_all(root.join("cli")).expect("create cli");
+        std::fs::write(
+            root.join("cli/go.mod"),
+            "module example.com/cli\n\nrequire (\n\tgithub.com/spf13/cobra v1.8.0\n\tgithub.com/labstack/echo/v4 v4.12.0\n)\n",
+        )
+        .expect("write go.mod");
+
+        let stack = detect_workspace_stack(&root);
+        assert_eq!(stack.languages, vec!["Python", "Go"]);
+        assert_eq!(stack.frameworks, vec!["FastAPI", "pytest", "Echo", "Cobra"]);
+        assert_eq!(stack.package_manager.as_deref(), Some("uv"));
+        assert_eq!(stack.test_command.as_deref(), Some("uv run pytest"));
+
+        let plain = temp_root();
+        std::fs::write(plain.join("notes.txt"), "groceries").expect("write notes");
+        let stack = detect_workspace_stack(&plain);
+        assert!(stack.is_empty());
+        assert_eq!(stack_prompt_context(&stack), None);
+        assert!(detect_workspace_stack(&plain.join("missing")).is_empty());
+
+        let _ = std::fs::remove_dir_all(&root);
+        let _ = std::fs::remove_dir_all(&plain);
+    }
+
+    #[test]
+    fn overrides_replace_detected_fields() {
+        let entry = WorkspaceEntry {
+            id: "ws-1".to_string(),
+            name: "ws".to_string(),
+            path: "/tmp/ws".to_string(),
+            agent_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings {
+                stack: Some(WorkspaceStackOverride {
+                    frameworks: Some(vec!["Next.js".to_string()]),
+                    test_command: Some("pnpm test:unit".to_string()),
+                    ..WorkspaceStackOverride::default()
+                }),
+                ..WorkspaceSettings::default()
+            },
+            stack: None,
+        };
+        let detected = WorkspaceStack {
+            languages: vec!["TypeScript".to_string()],
+            frameworks: vec!["React".to_string()],
+            package_manager: Some("pnpm".to_string()),
+            test_command: None,
+            ..WorkspaceStack::default()
+        };
+
+        let stack = effective_workspace_stack(&entry, detected);
+        assert_eq!(stack.frameworks, vec!["Next.js"]);
+        assert_eq!(
+            stack_prompt_context(&stack).as_deref(),
+            Some(
+                "languages: TypeScript; frameworks: Next.js; package manager: pnpm; \
+tests: `pnpm test:unit`"
+            )
+        );
+    }
+}
@@ -1,24 +1,66 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
 
+use serde_json::{json, Value};
 use sha2::{Digest, Sha256};
 use tokio::sync::Mutex;
+use tokio::time::{sleep, Instant};
 
-use crate::backend::app_server::WorkspaceSession;
+use crate::backend::app_server::{
+    now_ms, prune_thread_history_at, SessionLaunchConfig, WorkspaceSession,
+};
+use crate::backend::connect_queue::ConnectQueue;
+use crate::backend::connection_state::ConnectionStates;
+use crate::backend::event_methods;
+use crate::backend::events::AppServerEvent;
+use crate::backend::history_prune::{HistoryPruneOptions, HistoryPruneResult};
+use crate::backend::redaction::{redact_text, Redacted, Redactor};
+use crate::backend::sampling::validate_sampling_params;
+use crate::backend::store_maintenance::{StoreMaintenanceReport, StoreMaintenanceStatus};
+use crate::backend::turn_audit::{read_turn_audit, TurnAudit};
+use crate::backend::turn_reviews::{
+    read_turn_review, set_file_review_state, FileReviewState, TurnReview,
+};
+use crate::backend::workspace_paths::normalize_workspace_path;
 use crate::micode::args::resolve_workspace_micode_args;
-use crate::micode::home::{resolve_default_micode_home, resolve_workspace_micode_home};
+use crate::micode::home::{
+    isolated_agent_home_path, remove_isolated_agent_home, resolve_default_micode_home,
+    resolve_workspace_micode_home,
+};
+use crate::shared::bootstrap_core::{bootstrap_warnings_event, check_workspace_bootstrap_core};
+use crate::shared::run_kickoff_core::validate_kickoff_template;
+use crate::shared::workspace_roots_core::{select_root, workspace_roots};
+use crate::shared::workspace_stack_core::detect_workspace_stack_core;
 use crate::storage::write_workspaces;
+use crate::types::errors::CommandError;
 use crate::types::{
-    AppSettings, WorkspaceEntry, WorkspaceInfo, WorkspaceKind, WorkspaceSettings, WorktreeInfo,
-    WorktreeSetupStatus,
+    AppSettings, RedactionSettings, WorkspaceBootstrapWarning, WorkspaceEntry, WorkspaceInfo,
+    WorkspaceKind, WorkspaceRuntimeInfo, WorkspaceSettings, WorktreeInfo, WorktreeSetupStatus,
 };
 use uuid::Uuid;
 
 pub(crate) const WORKTREE_SETUP_MARKERS_DIR: &str = "worktree-setup";
 pub(crate) const WORKTREE_SETUP_MARKER_EXT: &str = "ran";
-const AGENTS_MD_FILE_NAME: &str = "AGENTS.md";
+pub(crate) const AGENTS_MD_FILE_NAME: &str = "AGENTS.md";
+const RESTART_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+const RESTART_DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+pub(crate) const LIVENESS_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const UNRESPONSIVE_AGENT_REASON: &str = "agent unresponsive";
+/// Wait before each attempt to respawn an agent that crashed.
+const CRASH_RESPAWN_BACKOFF: [Duration; 3] = [
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(5),
+];
+pub(crate) const STORE_MAINTENANCE_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const STORE_MAINTENANCE_IDLE_THRESHOLD: Duration = Duration::from_secs(15 * 60);
+const STORE_MAINTENANCE_MIN_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+pub(crate) const STORE_MAINTENANCE_LOG_FILE: &str = "store-maintenance.jsonl";
 
 fn copy_agents_md_from_parent_to_worktree(
     parent_repo_root: &PathBuf,
@@ -93,21 +135,59 @@ pub(crate) async fn list_workspaces_core(
             parent_id: entry.parent_id.clone(),
             worktree: entry.worktree.clone(),
             settings: entry.settings.clone(),
+            config_stale: sessions
+                .get(&entry.id)
+                .map(|session| session.is_config_stale())
+                .unwrap_or(false),
+            runtime: None,
+            bootstrap_warnings: Vec::new(),
         });
     }
     sort_workspaces(&mut result);
     result
 }
 
+/// Joins runtime state into a `list_workspaces` result: connection status, the last
+/// connect error, active turns and, when the caller has usage data, today's turn count
+/// keyed by workspace id.
+pub(crate) async fn attach_workspace_runtime_core(
+    workspaces: &mut [WorkspaceInfo],
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    connection_states: &ConnectionStates,
+    turns_today: Option<&HashMap<String, u64>>,
+) {
+    let sessions: HashMap<String, Arc<WorkspaceSession>> = sessions.lock().await.clone();
+    for workspace in workspaces.iter_mut() {
+        let session = sessions.get(&workspace.id);
+        let (status, last_error) =
+            connection_states.connection_status(&workspace.id, session.is_some());
+        let active_turns = match session {
+            Some(session) => session.active_turn_count().await,
+            None => 0,
+        };
+        let (last_error, last_error_at) = match last_error {
+            Some((message, at)) => (Some(message), Some(at)),
+            None => (None, None),
+        };
+        workspace.runtime = Some(WorkspaceRuntimeInfo {
+            status,
+            last_error,
+            last_error_at,
+            active_turns,
+            turns_today: turns_today.map(|counts| counts.get(&workspace.id).copied().unwrap_or(0)),
+        });
+    }
+}
+
 async fn resolve_entry_and_parent(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     workspace_id: &str,
-) -> Result<(WorkspaceEntry, Option<WorkspaceEntry>), String> {
+) -> Result<(WorkspaceEntry, Option<WorkspaceEntry>), CommandError> {
     let workspaces = workspaces.lock().await;
     let entry = workspaces
         .get(workspace_id)
         .cloned()
-        .ok_or_else(|| "workspace not found".to_string())?;
+        .ok_or_else(CommandError::workspace_not_found)?;
     let parent_entry = entry
         .parent_id
         .as_ref()
@@ -119,12 +199,12 @@ async fn resolve_entry_and_parent(
 async fn resolve_workspace_root(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     workspace_id: &str,
-) -> Result<PathBuf, String> {
+) -> Result<PathBuf, CommandError> {
     let workspaces = workspaces.lock().await;
     let entry = workspaces
         .get(workspace_id)
         .cloned()
-        .ok_or_else(|| "workspace not found".to_string())?;
+        .ok_or_else(CommandError::workspace_not_found)?;
     Ok(PathBuf::from(entry.path))
 }
 
@@ -132,13 +212,13 @@ pub(crate) async fn worktree_setup_status_core(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     workspace_id: &str,
     data_dir: &PathBuf,
-) -> Result<WorktreeSetupStatus, String> {
+) -> Result<WorktreeSetupStatus, CommandError> {
     let entry = {
         let workspaces = workspaces.lock().await;
         workspaces
             .get(workspace_id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?
+            .ok_or_else(CommandError::workspace_not_found)?
     };
 
     let script = normalize_setup_script(entry.settings.worktree_setup_script.clone());
@@ -156,16 +236,16 @@ pub(crate) async fn worktree_setup_mark_ran_core(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     workspace_id: &str,
     data_dir: &PathBuf,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let entry = {
         let workspaces = workspaces.lock().await;
         workspaces
             .get(workspace_id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?
+            .ok_or_else(CommandError::workspace_not_found)?
     };
     if !entry.kind.is_worktree() {
-        return Err("Not a worktree workspace.".to_string());
+        return Err("Not a worktree workspace.".into());
     }
     let marker_path = worktree_setup_marker_path(data_dir, &entry.id);
     if let Some(parent) = marker_path.parent() {
@@ -181,19 +261,29 @@ pub(crate) async fn worktree_setup_mark_ran_core(
     Ok(())
 }
 
+/// A folder to add as a main workspace, and the agent binary it uses instead of the
+/// app's, if any.
+#[derive(Debug, Clone)]
+pub(crate) struct NewWorkspace {
+    pub(crate) path: String,
+    pub(crate) agent_bin: Option<String>,
+}
+
 pub(crate) async fn add_workspace_core<F, Fut>(
-    path: String,
-    agent_bin: Option<String>,
+    workspace: NewWorkspace,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     app_settings: &Mutex<AppSettings>,
+    data_dir: &Path,
     storage_path: &PathBuf,
     spawn_session: F,
 ) -> Result<WorkspaceInfo, String>
 where
     F: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> Fut,
-    Fut: Future<Output = Result<Arc<WorkspaceSession>, String>>,
+    Fut: Future<Output = Result<Arc<WorkspaceSession>, CommandError>>,
 {
+    let NewWorkspace { path, agent_bin } = workspace;
+    let path = normalize_workspace_path(Path::new(&path))?;
     if !PathBuf::from(&path).is_dir() {
         return Err("Workspace path must be a folder.".to_string());
     }
@@ -212,6 +302,7 @@ where
         parent_id: None,
         worktree: None,
         settings: WorkspaceSettings::default(),
+        stack: Some(detect_workspace_stack_core(&path).await),
     };
 
     let (default_bin, agent_args) = {
@@ -221,7 +312,8 @@ where
             resolve_workspace_micode_args(&entry, None, Some(&settings)),
         )
     };
-    let agent_home = resolve_workspace_micode_home(&entry, None);
+    let agent_home = resolve_workspace_micode_home(&entry, None, data_dir);
+    let bootstrap_warnings = check_workspace_bootstrap_core(&entry).await;
     let session = spawn_session(entry.clone(), default_bin, agent_args, agent_home).await?;
 
     if let Err(error) = {
@@ -234,8 +326,7 @@ where
             let mut workspaces = workspaces.lock().await;
             workspaces.remove(&entry.id);
         }
-        let mut child = session.child.lock().await;
-        let _ = child.kill().await;
+        session.kill().await;
         return Err(error);
     }
 
@@ -251,6 +342,9 @@ where
         parent_id: entry.parent_id,
         worktree: entry.worktree,
         settings: entry.settings,
+        config_stale: false,
+        runtime: None,
+        bootstrap_warnings,
     })
 }
 
@@ -311,15 +405,71 @@ pub(crate) async fn clear_workspace_history_core(
     Ok(())
 }
 
+/// Clears workspace history. Unscoped, non-dry-run calls wipe everything as
+/// `clear_workspace_history_core` always did and stop the affected sessions; otherwise only
+/// matching threads are listed or removed. Returns the summary and the `threads/changed`
+/// events to emit.
+pub(crate) async fn clear_workspace_history_scoped_core(
+    workspace_id: &str,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    options: &HistoryPruneOptions,
+) -> Result<(HistoryPruneResult, Vec<AppServerEvent>), String> {
+    let (target_ids, target_paths) =
+        resolve_workspace_history_targets_core(workspace_id, workspaces).await?;
+    let mut result = HistoryPruneResult::new(options);
+    if !result.scoped {
+        clear_workspace_history_core(workspace_id, workspaces).await?;
+        kill_sessions_core(sessions, &target_ids).await;
+        let events = target_ids
+            .iter()
+            .map(|id| threads_changed_event(id, true, &[]))
+            .collect();
+        return Ok((result, events));
+    }
+
+    let mut events = Vec::new();
+    for (id, path) in target_ids.iter().zip(target_paths.iter()) {
+        let session = sessions.lock().await.get(id).cloned();
+        let threads = match session {
+            Some(session) => session.prune_thread_history(options).await,
+            None => prune_thread_history_at(id, path, options),
+        };
+        if !options.dry_run && !threads.is_empty() {
+            let removed: Vec<&str> = threads
+                .iter()
+                .map(|thread| thread.thread_id.as_str())
+                .collect();
+            events.push(threads_changed_event(id, false, &removed));
+        }
+        result.extend(threads);
+    }
+    Ok((result, events))
+}
+
+fn threads_changed_event(workspace_id: &str, cleared: bool, removed: &[&str]) -> AppServerEvent {
+    AppServerEvent {
+        workspace_id: workspace_id.to_string(),
+        message: json!({
+            "method": event_methods::THREADS_CHANGED,
+            "params": {
+                "workspaceId": workspace_id,
+                "cleared": cleared,
+                "removedThreadIds": removed
+            }
+        }),
+    }
+}
+
 pub(crate) async fn resolve_workspace_history_targets_core(
     workspace_id: &str,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
-) -> Result<(Vec<String>, Vec<String>), String> {
+) -> Result<(Vec<String>, Vec<String>), CommandError> {
     let map = workspaces.lock().await;
     let entry = map
         .get(workspace_id)
         .cloned()
-        .ok_or_else(|| "workspace not found".to_string())?;
+        .ok_or_else(CommandError::workspace_not_found)?;
 
     let mut ids = vec![entry.id.clone()];
     let mut paths = vec![entry.path.clone()];
@@ -376,7 +526,7 @@ pub(crate) async fn add_worktree_core<
 ) -> Result<WorkspaceInfo, String>
 where
     FSpawn: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> FutSpawn,
-    FutSpawn: Future<Output = Result<Arc<WorkspaceSession>, String>>,
+    FutSpawn: Future<Output = Result<Arc<WorkspaceSession>, CommandError>>,
     FSanitize: Fn(&str) -> String,
     FUniquePath: Fn(&PathBuf, &str) -> Result<PathBuf, String>,
     FBranchExists: Fn(&PathBuf, &str) -> FutBranchExists,
@@ -462,6 +612,8 @@ where
         }
     }
 
+    let worktree_path_string = normalize_workspace_path(&worktree_path)?;
+    let stack = detect_workspace_stack_core(&worktree_path_string).await;
     let entry = WorkspaceEntry {
         id: Uuid::new_v4().to_string(),
         name: name.clone().unwrap_or_else(|| branch.clone()),
@@ -476,6 +628,7 @@ where
             ),
             ..WorkspaceSettings::default()
         },
+        stack: Some(stack),
     };
 
     let (default_bin, agent_args) = {
@@ -485,7 +638,7 @@ where
             resolve_workspace_micode_args(&entry, Some(&parent_entry), Some(&settings)),
         )
     };
-    let agent_home = resolve_workspace_micode_home(&entry, Some(&parent_entry));
+    let agent_home = resolve_workspace_micode_home(&entry, Some(&parent_entry), data_dir);
     let session = spawn_session(entry.clone(), default_bin, agent_args, agent_home).await?;
 
     {
@@ -507,6 +660,9 @@ where
         parent_id: entry.parent_id,
         worktree: entry.worktree,
         settings: entry.settings,
+        config_stale: false,
+        runtime: None,
+        bootstrap_warnings: Vec::new(),
     })
 }
 
@@ -515,13 +671,19 @@ pub(crate) async fn connect_workspace_core<F, Fut>(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     app_settings: &Mutex<AppSettings>,
+    data_dir: &Path,
     spawn_session: F,
-) -> Result<(), String>
+) -> Result<Vec<WorkspaceBootstrapWarning>, CommandError>
 where
     F: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> Fut,
-    Fut: Future<Output = Result<Arc<WorkspaceSession>, String>>,
+    Fut: Future<Output = Result<Arc<WorkspaceSession>, CommandError>>,
 {
-    let (entry, parent_entry) = resolve_entry_and_parent(workspaces, &workspace_id).await?;
+    let (mut entry, parent_entry) = resolve_entry_and_parent(workspaces, &workspace_id).await?;
+    let bootstrap_warnings = check_workspace_bootstrap_core(&entry).await;
+    entry.stack = Some(detect_workspace_stack_core(&entry.path).await);
+    if let Some(stored) = workspaces.lock().await.get_mut(&entry.id) {
+        stored.stack = entry.stack.clone();
+    }
     let (default_bin, agent_args) = {
         let settings = app_settings.lock().await;
         (
@@ -529,16 +691,142 @@ where
             resolve_workspace_micode_args(&entry, parent_entry.as_ref(), Some(&settings)),
         )
     };
-    let agent_home = resolve_workspace_micode_home(&entry, parent_entry.as_ref());
+    let agent_home = resolve_workspace_micode_home(&entry, parent_entry.as_ref(), data_dir);
     let session = spawn_session(entry.clone(), default_bin, agent_args, agent_home).await?;
     sessions.lock().await.insert(entry.id, session);
-    Ok(())
+    Ok(bootstrap_warnings)
+}
+
+/// Drives `futures` concurrently on the current task; outputs keep the input order.
+pub(crate) async fn join_all<F: Future>(futures: impl IntoIterator<Item = F>) -> Vec<F::Output> {
+    let mut futures: Vec<_> = futures
+        .into_iter()
+        .map(|future| Some(Box::pin(future)))
+        .collect();
+    let mut outputs: Vec<Option<F::Output>> = futures.iter().map(|_| None).collect();
+    std::future::poll_fn(|cx| {
+        let mut pending = false;
+        for (slot, output) in futures.iter_mut().zip(outputs.iter_mut()) {
+            let Some(future) = slot else {
+                continue;
+            };
+            match future.as_mut().poll(cx) {
+                Poll::Ready(value) => {
+                    *output = Some(value);
+                    *slot = None;
+                }
+                Poll::Pending => pending = true,
+            }
+        }
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    })
+    .await;
+    outputs.into_iter().flatten().collect()
+}
+
+/// Which workspaces `connect_all_workspaces_core` connects: `workspace_ids`, or every
+/// workspace when `None`, with `priority_workspace_id` started first.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ConnectAllSelection {
+    pub(crate) workspace_ids: Option<Vec<String>>,
+    pub(crate) priority_workspace_id: Option<String>,
+}
+
+/// Connects the selected workspaces that have no session yet. Spawns go through the
+/// connect queue, so at most `maxConcurrentConnects` start at once. Returns the summary
+/// plus the bootstrap-warning events of the connected workspaces.
+pub(crate) async fn connect_all_workspaces_core<F, Fut>(
+    selection: ConnectAllSelection,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    app_settings: &Mutex<AppSettings>,
+    data_dir: &Path,
+    connect_queue: &ConnectQueue,
+    spawn_session: F,
+) -> (Value, Vec<AppServerEvent>)
+where
+    F: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> Fut,
+    Fut: Future<Output = Result<Arc<WorkspaceSession>, CommandError>>,
+{
+    let ConnectAllSelection {
+        workspace_ids,
+        priority_workspace_id,
+    } = selection;
+    let mut ids = {
+        let workspaces = workspaces.lock().await;
+        let mut seen = HashSet::new();
+        let mut ids: Vec<String> = workspace_ids
+            .unwrap_or_else(|| workspaces.keys().cloned().collect())
+            .into_iter()
+            .filter(|id| workspaces.contains_key(id) && seen.insert(id.clone()))
+            .collect();
+        ids.sort_by_key(|id| {
+            let entry = &workspaces[id];
+            (
+                priority_workspace_id.as_deref() != Some(id.as_str()),
+                entry.settings.sort_order.unwrap_or(u32::MAX),
+                entry.name.to_lowercase(),
+            )
+        });
+        ids
+    };
+    let mut already_connected = Vec::new();
+    {
+        let sessions = sessions.lock().await;
+        ids.retain(|id| {
+            let connected = sessions.contains_key(id);
+            if connected {
+                already_connected.push(id.clone());
+            }
+            !connected
+        });
+    }
+
+    connect_queue.set_priority_workspace(priority_workspace_id.as_deref());
+    let results = join_all(ids.iter().map(|id| {
+        connect_workspace_core(
+            id.clone(),
+            workspaces,
+            sessions,
+            app_settings,
+            data_dir,
+            &spawn_session,
+        )
+    }))
+    .await;
+    connect_queue.set_priority_workspace(None);
+
+    let mut connected = Vec::new();
+    let mut failed = Vec::new();
+    let mut events = Vec::new();
+    for (id, result) in ids.into_iter().zip(results) {
+        match result {
+            Ok(warnings) => {
+                events.extend(bootstrap_warnings_event(&id, &warnings));
+                connected.push(id);
+            }
+            Err(error) => failed.push(json!({
+                "workspaceId": id,
+                "error": error.message,
+                "code": error.code,
+            })),
+        }
+    }
+    let summary = json!({
+        "connected": connected,
+        "alreadyConnected": already_connected,
+        "failed": failed,
+    });
+    (summary, events)
 }
 
 async fn kill_session_by_id(sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>, id: &str) {
     if let Some(session) = sessions.lock().await.remove(id) {
-        let mut child = session.child.lock().await;
-        let _ = child.kill().await;
+        session.kill().await;
     }
 }
 
@@ -546,13 +834,16 @@ pub(crate) async fn remove_workspace_core<FRunGit, FutRunGit, FIsMissing, FRemov
     id: String,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    connection_states: &ConnectionStates,
     storage_path: &PathBuf,
+    data_dir: &Path,
     run_git_command: FRunGit,
     is_missing_worktree_error: FIsMissing,
     remove_dir_all: FRemoveDirAll,
     require_all_children_removed_to_remove_parent: bool,
     continue_on_child_error: bool,
-) -> Result<(), String>
+    delete_agent_home: bool,
+) -> Result<(), CommandError>
 where
     FRunGit: Fn(&PathBuf, &[&str]) -> FutRunGit,
     FutRunGit: Future<Output = Result<(), String>>,
@@ -564,9 +855,9 @@ where
         let entry = workspaces
             .get(&id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?;
+            .ok_or_else(CommandError::workspace_not_found)?;
         if entry.kind.is_worktree() {
-            return Err("Use remove_worktree for worktree agents.".to_string());
+            return Err("Use remove_worktree for worktree agents.".into());
         }
         let children = workspaces
             .values()
@@ -595,7 +886,7 @@ where
                                 failures.push((child.id.clone(), fs_error));
                                 continue;
                             }
-                            return Err(fs_error);
+                            return Err(fs_error.into());
                         }
                     }
                 } else {
@@ -603,7 +894,7 @@ where
                         failures.push((child.id.clone(), error));
                         continue;
                     }
-                    return Err(error);
+                    return Err(error.into());
                 }
             }
         }
@@ -613,7 +904,8 @@ where
     let _ = run_git_command(&repo_path, &["worktree", "prune", "--expire", "now"]).await;
 
     let mut ids_to_remove = removed_child_ids;
-    if failures.is_empty() || !require_all_children_removed_to_remove_parent {
+    let removing_parent = failures.is_empty() || !require_all_children_removed_to_remove_parent;
+    if removing_parent {
         kill_session_by_id(sessions, &id).await;
         ids_to_remove.push(id.clone());
     }
@@ -622,11 +914,20 @@ where
         let mut workspaces = workspaces.lock().await;
         for workspace_id in ids_to_remove {
             workspaces.remove(&workspace_id);
+            connection_states.forget_workspace(&workspace_id);
         }
         let list: Vec<_> = workspaces.values().cloned().collect();
         write_workspaces(storage_path, &list)?;
     }
 
+    // Looked up by id rather than the setting so a home left behind after turning
+    // isolation off is still cleaned up.
+    if removing_parent && delete_agent_home {
+        if let Some(home) = isolated_agent_home_path(&entry.id, data_dir) {
+            remove_isolated_agent_home(&home, data_dir)?;
+        }
+    }
+
     if failures.is_empty() {
         return Ok(());
     }
@@ -637,35 +938,47 @@ where
         for (child_id, error) in failures {
             message.push_str(&format!("\n- {child_id}: {error}"));
         }
-        return Err(message);
+        return Err(message.into());
     }
 
     Ok(())
 }
 
+/// App or daemon state that removing a workspace updates.
+pub(crate) struct WorkspaceStores<'a> {
+    pub(crate) workspaces: &'a Mutex<HashMap<String, WorkspaceEntry>>,
+    pub(crate) sessions: &'a Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    pub(crate) connection_states: &'a ConnectionStates,
+    pub(crate) storage_path: &'a PathBuf,
+}
+
 pub(crate) async fn remove_worktree_core<FRunGit, FutRunGit, FIsMissing, FRemoveDirAll>(
     id: String,
-    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    storage_path: &PathBuf,
+    stores: WorkspaceStores<'_>,
     run_git_command: FRunGit,
     is_missing_worktree_error: FIsMissing,
     remove_dir_all: FRemoveDirAll,
-) -> Result<(), String>
+) -> Result<(), CommandError>
 where
     FRunGit: Fn(&PathBuf, &[&str]) -> FutRunGit,
     FutRunGit: Future<Output = Result<(), String>>,
     FIsMissing: Fn(&str) -> bool,
     FRemoveDirAll: Fn(&PathBuf) -> Result<(), String>,
 {
+    let WorkspaceStores {
+        workspaces,
+        sessions,
+        connection_states,
+        storage_path,
+    } = stores;
     let (entry, parent) = {
         let workspaces = workspaces.lock().await;
         let entry = workspaces
             .get(&id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?;
+            .ok_or_else(CommandError::workspace_not_found)?;
         if !entry.kind.is_worktree() {
-            return Err("Not a worktree workspace.".to_string());
+            return Err("Not a worktree workspace.".into());
         }
         let parent_id = entry
             .parent_id
@@ -694,7 +1007,7 @@ where
                     remove_dir_all(&entry_path)?;
                 }
             } else {
-                return Err(error);
+                return Err(error.into());
             }
         }
     }
@@ -706,6 +1019,7 @@ where
         let list: Vec<_> = workspaces.values().cloned().collect();
         write_workspaces(storage_path, &list)?;
     }
+    connection_states.forget_workspace(&entry.id);
 
     Ok(())
 }
@@ -726,6 +1040,7 @@ pub(crate) async fn rename_worktree_core<
     data_dir: &PathBuf,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    connection_states: &ConnectionStates,
     app_settings: &Mutex<AppSettings>,
     storage_path: &PathBuf,
     resolve_git_root: FResolveGitRoot,
@@ -734,10 +1049,10 @@ pub(crate) async fn rename_worktree_core<
     unique_worktree_path_for_rename: FUniqueRenamePath,
     run_git_command: FRunGit,
     spawn_session: FSpawn,
-) -> Result<WorkspaceInfo, String>
+) -> Result<WorkspaceInfo, CommandError>
 where
     FSpawn: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> FutSpawn,
-    FutSpawn: Future<Output = Result<Arc<WorkspaceSession>, String>>,
+    FutSpawn: Future<Output = Result<Arc<WorkspaceSession>, CommandError>>,
     FResolveGitRoot: Fn(&WorkspaceEntry) -> Result<PathBuf, String>,
     FUniqueBranch: Fn(&PathBuf, &str) -> FutUniqueBranch,
     FutUniqueBranch: Future<Output = Result<String, String>>,
@@ -748,7 +1063,7 @@ where
 {
     let trimmed = branch.trim();
     if trimmed.is_empty() {
-        return Err("Branch name is required.".to_string());
+        return Err("Branch name is required.".into());
     }
 
     let (entry, parent) = {
@@ -756,9 +1071,9 @@ where
         let entry = workspaces
             .get(&id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?;
+            .ok_or_else(CommandError::workspace_not_found)?;
         if !entry.kind.is_worktree() {
-            return Err("Not a worktree workspace.".to_string());
+            return Err("Not a worktree workspace.".into());
         }
         let parent_id = entry
             .parent_id
@@ -777,13 +1092,13 @@ where
         .map(|worktree| worktree.branch.clone())
         .ok_or_else(|| "worktree metadata missing".to_string())?;
     if old_branch == trimmed {
-        return Err("Branch name is unchanged.".to_string());
+        return Err("Branch name is unchanged.".into());
     }
 
     let parent_root = resolve_git_root(&parent)?;
     let final_branch = unique_branch_name(&parent_root, trimmed).await?;
     if final_branch == old_branch {
-        return Err("Branch name is unchanged.".to_string());
+        return Err("Branch name is unchanged.".into());
     }
 
     run_git_command(&parent_root, &["branch", "-m", &old_branch, &final_branch]).await?;
@@ -805,7 +1120,7 @@ where
         {
             let _ =
                 run_git_command(&parent_root, &["branch", "-m", &final_branch, &old_branch]).await;
-            return Err(error);
+            return Err(error.into());
         }
     }
 
@@ -813,7 +1128,7 @@ where
         let mut workspaces = workspaces.lock().await;
         let entry = match workspaces.get_mut(&id) {
             Some(entry) => entry,
-            None => return Err("workspace not found".to_string()),
+            None => return Err(CommandError::workspace_not_found()),
         };
         if entry.name.trim() == old_branch {
             entry.name = final_branch.clone();
@@ -834,6 +1149,8 @@ where
         (snapshot, list)
     };
     write_workspaces(storage_path, &list)?;
+    // Errors recorded for the old path no longer apply.
+    connection_states.forget_workspace(&entry_snapshot.id);
 
     let was_connected = sessions.lock().await.contains_key(&entry_snapshot.id);
     if was_connected {
@@ -845,7 +1162,7 @@ where
                 resolve_workspace_micode_args(&entry_snapshot, Some(&parent), Some(&settings)),
             )
         };
-        let agent_home = resolve_workspace_micode_home(&entry_snapshot, Some(&parent));
+        let agent_home = resolve_workspace_micode_home(&entry_snapshot, Some(&parent), data_dir);
         match spawn_session(entry_snapshot.clone(), default_bin, agent_args, agent_home).await {
             Ok(session) => {
                 sessions
@@ -873,6 +1190,9 @@ where
         parent_id: entry_snapshot.parent_id,
         worktree: entry_snapshot.worktree,
         settings: entry_snapshot.settings,
+        config_stale: false,
+        runtime: None,
+        bootstrap_warnings: Vec::new(),
     })
 }
 
@@ -899,7 +1219,7 @@ pub(crate) async fn rename_worktree_upstream_core<
     git_remote_exists: FRemoteExists,
     git_remote_branch_exists: FRemoteBranchExists,
     run_git_command: FRunGit,
-) -> Result<(), String>
+) -> Result<(), CommandError>
 where
     FResolveGitRoot: Fn(&WorkspaceEntry) -> Result<PathBuf, String>,
     FBranchExists: Fn(&PathBuf, &str) -> FutBranchExists,
@@ -916,10 +1236,10 @@ where
     let old_branch = old_branch.trim().to_string();
     let new_branch = new_branch.trim().to_string();
     if old_branch.is_empty() || new_branch.is_empty() {
-        return Err("Branch name is required.".to_string());
+        return Err("Branch name is required.".into());
     }
     if old_branch == new_branch {
-        return Err("Branch name is unchanged.".to_string());
+        return Err("Branch name is unchanged.".into());
     }
 
     let (_entry, parent) = {
@@ -927,9 +1247,9 @@ where
         let entry = workspaces
             .get(&id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?;
+            .ok_or_else(CommandError::workspace_not_found)?;
         if !entry.kind.is_worktree() {
-            return Err("Not a worktree workspace.".to_string());
+            return Err("Not a worktree workspace.".into());
         }
         let parent_id = entry
             .parent_id
@@ -944,7 +1264,7 @@ where
 
     let parent_root = resolve_git_root(&parent)?;
     if !git_branch_exists(&parent_root, &new_branch).await? {
-        return Err("Local branch not found.".to_string());
+        return Err("Local branch not found.".into());
     }
 
     let remote_for_old = git_find_remote_for_branch(&parent_root, &old_branch).await?;
@@ -954,13 +1274,13 @@ where
             if git_remote_exists(&parent_root, "origin").await? {
                 "origin".to_string()
             } else {
-                return Err("No git remote configured for this worktree.".to_string());
+                return Err("No git remote configured for this worktree.".into());
             }
         }
     };
 
     if git_remote_branch_exists(&parent_root, &remote_name, &new_branch).await? {
-        return Err("Remote branch already exists.".to_string());
+        return Err("Remote branch already exists.".into());
     }
 
     if remote_for_old.is_some() {
@@ -998,10 +1318,11 @@ pub(crate) async fn update_workspace_settings_core<FApplySettings, FSpawn, FutSp
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     app_settings: &Mutex<AppSettings>,
+    data_dir: &Path,
     storage_path: &PathBuf,
     apply_settings_update: FApplySettings,
     spawn_session: FSpawn,
-) -> Result<WorkspaceInfo, String>
+) -> Result<WorkspaceInfo, CommandError>
 where
     FApplySettings: Fn(
         &mut HashMap<String, WorkspaceEntry>,
@@ -1009,9 +1330,18 @@ where
         WorkspaceSettings,
     ) -> Result<WorkspaceEntry, String>,
     FSpawn: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> FutSpawn,
-    FutSpawn: Future<Output = Result<Arc<WorkspaceSession>, String>>,
+    FutSpawn: Future<Output = Result<Arc<WorkspaceSession>, CommandError>>,
 {
     settings.worktree_setup_script = normalize_setup_script(settings.worktree_setup_script);
+    if let Some(params) = settings.sampling_params.as_ref() {
+        validate_sampling_params(params)?;
+    }
+    if let Some(redaction) = settings.redaction.as_ref() {
+        Redactor::from_settings(redaction)?;
+    }
+    if let Some(template) = settings.run_kickoff_template.as_deref() {
+        validate_kickoff_template(template)?;
+    }
 
     let (
         previous_entry,
@@ -1026,7 +1356,7 @@ where
         let previous_entry = workspaces
             .get(&id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?;
+            .ok_or_else(CommandError::workspace_not_found)?;
         let previous_micode_home = previous_entry.settings.agent_home.clone();
         let previous_micode_args = previous_entry.settings.agent_args.clone();
         let previous_worktree_setup_script = previous_entry.settings.worktree_setup_script.clone();
@@ -1052,12 +1382,32 @@ where
         )
     };
 
-    let micode_home_changed = previous_micode_home != entry_snapshot.settings.agent_home;
-    let micode_args_changed = previous_micode_args != entry_snapshot.settings.agent_args;
+    let micode_home_changed = previous_micode_home != entry_snapshot.settings.agent_home
+        || previous_entry.settings.isolated_agent_home
+            != entry_snapshot.settings.isolated_agent_home;
+    // The model is passed as `--model`, so switching it restarts the agent like an args change.
+    let model_changed =
+        previous_entry.settings.preferred_model != entry_snapshot.settings.preferred_model;
+    let micode_args_changed =
+        previous_micode_args != entry_snapshot.settings.agent_args || model_changed;
     let worktree_setup_script_changed =
         previous_worktree_setup_script != entry_snapshot.settings.worktree_setup_script;
-    let connected = sessions.lock().await.contains_key(&id);
+    let connected = match sessions.lock().await.get(&id) {
+        Some(session) => {
+            session.set_redaction_settings(entry_snapshot.settings.redaction.clone());
+            session.set_audit_settings(entry_snapshot.settings.audit.clone());
+            session.set_prompt_timeout_secs(entry_snapshot.settings.prompt_timeout_secs);
+            true
+        }
+        None => false,
+    };
     if connected && (micode_home_changed || micode_args_changed) {
+        if model_changed {
+            // Threads start over on the new model instead of resuming their old sessions.
+            if let Some(session) = sessions.lock().await.get(&id).cloned() {
+                session.invalidate_all_thread_sessions().await;
+            }
+        }
         let rollback_entry = previous_entry.clone();
         let (default_bin, agent_args) = {
             let settings = app_settings.lock().await;
@@ -1070,7 +1420,8 @@ where
                 ),
             )
         };
-        let agent_home = resolve_workspace_micode_home(&entry_snapshot, parent_entry.as_ref());
+        let agent_home =
+            resolve_workspace_micode_home(&entry_snapshot, parent_entry.as_ref(), data_dir);
         let new_session = match spawn_session(
             entry_snapshot.clone(),
             default_bin,
@@ -1083,7 +1434,7 @@ where
             Err(error) => {
                 let mut workspaces = workspaces.lock().await;
                 workspaces.insert(rollback_entry.id.clone(), rollback_entry);
-                return Err(error);
+                return Err(error.into());
             }
         };
         if let Some(old_session) = sessions
@@ -1091,8 +1442,7 @@ where
             .await
             .insert(entry_snapshot.id.clone(), new_session)
         {
-            let mut child = old_session.child.lock().await;
-            let _ = child.kill().await;
+            old_session.kill().await;
         }
     }
     if micode_home_changed || micode_args_changed {
@@ -1103,8 +1453,10 @@ where
             if !connected {
                 continue;
             }
-            let previous_child_home = resolve_workspace_micode_home(child, Some(&previous_entry));
-            let next_child_home = resolve_workspace_micode_home(child, Some(&entry_snapshot));
+            let previous_child_home =
+                resolve_workspace_micode_home(child, Some(&previous_entry), data_dir);
+            let next_child_home =
+                resolve_workspace_micode_home(child, Some(&entry_snapshot), data_dir);
             let previous_child_args = resolve_workspace_micode_args(
                 child,
                 Some(&previous_entry),
@@ -1136,8 +1488,7 @@ where
                 }
             };
             if let Some(old_session) = sessions.lock().await.insert(child.id.clone(), new_session) {
-                let mut child = old_session.child.lock().await;
-                let _ = child.kill().await;
+                old_session.kill().await;
             }
         }
     }
@@ -1171,16 +1522,39 @@ where
         parent_id: entry_snapshot.parent_id,
         worktree: entry_snapshot.worktree,
         settings: entry_snapshot.settings,
+        config_stale: false,
+        runtime: None,
+        bootstrap_warnings: Vec::new(),
     })
 }
 
+/// Previews `text` as it would be sent from the workspace. `settings` overrides the saved
+/// redaction settings so unsaved edits can be tried out.
+pub(crate) async fn test_redaction_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    text: &str,
+    settings: Option<RedactionSettings>,
+) -> Result<Redacted, CommandError> {
+    let saved = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .get(workspace_id)
+            .ok_or_else(CommandError::workspace_not_found)?
+            .settings
+            .redaction
+            .clone()
+    };
+    Ok(redact_text(settings.or(saved).as_ref(), text)?)
+}
+
 pub(crate) async fn update_workspace_micode_bin_core(
     id: String,
     agent_bin: Option<String>,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     storage_path: &PathBuf,
-) -> Result<WorkspaceInfo, String> {
+) -> Result<WorkspaceInfo, CommandError> {
     let (entry_snapshot, list) = {
         let mut workspaces = workspaces.lock().await;
         let entry_snapshot = match workspaces.get_mut(&id) {
@@ -1188,37 +1562,694 @@ pub(crate) async fn update_workspace_micode_bin_core(
                 entry.agent_bin = agent_bin.clone();
                 entry.clone()
             }
-            None => return Err("workspace not found".to_string()),
+            None => return Err(CommandError::workspace_not_found()),
         };
         let list: Vec<_> = workspaces.values().cloned().collect();
         (entry_snapshot, list)
     };
     write_workspaces(storage_path, &list)?;
 
-    let connected = sessions.lock().await.contains_key(&id);
+    let session = sessions.lock().await.get(&id).cloned();
     Ok(WorkspaceInfo {
         id: entry_snapshot.id,
         name: entry_snapshot.name,
         path: entry_snapshot.path,
         agent_bin: entry_snapshot.agent_bin,
-        connected,
+        connected: session.is_some(),
         kind: entry_snapshot.kind,
         parent_id: entry_snapshot.parent_id,
         worktree: entry_snapshot.worktree,
         settings: entry_snapshot.settings,
+        config_stale: session
+            .map(|session| session.is_config_stale())
+            .unwrap_or(false),
+        runtime: None,
+        bootstrap_warnings: Vec::new(),
     })
 }
 
+pub(crate) fn expected_launch_config(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+    settings: &AppSettings,
+) -> SessionLaunchConfig {
+    SessionLaunchConfig::resolve(
+        entry,
+        settings.agent_bin.clone(),
+        resolve_workspace_micode_args(entry, parent_entry, Some(settings)),
+    )
+}
+
+/// Returns ids of live sessions whose launch config differs from what `settings` would produce.
+#[allow(dead_code)]
+pub(crate) async fn find_stale_sessions_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    settings: &AppSettings,
+) -> Vec<String> {
+    let workspaces = workspaces.lock().await.clone();
+    let sessions = sessions.lock().await;
+    let mut stale = sessions
+        .iter()
+        .filter(|(id, session)| {
+            let Some(entry) = workspaces.get(*id) else {
+                return false;
+            };
+            let parent_entry = entry
+                .parent_id
+                .as_ref()
+                .and_then(|parent_id| workspaces.get(parent_id));
+            session.launch_config != expected_launch_config(entry, parent_entry, settings)
+        })
+        .map(|(id, _)| id.clone())
+        .collect::<Vec<_>>();
+    stale.sort();
+    stale
+}
+
+/// Re-evaluates the stale flag on every live session and returns one
+/// `workspace/configStale` event per workspace whose flag changed.
+pub(crate) async fn refresh_stale_sessions_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    app_settings: &Mutex<AppSettings>,
+) -> Vec<AppServerEvent> {
+    let settings = app_settings.lock().await.clone();
+    let workspaces = workspaces.lock().await.clone();
+    let sessions = sessions.lock().await.clone();
+    let mut changed = Vec::new();
+    for (id, session) in &sessions {
+        let Some(entry) = workspaces.get(id) else {
+            continue;
+        };
+        let parent_entry = entry
+            .parent_id
+            .as_ref()
+            .and_then(|parent_id| workspaces.get(parent_id));
+        let expected = expected_launch_config(entry, parent_entry, &settings);
+        if session.refresh_config_stale(&expected) {
+            changed.push(id.clone());
+        }
+    }
+    if changed.is_empty() {
+        return Vec::new();
+    }
+    let mut stale_ids = sessions
+        .iter()
+        .filter(|(_, session)| session.is_config_stale())
+        .map(|(id, _)| id.clone())
+        .collect::<Vec<_>>();
+    stale_ids.sort();
+    changed.sort();
+    changed
+        .into_iter()
+        .map(|workspace_id| AppServerEvent {
+            message: json!({
+                "method": event_methods::WORKSPACE_CONFIG_STALE,
+                "params": {
+                    "workspaceId": workspace_id,
+                    "stale": stale_ids.contains(&workspace_id),
+                    "staleWorkspaceIds": stale_ids,
+                }
+            }),
+            workspace_id,
+        })
+        .collect()
+}
+
+/// Resolves the current settings for `workspace_id` and spawns a session with them, without
+/// touching the one that is running.
+async fn spawn_replacement_session<F, Fut>(
+    workspace_id: &str,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    data_dir: &Path,
+    spawn_session: F,
+) -> Result<(WorkspaceEntry, Arc<WorkspaceSession>), String>
+where
+    F: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> Fut,
+    Fut: Future<Output = Result<Arc<WorkspaceSession>, CommandError>>,
+{
+    let (entry, parent_entry) = resolve_entry_and_parent(workspaces, workspace_id).await?;
+    let (default_bin, agent_args) = {
+        let settings = app_settings.lock().await;
+        (
+            settings.agent_bin.clone(),
+            resolve_workspace_micode_args(&entry, parent_entry.as_ref(), Some(&settings)),
+        )
+    };
+    let agent_home = resolve_workspace_micode_home(&entry, parent_entry.as_ref(), data_dir);
+    let session = spawn_session(entry.clone(), default_bin, agent_args, agent_home).await?;
+    Ok((entry, session))
+}
+
+fn restarted_workspace_info(entry: WorkspaceEntry) -> WorkspaceInfo {
+    WorkspaceInfo {
+        id: entry.id,
+        name: entry.name,
+        path: entry.path,
+        agent_bin: entry.agent_bin,
+        connected: true,
+        kind: entry.kind,
+        parent_id: entry.parent_id,
+        worktree: entry.worktree,
+        settings: entry.settings,
+        config_stale: false,
+        runtime: None,
+        bootstrap_warnings: Vec::new(),
+    }
+}
+
+/// Respawns a workspace session with the current settings, killing the running one
+/// whatever it is doing.
+async fn respawn_session<F, Fut>(
+    workspace_id: &str,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    app_settings: &Mutex<AppSettings>,
+    data_dir: &Path,
+    spawn_session: F,
+) -> Result<WorkspaceInfo, String>
+where
+    F: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> Fut,
+    Fut: Future<Output = Result<Arc<WorkspaceSession>, CommandError>>,
+{
+    let (entry, new_session) = spawn_replacement_session(
+        workspace_id,
+        workspaces,
+        app_settings,
+        data_dir,
+        spawn_session,
+    )
+    .await?;
+    if let Some(old_session) = sessions.lock().await.insert(entry.id.clone(), new_session) {
+        old_session.drop_pending_approvals().await;
+        old_session.kill().await;
+    }
+    Ok(restarted_workspace_info(entry))
+}
+
+/// Respawns a workspace session with the current settings. Without `force` a session
+/// running turns is left alone and `None` returned; callers then run
+/// `drain_and_restart_session_core` in its own task. The turns are checked again under the
+/// sessions lock right before the swap, so a turn started while the new agent spawned
+/// is not killed either.
+pub(crate) async fn restart_workspace_session_core<F, Fut>(
+    workspace_id: String,
+    force: bool,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    app_settings: &Mutex<AppSettings>,
+    data_dir: &Path,
+    spawn_session: F,
+) -> Result<Option<WorkspaceInfo>, String>
+where
+    F: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> Fut,
+    Fut: Future<Output = Result<Arc<WorkspaceSession>, CommandError>>,
+{
+    if force {
+        return respawn_session(
+            &workspace_id,
+            workspaces,
+            sessions,
+            app_settings,
+            data_dir,
+            spawn_session,
+        )
+        .await
+        .map(Some);
+    }
+    let existing = sessions.lock().await.get(&workspace_id).cloned();
+    if let Some(session) = existing {
+        if session.has_active_turns().await {
+            return Ok(None);
+        }
+    }
+
+    let (entry, new_session) = spawn_replacement_session(
+        &workspace_id,
+        workspaces,
+        app_settings,
+        data_dir,
+        spawn_session,
+    )
+    .await?;
+    let mut sessions = sessions.lock().await;
+    let turn_started = match sessions.get(&entry.id) {
+        Some(old_session) => old_session.has_active_turns().await,
+        None => false,
+    };
+    if turn_started {
+        drop(sessions);
+        new_session.kill().await;
+        return Ok(None);
+    }
+    if let Some(old_session) = sessions.insert(entry.id.clone(), new_session) {
+        old_session.drop_pending_approvals().await;
+        old_session.kill().await;
+    }
+    Ok(Some(restarted_workspace_info(entry)))
+}
+
+/// Finishes a restart `restart_workspace_session_core` deferred: emits
+/// `workspace/restartPending`, retries until the running turns are done, then emits
+/// `workspace/restarted`, or `micode/restartFailed` once `RESTART_DRAIN_TIMEOUT` passed.
+/// Stops quietly when the session was replaced or removed in the meantime.
+pub(crate) async fn drain_and_restart_session_core<F, Fut>(
+    workspace_id: String,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    app_settings: &Mutex<AppSettings>,
+    data_dir: &Path,
+    emit: impl Fn(AppServerEvent),
+    spawn_session: F,
+) where
+    F: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> Fut,
+    Fut: Future<Output = Result<Arc<WorkspaceSession>, CommandError>>,
+{
+    let Some(draining) = sessions.lock().await.get(&workspace_id).cloned() else {
+        return;
+    };
+    emit(AppServerEvent {
+        workspace_id: workspace_id.clone(),
+        message: json!({
+            "method": event_methods::WORKSPACE_RESTART_PENDING,
+            "params": {
+                "workspaceId": workspace_id,
+                "timeoutMs": RESTART_DRAIN_TIMEOUT.as_millis() as u64
+            }
+        }),
+    });
+    let started_at = Instant::now();
+    let result = loop {
+        if started_at.elapsed() >= RESTART_DRAIN_TIMEOUT {
+            break Err(
+                "Workspace still has an active turn. Wait for it to finish or force the restart."
+                    .to_string(),
+            );
+        }
+        sleep(RESTART_DRAIN_POLL_INTERVAL).await;
+        let unchanged = sessions
+            .lock()
+            .await
+            .get(&workspace_id)
+            .is_some_and(|session| Arc::ptr_eq(session, &draining));
+        if !unchanged {
+            return;
+        }
+        match restart_workspace_session_core(
+            workspace_id.clone(),
+            false,
+            workspaces,
+            sessions,
+            app_settings,
+            data_dir,
+            &spawn_session,
+        )
+        .await
+        {
+            Ok(Some(_)) => break Ok(()),
+            Ok(None) => continue,
+            Err(error) => break Err(error),
+        }
+    };
+    let message = match result {
+        Ok(()) => json!({
+            "method": event_methods::WORKSPACE_RESTARTED,
+            "params": { "workspaceId": workspace_id }
+        }),
+        Err(error) => json!({
+            "method": event_methods::MICODE_RESTART_FAILED,
+            "params": { "workspaceId": workspace_id, "error": error }
+        }),
+    };
+    emit(AppServerEvent {
+        workspace_id,
+        message,
+    });
+}
+
+/// Checks every connected session for an agent the heartbeat flagged as wedged. Returns one
+/// `micode/unresponsive` event per newly flagged workspace, plus the ids that should be restarted right away
+/// because auto-restart is enabled. A timeout of `0` disables the check.
+pub(crate) async fn detect_unresponsive_sessions_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    app_settings: &Mutex<AppSettings>,
+) -> (Vec<AppServerEvent>, Vec<String>) {
+    let (timeout_secs, auto_restart) = {
+        let settings = app_settings.lock().await;
+        (
+            settings.agent_unresponsive_timeout_secs,
+            settings.auto_restart_unresponsive_agent,
+        )
+    };
+    if timeout_secs == 0 {
+        return (Vec::new(), Vec::new());
+    }
+    let threshold = Duration::from_secs(timeout_secs);
+    let snapshot: Vec<(String, Arc<WorkspaceSession>)> = sessions
+        .lock()
+        .await
+        .iter()
+        .map(|(id, session)| (id.clone(), Arc::clone(session)))
+        .collect();
+    let mut events = Vec::new();
+    let mut restart_ids = Vec::new();
+    for (workspace_id, session) in snapshot {
+        let Some((idle, pending_requests)) = session.check_unresponsive(threshold).await else {
+            continue;
+        };
+        let now = now_ms();
+        let stuck_on = pending_requests.first().map(|info| {
+            json!({
+                "method": info.method,
+                "threadId": info.thread_id,
+                "caller": info.caller,
+                "seconds": info.age_secs(now),
+                "message": format!("stuck on {}", info.describe(now))
+            })
+        });
+        events.push(AppServerEvent {
+            workspace_id: workspace_id.clone(),
+            message: json!({
+                "method": event_methods::MICODE_UNRESPONSIVE,
+                "params": {
+                    "workspaceId": workspace_id,
+                    "idleSeconds": idle.as_secs(),
+                    "pendingRequests": pending_requests.len(),
+                    "stuckOn": stuck_on,
+                    "missedPings": session.missed_pings(),
+                    "autoRestart": auto_restart
+                }
+            }),
+        });
+        if auto_restart {
+            restart_ids.push(workspace_id);
+        }
+    }
+    (events, restart_ids)
+}
+
+/// Live details of a workspace's agent session, including the ACP requests it is still
+/// waiting on and who sent them.
+pub(crate) async fn get_session_info_core(
+    workspace_id: &str,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+) -> Result<Value, CommandError> {
+    let session = sessions
+        .lock()
+        .await
+        .get(workspace_id)
+        .cloned()
+        .ok_or_else(CommandError::workspace_not_connected)?;
+    Ok(json!({
+        "workspaceId": workspace_id,
+        "pendingRequests": session.list_pending_requests().await,
+        "activeTurns": session.has_active_turns().await,
+        "backgroundThreads": session.background_thread_count().await,
+        "idleSeconds": session.idle_for().as_secs(),
+        "unresponsive": session.is_unresponsive(),
+        "configStale": session.is_config_stale(),
+        "commandLine": session.command_line(),
+        "uptimeSeconds": session.uptime().as_secs(),
+        "isolatedAgentHome": session.isolated_home.is_some(),
+        "agentHome": session.isolated_home,
+        "resources": session
+            .resource_usage
+            .lock()
+            .map(|tracker| tracker.usage())
+            .unwrap_or_default()
+    }))
+}
+
+/// Workspaces whose agent exited without being stopped by the app, claimed for respawning.
+/// A session already being respawned by an earlier check is skipped.
+pub(crate) async fn crashed_session_ids_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+) -> Vec<String> {
+    sessions
+        .lock()
+        .await
+        .iter()
+        .filter(|(_, session)| session.has_crashed() && session.claim_respawn())
+        .map(|(id, _)| id.clone())
+        .collect()
+}
+
+async fn session_crashed(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: &str,
+) -> bool {
+    sessions
+        .lock()
+        .await
+        .get(workspace_id)
+        .is_some_and(|session| session.has_crashed())
+}
+
+/// Respawns a crashed agent, waiting `CRASH_RESPAWN_BACKOFF` before each attempt. Callers run
+/// it as its own task so the backoff does not hold up liveness checks of other workspaces.
+/// Stops early when the workspace was reconnected or removed in the meantime. After the last failure the
+/// dead session is dropped so commands report the workspace as disconnected.
+pub(crate) async fn respawn_crashed_session_core<F, Fut>(
+    workspace_id: String,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    app_settings: &Mutex<AppSettings>,
+    data_dir: &Path,
+    emit: impl Fn(AppServerEvent),
+    spawn_session: F,
+) where
+    F: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> Fut,
+    Fut: Future<Output = Result<Arc<WorkspaceSession>, CommandError>>,
+{
+    let max_attempts = CRASH_RESPAWN_BACKOFF.len();
+    let mut last_error = String::new();
+    for (index, delay) in CRASH_RESPAWN_BACKOFF.iter().enumerate() {
+        if !session_crashed(sessions, &workspace_id).await {
+            return;
+        }
+        emit(AppServerEvent {
+            workspace_id: workspace_id.clone(),
+            message: json!({
+                "method": event_methods::MICODE_RECONNECTING,
+                "params": {
+                    "workspaceId": workspace_id,
+                    "attempt": index + 1,
+                    "maxAttempts": max_attempts,
+                    "delayMs": delay.as_millis() as u64
+                }
+            }),
+        });
+        sleep(*delay).await;
+        if !session_crashed(sessions, &workspace_id).await {
+            return;
+        }
+        match respawn_session(
+            &workspace_id,
+            workspaces,
+            sessions,
+            app_settings,
+            data_dir,
+            &spawn_session,
+        )
+        .await
+        {
+            Ok(_) => return,
+            Err(error) => last_error = error,
+        }
+    }
+    {
+        let mut sessions = sessions.lock().await;
+        if sessions
+            .get(&workspace_id)
+            .is_some_and(|session| session.has_crashed())
+        {
+            sessions.remove(&workspace_id);
+        }
+    }
+    emit(AppServerEvent {
+        workspace_id: workspace_id.clone(),
+        message: json!({
+            "method": event_methods::MICODE_RECONNECT_FAILED,
+            "params": {
+                "workspaceId": workspace_id,
+                "attempts": max_attempts,
+                "error": last_error
+            }
+        }),
+    });
+}
+
+/// Heartbeat view of a session: whether the agent still answers pings and for how long
+/// it has been running.
+pub(crate) async fn micode_session_status_core(
+    workspace_id: &str,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+) -> Result<Value, CommandError> {
+    let session = sessions
+        .lock()
+        .await
+        .get(workspace_id)
+        .cloned()
+        .ok_or_else(CommandError::workspace_not_connected)?;
+    Ok(json!({
+        "workspaceId": workspace_id,
+        "health": session.health(),
+        "pid": session.pid().await,
+        "uptimeSeconds": session.uptime().as_secs(),
+        "pendingRequests": session.list_pending_requests().await.len(),
+        "missedPings": session.missed_pings(),
+        "idleSeconds": session.idle_for().as_secs(),
+    }))
+}
+
+/// Kills and respawns a session without waiting for active turns. Outstanding requests
+/// fail with "session restarted" and active turns are reported as `turn/failed`.
+pub(crate) async fn force_restart_session_core<F, Fut>(
+    workspace_id: String,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    app_settings: &Mutex<AppSettings>,
+    data_dir: &Path,
+    spawn_session: F,
+) -> Result<WorkspaceInfo, String>
+where
+    F: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> Fut,
+    Fut: Future<Output = Result<Arc<WorkspaceSession>, CommandError>>,
+{
+    let existing = sessions.lock().await.get(&workspace_id).cloned();
+    if let Some(session) = existing {
+        session.abort_in_flight(UNRESPONSIVE_AGENT_REASON).await;
+    }
+    respawn_session(
+        &workspace_id,
+        workspaces,
+        sessions,
+        app_settings,
+        data_dir,
+        spawn_session,
+    )
+    .await
+}
+
+/// Runs store maintenance for one workspace, or for every connected workspace when
+/// `workspace_id` is `None`.
+pub(crate) async fn run_store_maintenance_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: Option<&str>,
+) -> Result<Vec<StoreMaintenanceReport>, CommandError> {
+    let targets: Vec<Arc<WorkspaceSession>> = {
+        let sessions = sessions.lock().await;
+        match workspace_id {
+            Some(id) => vec![sessions
+                .get(id)
+                .cloned()
+                .ok_or_else(CommandError::workspace_not_connected)?],
+            None => sessions.values().cloned().collect(),
+        }
+    };
+    let mut reports = Vec::with_capacity(targets.len());
+    for session in targets {
+        reports.push(session.run_store_maintenance().await);
+    }
+    Ok(reports)
+}
+
+/// Automatic counterpart of `run_store_maintenance_core`: only touches workspaces whose
+/// agent has been quiet for a while and that were not maintained in the last day.
+pub(crate) async fn run_idle_store_maintenance_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    app_settings: &Mutex<AppSettings>,
+) -> Vec<StoreMaintenanceReport> {
+    if !app_settings.lock().await.auto_store_maintenance {
+        return Vec::new();
+    }
+    let snapshot: Vec<Arc<WorkspaceSession>> = sessions.lock().await.values().cloned().collect();
+    let mut reports = Vec::new();
+    for session in snapshot {
+        if session.idle_for() < STORE_MAINTENANCE_IDLE_THRESHOLD
+            || session.since_last_store_maintenance() < STORE_MAINTENANCE_MIN_INTERVAL
+        {
+            continue;
+        }
+        let report = session.run_store_maintenance().await;
+        if report.status != StoreMaintenanceStatus::Skipped {
+            reports.push(report);
+        }
+    }
+    reports
+}
+
+/// Appends maintenance reports to the activity log in `logs_dir` and builds the matching
+/// `micode/storeMaintenance` events.
+pub(crate) fn record_store_maintenance_reports(
+    logs_dir: &Path,
+    reports: &[StoreMaintenanceReport],
+) -> Vec<AppServerEvent> {
+    if reports.is_empty() {
+        return Vec::new();
+    }
+    let lines: Vec<String> = reports
+        .iter()
+        .filter_map(|report| serde_json::to_string(report).ok())
+        .collect();
+    let _ = std::fs::create_dir_all(logs_dir);
+    if let Ok(mut file) = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(logs_dir.join(STORE_MAINTENANCE_LOG_FILE))
+    {
+        for line in lines {
+            let _ = writeln!(file, "{line}");
+        }
+    }
+    reports
+        .iter()
+        .map(|report| AppServerEvent {
+            workspace_id: report.workspace_id.clone(),
+            message: json!({
+                "method": event_methods::MICODE_STORE_MAINTENANCE,
+                "params": report
+            }),
+        })
+        .collect()
+}
+
 pub(crate) async fn list_workspace_files_core<F>(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     workspace_id: &str,
     list_files: F,
-) -> Result<Vec<String>, String>
+) -> Result<Vec<String>, CommandError>
 where
     F: Fn(&PathBuf) -> Vec<String>,
 {
-    let root = resolve_workspace_root(workspaces, workspace_id).await?;
-    Ok(list_files(&root))
+    let entry = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .get(workspace_id)
+            .cloned()
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
+    let roots = workspace_roots(&entry);
+    let primary_path = roots[0].path.clone();
+    let mut files = Vec::new();
+    for root in &roots {
+        // Roots nested inside the primary root are already covered by its listing.
+        if !root.primary && root.path.starts_with(&primary_path) {
+            continue;
+        }
+        files.extend(
+            list_files(&root.path)
+                .into_iter()
+                .map(|path| root.prefixed_path(&path)),
+        );
+    }
+    if roots.len() > 1 {
+        files.sort();
+        files.dedup();
+    }
+    Ok(files)
 }
 
 pub(crate) async fn read_workspace_file_core<F, T>(
@@ -1226,12 +2257,78 @@ pub(crate) async fn read_workspace_file_core<F, T>(
     workspace_id: &str,
     path: &str,
     read_file: F,
-) -> Result<T, String>
+) -> Result<T, CommandError>
 where
     F: Fn(&PathBuf, &str) -> Result<T, String>,
 {
+    let entry = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .get(workspace_id)
+            .cloned()
+            .ok_or_else(CommandError::workspace_not_found)?
+    };
+    let (root, relative) = select_root(&entry, None, Some(path))?;
+    Ok(read_file(&root.path, relative.as_deref().unwrap_or(path))?)
+}
+
+/// Reads an artifact recorded on a turn, applying the same root/size checks as
+/// `read_workspace_file` after confirming the path was actually listed on that turn.
+pub(crate) async fn read_turn_artifact_core<F, T>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+    path: &str,
+    read_file: F,
+) -> Result<T, CommandError>
+where
+    F: Fn(&PathBuf, &str) -> Result<T, String>,
+{
+    let root = resolve_workspace_root(workspaces, workspace_id).await?;
+    let session = sessions
+        .lock()
+        .await
+        .get(workspace_id)
+        .cloned()
+        .ok_or_else(CommandError::workspace_not_connected)?;
+    session.find_turn_artifact(thread_id, turn_id, path).await?;
+    Ok(read_file(&root, path)?)
+}
+
+/// Reads the file-read audit recorded for a turn. Works without a connected session since
+/// audits live on disk under the workspace.
+pub(crate) async fn get_turn_audit_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+) -> Result<TurnAudit, String> {
+    let root = resolve_workspace_root(workspaces, workspace_id).await?;
+    read_turn_audit(&root, thread_id, turn_id)
+}
+
+/// Reads the review queue of a turn. Like audits, reviews live on disk under the workspace.
+pub(crate) async fn get_turn_review_state_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    turn_id: &str,
+) -> Result<TurnReview, String> {
     let root = resolve_workspace_root(workspaces, workspace_id).await?;
-    read_file(&root, path)
+    read_turn_review(&root, turn_id, now_ms())
+}
+
+pub(crate) async fn set_file_review_state_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    turn_id: &str,
+    path: &str,
+    state: FileReviewState,
+    note: Option<String>,
+) -> Result<TurnReview, String> {
+    let root = resolve_workspace_root(workspaces, workspace_id).await?;
+    set_file_review_state(&root, turn_id, path, state, note, now_ms())
 }
 
 fn sort_workspaces(workspaces: &mut [WorkspaceInfo]) {
@@ -1248,7 +2345,10 @@ fn sort_workspaces(workspaces: &mut [WorkspaceInfo]) {
 #[cfg(test)]
 mod tests {
     use super::copy_agents_md_from_parent_to_worktree;
+    use super::expected_launch_config;
     use super::AGENTS_MD_FILE_NAME;
+    use crate::backend::app_server::SessionLaunchConfig;
+    use crate::types::{AppSettings, WorkspaceEntry, WorkspaceKind, WorkspaceSettings};
     use uuid::Uuid;
 
     fn make_temp_dir() -> std::path::PathBuf {
@@ -1296,4 +2396,45 @@ mod tests {
         let _ = std::fs::remove_dir_all(parent);
         let _ = std::fs::remove_dir_all(worktree);
     }
+
+    #[test]
+    fn expected_launch_config_tracks_settings_and_overrides() {
+        let mut settings = AppSettings {
+            agent_bin: Some("/opt/micode/bin/micode".to_string()),
+            agent_args: Some("--verbose".to_string()),
+            ..AppSettings::default()
+        };
+        let mut entry = WorkspaceEntry {
+            id: "ws".to_string(),
+            name: "Workspace".to_string(),
+            path: "/tmp/ws".to_string(),
+            agent_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings::default(),
+            stack: None,
+        };
+
+        let launched = expected_launch_config(&entry, None, &settings);
+        assert_eq!(
+            launched,
+            SessionLaunchConfig {
+                agent_bin: Some("/opt/micode/bin/micode".to_string()),
+                agent_args: Some("--verbose".to_string()),
+            }
+        );
+
+        settings.agent_bin = Some("/usr/local/bin/micode".to_string());
+        assert_ne!(expected_launch_config(&entry, None, &settings), launched);
+
+        entry.agent_bin = Some("/opt/micode/bin/micode".to_string());
+        assert_eq!(expected_launch_config(&entry, None, &settings), launched);
+
+        entry.agent_bin = Some("   ".to_string());
+        assert_eq!(
+            expected_launch_config(&entry, None, &settings).agent_bin,
+            Some("/usr/local/bin/micode".to_string())
+        );
+    }
 }
@@ -1,21 +1,41 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tauri::{AppHandle, Manager};
-use tokio::sync::Mutex;
+use tokio::sync::{oneshot, Mutex};
 
+use crate::backend::connect_queue::ConnectQueue;
+use crate::backend::connection_state::ConnectionStates;
+use crate::backend::handshake_cache::HandshakeCache;
+use crate::backend::settings_events::SettingsRevision;
+use crate::backend::settings_json::SettingsParseErrors;
+use crate::blocking::BlockingState;
 use crate::dictation::DictationState;
-use crate::shared::micode_core::MiCodeLoginCancelState;
-use crate::storage::{read_settings, read_workspaces};
+use crate::event_subscriptions::EventSubscriptions;
+use crate::notification_inbox::{NotificationInbox, NOTIFICATIONS_FILE};
+use crate::shared::auto_run_core::AutoRunRegistry;
+use crate::shared::command_timings_core::CommandTimingsRegistry;
+use crate::shared::micode_core::{self, MiCodeLoginCancelState};
+use crate::shared::operations_core::OperationRegistry;
+use crate::shared::usage_counters_core::FeatureUsageCounters;
+use crate::shared::usage_ledger_core::UsageLedger;
+use crate::storage::{read_settings, read_workspaces, write_workspaces};
 use crate::types::{AppSettings, WorkspaceEntry};
 
 pub(crate) struct AppState {
     pub(crate) workspaces: Mutex<HashMap<String, WorkspaceEntry>>,
     pub(crate) sessions: Mutex<HashMap<String, Arc<crate::micode::WorkspaceSession>>>,
+    /// Connect attempts and last errors, see `connection_state`.
+    pub(crate) connection_states: ConnectionStates,
     pub(crate) terminal_sessions: Mutex<HashMap<String, Arc<crate::terminal::TerminalSession>>>,
     pub(crate) remote_backend: Mutex<Option<crate::remote_backend::RemoteBackend>>,
+    /// App data directory the stores and isolated agent homes live under.
+    pub(crate) data_dir: PathBuf,
     pub(crate) storage_path: PathBuf,
     pub(crate) settings_path: PathBuf,
+    /// Credentials kept out of `settings.json`, see `secrets`.
+    pub(crate) secrets_path: PathBuf,
+    pub(crate) notifications_path: PathBuf,
     pub(crate) logs_dir: PathBuf,
     pub(crate) log_session_file: String,
     pub(crate) app_session_id: String,
@@ -23,8 +43,36 @@ pub(crate) struct AppState {
     pub(crate) actor_client_user: String,
     pub(crate) actor_lark_user_token: String,
     pub(crate) app_settings: Mutex<AppSettings>,
+    /// Revision announced in `settings/updated`, see `settings_events`.
+    pub(crate) settings_revision: SettingsRevision,
     pub(crate) dictation: Mutex<DictationState>,
     pub(crate) micode_login_cancels: Mutex<HashMap<String, MiCodeLoginCancelState>>,
+    /// Remote threads opened in watch mode, keyed by `(workspace_id, thread_id)`.
+    pub(crate) watched_threads: Mutex<HashSet<(String, String)>>,
+    /// Recent doctor handshake probes, see `micode_doctor`.
+    pub(crate) handshake_cache: Mutex<HandshakeCache>,
+    /// Pending approvals that pause dictation and notifications, see `blocking`.
+    pub(crate) blocking: std::sync::Mutex<BlockingState>,
+    /// Running template generalizations keyed by request id, see `prompts`.
+    pub(crate) prompt_extraction_cancels: Mutex<HashMap<String, oneshot::Sender<()>>>,
+    /// History of dispatched notifications, see `notification_inbox`.
+    pub(crate) notification_inbox: Mutex<NotificationInbox>,
+    /// Workspaces the webview renders, see `event_subscriptions`.
+    pub(crate) event_subscriptions: std::sync::Mutex<EventSubscriptions>,
+    /// Cancellable long-running commands, see `operations`.
+    pub(crate) operations: OperationRegistry,
+    /// Auto runs looping in this process, see `auto_run_core`.
+    pub(crate) auto_runs: AutoRunRegistry,
+    /// MiCode settings files that failed to parse, see `settings_json`.
+    pub(crate) settings_parse_errors: SettingsParseErrors,
+    /// Caps concurrent agent spawns, see `connect_queue`.
+    pub(crate) connect_queue: ConnectQueue,
+    /// Command durations and the slow-call log, see `command_timings_core`.
+    pub(crate) command_timings: CommandTimingsRegistry,
+    /// Turn-end token usage, see `usage_ledger_core`.
+    pub(crate) usage_ledger: UsageLedger,
+    /// Opt-in feature-usage counters, see `usage_counters_core`.
+    pub(crate) usage_counters: FeatureUsageCounters,
 }
 
 impl AppState {
@@ -35,21 +83,41 @@ impl AppState {
             .unwrap_or_else(|_| std::env::current_dir().unwrap_or_else(|_| ".".into()));
         let storage_path = data_dir.join("workspaces.json");
         let settings_path = data_dir.join("settings.json");
+        let secrets_path = data_dir.join("secrets.json");
+        let notifications_path = data_dir.join(NOTIFICATIONS_FILE);
         let logs_dir = crate::debug_logs::resolve_logs_dir(&data_dir);
         let log_session_file = crate::debug_logs::build_log_session_file_name();
         let app_session_id = crate::debug_logs::build_app_session_id();
         let (actor_client_user, actor_lark_user_token) =
             crate::debug_logs::resolve_actor_identity();
         let actor_id = actor_client_user.clone();
-        let workspaces = read_workspaces(&storage_path).unwrap_or_default();
+        let settings_parse_errors = SettingsParseErrors::default();
+        let mut workspaces = read_workspaces(&storage_path).unwrap_or_default();
+        if micode_core::migrate_workspace_preferred_models(
+            &mut workspaces,
+            &data_dir,
+            &settings_parse_errors,
+        ) {
+            let list: Vec<WorkspaceEntry> = workspaces.values().cloned().collect();
+            let _ = write_workspaces(&storage_path, &list);
+        }
         let app_settings = read_settings(&settings_path).unwrap_or_default();
+        let usage_counters = FeatureUsageCounters::new();
+        usage_counters.configure(Some(&data_dir), &app_settings, &workspaces);
+        let notification_inbox = NotificationInbox::load(&notifications_path);
+        let connect_queue = ConnectQueue::new(app_settings.max_concurrent_connects);
+        let command_timings =
+            CommandTimingsRegistry::new(Some(&logs_dir), app_settings.slow_command_threshold_ms);
         Self {
             workspaces: Mutex::new(workspaces),
             sessions: Mutex::new(HashMap::new()),
+            connection_states: ConnectionStates::default(),
             terminal_sessions: Mutex::new(HashMap::new()),
             remote_backend: Mutex::new(None),
             storage_path,
             settings_path,
+            secrets_path,
+            notifications_path,
             logs_dir,
             log_session_file,
             app_session_id,
@@ -57,8 +125,23 @@ impl AppState {
             actor_client_user,
             actor_lark_user_token,
             app_settings: Mutex::new(app_settings),
+            settings_revision: SettingsRevision::default(),
             dictation: Mutex::new(DictationState::default()),
             micode_login_cancels: Mutex::new(HashMap::new()),
+            watched_threads: Mutex::new(HashSet::new()),
+            handshake_cache: Mutex::new(HandshakeCache::default()),
+            blocking: std::sync::Mutex::new(BlockingState::default()),
+            prompt_extraction_cancels: Mutex::new(HashMap::new()),
+            notification_inbox: Mutex::new(notification_inbox),
+            event_subscriptions: std::sync::Mutex::new(EventSubscriptions::default()),
+            operations: OperationRegistry::default(),
+            auto_runs: AutoRunRegistry::default(),
+            settings_parse_errors,
+            connect_queue,
+            command_timings,
+            usage_ledger: UsageLedger::new(Some(&data_dir)),
+            usage_counters,
+            data_dir,
         }
     }
 }
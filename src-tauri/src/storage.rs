@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::types::{AppSettings, WorkspaceEntry};
 
@@ -50,6 +50,28 @@ pub(crate) fn write_settings(path: &PathBuf, settings: &AppSettings) -> Result<(
     std::fs::write(path, data).map_err(|e| e.to_string())
 }
 
+/// Writes next to `path` first and renames over it, so an interrupted write never leaves
+/// a truncated file behind.
+pub(crate) fn write_file_atomically(path: &Path, contents: &str) -> Result<(), String> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Invalid path: {}", path.display()))?;
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty());
+    if let Some(parent) = parent {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    std::fs::write(&tmp_path, contents).map_err(|err| err.to_string())?;
+    std::fs::rename(&tmp_path, path).map_err(|err| {
+        let _ = std::fs::remove_file(&tmp_path);
+        err.to_string()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::{read_settings, read_workspaces, write_workspaces};
@@ -78,6 +100,7 @@ mod tests {
             parent_id: None,
             worktree: None,
             settings: settings.clone(),
+            stack: None,
         };
 
         write_workspaces(&path, &[entry]).expect("write workspaces");
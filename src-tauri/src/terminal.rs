@@ -7,8 +7,11 @@ use serde::Serialize;
 use tauri::{AppHandle, State};
 use tokio::sync::Mutex;
 
-use crate::backend::events::{EventSink, TerminalExit, TerminalOutput};
+use crate::backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
 use crate::event_sink::TauriEventSink;
+use crate::shared::resource_monitor_core::{
+    resource_warning_event, sample_processes, ResourceThresholds, ResourceTracker,
+};
 use crate::state::AppState;
 
 pub(crate) struct TerminalSession {
@@ -16,6 +19,8 @@ pub(crate) struct TerminalSession {
     pub(crate) master: Mutex<Box<dyn portable_pty::MasterPty + Send>>,
     pub(crate) writer: Mutex<Box<dyn Write + Send>>,
     pub(crate) child: Mutex<Box<dyn portable_pty::Child + Send>>,
+    pub(crate) pid: Option<u32>,
+    pub(crate) resource_usage: std::sync::Mutex<ResourceTracker>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -213,6 +218,7 @@ pub(crate) async fn terminal_open(
         .slave
         .spawn_command(cmd)
         .map_err(|e| format!("Failed to spawn shell: {e}"))?;
+    let pid = child.process_id();
     let reader = pair
         .master
         .try_clone_reader()
@@ -227,6 +233,8 @@ pub(crate) async fn terminal_open(
         master: Mutex::new(pair.master),
         writer: Mutex::new(writer),
         child: Mutex::new(child),
+        pid,
+        resource_usage: std::sync::Mutex::new(ResourceTracker::default()),
     });
     let session_id = session.id.clone();
 
@@ -335,3 +343,68 @@ pub(crate) async fn terminal_close(
     .await;
     Ok(())
 }
+
+/// Samples every open terminal shell, with the commands it spawned, and returns warning
+/// events for runaway ones.
+pub(crate) async fn sample_terminal_resources(
+    state: &AppState,
+    thresholds: &ResourceThresholds,
+) -> Vec<AppServerEvent> {
+    let sessions: Vec<(String, Arc<TerminalSession>)> = state
+        .terminal_sessions
+        .lock()
+        .await
+        .iter()
+        .map(|(key, session)| (key.clone(), Arc::clone(session)))
+        .collect();
+    let pids: Vec<u32> = sessions
+        .iter()
+        .filter_map(|(_, session)| session.pid)
+        .collect();
+    let samples = sample_processes(&pids).await;
+    let now = tokio::time::Instant::now();
+    let mut events = Vec::new();
+    for (key, session) in sessions {
+        let Some((pid, sample)) = session
+            .pid
+            .and_then(|pid| samples.get(&pid).map(|sample| (pid, *sample)))
+        else {
+            continue;
+        };
+        let Ok(mut tracker) = session.resource_usage.lock() else {
+            continue;
+        };
+        if let Some(kind) = tracker.record(pid, sample, now, thresholds) {
+            let workspace_id = key.split_once(':').map(|(id, _)| id).unwrap_or(&key);
+            events.push(resource_warning_event(
+                workspace_id,
+                Some(&session.id),
+                kind,
+                &tracker.usage(),
+                thresholds,
+            ));
+        }
+    }
+    events
+}
+
+/// Current and peak usage of each open terminal shell.
+pub(crate) async fn terminal_resource_usage(state: &AppState) -> Vec<serde_json::Value> {
+    let sessions = state.terminal_sessions.lock().await;
+    sessions
+        .iter()
+        .map(|(key, session)| {
+            let workspace_id = key.split_once(':').map(|(id, _)| id).unwrap_or(key);
+            let usage = session
+                .resource_usage
+                .lock()
+                .map(|tracker| tracker.usage())
+                .unwrap_or_default();
+            serde_json::json!({
+                "workspaceId": workspace_id,
+                "terminalId": session.id,
+                "usage": usage
+            })
+        })
+        .collect()
+}
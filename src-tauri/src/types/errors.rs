@@ -0,0 +1,199 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Returned by the shared cores when the workspace has no live agent session.
+pub(crate) const WORKSPACE_NOT_CONNECTED: &str = "workspace not connected";
+pub(crate) const WORKSPACE_NOT_FOUND: &str = "workspace not found";
+/// Prefix of the error `thread/archive` returns for a pinned thread archived without `force`.
+pub(crate) const THREAD_PINNED: &str = "thread is pinned";
+/// Prefix of the error for a slash command the agent has not advertised.
+pub(crate) const COMMAND_NOT_AVAILABLE: &str = "slash command not available";
+
+/// Stable identifiers the frontend branches on; the message is for display only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ErrorCode {
+    WorkspaceNotConnected,
+    WorkspaceNotFound,
+    SessionNotFound,
+    AcpTimeout,
+    GitCommandFailed,
+    /// Another git operation holds the repository; `details.heldBy` names it.
+    RepositoryBusy,
+    /// Git would commit as someone else; `details` has the `expected` and `actual` identity.
+    IdentityMismatch,
+    MicodeBinMissing,
+    InvalidAgentArgs,
+    ThreadPinned,
+    CommandNotAvailable,
+    /// The thread is running a turn and the message was not queued behind it.
+    TurnInProgress,
+    /// Anything the backend has no dedicated code for yet.
+    CommandFailed,
+}
+
+impl ErrorCode {
+    /// Whether the same call can succeed once the app recovers on its own (reconnects
+    /// the workspace, or the agent answers again).
+    fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            Self::WorkspaceNotConnected | Self::AcpTimeout | Self::RepositoryBusy
+        )
+    }
+}
+
+/// Error payload of the micode, workspace and git commands.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CommandError {
+    pub(crate) code: ErrorCode,
+    pub(crate) message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) details: Option<Value>,
+    pub(crate) retryable: bool,
+}
+
+impl CommandError {
+    pub(crate) fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+            retryable: code.is_retryable(),
+        }
+    }
+
+    pub(crate) fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub(crate) fn workspace_not_connected() -> Self {
+        Self::new(ErrorCode::WorkspaceNotConnected, WORKSPACE_NOT_CONNECTED)
+    }
+
+    pub(crate) fn workspace_not_found() -> Self {
+        Self::new(ErrorCode::WorkspaceNotFound, WORKSPACE_NOT_FOUND)
+    }
+
+    pub(crate) fn thread_not_found(thread_id: &str) -> Self {
+        Self::new(
+            ErrorCode::SessionNotFound,
+            format!("thread not found: {thread_id}"),
+        )
+        .with_details(json!({ "threadId": thread_id }))
+    }
+
+    pub(crate) fn turn_in_progress(thread_id: &str) -> Self {
+        Self::new(
+            ErrorCode::TurnInProgress,
+            "A turn is already running in this thread.",
+        )
+        .with_details(json!({ "threadId": thread_id }))
+    }
+
+    /// A git failure reported as a plain message.
+    pub(crate) fn git(message: String) -> Self {
+        Self::new(ErrorCode::GitCommandFailed, message)
+    }
+}
+
+/// Plain messages have no dedicated code; errors with one are built with `CommandError::new`
+/// where they happen.
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorCode::CommandFailed, message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        Self::from(message.to_string())
+    }
+}
+
+/// Callers that still report plain strings, such as the daemon, keep the message.
+impl From<CommandError> for String {
+    fn from(error: CommandError) -> Self {
+        error.message
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_with_stable_code_strings() {
+        let cases = [
+            (ErrorCode::WorkspaceNotConnected, "workspaceNotConnected"),
+            (ErrorCode::WorkspaceNotFound, "workspaceNotFound"),
+            (ErrorCode::SessionNotFound, "sessionNotFound"),
+            (ErrorCode::AcpTimeout, "acpTimeout"),
+            (ErrorCode::GitCommandFailed, "gitCommandFailed"),
+            (ErrorCode::RepositoryBusy, "repositoryBusy"),
+            (ErrorCode::IdentityMismatch, "identityMismatch"),
+            (ErrorCode::MicodeBinMissing, "micodeBinMissing"),
+            (ErrorCode::InvalidAgentArgs, "invalidAgentArgs"),
+            (ErrorCode::ThreadPinned, "threadPinned"),
+            (ErrorCode::CommandNotAvailable, "commandNotAvailable"),
+            (ErrorCode::TurnInProgress, "turnInProgress"),
+            (ErrorCode::CommandFailed, "commandFailed"),
+        ];
+        for (code, expected) in cases {
+            assert_eq!(serde_json::to_value(code).unwrap(), json!(expected));
+        }
+
+        let error = CommandError::workspace_not_connected();
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "workspaceNotConnected",
+                "message": "workspace not connected",
+                "retryable": true,
+            })
+        );
+        let error = CommandError::thread_not_found("t-1");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "sessionNotFound",
+                "message": "thread not found: t-1",
+                "details": { "threadId": "t-1" },
+                "retryable": false,
+            })
+        );
+    }
+
+    #[test]
+    fn plain_messages_never_get_a_dedicated_code() {
+        let code = |message: &str| CommandError::from(message).code;
+        assert_eq!(code(WORKSPACE_NOT_CONNECTED), ErrorCode::CommandFailed);
+        assert_eq!(
+            code("turn/start timed out waiting for MiCode response after prompt"),
+            ErrorCode::CommandFailed
+        );
+        assert!(!CommandError::from("disk full").retryable);
+        assert_eq!(
+            CommandError::git(WORKSPACE_NOT_FOUND.to_string()).code,
+            ErrorCode::GitCommandFailed
+        );
+        assert_eq!(
+            CommandError::workspace_not_found().code,
+            ErrorCode::WorkspaceNotFound
+        );
+        assert_eq!(
+            String::from(CommandError::workspace_not_connected()),
+            WORKSPACE_NOT_CONNECTED
+        );
+    }
+}
@@ -1,3 +1,7 @@
+pub(crate) mod errors;
+
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -28,6 +32,9 @@ pub(crate) struct GitFileDiff {
     pub(crate) old_image_mime: Option<String>,
     #[serde(rename = "newImageMime")]
     pub(crate) new_image_mime: Option<String>,
+    /// Root the file belongs to in multi-root workspaces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) root: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -156,6 +163,9 @@ pub(crate) struct LocalUsageDay {
     pub(crate) agent_time_ms: i64,
     #[serde(default)]
     pub(crate) agent_runs: i64,
+    /// Estimated input tokens background helpers skipped by reusing the primer session.
+    #[serde(default)]
+    pub(crate) primer_tokens_saved: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -167,6 +177,10 @@ pub(crate) struct LocalUsageTotals {
     pub(crate) cache_hit_rate_percent: f64,
     pub(crate) peak_day: Option<String>,
     pub(crate) peak_day_tokens: i64,
+    #[serde(default)]
+    pub(crate) primer_tokens_saved: i64,
+    #[serde(default)]
+    pub(crate) primer_reuses: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -177,6 +191,44 @@ pub(crate) struct LocalUsageModel {
     pub(crate) share_percent: f64,
 }
 
+/// What a model costs per million tokens; used to estimate turn cost in usage exports.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ModelPrice {
+    pub(crate) model: String,
+    pub(crate) input_per_million: f64,
+    /// Price of cache hits; the input price when unset.
+    #[serde(default)]
+    pub(crate) cached_input_per_million: Option<f64>,
+    pub(crate) output_per_million: f64,
+}
+
+impl ModelPrice {
+    /// The price listed for `model`, matched case-insensitively.
+    pub(crate) fn find<'a>(prices: &'a [ModelPrice], model: &str) -> Option<&'a ModelPrice> {
+        let model = model.trim();
+        prices
+            .iter()
+            .find(|price| price.model.trim().eq_ignore_ascii_case(model))
+    }
+
+    /// Estimated USD cost; `cached` is the part of `input` served from cache.
+    pub(crate) fn estimate_cost(&self, input: i64, cached: i64, output: i64) -> f64 {
+        let cached = cached.min(input) as f64;
+        let uncached = (input as f64) - cached;
+        let cached_price = self.cached_input_per_million.unwrap_or(self.input_per_million);
+        (uncached * self.input_per_million + cached * cached_price + (output as f64) * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UsageCsvExport {
+    pub(crate) path: String,
+    pub(crate) row_count: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct LocalUsageSnapshot {
@@ -208,6 +260,61 @@ pub(crate) struct WorkspaceEntry {
     pub(crate) worktree: Option<WorktreeInfo>,
     #[serde(default)]
     pub(crate) settings: WorkspaceSettings,
+    /// Stack detected when the workspace is added or connected; never persisted.
+    #[serde(skip)]
+    pub(crate) stack: Option<WorkspaceStack>,
+}
+
+/// Languages and tooling derived from a workspace's manifests, lockfiles and CI configs.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorkspaceStack {
+    #[serde(default)]
+    pub(crate) languages: Vec<String>,
+    #[serde(default)]
+    pub(crate) frameworks: Vec<String>,
+    #[serde(default)]
+    pub(crate) package_manager: Option<String>,
+    #[serde(default)]
+    pub(crate) test_command: Option<String>,
+    #[serde(default)]
+    pub(crate) ci: Vec<String>,
+    /// Workspace-relative files the descriptor was derived from.
+    #[serde(default)]
+    pub(crate) sources: Vec<String>,
+}
+
+impl WorkspaceStack {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.languages.is_empty()
+            && self.frameworks.is_empty()
+            && self.package_manager.is_none()
+            && self.test_command.is_none()
+            && self.ci.is_empty()
+    }
+}
+
+/// Manual corrections to the detected stack; every field set here replaces detection.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorkspaceStackOverride {
+    #[serde(default)]
+    pub(crate) languages: Option<Vec<String>>,
+    #[serde(default)]
+    pub(crate) frameworks: Option<Vec<String>>,
+    #[serde(default)]
+    pub(crate) package_manager: Option<String>,
+    #[serde(default)]
+    pub(crate) test_command: Option<String>,
+}
+
+/// Which run kickoff template produced the first message of a thread.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RunKickoffTemplateRef {
+    /// `workspace:<id>`, `global` or `default`.
+    pub(crate) template_id: String,
+    pub(crate) template_hash: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -226,6 +333,59 @@ pub(crate) struct WorkspaceInfo {
     pub(crate) worktree: Option<WorktreeInfo>,
     #[serde(default)]
     pub(crate) settings: WorkspaceSettings,
+    #[serde(default, rename = "configStale")]
+    pub(crate) config_stale: bool,
+    /// Only filled when `list_workspaces` is asked for runtime state.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) runtime: Option<WorkspaceRuntimeInfo>,
+    /// Findings of the bootstrap check, only filled by `add_workspace`.
+    #[serde(
+        default,
+        rename = "bootstrapWarnings",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub(crate) bootstrap_warnings: Vec<WorkspaceBootstrapWarning>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum WorkspaceBootstrapWarningKind {
+    NotGitRepo,
+    InsideGitRepo,
+    NestedRepositories,
+    StateDirNotWritable,
+    CloudSynced,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorkspaceBootstrapWarning {
+    pub(crate) kind: WorkspaceBootstrapWarningKind,
+    pub(crate) message: String,
+    #[serde(default)]
+    pub(crate) suggestion: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum WorkspaceConnectionStatus {
+    Connected,
+    Connecting,
+    Disconnected,
+    Error,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorkspaceRuntimeInfo {
+    pub(crate) status: WorkspaceConnectionStatus,
+    pub(crate) last_error: Option<String>,
+    pub(crate) last_error_at: Option<u64>,
+    pub(crate) active_turns: usize,
+    /// `None` when usage data is not available, e.g. on the daemon.
+    pub(crate) turns_today: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -262,6 +422,41 @@ pub(crate) struct WorkspaceGroup {
     pub(crate) copies_folder: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub(crate) struct SamplingParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) temperature: Option<f64>,
+    #[serde(
+        default,
+        rename = "topP",
+        alias = "top_p",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) top_p: Option<f64>,
+    #[serde(
+        default,
+        rename = "maxOutputTokens",
+        alias = "max_output_tokens",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) max_output_tokens: Option<u32>,
+}
+
+impl SamplingParams {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.temperature.is_none() && self.top_p.is_none() && self.max_output_tokens.is_none()
+    }
+}
+
+/// A git author identity; unset fields are left alone when compared or written.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub(crate) struct GitIdentity {
+    #[serde(default)]
+    pub(crate) name: Option<String>,
+    #[serde(default)]
+    pub(crate) email: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub(crate) struct WorkspaceSettings {
     #[serde(default, rename = "sidebarCollapsed")]
@@ -282,6 +477,173 @@ pub(crate) struct WorkspaceSettings {
     pub(crate) launch_scripts: Option<Vec<LaunchScriptEntry>>,
     #[serde(default, rename = "worktreeSetupScript")]
     pub(crate) worktree_setup_script: Option<String>,
+    #[serde(default, rename = "defaultEditor")]
+    pub(crate) default_editor: Option<String>,
+    #[serde(default, rename = "artifactGlobs")]
+    pub(crate) artifact_globs: Option<Vec<String>>,
+    #[serde(default, rename = "samplingParams")]
+    pub(crate) sampling_params: Option<SamplingParams>,
+    /// Model the workspace's sessions run; `None` follows the MiCode settings file, or the
+    /// parent workspace for worktrees.
+    #[serde(default, rename = "preferredModel")]
+    pub(crate) preferred_model: Option<String>,
+    /// Extra roots next to the workspace path, for workspaces spanning several repos.
+    #[serde(default)]
+    pub(crate) roots: Option<Vec<String>>,
+    /// Reuse one repository-primed session for background helpers instead of a cold one.
+    #[serde(default, rename = "contextPriming")]
+    pub(crate) context_priming: bool,
+    /// Secret masking applied to prompts before they reach the agent; `None` uses defaults.
+    #[serde(default)]
+    pub(crate) redaction: Option<RedactionSettings>,
+    /// Opt-in record of the files the agent read during each turn.
+    #[serde(default)]
+    pub(crate) audit: Option<AuditSettings>,
+    /// Replaces the app-level proxy for this workspace's outbound HTTP.
+    #[serde(default)]
+    pub(crate) proxy: Option<ProxySettings>,
+    /// Overrides for the detected language/framework stack.
+    #[serde(default)]
+    pub(crate) stack: Option<WorkspaceStackOverride>,
+    /// Replaces the app-level run kickoff template for runs in this workspace and its
+    /// worktrees.
+    #[serde(default, rename = "runKickoffTemplate")]
+    pub(crate) run_kickoff_template: Option<String>,
+    /// Opts this workspace in or out of the local feature-usage counters; `None` follows
+    /// the app setting.
+    #[serde(default, rename = "usageCounters")]
+    pub(crate) usage_counters: Option<bool>,
+    /// Runs the agent with a dedicated `MICODE_HOME` under app data instead of the user's
+    /// real home; `agentHome` is ignored while set.
+    #[serde(default, rename = "isolatedAgentHome")]
+    pub(crate) isolated_agent_home: bool,
+    /// Settings file copied into the isolated home when it is first created.
+    #[serde(default, rename = "isolatedAgentHomeTemplate")]
+    pub(crate) isolated_agent_home_template: Option<String>,
+    /// Identity commits from this workspace must use; `commit_git` refuses others.
+    #[serde(default, rename = "expectedGitIdentity")]
+    pub(crate) expected_git_identity: Option<GitIdentity>,
+    /// Overrides the app-wide `promptTimeoutSecs` for this workspace; 0 disables it.
+    #[serde(default, rename = "promptTimeoutSecs")]
+    pub(crate) prompt_timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ProxyMode {
+    /// Follows `HTTPS_PROXY`/`HTTP_PROXY`/`ALL_PROXY` and `NO_PROXY`.
+    #[default]
+    System,
+    Manual,
+    /// Connects directly, ignoring the proxy environment.
+    None,
+}
+
+/// Outbound HTTP proxy. The password lives in the secrets store, never in settings.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub(crate) struct ProxySettings {
+    #[serde(default)]
+    pub(crate) mode: ProxyMode,
+    /// `http://host:port` used in `manual` mode.
+    #[serde(default)]
+    pub(crate) url: Option<String>,
+    #[serde(default)]
+    pub(crate) username: Option<String>,
+    /// Hosts reached directly, matched like `NO_PROXY` entries.
+    #[serde(default, rename = "noProxy")]
+    pub(crate) no_proxy: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct AuditSettings {
+    #[serde(default)]
+    pub(crate) enabled: bool,
+    /// Days turn audits are kept under `.micodemonitor/audit`; 0 keeps them forever.
+    #[serde(default = "default_audit_retention_days", rename = "retentionDays")]
+    pub(crate) retention_days: u32,
+}
+
+impl Default for AuditSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retention_days: default_audit_retention_days(),
+        }
+    }
+}
+
+fn default_audit_retention_days() -> u32 {
+    30
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct RedactionPattern {
+    /// Placeholder category, e.g. `awsKey` renders as `[REDACTED:awsKey]`.
+    pub(crate) category: String,
+    pub(crate) pattern: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct RedactionSettings {
+    #[serde(default = "default_redaction_enabled")]
+    pub(crate) enabled: bool,
+    #[serde(default = "default_redaction_patterns")]
+    pub(crate) patterns: Vec<RedactionPattern>,
+    /// `.env`-style assignments whose name contains one of these get their value masked.
+    #[serde(default = "default_redaction_secret_names", rename = "secretNames")]
+    pub(crate) secret_names: Vec<String>,
+    /// Lets a single message opt out of redaction with `skipRedaction`.
+    #[serde(default, rename = "allowSkip")]
+    pub(crate) allow_skip: bool,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_redaction_enabled(),
+            patterns: default_redaction_patterns(),
+            secret_names: default_redaction_secret_names(),
+            allow_skip: false,
+        }
+    }
+}
+
+fn default_redaction_enabled() -> bool {
+    true
+}
+
+fn default_redaction_patterns() -> Vec<RedactionPattern> {
+    [
+        (
+            "privateKey",
+            r"-----BEGIN [A-Z0-9 ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z0-9 ]*PRIVATE KEY-----",
+        ),
+        ("awsKey", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+        ("bearerToken", r"(?i)\bbearer\s+[A-Za-z0-9\-._~+/]{16,}=*"),
+    ]
+    .into_iter()
+    .map(|(category, pattern)| RedactionPattern {
+        category: category.to_string(),
+        pattern: pattern.to_string(),
+    })
+    .collect()
+}
+
+fn default_redaction_secret_names() -> Vec<String> {
+    [
+        "PASSWORD",
+        "PASSWD",
+        "SECRET",
+        "TOKEN",
+        "API_KEY",
+        "APIKEY",
+        "ACCESS_KEY",
+        "PRIVATE_KEY",
+        "CREDENTIALS",
+    ]
+    .into_iter()
+    .map(ToString::to_string)
+    .collect()
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -445,6 +807,12 @@ pub(crate) struct AppSettings {
         rename = "systemNotificationsEnabled"
     )]
     pub(crate) system_notifications_enabled: bool,
+    /// Days dispatched notifications stay in the inbox; 0 keeps them until the inbox is full.
+    #[serde(
+        default = "default_notification_retention_days",
+        rename = "notificationRetentionDays"
+    )]
+    pub(crate) notification_retention_days: u32,
     #[serde(
         default = "default_experimental_collab_enabled",
         rename = "experimentalCollabEnabled"
@@ -461,6 +829,10 @@ pub(crate) struct AppSettings {
         alias = "experimentalSteerEnabled"
     )]
     pub(crate) steer_enabled: bool,
+    /// Queue messages sent while their thread runs a turn instead of rejecting them with
+    /// `turnInProgress`.
+    #[serde(default = "default_queue_when_busy", rename = "queueWhenBusy")]
+    pub(crate) queue_when_busy: bool,
     #[serde(
         default = "default_unified_exec_enabled",
         rename = "unifiedExecEnabled",
@@ -533,6 +905,140 @@ pub(crate) struct AppSettings {
     pub(crate) open_app_targets: Vec<OpenAppTarget>,
     #[serde(default = "default_selected_open_app_id", rename = "selectedOpenAppId")]
     pub(crate) selected_open_app_id: String,
+    #[serde(
+        default = "default_agent_unresponsive_timeout_secs",
+        rename = "agentUnresponsiveTimeoutSecs"
+    )]
+    pub(crate) agent_unresponsive_timeout_secs: u64,
+    #[serde(default, rename = "autoRestartUnresponsiveAgent")]
+    pub(crate) auto_restart_unresponsive_agent: bool,
+    /// Sampling defaults keyed by model id; workspace and per-message values override them.
+    #[serde(default, rename = "modelSamplingParams")]
+    pub(crate) model_sampling_params: HashMap<String, SamplingParams>,
+    /// Compacts and integrity-checks thread stores once the app has been idle for a while.
+    #[serde(
+        default = "default_auto_store_maintenance",
+        rename = "autoStoreMaintenance"
+    )]
+    pub(crate) auto_store_maintenance: bool,
+    /// Samples CPU/RAM of agent and terminal children and warns about runaway ones.
+    #[serde(
+        default = "default_resource_monitoring_enabled",
+        rename = "resourceMonitoringEnabled"
+    )]
+    pub(crate) resource_monitoring_enabled: bool,
+    #[serde(
+        default = "default_resource_warning_rss_mb",
+        rename = "resourceWarningRssMb"
+    )]
+    pub(crate) resource_warning_rss_mb: u64,
+    #[serde(
+        default = "default_resource_warning_cpu_percent",
+        rename = "resourceWarningCpuPercent"
+    )]
+    pub(crate) resource_warning_cpu_percent: f64,
+    #[serde(
+        default = "default_resource_warning_sustained_secs",
+        rename = "resourceWarningSustainedSecs"
+    )]
+    pub(crate) resource_warning_sustained_secs: u64,
+    /// Commands slower than this are appended to the slow-command log; 0 disables it.
+    #[serde(
+        default = "default_slow_command_threshold_ms",
+        rename = "slowCommandThresholdMs"
+    )]
+    pub(crate) slow_command_threshold_ms: u64,
+    #[serde(default)]
+    pub(crate) proxy: ProxySettings,
+    /// First message of new runs, see `run_kickoff_core`; `None` uses the built-in one.
+    #[serde(default, rename = "runKickoffTemplate")]
+    pub(crate) run_kickoff_template: Option<String>,
+    /// Counts command, event and feature usage in a local file, see `usage_counters_core`.
+    /// Off by default; nothing is ever sent anywhere.
+    #[serde(default, rename = "usageCountersEnabled")]
+    pub(crate) usage_counters_enabled: bool,
+    /// Deleted prompts older than this are purged from the trash; 0 keeps them forever.
+    #[serde(
+        default = "default_prompt_trash_retention_days",
+        rename = "promptTrashRetentionDays"
+    )]
+    pub(crate) prompt_trash_retention_days: u32,
+    /// How many agent processes may be starting at once; further connects queue.
+    #[serde(
+        default = "default_max_concurrent_connects",
+        rename = "maxConcurrentConnects"
+    )]
+    pub(crate) max_concurrent_connects: u32,
+    /// How long a `session/prompt` may run before the turn gives up; 0 means never.
+    #[serde(default = "default_prompt_timeout_secs", rename = "promptTimeoutSecs")]
+    pub(crate) prompt_timeout_secs: u64,
+    /// Silence after which a running turn is reported with `turn/stalled`; 0 never warns.
+    #[serde(
+        default = "default_turn_stall_warning_secs",
+        rename = "turnStallWarningSecs"
+    )]
+    pub(crate) turn_stall_warning_secs: u64,
+    /// Longer silence allowed while a tool call runs; unset uses `turnStallWarningSecs`.
+    #[serde(default, rename = "toolStallWarningSecs")]
+    pub(crate) tool_stall_warning_secs: Option<u64>,
+    /// How long a tool call may run before `tool/slow` warns about it; 0 never warns.
+    #[serde(
+        default = "default_tool_slow_warning_secs",
+        rename = "toolSlowWarningSecs"
+    )]
+    pub(crate) tool_slow_warning_secs: u64,
+    /// How long an approval may wait for an answer before the agent gets the cancelled
+    /// outcome; a reminder goes out halfway. 0 waits forever.
+    #[serde(
+        default = "default_approval_timeout_secs",
+        rename = "approvalTimeoutSecs"
+    )]
+    pub(crate) approval_timeout_secs: u64,
+    /// Silence after which a background helper (commit message, run metadata) takes its
+    /// reply as finished.
+    #[serde(
+        default = "default_background_idle_timeout_ms",
+        rename = "backgroundIdleTimeoutMs"
+    )]
+    pub(crate) background_idle_timeout_ms: u64,
+    /// Longest a background helper waits for one reply.
+    #[serde(
+        default = "default_background_max_wait_secs",
+        rename = "backgroundMaxWaitSecs"
+    )]
+    pub(crate) background_max_wait_secs: u64,
+    /// Workspace diffs larger than this are summarized to per-file stats and the first
+    /// hunks of the largest files before a commit message is generated from them.
+    #[serde(
+        default = "default_commit_message_diff_max_bytes",
+        rename = "commitMessageDiffMaxBytes"
+    )]
+    pub(crate) commit_message_diff_max_bytes: usize,
+    /// Largest tool call result, in KB, kept in events and thread items; longer results
+    /// are cut with a marker. 0 keeps everything.
+    #[serde(default = "default_tool_result_max_kb", rename = "toolResultMaxKb")]
+    pub(crate) tool_result_max_kb: u32,
+    /// After a thread's first turn, asks the agent for a short title in the background
+    /// and replaces the one cut from the prompt, unless the user renamed the thread.
+    #[serde(default, rename = "autoTitleThreads")]
+    pub(crate) auto_title_threads: bool,
+    /// Saves the reasoning of each turn into the thread history, so resumed threads show it.
+    #[serde(default = "default_persist_reasoning", rename = "persistReasoning")]
+    pub(crate) persist_reasoning: bool,
+    /// Archived threads older than this are deleted for good when their workspace next
+    /// connects; 0 keeps them forever.
+    #[serde(
+        default = "default_archived_thread_retention_days",
+        rename = "archivedThreadRetentionDays"
+    )]
+    pub(crate) archived_thread_retention_days: u32,
+    /// Prices for the estimated costs of `export_usage_csv` and `usage_summary`; models
+    /// without an entry are left out of the cost.
+    #[serde(default, rename = "modelPrices")]
+    pub(crate) model_prices: Vec<ModelPrice>,
+    /// Settings revision when these settings were read; set by the backend, never loaded.
+    #[serde(default, rename = "settingsRevision", skip_deserializing)]
+    pub(crate) settings_revision: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -665,6 +1171,10 @@ fn default_system_notifications_enabled() -> bool {
     true
 }
 
+fn default_notification_retention_days() -> u32 {
+    14
+}
+
 fn default_preload_git_diffs() -> bool {
     true
 }
@@ -685,6 +1195,10 @@ fn default_steer_enabled() -> bool {
     true
 }
 
+fn default_queue_when_busy() -> bool {
+    true
+}
+
 fn default_unified_exec_enabled() -> bool {
     true
 }
@@ -782,6 +1296,82 @@ fn default_selected_open_app_id() -> String {
     "system".to_string()
 }
 
+fn default_agent_unresponsive_timeout_secs() -> u64 {
+    180
+}
+
+fn default_auto_store_maintenance() -> bool {
+    true
+}
+
+fn default_resource_monitoring_enabled() -> bool {
+    true
+}
+
+fn default_resource_warning_rss_mb() -> u64 {
+    4096
+}
+
+fn default_resource_warning_cpu_percent() -> f64 {
+    90.0
+}
+
+fn default_resource_warning_sustained_secs() -> u64 {
+    60
+}
+
+fn default_slow_command_threshold_ms() -> u64 {
+    1_000
+}
+
+fn default_prompt_trash_retention_days() -> u32 {
+    30
+}
+
+fn default_max_concurrent_connects() -> u32 {
+    3
+}
+
+fn default_prompt_timeout_secs() -> u64 {
+    6 * 60 * 60
+}
+
+fn default_turn_stall_warning_secs() -> u64 {
+    60
+}
+
+fn default_tool_slow_warning_secs() -> u64 {
+    120
+}
+
+fn default_approval_timeout_secs() -> u64 {
+    5 * 60
+}
+
+fn default_background_idle_timeout_ms() -> u64 {
+    2_000
+}
+
+fn default_background_max_wait_secs() -> u64 {
+    30
+}
+
+fn default_commit_message_diff_max_bytes() -> usize {
+    64 * 1024
+}
+
+fn default_tool_result_max_kb() -> u32 {
+    64
+}
+
+fn default_persist_reasoning() -> bool {
+    true
+}
+
+fn default_archived_thread_retention_days() -> u32 {
+    30
+}
+
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
@@ -819,11 +1409,13 @@ impl Default for AppSettings {
             code_font_size: default_code_font_size(),
             notification_sounds_enabled: true,
             system_notifications_enabled: true,
+            notification_retention_days: default_notification_retention_days(),
             preload_git_diffs: default_preload_git_diffs(),
             git_diff_ignore_whitespace_changes: default_git_diff_ignore_whitespace_changes(),
             experimental_collab_enabled: false,
             collaboration_modes_enabled: true,
             steer_enabled: true,
+            queue_when_busy: default_queue_when_busy(),
             unified_exec_enabled: true,
             experimental_apps_enabled: false,
             personality: default_personality(),
@@ -845,6 +1437,34 @@ impl Default for AppSettings {
             workspace_groups: default_workspace_groups(),
             open_app_targets: default_open_app_targets(),
             selected_open_app_id: default_selected_open_app_id(),
+            agent_unresponsive_timeout_secs: default_agent_unresponsive_timeout_secs(),
+            auto_restart_unresponsive_agent: false,
+            model_sampling_params: HashMap::new(),
+            auto_store_maintenance: default_auto_store_maintenance(),
+            resource_monitoring_enabled: default_resource_monitoring_enabled(),
+            resource_warning_rss_mb: default_resource_warning_rss_mb(),
+            resource_warning_cpu_percent: default_resource_warning_cpu_percent(),
+            resource_warning_sustained_secs: default_resource_warning_sustained_secs(),
+            slow_command_threshold_ms: default_slow_command_threshold_ms(),
+            proxy: ProxySettings::default(),
+            run_kickoff_template: None,
+            usage_counters_enabled: false,
+            prompt_trash_retention_days: default_prompt_trash_retention_days(),
+            max_concurrent_connects: default_max_concurrent_connects(),
+            prompt_timeout_secs: default_prompt_timeout_secs(),
+            turn_stall_warning_secs: default_turn_stall_warning_secs(),
+            tool_stall_warning_secs: None,
+            tool_slow_warning_secs: default_tool_slow_warning_secs(),
+            approval_timeout_secs: default_approval_timeout_secs(),
+            background_idle_timeout_ms: default_background_idle_timeout_ms(),
+            background_max_wait_secs: default_background_max_wait_secs(),
+            commit_message_diff_max_bytes: default_commit_message_diff_max_bytes(),
+            tool_result_max_kb: default_tool_result_max_kb(),
+            auto_title_threads: false,
+            persist_reasoning: default_persist_reasoning(),
+            archived_thread_retention_days: default_archived_thread_retention_days(),
+            model_prices: Vec::new(),
+            settings_revision: 0,
         }
     }
 }
@@ -924,10 +1544,12 @@ mod tests {
         assert_eq!(settings.code_font_size, 11);
         assert!(settings.notification_sounds_enabled);
         assert!(settings.system_notifications_enabled);
+        assert_eq!(settings.notification_retention_days, 14);
         assert!(settings.preload_git_diffs);
         assert!(!settings.git_diff_ignore_whitespace_changes);
         assert!(settings.collaboration_modes_enabled);
         assert!(settings.steer_enabled);
+        assert!(settings.queue_when_busy);
         assert!(settings.unified_exec_enabled);
         assert!(!settings.experimental_apps_enabled);
         assert_eq!(settings.personality, "friendly");
@@ -946,6 +1568,29 @@ mod tests {
         assert!(!settings.composer_code_block_copy_use_modifier);
         assert!(settings.workspace_groups.is_empty());
         assert_eq!(settings.selected_open_app_id, "system");
+        assert_eq!(settings.agent_unresponsive_timeout_secs, 180);
+        assert!(!settings.auto_restart_unresponsive_agent);
+        assert!(settings.auto_store_maintenance);
+        assert!(settings.resource_monitoring_enabled);
+        assert!(!settings.usage_counters_enabled);
+        assert_eq!(settings.resource_warning_rss_mb, 4096);
+        assert_eq!(settings.resource_warning_sustained_secs, 60);
+        assert_eq!(settings.slow_command_threshold_ms, 1_000);
+        assert_eq!(settings.prompt_trash_retention_days, 30);
+        assert_eq!(settings.max_concurrent_connects, 3);
+        assert_eq!(settings.prompt_timeout_secs, 6 * 60 * 60);
+        assert_eq!(settings.turn_stall_warning_secs, 60);
+        assert_eq!(settings.tool_stall_warning_secs, None);
+        assert_eq!(settings.tool_slow_warning_secs, 120);
+        assert_eq!(settings.approval_timeout_secs, 5 * 60);
+        assert_eq!(settings.background_idle_timeout_ms, 2_000);
+        assert_eq!(settings.background_max_wait_secs, 30);
+        assert_eq!(settings.commit_message_diff_max_bytes, 64 * 1024);
+        assert_eq!(settings.tool_result_max_kb, 64);
+        assert!(!settings.auto_title_threads);
+        assert!(settings.persist_reasoning);
+        assert_eq!(settings.archived_thread_retention_days, 30);
+        assert!(settings.model_prices.is_empty());
         assert_eq!(settings.open_app_targets.len(), 3);
         assert_eq!(settings.open_app_targets[0].id, "system");
     }
@@ -1,17 +1,22 @@
 use std::path::PathBuf;
 use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
-use serde_json::json;
+use serde_json::{json, Value};
 use tauri::{AppHandle, Manager, State};
 use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
 
+use super::editors::{
+    expand_command_template, has_command_placeholders, list_openable_apps_inner,
+    resolve_editor_invocation, EditorLaunchError, OpenTarget, OpenableApp,
+};
 use super::files::{list_workspace_files_inner, read_workspace_file_inner, WorkspaceFileResponse};
 use super::git::{
     git_branch_exists, git_find_remote_for_branch, git_get_origin_url, git_remote_branch_exists,
     git_remote_exists, is_missing_worktree_error, run_git_command, run_git_command_bytes,
-    run_git_command_owned, run_git_diff, unique_branch_name,
+    run_git_command_cancellable, run_git_command_owned, run_git_diff, unique_branch_name,
 };
 #[cfg(target_os = "macos")]
 use super::macos::get_open_app_icon_inner;
@@ -22,17 +27,33 @@ use super::worktree::{
 };
 
 use crate::backend::app_server::WorkspaceSession;
+use crate::backend::documents::ReadFormat;
+use crate::backend::event_methods;
+use crate::backend::events::AppServerEvent;
+use crate::backend::history_prune::{HistoryPruneOptions, HistoryPruneParams};
+use crate::backend::settings_events::SettingsScope;
+use crate::backend::turn_audit::TurnAudit;
+use crate::backend::turn_reviews::{FileReviewState, TurnReview};
+use crate::event_sink::TauriEventSink;
+use crate::event_subscriptions::emit_routed_event;
 use crate::git_utils::resolve_git_root;
+use crate::http_client;
 use crate::micode::args::resolve_workspace_micode_args;
 use crate::micode::home::resolve_workspace_micode_home;
 use crate::micode::spawn_workspace_session;
 use crate::remote_backend;
+use crate::shared::bootstrap_core::bootstrap_warnings_event;
+use crate::shared::operations_core::{Operation, OperationOutcome};
 use crate::shared::process_core::tokio_command;
-use crate::shared::workspaces_core;
+use crate::shared::resource_monitor_core::{self, ResourceThresholds};
+use crate::shared::workspaces_core::{ConnectAllSelection, NewWorkspace, WorkspaceStores};
+use crate::shared::{workspace_stack_core, workspaces_core};
 use crate::state::AppState;
 use crate::storage::write_workspaces;
+use crate::types::errors::CommandError;
 use crate::types::{
-    WorkspaceEntry, WorkspaceInfo, WorkspaceKind, WorkspaceSettings, WorktreeSetupStatus,
+    RedactionSettings, WorkspaceEntry, WorkspaceInfo, WorkspaceKind, WorkspaceSettings,
+    WorkspaceStack, WorktreeSetupStatus,
 };
 use crate::utils::{git_env_path, resolve_git_binary};
 
@@ -42,7 +63,7 @@ fn spawn_with_app(
     default_bin: Option<String>,
     agent_args: Option<String>,
     agent_home: Option<PathBuf>,
-) -> impl std::future::Future<Output = Result<Arc<WorkspaceSession>, String>> {
+) -> impl std::future::Future<Output = Result<Arc<WorkspaceSession>, CommandError>> {
     spawn_workspace_session(entry, default_bin, agent_args, app.clone(), agent_home)
 }
 
@@ -50,41 +71,227 @@ fn spawn_with_app(
 pub(crate) async fn read_workspace_file(
     workspace_id: String,
     path: String,
+    format: Option<String>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<WorkspaceFileResponse, String> {
+) -> Result<WorkspaceFileResponse, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         let response = remote_backend::call_remote(
             &*state,
             app,
             "read_workspace_file",
-            json!({ "workspaceId": workspace_id, "path": path }),
+            json!({ "workspaceId": workspace_id, "path": path, "format": format }),
         )
         .await?;
-        return serde_json::from_value(response).map_err(|err| err.to_string());
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
     }
 
-    workspaces_core::read_workspace_file_core(
+    let format = ReadFormat::parse(format.as_deref())?;
+    state
+        .command_timings
+        .timed(
+            "read_workspace_file",
+            Some(&workspace_id),
+            workspaces_core::read_workspace_file_core(
+                &state.workspaces,
+                &workspace_id,
+                &path,
+                |root, rel_path| read_workspace_file_inner(root, rel_path, format),
+            ),
+        )
+        .await
+}
+
+#[tauri::command]
+pub(crate) async fn read_turn_artifact(
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<WorkspaceFileResponse, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "read_turn_artifact",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "turnId": turn_id,
+                "path": path
+            }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
+    }
+
+    workspaces_core::read_turn_artifact_core(
         &state.workspaces,
+        &state.sessions,
         &workspace_id,
+        &thread_id,
+        &turn_id,
         &path,
-        |root, rel_path| read_workspace_file_inner(root, rel_path),
+        |root, rel_path| read_workspace_file_inner(root, rel_path, ReadFormat::Auto),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn get_turn_audit(
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<TurnAudit, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "get_turn_audit",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "turnId": turn_id
+            }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
+    }
+
+    workspaces_core::get_turn_audit_core(&state.workspaces, &workspace_id, &thread_id, &turn_id)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub(crate) async fn get_workspace_stack(
+    workspace_id: String,
+    refresh: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<WorkspaceStack, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "get_workspace_stack",
+            json!({ "workspaceId": workspace_id, "refresh": refresh }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
+    }
+
+    workspace_stack_core::get_workspace_stack_core(
+        &state.workspaces,
+        &workspace_id,
+        refresh.unwrap_or(false),
     )
     .await
+    .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub(crate) async fn get_turn_review_state(
+    workspace_id: String,
+    turn_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<TurnReview, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "get_turn_review_state",
+            json!({ "workspaceId": workspace_id, "turnId": turn_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
+    }
+
+    workspaces_core::get_turn_review_state_core(&state.workspaces, &workspace_id, &turn_id)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub(crate) async fn set_file_review_state(
+    workspace_id: String,
+    turn_id: String,
+    path: String,
+    review_state: FileReviewState,
+    note: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<TurnReview, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "set_file_review_state",
+            json!({
+                "workspaceId": workspace_id,
+                "turnId": turn_id,
+                "path": path,
+                "reviewState": review_state,
+                "note": note
+            }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
+    }
+
+    workspaces_core::set_file_review_state_core(
+        &state.workspaces,
+        &workspace_id,
+        &turn_id,
+        &path,
+        review_state,
+        note,
+    )
+    .await
+    .map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub(crate) async fn list_workspaces(
+    include_runtime: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Vec<WorkspaceInfo>, String> {
+) -> Result<Vec<WorkspaceInfo>, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
-        let response =
-            remote_backend::call_remote(&*state, app, "list_workspaces", json!({})).await?;
-        return serde_json::from_value(response).map_err(|err| err.to_string());
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "list_workspaces",
+            json!({ "includeRuntime": include_runtime }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
     }
 
-    Ok(workspaces_core::list_workspaces_core(&state.workspaces, &state.sessions).await)
+    let mut workspaces =
+        workspaces_core::list_workspaces_core(&state.workspaces, &state.sessions).await;
+    if include_runtime.unwrap_or(false) {
+        let entries = state.workspaces.lock().await.clone();
+        let data_dir = state.data_dir.clone();
+        let turns_today = tokio::task::spawn_blocking(move || {
+            crate::local_usage::today_agent_runs_by_workspace(&entries, &data_dir)
+        })
+        .await
+        .map_err(|err| err.to_string())?;
+        workspaces_core::attach_workspace_runtime_core(
+            &mut workspaces,
+            &state.sessions,
+            &state.connection_states,
+            Some(&turns_today),
+        )
+        .await;
+    }
+    Ok(workspaces)
 }
 
 #[tauri::command]
@@ -92,7 +299,7 @@ pub(crate) async fn is_workspace_path_dir(
     path: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<bool, String> {
+) -> Result<bool, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         let response = remote_backend::call_remote(
             &*state,
@@ -101,7 +308,7 @@ pub(crate) async fn is_workspace_path_dir(
             json!({ "path": path }),
         )
         .await?;
-        return serde_json::from_value(response).map_err(|err| err.to_string());
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
     }
     Ok(workspaces_core::is_workspace_path_dir_core(&path))
 }
@@ -112,7 +319,7 @@ pub(crate) async fn add_workspace(
     micode_bin: Option<String>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<WorkspaceInfo, String> {
+) -> Result<WorkspaceInfo, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         let path = remote_backend::normalize_path_for_remote(path);
         let micode_bin = micode_bin.map(remote_backend::normalize_path_for_remote);
@@ -123,45 +330,79 @@ pub(crate) async fn add_workspace(
             json!({ "path": path, "micode_bin": micode_bin }),
         )
         .await?;
-        return serde_json::from_value(response).map_err(|err| err.to_string());
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
     }
 
-    workspaces_core::add_workspace_core(
-        path,
-        micode_bin,
+    let workspace = workspaces_core::add_workspace_core(
+        NewWorkspace {
+            path,
+            agent_bin: micode_bin,
+        },
         &state.workspaces,
         &state.sessions,
         &state.app_settings,
+        &state.data_dir,
         &state.storage_path,
         |entry, default_bin, agent_args, agent_home| {
             spawn_with_app(&app, entry, default_bin, agent_args, agent_home)
         },
     )
-    .await
+    .await?;
+    if let Some(event) = bootstrap_warnings_event(&workspace.id, &workspace.bootstrap_warnings) {
+        emit_routed_event(&app, event);
+    }
+    Ok(workspace)
 }
 
+/// Clones a workspace into `copies_folder`. Cancellable through `cancel_operation` until
+/// the clone's session starts; a cancelled clone leaves no directory behind.
 #[tauri::command]
 pub(crate) async fn add_clone(
     source_workspace_id: String,
     copy_name: String,
     copies_folder: String,
+    operation_id: Option<String>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<WorkspaceInfo, String> {
+) -> Result<OperationOutcome<WorkspaceInfo>, CommandError> {
+    let operation =
+        state
+            .operations
+            .start(operation_id, "clone", TauriEventSink::new(app.clone()));
+    let result = add_clone_inner(
+        source_workspace_id,
+        copy_name,
+        copies_folder,
+        &operation,
+        &state,
+        app,
+    )
+    .await;
+    operation.finish(result)
+}
+
+async fn add_clone_inner(
+    source_workspace_id: String,
+    copy_name: String,
+    copies_folder: String,
+    operation: &Operation<'_, TauriEventSink>,
+    state: &AppState,
+    app: AppHandle,
+) -> Result<Option<WorkspaceInfo>, CommandError> {
     let copy_name = copy_name.trim().to_string();
     if copy_name.is_empty() {
-        return Err("Copy name is required.".to_string());
+        return Err("Copy name is required.".into());
     }
 
     let copies_folder = copies_folder.trim().to_string();
     if copies_folder.is_empty() {
-        return Err("Copies folder is required.".to_string());
+        return Err("Copies folder is required.".into());
     }
     let copies_folder_path = PathBuf::from(&copies_folder);
     std::fs::create_dir_all(&copies_folder_path)
         .map_err(|e| format!("Failed to create copies folder: {e}"))?;
     if !copies_folder_path.is_dir() {
-        return Err("Copies folder must be a directory.".to_string());
+        return Err("Copies folder must be a directory.".into());
     }
 
     let (source_entry, inherited_group_id) = {
@@ -185,14 +426,23 @@ pub(crate) async fn add_clone(
     let destination_path = build_clone_destination_path(&copies_folder_path, &copy_name);
     let destination_path_string = destination_path.to_string_lossy().to_string();
 
-    if let Err(error) = run_git_command(
+    operation.progress("cloning", None, None);
+    match run_git_command_cancellable(
         &copies_folder_path,
         &["clone", &source_entry.path, &destination_path_string],
+        operation.token(),
     )
     .await
     {
-        let _ = tokio::fs::remove_dir_all(&destination_path).await;
-        return Err(error);
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            let _ = tokio::fs::remove_dir_all(&destination_path).await;
+            return Ok(None);
+        }
+        Err(error) => {
+            let _ = tokio::fs::remove_dir_all(&destination_path).await;
+            return Err(error.into());
+        }
     }
 
     if let Some(origin_url) = git_get_origin_url(&PathBuf::from(&source_entry.path)).await {
@@ -215,6 +465,7 @@ pub(crate) async fn add_clone(
             group_id: inherited_group_id,
             ..WorkspaceSettings::default()
         },
+        stack: source_entry.stack.clone(),
     };
 
     let (default_bin, agent_args) = {
@@ -224,7 +475,12 @@ pub(crate) async fn add_clone(
             resolve_workspace_micode_args(&entry, None, Some(&settings)),
         )
     };
-    let agent_home = resolve_workspace_micode_home(&entry, None);
+    let agent_home = resolve_workspace_micode_home(&entry, None, &state.data_dir);
+    if operation.is_cancelled() {
+        let _ = tokio::fs::remove_dir_all(&destination_path).await;
+        return Ok(None);
+    }
+    operation.progress("starting", None, None);
     let session = match spawn_workspace_session(
         entry.clone(),
         default_bin,
@@ -251,10 +507,9 @@ pub(crate) async fn add_clone(
             let mut workspaces = state.workspaces.lock().await;
             workspaces.remove(&entry.id);
         }
-        let mut child = session.child.lock().await;
-        let _ = child.kill().await;
+        session.kill().await;
         let _ = tokio::fs::remove_dir_all(&destination_path).await;
-        return Err(error);
+        return Err(error.into());
     }
 
     state
@@ -263,7 +518,7 @@ pub(crate) async fn add_clone(
         .await
         .insert(entry.id.clone(), session);
 
-    Ok(WorkspaceInfo {
+    Ok(Some(WorkspaceInfo {
         id: entry.id,
         name: entry.name,
         path: entry.path,
@@ -273,7 +528,10 @@ pub(crate) async fn add_clone(
         parent_id: entry.parent_id,
         worktree: entry.worktree,
         settings: entry.settings,
-    })
+        config_stale: false,
+        runtime: None,
+        bootstrap_warnings: Vec::new(),
+    }))
 }
 
 #[tauri::command]
@@ -284,7 +542,7 @@ pub(crate) async fn add_worktree(
     copy_agents_md: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<WorkspaceInfo, String> {
+) -> Result<WorkspaceInfo, CommandError> {
     let copy_agents_md = copy_agents_md.unwrap_or(true);
     if remote_backend::is_remote_mode(&*state).await {
         let response = remote_backend::call_remote(
@@ -299,7 +557,7 @@ pub(crate) async fn add_worktree(
             }),
         )
         .await?;
-        return serde_json::from_value(response).map_err(|err| err.to_string());
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
     }
 
     let data_dir = app
@@ -335,6 +593,7 @@ pub(crate) async fn add_worktree(
         },
     )
     .await
+    .map_err(CommandError::from)
 }
 
 #[tauri::command]
@@ -342,7 +601,7 @@ pub(crate) async fn worktree_setup_status(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<WorktreeSetupStatus, String> {
+) -> Result<WorktreeSetupStatus, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         let response = remote_backend::call_remote(
             &*state,
@@ -351,7 +610,7 @@ pub(crate) async fn worktree_setup_status(
             json!({ "workspaceId": workspace_id }),
         )
         .await?;
-        return serde_json::from_value(response).map_err(|err| err.to_string());
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
     }
 
     let data_dir = app
@@ -366,7 +625,7 @@ pub(crate) async fn worktree_setup_mark_ran(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         remote_backend::call_remote(
             &*state,
@@ -388,11 +647,18 @@ pub(crate) async fn worktree_setup_mark_ran(
 #[tauri::command]
 pub(crate) async fn remove_workspace(
     id: String,
+    delete_agent_home: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
-        remote_backend::call_remote(&*state, app, "remove_workspace", json!({ "id": id })).await?;
+        remote_backend::call_remote(
+            &*state,
+            app,
+            "remove_workspace",
+            json!({ "id": id, "deleteAgentHome": delete_agent_home }),
+        )
+        .await?;
         return Ok(());
     }
 
@@ -400,7 +666,9 @@ pub(crate) async fn remove_workspace(
         id,
         &state.workspaces,
         &state.sessions,
+        &state.connection_states,
         &state.storage_path,
+        &state.data_dir,
         |root, args| {
             workspaces_core::run_git_command_unit(root, args, |repo, args_owned| {
                 run_git_command_owned(repo, args_owned)
@@ -413,6 +681,7 @@ pub(crate) async fn remove_workspace(
         },
         true,
         true,
+        delete_agent_home.unwrap_or(false),
     )
     .await
 }
@@ -422,7 +691,7 @@ pub(crate) async fn remove_worktree(
     id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         remote_backend::call_remote(&*state, app, "remove_worktree", json!({ "id": id })).await?;
         return Ok(());
@@ -430,9 +699,12 @@ pub(crate) async fn remove_worktree(
 
     workspaces_core::remove_worktree_core(
         id,
-        &state.workspaces,
-        &state.sessions,
-        &state.storage_path,
+        WorkspaceStores {
+            workspaces: &state.workspaces,
+            sessions: &state.sessions,
+            connection_states: &state.connection_states,
+            storage_path: &state.storage_path,
+        },
         |root, args| {
             workspaces_core::run_git_command_unit(root, args, |repo, args_owned| {
                 run_git_command_owned(repo, args_owned)
@@ -450,25 +722,40 @@ pub(crate) async fn remove_worktree(
 #[tauri::command]
 pub(crate) async fn clear_workspace_history(
     id: String,
+    options: HistoryPruneParams,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<(), String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
-        remote_backend::call_remote(
+        return remote_backend::call_remote(
             &*state,
             app,
             "clear_workspace_history",
-            json!({ "id": id }),
+            json!({
+                "id": id,
+                "olderThanDays": options.older_than_days,
+                "tags": options.tags,
+                "excludePinned": options.exclude_pinned,
+                "pinnedThreadIds": options.pinned_thread_ids,
+                "dryRun": options.dry_run
+            }),
         )
-        .await?;
-        return Ok(());
+        .await
+        .map_err(CommandError::from);
     }
 
-    let (workspace_ids, _paths) =
-        workspaces_core::resolve_workspace_history_targets_core(&id, &state.workspaces).await?;
-    workspaces_core::clear_workspace_history_core(&id, &state.workspaces).await?;
-    workspaces_core::kill_sessions_core(&state.sessions, &workspace_ids).await;
-    Ok(())
+    let options = HistoryPruneOptions::from_params(options);
+    let (result, events) = workspaces_core::clear_workspace_history_scoped_core(
+        &id,
+        &state.workspaces,
+        &state.sessions,
+        &options,
+    )
+    .await?;
+    for event in events {
+        emit_routed_event(&app, event);
+    }
+    serde_json::to_value(result).map_err(|err| CommandError::from(err.to_string()))
 }
 
 #[tauri::command]
@@ -477,7 +764,7 @@ pub(crate) async fn rename_worktree(
     branch: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<WorkspaceInfo, String> {
+) -> Result<WorkspaceInfo, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         let response = remote_backend::call_remote(
             &*state,
@@ -486,7 +773,7 @@ pub(crate) async fn rename_worktree(
             json!({ "id": id, "branch": branch }),
         )
         .await?;
-        return serde_json::from_value(response).map_err(|err| err.to_string());
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
     }
 
     let data_dir = app
@@ -500,6 +787,7 @@ pub(crate) async fn rename_worktree(
         &data_dir,
         &state.workspaces,
         &state.sessions,
+        &state.connection_states,
         &state.app_settings,
         &state.storage_path,
         |entry| resolve_git_root(entry),
@@ -533,7 +821,7 @@ pub(crate) async fn rename_worktree_upstream(
     new_branch: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         remote_backend::call_remote(
             &*state,
@@ -585,15 +873,15 @@ pub(crate) async fn rename_worktree_upstream(
 pub(crate) async fn apply_worktree_changes(
     workspace_id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let (entry, parent) = {
         let workspaces = state.workspaces.lock().await;
         let entry = workspaces
             .get(&workspace_id)
             .cloned()
-            .ok_or("workspace not found")?;
+            .ok_or_else(CommandError::workspace_not_found)?;
         if !entry.kind.is_worktree() {
-            return Err("Not a worktree workspace.".to_string());
+            return Err("Not a worktree workspace.".into());
         }
         let parent_id = entry.parent_id.clone().ok_or("worktree parent not found")?;
         let parent = workspaces
@@ -610,7 +898,7 @@ pub(crate) async fn apply_worktree_changes(
     if !String::from_utf8_lossy(&parent_status).trim().is_empty() {
         return Err(
             "Your current branch has uncommitted changes. Please commit, stash, or discard them before applying worktree changes."
-                .to_string(),
+                .into(),
         );
     }
 
@@ -651,7 +939,7 @@ pub(crate) async fn apply_worktree_changes(
     }
 
     if String::from_utf8_lossy(&patch).trim().is_empty() {
-        return Err("No changes to apply.".to_string());
+        return Err("No changes to apply.".into());
     }
 
     let git_bin = resolve_git_binary().map_err(|e| format!("Failed to run git: {e}"))?;
@@ -689,32 +977,34 @@ pub(crate) async fn apply_worktree_changes(
         stderr.trim()
     };
     if detail.is_empty() {
-        return Err("Git apply failed.".to_string());
+        return Err("Git apply failed.".into());
     }
 
     if detail.contains("Applied patch to") {
         if detail.contains("with conflicts") {
             return Err(
                 "Applied with conflicts. Resolve conflicts in the parent repo before retrying."
-                    .to_string(),
+                    .into(),
             );
         }
         return Err(
-            "Patch applied partially. Resolve changes in the parent repo before retrying."
-                .to_string(),
+            "Patch applied partially. Resolve changes in the parent repo before retrying.".into(),
         );
     }
 
-    Err(detail.to_string())
+    Err(detail.into())
 }
 
 #[tauri::command]
 pub(crate) async fn update_workspace_settings(
     id: String,
-    settings: WorkspaceSettings,
+    mut settings: WorkspaceSettings,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<WorkspaceInfo, String> {
+) -> Result<WorkspaceInfo, CommandError> {
+    if let Some(proxy) = settings.proxy.as_mut() {
+        http_client::store_url_credentials(proxy, &state.secrets_path, Some(&id))?;
+    }
     if remote_backend::is_remote_mode(&*state).await {
         let response = remote_backend::call_remote(
             &*state,
@@ -723,15 +1013,22 @@ pub(crate) async fn update_workspace_settings(
             json!({ "id": id, "settings": settings }),
         )
         .await?;
-        return serde_json::from_value(response).map_err(|err| err.to_string());
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
     }
 
-    workspaces_core::update_workspace_settings_core(
+    let previous = state
+        .workspaces
+        .lock()
+        .await
+        .get(&id)
+        .map(|entry| entry.settings.clone());
+    let workspace = workspaces_core::update_workspace_settings_core(
         id,
         settings,
         &state.workspaces,
         &state.sessions,
         &state.app_settings,
+        &state.data_dir,
         &state.storage_path,
         |workspaces, workspace_id, next_settings| {
             apply_workspace_settings_update(workspaces, workspace_id, next_settings)
@@ -740,7 +1037,17 @@ pub(crate) async fn update_workspace_settings(
             spawn_with_app(&app, entry, default_bin, agent_args, agent_home)
         },
     )
-    .await
+    .await?;
+    state
+        .usage_counters
+        .set_workspace(&workspace.id, workspace.settings.usage_counters);
+    let (_, event) = state.settings_revision.record_update(
+        SettingsScope::Workspace(&workspace.id),
+        &json!(previous),
+        &json!(workspace.settings),
+    );
+    emit_routed_event(&app, event);
+    Ok(workspace)
 }
 
 #[tauri::command]
@@ -749,7 +1056,7 @@ pub(crate) async fn update_workspace_micode_bin(
     micode_bin: Option<String>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<WorkspaceInfo, String> {
+) -> Result<WorkspaceInfo, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         let micode_bin = micode_bin.map(remote_backend::normalize_path_for_remote);
         let response = remote_backend::call_remote(
@@ -759,17 +1066,362 @@ pub(crate) async fn update_workspace_micode_bin(
             json!({ "id": id, "micode_bin": micode_bin }),
         )
         .await?;
-        return serde_json::from_value(response).map_err(|err| err.to_string());
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
     }
 
-    workspaces_core::update_workspace_micode_bin_core(
+    let mut workspace = workspaces_core::update_workspace_micode_bin_core(
         id,
         micode_bin,
         &state.workspaces,
         &state.sessions,
         &state.storage_path,
     )
+    .await?;
+    refresh_stale_sessions(&state, &app).await;
+    workspace.config_stale = state
+        .sessions
+        .lock()
+        .await
+        .get(&workspace.id)
+        .map(|session| session.is_config_stale())
+        .unwrap_or(false);
+    Ok(workspace)
+}
+
+/// Re-checks live sessions against current settings and emits `workspace/configStale`
+/// for every workspace whose stale flag changed.
+pub(crate) async fn refresh_stale_sessions(state: &AppState, app: &AppHandle) {
+    let events = workspaces_core::refresh_stale_sessions_core(
+        &state.workspaces,
+        &state.sessions,
+        &state.app_settings,
+    )
+    .await;
+    for event in events {
+        emit_routed_event(app, event);
+    }
+}
+
+/// Restarts the agent with the current settings. Returns `None` when running turns have to
+/// finish first; the restart then completes in the background and reports through events.
+#[tauri::command]
+pub(crate) async fn restart_workspace_session(
+    workspace_id: String,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Option<WorkspaceInfo>, CommandError> {
+    let force = force.unwrap_or(false);
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "restart_workspace_session",
+            json!({ "workspaceId": workspace_id, "force": force }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
+    }
+
+    let restarted = workspaces_core::restart_workspace_session_core(
+        workspace_id.clone(),
+        force,
+        &state.workspaces,
+        &state.sessions,
+        &state.app_settings,
+        &state.data_dir,
+        |entry, default_bin, agent_args, agent_home| {
+            spawn_with_app(&app, entry, default_bin, agent_args, agent_home)
+        },
+    )
     .await
+    .map_err(CommandError::from)?;
+    if restarted.is_none() {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let state = app.state::<AppState>();
+            workspaces_core::drain_and_restart_session_core(
+                workspace_id,
+                &state.workspaces,
+                &state.sessions,
+                &state.app_settings,
+                &state.data_dir,
+                |event| {
+                    emit_routed_event(&app, event);
+                },
+                |entry, default_bin, agent_args, agent_home| {
+                    spawn_with_app(&app, entry, default_bin, agent_args, agent_home)
+                },
+            )
+            .await;
+        });
+    }
+    Ok(restarted)
+}
+
+#[tauri::command]
+pub(crate) async fn force_restart_session(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<WorkspaceInfo, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "force_restart_session",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
+    }
+
+    workspaces_core::force_restart_session_core(
+        workspace_id,
+        &state.workspaces,
+        &state.sessions,
+        &state.app_settings,
+        &state.data_dir,
+        |entry, default_bin, agent_args, agent_home| {
+            spawn_with_app(&app, entry, default_bin, agent_args, agent_home)
+        },
+    )
+    .await
+    .map_err(CommandError::from)
+}
+
+/// Periodically looks for wedged agent processes, emits `micode/unresponsive` and, when
+/// auto-restart is enabled, restarts them. Agents that crashed are always respawned.
+pub(crate) fn spawn_liveness_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(workspaces_core::LIVENESS_CHECK_INTERVAL).await;
+            let state = app.state::<AppState>();
+            let (events, restart_ids) = workspaces_core::detect_unresponsive_sessions_core(
+                &state.sessions,
+                &state.app_settings,
+            )
+            .await;
+            for event in events {
+                emit_routed_event(&app, event);
+            }
+            for workspace_id in restart_ids {
+                let result = workspaces_core::force_restart_session_core(
+                    workspace_id.clone(),
+                    &state.workspaces,
+                    &state.sessions,
+                    &state.app_settings,
+                    &state.data_dir,
+                    |entry, default_bin, agent_args, agent_home| {
+                        spawn_with_app(&app, entry, default_bin, agent_args, agent_home)
+                    },
+                )
+                .await;
+                if let Err(error) = result {
+                    emit_routed_event(
+                        &app,
+                        AppServerEvent {
+                            workspace_id: workspace_id.clone(),
+                            message: json!({
+                                "method": event_methods::MICODE_RESTART_FAILED,
+                                "params": { "workspaceId": workspace_id, "error": error }
+                            }),
+                        },
+                    );
+                }
+            }
+            for workspace_id in workspaces_core::crashed_session_ids_core(&state.sessions).await {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    let state = app.state::<AppState>();
+                    workspaces_core::respawn_crashed_session_core(
+                        workspace_id,
+                        &state.workspaces,
+                        &state.sessions,
+                        &state.app_settings,
+                        &state.data_dir,
+                        |event| {
+                            emit_routed_event(&app, event);
+                        },
+                        |entry, default_bin, agent_args, agent_home| {
+                            spawn_with_app(&app, entry, default_bin, agent_args, agent_home)
+                        },
+                    )
+                    .await;
+                });
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub(crate) async fn run_store_maintenance_now(
+    workspace_id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "run_store_maintenance_now",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let reports =
+        workspaces_core::run_store_maintenance_core(&state.sessions, workspace_id.as_deref())
+            .await?;
+    for event in workspaces_core::record_store_maintenance_reports(&state.logs_dir, &reports) {
+        emit_routed_event(&app, event);
+    }
+    serde_json::to_value(reports).map_err(|err| CommandError::from(err.to_string()))
+}
+
+#[tauri::command]
+pub(crate) async fn get_session_info(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "get_session_info",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    workspaces_core::get_session_info_core(&workspace_id, &state.sessions).await
+}
+
+#[tauri::command]
+pub(crate) async fn micode_session_status(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "micode_session_status",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    workspaces_core::micode_session_status_core(&workspace_id, &state.sessions).await
+}
+
+/// Shows what `text` looks like after the workspace's secret redaction.
+#[tauri::command]
+pub(crate) async fn test_redaction(
+    workspace_id: String,
+    text: String,
+    settings: Option<RedactionSettings>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "test_redaction",
+            json!({ "workspaceId": workspace_id, "text": text, "settings": settings }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let redacted =
+        workspaces_core::test_redaction_core(&state.workspaces, &workspace_id, &text, settings)
+            .await?;
+    serde_json::to_value(redacted).map_err(|err| CommandError::from(err.to_string()))
+}
+
+/// CPU/RAM of every agent child and open terminal shell.
+#[tauri::command]
+pub(crate) async fn get_resource_usage(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let mut usage =
+            remote_backend::call_remote(&*state, app, "get_resource_usage", json!({}))
+                .await
+                .map_err(CommandError::from)?;
+        // Terminals run in this app even in remote mode.
+        let terminals = crate::terminal::terminal_resource_usage(&state).await;
+        if let Some(usage) = usage.as_object_mut() {
+            usage.insert("terminals".to_string(), json!(terminals));
+        }
+        return Ok(usage);
+    }
+
+    Ok(json!({
+        "sessions": resource_monitor_core::session_resource_usage_core(&state.sessions).await,
+        "terminals": crate::terminal::terminal_resource_usage(&state).await
+    }))
+}
+
+/// Samples agent children and terminal shells and emits `workspace/resourceWarning` for
+/// ones that stay above the configured limits.
+pub(crate) fn spawn_resource_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(resource_monitor_core::RESOURCE_SAMPLE_INTERVAL).await;
+            let state = app.state::<AppState>();
+            let mut events = resource_monitor_core::sample_session_resources_core(
+                &state.sessions,
+                &state.app_settings,
+            )
+            .await;
+            let thresholds = {
+                let settings = state.app_settings.lock().await;
+                settings
+                    .resource_monitoring_enabled
+                    .then(|| ResourceThresholds::from_settings(&settings))
+            };
+            if let Some(thresholds) = thresholds {
+                events
+                    .extend(crate::terminal::sample_terminal_resources(&state, &thresholds).await);
+            }
+            for event in events {
+                emit_routed_event(&app, event);
+            }
+        }
+    });
+}
+
+/// Compacts and integrity-checks thread stores of workspaces that have been idle for a
+/// while, unless automatic maintenance is disabled in settings.
+pub(crate) fn spawn_store_maintenance_task(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(workspaces_core::STORE_MAINTENANCE_CHECK_INTERVAL).await;
+            let state = app.state::<AppState>();
+            if remote_backend::is_remote_mode(&*state).await {
+                continue;
+            }
+            let reports = workspaces_core::run_idle_store_maintenance_core(
+                &state.sessions,
+                &state.app_settings,
+            )
+            .await;
+            for event in
+                workspaces_core::record_store_maintenance_reports(&state.logs_dir, &reports)
+            {
+                emit_routed_event(&app, event);
+            }
+        }
+    });
 }
 
 #[tauri::command]
@@ -777,45 +1429,115 @@ pub(crate) async fn connect_workspace(
     id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<(), String> {
+) -> Result<Value, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
-        remote_backend::call_remote(&*state, app, "connect_workspace", json!({ "id": id })).await?;
-        return Ok(());
+        return remote_backend::call_remote(&*state, app, "connect_workspace", json!({ "id": id }))
+            .await
+            .map_err(CommandError::from);
     }
 
-    workspaces_core::connect_workspace_core(
-        id,
+    let bootstrap_warnings = workspaces_core::connect_workspace_core(
+        id.clone(),
         &state.workspaces,
         &state.sessions,
         &state.app_settings,
+        &state.data_dir,
         |entry, default_bin, agent_args, agent_home| {
             spawn_with_app(&app, entry, default_bin, agent_args, agent_home)
         },
     )
-    .await
+    .await?;
+    if let Some(event) = bootstrap_warnings_event(&id, &bootstrap_warnings) {
+        emit_routed_event(&app, event);
+    }
+    Ok(json!({ "ok": true, "bootstrapWarnings": bootstrap_warnings }))
 }
 
+/// Connects many workspaces with one call; progress arrives as `workspace/connect*`
+/// events and the summary is returned once every connect finished.
+#[tauri::command]
+pub(crate) async fn connect_all_workspaces(
+    workspace_ids: Option<Vec<String>>,
+    priority_workspace_id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "connect_all_workspaces",
+            json!({
+                "workspaceIds": workspace_ids,
+                "priorityWorkspaceId": priority_workspace_id,
+            }),
+        )
+        .await
+        .map_err(CommandError::from);
+    }
+
+    let (summary, events) = workspaces_core::connect_all_workspaces_core(
+        ConnectAllSelection {
+            workspace_ids,
+            priority_workspace_id,
+        },
+        &state.workspaces,
+        &state.sessions,
+        &state.app_settings,
+        &state.data_dir,
+        &state.connect_queue,
+        |entry, default_bin, agent_args, agent_home| {
+            spawn_with_app(&app, entry, default_bin, agent_args, agent_home)
+        },
+    )
+    .await;
+    for event in events {
+        emit_routed_event(&app, event);
+    }
+    Ok(summary)
+}
+
+/// Lists the workspace's files. Cancellable through `cancel_operation`, which stops the
+/// walk and returns a `cancelled` outcome without the partial listing.
 #[tauri::command]
 pub(crate) async fn list_workspace_files(
     workspace_id: String,
+    operation_id: Option<String>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Vec<String>, String> {
+) -> Result<OperationOutcome<Vec<String>>, CommandError> {
     if remote_backend::is_remote_mode(&*state).await {
         let response = remote_backend::call_remote(
             &*state,
             app,
             "list_workspace_files",
-            json!({ "workspaceId": workspace_id }),
+            json!({ "workspaceId": workspace_id, "operationId": operation_id }),
         )
         .await?;
-        return serde_json::from_value(response).map_err(|err| err.to_string());
+        return serde_json::from_value(response).map_err(|err| CommandError::from(err.to_string()));
     }
 
-    workspaces_core::list_workspace_files_core(&state.workspaces, &workspace_id, |root| {
-        list_workspace_files_inner(root, usize::MAX)
-    })
-    .await
+    let operation =
+        state
+            .operations
+            .start(operation_id, "workspaceSearch", TauriEventSink::new(app));
+    let scanned = AtomicU64::new(0);
+    let result = state
+        .command_timings
+        .timed(
+            "list_workspace_files",
+            Some(&workspace_id),
+            workspaces_core::list_workspace_files_core(&state.workspaces, &workspace_id, |root| {
+                let files = list_workspace_files_inner(root, usize::MAX, operation.token());
+                let total =
+                    scanned.fetch_add(files.len() as u64, Ordering::Relaxed) + files.len() as u64;
+                operation.progress("scanning", Some(total), None);
+                files
+            }),
+        )
+        .await
+        .map(|files| (!operation.is_cancelled()).then_some(files));
+    operation.finish(result)
 }
 
 #[tauri::command]
@@ -824,7 +1546,70 @@ pub(crate) async fn open_workspace_in(
     app: Option<String>,
     args: Vec<String>,
     command: Option<String>,
-) -> Result<(), String> {
+    target: Option<OpenTarget>,
+    state: State<'_, AppState>,
+) -> Result<(), EditorLaunchError> {
+    let OpenTarget {
+        editor_id,
+        workspace_id,
+        file,
+        line,
+    } = target.unwrap_or_default();
+    let custom_targets = state.app_settings.lock().await.open_app_targets.clone();
+    let default_editor = match workspace_id.as_deref() {
+        Some(id) if app.is_none() && command.is_none() => state
+            .workspaces
+            .lock()
+            .await
+            .get(id)
+            .and_then(|entry| entry.settings.default_editor.clone()),
+        _ => None,
+    };
+    let editor_id = editor_id
+        .or(default_editor)
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+
+    if let Some(editor_id) = editor_id {
+        let invocation =
+            resolve_editor_invocation(&editor_id, &custom_targets, &path, file.as_deref(), line)?;
+        let status = std::process::Command::new(&invocation.program)
+            .args(&invocation.args)
+            .status()
+            .map_err(|error| {
+                format!(
+                    "Failed to open editor `{editor_id}` ({}): {error}",
+                    invocation.program
+                )
+            })?;
+        if status.success() {
+            return Ok(());
+        }
+        return Err(format!("Failed to open editor `{editor_id}` ({status}).").into());
+    }
+
+    if let Some(template) = command
+        .as_deref()
+        .filter(|value| has_command_placeholders(value, &args))
+    {
+        let invocation = expand_command_template(template, &args, &path, file.as_deref(), line)?;
+        let status = std::process::Command::new(&invocation.program)
+            .args(&invocation.args)
+            .status()
+            .map_err(|error| format!("Failed to open app (command `{template}`): {error}"))?;
+        if status.success() {
+            return Ok(());
+        }
+        return Err(format!("Failed to open app (command `{template}` returned {status}).").into());
+    }
+
+    let path = match file.as_deref().filter(|value| !value.trim().is_empty()) {
+        Some(file) => PathBuf::from(&path)
+            .join(file)
+            .to_string_lossy()
+            .to_string(),
+        None => path,
+    };
     let target_label = command
         .as_ref()
         .map(|value| format!("command `{value}`"))
@@ -902,13 +1687,22 @@ pub(crate) async fn open_workspace_in(
         .code()
         .map(|code| format!("exit code {code}"))
         .unwrap_or_else(|| "terminated by signal".to_string());
-    Err(format!(
-        "Failed to open app ({target_label} returned {exit_detail})."
-    ))
+    Err(format!("Failed to open app ({target_label} returned {exit_detail}).").into())
+}
+
+#[tauri::command]
+pub(crate) async fn list_openable_apps(
+    state: State<'_, AppState>,
+) -> Result<Vec<OpenableApp>, CommandError> {
+    let custom_targets = state.app_settings.lock().await.open_app_targets.clone();
+    tokio::task::spawn_blocking(move || list_openable_apps_inner(&custom_targets))
+        .await
+        .map_err(|err| err.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
-pub(crate) async fn get_open_app_icon(app_name: String) -> Result<Option<String>, String> {
+pub(crate) async fn get_open_app_icon(app_name: String) -> Result<Option<String>, CommandError> {
     #[cfg(target_os = "macos")]
     {
         let trimmed = app_name.trim().to_string();
@@ -0,0 +1,512 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::OpenAppTarget;
+
+#[cfg(target_os = "macos")]
+use super::macos::get_open_app_icon_inner;
+
+const PLACEHOLDER_PATH: &str = "{path}";
+const PLACEHOLDER_FILE: &str = "{file}";
+const PLACEHOLDER_LINE: &str = "{line}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineArgStyle {
+    /// `code --goto file:line`
+    Goto,
+    /// `zed file:line`
+    ColonSuffix,
+    /// `idea --line 12 file`
+    JetBrains,
+}
+
+struct EditorDefinition {
+    id: &'static str,
+    label: &'static str,
+    cli_names: &'static [&'static str],
+    app_name: Option<&'static str>,
+    line_style: LineArgStyle,
+    install_hint: &'static str,
+}
+
+const JETBRAINS_HINT: &str =
+    "Install it with JetBrains Toolbox and enable \"Generate shell scripts\" in Toolbox settings.";
+
+const EDITORS: &[EditorDefinition] = &[
+    EditorDefinition {
+        id: "vscode",
+        label: "VS Code",
+        cli_names: &["code"],
+        app_name: Some("Visual Studio Code"),
+        line_style: LineArgStyle::Goto,
+        install_hint: "Run \"Shell Command: Install 'code' command in PATH\" from the VS Code command palette.",
+    },
+    EditorDefinition {
+        id: "cursor",
+        label: "Cursor",
+        cli_names: &["cursor"],
+        app_name: Some("Cursor"),
+        line_style: LineArgStyle::Goto,
+        install_hint: "Run \"Shell Command: Install 'cursor' command in PATH\" from the Cursor command palette.",
+    },
+    EditorDefinition {
+        id: "zed",
+        label: "Zed",
+        cli_names: &["zed", "zeditor"],
+        app_name: Some("Zed"),
+        line_style: LineArgStyle::ColonSuffix,
+        install_hint: "Run \"cli: install\" from the Zed command palette.",
+    },
+    EditorDefinition {
+        id: "idea",
+        label: "IntelliJ IDEA",
+        cli_names: &["idea", "idea64"],
+        app_name: Some("IntelliJ IDEA"),
+        line_style: LineArgStyle::JetBrains,
+        install_hint: JETBRAINS_HINT,
+    },
+    EditorDefinition {
+        id: "webstorm",
+        label: "WebStorm",
+        cli_names: &["webstorm", "webstorm64"],
+        app_name: Some("WebStorm"),
+        line_style: LineArgStyle::JetBrains,
+        install_hint: JETBRAINS_HINT,
+    },
+    EditorDefinition {
+        id: "pycharm",
+        label: "PyCharm",
+        cli_names: &["pycharm", "pycharm64", "charm"],
+        app_name: Some("PyCharm"),
+        line_style: LineArgStyle::JetBrains,
+        install_hint: JETBRAINS_HINT,
+    },
+    EditorDefinition {
+        id: "goland",
+        label: "GoLand",
+        cli_names: &["goland", "goland64"],
+        app_name: Some("GoLand"),
+        line_style: LineArgStyle::JetBrains,
+        install_hint: JETBRAINS_HINT,
+    },
+    EditorDefinition {
+        id: "rustrover",
+        label: "RustRover",
+        cli_names: &["rustrover", "rustrover64"],
+        app_name: Some("RustRover"),
+        line_style: LineArgStyle::JetBrains,
+        install_hint: JETBRAINS_HINT,
+    },
+    EditorDefinition {
+        id: "clion",
+        label: "CLion",
+        cli_names: &["clion", "clion64"],
+        app_name: Some("CLion"),
+        line_style: LineArgStyle::JetBrains,
+        install_hint: JETBRAINS_HINT,
+    },
+];
+
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct OpenableApp {
+    pub(crate) id: String,
+    pub(crate) label: String,
+    pub(crate) kind: String,
+    pub(crate) available: bool,
+    pub(crate) icon: Option<String>,
+    pub(crate) launcher: Option<String>,
+    #[serde(rename = "installHint")]
+    pub(crate) install_hint: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct EditorLaunchError {
+    pub(crate) code: String,
+    pub(crate) message: String,
+    #[serde(rename = "editorId")]
+    pub(crate) editor_id: Option<String>,
+    #[serde(rename = "installHint")]
+    pub(crate) install_hint: Option<String>,
+}
+
+impl From<String> for EditorLaunchError {
+    fn from(message: String) -> Self {
+        Self {
+            code: "launchFailed".to_string(),
+            message,
+            editor_id: None,
+            install_hint: None,
+        }
+    }
+}
+
+/// Which editor `open_workspace_in` uses and where it opens: `editor_id`, else the
+/// workspace's default editor, at `file` and `line` under the workspace path.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OpenTarget {
+    pub(crate) editor_id: Option<String>,
+    pub(crate) workspace_id: Option<String>,
+    pub(crate) file: Option<String>,
+    pub(crate) line: Option<u32>,
+}
+
+/// A resolved way to open a location: an executable plus its full argument list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct EditorInvocation {
+    pub(crate) program: String,
+    pub(crate) args: Vec<String>,
+}
+
+fn find_editor_definition(editor_id: &str) -> Option<&'static EditorDefinition> {
+    let normalized = editor_id.trim().to_ascii_lowercase();
+    EDITORS.iter().find(|editor| editor.id == normalized)
+}
+
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    let names: Vec<String> = if cfg!(windows) {
+        vec![
+            format!("{name}.cmd"),
+            format!("{name}.exe"),
+            format!("{name}.bat"),
+        ]
+    } else {
+        vec![name.to_string()]
+    };
+    env::split_paths(&path)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|candidate| candidate.is_file())
+}
+
+/// JetBrains Toolbox writes launcher scripts here when shell scripts are enabled.
+fn jetbrains_toolbox_script_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if cfg!(target_os = "windows") {
+        if let Some(local) = env::var_os("LOCALAPPDATA") {
+            dirs.push(
+                PathBuf::from(local)
+                    .join("JetBrains")
+                    .join("Toolbox")
+                    .join("scripts"),
+            );
+        }
+    } else if let Some(home) = env::var_os("HOME") {
+        let home = PathBuf::from(home);
+        if cfg!(target_os = "macos") {
+            dirs.push(
+                home.join("Library")
+                    .join("Application Support")
+                    .join("JetBrains")
+                    .join("Toolbox")
+                    .join("scripts"),
+            );
+        } else {
+            dirs.push(
+                home.join(".local")
+                    .join("share")
+                    .join("JetBrains")
+                    .join("Toolbox")
+                    .join("scripts"),
+            );
+        }
+    }
+    dirs
+}
+
+fn resolve_editor_launcher(editor: &EditorDefinition) -> Option<PathBuf> {
+    for name in editor.cli_names {
+        if let Some(found) = find_on_path(name) {
+            return Some(found);
+        }
+    }
+    if editor.line_style == LineArgStyle::JetBrains {
+        for dir in jetbrains_toolbox_script_dirs() {
+            for name in editor.cli_names {
+                let candidates = if cfg!(windows) {
+                    vec![
+                        dir.join(format!("{name}.cmd")),
+                        dir.join(format!("{name}.bat")),
+                    ]
+                } else {
+                    vec![dir.join(name)]
+                };
+                if let Some(found) = candidates.into_iter().find(|path| path.is_file()) {
+                    return Some(found);
+                }
+            }
+        }
+    }
+    None
+}
+
+fn resolve_target_path(path: &str, file: Option<&str>) -> String {
+    match file.map(str::trim).filter(|value| !value.is_empty()) {
+        Some(file) => {
+            let file_path = Path::new(file);
+            if file_path.is_absolute() {
+                file.to_string()
+            } else {
+                Path::new(path)
+                    .join(file_path)
+                    .to_string_lossy()
+                    .to_string()
+            }
+        }
+        None => path.to_string(),
+    }
+}
+
+fn build_editor_args(
+    style: LineArgStyle,
+    workspace_path: &str,
+    target: &str,
+    line: Option<u32>,
+) -> Vec<String> {
+    let is_file = target != workspace_path;
+    match (style, line.filter(|_| is_file)) {
+        (LineArgStyle::Goto, Some(line)) => vec![
+            workspace_path.to_string(),
+            "--goto".to_string(),
+            format!("{target}:{line}"),
+        ],
+        (LineArgStyle::ColonSuffix, Some(line)) => vec![format!("{target}:{line}")],
+        (LineArgStyle::JetBrains, Some(line)) => {
+            vec!["--line".to_string(), line.to_string(), target.to_string()]
+        }
+        _ => vec![target.to_string()],
+    }
+}
+
+pub(crate) fn has_command_placeholders(command: &str, args: &[String]) -> bool {
+    std::iter::once(command)
+        .chain(args.iter().map(String::as_str))
+        .any(|value| {
+            value.contains(PLACEHOLDER_PATH)
+                || value.contains(PLACEHOLDER_FILE)
+                || value.contains(PLACEHOLDER_LINE)
+        })
+}
+
+/// Expands a custom command template such as `subl {file}:{line}`. `{file}` falls back to
+/// the workspace path and `{line}` to `1` when no location was requested.
+pub(crate) fn expand_command_template(
+    command: &str,
+    args: &[String],
+    path: &str,
+    file: Option<&str>,
+    line: Option<u32>,
+) -> Result<EditorInvocation, String> {
+    let mut tokens = shell_words::split(command.trim())
+        .map_err(|err| format!("Invalid command template: {err}"))?;
+    tokens.extend(args.iter().cloned());
+    let target = resolve_target_path(path, file);
+    let line = line.unwrap_or(1).to_string();
+    let mut expanded = tokens.into_iter().map(|token| {
+        token
+            .replace(PLACEHOLDER_PATH, path)
+            .replace(PLACEHOLDER_FILE, &target)
+            .replace(PLACEHOLDER_LINE, &line)
+    });
+    let program = expanded
+        .next()
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| "Command template is empty.".to_string())?;
+    Ok(EditorInvocation {
+        program,
+        args: expanded.collect(),
+    })
+}
+
+/// Resolves a registry editor (or a custom command target from settings) to a concrete
+/// invocation for `path`, optionally pointing at `file`/`line`.
+pub(crate) fn resolve_editor_invocation(
+    editor_id: &str,
+    custom_targets: &[OpenAppTarget],
+    path: &str,
+    file: Option<&str>,
+    line: Option<u32>,
+) -> Result<EditorInvocation, EditorLaunchError> {
+    if let Some(editor) = find_editor_definition(editor_id) {
+        let launcher = resolve_editor_launcher(editor).ok_or_else(|| EditorLaunchError {
+            code: "editorNotInstalled".to_string(),
+            message: format!("{} was not found on this machine.", editor.label),
+            editor_id: Some(editor.id.to_string()),
+            install_hint: Some(editor.install_hint.to_string()),
+        })?;
+        let target = resolve_target_path(path, file);
+        return Ok(EditorInvocation {
+            program: launcher.to_string_lossy().to_string(),
+            args: build_editor_args(editor.line_style, path, &target, line),
+        });
+    }
+
+    let custom = custom_targets
+        .iter()
+        .find(|target| target.id == editor_id && target.kind == "command");
+    if let Some(command) = custom.and_then(|target| target.command.as_deref()) {
+        let mut args = custom.map(|target| target.args.clone()).unwrap_or_default();
+        if !has_command_placeholders(command, &args) {
+            args.push(PLACEHOLDER_FILE.to_string());
+        }
+        return expand_command_template(command, &args, path, file, line)
+            .map_err(EditorLaunchError::from);
+    }
+
+    let known = EDITORS
+        .iter()
+        .map(|editor| editor.id)
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(EditorLaunchError {
+        code: "unknownEditor".to_string(),
+        message: format!("Unknown editor `{editor_id}`."),
+        editor_id: Some(editor_id.to_string()),
+        install_hint: Some(format!(
+            "Choose one of: {known}, or add a custom command with {{path}}/{{file}}/{{line}} placeholders."
+        )),
+    })
+}
+
+fn app_icon(app_name: Option<&str>) -> Option<String> {
+    #[cfg(target_os = "macos")]
+    {
+        return app_name.and_then(get_open_app_icon_inner);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = app_name;
+        None
+    }
+}
+
+pub(crate) fn list_openable_apps_inner(custom_targets: &[OpenAppTarget]) -> Vec<OpenableApp> {
+    let mut apps = EDITORS
+        .iter()
+        .map(|editor| {
+            let launcher = resolve_editor_launcher(editor);
+            OpenableApp {
+                id: editor.id.to_string(),
+                label: editor.label.to_string(),
+                kind: "editor".to_string(),
+                available: launcher.is_some(),
+                icon: app_icon(editor.app_name),
+                launcher: launcher.map(|path| path.to_string_lossy().to_string()),
+                install_hint: Some(editor.install_hint.to_string()),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    for target in custom_targets {
+        if apps.iter().any(|app| app.id == target.id) {
+            continue;
+        }
+        let (available, launcher) = match target.kind.as_str() {
+            "command" => {
+                let program = target
+                    .command
+                    .as_deref()
+                    .and_then(|command| shell_words::split(command).ok())
+                    .and_then(|tokens| tokens.into_iter().next());
+                let launcher = program.as_deref().and_then(|program| {
+                    let direct = PathBuf::from(program);
+                    if direct.is_absolute() {
+                        direct.is_file().then_some(direct)
+                    } else {
+                        find_on_path(program)
+                    }
+                });
+                (
+                    launcher.is_some(),
+                    launcher.map(|path| path.to_string_lossy().to_string()),
+                )
+            }
+            _ => (true, None),
+        };
+        apps.push(OpenableApp {
+            id: target.id.clone(),
+            label: target.label.clone(),
+            kind: target.kind.clone(),
+            available,
+            icon: app_icon(target.app_name.as_deref()),
+            launcher,
+            install_hint: None,
+        });
+    }
+    apps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_editor_args, expand_command_template, resolve_editor_invocation, LineArgStyle,
+    };
+    use crate::types::OpenAppTarget;
+
+    #[test]
+    fn builds_line_args_per_editor_style() {
+        assert_eq!(
+            build_editor_args(LineArgStyle::Goto, "/repo", "/repo/src/main.rs", Some(12)),
+            vec!["/repo", "--goto", "/repo/src/main.rs:12"]
+        );
+        assert_eq!(
+            build_editor_args(LineArgStyle::ColonSuffix, "/repo", "/repo/a.rs", Some(3)),
+            vec!["/repo/a.rs:3"]
+        );
+        assert_eq!(
+            build_editor_args(LineArgStyle::JetBrains, "/repo", "/repo/a.rs", Some(7)),
+            vec!["--line", "7", "/repo/a.rs"]
+        );
+        assert_eq!(
+            build_editor_args(LineArgStyle::JetBrains, "/repo", "/repo", Some(7)),
+            vec!["/repo"]
+        );
+    }
+
+    #[test]
+    fn expands_command_template_placeholders() {
+        let invocation = expand_command_template(
+            "subl --wait",
+            &["{file}:{line}".to_string(), "--project={path}".to_string()],
+            "/repo",
+            Some("src/lib.rs"),
+            Some(42),
+        )
+        .expect("expand");
+        assert_eq!(invocation.program, "subl");
+        assert_eq!(
+            invocation.args,
+            vec!["--wait", "/repo/src/lib.rs:42", "--project=/repo"]
+        );
+    }
+
+    #[test]
+    fn unknown_editor_returns_structured_error() {
+        let error = resolve_editor_invocation("notepad-xyz", &[], "/repo", None, None)
+            .expect_err("unknown editor");
+        assert_eq!(error.code, "unknownEditor");
+        assert_eq!(error.editor_id.as_deref(), Some("notepad-xyz"));
+        assert!(error.install_hint.unwrap_or_default().contains("vscode"));
+    }
+
+    #[test]
+    fn custom_command_target_appends_file_when_no_placeholders() {
+        let targets = vec![OpenAppTarget {
+            id: "helix".to_string(),
+            label: "Helix".to_string(),
+            kind: "command".to_string(),
+            app_name: None,
+            command: Some("hx".to_string()),
+            args: Vec::new(),
+        }];
+        let invocation =
+            resolve_editor_invocation("helix", &targets, "/repo", Some("/tmp/x.rs"), Some(2))
+                .expect("custom target");
+        assert_eq!(invocation.program, "hx");
+        assert_eq!(invocation.args, vec!["/tmp/x.rs"]);
+    }
+}
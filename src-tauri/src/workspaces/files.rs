@@ -5,6 +5,8 @@ use std::path::PathBuf;
 use ignore::WalkBuilder;
 use serde::{Deserialize, Serialize};
 
+use crate::backend::documents::{read_structured, ReadFormat, StructuredDocument};
+use crate::shared::operations_core::CancellationToken;
 use crate::utils::normalize_git_path;
 
 fn should_skip_dir(name: &str) -> bool {
@@ -14,7 +16,12 @@ fn should_skip_dir(name: &str) -> bool {
     )
 }
 
-pub(crate) fn list_workspace_files_inner(root: &PathBuf, max_files: usize) -> Vec<String> {
+/// Walks `root` for up to `max_files` files; stops early once `token` is cancelled.
+pub(crate) fn list_workspace_files_inner(
+    root: &PathBuf,
+    max_files: usize,
+    token: &CancellationToken,
+) -> Vec<String> {
     let mut results = Vec::new();
     let walker = WalkBuilder::new(root)
         // Allow hidden entries.
@@ -36,6 +43,9 @@ pub(crate) fn list_workspace_files_inner(root: &PathBuf, max_files: usize) -> Ve
         .build();
 
     for entry in walker {
+        if token.is_cancelled() {
+            break;
+        }
         let entry = match entry {
             Ok(entry) => entry,
             Err(_) => continue,
@@ -64,11 +74,15 @@ const MAX_WORKSPACE_FILE_BYTES: u64 = 400_000;
 pub(crate) struct WorkspaceFileResponse {
     content: String,
     truncated: bool,
+    /// Notebook cells or PDF pages; `content` then holds their text rendering.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    document: Option<StructuredDocument>,
 }
 
 pub(crate) fn read_workspace_file_inner(
     root: &PathBuf,
     relative_path: &str,
+    format: ReadFormat,
 ) -> Result<WorkspaceFileResponse, String> {
     let canonical_root = root
         .canonicalize()
@@ -85,6 +99,14 @@ pub(crate) fn read_workspace_file_inner(
     if !metadata.is_file() {
         return Err("Path is not a file".to_string());
     }
+    if let Some(read) = read_structured(&canonical_path, format, MAX_WORKSPACE_FILE_BYTES as usize)?
+    {
+        return Ok(WorkspaceFileResponse {
+            content: read.content,
+            truncated: read.truncated,
+            document: Some(read.document),
+        });
+    }
 
     let file = File::open(&canonical_path).map_err(|err| format!("Failed to open file: {err}"))?;
     let mut buffer = Vec::new();
@@ -98,5 +120,9 @@ pub(crate) fn read_workspace_file_inner(
     }
 
     let content = String::from_utf8(buffer).map_err(|_| "File is not valid UTF-8".to_string())?;
-    Ok(WorkspaceFileResponse { content, truncated })
+    Ok(WorkspaceFileResponse {
+        content,
+        truncated,
+        document: None,
+    })
 }
@@ -1,11 +1,20 @@
 use std::path::PathBuf;
 
 use crate::shared::git_core;
+use crate::shared::operations_core::CancellationToken;
 
 pub(crate) async fn run_git_command(repo_path: &PathBuf, args: &[&str]) -> Result<String, String> {
     git_core::run_git_command(repo_path, args).await
 }
 
+pub(crate) async fn run_git_command_cancellable(
+    repo_path: &PathBuf,
+    args: &[&str],
+    token: &CancellationToken,
+) -> Result<Option<String>, String> {
+    git_core::run_git_command_cancellable(repo_path, args, token).await
+}
+
 pub(crate) async fn run_git_command_owned(
     repo_path: PathBuf,
     args_owned: Vec<String>,
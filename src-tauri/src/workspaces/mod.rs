@@ -1,4 +1,5 @@
 mod commands;
+mod editors;
 mod files;
 mod git;
 mod macos;
@@ -8,6 +8,7 @@ use super::worktree::{
     build_clone_destination_path, sanitize_clone_dir_name, sanitize_worktree_name,
 };
 use crate::backend::app_server::WorkspaceSession;
+use crate::backend::connection_state::ConnectionStates;
 use crate::shared::workspaces_core::rename_worktree_core;
 use crate::storage::{read_workspaces, write_workspaces};
 use crate::types::{
@@ -51,12 +52,31 @@ fn workspace_with_id_and_kind(
             sort_order,
             group_id: None,
             git_root: None,
+            roots: None,
+            context_priming: false,
             agent_home: None,
             agent_args: None,
             launch_script: None,
             launch_scripts: None,
             worktree_setup_script: None,
+            default_editor: None,
+            artifact_globs: None,
+            sampling_params: None,
+            preferred_model: None,
+            redaction: None,
+            audit: None,
+            proxy: None,
+            stack: None,
+            run_kickoff_template: None,
+            usage_counters: None,
+            isolated_agent_home: false,
+            isolated_agent_home_template: None,
+            expected_git_identity: None,
+            prompt_timeout_secs: None,
         },
+        config_stale: false,
+        runtime: None,
+        bootstrap_warnings: Vec::new(),
     }
 }
 
@@ -208,6 +228,7 @@ fn update_workspace_settings_persists_sort_and_group() {
         parent_id: None,
         worktree: None,
         settings: WorkspaceSettings::default(),
+        stack: None,
     };
     let mut workspaces = HashMap::from([(id.clone(), entry)]);
 
@@ -274,6 +295,7 @@ fn rename_worktree_preserves_custom_name() {
             parent_id: None,
             worktree: None,
             settings: WorkspaceSettings::default(),
+            stack: None,
         };
         let worktree = WorkspaceEntry {
             id: "wt-1".to_string(),
@@ -286,6 +308,7 @@ fn rename_worktree_preserves_custom_name() {
                 branch: "feature/old".to_string(),
             }),
             settings: WorkspaceSettings::default(),
+            stack: None,
         };
         let workspaces = Mutex::new(HashMap::from([
             (parent.id.clone(), parent.clone()),
@@ -301,6 +324,7 @@ fn rename_worktree_preserves_custom_name() {
             &temp_dir,
             &workspaces,
             &sessions,
+            &ConnectionStates::default(),
             &app_settings,
             &storage_path,
             |_| Ok(repo_path.clone()),
@@ -312,7 +336,7 @@ fn rename_worktree_preserves_custom_name() {
             |_, _, current| Ok(current.to_path_buf()),
             |_root, _args| async move { Ok(()) },
             |_entry, _default_bin, _micode_args, _micode_home| async move {
-                Err("spawn not expected".to_string())
+                Err("spawn not expected".into())
             },
         )
         .await
@@ -347,6 +371,7 @@ fn rename_worktree_updates_name_when_unmodified() {
             parent_id: None,
             worktree: None,
             settings: WorkspaceSettings::default(),
+            stack: None,
         };
         let worktree = WorkspaceEntry {
             id: "wt-2".to_string(),
@@ -359,6 +384,7 @@ fn rename_worktree_updates_name_when_unmodified() {
                 branch: "feature/old".to_string(),
             }),
             settings: WorkspaceSettings::default(),
+            stack: None,
         };
         let workspaces = Mutex::new(HashMap::from([
             (parent.id.clone(), parent.clone()),
@@ -374,6 +400,7 @@ fn rename_worktree_updates_name_when_unmodified() {
             &temp_dir,
             &workspaces,
             &sessions,
+            &ConnectionStates::default(),
             &app_settings,
             &storage_path,
             |_| Ok(repo_path.clone()),
@@ -385,7 +412,7 @@ fn rename_worktree_updates_name_when_unmodified() {
             |_, _, current| Ok(current.to_path_buf()),
             |_root, _args| async move { Ok(()) },
             |_entry, _default_bin, _micode_args, _micode_home| async move {
-                Err("spawn not expected".to_string())
+                Err("spawn not expected".into())
             },
         )
         .await
@@ -100,7 +100,13 @@ import { useGitCommitController } from "./features/app/hooks/useGitCommitControl
 import { WorkspaceHome } from "./features/workspaces/components/WorkspaceHome";
 import { useWorkspaceHome } from "./features/workspaces/hooks/useWorkspaceHome";
 import { useWorkspaceAgentMd } from "./features/workspaces/hooks/useWorkspaceAgentMd";
-import { pickWorkspacePath, runMiCodeInstallWindows } from "./services/tauri";
+import {
+  CommandError,
+  pickWorkspacePath,
+  runMiCodeInstallWindows,
+  setEventSubscriptions,
+  setFocusedWorkspace,
+} from "./services/tauri";
 import type {
   AccessMode,
   AppSettings,
@@ -276,6 +282,9 @@ function buildSyntheticUsageSnapshot(
 }
 
 function isMiCodeMissingError(error: unknown): boolean {
+  if (error instanceof CommandError) {
+    return error.code === "micodeBinMissing";
+  }
   const message =
     error instanceof Error ? error.message : typeof error === "string" ? error : "";
   const normalized = message.toLowerCase();
@@ -629,6 +638,13 @@ function MainApp() {
   useEffect(() => {
     resetGitHubPanelState();
   }, [activeWorkspaceId, resetGitHubPanelState]);
+
+  useEffect(() => {
+    void setFocusedWorkspace(activeWorkspaceId ?? null).catch(() => {});
+    void setEventSubscriptions(
+      activeWorkspaceId ? [activeWorkspaceId] : "*",
+    ).catch(() => {});
+  }, [activeWorkspaceId]);
   const { remote: gitRemoteUrl } = useGitRemote(activeWorkspace);
   const {
     repos: gitRootCandidates,
@@ -652,7 +668,8 @@ function MainApp() {
   } = useModels({
     activeWorkspace,
     onDebug: addDebugEntry,
-    preferredModelId: appSettings.lastComposerModelId,
+    preferredModelId:
+      activeWorkspace?.settings.preferredModel ?? appSettings.lastComposerModelId,
     preferredEffort: appSettings.lastComposerReasoningEffort,
   });
 
@@ -1734,7 +1751,7 @@ function MainApp() {
   useWorkspaceRestore({
     workspaces,
     hasLoaded,
-    connectWorkspace,
+    activeWorkspaceId,
     listThreadsForWorkspace
   });
   useWorkspaceRefreshOnFocus({
@@ -1,11 +1,12 @@
 import { useEffect, useState } from "react";
-import { getVersion } from "@tauri-apps/api/app";
 import { openUrl } from "@tauri-apps/plugin-opener";
+import { getAppInfo } from "../../../services/tauri";
+import type { AppInfo } from "../../../types";
 
 const GITHUB_URL = "https://github.com/EasonYan7/micode_monitor";
 
 export function AboutView() {
-  const [version, setVersion] = useState<string | null>(null);
+  const [info, setInfo] = useState<AppInfo | null>(null);
 
   const handleOpenGitHub = () => {
     void openUrl(GITHUB_URL);
@@ -13,20 +14,20 @@ export function AboutView() {
 
   useEffect(() => {
     let active = true;
-    const fetchVersion = async () => {
+    const fetchInfo = async () => {
       try {
-        const value = await getVersion();
+        const value = await getAppInfo();
         if (active) {
-          setVersion(value);
+          setInfo(value);
         }
       } catch {
         if (active) {
-          setVersion(null);
+          setInfo(null);
         }
       }
     };
 
-    void fetchVersion();
+    void fetchInfo();
     return () => {
       active = false;
     };
@@ -43,7 +44,16 @@ export function AboutView() {
           />
           <div className="about-title">MiCode Monitor</div>
         </div>
-        <div className="about-version">{version ? `Version ${version}` : "Version -"}</div>
+        <div className="about-version">
+          {info ? `Version ${info.appVersion}` : "Version -"}
+        </div>
+        {info ? (
+          <div className="about-build">
+            {[info.targetTriple, info.gitCommit, info.updateChannel]
+              .filter(Boolean)
+              .join(" · ")}
+          </div>
+        ) : null}
         <div className="about-tagline">Monitor the situation of your MiCode agents</div>
         <div className="about-divider" />
         <div className="about-links">
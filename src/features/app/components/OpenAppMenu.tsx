@@ -1,7 +1,7 @@
 import { useEffect, useMemo, useRef, useState } from "react";
 import ChevronDown from "lucide-react/dist/esm/icons/chevron-down";
 import { revealItemInDir } from "@tauri-apps/plugin-opener";
-import { openWorkspaceIn } from "../../../services/tauri";
+import { EditorLaunchError, openWorkspaceIn } from "../../../services/tauri";
 import { pushErrorToast } from "../../../services/toasts";
 import type { OpenAppTarget } from "../../../types";
 import {
@@ -80,7 +80,11 @@ export function OpenAppMenu({
     fallbackTarget;
 
   const reportOpenError = (error: unknown, target: OpenTarget) => {
-    const message = error instanceof Error ? error.message : String(error);
+    const baseMessage = error instanceof Error ? error.message : String(error);
+    const message =
+      error instanceof EditorLaunchError && error.installHint
+        ? `${baseMessage} ${error.installHint}`
+        : baseMessage;
     pushErrorToast({
       title: "Couldn't open workspace",
       message,
@@ -55,6 +55,25 @@ describe("useAppServerEvents", () => {
       onReasoningSummaryBoundary: vi.fn(),
       onPlanDelta: vi.fn(),
       onApprovalRequest: vi.fn(),
+      onApprovalResolved: vi.fn(),
+      onApprovalReminder: vi.fn(),
+      onTurnPhase: vi.fn(),
+      onTurnTimeout: vi.fn(),
+      onTurnStalled: vi.fn(),
+      onToolSlow: vi.fn(),
+      onTurnResumedStreaming: vi.fn(),
+      onTurnQueued: vi.fn(),
+      onTurnStarted: vi.fn(),
+      onTurnEffortNotApplied: vi.fn(),
+      onTurnCancelled: vi.fn(),
+      onTurnDequeued: vi.fn(),
+      onTurnQueueCleared: vi.fn(),
+      onWorkspaceConnectQueued: vi.fn(),
+      onWorkspaceConnecting: vi.fn(),
+      onWorkspaceConnectFinished: vi.fn(),
+      onAgentReconnecting: vi.fn(),
+      onAgentReconnectFailed: vi.fn(),
+      onNotificationsUnreadChanged: vi.fn(),
       onRequestUserInput: vi.fn(),
       onItemCompleted: vi.fn(),
       onAgentMessageCompleted: vi.fn(),
@@ -201,6 +220,277 @@ describe("useAppServerEvents", () => {
       params: { mode: "full" },
     });
 
+    act(() => {
+      listener?.({
+        workspace_id: "ws-1",
+        message: {
+          method: "workspace/approvalResolved",
+          params: {
+            requestId: 7,
+            threadId: "thread-1",
+            decision: "cancel",
+            resolvedBy: "turn-cancelled",
+          },
+        },
+      });
+    });
+    expect(handlers.onApprovalResolved).toHaveBeenCalledWith("ws-1", 7);
+
+    act(() => {
+      listener?.({
+        workspace_id: "ws-1",
+        message: {
+          method: "workspace/approvalReminder",
+          params: {
+            requestId: 8,
+            threadId: "thread-1",
+            title: "rm -rf build",
+            remainingSecs: 150,
+            timeoutSecs: 300,
+          },
+        },
+      });
+    });
+    expect(handlers.onApprovalReminder).toHaveBeenCalledWith(
+      "ws-1",
+      8,
+      "thread-1",
+      "rm -rf build",
+      150,
+    );
+
+    act(() => {
+      listener?.({
+        workspace_id: "ws-1",
+        message: {
+          method: "turn/phase",
+          params: {
+            threadId: "thread-1",
+            turnId: "turn-1",
+            phase: "tool_running",
+            at: 1700000000000,
+          },
+        },
+      });
+    });
+    expect(handlers.onTurnPhase).toHaveBeenCalledWith(
+      "ws-1",
+      "thread-1",
+      "turn-1",
+      "tool_running",
+      1700000000000,
+    );
+
+    act(() => {
+      listener?.({
+        workspace_id: "ws-1",
+        message: {
+          method: "turn/timeout",
+          params: {
+            threadId: "thread-1",
+            turnId: "turn-1",
+            elapsedMs: 90000,
+            timeoutMs: 90000,
+          },
+        },
+      });
+    });
+    expect(handlers.onTurnTimeout).toHaveBeenCalledWith(
+      "ws-1",
+      "thread-1",
+      "turn-1",
+      90000,
+    );
+
+    act(() => {
+      listener?.({
+        workspace_id: "ws-1",
+        message: {
+          method: "tool/slow",
+          params: {
+            threadId: "thread-1",
+            toolCallId: "call-1",
+            itemId: "tool-call-1",
+            tool: "execute",
+            elapsedMs: 120000,
+            thresholdMs: 120000,
+          },
+        },
+      });
+    });
+    expect(handlers.onToolSlow).toHaveBeenCalledWith(
+      "ws-1",
+      "thread-1",
+      "tool-call-1",
+      120000,
+    );
+
+    act(() => {
+      listener?.({
+        workspace_id: "ws-1",
+        message: {
+          method: "turn/stalled",
+          params: { threadId: "thread-1", turnId: "turn-1", idleSecs: 61 },
+        },
+      });
+      listener?.({
+        workspace_id: "ws-1",
+        message: {
+          method: "turn/resumedStreaming",
+          params: { threadId: "thread-1", turnId: "turn-1", stalledSecs: 75 },
+        },
+      });
+    });
+    expect(handlers.onTurnStalled).toHaveBeenCalledWith(
+      "ws-1",
+      "thread-1",
+      "turn-1",
+      61,
+    );
+    expect(handlers.onTurnResumedStreaming).toHaveBeenCalledWith(
+      "ws-1",
+      "thread-1",
+      "turn-1",
+    );
+
+    act(() => {
+      for (const [method, params] of [
+        ["turn/queued", { threadId: "thread-1", queueId: "q-1", position: 1 }],
+        ["turn/dequeued", { threadId: "thread-1", queueId: "q-1", remaining: 0 }],
+        [
+          "turn/queueCleared",
+          { threadId: "thread-1", cleared: 2, reason: "interrupted" },
+        ],
+      ] as const) {
+        listener?.({ workspace_id: "ws-1", message: { method, params } });
+      }
+    });
+    expect(handlers.onTurnQueued).toHaveBeenCalledWith("ws-1", "thread-1", "q-1", 1);
+    expect(handlers.onTurnDequeued).toHaveBeenCalledWith("ws-1", "thread-1", "q-1", 0);
+    expect(handlers.onTurnQueueCleared).toHaveBeenCalledWith(
+      "ws-1",
+      "thread-1",
+      2,
+      "interrupted",
+    );
+
+    act(() => {
+      for (const effortApplied of ["session", null]) {
+        listener?.({
+          workspace_id: "ws-1",
+          message: {
+            method: "turn/started",
+            params: {
+              threadId: "thread-1",
+              turn: { id: "turn-1", threadId: "thread-1" },
+              effort: "high",
+              effortApplied,
+            },
+          },
+        });
+      }
+    });
+    expect(handlers.onTurnStarted).toHaveBeenCalledTimes(2);
+    expect(handlers.onTurnEffortNotApplied).toHaveBeenCalledTimes(1);
+    expect(handlers.onTurnEffortNotApplied).toHaveBeenCalledWith(
+      "ws-1",
+      "thread-1",
+      "high",
+    );
+
+    act(() => {
+      listener?.({
+        workspace_id: "ws-1",
+        message: {
+          method: "turn/cancelled",
+          params: {
+            threadId: "thread-1",
+            turnId: "turn-2",
+            turn: { id: "turn-2", threadId: "thread-1" },
+            cancelledBy: "user",
+          },
+        },
+      });
+    });
+    expect(handlers.onTurnCancelled).toHaveBeenCalledWith(
+      "ws-1",
+      "thread-1",
+      "turn-2",
+      "user",
+    );
+
+    act(() => {
+      listener?.({
+        workspace_id: "ws-3",
+        message: {
+          method: "workspace/connectQueued",
+          params: { workspaceId: "ws-3", position: 2, queueLength: 4 },
+        },
+      });
+      listener?.({
+        workspace_id: "ws-3",
+        message: {
+          method: "workspace/connecting",
+          params: { workspaceId: "ws-3" },
+        },
+      });
+      listener?.({
+        workspace_id: "ws-3",
+        message: {
+          method: "workspace/connected",
+          params: { workspaceId: "ws-3", ok: false, error: "micode not found" },
+        },
+      });
+    });
+    expect(handlers.onWorkspaceConnectQueued).toHaveBeenCalledWith("ws-3", 2, 4);
+    expect(handlers.onWorkspaceConnecting).toHaveBeenCalledWith("ws-3");
+    expect(handlers.onWorkspaceConnectFinished).toHaveBeenCalledWith("ws-3", {
+      ok: false,
+      error: "micode not found",
+    });
+
+    act(() => {
+      listener?.({
+        workspace_id: "ws-4",
+        message: {
+          method: "micode/reconnecting",
+          params: {
+            workspaceId: "ws-4",
+            attempt: 2,
+            maxAttempts: 3,
+            delayMs: 2000,
+          },
+        },
+      });
+      listener?.({
+        workspace_id: "ws-4",
+        message: {
+          method: "micode/reconnectFailed",
+          params: {
+            workspaceId: "ws-4",
+            attempts: 3,
+            error: "micode not found",
+          },
+        },
+      });
+    });
+    expect(handlers.onAgentReconnecting).toHaveBeenCalledWith("ws-4", 2, 3);
+    expect(handlers.onAgentReconnectFailed).toHaveBeenCalledWith(
+      "ws-4",
+      "micode not found",
+    );
+
+    act(() => {
+      listener?.({
+        workspace_id: "",
+        message: {
+          method: "notifications/unreadChanged",
+          params: { unreadCount: 4 },
+        },
+      });
+    });
+    expect(handlers.onNotificationsUnreadChanged).toHaveBeenCalledWith(4);
+
     act(() => {
       listener?.({
         workspace_id: "ws-1",
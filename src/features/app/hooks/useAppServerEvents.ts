@@ -3,6 +3,7 @@ import type {
   AppServerEvent,
   ApprovalRequest,
   RequestUserInputRequest,
+  TurnPhase,
 } from "../../../types";
 import { subscribeAppServerEvents } from "../../../services/events";
 import {
@@ -35,6 +36,23 @@ type AvailableCommand = {
 
 type AppServerEventHandlers = {
   onWorkspaceConnected?: (workspaceId: string) => void;
+  onWorkspaceConnectQueued?: (
+    workspaceId: string,
+    position: number,
+    queueLength: number,
+  ) => void;
+  onWorkspaceConnecting?: (workspaceId: string) => void;
+  onWorkspaceConnectFinished?: (
+    workspaceId: string,
+    payload: { ok: boolean; error: string | null },
+  ) => void;
+  onAgentReconnecting?: (
+    workspaceId: string,
+    attempt: number,
+    maxAttempts: number,
+  ) => void;
+  onAgentReconnectFailed?: (workspaceId: string, error: string) => void;
+  onNotificationsUnreadChanged?: (unreadCount: number) => void;
   onThreadStarted?: (workspaceId: string, thread: Record<string, unknown>) => void;
   onThreadNameUpdated?: (
     workspaceId: string,
@@ -51,12 +69,27 @@ type AppServerEventHandlers = {
     commands: AvailableCommand[],
   ) => void;
   onApprovalRequest?: (request: ApprovalRequest) => void;
+  onApprovalResolved?: (workspaceId: string, requestId: string | number) => void;
+  onApprovalReminder?: (
+    workspaceId: string,
+    requestId: string | number,
+    threadId: string,
+    title: string | null,
+    remainingSecs: number,
+  ) => void;
   onRequestUserInput?: (request: RequestUserInputRequest) => void;
   onAgentMessageDelta?: (event: AgentDelta) => void;
   onAgentMessageCompleted?: (event: AgentCompleted) => void;
   onAppServerEvent?: (event: AppServerEvent) => void;
   onTurnStarted?: (workspaceId: string, threadId: string, turnId: string) => void;
+  onTurnEffortNotApplied?: (workspaceId: string, threadId: string, effort: string) => void;
   onTurnCompleted?: (workspaceId: string, threadId: string, turnId: string) => void;
+  onTurnCancelled?: (
+    workspaceId: string,
+    threadId: string,
+    turnId: string,
+    cancelledBy: string,
+  ) => void;
   onTurnError?: (
     workspaceId: string,
     threadId: string,
@@ -69,6 +102,54 @@ type AppServerEventHandlers = {
     turnId: string,
     payload: { explanation: unknown; plan: unknown },
   ) => void;
+  onTurnPhase?: (
+    workspaceId: string,
+    threadId: string,
+    turnId: string,
+    phase: TurnPhase,
+    at: number,
+  ) => void;
+  onTurnQueued?: (
+    workspaceId: string,
+    threadId: string,
+    queueId: string,
+    position: number,
+  ) => void;
+  onTurnDequeued?: (
+    workspaceId: string,
+    threadId: string,
+    queueId: string,
+    remaining: number,
+  ) => void;
+  onTurnQueueCleared?: (
+    workspaceId: string,
+    threadId: string,
+    cleared: number,
+    reason: string,
+  ) => void;
+  onTurnTimeout?: (
+    workspaceId: string,
+    threadId: string,
+    turnId: string,
+    elapsedMs: number,
+  ) => void;
+  onTurnStalled?: (
+    workspaceId: string,
+    threadId: string,
+    turnId: string,
+    idleSecs: number,
+  ) => void;
+  onToolSlow?: (
+    workspaceId: string,
+    threadId: string,
+    itemId: string,
+    elapsedMs: number,
+  ) => void;
+  onTurnResumedStreaming?: (
+    workspaceId: string,
+    threadId: string,
+    turnId: string,
+  ) => void;
   onItemStarted?: (workspaceId: string, threadId: string, item: Record<string, unknown>) => void;
   onItemCompleted?: (workspaceId: string, threadId: string, item: Record<string, unknown>) => void;
   onReasoningSummaryDelta?: (workspaceId: string, threadId: string, itemId: string, delta: string) => void;
@@ -107,6 +188,8 @@ export const METHODS_ROUTED_IN_USE_APP_SERVER_EVENTS = [
   "micode/backgroundThread",
   "micode/availableCommands/updated",
   "micode/connected",
+  "micode/reconnectFailed",
+  "micode/reconnecting",
   "error",
   "item/agentMessage/delta",
   "item/commandExecution/outputDelta",
@@ -119,13 +202,29 @@ export const METHODS_ROUTED_IN_USE_APP_SERVER_EVENTS = [
   "item/reasoning/textDelta",
   "item/started",
   "item/tool/requestUserInput",
+  "notifications/unreadChanged",
   "thread/name/updated",
   "thread/started",
   "thread/tokenUsage/updated",
+  "tool/slow",
+  "turn/cancelled",
   "turn/completed",
   "turn/diff/updated",
+  "turn/failed",
+  "turn/phase",
   "turn/plan/updated",
+  "turn/dequeued",
+  "turn/queueCleared",
+  "turn/queued",
+  "turn/resumedStreaming",
+  "turn/stalled",
   "turn/started",
+  "turn/timeout",
+  "workspace/approvalReminder",
+  "workspace/approvalResolved",
+  "workspace/connectQueued",
+  "workspace/connected",
+  "workspace/connecting",
 ] as const satisfies readonly SupportedAppServerMethod[];
 
 export function useAppServerEvents(handlers: AppServerEventHandlers) {
@@ -145,6 +244,50 @@ export function useAppServerEvents(handlers: AppServerEventHandlers) {
         return;
       }
 
+      if (method === "micode/reconnecting") {
+        handlers.onAgentReconnecting?.(
+          workspace_id,
+          Number(params.attempt ?? 0),
+          Number(params.maxAttempts ?? 0),
+        );
+        return;
+      }
+
+      if (method === "micode/reconnectFailed") {
+        handlers.onAgentReconnectFailed?.(
+          workspace_id,
+          String(params.error ?? ""),
+        );
+        return;
+      }
+
+      if (method === "notifications/unreadChanged") {
+        handlers.onNotificationsUnreadChanged?.(Number(params.unreadCount ?? 0));
+        return;
+      }
+
+      if (method === "workspace/connectQueued") {
+        handlers.onWorkspaceConnectQueued?.(
+          workspace_id,
+          Number(params.position ?? 0),
+          Number(params.queueLength ?? 0),
+        );
+        return;
+      }
+
+      if (method === "workspace/connecting") {
+        handlers.onWorkspaceConnecting?.(workspace_id);
+        return;
+      }
+
+      if (method === "workspace/connected") {
+        handlers.onWorkspaceConnectFinished?.(workspace_id, {
+          ok: Boolean(params.ok),
+          error: typeof params.error === "string" ? params.error : null,
+        });
+        return;
+      }
+
       const requestId = getAppServerRequestId(payload);
       const hasRequestId = requestId !== null;
 
@@ -224,6 +367,10 @@ export function useAppServerEvents(handlers: AppServerEventHandlers) {
         const turnId = String(turn?.id ?? params.turnId ?? params.turn_id ?? "");
         if (threadId) {
           handlers.onTurnStarted?.(workspace_id, threadId, turnId);
+          // The agent could not take the requested effort, see `effortApplied`.
+          if (typeof params.effort === "string" && params.effortApplied == null) {
+            handlers.onTurnEffortNotApplied?.(workspace_id, threadId, params.effort);
+          }
         }
         return;
       }
@@ -321,6 +468,165 @@ export function useAppServerEvents(handlers: AppServerEventHandlers) {
         return;
       }
 
+      if (method === "turn/cancelled") {
+        const turn = params.turn as Record<string, unknown> | undefined;
+        const threadId = String(params.threadId ?? turn?.threadId ?? "");
+        const turnId = String(params.turnId ?? turn?.id ?? "");
+        if (threadId) {
+          handlers.onTurnCancelled?.(
+            workspace_id,
+            threadId,
+            turnId,
+            String(params.cancelledBy ?? "user"),
+          );
+        }
+        return;
+      }
+
+      if (method === "turn/failed") {
+        const turn = params.turn as Record<string, unknown> | undefined;
+        const threadId = String(
+          params.threadId ?? params.thread_id ?? turn?.threadId ?? turn?.thread_id ?? "",
+        );
+        const turnId = String(turn?.id ?? params.turnId ?? params.turn_id ?? "");
+        if (threadId) {
+          handlers.onTurnError?.(workspace_id, threadId, turnId, {
+            message: String(params.reason ?? "Turn failed"),
+            willRetry: false,
+          });
+        }
+        return;
+      }
+
+      if (method === "workspace/approvalReminder") {
+        const requestId = params.requestId;
+        if (typeof requestId === "string" || typeof requestId === "number") {
+          handlers.onApprovalReminder?.(
+            workspace_id,
+            requestId,
+            String(params.threadId ?? ""),
+            typeof params.title === "string" ? params.title : null,
+            Number(params.remainingSecs ?? 0),
+          );
+        }
+        return;
+      }
+
+      if (method === "workspace/approvalResolved") {
+        const requestId = params.requestId;
+        if (typeof requestId === "string" || typeof requestId === "number") {
+          handlers.onApprovalResolved?.(workspace_id, requestId);
+        }
+        return;
+      }
+
+      if (method === "turn/phase") {
+        const threadId = String(params.threadId ?? "");
+        const turnId = String(params.turnId ?? "");
+        const phase = params.phase;
+        if (threadId && typeof phase === "string") {
+          handlers.onTurnPhase?.(
+            workspace_id,
+            threadId,
+            turnId,
+            phase as TurnPhase,
+            Number(params.at ?? Date.now()),
+          );
+        }
+        return;
+      }
+
+      if (method === "turn/timeout") {
+        const threadId = String(params.threadId ?? "");
+        const turnId = String(params.turnId ?? "");
+        if (threadId) {
+          handlers.onTurnTimeout?.(
+            workspace_id,
+            threadId,
+            turnId,
+            Number(params.elapsedMs ?? 0),
+          );
+        }
+        return;
+      }
+
+      if (method === "turn/stalled") {
+        const threadId = String(params.threadId ?? "");
+        if (threadId) {
+          handlers.onTurnStalled?.(
+            workspace_id,
+            threadId,
+            String(params.turnId ?? ""),
+            Number(params.idleSecs ?? 0),
+          );
+        }
+        return;
+      }
+
+      if (method === "tool/slow") {
+        const threadId = String(params.threadId ?? "");
+        if (threadId) {
+          handlers.onToolSlow?.(
+            workspace_id,
+            threadId,
+            String(params.itemId ?? ""),
+            Number(params.elapsedMs ?? 0),
+          );
+        }
+        return;
+      }
+
+      if (method === "turn/resumedStreaming") {
+        const threadId = String(params.threadId ?? "");
+        if (threadId) {
+          handlers.onTurnResumedStreaming?.(
+            workspace_id,
+            threadId,
+            String(params.turnId ?? ""),
+          );
+        }
+        return;
+      }
+
+      if (method === "turn/queued") {
+        const threadId = String(params.threadId ?? "");
+        if (threadId) {
+          handlers.onTurnQueued?.(
+            workspace_id,
+            threadId,
+            String(params.queueId ?? ""),
+            Number(params.position ?? 0),
+          );
+        }
+        return;
+      }
+
+      if (method === "turn/dequeued") {
+        const threadId = String(params.threadId ?? "");
+        if (threadId) {
+          handlers.onTurnDequeued?.(
+            workspace_id,
+            threadId,
+            String(params.queueId ?? ""),
+            Number(params.remaining ?? 0),
+          );
+        }
+        return;
+      }
+
+      if (method === "turn/queueCleared") {
+        const threadId = String(params.threadId ?? "");
+        if (threadId) {
+          handlers.onTurnQueueCleared?.(
+            workspace_id,
+            threadId,
+            Number(params.cleared ?? 0),
+            String(params.reason ?? ""),
+          );
+        }
+        return;
+      }
+
       if (method === "turn/plan/updated") {
         const threadId = String(params.threadId ?? params.thread_id ?? "");
         const turnId = String(params.turnId ?? params.turn_id ?? "");
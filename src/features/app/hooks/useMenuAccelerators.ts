@@ -23,12 +23,25 @@ export function useMenuAccelerators({
         if (!active) {
           return;
         }
-        await setMenuAccelerators(
+        const results = await setMenuAccelerators(
           accelerators.map(({ id, shortcut }) => ({
             id,
             accelerator: toMenuAccelerator(shortcut),
           })),
         );
+        const rejected = (results ?? []).filter(
+          (result) =>
+            result.status === "conflicted" || result.status === "invalidSyntax",
+        );
+        if (active && rejected.length > 0) {
+          onError?.(
+            new Error(
+              rejected
+                .map((result) => `${result.id}: ${result.error ?? result.status}`)
+                .join("\n"),
+            ),
+          );
+        }
       } catch (error) {
         onError?.(error);
       }
@@ -1015,6 +1015,11 @@ const MessageRow = memo(function MessageRow({
             onOpenThreadLink={onOpenThreadLink}
           />
         )}
+        {item.partial && (
+          <div className="message-partial-note">
+            Reply cut off: the app closed before it finished.
+          </div>
+        )}
         {lightboxIndex !== null && imageItems.length > 0 && (
           <ImageLightbox
             images={imageItems}
@@ -214,6 +214,7 @@ export function useModels({
           item.defaultReasoningEffort ?? item.default_reasoning_effort,
         ),
         isDefault: Boolean(item.isDefault ?? item.is_default ?? false),
+        source: item.source === "settings" ? "settings" : "bundle",
       }));
       const data = (() => {
         if (!configModelFromConfig) {
@@ -246,6 +246,60 @@ describe("useAgentResponseRequiredNotifications", () => {
     });
   });
 
+  it("surfaces approval reminders while the window is unfocused", async () => {
+    const { rerender } = renderHook(
+      ({ isWindowFocused }) =>
+        useAgentResponseRequiredNotifications({
+          enabled: true,
+          isWindowFocused,
+          approvals: [],
+          userInputRequests: [],
+          getWorkspaceName: () => "Repo",
+        }),
+      { initialProps: { isWindowFocused: true } },
+    );
+
+    const handlersFor = () =>
+      useAppServerEventsMock.mock.calls[useAppServerEventsMock.mock.calls.length - 1]?.[0] as {
+        onApprovalReminder?: (
+          workspaceId: string,
+          requestId: string | number,
+          threadId: string,
+          title: string | null,
+          remainingSecs: number,
+        ) => void;
+      };
+
+    act(() => {
+      handlersFor().onApprovalReminder?.("ws-1", 7, "thread-1", "rm -rf build", 150);
+    });
+    expect(sendNotification).not.toHaveBeenCalled();
+
+    rerender({ isWindowFocused: false });
+    act(() => {
+      handlersFor().onApprovalReminder?.("ws-1", 7, "thread-1", "rm -rf build", 150);
+    });
+
+    await act(async () => {
+      await Promise.resolve();
+    });
+    expect(sendNotification).toHaveBeenCalledTimes(1);
+    expect(vi.mocked(sendNotification).mock.calls[0]).toEqual([
+      "Approval still waiting — Repo",
+      "rm -rf build is cancelled in 3 min unless answered.",
+      {
+        autoCancel: true,
+        extra: {
+          kind: "response_required",
+          type: "approval_reminder",
+          workspaceId: "ws-1",
+          threadId: "thread-1",
+          requestId: 7,
+        },
+      },
+    ]);
+  });
+
   it("notifies again when an approval request ID is reused after resolution", async () => {
     const firstApproval: ApprovalRequest = {
       workspace_id: "ws-1",
@@ -52,7 +52,7 @@ type ResponseRequiredNotificationOptions = {
   onDebug?: (entry: DebugEntry) => void;
 };
 
-type PendingPlanNotification = {
+type PendingNotification = {
   title: string;
   body: string;
   extra: Record<string, unknown>;
@@ -70,10 +70,10 @@ export function useAgentResponseRequiredNotifications({
   const notifiedApprovalsRef = useRef(new Set<string>());
   const notifiedUserInputsRef = useRef(new Set<string>());
   const notifiedPlanItemsRef = useRef(new Set<string>());
-  const pendingPlanNotificationsRef = useRef(new Map<string, PendingPlanNotification>());
+  const pendingNotificationsRef = useRef(new Map<string, PendingNotification>());
   const retryTimeoutRef = useRef<ReturnType<typeof setTimeout> | null>(null);
   const [retrySignal, setRetrySignal] = useState(0);
-  const [pendingPlansSignal, setPendingPlansSignal] = useState(0);
+  const [pendingNotificationsSignal, setPendingNotificationsSignal] = useState(0);
 
   const canNotifyNow = useCallback(() => {
     if (!enabled) {
@@ -280,28 +280,28 @@ export function useAgentResponseRequiredNotifications({
   ]);
 
   useEffect(() => {
-    if (!pendingPlanNotificationsRef.current.size) {
+    if (!pendingNotificationsRef.current.size) {
       return;
     }
     if (!canNotifyNow()) {
       scheduleRetry();
       return;
     }
-    const next = pendingPlanNotificationsRef.current.entries().next().value as
-      | [string, PendingPlanNotification]
+    const next = pendingNotificationsRef.current.entries().next().value as
+      | [string, PendingNotification]
       | undefined;
     if (!next) {
       return;
     }
     const [key, pending] = next;
-    pendingPlanNotificationsRef.current.delete(key);
+    pendingNotificationsRef.current.delete(key);
     notifiedPlanItemsRef.current.add(key);
-    setPendingPlansSignal((value) => value + 1);
+    setPendingNotificationsSignal((value) => value + 1);
     void notify(pending.title, pending.body, pending.extra);
-    if (pendingPlanNotificationsRef.current.size) {
+    if (pendingNotificationsRef.current.size) {
       scheduleRetry();
     }
-  }, [canNotifyNow, notify, pendingPlansSignal, retrySignal, scheduleRetry]);
+  }, [canNotifyNow, notify, pendingNotificationsSignal, retrySignal, scheduleRetry]);
 
   const onItemCompleted = useCallback(
     (workspaceId: string, threadId: string, item: Record<string, unknown>) => {
@@ -319,7 +319,7 @@ export function useAgentResponseRequiredNotifications({
       const key = buildPlanKey(workspaceId, threadId, itemId);
       if (
         notifiedPlanItemsRef.current.has(key) ||
-        pendingPlanNotificationsRef.current.has(key)
+        pendingNotificationsRef.current.has(key)
       ) {
         return;
       }
@@ -337,8 +337,8 @@ export function useAgentResponseRequiredNotifications({
         itemId,
       };
       if (!canNotifyNow()) {
-        pendingPlanNotificationsRef.current.set(key, { title, body, extra });
-        setPendingPlansSignal((value) => value + 1);
+        pendingNotificationsRef.current.set(key, { title, body, extra });
+        setPendingNotificationsSignal((value) => value + 1);
         scheduleRetry();
         return;
       }
@@ -349,12 +349,55 @@ export function useAgentResponseRequiredNotifications({
     [canNotifyNow, getWorkspaceName, notify, scheduleRetry],
   );
 
+  const onApprovalReminder = useCallback(
+    (
+      workspaceId: string,
+      requestId: string | number,
+      threadId: string,
+      title: string | null,
+      remainingSecs: number,
+    ) => {
+      if (!enabled || isWindowFocused) {
+        return;
+      }
+      const key = `reminder:${buildApprovalKey(workspaceId, requestId)}`;
+      if (pendingNotificationsRef.current.has(key)) {
+        return;
+      }
+      const workspaceName = getWorkspaceName?.(workspaceId);
+      const notificationTitle = workspaceName
+        ? `Approval still waiting — ${workspaceName}`
+        : "Approval still waiting";
+      const minutes = Math.max(1, Math.round(remainingSecs / 60));
+      const body = truncateText(
+        `${title?.trim() || "An approval"} is cancelled in ${minutes} min unless answered.`,
+        MAX_BODY_LENGTH,
+      );
+      const extra = {
+        kind: "response_required",
+        type: "approval_reminder",
+        workspaceId,
+        threadId,
+        requestId,
+      };
+      if (!canNotifyNow()) {
+        pendingNotificationsRef.current.set(key, { title: notificationTitle, body, extra });
+        setPendingNotificationsSignal((value) => value + 1);
+        scheduleRetry();
+        return;
+      }
+      void notify(notificationTitle, body, extra);
+    },
+    [canNotifyNow, enabled, getWorkspaceName, isWindowFocused, notify, scheduleRetry],
+  );
+
   useAppServerEvents(
     useMemo(
       () => ({
         onItemCompleted,
+        onApprovalReminder,
       }),
-      [onItemCompleted],
+      [onApprovalReminder, onItemCompleted],
     ),
   );
 }
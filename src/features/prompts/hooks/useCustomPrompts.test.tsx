@@ -11,7 +11,9 @@ vi.mock("../../../services/tauri", () => ({
   getPromptsList: vi.fn(),
   getGlobalPromptsDir: vi.fn(),
   getWorkspacePromptsDir: vi.fn(),
+  listPromptTrash: vi.fn(),
   movePrompt: vi.fn(),
+  restorePrompt: vi.fn(),
   updatePrompt: vi.fn(),
 }));
 
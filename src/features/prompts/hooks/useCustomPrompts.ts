@@ -1,12 +1,20 @@
 import { useCallback, useEffect, useMemo, useRef, useState } from "react";
-import type { CustomPromptOption, DebugEntry, WorkspaceInfo } from "../../../types";
+import type {
+  CustomPromptOption,
+  DebugEntry,
+  PromptRestoreResult,
+  TrashedPrompt,
+  WorkspaceInfo,
+} from "../../../types";
 import {
   createPrompt as createPromptService,
   deletePrompt as deletePromptService,
   getPromptsList,
   getGlobalPromptsDir as getGlobalPromptsDirService,
   getWorkspacePromptsDir as getWorkspacePromptsDirService,
+  listPromptTrash as listPromptTrashService,
   movePrompt as movePromptService,
+  restorePrompt as restorePromptService,
   updatePrompt as updatePromptService,
 } from "../../../services/tauri";
 
@@ -179,6 +187,31 @@ export function useCustomPrompts({ activeWorkspace, onDebug }: UseCustomPromptsO
     [logPromptError, refreshPrompts, requireWorkspaceId],
   );
 
+  const listTrashedPrompts = useCallback(async (): Promise<TrashedPrompt[]> => {
+    const id = requireWorkspaceId();
+    try {
+      return await listPromptTrashService(id);
+    } catch (error) {
+      logPromptError("client-prompts-trash-error", "prompts/trash error", error);
+      throw error;
+    }
+  }, [logPromptError, requireWorkspaceId]);
+
+  const restorePrompt = useCallback(
+    async (path: string): Promise<PromptRestoreResult> => {
+      const id = requireWorkspaceId();
+      try {
+        const result = await restorePromptService(id, path);
+        await refreshPrompts();
+        return result;
+      } catch (error) {
+        logPromptError("client-prompts-restore-error", "prompts/restore error", error);
+        throw error;
+      }
+    },
+    [logPromptError, refreshPrompts, requireWorkspaceId],
+  );
+
   const movePrompt = useCallback(
     async (data: { path: string; scope: "workspace" | "global" }) => {
       const id = requireWorkspaceId();
@@ -221,6 +254,8 @@ export function useCustomPrompts({ activeWorkspace, onDebug }: UseCustomPromptsO
     createPrompt,
     updatePrompt,
     deletePrompt,
+    listTrashedPrompts,
+    restorePrompt,
     movePrompt,
     getWorkspacePromptsDir,
     getGlobalPromptsDir,
@@ -70,11 +70,13 @@ const baseSettings: AppSettings = {
   codeFontSize: 11,
   notificationSoundsEnabled: true,
   systemNotificationsEnabled: true,
+  notificationRetentionDays: 14,
   preloadGitDiffs: true,
   gitDiffIgnoreWhitespaceChanges: false,
   experimentalCollabEnabled: false,
   collaborationModesEnabled: true,
   steerEnabled: true,
+  queueWhenBusy: true,
   unifiedExecEnabled: true,
   experimentalAppsEnabled: false,
   personality: "friendly",
@@ -103,6 +105,34 @@ const baseSettings: AppSettings = {
     },
   ],
   selectedOpenAppId: "vscode",
+  agentUnresponsiveTimeoutSecs: 180,
+  autoRestartUnresponsiveAgent: false,
+  modelSamplingParams: {},
+  autoStoreMaintenance: true,
+  resourceMonitoringEnabled: true,
+  resourceWarningRssMb: 4096,
+  resourceWarningCpuPercent: 90,
+  resourceWarningSustainedSecs: 60,
+  slowCommandThresholdMs: 1000,
+  proxy: { mode: "system", url: null, username: null, noProxy: [] },
+  runKickoffTemplate: null,
+  usageCountersEnabled: false,
+  promptTrashRetentionDays: 30,
+  maxConcurrentConnects: 3,
+  promptTimeoutSecs: 21600,
+  turnStallWarningSecs: 60,
+  toolStallWarningSecs: null,
+  toolSlowWarningSecs: 120,
+  approvalTimeoutSecs: 300,
+  backgroundIdleTimeoutMs: 2000,
+  backgroundMaxWaitSecs: 30,
+  commitMessageDiffMaxBytes: 64 * 1024,
+  toolResultMaxKb: 64,
+  autoTitleThreads: false,
+  persistReasoning: true,
+  archivedThreadRetentionDays: 30,
+  modelPrices: [],
+  settingsRevision: 0,
 };
 
 const createDoctorResult = () => ({
@@ -348,6 +378,31 @@ describe("SettingsView Display", () => {
     });
   });
 
+  it("turns off reasoning persistence", async () => {
+    const onUpdateAppSettings = vi.fn().mockResolvedValue(undefined);
+    renderDisplaySection({ onUpdateAppSettings });
+
+    const row = screen
+      .getByText("Keep reasoning in history")
+      .closest(".settings-toggle-row") as HTMLElement | null;
+    if (!row) {
+      throw new Error("Expected reasoning history row");
+    }
+    const toggle = row.querySelector(
+      "button.settings-toggle",
+    ) as HTMLButtonElement | null;
+    if (!toggle) {
+      throw new Error("Expected reasoning history toggle");
+    }
+    fireEvent.click(toggle);
+
+    await waitFor(() => {
+      expect(onUpdateAppSettings).toHaveBeenCalledWith(
+        expect.objectContaining({ persistReasoning: false }),
+      );
+    });
+  });
+
   it("toggles reduce transparency", () => {
     const onToggleTransparency = vi.fn();
     renderDisplaySection({ onToggleTransparency, reduceTransparency: false });
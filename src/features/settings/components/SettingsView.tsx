@@ -1672,6 +1672,34 @@ export function SettingsView({
                     <span className="settings-toggle-knob" />
                   </button>
                 </div>
+                <div className="settings-toggle-row">
+                  <div>
+                    <div className="settings-toggle-title">
+                      {t("Keep reasoning in history", "在历史中保留推理过程")}
+                    </div>
+                    <div className="settings-toggle-subtitle">
+                      {t(
+                        "Save the agent's reasoning so resumed threads still show it.",
+                        "保存 Agent 的推理过程，恢复会话时仍可查看。",
+                      )}
+                    </div>
+                  </div>
+                  <button
+                    type="button"
+                    className={`settings-toggle ${
+                      appSettings.persistReasoning ? "on" : ""
+                    }`}
+                    onClick={() =>
+                      void onUpdateAppSettings({
+                        ...appSettings,
+                        persistReasoning: !appSettings.persistReasoning,
+                      })
+                    }
+                    aria-pressed={appSettings.persistReasoning}
+                  >
+                    <span className="settings-toggle-knob" />
+                  </button>
+                </div>
                 <div className="settings-toggle-row">
                   <div>
                     <div className="settings-toggle-title">{t("Reduce transparency", "降低透明效果")}</div>
@@ -1,13 +1,18 @@
 // @vitest-environment jsdom
 import { act, cleanup, renderHook, waitFor } from "@testing-library/react";
 import { afterEach, beforeEach, describe, expect, it, vi } from "vitest";
-import type { AppSettings, MiCodeDoctorResult } from "../../../types";
+import type {
+  AppServerEvent,
+  AppSettings,
+  MiCodeDoctorResult,
+} from "../../../types";
 import { useAppSettings } from "./useAppSettings";
 import {
   getAppSettings,
   runMiCodeDoctor,
   updateAppSettings,
 } from "../../../services/tauri";
+import { subscribeAppServerEvents } from "../../../services/events";
 import { UI_SCALE_DEFAULT, UI_SCALE_MAX } from "../../../utils/uiScale";
 
 vi.mock("../../../services/tauri", () => ({
@@ -16,14 +21,35 @@ vi.mock("../../../services/tauri", () => ({
   runMiCodeDoctor: vi.fn(),
 }));
 
+vi.mock("../../../services/events", () => ({
+  subscribeAppServerEvents: vi.fn(),
+}));
+
 const getAppSettingsMock = vi.mocked(getAppSettings);
 const updateAppSettingsMock = vi.mocked(updateAppSettings);
 const runMiCodeDoctorMock = vi.mocked(runMiCodeDoctor);
 
+let listener: ((event: AppServerEvent) => void) | null = null;
+
+function settingsUpdated(scope: string, revision: number): AppServerEvent {
+  return {
+    workspace_id: "",
+    message: {
+      method: "settings/updated",
+      params: { scope, revision, keys: ["theme"], values: { theme: "dark" } },
+    },
+  };
+}
+
 describe("useAppSettings", () => {
   beforeEach(() => {
     vi.clearAllMocks();
     window.localStorage.clear();
+    listener = null;
+    vi.mocked(subscribeAppServerEvents).mockImplementation((cb) => {
+      listener = cb;
+      return () => {};
+    });
   });
 
   afterEach(() => {
@@ -144,6 +170,35 @@ describe("useAppSettings", () => {
     expect(result.current.settings.uiScale).toBe(2.4);
   });
 
+  it("reloads settings saved in another window once they are newer", async () => {
+    getAppSettingsMock.mockResolvedValueOnce({
+      theme: "light",
+      settingsRevision: 3,
+    } as AppSettings);
+    const { result } = renderHook(() => useAppSettings());
+
+    await waitFor(() => expect(result.current.isLoading).toBe(false));
+    expect(result.current.settings.theme).toBe("light");
+
+    act(() => {
+      listener?.(settingsUpdated("app", 3));
+      listener?.(settingsUpdated("workspace", 4));
+    });
+    expect(getAppSettingsMock).toHaveBeenCalledTimes(1);
+
+    getAppSettingsMock.mockResolvedValueOnce({
+      theme: "dark",
+      settingsRevision: 4,
+    } as AppSettings);
+    act(() => {
+      listener?.(settingsUpdated("app", 4));
+    });
+
+    await waitFor(() => expect(result.current.settings.theme).toBe("dark"));
+    expect(result.current.settings.settingsRevision).toBe(4);
+    expect(getAppSettingsMock).toHaveBeenCalledTimes(2);
+  });
+
   it("surfaces doctor errors", async () => {
     getAppSettingsMock.mockResolvedValue({} as AppSettings);
     runMiCodeDoctorMock.mockRejectedValue(new Error("doctor fail"));
@@ -1,6 +1,8 @@
-import { useCallback, useEffect, useState } from "react";
+import { useCallback, useEffect, useRef, useState } from "react";
 import type { AppSettings } from "../../../types";
 import { getAppSettings, runMiCodeDoctor, updateAppSettings } from "../../../services/tauri";
+import { subscribeAppServerEvents } from "../../../services/events";
+import { getSettingsUpdate } from "../../../utils/appServerEvents";
 import { clampUiScale, UI_SCALE_DEFAULT } from "../../../utils/uiScale";
 import {
   DEFAULT_CODE_FONT_FAMILY,
@@ -62,11 +64,13 @@ const defaultSettings: AppSettings = {
   codeFontSize: CODE_FONT_SIZE_DEFAULT,
   notificationSoundsEnabled: true,
   systemNotificationsEnabled: true,
+  notificationRetentionDays: 14,
   preloadGitDiffs: true,
   gitDiffIgnoreWhitespaceChanges: false,
   experimentalCollabEnabled: false,
   collaborationModesEnabled: true,
   steerEnabled: true,
+  queueWhenBusy: true,
   unifiedExecEnabled: true,
   experimentalAppsEnabled: false,
   personality: "friendly",
@@ -86,6 +90,34 @@ const defaultSettings: AppSettings = {
   workspaceGroups: [],
   openAppTargets: DEFAULT_OPEN_APP_TARGETS,
   selectedOpenAppId: DEFAULT_OPEN_APP_ID,
+  agentUnresponsiveTimeoutSecs: 180,
+  autoRestartUnresponsiveAgent: false,
+  modelSamplingParams: {},
+  autoStoreMaintenance: true,
+  resourceMonitoringEnabled: true,
+  resourceWarningRssMb: 4096,
+  resourceWarningCpuPercent: 90,
+  resourceWarningSustainedSecs: 60,
+  slowCommandThresholdMs: 1000,
+  proxy: { mode: "system", url: null, username: null, noProxy: [] },
+  runKickoffTemplate: null,
+  usageCountersEnabled: false,
+  promptTrashRetentionDays: 30,
+  maxConcurrentConnects: 3,
+  promptTimeoutSecs: 6 * 60 * 60,
+  turnStallWarningSecs: 60,
+  toolStallWarningSecs: null,
+  toolSlowWarningSecs: 120,
+  approvalTimeoutSecs: 300,
+  backgroundIdleTimeoutMs: 2000,
+  backgroundMaxWaitSecs: 30,
+  commitMessageDiffMaxBytes: 64 * 1024,
+  toolResultMaxKb: 64,
+  autoTitleThreads: false,
+  persistReasoning: true,
+  archivedThreadRetentionDays: 30,
+  modelPrices: [],
+  settingsRevision: 0,
 };
 
 function readDisplaySettingsFallback(): Partial<DisplaySettingsFallback> {
@@ -200,6 +232,7 @@ function normalizeAppSettings(settings: AppSettings): AppSettings {
 export function useAppSettings() {
   const [settings, setSettings] = useState<AppSettings>(defaultSettings);
   const [isLoading, setIsLoading] = useState(true);
+  const revisionRef = useRef(0);
 
   useEffect(() => {
     let active = true;
@@ -212,6 +245,10 @@ export function useAppSettings() {
       try {
         const response = await getAppSettings();
         if (active) {
+          revisionRef.current = Math.max(
+            revisionRef.current,
+            response.settingsRevision ?? 0,
+          );
           setSettings(
             normalizeAppSettings({
               ...settingsWithFallback,
@@ -234,6 +271,38 @@ export function useAppSettings() {
     };
   }, []);
 
+  // Another window saved settings (or config.toml): reload them once they are newer
+  // than the ones shown here.
+  useEffect(() => {
+    return subscribeAppServerEvents((event) => {
+      const update = getSettingsUpdate(event);
+      if (
+        !update ||
+        (update.scope !== "app" && update.scope !== "micodeConfig") ||
+        update.revision <= revisionRef.current
+      ) {
+        return;
+      }
+      void getAppSettings()
+        .then((response) => {
+          const revision = response.settingsRevision ?? 0;
+          if (revision <= revisionRef.current) {
+            return;
+          }
+          revisionRef.current = revision;
+          setSettings(
+            normalizeAppSettings({
+              ...defaultSettings,
+              ...response,
+            }),
+          );
+        })
+        .catch(() => {
+          // Keep the settings shown; the next update retries.
+        });
+    });
+  }, []);
+
   useEffect(() => {
     writeDisplaySettingsFallback(
       settings.theme,
@@ -245,6 +314,7 @@ export function useAppSettings() {
   const saveSettings = useCallback(async (next: AppSettings) => {
     const normalized = normalizeAppSettings(next);
     const saved = await updateAppSettings(normalized);
+    revisionRef.current = Math.max(revisionRef.current, saved.settingsRevision ?? 0);
     setSettings(
       normalizeAppSettings({
         ...defaultSettings,
@@ -73,6 +73,45 @@ describe("useSkills", () => {
     });
   });
 
+  it("keeps skill sources and reports unreadable skill files", async () => {
+    vi.mocked(getSkillsList).mockResolvedValueOnce({
+      result: {
+        skills: [
+          {
+            id: "deploy",
+            name: "deploy",
+            path: "/repo/.micode/skills/deploy.md",
+            description: "Ship it",
+            source: "workspace",
+          },
+        ],
+        warnings: ["/home/.micode/skills/broken.md: frontmatter is not closed with `---`"],
+      },
+    });
+    const onDebug = vi.fn();
+
+    const { result } = renderHook(() =>
+      useSkills({ activeWorkspace: workspace, onDebug }),
+    );
+
+    await waitFor(() => {
+      expect(result.current.skills).toEqual([
+        {
+          name: "deploy",
+          path: "/repo/.micode/skills/deploy.md",
+          description: "Ship it",
+          source: "workspace",
+        },
+      ]);
+    });
+    expect(onDebug).toHaveBeenCalledWith(
+      expect.objectContaining({
+        label: "skills/list warnings",
+        payload: ["/home/.micode/skills/broken.md: frontmatter is not closed with `---`"],
+      }),
+    );
+  });
+
   it("ignores non-canonical direct skills update methods", async () => {
     vi.mocked(getSkillsList)
       .mockResolvedValueOnce({ result: { skills: [{ name: "first", path: "/skills/first" }] } });
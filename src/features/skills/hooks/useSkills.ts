@@ -52,7 +52,22 @@ export function useSkills({ activeWorkspace, onDebug }: UseSkillsOptions) {
         name: String(item.name ?? ""),
         path: String(item.path ?? ""),
         description: item.description ? String(item.description) : undefined,
+        source:
+          item.source === "global" || item.source === "workspace"
+            ? item.source
+            : undefined,
       }));
+      const warnings: unknown[] =
+        response.result?.warnings ?? response.warnings ?? [];
+      if (warnings.length > 0) {
+        onDebug?.({
+          id: `${Date.now()}-server-skills-list-warnings`,
+          timestamp: Date.now(),
+          source: "error",
+          label: "skills/list warnings",
+          payload: warnings,
+        });
+      }
       setSkills(data);
       lastFetchedWorkspaceId.current = workspaceId;
     } catch (error) {
@@ -49,7 +49,12 @@ describe("useThreadApprovalEvents", () => {
       result.current(approval);
     });
 
-    expect(respondToServerRequest).toHaveBeenCalledWith("ws-1", 42, "accept_always");
+    expect(respondToServerRequest).toHaveBeenCalledWith(
+      "ws-1",
+      42,
+      "accept_always",
+      "auto-rule",
+    );
     expect(dispatch).not.toHaveBeenCalled();
   });
 
@@ -27,6 +27,7 @@ export function useThreadApprovalEvents({
           approval.workspace_id,
           approval.request_id,
           "accept_always",
+          "auto-rule",
         );
         return;
       }
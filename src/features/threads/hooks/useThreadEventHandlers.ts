@@ -156,6 +156,12 @@ export function useThreadEventHandlers({
     dispatch,
     approvalAllowlistRef,
   });
+  const onApprovalResolved = useCallback(
+    (workspaceId: string, requestId: string | number) => {
+      dispatch({ type: "removeApproval", requestId, workspaceId });
+    },
+    [dispatch],
+  );
   const onRequestUserInput = useThreadUserInputEvents({ dispatch });
 
   const {
@@ -186,7 +192,9 @@ export function useThreadEventHandlers({
     onThreadStarted,
     onThreadNameUpdated,
     onTurnStarted,
+    onTurnEffortNotApplied,
     onTurnCompleted,
+    onTurnCancelled,
     onTurnPlanUpdated,
     onThreadTokenUsageUpdated,
     onAccountRateLimitsUpdated,
@@ -315,6 +323,7 @@ export function useThreadEventHandlers({
     () => ({
       onWorkspaceConnected,
       onApprovalRequest,
+      onApprovalResolved,
       onRequestUserInput,
       onBackgroundThreadAction,
       onAppServerEvent,
@@ -332,7 +341,9 @@ export function useThreadEventHandlers({
       onThreadStarted,
       onThreadNameUpdated,
       onTurnStarted,
+      onTurnEffortNotApplied,
       onTurnCompleted,
+      onTurnCancelled,
       onTurnPlanUpdated,
       onThreadTokenUsageUpdated,
       onAccountRateLimitsUpdated,
@@ -341,6 +352,7 @@ export function useThreadEventHandlers({
     [
       onWorkspaceConnected,
       onApprovalRequest,
+      onApprovalResolved,
       onRequestUserInput,
       onBackgroundThreadAction,
       onAppServerEvent,
@@ -358,7 +370,9 @@ export function useThreadEventHandlers({
       onThreadStarted,
       onThreadNameUpdated,
       onTurnStarted,
+      onTurnEffortNotApplied,
       onTurnCompleted,
+      onTurnCancelled,
       onTurnPlanUpdated,
       onThreadTokenUsageUpdated,
       onAccountRateLimitsUpdated,
@@ -9,6 +9,7 @@ import type {
   WorkspaceInfo,
 } from "../../../types";
 import {
+  CommandError,
   compactThread as compactThreadService,
   sendUserMessage as sendUserMessageService,
   startReview as startReviewService,
@@ -211,6 +212,10 @@ export function useThreadMessaging({
           return;
         }
         const result = (response?.result ?? response) as Record<string, unknown>;
+        if (result?.queued === true) {
+          // Starts on its own once the running turn ends, see `turn/dequeued`.
+          return;
+        }
         const turn = (result?.turn ?? response?.turn ?? null) as
           | Record<string, unknown>
           | null;
@@ -224,6 +229,12 @@ export function useThreadMessaging({
         }
         setActiveTurnId(threadId, turnId);
       } catch (error) {
+        if (error instanceof CommandError && error.code === "turnInProgress") {
+          // The running turn keeps the thread busy; only this message was refused.
+          pushThreadErrorMessage(threadId, error.message);
+          safeMessageActivity();
+          return;
+        }
         markProcessing(threadId, false);
         setActiveTurnId(threadId, null);
         onDebug?.({
@@ -670,6 +681,13 @@ export function useThreadMessaging({
                   ? String((authStatus as { status?: unknown }).status ?? "")
                   : "";
             lines.push(`- ${name}${authLabel ? ` (auth: ${authLabel})` : ""}`);
+            if (typeof server.status === "string") {
+              const detail =
+                typeof server.detail === "string" && server.detail
+                  ? ` — ${server.detail}`
+                  : "";
+              lines.push(`  status: ${server.status}${detail}`);
+            }
 
             const toolsRecord =
               server.tools && typeof server.tools === "object"
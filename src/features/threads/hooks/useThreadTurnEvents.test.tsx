@@ -249,6 +249,25 @@ describe("useThreadTurnEvents", () => {
     expect(pendingInterruptsRef.current.has("thread-1")).toBe(false);
   });
 
+  it("ends cancelled turns without a completion hint", () => {
+    const { result, dispatch, markProcessing, setActiveTurnId, pendingInterruptsRef } =
+      makeOptions({
+        pendingInterrupts: ["thread-1"],
+        itemsByThread: {
+          "thread-1": [{ kind: "tool", status: "completed" }],
+        },
+      });
+
+    act(() => {
+      result.current.onTurnCancelled("ws-1", "thread-1", "turn-1");
+    });
+
+    expect(markProcessing).toHaveBeenCalledWith("thread-1", false);
+    expect(setActiveTurnId).toHaveBeenCalledWith("thread-1", null);
+    expect(pendingInterruptsRef.current.has("thread-1")).toBe(false);
+    expect(dispatch).not.toHaveBeenCalled();
+  });
+
   it("adds a completion hint when a turn ends on a completed tool item", () => {
     const { result, dispatch } = makeOptions({
       itemsByThread: {
@@ -128,6 +128,16 @@ export function useThreadTurnEvents({
     [dispatch, markProcessing, pendingInterruptsRef, setActiveTurnId],
   );
 
+  const onTurnEffortNotApplied = useCallback(
+    (_workspaceId: string, threadId: string, effort: string) => {
+      pushThreadErrorMessage(
+        threadId,
+        `Reasoning effort "${effort}" was not applied: this agent only reads it from its settings file, which is shared with other workspaces. Turn on an isolated agent home for this workspace to use it.`,
+      );
+    },
+    [pushThreadErrorMessage],
+  );
+
   const onTurnCompleted = useCallback(
     (_workspaceId: string, threadId: string, turnId: string) => {
       const threadItems = itemsByThread[threadId] ?? [];
@@ -159,6 +169,16 @@ export function useThreadTurnEvents({
     ],
   );
 
+  // The interrupt already told the user the session stopped; no completion hint here.
+  const onTurnCancelled = useCallback(
+    (_workspaceId: string, threadId: string, _turnId: string) => {
+      markProcessing(threadId, false);
+      setActiveTurnId(threadId, null);
+      pendingInterruptsRef.current.delete(threadId);
+    },
+    [markProcessing, pendingInterruptsRef, setActiveTurnId],
+  );
+
   const onTurnPlanUpdated = useCallback(
     (
       workspaceId: string,
@@ -235,7 +255,9 @@ export function useThreadTurnEvents({
     onThreadStarted,
     onThreadNameUpdated,
     onTurnStarted,
+    onTurnEffortNotApplied,
     onTurnCompleted,
+    onTurnCancelled,
     onTurnPlanUpdated,
     onThreadTokenUsageUpdated,
     onAccountRateLimitsUpdated,
@@ -3,6 +3,7 @@ import { act, renderHook, waitFor } from "@testing-library/react";
 import { afterEach, beforeEach, describe, expect, it, vi } from "vitest";
 import { check } from "@tauri-apps/plugin-updater";
 import { relaunch } from "@tauri-apps/plugin-process";
+import { getUpdaterProxy } from "../../../services/tauri";
 import type { DebugEntry } from "../../../types";
 import { useUpdater } from "./useUpdater";
 
@@ -18,7 +19,12 @@ vi.mock("@tauri-apps/plugin-process", () => ({
   relaunch: vi.fn(),
 }));
 
+vi.mock("../../../services/tauri", () => ({
+  getUpdaterProxy: vi.fn(async () => null),
+}));
+
 const checkMock = vi.mocked(check);
+const getUpdaterProxyMock = vi.mocked(getUpdaterProxy);
 const relaunchMock = vi.mocked(relaunch);
 
 describe("useUpdater", () => {
@@ -74,6 +80,40 @@ describe("useUpdater", () => {
     expect(result.current.state.stage).toBe("idle");
   });
 
+  it("checks through the configured proxy", async () => {
+    getUpdaterProxyMock.mockResolvedValueOnce({
+      url: "http://proxy.corp:3128",
+      hasCredentials: false,
+    });
+    checkMock.mockResolvedValue(null);
+    const { result } = renderHook(() => useUpdater({}));
+
+    await act(async () => {
+      await result.current.checkForUpdates();
+    });
+
+    expect(checkMock).toHaveBeenCalledWith({ proxy: "http://proxy.corp:3128" });
+  });
+
+  it("checks directly when the proxy needs a password", async () => {
+    getUpdaterProxyMock.mockResolvedValueOnce({
+      url: "http://alice@proxy.corp:3128",
+      hasCredentials: true,
+    });
+    checkMock.mockResolvedValue(null);
+    const onDebug = vi.fn();
+    const { result } = renderHook(() => useUpdater({ onDebug }));
+
+    await act(async () => {
+      await result.current.checkForUpdates();
+    });
+
+    expect(checkMock).toHaveBeenCalledWith(undefined);
+    expect(onDebug).toHaveBeenCalledWith(
+      expect.objectContaining({ label: "updater/proxy" }),
+    );
+  });
+
   it("announces when no update is available for manual checks", async () => {
     vi.useFakeTimers();
     checkMock.mockResolvedValue(null);
@@ -3,6 +3,7 @@ import { isTauri } from "@tauri-apps/api/core";
 import { check } from "@tauri-apps/plugin-updater";
 import { relaunch } from "@tauri-apps/plugin-process";
 import type { DownloadEvent, Update } from "@tauri-apps/plugin-updater";
+import { getUpdaterProxy } from "../../../services/tauri";
 import type { DebugEntry } from "../../../types";
 
 type UpdateStage =
@@ -63,7 +64,21 @@ export function useUpdater({ enabled = true, onDebug }: UseUpdaterOptions) {
       await previousUpdate?.close();
       clearLatestTimeout();
       setState({ stage: "checking" });
-      update = await check();
+      const proxy = await getUpdaterProxy().catch(() => null);
+      if (proxy?.hasCredentials) {
+        // The password stays in the backend, so the updater cannot authenticate
+        // against this proxy and checks directly instead.
+        onDebug?.({
+          id: `${Date.now()}-client-updater-proxy`,
+          timestamp: Date.now(),
+          source: "client",
+          label: "updater/proxy",
+          payload: `Skipping authenticated proxy ${proxy.url} for the update check`,
+        });
+      }
+      update = await check(
+        proxy && !proxy.hasCredentials ? { proxy: proxy.url } : undefined,
+      );
       if (!update) {
         if (options?.announceNoUpdate) {
           setState({ stage: "latest" });
@@ -1,6 +1,6 @@
 import { useCallback, useEffect, useMemo, useRef, useState } from "react";
 import type { DebugEntry, WorkspaceInfo } from "../../../types";
-import { getWorkspaceFiles } from "../../../services/tauri";
+import { cancelOperation, getWorkspaceFiles } from "../../../services/tauri";
 
 type UseWorkspaceFilesOptions = {
   activeWorkspace: WorkspaceInfo | null;
@@ -24,6 +24,13 @@ function areStringArraysEqual(a: string[], b: string[]) {
   return true;
 }
 
+function createOperationId() {
+  if (typeof crypto !== "undefined" && typeof crypto.randomUUID === "function") {
+    return crypto.randomUUID();
+  }
+  return `files-${Date.now()}-${Math.random().toString(36).slice(2)}`;
+}
+
 export function useWorkspaceFiles({
   activeWorkspace,
   onDebug,
@@ -34,6 +41,7 @@ export function useWorkspaceFiles({
   const [isLoading, setIsLoading] = useState(false);
   const lastFetchedWorkspaceId = useRef<string | null>(null);
   const inFlight = useRef<string | null>(null);
+  const inFlightOperationId = useRef<string | null>(null);
 
   const REFRESH_INTERVAL_MS = 5000;
   const LARGE_REFRESH_INTERVAL_MS = 20000;
@@ -52,11 +60,13 @@ export function useWorkspaceFiles({
     }
     inFlight.current = workspaceId;
     const requestWorkspaceId = workspaceId;
+    const operationId = createOperationId();
+    inFlightOperationId.current = operationId;
     setIsLoading(true);
     try {
-      const response = await getWorkspaceFiles(requestWorkspaceId);
-      if (requestWorkspaceId === workspaceId) {
-        const nextFiles = Array.isArray(response) ? response : [];
+      const outcome = await getWorkspaceFiles(requestWorkspaceId, operationId);
+      if (outcome.status === "completed" && requestWorkspaceId === workspaceId) {
+        const nextFiles = Array.isArray(outcome.result) ? outcome.result : [];
         setFiles((prev) => (areStringArraysEqual(prev, nextFiles) ? prev : nextFiles));
         lastFetchedWorkspaceId.current = requestWorkspaceId;
       }
@@ -69,6 +79,9 @@ export function useWorkspaceFiles({
         payload: error instanceof Error ? error.message : String(error),
       });
     } finally {
+      if (inFlightOperationId.current === operationId) {
+        inFlightOperationId.current = null;
+      }
       if (inFlight.current === requestWorkspaceId) {
         inFlight.current = null;
         setIsLoading(false);
@@ -80,6 +93,14 @@ export function useWorkspaceFiles({
     setFiles([]);
     lastFetchedWorkspaceId.current = null;
     inFlight.current = null;
+    return () => {
+      // A listing for the workspace we are leaving would only be thrown away.
+      const operationId = inFlightOperationId.current;
+      inFlightOperationId.current = null;
+      if (operationId) {
+        cancelOperation(operationId).catch(() => {});
+      }
+    };
   }, [isConnected, workspaceId]);
 
   useEffect(() => {
@@ -1,10 +1,11 @@
 import { useEffect, useRef } from "react";
 import type { WorkspaceInfo } from "../../../types";
+import { connectAllWorkspaces } from "../../../services/tauri";
 
 type WorkspaceRestoreOptions = {
   workspaces: WorkspaceInfo[];
   hasLoaded: boolean;
-  connectWorkspace: (workspace: WorkspaceInfo) => Promise<void>;
+  activeWorkspaceId: string | null;
   listThreadsForWorkspace: (
     workspace: WorkspaceInfo,
     options?: { preserveState?: boolean },
@@ -14,7 +15,7 @@ type WorkspaceRestoreOptions = {
 export function useWorkspaceRestore({
   workspaces,
   hasLoaded,
-  connectWorkspace,
+  activeWorkspaceId,
   listThreadsForWorkspace,
 }: WorkspaceRestoreOptions) {
   const restoredWorkspaces = useRef(new Set<string>());
@@ -23,21 +24,30 @@ export function useWorkspaceRestore({
     if (!hasLoaded) {
       return;
     }
-    workspaces.forEach((workspace) => {
-      if (restoredWorkspaces.current.has(workspace.id)) {
-        return;
-      }
-      restoredWorkspaces.current.add(workspace.id);
-      void (async () => {
+    const pending = workspaces.filter(
+      (workspace) => !restoredWorkspaces.current.has(workspace.id),
+    );
+    if (pending.length === 0) {
+      return;
+    }
+    pending.forEach((workspace) => restoredWorkspaces.current.add(workspace.id));
+    const disconnectedIds = pending
+      .filter((workspace) => !workspace.connected)
+      .map((workspace) => workspace.id);
+    void (async () => {
+      if (disconnectedIds.length > 0) {
         try {
-          if (!workspace.connected) {
-            await connectWorkspace(workspace);
-          }
-          await listThreadsForWorkspace(workspace);
+          // One call; the backend connects a few at a time, active workspace first.
+          await connectAllWorkspaces(disconnectedIds, activeWorkspaceId);
         } catch {
           // Silent: connection errors show in debug panel.
         }
-      })();
-    });
-  }, [connectWorkspace, hasLoaded, listThreadsForWorkspace, workspaces]);
+      }
+      await Promise.all(
+        pending.map((workspace) =>
+          listThreadsForWorkspace(workspace).catch(() => undefined),
+        ),
+      );
+    })();
+  }, [activeWorkspaceId, hasLoaded, listThreadsForWorkspace, workspaces]);
 }
@@ -1,7 +1,8 @@
 // @vitest-environment jsdom
 import { act, renderHook } from "@testing-library/react";
 import { describe, expect, it, vi } from "vitest";
-import type { WorkspaceInfo } from "../../../types";
+import type { AppServerEvent, WorkspaceInfo } from "../../../types";
+import { subscribeAppServerEvents } from "../../../services/events";
 import {
   addWorkspace,
   listWorkspaces,
@@ -27,6 +28,10 @@ vi.mock("../../../services/tauri", () => ({
   updateWorkspaceSettings: vi.fn(),
 }));
 
+vi.mock("../../../services/events", () => ({
+  subscribeAppServerEvents: vi.fn(() => () => {}),
+}));
+
 const worktree: WorkspaceInfo = {
   id: "wt-1",
   name: "feature/old",
@@ -217,6 +222,55 @@ describe("useWorkspaces.updateWorkspaceSettings", () => {
   });
 });
 
+let settingsListener: ((event: AppServerEvent) => void) | null = null;
+
+describe("useWorkspaces settings/updated", () => {
+  it("reloads workspaces when another window saves workspace settings", async () => {
+    vi.mocked(subscribeAppServerEvents).mockImplementation((cb) => {
+      settingsListener = cb;
+      return () => {};
+    });
+    const listWorkspacesMock = vi.mocked(listWorkspaces);
+    listWorkspacesMock.mockReset();
+    listWorkspacesMock.mockResolvedValueOnce([workspaceOne]).mockResolvedValueOnce([
+      { ...workspaceOne, settings: { ...workspaceOne.settings, sidebarCollapsed: true } },
+    ]);
+
+    const { result } = renderHook(() => useWorkspaces());
+
+    await act(async () => {
+      await Promise.resolve();
+    });
+
+    await act(async () => {
+      settingsListener?.({
+        workspace_id: "",
+        message: {
+          method: "settings/updated",
+          params: { scope: "app", revision: 1, keys: [], values: {} },
+        },
+      });
+      settingsListener?.({
+        workspace_id: workspaceOne.id,
+        message: {
+          method: "settings/updated",
+          params: {
+            scope: "workspace",
+            workspaceId: workspaceOne.id,
+            revision: 2,
+            keys: ["sidebarCollapsed"],
+            values: { sidebarCollapsed: true },
+          },
+        },
+      });
+      await Promise.resolve();
+    });
+
+    expect(listWorkspacesMock).toHaveBeenCalledTimes(2);
+    expect(result.current.workspaces[0]?.settings.sidebarCollapsed).toBe(true);
+  });
+});
+
 describe("useWorkspaces.addWorkspaceFromPath", () => {
   it("adds a workspace and sets it active", async () => {
     const listWorkspacesMock = vi.mocked(listWorkspaces);
@@ -23,6 +23,8 @@ import {
   updateWorkspaceMiCodeBin as updateWorkspaceMiCodeBinService,
   updateWorkspaceSettings as updateWorkspaceSettingsService,
 } from "../../../services/tauri";
+import { subscribeAppServerEvents } from "../../../services/events";
+import { getSettingsUpdate } from "../../../utils/appServerEvents";
 
 const GROUP_ID_RANDOM_MODULUS = 1_000_000;
 const RESERVED_GROUP_NAME = "Ungrouped";
@@ -107,6 +109,15 @@ export function useWorkspaces(options: UseWorkspacesOptions = {}) {
     void refreshWorkspaces();
   }, [refreshWorkspaces]);
 
+  // Workspace settings saved in another window.
+  useEffect(() => {
+    return subscribeAppServerEvents((event) => {
+      if (getSettingsUpdate(event)?.scope === "workspace") {
+        void refreshWorkspaces();
+      }
+    });
+  }, [refreshWorkspaces]);
+
   useEffect(() => {
     const next = new Map<string, WorkspaceSettings>();
     workspaces.forEach((entry) => {
@@ -328,6 +339,7 @@ export function useWorkspaces(options: UseWorkspacesOptions = {}) {
     source: WorkspaceInfo,
     copyName: string,
     copiesFolder: string,
+    operationId: string | null = null,
   ) {
     const trimmedName = copyName.trim();
     if (!trimmedName) {
@@ -349,7 +361,17 @@ export function useWorkspaces(options: UseWorkspacesOptions = {}) {
       },
     });
     try {
-      const workspace = await addCloneService(source.id, trimmedFolder, trimmedName);
+      const outcome = await addCloneService(
+        source.id,
+        trimmedFolder,
+        trimmedName,
+        operationId,
+      );
+      // Cancelled through `cancelOperation`; the backend removed the partial clone.
+      const workspace = outcome.result;
+      if (outcome.status === "cancelled" || !workspace) {
+        return null;
+      }
       setWorkspaces((prev) => [...prev, workspace]);
       setActiveWorkspaceId(workspace.id);
       return workspace;
@@ -673,15 +695,27 @@ export function useWorkspaces(options: UseWorkspacesOptions = {}) {
       return;
     }
 
+    const deleteAgentHome = workspace?.settings.isolatedAgentHome
+      ? await ask(
+          `"${workspaceName}" runs with an isolated agent home.\n\nDelete its agent home as well? Auth and settings stored there will be lost.`,
+          {
+            title: "Delete Agent Home",
+            kind: "warning",
+            okLabel: "Delete",
+            cancelLabel: "Keep",
+          },
+        )
+      : false;
+
     onDebug?.({
       id: `${Date.now()}-client-remove-workspace`,
       timestamp: Date.now(),
       source: "client",
       label: "workspace/remove",
-      payload: { workspaceId },
+      payload: { workspaceId, deleteAgentHome },
     });
     try {
-      await removeWorkspaceService(workspaceId);
+      await removeWorkspaceService(workspaceId, deleteAgentHome);
       setWorkspaces((prev) =>
         prev.filter(
           (entry) =>
@@ -3,27 +3,47 @@ import { invoke } from "@tauri-apps/api/core";
 import * as notification from "@tauri-apps/plugin-notification";
 import {
   addWorkspace,
+  applyExtractedPatch,
+  approvalRulesDelete,
+  approvalRulesExport,
+  approvalRulesImport,
+  approvalRulesList,
+  approvalRulesUpdate,
+  buildRunKickoffMessage,
+  commitGit,
+  CommandError,
   compactThread,
+  extractPromptFromThread,
   fetchGit,
   forkThread,
+  generateChangelog,
+  generatePrDescription,
   getGitHubIssues,
   getGitLog,
   getGitStatus,
   getOpenAppIcon,
   listMcpServerStatus,
+  addMcpServer,
+  removeMcpServer,
   listApprovalRules,
   readGlobalAgentsMd,
   readGlobalMiCodeConfigToml,
   listWorkspaces,
   openWorkspaceIn,
   readAgentMd,
+  rememberApprovalRule,
   removeApprovalRule,
   stageGitAll,
   respondToServerRequest,
   respondToUserInputRequest,
+  revertGitAll,
+  runSlashCommand,
   sendUserMessage,
+  setGitIdentity,
   sendNotification,
+  setEventSubscriptions,
   startReview,
+  setFileReviewState,
   setThreadName,
   updateAppSettings,
   updateWorkspaceSettings,
@@ -216,6 +236,21 @@ describe("tauri invoke wrappers", () => {
     });
   });
 
+  it("sends the wildcard as a list for set_event_subscriptions", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValue(undefined);
+
+    await setEventSubscriptions(["ws-1"]);
+    await setEventSubscriptions("*");
+
+    expect(invokeMock).toHaveBeenNthCalledWith(1, "set_event_subscriptions", {
+      workspaceIds: ["ws-1"],
+    });
+    expect(invokeMock).toHaveBeenNthCalledWith(2, "set_event_subscriptions", {
+      workspaceIds: ["*"],
+    });
+  });
+
   it("maps workspaceId/cursor/limit for list_mcp_server_status", async () => {
     const invokeMock = vi.mocked(invoke);
     invokeMock.mockResolvedValueOnce({});
@@ -229,6 +264,87 @@ describe("tauri invoke wrappers", () => {
     });
   });
 
+  it("passes the server name and config to the MCP server commands", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValue(undefined);
+
+    await addMcpServer("ws-10", "docs", { url: "https://mcp.example.com" });
+    await removeMcpServer("ws-10", "docs");
+
+    expect(invokeMock).toHaveBeenCalledWith("mcp_server_add", {
+      workspaceId: "ws-10",
+      name: "docs",
+      config: { url: "https://mcp.example.com" },
+    });
+    expect(invokeMock).toHaveBeenCalledWith("mcp_server_remove", {
+      workspaceId: "ws-10",
+      name: "docs",
+    });
+  });
+
+  it("passes the optional base branch to generate_pr_description", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValueOnce({ title: "Add retries", body: "## Summary" });
+    invokeMock.mockResolvedValueOnce({ title: "Add retries", body: "## Summary" });
+
+    await expect(generatePrDescription("ws-11")).resolves.toEqual({
+      title: "Add retries",
+      body: "## Summary",
+    });
+    await generatePrDescription("ws-11", "origin/develop");
+
+    expect(invokeMock).toHaveBeenCalledWith("generate_pr_description", {
+      workspaceId: "ws-11",
+      base: null,
+    });
+    expect(invokeMock).toHaveBeenCalledWith("generate_pr_description", {
+      workspaceId: "ws-11",
+      base: "origin/develop",
+    });
+  });
+
+  it("sends the ref range and style to generate_changelog", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValue("## [Unreleased]\n");
+
+    await generateChangelog("ws-12", "v1.0.0");
+    await generateChangelog("ws-12", null, "release/2.0", "plain");
+
+    expect(invokeMock).toHaveBeenCalledWith("generate_changelog", {
+      workspaceId: "ws-12",
+      fromRef: "v1.0.0",
+      toRef: null,
+      style: "keep-a-changelog",
+    });
+    expect(invokeMock).toHaveBeenCalledWith("generate_changelog", {
+      workspaceId: "ws-12",
+      fromRef: null,
+      toRef: "release/2.0",
+      style: "plain",
+    });
+  });
+
+  it("sends the command name and args to run_slash_command", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValue({});
+
+    await runSlashCommand("ws-13", "thread-13", "review", "src/lib.rs");
+    await runSlashCommand("ws-13", "thread-13", "init");
+
+    expect(invokeMock).toHaveBeenCalledWith("run_slash_command", {
+      workspaceId: "ws-13",
+      threadId: "thread-13",
+      commandName: "review",
+      args: "src/lib.rs",
+    });
+    expect(invokeMock).toHaveBeenCalledWith("run_slash_command", {
+      workspaceId: "ws-13",
+      threadId: "thread-13",
+      commandName: "init",
+      args: null,
+    });
+  });
+
   it("invokes stage_git_all", async () => {
     const invokeMock = vi.mocked(invoke);
     invokeMock.mockResolvedValueOnce({});
@@ -240,6 +356,127 @@ describe("tauri invoke wrappers", () => {
     });
   });
 
+  it("applies extracted patches through the git mutation path", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockRejectedValueOnce({
+      code: "gitCommandFailed",
+      message: "context does not match `src/lib.rs` at line 12",
+      retryable: false,
+    });
+
+    const error = await applyExtractedPatch("ws-6", "thread-1", "agent-1", 0, {
+      stage: true,
+      root: "api",
+    }).catch((err: unknown) => err);
+
+    expect(invokeMock).toHaveBeenCalledWith("apply_extracted_patch", {
+      workspaceId: "ws-6",
+      threadId: "thread-1",
+      itemId: "agent-1",
+      index: 0,
+      stage: true,
+      root: "api",
+    });
+    expect(error).toBeInstanceOf(CommandError);
+    expect((error as CommandError).code).toBe("gitCommandFailed");
+    expect((error as CommandError).message).toBe(
+      "context does not match `src/lib.rs` at line 12",
+    );
+  });
+
+  it("rejects busy git mutations with a repositoryBusy CommandError", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockRejectedValueOnce({
+      code: "repositoryBusy",
+      message: "Repository is busy with stage; commit was not started.",
+      details: { heldBy: "stage" },
+      retryable: true,
+    });
+
+    const error = await commitGit("ws-6", "msg").catch((err: unknown) => err);
+
+    expect(error).toBeInstanceOf(CommandError);
+    expect((error as CommandError).code).toBe("repositoryBusy");
+    expect((error as CommandError).details).toEqual({ heldBy: "stage" });
+    expect((error as CommandError).retryable).toBe(true);
+    expect((error as CommandError).message).toContain("busy with stage");
+  });
+
+  it("rejects with CommandError for structured command failures", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockRejectedValueOnce({
+      code: "workspaceNotConnected",
+      message: "workspace not connected",
+      retryable: true,
+    });
+
+    const error = await forkThread("ws-1", "thread-1").catch((err: unknown) => err);
+
+    expect(error).toBeInstanceOf(CommandError);
+    expect((error as CommandError).code).toBe("workspaceNotConnected");
+    expect((error as CommandError).retryable).toBe(true);
+    expect((error as CommandError).message).toBe("workspace not connected");
+
+    invokeMock.mockRejectedValueOnce("plain failure");
+    await expect(fetchGit("ws-1")).rejects.toBe("plain failure");
+  });
+
+  it("surfaces identityMismatch with both identities and allows overriding", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockRejectedValueOnce({
+      code: "identityMismatch",
+      message: "Git would commit as Dev <dev@home.example>",
+      expected: { name: null, email: "dev@client.example" },
+      actual: { name: "Dev", email: "dev@home.example" },
+    });
+    invokeMock.mockResolvedValueOnce(undefined);
+
+    const error = await commitGit("ws-6", "msg").catch((err: unknown) => err);
+    await commitGit("ws-6", "msg", null, { allowMismatch: true });
+
+    expect((error as GitOperationError).code).toBe("identityMismatch");
+    expect((error as GitOperationError).expected?.email).toBe(
+      "dev@client.example",
+    );
+    expect((error as GitOperationError).actual?.email).toBe("dev@home.example");
+    expect(invokeMock).toHaveBeenNthCalledWith(2, "commit_git", {
+      workspaceId: "ws-6",
+      message: "msg",
+      allowMismatch: true,
+    });
+  });
+
+  it("sets the repository-local git identity", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValueOnce({});
+
+    await setGitIdentity("ws-6", { email: "dev@client.example" });
+
+    expect(invokeMock).toHaveBeenCalledWith("set_git_identity", {
+      workspaceId: "ws-6",
+      name: null,
+      email: "dev@client.example",
+    });
+  });
+
+  it("passes skipBackup to revert_git_all and returns the trash id", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValueOnce({ trashId: "1700000000000", warnings: [] });
+
+    const result = await revertGitAll("ws-6");
+    await revertGitAll("ws-6", null, { skipBackup: true });
+
+    expect(result.trashId).toBe("1700000000000");
+    expect(invokeMock).toHaveBeenNthCalledWith(1, "revert_git_all", {
+      workspaceId: "ws-6",
+      skipBackup: false,
+    });
+    expect(invokeMock).toHaveBeenNthCalledWith(2, "revert_git_all", {
+      workspaceId: "ws-6",
+      skipBackup: true,
+    });
+  });
+
   it("invokes fetch_git", async () => {
     const invokeMock = vi.mocked(invoke);
     invokeMock.mockResolvedValueOnce({});
@@ -265,6 +502,12 @@ describe("tauri invoke wrappers", () => {
       app: "Xcode",
       command: null,
       args: ["--reuse-window"],
+      target: {
+        editorId: null,
+        workspaceId: null,
+        file: null,
+        line: null,
+      },
     });
   });
 
@@ -393,6 +636,102 @@ describe("tauri invoke wrappers", () => {
     });
   });
 
+  it("passes review context options when requested", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValueOnce({});
+
+    await startReview(
+      "ws-5",
+      "thread-2",
+      { type: "baseBranch", branch: "main" },
+      undefined,
+      { byteBudget: 32768, depth: 2 },
+    );
+
+    expect(invokeMock).toHaveBeenCalledWith("start_review", {
+      workspaceId: "ws-5",
+      threadId: "thread-2",
+      target: { type: "baseBranch", branch: "main" },
+      context: { byteBudget: 32768, depth: 2 },
+    });
+  });
+
+  it("defaults prompt extraction to a saved, verbatim prompt", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValueOnce({
+      promptId: "/prompts/fix-test.md",
+      prompt: null,
+      text: "Fix the flaky test",
+      generalized: false,
+      variables: [],
+    });
+
+    const result = await extractPromptFromThread("ws-5", {
+      threadId: "thread-2",
+      itemIds: ["user-thread-2-turn-1"],
+      scope: "workspace",
+      name: "fix-test",
+    });
+
+    expect(result.promptId).toBe("/prompts/fix-test.md");
+    expect(invokeMock).toHaveBeenCalledWith("extract_prompt_from_thread", {
+      workspaceId: "ws-5",
+      request: {
+        threadId: "thread-2",
+        itemIds: ["user-thread-2-turn-1"],
+        scope: "workspace",
+        name: "fix-test",
+        description: null,
+        generalize: false,
+        dryRun: false,
+        requestId: null,
+      },
+    });
+  });
+
+  it("renders run kickoffs without a branch by default", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValueOnce({
+      message: "Fix the login loop",
+      template: { templateId: "default", templateHash: "0123456789ab" },
+    });
+
+    const kickoff = await buildRunKickoffMessage("ws-5", "Fix the login loop");
+
+    expect(kickoff.template.templateId).toBe("default");
+    expect(invokeMock).toHaveBeenCalledWith("build_run_kickoff_message", {
+      workspaceId: "ws-5",
+      task: "Fix the login loop",
+      branch: null,
+    });
+  });
+
+  it("sends an explicit null note when marking a file reviewed", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValueOnce({
+      threadId: "thread-2",
+      turnId: "turn-1",
+      files: [],
+      progress: { reviewed: 1, total: 3 },
+    });
+
+    const review = await setFileReviewState(
+      "ws-5",
+      "turn-1",
+      "src/lib.rs",
+      "reviewed",
+    );
+
+    expect(review.progress).toEqual({ reviewed: 1, total: 3 });
+    expect(invokeMock).toHaveBeenCalledWith("set_file_review_state", {
+      workspaceId: "ws-5",
+      turnId: "turn-1",
+      path: "src/lib.rs",
+      reviewState: "reviewed",
+      note: null,
+    });
+  });
+
   it("nests decisions for server request responses", async () => {
     const invokeMock = vi.mocked(invoke);
     invokeMock.mockResolvedValueOnce({});
@@ -433,6 +772,7 @@ describe("tauri invoke wrappers", () => {
     });
     expect(result).toEqual({
       rules: [{ command: ["fetch", "https://example.com"] }],
+      denyRules: [],
       rulesPath: "/tmp/.micode/rules/default.rules",
     });
   });
@@ -455,10 +795,38 @@ describe("tauri invoke wrappers", () => {
         { command: ["execute", "npm", "123", "true"] },
         { command: ['{"kind":"fetch","target":"https://example.com"}'] },
       ],
+      denyRules: [],
       rulesPath: "/tmp/.micode/rules/default.rules",
     });
   });
 
+  it("lists deny rules and sends the rule decision", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValueOnce({
+      rules: [],
+      denyRules: [["git", "push", "--force"]],
+      rulesPath: "/tmp/.micode/rules/default.rules",
+    });
+
+    const result = await listApprovalRules("ws-12");
+    expect(result.denyRules).toEqual([{ command: ["git", "push", "--force"] }]);
+
+    invokeMock.mockResolvedValue({ ok: true, removed: true });
+    await rememberApprovalRule("ws-12", ["git", "push", "--force"], "deny");
+    await removeApprovalRule("ws-12", ["git", "push", "--force"], "deny");
+
+    expect(invokeMock).toHaveBeenCalledWith("remember_approval_rule", {
+      workspaceId: "ws-12",
+      command: ["git", "push", "--force"],
+      decision: "deny",
+    });
+    expect(invokeMock).toHaveBeenCalledWith("remove_approval_rule", {
+      workspaceId: "ws-12",
+      command: ["git", "push", "--force"],
+      decision: "deny",
+    });
+  });
+
   it("removes approval rules by command pattern", async () => {
     const invokeMock = vi.mocked(invoke);
     invokeMock.mockResolvedValueOnce({ ok: true, removed: true });
@@ -472,6 +840,39 @@ describe("tauri invoke wrappers", () => {
     expect(result).toEqual({ ok: true, removed: true });
   });
 
+  it("manages stored approval rules by id", async () => {
+    const invokeMock = vi.mocked(invoke);
+    invokeMock.mockResolvedValue({});
+
+    await approvalRulesList("ws-14");
+    await approvalRulesDelete("ws-14", "rule-1");
+    await approvalRulesUpdate("ws-14", "rule-2", { decision: "deny" });
+    await approvalRulesExport("ws-14", "/tmp/rules.json");
+    await approvalRulesImport("ws-14", "/tmp/rules.json");
+
+    expect(invokeMock).toHaveBeenCalledWith("approval_rules_list", {
+      workspaceId: "ws-14",
+    });
+    expect(invokeMock).toHaveBeenCalledWith("approval_rules_delete", {
+      workspaceId: "ws-14",
+      ruleId: "rule-1",
+    });
+    expect(invokeMock).toHaveBeenCalledWith("approval_rules_update", {
+      workspaceId: "ws-14",
+      ruleId: "rule-2",
+      pattern: null,
+      decision: "deny",
+    });
+    expect(invokeMock).toHaveBeenCalledWith("approval_rules_export", {
+      workspaceId: "ws-14",
+      path: "/tmp/rules.json",
+    });
+    expect(invokeMock).toHaveBeenCalledWith("approval_rules_import", {
+      workspaceId: "ws-14",
+      path: "/tmp/rules.json",
+    });
+  });
+
   it("nests answers for user input responses", async () => {
     const invokeMock = vi.mocked(invoke);
     invokeMock.mockResolvedValueOnce({});
@@ -543,6 +944,48 @@ describe("tauri invoke wrappers", () => {
     });
   });
 
+  it("records dispatched notifications in the inbox", async () => {
+    const invokeMock = vi.mocked(invoke);
+    vi.mocked(notification.isPermissionGranted).mockResolvedValueOnce(true);
+
+    await sendNotification("Approval needed", "rm -rf build", {
+      extra: { type: "approval", workspaceId: "ws-1", threadId: "t-1", requestId: 7 },
+    });
+
+    expect(invokeMock).toHaveBeenCalledWith("record_notification", {
+      notification: {
+        type: "approval",
+        title: "Approval needed",
+        message: "rm -rf build",
+        workspaceId: "ws-1",
+        threadId: "t-1",
+        turnId: null,
+        itemId: null,
+        requestId: 7,
+      },
+    });
+  });
+
+  it("skips notifications the backend suppresses during a pending approval", async () => {
+    const invokeMock = vi.mocked(invoke);
+    const isPermissionGrantedMock = vi.mocked(notification.isPermissionGranted);
+    const sendNotificationMock = vi.mocked(notification.sendNotification);
+    invokeMock.mockImplementation(async (command: string) =>
+      command === "should_dispatch_notification" ? false : undefined,
+    );
+
+    await sendNotification("Done", "Turn finished", {
+      extra: { kind: "thread", workspaceId: "ws-1", threadId: "t-1" },
+    });
+
+    expect(invokeMock).toHaveBeenCalledWith("should_dispatch_notification", {
+      workspaceId: "ws-1",
+      kind: "thread",
+    });
+    expect(isPermissionGrantedMock).not.toHaveBeenCalled();
+    expect(sendNotificationMock).not.toHaveBeenCalled();
+  });
+
   it("requests permission once when needed and sends on grant", async () => {
     const isPermissionGrantedMock = vi.mocked(notification.isPermissionGranted);
     const requestPermissionMock = vi.mocked(notification.requestPermission);
@@ -1,19 +1,85 @@
-import { invoke } from "@tauri-apps/api/core";
+import { invoke as tauriInvoke } from "@tauri-apps/api/core";
 import { open } from "@tauri-apps/plugin-dialog";
 import type { Options as NotificationOptions } from "@tauri-apps/plugin-notification";
 import type {
+  AppInfo,
   ApprovalDecision,
   ApprovalRule,
+  ApprovalRuleDecision,
+  ApprovalRulesImport,
   AppSettings,
+  ArchivedThread,
+  AutoRun,
+  BackendCapabilities,
+  BlockingState,
+  CancelledToolCall,
+  ChangelogStyle,
+  ClearWorkspaceHistoryResult,
+  CommandErrorCode,
+  CommandErrorPayload,
+  ConnectAllWorkspacesSummary,
   DebugEntry,
+  DefaultMenuAccelerator,
+  CommandTimings,
+  DiagnosticsExport,
   MiCodeDoctorResult,
+  MiCodeSessionStatus,
   DictationModelStatus,
   DictationSessionState,
+  EditorLaunchErrorCode,
+  EditorLaunchErrorPayload,
+  FeatureUsage,
+  FeatureUsageExport,
+  FileReviewState,
+  GitIdentity,
+  GitIdentityInfo,
+  GitRevertResult,
+  GitTrashEntry,
+  GitTrashRestoreResult,
+  ItemAnnotation,
   LocalUsageSnapshot,
+  McpServerConfig,
+  NotificationPage,
+  MenuAcceleratorResult,
+  OpenableApp,
+  OperationOutcome,
+  PrDescription,
+  PromptExtraction,
+  PromptRestoreResult,
+  ProxyConnectivityReport,
+  RedactionPreview,
+  TurnAudit,
+  TurnLiveChanges,
+  TurnReview,
+  RedactionSettings,
+  ResourceUsageReport,
+  RunKickoffMessage,
+  ReviewSarifExport,
+  SamplingParams,
+  SessionInfo,
+  StoreMaintenanceReport,
+  StoredApprovalRule,
+  ThreadExport,
+  ThreadExportFormat,
+  ThreadOwnershipInfo,
+  ThreadSearchHit,
+  TrashedPrompt,
+  UpdaterProxy,
+  UsageCsvExport,
+  UsageSummary,
+  WorkspaceBootstrapWarning,
+  WorkspaceFileContent,
+  WorkspaceFileFormat,
+  WorkspaceGitInfo,
   WorkspaceInfo,
   WorkspaceSettings,
+  WorkspaceStack,
 } from "../types";
 import type {
+  AppliedPatch,
+  ConflictDetail,
+  ConflictResolution,
+  ExtractedPatch,
   GitFileDiff,
   GitFileStatus,
   GitCommitDiff,
@@ -22,9 +88,49 @@ import type {
   GitHubPullRequestDiff,
   GitHubPullRequestsResponse,
   GitLogResponse,
+  GitRootStatus,
+  ReviewContextOptions,
   ReviewTarget,
+  WorkspaceRoot,
 } from "../types";
 
+export class CommandError extends Error {
+  code: CommandErrorCode;
+  details: unknown;
+  retryable: boolean;
+
+  constructor(payload: CommandErrorPayload) {
+    super(payload.message);
+    this.name = "CommandError";
+    this.code = payload.code;
+    this.details = payload.details ?? null;
+    this.retryable = payload.retryable;
+  }
+}
+
+function isCommandErrorPayload(value: unknown): value is CommandErrorPayload {
+  return (
+    typeof value === "object" &&
+    value !== null &&
+    typeof (value as { code?: unknown }).code === "string" &&
+    typeof (value as { message?: unknown }).message === "string" &&
+    typeof (value as { retryable?: unknown }).retryable === "boolean"
+  );
+}
+
+// Commands that reject with a `CommandError` payload surface as `CommandError`
+// instances; other payloads (strings, editor errors) are rethrown as is.
+async function invoke<T>(...args: Parameters<typeof tauriInvoke>): Promise<T> {
+  try {
+    return await tauriInvoke<T>(...args);
+  } catch (error) {
+    if (isCommandErrorPayload(error)) {
+      throw new CommandError(error);
+    }
+    throw error;
+  }
+}
+
 function isMissingTauriInvokeError(error: unknown) {
   return (
     error instanceof TypeError &&
@@ -57,8 +163,15 @@ export async function pickImageFiles(): Promise<string[]> {
   return Array.isArray(selection) ? selection : [selection];
 }
 
-export async function listWorkspaces(): Promise<WorkspaceInfo[]> {
+export async function listWorkspaces(
+  options: { includeRuntime?: boolean } = {},
+): Promise<WorkspaceInfo[]> {
   try {
+    if (options.includeRuntime) {
+      return await invoke<WorkspaceInfo[]>("list_workspaces", {
+        includeRuntime: true,
+      });
+    }
     return await invoke<WorkspaceInfo[]>("list_workspaces");
   } catch (error) {
     if (isMissingTauriInvokeError(error)) {
@@ -148,11 +261,13 @@ export async function addClone(
   sourceWorkspaceId: string,
   copiesFolder: string,
   copyName: string,
-): Promise<WorkspaceInfo> {
-  return invoke<WorkspaceInfo>("add_clone", {
+  operationId: string | null = null,
+): Promise<OperationOutcome<WorkspaceInfo>> {
+  return invoke<OperationOutcome<WorkspaceInfo>>("add_clone", {
     sourceWorkspaceId,
     copiesFolder,
     copyName,
+    operationId,
   });
 }
 
@@ -198,16 +313,39 @@ export async function updateWorkspaceMiCodeBin(
   return invoke<WorkspaceInfo>("update_workspace_micode_bin", { id, micode_bin });
 }
 
-export async function removeWorkspace(id: string): Promise<void> {
-  return invoke("remove_workspace", { id });
+export async function removeWorkspace(
+  id: string,
+  deleteAgentHome = false,
+): Promise<void> {
+  return invoke("remove_workspace", { id, deleteAgentHome });
 }
 
 export async function removeWorktree(id: string): Promise<void> {
   return invoke("remove_worktree", { id });
 }
 
-export async function clearWorkspaceHistory(id: string): Promise<void> {
-  return invoke("clear_workspace_history", { id });
+export type ClearWorkspaceHistoryOptions = {
+  olderThanDays?: number | null;
+  tags?: string[] | null;
+  excludePinned?: boolean | null;
+  pinnedThreadIds?: string[] | null;
+  dryRun?: boolean | null;
+};
+
+export async function clearWorkspaceHistory(
+  id: string,
+  options: ClearWorkspaceHistoryOptions = {},
+): Promise<ClearWorkspaceHistoryResult> {
+  return invoke<ClearWorkspaceHistoryResult>("clear_workspace_history", {
+    id,
+    options: {
+      olderThanDays: options.olderThanDays ?? null,
+      tags: options.tags ?? null,
+      excludePinned: options.excludePinned ?? null,
+      pinnedThreadIds: options.pinnedThreadIds ?? null,
+      dryRun: options.dryRun ?? null,
+    },
+  });
 }
 
 export async function renameWorktree(
@@ -229,30 +367,324 @@ export async function applyWorktreeChanges(workspaceId: string): Promise<void> {
   return invoke("apply_worktree_changes", { workspaceId });
 }
 
+export class EditorLaunchError extends Error {
+  code: EditorLaunchErrorCode;
+  editorId: string | null;
+  installHint: string | null;
+
+  constructor(payload: EditorLaunchErrorPayload) {
+    super(payload.message);
+    this.name = "EditorLaunchError";
+    this.code = payload.code;
+    this.editorId = payload.editorId ?? null;
+    this.installHint = payload.installHint ?? null;
+  }
+}
+
+function isEditorLaunchErrorPayload(
+  value: unknown,
+): value is EditorLaunchErrorPayload {
+  return (
+    typeof value === "object" &&
+    value !== null &&
+    typeof (value as { code?: unknown }).code === "string" &&
+    typeof (value as { message?: unknown }).message === "string"
+  );
+}
+
 export async function openWorkspaceIn(
   path: string,
   options: {
     appName?: string | null;
     command?: string | null;
     args?: string[];
+    editorId?: string | null;
+    workspaceId?: string | null;
+    file?: string | null;
+    line?: number | null;
   },
 ): Promise<void> {
-  return invoke("open_workspace_in", {
-    path,
-    app: options.appName ?? null,
-    command: options.command ?? null,
-    args: options.args ?? [],
-  });
+  try {
+    await invoke("open_workspace_in", {
+      path,
+      app: options.appName ?? null,
+      command: options.command ?? null,
+      args: options.args ?? [],
+      target: {
+        editorId: options.editorId ?? null,
+        workspaceId: options.workspaceId ?? null,
+        file: options.file ?? null,
+        line: options.line ?? null,
+      },
+    });
+  } catch (error) {
+    if (isEditorLaunchErrorPayload(error)) {
+      throw new EditorLaunchError(error);
+    }
+    throw error;
+  }
+}
+
+export async function listOpenableApps(): Promise<OpenableApp[]> {
+  return invoke<OpenableApp[]>("list_openable_apps");
 }
 
 export async function getOpenAppIcon(appName: string): Promise<string | null> {
   return invoke<string | null>("get_open_app_icon", { appName });
 }
 
-export async function connectWorkspace(id: string): Promise<void> {
+export async function connectWorkspace(id: string): Promise<{
+  ok: boolean;
+  bootstrapWarnings?: WorkspaceBootstrapWarning[];
+}> {
   return invoke("connect_workspace", { id });
 }
 
+// Connects in the backend's bounded queue; progress arrives as `workspace/connect*` events.
+export async function connectAllWorkspaces(
+  workspaceIds?: string[] | null,
+  priorityWorkspaceId?: string | null,
+): Promise<ConnectAllWorkspacesSummary> {
+  return invoke<ConnectAllWorkspacesSummary>("connect_all_workspaces", {
+    workspaceIds: workspaceIds ?? null,
+    priorityWorkspaceId: priorityWorkspaceId ?? null,
+  });
+}
+
+export async function restartWorkspaceSession(
+  workspaceId: string,
+  force = false,
+): Promise<WorkspaceInfo | null> {
+  return invoke<WorkspaceInfo | null>("restart_workspace_session", {
+    workspaceId,
+    force,
+  });
+}
+
+export async function forceRestartSession(
+  workspaceId: string,
+): Promise<WorkspaceInfo> {
+  return invoke<WorkspaceInfo>("force_restart_session", { workspaceId });
+}
+
+export async function runStoreMaintenanceNow(
+  workspaceId?: string | null,
+): Promise<StoreMaintenanceReport[]> {
+  return invoke<StoreMaintenanceReport[]>("run_store_maintenance_now", {
+    workspaceId: workspaceId ?? null,
+  });
+}
+
+export async function getSessionInfo(
+  workspaceId: string,
+): Promise<SessionInfo> {
+  return invoke<SessionInfo>("get_session_info", { workspaceId });
+}
+
+export async function micodeSessionStatus(
+  workspaceId: string,
+): Promise<MiCodeSessionStatus> {
+  return invoke<MiCodeSessionStatus>("micode_session_status", { workspaceId });
+}
+
+export async function getResourceUsage(): Promise<ResourceUsageReport> {
+  return invoke<ResourceUsageReport>("get_resource_usage");
+}
+
+export async function testRedaction(
+  workspaceId: string,
+  text: string,
+  settings?: RedactionSettings | null,
+): Promise<RedactionPreview> {
+  return invoke<RedactionPreview>("test_redaction", {
+    workspaceId,
+    text,
+    settings: settings ?? null,
+  });
+}
+
+export async function setThreadTags(
+  workspaceId: string,
+  threadId: string,
+  tags: string[],
+) {
+  return invoke<any>("set_thread_tags", { workspaceId, threadId, tags });
+}
+
+export async function setThreadPinned(
+  workspaceId: string,
+  threadId: string,
+  pinned: boolean,
+) {
+  return invoke<any>("set_thread_pinned", { workspaceId, threadId, pinned });
+}
+
+export async function searchThreads(
+  workspaceId: string,
+  query: string,
+  limit: number | null = null,
+): Promise<ThreadSearchHit[]> {
+  return invoke<ThreadSearchHit[]>("search_threads", { workspaceId, query, limit });
+}
+
+export async function exportThread(
+  workspaceId: string,
+  threadId: string,
+  format: ThreadExportFormat,
+  path: string | null = null,
+): Promise<ThreadExport> {
+  return invoke<ThreadExport>("export_thread", { workspaceId, threadId, format, path });
+}
+
+export async function startAutoRun(
+  workspaceId: string,
+  goal: string,
+  options: {
+    threadId?: string | null;
+    maxTurns?: number | null;
+    maxDurationSecs?: number | null;
+    accessMode?: "read-only" | "current" | "full-access" | null;
+  } = {},
+): Promise<{ run: AutoRun }> {
+  return invoke("start_auto_run", {
+    workspaceId,
+    request: {
+      goal,
+      threadId: options.threadId ?? null,
+      maxTurns: options.maxTurns ?? null,
+      maxDurationSecs: options.maxDurationSecs ?? null,
+      accessMode: options.accessMode ?? null,
+    },
+  });
+}
+
+export async function cancelAutoRun(
+  workspaceId: string,
+  runId: string,
+  interrupt = false,
+): Promise<{ runId: string; cancelled: boolean }> {
+  return invoke("cancel_auto_run", { workspaceId, runId, interrupt });
+}
+
+export async function listAutoRuns(
+  workspaceId: string,
+): Promise<{ runs: AutoRun[] }> {
+  return invoke("list_auto_runs", { workspaceId });
+}
+
+export async function exportReviewSarif(
+  workspaceId: string,
+  threadId: string,
+  options: { itemId?: string | null; outputPath?: string | null } = {},
+): Promise<ReviewSarifExport> {
+  return invoke("export_review_sarif", {
+    workspaceId,
+    threadId,
+    itemId: options.itemId ?? null,
+    outputPath: options.outputPath ?? null,
+  });
+}
+
+export async function markThreadSeen(
+  workspaceId: string,
+  threadId: string,
+  itemSeq?: number | null,
+): Promise<{ lastSeenItemSeq: number }> {
+  return invoke("mark_thread_seen", { workspaceId, threadId, itemSeq: itemSeq ?? null });
+}
+
+export async function getUnseenItems(
+  workspaceId: string,
+  threadId: string,
+): Promise<{ lastSeenItemSeq: number | null; items: any[] }> {
+  return invoke("get_unseen_items", { workspaceId, threadId });
+}
+
+export type ThreadSyncReport = {
+  status: "inSync" | "cliAhead" | "monitorAhead";
+  monitorMessageCount: number;
+  cliMessageCount: number;
+  aheadBy: number;
+  monitorLastMessageAt: number | null;
+  cliLastMessageAt: number | null;
+};
+
+export async function checkThreadSync(
+  workspaceId: string,
+  threadId: string,
+): Promise<{ sync: ThreadSyncReport | null }> {
+  return invoke("check_thread_sync", { workspaceId, threadId });
+}
+
+export async function syncThreadFromCli(
+  workspaceId: string,
+  threadId: string,
+): Promise<{ imported: number; sync: ThreadSyncReport | null }> {
+  return invoke("sync_thread_from_cli", { workspaceId, threadId });
+}
+
+export type TranscriptFormat = "markdown" | "plain" | "code-only";
+
+export async function copyThreadRange(
+  workspaceId: string,
+  threadId: string,
+  options: {
+    startItemId?: string | null;
+    endItemId?: string | null;
+    format?: TranscriptFormat;
+  } = {},
+): Promise<{ text: string }> {
+  return invoke("copy_thread_range", {
+    workspaceId,
+    threadId,
+    startItemId: options.startItemId ?? null,
+    endItemId: options.endItemId ?? null,
+    format: options.format ?? "markdown",
+  });
+}
+
+export async function listItemAnnotations(
+  workspaceId: string,
+  threadId: string,
+): Promise<{ annotations: ItemAnnotation[] }> {
+  return invoke("list_item_annotations", { workspaceId, threadId });
+}
+
+export async function addItemAnnotation(
+  workspaceId: string,
+  threadId: string,
+  itemId: string,
+  text: string,
+): Promise<{ annotation: ItemAnnotation }> {
+  return invoke("add_item_annotation", { workspaceId, threadId, itemId, text });
+}
+
+export async function updateItemAnnotation(
+  workspaceId: string,
+  threadId: string,
+  annotationId: string,
+  text: string,
+): Promise<{ annotation: ItemAnnotation }> {
+  return invoke("update_item_annotation", {
+    workspaceId,
+    threadId,
+    annotationId,
+    text,
+  });
+}
+
+export async function deleteItemAnnotation(
+  workspaceId: string,
+  threadId: string,
+  annotationId: string,
+) {
+  return invoke<{ ok: boolean }>("delete_item_annotation", {
+    workspaceId,
+    threadId,
+    annotationId,
+  });
+}
+
 export async function startThread(workspaceId: string) {
   return invoke<any>("start_thread", { workspaceId });
 }
@@ -275,6 +707,9 @@ export async function sendUserMessage(
     accessMode?: "read-only" | "current" | "full-access";
     images?: string[];
     collaborationMode?: Record<string, unknown> | null;
+    rawText?: boolean;
+    samplingParams?: SamplingParams | null;
+    skipRedaction?: boolean;
   },
 ) {
   const payload: Record<string, unknown> = {
@@ -289,9 +724,32 @@ export async function sendUserMessage(
   if (options?.collaborationMode) {
     payload.collaborationMode = options.collaborationMode;
   }
+  if (options?.rawText) {
+    payload.rawText = true;
+  }
+  if (options?.samplingParams) {
+    payload.samplingParams = options.samplingParams;
+  }
+  if (options?.skipRedaction) {
+    payload.skipRedaction = true;
+  }
   return invoke("send_user_message", payload);
 }
 
+export async function runSlashCommand(
+  workspaceId: string,
+  threadId: string,
+  commandName: string,
+  args?: string | null,
+) {
+  return invoke("run_slash_command", {
+    workspaceId,
+    threadId,
+    commandName,
+    args: args ?? null,
+  });
+}
+
 export async function interruptTurn(
   workspaceId: string,
   threadId: string,
@@ -300,16 +758,40 @@ export async function interruptTurn(
   return invoke("turn_interrupt", { workspaceId, threadId, turnId });
 }
 
+export async function cancelToolCall(
+  workspaceId: string,
+  toolCallId: string,
+): Promise<CancelledToolCall> {
+  return invoke<CancelledToolCall>("cancel_tool_call", {
+    workspaceId,
+    toolCallId,
+  });
+}
+
+export async function getTurnLiveChanges(
+  workspaceId: string,
+  threadId: string,
+): Promise<TurnLiveChanges> {
+  return invoke<TurnLiveChanges>("get_turn_live_changes", {
+    workspaceId,
+    threadId,
+  });
+}
+
 export async function startReview(
   workspaceId: string,
   threadId: string,
   target: ReviewTarget,
   delivery?: "inline" | "detached",
+  context?: ReviewContextOptions,
 ) {
   const payload: Record<string, unknown> = { workspaceId, threadId, target };
   if (delivery) {
     payload.delivery = delivery;
   }
+  if (context) {
+    payload.context = context;
+  }
   return invoke("start_review", payload);
 }
 
@@ -317,11 +799,12 @@ export async function respondToServerRequest(
   workspaceId: string,
   requestId: number | string,
   decision: ApprovalDecision,
+  resolvedBy?: "auto-rule",
 ) {
   return invoke("respond_to_server_request", {
     workspaceId,
     requestId,
-    result: { decision },
+    result: resolvedBy ? { decision, resolvedBy } : { decision },
   });
 }
 
@@ -340,17 +823,25 @@ export async function respondToUserInputRequest(
 export async function rememberApprovalRule(
   workspaceId: string,
   command: string[],
+  decision?: ApprovalRuleDecision,
 ) {
-  return invoke("remember_approval_rule", { workspaceId, command });
+  return invoke("remember_approval_rule", {
+    workspaceId,
+    command,
+    ...(decision ? { decision } : {}),
+  });
 }
 
-export async function listApprovalRules(
-  workspaceId: string,
-): Promise<{ rules: ApprovalRule[]; rulesPath?: string | null }> {
-  const response = await invoke<{ rules?: unknown[]; rulesPath?: string | null }>(
-    "list_approval_rules",
-    { workspaceId },
-  );
+export async function listApprovalRules(workspaceId: string): Promise<{
+  rules: ApprovalRule[];
+  denyRules: ApprovalRule[];
+  rulesPath?: string | null;
+}> {
+  const response = await invoke<{
+    rules?: unknown[];
+    denyRules?: unknown[];
+    rulesPath?: string | null;
+  }>("list_approval_rules", { workspaceId });
   const normalizeToken = (token: unknown) => {
     if (typeof token === "string") {
       return token.trim();
@@ -379,10 +870,13 @@ export async function listApprovalRules(
     const single = normalizeToken(raw);
     return single ? [single] : [];
   };
-  return {
-    rules: (response.rules ?? [])
+  const normalizeRules = (raw: unknown[] | undefined): ApprovalRule[] =>
+    (raw ?? [])
       .map((command) => ({ command: normalizeCommand(command) }))
-      .filter((rule) => rule.command.length > 0),
+      .filter((rule) => rule.command.length > 0);
+  return {
+    rules: normalizeRules(response.rules),
+    denyRules: normalizeRules(response.denyRules),
     rulesPath: response.rulesPath ?? null,
   };
 }
@@ -390,10 +884,11 @@ export async function listApprovalRules(
 export async function removeApprovalRule(
   workspaceId: string,
   command: string[],
+  decision?: ApprovalRuleDecision,
 ): Promise<{ ok: boolean; removed: boolean }> {
   const response = await invoke<{ ok?: boolean; removed?: boolean }>(
     "remove_approval_rule",
-    { workspaceId, command },
+    { workspaceId, command, ...(decision ? { decision } : {}) },
   );
   return {
     ok: Boolean(response.ok),
@@ -401,6 +896,49 @@ export async function removeApprovalRule(
   };
 }
 
+export async function approvalRulesList(
+  workspaceId: string,
+): Promise<{ rules: StoredApprovalRule[]; rulesPath?: string | null }> {
+  return invoke("approval_rules_list", { workspaceId });
+}
+
+export async function approvalRulesDelete(
+  workspaceId: string,
+  ruleId: string,
+): Promise<{ ok: boolean; removed: boolean }> {
+  return invoke("approval_rules_delete", { workspaceId, ruleId });
+}
+
+export async function approvalRulesUpdate(
+  workspaceId: string,
+  ruleId: string,
+  changes: { pattern?: string[]; decision?: ApprovalRuleDecision },
+): Promise<StoredApprovalRule> {
+  return invoke<StoredApprovalRule>("approval_rules_update", {
+    workspaceId,
+    ruleId,
+    pattern: changes.pattern ?? null,
+    decision: changes.decision ?? null,
+  });
+}
+
+export async function approvalRulesExport(
+  workspaceId: string,
+  path: string,
+): Promise<{ exported: number; path: string }> {
+  return invoke("approval_rules_export", { workspaceId, path });
+}
+
+export async function approvalRulesImport(
+  workspaceId: string,
+  path: string,
+): Promise<ApprovalRulesImport> {
+  return invoke<ApprovalRulesImport>("approval_rules_import", {
+    workspaceId,
+    path,
+  });
+}
+
 export async function getGitStatus(workspace_id: string): Promise<{
   branchName: string;
   files: GitFileStatus[];
@@ -408,6 +946,7 @@ export async function getGitStatus(workspace_id: string): Promise<{
   unstagedFiles: GitFileStatus[];
   totalAdditions: number;
   totalDeletions: number;
+  roots?: GitRootStatus[];
 }> {
   return invoke("get_git_status", { workspaceId: workspace_id });
 }
@@ -419,6 +958,13 @@ export async function listGitRoots(
   return invoke("list_git_roots", { workspaceId: workspace_id, depth });
 }
 
+export async function detectWorkspaceRoots(
+  workspace_id: string,
+  depth?: number,
+): Promise<{ roots: WorkspaceRoot[]; candidates: string[] }> {
+  return invoke("detect_workspace_roots", { workspaceId: workspace_id, depth });
+}
+
 export async function getGitDiffs(
   workspace_id: string,
 ): Promise<GitFileDiff[]> {
@@ -443,31 +989,125 @@ export async function getGitRemote(workspace_id: string): Promise<string | null>
   return invoke("get_git_remote", { workspaceId: workspace_id });
 }
 
-export async function stageGitFile(workspaceId: string, path: string) {
-  return invoke("stage_git_file", { workspaceId, path });
+function withRoot(root?: string | null) {
+  return root ? { root } : {};
 }
 
-export async function stageGitAll(workspaceId: string): Promise<void> {
-  return invoke("stage_git_all", { workspaceId });
+export async function stageGitFile(
+  workspaceId: string,
+  path: string,
+  root?: string | null,
+) {
+  return invoke<void>("stage_git_file", {
+    workspaceId,
+    path,
+    ...withRoot(root),
+  });
 }
 
-export async function unstageGitFile(workspaceId: string, path: string) {
-  return invoke("unstage_git_file", { workspaceId, path });
+export async function stageGitAll(
+  workspaceId: string,
+  root?: string | null,
+): Promise<void> {
+  return invoke<void>("stage_git_all", { workspaceId, ...withRoot(root) });
 }
 
-export async function revertGitFile(workspaceId: string, path: string) {
-  return invoke("revert_git_file", { workspaceId, path });
+export async function unstageGitFile(
+  workspaceId: string,
+  path: string,
+  root?: string | null,
+) {
+  return invoke<void>("unstage_git_file", {
+    workspaceId,
+    path,
+    ...withRoot(root),
+  });
 }
 
-export async function revertGitAll(workspaceId: string) {
-  return invoke("revert_git_all", { workspaceId });
+export async function revertGitFile(
+  workspaceId: string,
+  path: string,
+  root?: string | null,
+  options: { skipBackup?: boolean } = {},
+) {
+  return invoke<GitRevertResult>("revert_git_file", {
+    workspaceId,
+    path,
+    ...withRoot(root),
+    skipBackup: options.skipBackup ?? false,
+  });
+}
+
+export async function revertGitAll(
+  workspaceId: string,
+  root?: string | null,
+  options: { skipBackup?: boolean } = {},
+) {
+  return invoke<GitRevertResult>("revert_git_all", {
+    workspaceId,
+    ...withRoot(root),
+    skipBackup: options.skipBackup ?? false,
+  });
+}
+
+export async function listGitTrash(
+  workspaceId: string,
+): Promise<GitTrashEntry[]> {
+  return invoke<GitTrashEntry[]>("list_git_trash", { workspaceId });
+}
+
+export async function restoreGitTrash(workspaceId: string, trashId: string) {
+  return invoke<GitTrashRestoreResult>("restore_git_trash", {
+    workspaceId,
+    trashId,
+  });
 }
 
 export async function commitGit(
   workspaceId: string,
   message: string,
+  root?: string | null,
+  options: { allowMismatch?: boolean } = {},
 ): Promise<void> {
-  return invoke("commit_git", { workspaceId, message });
+  return invoke<void>("commit_git", {
+    workspaceId,
+    message,
+    ...withRoot(root),
+    allowMismatch: options.allowMismatch ?? false,
+  });
+}
+
+export async function getGitIdentity(
+  workspaceId: string,
+  root?: string | null,
+): Promise<GitIdentityInfo> {
+  return invoke<GitIdentityInfo>("get_git_identity", {
+    workspaceId,
+    ...withRoot(root),
+  });
+}
+
+export async function setGitIdentity(
+  workspaceId: string,
+  identity: GitIdentity,
+  root?: string | null,
+): Promise<GitIdentityInfo> {
+  return invoke<GitIdentityInfo>("set_git_identity", {
+    workspaceId,
+    name: identity.name ?? null,
+    email: identity.email ?? null,
+    ...withRoot(root),
+  });
+}
+
+export async function getWorkspaceGitInfo(
+  workspaceId: string,
+  root?: string | null,
+): Promise<WorkspaceGitInfo> {
+  return invoke<WorkspaceGitInfo>("get_workspace_git_info", {
+    workspaceId,
+    ...withRoot(root),
+  });
 }
 
 export async function pushGit(workspaceId: string): Promise<void> {
@@ -475,7 +1115,7 @@ export async function pushGit(workspaceId: string): Promise<void> {
 }
 
 export async function pullGit(workspaceId: string): Promise<void> {
-  return invoke("pull_git", { workspaceId });
+  return invoke<void>("pull_git", { workspaceId });
 }
 
 export async function fetchGit(workspaceId: string): Promise<void> {
@@ -483,7 +1123,7 @@ export async function fetchGit(workspaceId: string): Promise<void> {
 }
 
 export async function syncGit(workspaceId: string): Promise<void> {
-  return invoke("sync_git", { workspaceId });
+  return invoke<void>("sync_git", { workspaceId });
 }
 
 export async function getGitHubIssues(
@@ -529,8 +1169,31 @@ export async function localUsageSnapshot(
   return invoke("local_usage_snapshot", payload);
 }
 
-export async function getModelList(workspaceId: string) {
-  return invoke<any>("model_list", { workspaceId });
+export async function exportUsageCsv(
+  startDay: string,
+  endDay: string,
+  destination: string,
+): Promise<UsageCsvExport> {
+  return invoke<UsageCsvExport>("export_usage_csv", {
+    startDay,
+    endDay,
+    destination,
+  });
+}
+
+export async function usageSummary(
+  workspaceId: string | null = null,
+  since: string | null = null,
+): Promise<UsageSummary> {
+  return invoke<UsageSummary>("usage_summary", { workspaceId, since });
+}
+
+export async function usageExportCsv(path: string): Promise<UsageCsvExport> {
+  return invoke<UsageCsvExport>("usage_export_csv", { path });
+}
+
+export async function getModelList(workspaceId: string, refresh = false) {
+  return invoke<any>("model_list", { workspaceId, refresh });
 }
 
 export async function generateRunMetadata(workspaceId: string, prompt: string) {
@@ -540,6 +1203,35 @@ export async function generateRunMetadata(workspaceId: string, prompt: string) {
   });
 }
 
+export async function buildRunKickoffMessage(
+  workspaceId: string,
+  task: string,
+  branch?: string | null,
+): Promise<RunKickoffMessage> {
+  return invoke<RunKickoffMessage>("build_run_kickoff_message", {
+    workspaceId,
+    task,
+    branch: branch ?? null,
+  });
+}
+
+export async function startRun(
+  workspaceId: string,
+  task: string,
+  options: { branch?: string | null; sendKickoff?: boolean } = {},
+) {
+  return invoke<{
+    threadId: string;
+    kickoff: RunKickoffMessage;
+    turn: unknown;
+  }>("start_run", {
+    workspaceId,
+    task,
+    branch: options.branch ?? null,
+    sendKickoff: options.sendKickoff ?? true,
+  });
+}
+
 export async function getCollaborationModes(workspaceId: string) {
   return invoke<any>("collaboration_mode_list", { workspaceId });
 }
@@ -629,8 +1321,25 @@ export async function updatePrompt(
   });
 }
 
-export async function deletePrompt(workspaceId: string, path: string) {
-  return invoke<any>("prompts_delete", { workspaceId, path });
+export async function deletePrompt(
+  workspaceId: string,
+  path: string,
+  purgeImmediately = false,
+) {
+  return invoke<any>("prompts_delete", { workspaceId, path, purgeImmediately });
+}
+
+export async function listPromptTrash(
+  workspaceId: string,
+): Promise<TrashedPrompt[]> {
+  return invoke<TrashedPrompt[]>("prompts_list_trash", { workspaceId });
+}
+
+export async function restorePrompt(
+  workspaceId: string,
+  path: string,
+): Promise<PromptRestoreResult> {
+  return invoke<PromptRestoreResult>("prompts_restore", { workspaceId, path });
 }
 
 export async function movePrompt(
@@ -644,10 +1353,72 @@ export async function movePrompt(
   });
 }
 
+export async function extractPromptFromThread(
+  workspaceId: string,
+  data: {
+    threadId: string;
+    itemIds: string[];
+    scope: "workspace" | "global";
+    name: string;
+    description?: string | null;
+    generalize?: boolean;
+    dryRun?: boolean;
+    requestId?: string | null;
+  },
+): Promise<PromptExtraction> {
+  return invoke<PromptExtraction>("extract_prompt_from_thread", {
+    workspaceId,
+    request: {
+      threadId: data.threadId,
+      itemIds: data.itemIds,
+      scope: data.scope,
+      name: data.name,
+      description: data.description ?? null,
+      generalize: data.generalize ?? false,
+      dryRun: data.dryRun ?? false,
+      requestId: data.requestId ?? null,
+    },
+  });
+}
+
+/** Cancels a clone, file listing or diagnostics export started with `operationId`. */
+export async function cancelOperation(operationId: string): Promise<boolean> {
+  return invoke<boolean>("cancel_operation", { operationId });
+}
+
+export async function cancelPromptExtraction(
+  requestId: string,
+): Promise<boolean> {
+  return invoke<boolean>("cancel_prompt_extraction", { requestId });
+}
+
+export async function setProxyPassword(
+  workspaceId: string | null,
+  password: string | null,
+): Promise<void> {
+  return invoke("set_proxy_password", { workspaceId, password });
+}
+
+export async function getUpdaterProxy(): Promise<UpdaterProxy | null> {
+  return invoke<UpdaterProxy | null>("get_updater_proxy");
+}
+
+export async function testProxyConnectivity(
+  workspaceId?: string | null,
+): Promise<ProxyConnectivityReport> {
+  return invoke<ProxyConnectivityReport>("test_proxy_connectivity", {
+    workspaceId: workspaceId ?? null,
+  });
+}
+
 export async function getAppSettings(): Promise<AppSettings> {
   return invoke<AppSettings>("get_app_settings");
 }
 
+export async function getSettingsRevision(): Promise<number> {
+  return invoke<number>("get_settings_revision");
+}
+
 export async function updateAppSettings(settings: AppSettings): Promise<AppSettings> {
   const canonicalSettings = toCanonicalAppSettings(settings);
   return invoke<AppSettings>("update_app_settings", { settings: canonicalSettings });
@@ -700,6 +1471,50 @@ export async function appendDebugLogs(entries: PersistedDebugEntry[]): Promise<v
   return invoke("append_debug_logs", { entries });
 }
 
+export async function getAppInfo(maskPaths = false): Promise<AppInfo> {
+  return invoke<AppInfo>("get_app_info", { maskPaths });
+}
+
+export async function exportDiagnostics(options: {
+  includeConversations?: boolean;
+  hashPaths?: boolean;
+  dryRun?: boolean;
+  destination?: string | null;
+  operationId?: string | null;
+} = {}): Promise<OperationOutcome<DiagnosticsExport>> {
+  return invoke<OperationOutcome<DiagnosticsExport>>("export_diagnostics", {
+    includeConversations: options.includeConversations ?? false,
+    hashPaths: options.hashPaths ?? false,
+    dryRun: options.dryRun ?? false,
+    destination: options.destination ?? null,
+    operationId: options.operationId ?? null,
+  });
+}
+
+export async function getCommandTimings(): Promise<CommandTimings[]> {
+  return invoke<CommandTimings[]>("get_command_timings");
+}
+
+export async function resetCommandTimings(): Promise<void> {
+  return invoke("reset_command_timings");
+}
+
+export async function getFeatureUsage(): Promise<FeatureUsage> {
+  return invoke<FeatureUsage>("get_feature_usage");
+}
+
+export async function exportFeatureUsage(
+  destination?: string | null,
+): Promise<FeatureUsageExport> {
+  return invoke<FeatureUsageExport>("export_feature_usage", {
+    destination: destination ?? null,
+  });
+}
+
+export async function getBackendCapabilities(): Promise<BackendCapabilities> {
+  return invoke<BackendCapabilities>("get_backend_capabilities");
+}
+
 type MenuAcceleratorUpdate = {
   id: string;
   accelerator: string | null;
@@ -707,14 +1522,26 @@ type MenuAcceleratorUpdate = {
 
 export async function setMenuAccelerators(
   updates: MenuAcceleratorUpdate[],
-): Promise<void> {
-  return invoke("menu_set_accelerators", { updates });
+): Promise<MenuAcceleratorResult[]> {
+  return invoke<MenuAcceleratorResult[]>("menu_set_accelerators", { updates });
+}
+
+export async function getDefaultAccelerators(): Promise<DefaultMenuAccelerator[]> {
+  return invoke<DefaultMenuAccelerator[]>("get_default_accelerators");
 }
 
 export async function runMiCodeDoctor(
   micodeBin: string | null,
   micodeArgs: string | null,
+  options: { forceRecheck?: boolean } = {},
 ): Promise<MiCodeDoctorResult> {
+  if (options.forceRecheck) {
+    return invoke<MiCodeDoctorResult>("micode_doctor", {
+      micodeBin,
+      micodeArgs,
+      forceRecheck: true,
+    });
+  }
   return invoke<MiCodeDoctorResult>("micode_doctor", { micodeBin, micodeArgs });
 }
 
@@ -727,20 +1554,80 @@ export async function runMiCodeInstallWindows(): Promise<{
   return invoke("micode_install_windows");
 }
 
-export async function getWorkspaceFiles(workspaceId: string) {
-  return invoke<string[]>("list_workspace_files", { workspaceId });
+export async function getWorkspaceFiles(
+  workspaceId: string,
+  operationId: string | null = null,
+) {
+  return invoke<OperationOutcome<string[]>>("list_workspace_files", {
+    workspaceId,
+    operationId,
+  });
 }
 
 export async function readWorkspaceFile(
   workspaceId: string,
   path: string,
-): Promise<{ content: string; truncated: boolean }> {
-  return invoke<{ content: string; truncated: boolean }>("read_workspace_file", {
+  format: WorkspaceFileFormat = "auto",
+): Promise<WorkspaceFileContent> {
+  return invoke<WorkspaceFileContent>("read_workspace_file", {
+    workspaceId,
+    path,
+    format,
+  });
+}
+
+export async function readTurnArtifact(
+  workspaceId: string,
+  threadId: string,
+  turnId: string,
+  path: string,
+): Promise<WorkspaceFileContent> {
+  return invoke<WorkspaceFileContent>("read_turn_artifact", {
     workspaceId,
+    threadId,
+    turnId,
     path,
   });
 }
 
+export async function getTurnAudit(
+  workspaceId: string,
+  threadId: string,
+  turnId: string,
+): Promise<TurnAudit> {
+  return invoke<TurnAudit>("get_turn_audit", { workspaceId, threadId, turnId });
+}
+
+export async function getWorkspaceStack(
+  workspaceId: string,
+  refresh = false,
+): Promise<WorkspaceStack> {
+  return invoke<WorkspaceStack>("get_workspace_stack", { workspaceId, refresh });
+}
+
+export async function getTurnReviewState(
+  workspaceId: string,
+  turnId: string,
+): Promise<TurnReview> {
+  return invoke<TurnReview>("get_turn_review_state", { workspaceId, turnId });
+}
+
+export async function setFileReviewState(
+  workspaceId: string,
+  turnId: string,
+  path: string,
+  reviewState: FileReviewState,
+  note?: string | null,
+): Promise<TurnReview> {
+  return invoke<TurnReview>("set_file_review_state", {
+    workspaceId,
+    turnId,
+    path,
+    reviewState,
+    note: note ?? null,
+  });
+}
+
 export async function readAgentMd(workspaceId: string): Promise<AgentMdResponse> {
   return fileRead("workspace", "agents", workspaceId);
 }
@@ -754,11 +1641,70 @@ export async function listGitBranches(workspaceId: string) {
 }
 
 export async function checkoutGitBranch(workspaceId: string, name: string) {
-  return invoke("checkout_git_branch", { workspaceId, name });
+  return invoke<void>("checkout_git_branch", { workspaceId, name });
 }
 
 export async function createGitBranch(workspaceId: string, name: string) {
-  return invoke("create_git_branch", { workspaceId, name });
+  return invoke<void>("create_git_branch", { workspaceId, name });
+}
+
+export async function getConflictDetail(
+  workspaceId: string,
+  path: string,
+  root?: string | null,
+): Promise<ConflictDetail> {
+  return invoke<ConflictDetail>("get_conflict_detail", {
+    workspaceId,
+    path,
+    ...withRoot(root),
+  });
+}
+
+export async function resolveConflict(
+  workspaceId: string,
+  path: string,
+  resolution: ConflictResolution,
+  content?: string | null,
+  root?: string | null,
+) {
+  return invoke<void>("resolve_conflict", {
+    workspaceId,
+    path,
+    resolution,
+    content: content ?? null,
+    ...withRoot(root),
+  });
+}
+
+export async function extractPatchesFromItem(
+  workspaceId: string,
+  threadId: string,
+  itemId: string,
+  root?: string | null,
+): Promise<ExtractedPatch[]> {
+  return invoke<ExtractedPatch[]>("extract_patches_from_item", {
+    workspaceId,
+    threadId,
+    itemId,
+    ...withRoot(root),
+  });
+}
+
+export async function applyExtractedPatch(
+  workspaceId: string,
+  threadId: string,
+  itemId: string,
+  index: number,
+  options: { stage?: boolean; root?: string | null } = {},
+): Promise<AppliedPatch> {
+  return invoke<AppliedPatch>("apply_extracted_patch", {
+    workspaceId,
+    threadId,
+    itemId,
+    index,
+    stage: options.stage ?? false,
+    ...withRoot(options.root),
+  });
 }
 
 function withModelId(modelId?: string | null) {
@@ -819,6 +1765,25 @@ export async function cancelDictation(): Promise<DictationSessionState> {
   return invoke("dictation_cancel");
 }
 
+export async function getBlockingState(): Promise<BlockingState> {
+  return invoke<BlockingState>("get_blocking_state");
+}
+
+export async function setFocusedWorkspace(
+  workspaceId: string | null,
+): Promise<void> {
+  return invoke("set_focused_workspace", { workspaceId });
+}
+
+/** Workspaces whose events the webview renders; `"*"` subscribes to all of them. */
+export async function setEventSubscriptions(
+  workspaceIds: string[] | "*",
+): Promise<void> {
+  return invoke("set_event_subscriptions", {
+    workspaceIds: workspaceIds === "*" ? ["*"] : workspaceIds,
+  });
+}
+
 export async function openTerminalSession(
   workspaceId: string,
   terminalId: string,
@@ -868,12 +1833,88 @@ export async function listMcpServerStatus(
   return invoke<any>("list_mcp_server_status", { workspaceId, cursor, limit });
 }
 
+export async function addMcpServer(
+  workspaceId: string,
+  name: string,
+  config: McpServerConfig,
+): Promise<void> {
+  return invoke("mcp_server_add", { workspaceId, name, config });
+}
+
+export async function updateMcpServer(
+  workspaceId: string,
+  name: string,
+  config: McpServerConfig,
+): Promise<void> {
+  return invoke("mcp_server_update", { workspaceId, name, config });
+}
+
+export async function removeMcpServer(
+  workspaceId: string,
+  name: string,
+): Promise<void> {
+  return invoke("mcp_server_remove", { workspaceId, name });
+}
+
 export async function resumeThread(workspaceId: string, threadId: string) {
   return invoke<any>("resume_thread", { workspaceId, threadId });
 }
 
-export async function archiveThread(workspaceId: string, threadId: string) {
-  return invoke<any>("archive_thread", { workspaceId, threadId });
+export async function watchThread(workspaceId: string, threadId: string) {
+  return invoke<ThreadOwnershipInfo & { thread: any }>("watch_thread", {
+    workspaceId,
+    threadId,
+  });
+}
+
+export async function takeOverThread(workspaceId: string, threadId: string) {
+  return invoke<
+    ThreadOwnershipInfo & { previousOwnerClientId: string | null }
+  >("take_over_thread", { workspaceId, threadId });
+}
+
+export async function getThreadOwnership(
+  workspaceId: string,
+  threadId: string,
+): Promise<ThreadOwnershipInfo> {
+  return invoke<ThreadOwnershipInfo>("get_thread_ownership", {
+    workspaceId,
+    threadId,
+  });
+}
+
+export async function archiveThread(
+  workspaceId: string,
+  threadId: string,
+  force = false,
+) {
+  return invoke<any>("archive_thread", { workspaceId, threadId, force });
+}
+
+export async function listArchivedThreads(
+  workspaceId: string,
+): Promise<ArchivedThread[]> {
+  const response = await invoke<{ result?: { data?: ArchivedThread[] } }>(
+    "list_archived_threads",
+    { workspaceId },
+  );
+  return response?.result?.data ?? [];
+}
+
+export async function restoreThread(workspaceId: string, threadId: string) {
+  return invoke<any>("restore_thread", { workspaceId, threadId });
+}
+
+export async function deleteThreadPermanently(
+  workspaceId: string,
+  threadId: string,
+  force = false,
+) {
+  return invoke<any>("delete_thread_permanently", {
+    workspaceId,
+    threadId,
+    force,
+  });
 }
 
 export async function setThreadName(
@@ -896,6 +1937,80 @@ export async function generateCommitMessage(
   return invoke("generate_commit_message", { workspaceId });
 }
 
+export async function generatePrDescription(
+  workspaceId: string,
+  base?: string | null,
+): Promise<PrDescription> {
+  return invoke<PrDescription>("generate_pr_description", {
+    workspaceId,
+    base: base ?? null,
+  });
+}
+
+export async function generateChangelog(
+  workspaceId: string,
+  fromRef: string | null,
+  toRef: string | null = null,
+  style: ChangelogStyle = "keep-a-changelog",
+): Promise<string> {
+  return invoke<string>("generate_changelog", {
+    workspaceId,
+    fromRef,
+    toRef,
+    style,
+  });
+}
+
+function optionalString(value: unknown) {
+  return typeof value === "string" ? value : null;
+}
+
+// Keeps a copy in the notification inbox so it outlives the system notification.
+async function recordNotification(
+  title: string,
+  message: string,
+  extra?: Record<string, unknown>,
+) {
+  const kind = extra?.type ?? extra?.kind;
+  const requestId = extra?.requestId;
+  await invoke("record_notification", {
+    notification: {
+      type: optionalString(kind) ?? "app",
+      title,
+      message,
+      workspaceId: optionalString(extra?.workspaceId),
+      threadId: optionalString(extra?.threadId),
+      turnId: optionalString(extra?.turnId),
+      itemId: optionalString(extra?.itemId),
+      requestId:
+        typeof requestId === "string" || typeof requestId === "number"
+          ? requestId
+          : null,
+    },
+  }).catch(() => undefined);
+}
+
+export async function listNotifications(options?: {
+  unreadOnly?: boolean;
+  offset?: number;
+  limit?: number;
+}): Promise<NotificationPage> {
+  return invoke<NotificationPage>("list_notifications", {
+    unreadOnly: options?.unreadOnly ?? null,
+    offset: options?.offset ?? null,
+    limit: options?.limit ?? null,
+  });
+}
+
+/** Marks one notification read, or all of them when `id` is omitted. */
+export async function markNotificationRead(id?: string): Promise<number> {
+  return invoke<number>("mark_notification_read", { id: id ?? null });
+}
+
+export async function clearNotifications(readOnly?: boolean): Promise<number> {
+  return invoke<number>("clear_notifications", { readOnly: readOnly ?? null });
+}
+
 export async function sendNotification(
   title: string,
   body: string,
@@ -908,6 +2023,20 @@ export async function sendNotification(
     extra?: Record<string, unknown>;
   },
 ): Promise<void> {
+  // While an approval is pending for the focused workspace, only approval
+  // notifications for it go out.
+  const workspaceId = options?.extra?.workspaceId;
+  if (typeof workspaceId === "string") {
+    const kind = options?.extra?.type ?? options?.extra?.kind;
+    const allowed = await invoke<boolean>("should_dispatch_notification", {
+      workspaceId,
+      kind: typeof kind === "string" ? kind : null,
+    }).catch(() => true);
+    if (allowed === false) {
+      return;
+    }
+  }
+  void recordNotification(title, body, options?.extra);
   const macosDebugBuild = await invoke<boolean>("is_macos_debug_build").catch(
     () => false,
   );
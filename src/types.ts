@@ -3,6 +3,7 @@ export type WorkspaceSettings = {
   sortOrder?: number | null;
   groupId?: string | null;
   gitRoot?: string | null;
+  roots?: string[] | null;
   agentHome?: string | null;
   agentArgs?: string | null;
   micodeHome?: string | null;
@@ -10,6 +11,194 @@ export type WorkspaceSettings = {
   launchScript?: string | null;
   launchScripts?: LaunchScriptEntry[] | null;
   worktreeSetupScript?: string | null;
+  defaultEditor?: string | null;
+  artifactGlobs?: string[] | null;
+  samplingParams?: SamplingParams | null;
+  /** Model this workspace runs; unset follows the MiCode settings file. */
+  preferredModel?: string | null;
+  contextPriming?: boolean;
+  redaction?: RedactionSettings | null;
+  audit?: AuditSettings | null;
+  proxy?: ProxySettings | null;
+  stack?: WorkspaceStackOverride | null;
+  runKickoffTemplate?: string | null;
+  usageCounters?: boolean | null;
+  isolatedAgentHome?: boolean;
+  isolatedAgentHomeTemplate?: string | null;
+  expectedGitIdentity?: GitIdentity | null;
+  promptTimeoutSecs?: number | null;
+};
+
+export type WorkspaceStack = {
+  languages: string[];
+  frameworks: string[];
+  packageManager: string | null;
+  testCommand: string | null;
+  ci: string[];
+  sources: string[];
+};
+
+// Fields set here replace the detected values.
+export type WorkspaceStackOverride = {
+  languages?: string[] | null;
+  frameworks?: string[] | null;
+  packageManager?: string | null;
+  testCommand?: string | null;
+};
+
+export type ProxyMode = "system" | "manual" | "none";
+
+// The password is stored separately through setProxyPassword.
+export type ProxySettings = {
+  mode: ProxyMode;
+  url?: string | null;
+  username?: string | null;
+  noProxy: string[];
+};
+
+export type ProxyProbe = {
+  name: string;
+  target: string;
+  viaProxy: boolean;
+  reachable: boolean;
+  status: number | null;
+  latencyMs: number;
+  error: string | null;
+};
+
+export type ProxyConnectivityReport = {
+  proxyUrl: string | null;
+  source: "workspace" | "app" | "environment" | null;
+  authenticated: boolean;
+  probes: ProxyProbe[];
+};
+
+export type UpdaterProxy = {
+  url: string;
+  hasCredentials: boolean;
+};
+
+export type AuditSettings = {
+  enabled: boolean;
+  retentionDays: number;
+};
+
+export type AuditRead = {
+  path: string;
+  tool: string;
+  toolItemId: string;
+  kind: "read" | "search";
+  bytes: number;
+  readAtMs: number;
+  outsideWorkspace: boolean;
+};
+
+export type AuditSummary = {
+  fileCount: number;
+  totalBytes: number;
+  outsideWorkspace: string[];
+};
+
+export type TurnAudit = {
+  threadId: string;
+  turnId: string;
+  recordedAtMs: number;
+  summary: AuditSummary;
+  reads: AuditRead[];
+};
+
+export type FileReviewState = "pending" | "reviewed" | "needs-changes";
+
+export type FileReview = {
+  path: string;
+  state: FileReviewState;
+  note: string | null;
+  contentHash: string | null;
+  updatedAtMs: number;
+};
+
+export type ReviewProgress = {
+  reviewed: number;
+  total: number;
+};
+
+export type TurnReview = {
+  threadId: string;
+  turnId: string;
+  files: FileReview[];
+  progress: ReviewProgress;
+};
+
+export type RunKickoffTemplateRef = {
+  templateId: string;
+  templateHash: string;
+};
+
+export type RunKickoffMessage = {
+  message: string;
+  template: RunKickoffTemplateRef;
+};
+
+export type AppInfo = {
+  appVersion: string;
+  gitCommit: string | null;
+  targetTriple: string;
+  os: string;
+  arch: string;
+  family: string;
+  tauriVersion: string;
+  webviewVersion: string | null;
+  updateChannel: string;
+  paths: {
+    appData: string;
+    settingsFile: string;
+    workspacesFile: string;
+    logsDir: string;
+  };
+  pathsMasked: boolean;
+  remoteMode: boolean;
+  workspaces: { registered: number; connected: number } | null;
+  unreadCount: number;
+};
+
+export type CancelledToolCall = {
+  toolCallId: string;
+  itemId: string;
+  elapsedMs: number;
+  cancelledAtMs: number;
+};
+
+export type LiveFileChange = {
+  path: string;
+  additions: number;
+  deletions: number;
+  toolItemIds: string[];
+  unobserved: boolean;
+};
+
+export type TurnLiveChanges = {
+  threadId: string;
+  turnId: string;
+  files: LiveFileChange[];
+  additions: number;
+  deletions: number;
+};
+
+export type RedactionPattern = {
+  category: string;
+  pattern: string;
+};
+
+export type RedactionSettings = {
+  enabled: boolean;
+  patterns: RedactionPattern[];
+  secretNames: string[];
+  allowSkip: boolean;
+};
+
+export type RedactionPreview = {
+  text: string;
+  redactions: Record<string, number>;
 };
 
 export type LaunchScriptIconId =
@@ -60,6 +249,43 @@ export type WorkspaceInfo = {
   parentId?: string | null;
   worktree?: WorktreeInfo | null;
   settings: WorkspaceSettings;
+  configStale?: boolean;
+  runtime?: WorkspaceRuntimeInfo;
+  bootstrapWarnings?: WorkspaceBootstrapWarning[];
+};
+
+export type WorkspaceBootstrapWarningKind =
+  | "notGitRepo"
+  | "insideGitRepo"
+  | "nestedRepositories"
+  | "stateDirNotWritable"
+  | "cloudSynced";
+
+export type WorkspaceBootstrapWarning = {
+  kind: WorkspaceBootstrapWarningKind;
+  message: string;
+  suggestion: string | null;
+  paths?: string[];
+};
+
+export type WorkspaceConnectionStatus =
+  | "connected"
+  | "connecting"
+  | "disconnected"
+  | "error";
+
+export type ConnectAllWorkspacesSummary = {
+  connected: string[];
+  alreadyConnected: string[];
+  failed: { workspaceId: string; error: string; code: CommandErrorCode }[];
+};
+
+export type WorkspaceRuntimeInfo = {
+  status: WorkspaceConnectionStatus;
+  lastError: string | null;
+  lastErrorAt: number | null;
+  activeTurns: number;
+  turnsToday: number | null;
 };
 
 export type AppServerEvent = {
@@ -80,6 +306,8 @@ export type ConversationItem =
       role: "user" | "assistant";
       text: string;
       images?: string[];
+      /** The app stopped before the reply finished streaming; `text` is cut off. */
+      partial?: boolean;
     }
   | { id: string; kind: "reasoning"; summary: string; content: string }
   | { id: string; kind: "diff"; title: string; diff: string; status?: string }
@@ -96,6 +324,7 @@ export type ConversationItem =
       toolType: string;
       title: string;
       detail: string;
+      summaryText?: string;
       status?: string;
       output?: string;
       durationMs?: number | null;
@@ -114,6 +343,23 @@ export type ReviewTarget =
   | { type: "commit"; sha: string; title?: string }
   | { type: "custom"; instructions: string };
 
+export type ReviewContextOptions = {
+  byteBudget?: number;
+  depth?: number;
+};
+
+export type ReviewContextFile = {
+  path: string;
+  bytes: number;
+  importedBy: string;
+};
+
+export type ReviewContextReport = {
+  files: ReviewContextFile[];
+  unsupportedFiles: string[];
+  overBudget: string[];
+};
+
 export type AccessMode = "read-only" | "current" | "full-access";
 export type BackendMode = "local" | "remote";
 export type ThemePreference = "system" | "light" | "dark" | "dim";
@@ -143,6 +389,462 @@ export type OpenAppTarget = {
   args: string[];
 };
 
+export type SamplingParams = {
+  temperature?: number;
+  topP?: number;
+  maxOutputTokens?: number;
+};
+
+export type TurnArtifact = {
+  path: string;
+  size: number;
+  mime: string;
+  createdByToolId: string | null;
+  missing: boolean;
+  /** Set on patches applied with `applyExtractedPatch`. */
+  appliedPatch?: {
+    index: number;
+    files: string[];
+    staged: boolean;
+    appliedAtMs: number;
+  };
+};
+
+export type MenuAcceleratorResult = {
+  id: string;
+  status: "applied" | "conflicted" | "invalidSyntax" | "unknownItem";
+  accelerator: string | null;
+  conflictedWith: string | null;
+  error: string | null;
+};
+
+export type DefaultMenuAccelerator = {
+  id: string;
+  accelerator: string | null;
+  resolved: string | null;
+};
+
+export type StoreMaintenanceReport = {
+  workspaceId: string;
+  status: "completed" | "aborted" | "skipped";
+  startedAt: number;
+  finishedAt: number;
+  filesChecked: number;
+  filesRewritten: number;
+  corruptedFiles: string[];
+  duplicateItemsRemoved: number;
+  sessionCollisionsRepaired: boolean;
+  bytesBefore: number;
+  bytesAfter: number;
+};
+
+export type ItemAnnotation = {
+  id: string;
+  itemId: string;
+  text: string;
+  author: string;
+  createdAt: number;
+  updatedAt?: number;
+};
+
+export type AutoRunStatus =
+  | "running"
+  | "completed"
+  | "turnLimit"
+  | "timeLimit"
+  | "cancelled"
+  | "failed";
+
+export type AutoRun = {
+  id: string;
+  threadId: string;
+  goal: string;
+  maxTurns: number;
+  maxDurationSecs: number;
+  accessMode?: string;
+  iteration: number;
+  startedAtMs: number;
+  updatedAtMs: number;
+  status: AutoRunStatus;
+  error?: string;
+};
+
+export type ReviewSarifExport = {
+  sarif: Record<string, unknown>;
+  itemId: string | null;
+  resultCount: number;
+  path: string | null;
+};
+
+export type ClearedThread = {
+  workspaceId: string;
+  threadId: string;
+  title: string;
+  updatedAt: number;
+  itemCount: number;
+  diskBytes: number;
+};
+
+export type ClearWorkspaceHistoryResult = {
+  dryRun: boolean;
+  scoped: boolean;
+  threads: ClearedThread[];
+  removedCount: number;
+  freedBytes: number;
+};
+
+export type PendingRequestInfo = {
+  id: number;
+  method: string;
+  threadId: string | null;
+  caller: string;
+  startedAtMs: number;
+};
+
+export type SessionInfo = {
+  workspaceId: string;
+  pendingRequests: PendingRequestInfo[];
+  activeTurns: boolean;
+  backgroundThreads: number;
+  idleSeconds: number;
+  unresponsive: boolean;
+  configStale: boolean;
+  commandLine: string;
+  uptimeSeconds: number;
+  isolatedAgentHome: boolean;
+  agentHome: string | null;
+  resources: ResourceUsage;
+};
+
+export type MiCodeSessionHealth = "healthy" | "unresponsive" | "disconnected";
+
+export type MiCodeSessionStatus = {
+  workspaceId: string;
+  health: MiCodeSessionHealth;
+  pid: number | null;
+  uptimeSeconds: number;
+  pendingRequests: number;
+  missedPings: number;
+  idleSeconds: number;
+};
+
+export type ResourceUsage = {
+  pid: number | null;
+  cpuPercent: number;
+  rssBytes: number;
+  peakCpuPercent: number;
+  peakRssBytes: number;
+  sampledAtMs: number | null;
+};
+
+export type WorkspaceFileFormat = "auto" | "raw" | "structured";
+
+export type NotebookCell = {
+  cellType: string;
+  source: string;
+  executionCount: number | null;
+  outputs: { outputType: string; text: string; truncated: boolean }[];
+};
+
+export type StructuredDocument =
+  | {
+      kind: "notebook";
+      language: string | null;
+      cells: NotebookCell[];
+      omittedCells: number;
+    }
+  | {
+      kind: "pdf";
+      pageCount: number;
+      pages: { number: number; text: string }[];
+      omittedPages: number;
+      noText: boolean;
+    };
+
+export type WorkspaceFileContent = {
+  content: string;
+  truncated: boolean;
+  document?: StructuredDocument;
+};
+
+export type DiagnosticsExport = {
+  path: string;
+  sizeBytes: number;
+  entries: { name: string; sizeBytes: number }[];
+  written: boolean;
+};
+
+export type OperationStatus = "completed" | "cancelled";
+
+/** What a cancellable command resolves with; `result` is missing once cancelled. */
+export type OperationOutcome<T> = {
+  operationId: string;
+  status: OperationStatus;
+  result?: T;
+};
+
+export type OperationUpdate =
+  | {
+      type: "progress";
+      operationId: string;
+      kind: string;
+      stage: string;
+      completed: number | null;
+      total: number | null;
+    }
+  | {
+      type: "finished";
+      operationId: string;
+      kind: string;
+      status: OperationStatus | "failed";
+      error: string | null;
+    };
+
+export type TimingSummary = {
+  calls: number;
+  errors: number;
+  meanMs: number;
+  p50Ms: number;
+  p95Ms: number;
+  maxMs: number;
+  lastErrorAtMs: number | null;
+};
+
+export type CommandTimings = TimingSummary & {
+  command: string;
+  workspaces: Record<string, TimingSummary>;
+};
+
+export type FeatureUsage = {
+  enabled: boolean;
+  counters: { days: Record<string, Record<string, number>> };
+  totals: Record<string, number>;
+};
+
+export type FeatureUsageExport = {
+  path: string;
+  sizeBytes: number;
+};
+
+export type NotificationRecord = {
+  id: string;
+  type: string;
+  title: string;
+  message: string;
+  workspaceId: string | null;
+  threadId: string | null;
+  turnId: string | null;
+  itemId: string | null;
+  requestId: string | number | null;
+  createdAt: number;
+  readAt: number | null;
+};
+
+export type NotificationPage = {
+  items: NotificationRecord[];
+  total: number;
+  unreadCount: number;
+};
+
+export type BlockingState = {
+  focusedWorkspaceId: string | null;
+  blocked: boolean;
+  dictationPaused: boolean;
+  pendingApprovals: Record<string, number>;
+};
+
+export type EventMethod = {
+  method: string;
+  description: string;
+};
+
+export type BackendCapabilities = {
+  backendVersion: string;
+  eventsSchemaVersion: number;
+  events: EventMethod[];
+  daemonNotifications: EventMethod[];
+};
+
+export type ResourceUsageReport = {
+  sessions: Record<string, ResourceUsage>;
+  terminals: {
+    workspaceId: string;
+    terminalId: string;
+    usage: ResourceUsage;
+  }[];
+};
+
+export type ThreadOwnership = {
+  workspaceId: string;
+  threadId: string;
+  ownerClientId: string;
+  since: number;
+};
+
+export type ThreadOwnershipInfo = {
+  clientId: string | null;
+  ownership: ThreadOwnership | null;
+};
+
+/** One hit of `searchThreads`; `itemId` is null when only the thread name matched. */
+export type ThreadSearchHit = {
+  threadId: string;
+  threadName: string;
+  itemId: string | null;
+  snippet: string;
+  updatedAt: number;
+};
+
+export type ArchivedThread = {
+  id: string;
+  name: string;
+  updatedAt: number;
+  /** Unix seconds; retention counts from here. */
+  archivedAt: number;
+  tags: string[];
+  pinned: boolean;
+};
+
+export type ThreadExportFormat = "markdown" | "json";
+
+export type ThreadExport = {
+  format: ThreadExportFormat;
+  path?: string;
+  content?: string;
+  bytes: number;
+};
+
+export type OpenableApp = {
+  id: string;
+  label: string;
+  kind: string;
+  available: boolean;
+  icon: string | null;
+  launcher: string | null;
+  installHint: string | null;
+};
+
+export type CommandErrorCode =
+  | "workspaceNotConnected"
+  | "workspaceNotFound"
+  | "sessionNotFound"
+  | "acpTimeout"
+  | "gitCommandFailed"
+  | "repositoryBusy"
+  | "identityMismatch"
+  | "micodeBinMissing"
+  | "invalidAgentArgs"
+  | "threadPinned"
+  | "commandNotAvailable"
+  | "turnInProgress"
+  | "commandFailed";
+
+export type CommandErrorPayload = {
+  code: CommandErrorCode;
+  message: string;
+  details?: unknown;
+  retryable: boolean;
+};
+
+export type EditorLaunchErrorCode =
+  | "unknownEditor"
+  | "editorNotInstalled"
+  | "launchFailed";
+
+export type GitIdentity = {
+  name?: string | null;
+  email?: string | null;
+};
+
+export type GitIdentityInfo = {
+  effective: GitIdentity;
+  local: GitIdentity;
+  expected: GitIdentity | null;
+  matches: boolean;
+};
+
+export type WorkspaceGitInfo = {
+  repoRoot: string;
+  branchName: string | null;
+  remoteUrl: string | null;
+  identity: GitIdentityInfo;
+};
+
+export type ConflictHunk = {
+  startLine: number;
+  endLine: number;
+  oursLabel: string;
+  theirsLabel: string;
+  baseLabel: string | null;
+  ours: string;
+  base: string | null;
+  theirs: string;
+};
+
+export type ConflictDetail = {
+  path: string;
+  operation: "merge" | "rebase" | "cherryPick" | "revert" | "am" | null;
+  binary: boolean;
+  stages: number[];
+  base: string | null;
+  ours: string | null;
+  theirs: string | null;
+  working: string | null;
+  hunks: ConflictHunk[];
+};
+
+export type ConflictResolution = "ours" | "theirs" | "content";
+
+export type ExtractedPatch = {
+  index: number;
+  line: number;
+  files: string[];
+  patch: string;
+  applicable: boolean;
+  reason: string | null;
+};
+
+export type AppliedPatch = {
+  index: number;
+  files: string[];
+  staged: boolean;
+  artifact: TurnArtifact;
+};
+
+export type GitRevertResult = {
+  trashId: string | null;
+  warnings: string[];
+};
+
+export type GitTrashFile = {
+  path: string;
+  bytes: number;
+  deleted: boolean;
+  revertedHash: string | null;
+};
+
+export type GitTrashEntry = {
+  id: string;
+  createdAtMs: number;
+  operation: "revertFile" | "revertAll";
+  repoRoot: string;
+  totalBytes: number;
+  files: GitTrashFile[];
+  skipped: { path: string; bytes: number; reason: string }[];
+};
+
+export type GitTrashRestoreResult = {
+  restored: string[];
+  conflicts: string[];
+};
+
+export type EditorLaunchErrorPayload = {
+  code: EditorLaunchErrorCode;
+  message: string;
+  editorId?: string | null;
+  installHint?: string | null;
+};
+
 export type AppSettings = {
   agentProvider: "micode-acp";
   agentBin: string | null;
@@ -181,11 +883,13 @@ export type AppSettings = {
   codeFontSize: number;
   notificationSoundsEnabled: boolean;
   systemNotificationsEnabled: boolean;
+  notificationRetentionDays: number;
   preloadGitDiffs: boolean;
   gitDiffIgnoreWhitespaceChanges: boolean;
   experimentalCollabEnabled: boolean;
   collaborationModesEnabled: boolean;
   steerEnabled: boolean;
+  queueWhenBusy: boolean;
   unifiedExecEnabled: boolean;
   experimentalAppsEnabled: boolean;
   personality: PersonalityPreference;
@@ -205,6 +909,49 @@ export type AppSettings = {
   workspaceGroups: WorkspaceGroup[];
   openAppTargets: OpenAppTarget[];
   selectedOpenAppId: string;
+  agentUnresponsiveTimeoutSecs: number;
+  autoRestartUnresponsiveAgent: boolean;
+  modelSamplingParams: Record<string, SamplingParams>;
+  autoStoreMaintenance: boolean;
+  resourceMonitoringEnabled: boolean;
+  resourceWarningRssMb: number;
+  resourceWarningCpuPercent: number;
+  resourceWarningSustainedSecs: number;
+  slowCommandThresholdMs: number;
+  proxy: ProxySettings;
+  runKickoffTemplate: string | null;
+  usageCountersEnabled: boolean;
+  promptTrashRetentionDays: number;
+  maxConcurrentConnects: number;
+  promptTimeoutSecs: number;
+  turnStallWarningSecs: number;
+  toolStallWarningSecs: number | null;
+  toolSlowWarningSecs: number;
+  approvalTimeoutSecs: number;
+  backgroundIdleTimeoutMs: number;
+  backgroundMaxWaitSecs: number;
+  commitMessageDiffMaxBytes: number;
+  toolResultMaxKb: number;
+  autoTitleThreads: boolean;
+  persistReasoning: boolean;
+  archivedThreadRetentionDays: number;
+  modelPrices: ModelPrice[];
+  /** Settings revision these settings were read at; ignored when saving. */
+  settingsRevision: number;
+};
+
+export type SettingsUpdateScope =
+  | "app"
+  | "workspace"
+  | "accelerators"
+  | "micodeConfig";
+
+export type SettingsUpdate = {
+  scope: SettingsUpdateScope;
+  workspaceId: string | null;
+  revision: number;
+  keys: string[];
+  values: Record<string, unknown>;
 };
 
 export type MiCodeDoctorResult = {
@@ -212,11 +959,23 @@ export type MiCodeDoctorResult = {
   micodeBin: string | null;
   version: string | null;
   appServerOk: boolean;
+  handshakeLatencyMs?: number | null;
+  protocolVersion?: number | null;
+  handshakeSource?: "probe" | "cache" | "session";
+  handshakeCheckedAt?: number;
   details: string | null;
   path: string | null;
   nodeOk: boolean;
   nodeVersion: string | null;
   nodeDetails: string | null;
+  configMismatch?: boolean;
+  staleWorkspaceIds?: string[];
+  isolatedAgentHomes?: IsolatedAgentHome[];
+};
+
+export type IsolatedAgentHome = {
+  workspaceId: string;
+  agentHome: string;
 };
 
 export type ApprovalRequest = {
@@ -234,10 +993,25 @@ export type ApprovalDecision =
   | "decline_once"
   | "decline_always";
 
+export type ApprovalRuleDecision = "allow" | "deny";
+
 export type ApprovalRule = {
   command: string[];
 };
 
+export type StoredApprovalRule = {
+  id: string;
+  pattern: string[];
+  decision: ApprovalRuleDecision;
+  createdAtMs: number;
+  hits: number;
+};
+
+export type ApprovalRulesImport = {
+  added: number;
+  skipped: number;
+};
+
 export type RequestUserInputOption = {
   label: string;
   description: string;
@@ -290,6 +1064,23 @@ export type GitFileDiff = {
   newImageData?: string | null;
   oldImageMime?: string | null;
   newImageMime?: string | null;
+  root?: string | null;
+};
+
+export type WorkspaceRoot = {
+  name: string;
+  path: string;
+  primary: boolean;
+};
+
+export type GitRootStatus = WorkspaceRoot & {
+  branchName?: string;
+  files?: GitFileStatus[];
+  stagedFiles?: GitFileStatus[];
+  unstagedFiles?: GitFileStatus[];
+  totalAdditions?: number;
+  totalDeletions?: number;
+  error?: string;
 };
 
 export type GitCommitDiff = {
@@ -383,6 +1174,7 @@ export type ThreadTokenUsage = {
   total: TokenUsageBreakdown;
   last: TokenUsageBreakdown;
   modelContextWindow: number | null;
+  model?: string | null;
 };
 
 export type LocalUsageDay = {
@@ -393,6 +1185,7 @@ export type LocalUsageDay = {
   totalTokens: number;
   agentTimeMs: number;
   agentRuns: number;
+  primerTokensSaved?: number;
 };
 
 export type LocalUsageTotals = {
@@ -402,6 +1195,8 @@ export type LocalUsageTotals = {
   cacheHitRatePercent: number;
   peakDay: string | null;
   peakDayTokens: number;
+  primerTokensSaved?: number;
+  primerReuses?: number;
 };
 
 export type LocalUsageModel = {
@@ -417,6 +1212,36 @@ export type LocalUsageSnapshot = {
   topModels: LocalUsageModel[];
 };
 
+export type ModelPrice = {
+  model: string;
+  inputPerMillion: number;
+  cachedInputPerMillion?: number | null;
+  outputPerMillion: number;
+};
+
+export type UsageCsvExport = {
+  path: string;
+  rowCount: number;
+};
+
+export type UsageSummaryTotals = {
+  turns: number;
+  inputTokens: number;
+  cachedInputTokens: number;
+  outputTokens: number;
+  estimatedCostUsd: number;
+  unpricedTurns: number;
+};
+
+export type UsageSummaryDay = UsageSummaryTotals & {
+  day: string;
+};
+
+export type UsageSummary = {
+  days: UsageSummaryDay[];
+  totals: UsageSummaryTotals;
+};
+
 export type TurnPlanStepStatus = "pending" | "inProgress" | "completed";
 
 export type TurnPlanStep = {
@@ -430,6 +1255,13 @@ export type TurnPlan = {
   steps: TurnPlanStep[];
 };
 
+export type TurnPhase =
+  | "sending"
+  | "waiting_for_model"
+  | "streaming"
+  | "tool_running"
+  | "finalizing";
+
 export type RateLimitWindow = {
   usedPercent: number;
   windowDurationMins: number | null;
@@ -471,6 +1303,27 @@ export type ModelOption = {
   supportedReasoningEfforts: { reasoningEffort: string; description: string }[];
   defaultReasoningEffort: string | null;
   isDefault: boolean;
+  /** Where the model is defined; `settings` for custom models in settings.json. */
+  source?: "bundle" | "settings";
+};
+
+/** Markdown layout of a generated changelog. */
+export type ChangelogStyle = "keep-a-changelog" | "plain";
+
+/** A generated pull request title and Markdown body. */
+export type PrDescription = {
+  title: string;
+  body: string;
+};
+
+/** An entry of `mcpServers` in the MiCode settings file. */
+export type McpServerConfig = {
+  command?: string;
+  args?: string[];
+  url?: string;
+  httpUrl?: string;
+  env?: Record<string, string>;
+  [key: string]: unknown;
 };
 
 export type CollaborationModeOption = {
@@ -487,6 +1340,8 @@ export type SkillOption = {
   name: string;
   path: string;
   description?: string;
+  /** `workspace` skills come from the repo's `.micode/skills` and shadow global ones. */
+  source?: "global" | "workspace";
 };
 
 export type AppOption = {
@@ -507,6 +1362,25 @@ export type CustomPromptOption = {
   scope?: "workspace" | "global";
 };
 
+export type TrashedPrompt = CustomPromptOption & {
+  deletedAt: number;
+};
+
+export type PromptRestoreResult = {
+  prompt: CustomPromptOption;
+  originalName: string;
+  renamed: boolean;
+};
+
+export type PromptExtraction = {
+  // Path of the created prompt; null on a dry run.
+  promptId: string | null;
+  prompt: CustomPromptOption | null;
+  text: string;
+  generalized: boolean;
+  variables: string[];
+};
+
 export type BranchInfo = {
   name: string;
   lastCommit: number;
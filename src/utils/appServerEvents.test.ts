@@ -7,6 +7,8 @@ import {
   getAppServerParams,
   getAppServerRawMethod,
   getAppServerRequestId,
+  getOperationUpdate,
+  getSettingsUpdate,
   isApprovalRequestMethod,
   isSkillsUpdateAvailableEvent,
   isSupportedAppServerMethod,
@@ -53,6 +55,73 @@ describe("appServerEvents", () => {
     expect(isSkillsUpdateAvailableEvent(nonCanonicalMethod)).toBe(false);
   });
 
+  it("reads settings updates and ignores unknown scopes", () => {
+    expect(
+      getSettingsUpdate(
+        makeEvent({
+          method: "settings/updated",
+          params: {
+            scope: "workspace",
+            workspaceId: "ws-1",
+            revision: 7,
+            keys: ["sidebarCollapsed"],
+            values: { sidebarCollapsed: true },
+          },
+        }),
+      ),
+    ).toEqual({
+      scope: "workspace",
+      workspaceId: "ws-1",
+      revision: 7,
+      keys: ["sidebarCollapsed"],
+      values: { sidebarCollapsed: true },
+    });
+    expect(
+      getSettingsUpdate(
+        makeEvent({ method: "settings/updated", params: { scope: "other", revision: 1 } }),
+      ),
+    ).toBeNull();
+    expect(getSettingsUpdate(makeEvent({ method: "turn/started", params: {} }))).toBeNull();
+  });
+
+  it("reads operation progress and finish events", () => {
+    expect(
+      getOperationUpdate(
+        makeEvent({
+          method: "operation/progress",
+          params: { operationId: "op-1", kind: "workspaceSearch", stage: "scanning", completed: 42 },
+        }),
+      ),
+    ).toEqual({
+      type: "progress",
+      operationId: "op-1",
+      kind: "workspaceSearch",
+      stage: "scanning",
+      completed: 42,
+      total: null,
+    });
+    expect(
+      getOperationUpdate(
+        makeEvent({
+          method: "operation/finished",
+          params: { operationId: "op-1", kind: "clone", status: "cancelled" },
+        }),
+      ),
+    ).toEqual({
+      type: "finished",
+      operationId: "op-1",
+      kind: "clone",
+      status: "cancelled",
+      error: null,
+    });
+    expect(
+      getOperationUpdate(
+        makeEvent({ method: "operation/finished", params: { operationId: "", status: "failed" } }),
+      ),
+    ).toBeNull();
+    expect(getOperationUpdate(makeEvent({ method: "turn/started", params: {} }))).toBeNull();
+  });
+
   it("gracefully handles malformed event payloads", () => {
     const missingMessage = { workspace_id: "ws-1" } as unknown as AppServerEvent;
     const nonObjectMessage = {
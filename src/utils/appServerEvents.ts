@@ -1,4 +1,9 @@
-import type { AppServerEvent } from "../types";
+import type {
+  AppServerEvent,
+  OperationUpdate,
+  SettingsUpdate,
+  SettingsUpdateScope,
+} from "../types";
 
 export const SUPPORTED_APP_SERVER_METHODS = [
   "account/login/completed",
@@ -8,6 +13,8 @@ export const SUPPORTED_APP_SERVER_METHODS = [
   "micode/availableCommands/updated",
   "micode/connected",
   "micode/event/skills_update_available",
+  "micode/reconnectFailed",
+  "micode/reconnecting",
   "error",
   "item/agentMessage/delta",
   "item/commandExecution/outputDelta",
@@ -20,19 +27,41 @@ export const SUPPORTED_APP_SERVER_METHODS = [
   "item/reasoning/textDelta",
   "item/started",
   "item/tool/requestUserInput",
+  "notifications/unreadChanged",
+  "operation/finished",
+  "operation/progress",
+  "settings/updated",
   "thread/name/updated",
   "thread/started",
   "thread/tokenUsage/updated",
+  "tool/slow",
+  "turn/cancelled",
   "turn/completed",
   "turn/diff/updated",
+  "turn/failed",
+  "turn/phase",
   "turn/plan/updated",
+  "turn/dequeued",
+  "turn/queueCleared",
+  "turn/queued",
+  "turn/resumedStreaming",
+  "turn/stalled",
   "turn/started",
+  "turn/timeout",
+  "workspace/approvalReminder",
+  "workspace/approvalResolved",
+  "workspace/connectQueued",
+  "workspace/connected",
+  "workspace/connecting",
 ] as const;
 
 export type SupportedAppServerMethod = (typeof SUPPORTED_APP_SERVER_METHODS)[number];
 
 export const METHODS_HANDLED_OUTSIDE_USE_APP_SERVER_EVENTS = [
   "micode/event/skills_update_available",
+  "operation/finished",
+  "operation/progress",
+  "settings/updated",
 ] as const satisfies readonly SupportedAppServerMethod[];
 
 const SUPPORTED_METHOD_SET = new Set<string>(SUPPORTED_APP_SERVER_METHODS);
@@ -100,3 +129,71 @@ export function isApprovalRequestMethod(method: string): boolean {
 export function isSkillsUpdateAvailableEvent(event: AppServerEvent): boolean {
   return getAppServerRawMethod(event) === "micode/event/skills_update_available";
 }
+
+const SETTINGS_UPDATE_SCOPES = new Set<string>([
+  "app",
+  "workspace",
+  "accelerators",
+  "micodeConfig",
+]);
+
+export function getSettingsUpdate(event: AppServerEvent): SettingsUpdate | null {
+  if (getAppServerRawMethod(event) !== "settings/updated") {
+    return null;
+  }
+  const params = getAppServerParams(event);
+  const scope = String(params.scope ?? "");
+  if (!SETTINGS_UPDATE_SCOPES.has(scope)) {
+    return null;
+  }
+  const values =
+    params.values && typeof params.values === "object" && !Array.isArray(params.values)
+      ? (params.values as Record<string, unknown>)
+      : {};
+  return {
+    scope: scope as SettingsUpdateScope,
+    workspaceId: typeof params.workspaceId === "string" ? params.workspaceId : null,
+    revision: Number(params.revision ?? 0),
+    keys: Array.isArray(params.keys) ? params.keys.map(String) : [],
+    values,
+  };
+}
+
+function optionalNumber(value: unknown): number | null {
+  return typeof value === "number" && Number.isFinite(value) ? value : null;
+}
+
+/** Reads `operation/progress` and `operation/finished`, which carry the operation id. */
+export function getOperationUpdate(event: AppServerEvent): OperationUpdate | null {
+  const method = getAppServerRawMethod(event);
+  if (method !== "operation/progress" && method !== "operation/finished") {
+    return null;
+  }
+  const params = getAppServerParams(event);
+  const operationId = typeof params.operationId === "string" ? params.operationId : "";
+  if (!operationId) {
+    return null;
+  }
+  const kind = String(params.kind ?? "");
+  if (method === "operation/progress") {
+    return {
+      type: "progress",
+      operationId,
+      kind,
+      stage: String(params.stage ?? ""),
+      completed: optionalNumber(params.completed),
+      total: optionalNumber(params.total),
+    };
+  }
+  const status = String(params.status ?? "");
+  if (status !== "completed" && status !== "cancelled" && status !== "failed") {
+    return null;
+  }
+  return {
+    type: "finished",
+    operationId,
+    kind,
+    status,
+    error: typeof params.error === "string" ? params.error : null,
+  };
+}
@@ -584,6 +584,43 @@ describe("threadItems", () => {
     }
   });
 
+  it("restores persisted reasoning from the resumed history", () => {
+    const item = buildConversationItemFromThreadItem({
+      type: "reasoning",
+      id: "reasoning-thread-1-turn-1",
+      summary: "",
+      content: "Looking at the file",
+    });
+    expect(item).toEqual({
+      id: "reasoning-thread-1-turn-1",
+      kind: "reasoning",
+      summary: "",
+      content: "Looking at the file",
+    });
+  });
+
+  it("flags agent messages saved before their reply finished", () => {
+    const partial = buildConversationItemFromThreadItem({
+      type: "agentMessage",
+      id: "agent-thread-1-turn-1",
+      text: "Half an ans",
+      partial: true,
+    });
+    expect(partial).toEqual({
+      id: "agent-thread-1-turn-1",
+      kind: "message",
+      role: "assistant",
+      text: "Half an ans",
+      partial: true,
+    });
+    const complete = buildConversationItemFromThreadItem({
+      type: "agentMessage",
+      id: "agent-thread-1-turn-2",
+      text: "Done",
+    });
+    expect(complete).not.toHaveProperty("partial");
+  });
+
   it("keeps image-only user messages without placeholder text", () => {
     const item = buildConversationItemFromThreadItem({
       type: "userMessage",
@@ -619,6 +656,21 @@ describe("threadItems", () => {
     }
   });
 
+  it("renders system notes as assistant messages", () => {
+    const item = buildConversationItem({
+      type: "systemNote",
+      id: "note-thread-1-turn-1",
+      reason: "interrupted",
+      text: "Turn interrupted by user",
+    });
+    expect(item).toEqual({
+      id: "note-thread-1-turn-1",
+      kind: "message",
+      role: "assistant",
+      text: "Turn interrupted by user",
+    });
+  });
+
   it("builds context compaction items", () => {
     const item = buildConversationItem({
       type: "contextCompaction",
@@ -662,6 +714,25 @@ describe("threadItems", () => {
     }
   });
 
+  it("shows structured mcp tool results when there is no text result", () => {
+    const item = buildConversationItem({
+      type: "mcpToolCall",
+      id: "mcp-2",
+      server: "micode",
+      tool: "glob",
+      arguments: { pattern: "**/*.rs" },
+      status: "completed",
+      result: null,
+      resultJson: { files: ["src/lib.rs"] },
+      durationMs: 2345,
+    });
+    expect(item).not.toBeNull();
+    if (item && item.kind === "tool") {
+      expect(item.output).toBe(JSON.stringify({ files: ["src/lib.rs"] }, null, 2));
+      expect(item.durationMs).toBe(2345);
+    }
+  });
+
   it("parses ISO timestamps for thread updates", () => {
     const timestamp = getThreadTimestamp({ updated_at: "2025-01-01T00:00:00Z" });
     expect(timestamp).toBe(Date.parse("2025-01-01T00:00:00Z"));
@@ -734,14 +734,18 @@ export function buildConversationItem(
     const server = asString(item.server ?? "");
     const tool = asString(item.tool ?? "");
     const args = stringifyToolArguments(item.arguments);
+    const summaryText = asString(item.summaryText ?? "");
     return {
       id,
       kind: "tool",
       toolType: type,
       title: `Tool: ${server}${tool ? ` / ${tool}` : ""}`,
       detail: args,
+      ...(summaryText ? { summaryText } : {}),
       status: asString(item.status ?? ""),
-      output: asString(item.result ?? item.error ?? ""),
+      output:
+        asString(item.result ?? item.error ?? "") || stringifyToolArguments(item.resultJson),
+      durationMs: asNumber(item.durationMs ?? item.duration_ms),
     };
   }
   if (type === "collabToolCall" || type === "collabAgentToolCall") {
@@ -805,6 +809,25 @@ export function buildConversationItem(
       output: "",
     };
   }
+  if (type === "autoRunSummary") {
+    return {
+      id,
+      kind: "tool",
+      toolType: type,
+      title: "Auto run",
+      detail: asString(item.goal ?? ""),
+      status: asString(item.status ?? ""),
+      output: asString(item.text ?? ""),
+    };
+  }
+  if (type === "systemNote") {
+    return {
+      id,
+      kind: "message",
+      role: "assistant",
+      text: asString(item.text ?? ""),
+    };
+  }
   if (type === "enteredReviewMode" || type === "exitedReviewMode") {
     return {
       id,
@@ -880,6 +903,7 @@ export function buildConversationItemFromThreadItem(
       kind: "message",
       role: "assistant",
       text: asString(item.text),
+      ...(item.partial === true ? { partial: true } : {}),
     };
   }
   if (type === "reasoning") {